@@ -22,7 +22,7 @@ use std::path::PathBuf;
 #[derive(clap::Args, Debug)]
 #[clap(author)]
 pub(crate) struct Connect {
-    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`?
+    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`, `pg15`?
     #[clap(env = "PG_VERSION")]
     pg_version: Option<String>,
     /// The database to connect to (and create if the first time).  Defaults to a database with the same name as the current extension name
@@ -36,6 +36,9 @@ pub(crate) struct Connect {
     /// Path to Cargo.toml
     #[clap(long, parse(from_os_str))]
     manifest_path: Option<PathBuf>,
+    /// Arguments to pass through to psql, after a `--`.  Something like `-- -c 'SELECT 1'` or `-- -f script.sql` runs psql non-interactively and exits with its status
+    #[clap(last = true)]
+    psql_args: Vec<String>,
 }
 
 impl CommandExecute for Connect {
@@ -104,7 +107,11 @@ impl CommandExecute for Connect {
             }
         };
 
-        connect_psql(Pgx::from_config()?.get(&pg_version)?, &dbname)
+        connect_psql(
+            Pgx::from_config()?.get(&pg_version)?,
+            &dbname,
+            &self.psql_args,
+        )
     }
 }
 
@@ -112,12 +119,18 @@ impl CommandExecute for Connect {
     pg_version = %pg_config.version()?,
     dbname,
 ))]
-pub(crate) fn connect_psql(pg_config: &PgConfig, dbname: &str) -> eyre::Result<()> {
-    // restart postgres
+pub(crate) fn connect_psql(
+    pg_config: &PgConfig,
+    dbname: &str,
+    psql_args: &[String],
+) -> eyre::Result<()> {
+    // restart postgres.  It, and creating the database, say so on stderr, and when psql is being
+    // scripted we stay quiet about re-using the database, so as not to mix our output in with
+    // the script's
     start_postgres(pg_config)?;
 
     // create the named database
-    if !createdb(pg_config, dbname, false, true)? {
+    if !createdb(pg_config, dbname, false, true)? && psql_args.is_empty() {
         println!(
             "{} existing database {}",
             "    Re-using".bold().cyan(),
@@ -126,5 +139,5 @@ pub(crate) fn connect_psql(pg_config: &PgConfig, dbname: &str) -> eyre::Result<(
     }
 
     // run psql
-    exec_psql(pg_config, dbname)
+    exec_psql(pg_config, dbname, psql_args)
 }
@@ -12,11 +12,15 @@ use crate::CommandExecute;
 use eyre::{eyre, WrapErr};
 use owo_colors::OwoColorize;
 use pgx_utils::{
-    pg_config::{PgConfig, PgConfigSelector, Pgx},
+    pg_config::{
+        parse_source_index, parse_source_tarball_name, source_checksum_url, source_tarball_name,
+        PgConfig, PgConfigSelector, PgVersion, Pgx, POSTGRES_SOURCE_URL,
+    },
     prefix_path, SUPPORTED_MAJOR_VERSIONS,
 };
 use rayon::prelude::*;
 use rttp_client::{types::Proxy, HttpClient};
+use sha2::{Digest, Sha256};
 
 use std::collections::HashMap;
 use std::fs::File;
@@ -60,13 +64,54 @@ pub(crate) struct Init {
     /// If installed locally, the path to PG14's `pgconfig` tool, or `downLoad` to have pgx download/compile/install it
     #[clap(env = "PG14_PG_CONFIG", long)]
     pg14: Option<String>,
+    /// If installed locally, the path to PG15's `pgconfig` tool, or `downLoad` to have pgx download/compile/install it
+    #[clap(env = "PG15_PG_CONFIG", long)]
+    pg15: Option<String>,
+    /// Look for Postgres installations in common locations (system packages, pgenv, Homebrew) and offer to use them for any version not given explicitly
+    #[clap(long, short)]
+    discover: bool,
+    /// An extra `./configure` argument, like `--with-llvm`, for every version pgx compiles.  May be repeated
+    #[clap(
+        long = "configure-flag",
+        value_name = "FLAG",
+        multiple_occurrences = true,
+        allow_hyphen_values = true,
+        number_of_values = 1
+    )]
+    configure_flags: Vec<String>,
+    /// Compile Postgres only from the source tarballs in `--source-dir`, without using the network, so the latest version of each found there is the one compiled
+    #[clap(long, requires = "source-dir")]
+    offline: bool,
+    /// A directory of Postgres source tarballs, like `postgresql-14.2.tar.bz2`, each next to its `.sha256` checksum file, to compile instead of downloading them.  Tarballs that are downloaded are saved there, with their checksums
+    #[clap(long, env = "PGX_SOURCE_DIR", parse(from_os_str), value_name = "DIR")]
+    source_dir: Option<PathBuf>,
+    /// Download the Postgres sources, and the list of releases, from this mirror of https://ftp.postgresql.org/pub/source.  Their checksums still come from postgresql.org, unless they're in `--source-dir`
+    #[clap(
+        long,
+        env = "PGX_POSTGRES_MIRROR",
+        value_name = "URL",
+        conflicts_with = "offline"
+    )]
+    mirror: Option<String>,
     #[clap(from_global, parse(from_occurrences))]
     verbose: usize,
 }
 
+/// Where the sources of the Postgres versions `cargo pgx init` compiles come from
+pub(crate) struct PostgresSources {
+    offline: bool,
+    source_dir: Option<PathBuf>,
+    mirror: Option<String>,
+}
+
 impl CommandExecute for Init {
     #[tracing::instrument(level = "error", skip(self))]
     fn execute(self) -> eyre::Result<()> {
+        let sources = PostgresSources {
+            offline: self.offline,
+            source_dir: self.source_dir,
+            mirror: self.mirror,
+        };
         let mut versions = HashMap::new();
 
         if let Some(version) = self.pg10 {
@@ -84,10 +129,24 @@ impl CommandExecute for Init {
         if let Some(version) = self.pg14 {
             versions.insert("pg14", version.clone());
         }
+        if let Some(version) = self.pg15 {
+            versions.insert("pg15", version.clone());
+        }
+
+        if self.discover {
+            for (label, pg_config_path) in discover_pg_configs(&versions)? {
+                versions.insert(label, pg_config_path);
+            }
+            if versions.is_empty() {
+                return Err(eyre!(
+                    "no Postgres installations were selected.  Specify `--pg$VERSION` flags, or run `cargo pgx init` without `--discover` to download them"
+                ));
+            }
+        }
 
         if versions.is_empty() {
             // no arguments specified, so we'll just install our defaults
-            init_pgx(&Pgx::default(SUPPORTED_MAJOR_VERSIONS)?)
+            init_pgx(&sources.latest_versions()?, &self.configure_flags, &sources)
         } else {
             // user specified arguments, so we'll only install those versions of Postgres
             let mut default_pgx = None;
@@ -96,7 +155,7 @@ impl CommandExecute for Init {
             for (pgver, pg_config_path) in versions {
                 let config = if pg_config_path == "download" {
                     if default_pgx.is_none() {
-                        default_pgx = Some(Pgx::default(SUPPORTED_MAJOR_VERSIONS)?);
+                        default_pgx = Some(sources.latest_versions()?);
                     }
                     default_pgx
                         .as_ref()
@@ -110,14 +169,355 @@ impl CommandExecute for Init {
                 pgx.push(config);
             }
 
-            init_pgx(&pgx)
+            init_pgx(&pgx, &self.configure_flags, &sources)
+        }
+    }
+}
+
+impl PostgresSources {
+    /// The latest release of each supported Postgres version, from postgresql.org's list of
+    /// them, or the mirror's index of its releases, or when offline, from the tarballs in the
+    /// source directory
+    fn latest_versions(&self) -> eyre::Result<Pgx> {
+        if self.offline {
+            let source_dir = self
+                .source_dir
+                .as_ref()
+                .ok_or_else(|| eyre!("`--offline` needs a `--source-dir`"))?;
+            let mut versions = vec![];
+            for entry in std::fs::read_dir(source_dir)
+                .wrap_err_with(|| eyre!("couldn't read `{}`", source_dir.display()))?
+            {
+                let name = entry?.file_name();
+                versions.extend(parse_source_tarball_name(&name.to_string_lossy()));
+            }
+            if versions.is_empty() {
+                return Err(eyre!(
+                    "there are no Postgres source tarballs, like `{}`, in `{}`",
+                    source_tarball_name(14, 2),
+                    source_dir.display()
+                ));
+            }
+            return latest_of(
+                versions,
+                POSTGRES_SOURCE_URL,
+                &source_dir.display().to_string(),
+            );
+        }
+
+        match &self.mirror {
+            Some(mirror) => {
+                let index = format!("{}/", mirror.trim_end_matches('/'));
+                let versions = parse_source_index(&String::from_utf8_lossy(&download(&index)?));
+                latest_of(versions, mirror, &index)
+            }
+            None => Pgx::default(SUPPORTED_MAJOR_VERSIONS),
+        }
+    }
+
+    /// The source tarball of `pg_config`'s version, from the source directory if it's there, or
+    /// else downloaded, once it matches its SHA-256 checksum
+    fn fetch(&self, pg_config: &PgConfig) -> eyre::Result<Vec<u8>> {
+        let (major, minor) = (pg_config.major_version()?, pg_config.minor_version()?);
+        let tarball_name = source_tarball_name(major, minor);
+
+        if let Some(source_dir) = &self.source_dir {
+            let tarball = source_dir.join(&tarball_name);
+            if tarball.exists() {
+                println!(
+                    "{} Postgres v{}.{} from {}",
+                    "        Using".bold().green(),
+                    major,
+                    minor,
+                    tarball.display()
+                );
+                let bytes = std::fs::read(&tarball)
+                    .wrap_err_with(|| eyre!("couldn't read `{}`", tarball.display()))?;
+                let checksum_file = source_dir.join(format!("{}.sha256", tarball_name));
+                let checksum = std::fs::read_to_string(&checksum_file).wrap_err_with(|| {
+                    eyre!(
+                        "couldn't read `{}`, the SHA-256 checksum of `{}`",
+                        checksum_file.display(),
+                        tarball.display()
+                    )
+                })?;
+                verify_sha256(&bytes, &checksum, &tarball_name)?;
+                return Ok(bytes);
+            } else if self.offline {
+                return Err(eyre!(
+                    "`{}` doesn't exist, and `--offline` doesn't download it",
+                    tarball.display()
+                ));
+            }
+        }
+
+        let url = pg_config.url().expect("no url for pg_config").as_str();
+        println!(
+            "{} Postgres v{}.{} from {}",
+            "  Downloading".bold().green(),
+            major,
+            minor,
+            url,
+        );
+        let bytes = download(url)?;
+        let checksum = self.checksum(major, minor)?;
+        verify_sha256(&bytes, &checksum, &tarball_name)?;
+
+        if let Some(source_dir) = &self.source_dir {
+            std::fs::create_dir_all(source_dir)
+                .wrap_err_with(|| eyre!("couldn't create `{}`", source_dir.display()))?;
+            std::fs::write(source_dir.join(&tarball_name), &bytes)?;
+            std::fs::write(
+                source_dir.join(format!("{}.sha256", tarball_name)),
+                checksum,
+            )?;
+        }
+        Ok(bytes)
+    }
+
+    /// The SHA-256 checksum file of a downloaded tarball: the one next to where it would be in
+    /// the source directory, if it's there, or else postgresql.org's, even when the tarball
+    /// comes from a mirror, which can't vouch for its own downloads
+    fn checksum(&self, major: u16, minor: u16) -> eyre::Result<String> {
+        if let Some(source_dir) = &self.source_dir {
+            let checksum_file =
+                source_dir.join(format!("{}.sha256", source_tarball_name(major, minor)));
+            if checksum_file.exists() {
+                return std::fs::read_to_string(&checksum_file)
+                    .wrap_err_with(|| eyre!("couldn't read `{}`", checksum_file.display()));
+            }
+        }
+
+        let url = source_checksum_url(major, minor);
+        let checksum = download(&url).wrap_err_with(|| {
+            eyre!(
+                "couldn't download the SHA-256 checksum of Postgres v{}.{}.  Without access to postgresql.org, put it in `--source-dir`, as `{}.sha256`",
+                major,
+                minor,
+                source_tarball_name(major, minor)
+            )
+        })?;
+        String::from_utf8(checksum)
+            .wrap_err_with(|| eyre!("the SHA-256 checksum at {} isn't text", url))
+    }
+}
+
+/// The latest of `versions` for each supported major version, downloaded from `source_url`, and
+/// found in `found_in`
+fn latest_of(versions: Vec<(u16, u16)>, source_url: &str, found_in: &str) -> eyre::Result<Pgx> {
+    let mut latest = HashMap::new();
+    for (major, minor) in versions {
+        if SUPPORTED_MAJOR_VERSIONS.contains(&major) {
+            let latest_minor = latest.entry(major).or_insert(minor);
+            *latest_minor = minor.max(*latest_minor);
+        }
+    }
+    if latest.is_empty() {
+        return Err(eyre!(
+            "there are no releases of the supported Postgres versions in {}",
+            found_in
+        ));
+    }
+
+    let mut latest = latest.into_iter().collect::<Vec<_>>();
+    latest.sort();
+    println!(
+        "{} Postgres {} in {}",
+        "  Discovered".white().bold(),
+        latest
+            .iter()
+            .map(|(major, minor)| format!("v{}.{}", major, minor))
+            .collect::<Vec<_>>()
+            .join(", "),
+        found_in
+    );
+    let mut pgx = Pgx::new();
+    for (major, minor) in latest {
+        pgx.push(PgConfig::from_version(PgVersion::new(
+            major, minor, source_url,
+        )?));
+    }
+    Ok(pgx)
+}
+
+fn download(url: &str) -> eyre::Result<Vec<u8>> {
+    tracing::debug!(url = %url, "Fetching");
+    let mut http_client = HttpClient::new();
+    http_client.get().url(url);
+    if let Some((host, port)) = env_proxy::for_url_str(url).host_port() {
+        http_client.proxy(Proxy::https(host, port as u32));
+    }
+    let http_response = http_client.emit()?;
+    tracing::trace!(status_code = %http_response.code(), url = %url, "Fetched");
+    if http_response.code() != 200 {
+        return Err(eyre!(
+            "Problem downloading {}:\ncode={}\n{}",
+            url.yellow().bold(),
+            http_response.code(),
+            http_response.body().to_string()
+        ));
+    }
+    Ok(http_response.body().binary().to_vec())
+}
+
+/// Check `bytes` against the SHA-256 checksum in `checksum_file`, which is in the format of
+/// `sha256sum`, as postgresql.org's are
+fn verify_sha256(bytes: &[u8], checksum_file: &str, name: &str) -> eyre::Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let actual = Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "`{}` doesn't match its SHA-256 checksum, which is {}, not {}",
+            name,
+            expected,
+            actual
+        ))
+    }
+}
+
+/// Find `pg_config` binaries for supported Postgres versions that weren't already specified,
+/// asking the user to confirm each one when running interactively
+#[tracing::instrument(level = "error", skip_all)]
+fn discover_pg_configs(
+    specified: &HashMap<&'static str, String>,
+) -> eyre::Result<HashMap<&'static str, String>> {
+    let interactive = atty::is(atty::Stream::Stdin);
+    let mut found = HashMap::new();
+
+    for candidate in pg_config_candidates() {
+        let pg_config = PgConfig::new(candidate.clone());
+        let major_version = match pg_config.major_version() {
+            Ok(major_version) => major_version,
+            Err(e) => {
+                tracing::debug!(path = %candidate.display(), error = %e, "Skipping");
+                continue;
+            }
+        };
+        if !SUPPORTED_MAJOR_VERSIONS.contains(&major_version) {
+            println!(
+                "{} {} (Postgres v{} is not supported)",
+                "     Skipping".bold().yellow(),
+                candidate.display(),
+                major_version
+            );
+            continue;
+        }
+        let label = label_for(major_version);
+        if specified.contains_key(label) || found.contains_key(label) {
+            continue;
+        }
+
+        println!(
+            "{} Postgres v{} at {}",
+            "   Discovered".bold().green(),
+            major_version,
+            candidate.display()
+        );
+        if interactive && !confirm(&format!("Use it for {}?", label))? {
+            continue;
         }
+        found.insert(label, candidate.display().to_string());
     }
+
+    Ok(found)
+}
+
+fn label_for(major_version: u16) -> &'static str {
+    match major_version {
+        10 => "pg10",
+        11 => "pg11",
+        12 => "pg12",
+        13 => "pg13",
+        14 => "pg14",
+        15 => "pg15",
+        _ => unreachable!("Postgres v{} is not supported", major_version),
+    }
+}
+
+fn confirm(question: &str) -> eyre::Result<bool> {
+    print!("{} [Y/n] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim().to_lowercase();
+    Ok(answer.is_empty() || answer == "y" || answer == "yes")
+}
+
+/// Every `pg_config` in the places Postgres is commonly installed:
+///
+/// * Debian/Ubuntu packages: `/usr/lib/postgresql/*/bin/pg_config`
+/// * RHEL/Fedora packages: `/usr/pgsql-*/bin/pg_config`
+/// * pgenv: `$PGENV_ROOT/pgsql-*/bin/pg_config`, defaulting to `~/.pgenv`
+/// * Homebrew: `$HOMEBREW_PREFIX/opt/postgresql@*/bin/pg_config`
+fn pg_config_candidates() -> Vec<PathBuf> {
+    let mut roots = vec![
+        (PathBuf::from("/usr/lib/postgresql"), ""),
+        (PathBuf::from("/usr"), "pgsql-"),
+    ];
+
+    match std::env::var_os("PGENV_ROOT") {
+        Some(pgenv_root) => roots.push((PathBuf::from(pgenv_root), "pgsql-")),
+        None => {
+            if let Some(home) = std::env::var_os("HOME") {
+                roots.push((PathBuf::from(home).join(".pgenv"), "pgsql-"));
+            }
+        }
+    }
+
+    let mut homebrew_prefixes = vec![
+        PathBuf::from("/opt/homebrew"),
+        PathBuf::from("/usr/local"),
+        PathBuf::from("/home/linuxbrew/.linuxbrew"),
+    ];
+    if let Some(prefix) = std::env::var_os("HOMEBREW_PREFIX") {
+        homebrew_prefixes.insert(0, PathBuf::from(prefix));
+    }
+    for prefix in homebrew_prefixes {
+        roots.push((prefix.join("opt"), "postgresql"));
+    }
+
+    let mut candidates = Vec::new();
+    for (root, prefix) in roots {
+        let entries = match std::fs::read_dir(&root) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let mut dirs = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+            .map(|entry| entry.path().join("bin").join("pg_config"))
+            .filter(|pg_config| pg_config.is_file())
+            .collect::<Vec<_>>();
+        dirs.sort();
+        for pg_config in dirs {
+            // Homebrew's `opt/` entries are symlinks into its Cellar, and `opt/postgresql`
+            // usually points at the same installation as one of the `opt/postgresql@*`
+            let pg_config = std::fs::canonicalize(&pg_config).unwrap_or(pg_config);
+            if !candidates.contains(&pg_config) {
+                candidates.push(pg_config);
+            }
+        }
+    }
+    candidates
 }
 
 #[tracing::instrument(skip_all, fields(pgx_home = %Pgx::home()?.display()))]
-pub(crate) fn init_pgx(pgx: &Pgx) -> eyre::Result<()> {
+pub(crate) fn init_pgx(
+    pgx: &Pgx,
+    configure_flags: &[String],
+    sources: &PostgresSources,
+) -> eyre::Result<()> {
     let dir = Pgx::home()?;
+    let config_configure_flags = Pgx::configure_flags()?;
 
     let output_configs = Arc::new(Mutex::new(Vec::new()));
 
@@ -134,7 +534,14 @@ pub(crate) fn init_pgx(pgx: &Pgx) -> eyre::Result<()> {
             let mut pg_config = pg_config.clone();
             stop_postgres(&pg_config).ok(); // no need to fail on errors trying to stop postgres while initializing
             if !pg_config.is_real() {
-                pg_config = match download_postgres(&pg_config, &dir) {
+                // flags for this version from config.toml go first so the command line can override them
+                let mut flags = config_configure_flags
+                    .get(&pg_config.label()?)
+                    .cloned()
+                    .unwrap_or_default();
+                flags.extend(configure_flags.iter().cloned());
+
+                pg_config = match download_postgres(&pg_config, &dir, &flags, sources) {
                     Ok(pg_config) => pg_config,
                     Err(e) => return Err(eyre!(e)),
                 }
@@ -173,40 +580,28 @@ pub(crate) fn init_pgx(pgx: &Pgx) -> eyre::Result<()> {
         }
     }
 
-    write_config(output_configs)?;
+    write_config(output_configs, &config_configure_flags)?;
     Ok(())
 }
 
 #[tracing::instrument(level = "error", skip_all, fields(pg_version = %pg_config.version()?, pgx_home))]
-fn download_postgres(pg_config: &PgConfig, pgx_home: &PathBuf) -> eyre::Result<PgConfig> {
-    println!(
-        "{} Postgres v{}.{} from {}",
-        "  Downloading".bold().green(),
-        pg_config.major_version()?,
-        pg_config.minor_version()?,
-        pg_config.url().expect("no url"),
-    );
-    let url = pg_config.url().expect("no url for pg_config").as_str();
-    tracing::debug!(url = %url, "Fetching");
-    let mut http_client = HttpClient::new();
-    http_client.get().url(url);
-    if let Some((host, port)) =
-        env_proxy::for_url_str(pg_config.url().expect("no url for pg_config")).host_port()
-    {
-        http_client.proxy(Proxy::https(host, port as u32));
-    }
-    let http_response = http_client.emit()?;
-    tracing::trace!(status_code = %http_response.code(), url = %url, "Fetched");
-    if http_response.code() != 200 {
+fn download_postgres(
+    pg_config: &PgConfig,
+    pgx_home: &PathBuf,
+    configure_flags: &[String],
+    sources: &PostgresSources,
+) -> eyre::Result<PgConfig> {
+    if cfg!(windows) {
         return Err(eyre!(
-            "Problem downloading {}:\ncode={}\n{}",
-            pg_config.url().unwrap().to_string().yellow().bold(),
-            http_response.code(),
-            http_response.body().to_string()
+            "`cargo pgx init` can't build Postgres from source on Windows.  Install Postgres, such as with the EnterpriseDB installer, and pass its `pg_config.exe`, as in `cargo pgx init --{} \"C:\\Program Files\\PostgreSQL\\{}\\bin\\pg_config.exe\"`",
+            pg_config.label()?,
+            pg_config.major_version()?
         ));
     }
-    let pgdir = untar(http_response.body().binary(), pgx_home, pg_config)?;
-    configure_postgres(pg_config, &pgdir)?;
+
+    let bytes = sources.fetch(pg_config)?;
+    let pgdir = untar(&bytes, pgx_home, pg_config)?;
+    configure_postgres(pg_config, &pgdir, configure_flags)?;
     make_postgres(pg_config, &pgdir)?;
     make_install_postgres(pg_config, &pgdir) // returns a new PgConfig object
 }
@@ -259,7 +654,11 @@ fn untar(bytes: &[u8], pgxdir: &PathBuf, pg_config: &PgConfig) -> eyre::Result<P
     }
 }
 
-fn configure_postgres(pg_config: &PgConfig, pgdir: &PathBuf) -> eyre::Result<()> {
+fn configure_postgres(
+    pg_config: &PgConfig,
+    pgdir: &PathBuf,
+    configure_flags: &[String],
+) -> eyre::Result<()> {
     println!(
         "{} Postgres v{}.{}",
         "  Configuring".bold().green(),
@@ -275,6 +674,8 @@ fn configure_postgres(pg_config: &PgConfig, pgdir: &PathBuf) -> eyre::Result<()>
         .arg(format!("--with-pgport={}", pg_config.port()?))
         .arg("--enable-debug")
         .arg("--enable-cassert")
+        // user flags go last, so that one like `--disable-cassert` overrides our defaults
+        .args(configure_flags)
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
         .stdin(std::process::Stdio::null())
@@ -398,22 +799,38 @@ fn validate_pg_config(pg_config: &PgConfig) -> eyre::Result<()> {
     Ok(())
 }
 
-fn write_config(pg_configs: &Vec<PgConfig>) -> eyre::Result<()> {
+fn write_config(
+    pg_configs: &Vec<PgConfig>,
+    configure_flags: &HashMap<String, Vec<String>>,
+) -> eyre::Result<()> {
     let config_path = Pgx::config_toml()?;
     let mut file = File::create(&config_path)?;
     file.write_all(b"[configs]\n")?;
     for pg_config in pg_configs {
-        file.write_all(
-            format!(
-                "{}=\"{}\"\n",
-                pg_config.label()?,
-                pg_config
-                    .path()
-                    .ok_or(eyre!("no path for pg_config"))?
-                    .display()
-            )
-            .as_bytes(),
-        )?;
+        // quoted by `toml`, as the backslashes of a Windows path would be escapes otherwise
+        let path = pg_config
+            .path()
+            .ok_or(eyre!("no path for pg_config"))?
+            .display()
+            .to_string();
+        file.write_all(format!("{}={}\n", pg_config.label()?, toml::Value::from(path)).as_bytes())?;
+    }
+
+    // preserve the user's `[configure-flags]` for the next time we compile Postgres
+    if !configure_flags.is_empty() {
+        let mut labels = configure_flags.keys().collect::<Vec<_>>();
+        labels.sort();
+        file.write_all(b"\n[configure-flags]\n")?;
+        for label in labels {
+            file.write_all(
+                format!(
+                    "{}={}\n",
+                    label,
+                    toml::Value::from(configure_flags[label].clone())
+                )
+                .as_bytes(),
+            )?;
+        }
     }
 
     Ok(())
@@ -426,12 +843,13 @@ fn get_pg_installdir(pgdir: &PathBuf) -> PathBuf {
 }
 
 pub(crate) fn initdb(bindir: &PathBuf, datadir: &PathBuf) -> eyre::Result<()> {
-    println!(
+    eprintln!(
         " {} data directory at {}",
         "Initializing".bold().green(),
         datadir.display()
     );
-    let mut command = std::process::Command::new(format!("{}/initdb", bindir.display()));
+    let mut command =
+        std::process::Command::new(bindir.join(format!("initdb{}", std::env::consts::EXE_SUFFIX)));
     command
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -16,6 +16,7 @@ use eyre::{eyre, WrapErr};
 use owo_colors::OwoColorize;
 use pgx_utils::get_target_dir;
 use pgx_utils::pg_config::PgConfig;
+use pgx_utils::sanitizer::Sanitizer;
 use std::{
     io::BufReader,
     path::{Path, PathBuf},
@@ -42,11 +43,54 @@ pub(crate) struct Install {
     #[clap(long, short = 'c')]
     pg_config: Option<String>,
     #[clap(flatten)]
+    install_dirs: InstallDirs,
+    #[clap(flatten)]
     features: clap_cargo::Features,
+    /// Mark the installed extension `trusted = true` in its control file, so non-superusers may install it, once it passes the audit of `cargo pgx schema --trusted`
+    #[clap(long)]
+    trusted: bool,
     #[clap(from_global, parse(from_occurrences))]
     verbose: usize,
 }
 
+/// Directories to install the extension into instead of the ones `pg_config` reports
+#[derive(clap::Args, Debug, Default, Clone)]
+pub(crate) struct InstallDirs {
+    /// Install the shared library into this absolute path instead of `pg_config --pkglibdir`.  The control file's `module_pathname` is pointed at it
+    #[clap(long, parse(from_os_str))]
+    pub(crate) pkglibdir: Option<PathBuf>,
+    /// Install the control file and SQL scripts into `extension/` under this absolute path instead of under `pg_config --sharedir`
+    #[clap(long, parse(from_os_str))]
+    pub(crate) sharedir: Option<PathBuf>,
+}
+
+impl InstallDirs {
+    fn pkglibdir(&self, pg_config: &PgConfig) -> eyre::Result<PathBuf> {
+        match &self.pkglibdir {
+            Some(pkglibdir) => absolute(pkglibdir, "--pkglibdir"),
+            None => pg_config.pkglibdir(),
+        }
+    }
+
+    fn extension_dir(&self, pg_config: &PgConfig) -> eyre::Result<PathBuf> {
+        match &self.sharedir {
+            Some(sharedir) => Ok(absolute(sharedir, "--sharedir")?.join("extension")),
+            None => pg_config.extension_dir(),
+        }
+    }
+}
+
+fn absolute(path: &Path, flag: &str) -> eyre::Result<PathBuf> {
+    if path.is_relative() {
+        return Err(eyre!(
+            "`{} {}` must be an absolute path",
+            flag,
+            path.display()
+        ));
+    }
+    Ok(path.to_path_buf())
+}
+
 impl CommandExecute for Install {
     #[tracing::instrument(level = "error", skip(self))]
     fn execute(self) -> eyre::Result<()> {
@@ -61,7 +105,12 @@ impl CommandExecute for Install {
 
         let pg_config = match self.pg_config {
             None => PgConfig::from_path(),
-            Some(config) => PgConfig::new(PathBuf::from(config)),
+            Some(config) => {
+                let pg_config = PgConfig::new(PathBuf::from(config));
+                // so the pgx-pg-sys bindings get generated from the same Postgres
+                pg_config.export();
+                pg_config
+            }
         };
         let pg_version = format!("pg{}", pg_config.major_version()?);
 
@@ -76,7 +125,9 @@ impl CommandExecute for Install {
             self.release,
             self.test,
             None,
+            &self.install_dirs,
             &features,
+            self.trusted,
         )
     }
 }
@@ -96,43 +147,86 @@ pub(crate) fn install_extension(
     is_release: bool,
     is_test: bool,
     base_directory: Option<PathBuf>,
+    install_dirs: &InstallDirs,
     features: &clap_cargo::Features,
+    trusted: bool,
 ) -> eyre::Result<()> {
-    let base_directory = base_directory.unwrap_or("/".into());
-    tracing::Span::current().record(
-        "base_directory",
-        &tracing::field::display(&base_directory.display()),
-    );
+    check_relocatable(&package_manifest_path)?;
 
-    let manifest = Manifest::from_path(&package_manifest_path)?;
-    let (control_file, extname) = find_control_file(&package_manifest_path)?;
+    let build_command_output = build_extension(
+        user_manifest_path.as_ref(),
+        user_package,
+        is_release,
+        &features,
+    )?;
+    let build_command_messages = parse_build_messages(&build_command_output.stdout)?;
+
+    install_built_extension(
+        user_manifest_path,
+        user_package,
+        package_manifest_path,
+        pg_config,
+        is_release,
+        is_test,
+        base_directory,
+        install_dirs,
+        features,
+        trusted,
+        &build_command_messages,
+    )
+}
 
+/// The extension's control file must say it isn't `relocatable`
+pub(crate) fn check_relocatable(package_manifest_path: impl AsRef<Path>) -> eyre::Result<()> {
     if get_property(&package_manifest_path, "relocatable")? != Some("false".into()) {
+        let (control_file, _) = find_control_file(&package_manifest_path)?;
         return Err(eyre!(
             "{}:  The `relocatable` property MUST be `false`.  Please update your .control file.",
             control_file.display()
         ));
     }
+    Ok(())
+}
 
-    let build_command_output = build_extension(
-        user_manifest_path.as_ref(),
-        user_package,
-        is_release,
-        &features,
-    )?;
-    let build_command_bytes = build_command_output.stdout;
-    let build_command_reader = BufReader::new(build_command_bytes.as_slice());
+/// The messages in the `--message-format=json-render-diagnostics` output of the build
+pub(crate) fn parse_build_messages(stdout: &[u8]) -> eyre::Result<Vec<cargo_metadata::Message>> {
+    let build_command_reader = BufReader::new(stdout);
     let build_command_stream = cargo_metadata::Message::parse_stream(build_command_reader);
-    let build_command_messages =
-        build_command_stream.collect::<Result<Vec<_>, std::io::Error>>()?;
+    Ok(build_command_stream.collect::<Result<Vec<_>, std::io::Error>>()?)
+}
+
+/// Install the extension whose build reported `build_command_messages`, without building it again
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn install_built_extension(
+    user_manifest_path: Option<impl AsRef<Path>>,
+    user_package: Option<&String>,
+    package_manifest_path: impl AsRef<Path>,
+    pg_config: &PgConfig,
+    is_release: bool,
+    is_test: bool,
+    base_directory: Option<PathBuf>,
+    install_dirs: &InstallDirs,
+    features: &clap_cargo::Features,
+    trusted: bool,
+    build_command_messages: &Vec<cargo_metadata::Message>,
+) -> eyre::Result<()> {
+    let base_directory = base_directory.unwrap_or("/".into());
+    tracing::Span::current().record(
+        "base_directory",
+        &tracing::field::display(&base_directory.display()),
+    );
+
+    let manifest = Manifest::from_path(&package_manifest_path)?;
+    let (control_file, extname) = find_control_file(&package_manifest_path)?;
 
     println!();
     println!("installing extension");
-    let pkgdir = make_relative(pg_config.pkglibdir()?);
-    let extdir = make_relative(pg_config.extension_dir()?);
-    let shlibpath = find_library_file(&manifest, &build_command_messages)?;
+    let pkglibdir = install_dirs.pkglibdir(pg_config)?;
+    let pkgdir = make_relative(pkglibdir.clone());
+    let extdir = make_relative(install_dirs.extension_dir(pg_config)?);
+    let shlibpath = find_library_file(&manifest, build_command_messages)?;
 
-    {
+    let control_file_dest = {
         let mut dest = base_directory.clone();
         dest.push(&extdir);
         dest.push(
@@ -147,12 +241,19 @@ pub(crate) fn install_extension(
             true,
             &package_manifest_path,
         )?;
-    }
+        if install_dirs.pkglibdir.is_some() {
+            // `$libdir` won't find the shared library, so tell Postgres exactly where it is
+            relocate_module_pathname(&dest, &pkglibdir)?;
+        }
+        dest
+    };
 
     {
         let mut dest = base_directory.clone();
         dest.push(&pkgdir);
-        dest.push(format!("{}.so", extname));
+        // Postgres looks for `.so` on macOS too, but for `.dll` on Windows
+        let dlsuffix = if cfg!(windows) { "dll" } else { "so" };
+        dest.push(format!("{}.{}", extname, dlsuffix));
 
         if cfg!(target_os = "macos") {
             // Remove the existing .so if present. This is a workaround for an
@@ -184,8 +285,23 @@ pub(crate) fn install_extension(
         &extdir,
         &base_directory,
         true,
+        trusted,
     )?;
 
+    // the extensions its `requires_extension!()`s check for, which Postgres has to know about too
+    let schema_file = get_target_sql_file(&package_manifest_path, &extdir, &base_directory)?;
+    let schema = std::fs::read_to_string(&schema_file)
+        .wrap_err_with(|| format!("failed to read `{}`", schema_file.display()))?;
+    let required = pgx_utils::sql_entity_graph::required_extensions(&schema);
+    if !required.is_empty() {
+        add_requires(&control_file_dest, &required)?;
+    }
+
+    if trusted {
+        // only now that the SQL has passed the audit
+        mark_trusted(&control_file_dest)?;
+    }
+
     println!("{} installing {}", "    Finished".bold().green(), extname);
     Ok(())
 }
@@ -237,15 +353,47 @@ pub(crate) fn build_extension(
     is_release: bool,
     features: &clap_cargo::Features,
 ) -> eyre::Result<std::process::Output> {
+    let mut command =
+        build_extension_command(user_manifest_path, user_package, is_release, features)?;
+    let command = command.stderr(Stdio::inherit());
+    let command_str = format!("{:?}", command);
+    println!(
+        "building extension with features `{}`\n{}",
+        features.features.join(" "),
+        command_str
+    );
+    let cargo_output = command
+        .output()
+        .wrap_err_with(|| format!("failed to spawn cargo: {}", command_str))?;
+    if !cargo_output.status.success() {
+        // We explicitly do not want to return a spantraced error here.
+        std::process::exit(1)
+    } else {
+        Ok(cargo_output)
+    }
+}
+
+/// The `cargo build` invocation used to compile the extension's shared library
+pub(crate) fn build_extension_command(
+    user_manifest_path: Option<impl AsRef<Path>>,
+    user_package: Option<&String>,
+    is_release: bool,
+    features: &clap_cargo::Features,
+) -> eyre::Result<Command> {
     let flags = std::env::var("PGX_BUILD_FLAGS").unwrap_or_default();
 
     let mut command = Command::new("cargo");
     command.arg("build");
 
+    let mut manifest_dir = PathBuf::new();
     if let Some(user_manifest_path) = user_manifest_path {
         command.arg("--manifest-path");
         command.arg(user_manifest_path.as_ref());
+        if let Some(parent) = user_manifest_path.as_ref().parent() {
+            manifest_dir = parent.to_path_buf();
+        }
     }
+    command.envs(pgx_utils::build_info_env(&manifest_dir));
 
     if let Some(user_package) = user_package {
         command.arg("--package");
@@ -272,25 +420,21 @@ pub(crate) fn build_extension(
 
     command.arg("--message-format=json-render-diagnostics");
 
+    // for `cargo pgx test --sanitizer`, whose test framework runs this
+    if let Some(sanitizer) = Sanitizer::from_env()? {
+        command
+            .arg("--target")
+            .arg(sanitizer.host_target()?)
+            .env("RUSTFLAGS", sanitizer.rustflags());
+    } else if let Some(rustflags) = pgx_utils::musl_rustflags() {
+        command.env("RUSTFLAGS", rustflags);
+    }
+
     for arg in flags.split_ascii_whitespace() {
         command.arg(arg);
     }
 
-    let command = command.stderr(Stdio::inherit());
-    let command_str = format!("{:?}", command);
-    println!(
-        "building extension with features `{}`\n{}",
-        features_arg, command_str
-    );
-    let cargo_output = command
-        .output()
-        .wrap_err_with(|| format!("failed to spawn cargo: {}", command_str))?;
-    if !cargo_output.status.success() {
-        // We explicitly do not want to return a spantraced error here.
-        std::process::exit(1)
-    } else {
-        Ok(cargo_output)
-    }
+    Ok(command)
 }
 
 fn get_target_sql_file(
@@ -319,23 +463,41 @@ fn copy_sql_files(
     extdir: &PathBuf,
     base_directory: &PathBuf,
     skip_build: bool,
+    trusted: bool,
 ) -> eyre::Result<()> {
     let dest = get_target_sql_file(&package_manifest_path, extdir, base_directory)?;
     let (_, extname) = crate::command::get::find_control_file(&package_manifest_path)?;
 
-    crate::command::schema::generate_schema(
-        pg_config,
-        user_manifest_path,
-        user_package,
-        &package_manifest_path,
-        is_release,
-        is_test,
-        features,
-        Some(&dest),
-        Option::<String>::None,
-        None,
-        skip_build,
-    )?;
+    if cfg!(windows) {
+        // the schema can't be generated here, so it's copied from `sql/` with the upgrade files
+        let schema = Path::new("sql").join(dest.file_name().unwrap());
+        if !schema.exists() {
+            return Err(eyre!(
+                "`{}` is missing.  Windows can't generate the schema, so run `cargo pgx schema --out {}` on Linux or macOS first",
+                schema.display(),
+                schema.display()
+            ));
+        }
+    } else {
+        crate::command::schema::generate_schema(
+            pg_config,
+            user_manifest_path,
+            user_package,
+            &package_manifest_path,
+            is_release,
+            is_test,
+            features,
+            Some(&dest),
+            Option::<String>::None,
+            Option::<String>::None,
+            Option::<String>::None,
+            Option::<String>::None,
+            false,
+            trusted,
+            None,
+            skip_build,
+        )?;
+    }
 
     // now copy all the version upgrade files too
     if let Ok(dir) = std::fs::read_dir("sql/") {
@@ -381,12 +543,7 @@ pub(crate) fn find_library_file(
                     continue;
                 }
                 for filename in &artifact.filenames {
-                    let so_extension = if cfg!(target_os = "macos") {
-                        "dylib"
-                    } else {
-                        "so"
-                    };
-                    if filename.extension() == Some(so_extension) {
+                    if filename.extension() == Some(std::env::consts::DLL_EXTENSION) {
                         library_file = Some(filename.to_string());
                         break;
                     }
@@ -459,6 +616,78 @@ pub(crate) fn format_display_path(path: impl AsRef<Path>) -> eyre::Result<String
     Ok(out)
 }
 
+/// Point a control file's `module_pathname` at `pkglibdir` rather than `$libdir`
+fn relocate_module_pathname(control_file: &PathBuf, pkglibdir: &Path) -> eyre::Result<()> {
+    let contents = std::fs::read_to_string(control_file)
+        .wrap_err_with(|| format!("failed to read `{}`", control_file.display()))?;
+    let module_pathname = regex::Regex::new(r"(?m)^(\s*module_pathname\s*=\s*')\$libdir/")?;
+    let relocated = module_pathname.replace_all(&contents, |captures: &regex::Captures| {
+        format!("{}{}/", &captures[1], pkglibdir.display())
+    });
+    std::fs::write(control_file, relocated.as_bytes())
+        .wrap_err_with(|| format!("failed writing `{}`", control_file.display()))?;
+    Ok(())
+}
+
+/// Set a control file's `trusted` to `true`, adding it if it isn't there
+fn mark_trusted(control_file: &PathBuf) -> eyre::Result<()> {
+    let contents = std::fs::read_to_string(control_file)
+        .wrap_err_with(|| format!("failed to read `{}`", control_file.display()))?;
+    let trusted = regex::Regex::new(r"(?m)^\s*trusted\s*=.*$")?;
+    let marked = if trusted.is_match(&contents) {
+        trusted
+            .replace_all(&contents, "trusted = true")
+            .into_owned()
+    } else {
+        let mut marked = contents;
+        if !marked.is_empty() && !marked.ends_with('\n') {
+            marked.push('\n');
+        }
+        marked.push_str("trusted = true\n");
+        marked
+    };
+    std::fs::write(control_file, marked.as_bytes())
+        .wrap_err_with(|| format!("failed writing `{}`", control_file.display()))?;
+    Ok(())
+}
+
+/// Add `extensions` to a control file's `requires`, adding it if it isn't there
+fn add_requires(control_file: &PathBuf, extensions: &[&str]) -> eyre::Result<()> {
+    let contents = std::fs::read_to_string(control_file)
+        .wrap_err_with(|| format!("failed to read `{}`", control_file.display()))?;
+    let requires_line = regex::Regex::new(r"(?m)^\s*requires\s*=\s*'([^']*)'.*$")?;
+    let mut requires = match requires_line.captures(&contents) {
+        Some(captures) => captures[1]
+            .split(',')
+            .map(|extension| extension.trim().to_string())
+            .filter(|extension| !extension.is_empty())
+            .collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    for extension in extensions {
+        if !requires.iter().any(|existing| existing == extension) {
+            requires.push(extension.to_string());
+        }
+    }
+    let line = format!("requires = '{}'", requires.join(", "));
+    let added = if requires_line.is_match(&contents) {
+        requires_line
+            .replace_all(&contents, regex::NoExpand(&line))
+            .into_owned()
+    } else {
+        let mut added = contents;
+        if !added.is_empty() && !added.ends_with('\n') {
+            added.push('\n');
+        }
+        added.push_str(&line);
+        added.push('\n');
+        added
+    };
+    std::fs::write(control_file, added.as_bytes())
+        .wrap_err_with(|| format!("failed writing `{}`", control_file.display()))?;
+    Ok(())
+}
+
 fn filter_contents(manifest_path: impl AsRef<Path>, mut input: String) -> eyre::Result<String> {
     if input.contains("@GIT_HASH@") {
         // avoid doing this if we don't actually have the token
@@ -0,0 +1,161 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{
+    command::{get::find_control_file, install::get_version, status::status_postgres},
+    CommandExecute,
+};
+use cargo_toml::Manifest;
+use eyre::{eyre, WrapErr};
+use pgx_utils::pg_config::{PgConfig, PgConfigSelector, Pgx};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// The version of the JSON `cargo pgx metadata` prints, which changes only when a field is
+/// removed or changes its meaning
+const FORMAT_VERSION: u32 = 1;
+
+/// Print a JSON description of the extension and the pgx-managed Postgres instances, for tools
+#[derive(clap::Args, Debug)]
+#[clap(author)]
+pub(crate) struct Metadata {
+    /// The Postgres versions to describe (`pg10`, `pg11`, `pg12`, `pg13`, `pg14`, `pg15`, or `all`)
+    #[clap(env = "PG_VERSION")]
+    pg_version: Option<String>,
+    #[clap(from_global, parse(from_occurrences))]
+    verbose: usize,
+    /// Package to describe (see `cargo help pkgid`)
+    #[clap(long, short)]
+    package: Option<String>,
+    /// Path to Cargo.toml
+    #[clap(long, parse(from_os_str))]
+    manifest_path: Option<PathBuf>,
+    /// Indent the JSON for people to read
+    #[clap(long)]
+    pretty: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct ExtensionMetadata {
+    format_version: u32,
+    /// The extension's name, from its control file's
+    name: String,
+    /// The name of the crate the extension is built from
+    package: String,
+    /// The extension's `default_version`
+    version: String,
+    manifest_path: PathBuf,
+    control_file: PathBuf,
+    /// The `pg$VERSION` feature enabled by default, if any
+    default_pg_version: Option<String>,
+    pg_versions: Vec<PgVersionMetadata>,
+}
+
+#[derive(Serialize, Debug)]
+struct PgVersionMetadata {
+    /// Such as `pg14`
+    label: String,
+    /// Such as `14.2`
+    version: String,
+    pg_config: Option<PathBuf>,
+    /// Does the crate have a `pg$VERSION` feature for this version?
+    has_feature: bool,
+    /// The port `cargo pgx start` and `cargo pgx run` have Postgres listen on
+    port: u16,
+    /// The port of the Postgres `cargo pgx test` starts
+    test_port: u16,
+    data_dir: PathBuf,
+    log_file: PathBuf,
+    running: bool,
+    /// Where `cargo pgx install` puts the shared library
+    pkglibdir: PathBuf,
+    /// Where `cargo pgx install` puts the control file and SQL
+    extension_dir: PathBuf,
+    /// The SQL `cargo pgx install` generates for this version of the extension
+    sql_file: PathBuf,
+}
+
+impl CommandExecute for Metadata {
+    #[tracing::instrument(level = "error", skip(self))]
+    fn execute(self) -> eyre::Result<()> {
+        let metadata = crate::metadata::metadata(&Default::default(), self.manifest_path.as_ref())
+            .wrap_err("couldn't get cargo metadata")?;
+        crate::metadata::validate(&metadata)?;
+        let package_manifest_path =
+            crate::manifest::manifest_path(&metadata, self.package.as_ref())
+                .wrap_err("Couldn't get manifest path")?;
+        let package_manifest =
+            Manifest::from_path(&package_manifest_path).wrap_err("Couldn't parse manifest")?;
+        let package = package_manifest
+            .package
+            .as_ref()
+            .ok_or_else(|| eyre!("Could not find crate name in Cargo.toml."))?
+            .name
+            .clone();
+
+        let (control_file, name) = find_control_file(&package_manifest_path)?;
+        let version = get_version(&package_manifest_path)?;
+
+        let pgx = Pgx::from_config()?;
+        let selector = self.pg_version.unwrap_or_else(|| "all".to_string());
+        let mut pg_versions = Vec::new();
+        for pg_config in pgx.iter(PgConfigSelector::new(&selector)) {
+            pg_versions.push(describe_pg_version(
+                pg_config?,
+                &package_manifest,
+                &name,
+                &version,
+            )?);
+        }
+
+        let extension = ExtensionMetadata {
+            format_version: FORMAT_VERSION,
+            name,
+            package,
+            version,
+            manifest_path: package_manifest_path,
+            control_file,
+            default_pg_version: crate::manifest::default_pg_version(&package_manifest),
+            pg_versions,
+        };
+
+        let json = if self.pretty {
+            serde_json::to_string_pretty(&extension)
+        } else {
+            serde_json::to_string(&extension)
+        }
+        .wrap_err("couldn't serialize the extension's metadata")?;
+        println!("{}", json);
+        Ok(())
+    }
+}
+
+fn describe_pg_version(
+    pg_config: &PgConfig,
+    manifest: &Manifest,
+    extname: &str,
+    version: &str,
+) -> eyre::Result<PgVersionMetadata> {
+    let label = pg_config.label()?;
+    let extension_dir = pg_config.extension_dir()?;
+    Ok(PgVersionMetadata {
+        version: pg_config.version()?,
+        pg_config: pg_config.path(),
+        has_feature: manifest.features.contains_key(&label),
+        port: pg_config.port()?,
+        test_port: pg_config.test_port()?,
+        data_dir: pg_config.data_dir()?,
+        log_file: pg_config.log_file()?,
+        running: status_postgres(pg_config)?,
+        pkglibdir: pg_config.pkglibdir()?,
+        sql_file: extension_dir.join(format!("{}--{}.sql", extname, version)),
+        extension_dir,
+        label,
+    })
+}
@@ -11,12 +11,15 @@ pub(crate) mod connect;
 pub(crate) mod get;
 pub(crate) mod init;
 pub(crate) mod install;
+pub(crate) mod metadata;
 pub(crate) mod new;
 pub(crate) mod package;
 pub(crate) mod pgx;
+pub(crate) mod regress;
 pub(crate) mod run;
 pub(crate) mod schema;
 pub(crate) mod start;
 pub(crate) mod status;
 pub(crate) mod stop;
 pub(crate) mod test;
+pub(crate) mod watch;
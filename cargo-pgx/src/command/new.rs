@@ -18,9 +18,12 @@ use crate::CommandExecute;
 pub(crate) struct New {
     /// The name of the extension
     name: String,
-    /// Create a background worker template
-    #[clap(long, short)]
+    /// Create a background worker template (shorthand for `--template bgworker`)
+    #[clap(long, short, conflicts_with = "template")]
     bgworker: bool,
+    /// The kind of extension to scaffold
+    #[clap(long, short, arg_enum, default_value = "default")]
+    template: Template,
     #[clap(from_global, parse(from_occurrences))]
     verbose: usize,
 }
@@ -30,7 +33,38 @@ impl CommandExecute for New {
     fn execute(self) -> eyre::Result<()> {
         validate_extension_name(&self.name)?;
         let path = PathBuf::from_str(&format!("{}/", self.name)).unwrap();
-        create_crate_template(path, &self.name, self.bgworker)
+        let template = if self.bgworker {
+            Template::Bgworker
+        } else {
+            self.template
+        };
+        create_crate_template(path, &self.name, template)
+    }
+}
+
+/// The archetypes `cargo pgx new` knows how to scaffold
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Template {
+    /// A single "hello world" function
+    Default,
+    /// A background worker, started through `shared_preload_libraries`
+    Bgworker,
+    /// A custom type with comparison operators and btree/hash operator classes
+    CustomType,
+    /// A row-level trigger function and a table that uses it
+    Trigger,
+    /// A foreign data wrapper built on Postgres' `FdwRoutine` callbacks
+    Fdw,
+    /// Executor hooks, registered through `shared_preload_libraries`
+    Hooks,
+}
+
+impl Template {
+    fn extra_dependencies(&self) -> &'static str {
+        match self {
+            Template::CustomType => "serde = { version = \"1.0\", features = [\"derive\"] }\n",
+            _ => "",
+        }
     }
 }
 
@@ -47,13 +81,13 @@ fn validate_extension_name(extname: &str) -> eyre::Result<()> {
 pub(crate) fn create_crate_template(
     path: PathBuf,
     name: &str,
-    is_bgworker: bool,
+    template: Template,
 ) -> eyre::Result<()> {
     create_directory_structure(&path)?;
     create_control_file(&path, name)?;
-    create_cargo_toml(&path, name)?;
+    create_cargo_toml(&path, name, template)?;
     create_dotcargo_config(&path, name)?;
-    create_lib_rs(&path, name, is_bgworker)?;
+    create_lib_rs(&path, name, template)?;
     create_git_ignore(&path, name)?;
 
     Ok(())
@@ -85,13 +119,24 @@ fn create_control_file(path: &PathBuf, name: &str) -> Result<(), std::io::Error>
     Ok(())
 }
 
-fn create_cargo_toml(path: &PathBuf, name: &str) -> Result<(), std::io::Error> {
+fn create_cargo_toml(
+    path: &PathBuf,
+    name: &str,
+    template: Template,
+) -> Result<(), std::io::Error> {
     let mut filename = path.clone();
 
     filename.push("Cargo.toml");
     let mut file = std::fs::File::create(filename)?;
 
-    file.write_all(&format!(include_str!("../templates/cargo_toml"), name = name).as_bytes())?;
+    file.write_all(
+        &format!(
+            include_str!("../templates/cargo_toml"),
+            name = name,
+            extra_dependencies = template.extra_dependencies()
+        )
+        .as_bytes(),
+    )?;
 
     Ok(())
 }
@@ -108,20 +153,24 @@ fn create_dotcargo_config(path: &PathBuf, _name: &str) -> Result<(), std::io::Er
     Ok(())
 }
 
-fn create_lib_rs(path: &PathBuf, name: &str, is_bgworker: bool) -> Result<(), std::io::Error> {
+fn create_lib_rs(path: &PathBuf, name: &str, template: Template) -> Result<(), std::io::Error> {
     let mut filename = path.clone();
 
     filename.push("src");
     filename.push("lib.rs");
     let mut file = std::fs::File::create(filename)?;
 
-    if is_bgworker {
-        file.write_all(
-            &format!(include_str!("../templates/bgworker_lib_rs"), name = name).as_bytes(),
-        )?;
-    } else {
-        file.write_all(&format!(include_str!("../templates/lib_rs"), name = name).as_bytes())?;
-    }
+    let lib_rs = match template {
+        Template::Default => format!(include_str!("../templates/lib_rs"), name = name),
+        Template::Bgworker => format!(include_str!("../templates/bgworker_lib_rs"), name = name),
+        Template::CustomType => {
+            format!(include_str!("../templates/custom_type_lib_rs"), name = name)
+        }
+        Template::Trigger => format!(include_str!("../templates/trigger_lib_rs"), name = name),
+        Template::Fdw => format!(include_str!("../templates/fdw_lib_rs"), name = name),
+        Template::Hooks => format!(include_str!("../templates/hooks_lib_rs"), name = name),
+    };
+    file.write_all(lib_rs.as_bytes())?;
 
     Ok(())
 }
@@ -8,13 +8,31 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use crate::{
-    command::{get::get_property, install::install_extension},
+    command::{
+        get::get_property,
+        install::{install_extension, InstallDirs},
+    },
     CommandExecute,
 };
 use cargo_toml::Manifest;
 use eyre::{eyre, WrapErr};
+use owo_colors::OwoColorize;
 use pgx_utils::{get_target_dir, pg_config::PgConfig};
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The environment variables passed on to `cargo pgx package` in a `--container`, when they're set
+const CONTAINER_ENV_PASSTHROUGH: &[&str] = &[
+    "PGX_BUILD_FLAGS",
+    "PGX_BUILD_VERBOSE",
+    "PGX_PG_SYS_EXTRA_HEADERS",
+    "PGX_PG_SYS_BLOCKLIST",
+    "PGX_PG_SYS_ALLOWLIST",
+    "RUSTFLAGS",
+    "SOURCE_DATE_EPOCH",
+];
 
 /// Create an installation package directory.
 #[derive(clap::Args, Debug)]
@@ -39,7 +57,18 @@ pub(crate) struct Package {
     #[clap(long, parse(from_os_str))]
     out_dir: Option<PathBuf>,
     #[clap(flatten)]
+    install_dirs: InstallDirs,
+    #[clap(flatten)]
     features: clap_cargo::Features,
+    /// Mark the packaged extension `trusted = true` in its control file, so non-superusers may install it, once it passes the audit of `cargo pgx schema --trusted`
+    #[clap(long)]
+    trusted: bool,
+    /// Build the package inside this Docker or Podman image, such as one with an older glibc than the host's, so it runs on more distributions (default output is `./target/container/$IMAGE/[debug|release]/extname-pgXX/`)
+    #[clap(long, value_name = "IMAGE")]
+    container: Option<String>,
+    /// The program to run the `--container` with (default is `docker`, or else `podman`)
+    #[clap(long, env = "PGX_CONTAINER_ENGINE", value_name = "ENGINE")]
+    container_engine: Option<String>,
     #[clap(from_global, parse(from_occurrences))]
     verbose: usize,
 }
@@ -56,9 +85,24 @@ impl CommandExecute for Package {
         let package_manifest =
             Manifest::from_path(&package_manifest_path).wrap_err("Couldn't parse manifest")?;
 
+        if let Some(image) = &self.container {
+            return package_in_container(
+                image,
+                self.container_engine.as_deref(),
+                &metadata,
+                self.pg_config.is_some(),
+                self.out_dir.as_ref(),
+            );
+        }
+
         let pg_config = match self.pg_config {
             None => PgConfig::from_path(),
-            Some(config) => PgConfig::new(PathBuf::from(config)),
+            Some(config) => {
+                let pg_config = PgConfig::new(PathBuf::from(config));
+                // so the pgx-pg-sys bindings get generated from the same Postgres
+                pg_config.export();
+                pg_config
+            }
         };
         let pg_version = format!("pg{}", pg_config.major_version()?);
 
@@ -78,7 +122,9 @@ impl CommandExecute for Package {
             out_dir,
             self.debug,
             self.test,
+            &self.install_dirs,
             &features,
+            self.trusted,
         )
     }
 }
@@ -96,7 +142,9 @@ pub(crate) fn package_extension(
     out_dir: PathBuf,
     is_debug: bool,
     is_test: bool,
+    install_dirs: &InstallDirs,
     features: &clap_cargo::Features,
+    trusted: bool,
 ) -> eyre::Result<()> {
     if !out_dir.exists() {
         std::fs::create_dir_all(&out_dir)?;
@@ -110,7 +158,9 @@ pub(crate) fn package_extension(
         !is_debug,
         is_test,
         Some(out_dir),
+        install_dirs,
         features,
+        trusted,
     )
 }
 
@@ -127,3 +177,150 @@ fn build_base_path(
     target_dir.push(format!("{}-pg{}", extname, pgver));
     Ok(target_dir)
 }
+
+/// Run this `cargo pgx package`, but for `--container`, in `image`, which has the workspace, and
+/// the target directory, mounted at the same paths as on the host, so the paths in the command
+/// and its output are the same in both.
+///
+/// The build is in a target directory of the image's own, so nothing built on the host, or in
+/// another image, is mixed into it, and the crates it downloads are kept there for the next
+/// build.  Docker runs the build as the owner of the workspace, so the files it makes are theirs,
+/// while rootless Podman already maps the container's root to the user running it.
+#[tracing::instrument(level = "error", skip_all, fields(image = %image))]
+fn package_in_container(
+    image: &str,
+    engine: Option<&str>,
+    metadata: &cargo_metadata::Metadata,
+    has_pg_config: bool,
+    out_dir: Option<&PathBuf>,
+) -> eyre::Result<()> {
+    let engine = match engine {
+        Some(engine) => engine.to_string(),
+        None => ["docker", "podman"]
+            .iter()
+            .find(|engine| {
+                Command::new(engine)
+                    .arg("--version")
+                    .output()
+                    .map(|output| output.status.success())
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| eyre!("neither `docker` nor `podman` was found to run the `--container`.  Give another with `--container-engine`"))?
+            .to_string(),
+    };
+
+    let workspace_root = metadata.workspace_root.clone().into_std_path_buf();
+    let current_dir = std::env::current_dir()?;
+    if !current_dir.starts_with(&workspace_root) {
+        return Err(eyre!(
+            "`--container` builds run in the workspace, at `{}`, so `cargo pgx package` must be run from inside it",
+            workspace_root.display()
+        ));
+    }
+
+    let host_target_dir = metadata.target_directory.clone().into_std_path_buf();
+    let image_dir = host_target_dir.join("container").join(
+        image
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>(),
+    );
+    std::fs::create_dir_all(image_dir.join("home"))
+        .wrap_err_with(|| eyre!("couldn't create `{}`", image_dir.display()))?;
+
+    let mut mounts = vec![workspace_root.clone()];
+    if !host_target_dir.starts_with(&workspace_root) {
+        mounts.push(host_target_dir.clone());
+    }
+    if let Some(out_dir) = out_dir {
+        let out_dir = current_dir.join(out_dir);
+        if !mounts.iter().any(|mount| out_dir.starts_with(mount)) {
+            std::fs::create_dir_all(&out_dir)?;
+            mounts.push(out_dir);
+        }
+    }
+
+    let mut command = Command::new(&engine);
+    command.arg("run").arg("--rm");
+    if atty::is(atty::Stream::Stdout) {
+        command.arg("--tty");
+    }
+    for mount in &mounts {
+        command
+            .arg("--volume")
+            .arg(format!("{}:{}", mount.display(), mount.display()));
+    }
+    command.arg("--workdir").arg(&current_dir);
+    #[cfg(unix)]
+    if !engine.ends_with("podman") {
+        use std::os::unix::fs::MetadataExt;
+        let owner = std::fs::metadata(&workspace_root)?;
+        command
+            .arg("--user")
+            .arg(format!("{}:{}", owner.uid(), owner.gid()));
+    }
+    command
+        .arg("--env")
+        .arg(format!("CARGO_TARGET_DIR={}", image_dir.display()))
+        .arg("--env")
+        .arg(format!("CARGO_HOME={}", image_dir.join("cargo").display()))
+        .arg("--env")
+        .arg(format!("HOME={}", image_dir.join("home").display()));
+    if !has_pg_config {
+        // the bindings are generated from the image's Postgres too
+        command.arg("--env").arg("PGX_PG_CONFIG_PATH=pg_config");
+    }
+    for var in CONTAINER_ENV_PASSTHROUGH {
+        if std::env::var_os(var).is_some() {
+            command.arg("--env").arg(var);
+        }
+    }
+    command
+        .arg(image)
+        .arg("cargo")
+        .arg("pgx")
+        .args(container_args(std::env::args().skip(1)));
+
+    println!(
+        "{} `cargo pgx package` in {} with {}",
+        "      Running".bold().green(),
+        image.cyan(),
+        engine
+    );
+    let command_str = format!("{:?}", command);
+    tracing::debug!(command = %command_str, "Running");
+    let status = command
+        .status()
+        .wrap_err_with(|| eyre!("unable to execute: {}", command_str))?;
+    tracing::trace!(status_code = %status, command = %command_str, "Finished");
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(eyre!("`cargo pgx package` failed in {}: {}", image, status))
+    }
+}
+
+/// This command's arguments, after `cargo pgx`, without the ones that ran it in a container
+fn container_args(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut args = args.peekable();
+    if args.peek().map(String::as_str) == Some("pgx") {
+        args.next();
+    }
+
+    let mut container_args = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--container" || arg == "--container-engine" {
+            args.next();
+        } else if !arg.starts_with("--container=") && !arg.starts_with("--container-engine=") {
+            container_args.push(arg);
+        }
+    }
+    container_args
+}
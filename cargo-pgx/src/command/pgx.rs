@@ -39,6 +39,8 @@ enum CargoPgxSubCommands {
     Connect(super::connect::Connect),
     Test(super::test::Test),
     Get(super::get::Get),
+    Metadata(super::metadata::Metadata),
+    Watch(super::watch::Watch),
 }
 
 impl CommandExecute for CargoPgxSubCommands {
@@ -58,6 +60,8 @@ impl CommandExecute for CargoPgxSubCommands {
             Connect(c) => c.execute(),
             Test(c) => c.execute(),
             Get(c) => c.execute(),
+            Metadata(c) => c.execute(),
+            Watch(c) => c.execute(),
         }
     }
 }
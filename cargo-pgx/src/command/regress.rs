@@ -0,0 +1,167 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! `pg_regress` tests: SQL scripts in `pg_regress/sql/`, whose `psql` output must match the
+//! files of the same name in `pg_regress/expected/`
+use crate::command::{
+    get::get_property,
+    install::{install_extension, InstallDirs},
+    start::start_postgres,
+    stop::stop_postgres,
+};
+use eyre::{eyre, WrapErr};
+use owo_colors::OwoColorize;
+use pgx_utils::{get_target_dir, pg_config::PgConfig};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Where a crate's `pg_regress` tests are, relative to its manifest
+pub(crate) const REGRESS_DIR: &str = "pg_regress";
+
+/// The names of the `pg_regress` tests of the crate at `package_manifest_path`, in the order
+/// they're run, which is by name.  Only those containing `testname` are included, if it's given
+pub(crate) fn find_regression_tests(
+    package_manifest_path: impl AsRef<Path>,
+    testname: Option<&str>,
+) -> eyre::Result<Vec<String>> {
+    let sql_dir = regress_dir(package_manifest_path).join("sql");
+    if !sql_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut tests = Vec::new();
+    for entry in std::fs::read_dir(&sql_dir)
+        .wrap_err_with(|| format!("failed to read `{}`", sql_dir.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().map(|ext| ext == "sql") != Some(true) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            if testname.map(|testname| name.contains(testname)) != Some(false) {
+                tests.push(name.to_string());
+            }
+        }
+    }
+    tests.sort();
+    Ok(tests)
+}
+
+/// Install the extension into the pgx-managed Postgres of `pg_config`, and run the `tests` with
+/// `pg_regress` in a fresh database, printing the differences from what's expected of those
+/// which fail
+#[tracing::instrument(level = "error", skip_all, fields(
+    pg_version = %pg_config.version()?,
+    release = is_release,
+))]
+pub(crate) fn run_regression_tests(
+    pg_config: &PgConfig,
+    user_manifest_path: Option<impl AsRef<Path>>,
+    user_package: Option<&String>,
+    package_manifest_path: impl AsRef<Path>,
+    is_release: bool,
+    features: &clap_cargo::Features,
+    tests: &[String],
+) -> eyre::Result<()> {
+    let extname = get_property(&package_manifest_path, "extname")?
+        .ok_or(eyre!("could not determine extension name"))?;
+    let pg_regress = pg_regress_path(pg_config)?;
+    let input_dir = regress_dir(&package_manifest_path);
+    let output_dir =
+        get_target_dir()?.join(format!("pgx-regress-pg{}", pg_config.major_version()?));
+    std::fs::create_dir_all(&output_dir)
+        .wrap_err_with(|| format!("failed to create `{}`", output_dir.display()))?;
+    let diffs = output_dir.join("regression.diffs");
+    if diffs.exists() {
+        std::fs::remove_file(&diffs)?;
+    }
+
+    // the same as `cargo pgx run`, so the server has the newly built library
+    stop_postgres(pg_config)?;
+    install_extension(
+        user_manifest_path,
+        user_package,
+        &package_manifest_path,
+        pg_config,
+        is_release,
+        false,
+        None,
+        &InstallDirs::default(),
+        features,
+        false,
+    )?;
+    start_postgres(pg_config)?;
+
+    println!(
+        "{} {} pg_regress tests for {}",
+        "     Running".bold().green(),
+        tests.len().to_string().bold().cyan(),
+        extname
+    );
+    let mut command = Command::new(&pg_regress);
+    command
+        .env_remove("PGDATABASE")
+        .env_remove("PGHOST")
+        .env_remove("PGPORT")
+        .env_remove("PGUSER")
+        .arg(format!("--bindir={}", pg_config.bin_dir()?.display()))
+        .arg(format!("--inputdir={}", input_dir.display()))
+        .arg(format!("--outputdir={}", output_dir.display()))
+        .arg(format!("--host={}", pg_config.host()))
+        .arg(format!("--port={}", pg_config.port()?))
+        .arg(format!("--dbname={}_regress", extname))
+        .arg(format!("--load-extension={}", extname))
+        .args(tests);
+
+    tracing::debug!(command = ?command, "Running");
+    let status = command
+        .status()
+        .wrap_err_with(|| format!("failed to run `{}`", pg_regress.display()))?;
+    tracing::trace!(status_code = %status, command = ?command, "Finished");
+    if !status.success() {
+        if let Ok(diffs) = std::fs::read_to_string(&diffs) {
+            eprintln!("{}", diffs);
+        }
+        return Err(eyre!(
+            "pg_regress tests failed.  Their output is in `{}`, and the differences from `{}` in `{}`",
+            output_dir.join("results").display(),
+            input_dir.join("expected").display(),
+            diffs.display()
+        ));
+    }
+    Ok(())
+}
+
+fn regress_dir(package_manifest_path: impl AsRef<Path>) -> PathBuf {
+    package_manifest_path
+        .as_ref()
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(REGRESS_DIR)
+}
+
+/// `pg_regress` is installed with the extension build infrastructure, PGXS, which the packages
+/// of some distributions split out into a `-dev` package
+fn pg_regress_path(pg_config: &PgConfig) -> eyre::Result<PathBuf> {
+    let path = pg_config
+        .pkglibdir()?
+        .join("pgxs")
+        .join("src")
+        .join("test")
+        .join("regress")
+        .join(format!("pg_regress{}", std::env::consts::EXE_SUFFIX));
+    if !path.exists() {
+        return Err(eyre!(
+            "`{}` doesn't exist, so the pg_regress tests can't be run.  Install the Postgres development package that has PGXS, or skip them with `--no-regress`",
+            path.display()
+        ));
+    }
+    Ok(path)
+}
@@ -9,7 +9,10 @@ Use of this source code is governed by the MIT license that can be found in the
 
 use crate::{
     command::{
-        get::get_property, install::install_extension, start::start_postgres, stop::stop_postgres,
+        get::get_property,
+        install::{install_extension, InstallDirs},
+        start::start_postgres,
+        stop::stop_postgres,
     },
     CommandExecute,
 };
@@ -20,12 +23,12 @@ use pgx_utils::{
     createdb,
     pg_config::{PgConfig, Pgx},
 };
-use std::{os::unix::process::CommandExt, path::Path, process::Command};
+use std::{path::Path, process::Command};
 /// Compile/install extension to a pgx-managed Postgres instance and start psql
 #[derive(clap::Args, Debug)]
 #[clap(author)]
 pub(crate) struct Run {
-    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`?
+    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`, `pg15`?
     #[clap(env = "PG_VERSION")]
     pg_version: Option<String>,
     /// The database to connect to (and create if the first time).  Defaults to a database with the same name as the current extension name
@@ -131,7 +134,9 @@ pub(crate) fn run_psql(
         is_release,
         false,
         None,
+        &InstallDirs::default(),
         features,
+        false,
     )?;
 
     // restart postgres
@@ -147,10 +152,32 @@ pub(crate) fn run_psql(
     }
 
     // run psql
-    exec_psql(pg_config, dbname)
+    exec_psql(pg_config, dbname, &[])
 }
 
-pub(crate) fn exec_psql(pg_config: &PgConfig, dbname: &str) -> eyre::Result<()> {
+/// Replace this process with psql, passing `psql_args` along after the connection arguments
+pub(crate) fn exec_psql(
+    pg_config: &PgConfig,
+    dbname: &str,
+    psql_args: &[String],
+) -> eyre::Result<()> {
+    let mut command = psql_command(pg_config, dbname)?;
+    command.args(psql_args);
+
+    // we'll never return from here as we've now become psql
+    #[cfg(unix)]
+    panic!("{}", std::os::unix::process::CommandExt::exec(&mut command));
+
+    // Windows can't replace a process with another, so psql runs as a child, and exits with it
+    #[cfg(not(unix))]
+    {
+        let status = command.status()?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
+/// A `psql` invocation connected to `dbname` on the pgx-managed instance described by `pg_config`
+pub(crate) fn psql_command(pg_config: &PgConfig, dbname: &str) -> eyre::Result<Command> {
     let mut command = Command::new(pg_config.psql_path()?);
     command
         .env_remove("PGDATABASE")
@@ -162,7 +189,5 @@ pub(crate) fn exec_psql(pg_config: &PgConfig, dbname: &str) -> eyre::Result<()>
         .arg("-p")
         .arg(pg_config.port()?.to_string())
         .arg(dbname);
-
-    // we'll never return from here as we've now become psql
-    panic!("{}", command.exec());
+    Ok(command)
 }
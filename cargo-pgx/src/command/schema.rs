@@ -19,6 +19,7 @@ use object::Object;
 use owo_colors::OwoColorize;
 use pgx_utils::{
     pg_config::{PgConfig, Pgx},
+    sanitizer::Sanitizer,
     sql_entity_graph::{PgxSql, RustSourceOnlySqlMapping, RustSqlMapping, SqlGraphEntity},
     PgxPgSysStub,
 };
@@ -45,7 +46,7 @@ pub(crate) struct Schema {
     /// Build in test mode (for `cargo pgx test`)
     #[clap(long)]
     test: bool,
-    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`?
+    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`, `pg15`?
     pg_version: Option<String>,
     /// Compile for release mode (default is debug)
     #[clap(env = "PROFILE", long, short)]
@@ -61,6 +62,27 @@ pub(crate) struct Schema {
     /// A path to output a produced GraphViz DOT file
     #[clap(long, short, parse(from_os_str))]
     dot: Option<PathBuf>,
+    /// A path to output the SQL entity graph, and the order it is emitted in, as JSON
+    #[clap(long, parse(from_os_str))]
+    json: Option<PathBuf>,
+    /// The SQL file of an earlier version, to write the `ALTER TYPE ... ADD VALUE` statements
+    /// for enum labels added since then, and the columns, indexes, and rows added to catalog
+    /// tables, to `--upgrade-out`
+    #[clap(long, parse(from_os_str), requires = "upgrade-out")]
+    upgrade_from: Option<PathBuf>,
+    /// A path to output the upgrade SQL for `--upgrade-from`, to be included in the extension's
+    /// upgrade script
+    #[clap(long, parse(from_os_str), requires = "upgrade-from")]
+    upgrade_out: Option<PathBuf>,
+    /// Don't write the SQL to `--out`, but fail if it differs from what's already there, such as
+    /// to check that the SQL committed alongside the extension is up to date
+    #[clap(long, requires = "out")]
+    check: bool,
+    /// Fail unless the extension may be marked `trusted = true` in its control file, which it's
+    /// audited for whenever it is marked so: none of its entities may be in `pg_catalog`, use SQL
+    /// only a superuser should run, or be `#[pg_extern(untrusted)]`
+    #[clap(long)]
+    trusted: bool,
     #[clap(from_global, parse(from_occurrences))]
     verbose: usize,
     /// Skip building a fresh extension shared object.
@@ -105,6 +127,8 @@ impl CommandExecute for Schema {
             },
             Some(config) => {
                 let pg_config = PgConfig::new(PathBuf::from(config));
+                // so the pgx-pg-sys bindings get generated from the same Postgres
+                pg_config.export();
                 let pg_version = format!("pg{}", pg_config.major_version()?);
                 (pg_config, pg_version)
             }
@@ -123,6 +147,11 @@ impl CommandExecute for Schema {
             &features,
             self.out.as_ref(),
             self.dot,
+            self.json,
+            self.upgrade_from,
+            self.upgrade_out,
+            self.check,
+            self.trusted,
             log_level,
             self.skip_build,
         )
@@ -135,6 +164,10 @@ impl CommandExecute for Schema {
     test = is_test,
     path = path.as_ref().map(|path| tracing::field::display(path.as_ref().display())),
     dot,
+    json,
+    upgrade_from = upgrade_from.as_ref().map(|path| tracing::field::display(path.as_ref().display())),
+    check,
+    trusted,
     features = ?features.features,
 ))]
 pub(crate) fn generate_schema(
@@ -147,9 +180,20 @@ pub(crate) fn generate_schema(
     features: &clap_cargo::Features,
     path: Option<impl AsRef<std::path::Path>>,
     dot: Option<impl AsRef<std::path::Path>>,
+    json: Option<impl AsRef<std::path::Path>>,
+    upgrade_from: Option<impl AsRef<std::path::Path>>,
+    upgrade_out: Option<impl AsRef<std::path::Path>>,
+    check: bool,
+    trusted: bool,
     log_level: Option<String>,
     skip_build: bool,
 ) -> eyre::Result<()> {
+    if cfg!(windows) {
+        return Err(eyre!(
+            "Generating the schema loads the extension's library outside of Postgres, which isn't possible on Windows.  Run `cargo pgx schema` on Linux or macOS, and put the SQL it writes in `sql/`"
+        ));
+    }
+
     let manifest = Manifest::from_path(&package_manifest_path)?;
     let (control_file, _extname) = find_control_file(&package_manifest_path)?;
     let package_name = &manifest
@@ -167,7 +211,16 @@ pub(crate) fn generate_schema(
 
     let flags = std::env::var("PGX_BUILD_FLAGS").unwrap_or_default();
 
+    // the shared library `cargo pgx test --sanitizer` installs is instrumented, so it can't be
+    // loaded here, and an uninstrumented one is built to generate the SQL from instead
+    let sanitizer = Sanitizer::from_env()?;
+    let skip_build = skip_build && sanitizer.is_none();
+
     let mut target_dir_with_profile = pgx_utils::get_target_dir()?;
+    // cargo puts what's built for a `--target` from `PGX_BUILD_FLAGS` in a directory of its own
+    if let Some(target) = pgx_utils::build_target() {
+        target_dir_with_profile.push(target);
+    }
     target_dir_with_profile.push(if is_release { "release" } else { "debug" });
 
     // First, build the SQL generator so we can get a look at the symbol table
@@ -200,6 +253,20 @@ pub(crate) fn generate_schema(
             command.env("RUST_LOG", log_level);
         }
 
+        // the same as `cargo pgx install`'s, so the library isn't built again for it
+        let manifest_dir = package_manifest_path
+            .as_ref()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        command.envs(pgx_utils::build_info_env(&manifest_dir));
+
+        if let Some(sanitizer) = sanitizer {
+            command.env("RUSTFLAGS", sanitizer.uninstrumented_rustflags());
+        } else if let Some(rustflags) = pgx_utils::musl_rustflags() {
+            command.env("RUSTFLAGS", rustflags);
+        }
+
         let features_arg = features.features.join(" ");
         if !features_arg.trim().is_empty() {
             command.arg("--features");
@@ -264,16 +331,11 @@ pub(crate) fn generate_schema(
     // Inspect the symbol table for a list of `__pgx_internals` we should have the generator call
     let mut lib_so = target_dir_with_profile.clone();
 
-    let so_extension = if cfg!(target_os = "macos") {
-        ".dylib"
-    } else {
-        ".so"
-    };
-
     lib_so.push(&format!(
-        "lib{}{}",
+        "{}{}{}",
+        std::env::consts::DLL_PREFIX,
         package_name.replace("-", "_"),
-        so_extension
+        std::env::consts::DLL_SUFFIX
     ));
 
     let lib_so_data = std::fs::read(&lib_so).wrap_err("couldn't read extension shared object")?;
@@ -306,10 +368,12 @@ pub(crate) fn generate_schema(
     let mut num_funcs = 0_usize;
     let mut num_types = 0_usize;
     let mut num_enums = 0_usize;
+    let mut num_domains = 0_usize;
     let mut num_sqls = 0_usize;
     let mut num_ords = 0_usize;
     let mut num_hashes = 0_usize;
     let mut num_aggregates = 0_usize;
+    let mut num_customs = 0_usize;
     for func in &fns_to_call {
         if func.starts_with("__pgx_internals_schema_") {
             let schema = func
@@ -324,6 +388,8 @@ pub(crate) fn generate_schema(
             num_types += 1;
         } else if func.starts_with("__pgx_internals_enum_") {
             num_enums += 1;
+        } else if func.starts_with("__pgx_internals_domain_") {
+            num_domains += 1;
         } else if func.starts_with("__pgx_internals_sql_") {
             num_sqls += 1;
         } else if func.starts_with("__pgx_internals_ord_") {
@@ -332,11 +398,13 @@ pub(crate) fn generate_schema(
             num_hashes += 1;
         } else if func.starts_with("__pgx_internals_aggregate_") {
             num_aggregates += 1;
+        } else if func.starts_with("__pgx_internals_custom_") {
+            num_customs += 1;
         }
     }
 
     eprintln!(
-        "{} {} SQL entities: {} schemas ({} unique), {} functions, {} types, {} enums, {} sqls, {} ords, {} hashes, {} aggregates",
+        "{} {} SQL entities: {} schemas ({} unique), {} functions, {} types, {} enums, {} domains, {} sqls, {} ords, {} hashes, {} aggregates, {} custom entities",
         "  Discovered".bold().green(),
         fns_to_call.len().to_string().bold().cyan(),
         seen_schemas.iter().count().to_string().bold().cyan(),
@@ -344,13 +412,175 @@ pub(crate) fn generate_schema(
         num_funcs.to_string().bold().cyan(),
         num_types.to_string().bold().cyan(),
         num_enums.to_string().bold().cyan(),
+        num_domains.to_string().bold().cyan(),
         num_sqls.to_string().bold().cyan(),
         num_ords.to_string().bold().cyan(),
         num_hashes.to_string().bold().cyan(),
         num_aggregates.to_string().bold().cyan(),
+        num_customs.to_string().bold().cyan(),
     );
 
     tracing::debug!("Collecting {} SQL entities", fns_to_call.len());
+    let (typeid_sql_mapping, source_only_sql_mapping, entities) =
+        load_entities(&postmaster_stub_built, &lib_so, fns_to_call);
+
+    let pgx_sql = PgxSql::build(
+        typeid_sql_mapping.clone().into_iter(),
+        source_only_sql_mapping.clone().into_iter(),
+        entities.into_iter(),
+    )
+    .wrap_err("SQL generation error")?;
+
+    if trusted || get_property(&package_manifest_path, "trusted")? == Some("true".into()) {
+        audit_trusted(&pgx_sql, pg_config, &control_file)?;
+    }
+
+    if check {
+        let out_path = path
+            .as_ref()
+            .ok_or_else(|| eyre!("`--check` needs the `--out` to check"))?
+            .as_ref();
+
+        eprintln!(
+            "{} SQL entities against {}",
+            "    Checking".bold().green(),
+            format_display_path(out_path)?.cyan()
+        );
+        let generated = pgx_sql.to_sql().wrap_err("SQL generation error")?;
+        let existing = std::fs::read_to_string(out_path)
+            .wrap_err_with(|| eyre!("Could not read {}", out_path.display()))?;
+        if let Some(line) = first_difference(&existing, &generated) {
+            return Err(eyre!(
+                "{} differs from the generated SQL, starting at line {}.  Run `cargo pgx schema` without `--check` to regenerate it.",
+                out_path.display(),
+                line
+            ));
+        }
+    } else if let Some(out_path) = path {
+        let out_path = out_path.as_ref();
+
+        eprintln!(
+            "{} SQL entities to {}",
+            "     Writing".bold().green(),
+            format_display_path(out_path)?.cyan()
+        );
+
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent).wrap_err("Could not create parent directory")?
+        }
+        pgx_sql
+            .to_file(out_path)
+            .wrap_err_with(|| eyre!("Could not write SQL to {}", out_path.display()))?;
+    } else {
+        eprintln!(
+            "{} SQL entities to {}",
+            "     Writing".bold().green(),
+            "/dev/stdout".cyan(),
+        );
+        pgx_sql
+            .write(&mut std::io::stdout())
+            .wrap_err_with(|| eyre!("Could not write SQL to stdout"))?;
+    }
+
+    if let Some(dot_path) = dot {
+        let dot_path = dot_path.as_ref();
+        tracing::info!(dot = %dot_path.display(), "Writing Graphviz DOT");
+        pgx_sql.to_dot(dot_path)?;
+    }
+    if let Some(json_path) = json {
+        let json_path = json_path.as_ref();
+        tracing::info!(json = %json_path.display(), "Writing JSON entity graph");
+        pgx_sql.to_json(json_path)?;
+    }
+    if let (Some(upgrade_from), Some(upgrade_out)) = (upgrade_from, upgrade_out) {
+        let (upgrade_from, upgrade_out) = (upgrade_from.as_ref(), upgrade_out.as_ref());
+        let previous = std::fs::read_to_string(upgrade_from)
+            .wrap_err_with(|| eyre!("Could not read {}", upgrade_from.display()))?;
+        let mut upgrade_sql = pgx_sql
+            .enum_upgrade_sql(&previous)
+            .wrap_err("Enum upgrade SQL generation error")?;
+        upgrade_sql.push_str(
+            &pgx_sql
+                .catalog_table_upgrade_sql(&previous)
+                .wrap_err("Catalog table upgrade SQL generation error")?,
+        );
+
+        eprintln!(
+            "{} upgrade SQL to {}",
+            "     Writing".bold().green(),
+            format_display_path(upgrade_out)?.cyan()
+        );
+        if let Some(parent) = upgrade_out.parent() {
+            std::fs::create_dir_all(parent).wrap_err("Could not create parent directory")?
+        }
+        std::fs::write(upgrade_out, upgrade_sql)
+            .wrap_err_with(|| eyre!("Could not write SQL to {}", upgrade_out.display()))?;
+    }
+    Ok(())
+}
+
+/// Fail unless the extension `pgx_sql` generates the SQL of may be a trusted one, listing the
+/// entities which keep it from being one
+fn audit_trusted(pgx_sql: &PgxSql, pg_config: &PgConfig, control_file: &Path) -> eyre::Result<()> {
+    let major_version = pg_config.major_version()?;
+    if major_version < 13 {
+        return Err(eyre!(
+            "Postgres {} doesn't have trusted extensions, which need Postgres 13 or later",
+            major_version
+        ));
+    }
+
+    eprintln!(
+        "{} SQL entities for a trusted extension",
+        "    Auditing".bold().green()
+    );
+    let findings = pgx_sql
+        .trusted_audit()
+        .wrap_err("Trusted extension audit error")?;
+    if !findings.is_empty() {
+        let findings = findings
+            .iter()
+            .map(|finding| format!("  * {}", finding))
+            .collect::<Vec<_>>();
+        return Err(eyre!(
+            "{} can't be a trusted extension:\n{}",
+            control_file.display(),
+            findings.join("\n")
+        ));
+    }
+    Ok(())
+}
+
+/// The number of the first line `existing` and `generated` differ at, if they differ
+fn first_difference(existing: &str, generated: &str) -> Option<usize> {
+    if existing == generated {
+        return None;
+    }
+    let mut existing_lines = existing.lines();
+    let mut generated_lines = generated.lines();
+    let mut line = 1;
+    loop {
+        match (existing_lines.next(), generated_lines.next()) {
+            (Some(left), Some(right)) if left == right => line += 1,
+            // they differ only in their trailing newlines
+            (None, None) => return Some(line - 1),
+            _ => return Some(line),
+        }
+    }
+}
+
+/// Load the extension's library, on top of the stub of the postmaster it calls into, and call the
+/// `__pgx_internals` functions in `fns_to_call` for what they describe
+#[cfg(unix)]
+fn load_entities(
+    postmaster_stub_built: &Path,
+    lib_so: &Path,
+    fns_to_call: HashSet<String>,
+) -> (
+    &'static HashSet<RustSqlMapping>,
+    &'static HashSet<RustSourceOnlySqlMapping>,
+    Vec<SqlGraphEntity>,
+) {
     let mut entities = Vec::default();
     let typeid_sql_mapping;
     let source_only_sql_mapping;
@@ -397,45 +627,20 @@ pub(crate) fn generate_schema(
         }
     };
 
-    let pgx_sql = PgxSql::build(
-        typeid_sql_mapping.clone().into_iter(),
-        source_only_sql_mapping.clone().into_iter(),
-        entities.into_iter(),
-    )
-    .wrap_err("SQL generation error")?;
-
-    if let Some(out_path) = path {
-        let out_path = out_path.as_ref();
-
-        eprintln!(
-            "{} SQL entities to {}",
-            "     Writing".bold().green(),
-            format_display_path(out_path)?.cyan()
-        );
-
-        if let Some(parent) = out_path.parent() {
-            std::fs::create_dir_all(parent).wrap_err("Could not create parent directory")?
-        }
-        pgx_sql
-            .to_file(out_path)
-            .wrap_err_with(|| eyre!("Could not write SQL to {}", out_path.display()))?;
-    } else {
-        eprintln!(
-            "{} SQL entities to {}",
-            "     Writing".bold().green(),
-            "/dev/stdout".cyan(),
-        );
-        pgx_sql
-            .write(&mut std::io::stdout())
-            .wrap_err_with(|| eyre!("Could not write SQL to stdout"))?;
-    }
+    (typeid_sql_mapping, source_only_sql_mapping, entities)
+}
 
-    if let Some(dot_path) = dot {
-        let dot_path = dot_path.as_ref();
-        tracing::info!(dot = %dot_path.display(), "Writing Graphviz DOT");
-        pgx_sql.to_dot(dot_path)?;
-    }
-    Ok(())
+#[cfg(not(unix))]
+fn load_entities(
+    _postmaster_stub_built: &Path,
+    _lib_so: &Path,
+    _fns_to_call: HashSet<String>,
+) -> (
+    &'static HashSet<RustSqlMapping>,
+    &'static HashSet<RustSourceOnlySqlMapping>,
+    Vec<SqlGraphEntity>,
+) {
+    unreachable!("`generate_schema()` returns early on Windows")
 }
 
 #[tracing::instrument(level = "error", skip_all, fields(
@@ -503,10 +708,12 @@ fn create_stub(
     let mut so_rustc_invocation = Command::new("rustc");
     so_rustc_invocation.stderr(Stdio::inherit());
 
-    if let Some(rustc_flags_str) = std::env::var("RUSTFLAGS").ok() {
-        let rustc_flags = rustc_flags_str
-            .split(" ")
-            .collect::<Vec<_>>();
+    let rustflags = match Sanitizer::from_env()? {
+        Some(sanitizer) => Some(sanitizer.uninstrumented_rustflags()).filter(|f| !f.is_empty()),
+        None => pgx_utils::musl_rustflags().or_else(|| std::env::var("RUSTFLAGS").ok()),
+    };
+    if let Some(rustc_flags_str) = rustflags {
+        let rustc_flags = rustc_flags_str.split(" ").collect::<Vec<_>>();
         so_rustc_invocation.args(rustc_flags);
     }
 
@@ -14,13 +14,13 @@ use cargo_toml::Manifest;
 use eyre::{eyre, WrapErr};
 use owo_colors::OwoColorize;
 use pgx_utils::pg_config::{PgConfig, PgConfigSelector, Pgx};
-use std::{os::unix::process::CommandExt, path::PathBuf, process::Stdio};
+use std::{path::PathBuf, process::Stdio};
 
 /// Start a pgx-managed Postgres instance
 #[derive(clap::Args, Debug)]
 #[clap(author)]
 pub(crate) struct Start {
-    /// The Postgres version to start (`pg10`, `pg11`, `pg12`, `pg13`, `pg14`, or `all`)
+    /// The Postgres version to start (`pg10`, `pg11`, `pg12`, `pg13`, `pg14`, `pg15`, or `all`)
     #[clap(env = "PG_VERSION")]
     pg_version: Option<String>,
     #[clap(from_global, parse(from_occurrences))]
@@ -81,36 +81,44 @@ pub(crate) fn start_postgres(pg_config: &PgConfig) -> eyre::Result<()> {
         return Ok(());
     }
 
-    println!(
+    eprintln!(
         "{} Postgres v{} on port {}",
         "    Starting".bold().green(),
         pg_config.major_version()?,
         port.to_string().bold().cyan()
     );
-    let mut command = std::process::Command::new(format!("{}/pg_ctl", bindir.display()));
+    // Windows doesn't have the unix sockets Postgres uses elsewhere, so it's only reached over TCP
+    let options = if cfg!(windows) {
+        format!("-o -i -p {}", port)
+    } else {
+        format!(
+            "-o -i -p {} -c unix_socket_directories={}",
+            port,
+            Pgx::home()?.display()
+        )
+    };
+    let mut command = std::process::Command::new(pg_config.pg_ctl_path()?);
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .arg("start")
+        .arg(options)
+        .arg("-D")
+        .arg(&datadir)
+        .arg("-l")
+        .arg(&logfile);
+
     // Unsafe block is for the pre_exec setsid call below
     //
     // This is to work around a bug in PG10 + PG11 which don't call setsid in pg_ctl
     // This means that when cargo pgx run dumps a user into psql, pushing ctrl-c will abort
-    // the postgres server started by pgx
+    // the postgres server started by pgx.  On Windows, pg_ctl already starts Postgres detached
+    #[cfg(unix)]
     unsafe {
-        command
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .arg("start")
-            .arg(format!(
-                "-o -i -p {} -c unix_socket_directories={}",
-                port,
-                Pgx::home()?.display()
-            ))
-            .arg("-D")
-            .arg(&datadir)
-            .arg("-l")
-            .arg(&logfile)
-            .pre_exec(|| {
-                fork::setsid().expect("setsid call failed for pg_ctl");
-                Ok(())
-            });
+        std::os::unix::process::CommandExt::pre_exec(&mut command, || {
+            fork::setsid().expect("setsid call failed for pg_ctl");
+            Ok(())
+        });
     }
 
     let command_str = format!("{:?}", command);
@@ -65,7 +65,6 @@ impl CommandExecute for Status {
 #[tracing::instrument(level = "error", skip_all, fields(pg_version = %pg_config.version()?))]
 pub(crate) fn status_postgres(pg_config: &PgConfig) -> eyre::Result<bool> {
     let datadir = pg_config.data_dir()?;
-    let bindir = pg_config.bin_dir()?;
 
     if !datadir.exists() {
         // Postgres couldn't possibly be running if there's no data directory
@@ -73,7 +72,7 @@ pub(crate) fn status_postgres(pg_config: &PgConfig) -> eyre::Result<bool> {
         return Ok(false);
     }
 
-    let mut command = std::process::Command::new(format!("{}/pg_ctl", bindir.display()));
+    let mut command = std::process::Command::new(pg_config.pg_ctl_path()?);
     command
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -18,7 +18,7 @@ use std::{path::PathBuf, process::Stdio};
 #[derive(clap::Args, Debug)]
 #[clap(author)]
 pub(crate) struct Stop {
-    /// The Postgres version to stop (`pg10`, `pg11`, `pg12`, `pg13`, `pg14`, or `all`)
+    /// The Postgres version to stop (`pg10`, `pg11`, `pg12`, `pg13`, `pg14`, `pg15`, or `all`)
     #[clap(env = "PG_VERSION")]
     pg_version: Option<String>,
     #[clap(from_global, parse(from_occurrences))]
@@ -67,7 +67,6 @@ impl CommandExecute for Stop {
 pub(crate) fn stop_postgres(pg_config: &PgConfig) -> eyre::Result<()> {
     Pgx::home()?;
     let datadir = pg_config.data_dir()?;
-    let bindir = pg_config.bin_dir()?;
 
     if status_postgres(pg_config)? == false {
         // it's not running, no need to stop it
@@ -81,7 +80,7 @@ pub(crate) fn stop_postgres(pg_config: &PgConfig) -> eyre::Result<()> {
         pg_config.major_version()?
     );
 
-    let mut command = std::process::Command::new(format!("{}/pg_ctl", bindir.display()));
+    let mut command = std::process::Command::new(pg_config.pg_ctl_path()?);
     command
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -11,24 +11,31 @@ use cargo_toml::Manifest;
 use eyre::{eyre, WrapErr};
 use pgx_utils::{
     get_target_dir,
-    pg_config::{PgConfig, PgConfigSelector, Pgx},
+    pg_config::{PgConfig, PgConfigSelector, Pgx, ProjectConfig},
+    sanitizer::{Sanitizer, SANITIZER_ENV},
 };
 use std::{
     path::{Path, PathBuf},
     process::{Command, Stdio},
 };
 
-use crate::CommandExecute;
+use crate::{
+    command::regress::{find_regression_tests, run_regression_tests},
+    CommandExecute,
+};
 
 /// Run the test suite for this crate
 #[derive(clap::Args, Debug)]
 #[clap(author)]
 pub(crate) struct Test {
-    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`, or `all`?
+    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`, `pg15`, or `all`?
     #[clap(env = "PG_VERSION")]
     pg_version: Option<String>,
     /// If specified, only run tests containing this string in their names
     testname: Option<String>,
+    /// Arguments for the test binaries, such as `--exact`
+    #[clap(last = true)]
+    test_args: Vec<String>,
     /// Package to build (see `cargo help pkgid`)
     #[clap(long, short)]
     package: Option<String>,
@@ -41,6 +48,24 @@ pub(crate) struct Test {
     /// Don't regenerate the schema
     #[clap(long, short)]
     no_schema: bool,
+    /// Test against this `pg_config` instead of the ones `cargo pgx init` set up, such as one from a Postgres fork
+    #[clap(long, short = 'c', parse(from_os_str))]
+    pg_config: Option<PathBuf>,
+    /// Build the extension and the tests with this sanitizer, `address`, and preload its runtime into the test Postgres.  Needs a nightly toolchain
+    #[clap(long)]
+    sanitizer: Option<Sanitizer>,
+    /// Seed the `#[pg_test]`s with this rather than a new seed, to reproduce a failure
+    #[clap(long, env = "PGX_TEST_SEED")]
+    seed: Option<u32>,
+    /// If a `#[pg_test]` fails, leave the test Postgres running with its data directory, to look into the failure
+    #[clap(long)]
+    keep_on_failure: bool,
+    /// Leave the test Postgres running after the tests, for the next `--reuse-server` run to use rather than starting another
+    #[clap(long)]
+    reuse_server: bool,
+    /// Don't run the `pg_regress` tests in `pg_regress/sql/` after the `#[pg_test]`s
+    #[clap(long)]
+    no_regress: bool,
     #[clap(flatten)]
     features: clap_cargo::Features,
     #[clap(from_global, parse(from_occurrences))]
@@ -50,9 +75,23 @@ pub(crate) struct Test {
 impl CommandExecute for Test {
     #[tracing::instrument(level = "error", skip(self))]
     fn execute(self) -> eyre::Result<()> {
+        let pg_config_override = self.pg_config.clone().map(PgConfig::new);
+        if let Some(pg_config) = &pg_config_override {
+            pg_config.export();
+        }
         let pgx = Pgx::from_config()?;
 
-        let metadata = crate::metadata::metadata(&self.features, self.manifest_path.as_ref())
+        // the project's `pgx.toml` can turn the flags on, and add features, but not take them away
+        let project = ProjectConfig::load()?;
+        let mut features = self.features.clone();
+        features
+            .features
+            .extend(project.test.features.iter().cloned());
+        let no_schema = self.no_schema || project.test.no_schema;
+        let keep_on_failure = self.keep_on_failure || project.test.keep_on_failure;
+        let reuse_server = self.reuse_server || project.test.reuse_server;
+
+        let metadata = crate::metadata::metadata(&features, self.manifest_path.as_ref())
             .wrap_err("couldn't get cargo metadata")?;
         crate::metadata::validate(&metadata)?;
         let package_manifest_path =
@@ -61,9 +100,16 @@ impl CommandExecute for Test {
         let package_manifest =
             Manifest::from_path(&package_manifest_path).wrap_err("Couldn't parse manifest")?;
 
+        // an explicit `pg_config` decides the version, rather than the manifest
+        let default_pg_version = match &pg_config_override {
+            Some(pg_config) => Some(pg_config.label()?),
+            None => crate::manifest::default_pg_version(&package_manifest),
+        };
+
         let pg_version = match self.pg_version {
             Some(ref s) => s.clone(),
-            None => crate::manifest::default_pg_version(&package_manifest)
+            None => default_pg_version
+                .clone()
                 .ok_or(eyre!("No provided `pg$VERSION` flag."))?,
         };
 
@@ -78,7 +124,8 @@ impl CommandExecute for Test {
                     );
                     testname = Some(pg_version.clone());
                     pgx.get(
-                        &crate::manifest::default_pg_version(&package_manifest)
+                        default_pg_version
+                            .as_ref()
                             .ok_or(eyre!("No provided `pg$VERSION` flag."))?,
                     )?
                 }
@@ -87,7 +134,7 @@ impl CommandExecute for Test {
             let pg_version = format!("pg{}", pg_config.major_version()?);
 
             let features = crate::manifest::features_for_version(
-                self.features.clone(),
+                features.clone(),
                 &package_manifest,
                 &pg_version,
             );
@@ -97,20 +144,42 @@ impl CommandExecute for Test {
                 self.manifest_path.as_ref(),
                 self.package.as_ref(),
                 self.release,
-                self.no_schema,
+                no_schema,
                 &features,
                 testname.clone(),
-            )?
+                &self.test_args,
+                self.sanitizer,
+                self.seed,
+                keep_on_failure,
+                reuse_server,
+            )?;
+
+            if !self.no_regress {
+                let tests = find_regression_tests(&package_manifest_path, testname.as_deref())?;
+                if !tests.is_empty() {
+                    run_regression_tests(
+                        pg_config,
+                        self.manifest_path.as_ref(),
+                        self.package.as_ref(),
+                        &package_manifest_path,
+                        self.release,
+                        &features,
+                        &tests,
+                    )?;
+                }
+            }
         }
 
         Ok(())
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(skip_all, fields(
     pg_version = %pg_config.version()?,
     testname =  tracing::field::Empty,
     release = is_release,
+    sanitizer = ?sanitizer,
 ))]
 pub fn test_extension(
     pg_config: &PgConfig,
@@ -120,6 +189,11 @@ pub fn test_extension(
     no_schema: bool,
     features: &clap_cargo::Features,
     testname: Option<impl AsRef<str>>,
+    test_args: &[String],
+    sanitizer: Option<Sanitizer>,
+    seed: Option<u32>,
+    keep_on_failure: bool,
+    reuse_server: bool,
 ) -> eyre::Result<()> {
     if let Some(ref testname) = testname {
         tracing::Span::current().record("testname", &tracing::field::display(&testname.as_ref()));
@@ -160,7 +234,27 @@ pub fn test_extension(
             "PGX_BUILD_PROFILE",
             if is_release { "release" } else { "debug" },
         )
-        .env("PGX_NO_SCHEMA", if no_schema { "true" } else { "false" });
+        .env("PGX_NO_SCHEMA", if no_schema { "true" } else { "false" })
+        .env(
+            "PGX_TEST_KEEP_ON_FAILURE",
+            if keep_on_failure { "true" } else { "false" },
+        )
+        .env(
+            "PGX_TEST_REUSE_SERVER",
+            if reuse_server { "true" } else { "false" },
+        );
+
+    if let Some(seed) = seed {
+        command.env("PGX_TEST_SEED", seed.to_string());
+    }
+
+    // set here, so the installs the tests make record the same build as the tests themselves
+    let manifest_dir = user_manifest_path
+        .as_ref()
+        .and_then(|path| path.as_ref().parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    command.envs(pgx_utils::build_info_env(&manifest_dir));
 
     if let Ok(rust_log) = std::env::var("RUST_LOG") {
         command.env("RUST_LOG", rust_log);
@@ -193,10 +287,26 @@ pub fn test_extension(
         command.arg(user_package);
     }
 
+    if let Some(sanitizer) = sanitizer {
+        // only the crates for the `--target` are instrumented, not build scripts or proc macros
+        command
+            .arg("--target")
+            .arg(sanitizer.host_target()?)
+            .env(SANITIZER_ENV, sanitizer.name())
+            .env("RUSTFLAGS", sanitizer.rustflags());
+    } else if let Some(rustflags) = pgx_utils::musl_rustflags() {
+        command.env("RUSTFLAGS", rustflags);
+    }
+
     if let Some(testname) = testname {
         command.arg(testname.as_ref());
     }
 
+    if !test_args.is_empty() {
+        command.arg("--");
+        command.args(test_args);
+    }
+
     eprintln!("{:?}", command);
 
     tracing::debug!(command = ?command, "Running");
@@ -0,0 +1,321 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{
+    command::{
+        get::get_property,
+        install::{
+            build_extension_command, check_relocatable, install_built_extension,
+            parse_build_messages, InstallDirs,
+        },
+        run::psql_command,
+        start::start_postgres,
+        stop::stop_postgres,
+    },
+    CommandExecute,
+};
+use cargo_toml::Manifest;
+use eyre::{eyre, WrapErr};
+use owo_colors::OwoColorize;
+use pgx_utils::{
+    createdb,
+    pg_config::{PgConfig, Pgx},
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, SystemTime},
+};
+
+/// Rebuild and reinstall the extension into a pgx-managed Postgres instance whenever its sources change
+#[derive(clap::Args, Debug)]
+#[clap(author)]
+pub(crate) struct Watch {
+    /// Do you want to run against Postgres `pg10`, `pg11`, `pg12`, `pg13`, `pg14`, `pg15`?
+    #[clap(env = "PG_VERSION")]
+    pg_version: Option<String>,
+    /// The database to use for `--recreate` and `--smoke` (and create if the first time).  Defaults to a database with the same name as the current extension name
+    dbname: Option<String>,
+    /// Package to build (see `cargo help pkgid`)
+    #[clap(long, short)]
+    package: Option<String>,
+    /// Path to Cargo.toml
+    #[clap(long)]
+    manifest_path: Option<String>,
+    /// Compile for release mode (default is debug)
+    #[clap(env = "PROFILE", long, short)]
+    release: bool,
+    /// `DROP EXTENSION ... CASCADE` and `CREATE EXTENSION` in the database after each install
+    #[clap(long)]
+    recreate: bool,
+    /// A SQL file to run, via psql, against the database after each install
+    #[clap(long, short, parse(from_os_str))]
+    smoke: Option<PathBuf>,
+    /// How often, in milliseconds, to check the source tree for changes
+    #[clap(long, default_value = "500")]
+    interval: u64,
+    #[clap(flatten)]
+    features: clap_cargo::Features,
+    #[clap(from_global, parse(from_occurrences))]
+    verbose: usize,
+}
+
+impl CommandExecute for Watch {
+    #[tracing::instrument(level = "error", skip(self))]
+    fn execute(mut self) -> eyre::Result<()> {
+        let metadata = crate::metadata::metadata(&self.features, self.manifest_path.as_ref())
+            .wrap_err("couldn't get cargo metadata")?;
+        crate::metadata::validate(&metadata)?;
+        let package_manifest_path =
+            crate::manifest::manifest_path(&metadata, self.package.as_ref())
+                .wrap_err("Couldn't get manifest path")?;
+        let package_manifest =
+            Manifest::from_path(&package_manifest_path).wrap_err("Couldn't parse manifest")?;
+
+        let pgx = Pgx::from_config()?;
+
+        let (pg_config, pg_version) = match self.pg_version {
+            Some(pg_version) => {
+                match pgx.get(&pg_version) {
+                    Ok(pg_config) => (pg_config, pg_version),
+                    Err(err) => {
+                        if self.dbname.is_some() {
+                            return Err(err);
+                        }
+                        // It's actually the dbname! We should infer from the manifest.
+                        self.dbname = Some(pg_version);
+                        let default_pg_version =
+                            crate::manifest::default_pg_version(&package_manifest)
+                                .ok_or(eyre!("No provided `pg$VERSION` flag."))?;
+                        (pgx.get(&default_pg_version)?, default_pg_version)
+                    }
+                }
+            }
+            None => {
+                // We should infer from the manifest.
+                let default_pg_version = crate::manifest::default_pg_version(&package_manifest)
+                    .ok_or(eyre!("No provided `pg$VERSION` flag."))?;
+                (pgx.get(&default_pg_version)?, default_pg_version)
+            }
+        };
+
+        let features =
+            crate::manifest::features_for_version(self.features, &package_manifest, &pg_version);
+
+        let extname = get_property(&package_manifest_path, "extname")?
+            .ok_or(eyre!("could not determine extension name"))?;
+        let dbname = self.dbname.unwrap_or_else(|| extname.clone());
+
+        let crate_dir = package_manifest_path
+            .parent()
+            .ok_or(eyre!("could not determine crate directory"))?
+            .to_path_buf();
+        let interval = Duration::from_millis(self.interval);
+
+        check_relocatable(&package_manifest_path)?;
+
+        let mut last_snapshot = HashMap::new();
+        loop {
+            if snapshot_sources(&crate_dir)? != last_snapshot {
+                watch_iteration(
+                    pg_config,
+                    self.manifest_path.as_ref(),
+                    self.package.as_ref(),
+                    &package_manifest_path,
+                    &extname,
+                    &dbname,
+                    self.release,
+                    &features,
+                    self.recreate,
+                    self.smoke.as_ref(),
+                )?;
+
+                println!(
+                    "{} for changes in {}",
+                    "    Watching".bold().cyan(),
+                    crate_dir.display()
+                );
+
+                // Snapshot after the iteration so files written while building (like a freshly
+                // generated `sql/` script) don't immediately trigger another rebuild
+                last_snapshot = snapshot_sources(&crate_dir)?;
+            }
+            std::thread::sleep(interval);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(level = "error", skip_all, fields(
+    pg_version = %pg_config.version()?,
+    dbname,
+    release = is_release,
+))]
+fn watch_iteration(
+    pg_config: &PgConfig,
+    user_manifest_path: Option<impl AsRef<Path>>,
+    user_package: Option<&String>,
+    package_manifest_path: impl AsRef<Path>,
+    extname: &str,
+    dbname: &str,
+    is_release: bool,
+    features: &clap_cargo::Features,
+    recreate: bool,
+    smoke: Option<&PathBuf>,
+) -> eyre::Result<()> {
+    // Build up front so that a compilation error leaves the running instance alone and
+    // doesn't end the watch.  The install then uses this build's artifact
+    let mut command = build_extension_command(
+        user_manifest_path.as_ref(),
+        user_package,
+        is_release,
+        features,
+    )?;
+    command.stdout(Stdio::piped()).stderr(Stdio::inherit());
+    let command_str = format!("{:?}", command);
+    tracing::debug!(command = %command_str, "Running");
+    let output = command
+        .output()
+        .wrap_err_with(|| format!("failed to spawn cargo: {}", command_str))?;
+    if !output.status.success() {
+        println!(
+            "{} build failed, waiting for further changes",
+            "       Error".bold().red()
+        );
+        return Ok(());
+    }
+    let build_command_messages = parse_build_messages(&output.stdout)?;
+
+    // Postgres is only down while the library is replaced, and is started again even if that
+    // fails, which doesn't end the watch either
+    stop_postgres(pg_config)?;
+    let installed = install_built_extension(
+        user_manifest_path,
+        user_package,
+        package_manifest_path,
+        pg_config,
+        is_release,
+        false,
+        None,
+        &InstallDirs::default(),
+        features,
+        false,
+        &build_command_messages,
+    );
+    start_postgres(pg_config)?;
+    if let Err(e) = installed {
+        println!(
+            "{} install failed, waiting for further changes: {:?}",
+            "       Error".bold().red(),
+            e
+        );
+        return Ok(());
+    }
+
+    if !recreate && smoke.is_none() {
+        return Ok(());
+    }
+
+    if !createdb(pg_config, dbname, false, true)? {
+        println!(
+            "{} existing database {}",
+            "    Re-using".bold().cyan(),
+            dbname
+        );
+    }
+
+    if recreate {
+        println!(
+            "{} extension {} in database {}",
+            "  Recreating".bold().green(),
+            extname,
+            dbname
+        );
+        let mut command = psql_command(pg_config, dbname)?;
+        command
+            .arg("-X")
+            .arg("-v")
+            .arg("ON_ERROR_STOP=1")
+            .arg("-c")
+            .arg(format!(
+                "DROP EXTENSION IF EXISTS \"{extname}\" CASCADE; CREATE EXTENSION \"{extname}\";",
+                extname = extname.replace('"', "\"\"")
+            ));
+        run_psql_script(command)?;
+    }
+
+    if let Some(smoke) = smoke {
+        println!(
+            "{} {} in database {}",
+            "     Running".bold().green(),
+            smoke.display(),
+            dbname
+        );
+        let mut command = psql_command(pg_config, dbname)?;
+        command
+            .arg("-X")
+            .arg("-v")
+            .arg("ON_ERROR_STOP=1")
+            .arg("-f")
+            .arg(smoke);
+        run_psql_script(command)?;
+    }
+
+    Ok(())
+}
+
+/// Run a non-interactive psql.  A failing script is reported, but doesn't end the watch
+fn run_psql_script(mut command: std::process::Command) -> eyre::Result<()> {
+    let command_str = format!("{:?}", command);
+    tracing::debug!(command = %command_str, "Running");
+    let status = command
+        .status()
+        .wrap_err_with(|| format!("failed to spawn psql: {}", command_str))?;
+    tracing::trace!(status_code = %status, command = %command_str, "Finished");
+    if !status.success() {
+        println!(
+            "{} psql exited with {}",
+            "       Error".bold().red(),
+            status
+        );
+    }
+    Ok(())
+}
+
+/// Modification times of every file that can affect the built extension: everything under the
+/// crate directory except build output and VCS metadata
+fn snapshot_sources(crate_dir: &Path) -> eyre::Result<HashMap<PathBuf, SystemTime>> {
+    let mut snapshot = HashMap::new();
+    let mut pending = vec![crate_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .wrap_err_with(|| format!("cannot read directory `{}`", dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if file_name == "target" || file_name.starts_with('.') {
+                    continue;
+                }
+                pending.push(path);
+            } else if file_type.is_file() {
+                if file_name.starts_with('.') || file_name.ends_with('~') {
+                    // editor swap and backup files
+                    continue;
+                }
+                snapshot.insert(path, entry.metadata()?.modified()?);
+            }
+        }
+    }
+    Ok(snapshot)
+}
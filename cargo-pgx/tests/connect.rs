@@ -0,0 +1,75 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! `cargo pgx connect`, against the oldest Postgres `cargo pgx init` has set up
+
+use pgx_utils::pg_config::{PgConfigSelector, Pgx};
+use std::process::{Command, Output};
+
+const DBNAME: &str = "pgx_connect_test";
+
+fn cargo_pgx(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_cargo-pgx"))
+        .arg("pgx")
+        .args(args)
+        .output()
+        .expect("couldn't run cargo-pgx");
+    assert!(
+        output.status.success(),
+        "`cargo pgx {}` failed:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn connect_scripted_writes_only_psql_output() {
+    let pgx = match Pgx::from_config() {
+        Ok(pgx) => pgx,
+        Err(e) => {
+            eprintln!("skipping, as `cargo pgx init` hasn't been run: {}", e);
+            return;
+        }
+    };
+    let label = match pgx.iter(PgConfigSelector::All).next() {
+        Some(pg_config) => pg_config.unwrap().label().unwrap(),
+        None => {
+            eprintln!("skipping, as `cargo pgx init` set up no Postgres");
+            return;
+        }
+    };
+
+    // so that connecting has to start Postgres and create the database
+    cargo_pgx(&["stop", &label]);
+    cargo_pgx(&[
+        "connect",
+        &label,
+        "postgres",
+        "--",
+        "-c",
+        &format!("DROP DATABASE IF EXISTS {}", DBNAME),
+    ]);
+    cargo_pgx(&["stop", &label]);
+
+    let output = cargo_pgx(&["connect", &label, DBNAME, "--", "-At", "-c", "SELECT 42"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Starting"), "{}", stderr);
+    assert!(stderr.contains("Creating"), "{}", stderr);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+
+    cargo_pgx(&[
+        "connect",
+        &label,
+        "postgres",
+        "--",
+        "-c",
+        &format!("DROP DATABASE {}", DBNAME),
+    ]);
+}
@@ -18,13 +18,15 @@ use serde::{Deserialize, Serialize};
 /// automatically generate =, <> SQL operator functions
 #[derive(PostgresEq)]
 
-/// automatically generate <, >, <=, >=, and a "_cmp" SQL functions
+/// automatically generate <, >, <=, >=, and a "_cmp" SQL functions, plus a "_sortsupport" function
+/// so Postgres can sort the type without calling "_cmp" for every comparison
 /// When "PostgresEq" is also derived, pgx also creates an "opclass" (and family)
 /// so that the type can be used in indexes `USING btree`
 #[derive(PostgresOrd)]
 
 /// automatically generate a "_hash" function, and the necessary "opclass" (and family)
-/// so the type can also be used in indexes `USING hash`
+/// so the type can also be used in indexes `USING hash`, plus a "_hash_extended" function
+/// so tables can be partitioned `BY HASH` on it
 #[derive(PostgresHash)]
 pub struct Thing(String);
 
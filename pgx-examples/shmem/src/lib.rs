@@ -36,6 +36,7 @@ static HASH: PgLwLock<heapless::FnvIndexMap<i32, i32, 4>> = PgLwLock::new();
 static STRUCT: PgLwLock<Pgtest> = PgLwLock::new();
 static PRIMITIVE: PgLwLock<i32> = PgLwLock::new();
 static ATOMIC: PgAtomic<std::sync::atomic::AtomicBool> = PgAtomic::new();
+static DYNAHASH: PgShmemHash<i64, i64> = PgShmemHash::new("shmem_example_dynahash", 1000);
 
 #[pg_guard]
 pub extern "C" fn _PG_init() {
@@ -44,6 +45,7 @@ pub extern "C" fn _PG_init() {
     pg_shmem_init!(STRUCT);
     pg_shmem_init!(PRIMITIVE);
     pg_shmem_init!(ATOMIC);
+    pg_shmem_init!(DYNAHASH);
 }
 
 #[pg_extern]
@@ -119,3 +121,26 @@ fn atomic_get() -> bool {
 fn atomic_set(value: bool) -> bool {
     ATOMIC.get().swap(value, Ordering::Relaxed)
 }
+
+#[pg_extern]
+fn dynahash_insert(key: i64, value: i64) -> Option<i64> {
+    DYNAHASH
+        .exclusive()
+        .insert(key, value)
+        .unwrap_or_else(|_| error!("dynahash is full"))
+}
+
+#[pg_extern]
+fn dynahash_get(key: i64) -> Option<i64> {
+    DYNAHASH.share().get(&key)
+}
+
+#[pg_extern]
+fn dynahash_remove(key: i64) -> Option<i64> {
+    DYNAHASH.exclusive().remove(&key)
+}
+
+#[pg_extern]
+fn dynahash_entries() -> impl Iterator<Item = (name!(key, i64), name!(value, i64))> {
+    DYNAHASH.share().iter().collect::<Vec<_>>().into_iter()
+}
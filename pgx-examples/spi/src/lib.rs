@@ -41,6 +41,8 @@ fn spi_return_query(
     let query = "SELECT oid, relname::text || '-pg13' FROM pg_class";
     #[cfg(feature = "pg14")]
     let query = "SELECT oid, relname::text || '-pg14' FROM pg_class";
+    #[cfg(feature = "pg15")]
+    let query = "SELECT oid, relname::text || '-pg15' FROM pg_class";
 
     let mut results = Vec::new();
     Spi::connect(|client| {
@@ -54,7 +56,7 @@ fn spi_return_query(
     results.into_iter()
 }
 
-#[pg_extern(immutable, parallel_safe)]
+#[pg_extern]
 fn spi_query_random_id() -> Option<i64> {
     Spi::get_one("SELECT id FROM spi.spi_example ORDER BY random() LIMIT 1")
 }
@@ -0,0 +1,110 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx_utils::{BUILD_GIT_SHA_ENV, BUILD_RUSTC_VERSION_ENV};
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::Ident;
+
+/// A parsed `pg_introspection_functions!()`, with the prefix of the functions it declares, which
+/// is the crate's name unless one is given
+pub(crate) struct PgIntrospectionFunctions {
+    prefix: Option<Ident>,
+}
+
+impl Parse for PgIntrospectionFunctions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let prefix = if input.is_empty() {
+            None
+        } else {
+            Some(input.parse()?)
+        };
+        if !input.is_empty() {
+            return Err(input.error(
+                "expected `pg_introspection_functions!()` or `pg_introspection_functions!(prefix)`",
+            ));
+        }
+        Ok(Self { prefix })
+    }
+}
+
+pub(crate) fn impl_pg_introspection_functions(
+    functions: PgIntrospectionFunctions,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let prefix = match functions.prefix {
+        Some(prefix) => prefix,
+        None => {
+            let crate_name = std::env::var("CARGO_CRATE_NAME").map_err(|_| {
+                syn::Error::new(
+                    Span::call_site(),
+                    "`CARGO_CRATE_NAME` isn't set, so give `pg_introspection_functions!(prefix)` a prefix",
+                )
+            })?;
+            Ident::new(&crate_name, Span::call_site())
+        }
+    };
+    let version = format_ident!("{}_version", prefix);
+    let build_info = format_ident!("{}_build_info", prefix);
+    let internal_settings = format_ident!("{}_internal_settings", prefix);
+
+    Ok(quote! {
+        /// The extension's version, from its `Cargo.toml`
+        #[pgx::pg_extern(stable, parallel_safe)]
+        pub fn #version() -> &'static str {
+            env!("CARGO_PKG_VERSION")
+        }
+
+        /// What the extension's library was built from and with.  `git_sha` and `rustc_version`
+        /// are null unless it was built by `cargo pgx`
+        #[pgx::pg_extern(stable, parallel_safe)]
+        pub fn #build_info() -> impl std::iter::Iterator<
+            Item = (
+                pgx::name!(version, &'static str),
+                pgx::name!(git_sha, Option<&'static str>),
+                pgx::name!(rustc_version, Option<&'static str>),
+                pgx::name!(pgx_version, &'static str),
+                pgx::name!(profile, &'static str),
+                pgx::name!(pg_version, i32),
+            ),
+        > {
+            let profile = if cfg!(debug_assertions) { "debug" } else { "release" };
+            std::iter::once((
+                env!("CARGO_PKG_VERSION"),
+                option_env!(#BUILD_GIT_SHA_ENV),
+                option_env!(#BUILD_RUSTC_VERSION_ENV),
+                pgx::PGX_VERSION,
+                profile,
+                pgx::pg_sys::PG_VERSION_NUM as i32,
+            ))
+        }
+
+        /// The settings the extension has defined through `GucRegistry`, like `pg_settings`
+        #[pgx::pg_extern(stable, parallel_safe)]
+        pub fn #internal_settings() -> impl std::iter::Iterator<
+            Item = (
+                pgx::name!(name, String),
+                pgx::name!(setting, Option<String>),
+                pgx::name!(vartype, &'static str),
+                pgx::name!(context, &'static str),
+                pgx::name!(short_desc, String),
+            ),
+        > {
+            pgx::GucRegistry::defined().into_iter().map(|guc| {
+                let setting = guc.setting();
+                (
+                    guc.name,
+                    setting,
+                    guc.vartype,
+                    guc.context.name(),
+                    guc.short_description,
+                )
+            })
+        }
+    })
+}
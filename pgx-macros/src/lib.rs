@@ -9,13 +9,19 @@ Use of this source code is governed by the MIT license that can be found in the
 
 extern crate proc_macro;
 
+mod introspection;
 mod operators;
 mod rewriter;
+mod tsearch;
+use introspection::{impl_pg_introspection_functions, PgIntrospectionFunctions};
 use operators::{impl_postgres_eq, impl_postgres_hash, impl_postgres_ord};
+use tsearch::{impl_postgres_text_search_dictionary, impl_postgres_text_search_parser};
 
 use pgx_utils::{
     sql_entity_graph::{
-        ExtensionSql, ExtensionSqlFile, PgAggregate, PgExtern, PostgresEnum, PostgresType, Schema,
+        expand_generic_pg_extern, expand_impl_pg_extern, ExtensionSql, ExtensionSqlFile,
+        PgAggregate, PgCatalogTable, PgExtern, PgGeneratedColumn, PgIndex, PgPolicy, PostgresDomain, PostgresEnum, PostgresType,
+        RequiredExtension, Schema, SqlName,
     },
     *,
 };
@@ -51,8 +57,23 @@ pub fn pg_guard(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/// Declare that a function calls into Postgres, so it panics with a clear message when it's
+/// called outside of a Postgres backend, or from a thread other than the backend's, instead of
+/// crashing.  See `pgx::assert_backend()`
+#[proc_macro_attribute]
+pub fn requires_backend(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut func = parse_macro_input!(item as ItemFn);
+    func.block
+        .stmts
+        .insert(0, syn::parse_quote! { ::pgx::assert_backend(); });
+    func.into_token_stream().into()
+}
+
 /// `#[pg_test]` functions are test functions (akin to `#[test]`), but they run in-process inside
 /// Postgres during `cargo pgx test`.
+///
+/// Postgres' `random()` is seeded for each of them, with the seed `pgx_tests::test_seed()` returns.
+/// If one fails, the command which reproduces it, with the same seed, is printed with the failure.
 #[proc_macro_attribute]
 pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut stream = proc_macro2::TokenStream::new();
@@ -99,7 +120,7 @@ pub fn pg_test(attr: TokenStream, item: TokenStream) -> TokenStream {
                     #att_stream
 
                     crate::pg_test::setup(options);
-                    let res = pgx_tests::run_test(#sql_funcname, #expected_error, crate::pg_test::postgresql_conf_options());
+                    let res = pgx_tests::run_test(#sql_funcname, #expected_error, crate::pg_test::postgresql_conf_options(), concat!(module_path!(), "::", stringify!(#test_func_name)));
                     match res {
                         Ok(()) => (),
                         Err(e) => panic!("{:?}", e)
@@ -210,6 +231,7 @@ Accepts a String literal, a `name` attribute, and optionally others:
 
 * `name = "item"`: Set the unique identifer to `"item"` for use in `requires` declarations.
 * `requires = [item, item_two]`: References to other `name`s or Rust items which this SQL should be present after.
+  A `name` can also be that of an entity declared by another crate's macro with `pgx_utils::sql_entity_graph::Custom`.
 * `creates = [ Type(submod::Cust), Enum(Pre), Function(defined)]`: Communicates that this SQL block creates certain entities.
   Please note it **does not** create matching Rust types.
 * `bootstrap` (**Unique**): Communicates that this is SQL intended to go before all other generated SQL.
@@ -346,6 +368,142 @@ pub fn extension_sql(input: TokenStream) -> TokenStream {
     }
 }
 
+/**
+Declare a row level security policy, generating its `CREATE POLICY` in the extension script.
+
+```rust,ignore
+use pgx_macros::pg_policy;
+
+pg_policy!(
+    name = "own_documents",
+    on = "documents",
+    command = select,
+    to = ["PUBLIC"],
+    using = "owner = current_user_id()",
+    requires = ["create_documents", current_user_id],
+);
+```
+
+* `name` and `on` (the table) are required.
+* `command` is one of `all` (the default), `select`, `insert`, `update`, or `delete`.
+* `restrictive` makes it an `AS RESTRICTIVE` policy.
+* `to` lists the roles the policy applies to.
+* `using` and `with_check` are SQL expressions, at least one of which must be given.
+* `requires` orders the policy after the SQL creating its table and the functions its expressions
+  use, like [`macro@extension_sql`].
+
+Other SQL can `require` the policy by the name `policy_<name>_on_<table>`.  Row level security
+still has to be enabled on the table with `ALTER TABLE ... ENABLE ROW LEVEL SECURITY`.
+*/
+#[proc_macro]
+pub fn pg_policy(input: TokenStream) -> TokenStream {
+    fn wrapped(input: TokenStream) -> Result<TokenStream, syn::Error> {
+        let policy: PgPolicy = syn::parse(input)?;
+        Ok(policy.to_token_stream().into())
+    }
+
+    match wrapped(input) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare an index, such as of an expression using the extension's functions, generating its
+`CREATE INDEX` in the extension script, ordered after its table and the functions, types, and
+operator classes its keys use.
+
+```rust,ignore
+use pgx_macros::pg_index;
+
+pg_index!(
+    name = "documents_tokens",
+    on = "documents",
+    using = gin,
+    keys = [("document_tokens(body)", "document_tokens_ops")],
+    where = "body IS NOT NULL",
+    requires = ["create_documents", document_tokens, "create_document_tokens_ops"],
+);
+```
+
+* `name`, `on` (the table), and `keys` are required.
+* `using` is the index's access method, like `btree` (the default), `gin`, or `gist`.
+* `unique` makes it a `UNIQUE` index.
+* `keys` lists the columns and expressions indexed, each optionally paired with the operator
+  class to index it with, as `("expression", "opclass")`.
+* `include` lists columns stored in the index without being keys.
+* `where` makes it a partial index, of the rows matching the expression.
+* `requires` orders the index after the SQL creating its table, and the functions, types, and
+  operator classes its keys use, like [`macro@extension_sql`].
+
+Other SQL can `require` the index by the name `index_<name>`.  Functions used in keys must be
+`IMMUTABLE`.
+*/
+#[proc_macro]
+pub fn pg_index(input: TokenStream) -> TokenStream {
+    fn wrapped(input: TokenStream) -> Result<TokenStream, syn::Error> {
+        let index: PgIndex = syn::parse(input)?;
+        Ok(index.to_token_stream().into())
+    }
+
+    match wrapped(input) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare a generated column of a table the extension creates, generating its `ALTER TABLE ... ADD
+COLUMN ... GENERATED ALWAYS AS (...) STORED` in the extension script, ordered after the functions
+its expression uses.  Generated columns are new in Postgres 12.
+
+```rust,ignore
+use pgx_macros::pg_generated_column;
+
+pg_generated_column!(
+    name = "tokens",
+    on = "documents",
+    type = "tsvector",
+    expression = "document_tokens(body)",
+    requires = ["create_documents", document_tokens],
+);
+```
+
+* `name`, `on` (the table), `type`, and `expression` are required.
+* `requires` orders the column after the SQL creating its table and the functions its expression
+  uses, like [`macro@extension_sql`].
+
+Other SQL, like an index of the column, can `require` it by the name
+`generated_column_<name>_on_<table>`.  Functions used in the expression must be `IMMUTABLE`.
+*/
+#[proc_macro]
+pub fn pg_generated_column(input: TokenStream) -> TokenStream {
+    fn wrapped(input: TokenStream) -> Result<TokenStream, syn::Error> {
+        let column: PgGeneratedColumn = syn::parse(input)?;
+        Ok(column.to_token_stream().into())
+    }
+
+    match wrapped(input) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
 /**
 Declare SQL (from a file) to be included in generated extension script.
 
@@ -391,6 +549,83 @@ pub fn extension_sql_file(input: TokenStream) -> TokenStream {
     }
 }
 
+/**
+Declare that this extension requires another one, optionally in a version which meets a
+requirement like `">=1.4"` or `">=1.4, <2"` (comparisons of dotted numbers, joined with `,`, with
+a bare version meaning exactly that version).
+
+`cargo pgx install` adds the extension to the control file's `requires`, so Postgres won't create
+this extension without it, or will create it first with `CREATE EXTENSION ... CASCADE`.  The
+generated SQL checks for it, and its version, before creating anything else, so `CREATE
+EXTENSION` fails with an error which says what's missing rather than whatever first uses it:
+
+```rust,ignore
+use pgx_macros::requires_extension;
+
+requires_extension!("hstore", ">=1.4");
+requires_extension!("plpgsql");
+```
+
+Only the leading numbers of the installed version are compared, so `1.4beta` is taken as `1.4`,
+and a version which doesn't start with one isn't checked.
+*/
+#[proc_macro]
+pub fn requires_extension(input: TokenStream) -> TokenStream {
+    fn wrapped(input: TokenStream) -> Result<TokenStream, syn::Error> {
+        let required: RequiredExtension = syn::parse(input)?;
+        Ok(required.to_token_stream().into())
+    }
+
+    match wrapped(input) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
+/**
+Declare SQL functions describing the extension, for checking what's installed on a server:
+
+* `<ext>_version()` returns the extension's version, from its `Cargo.toml`.
+* `<ext>_build_info()` returns a row of its `version`, the `git_sha` of the commit and the
+  `rustc_version` it was built from and with, the `pgx_version`, the build `profile` (`debug` or
+  `release`), and the `pg_version` (like `140002`) it was built for.
+* `<ext>_internal_settings()` returns a row for each setting the extension has defined through
+  `GucRegistry`, with its `name`, current `setting`, `vartype`, `context`, and `short_desc`, as
+  `pg_settings` shows them.
+
+```rust,ignore
+use pgx::*;
+
+pg_introspection_functions!();
+```
+
+`<ext>` is the crate's name unless a prefix is given, as `pg_introspection_functions!(my_ext)`.
+`git_sha` and `rustc_version` are recorded by `cargo pgx install`, `package`, `schema`, and `test`,
+and are null if the library was built some other way, or `git_sha` if it's not in a git checkout.
+*/
+#[proc_macro]
+pub fn pg_introspection_functions(input: TokenStream) -> TokenStream {
+    fn wrapped(input: TokenStream) -> Result<TokenStream, syn::Error> {
+        let functions: PgIntrospectionFunctions = syn::parse(input)?;
+        Ok(impl_pg_introspection_functions(functions)?.into())
+    }
+
+    match wrapped(input) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            let msg = e.to_string();
+            TokenStream::from(quote! {
+              compile_error!(#msg);
+            })
+        }
+    }
+}
+
 /// Associated macro for `#[pg_extern]` or `#[macro@pg_operator]`.  Used to set the `SEARCH_PATH` option
 /// on the `CREATE FUNCTION` statement.
 #[proc_macro_attribute]
@@ -404,6 +639,8 @@ Declare a function as `#[pg_extern]` to indicate that it can be used by Postgres
 Optionally accepts the following attributes:
 
 * `immutable`: Corresponds to [`IMMUTABLE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
+  + A call in its body that can't be, such as one that runs SQL through `Spi`, reads the clock, or uses a sequence, gets a
+    deprecation warning, which `#[allow(deprecated)]` on the function silences.
 * `strict`: Corresponds to [`STRICT`](https://www.postgresql.org/docs/current/sql-createfunction.html).
   + In most cases, `#[pg_extern]` can detect when no `Option<T>`s are used, and automatically set this.
 * `stable`: Corresponds to [`STABLE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
@@ -413,11 +650,85 @@ Optionally accepts the following attributes:
 * `parallel_unsafe`: Corresponds to [`PARALLEL UNSAFE`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `parallel_restricted`: Corresponds to [`PARALLEL RESTRICTED`](https://www.postgresql.org/docs/current/sql-createfunction.html).
 * `no_guard`: Do not use `#[pg_guard]` with the function.
+* `untrusted`: The function isn't safe to give those who aren't superusers, such as one which reads the server's files, so
+  the extension fails the audit for being marked `trusted = true` in its control file (see `cargo pgx schema --trusted`).
+* `sql_name = "name"` (or `name = "name"`): Create the function as `name` in SQL, rather than with the Rust function's name.
+* `schema = "schema"`: Create the function, and its operator if it's one, in `schema`, rather than in its module's schema.
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+* `instances(..)`: The types to create a generic function for (see below).
+* `rows = N`: Corresponds to [`ROWS`](https://www.postgresql.org/docs/current/sql-createfunction.html), the number of rows
+  the planner expects a set-returning function to return, rather than 1000.
+* `support = function`: Corresponds to [`SUPPORT`](https://www.postgresql.org/docs/current/sql-createfunction.html), a
+  `#[pg_extern]` planner support function, such as one which estimates the rows returned from the arguments given
+  (see `pgx::SupportRequest`).  Postgres 12 or later.
+* `inline = "expression"`: Create the function in SQL, as `SELECT expression`, rather than as a call to the Rust function,
+  so the planner can inline it into the queries using it, without a function call per row (see below).
 
 Functions can accept and return any type which `pgx` supports. `pgx` supports many PostgreSQL types by default.
 New types can be defined via [`macro@PostgresType`] or [`macro@PostgresEnum`].
 
+A function with `sql = false` is still compiled, along with the wrapper Postgres would call, but it's left out of the
+generated SQL, for helpers which are only called from Rust, or whose `CREATE FUNCTION` is written by hand:
+
+```rust,ignore
+use pgx::*;
+#[pg_extern(sql = false)]
+fn internal_helper(x: i32) -> i32 { x * 2 }
+```
+
+`#[cfg(..)]` attributes apply to everything generated for the function, wherever they're placed, so a function behind
+a feature which isn't enabled isn't in the generated SQL either:
+
+```rust,ignore
+use pgx::*;
+#[pg_extern]
+#[cfg(feature = "fancy")]
+fn fancy() { todo!() }
+```
+
+A function with `inline` is created in SQL instead, from an expression which must compute the same thing as the Rust
+function, referring to the arguments by name.  The Rust function is still there for Rust to call.  Postgres only
+inlines it when it's no more volatile than it's declared to be, and isn't `strict` unless the expression is:
+
+```rust,ignore
+use pgx::*;
+#[pg_extern(immutable, parallel_safe, inline = "a * 2 + b")]
+fn double_plus(a: i32, b: i32) -> i32 { a * 2 + b }
+```
+
+A generic function is created for each of its `instances`, as a function called its name and the types', which calls
+it with them.  Give them all the same `name` to create them as overloads of one SQL function, as long as their
+arguments' types differ.  A function with more than one type parameter has a tuple of types for each instance.
+
+```rust,ignore
+use pgx::*;
+// sum_array(integer[]), sum_array(bigint[]), and sum_array(double precision[])
+#[pg_extern(immutable, instances(i32, i64, f64), name = "sum_array")]
+fn sum_array<T: std::iter::Sum>(values: Vec<T>) -> T {
+    values.into_iter().sum()
+}
+```
+
+On an inherent or trait `impl`, `#[pg_extern]` creates a function for each of its associated functions which are
+marked `#[pg_extern]` themselves, called the type's name and the associated function's, with any `self` as the first
+argument, `this`:
+
+```rust,ignore
+use pgx::*;
+use serde::{Deserialize, Serialize};
+#[derive(Serialize, Deserialize, PostgresType)]
+pub struct Point { x: f64, y: f64 }
+
+#[pg_extern]
+impl Point {
+    // point_distance(this Point, other Point)
+    #[pg_extern(immutable)]
+    fn distance(&self, other: Self) -> f64 { todo!() }
+
+    fn helper(&self) { todo!() }
+}
+```
+
 
 Without any arguments or returns:
 ```rust,ignore
@@ -513,6 +824,25 @@ It accepts 2 arguments:
 * A name, such as `example`
 * A type
 
+A table whose columns aren't known until the function is called, such as a pivot of its
+arguments, is returned as `SETOF record` from an iterator of `PgComposite` rows.  They're made
+with `PgComposite::from_tupdesc()`, for the columns the query gives, from
+`FcInfoContext::result_tupdesc()`, or ones made with `PgTupleDesc::from_columns()`:
+
+```rust,ignore
+use pgx::*;
+// SELECT * FROM pivot(ARRAY['a', 'b']) AS t(a text, b text)
+#[pg_extern]
+fn pivot(keys: Vec<String>, context: FcInfoContext) -> impl Iterator<Item = PgComposite> {
+    let tupdesc = context.result_tupdesc().unwrap_or_else(|| {
+        let columns = keys.iter().map(|key| (key.as_str(), pg_sys::TEXTOID)).collect::<Vec<_>>();
+        PgTupleDesc::from_columns(&columns)
+    });
+    let values = keys.iter().map(|key| key.clone().into_datum()).collect();
+    std::iter::once(PgComposite::from_tupdesc(&tupdesc, values))
+}
+```
+
 # Special Cases
 
 `pg_sys::Oid` is a special cased type alias, in order to use it as an argument or return it must be
@@ -535,14 +865,25 @@ fn example_return() -> pg_sys::Oid {
 */
 #[proc_macro_attribute]
 pub fn pg_extern(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let args = parse_extern_attributes(proc_macro2::TokenStream::from(attr.clone()));
-
-    let sql_graph_entity_item = PgExtern::new(attr.clone().into(), item.clone().into()).unwrap();
-
     let ast = parse_macro_input!(item as syn::Item);
     match ast {
-        Item::Fn(func) => rewrite_item_fn(func, args, &sql_graph_entity_item).into(),
-        _ => panic!("#[pg_extern] can only be applied to top-level functions"),
+        Item::Fn(func)
+            if func.sig.generics.type_params().next().is_some()
+                || func.sig.generics.const_params().next().is_some() =>
+        {
+            expand_generic_pg_extern(attr.into(), func)
+                .unwrap_or_else(|e| e.to_compile_error())
+                .into()
+        }
+        Item::Fn(func) => {
+            let args = parse_extern_attributes(proc_macro2::TokenStream::from(attr.clone()));
+            let sql_graph_entity_item = PgExtern::new(attr.into(), func.to_token_stream()).unwrap();
+            rewrite_item_fn(func, args, &sql_graph_entity_item).into()
+        }
+        Item::Impl(item_impl) => expand_impl_pg_extern(attr.into(), item_impl)
+            .unwrap_or_else(|e| e.to_compile_error())
+            .into(),
+        _ => panic!("#[pg_extern] can only be applied to top-level functions and impls"),
     }
 }
 
@@ -567,6 +908,7 @@ fn rewrite_item_fn(
     // make the function 'extern "C"' because this is for the #[pg_extern[ macro
     func.sig.abi = Some(syn::parse_str("extern \"C\"").unwrap());
     let func_span = func.span();
+    let cfg_attrs = pgx_utils::cfg_attrs(&func.attrs);
     let (rewritten_func, need_wrapper) = rewriter.item_fn(
         func,
         Some(sql_graph_entity_submission),
@@ -577,6 +919,7 @@ fn rewrite_item_fn(
 
     if need_wrapper {
         quote_spanned! {func_span=>
+            #(#cfg_attrs)*
             #[no_mangle]
             #[doc(hidden)]
             pub extern "C" fn #finfo_name() -> &'static pg_sys::Pg_finfo_record {
@@ -608,6 +951,36 @@ enum DogNames {
 }
 ```
 
+The labels are the variants' names, sorted in the order they're declared.  Variants optionally
+accept the following attributes:
+
+* `#[pgx(name = "label")]`: Use `label` as the variant's label in Postgres.
+* `#[pgx(order = 1)]`: Sort the variant as if it were declared at that position, so variants can be
+  declared in whichever order suits the Rust code.
+
+The enum itself accepts `#[pgx(sql_name = "name", schema = "schema")]`, as
+[`macro@PostgresType`] does.
+
+```rust,ignore
+# use pgx_pg_sys as pg_sys;
+use pgx::*;
+#[derive(Debug, PostgresEnum)]
+enum Status {
+    #[pgx(name = "in progress")]
+    InProgress,
+    Done,
+    #[pgx(order = 0)]
+    Todo,
+}
+```
+
+Postgres can add labels to an enum, but can't remove or reorder them.  To add a variant in a new
+version of an extension, declare it where it belongs in the sort order, then have
+`cargo pgx schema --upgrade-from sql/$EXTNAME-$PREVIOUS.sql --upgrade-out some/file.sql` write the
+`ALTER TYPE ... ADD VALUE` statements for the new labels, to be copied into the extension's upgrade
+script.  Before Postgres 12, `ALTER TYPE ... ADD VALUE` can't be run in a transaction, as extension
+scripts are, so those versions have to run the statements themselves before `ALTER EXTENSION ...
+UPDATE`.
 */
 #[proc_macro_derive(PostgresEnum, attributes(requires, pgx))]
 pub fn postgres_enum(input: TokenStream) -> TokenStream {
@@ -620,21 +993,24 @@ fn impl_postgres_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
     let mut stream = proc_macro2::TokenStream::new();
     let sql_graph_entity_ast = ast.clone();
     let enum_ident = ast.ident;
-    let enum_name = enum_ident.to_string();
 
     // validate that we're only operating on an enum
-    let enum_data = match ast.data {
-        Data::Enum(e) => e,
-        _ => panic!("#[derive(PostgresEnum)] can only be applied to enums"),
+    if !matches!(ast.data, Data::Enum(_)) {
+        panic!("#[derive(PostgresEnum)] can only be applied to enums");
+    }
+
+    let sql_graph_entity_item = match PostgresEnum::from_derive_input(sql_graph_entity_ast) {
+        Ok(item) => item,
+        Err(e) => return e.to_compile_error(),
     };
+    let enum_name = sql_graph_entity_item.sql_name().qualified_name(&enum_ident);
+    // checked when parsed
+    let labels = sql_graph_entity_item.labels().unwrap();
 
     let mut from_datum = proc_macro2::TokenStream::new();
     let mut into_datum = proc_macro2::TokenStream::new();
 
-    for d in enum_data.variants.clone() {
-        let label_ident = &d.ident;
-        let label_string = label_ident.to_string();
-
+    for (label_ident, label_string) in labels {
         from_datum.extend(quote! { #label_string => Some(#enum_ident::#label_ident), });
         into_datum.extend(quote! { #enum_ident::#label_ident => Some(pgx::lookup_enum_by_label(#enum_name, #label_string)), });
     }
@@ -670,7 +1046,332 @@ fn impl_postgres_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
         }
     });
 
-    let sql_graph_entity_item = PostgresEnum::from_derive_input(sql_graph_entity_ast).unwrap();
+    sql_graph_entity_item.to_tokens(&mut stream);
+
+    stream
+}
+
+/**
+Generate necessary bindings for using the tuple struct as a PostgreSQL `DOMAIN` over the type of
+its only field, constrained by each `#[check("...")]` expression.
+
+```rust,ignore
+# use pgx_pg_sys as pg_sys;
+use pgx::*;
+#[derive(Debug, PostgresDomain)]
+#[check("VALUE > 0")]
+struct PositiveInt(i32);
+```
+
+Values are checked against the domain's constraints when they're made from a `Datum` of another
+type, such as the base type, and when they're returned to Postgres.
+
+Optionally accepts the following attributes:
+
+* `check("...")`: A `CHECK` constraint, where `VALUE` is the value being checked.  Can be repeated.
+* `#[pgx(sql_name = "name", schema = "schema")]`: Same arguments as for [`macro@PostgresType`].
+* `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
+*/
+#[proc_macro_derive(PostgresDomain, attributes(check, requires, pgx))]
+pub fn postgres_domain(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    impl_postgres_domain(ast).into()
+}
+
+fn impl_postgres_domain(ast: DeriveInput) -> proc_macro2::TokenStream {
+    let mut stream = proc_macro2::TokenStream::new();
+    let sql_graph_entity_ast = ast.clone();
+    let domain_ident = ast.ident;
+    let sql_graph_entity_item = match PostgresDomain::from_derive_input(sql_graph_entity_ast) {
+        Ok(item) => item,
+        Err(e) => return e.to_compile_error(),
+    };
+    let domain_name = sql_graph_entity_item.sql_name().qualified_name(&domain_ident);
+
+    // validate that we're only operating on a tuple struct with a single field
+    let base = match ast.data {
+        Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Unnamed(fields),
+            ..
+        }) if fields.unnamed.len() == 1 => fields.unnamed.into_iter().next().unwrap().ty,
+        _ => panic!(
+            "#[derive(PostgresDomain)] can only be applied to tuple structs with a single field"
+        ),
+    };
+
+    stream.extend(quote! {
+        impl pgx::FromDatum for #domain_ident {
+            #[inline]
+            unsafe fn from_datum(datum: pgx::pg_sys::Datum, is_null: bool, typeoid: pgx::pg_sys::Oid) -> Option<#domain_ident> {
+                if is_null {
+                    return None;
+                }
+
+                let mut typeoid = typeoid;
+                if typeoid != pgx::pg_sys::InvalidOid {
+                    // Postgres has already checked values of the domain itself
+                    let domain = <#domain_ident as pgx::IntoDatum>::type_oid();
+                    if typeoid != domain {
+                        pgx::domain_check(datum, false, domain);
+                    }
+                    typeoid = pgx::domain_base_type(typeoid);
+                }
+                <#base as pgx::FromDatum>::from_datum(datum, false, typeoid).map(#domain_ident)
+            }
+        }
+
+        impl pgx::IntoDatum for #domain_ident {
+            #[inline]
+            fn into_datum(self) -> Option<pgx::pg_sys::Datum> {
+                let datum = <#base as pgx::IntoDatum>::into_datum(self.0);
+                // Postgres doesn't check what functions return against the domain
+                pgx::domain_check(datum.unwrap_or(0), datum.is_none(), Self::type_oid());
+                datum
+            }
+
+            fn type_oid() -> pg_sys::Oid {
+                pgx::regtypein(#domain_name)
+            }
+
+            fn is_compatible_with(other: pgx::pg_sys::Oid) -> bool {
+                // values of the base type are checked against the domain as they're converted
+                other == Self::type_oid()
+                    || <#base as pgx::IntoDatum>::is_compatible_with(pgx::domain_base_type(other))
+            }
+        }
+    });
+
+    sql_graph_entity_item.to_tokens(&mut stream);
+
+    stream
+}
+
+/**
+Declare a table the extension keeps its own metadata in, whose rows are the struct, generating its
+`CREATE TABLE` in the extension script, with its indexes and the rows it's created with, and
+an implementation of `pgx::PgCatalogTable` to read and insert rows.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(PgCatalogTable)]
+#[pgx(sql_name = "job_queues", schema = "jobs", dump)]
+#[index(name = "job_queues_priority", keys = ["priority"], where = "NOT paused")]
+#[row(name = "default", priority = 0)]
+#[requires("create_jobs_schema")]
+struct JobQueue {
+    #[pgx(primary_key)]
+    name: String,
+    priority: i32,
+    #[pgx(default = "false")]
+    paused: bool,
+    #[pgx(sql_type = "varchar(200)")]
+    description: Option<String>,
+}
+```
+
+The struct accepts:
+
+* `#[pgx(sql_name = "name", schema = "schema")]`: Same arguments as for [`macro@PostgresType`].
+* `#[pgx(dump)]`: Have `pg_dump` dump the table's rows, which it otherwise leaves to the extension's
+  script, or with `#[pgx(dump = "WHERE ...")]`, the rows which match.
+* `#[index(...)]`: An index of the table, with the same arguments as [`macro@pg_index`] other than
+  `on` and `requires`.  Can be repeated.
+* `#[row(column = value, ...)]`: A row the table is created with, of literals, or `None` for `NULL`,
+  which must give every `NOT NULL` column without a default a value.  Can be repeated.
+* `#[requires(...)]`: The SQL the table must be created after, like [`macro@extension_sql`], such as
+  the types of its columns.
+
+Fields accept `#[pgx(primary_key)]`, `#[pgx(name = "column")]`, `#[pgx(sql_type = "type")]`, for
+a type other than the one the field's Rust type maps to, and `#[pgx(default = "expression")]`.
+Fields of an `Option<T>` are nullable, and the rest are `NOT NULL`.
+
+`cargo pgx schema --upgrade-from` writes the `ALTER TABLE ... ADD COLUMN`, `CREATE INDEX`, and
+`INSERT` statements for the fields, indexes, and rows added since an earlier version, to be copied
+into the extension's upgrade script.  A new field that's `NOT NULL` needs a `#[pgx(default)]` for
+the rows the table already has, and is only commented on without one.
+*/
+#[proc_macro_derive(PgCatalogTable, attributes(pgx, index, row, requires))]
+pub fn pg_catalog_table(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    impl_pg_catalog_table(ast).into()
+}
+
+fn impl_pg_catalog_table(ast: DeriveInput) -> proc_macro2::TokenStream {
+    let mut stream = proc_macro2::TokenStream::new();
+    let table_ident = ast.ident.clone();
+    let sql_graph_entity_item = match PgCatalogTable::from_derive_input(ast) {
+        Ok(item) => item,
+        Err(e) => return e.to_compile_error(),
+    };
+    let name = sql_graph_entity_item.sql_name().name(&table_ident);
+    let schema = sql_graph_entity_item.sql_name().schema_tokens();
+
+    let fields = sql_graph_entity_item.fields();
+    let columns = fields.iter().map(|field| &field.column);
+    let from_row = fields.iter().enumerate().map(|(position, field)| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let ordinal = position + 1;
+        let value = quote! {
+            row.by_ordinal(#ordinal)
+                .expect("the row is of the table's columns")
+                .value::<#ty>()
+        };
+        if field.nullable {
+            quote! { #ident: #value }
+        } else {
+            let message = format!("the column `{}` of `{}` is NULL", field.column, name);
+            quote! { #ident: #value.expect(#message) }
+        }
+    });
+    let into_args = fields.iter().map(|field| &field.ident);
+
+    stream.extend(quote! {
+        impl pgx::PgCatalogTable for #table_ident {
+            const SCHEMA: Option<&'static str> = #schema;
+            const NAME: &'static str = #name;
+            const COLUMNS: &'static [&'static str] = &[#(#columns),*];
+
+            fn from_row(row: &pgx::SpiHeapTupleData) -> Self {
+                Self {
+                    #(#from_row),*
+                }
+            }
+
+            fn into_args(self) -> Vec<(pgx::PgOid, Option<pgx::pg_sys::Datum>)> {
+                vec![#(pgx::spi_arg(self.#into_args)),*]
+            }
+        }
+    });
+
+    sql_graph_entity_item.to_tokens(&mut stream);
+
+    stream
+}
+
+/**
+Generate necessary bindings for using a fieldless enum as a PostgreSQL `text` `DOMAIN`, whose values
+are checked to be one of the enum's labels, for function arguments that pick between a few modes
+without a full [`macro@PostgresEnum`].
+
+```rust,ignore
+# use pgx_pg_sys as pg_sys;
+use pgx::*;
+#[derive(Debug, PostgresTextEnum)]
+enum Rounding {
+    Up,
+    #[pgx(name = "down")]
+    Down,
+}
+
+#[pg_extern]
+fn round_to(value: f64, rounding: default!(Rounding, "'Up'")) -> f64 {
+    match rounding {
+        Rounding::Up => value.ceil(),
+        Rounding::Down => value.floor(),
+    }
+}
+```
+
+This creates `CREATE DOMAIN Rounding AS text CHECK (VALUE IN ('Up', 'down'))`, so Postgres rejects
+any other value, and the enum converts from a plain `text` `Datum`, too, with an error that lists
+the labels when the value isn't one of them.
+
+The labels, and the enum's attributes, are as for a [`macro@PostgresEnum`].
+*/
+#[proc_macro_derive(PostgresTextEnum, attributes(requires, pgx))]
+pub fn postgres_text_enum(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+
+    impl_postgres_text_enum(ast).into()
+}
+
+fn impl_postgres_text_enum(ast: DeriveInput) -> proc_macro2::TokenStream {
+    let mut stream = proc_macro2::TokenStream::new();
+    let enum_ident = ast.ident.clone();
+
+    // validate that we're only operating on an enum
+    if !matches!(ast.data, Data::Enum(_)) {
+        panic!("#[derive(PostgresTextEnum)] can only be applied to enums");
+    }
+
+    let (sql_graph_entity_item, labels) = match PostgresDomain::from_text_enum(ast) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.to_compile_error(),
+    };
+    let domain_name = sql_graph_entity_item.sql_name().qualified_name(&enum_ident);
+    let expected = labels
+        .iter()
+        .map(|(_, label)| format!("\"{}\"", label))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut from_datum = proc_macro2::TokenStream::new();
+    let mut into_datum = proc_macro2::TokenStream::new();
+
+    for (label_ident, label_string) in labels {
+        from_datum.extend(quote! { #label_string => Some(#enum_ident::#label_ident), });
+        into_datum.extend(quote! { #enum_ident::#label_ident => #label_string, });
+    }
+
+    stream.extend(quote! {
+        impl pgx::FromDatum for #enum_ident {
+            #[inline]
+            unsafe fn from_datum(datum: pgx::pg_sys::Datum, is_null: bool, typeoid: pgx::pg_sys::Oid) -> Option<#enum_ident> {
+                if is_null {
+                    return None;
+                }
+
+                let mut typeoid = typeoid;
+                if typeoid != pgx::pg_sys::InvalidOid {
+                    typeoid = pgx::domain_base_type(typeoid);
+                }
+                let label = <String as pgx::FromDatum>::from_datum(datum, false, typeoid)?;
+                match label.as_str() {
+                    #from_datum
+                    _ => {
+                        // values of the domain itself have already been checked, but not `text`
+                        pgx::ereport(
+                            pgx::PgLogLevel::ERROR,
+                            pgx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                            &format!(
+                                "invalid value for {}: \"{}\", expected one of {}",
+                                #domain_name, label, #expected
+                            ),
+                            file!(),
+                            line!(),
+                            column!(),
+                        );
+                        unreachable!()
+                    }
+                }
+            }
+        }
+
+        impl pgx::IntoDatum for #enum_ident {
+            #[inline]
+            fn into_datum(self) -> Option<pgx::pg_sys::Datum> {
+                let label = match self {
+                    #into_datum
+                };
+                pgx::IntoDatum::into_datum(label)
+            }
+
+            fn type_oid() -> pg_sys::Oid {
+                pgx::regtypein(#domain_name)
+            }
+
+            fn is_compatible_with(other: pgx::pg_sys::Oid) -> bool {
+                // values of the base type are checked against the labels as they're converted
+                other == Self::type_oid()
+                    || <String as pgx::IntoDatum>::is_compatible_with(pgx::domain_base_type(other))
+            }
+        }
+    });
+
     sql_graph_entity_item.to_tokens(&mut stream);
 
     stream
@@ -694,6 +1395,16 @@ Optionally accepts the following attributes:
 
 * `inoutfuncs(some_in_fn, some_out_fn)`: Define custom in/out functions for the type.
 * `pgvarlena_inoutfuncs(some_in_fn, some_out_fn)`: Define custom in/out functions for the `PgVarlena` of this type.
+* `#[pgx(sql_name = "name", schema = "schema")]`: Create the type as `name` in SQL, rather than
+  with the Rust type's name, or in `schema`, rather than in its module's schema, which has to exist
+  by then, such as from a [`macro@pg_schema`] module or `extension_sql!()`.  Functions, operators,
+  and aggregates use the type by that name, and so does `type_oid()` look it up, except for a
+  `PgVarlena<T>`'s.
+* `#[pgx(storage = "main", alignment = "double")]`: The `STORAGE` of the type's values, which is
+  `extended` unless it's `plain`, `main`, or `external`, and their `ALIGNMENT`, which is `int4`
+  unless it's `char`, `int2`, or `double`.  A large value that's read only in part is best
+  `external`, which isn't compressed, so `pgx::detoast_datum_slice()` only reads the part.
+  Compression is set for a column rather than a type, with `ALTER TABLE .. SET COMPRESSION`.
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
 */
 #[proc_macro_derive(
@@ -713,6 +1424,10 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
     let funcname_in = Ident::new(&format!("{}_in", name).to_lowercase(), name.span());
     let funcname_out = Ident::new(&format!("{}_out", name).to_lowercase(), name.span());
     let mut args = parse_postgres_type_args(&ast.attrs);
+    let sql_name = match SqlName::from_attributes(&ast.attrs) {
+        Ok(sql_name) => sql_name,
+        Err(e) => return e.to_compile_error(),
+    };
     let mut stream = proc_macro2::TokenStream::new();
 
     // validate that we're only operating on a struct
@@ -732,8 +1447,14 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
     };
 
     // all #[derive(PostgresType)] need to implement that trait
+    let sql_name_const = if sql_name != SqlName::default() {
+        let qualified_name = sql_name.qualified_name(name);
+        quote! { const SQL_NAME: Option<&'static str> = Some(#qualified_name); }
+    } else {
+        quote! {}
+    };
     stream.extend(quote! {
-        impl #generics pgx::PostgresType for #name #generics { }
+        impl #generics pgx::PostgresType for #name #generics { #sql_name_const }
     });
 
     // and if we don't have custom inout/funcs, we use the JsonInOutFuncs trait
@@ -757,7 +1478,7 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
             #[doc(hidden)]
             #[pg_extern(immutable,parallel_safe)]
             pub fn #funcname_out #generics(input: #name #generics) -> &#lifetime pgx::cstr_core::CStr {
-                let mut buffer = StringInfo::new();
+                let mut buffer = StringInfo::pooled();
                 input.output(&mut buffer);
                 buffer.into()
             }
@@ -775,7 +1496,7 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
             #[doc(hidden)]
             #[pg_extern(immutable,parallel_safe)]
             pub fn #funcname_out #generics(input: #name #generics) -> &#lifetime pgx::cstr_core::CStr {
-                let mut buffer = StringInfo::new();
+                let mut buffer = StringInfo::pooled();
                 input.output(&mut buffer);
                 buffer.into()
             }
@@ -792,14 +1513,17 @@ fn impl_postgres_type(ast: DeriveInput) -> proc_macro2::TokenStream {
             #[doc(hidden)]
             #[pg_extern(immutable,parallel_safe)]
             pub fn #funcname_out #generics(input: pgx::PgVarlena<#name #generics>) -> &#lifetime pgx::cstr_core::CStr {
-                let mut buffer = StringInfo::new();
+                let mut buffer = StringInfo::pooled();
                 input.output(&mut buffer);
                 buffer.into()
             }
         });
     }
 
-    let sql_graph_entity_item = PostgresType::from_derive_input(ast).unwrap();
+    let sql_graph_entity_item = match PostgresType::from_derive_input(ast) {
+        Ok(item) => item,
+        Err(e) => return e.to_compile_error(),
+    };
     sql_graph_entity_item.to_tokens(&mut stream);
 
     stream
@@ -959,11 +1683,21 @@ enum DogNames {
     Brandy,
 }
 ```
+The type also gets a `sortsupport` function, so Postgres can sort it without going through SQL
+for every comparison.
+
 Optionally accepts the following attributes:
 
+* `abbreviated_keys`: Sort by the keys from the type's `pgx::AbbreviatedKeys` implementation.
+* `#[pgx(default_opclass = false)]`: Don't make the type's `btree` operator class its `DEFAULT`
+  one, so indexes and `ORDER BY` only use it when it's named.
+* `#[pgx(btree_family = "integer_ops")]`: Also add the type's operators and support functions to
+  the existing `btree` operator family `integer_ops`, with `ALTER OPERATOR FAMILY .. ADD`, so the
+  family's cross-type operators, which the extension adds itself, can be used with the type.  It
+  can be given more than once.
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
 */
-#[proc_macro_derive(PostgresOrd, attributes(pgx))]
+#[proc_macro_derive(PostgresOrd, attributes(pgx, abbreviated_keys))]
 pub fn postgres_ord(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as syn::DeriveInput);
     impl_postgres_ord(ast)
@@ -974,6 +1708,9 @@ pub fn postgres_ord(input: TokenStream) -> TokenStream {
 /**
 Generate necessary code for stable hashing the type so it can be used with `USING hash` indexes.
 
+On Postgres 11 and later, the type also gets an extended, 64-bit hash function, which hash
+partitioning needs.
+
 ```rust,ignore
 # use pgx_pg_sys as pg_sys;
 use pgx::*;
@@ -986,6 +1723,11 @@ enum DogNames {
 ```
 Optionally accepts the following attributes:
 
+* `#[pgx(default_opclass = false)]`: Don't make the type's `hash` operator class its `DEFAULT` one.
+* `#[pgx(hash_family = "integer_ops")]`: Also add the type's `=` operator and hash functions to the
+  existing `hash` operator family `integer_ops`, with `ALTER OPERATOR FAMILY .. ADD`.  Values
+  which are equal across the family's types must hash the same, as Postgres' own do.  It can be
+  given more than once.
 * `sql`: Same arguments as [`#[pgx(sql = ..)]`](macro@pgx).
 */
 #[proc_macro_derive(PostgresHash, attributes(pgx))]
@@ -996,10 +1738,57 @@ pub fn postgres_hash(input: TokenStream) -> TokenStream {
         .into()
 }
 
+/**
+Generate the functions and `CREATE TEXT SEARCH TEMPLATE` for a type implementing
+`pgx::TextSearchDictionary`.
+
+The template is named after the type, in lowercase.
+
+```rust,ignore
+use pgx::*;
+
+#[derive(PostgresTextSearchDictionary)]
+struct Lowercase;
+
+impl TextSearchDictionary for Lowercase {
+    fn init(_options: Vec<(String, String)>) -> Self {
+        Lowercase
+    }
+
+    fn lexize(&self, token: &str) -> Option<Vec<String>> {
+        Some(vec![token.to_lowercase()])
+    }
+}
+```
+*/
+#[proc_macro_derive(PostgresTextSearchDictionary)]
+pub fn postgres_text_search_dictionary(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_postgres_text_search_dictionary(ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+Generate the functions and `CREATE TEXT SEARCH PARSER` for a type implementing
+`pgx::TextSearchParser`.
+
+The parser is named after the type, in lowercase.
+*/
+#[proc_macro_derive(PostgresTextSearchParser)]
+pub fn postgres_text_search_parser(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_postgres_text_search_parser(ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 /**
 Declare a `pgx::Aggregate` implentation on a type as able to used by Postgres as an aggregate.
 
 Functions inside the `impl` may use the [`#[pgx]`](macro@pgx) attribute.
+
+`#[cfg(..)]` attributes of the `impl` apply to the functions generated for the aggregate, too.
 */
 #[proc_macro_attribute]
 pub fn pg_aggregate(_attr: TokenStream, item: TokenStream) -> TokenStream {
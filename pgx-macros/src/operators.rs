@@ -31,6 +31,11 @@ pub(crate) fn impl_postgres_ord(ast: DeriveInput) -> syn::Result<proc_macro2::To
     stream.extend(le(&ast.ident));
     stream.extend(ge(&ast.ident));
     stream.extend(cmp(&ast.ident));
+    let abbreviated_keys = ast
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("abbreviated_keys"));
+    stream.extend(sortsupport(&ast.ident, abbreviated_keys));
 
     let sql_graph_entity_item = PostgresOrd::from_derive_input(ast)?;
     sql_graph_entity_item.to_tokens(&mut stream);
@@ -42,6 +47,7 @@ pub(crate) fn impl_postgres_hash(ast: DeriveInput) -> syn::Result<proc_macro2::T
     let mut stream = proc_macro2::TokenStream::new();
 
     stream.extend(hash(&ast.ident));
+    stream.extend(hash_extended(&ast.ident));
 
     let sql_graph_entity_item = PostgresHash::from_derive_input(ast)?;
     sql_graph_entity_item.to_tokens(&mut stream);
@@ -9,7 +9,7 @@ Use of this source code is governed by the MIT license that can be found in the
 
 extern crate proc_macro;
 
-use pgx_utils::{categorize_return_type, sql_entity_graph::PgExtern, CategorizedType};
+use pgx_utils::{categorize_return_type, cfg_attrs, sql_entity_graph::PgExtern, CategorizedType};
 use proc_macro2::{Ident, Span};
 use quote::{quote, quote_spanned, ToTokens};
 use std::ops::Deref;
@@ -75,6 +75,7 @@ impl PgGuardRewriter {
         let func_name = &func.sig.ident;
         let func_span = func.span();
         let rewritten_args = self.rewrite_args(func.clone(), is_raw);
+        let null_check = self.rewrite_null_check(func.clone(), is_raw);
         let rewritten_return_type = self.rewrite_return_type(func.clone());
         let generics = &func.sig.generics;
         let func_name_wrapper = Ident::new(
@@ -99,9 +100,11 @@ impl PgGuardRewriter {
             };
         };
 
+        let cfg_attrs = cfg_attrs(&func.attrs);
         let prolog = quote! {
             #func
 
+            #(#cfg_attrs)*
             #[doc(hidden)]
             #[allow(unused_variables)]
         };
@@ -114,6 +117,7 @@ impl PgGuardRewriter {
                     func_name_wrapper,
                     generics,
                     func_call,
+                    null_check,
                     rewritten_return_type,
                     entity_submission,
                     no_guard,
@@ -135,6 +139,7 @@ impl PgGuardRewriter {
                     func_name_wrapper,
                     generics,
                     func_call,
+                    null_check.clone(),
                     entity_submission,
                     false,
                 ),
@@ -150,6 +155,7 @@ impl PgGuardRewriter {
                     func_name_wrapper,
                     generics,
                     func_call,
+                    null_check.clone(),
                     entity_submission,
                     true,
                 ),
@@ -165,6 +171,7 @@ impl PgGuardRewriter {
                     func_name_wrapper,
                     generics,
                     func_call,
+                    null_check.clone(),
                     entity_submission,
                     false,
                 ),
@@ -180,6 +187,7 @@ impl PgGuardRewriter {
                     func_name_wrapper,
                     generics,
                     func_call,
+                    null_check.clone(),
                     entity_submission,
                     true,
                 ),
@@ -188,6 +196,19 @@ impl PgGuardRewriter {
         }
     }
 
+    /// Run the function's SPI statements read-only if it's `immutable` or `stable`, as they must
+    /// see the snapshot of the query calling it, and read-write otherwise, including when it's
+    /// called from one that isn't
+    fn spi_mode_scope(entity_submission: Option<&PgExtern>) -> proc_macro2::TokenStream {
+        let mode = match entity_submission {
+            Some(entity) if !entity.is_volatile() => quote! { pgx::SpiMode::ReadOnly },
+            _ => quote! { pgx::SpiMode::ReadWrite },
+        };
+        quote! {
+            let _spi_mode = pgx::SpiModeScope::enter(#mode);
+        }
+    }
+
     fn impl_standard_udf(
         func_span: Span,
         prolog: proc_macro2::TokenStream,
@@ -195,6 +216,7 @@ impl PgGuardRewriter {
         func_name_wrapper: Ident,
         generics: &Generics,
         func_call: proc_macro2::TokenStream,
+        null_check: Option<proc_macro2::TokenStream>,
         rewritten_return_type: proc_macro2::TokenStream,
         sql_graph_entity_submission: Option<&PgExtern>,
         no_guard: bool,
@@ -204,6 +226,14 @@ impl PgGuardRewriter {
         } else {
             quote! {#[pg_guard]}
         };
+        let null_check = null_check.map(|is_null| {
+            quote! {
+                if #is_null {
+                    return pgx::pg_return_null(fcinfo);
+                }
+            }
+        });
+        let spi_mode = PgGuardRewriter::spi_mode_scope(sql_graph_entity_submission);
         let sql_graph_entity_submission = sql_graph_entity_submission.cloned().into_iter();
         quote_spanned! {func_span=>
             #prolog
@@ -211,6 +241,9 @@ impl PgGuardRewriter {
             #[allow(clippy::redundant_closure)]
             #guard
             #vis unsafe extern "C" fn #func_name_wrapper #generics(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+                #spi_mode
+
+                #null_check
 
                 #func_call
 
@@ -246,8 +279,10 @@ impl PgGuardRewriter {
         // This is because we quietely rewrite the function signature to `Iterator<Item = T>` and
         // rely on #[pg_extern] being called again during compilation.  It is important that we
         // include the original #[pg_extern(<attributes>)] in the generated code.
+        let func_attrs = func.attrs;
         quote_spanned! {func_span=>
             #[pg_extern(#attrs)]
+            #(#func_attrs)*
             #sig -> #return_type {
                 Some(#body).into_iter()
             }
@@ -262,6 +297,7 @@ impl PgGuardRewriter {
         func_name_wrapper: Ident,
         generics: &Generics,
         func_call: proc_macro2::TokenStream,
+        null_check: Option<proc_macro2::TokenStream>,
         sql_graph_entity_submission: Option<&PgExtern>,
         optional: bool,
     ) -> proc_macro2::TokenStream {
@@ -269,6 +305,16 @@ impl PgGuardRewriter {
         let mut generic_type = syn::parse2::<syn::Type>(generic_type).unwrap();
         pgx_utils::anonymonize_lifetimes(&mut generic_type);
 
+        // a NULL required argument means an empty set, as it would for a STRICT function
+        let null_check = null_check.map(|is_null| {
+            quote! {
+                if #is_null {
+                    pgx::srf_return_done(fcinfo, &mut funcctx);
+                    return pgx::pg_return_null(fcinfo);
+                }
+            }
+        });
+
         let result_handler = if optional {
             quote! {
                 let result = match pgx::PgMemoryContexts::For(funcctx.multi_call_memory_ctx).switch_to(|_| { #func_call result }) {
@@ -285,12 +331,14 @@ impl PgGuardRewriter {
             }
         };
 
+        let spi_mode = PgGuardRewriter::spi_mode_scope(sql_graph_entity_submission);
         let sql_graph_entity_submission = sql_graph_entity_submission.cloned().into_iter();
 
         quote_spanned! {func_span=>
             #prolog
             #[pg_guard]
             #vis unsafe extern "C" fn #func_name_wrapper #generics(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+                #spi_mode
 
                 struct IteratorHolder<T> {
                     iter: *mut dyn Iterator<Item=T>,
@@ -301,6 +349,9 @@ impl PgGuardRewriter {
 
                 if srf_is_first_call(fcinfo) {
                     funcctx = pgx::srf_first_call_init(fcinfo);
+
+                    #null_check
+
                     funcctx.user_fctx = pgx::PgMemoryContexts::For(funcctx.multi_call_memory_ctx).palloc_struct::<IteratorHolder<#generic_type>>() as void_mut_ptr;
 
                     iterator_holder = pgx::PgBox::from_pg(funcctx.user_fctx as *mut IteratorHolder<#generic_type>);
@@ -349,6 +400,7 @@ impl PgGuardRewriter {
         func_name_wrapper: Ident,
         generics: &Generics,
         func_call: proc_macro2::TokenStream,
+        null_check: Option<proc_macro2::TokenStream>,
         entity_submission: Option<&PgExtern>,
         optional: bool,
     ) -> proc_macro2::TokenStream {
@@ -376,6 +428,16 @@ impl PgGuardRewriter {
         let mut generic_type = syn::parse2::<syn::Type>(generic_type).unwrap();
         pgx_utils::anonymonize_lifetimes(&mut generic_type);
 
+        // a NULL required argument means an empty set, as it would for a STRICT function
+        let null_check = null_check.map(|is_null| {
+            quote! {
+                if #is_null {
+                    pgx::srf_return_done(fcinfo, &mut funcctx);
+                    return pgx::pg_return_null(fcinfo);
+                }
+            }
+        });
+
         let result_handler = if optional {
             quote! {
                 let result = match pgx::PgMemoryContexts::For(funcctx.multi_call_memory_ctx).switch_to(|_| { #func_call result }) {
@@ -391,12 +453,14 @@ impl PgGuardRewriter {
                 let result = pgx::PgMemoryContexts::For(funcctx.multi_call_memory_ctx).switch_to(|_| { #func_call result });
             }
         };
+        let spi_mode = PgGuardRewriter::spi_mode_scope(entity_submission);
         let sql_graph_entity_submission = entity_submission.cloned().into_iter();
 
         quote_spanned! {func_span=>
             #prolog
             #[pg_guard]
             #vis unsafe extern "C" fn #func_name_wrapper #generics(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+                #spi_mode
 
                 struct IteratorHolder<T> {
                     iter: *mut dyn Iterator<Item=T>,
@@ -407,6 +471,9 @@ impl PgGuardRewriter {
 
                 if srf_is_first_call(fcinfo) {
                     funcctx = pgx::srf_first_call_init(fcinfo);
+
+                    #null_check
+
                     funcctx.user_fctx = pgx::PgMemoryContexts::For(funcctx.multi_call_memory_ctx).palloc_struct::<IteratorHolder<#generic_type>>() as void_mut_ptr;
                     funcctx.tuple_desc = pgx::PgMemoryContexts::For(funcctx.multi_call_memory_ctx).switch_to(|_| {
                         let mut tupdesc: *mut pgx::pg_sys::TupleDescData = std::ptr::null_mut();
@@ -543,6 +610,10 @@ impl PgGuardRewriter {
             #[allow(clippy::redundant_closure)]
             #[allow(improper_ctypes_definitions)] /* for i128 */
             pub unsafe fn #func_name ( #arg_list_with_types ) #return_type {
+                if crate::submodules::guard::postgres_calls_forbidden() {
+                    panic!("functions under #[pg_guard] cannot be called from a thread that forbids calling into Postgres");
+                }
+
                 // as the panic message says, we can't call Postgres functions from threads
                 // the value of IS_MAIN_THREAD gets set through the pg_module_magic!() macro
                 #[cfg(debug_assertions)]
@@ -662,6 +733,17 @@ impl PgGuardRewriter {
         }
     }
 
+    /// The condition under which the function is called with a NULL for one of its required,
+    /// non-`Option<T>`, arguments, if it can be at all
+    pub fn rewrite_null_check(
+        &self,
+        func: ItemFn,
+        is_raw: bool,
+    ) -> Option<proc_macro2::TokenStream> {
+        let fsr = FunctionSignatureRewriter::new(func);
+        fsr.null_check(is_raw)
+    }
+
     pub fn rewrite_return_type(&self, func: ItemFn) -> proc_macro2::TokenStream {
         let fsr = FunctionSignatureRewriter::new(func);
         let result = fsr.return_type();
@@ -741,6 +823,8 @@ impl FunctionSignatureRewriter {
                         let name = Ident::new(&format!("{}_", ident.ident), ident.span());
                         let mut type_ = ty.ty.clone();
                         let is_option = type_matches(&type_, "Option");
+                        let is_fcinfo_context = type_matches(&type_, "FcInfoContext")
+                            || type_matches(&type_, "pgx :: FcInfoContext");
 
                         let ts = if is_option {
                             let option_type = extract_option_type(&type_);
@@ -756,6 +840,10 @@ impl FunctionSignatureRewriter {
                             quote_spanned! {ident.span()=>
                                 let #name = #fcinfo_ident;
                             }
+                        } else if is_fcinfo_context {
+                            quote_spanned! {ident.span()=>
+                                let #name = pgx::FcInfoContext::from_fcinfo(#fcinfo_ident);
+                            }
                         } else if is_raw {
                             quote_spanned! {ident.span()=>
                                 let #name = pgx::pg_getarg_datum_raw(#fcinfo_ident, #i) as #type_;
@@ -769,7 +857,10 @@ impl FunctionSignatureRewriter {
 
                         stream.extend(ts);
 
-                        i += 1;
+                        // the context isn't a SQL argument, so the ones after it don't shift
+                        if !is_fcinfo_context {
+                            i += 1;
+                        }
                     }
                     _ => panic!(
                         "Unrecognized function arg type: {}",
@@ -782,6 +873,46 @@ impl FunctionSignatureRewriter {
         stream
     }
 
+    /// Functions with any `Option<T>` arguments aren't STRICT, so Postgres calls them with NULLs.
+    /// The required arguments among them are checked up front, and the function returns NULL if
+    /// any of those are, rather than being called at all.
+    fn null_check(&self, is_raw: bool) -> Option<proc_macro2::TokenStream> {
+        if is_raw {
+            return None;
+        }
+
+        let fcinfo_ident: syn::Ident = syn::parse_quote! { fcinfo };
+        let mut has_optional = false;
+        let mut required = Vec::new();
+        let mut i = 0usize;
+        for arg in &self.func.sig.inputs {
+            if let FnArg::Typed(ty) = arg {
+                let type_ = &ty.ty;
+                if type_matches(type_, "FcInfoContext")
+                    || type_matches(type_, "pgx :: FcInfoContext")
+                {
+                    continue;
+                }
+                if type_matches(type_, "Option") {
+                    has_optional = true;
+                } else if !(type_matches(type_, "pg_sys :: FunctionCallInfo")
+                    || type_matches(type_, "pgx :: pg_sys :: FunctionCallInfo")
+                    || type_matches(type_, "Internal")
+                    || type_matches(type_, "pgx :: Internal"))
+                {
+                    required.push(quote! { pgx::pg_arg_is_null(#fcinfo_ident, #i) });
+                }
+                i += 1;
+            }
+        }
+
+        if has_optional && !required.is_empty() {
+            Some(quote! { #(#required)||* })
+        } else {
+            None
+        }
+    }
+
     fn return_type_is_datum(&self) -> bool {
         match &self.func.sig.output {
             ReturnType::Default => false,
@@ -0,0 +1,113 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx_utils::sql_entity_graph::ExtensionSql;
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{DeriveInput, LitStr};
+
+pub(crate) fn impl_postgres_text_search_dictionary(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let type_name = &ast.ident;
+    let name = type_name.to_string().to_lowercase();
+    let init = function_ident(type_name, "init");
+    let lexize = function_ident(type_name, "lexize");
+
+    let mut stream = quote! {
+        #[pgx::pg_extern(immutable, parallel_safe)]
+        fn #init(options: pgx::Internal) -> pgx::Internal {
+            pgx::tsearch_dictionary_init::<#type_name>(options)
+        }
+
+        #[pgx::pg_extern(immutable, parallel_safe)]
+        fn #lexize(
+            dictionary: pgx::Internal,
+            token: pgx::Internal,
+            length: i32,
+            state: pgx::Internal,
+        ) -> pgx::Internal {
+            let _ = state;
+            pgx::tsearch_dictionary_lexize::<#type_name>(dictionary, token, length)
+        }
+    };
+
+    let sql = format!(
+        "CREATE TEXT SEARCH TEMPLATE {} (INIT = {}, LEXIZE = {});",
+        name, init, lexize
+    );
+    extension_sql(
+        &sql,
+        &format!("{}_text_search_template", name),
+        &[&init, &lexize],
+    )?
+    .to_tokens(&mut stream);
+
+    Ok(stream)
+}
+
+pub(crate) fn impl_postgres_text_search_parser(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let type_name = &ast.ident;
+    let name = type_name.to_string().to_lowercase();
+    let start = function_ident(type_name, "start");
+    let gettoken = function_ident(type_name, "gettoken");
+    let end = function_ident(type_name, "end");
+    let lextypes = function_ident(type_name, "lextypes");
+
+    let mut stream = quote! {
+        #[pgx::pg_extern(immutable, parallel_safe)]
+        fn #start(text: pgx::Internal, length: i32) -> pgx::Internal {
+            pgx::tsearch_parser_start::<#type_name>(text, length)
+        }
+
+        #[pgx::pg_extern(immutable, parallel_safe)]
+        fn #gettoken(state: pgx::Internal, token: pgx::Internal, length: pgx::Internal) -> i32 {
+            pgx::tsearch_parser_gettoken::<#type_name>(state, token, length)
+        }
+
+        #[pgx::pg_extern(immutable, parallel_safe)]
+        fn #end(state: pgx::Internal) {
+            // the state is dropped along with the memory context it was allocated in
+            let _ = state;
+        }
+
+        #[pgx::pg_extern(immutable, parallel_safe)]
+        fn #lextypes(unused: pgx::Internal) -> pgx::Internal {
+            let _ = unused;
+            pgx::tsearch_parser_lextypes::<#type_name>()
+        }
+    };
+
+    let sql = format!(
+        "CREATE TEXT SEARCH PARSER {} (START = {}, GETTOKEN = {}, END = {}, LEXTYPES = {});",
+        name, start, gettoken, end, lextypes
+    );
+    extension_sql(
+        &sql,
+        &format!("{}_text_search_parser", name),
+        &[&start, &gettoken, &end, &lextypes],
+    )?
+    .to_tokens(&mut stream);
+
+    Ok(stream)
+}
+
+fn function_ident(type_name: &Ident, suffix: &str) -> Ident {
+    Ident::new(
+        &format!("{}_{}", type_name, suffix).to_lowercase(),
+        type_name.span(),
+    )
+}
+
+fn extension_sql(sql: &str, name: &str, requires: &[&Ident]) -> syn::Result<ExtensionSql> {
+    let sql = LitStr::new(sql, proc_macro2::Span::call_site());
+    let name = LitStr::new(name, proc_macro2::Span::call_site());
+    syn::parse2(quote! {
+        #sql,
+        name = #name,
+        requires = [#(#requires),*]
+    })
+}
@@ -15,9 +15,10 @@ use pgx_utils::pg_config::{PgConfig, PgConfigSelector, Pgx};
 use pgx_utils::prefix_path;
 use quote::quote;
 use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 use std::{
     collections::{HashMap, HashSet},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, Output},
 };
 use syn::Item;
@@ -67,6 +68,11 @@ fn main() -> color_eyre::Result<()> {
     }
 
     println!("cargo:rerun-if-env-changed=PGX_PG_SYS_GENERATE_BINDINGS_FOR_RELEASE");
+    println!("cargo:rerun-if-env-changed=PGX_PG_CONFIG_PATH");
+    println!("cargo:rerun-if-env-changed=PGX_PG_SYS_EXTRA_HEADERS");
+    println!("cargo:rerun-if-env-changed=PGX_PG_SYS_BLOCKLIST");
+    println!("cargo:rerun-if-env-changed=PGX_PG_SYS_ALLOWLIST");
+    println!("cargo:rerun-if-env-changed=PGX_PG_SYS_BINDINGS_CACHE");
 
     let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
     let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
@@ -120,6 +126,16 @@ fn main() -> color_eyre::Result<()> {
         vec![specific]
     };
 
+    // the bindings for release are always generated from scratch
+    let bindings_cache = if std::env::var("PGX_PG_SYS_GENERATE_BINDINGS_FOR_RELEASE")
+        .unwrap_or("false".into())
+        == "1"
+    {
+        None
+    } else {
+        Pgx::bindings_cache_dir().ok()
+    };
+
     pg_configs
         .par_iter()
         .map(|pg_config| {
@@ -130,6 +146,28 @@ fn main() -> color_eyre::Result<()> {
             include_h.push("include");
             include_h.push(format!("pg{}.h", major_version));
 
+            let cached = match &bindings_cache {
+                Some(cache_dir) => {
+                    let key = bindings_cache_key(pg_config, &include_h).wrap_err_with(|| {
+                        format!(
+                            "unable to hash the inputs of the pg{} bindings",
+                            major_version
+                        )
+                    })?;
+                    let cached = cache_dir.join(format!("pg{}-{}", major_version, key));
+                    if restore_cached_bindings(&cached, &out_dir, major_version) {
+                        eprintln!(
+                            "Using cached bindings for pg{} from `{}`",
+                            major_version,
+                            cached.display()
+                        );
+                        return Ok(());
+                    }
+                    Some(cached)
+                }
+                None => None,
+            };
+
             let bindgen_output = run_bindgen(&pg_config, &include_h)
                 .wrap_err_with(|| format!("bindgen failed for pg{}", major_version))?;
 
@@ -176,6 +214,18 @@ fn main() -> color_eyre::Result<()> {
                     )
                 })?;
             }
+
+            if let Some(cached) = cached {
+                // a cache that can't be written to only means the next build generates them again
+                if let Err(e) = store_cached_bindings(&cached, &out_dir, major_version) {
+                    eprintln!(
+                        "Unable to cache bindings for pg{} in `{}`: {}",
+                        major_version,
+                        cached.display(),
+                        e
+                    );
+                }
+            }
             Ok(())
         })
         .collect::<eyre::Result<Vec<_>>>()?;
@@ -497,14 +547,60 @@ struct StructDescriptor<'a> {
     children: Vec<usize>,
 }
 
+/// Features of this crate that each add a group of Postgres headers to the bindings
+const OPT_IN_FEATURES: &[&str] = &["planner", "replication", "storage"];
+
 /// Given a specific postgres version, `run_bindgen` generates bindings for the given
 /// postgres version and returns them as a token stream.
 fn run_bindgen(pg_config: &PgConfig, include_h: &PathBuf) -> eyre::Result<syn::File> {
     let major_version = pg_config.major_version()?;
     eprintln!("Generating bindings for pg{}", major_version);
     let includedir_server = pg_config.includedir_server()?;
-    let bindings = bindgen::Builder::default()
-        .header(include_h.display().to_string())
+    let mut builder = bindgen::Builder::default().header(include_h.display().to_string());
+
+    // Postgres forks sometimes need their own headers to be bound, or have declarations in the
+    // standard ones that bindgen can't digest.  Both lists are whitespace-separated:  header
+    // paths are relative to `pg_config --includedir-server`, blocklist entries are bindgen
+    // item patterns
+    for header in env_list("PGX_PG_SYS_EXTRA_HEADERS") {
+        builder = builder.header(includedir_server.join(header).display().to_string());
+    }
+    for item in env_list("PGX_PG_SYS_BLOCKLIST") {
+        builder = builder.blocklist_item(item);
+    }
+
+    // most of the time spent compiling the bindings goes to the `#[pg_guard]` wrappers of its
+    // thousands of functions, so extensions which only call a few of them can ask for just those
+    // (as bindgen patterns), along with the ones pgx calls itself.  All the types and constants
+    // are still generated
+    let allowlist = env_list("PGX_PG_SYS_ALLOWLIST");
+    if !allowlist.is_empty() {
+        let pgx_functions = include_h.with_file_name("pgx_functions.txt");
+        let pgx_functions = std::fs::read_to_string(&pgx_functions)
+            .wrap_err_with(|| format!("unable to read `{}`", pgx_functions.display()))?;
+        let pgx_functions = pgx_functions
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from);
+        for function in allowlist.into_iter().chain(pgx_functions) {
+            builder = builder.allowlist_function(function);
+        }
+        builder = builder.allowlist_type(".*").allowlist_var(".*");
+    }
+
+    // the opt-in parts of the bindings, gated by `#ifdef`s at the bottom of include/pgXX.h.  The
+    // bindings committed for docs.rs get all of them
+    let generate_all =
+        std::env::var("PGX_PG_SYS_GENERATE_BINDINGS_FOR_RELEASE").unwrap_or("false".into()) == "1";
+    for feature in OPT_IN_FEATURES {
+        let feature = feature.to_uppercase();
+        if generate_all || std::env::var(format!("CARGO_FEATURE_{}", feature)).is_ok() {
+            builder = builder.clang_arg(format!("-DPGX_PG_SYS_{}", feature));
+        }
+    }
+
+    let bindings = builder
         .clang_arg(&format!("-I{}", includedir_server.display()))
         .parse_callbacks(Box::new(IgnoredMacros::default()))
         .blocklist_function("varsize_any") // pgx converts the VARSIZE_ANY macro, so we don't want to also have this function, which is in heaptuple.c
@@ -535,6 +631,95 @@ fn run_bindgen(pg_config: &PgConfig, include_h: &PathBuf) -> eyre::Result<syn::F
     syn::parse_file(bindings.to_string().as_str()).map_err(|e| From::from(e))
 }
 
+/// Generated bindings depend on the Postgres they're for, how it was configured (which
+/// `pg_config.h` records), pgx' own headers and function list, and the settings the bindings are
+/// generated with, so a change to any of them gets a new key
+fn bindings_cache_key(pg_config: &PgConfig, include_h: &PathBuf) -> eyre::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut hash = |part: &[u8]| {
+        // length-prefixed, so no two different lists of parts hash the same
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    };
+    // a git checkout of pgx can change how the bindings are rewritten without changing its version
+    hash(env!("CARGO_PKG_VERSION").as_bytes());
+    hash(include_str!("build.rs").as_bytes());
+    // as can the bindgen and libclang that generate them, and the target they're generated for
+    hash(bindgen_version().as_bytes());
+    hash(bindgen::clang_version().full.as_bytes());
+    hash(std::env::var("TARGET").unwrap_or_default().as_bytes());
+    hash(format!("{:?}", pg_config.path()).as_bytes());
+    hash(pg_config.version()?.as_bytes());
+    let includedir_server = pg_config.includedir_server()?;
+    hash(includedir_server.display().to_string().as_bytes());
+    hash(&std::fs::read(includedir_server.join("pg_config.h"))?);
+    hash(&std::fs::read(include_h)?);
+    hash(&std::fs::read(
+        include_h.with_file_name("pgx_functions.txt"),
+    )?);
+    for name in [
+        "PGX_PG_SYS_EXTRA_HEADERS",
+        "PGX_PG_SYS_BLOCKLIST",
+        "PGX_PG_SYS_ALLOWLIST",
+    ] {
+        hash(env_list(name).join(" ").as_bytes());
+    }
+    for feature in OPT_IN_FEATURES {
+        let feature = feature.to_uppercase();
+        let enabled = std::env::var_os(format!("CARGO_FEATURE_{}", feature)).is_some();
+        hash(&[enabled as u8]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The version of bindgen this build script was compiled with, which it only tells the bindings it
+/// writes, in their header comment
+fn bindgen_version() -> String {
+    bindgen::Builder::default()
+        .header_contents("empty.h", "")
+        .generate()
+        .map(|bindings| bindings.to_string())
+        .unwrap_or_default()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn bindings_files(major_version: u16) -> [String; 2] {
+    [
+        format!("pg{}.rs", major_version),
+        format!("pg{}_oids.rs", major_version),
+    ]
+}
+
+/// Copy the bindings cached in `cached` into `out_dir`, if they're all there
+fn restore_cached_bindings(cached: &Path, out_dir: &Path, major_version: u16) -> bool {
+    bindings_files(major_version)
+        .iter()
+        .all(|file| std::fs::copy(cached.join(file), out_dir.join(file)).is_ok())
+}
+
+fn store_cached_bindings(cached: &Path, out_dir: &Path, major_version: u16) -> std::io::Result<()> {
+    std::fs::create_dir_all(cached)?;
+    for file in bindings_files(major_version) {
+        // other builds may be reading the cache while this one writes to it, so each file only
+        // appears once it's complete
+        let partial = cached.join(format!("{}.{}.partial", file, std::process::id()));
+        std::fs::copy(out_dir.join(&file), &partial)?;
+        std::fs::rename(&partial, cached.join(&file))?;
+    }
+    Ok(())
+}
+
+fn env_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
 fn build_shim(shim_src: &PathBuf, shim_dst: &PathBuf, pg_config: &PgConfig) -> eyre::Result<()> {
     let major_version = pg_config.major_version()?;
     let mut libpgx_cshim: PathBuf = shim_dst.clone();
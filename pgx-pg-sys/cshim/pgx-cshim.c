@@ -49,6 +49,13 @@ void pgx_ereport(int level, int code, char *message, char *file, int lineno, int
                     errmsg("%s", message), errcontext_msg("%s:%d:%d", file, lineno, colno)));
 }
 
+PGDLLEXPORT void pgx_ereport_with_log_detail(int level, int code, char *message, char *log_detail, char *file, int lineno, int colno);
+void pgx_ereport_with_log_detail(int level, int code, char *message, char *log_detail, char *file, int lineno, int colno) {
+    ereport(level,
+            (errcode(code),
+                    errmsg("%s", message), errdetail_log("%s", log_detail), errcontext_msg("%s:%d:%d", file, lineno, colno)));
+}
+
 PGDLLEXPORT void pgx_SET_VARSIZE(struct varlena *ptr, int size);
 void pgx_SET_VARSIZE(struct varlena *ptr, int size) {
     SET_VARSIZE(ptr, size);
@@ -28,10 +28,16 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "catalog/index.h"
 #include "catalog/namespace.h"
 #include "catalog/objectaddress.h"
+#include "catalog/partition.h"
 #include "catalog/pg_class.h"
+#include "catalog/pg_collation.h"
 #include "catalog/pg_enum.h"
+#include "catalog/pg_extension.h"
+#include "catalog/pg_inherits.h"
+#include "catalog/pg_inherits_fn.h"
 #include "catalog/pg_operator.h"
 #include "catalog/pg_proc.h"
+#include "catalog/pg_sequence.h"
 #include "catalog/pg_trigger.h"
 #include "catalog/pg_type.h"
 #include "commands/comment.h"
@@ -39,8 +45,12 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "commands/defrem.h"
 #include "commands/event_trigger.h"
 #include "commands/explain.h"
+#include "commands/extension.h"
 #include "commands/proclang.h"
+#include "commands/seclabel.h"
+#include "commands/sequence.h"
 #include "commands/tablecmds.h"
+#include "commands/tablespace.h"
 #include "commands/trigger.h"
 #include "commands/vacuum.h"
 #include "executor/executor.h"
@@ -62,24 +72,17 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "nodes/replnodes.h"
 #include "nodes/tidbitmap.h"
 #include "nodes/value.h"
-#include "optimizer/clauses.h"
-#include "optimizer/cost.h"
-#include "optimizer/pathnode.h"
-#include "optimizer/paths.h"
-#include "optimizer/planmain.h"
-#include "optimizer/planner.h"
-#include "optimizer/restrictinfo.h"
-#include "optimizer/tlist.h"
+#include "parser/parse_coerce.h"
+#include "parser/parse_collate.h"
 #include "parser/parse_func.h"
 #include "parser/parse_type.h"
 #include "parser/parser.h"
 #include "parser/parsetree.h"
+#include "partitioning/partbounds.h"
 #include "postmaster/bgworker.h"
-#include "replication/output_plugin.h"
 #include "rewrite/rewriteHandler.h"
 #include "storage/block.h"
 #include "storage/bufmgr.h"
-#include "storage/buffile.h"
 #include "storage/ipc.h"
 #include "storage/itemptr.h"
 #include "storage/lwlock.h"
@@ -88,6 +91,7 @@ Use of this source code is governed by the MIT license that can be found in the
 #include "tcop/utility.h"
 #include "tsearch/ts_public.h"
 #include "tsearch/ts_utils.h"
+#include "utils/acl.h"
 #include "utils/builtins.h"
 #include "utils/date.h"
 #include "utils/datetime.h"
@@ -97,15 +101,45 @@ Use of this source code is governed by the MIT license that can be found in the
 #undef double
 
 #include "utils/guc.h"
+#include "utils/inval.h"
 #include "utils/json.h"
 #include "utils/jsonb.h"
 #include "utils/lsyscache.h"
 #include "utils/memutils.h"
 #include "utils/palloc.h"
+#include "utils/partcache.h"
+#include "utils/pg_locale.h"
+#include "utils/ps_status.h"
 #include "utils/rel.h"
 #include "utils/relcache.h"
 #include "utils/sampling.h"
-#include "utils/selfuncs.h"
 #include "utils/snapmgr.h"
 #include "utils/syscache.h"
+#include "utils/timeout.h"
 #include "utils/typcache.h"
+#include "utils/varlena.h"
+
+/* the opt-in parts of the bindings, defined by pgx-pg-sys' build.rs from its features */
+#ifdef PGX_PG_SYS_PLANNER
+#include "optimizer/clauses.h"
+#include "optimizer/cost.h"
+#include "optimizer/pathnode.h"
+#include "optimizer/paths.h"
+#include "optimizer/planmain.h"
+#include "optimizer/planner.h"
+#include "optimizer/restrictinfo.h"
+#include "optimizer/tlist.h"
+#include "utils/selfuncs.h"
+#endif
+#ifdef PGX_PG_SYS_REPLICATION
+#include "access/xlog_internal.h"
+#include "catalog/pg_replication_origin.h"
+#include "replication/logical.h"
+#include "replication/origin.h"
+#include "replication/output_plugin.h"
+#include "storage/lmgr.h"
+#endif
+#ifdef PGX_PG_SYS_STORAGE
+#include "storage/buffile.h"
+#include "storage/shm_mq.h"
+#endif
@@ -21,9 +21,11 @@ Use of this source code is governed by the MIT license that can be found in the
 #[cfg(
     any(
         // no features at all will cause problems
-        not(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14")),
+        not(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15")),
   ))]
-std::compile_error!("exactly one one feature must be provided (pg10, pg11, pg12, pg13, pg14)");
+std::compile_error!(
+    "exactly one one feature must be provided (pg10, pg11, pg12, pg13, pg14, pg15)"
+);
 
 pub mod submodules;
 
@@ -70,6 +72,14 @@ mod pg14 {
 #[cfg(all(feature = "pg14", docsrs))]
 mod pg14;
 
+#[cfg(all(feature = "pg15", not(docsrs)))]
+mod pg15 {
+    include!(concat!(env!("OUT_DIR"), "/pg15.rs"));
+}
+// there's no pg15 release to commit docs.rs bindings from yet
+#[cfg(all(feature = "pg15", docsrs))]
+std::compile_error!("there are no docs.rs bindings for pg15 yet, so document pgx-pg-sys with pg14");
+
 // export each module publicly
 #[cfg(feature = "pg10")]
 pub use pg10::*;
@@ -81,6 +91,8 @@ pub use pg12::*;
 pub use pg13::*;
 #[cfg(feature = "pg14")]
 pub use pg14::*;
+#[cfg(feature = "pg15")]
+pub use pg15::*;
 
 // feature gate each pg-specific oid module
 #[cfg(all(feature = "pg10", not(docsrs)))]
@@ -95,7 +107,7 @@ mod pg11_oids {
     include!(concat!(env!("OUT_DIR"), "/pg11_oids.rs"));
 }
 #[cfg(all(feature = "pg11", docsrs))]
-mod pg11;
+mod pg11_oids;
 
 #[cfg(all(feature = "pg12", not(docsrs)))]
 mod pg12_oids {
@@ -118,6 +130,11 @@ mod pg14_oids {
 #[cfg(all(feature = "pg14", docsrs))]
 mod pg14_oids;
 
+#[cfg(all(feature = "pg15", not(docsrs)))]
+mod pg15_oids {
+    include!(concat!(env!("OUT_DIR"), "/pg15_oids.rs"));
+}
+
 // export that module publicly
 #[cfg(feature = "pg10")]
 pub use pg10_oids::*;
@@ -129,6 +146,8 @@ pub use pg12_oids::*;
 pub use pg13_oids::*;
 #[cfg(feature = "pg14")]
 pub use pg14_oids::*;
+#[cfg(feature = "pg15")]
+pub use pg15_oids::*;
 
 // expose things we want available for all versions
 pub use all_versions::*;
@@ -159,6 +178,9 @@ pub use internal::pg13::*;
 #[cfg(feature = "pg14")]
 pub use internal::pg14::*;
 
+#[cfg(feature = "pg15")]
+pub use internal::pg15::*;
+
 /// A trait applied to all of Postgres' `pg_sys::Node` types and its subtypes
 pub trait PgNode {
     type NodeType;
@@ -685,4 +707,40 @@ mod internal {
             );
         }
     }
+
+    #[cfg(feature = "pg15")]
+    pub(crate) mod pg15 {
+        pub use crate::pg15::AllocSetContextCreateInternal as AllocSetContextCreateExtended;
+
+        pub const QTW_EXAMINE_RTES: u32 = crate::pg15::QTW_EXAMINE_RTES_BEFORE;
+
+        /// # Safety
+        ///
+        /// This function wraps Postgres' internal `IndexBuildHeapScan` method, and therefore, is
+        /// inherently unsafe
+        pub unsafe fn IndexBuildHeapScan<T>(
+            heap_relation: crate::Relation,
+            index_relation: crate::Relation,
+            index_info: *mut crate::IndexInfo,
+            build_callback: crate::IndexBuildCallback,
+            build_callback_state: *mut T,
+        ) {
+            let heap_relation_ref = heap_relation.as_ref().unwrap();
+            let table_am = heap_relation_ref.rd_tableam.as_ref().unwrap();
+
+            table_am.index_build_range_scan.unwrap()(
+                heap_relation,
+                index_relation,
+                index_info,
+                true,
+                false,
+                true,
+                0,
+                crate::InvalidBlockNumber,
+                build_callback,
+                build_callback_state as *mut std::os::raw::c_void,
+                std::ptr::null_mut(),
+            );
+        }
+    }
 }
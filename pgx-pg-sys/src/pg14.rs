@@ -16198,19 +16198,70 @@ extern "C" {
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct PartitionBoundInfoData {
-    _unused: [u8; 0],
+    pub strategy: ::std::os::raw::c_char,
+    pub ndatums: ::std::os::raw::c_int,
+    pub datums: *mut *mut Datum,
+    pub kind: *mut *mut PartitionRangeDatumKind,
+    pub nindexes: ::std::os::raw::c_int,
+    pub indexes: *mut ::std::os::raw::c_int,
+    pub null_index: ::std::os::raw::c_int,
+    pub default_index: ::std::os::raw::c_int,
+}
+impl Default for PartitionBoundInfoData {
+    fn default() -> Self {
+        let mut s = ::std::mem::MaybeUninit::<Self>::uninit();
+        unsafe {
+            ::std::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
+            s.assume_init()
+        }
+    }
 }
 pub type PartitionBoundInfo = *mut PartitionBoundInfoData;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct PartitionKeyData {
-    _unused: [u8; 0],
+    pub strategy: ::std::os::raw::c_char,
+    pub partnatts: int16,
+    pub partattrs: *mut AttrNumber,
+    pub partexprs: *mut List,
+    pub partopfamily: *mut Oid,
+    pub partopcintype: *mut Oid,
+    pub partsupfunc: *mut FmgrInfo,
+    pub partcollation: *mut Oid,
+    pub parttypid: *mut Oid,
+    pub parttypmod: *mut int32,
+    pub parttyplen: *mut int16,
+    pub parttypbyval: *mut bool,
+    pub parttypalign: *mut ::std::os::raw::c_char,
+    pub parttypcoll: *mut Oid,
+}
+impl Default for PartitionKeyData {
+    fn default() -> Self {
+        let mut s = ::std::mem::MaybeUninit::<Self>::uninit();
+        unsafe {
+            ::std::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
+            s.assume_init()
+        }
+    }
 }
 pub type PartitionKey = *mut PartitionKeyData;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct PartitionDescData {
-    _unused: [u8; 0],
+    pub nparts: ::std::os::raw::c_int,
+    pub detached_exist: bool,
+    pub oids: *mut Oid,
+    pub is_leaf: *mut bool,
+    pub boundinfo: PartitionBoundInfo,
+}
+impl Default for PartitionDescData {
+    fn default() -> Self {
+        let mut s = ::std::mem::MaybeUninit::<Self>::uninit();
+        unsafe {
+            ::std::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
+            s.assume_init()
+        }
+    }
 }
 pub type PartitionDesc = *mut PartitionDescData;
 #[repr(C)]
@@ -45622,6 +45673,64 @@ extern "C" {
         targets_contain_srfs: *mut *mut List,
     );
 }
+pub type TYPCATEGORY = ::std::os::raw::c_char;
+pub const CoercionPathType_COERCION_PATH_NONE: CoercionPathType = 0;
+pub const CoercionPathType_COERCION_PATH_FUNC: CoercionPathType = 1;
+pub const CoercionPathType_COERCION_PATH_RELABELTYPE: CoercionPathType = 2;
+pub const CoercionPathType_COERCION_PATH_ARRAYCOERCE: CoercionPathType = 3;
+pub const CoercionPathType_COERCION_PATH_COERCEVIAIO: CoercionPathType = 4;
+pub type CoercionPathType = ::std::os::raw::c_uint;
+#[pg_guard]
+extern "C" {
+    pub fn IsBinaryCoercible(srctype: Oid, targettype: Oid) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn IsPreferredType(category: TYPCATEGORY, type_: Oid) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn TypeCategory(type_: Oid) -> TYPCATEGORY;
+}
+#[pg_guard]
+extern "C" {
+    pub fn coerce_to_target_type(
+        pstate: *mut ParseState,
+        expr: *mut Node,
+        exprtype: Oid,
+        targettype: Oid,
+        targettypmod: int32,
+        ccontext: CoercionContext,
+        cformat: CoercionForm,
+        location: ::std::os::raw::c_int,
+    ) -> *mut Node;
+}
+#[pg_guard]
+extern "C" {
+    pub fn can_coerce_type(
+        nargs: ::std::os::raw::c_int,
+        input_typeids: *const Oid,
+        target_typeids: *const Oid,
+        ccontext: CoercionContext,
+    ) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn find_coercion_pathway(
+        targetTypeId: Oid,
+        sourceTypeId: Oid,
+        ccontext: CoercionContext,
+        funcid: *mut Oid,
+    ) -> CoercionPathType;
+}
+#[pg_guard]
+extern "C" {
+    pub fn find_typmod_coercion_function(typeId: Oid, funcid: *mut Oid) -> CoercionPathType;
+}
+#[pg_guard]
+extern "C" {
+    pub fn assign_expr_collations(pstate: *mut ParseState, expr: *mut Node);
+}
 pub const FuncDetailCode_FUNCDETAIL_NOTFOUND: FuncDetailCode = 0;
 pub const FuncDetailCode_FUNCDETAIL_MULTIPLE: FuncDetailCode = 1;
 pub const FuncDetailCode_FUNCDETAIL_NORMAL: FuncDetailCode = 2;
@@ -62485,6 +62594,518 @@ pub struct RowSecurityDesc {
 pub struct ResourceOwnerData {
     pub _address: u8,
 }
+pub const ReplicationOriginRelationId: u32 = 6000;
+pub const Anum_pg_replication_origin_roident: u32 = 1;
+pub const Anum_pg_replication_origin_roname: u32 = 2;
+pub const Natts_pg_replication_origin: u32 = 2;
+pub const XLOG_REPLORIGIN_SET: u32 = 0;
+pub const XLOG_REPLORIGIN_DROP: u32 = 16;
+pub const InvalidRepOriginId: u32 = 0;
+pub const DoNotReplicateId: u32 = 65535;
+#[pg_guard]
+extern "C" {
+    pub static mut replorigin_session_origin: RepOriginId;
+}
+#[pg_guard]
+extern "C" {
+    pub static mut replorigin_session_origin_lsn: XLogRecPtr;
+}
+#[pg_guard]
+extern "C" {
+    pub static mut replorigin_session_origin_timestamp: TimestampTz;
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_by_name(name: *const ::std::os::raw::c_char, missing_ok: bool)
+        -> RepOriginId;
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_create(name: *const ::std::os::raw::c_char) -> RepOriginId;
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_drop_by_name(
+        name: *const ::std::os::raw::c_char,
+        missing_ok: bool,
+        nowait: bool,
+    );
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_by_oid(
+        roident: RepOriginId,
+        missing_ok: bool,
+        roname: *mut *mut ::std::os::raw::c_char,
+    ) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_advance(
+        node: RepOriginId,
+        remote_commit: XLogRecPtr,
+        local_commit: XLogRecPtr,
+        go_backward: bool,
+        wal_log: bool,
+    );
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_get_progress(node: RepOriginId, flush: bool) -> XLogRecPtr;
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_session_advance(remote_commit: XLogRecPtr, local_commit: XLogRecPtr);
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_session_setup(node: RepOriginId);
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_session_reset();
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_session_get_progress(flush: bool) -> XLogRecPtr;
+}
+#[pg_guard]
+extern "C" {
+    pub fn CheckPointReplicationOrigin();
+}
+#[pg_guard]
+extern "C" {
+    pub fn StartupReplicationOrigin();
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_redo(record: *mut XLogReaderState);
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_desc(buf: StringInfo, record: *mut XLogReaderState);
+}
+#[pg_guard]
+extern "C" {
+    pub fn replorigin_identify(info: uint8) -> *const ::std::os::raw::c_char;
+}
+#[pg_guard]
+extern "C" {
+    pub fn ReplicationOriginShmemSize() -> Size;
+}
+#[pg_guard]
+extern "C" {
+    pub fn ReplicationOriginShmemInit();
+}
+#[pg_guard]
+extern "C" {
+    pub fn RelationInitLockInfo(relation: Relation);
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockRelationOid(relid: Oid, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn ConditionalLockRelationOid(relid: Oid, lockmode: LOCKMODE) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockRelationId(relid: *mut LockRelId, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockRelationOid(relid: Oid, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockRelation(relation: Relation, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn ConditionalLockRelation(relation: Relation, lockmode: LOCKMODE) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockRelation(relation: Relation, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn CheckRelationLockedByMe(
+        relation: Relation,
+        lockmode: LOCKMODE,
+        orstronger: bool,
+    ) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn CheckRelationOidLockedByMe(relid: Oid, lockmode: LOCKMODE, orstronger: bool) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockHasWaitersRelation(relation: Relation, lockmode: LOCKMODE) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockRelationIdForSession(relid: *mut LockRelId, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockRelationIdForSession(relid: *mut LockRelId, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockRelationForExtension(relation: Relation, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockRelationForExtension(relation: Relation, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn ConditionalLockRelationForExtension(relation: Relation, lockmode: LOCKMODE) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn RelationExtensionLockWaiterCount(relation: Relation) -> ::std::os::raw::c_int;
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockDatabaseFrozenIds(lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockPage(relation: Relation, blkno: BlockNumber, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn ConditionalLockPage(relation: Relation, blkno: BlockNumber, lockmode: LOCKMODE) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockPage(relation: Relation, blkno: BlockNumber, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockTuple(relation: Relation, tid: ItemPointer, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn ConditionalLockTuple(relation: Relation, tid: ItemPointer, lockmode: LOCKMODE) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockTuple(relation: Relation, tid: ItemPointer, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn XactLockTableInsert(xid: TransactionId);
+}
+#[pg_guard]
+extern "C" {
+    pub fn XactLockTableDelete(xid: TransactionId);
+}
+#[pg_guard]
+extern "C" {
+    pub fn XactLockTableWait(xid: TransactionId, rel: Relation, ctid: ItemPointer, oper: XLTW_Oper);
+}
+#[pg_guard]
+extern "C" {
+    pub fn ConditionalXactLockTableWait(xid: TransactionId) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn WaitForLockers(heaplocktag: LOCKTAG, lockmode: LOCKMODE, progress: bool);
+}
+#[pg_guard]
+extern "C" {
+    pub fn WaitForLockersMultiple(locktags: *mut List, lockmode: LOCKMODE, progress: bool);
+}
+#[pg_guard]
+extern "C" {
+    pub fn SpeculativeInsertionLockAcquire(xid: TransactionId) -> uint32;
+}
+#[pg_guard]
+extern "C" {
+    pub fn SpeculativeInsertionLockRelease(xid: TransactionId);
+}
+#[pg_guard]
+extern "C" {
+    pub fn SpeculativeInsertionWait(xid: TransactionId, token: uint32);
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockDatabaseObject(classid: Oid, objid: Oid, objsubid: uint16, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn ConditionalLockDatabaseObject(
+        classid: Oid,
+        objid: Oid,
+        objsubid: uint16,
+        lockmode: LOCKMODE,
+    ) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockDatabaseObject(classid: Oid, objid: Oid, objsubid: uint16, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockSharedObject(classid: Oid, objid: Oid, objsubid: uint16, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockSharedObject(classid: Oid, objid: Oid, objsubid: uint16, lockmode: LOCKMODE);
+}
+#[pg_guard]
+extern "C" {
+    pub fn LockSharedObjectForSession(
+        classid: Oid,
+        objid: Oid,
+        objsubid: uint16,
+        lockmode: LOCKMODE,
+    );
+}
+#[pg_guard]
+extern "C" {
+    pub fn UnlockSharedObjectForSession(
+        classid: Oid,
+        objid: Oid,
+        objsubid: uint16,
+        lockmode: LOCKMODE,
+    );
+}
+#[pg_guard]
+extern "C" {
+    pub fn DescribeLockTag(buf: StringInfo, tag: *const LOCKTAG);
+}
+#[pg_guard]
+extern "C" {
+    pub fn GetLockNameFromTagType(locktag_type: uint16) -> *const ::std::os::raw::c_char;
+}
+pub type XLTW_Oper = ::std::os::raw::c_uint;
+pub const XLTW_Oper_XLTW_None: XLTW_Oper = 0;
+pub const XLTW_Oper_XLTW_Update: XLTW_Oper = 1;
+pub const XLTW_Oper_XLTW_Delete: XLTW_Oper = 2;
+pub const XLTW_Oper_XLTW_Lock: XLTW_Oper = 3;
+pub const XLTW_Oper_XLTW_LockUpdated: XLTW_Oper = 4;
+pub const XLTW_Oper_XLTW_InsertIndex: XLTW_Oper = 5;
+pub const XLTW_Oper_XLTW_InsertIndexUnique: XLTW_Oper = 6;
+pub const XLTW_Oper_XLTW_FetchUpdated: XLTW_Oper = 7;
+pub const XLTW_Oper_XLTW_RecheckExclusionConstr: XLTW_Oper = 8;
+pub type TimeoutId = ::std::os::raw::c_uint;
+pub const TimeoutId_STARTUP_PACKET_TIMEOUT: TimeoutId = 0;
+pub const TimeoutId_DEADLOCK_TIMEOUT: TimeoutId = 1;
+pub const TimeoutId_LOCK_TIMEOUT: TimeoutId = 2;
+pub const TimeoutId_STATEMENT_TIMEOUT: TimeoutId = 3;
+pub const TimeoutId_STANDBY_DEADLOCK_TIMEOUT: TimeoutId = 4;
+pub const TimeoutId_STANDBY_TIMEOUT: TimeoutId = 5;
+pub const TimeoutId_STANDBY_LOCK_TIMEOUT: TimeoutId = 6;
+pub const TimeoutId_IDLE_IN_TRANSACTION_SESSION_TIMEOUT: TimeoutId = 7;
+pub const TimeoutId_IDLE_SESSION_TIMEOUT: TimeoutId = 8;
+pub const TimeoutId_CLIENT_CONNECTION_CHECK_TIMEOUT: TimeoutId = 9;
+pub const TimeoutId_USER_TIMEOUT: TimeoutId = 10;
+pub const TimeoutId_MAX_TIMEOUTS: TimeoutId = 20;
+#[pg_guard]
+extern "C" {
+    pub fn enable_timeout_after(id: TimeoutId, delay_ms: ::std::os::raw::c_int);
+}
+#[pg_guard]
+extern "C" {
+    pub fn enable_timeout_at(id: TimeoutId, fin_time: TimestampTz);
+}
+#[pg_guard]
+extern "C" {
+    pub fn disable_timeout(id: TimeoutId, keep_indicator: bool);
+}
+#[pg_guard]
+extern "C" {
+    pub fn get_timeout_finish_time(id: TimeoutId) -> TimestampTz;
+}
+#[pg_guard]
+extern "C" {
+    pub fn get_timeout_active(id: TimeoutId) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn DefineSequence(pstate: *mut ParseState, stmt: *mut CreateSeqStmt) -> ObjectAddress;
+}
+pub type check_object_relabel_type = ::std::option::Option<
+    unsafe extern "C" fn(object: *const ObjectAddress, seclabel: *const ::std::os::raw::c_char),
+>;
+#[pg_guard]
+extern "C" {
+    pub fn register_label_provider(
+        provider: *const ::std::os::raw::c_char,
+        hook: check_object_relabel_type,
+    );
+}
+#[pg_guard]
+extern "C" {
+    pub fn GetSecurityLabel(
+        object: *const ObjectAddress,
+        provider: *const ::std::os::raw::c_char,
+    ) -> *mut ::std::os::raw::c_char;
+}
+pub type AclResult = ::std::os::raw::c_uint;
+pub const AclResult_ACLCHECK_OK: AclResult = 0;
+pub const AclResult_ACLCHECK_NO_PRIV: AclResult = 1;
+pub const AclResult_ACLCHECK_NOT_OWNER: AclResult = 2;
+#[pg_guard]
+extern "C" {
+    pub fn pg_class_aclcheck(table_oid: Oid, roleid: Oid, mode: AclMode) -> AclResult;
+}
+#[pg_guard]
+extern "C" {
+    pub fn pg_attribute_aclcheck(
+        table_oid: Oid,
+        attnum: AttrNumber,
+        roleid: Oid,
+        mode: AclMode,
+    ) -> AclResult;
+}
+#[pg_guard]
+extern "C" {
+    pub fn pg_namespace_aclcheck(nsp_oid: Oid, roleid: Oid, mode: AclMode) -> AclResult;
+}
+#[pg_guard]
+extern "C" {
+    pub fn pg_proc_aclcheck(proc_oid: Oid, roleid: Oid, mode: AclMode) -> AclResult;
+}
+#[pg_guard]
+extern "C" {
+    pub fn pg_database_aclcheck(db_oid: Oid, roleid: Oid, mode: AclMode) -> AclResult;
+}
+#[pg_guard]
+extern "C" {
+    pub fn pg_type_aclcheck(type_oid: Oid, roleid: Oid, mode: AclMode) -> AclResult;
+}
+#[pg_guard]
+extern "C" {
+    pub fn is_member_of_role(member: Oid, role: Oid) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn has_privs_of_role(member: Oid, role: Oid) -> bool;
+}
+#[pg_guard]
+extern "C" {
+    pub fn get_role_oid(rolename: *const ::std::os::raw::c_char, missing_ok: bool) -> Oid;
+}
+#[pg_guard]
+extern "C" {
+    pub fn find_inheritance_children(parentrelId: Oid, lockmode: LOCKMODE) -> *mut List;
+}
+#[pg_guard]
+extern "C" {
+    pub fn get_tablespace_name(spc_oid: Oid) -> *mut ::std::os::raw::c_char;
+}
+#[pg_guard]
+extern "C" {
+    pub fn find_all_inheritors(
+        parentrelId: Oid,
+        lockmode: LOCKMODE,
+        numparents: *mut *mut List,
+    ) -> *mut List;
+}
+#[pg_guard]
+extern "C" {
+    pub fn RelationGetPartitionQual(rel: Relation) -> *mut List;
+}
+#[pg_guard]
+extern "C" {
+    pub fn partition_list_bsearch(
+        partsupfunc: *mut FmgrInfo,
+        partcollation: *mut Oid,
+        boundinfo: PartitionBoundInfo,
+        value: Datum,
+        is_equal: *mut bool,
+    ) -> ::std::os::raw::c_int;
+}
+#[pg_guard]
+extern "C" {
+    pub fn partition_range_datum_bsearch(
+        partsupfunc: *mut FmgrInfo,
+        partcollation: *mut Oid,
+        boundinfo: PartitionBoundInfo,
+        nvalues: ::std::os::raw::c_int,
+        values: *mut Datum,
+        is_equal: *mut bool,
+    ) -> ::std::os::raw::c_int;
+}
+#[pg_guard]
+extern "C" {
+    pub fn RelationGetPartitionKey(rel: Relation) -> PartitionKey;
+}
+#[pg_guard]
+extern "C" {
+    pub fn RelationGetPartitionDesc(rel: Relation, omit_detached: bool) -> PartitionDesc;
+}
+pub const ExtensionRelationId: u32 = 3079;
+#[pg_guard]
+extern "C" {
+    pub static mut creating_extension: bool;
+}
+#[pg_guard]
+extern "C" {
+    pub static mut CurrentExtensionObject: Oid;
+}
+#[pg_guard]
+extern "C" {
+    pub fn get_extension_oid(extname: *const ::std::os::raw::c_char, missing_ok: bool) -> Oid;
+}
+pub type SyscacheCallbackFunction = ::std::option::Option<
+    unsafe extern "C" fn(arg: Datum, cacheid: ::std::os::raw::c_int, hashvalue: uint32),
+>;
+pub type RelcacheCallbackFunction =
+    ::std::option::Option<unsafe extern "C" fn(arg: Datum, relid: Oid)>;
+#[pg_guard]
+extern "C" {
+    pub fn CacheRegisterSyscacheCallback(
+        cacheid: ::std::os::raw::c_int,
+        func: SyscacheCallbackFunction,
+        arg: Datum,
+    );
+}
+#[pg_guard]
+extern "C" {
+    pub fn CacheRegisterRelcacheCallback(func: RelcacheCallbackFunction, arg: Datum);
+}
+#[pg_guard]
+extern "C" {
+    pub fn set_ps_display(activity: *const ::std::os::raw::c_char);
+}
+pub const DEFAULT_COLLATION_OID: u32 = 100;
+pub const C_COLLATION_OID: u32 = 950;
+pub const POSIX_COLLATION_OID: u32 = 951;
+#[pg_guard]
+extern "C" {
+    pub fn varstr_cmp(
+        arg1: *const ::std::os::raw::c_char,
+        len1: ::std::os::raw::c_int,
+        arg2: *const ::std::os::raw::c_char,
+        len2: ::std::os::raw::c_int,
+        collid: Oid,
+    ) -> ::std::os::raw::c_int;
+}
+#[pg_guard]
+extern "C" {
+    pub fn lc_collate_is_c(collation: Oid) -> bool;
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FormData_pg_sequence {
+    pub seqrelid: Oid,
+    pub seqtypid: Oid,
+    pub seqstart: int64,
+    pub seqincrement: int64,
+    pub seqmax: int64,
+    pub seqmin: int64,
+    pub seqcache: int64,
+    pub seqcycle: bool,
+}
+pub type Form_pg_sequence = *mut FormData_pg_sequence;
 impl pg_sys::PgNode for A_ArrayExpr {
     type NodeType = A_ArrayExpr;
 }
@@ -10,7 +10,9 @@ Use of this source code is governed by the MIT license that can be found in the
 #![allow(non_snake_case)]
 
 use crate::FlushErrorState;
+use once_cell::sync::OnceCell;
 use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
 use std::cell::Cell;
 use std::panic::catch_unwind;
 
@@ -24,7 +26,15 @@ extern "C" {
         lineno: i32,
         colno: i32,
     );
-
+    fn pgx_ereport_with_log_detail(
+        level: i32,
+        code: i32,
+        message: *const std::os::raw::c_char,
+        log_detail: *const std::os::raw::c_char,
+        file: *const std::os::raw::c_char,
+        lineno: i32,
+        colno: i32,
+    );
 }
 
 #[derive(Clone, Debug)]
@@ -53,6 +63,7 @@ struct PanicLocation {
     file: String,
     line: u32,
     col: u32,
+    backtrace: Option<String>,
 }
 
 thread_local! { static PANIC_LOCATION: Cell<Option<PanicLocation>> = Cell::new(None) }
@@ -66,15 +77,59 @@ fn take_panic_location() -> PanicLocation {
             file: "<unknown>".to_string(),
             line: 0,
             col: 0,
+            backtrace: None,
         },
     })
 }
 
+/// Whether panics capture a backtrace even when `RUST_BACKTRACE` doesn't say to
+static FORCE_BACKTRACES: OnceCell<Box<dyn Fn() -> bool + Send + Sync>> = OnceCell::new();
+
+/// Have the ERROR a Rust panic is converted to include the panic's backtrace whenever `enabled`
+/// returns true, such as while a GUC is on, as well as when `RUST_BACKTRACE` or
+/// `RUST_LIB_BACKTRACE` say to capture backtraces.  The backtrace is only written to the server
+/// log, as the ERROR's `DETAIL`, not sent to the client.
+///
+/// Only the first call has an effect, so it belongs in `_PG_init()`.
+pub fn set_panic_backtraces<F: Fn() -> bool + Send + Sync + 'static>(enabled: F) {
+    let _ = FORCE_BACKTRACES.set(Box::new(enabled));
+}
+
+#[allow(clippy::unnecessary_map_or)]
+fn capture_backtrace() -> Option<String> {
+    let force = FORCE_BACKTRACES.get().map_or(false, |enabled| enabled());
+    let backtrace = if force {
+        Backtrace::force_capture()
+    } else {
+        Backtrace::capture()
+    };
+    match backtrace.status() {
+        BacktraceStatus::Captured => Some(backtrace.to_string()),
+        _ => None,
+    }
+}
+
 // via pg_module_magic!() this gets set to Some(()) for the "main" thread, and remains at None
 // for all other threads.
 #[cfg(debug_assertions)]
 thread_local! { pub(crate) static IS_MAIN_THREAD: once_cell::sync::OnceCell<()> = once_cell::sync::OnceCell::new() }
 
+thread_local! { static POSTGRES_CALLS_FORBIDDEN: Cell<bool> = const { Cell::new(false) } }
+
+/// Make the `#[pg_guard]`ed Postgres functions panic, rather than run, when called from this
+/// thread, for threads which must never call into Postgres, like those of `pgx::compute_pool`.
+/// Unlike the check of the thread in debug builds, this is also checked in release builds.  It
+/// can't be undone
+pub fn forbid_postgres_calls() {
+    POSTGRES_CALLS_FORBIDDEN.with(|forbidden| forbidden.set(true))
+}
+
+/// Has [`forbid_postgres_calls()`] been called on this thread?
+#[inline]
+pub fn postgres_calls_forbidden() -> bool {
+    POSTGRES_CALLS_FORBIDDEN.with(|forbidden| forbidden.get())
+}
+
 pub fn register_pg_guard_panic_handler() {
     // first, lets ensure we're not calling ourselves twice
     #[cfg(debug_assertions)]
@@ -112,6 +167,12 @@ pub fn register_pg_guard_panic_handler() {
                         file: location.file().to_string(),
                         line: location.line(),
                         col: location.column(),
+                        // a Postgres ERROR being rethrown has no use for one
+                        backtrace: if info.payload().is::<JumpContext>() {
+                            None
+                        } else {
+                            capture_backtrace()
+                        },
                     }),
                     None => None,
                 }
@@ -265,14 +326,29 @@ where
             let c_file = std::ffi::CString::new(location.file).unwrap();
 
             unsafe {
-                pgx_ereport(
-                    crate::ERROR as i32,
-                    2600, // ERRCODE_INTERNAL_ERROR
-                    c_message.as_ptr(),
-                    c_file.as_ptr(),
-                    location.line as i32,
-                    location.col as i32,
-                );
+                match location.backtrace {
+                    Some(backtrace) => {
+                        let c_backtrace =
+                            std::ffi::CString::new(backtrace.replace('\0', "")).unwrap();
+                        pgx_ereport_with_log_detail(
+                            crate::ERROR as i32,
+                            2600, // ERRCODE_INTERNAL_ERROR
+                            c_message.as_ptr(),
+                            c_backtrace.as_ptr(),
+                            c_file.as_ptr(),
+                            location.line as i32,
+                            location.col as i32,
+                        );
+                    }
+                    None => pgx_ereport(
+                        crate::ERROR as i32,
+                        2600, // ERRCODE_INTERNAL_ERROR
+                        c_message.as_ptr(),
+                        c_file.as_ptr(),
+                        location.line as i32,
+                        location.col as i32,
+                    ),
+                }
             }
             unreachable!("ereport() failed at depth==0 with message: {}", message);
         }
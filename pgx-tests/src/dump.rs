@@ -0,0 +1,208 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Checks that what's made with an extension survives `pg_dump` and a restore, which a custom
+//! type whose output and input functions don't agree, or an object left out of its extension,
+//! silently doesn't: the dump is taken, but doesn't restore, or restores as something else.
+//!
+//! A `#[pg_test]`'s transaction is never committed, so `pg_dump` can't see what it does.  So it
+//! gives the SQL to populate a database with instead, which is run in a database of its own.
+use crate::framework::{get_extension_name, get_pg_config, get_pg_user};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The database [`check_dump_restore()`] populates and dumps
+pub const DUMP_DATABASE: &str = "pgx_tests_dump";
+
+/// The database [`check_dump_restore()`] restores the dump into
+pub const RESTORE_DATABASE: &str = "pgx_tests_restore";
+
+/// Lists the rows of every table, as text, so they're compared with the output functions of their
+/// columns' types
+const TABLES_QUERY: &str = "\
+    SELECT pg_catalog.format('%I.%I', n.nspname, c.relname) \
+    FROM pg_catalog.pg_class c JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace \
+    WHERE c.relkind IN ('r', 'p') \
+    AND n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg_toast%' \
+    ORDER BY 1";
+
+/// Lists the objects which are members of the extension
+const MEMBERS_QUERY: &str = "\
+    SELECT pg_catalog.pg_describe_object(d.classid, d.objid, d.objsubid) \
+    FROM pg_catalog.pg_depend d JOIN pg_catalog.pg_extension e ON e.oid = d.refobjid \
+    WHERE d.refclassid = 'pg_catalog.pg_extension'::pg_catalog.regclass AND d.deptype = 'e' \
+    AND e.extname = :'extname' \
+    ORDER BY 1";
+
+/// Create the extension in a new database, [`DUMP_DATABASE`], run `setup` there, dump it with
+/// `pg_dump`, and restore that into another new database, [`RESTORE_DATABASE`].  Then check the
+/// two have the same members of the extension, the same rows in every table, compared as text,
+/// and the same results of each of the `queries`.  Panics with what differs, or with the error of
+/// the step which failed, leaving the databases to look into.  Call it from a `#[pg_test]`:
+///
+/// ```rust,no_run
+/// # fn test_my_type_dumps() {
+/// pgx_tests::check_dump_restore(
+///     "CREATE TABLE things (thing my_type); INSERT INTO things VALUES ('a thing'), (NULL);",
+///     &["SELECT thing::text FROM things WHERE thing = 'a thing'"],
+/// );
+/// # }
+/// ```
+pub fn check_dump_restore(setup: &str, queries: &[&str]) {
+    if let Err(failure) = dump_restore(setup, queries) {
+        panic!("{}", failure);
+    }
+}
+
+/// [`check_dump_restore()`], returning what differs or failed rather than panicking
+pub fn dump_restore(setup: &str, queries: &[&str]) -> Result<(), String> {
+    let tools = Tools::new()?;
+    let extname = get_extension_name();
+
+    for dbname in [DUMP_DATABASE, RESTORE_DATABASE] {
+        tools.psql(
+            "postgres",
+            &format!("DROP DATABASE IF EXISTS {0}; CREATE DATABASE {0};", dbname),
+        )?;
+    }
+    tools.psql(
+        DUMP_DATABASE,
+        &format!("CREATE EXTENSION {} CASCADE;\n{}", extname, setup),
+    )?;
+
+    let dump = tools.dump_path()?;
+    tools.run(
+        "pg_dump",
+        Command::new(&tools.pg_dump)
+            .args(tools.connection(DUMP_DATABASE))
+            .arg("--format=custom")
+            .arg("--file")
+            .arg(&dump),
+    )?;
+    tools.run(
+        "pg_restore",
+        Command::new(&tools.pg_restore)
+            .args(tools.connection(RESTORE_DATABASE))
+            .arg("--exit-on-error")
+            .arg(&dump),
+    )?;
+
+    let members = format!("\\set extname {}\n{}", extname, MEMBERS_QUERY);
+    tools.compare("the extension's members", &members)?;
+    let tables = tools.psql(DUMP_DATABASE, TABLES_QUERY)?;
+    for table in tables.lines() {
+        tools.compare(
+            &format!("the rows of {}", table),
+            &format!("SELECT t::text FROM {} t ORDER BY 1", table),
+        )?;
+    }
+    for query in queries {
+        tools.compare(&format!("`{}`", query), query)?;
+    }
+
+    for dbname in [DUMP_DATABASE, RESTORE_DATABASE] {
+        tools.psql("postgres", &format!("DROP DATABASE {};", dbname))?;
+    }
+    std::fs::remove_file(&dump).ok();
+    Ok(())
+}
+
+/// The client programs of the test Postgres, and how to connect to it
+struct Tools {
+    psql: PathBuf,
+    pg_dump: PathBuf,
+    pg_restore: PathBuf,
+    port: u16,
+    user: String,
+}
+
+impl Tools {
+    fn new() -> Result<Self, String> {
+        let pg_config = get_pg_config();
+        let path = |path: eyre::Result<PathBuf>| path.map_err(|e| e.to_string());
+        Ok(Self {
+            psql: path(pg_config.psql_path())?,
+            pg_dump: path(pg_config.pg_dump_path())?,
+            pg_restore: path(pg_config.pg_restore_path())?,
+            port: pg_config.test_port().map_err(|e| e.to_string())?,
+            user: get_pg_user(),
+        })
+    }
+
+    fn connection(&self, dbname: &str) -> Vec<String> {
+        vec![
+            "--host=localhost".to_string(),
+            format!("--port={}", self.port),
+            format!("--username={}", self.user),
+            format!("--dbname={}", dbname),
+        ]
+    }
+
+    /// Run `sql` in `dbname`, stopping at the first error, for its rows, one a line.  It's read
+    /// from a file, so each statement is its own transaction, as `CREATE DATABASE` has to be, and
+    /// it can use `psql` variables
+    fn psql(&self, dbname: &str, sql: &str) -> Result<String, String> {
+        let file = self.dump_path()?.with_extension("sql");
+        std::fs::write(&file, sql).map_err(|e| e.to_string())?;
+        let rows = self.run(
+            "psql",
+            Command::new(&self.psql)
+                .args(self.connection(dbname))
+                .args(["--no-psqlrc", "--quiet", "--no-align", "--tuples-only"])
+                .args(["--set", "ON_ERROR_STOP=1"])
+                .arg("--file")
+                .arg(&file),
+        );
+        std::fs::remove_file(&file).ok();
+        rows
+    }
+
+    /// Check that `sql` has the same rows in the dumped and the restored database
+    fn compare(&self, what: &str, sql: &str) -> Result<(), String> {
+        let dumped = self.psql(DUMP_DATABASE, sql)?;
+        let restored = self.psql(RESTORE_DATABASE, sql)?;
+        if dumped != restored {
+            return Err(format!(
+                "{} changed when dumped and restored.\n\ndumped, in {}:\n{}\nrestored, in {}:\n{}",
+                what, DUMP_DATABASE, dumped, RESTORE_DATABASE, restored
+            ));
+        }
+        Ok(())
+    }
+
+    fn run(&self, program: &str, command: &mut Command) -> Result<String, String> {
+        let output = command
+            .env_remove("PGDATABASE")
+            .env_remove("PGHOST")
+            .env_remove("PGPORT")
+            .env_remove("PGUSER")
+            .output()
+            .map_err(|e| format!("failed to run {}: {}", program, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed: {:?}\n\n{}{}",
+                program,
+                command,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// The backend's working directory is the test Postgres' data directory, so the dump goes
+    /// beside it
+    fn dump_path(&self) -> Result<PathBuf, String> {
+        let dir = std::env::current_dir().map_err(|e| e.to_string())?;
+        Ok(dir
+            .parent()
+            .unwrap_or(&dir)
+            .join(format!("{}.dump", DUMP_DATABASE)))
+    }
+}
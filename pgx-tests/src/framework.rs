@@ -10,37 +10,74 @@ Use of this source code is governed by the MIT license that can be found in the
 use std::process::{Command, Stdio};
 
 use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use eyre::{eyre, WrapErr};
 use owo_colors::OwoColorize;
 use pgx::*;
 use pgx_utils::pg_config::{PgConfig, Pgx};
+use pgx_utils::sanitizer::Sanitizer;
 use pgx_utils::{createdb, get_named_capture, get_target_dir};
 use postgres::error::DbError;
 use postgres::Client;
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Write};
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::PathBuf;
 
-type LogLines = Arc<Mutex<HashMap<String, Vec<String>>>>;
+type LogLines = Arc<Mutex<ServerLog>>;
+
+/// Everything the test Postgres has logged, in order, with the sessions that logged it
+#[derive(Default)]
+struct ServerLog {
+    lines: Vec<(String, String)>,
+    /// The sessions `#[pg_test]`s run in
+    test_sessions: HashSet<String>,
+}
+
+impl ServerLog {
+    /// The lines logged since the `start`th, by the test's `session_id` or by sessions which
+    /// aren't another test's, like background workers' or the postmaster's
+    fn segment(&self, start: usize, session_id: &str) -> String {
+        let mut result = String::new();
+        for (line_session_id, line) in &self.lines[start.min(self.lines.len())..] {
+            if line_session_id == session_id || !self.test_sessions.contains(line_session_id) {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+        result
+    }
+}
 
 struct SetupState {
     installed: bool,
     loglines: LogLines,
-    system_session_id: String,
 }
 
 static TEST_MUTEX: Lazy<Mutex<SetupState>> = Lazy::new(|| {
     Mutex::new(SetupState {
         installed: false,
-        loglines: Arc::new(Mutex::new(HashMap::new())),
-        system_session_id: "NONE".to_string(),
+        loglines: Arc::new(Mutex::new(ServerLog::default())),
     })
 });
 static SHUTDOWN_HOOKS: Lazy<Mutex<Vec<Box<dyn Fn() + Send>>>> =
     Lazy::new(|| Mutex::new(Vec::new()));
 
+/// The seed of every `#[pg_test]` in this run, from `PGX_TEST_SEED` if it's set
+static TEST_SEED: Lazy<u32> = Lazy::new(|| match std::env::var("PGX_TEST_SEED") {
+    Ok(seed) => seed
+        .parse()
+        .unwrap_or_else(|_| panic!("PGX_TEST_SEED `{}` isn't a u32", seed)),
+    Err(_) => {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        now.subsec_nanos() ^ now.as_secs() as u32
+    }
+});
+static ANY_TEST_FAILED: AtomicBool = AtomicBool::new(false);
+
 fn register_shutdown_hook() {
     extern "C" fn run_shutdown_hooks() {
         for func in SHUTDOWN_HOOKS.lock().unwrap().iter() {
@@ -57,19 +94,62 @@ where
     SHUTDOWN_HOOKS.lock().unwrap().push(Box::new(func));
 }
 
+/// The seed `cargo pgx test` gave the running `#[pg_test]`, for it to seed any randomness of its
+/// own with, so that `PGX_TEST_SEED` reproduces it.  Postgres' `random()` is already seeded with it.
+pub fn test_seed() -> Option<u32> {
+    Spi::get_one::<String>("SELECT current_setting('pgx.test_seed', true)")
+        .and_then(|seed| seed.parse().ok())
+}
+
+/// Runs the `#[pg_test]` function `sql_funcname`, which is the `#[test]` at `test_path`
 pub fn run_test(
     sql_funcname: &str,
     expected_error: Option<&str>,
     postgresql_conf: Vec<&'static str>,
+    test_path: &str,
 ) -> eyre::Result<()> {
-    let (loglines, system_session_id) = initialize_test_framework(postgresql_conf)?;
+    let loglines = initialize_test_framework(postgresql_conf)?;
 
     let (mut client, session_id) = client();
+    let log_start = {
+        let mut loglines = loglines.lock().unwrap();
+        loglines.test_sessions.insert(session_id.clone());
+        loglines.lines.len()
+    };
 
     let schema = "tests"; // get_extension_schema();
+    let reproduction = Reproduction {
+        test_path,
+        schema,
+        sql_funcname,
+        session_id: &session_id,
+        seed: *TEST_SEED,
+        loglines: &loglines,
+        log_start,
+    };
+    let result = run_test_in_transaction(&mut client, schema, sql_funcname, expected_error);
+    if result.is_err() {
+        reproduction.report();
+    }
+    result
+}
+
+fn run_test_in_transaction(
+    client: &mut Client,
+    schema: &str,
+    sql_funcname: &str,
+    expected_error: Option<&str>,
+) -> eyre::Result<()> {
+    let seed = *TEST_SEED;
     let result = match client.transaction() {
         // run the test function in a transaction
         Ok(mut tx) => {
+            tx.simple_query(&format!(
+                "SET LOCAL pgx.test_seed = '{}'; SELECT setseed({});",
+                seed,
+                seed as f64 / u32::MAX as f64
+            ))
+            .expect("couldn't seed the test");
             let result = tx.simple_query(&format!("SELECT \"{}\".\"{}\"();", schema, sql_funcname));
 
             if result.is_ok() {
@@ -98,10 +178,6 @@ pub fn run_test(
                     Ok(())
                 } else {
                     // we weren't expecting an error
-                    //
-                    // wait a second for Postgres to get log messages written to stdoerr
-                    std::thread::sleep(std::time::Duration::from_millis(1000));
-
                     let mut pg_location = String::new();
                     pg_location.push_str("Postgres location: ");
                     if dberror.file().is_some() {
@@ -123,11 +199,9 @@ pub fn run_test(
                         rust_location.push_str("<unknown>");
                     }
 
-                    // then we can panic with those messages plus those that belong to the system
+                    // then we can panic with those messages, and the test's log is reported after
                     panic!(
-                        "\n{}...\n{}\n{}\n{}\n{}\n\n",
-                        format_loglines(&system_session_id, &loglines),
-                        format_loglines(&session_id, &loglines),
+                        "\n{}\n{}\n{}\n\n",
                         received_error_message.bold().red(),
                         pg_location.dimmed().white(),
                         rust_location.yellow()
@@ -147,26 +221,70 @@ pub fn run_test(
     }
 }
 
-fn format_loglines(session_id: &str, loglines: &LogLines) -> String {
-    let mut result = String::new();
+/// What's needed to reproduce a `#[pg_test]`, which is reported if it fails
+struct Reproduction<'a> {
+    test_path: &'a str,
+    schema: &'a str,
+    sql_funcname: &'a str,
+    session_id: &'a str,
+    seed: u32,
+    loglines: &'a LogLines,
+    /// How much Postgres had logged when the test started
+    log_start: usize,
+}
 
-    for line in loglines
-        .lock()
-        .unwrap()
-        .entry(session_id.to_string())
-        .or_default()
-        .iter()
-    {
-        result.push_str(line);
-        result.push('\n');
+impl<'a> Reproduction<'a> {
+    fn report(&self) {
+        ANY_TEST_FAILED.store(true, Ordering::SeqCst);
+
+        // wait a second for Postgres to get log messages written to stderr
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+        let log = self
+            .loglines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .segment(self.log_start, self.session_id);
+        eprintln!(
+            "{}\n{}",
+            "Postgres log during the test:".bold().yellow(),
+            log
+        );
+
+        // the path `cargo test` filters by doesn't have the crate's name
+        let test_path = self
+            .test_path
+            .split_once("::")
+            .map_or(self.test_path, |(_, path)| path);
+        eprintln!(
+            "{}\n    PGX_TEST_SEED={} cargo pgx test pg{} {} -- --exact\n{}",
+            "Reproduce with:".bold().yellow(),
+            self.seed,
+            pg_sys::get_pg_major_version_num(),
+            test_path,
+            format!(
+                "database: {}, function: \"{}\".\"{}\"(), session: {}, seed: {}",
+                get_pg_dbname(),
+                self.schema,
+                self.sql_funcname,
+                self.session_id,
+                self.seed,
+            )
+            .dimmed()
+            .white(),
+        );
     }
+}
 
-    result
+impl<'a> Drop for Reproduction<'a> {
+    fn drop(&mut self) {
+        // the test panicked rather than returned an error
+        if std::thread::panicking() {
+            self.report();
+        }
+    }
 }
 
-fn initialize_test_framework(
-    postgresql_conf: Vec<&'static str>,
-) -> eyre::Result<(LogLines, String)> {
+fn initialize_test_framework(postgresql_conf: Vec<&'static str>) -> eyre::Result<LogLines> {
     let mut state = TEST_MUTEX.lock().unwrap_or_else(|_| {
         // if we can't get the lock, that means it was poisoned,
         // so we just abruptly exit, which cuts down on test failure spam
@@ -177,22 +295,36 @@ fn initialize_test_framework(
         register_shutdown_hook();
 
         install_extension()?;
-        initdb(postgresql_conf)?;
-
-        let system_session_id = start_pg(state.loglines.clone())?;
+        // a running Postgres has already loaded what it preloads, and read its configuration
+        let preloads = postgresql_conf
+            .iter()
+            .any(|setting| setting.trim_start().starts_with("shared_preload_libraries"));
+        let conf_changed = initdb(postgresql_conf)?;
+
+        match running_pg()? {
+            Some(pid)
+                if reuse_server() && !preloads && !conf_changed && get_pg_log_path()?.exists() =>
+            {
+                eprintln!("reusing the test Postgres from the last run, pid={}", pid);
+                follow_pg_log(state.loglines.clone())?;
+            }
+            _ => {
+                stop_kept_pg()?;
+                start_pg(state.loglines.clone())?;
+            }
+        }
         let pg_config = get_pg_config();
         dropdb();
         createdb(&pg_config, get_pg_dbname(), true, false).expect("failed to create test database");
         create_extension();
 
         state.installed = true;
-        state.system_session_id = system_session_id;
     }
 
-    Ok((state.loglines.clone(), state.system_session_id.clone()))
+    Ok(state.loglines.clone())
 }
 
-fn get_pg_config() -> PgConfig {
+pub(crate) fn get_pg_config() -> PgConfig {
     let pgx = Pgx::from_config().expect("Unable to load pgx config");
     pgx.get(&format!("pg{}", pg_sys::get_pg_major_version_num()))
         .expect("not a valid postgres version")
@@ -302,7 +434,8 @@ fn install_extension() -> eyre::Result<()> {
     Ok(())
 }
 
-fn initdb(postgresql_conf: Vec<&'static str>) -> eyre::Result<()> {
+/// Returns whether the configuration is different from the last run's
+fn initdb(postgresql_conf: Vec<&'static str>) -> eyre::Result<bool> {
     let pg_config = get_pg_config();
     let pgdata = get_pgdata_path()?;
 
@@ -327,35 +460,32 @@ fn initdb(postgresql_conf: Vec<&'static str>) -> eyre::Result<()> {
     modify_postgresql_conf(pgdata, postgresql_conf)
 }
 
-fn modify_postgresql_conf(pgdata: PathBuf, postgresql_conf: Vec<&'static str>) -> eyre::Result<()> {
-    let mut postgresql_conf_file = std::fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(format!("{}/postgresql.auto.conf", pgdata.display()))
-        .wrap_err("couldn't open postgresql.auto.conf")?;
-    postgresql_conf_file
-        .write_all("log_line_prefix='[%m] [%p] [%c]: '\n".as_bytes())
-        .wrap_err("couldn't append log_line_prefix")?;
-
+fn modify_postgresql_conf(
+    pgdata: PathBuf,
+    postgresql_conf: Vec<&'static str>,
+) -> eyre::Result<bool> {
+    let path = format!("{}/postgresql.auto.conf", pgdata.display());
+    let mut contents = String::from("log_line_prefix='[%m] [%p] [%c]: '\n");
     for setting in postgresql_conf {
-        postgresql_conf_file
-            .write_all(format!("{}\n", setting).as_bytes())
-            .wrap_err("couldn't append custom setting to postgresql.conf")?;
+        contents.push_str(setting);
+        contents.push('\n');
+    }
+    // Windows is reached over TCP, as `cargo pgx start` leaves its sockets out there too
+    if cfg!(not(windows)) {
+        contents.push_str(&format!(
+            "unix_socket_directories = '{}'",
+            Pgx::home().unwrap().display()
+        ));
     }
 
-    postgresql_conf_file
-        .write_all(
-            format!(
-                "unix_socket_directories = '{}'",
-                Pgx::home().unwrap().display()
-            )
-            .as_bytes(),
-        )
-        .wrap_err("couldn't append `unix_socket_directories` setting to postgresql.conf")?;
-    Ok(())
+    if std::fs::read_to_string(&path).ok().as_deref() == Some(contents.as_str()) {
+        return Ok(false);
+    }
+    std::fs::write(&path, contents).wrap_err("couldn't write postgresql.auto.conf")?;
+    Ok(true)
 }
 
-fn start_pg(loglines: LogLines) -> eyre::Result<String> {
+fn start_pg(loglines: LogLines) -> eyre::Result<()> {
     let pg_config = get_pg_config();
     let mut command = Command::new(
         pg_config
@@ -377,21 +507,210 @@ fn start_pg(loglines: LogLines) -> eyre::Result<String> {
         .stdout(Stdio::inherit())
         .stderr(Stdio::piped());
 
+    // `cargo pgx test --sanitizer` installed an instrumented extension, which needs the
+    // sanitizer's runtime loaded before it
+    if let Some(sanitizer) = Sanitizer::from_env()? {
+        let mut suppressions = get_target_dir()?;
+        suppressions.push(format!("pgx-{}-sanitizer.supp", sanitizer));
+        std::fs::write(&suppressions, sanitizer.suppressions())
+            .wrap_err("couldn't write the sanitizer's suppressions")?;
+        command.envs(sanitizer.server_env(&suppressions)?);
+    }
+
+    if reuse_server() {
+        // it outlives this run, so it logs to a file rather than to us, and isn't in our process
+        // group, where a ^C would stop it too
+        command
+            .stdout(Stdio::null())
+            .stderr(std::fs::File::create(get_pg_log_path()?)?);
+        #[cfg(unix)]
+        unsafe {
+            std::os::unix::process::CommandExt::pre_exec(&mut command, || {
+                libc::setsid();
+                Ok(())
+            });
+        }
+        #[cfg(windows)]
+        {
+            const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+            std::os::windows::process::CommandExt::creation_flags(
+                &mut command,
+                CREATE_NEW_PROCESS_GROUP,
+            );
+        }
+    } else {
+        // so a later `--reuse-server` doesn't mistake this one for one it can reuse
+        let _ = std::fs::remove_file(get_pg_log_path()?);
+    }
+
     let command_str = format!("{:?}", command);
 
     // start Postgres and monitor its stderr in the background
     // also notify the main thread when it's ready to accept connections
-    let (pgpid, session_id) = monitor_pg(command, command_str, loglines);
+    let (pgpid, _system_session_id) = monitor_pg(command, command_str, loglines);
 
     // add a shutdown hook so we can terminate it when the test framework exits
     add_shutdown_hook(move || unsafe {
+        if reuse_server() {
+            let message_string = std::ffi::CString::new(
+                "Leaving Postgres running for the next `cargo pgx test --reuse-server`\n\n"
+                    .bold()
+                    .blue()
+                    .to_string(),
+            )
+            .unwrap();
+            libc::printf(message_string.as_ptr());
+            return;
+        }
+        if keep_on_failure() && ANY_TEST_FAILED.load(Ordering::SeqCst) {
+            let pg_config = get_pg_config();
+            let message_string = std::ffi::CString::new(
+                format!(
+                    "Leaving Postgres running for the failed tests, with its data directory {}.  \
+                     Connect with `psql -h {} -p {} {}`; the next `cargo pgx test` stops it\n\n",
+                    get_pgdata_path().unwrap().display(),
+                    pg_config.host(),
+                    pg_config.test_port().unwrap(),
+                    get_pg_dbname(),
+                )
+                .bold()
+                .yellow()
+                .to_string(),
+            )
+            .unwrap();
+            libc::printf(message_string.as_ptr());
+            return;
+        }
         let message_string =
             std::ffi::CString::new("Stopping Postgres\n\n".bold().blue().to_string()).unwrap();
         libc::printf(message_string.as_ptr());
-        libc::kill(pgpid as libc::pid_t, libc::SIGTERM);
+        shutdown_pg(pgpid, false);
     });
 
-    Ok(session_id)
+    Ok(())
+}
+
+/// Was `cargo pgx test --keep-on-failure` run?
+fn keep_on_failure() -> bool {
+    std::env::var("PGX_TEST_KEEP_ON_FAILURE").unwrap_or("false".into()) == "true"
+}
+
+/// Was `cargo pgx test --reuse-server` run?
+fn reuse_server() -> bool {
+    std::env::var("PGX_TEST_REUSE_SERVER").unwrap_or("false".into()) == "true"
+}
+
+/// The pid of the Postgres a `cargo pgx test --keep-on-failure` or `--reuse-server` left running,
+/// if there is one
+fn running_pg() -> eyre::Result<Option<u32>> {
+    let mut postmaster_pid = get_pgdata_path()?;
+    postmaster_pid.push("postmaster.pid");
+    let pid = std::fs::read_to_string(&postmaster_pid)
+        .ok()
+        .and_then(|contents| contents.lines().next()?.parse::<u32>().ok());
+    Ok(pid.filter(|&pid| pg_is_running(pid)))
+}
+
+#[cfg(unix)]
+fn pg_is_running(pid: u32) -> bool {
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+/// Windows has no signals to probe a process with, so `pg_ctl` checks its data directory
+#[cfg(windows)]
+fn pg_is_running(_pid: u32) -> bool {
+    pg_ctl(&["status"])
+}
+
+/// Asks the Postgres with `pid` to shut down, a fast shutdown if `fast`, otherwise a smart one,
+/// returning if it was running to be asked
+#[cfg(unix)]
+fn shutdown_pg(pid: u32, fast: bool) -> bool {
+    let signal = if fast { libc::SIGINT } else { libc::SIGTERM };
+    unsafe { libc::kill(pid as libc::pid_t, signal) == 0 }
+}
+
+#[cfg(windows)]
+fn shutdown_pg(_pid: u32, fast: bool) -> bool {
+    pg_ctl(&[
+        "stop",
+        "--no-wait",
+        "-m",
+        if fast { "fast" } else { "smart" },
+    ])
+}
+
+/// Runs `pg_ctl` on the test data directory, returning if it succeeded
+#[cfg(windows)]
+fn pg_ctl(args: &[&str]) -> bool {
+    let pg_config = get_pg_config();
+    let (pg_ctl, pgdata) = match (pg_config.pg_ctl_path(), get_pgdata_path()) {
+        (Ok(pg_ctl), Ok(pgdata)) => (pg_ctl, pgdata),
+        _ => return false,
+    };
+    Command::new(pg_ctl)
+        .args(args)
+        .arg("-D")
+        .arg(pgdata)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_or(false, |status| status.success())
+}
+
+/// Stops the Postgres a `cargo pgx test --keep-on-failure` or `--reuse-server` left running, if
+/// there is one
+fn stop_kept_pg() -> eyre::Result<()> {
+    let mut postmaster_pid = get_pgdata_path()?;
+    postmaster_pid.push("postmaster.pid");
+    let pid = match running_pg()? {
+        Some(pid) => pid,
+        None => return Ok(()),
+    };
+
+    // a fast shutdown, so connections to it don't hold it up
+    if !shutdown_pg(pid, true) {
+        // it isn't running anymore
+        return Ok(());
+    }
+    eprintln!(
+        "stopping the Postgres left running by the last run, pid={}",
+        pid
+    );
+    for _ in 0..100 {
+        if !postmaster_pid.exists() {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    Err(eyre!(
+        "the Postgres left running by the last run, pid={}, didn't stop",
+        pid
+    ))
+}
+
+/// The log of a Postgres started for `--reuse-server`, read as it's written to
+struct FollowLog(std::fs::File);
+
+impl Read for FollowLog {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let read = self.0.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+    }
+}
+
+/// Follow the log of the Postgres kept from the last run, from what it logs next
+fn follow_pg_log(loglines: LogLines) -> eyre::Result<()> {
+    let mut log =
+        std::fs::File::open(get_pg_log_path()?).wrap_err("couldn't open the kept Postgres' log")?;
+    log.seek(SeekFrom::End(0))?;
+    std::thread::spawn(move || read_pg_log(FollowLog(log), None, loglines));
+    Ok(())
 }
 
 fn monitor_pg(mut command: Command, cmd_string: String, loglines: LogLines) -> (u32, String) {
@@ -410,54 +729,19 @@ fn monitor_pg(mut command: Command, cmd_string: String, loglines: LogLines) -> (
         eprintln!("{}", pg_sys::get_pg_version_string().bold().purple());
 
         // wait for the database to say its ready to start up
-        let reader = BufReader::new(
-            child
-                .stderr
-                .take()
-                .expect("couldn't take postmaster stderr"),
-        );
-
-        let regex = regex::Regex::new(r#"\[.*?\] \[.*?\] \[(?P<session_id>.*?)\]"#).unwrap();
-        let mut is_started_yet = false;
-        for line in reader.lines() {
-            match line {
-                Ok(line) => {
-                    let session_id = match get_named_capture(&regex, "session_id", &line) {
-                        Some(sid) => sid,
-                        None => "NONE".to_string(),
-                    };
-
-                    if line.contains("database system is ready to accept connections") {
-                        // Postgres says it's ready to go
-                        sender.send((pid, session_id.clone())).unwrap();
-                        is_started_yet = true;
-                    }
-
-                    if !is_started_yet || line.contains("TMSG: ") {
-                        eprintln!("{}", line.cyan());
-                    }
-
-                    //                    if line.contains("INFO: ") {
-                    //                        eprintln!("{}", line.cyan());
-                    //                    } else if line.contains("WARNING: ") {
-                    //                        eprintln!("{}", line.bold().yellow());
-                    //                    } else if line.contains("ERROR: ") {
-                    //                        eprintln!("{}", line.bold().red());
-                    //                    } else if line.contains("statement: ") || line.contains("duration: ") {
-                    //                        eprintln!("{}", line.bold().blue());
-                    //                    } else if line.contains("LOG: ") {
-                    //                        eprintln!("{}", line.dimmed().white());
-                    //                    } else {
-                    //                        eprintln!("{}", line.bold().purple());
-                    //                    }
-
-                    let mut loglines = loglines.lock().unwrap();
-                    let session_lines = loglines.entry(session_id).or_insert_with(Vec::new);
-                    session_lines.push(line);
-                }
-                Err(e) => panic!("{}", e),
-            }
-        }
+        let reader: Box<dyn Read> = if reuse_server() {
+            let log = std::fs::File::open(get_pg_log_path().unwrap())
+                .expect("couldn't open the postmaster's log");
+            Box::new(FollowLog(log))
+        } else {
+            Box::new(
+                child
+                    .stderr
+                    .take()
+                    .expect("couldn't take postmaster stderr"),
+            )
+        };
+        read_pg_log(reader, Some((pid, sender)), loglines);
 
         // wait for Postgres to really finish
         match child.try_wait() {
@@ -475,8 +759,63 @@ fn monitor_pg(mut command: Command, cmd_string: String, loglines: LogLines) -> (
     receiver.recv().expect("Postgres failed to start")
 }
 
+/// Collect what Postgres logs, telling `ready` once it's accepting connections
+fn read_pg_log(
+    reader: impl Read,
+    mut ready: Option<(u32, std::sync::mpsc::Sender<(u32, String)>)>,
+    loglines: LogLines,
+) {
+    let sanitized = matches!(Sanitizer::from_env(), Ok(Some(_)));
+    let reader = BufReader::new(reader);
+
+    let regex = regex::Regex::new(r#"\[.*?\] \[.*?\] \[(?P<session_id>.*?)\]"#).unwrap();
+    let mut is_started_yet = ready.is_none();
+    for line in reader.lines() {
+        match line {
+            Ok(line) => {
+                let session_id = match get_named_capture(&regex, "session_id", &line) {
+                    Some(sid) => sid,
+                    None => "NONE".to_string(),
+                };
+
+                if line.contains("database system is ready to accept connections") {
+                    // Postgres says it's ready to go
+                    if let Some((pid, sender)) = ready.take() {
+                        sender.send((pid, session_id.clone())).unwrap();
+                    }
+                    is_started_yet = true;
+                }
+
+                // a sanitizer's reports don't have the log_line_prefix
+                if !is_started_yet || line.contains("TMSG: ") || (sanitized && session_id == "NONE")
+                {
+                    eprintln!("{}", line.cyan());
+                }
+
+                //                    if line.contains("INFO: ") {
+                //                        eprintln!("{}", line.cyan());
+                //                    } else if line.contains("WARNING: ") {
+                //                        eprintln!("{}", line.bold().yellow());
+                //                    } else if line.contains("ERROR: ") {
+                //                        eprintln!("{}", line.bold().red());
+                //                    } else if line.contains("statement: ") || line.contains("duration: ") {
+                //                        eprintln!("{}", line.bold().blue());
+                //                    } else if line.contains("LOG: ") {
+                //                        eprintln!("{}", line.dimmed().white());
+                //                    } else {
+                //                        eprintln!("{}", line.bold().purple());
+                //                    }
+
+                loglines.lock().unwrap().lines.push((session_id, line));
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
 fn dropdb() {
     let pg_config = get_pg_config();
+    terminate_test_database_sessions(&pg_config);
     let output = Command::new(
         pg_config
             .dropdb_path()
@@ -515,6 +854,44 @@ fn dropdb() {
     }
 }
 
+/// Stop the sessions of the last run's test database, like the background workers of a preloaded
+/// extension, which otherwise keep it from being dropped
+fn terminate_test_database_sessions(pg_config: &PgConfig) {
+    let mut client = postgres::Config::new()
+        .host(pg_config.host())
+        .port(
+            pg_config
+                .test_port()
+                .expect("unable to determine test port"),
+        )
+        .user(&get_pg_user())
+        .dbname("postgres")
+        .connect(postgres::NoTls)
+        .expect("failed to connect to the postgres database");
+    let sessions = "FROM pg_stat_activity WHERE datname = $1 AND pid <> pg_backend_pid()";
+
+    client
+        .execute(
+            format!("SELECT pg_terminate_backend(pid) {}", sessions).as_str(),
+            &[&get_pg_dbname()],
+        )
+        .expect("failed to terminate the test database's sessions");
+    let start = std::time::Instant::now();
+    while start.elapsed() < std::time::Duration::from_secs(5) {
+        let remaining: i64 = client
+            .query_one(
+                format!("SELECT count(*) {}", sessions).as_str(),
+                &[&get_pg_dbname()],
+            )
+            .expect("failed to count the test database's sessions")
+            .get(0);
+        if remaining == 0 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+}
+
 fn create_extension() {
     let (mut client, _) = client();
 
@@ -526,7 +903,7 @@ fn create_extension() {
         .unwrap();
 }
 
-fn get_extension_name() -> String {
+pub(crate) fn get_extension_name() -> String {
     std::env::var("CARGO_PKG_NAME")
         .unwrap_or_else(|_| panic!("CARGO_PKG_NAME is not an envvar"))
         .replace("-", "_")
@@ -541,10 +918,23 @@ fn get_pgdata_path() -> eyre::Result<PathBuf> {
     Ok(target_dir)
 }
 
+/// Where a Postgres started for `--reuse-server` logs to
+fn get_pg_log_path() -> eyre::Result<PathBuf> {
+    let mut target_dir = get_target_dir()?;
+    target_dir.push(format!(
+        "pgx-test-data-{}.log",
+        pg_sys::get_pg_major_version_num()
+    ));
+    Ok(target_dir)
+}
+
 fn get_pg_dbname() -> &'static str {
     "pgx_tests"
 }
 
-fn get_pg_user() -> String {
-    std::env::var("USER").unwrap_or_else(|_| panic!("USER is not an envvar"))
+pub(crate) fn get_pg_user() -> String {
+    // `initdb` names the superuser after whoever ran it, who Windows calls `USERNAME`
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| panic!("USER is not an envvar"))
 }
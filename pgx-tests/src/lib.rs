@@ -7,11 +7,19 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
+mod dump;
 mod framework;
+mod opclass;
+mod roundtrip;
 #[cfg(any(test, feature = "pg_test"))]
 mod tests;
+mod workers;
 
+pub use dump::*;
 pub use framework::*;
+pub use opclass::*;
+pub use roundtrip::*;
+pub use workers::*;
 
 #[cfg(any(test, feature = "pg_test"))]
 pgx::pg_sql_graph_magic!();
@@ -23,6 +31,11 @@ pub mod pg_test {
     }
 
     pub fn postgresql_conf_options() -> Vec<&'static str> {
-        vec![]
+        vec![
+            // for the logical replication tests
+            "wal_level = logical",
+            // for the background worker tests
+            "shared_preload_libraries = 'pgx_tests'",
+        ]
     }
 }
@@ -0,0 +1,367 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Tests of the operator classes `#[derive(PostgresOrd)]` and `#[derive(PostgresHash)]` create,
+//! which check that Postgres finds nothing wrong with them, and that the indexes, sorts, and joins
+//! that use them agree with the type's Rust `Ord` and `Eq`.
+//!
+//! A support function or operator wired up to the wrong strategy or signature is only noticed by
+//! the queries that happen to use it, which are usually chosen by the planner, so each check turns
+//! off the planner's alternatives to the plan it means to test, and fails if it isn't chosen.
+use pgx::*;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+/// The temporary table the checks fill with the values they're given
+const TABLE: &str = "pgx_opclass_check";
+
+/// Check the default `btree` operator class of `T`, such as the one `#[derive(PostgresOrd)]`
+/// creates, with `values`, each of which is used twice.  `amvalidate()` must find nothing wrong
+/// with it, and sorting, the comparison operators, with and without a `btree` index, an ordered
+/// index scan, and a merge join must all agree with `T`'s `Ord`.  Call it from a `#[pg_test]`, or
+/// have [`opclass_test!`](crate::opclass_test) write the test.
+pub fn check_btree_opclass<T>(values: Vec<T>)
+where
+    T: FromDatum + IntoDatum + Ord + Clone + Debug,
+{
+    check_amvalidate::<T>("btree");
+    let rows = create_table(&values);
+
+    let mut sorted = rows.clone();
+    sorted.sort();
+    expect_values(
+        &sorted,
+        &format!("SELECT value FROM {} ORDER BY value", TABLE),
+        "Sort",
+    );
+    check_comparisons(&rows, "Seq Scan");
+
+    Spi::run(&format!(
+        "CREATE INDEX {table}_btree ON {table} USING btree (value)",
+        table = TABLE
+    ));
+    with_settings(
+        &[
+            ("enable_seqscan", "off"),
+            ("enable_bitmapscan", "off"),
+            ("enable_sort", "off"),
+        ],
+        || {
+            expect_values(
+                &sorted,
+                &format!("SELECT value FROM {} ORDER BY value", TABLE),
+                "Index",
+            );
+            sorted.reverse();
+            expect_values(
+                &sorted,
+                &format!("SELECT value FROM {} ORDER BY value DESC", TABLE),
+                "Index",
+            );
+            check_comparisons(&rows, "Index");
+        },
+    );
+    with_settings(
+        &[
+            ("enable_hashjoin", "off"),
+            ("enable_nestloop", "off"),
+            ("enable_mergejoin", "on"),
+        ],
+        || check_self_join(&rows, "Merge Join"),
+    );
+
+    Spi::run(&format!("DROP TABLE {}", TABLE));
+}
+
+/// Check the default `hash` operator class of `T`, such as the one `#[derive(PostgresHash)]`
+/// creates, with `values`, each of which is used twice.  `amvalidate()` must find nothing wrong
+/// with it, and lookups with a `hash` index, a hash join, and a hashed `GROUP BY` must all agree
+/// with `T`'s `Eq`.  Call it from a `#[pg_test]`, or have [`opclass_test!`](crate::opclass_test)
+/// write the test.
+pub fn check_hash_opclass<T>(values: Vec<T>)
+where
+    T: FromDatum + IntoDatum + Eq + Clone + Debug,
+{
+    check_amvalidate::<T>("hash");
+    let rows = create_table(&values);
+
+    Spi::run(&format!(
+        "CREATE INDEX {table}_hash ON {table} USING hash (value)",
+        table = TABLE
+    ));
+    with_settings(
+        &[("enable_seqscan", "off"), ("enable_bitmapscan", "off")],
+        || {
+            for value in &rows {
+                expect_count(
+                    rows.iter().filter(|row| *row == value).count(),
+                    &format!("SELECT count(*) FROM {} WHERE value = $1", TABLE),
+                    value,
+                    "Index",
+                );
+            }
+        },
+    );
+    with_settings(
+        &[
+            ("enable_mergejoin", "off"),
+            ("enable_nestloop", "off"),
+            ("enable_hashjoin", "on"),
+        ],
+        || check_self_join(&rows, "Hash Join"),
+    );
+    with_settings(&[("enable_sort", "off"), ("enable_hashagg", "on")], || {
+        let mut distinct = Vec::<&T>::new();
+        for value in &rows {
+            if !distinct.contains(&value) {
+                distinct.push(value);
+            }
+        }
+        let query = format!(
+            "SELECT count(*) FROM (SELECT value FROM {} GROUP BY value) AS grouped",
+            TABLE
+        );
+        expect_plan(&query, vec![], "HashAggregate");
+        let groups = Spi::get_one::<i64>(&query).unwrap_or_default();
+        if groups as usize != distinct.len() {
+            panic!(
+                "`{}` found {} groups, rather than {}",
+                query,
+                groups,
+                distinct.len()
+            );
+        }
+    });
+
+    Spi::run(&format!("DROP TABLE {}", TABLE));
+}
+
+/// Write a `#[pg_test]` that checks the default operator class of a type for an index access
+/// method with [`check_btree_opclass()`] or [`check_hash_opclass()`], given the test's name, the
+/// type, `btree` or `hash`, and the values to check it with:
+///
+/// ```rust,ignore
+/// #[cfg(any(test, feature = "pg_test"))]
+/// #[pgx::pg_schema]
+/// mod tests {
+///     use pgx::*;
+///
+///     pgx_tests::opclass_test!(test_my_type_btree, MyType, btree, values = MyType::samples());
+///     pgx_tests::opclass_test!(test_my_type_hash, MyType, hash, values = MyType::samples());
+/// }
+/// ```
+#[macro_export]
+macro_rules! opclass_test {
+    ($name:ident, $ty:ty, btree, values = $values:expr) => {
+        #[pgx::pg_test]
+        fn $name() {
+            $crate::check_btree_opclass::<$ty>($values);
+        }
+    };
+    ($name:ident, $ty:ty, hash, values = $values:expr) => {
+        #[pgx::pg_test]
+        fn $name() {
+            $crate::check_hash_opclass::<$ty>($values);
+        }
+    };
+}
+
+/// `amvalidate()` reports what's wrong with an operator class as `INFO` messages, and only
+/// returns whether there was anything
+fn check_amvalidate<T: IntoDatum>(access_method: &str) {
+    let type_name = type_name::<T>();
+    let valid = Spi::get_one_with_args::<bool>(
+        "SELECT amvalidate(opclass.oid) FROM pg_opclass AS opclass
+         JOIN pg_am AS am ON am.oid = opclass.opcmethod
+         WHERE am.amname = $1 AND opclass.opcintype = $2 AND opclass.opcdefault",
+        spi_args![access_method, T::type_oid()],
+    )
+    .unwrap_or_else(|| {
+        panic!(
+            "{} has no default {} operator class",
+            type_name, access_method
+        )
+    });
+    assert!(
+        valid,
+        "amvalidate() found something wrong with the default {} operator class of {}, which it \
+         reported as INFO messages",
+        access_method, type_name
+    );
+}
+
+/// Create the table with each of `values` twice, the first time in reverse, and return its rows
+fn create_table<T>(values: &[T]) -> Vec<T>
+where
+    T: IntoDatum + Clone,
+{
+    Spi::run(&format!(
+        "CREATE TEMPORARY TABLE {} (value {})",
+        TABLE,
+        type_name::<T>()
+    ));
+    let rows = values
+        .iter()
+        .rev()
+        .chain(values.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    for row in &rows {
+        Spi::run_with_args(
+            &format!("INSERT INTO {} VALUES ($1)", TABLE),
+            vec![(PgOid::from(T::type_oid()), row.clone().into_datum())],
+        );
+    }
+    Spi::run(&format!("ANALYZE {}", TABLE));
+    rows
+}
+
+/// Whether the `Ordering` of two values satisfies a comparison operator
+type Comparison = fn(Ordering) -> bool;
+
+/// Count the rows each comparison operator finds for each value, which must be as many as `Ord`
+/// finds
+fn check_comparisons<T>(rows: &[T], node: &str)
+where
+    T: FromDatum + IntoDatum + Ord + Clone + Debug,
+{
+    let operators: [(&str, Comparison); 6] = [
+        ("<", Ordering::is_lt),
+        ("<=", Ordering::is_le),
+        ("=", Ordering::is_eq),
+        (">=", Ordering::is_ge),
+        (">", Ordering::is_gt),
+        ("<>", Ordering::is_ne),
+    ];
+    for &(operator, matches) in operators.iter() {
+        let query = format!("SELECT count(*) FROM {} WHERE value {} $1", TABLE, operator);
+        for value in rows {
+            expect_count(
+                rows.iter().filter(|row| matches((*row).cmp(value))).count(),
+                &query,
+                value,
+                // `<>` can't use an index
+                if operator == "<>" { "Seq Scan" } else { node },
+            );
+        }
+    }
+}
+
+/// Join the table to itself on `=`, which must pair up as many rows as `Eq` does
+fn check_self_join<T: PartialEq>(rows: &[T], node: &str) {
+    let query = format!(
+        "SELECT count(*) FROM {table} AS a JOIN {table} AS b ON a.value = b.value",
+        table = TABLE
+    );
+    expect_plan(&query, vec![], node);
+    let expected = rows
+        .iter()
+        .map(|a| rows.iter().filter(|b| a == *b).count())
+        .sum::<usize>();
+    let pairs = Spi::get_one::<i64>(&query).unwrap_or_default();
+    if pairs as usize != expected {
+        panic!(
+            "`{}` paired up {} rows, rather than {}",
+            query, pairs, expected
+        );
+    }
+}
+
+fn expect_count<T>(expected: usize, query: &str, value: &T, node: &str)
+where
+    T: IntoDatum + Clone + Debug,
+{
+    let args = || vec![(PgOid::from(T::type_oid()), value.clone().into_datum())];
+    expect_plan(query, args(), node);
+    let count = Spi::get_one_with_args::<i64>(query, args()).unwrap_or_default();
+    if count as usize != expected {
+        panic!(
+            "`{}` found {} rows for {:?}, rather than {}",
+            query, count, value, expected
+        );
+    }
+}
+
+fn expect_values<T>(expected: &[T], query: &str, node: &str)
+where
+    T: FromDatum + IntoDatum + PartialEq + Debug,
+{
+    expect_plan(query, vec![], node);
+    let values = Spi::get_one::<Vec<Option<T>>>(&format!(
+        "SELECT array_agg(value) FROM ({}) AS ordered",
+        query
+    ))
+    .unwrap_or_default()
+    .into_iter()
+    .map(|value| value.unwrap_or_else(|| panic!("`{}` returned a NULL", query)))
+    .collect::<Vec<_>>();
+    if values != expected {
+        panic!(
+            "`{}` returned {:?}, rather than {:?}",
+            query, values, expected
+        );
+    }
+}
+
+/// Fail unless the plan of `query` has a node whose name starts with `node`, such as "Index" for
+/// any kind of index scan
+fn expect_plan(query: &str, args: Vec<(PgOid, Option<pg_sys::Datum>)>, node: &str) {
+    let plan = Spi::explain_plan_with_args(query, args);
+    let nodes = plan.nodes().into_iter().map(node_name).collect::<Vec<_>>();
+    assert!(
+        nodes.iter().any(|name| name.starts_with(node)),
+        "the plan of `{}` has no {} node, only {:?}, so it didn't test the operator class",
+        query,
+        node,
+        nodes
+    );
+}
+
+/// The name of a plan node as `EXPLAIN`'s text format gives it, which for an aggregate is its
+/// strategy's, such as "HashAggregate"
+fn node_name(node: &PlanNode) -> String {
+    let strategy = node
+        .other
+        .get("Strategy")
+        .and_then(|strategy| strategy.as_str());
+    match (node.node_type.as_str(), strategy) {
+        ("Aggregate", Some("Hashed")) => "HashAggregate".to_string(),
+        ("Aggregate", Some("Sorted")) => "GroupAggregate".to_string(),
+        ("Aggregate", Some("Mixed")) => "MixedAggregate".to_string(),
+        (node_type, _) => node_type.to_string(),
+    }
+}
+
+/// Run `f` with the planner settings changed, as `SET LOCAL` would, and put them back afterwards
+fn with_settings<R, F: FnOnce() -> R>(settings: &[(&str, &str)], f: F) -> R {
+    let previous = settings
+        .iter()
+        .map(|(name, value)| {
+            let previous =
+                Spi::get_one_with_args::<String>("SELECT current_setting($1)", spi_args![*name])
+                    .unwrap_or_default();
+            Spi::run_with_args("SELECT set_config($1, $2, true)", spi_args![*name, *value]);
+            (*name, previous)
+        })
+        .collect::<Vec<_>>();
+    let result = f();
+    for (name, value) in previous {
+        Spi::run_with_args(
+            "SELECT set_config($1, $2, true)",
+            spi_args![name, value.as_str()],
+        );
+    }
+    result
+}
+
+fn type_name<T: IntoDatum>() -> String {
+    unsafe { std::ffi::CStr::from_ptr(pg_sys::format_type_be(T::type_oid())) }
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,209 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Property tests of a type's `Datum` conversions, which check that generated values come back
+//! unchanged from every trip through Postgres they can take.
+//!
+//! A failing value is shrunk to a minimal one before the test panics with it.  A Postgres `ERROR`
+//! raised along the way, such as from the type's input function, fails the test as it is, as
+//! the backend can't go on to try other values after one.
+use pgx::*;
+use proptest::arbitrary::{any, Arbitrary};
+use proptest::strategy::{Strategy, ValueTree};
+use proptest::test_runner::{Config, TestRunner};
+use std::fmt::Debug;
+
+/// How many values [`check_datum_roundtrip()`] tries, unless told otherwise
+pub const DEFAULT_ROUNDTRIP_CASES: u32 = 64;
+
+/// The most simpler values tried while shrinking a failing one
+const MAX_SHRINK_ITERATIONS: u32 = 1024;
+
+/// Check that values of `T`, generated from its [`Arbitrary`] implementation, come back unchanged
+/// from a `Datum`, from a query's parameter, from their text form, and in an array with `NULL`s,
+/// and that `NULL` comes back as `None`.  Call it from a `#[pg_test]`, or have
+/// [`datum_roundtrip_test!`](crate::datum_roundtrip_test) write the test.
+pub fn check_datum_roundtrip<T>(cases: u32)
+where
+    T: FromDatum + IntoDatum + Arbitrary + Clone + PartialEq + Debug,
+{
+    check_datum_roundtrip_with::<T, _>(any::<T>(), cases)
+}
+
+/// [`check_datum_roundtrip()`] for values from `strategy`, such as strings without the `\0`
+/// Postgres' text types can't hold
+pub fn check_datum_roundtrip_with<T, S>(strategy: S, cases: u32)
+where
+    T: FromDatum + IntoDatum + Clone + PartialEq + Debug,
+    S: Strategy<Value = T>,
+{
+    check_property(&strategy, cases, roundtrip_value::<T>);
+    check_null_roundtrip::<T>();
+
+    let arrays = proptest::collection::vec(proptest::option::of(strategy), 0..8);
+    check_property(&arrays, cases, roundtrip_value::<Vec<Option<T>>>);
+}
+
+/// Check that `value` comes back unchanged from a `Datum`, from a query's parameter, and from its
+/// text form, or say how it didn't
+pub fn roundtrip_value<T>(value: T) -> Result<(), String>
+where
+    T: FromDatum + IntoDatum + Clone + PartialEq + Debug,
+{
+    let type_oid = T::type_oid();
+    let datum = value
+        .clone()
+        .into_datum()
+        .ok_or_else(|| format!("{:?}.into_datum() is NULL", value))?;
+
+    let from_datum = unsafe { T::from_datum(datum, false, type_oid) };
+    expect_same(&value, from_datum, "from_datum(into_datum())")?;
+
+    let from_parameter =
+        Spi::get_one_with_args::<T>("SELECT $1", vec![(PgOid::from(type_oid), Some(datum))]);
+    expect_same(&value, from_parameter, "SELECT $1")?;
+
+    let type_name = unsafe { std::ffi::CStr::from_ptr(pg_sys::format_type_be(type_oid)) }
+        .to_string_lossy()
+        .into_owned();
+    let from_text = Spi::get_one_with_args::<T>(
+        &format!("SELECT $1::text::{}", type_name),
+        vec![(PgOid::from(type_oid), Some(datum))],
+    );
+    expect_same(
+        &value,
+        from_text,
+        &format!("SELECT $1::text::{}", type_name),
+    )
+}
+
+fn check_null_roundtrip<T>()
+where
+    T: FromDatum + IntoDatum + Debug,
+{
+    let from_datum = unsafe { T::from_datum(0, true, T::type_oid()) };
+    assert!(
+        from_datum.is_none(),
+        "a NULL Datum came back as {:?}",
+        from_datum
+    );
+
+    let from_parameter =
+        Spi::get_one_with_args::<T>("SELECT $1", vec![(PgOid::from(T::type_oid()), None)]);
+    assert!(
+        from_parameter.is_none(),
+        "a NULL parameter came back as {:?}",
+        from_parameter
+    );
+}
+
+fn expect_same<T: PartialEq + Debug>(
+    value: &T,
+    returned: Option<T>,
+    how: &str,
+) -> Result<(), String> {
+    match returned {
+        Some(returned) if returned == *value => Ok(()),
+        Some(returned) => Err(format!(
+            "{:?} came back from `{}` as {:?}",
+            value, how, returned
+        )),
+        None => Err(format!("{:?} came back from `{}` as NULL", value, how)),
+    }
+}
+
+/// Run `check` on `cases` values from `strategy`, and on a failure, shrink the value to the
+/// simplest that still fails, and panic with it
+fn check_property<S, F>(strategy: &S, cases: u32, check: F)
+where
+    S: Strategy,
+    S::Value: Clone,
+    F: Fn(S::Value) -> Result<(), String>,
+{
+    // failures aren't saved for later runs, as the backend's working directory is its data
+    // directory, not the crate's
+    let mut runner = TestRunner::new(Config {
+        cases,
+        failure_persistence: None,
+        ..Config::default()
+    });
+
+    for _ in 0..cases {
+        let mut tree = strategy
+            .new_tree(&mut runner)
+            .unwrap_or_else(|reason| panic!("couldn't generate a value: {}", reason));
+        let mut failure = match check(tree.current()) {
+            Ok(()) => continue,
+            Err(failure) => failure,
+        };
+
+        let mut iterations = 0;
+        if tree.simplify() {
+            while iterations < MAX_SHRINK_ITERATIONS {
+                iterations += 1;
+                match check(tree.current()) {
+                    Ok(()) => {
+                        if !tree.complicate() {
+                            break;
+                        }
+                    }
+                    Err(simpler) => {
+                        failure = simpler;
+                        if !tree.simplify() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        panic!("{}", failure);
+    }
+}
+
+/// Write a `#[pg_test]` that checks the `Datum` conversions of a type with
+/// [`check_datum_roundtrip()`], given the test's name and the type, and optionally how many values
+/// to try, or a `proptest` strategy for them:
+///
+/// ```rust,ignore
+/// #[cfg(any(test, feature = "pg_test"))]
+/// #[pgx::pg_schema]
+/// mod tests {
+///     use pgx::*;
+///
+///     pgx_tests::datum_roundtrip_test!(test_i64_roundtrip, i64);
+///     pgx_tests::datum_roundtrip_test!(test_bool_roundtrip, bool, cases = 8);
+///     pgx_tests::datum_roundtrip_test!(test_text_roundtrip, String, strategy = "[^\\x00]{0,32}");
+/// }
+/// ```
+#[macro_export]
+macro_rules! datum_roundtrip_test {
+    ($name:ident, $ty:ty) => {
+        $crate::datum_roundtrip_test!($name, $ty, cases = $crate::DEFAULT_ROUNDTRIP_CASES);
+    };
+    ($name:ident, $ty:ty, cases = $cases:expr) => {
+        #[pgx::pg_test]
+        fn $name() {
+            $crate::check_datum_roundtrip::<$ty>($cases);
+        }
+    };
+    ($name:ident, $ty:ty, strategy = $strategy:expr) => {
+        $crate::datum_roundtrip_test!(
+            $name,
+            $ty,
+            strategy = $strategy,
+            cases = $crate::DEFAULT_ROUNDTRIP_CASES
+        );
+    };
+    ($name:ident, $ty:ty, strategy = $strategy:expr, cases = $cases:expr) => {
+        #[pgx::pg_test]
+        fn $name() {
+            $crate::check_datum_roundtrip_with::<$ty, _>($strategy, $cases);
+        }
+    };
+}
@@ -0,0 +1,55 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    // pg_stat_activity is read once per transaction, so each test only gets to look at it once
+
+    #[pg_test]
+    fn test_report_activity() {
+        report_activity(PgBackendState::Running, "doing pgx things");
+        let query = Spi::get_one::<String>(
+            "SELECT query FROM pg_stat_activity WHERE pid = pg_backend_pid()",
+        );
+        assert_eq!(query, Some("doing pgx things".to_string()));
+    }
+
+    #[pg_test]
+    fn test_with_activity() {
+        let query = with_activity("inside with_activity()", || {
+            Spi::get_one::<String>(
+                "SELECT query FROM pg_stat_activity WHERE pid = pg_backend_pid()",
+            )
+        });
+        assert_eq!(query, Some("inside with_activity()".to_string()));
+    }
+
+    #[pg_test]
+    fn test_report_application_name() {
+        report_application_name("pgx worker");
+        let name = Spi::get_one::<String>(
+            "SELECT application_name FROM pg_stat_activity WHERE pid = pg_backend_pid()",
+        );
+        assert_eq!(name, Some("pgx worker".to_string()));
+    }
+
+    #[pg_test]
+    fn test_set_process_title() {
+        set_process_title("pgx is busy");
+    }
+}
@@ -90,7 +90,7 @@ impl Aggregate for DemoUnique {
         let first_inner = unsafe { first.get_or_insert_default::<HashSet<String>>() };
         let second_inner = unsafe { second.get_or_insert_default::<HashSet<String>>() };
 
-        let unioned: HashSet<_> = first_inner.union(second_inner).collect();
+        let unioned: HashSet<String> = first_inner.union(second_inner).cloned().collect();
         Internal::new(unioned)
     }
 
@@ -105,6 +105,37 @@ impl Aggregate for DemoUnique {
     }
 }
 
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DemoDistinctCount;
+
+#[pg_aggregate]
+impl Aggregate for DemoDistinctCount {
+    type Args = i32;
+    type State = Internal;
+    type Finalize = i64;
+
+    fn state(
+        mut current: Self::State,
+        arg: Self::Args,
+        fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        let inner = unsafe { Self::state_or_insert_default::<HashSet<i32>>(&mut current, fcinfo) };
+
+        inner.insert(arg);
+        current
+    }
+
+    fn finalize(
+        mut current: Self::State,
+        _direct_args: Self::OrderedSetArgs,
+        fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::Finalize {
+        let inner = unsafe { Self::state_or_insert_default::<HashSet<i32>>(&mut current, fcinfo) };
+
+        inner.len() as i64
+    }
+}
+
 #[derive(Copy, Clone, Default, Debug, PostgresType, Serialize, Deserialize)]
 pub struct DemoPercentileDisc;
 
@@ -178,6 +209,35 @@ mod tests {
         assert_eq!(retval, 2);
     }
 
+    #[pg_test]
+    fn aggregate_demo_distinct_count() {
+        let retval = Spi::get_one::<i64>(
+            "SELECT DemoDistinctCount(value) FROM UNNEST(ARRAY [1, 2, 2, 3, 1]) as value;",
+        )
+        .expect("SQL select failed");
+        assert_eq!(retval, 3);
+    }
+
+    #[pg_test]
+    fn aggregate_demo_distinct_count_hash_spill() {
+        // small enough for a hash aggregate over 1000 groups to spill, on Postgres 13 and later
+        Spi::run("SET LOCAL work_mem = '64kB'");
+        Spi::run("SET LOCAL enable_sort = off");
+
+        // each group is 50 values, which cover all 7 remainders
+        let retval = Spi::get_one::<i64>(
+            "
+            SELECT sum(distinct_count)::bigint FROM (
+                SELECT DemoDistinctCount(value % 7) as distinct_count
+                FROM generate_series(1, 50000) as value
+                GROUP BY value % 1000
+            ) as groups;
+        ",
+        )
+        .expect("SQL select failed");
+        assert_eq!(retval, 7000);
+    }
+
     #[pg_test]
     fn aggregate_demo_percentile_disc() {
         // Example from https://www.postgresql.org/docs/current/xaggr.html#XAGGR-ORDERED-SET-AGGREGATES
@@ -0,0 +1,109 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+    use std::collections::HashSet;
+
+    /// 1000 live rows, numbered from 1, and 5 dead ones
+    struct Numbers;
+
+    impl AcquireSampleRows for Numbers {
+        fn total_pages(_relation: &PgRelation) -> pg_sys::BlockNumber {
+            10
+        }
+
+        fn acquire_sample_rows(_relation: &PgRelation, sample: &mut PgAnalyzeSample) {
+            for i in 1..=1000i32 {
+                sample.add_row(&[i.into_datum().unwrap()], &[false]);
+            }
+            sample.add_dead_rows(5.0);
+        }
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    /// Sample `tests.analyze_test` through `Numbers`, returning its values and the total and
+    /// dead row counts
+    fn sample(target_rows: usize) -> (Vec<i32>, f64, f64) {
+        Spi::run("CREATE TABLE tests.analyze_test (value int)");
+        let relation = PgRelation::open_with_name_and_share_lock("tests.analyze_test").unwrap();
+
+        let mut rows = vec![std::ptr::null_mut(); target_rows];
+        let mut total_rows = 0.0;
+        let mut dead_rows = 0.0;
+        let len = unsafe {
+            acquire_sample_rows::<Numbers>(
+                relation.as_ptr(),
+                pg_sys::DEBUG2 as i32,
+                rows.as_mut_ptr(),
+                target_rows as i32,
+                &mut total_rows,
+                &mut dead_rows,
+            )
+        };
+
+        let values = rows[..len as usize]
+            .iter()
+            .map(|&tuple| unsafe {
+                let datum = heap_getattr_raw(tuple, 1, relation.rd_att).expect("value is NULL");
+                i32::from_datum(datum, false, pg_sys::INT4OID).unwrap()
+            })
+            .collect();
+        (values, total_rows, dead_rows)
+    }
+
+    #[pg_test]
+    fn test_acquire_sample_rows_all() {
+        let (values, total_rows, dead_rows) = sample(2000);
+        assert_eq!(values, (1..=1000).collect::<Vec<_>>());
+        assert_eq!(total_rows, 1000.0);
+        assert_eq!(dead_rows, 5.0);
+    }
+
+    #[pg_test]
+    fn test_acquire_sample_rows_sampled() {
+        let (values, total_rows, dead_rows) = sample(100);
+        assert_eq!(values.len(), 100);
+        assert_eq!(values.iter().collect::<HashSet<_>>().len(), 100);
+        assert!(values.iter().all(|value| (1..=1000).contains(value)));
+        // the later rows get their chance to replace the earlier ones
+        assert!(values.iter().any(|&value| value > 100));
+        assert_eq!(total_rows, 1000.0);
+        assert_eq!(dead_rows, 5.0);
+    }
+
+    #[pg_test]
+    fn test_acquire_sample_rows_none() {
+        let (values, total_rows, _) = sample(0);
+        assert!(values.is_empty());
+        assert_eq!(total_rows, 1000.0);
+    }
+
+    #[pg_test]
+    fn test_analyze_foreign_table() {
+        Spi::run("CREATE TABLE tests.analyze_foreign_test (value int)");
+        let relation =
+            PgRelation::open_with_name_and_share_lock("tests.analyze_foreign_test").unwrap();
+
+        let mut func: pg_sys::AcquireSampleRowsFunc = None;
+        let mut total_pages = 0;
+        let supported = unsafe {
+            analyze_foreign_table::<Numbers>(relation.as_ptr(), &mut func, &mut total_pages)
+        };
+        assert!(supported);
+        assert_eq!(total_pages, 10);
+        assert!(func.is_some());
+    }
+}
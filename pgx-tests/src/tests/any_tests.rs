@@ -0,0 +1,71 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::ffi::{CStr, CString};
+
+    #[pg_extern]
+    fn any_type_name(value: Any) -> String {
+        value.type_name()
+    }
+
+    #[pg_extern]
+    fn any_type_names(_first: variadic!(Any), fcinfo: pg_sys::FunctionCallInfo) -> String {
+        (0..unsafe { pg_nargs(fcinfo) })
+            .map(|i| match pg_getarg::<Any>(fcinfo, i) {
+                Some(value) => value.type_name(),
+                None => "NULL".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    #[pg_extern]
+    fn cstring_shout(input: &CStr) -> CString {
+        CString::new(input.to_string_lossy().to_uppercase()).unwrap()
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_any_argument() {
+        assert_eq!(
+            Spi::get_one::<String>("SELECT tests.any_type_name(42)").as_deref(),
+            Some("integer")
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SELECT tests.any_type_name('x'::text)").as_deref(),
+            Some("text")
+        );
+    }
+
+    #[pg_test]
+    fn test_variadic_any_arguments() {
+        assert_eq!(
+            Spi::get_one::<String>("SELECT tests.any_type_names(1, 'a'::text, true, current_date)")
+                .as_deref(),
+            Some("integer, text, boolean, date")
+        );
+    }
+
+    #[pg_test]
+    fn test_cstring_argument_and_return() {
+        assert_eq!(
+            Spi::get_one::<CString>("SELECT tests.cstring_shout('hello')"),
+            Some(CString::new("HELLO").unwrap())
+        );
+    }
+}
@@ -62,6 +62,35 @@ fn optional_array_arg(values: Option<Array<f32>>) -> f32 {
     values.unwrap().iter().map(|v| v.unwrap_or(0f32)).sum()
 }
 
+#[pg_extern]
+fn sum_array_non_null(values: Array<i32>) -> i64 {
+    values.iter_non_null().map(|v| v as i64).sum()
+}
+
+#[pg_extern]
+fn array_validity(values: Array<i32>) -> Vec<bool> {
+    values
+        .iter_with_validity()
+        .map(|(valid, _)| valid)
+        .collect()
+}
+
+#[pg_extern]
+fn sum_array_with_validity(values: Array<i32>) -> i64 {
+    values.iter_with_validity().map(|(_, v)| v as i64).sum()
+}
+
+#[pg_extern]
+fn moving_sums(values: Array<i32>, width: i32) -> Vec<i64> {
+    let width = width as usize;
+    (0..=values.len().saturating_sub(width))
+        .map(|start| {
+            let window = values.slice(start..start + width);
+            window.iter().map(|v| v.unwrap_or(0) as i64).sum()
+        })
+        .collect()
+}
+
 #[pg_extern]
 fn iterate_array_with_deny_null(values: Array<i32>) {
     for _ in values.iter_deny_null() {
@@ -164,6 +193,120 @@ mod tests {
         assert_eq!(cnt.unwrap(), 2);
     }
 
+    #[pg_test]
+    fn test_iter_non_null() {
+        let sum =
+            Spi::get_one::<i64>("SELECT sum_array_non_null(ARRAY[NULL, 1, 2, NULL, 3]::integer[])");
+        assert_eq!(sum, Some(6));
+        let sum = Spi::get_one::<i64>("SELECT sum_array_non_null(ARRAY[1, 2, 3]::integer[])");
+        assert_eq!(sum, Some(6));
+        let sum = Spi::get_one::<i64>("SELECT sum_array_non_null(ARRAY[NULL, NULL]::integer[])");
+        assert_eq!(sum, Some(0));
+    }
+
+    #[pg_test]
+    fn test_iter_non_null_multidimensional() {
+        let sum = Spi::get_one::<i64>(
+            "SELECT sum_array_non_null(ARRAY[[1, NULL], [NULL, 4]]::integer[])",
+        );
+        assert_eq!(sum, Some(5));
+    }
+
+    #[pg_test]
+    fn test_iter_with_validity() {
+        let validity = Spi::get_one::<Vec<bool>>(
+            "SELECT array_validity(ARRAY[1, NULL, 2, NULL, NULL, 3, 4, 5, NULL]::integer[])",
+        );
+        assert_eq!(
+            validity,
+            Some(vec![
+                true, false, true, false, false, true, true, true, false
+            ])
+        );
+        let sum =
+            Spi::get_one::<i64>("SELECT sum_array_with_validity(ARRAY[1, NULL, 2]::integer[])");
+        assert_eq!(sum, Some(3));
+    }
+
+    #[pg_test]
+    fn test_array_slice() {
+        let sums =
+            Spi::get_one::<Vec<i64>>("SELECT moving_sums(ARRAY[1, 2, NULL, 4, 5]::integer[], 2)");
+        assert_eq!(sums, Some(vec![3, 2, 4, 9]));
+    }
+
+    #[pg_test]
+    fn test_array_slice_into_datum() {
+        let datum = vec![Some(1), Some(2), None, Some(4), Some(5)]
+            .into_datum()
+            .unwrap();
+        let array = unsafe { Array::<i32>::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+
+        let slice = array.slice(1..4);
+        assert_eq!(slice.len(), 3);
+        assert_eq!(slice.get(1), Some(None));
+        assert_eq!(slice.slice(2..).iter().collect::<Vec<_>>(), vec![Some(4)]);
+
+        let sliced = unsafe {
+            Vec::<Option<i32>>::from_datum(slice.into_datum().unwrap(), false, pg_sys::InvalidOid)
+        };
+        assert_eq!(sliced, Some(vec![Some(2), None, Some(4)]));
+
+        // the whole array is passed on as-is
+        assert_eq!(array.slice(..).into_datum(), Some(datum));
+
+        let empty = unsafe {
+            Vec::<i32>::from_datum(
+                array.slice(2..2).into_datum().unwrap(),
+                false,
+                pg_sys::InvalidOid,
+            )
+        };
+        assert_eq!(empty, Some(vec![]));
+    }
+
+    #[pg_test]
+    fn test_whole_array_slice_is_one_dimensional() {
+        for (sql, same_datum) in [
+            ("'{{1, 2}, {3, 4}}'::integer[]", false),
+            ("'[0:3]={1, 2, 3, 4}'::integer[]", false),
+            ("'{1, 2, 3, 4}'::integer[]", true),
+        ] {
+            let datum = Spi::get_one::<pg_sys::Datum>(&format!("SELECT {}", sql)).unwrap();
+            let array =
+                unsafe { Array::<i32>::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+            let whole = array.slice(..).into_datum();
+            assert_eq!(whole == Some(datum), same_datum);
+
+            let shape = Spi::get_one_with_args::<String>(
+                "SELECT array_dims($1) || ' ' || $1::text",
+                vec![(PgBuiltInOids::INT4ARRAYOID.oid(), whole)],
+            );
+            assert_eq!(shape.as_deref(), Some("[1:4] {1,2,3,4}"));
+        }
+    }
+
+    #[pg_test]
+    fn test_array_slice_as_argument() {
+        let datum = vec![1, 2, 3, 4].into_datum().unwrap();
+        let array = unsafe { Array::<i32>::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+        let sum = Spi::get_one_with_args::<i32>(
+            "SELECT sum_array($1)",
+            vec![(
+                PgBuiltInOids::INT4ARRAYOID.oid(),
+                array.slice(2..).into_datum(),
+            )],
+        );
+        assert_eq!(sum, Some(7));
+    }
+
+    #[pg_test(error = "range end index 5 out of range for array of length 4")]
+    fn test_array_slice_out_of_bounds() {
+        let datum = vec![1, 2, 3, 4].into_datum().unwrap();
+        let array = unsafe { Array::<i32>::from_datum(datum, false, pg_sys::InvalidOid) }.unwrap();
+        array.slice(1..5);
+    }
+
     #[pg_test]
     fn test_optional_array() {
         let sum = Spi::get_one::<f32>("SELECT optional_array_arg(ARRAY[1,2,3]::real[])");
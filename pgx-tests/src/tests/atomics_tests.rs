@@ -0,0 +1,43 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_pg_atomic_u32() {
+        let counter = PgAtomicU32::new(u32::MAX);
+        assert_eq!(counter.fetch_add(2), u32::MAX);
+        assert_eq!(counter.read(), 1);
+        assert_eq!(counter.sub_fetch(1), 0);
+        assert_eq!(counter.compare_exchange(1, 5), Err(0));
+        assert_eq!(counter.compare_exchange(0, 5), Ok(0));
+        assert_eq!(counter.exchange(6), 5);
+        assert_eq!(counter.fetch_or(0b1001), 6);
+        assert_eq!(counter.fetch_and(0b0011), 0b1111);
+        assert_eq!(counter.read(), 0b0011);
+    }
+
+    #[pg_test]
+    fn test_pg_atomic_u64_from_ptr() {
+        let mut postgres_atomic = pg_sys::pg_atomic_uint64 { value: 41 };
+        let counter = unsafe { PgAtomicU64::from_ptr(&mut postgres_atomic) };
+        assert_eq!(counter.add_fetch(1), 42);
+        counter.write(u64::MAX);
+        assert_eq!(postgres_atomic.value, u64::MAX);
+    }
+}
@@ -0,0 +1,105 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    /// 1000 rows: `value` is `1` in 900 of them and distinct in the rest, `i` counts up, and
+    /// `label` is `NULL` in every fourth row
+    fn create_table() -> pg_sys::Oid {
+        Spi::run(
+            "CREATE TABLE attstats_test AS
+                SELECT CASE WHEN i <= 900 THEN 1 ELSE i END AS value,
+                       i,
+                       CASE WHEN i % 4 = 0 THEN NULL ELSE 'label' END AS label
+                  FROM generate_series(1, 1000) AS i;
+             ANALYZE attstats_test;",
+        );
+        Spi::get_one::<pg_sys::Oid>("SELECT 'attstats_test'::regclass::oid")
+            .expect("no oid for attstats_test")
+    }
+
+    #[pg_test]
+    fn test_attstats_not_analyzed() {
+        Spi::run("CREATE TABLE attstats_test_empty (value int)");
+        let relid = Spi::get_one::<pg_sys::Oid>("SELECT 'attstats_test_empty'::regclass::oid")
+            .expect("no oid for attstats_test_empty");
+        assert!(PgAttributeStats::for_column(relid, 1, false).is_none());
+    }
+
+    #[pg_test]
+    fn test_attstats_columns() {
+        let relid = create_table();
+        let stats = PgAttributeStats::for_column(relid, 1, false).expect("no statistics");
+        assert_eq!(stats.relid(), relid);
+        assert_eq!(stats.attnum(), 1);
+        assert!(!stats.inherited());
+        assert_eq!(stats.average_width(), 4);
+        assert_eq!(stats.null_fraction(), 0.0);
+    }
+
+    #[pg_test]
+    fn test_attstats_null_fraction() {
+        let relid = create_table();
+        let stats = PgAttributeStats::for_column(relid, 3, false).expect("no statistics");
+        assert_eq!(stats.null_fraction(), 0.25);
+    }
+
+    #[pg_test]
+    fn test_attstats_distinct_values() {
+        let relid = create_table();
+        let stats = PgAttributeStats::for_column(relid, 1, false).expect("no statistics");
+        // more than a tenth of the rows are distinct, so it's stored as a fraction of them
+        assert!(stats.n_distinct() < 0.0);
+        assert_eq!(stats.distinct_values(1000.0), Some(101.0));
+    }
+
+    #[pg_test]
+    fn test_attstats_most_common_values() {
+        let relid = create_table();
+        let stats = PgAttributeStats::for_column(relid, 1, false).expect("no statistics");
+        let mcvs = stats
+            .most_common_values::<i32>()
+            .expect("no most common values");
+        assert_eq!(mcvs.len(), 1);
+        assert_eq!(mcvs[0].0, 1);
+        assert!((mcvs[0].1 - 0.9).abs() < 1e-6);
+    }
+
+    #[pg_test]
+    fn test_attstats_wrong_type() {
+        let relid = create_table();
+        let stats = PgAttributeStats::for_column(relid, 1, false).expect("no statistics");
+        assert!(stats.most_common_values::<i64>().is_none());
+    }
+
+    #[pg_test]
+    fn test_attstats_histogram() {
+        let relid = create_table();
+        let stats = PgAttributeStats::for_column(relid, 1, false).expect("no statistics");
+        let histogram = stats.histogram::<i32>().expect("no histogram");
+        assert_eq!(histogram.first(), Some(&901));
+        assert_eq!(histogram.last(), Some(&1000));
+    }
+
+    #[pg_test]
+    fn test_attstats_correlation() {
+        let relid = create_table();
+        let stats = PgAttributeStats::for_column(relid, 2, false).expect("no statistics");
+        let correlation = stats.correlation().expect("no correlation");
+        assert!((correlation - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,170 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    // each test has a backend of its own, so registers the hooks again
+    static AUDIT: PgAuditLog = PgAuditLog::new("pgx_tests_audit");
+
+    fn create_audit_log_table() {
+        Spi::run(
+            "CREATE TABLE public.audit_log (id serial, class text, command text, username text, \
+             session_username text, database text, relations text[], statement text)",
+        );
+    }
+
+    fn audit_log() -> Option<String> {
+        Spi::get_one(
+            "SELECT string_agg(concat_ws(':', class, command, array_to_string(relations, ','), statement), ';' ORDER BY id) \
+             FROM public.audit_log",
+        )
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_audit_classes() {
+        AUDIT.define_gucs();
+        assert_eq!(AUDIT.classes(), vec![]);
+
+        Spi::run("SET pgx_tests_audit.log = 'all, -misc'");
+        assert_eq!(
+            AUDIT.classes(),
+            vec![
+                PgAuditClass::Read,
+                PgAuditClass::Write,
+                PgAuditClass::Ddl,
+                PgAuditClass::Role
+            ]
+        );
+
+        Spi::run("SET pgx_tests_audit.log = 'READ, none, ddl, bogus'");
+        assert_eq!(AUDIT.classes(), vec![PgAuditClass::Ddl]);
+    }
+
+    #[pg_test]
+    fn test_audit_event_csv() {
+        let event = PgAuditEvent {
+            class: PgAuditClass::Write,
+            command: "INSERT".to_string(),
+            statement: "INSERT INTO a VALUES ('x,\"y\"')".to_string(),
+            relations: vec![
+                PgAuditRelation {
+                    oid: 1,
+                    schema: "public".to_string(),
+                    name: "a".to_string(),
+                },
+                PgAuditRelation {
+                    oid: 2,
+                    schema: "public".to_string(),
+                    name: "b".to_string(),
+                },
+            ],
+            user: "alice".to_string(),
+            session_user: "bob".to_string(),
+            database: "db".to_string(),
+        };
+        assert_eq!(
+            event.to_csv(),
+            "write,INSERT,alice,bob,db,public.a public.b,\"INSERT INTO a VALUES ('x,\"\"y\"\"')\""
+        );
+    }
+
+    #[pg_test]
+    fn test_audit_table() {
+        AUDIT.register();
+        create_audit_log_table();
+        Spi::run("CREATE TABLE public.audited (id int)");
+
+        Spi::run(
+            "SET pgx_tests_audit.sink = 'table'; \
+             SET pgx_tests_audit.table = 'public.audit_log'; \
+             SET pgx_tests_audit.log = 'read, write, ddl'",
+        );
+        Spi::run("ALTER TABLE public.audited ADD COLUMN name text");
+        Spi::run("INSERT INTO public.audited VALUES (1, 'one')");
+        Spi::run("SELECT * FROM public.audited");
+        Spi::run("SHOW pgx_tests_audit.sink");
+
+        assert_eq!(
+            audit_log(),
+            Some(
+                "ddl:ALTER TABLE::ALTER TABLE public.audited ADD COLUMN name text;\
+                 write:INSERT:public.audited:INSERT INTO public.audited VALUES (1, 'one');\
+                 read:SELECT:public.audited:SELECT * FROM public.audited"
+                    .to_string()
+            )
+        );
+    }
+
+    #[pg_test]
+    fn test_audit_relations() {
+        AUDIT.register();
+        create_audit_log_table();
+        Spi::run("CREATE TABLE public.watched (id int); CREATE TABLE public.unwatched (id int)");
+
+        Spi::run(
+            "SET pgx_tests_audit.sink = 'table'; \
+             SET pgx_tests_audit.table = 'public.audit_log'; \
+             SET pgx_tests_audit.log = 'read'; \
+             SET pgx_tests_audit.log_relations = 'watched'",
+        );
+        Spi::run("SELECT * FROM public.unwatched");
+        Spi::run("SELECT * FROM public.watched JOIN public.unwatched USING (id)");
+
+        Spi::run("SET pgx_tests_audit.log_relations = ''; SET pgx_tests_audit.log_catalog = off");
+        Spi::run("SELECT count(*) FROM pg_catalog.pg_class");
+        Spi::run("SELECT * FROM public.unwatched");
+
+        assert_eq!(
+            audit_log(),
+            Some(
+                "read:SELECT:public.watched,public.unwatched:SELECT * FROM public.watched JOIN public.unwatched USING (id);\
+                 read:SELECT:public.unwatched:SELECT * FROM public.unwatched"
+                    .to_string()
+            )
+        );
+    }
+
+    #[pg_test]
+    fn test_audit_file() {
+        AUDIT.register();
+        // relative to the data directory, which is the backend's working directory
+        let file = "pgx_tests_audit.csv";
+        std::fs::remove_file(file).ok();
+
+        Spi::run(
+            "SET pgx_tests_audit.sink = 'file'; \
+             SET pgx_tests_audit.file = 'pgx_tests_audit.csv'; \
+             SET pgx_tests_audit.log = 'role'",
+        );
+        Spi::run("CREATE ROLE pgx_tests_audited");
+
+        let contents = std::fs::read_to_string(file).unwrap();
+        std::fs::remove_file(file).ok();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains(",role,CREATE ROLE,"));
+        assert!(lines[0].ends_with(",,CREATE ROLE pgx_tests_audited"));
+    }
+
+    #[pg_test(error = "pgx_tests_audit.file must be set when pgx_tests_audit.sink is 'file'")]
+    fn test_audit_file_unset() {
+        AUDIT.register();
+        Spi::run("SET pgx_tests_audit.sink = 'file'; SET pgx_tests_audit.log = 'read'");
+        Spi::run("SELECT 1");
+    }
+}
@@ -0,0 +1,104 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn backend_tests_exiting_worker(_arg: pg_sys::Datum) {
+    proc_exit(0);
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::bgworkers::{BackgroundWorkerBuilder, BackgroundWorkerStatus, BgWorkerStartTime};
+    use pgx::*;
+    use std::time::{Duration, Instant};
+
+    #[requires_backend]
+    fn backend_pid() -> i32 {
+        unsafe { pg_sys::MyProcPid }
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_in_backend() {
+        assert!(is_backend());
+        assert!(is_backend_thread());
+        assert_eq!(check_backend(), Ok(()));
+        assert_eq!(backend_pid(), unsafe { pg_sys::MyProcPid });
+    }
+
+    #[pg_test]
+    fn test_spawned_thread() {
+        let result = std::thread::Builder::new()
+            .name("backend_test".to_string())
+            .spawn(|| (is_backend(), is_backend_thread(), check_backend()))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(
+            result,
+            (
+                true,
+                false,
+                Err(BackendError::NotBackendThread {
+                    thread: Some("backend_test".to_string())
+                })
+            )
+        );
+    }
+
+    #[pg_test]
+    fn test_requires_backend_in_spawned_thread() {
+        let panic = std::thread::spawn(backend_pid).join().unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("Postgres functions can only be called from the backend's thread, not an unnamed thread")
+        );
+    }
+
+    #[pg_test]
+    fn test_proc_exit_ends_worker() {
+        let worker = BackgroundWorkerBuilder::new("backend_tests exiting worker")
+            .set_start_time(BgWorkerStartTime::ConsistentState)
+            .set_library("pgx_tests")
+            .set_function("backend_tests_exiting_worker")
+            .load_dynamic()
+            .expect("no free background worker slots");
+
+        let start = Instant::now();
+        while worker.status() != BackgroundWorkerStatus::Stopped {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "the worker didn't exit"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[pg_test]
+    fn test_proc_exit_in_spawned_thread() {
+        let panic = std::thread::spawn(|| proc_exit(0)).join().unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("Postgres functions can only be called from the backend's thread, not an unnamed thread")
+        );
+    }
+
+    #[pg_test(error = "exit code 2 would make the postmaster restart every backend")]
+    fn test_proc_exit_crash_code() {
+        proc_exit(2);
+    }
+}
@@ -0,0 +1,423 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+/// The background worker the test Postgres starts, since `pgx_tests` is in its
+/// `shared_preload_libraries`
+#[cfg(any(test, feature = "pg_test"))]
+mod test_worker {
+    use pgx::bgworkers::{
+        BackgroundWorker, BackgroundWorkerBuilder, PgPoolWorker, PgWorkerPool,
+        PgWorkerPoolBuilder, RestartPolicy, SignalWakeFlags,
+    };
+    use pgx::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::time::Duration;
+
+    /// Its type in `pg_stat_activity`, which is its name after Postgres 10
+    #[cfg(feature = "pg10")]
+    pub(super) const TEST_WORKER_TYPE: &str = "background worker";
+    #[cfg(not(feature = "pg10"))]
+    pub(super) const TEST_WORKER_TYPE: &str = "pgx_tests test worker";
+
+    /// How many times it's received a SIGHUP
+    pub(super) static TEST_WORKER_SIGHUPS: PgAtomic<AtomicI32> = PgAtomic::new();
+
+    /// A pool with a worker that keeps running and one that fails as soon as it starts
+    pub(super) static TEST_POOL: PgWorkerPool = PgWorkerPool::new("pgx_tests pool");
+
+    #[pg_guard]
+    pub extern "C" fn _PG_init() {
+        // `CREATE EXTENSION` loads the library again, too late to set up either
+        if unsafe { !pg_sys::process_shared_preload_libraries_in_progress } {
+            return;
+        }
+        pg_shmem_init!(TEST_WORKER_SIGHUPS);
+        pg_shmem_init!(crate::tests::metrics_tests::TEST_METRICS);
+        BackgroundWorkerBuilder::new("pgx_tests test worker")
+            .set_function("pgx_tests_test_worker_main")
+            .set_library("pgx_tests")
+            .enable_shmem_access(None)
+            .load();
+
+        pg_shmem_init!(TEST_POOL);
+        PgWorkerPoolBuilder::new(&TEST_POOL)
+            .set_library("pgx_tests")
+            .set_leader_function("pgx_tests_pool_leader")
+            .add_worker(PgPoolWorker::new("steady", "pgx_tests_pool_steady"))
+            .add_worker(
+                PgPoolWorker::new("failing", "pgx_tests_pool_failing")
+                    .set_restart_policy(RestartPolicy::OnFailure),
+            )
+            .set_backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .set_heartbeat_timeout(Some(Duration::from_secs(30)))
+            .load();
+    }
+
+    #[pg_guard]
+    #[no_mangle]
+    pub extern "C" fn pgx_tests_pool_leader(_arg: pg_sys::Datum) {
+        TEST_POOL.supervise();
+    }
+
+    #[pg_guard]
+    #[no_mangle]
+    pub extern "C" fn pgx_tests_pool_steady(_arg: pg_sys::Datum) {
+        TEST_POOL.join();
+        BackgroundWorker::attach_signal_handlers(
+            SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM,
+        );
+        while BackgroundWorker::wait_latch(Some(Duration::from_millis(100)))
+            && TEST_POOL.heartbeat()
+        {}
+    }
+
+    #[pg_guard]
+    #[no_mangle]
+    pub extern "C" fn pgx_tests_pool_failing(_arg: pg_sys::Datum) {
+        TEST_POOL.join();
+        error!("the failing pool worker failed");
+    }
+
+    #[pg_guard]
+    #[no_mangle]
+    pub extern "C" fn pgx_tests_test_worker_main(_arg: pg_sys::Datum) {
+        BackgroundWorker::attach_signal_handlers(
+            SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM,
+        );
+        while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) {
+            if BackgroundWorker::sighup_received() {
+                TEST_WORKER_SIGHUPS.get().fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Where the worker started by the backend with pid `backend_pid` writes what its
+    /// transactions returned
+    pub(super) fn transaction_log_path(backend_pid: i32) -> PathBuf {
+        std::env::temp_dir().join(format!("pgx_tests_worker_transactions_{}", backend_pid))
+    }
+
+    #[pg_guard]
+    #[no_mangle]
+    pub extern "C" fn pgx_tests_transaction_worker(arg: pg_sys::Datum) {
+        BackgroundWorker::connect_worker_to_spi(Some(BackgroundWorker::get_extra()), None);
+
+        // a failed transaction is aborted, and the worker carries on
+        let in_transaction = || unsafe { pg_sys::IsTransactionState() };
+        let log = [
+            format!(
+                "{:?}",
+                BackgroundWorker::try_transaction(|| Spi::get_one::<i32>("SELECT 1 / 0"))
+            ),
+            in_transaction().to_string(),
+            format!(
+                "{:?}",
+                BackgroundWorker::try_transaction(|| -> i32 { panic!("the transaction panicked") })
+            ),
+            in_transaction().to_string(),
+            format!(
+                "{:?}",
+                BackgroundWorker::transaction(|| Spi::get_one::<i32>("SELECT 42"))
+            ),
+        ];
+        std::fs::write(transaction_log_path(arg as i32), log.join("\n"))
+            .expect("couldn't write the transaction log");
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::bgworkers::{
+        BackgroundWorker, BackgroundWorkerBuilder, BackgroundWorkerConfig, BackgroundWorkerEvent,
+        BackgroundWorkerStatus, CostThrottle, ReloadableConfig, WorkerShutdownHandle,
+    };
+    use pgx::*;
+    use std::time::{Duration, Instant};
+
+    use super::test_worker::TEST_WORKER_TYPE;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_run_async() {
+        assert_eq!(BackgroundWorker::run_async(async { 42 }), Some(42));
+    }
+
+    #[pg_test]
+    fn test_wait_latch_async_timeout() {
+        let started = Instant::now();
+        let alive = BackgroundWorker::run_async(BackgroundWorker::wait_latch_async(Some(
+            Duration::from_millis(50),
+        )));
+        assert_eq!(alive, Some(true));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[pg_test]
+    fn test_wait_latch_async_wakes_on_latch() {
+        unsafe { pg_sys::SetLatch(pg_sys::MyLatch) };
+        let started = Instant::now();
+        let alive = BackgroundWorker::run_async(BackgroundWorker::wait_latch_async(None));
+        assert_eq!(alive, Some(true));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    static RELOAD_NAPTIME: GucSetting<i32> = GucSetting::new(10);
+
+    struct NaptimeConfig {
+        naptime: i32,
+    }
+
+    impl BackgroundWorkerConfig for NaptimeConfig {
+        fn load() -> Self {
+            NaptimeConfig {
+                naptime: RELOAD_NAPTIME.get(),
+            }
+        }
+    }
+
+    #[pg_test]
+    fn test_reloadable_config() {
+        GucRegistry::define_int_guc(
+            "test.reload_naptime",
+            "test reload naptime",
+            "test reload naptime",
+            &RELOAD_NAPTIME,
+            1,
+            100,
+            GucContext::Userset,
+        );
+
+        let reloaded = std::rc::Rc::new(std::cell::Cell::new(0));
+        let mut config = ReloadableConfig::<NaptimeConfig>::new().on_reload({
+            let reloaded = reloaded.clone();
+            move |config| reloaded.set(config.naptime)
+        });
+        assert_eq!(config.get().naptime, 10);
+
+        // no SIGHUP, so nothing changes
+        Spi::run("SET test.reload_naptime = 20");
+        assert!(!config.check_for_reload());
+        assert_eq!(config.get().naptime, 10);
+        assert_eq!(reloaded.get(), 0);
+
+        config.reload();
+        assert_eq!(config.get().naptime, 20);
+        assert_eq!(reloaded.get(), 20);
+    }
+
+    #[pg_test]
+    fn test_wait_latch_or_signal_timeout() {
+        unsafe { pg_sys::ResetLatch(pg_sys::MyLatch) };
+        let event = BackgroundWorker::wait_latch_or_signal(Some(Duration::from_millis(10)));
+        assert_eq!(event, BackgroundWorkerEvent::Timeout);
+    }
+
+    #[pg_test]
+    fn test_wait_latch_or_signal_latch() {
+        unsafe { pg_sys::SetLatch(pg_sys::MyLatch) };
+        let event = BackgroundWorker::wait_latch_or_signal(Some(Duration::from_secs(10)));
+        assert_eq!(event, BackgroundWorkerEvent::Latch);
+    }
+
+    #[pg_test]
+    fn test_wait_latch_or_signal_attached_signal() {
+        BackgroundWorker::attach_signal(libc::SIGUSR2);
+        unsafe { libc::raise(libc::SIGUSR2) };
+
+        let event = BackgroundWorker::wait_latch_or_signal(Some(Duration::from_secs(10)));
+        // regular backends ignore SIGUSR2
+        unsafe { libc::signal(libc::SIGUSR2, libc::SIG_IGN) };
+        assert_eq!(event, BackgroundWorkerEvent::Signal(libc::SIGUSR2));
+    }
+
+    #[pg_test]
+    fn test_shutdown_handle_not_shutdown() {
+        let shutdown = BackgroundWorker::shutdown_handle();
+        assert!(!shutdown.is_shutdown());
+        assert!(!shutdown.wait_timeout(Duration::from_millis(10)));
+    }
+
+    #[pg_test]
+    fn test_shutdown_handle_wakes_threads() {
+        let shutdown = BackgroundWorker::shutdown_handle();
+        let waiters = (0..2)
+            .map(|_| {
+                let shutdown: WorkerShutdownHandle = shutdown.clone();
+                std::thread::spawn(move || shutdown.wait_timeout(Duration::from_secs(10)))
+            })
+            .collect::<Vec<_>>();
+
+        let start = Instant::now();
+        shutdown.shutdown();
+        for waiter in waiters {
+            assert!(waiter.join().unwrap());
+        }
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(BackgroundWorker::shutdown_handle().is_shutdown());
+    }
+
+    #[pg_test]
+    fn test_worker_transactions() {
+        use super::test_worker::transaction_log_path;
+
+        let backend_pid = unsafe { pg_sys::MyProcPid };
+        let path = transaction_log_path(backend_pid);
+        let _ = std::fs::remove_file(&path);
+
+        let database = Spi::get_one::<String>("SELECT current_database()::text").unwrap();
+        let worker = BackgroundWorkerBuilder::new("pgx_tests transaction worker")
+            .set_library("pgx_tests")
+            .set_function("pgx_tests_transaction_worker")
+            .set_argument(Some(backend_pid as pg_sys::Datum))
+            .set_extra(&database)
+            .enable_spi_access()
+            .load_dynamic()
+            .expect("no free background worker slots");
+        assert!(
+            pgx_tests::wait_until(Duration::from_secs(10), || {
+                worker.status() == BackgroundWorkerStatus::Stopped
+            }),
+            "the worker didn't exit"
+        );
+
+        let log = std::fs::read_to_string(&path).expect("the worker's transactions wrote nothing");
+        std::fs::remove_file(&path).expect("couldn't remove the transaction log");
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            vec![
+                r#"Err("division by zero")"#,
+                "false",
+                r#"Err("the transaction panicked")"#,
+                "false",
+                "Some(42)",
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_wait_for_background_worker() {
+        let pid = pgx_tests::wait_for_background_worker(TEST_WORKER_TYPE, Duration::from_secs(10));
+        assert!(pid.is_some());
+        assert_eq!(pgx_tests::background_worker_pid("no such worker"), None);
+    }
+
+    // on Postgres 10, other background workers have the test worker's type
+    #[cfg(all(unix, not(feature = "pg10")))]
+    #[pg_test]
+    fn test_signal_background_worker() {
+        use super::test_worker::TEST_WORKER_SIGHUPS;
+        use std::sync::atomic::Ordering;
+
+        let sighups = || TEST_WORKER_SIGHUPS.get().load(Ordering::SeqCst);
+        pgx_tests::wait_for_background_worker(TEST_WORKER_TYPE, Duration::from_secs(10))
+            .expect("the test worker didn't start");
+        let before = sighups();
+
+        assert!(pgx_tests::signal_background_worker(
+            TEST_WORKER_TYPE,
+            libc::SIGHUP
+        ));
+        assert!(pgx_tests::wait_until(Duration::from_secs(10), || {
+            sighups() > before
+        }));
+        assert!(!pgx_tests::signal_background_worker(
+            "no such worker",
+            libc::SIGHUP
+        ));
+    }
+
+    #[pg_test]
+    fn test_worker_pool_status() {
+        use super::test_worker::TEST_POOL;
+
+        assert_eq!(TEST_POOL.get_name(), "pgx_tests pool");
+        let names = TEST_POOL
+            .status()
+            .into_iter()
+            .map(|status| status.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["steady", "failing"]);
+
+        // the steady worker joins and sends heartbeats, and is never restarted
+        assert!(pgx_tests::wait_until(Duration::from_secs(10), || {
+            let steady = &TEST_POOL.status()[0];
+            steady.pid.is_some() && steady.last_heartbeat.is_some()
+        }));
+        assert_eq!(TEST_POOL.status()[0].restarts, 0);
+    }
+
+    #[pg_test]
+    fn test_worker_pool_restarts_failures() {
+        use super::test_worker::TEST_POOL;
+
+        let restarts = || TEST_POOL.status()[1].restarts;
+        let before = restarts();
+        assert!(pgx_tests::wait_until(Duration::from_secs(10), || {
+            restarts() > before
+        }));
+    }
+
+    #[pg_test]
+    fn test_cost_throttle() {
+        static THROTTLE: CostThrottle = CostThrottle::new("pgx_tests_throttle", 10, 50.0);
+        THROTTLE.define_gucs();
+        assert_eq!(
+            Spi::get_one::<String>("SHOW pgx_tests_throttle.cost_limit").as_deref(),
+            Some("10")
+        );
+        assert_eq!(THROTTLE.cost_delay(), Some(Duration::from_millis(50)));
+
+        let started = Instant::now();
+        assert!(THROTTLE.charge(6));
+        assert_eq!(THROTTLE.balance(), 6);
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        // sleeps through the latch being set
+        unsafe { pg_sys::SetLatch(pg_sys::MyLatch) };
+        assert!(THROTTLE.charge(6));
+        assert_eq!(THROTTLE.balance(), 0);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        // at most four times the delay
+        let started = Instant::now();
+        assert!(THROTTLE.charge(1000));
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(200) && elapsed < Duration::from_secs(1));
+    }
+
+    #[pg_test]
+    fn test_cost_throttle_pages() {
+        static THROTTLE: CostThrottle = CostThrottle::new("pgx_tests_throttle_pages", 1000, 10.0);
+        let page_costs = Spi::get_one::<i64>(
+            "SELECT sum(setting::bigint) FROM pg_settings \
+             WHERE name IN ('vacuum_cost_page_hit', 'vacuum_cost_page_miss', 'vacuum_cost_page_dirty')",
+        )
+        .unwrap();
+        assert!(THROTTLE.charge_pages(1, 1, 1));
+        assert_eq!(THROTTLE.balance(), page_costs);
+        THROTTLE.reset();
+        assert_eq!(THROTTLE.balance(), 0);
+    }
+
+    #[pg_test]
+    fn test_cost_throttle_off() {
+        static THROTTLE: CostThrottle = CostThrottle::new("pgx_tests_throttle_off", 1, 0.0);
+        assert_eq!(THROTTLE.cost_delay(), None);
+        let started = Instant::now();
+        assert!(THROTTLE.charge(1_000_000));
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,76 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_temp_file_round_trip() {
+        let mut file = PgTempFile::new();
+        file.write_all(b"hello, temp file").unwrap();
+        assert_eq!(file.seek(SeekFrom::Start(0)).unwrap(), 0);
+
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello, temp file");
+    }
+
+    #[pg_test]
+    fn test_temp_file_seek() {
+        let mut file = PgTempFile::new();
+        file.write_all(b"0123456789").unwrap();
+
+        assert_eq!(file.seek(SeekFrom::Start(2)).unwrap(), 2);
+        assert_eq!(file.seek(SeekFrom::Current(3)).unwrap(), 5);
+
+        let mut digit = [0u8; 1];
+        file.read_exact(&mut digit).unwrap();
+        assert_eq!(&digit, b"5");
+
+        assert!(file.seek(SeekFrom::Current(-100)).is_err());
+    }
+
+    #[pg_test]
+    fn test_temp_file_larger_than_buffer() {
+        let data = (0..1_000_000u32)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+
+        let mut file = PgTempFile::new();
+        file.write_all(&data).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut read_back = Vec::new();
+        file.read_to_end(&mut read_back).unwrap();
+        assert_eq!(read_back, data);
+    }
+
+    #[pg_test]
+    fn test_temp_file_seek_from_end() {
+        let mut file = PgTempFile::new();
+        file.write_all(b"0123456789").unwrap();
+        file.seek(SeekFrom::Start(4)).unwrap();
+        file.write_all(b"x").unwrap();
+        assert_eq!(file.len(), 10);
+        assert_eq!(file.seek(SeekFrom::End(-1)).unwrap(), 9);
+
+        let mut digit = [0u8; 1];
+        file.read_exact(&mut digit).unwrap();
+        assert_eq!(&digit, b"9");
+    }
+}
@@ -0,0 +1,75 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_relcache_callback() {
+        static INVALIDATED: AtomicU32 = AtomicU32::new(pg_sys::InvalidOid);
+
+        Spi::run("CREATE TABLE relcache_callback_test (id int)");
+        let relid = PgRelation::open_with_name_and_share_lock("relcache_callback_test")
+            .expect("no relcache_callback_test table")
+            .oid();
+
+        register_relcache_callback(move |invalidated| {
+            if invalidated == Some(relid) {
+                INVALIDATED.store(relid, Ordering::SeqCst);
+            }
+        });
+        Spi::run("ALTER TABLE relcache_callback_test ADD COLUMN name text");
+        unsafe {
+            // process this transaction's own invalidations
+            pg_sys::CommandCounterIncrement();
+        }
+
+        assert_eq!(INVALIDATED.load(Ordering::SeqCst), relid);
+    }
+
+    #[pg_test]
+    fn test_syscache_callback() {
+        static INVALIDATED: AtomicU32 = AtomicU32::new(0);
+
+        Spi::run("CREATE TYPE syscache_callback_test AS ENUM ('a', 'b')");
+        let typoid = Spi::get_one::<pg_sys::Oid>("SELECT 'syscache_callback_test'::regtype::oid")
+            .expect("no syscache_callback_test type");
+        let hash = syscache_hash_value(
+            pg_sys::SysCacheIdentifier_TYPEOID,
+            &[typoid.into_datum().unwrap()],
+        );
+
+        register_syscache_callback(pg_sys::SysCacheIdentifier_TYPEOID, move |invalidated| {
+            if invalidated == Some(hash) {
+                INVALIDATED.store(hash, Ordering::SeqCst);
+            }
+        });
+        Spi::run("ALTER TYPE syscache_callback_test RENAME TO syscache_callback_renamed");
+        unsafe {
+            // process this transaction's own invalidations
+            pg_sys::CommandCounterIncrement();
+        }
+
+        assert_eq!(INVALIDATED.load(Ordering::SeqCst), hash);
+    }
+
+    #[pg_test(error = "syscaches have between one and four keys, not 0")]
+    fn test_syscache_hash_value_without_keys() {
+        syscache_hash_value(pg_sys::SysCacheIdentifier_TYPEOID, &[]);
+    }
+}
@@ -0,0 +1,78 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        assert!(!token.is_cancelled());
+        assert_eq!(token.error_if_cancelled(), Ok(()));
+        assert!(!token.wait_timeout(Duration::from_millis(1)));
+
+        let clone = token.clone();
+        std::thread::spawn(move || clone.cancel()).join().unwrap();
+        assert!(token.is_cancelled());
+        assert_eq!(token.error_if_cancelled(), Err(Cancelled));
+        assert!(token.wait_timeout(Duration::from_secs(10)));
+        token.cancel();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // a callback registered once the token is cancelled runs straight away
+        let counter = calls.clone();
+        token.on_cancel(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[pg_test]
+    fn test_run_cancellable() {
+        assert!(!query_cancel_pending());
+        let sum = run_cancellable(|token| {
+            assert!(!token.is_cancelled());
+            (1..=100i64).sum::<i64>()
+        });
+        assert_eq!(sum, 5050);
+    }
+
+    #[cfg(unix)]
+    #[pg_test(error = "canceling statement due to user request")]
+    fn test_run_cancellable_query_cancel() {
+        // what `pg_cancel_backend()` does.  Nothing checks for interrupts before the closure runs
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGINT);
+        }
+        run_cancellable(|token| {
+            assert!(token.wait_timeout(Duration::from_secs(30)), "not cancelled");
+        });
+    }
+
+    #[pg_test(error = "the closure panicked")]
+    fn test_run_cancellable_panic() {
+        run_cancellable(|_| panic!("the closure panicked"));
+    }
+}
@@ -0,0 +1,62 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_extension_version() {
+        assert_eq!(extension_version("pgx_tests"), Some("1.0".to_string()));
+        assert!(has_extension("plpgsql"));
+        assert!(!has_extension("capabilities_test_no_such_extension"));
+    }
+
+    #[pg_test]
+    fn test_has_function() {
+        assert!(has_function("pg_catalog.lower(text)"));
+        assert!(!has_function("capabilities_test_probe(int)"));
+
+        // creating it forgets the cached answer
+        Spi::run(
+            "CREATE FUNCTION capabilities_test_probe(int) RETURNS int LANGUAGE sql AS 'SELECT $1'",
+        );
+        unsafe {
+            // process this transaction's own invalidations
+            pg_sys::CommandCounterIncrement();
+        }
+        assert!(has_function("capabilities_test_probe(int)"));
+        assert!(!has_function("capabilities_test_probe(text)"));
+    }
+
+    #[pg_test]
+    fn test_jit_available() {
+        // Postgres 10 has no `jit` setting, and no JIT
+        #[cfg(not(feature = "pg10"))]
+        Spi::run("SET jit = off");
+        assert!(!jit_available());
+    }
+
+    #[pg_test]
+    fn test_huge_pages() {
+        let expected = match Spi::get_one::<String>("SHOW huge_pages").as_deref() {
+            Some("on") => Some(true),
+            Some("off") => Some(false),
+            _ => None,
+        };
+        assert_eq!(huge_pages(), expected);
+    }
+}
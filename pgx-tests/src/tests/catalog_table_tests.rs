@@ -0,0 +1,130 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE SCHEMA catalog_table_tests;
+"#,
+    name = "create_catalog_table_tests_schema",
+);
+
+#[derive(Debug, PartialEq, PgCatalogTable)]
+#[pgx(sql_name = "job_queues", schema = "catalog_table_tests", dump)]
+#[index(name = "job_queues_priority", keys = ["priority"], where = "NOT paused")]
+#[row(name = "default", priority = 0)]
+#[row(name = "backfill", priority = -10, paused = true, description = "Slow, and it's paused")]
+#[requires("create_catalog_table_tests_schema")]
+pub struct JobQueue {
+    #[pgx(primary_key)]
+    name: String,
+    priority: i32,
+    #[pgx(default = "false")]
+    paused: bool,
+    #[pgx(sql_type = "varchar(200)")]
+    description: Option<String>,
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::JobQueue;
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_catalog_table_initial_rows() {
+        let mut queues = JobQueue::select_all();
+        queues.sort_by_key(|queue| queue.priority);
+        assert_eq!(
+            queues,
+            vec![
+                JobQueue {
+                    name: "backfill".to_string(),
+                    priority: -10,
+                    paused: true,
+                    description: Some("Slow, and it's paused".to_string()),
+                },
+                JobQueue {
+                    name: "default".to_string(),
+                    priority: 0,
+                    paused: false,
+                    description: None,
+                },
+            ]
+        );
+        assert_eq!(JobQueue::qualified_name(), "catalog_table_tests.job_queues");
+    }
+
+    #[pg_test]
+    fn test_catalog_table_insert() {
+        JobQueue {
+            name: "urgent".to_string(),
+            priority: 100,
+            paused: false,
+            description: Some("Jumps the queue".to_string()),
+        }
+        .insert();
+        let urgent = JobQueue::select_where("priority > $1", vec![spi_arg(50)]);
+        assert_eq!(urgent.len(), 1);
+        assert_eq!(urgent[0].name, "urgent");
+        assert_eq!(urgent[0].description.as_deref(), Some("Jumps the queue"));
+    }
+
+    #[pg_test(error = "duplicate key value violates unique constraint \"job_queues_pkey\"")]
+    fn test_catalog_table_primary_key() {
+        JobQueue {
+            name: "default".to_string(),
+            priority: 1,
+            paused: false,
+            description: None,
+        }
+        .insert();
+    }
+
+    #[pg_test]
+    fn test_catalog_table_shape() {
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT format_type(atttypid, atttypmod) FROM pg_attribute \
+                 WHERE attrelid = 'catalog_table_tests.job_queues'::regclass AND attname = 'description'"
+            ),
+            Some("character varying(200)".to_string())
+        );
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT attnotnull FROM pg_attribute \
+                 WHERE attrelid = 'catalog_table_tests.job_queues'::regclass AND attname = 'paused'"
+            ),
+            Some(true)
+        );
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT indexdef FROM pg_indexes WHERE indexname = 'job_queues_priority'"
+            ),
+            Some(
+                "CREATE INDEX job_queues_priority ON catalog_table_tests.job_queues \
+                 USING btree (priority) WHERE (NOT paused)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT 'catalog_table_tests.job_queues'::regclass::oid = ANY (extconfig) \
+                 FROM pg_extension WHERE extname = 'pgx_tests'"
+            ),
+            Some(true)
+        );
+    }
+}
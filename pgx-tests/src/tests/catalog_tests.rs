@@ -0,0 +1,166 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE catalog_test_table (id int) WITH (fillfactor = 70, autovacuum_enabled = false);
+GRANT SELECT, INSERT ON catalog_test_table TO pg_monitor;
+GRANT SELECT ON catalog_test_table TO PUBLIC;
+"#,
+    name = "create_catalog_test_table",
+);
+
+#[pg_extern]
+fn catalog_test_longest_name(names: Vec<Name>) -> Option<Name> {
+    names.into_iter().max_by_key(|name| name.len())
+}
+
+#[pg_extern]
+fn catalog_test_public_can_select(relacl: Vec<AclItem>) -> bool {
+    relacl
+        .iter()
+        .any(|item| item.is_public() && item.privileges.contains(AclMode::SELECT))
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_name_from_datum() {
+        let name =
+            Spi::get_one::<Name>("SELECT relname FROM pg_class WHERE oid = 'pg_class'::regclass")
+                .expect("name was NULL");
+        assert_eq!(name, Name::from("pg_class"));
+        let names =
+            Spi::get_one::<Vec<Name>>("SELECT ARRAY['a', 'bb']::name[]").expect("names were NULL");
+        assert_eq!(names, vec![Name::from("a"), Name::from("bb")]);
+    }
+
+    #[pg_test]
+    fn test_name_arguments() {
+        let name = Spi::get_one::<String>(
+            "SELECT catalog_test_longest_name(ARRAY['a', 'ccc', 'bb']::name[])::text",
+        );
+        assert_eq!(name.as_deref(), Some("ccc"));
+    }
+
+    #[pg_test]
+    fn test_name_into_datum_truncates() {
+        let long = "x".repeat(100);
+        let result = Spi::get_one_with_args::<i32>(
+            "SELECT length($1::text)",
+            vec![(PgBuiltInOids::NAMEOID.oid(), Name(long).into_datum())],
+        );
+        assert_eq!(result, Some(pg_sys::NAMEDATALEN as i32 - 1));
+    }
+
+    #[pg_test]
+    fn test_oid_array() {
+        let oids = Spi::get_one::<Vec<u32>>(
+            "SELECT ARRAY['pg_class'::regclass, 'pg_type'::regclass]::oid[]",
+        )
+        .expect("oids were NULL");
+        assert_eq!(
+            oids,
+            vec![pg_sys::RelationRelationId, pg_sys::TypeRelationId]
+        );
+    }
+
+    #[pg_test]
+    fn test_parse_options() {
+        let reloptions = Spi::get_one::<Vec<String>>(
+            "SELECT reloptions FROM pg_class WHERE oid = 'catalog_test_table'::regclass",
+        )
+        .expect("no reloptions");
+        assert_eq!(
+            parse_options(&reloptions),
+            vec![
+                ("fillfactor".to_string(), Some("70".to_string())),
+                ("autovacuum_enabled".to_string(), Some("false".to_string())),
+            ]
+        );
+        assert_eq!(
+            parse_options(&["a=b=c", "d"]),
+            vec![
+                ("a".to_string(), Some("b=c".to_string())),
+                ("d".to_string(), None)
+            ]
+        );
+        assert_eq!(
+            format_options(&[("fillfactor", "70"), ("a", "b=c")]),
+            vec!["fillfactor=70", "a=b=c"]
+        );
+    }
+
+    #[pg_test]
+    fn test_aclitem_from_datum() {
+        let relacl = Spi::get_one::<Vec<AclItem>>(
+            "SELECT relacl FROM pg_class WHERE oid = 'catalog_test_table'::regclass",
+        )
+        .expect("no relacl");
+        let reader =
+            Spi::get_one::<pg_sys::Oid>("SELECT 'pg_monitor'::regrole::oid").expect("no role");
+        let item = relacl
+            .iter()
+            .find(|item| item.grantee == reader)
+            .expect("no aclitem for pg_monitor");
+        assert_eq!(item.privileges, AclMode::SELECT | AclMode::INSERT);
+        assert!(item.grant_options.is_empty());
+        assert!(!item.is_public());
+        assert_eq!(item.to_text().split('/').next(), Some("pg_monitor=ar"));
+
+        let public = relacl
+            .iter()
+            .find(|item| item.is_public())
+            .expect("no aclitem for PUBLIC");
+        assert_eq!(public.privileges, AclMode::SELECT);
+
+        let result = Spi::get_one::<bool>(
+            "SELECT catalog_test_public_can_select(relacl) FROM pg_class WHERE oid = 'catalog_test_table'::regclass",
+        );
+        assert_eq!(result, Some(true));
+    }
+
+    #[pg_test]
+    fn test_aclitem_parse() {
+        let owner = Spi::get_one::<String>("SELECT current_user::text").expect("no user");
+        let item = AclItem::parse(&format!("pg_monitor=r*w/{}", owner));
+        assert_eq!(item.privileges, AclMode::SELECT | AclMode::UPDATE);
+        assert_eq!(item.grant_options, AclMode::SELECT);
+        assert_eq!(item.to_text(), format!("pg_monitor=r*w/{}", owner));
+
+        let result = Spi::get_one_with_args::<bool>(
+            "SELECT $1 = makeaclitem('pg_monitor'::regrole, current_user::regrole, 'SELECT, UPDATE', false)",
+            vec![(
+                PgBuiltInOids::ACLITEMOID.oid(),
+                AclItem {
+                    grant_options: AclMode::empty(),
+                    ..item
+                }
+                .into_datum(),
+            )],
+        );
+        assert_eq!(result, Some(true));
+    }
+
+    #[pg_test(error = "role \"catalog_test_no_such_role\" does not exist")]
+    fn test_aclitem_parse_invalid() {
+        AclItem::parse("catalog_test_no_such_role=r/postgres");
+    }
+}
@@ -16,6 +16,43 @@ fn func_test_cfg() {}
 #[pg_extern]
 fn func_non_existent_cfg(t: NonexistentType) {}
 
+// an attribute macro sees the `#[cfg]`s which come after it, and gives them to what it generates
+#[pg_extern]
+#[cfg(any(test, feature = "pg_test"))]
+fn func_test_cfg_after_macro() -> i32 {
+    1
+}
+
+#[pg_extern]
+#[cfg(not(any(test, feature = "pg_test")))]
+fn func_test_cfg_disabled_after_macro() {}
+
+#[pg_extern]
+#[cfg(not(any(test, feature = "pg_test")))]
+fn func_test_cfg_disabled_table() -> impl std::iter::Iterator<Item = (name!(a, i32), name!(b, i32))>
+{
+    std::iter::once((1, 2))
+}
+
+#[allow(dead_code)]
+pub struct CfgDisabledCount;
+
+#[pg_aggregate]
+#[cfg(not(any(test, feature = "pg_test")))]
+impl Aggregate for CfgDisabledCount {
+    type State = i32;
+    type Args = i32;
+    const INITIAL_CONDITION: Option<&'static str> = Some("0");
+
+    fn state(
+        current: Self::State,
+        _arg: Self::Args,
+        _fcinfo: pg_sys::FunctionCallInfo,
+    ) -> Self::State {
+        current + 1
+    }
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -28,4 +65,26 @@ mod tests {
     fn test_cfg_exists() {
         Spi::run("SELECT func_test_cfg();");
     }
+
+    #[pg_test]
+    fn test_cfg_after_macro() {
+        assert_eq!(
+            Spi::get_one::<i32>("SELECT func_test_cfg_after_macro();"),
+            Some(1)
+        );
+        // the Rust function is compiled, and called directly, too
+        assert_eq!(super::func_test_cfg_after_macro(), 1);
+    }
+
+    #[pg_test]
+    fn test_cfg_disabled_after_macro() {
+        let count = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_proc WHERE proname IN (
+                 'func_test_cfg_disabled_after_macro',
+                 'func_test_cfg_disabled_table',
+                 'cfgdisabledcount'
+             )",
+        );
+        assert_eq!(count, Some(0));
+    }
 }
@@ -0,0 +1,260 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+#[pg_extern]
+fn coercion_test_to_bigint(value: AnyElement) -> Option<i64> {
+    value.coerce_to::<i64>(PgCoercionContext::Implicit)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_find_coercion_pathway_function() {
+        let castfunc = Spi::get_one::<pg_sys::Oid>(
+            "SELECT castfunc FROM pg_cast WHERE castsource = 'int4'::regtype AND casttarget = 'int8'::regtype",
+        )
+        .expect("no cast from int4 to int8");
+        assert_eq!(
+            find_coercion_pathway(
+                pg_sys::INT4OID,
+                pg_sys::INT8OID,
+                PgCoercionContext::Implicit
+            ),
+            Some(PgCoercionPathway::Function(castfunc))
+        );
+    }
+
+    #[pg_test]
+    fn test_find_coercion_pathway_relabel() {
+        assert_eq!(
+            find_coercion_pathway(
+                pg_sys::VARCHAROID,
+                pg_sys::TEXTOID,
+                PgCoercionContext::Implicit
+            ),
+            Some(PgCoercionPathway::RelabelType)
+        );
+    }
+
+    #[pg_test]
+    fn test_find_coercion_pathway_context() {
+        assert_eq!(
+            find_coercion_pathway(
+                pg_sys::INT8OID,
+                pg_sys::INT4OID,
+                PgCoercionContext::Implicit
+            ),
+            None
+        );
+        assert!(matches!(
+            find_coercion_pathway(
+                pg_sys::INT8OID,
+                pg_sys::INT4OID,
+                PgCoercionContext::Assignment
+            ),
+            Some(PgCoercionPathway::Function(_))
+        ));
+    }
+
+    #[pg_test]
+    fn test_find_coercion_pathway_via_io() {
+        assert_eq!(
+            find_coercion_pathway(
+                pg_sys::INT4OID,
+                pg_sys::TEXTOID,
+                PgCoercionContext::Implicit
+            ),
+            None
+        );
+        assert_eq!(
+            find_coercion_pathway(
+                pg_sys::INT4OID,
+                pg_sys::TEXTOID,
+                PgCoercionContext::Assignment
+            ),
+            Some(PgCoercionPathway::CoerceViaIO)
+        );
+    }
+
+    #[pg_test]
+    fn test_find_coercion_pathway_array() {
+        assert_eq!(
+            find_coercion_pathway(
+                pg_sys::INT4ARRAYOID,
+                unsafe { pg_sys::get_array_type(pg_sys::INT8OID) },
+                PgCoercionContext::Implicit
+            ),
+            Some(PgCoercionPathway::ArrayCoerce)
+        );
+    }
+
+    #[pg_test]
+    fn test_can_coerce_type() {
+        assert!(can_coerce_type(
+            pg_sys::INT4OID,
+            pg_sys::NUMERICOID,
+            PgCoercionContext::Implicit
+        ));
+        assert!(!can_coerce_type(
+            pg_sys::TEXTOID,
+            pg_sys::INT4OID,
+            PgCoercionContext::Implicit
+        ));
+        assert!(can_coerce_type(
+            pg_sys::TEXTOID,
+            pg_sys::INT4OID,
+            PgCoercionContext::Explicit
+        ));
+    }
+
+    #[pg_test]
+    fn test_is_binary_coercible() {
+        assert!(is_binary_coercible(pg_sys::VARCHAROID, pg_sys::TEXTOID));
+        assert!(!is_binary_coercible(pg_sys::INT4OID, pg_sys::INT8OID));
+    }
+
+    #[pg_test]
+    fn test_coerce_to() {
+        let value = coerce_to::<i64>(
+            42i32.into_datum().unwrap(),
+            false,
+            pg_sys::INT4OID,
+            PgCoercionContext::Implicit,
+        );
+        assert_eq!(value, Some(Some(42)));
+    }
+
+    #[pg_test]
+    fn test_coerce_to_null() {
+        let value = coerce_to::<i64>(0, true, pg_sys::INT4OID, PgCoercionContext::Implicit);
+        assert_eq!(value, Some(None));
+    }
+
+    #[pg_test]
+    fn test_coerce_to_context() {
+        let text = "12.5".into_datum().unwrap();
+        assert_eq!(
+            coerce_to::<f64>(text, false, pg_sys::TEXTOID, PgCoercionContext::Implicit),
+            None
+        );
+        assert_eq!(
+            coerce_to::<f64>(text, false, pg_sys::TEXTOID, PgCoercionContext::Explicit),
+            Some(Some(12.5))
+        );
+    }
+
+    #[pg_test]
+    fn test_coerce_to_array() {
+        let value = coerce_to::<Vec<i64>>(
+            vec![1i32, 2, 3].into_datum().unwrap(),
+            false,
+            pg_sys::INT4ARRAYOID,
+            PgCoercionContext::Implicit,
+        );
+        assert_eq!(value, Some(Some(vec![1, 2, 3])));
+    }
+
+    #[pg_test]
+    fn test_coerce_datum_typmod() {
+        // `varchar(3)`
+        let (datum, is_null) = coerce_datum(
+            "abcdef".into_datum().unwrap(),
+            false,
+            pg_sys::TEXTOID,
+            pg_sys::VARCHAROID,
+            3 + pg_sys::VARHDRSZ as i32,
+            PgCoercionContext::Explicit,
+        )
+        .expect("no coercion from text to varchar");
+        let value = unsafe { String::from_datum(datum, is_null, pg_sys::VARCHAROID) };
+        assert_eq!(value.as_deref(), Some("abc"));
+    }
+
+    #[pg_test(
+        error = "value for domain coercion_test_positive violates check constraint \"coercion_test_positive_check\""
+    )]
+    fn test_coerce_datum_domain() {
+        Spi::run("CREATE DOMAIN coercion_test_positive AS int CHECK (VALUE > 0)");
+        let domain = Spi::get_one::<pg_sys::Oid>("SELECT 'coercion_test_positive'::regtype::oid")
+            .expect("no oid for coercion_test_positive");
+        coerce_datum(
+            (-1i32).into_datum().unwrap(),
+            false,
+            pg_sys::INT4OID,
+            domain,
+            -1,
+            PgCoercionContext::Implicit,
+        );
+    }
+
+    #[pg_test]
+    fn test_any_element_coerce_to() {
+        let value = Spi::get_one::<i64>("SELECT coercion_test_to_bigint(7::smallint)");
+        assert_eq!(value, Some(7));
+        let value = Spi::get_one::<i64>("SELECT coercion_test_to_bigint('7'::text)");
+        assert_eq!(value, None);
+    }
+
+    #[pg_test]
+    fn test_into_datum_as() {
+        let numeric = 42i64.into_datum_as(pg_sys::NUMERICOID);
+        let numeric = unsafe { Numeric::from_datum(numeric.unwrap(), false, pg_sys::NUMERICOID) };
+        assert_eq!(numeric.map(|n| n.0).as_deref(), Some("42"));
+
+        let name = String::from("pgx").into_datum_as(pg_sys::NAMEOID);
+        let is_pgx = Spi::get_one_with_args::<bool>(
+            "SELECT $1 = 'pgx'::name",
+            vec![(PgBuiltInOids::NAMEOID.oid(), name)],
+        );
+        assert_eq!(is_pgx, Some(true));
+    }
+
+    #[pg_test]
+    fn test_into_datum_as_same_type() {
+        assert_eq!(7i32.into_datum_as(pg_sys::INT4OID), 7i32.into_datum());
+        assert_eq!(Option::<i32>::None.into_datum_as(pg_sys::INT8OID), None);
+    }
+
+    #[pg_test]
+    fn test_into_datum_as_tupdesc() {
+        Spi::run("CREATE TYPE tests.into_datum_as_row AS (a numeric, b name, c bigint)");
+        let typoid = regtypein("tests.into_datum_as_row");
+        let tupdesc = unsafe { PgTupleDesc::from_pg(pg_sys::lookup_rowtype_tupdesc(typoid, -1)) };
+        let oids = tupdesc
+            .iter()
+            .map(|att| att.type_oid().value())
+            .collect::<Vec<_>>();
+
+        let row = PgComposite::from_values(
+            typoid,
+            vec![
+                1.5f64.into_datum_as(oids[0]),
+                "pgx".into_datum_as(oids[1]),
+                7i32.into_datum_as(oids[2]),
+            ],
+        );
+        assert_eq!(row.get::<Numeric>(0).map(|n| n.0).as_deref(), Some("1.5"));
+        assert_eq!(row.get::<String>(1).as_deref(), Some("pgx"));
+        assert_eq!(row.get::<i64>(2), Some(7));
+    }
+
+    #[pg_test(error = "cannot convert a value of type boolean to type date")]
+    fn test_into_datum_as_no_coercion() {
+        true.into_datum_as(pg_sys::DATEOID);
+    }
+}
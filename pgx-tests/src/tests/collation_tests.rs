@@ -0,0 +1,70 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_extern]
+    fn collation_of(_value: &str, fcinfo: pg_sys::FunctionCallInfo) -> Option<String> {
+        unsafe { PgCollation::from_fcinfo(fcinfo) }.and_then(|collation| collation.name())
+    }
+
+    #[pg_extern]
+    fn collated_cmp(a: &str, b: &str, fcinfo: pg_sys::FunctionCallInfo) -> i32 {
+        let collation = unsafe { PgCollation::from_fcinfo(fcinfo) }.unwrap_or_default();
+        collation.compare(a, b) as i32
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_collation_from_fcinfo() {
+        let name = Spi::get_one::<String>(r#"SELECT tests.collation_of('a' COLLATE "C")"#);
+        assert_eq!(name, Some("C".to_string()));
+
+        let name = Spi::get_one::<String>("SELECT tests.collation_of('a')");
+        assert_eq!(name, Some("default".to_string()));
+    }
+
+    #[pg_test]
+    fn test_collated_cmp() {
+        let result = Spi::get_one::<i32>(r#"SELECT tests.collated_cmp('a', 'B' COLLATE "C")"#);
+        assert_eq!(result, Some(1));
+
+        let result = Spi::get_one::<i32>(r#"SELECT tests.collated_cmp('a', 'a' COLLATE "C")"#);
+        assert_eq!(result, Some(0));
+    }
+
+    #[pg_test]
+    fn test_compare_matches_text_ordering() {
+        let collation = PgCollation::default();
+        for (a, b) in [("apple", "Banana"), ("a", "ab"), ("zebra", "zebra")] {
+            let expected = Spi::get_one::<i32>(&format!(
+                "SELECT CASE WHEN {a} < {b} THEN -1 WHEN {a} > {b} THEN 1 ELSE 0 END",
+                a = quote_literal(a),
+                b = quote_literal(b)
+            ));
+            assert_eq!(Some(collation.compare(a, b) as i32), expected);
+        }
+    }
+
+    #[pg_test]
+    fn test_c_collation() {
+        let collation = PgCollation::c();
+        assert!(collation.is_c());
+        assert_eq!(collation.name(), Some("C".to_string()));
+        assert_eq!(collation.compare("a", "B"), std::cmp::Ordering::Greater);
+    }
+}
@@ -0,0 +1,217 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TYPE composite_test_member AS (name text, age int);
+CREATE TYPE composite_test_team AS (name text, members composite_test_member[]);
+"#,
+    name = "create_composite_test_types",
+);
+
+#[pg_extern]
+fn composite_test_oldest_member(team: PgComposite) -> Option<String> {
+    let members = team.get_by_name::<Vec<PgComposite>>("members")?;
+    members
+        .iter()
+        .max_by_key(|member| member.get::<i32>(1))
+        .and_then(|member| member.get::<String>(0))
+}
+
+#[pg_extern]
+fn composite_test_make_team(name: &str, members: Vec<PgComposite>) -> PgComposite {
+    PgComposite::from_values(
+        regtypein("composite_test_team"),
+        vec![name.into_datum(), members.into_datum()],
+    )
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_array_of_composite_from_datum() {
+        let members = Spi::get_one::<Vec<PgComposite>>(
+            "SELECT ARRAY[ROW('a', 1), ROW('b', NULL)]::composite_test_member[]",
+        )
+        .expect("no members");
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].oid(), regtypein("composite_test_member"));
+        assert_eq!(members[0].get::<String>(0).as_deref(), Some("a"));
+        assert_eq!(members[0].get::<i32>(1), Some(1));
+        assert_eq!(
+            members[1].get_by_name::<String>("name").as_deref(),
+            Some("b")
+        );
+        assert_eq!(members[1].get_by_name::<i32>("age"), None);
+    }
+
+    #[pg_test]
+    fn test_nested_composite_from_datum() {
+        let team = Spi::get_one::<PgComposite>(
+            "SELECT ROW('team', ARRAY[ROW('a', 1), ROW('b', 2)]::composite_test_member[])::composite_test_team",
+        )
+        .expect("no team");
+        assert_eq!(team.len(), 2);
+        let members = team
+            .get::<Vec<PgComposite>>(1)
+            .expect("no members")
+            .iter()
+            .map(|member| member.get::<String>(0).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec!["a", "b"]);
+    }
+
+    #[pg_test]
+    fn test_array_of_composite_into_datum() {
+        let typoid = regtypein("composite_test_member");
+        let members = vec![
+            PgComposite::from_values(typoid, vec!["a".into_datum(), 1.into_datum()]),
+            PgComposite::from_values(typoid, vec!["b".into_datum(), None]),
+        ];
+        let datum = members.into_datum().expect("array was NULL");
+        assert_eq!(
+            unsafe { (*(datum as *mut pg_sys::ArrayType)).elemtype },
+            typoid
+        );
+
+        let members = unsafe { Vec::<PgComposite>::from_datum(datum, false, pg_sys::InvalidOid) }
+            .expect("no members");
+        assert_eq!(members[0].get::<i32>(1), Some(1));
+        assert_eq!(members[1].get::<String>(0).as_deref(), Some("b"));
+        assert_eq!(members[1].get::<i32>(1), None);
+    }
+
+    #[pg_test]
+    fn test_composite_argument() {
+        let oldest = Spi::get_one::<String>(
+            "SELECT composite_test_oldest_member(ROW('team', ARRAY[ROW('a', 1), ROW('b', 2)]::composite_test_member[])::composite_test_team)",
+        );
+        assert_eq!(oldest.as_deref(), Some("b"));
+    }
+
+    #[pg_test]
+    fn test_composite_with_array_of_composite_return() {
+        let team = Spi::get_one::<String>(
+            "SELECT composite_test_make_team('team', ARRAY[ROW('a', 1)]::composite_test_member[])::text",
+        );
+        assert_eq!(team.as_deref(), Some(r#"(team,"{""(a,1)""}")"#));
+    }
+
+    #[pg_test]
+    fn test_composite_try_get() {
+        let team = Spi::get_one::<PgComposite>(
+            "SELECT ROW('team', ARRAY[ROW('a', 1)]::composite_test_member[])::composite_test_team",
+        )
+        .expect("no team");
+        assert_eq!(team.try_get::<String>(0), Ok(Some("team".to_string())));
+        let error = team.try_get_by_name::<Vec<i32>>("members").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "column \"members\" is composite_test_member[], expected integer[] for alloc::vec::Vec<i32>"
+        );
+        let members = team
+            .try_get_by_name::<Vec<PgComposite>>("members")
+            .unwrap()
+            .unwrap();
+        assert!(members[0].try_get::<String>(1).is_err());
+        assert!(members[0].try_get::<i64>(1).is_err());
+        assert_eq!(members[0].try_get::<i32>(1), Ok(Some(1)));
+    }
+
+    #[pg_test(error = "array elements must all be of the same composite type")]
+    fn test_array_of_mixed_composites() {
+        let member = PgComposite::from_values(
+            regtypein("composite_test_member"),
+            vec!["a".into_datum(), 1.into_datum()],
+        );
+        let team = PgComposite::from_values(
+            regtypein("composite_test_team"),
+            vec!["team".into_datum(), None],
+        );
+        vec![member, team].into_datum();
+    }
+
+    #[pg_test]
+    fn test_composite_builder() {
+        let members = vec![("a", 30i64), ("b", 40)]
+            .into_iter()
+            .map(|(name, age)| {
+                PgCompositeBuilder::new(regtypein("composite_test_member"))
+                    .set_by_name("age", age)
+                    .set_by_name("name", name)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let team = PgCompositeBuilder::new(regtypein("composite_test_team"))
+            .set_attr(0, "team")
+            .set_attr(1, members)
+            .build();
+
+        assert_eq!(team.oid(), regtypein("composite_test_team"));
+        assert_eq!(team.get::<String>(0).as_deref(), Some("team"));
+        let oldest = Spi::get_one_with_args::<String>(
+            "SELECT composite_test_oldest_member($1)",
+            vec![(
+                PgOid::from(regtypein("composite_test_team")),
+                team.into_datum(),
+            )],
+        );
+        assert_eq!(oldest.as_deref(), Some("b"));
+    }
+
+    #[pg_test]
+    fn test_composite_builder_nulls() {
+        let member = PgCompositeBuilder::new(regtypein("composite_test_member"))
+            .set_attr(0, "a")
+            .build();
+        assert_eq!(member.get::<String>(0).as_deref(), Some("a"));
+        assert_eq!(member.get::<i32>(1), None);
+
+        let member = PgCompositeBuilder::new(regtypein("composite_test_member"))
+            .set_attr(0, None::<&str>)
+            .set_attr(1, 7)
+            .build();
+        assert_eq!(member.get::<String>(0), None);
+        assert_eq!(member.get::<i32>(1), Some(7));
+    }
+
+    #[pg_test]
+    fn test_composite_builder_from_record_tupdesc() {
+        let row = Spi::get_one::<PgComposite>("SELECT ROW(1, 'a'::text)").expect("no row");
+        let copy = PgCompositeBuilder::from_tupdesc(row.tupdesc())
+            .set_attr(0, 2)
+            .set_attr(1, "b")
+            .build();
+        assert_eq!(copy.oid(), pg_sys::RECORDOID);
+        assert_eq!(copy.typmod(), row.typmod());
+        assert_eq!(copy.get::<i32>(0), Some(2));
+        assert_eq!(copy.get::<String>(1).as_deref(), Some("b"));
+    }
+
+    #[pg_test(error = "attribute 2 is out of bounds for a composite with 2 attributes")]
+    fn test_composite_builder_out_of_bounds() {
+        PgCompositeBuilder::new(regtypein("composite_test_member")).set_attr(2, 1);
+    }
+
+    #[pg_test(error = "cannot convert a value of type text to type integer")]
+    fn test_composite_builder_wrong_type() {
+        PgCompositeBuilder::new(regtypein("composite_test_member")).set_by_name("age", "old");
+    }
+}
@@ -0,0 +1,73 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::time::Duration;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_compute_pool_map() {
+        let numbers = (0..1000i64).collect::<Vec<_>>();
+        let doubled = compute_pool::run(|pool| {
+            assert!(pool.threads() > 0);
+            pool.map(&numbers, |n| n * 2)
+        });
+        assert_eq!(doubled, Ok(numbers.iter().map(|n| n * 2).collect()));
+
+        let empty: Vec<i64> = Vec::new();
+        assert_eq!(
+            compute_pool::run(|pool| pool.map(&empty, |n| *n)),
+            Ok(vec![])
+        );
+    }
+
+    #[pg_test]
+    fn test_compute_pool_spawn() {
+        let answer = compute_pool::run_with_threads(3, |pool| {
+            assert_eq!(pool.threads(), 3);
+            let halves = [pool.spawn(|_| 20), pool.spawn(|_| 22)];
+            halves
+                .into_iter()
+                .map(|half| half.join().unwrap())
+                .sum::<i32>()
+        });
+        assert_eq!(answer, 42);
+    }
+
+    #[pg_test(
+        error = "functions under #[pg_guard] cannot be called from a thread that forbids calling into Postgres"
+    )]
+    fn test_compute_pool_forbids_postgres() {
+        let numbers = vec![1, 2, 3];
+        compute_pool::run(|pool| pool.map(&numbers, |_| unsafe { pg_sys::GetCurrentTimestamp() }))
+            .unwrap();
+    }
+
+    #[cfg(unix)]
+    #[pg_test(error = "canceling statement due to user request")]
+    fn test_compute_pool_query_cancel() {
+        // what `pg_cancel_backend()` does.  Nothing checks for interrupts before the pool runs
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGINT);
+        }
+        let numbers = (0..1000).collect::<Vec<i32>>();
+        compute_pool::run(|pool| {
+            pool.map(&numbers, |_| std::thread::sleep(Duration::from_millis(100)))
+        })
+        .ok();
+    }
+}
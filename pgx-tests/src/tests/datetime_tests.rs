@@ -56,6 +56,45 @@ fn timestamptz_to_i64(tstz: pg_sys::TimestampTz) -> i64 {
     tstz
 }
 
+#[pg_extern]
+fn accept_maybe_infinite_date(d: MaybeInfinite<Date>) -> MaybeInfinite<Date> {
+    d
+}
+
+#[pg_extern]
+fn accept_maybe_infinite_timestamp(t: MaybeInfinite<Timestamp>) -> MaybeInfinite<Timestamp> {
+    t
+}
+
+#[pg_extern]
+fn accept_maybe_infinite_timestamp_with_time_zone(
+    t: MaybeInfinite<TimestampWithTimeZone>,
+) -> MaybeInfinite<TimestampWithTimeZone> {
+    t
+}
+
+#[pg_extern]
+fn accept_interval(i: Interval) -> Interval {
+    i
+}
+
+#[pg_extern]
+fn hourly_bucket(t: TimestampWithTimeZone, zone: &str) -> TimestampWithTimeZone {
+    t.at_time_zone(zone).trunc("hour").at_time_zone(zone)
+}
+
+#[pg_extern]
+fn chrono_weekday(d: Date) -> String {
+    use chrono::Datelike;
+    chrono::NaiveDate::from(d).weekday().to_string()
+}
+
+#[pg_extern]
+fn chrono_next_day(t: Timestamp) -> Timestamp {
+    let next = chrono::NaiveDateTime::from(t) + chrono::Duration::days(1);
+    Timestamp::try_from(next).expect("timestamp out of range")
+}
+
 #[cfg(test)]
 #[pgx::pg_schema]
 mod serialization_tests {
@@ -131,6 +170,25 @@ mod serialization_tests {
             json
         );
     }
+
+    #[test]
+    fn test_maybe_infinite_serialization() {
+        let date = Date::new(
+            time::Date::from_calendar_date(2020, time::Month::try_from(4).unwrap(), 07).unwrap(),
+        );
+        let json = json!({
+            "dates": [
+                MaybeInfinite::<Date>::NegativeInfinity,
+                MaybeInfinite::Finite(date),
+                MaybeInfinite::<Date>::Infinity
+            ]
+        });
+
+        assert_eq!(
+            json!({"dates":["-infinity", "2020-04-07", "infinity"]}),
+            json
+        );
+    }
 }
 
 #[cfg(any(test, feature = "pg_test"))]
@@ -274,4 +332,319 @@ mod tests {
 
         assert_eq!(result, Duration::from_secs(60).as_micros() as i64);
     }
+
+    #[pg_test]
+    fn test_accept_maybe_infinite_date() {
+        let result = Spi::get_one::<bool>(
+            "SELECT accept_maybe_infinite_date('infinity') = 'infinity'::date
+                AND accept_maybe_infinite_date('-infinity') = '-infinity'::date
+                AND accept_maybe_infinite_date('2022-04-01') = '2022-04-01'::date",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+    }
+
+    #[pg_test]
+    fn test_accept_maybe_infinite_timestamp() {
+        let result = Spi::get_one::<bool>(
+            "SELECT accept_maybe_infinite_timestamp('infinity') = 'infinity'::timestamp
+                AND accept_maybe_infinite_timestamp('-infinity') = '-infinity'::timestamp
+                AND accept_maybe_infinite_timestamp('2022-04-01 12:34') = '2022-04-01 12:34'::timestamp
+                AND accept_maybe_infinite_timestamp_with_time_zone('infinity') = 'infinity'::timestamptz",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+    }
+
+    #[pg_test]
+    fn test_maybe_infinite_from_datum() {
+        let date = Spi::get_one::<MaybeInfinite<Date>>("SELECT '-infinity'::date")
+            .expect("failed to get SPI result");
+        assert!(matches!(date, MaybeInfinite::NegativeInfinity));
+        let ts = Spi::get_one::<MaybeInfinite<TimestampWithTimeZone>>(
+            "SELECT '2020-02-18 14:08 -07'::timestamptz",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(ts.finite().map(|ts| ts.hour()), Some(21));
+    }
+
+    #[pg_test(error = "date is infinite, which can only be converted to a `MaybeInfinite<Date>`")]
+    fn test_infinite_date() {
+        Spi::get_one::<Date>("SELECT 'infinity'::date");
+    }
+
+    #[pg_test]
+    fn test_time_crate_conversions() {
+        let date = time::Date::from_calendar_date(2022, time::Month::April, 1).unwrap();
+        assert_eq!(time::Date::from(Date::from(date)), date);
+        let timestamp =
+            time::PrimitiveDateTime::new(date, time::Time::from_hms(12, 34, 56).unwrap());
+        assert_eq!(
+            time::PrimitiveDateTime::from(Timestamp::from(timestamp)),
+            timestamp
+        );
+        let offset = timestamp.assume_offset(time::UtcOffset::from_hms(-7, 0, 0).unwrap());
+        let tstz = TimestampWithTimeZone::from(offset);
+        assert_eq!(tstz.hour(), 19);
+        assert_eq!(time::OffsetDateTime::from(tstz), offset);
+    }
+
+    #[pg_test]
+    fn test_chrono_conversions() {
+        use chrono::TimeZone;
+        use std::convert::TryFrom;
+
+        let result = Spi::get_one::<String>("SELECT chrono_weekday('2022-04-01')")
+            .expect("failed to get SPI result");
+        assert_eq!(result, "Fri");
+        let result = Spi::get_one::<bool>(
+            "SELECT chrono_next_day('2022-02-28 23:59:59.123456') = '2022-03-01 23:59:59.123456'::timestamp",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        Spi::run("SET LOCAL timezone TO 'America/Denver'");
+        let tstz = Spi::get_one::<TimestampWithTimeZone>(
+            "SELECT '2020-02-18 14:08:00.5 -07'::timestamptz",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(
+            chrono::DateTime::<chrono::Utc>::from(tstz),
+            chrono::Utc.with_ymd_and_hms(2020, 2, 18, 21, 8, 0).unwrap()
+                + chrono::Duration::milliseconds(500)
+        );
+        let tstz = TimestampWithTimeZone::try_from(
+            chrono::FixedOffset::west_opt(7 * 3600)
+                .unwrap()
+                .with_ymd_and_hms(2020, 2, 18, 14, 8, 0)
+                .unwrap(),
+        )
+        .unwrap();
+        let result = Spi::get_one_with_args::<bool>(
+            "SELECT $1 = '2020-02-18 14:08 -07'::timestamptz",
+            vec![(PgBuiltInOids::TIMESTAMPTZOID.oid(), tstz.into_datum())],
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        let too_early = chrono::NaiveDate::from_ymd_opt(-10000, 1, 1).unwrap();
+        assert!(Date::try_from(too_early).is_err());
+    }
+
+    #[pg_test]
+    fn test_accept_interval() {
+        let result = Spi::get_one::<bool>(
+            "SELECT accept_interval('1 year 2 days 03:04:05.6') = '1 year 2 days 03:04:05.6'::interval",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+
+        let interval = Spi::get_one::<Interval>("SELECT '1 year 2 days 03:04:05.6'::interval")
+            .expect("failed to get SPI result");
+        assert_eq!(interval.months(), 12);
+        assert_eq!(interval.days(), 2);
+        assert_eq!(interval.micros(), 11_045_600_000);
+    }
+
+    #[pg_test]
+    fn test_interval_arithmetic() {
+        let interval =
+            Interval::from_days(1) + Interval::from_micros(1_000_000) - Interval::from_months(1);
+        assert_eq!(
+            (interval.months(), interval.days(), interval.micros()),
+            (-1, 1, 1_000_000)
+        );
+        let interval = -interval;
+        assert_eq!(
+            (interval.months(), interval.days(), interval.micros()),
+            (1, -1, -1_000_000)
+        );
+
+        let ts = Spi::get_one::<Timestamp>("SELECT '2022-01-31 12:00'::timestamp")
+            .expect("failed to get SPI result");
+        let next_month = ts + Interval::from_months(1);
+        assert_eq!((next_month.month() as u8, next_month.day()), (2, 28));
+        let interval = next_month - ts;
+        assert_eq!((interval.months(), interval.days()), (0, 28));
+        let previous_day = ts - Interval::from_days(1);
+        assert_eq!(previous_day.day(), 30);
+    }
+
+    #[pg_test]
+    fn test_timestamptz_interval_arithmetic() {
+        Spi::run("SET LOCAL timezone TO 'America/Denver'");
+        // the night the clocks went forward
+        let ts =
+            Spi::get_one::<TimestampWithTimeZone>("SELECT '2022-03-12 12:00 -07'::timestamptz")
+                .expect("failed to get SPI result");
+        let next_day = ts + Interval::from_days(1);
+        assert_eq!(next_day.hour(), 18);
+        assert_eq!((next_day - ts).micros(), 82_800_000_000);
+        assert_eq!((next_day - Interval::from_days(1)).hour(), 19);
+    }
+
+    #[pg_test]
+    fn test_timestamptz_unix_epoch() {
+        let ts = TimestampWithTimeZone::from_unix_epoch_micros(1_500_000);
+        let result = Spi::get_one_with_args::<bool>(
+            "SELECT $1 = to_timestamp(1.5)",
+            vec![(PgBuiltInOids::TIMESTAMPTZOID.oid(), ts.into_datum())],
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+        assert_eq!(ts.unix_epoch_micros(), 1_500_000);
+
+        let ts =
+            Spi::get_one::<TimestampWithTimeZone>("SELECT '2022-04-01 00:00 UTC'::timestamptz")
+                .expect("failed to get SPI result");
+        assert_eq!(ts.unix_epoch_micros(), 1_648_771_200_000_000);
+    }
+
+    #[pg_test]
+    fn test_timestamptz_fields_in() {
+        let ts = Spi::get_one::<TimestampWithTimeZone>(
+            "SELECT '2022-07-01 18:30:15.25 UTC'::timestamptz",
+        )
+        .expect("failed to get SPI result");
+        let denver = PgTimeZone::lookup("America/Denver").expect("no America/Denver time zone");
+        assert_eq!(denver.name(), "America/Denver");
+        let fields = ts.fields_in(&denver);
+        assert_eq!(
+            fields,
+            TimestampFields {
+                year: 2022,
+                month: 7,
+                day: 1,
+                hour: 12,
+                minute: 30,
+                second: 15,
+                microsecond: 250_000,
+                utc_offset: -6 * 3600,
+            }
+        );
+
+        let winter = TimestampFields { month: 1, ..fields };
+        let ts = TimestampWithTimeZone::from_fields_in(&winter, &denver);
+        assert_eq!(ts.hour(), 19);
+        assert_eq!(ts.fields_in(&denver).utc_offset, -7 * 3600);
+        assert!(PgTimeZone::lookup("Nowhere/Special").is_none());
+    }
+
+    #[pg_test]
+    fn test_timestamptz_fields_in_session_time_zone() {
+        Spi::run("SET LOCAL timezone TO 'Asia/Kolkata'");
+        let ts =
+            Spi::get_one::<TimestampWithTimeZone>("SELECT '2022-04-01 00:00 UTC'::timestamptz")
+                .expect("failed to get SPI result");
+        let session = PgTimeZone::session();
+        assert_eq!(session.name(), "Asia/Kolkata");
+        let fields = ts.fields_in(&session);
+        assert_eq!(
+            (fields.hour, fields.minute, fields.utc_offset),
+            (5, 30, 19800)
+        );
+    }
+
+    #[pg_test]
+    fn test_at_time_zone() {
+        let ts =
+            Spi::get_one::<TimestampWithTimeZone>("SELECT '2022-04-01 18:30 UTC'::timestamptz")
+                .expect("failed to get SPI result");
+        let local = ts.at_time_zone("America/Denver");
+        assert_eq!(local.hour(), 12);
+        assert_eq!(local.at_time_zone("America/Denver").hour(), 18);
+        assert_eq!(ts.at_time_zone("MST").hour(), 11);
+    }
+
+    #[pg_test]
+    fn test_trunc() {
+        Spi::run("SET LOCAL timezone TO 'America/Denver'");
+        let ts =
+            Spi::get_one::<TimestampWithTimeZone>("SELECT '2022-04-01 03:30 UTC'::timestamptz")
+                .expect("failed to get SPI result");
+        let day = ts.trunc("day");
+        // midnight in Denver, during daylight saving time
+        assert_eq!((day.day(), day.hour()), (31, 6));
+        let local = ts.at_time_zone("America/Denver").trunc("month");
+        assert_eq!((local.month() as u8, local.day(), local.hour()), (3, 1, 0));
+
+        let result = Spi::get_one::<bool>(
+            "SELECT hourly_bucket('2022-04-01 03:30 UTC', 'Asia/Kolkata') = '2022-04-01 03:30 UTC'::timestamptz",
+        )
+        .expect("failed to get SPI result");
+        assert!(result);
+    }
+
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    #[pg_test]
+    fn test_trunc_in_zone() {
+        let ts =
+            Spi::get_one::<TimestampWithTimeZone>("SELECT '2022-04-01 03:30 UTC'::timestamptz")
+                .expect("failed to get SPI result");
+        let day = ts.trunc_in_zone("day", "Asia/Tokyo");
+        assert_eq!((day.day(), day.hour()), (31, 15));
+    }
+
+    #[pg_test]
+    fn test_maybe_infinite_ordering() {
+        let mut dates = Spi::get_one::<Vec<MaybeInfinite<Date>>>(
+            "SELECT ARRAY['infinity', '2022-04-01', '-infinity', '2000-01-01']::date[]",
+        )
+        .expect("failed to get SPI result");
+        dates.sort();
+        let expected = Spi::get_one::<Vec<MaybeInfinite<Date>>>(
+            "SELECT array_agg(d ORDER BY d) FROM unnest(ARRAY['infinity', '2022-04-01', '-infinity', '2000-01-01']::date[]) d",
+        )
+        .expect("failed to get SPI result");
+        assert_eq!(dates, expected);
+        assert_eq!(dates[0], MaybeInfinite::NegativeInfinity);
+        assert!(dates[1].is_finite());
+        assert!(dates[3].is_infinite());
+    }
+
+    #[pg_test]
+    fn test_maybe_infinite_arithmetic() {
+        let ts = Spi::get_one::<MaybeInfinite<Timestamp>>("SELECT 'infinity'::timestamp")
+            .expect("failed to get SPI result");
+        assert_eq!(ts + Interval::from_days(1), MaybeInfinite::Infinity);
+        let ts =
+            Spi::get_one::<MaybeInfinite<TimestampWithTimeZone>>("SELECT '-infinity'::timestamptz")
+                .expect("failed to get SPI result");
+        assert!(matches!(
+            ts - Interval::from_days(1),
+            MaybeInfinite::NegativeInfinity
+        ));
+
+        let ts = Spi::get_one::<MaybeInfinite<Timestamp>>("SELECT '2022-01-31'::timestamp")
+            .expect("failed to get SPI result");
+        let next_month = (ts + Interval::from_months(1)).finite().unwrap();
+        assert_eq!((next_month.month() as u8, next_month.day()), (2, 28));
+    }
+
+    #[pg_test]
+    fn test_maybe_infinite_from_raw() {
+        assert_eq!(
+            MaybeInfinite::<Timestamp>::from(i64::MAX),
+            MaybeInfinite::Infinity
+        );
+        assert_eq!(
+            MaybeInfinite::<Date>::from(i32::MIN),
+            MaybeInfinite::NegativeInfinity
+        );
+        let date = MaybeInfinite::<Date>::from(0).finite().unwrap();
+        assert_eq!((date.year(), date.month() as u8, date.day()), (2000, 1, 1));
+    }
+
+    #[pg_test]
+    fn test_registry_infinite_date() {
+        let converter = lookup_datum_converter(pg_sys::DATEOID).expect("no converter for date");
+        let datum = Spi::get_one::<pg_sys::Datum>("SELECT 'infinity'::date")
+            .expect("failed to get SPI result");
+        let value =
+            unsafe { converter.from_datum(datum, false, pg_sys::DATEOID) }.expect("value was NULL");
+        assert_eq!(
+            value.downcast_ref::<MaybeInfinite<Date>>(),
+            Some(&MaybeInfinite::Infinity)
+        );
+    }
 }
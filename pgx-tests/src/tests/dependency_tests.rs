@@ -0,0 +1,99 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+// always installed, so `CREATE EXTENSION pgx_tests` checks for it and passes
+requires_extension!("plpgsql", ">=1.0");
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn create_table(name: &str) -> pg_sys::Oid {
+        Spi::run(&format!("CREATE TABLE {} (id int)", name));
+        PgRelation::open_with_name_and_share_lock(name)
+            .expect("table was not created")
+            .oid()
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_extension_address() {
+        let extension = extension_address("pgx_tests").expect("pgx_tests is not installed");
+        assert_eq!(extension.classId, pg_sys::ExtensionRelationId);
+        assert!(extension_address("dependency_test_no_such_extension").is_none());
+    }
+
+    #[pg_test]
+    fn test_add_to_extension() {
+        let table = relation_address(create_table("dependency_test_member"));
+        assert_eq!(extension_of_object(&table), None);
+
+        add_to_extension(&table, "pgx_tests");
+        unsafe { pg_sys::CommandCounterIncrement() };
+        let extension = extension_address("pgx_tests").unwrap();
+        assert_eq!(extension_of_object(&table), Some(extension.objectId));
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT deptype::text FROM pg_depend \
+                 WHERE objid = 'dependency_test_member'::regclass \
+                   AND refclassid = 'pg_extension'::regclass"
+            ),
+            Some("e".to_string())
+        );
+
+        // adding it again is a no-op
+        add_to_extension(&table, "pgx_tests");
+    }
+
+    #[pg_test(error = "extension \"dependency_test_no_such_extension\" does not exist")]
+    fn test_add_to_missing_extension() {
+        let table = relation_address(create_table("dependency_test_orphan"));
+        add_to_extension(&table, "dependency_test_no_such_extension");
+    }
+
+    #[pg_test]
+    fn test_record_dependency() {
+        let first = relation_address(create_table("dependency_test_first"));
+        let second = relation_address(create_table("dependency_test_second"));
+        record_dependency(&second, &first, PgDependencyType::Auto);
+        unsafe { pg_sys::CommandCounterIncrement() };
+
+        Spi::run("DROP TABLE dependency_test_first");
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT to_regclass('dependency_test_second') IS NULL"),
+            Some(true)
+        );
+    }
+
+    #[pg_test]
+    fn test_requires_extension() {
+        // from the control file's `requires`, which `cargo pgx install` added
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT EXISTS (SELECT 1 FROM pg_depend \
+                 WHERE classid = 'pg_extension'::regclass \
+                   AND objid = (SELECT oid FROM pg_extension WHERE extname = 'pgx_tests') \
+                   AND refobjid = (SELECT oid FROM pg_extension WHERE extname = 'plpgsql'))"
+            ),
+            Some(true)
+        );
+    }
+
+    #[pg_test]
+    fn test_not_creating_extension() {
+        assert_eq!(creating_extension(), None);
+    }
+}
@@ -0,0 +1,158 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[derive(PostgresDomain, PartialEq, Debug)]
+#[check("VALUE > 0")]
+pub struct DomainTestPositive(i32);
+
+#[derive(PostgresDomain, PartialEq, Debug)]
+#[check("VALUE = lower(VALUE)")]
+#[check("length(VALUE) < 10")]
+pub struct DomainTestLowercase(String);
+
+#[pg_extern]
+fn domain_test_double(value: DomainTestPositive) -> DomainTestPositive {
+    DomainTestPositive(value.0 * 2)
+}
+
+#[pg_extern]
+fn domain_test_negate(value: DomainTestPositive) -> DomainTestPositive {
+    DomainTestPositive(-value.0)
+}
+
+#[derive(PostgresTextEnum, PartialEq, Debug)]
+pub enum DomainTestSpeed {
+    Fast,
+    #[pgx(name = "careful")]
+    Careful,
+}
+
+#[pg_extern]
+fn domain_test_speed(speed: default!(DomainTestSpeed, "'Fast'")) -> &'static str {
+    match speed {
+        DomainTestSpeed::Fast => "fast",
+        DomainTestSpeed::Careful => "careful",
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use crate::tests::domain_tests::{DomainTestLowercase, DomainTestPositive, DomainTestSpeed};
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_domain_exists() {
+        let base = Spi::get_one::<String>(
+            "SELECT typbasetype::regtype::text FROM pg_type WHERE oid = 'DomainTestPositive'::regtype",
+        );
+        assert_eq!(base.as_deref(), Some("integer"));
+    }
+
+    #[pg_test]
+    fn test_domain_argument_and_return() {
+        let value = Spi::get_one::<DomainTestPositive>("SELECT domain_test_double(21)");
+        assert_eq!(value, Some(DomainTestPositive(42)));
+    }
+
+    #[pg_test]
+    fn test_domain_from_domain_datum() {
+        let value = Spi::get_one::<DomainTestLowercase>("SELECT 'pgx'::DomainTestLowercase");
+        assert_eq!(value, Some(DomainTestLowercase("pgx".to_string())));
+    }
+
+    #[pg_test]
+    fn test_domain_try_from_datum() {
+        let domain = DomainTestPositive::type_oid();
+        let datum = 7i32.into_datum().unwrap();
+        unsafe {
+            assert_eq!(
+                DomainTestPositive::try_from_datum(datum, false, domain),
+                Ok(Some(DomainTestPositive(7)))
+            );
+            assert_eq!(
+                DomainTestPositive::try_from_datum(datum, false, pg_sys::INT4OID),
+                Ok(Some(DomainTestPositive(7)))
+            );
+            assert!(DomainTestPositive::try_from_datum(datum, false, pg_sys::TEXTOID).is_err());
+        }
+    }
+
+    #[pg_test(
+        error = "value for domain domaintestpositive violates check constraint \"domaintestpositive_check\""
+    )]
+    fn test_domain_check_in_sql() {
+        Spi::get_one::<DomainTestPositive>("SELECT domain_test_double(-1)");
+    }
+
+    #[pg_test(
+        error = "value for domain domaintestpositive violates check constraint \"domaintestpositive_check\""
+    )]
+    fn test_domain_check_from_base_datum() {
+        Spi::get_one::<DomainTestPositive>("SELECT -1");
+    }
+
+    #[pg_test(
+        error = "value for domain domaintestlowercase violates check constraint \"domaintestlowercase_check1\""
+    )]
+    fn test_domain_check_every_constraint() {
+        Spi::get_one::<DomainTestLowercase>("SELECT 'much too long'");
+    }
+
+    #[pg_test(
+        error = "value for domain domaintestpositive violates check constraint \"domaintestpositive_check\""
+    )]
+    fn test_domain_check_on_return() {
+        Spi::get_one::<i32>("SELECT domain_test_negate(1)");
+    }
+
+    #[pg_test]
+    fn test_text_enum_argument() {
+        let speed = Spi::get_one::<&str>("SELECT domain_test_speed('careful')");
+        assert_eq!(speed, Some("careful"));
+        let speed = Spi::get_one::<&str>("SELECT domain_test_speed()");
+        assert_eq!(speed, Some("fast"));
+    }
+
+    #[pg_test]
+    fn test_text_enum_roundtrip() {
+        let speed = Spi::get_one::<DomainTestSpeed>("SELECT 'careful'::DomainTestSpeed");
+        assert_eq!(speed, Some(DomainTestSpeed::Careful));
+        let speed = Spi::get_one_with_args::<String>(
+            "SELECT $1::text",
+            vec![(
+                PgOid::from(DomainTestSpeed::type_oid()),
+                DomainTestSpeed::Fast.into_datum(),
+            )],
+        );
+        assert_eq!(speed.as_deref(), Some("Fast"));
+    }
+
+    #[pg_test(
+        error = "value for domain domaintestspeed violates check constraint \"domaintestspeed_check\""
+    )]
+    fn test_text_enum_check_in_sql() {
+        Spi::get_one::<&str>("SELECT domain_test_speed('slow')");
+    }
+
+    #[pg_test(
+        error = "invalid value for DomainTestSpeed: \"slow\", expected one of \"Fast\", \"careful\""
+    )]
+    fn test_text_enum_from_text() {
+        Spi::get_one::<DomainTestSpeed>("SELECT 'slow'::text");
+    }
+}
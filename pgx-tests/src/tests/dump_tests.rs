@@ -0,0 +1,42 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_dump_restore() {
+        pgx_tests::check_dump_restore(
+            "CREATE TABLE things (varlena VarlenaType, custom CustomTextFormatSerializedType, json JsonType);
+            INSERT INTO things VALUES
+                ('1.5,2.5,3', '1.5,2.5,3', '{\"a\": 1.5, \"b\": 2.5, \"c\": 3}'),
+                (NULL, NULL, NULL);",
+            &["SELECT varlena::text FROM things WHERE custom IS NOT NULL"],
+        );
+    }
+
+    #[pg_test]
+    fn test_dump_restore_finds_changes() {
+        let failure = pgx_tests::dump_restore("", &["SELECT current_database()"]).unwrap_err();
+        assert!(
+            failure.starts_with("`SELECT current_database()` changed when dumped and restored."),
+            "{}",
+            failure
+        );
+        assert!(failure.contains(pgx_tests::RESTORE_DATABASE));
+    }
+}
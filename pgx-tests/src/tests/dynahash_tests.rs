@@ -0,0 +1,82 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_hash_table_insert_get_remove() {
+        let mut table = PgHashTable::<i32, i64>::new("test table", 4);
+        assert!(table.is_empty());
+
+        // grows past its initial size
+        for key in 0..100 {
+            assert_eq!(table.insert(key, key as i64 * 10), None);
+        }
+        assert_eq!(table.len(), 100);
+        assert_eq!(table.get(&42), Some(420));
+        assert_eq!(table.get(&100), None);
+
+        assert_eq!(table.insert(42, -1), Some(420));
+        *table.get_mut(&42).unwrap() += 1;
+        assert_eq!(table.get(&42), Some(0));
+
+        assert_eq!(table.remove(&42), Some(0));
+        assert_eq!(table.remove(&42), None);
+        assert!(!table.contains_key(&42));
+        assert_eq!(table.len(), 99);
+    }
+
+    #[pg_test]
+    fn test_hash_table_iter_and_retain() {
+        let mut table = PgHashTable::<i32, i32>::new("test table", 16);
+        for key in 0..10 {
+            table.insert(key, key * key);
+        }
+
+        let mut entries = table.iter().collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(
+            entries,
+            (0..10).map(|key| (key, key * key)).collect::<Vec<_>>()
+        );
+
+        // stopping a scan early ends it cleanly
+        assert_eq!(table.iter().take(3).count(), 3);
+
+        table.retain(|key, _| key % 2 == 0);
+        let mut keys = table.iter().map(|(key, _)| key).collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec![0, 2, 4, 6, 8]);
+
+        table.clear();
+        assert!(table.is_empty());
+    }
+
+    #[pg_test]
+    fn test_hash_table_in_memory_context() {
+        let memcxt = PgMemoryContexts::new("test context");
+        let mut table = PgHashTable::<pg_sys::Oid, bool>::new_in(&memcxt, "test table", 8);
+        table.insert(pg_sys::INT4OID, true);
+        assert_eq!(table.get(&pg_sys::INT4OID), Some(true));
+
+        // the table gets its own context, under the one it was created in
+        let child = unsafe { *(*memcxt.value()).firstchild };
+        let name = unsafe { std::ffi::CStr::from_ptr(child.name) };
+        assert_eq!(name.to_str(), Ok("test table"));
+    }
+}
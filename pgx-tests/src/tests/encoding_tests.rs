@@ -0,0 +1,114 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern]
+fn encoding_decode(bytes: &[u8], encoding: &str) -> String {
+    PgEncoding::from_name(encoding)
+        .unwrap_or_else(|| error!("unknown encoding \"{}\"", encoding))
+        .decode(bytes)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_encoding_names() {
+        assert_eq!(PgEncoding::from_name("latin1"), Some(PgEncoding::LATIN1));
+        assert_eq!(PgEncoding::from_name("Shift_JIS"), Some(PgEncoding::SJIS));
+        assert_eq!(PgEncoding::from_name("no such encoding"), None);
+        assert_eq!(PgEncoding::LATIN1.name(), "LATIN1");
+        assert_eq!(PgEncoding::SJIS.to_string(), "SJIS");
+        assert_eq!(
+            PgEncoding::from_id(PgEncoding::UTF8.id()),
+            Some(PgEncoding::UTF8)
+        );
+        assert_eq!(PgEncoding::from_id(-1), None);
+
+        assert!(PgEncoding::UTF8.is_server_encoding());
+        assert!(!PgEncoding::SJIS.is_server_encoding());
+        assert_eq!(PgEncoding::UTF8.max_char_len(), 4);
+        assert_eq!(PgEncoding::LATIN1.max_char_len(), 1);
+    }
+
+    #[pg_test]
+    fn test_encoding_settings() {
+        assert_eq!(
+            Some(PgEncoding::database().name().to_string()),
+            Spi::get_one::<String>("SHOW server_encoding")
+        );
+        Spi::run("SET client_encoding = 'LATIN1'");
+        assert_eq!(PgEncoding::client(), PgEncoding::LATIN1);
+    }
+
+    #[pg_test]
+    fn test_encoding_decode() {
+        assert_eq!(PgEncoding::LATIN1.decode(b"caf\xe9"), "café");
+        assert_eq!(PgEncoding::SJIS.decode(b"\x93\xfa\x96\x7b"), "日本");
+        assert_eq!(PgEncoding::UTF8.decode("café".as_bytes()), "café");
+        assert_eq!(
+            Spi::get_one::<String>("SELECT encoding_decode('\\x636166e9'::bytea, 'latin1')"),
+            Some("café".to_string())
+        );
+    }
+
+    #[pg_test]
+    fn test_encoding_encode() {
+        assert_eq!(PgEncoding::LATIN1.encode("café"), b"caf\xe9");
+        assert_eq!(PgEncoding::SJIS.encode("日本"), b"\x93\xfa\x96\x7b");
+        assert_eq!(
+            convert_encoding(b"caf\xe9", PgEncoding::LATIN1, PgEncoding::WIN1252),
+            b"caf\xe9"
+        );
+        assert_eq!(
+            PgEncoding::LATIN1.to_database(b"caf\xe9"),
+            PgEncoding::database().encode("café")
+        );
+        assert_eq!(
+            PgEncoding::LATIN1.from_database(&PgEncoding::database().encode("café")),
+            b"caf\xe9"
+        );
+    }
+
+    #[pg_test]
+    fn test_encoding_text_p() {
+        let text = PgEncoding::LATIN1.to_text_p(b"caf\xe9");
+        let bytes = unsafe { varlena_to_byte_slice(text.as_ptr()) };
+        assert_eq!(bytes, PgEncoding::database().encode("café"));
+    }
+
+    #[pg_test]
+    fn test_encoding_is_valid() {
+        assert!(PgEncoding::UTF8.is_valid("café".as_bytes()));
+        assert!(!PgEncoding::UTF8.is_valid(b"caf\xe9"));
+        assert!(PgEncoding::LATIN1.is_valid(b"caf\xe9"));
+        assert!(PgEncoding::SQL_ASCII.is_valid(b"\xff"));
+    }
+
+    #[pg_test(error = "invalid byte sequence for encoding \"UTF8\": 0xe9")]
+    fn test_encoding_invalid() {
+        PgEncoding::UTF8.decode(b"caf\xe9");
+    }
+
+    #[pg_test(
+        error = "character with byte sequence 0xe6 0x97 0xa5 in encoding \"UTF8\" has no equivalent in encoding \"LATIN1\""
+    )]
+    fn test_encoding_untranslatable() {
+        PgEncoding::LATIN1.encode("日本");
+    }
+}
@@ -23,13 +23,30 @@ fn take_foo_enum(value: Foo) -> Foo {
     Foo::Three
 }
 
+#[derive(PostgresEnum, PartialEq, Debug)]
+pub enum FooStatus {
+    #[pgx(name = "in progress")]
+    InProgress,
+    Done,
+    #[pgx(order = 0)]
+    Todo,
+}
+
+#[pg_extern]
+fn next_foo_status(value: FooStatus) -> FooStatus {
+    match value {
+        FooStatus::Todo => FooStatus::InProgress,
+        FooStatus::InProgress | FooStatus::Done => FooStatus::Done,
+    }
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
     #[allow(unused_imports)]
     use crate as pgx_tests;
 
-    use crate::tests::enum_type_tests::Foo;
+    use crate::tests::enum_type_tests::{Foo, FooStatus};
     use pgx::*;
 
     #[test]
@@ -41,4 +58,20 @@ mod tests {
             Spi::get_one::<Foo>("SELECT take_foo_enum('One');").expect("failed to get SPI result");
         assert_eq!(Foo::Three, result);
     }
+
+    #[pg_test]
+    fn test_enum_renamed_label() {
+        let result = Spi::get_one::<FooStatus>("SELECT next_foo_status('Todo');")
+            .expect("failed to get SPI result");
+        assert_eq!(FooStatus::InProgress, result);
+        let result = Spi::get_one::<String>("SELECT next_foo_status('in progress')::text;");
+        assert_eq!(result.as_deref(), Some("Done"));
+    }
+
+    #[pg_test]
+    fn test_enum_label_order() {
+        let labels = Spi::get_one::<Vec<String>>("SELECT enum_range(NULL::FooStatus)::text[];")
+            .expect("failed to get SPI result");
+        assert_eq!(labels, vec!["Todo", "in progress", "Done"]);
+    }
 }
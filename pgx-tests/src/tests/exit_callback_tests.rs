@@ -0,0 +1,108 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Where the worker started by the backend with pid `backend_pid` writes what its exit callbacks
+/// see
+fn exit_log_path(backend_pid: i32) -> PathBuf {
+    std::env::temp_dir().join(format!("pgx_tests_exit_callbacks_{}", backend_pid))
+}
+
+fn log_exit(path: &Path, name: &str, code: i32) {
+    let attached = unsafe { !pg_sys::MyProc.is_null() };
+    let mut log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .expect("couldn't open the exit log");
+    writeln!(log, "{} {} {}", name, code, attached).expect("couldn't write the exit log");
+}
+
+#[pg_guard]
+#[no_mangle]
+pub extern "C" fn exit_callback_tests_worker(arg: pg_sys::Datum) {
+    let path = exit_log_path(arg as i32);
+
+    let first = path.clone();
+    register_exit_callback(PgExitCallbackEvent::ProcExit, move |code| {
+        log_exit(&first, "proc_exit first", code)
+    });
+    let second = path.clone();
+    register_exit_callback(PgExitCallbackEvent::ProcExit, move |code| {
+        log_exit(&second, "proc_exit second", code)
+    });
+    let unregistered = path.clone();
+    register_exit_callback(PgExitCallbackEvent::ProcExit, move |code| {
+        log_exit(&unregistered, "unregistered", code)
+    })
+    .unregister_callback();
+    register_exit_callback(PgExitCallbackEvent::BeforeShmemExit, move |code| {
+        log_exit(&path, "before_shmem_exit", code)
+    });
+    // it's called first, and the rest are still called after it panics
+    register_exit_callback(PgExitCallbackEvent::BeforeShmemExit, |_| {
+        panic!("exit callback panicked")
+    });
+
+    proc_exit(0);
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::exit_log_path;
+    use pgx::bgworkers::{BackgroundWorkerBuilder, BackgroundWorkerStatus, BgWorkerStartTime};
+    use pgx::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_exit_callbacks_order() {
+        let backend_pid = unsafe { pg_sys::MyProcPid };
+        let path = exit_log_path(backend_pid);
+        let _ = std::fs::remove_file(&path);
+
+        let worker = BackgroundWorkerBuilder::new("exit_callback_tests worker")
+            .set_start_time(BgWorkerStartTime::ConsistentState)
+            .set_library("pgx_tests")
+            .set_function("exit_callback_tests_worker")
+            .set_argument(Some(backend_pid as pg_sys::Datum))
+            .enable_shmem_access(None)
+            .load_dynamic()
+            .expect("no free background worker slots");
+
+        let start = Instant::now();
+        while worker.status() != BackgroundWorkerStatus::Stopped {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "the worker didn't exit"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let log =
+            std::fs::read_to_string(&path).expect("the worker's exit callbacks wrote nothing");
+        std::fs::remove_file(&path).expect("couldn't remove the exit log");
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            vec![
+                "before_shmem_exit 0 true",
+                "proc_exit second 0 false",
+                "proc_exit first 0 false",
+            ]
+        );
+    }
+}
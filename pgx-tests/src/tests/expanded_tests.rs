@@ -0,0 +1,109 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_extern]
+    fn expanded_array_sum(array: Any) -> String {
+        let array =
+            unsafe { ExpandedArray::<i32>::from_datum(array.datum(), false, array.oid()) }.unwrap();
+        let sum = array.iter().map(|v| v.unwrap_or(0) as i64).sum::<i64>();
+        format!("{} {} {:?}", array.was_expanded(), sum, array.dims())
+    }
+
+    #[pg_test]
+    fn test_expanded_array_from_flat() {
+        let result =
+            Spi::get_one::<String>("SELECT tests.expanded_array_sum(ARRAY[1, 2, NULL, 4])");
+        assert_eq!(result, Some("false 7 [4]".to_string()));
+    }
+
+    #[pg_test]
+    fn test_expanded_array_from_plpgsql() {
+        Spi::run(
+            "CREATE FUNCTION tests.plpgsql_expanded_array() RETURNS text LANGUAGE plpgsql AS $$
+            DECLARE
+                a int[] := ARRAY[[1, 2], [3, 4]];
+            BEGIN
+                a[1][2] := 20;
+                RETURN tests.expanded_array_sum(a);
+            END
+            $$",
+        );
+        let result = Spi::get_one::<String>("SELECT tests.plpgsql_expanded_array()");
+        assert_eq!(result, Some("true 28 [2, 2]".to_string()));
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Counts(Vec<i64>);
+
+    impl PgExpandedObject for Counts {
+        fn flat_size(&self) -> usize {
+            self.0.len() * 8
+        }
+
+        fn flatten_into(&self, data: &mut [u8]) {
+            for (count, bytes) in self.0.iter().zip(data.chunks_mut(8)) {
+                bytes.copy_from_slice(&count.to_ne_bytes());
+            }
+        }
+
+        fn expand(data: &[u8]) -> Self {
+            Counts(
+                data.chunks(8)
+                    .map(|bytes| i64::from_ne_bytes(bytes.try_into().unwrap()))
+                    .collect(),
+            )
+        }
+    }
+
+    #[pg_test]
+    fn test_expanded_object_in_place() {
+        let mut counts = PgExpanded::new(Counts(vec![1, 2]));
+        counts.0.push(3);
+        let datum = counts.into_datum();
+        assert!(unsafe { datum_is_expanded_rw(datum) });
+
+        // a read-write pointer gives back the same object
+        let mut counts = unsafe { PgExpanded::<Counts>::from_datum(datum) };
+        assert_eq!(*counts, Counts(vec![1, 2, 3]));
+        counts.0.push(4);
+        let again = unsafe { PgExpanded::<Counts>::from_datum(datum) };
+        assert_eq!(again.0.len(), 4);
+    }
+
+    #[pg_test]
+    fn test_expanded_object_flattens() {
+        let counts = PgExpanded::new(Counts(vec![10, 20, 30]));
+        let read_only = counts.as_read_only_datum();
+        assert!(unsafe { datum_is_expanded(read_only) });
+        assert!(unsafe { !datum_is_expanded_rw(read_only) });
+
+        // Postgres flattens it like it would to store it
+        let flat = unsafe { pg_sys::pg_detoast_datum(read_only as *mut pg_sys::varlena) };
+        assert_eq!(unsafe { varlena::varsize_any_exhdr(flat) }, 24);
+
+        // and a read-only pointer gets expanded again as a copy
+        let mut copy = unsafe { PgExpanded::<Counts>::from_datum(read_only) };
+        copy.0.clear();
+        assert_eq!(counts.0, vec![10, 20, 30]);
+        let from_flat = unsafe { PgExpanded::<Counts>::from_datum(flat as pg_sys::Datum) };
+        assert_eq!(*from_flat, Counts(vec![10, 20, 30]));
+    }
+}
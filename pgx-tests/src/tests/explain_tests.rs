@@ -0,0 +1,53 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_extern]
+    fn explain_counted(value: i64) -> i64 {
+        add_explain_property(
+            "Last Counted",
+            ExplainProperty::Integer {
+                value,
+                unit: Some("rows"),
+            },
+        );
+        value
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_add_explain_property() {
+        unsafe { register_explain_hooks() };
+        let explain = Spi::get_one::<Json>(
+            "EXPLAIN (ANALYZE, FORMAT JSON) SELECT tests.explain_counted(x) FROM generate_series(1, 3) x",
+        )
+        .expect("EXPLAIN returned null");
+        assert_eq!(explain.0[0]["Last Counted"], serde_json::json!(3));
+    }
+
+    #[pg_test]
+    fn test_add_explain_property_without_analyze() {
+        unsafe { register_explain_hooks() };
+        let explain = Spi::get_one::<Json>(
+            "EXPLAIN (FORMAT JSON) SELECT tests.explain_counted(x) FROM generate_series(1, 3) x",
+        )
+        .expect("EXPLAIN returned null");
+        assert!(explain.0[0].get("Last Counted").is_none());
+        assert!(!explain_analyze_in_progress());
+    }
+}
@@ -0,0 +1,104 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+pg_extension_api! {
+    name = "pgx_tests.counter", major = 1, minor = 1;
+
+    pub struct CounterApi {
+        pub add: extern "C" fn(i64, i64) -> i64,
+        pub negate: extern "C" fn(i64) -> i64,
+    }
+}
+
+// what an extension built against a later version of the API would see
+pg_extension_api! {
+    name = "pgx_tests.counter", major = 1, minor = 2;
+
+    pub struct CounterApiV1_2 {
+        pub add: extern "C" fn(i64, i64) -> i64,
+        pub negate: extern "C" fn(i64) -> i64,
+        pub double: extern "C" fn(i64) -> i64,
+    }
+}
+
+pg_extension_api! {
+    name = "pgx_tests.never_exported", major = 1, minor = 0;
+
+    pub struct NeverExportedApi {
+        pub noop: extern "C" fn(),
+    }
+}
+
+#[pg_guard]
+extern "C" fn counter_add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+#[pg_guard]
+extern "C" fn counter_negate(a: i64) -> i64 {
+    -a
+}
+
+pub static COUNTER_API: ExportedApi<CounterApi> = ExportedApi::new(CounterApi {
+    add: counter_add,
+    negate: counter_negate,
+});
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::{CounterApi, CounterApiV1_2, NeverExportedApi, COUNTER_API};
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_export_and_import() {
+        export_extension_api(&COUNTER_API);
+        // exporting the same API again is a no-op
+        export_extension_api(&COUNTER_API);
+
+        let api = CounterApi::import();
+        assert_eq!((api.add)(40, 2), 42);
+        assert_eq!((api.negate)(7), -7);
+    }
+
+    #[pg_test]
+    fn test_import_not_loaded() {
+        assert_eq!(
+            NeverExportedApi::try_import().err(),
+            Some(ExtensionApiError::NotLoaded {
+                name: "pgx_tests.never_exported"
+            })
+        );
+    }
+
+    #[pg_test]
+    fn test_import_newer_version() {
+        export_extension_api(&COUNTER_API);
+        assert_eq!(
+            CounterApiV1_2::try_import().err(),
+            Some(ExtensionApiError::IncompatibleVersion {
+                name: "pgx_tests.counter",
+                exported: (1, 1),
+                required: (1, 2),
+            })
+        );
+    }
+
+    #[pg_test(error = "extension API \"pgx_tests.never_exported\" is not loaded")]
+    fn test_import_raises_error() {
+        NeverExportedApi::import();
+    }
+}
@@ -0,0 +1,85 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::time::{Duration, Instant};
+
+    fn set_fault_points(faults: &str) {
+        Spi::run(&format!(
+            "SELECT set_config('pgx.fault_points', {}, true)",
+            quote_literal(faults)
+        ));
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[test]
+    fn fault_actions_parse() {
+        assert_eq!("error".parse(), Ok(FaultAction::Error));
+        assert_eq!(" panic ".parse(), Ok(FaultAction::Panic));
+        assert_eq!(
+            "sleep:250".parse(),
+            Ok(FaultAction::Sleep(Duration::from_millis(250)))
+        );
+        assert!("sleep".parse::<FaultAction>().is_err());
+    }
+
+    #[pg_test]
+    fn test_fault_point_without_faults() {
+        fault_point!("fault_tests_unset");
+        set_fault_points("fault_tests_other=error");
+        fault_point!("fault_tests_unset");
+    }
+
+    #[pg_test(error = "fault injected at `fault_tests_error`")]
+    fn test_fault_point_error() {
+        set_fault_points("fault_tests_error=error");
+        fault_point!("fault_tests_error");
+    }
+
+    #[pg_test(error = "fault injected at `fault_tests_panic`")]
+    fn test_fault_point_panic() {
+        set_fault_points("fault_tests_panic=panic");
+        fault_point!("fault_tests_panic");
+    }
+
+    #[pg_test]
+    fn test_fault_point_sleep() {
+        set_fault_points("fault_tests_error=error, fault_tests_sleep=sleep:100");
+        let start = Instant::now();
+        fault_point!("fault_tests_sleep");
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[pg_test]
+    fn test_injected_fault() {
+        set_fault_points("a=error,b = sleep:10");
+        assert_eq!(injected_fault("a"), Ok(Some(FaultAction::Error)));
+        assert_eq!(
+            injected_fault("b"),
+            Ok(Some(FaultAction::Sleep(Duration::from_millis(10))))
+        );
+        assert_eq!(injected_fault("c"), Ok(None));
+    }
+
+    #[pg_test(
+        error = "invalid pgx.fault_points: `explode` isn't a fault action, which are `error`, `panic`, and `sleep:<milliseconds>`"
+    )]
+    fn test_invalid_fault_action() {
+        set_fault_points("fault_tests_invalid=explode");
+        fault_point!("fault_tests_invalid");
+    }
+}
@@ -303,4 +303,110 @@ mod tests {
     fn test_same_name() {
         assert_eq!("test", same_name("test"));
     }
+
+    #[pg_extern]
+    fn context_arg_type(_value: AnyElement, context: FcInfoContext) -> String {
+        RegType(context.arg_type(0)).name()
+    }
+
+    #[pg_extern]
+    fn context_call_context(context: FcInfoContext) -> String {
+        format!("{:?}", context.call_context())
+    }
+
+    #[pg_extern]
+    fn context_count_state(state: i64, _value: i32, context: FcInfoContext) -> i64 {
+        match context.call_context() {
+            CallContext::Aggregate => state + 1,
+            CallContext::WindowAggregate => state + 100,
+            _ => panic!("not called as an aggregate"),
+        }
+    }
+
+    #[pg_extern]
+    fn context_cache_calls(_value: i32, mut context: FcInfoContext) -> i32 {
+        let calls = context.cache(|| 0);
+        *calls += 1;
+        *calls
+    }
+
+    #[pg_extern]
+    fn context_first_arg(context: FcInfoContext, a: i32, b: Option<i32>) -> String {
+        format!("{} {:?} {}", a, b, RegType(context.arg_type(0)).name())
+    }
+
+    #[pg_test]
+    fn test_context_arg_type() {
+        let result = Spi::get_one::<String>("SELECT tests.context_arg_type(1::bigint)");
+        assert_eq!(result, Some("bigint".to_string()));
+    }
+
+    #[pg_test]
+    fn test_context_first_arg() {
+        let result = Spi::get_one::<String>("SELECT tests.context_first_arg(1, 2)");
+        assert_eq!(result, Some("1 Some(2) integer".to_string()));
+
+        let result = Spi::get_one::<String>("SELECT tests.context_first_arg(1, NULL)");
+        assert_eq!(result, Some("1 None integer".to_string()));
+
+        let result = Spi::get_one::<String>("SELECT tests.context_first_arg(NULL, 2)");
+        assert_eq!(result, None);
+    }
+
+    #[pg_test]
+    fn test_context_call_context() {
+        let result = Spi::get_one::<String>("SELECT tests.context_call_context()");
+        assert_eq!(result, Some("Function".to_string()));
+    }
+
+    #[pg_test]
+    fn test_context_aggregate_call_context() {
+        Spi::run(
+            "CREATE AGGREGATE tests.context_count(integer) (
+                sfunc = tests.context_count_state,
+                stype = bigint,
+                initcond = '0'
+            )",
+        );
+
+        let result =
+            Spi::get_one::<i64>("SELECT tests.context_count(x) FROM generate_series(1, 3) x");
+        assert_eq!(result, Some(3));
+
+        let result = Spi::get_one::<i64>(
+            "SELECT tests.context_count(x) OVER () FROM generate_series(1, 3) x LIMIT 1",
+        );
+        assert_eq!(result, Some(300));
+    }
+
+    #[pg_test]
+    fn test_context_cache() {
+        let result = Spi::get_one::<i32>(
+            "SELECT max(tests.context_cache_calls(x)) FROM generate_series(1, 5) x",
+        );
+        assert_eq!(result, Some(5));
+    }
+
+    #[pg_extern]
+    fn fn_extra_cache_calls(value: i32, fcinfo: pg_sys::FunctionCallInfo) -> String {
+        let seen = unsafe { fn_extra_cache(fcinfo, Vec::<i32>::new) };
+        seen.push(value);
+        format!("{:?}", seen)
+    }
+
+    #[pg_test]
+    fn test_fn_extra_cache() {
+        let result = Spi::get_one::<String>(
+            "SELECT string_agg(tests.fn_extra_cache_calls(x), ' ') FROM generate_series(1, 3) x",
+        );
+        assert_eq!(result, Some("[1] [1, 2] [1, 2, 3]".to_string()));
+    }
+
+    #[pg_test]
+    fn test_fn_extra_cache_is_per_call_site() {
+        let result = Spi::get_one::<String>(
+            "SELECT tests.fn_extra_cache_calls(1) || tests.fn_extra_cache_calls(2)",
+        );
+        assert_eq!(result, Some("[1][2]".to_string()));
+    }
 }
@@ -0,0 +1,142 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    static FILES: PgFileArea = PgFileArea::new("pgx_tests_files");
+
+    /// [`FILES`], emptied of what earlier tests left there
+    fn files() -> &'static PgFileArea {
+        let _ = std::fs::remove_dir_all(FILES.directory());
+        &FILES
+    }
+
+    /// Run `f` as a new role without superuser, which is a member of `roles`
+    fn as_role<R>(roles: &[&str], f: impl FnOnce() -> R) -> R {
+        Spi::run("CREATE ROLE files_tests_role NOLOGIN");
+        for role in roles {
+            Spi::run(&format!("GRANT {} TO files_tests_role", role));
+        }
+        with_role(role_oid("files_tests_role").unwrap(), f)
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_files_write_read() {
+        let files = files();
+        assert_eq!(files.list().unwrap(), Vec::<String>::new());
+        assert!(!files.exists("staged/data.csv"));
+
+        files.write("staged/data.csv", b"a,b\n").unwrap();
+        files.append("staged/data.csv", b"1,2\n").unwrap();
+        files.write("notes.txt", b"notes").unwrap();
+        assert!(files.exists("staged/data.csv"));
+        assert_eq!(
+            files.read_to_string("staged/data.csv").unwrap(),
+            "a,b\n1,2\n"
+        );
+        assert_eq!(files.read("notes.txt").unwrap(), b"notes");
+        assert_eq!(files.list().unwrap(), vec!["notes.txt", "staged"]);
+
+        files.remove("notes.txt").unwrap();
+        assert_eq!(
+            files.read("notes.txt").unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[pg_test]
+    fn test_files_directory() {
+        let data_directory = Spi::get_one::<String>("SHOW data_directory").unwrap();
+        assert_eq!(
+            FILES.directory(),
+            std::path::Path::new(&data_directory).join("pgx_tests_files")
+        );
+        assert_eq!(
+            FILES.path("a/b.txt"),
+            FILES.directory().join("a").join("b.txt")
+        );
+    }
+
+    #[pg_test]
+    fn test_files_directory_setting() {
+        static ELSEWHERE: PgFileArea = PgFileArea::new("pgx_tests_elsewhere");
+        ELSEWHERE.define_guc();
+        let directory = std::env::temp_dir().join("pgx_tests_elsewhere");
+        Spi::run(&format!(
+            "SET pgx_tests_elsewhere.directory = '{}'",
+            directory.display()
+        ));
+        assert_eq!(ELSEWHERE.directory(), directory);
+
+        ELSEWHERE.write("file", b"elsewhere").unwrap();
+        assert_eq!(std::fs::read(directory.join("file")).unwrap(), b"elsewhere");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[pg_test(
+        error = "path \"../escape\" is not in the directory of the files of \"pgx_tests_files\""
+    )]
+    fn test_files_parent_path() {
+        files().read("../escape").ok();
+    }
+
+    #[pg_test(
+        error = "path \"/etc/passwd\" is not in the directory of the files of \"pgx_tests_files\""
+    )]
+    fn test_files_absolute_path() {
+        files().read("/etc/passwd").ok();
+    }
+
+    #[cfg(unix)]
+    #[pg_test(
+        error = "path \"link/escape\" is not in the directory of the files of \"pgx_tests_files\""
+    )]
+    fn test_files_symlink() {
+        let files = files();
+        files.write("file", b"").unwrap();
+        std::os::unix::fs::symlink(std::env::temp_dir(), files.path("link")).unwrap();
+        files.write("link/escape", b"escaped").ok();
+    }
+
+    #[pg_test]
+    fn test_files_permissions() {
+        assert!(FILES.can_read());
+        assert!(FILES.can_write());
+        as_role(&[], || {
+            assert!(!FILES.can_read());
+            assert!(!FILES.can_write());
+        });
+    }
+
+    #[cfg(not(feature = "pg10"))]
+    #[pg_test]
+    fn test_files_server_files_roles() {
+        as_role(&["pg_read_server_files"], || {
+            assert!(FILES.can_read());
+            assert!(!FILES.can_write());
+        });
+    }
+
+    #[pg_test(
+        error = "must be superuser or have privileges of the pg_write_server_files role to write the files of \"pgx_tests_files\""
+    )]
+    fn test_files_permission_denied() {
+        let files = files();
+        as_role(&[], || files.write("file", b"denied").ok());
+    }
+}
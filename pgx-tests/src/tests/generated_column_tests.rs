@@ -0,0 +1,69 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE generated_column_test_orders (
+    quantity int4 NOT NULL,
+    price float8 NOT NULL
+);
+"#,
+    name = "create_generated_column_test_orders",
+);
+
+#[pg_extern(immutable)]
+fn generated_column_test_total(quantity: i32, price: f64) -> f64 {
+    quantity as f64 * price
+}
+
+pg_generated_column!(
+    name = "total",
+    on = "generated_column_test_orders",
+    type = "float8",
+    expression = "generated_column_test_total(quantity, price)",
+    requires = [
+        "create_generated_column_test_orders",
+        generated_column_test_total
+    ],
+);
+
+pg_index!(
+    name = "generated_column_test_orders_total",
+    on = "generated_column_test_orders",
+    keys = ["total"],
+    requires = ["generated_column_total_on_generated_column_test_orders"],
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_pg_generated_column() {
+        Spi::run("INSERT INTO generated_column_test_orders (quantity, price) VALUES (3, 1.5)");
+        assert_eq!(
+            Spi::get_one::<String>("SELECT total::text FROM generated_column_test_orders"),
+            Some("4.5".to_string())
+        );
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT EXISTS (SELECT FROM pg_indexes WHERE indexname = 'generated_column_test_orders_total')"
+            ),
+            Some(true)
+        );
+    }
+}
@@ -0,0 +1,38 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_seed_is_set() {
+        let seed = pgx_tests::test_seed().expect("the test wasn't seeded");
+        let setting = Spi::get_one::<String>("SELECT current_setting('pgx.test_seed')");
+        assert_eq!(setting, Some(seed.to_string()));
+    }
+
+    #[pg_test]
+    fn test_random_is_seeded() {
+        let seed = pgx_tests::test_seed().expect("the test wasn't seeded");
+        let first = Spi::get_one::<f64>("SELECT random()");
+        Spi::run(&format!(
+            "SELECT setseed({})",
+            seed as f64 / u32::MAX as f64
+        ));
+        assert_eq!(Spi::get_one::<f64>("SELECT random()"), first);
+    }
+}
@@ -0,0 +1,82 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, PartialEq, Eq, Hash, Serialize, Deserialize, PostgresType, PostgresEq, PostgresHash,
+)]
+pub struct HashedThing(String);
+
+#[pg_extern]
+fn hashed_thing(value: String) -> HashedThing {
+    HashedThing(value)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_hash_extended_seed_zero_matches_hash() {
+        let matches = Spi::get_one::<bool>(
+            "SELECT bool_and(
+                (hashedthing_hash_extended(thing, 0) & 4294967295) = (hashedthing_hash(thing)::bigint & 4294967295)
+            ) FROM (SELECT hashed_thing('thing ' || i) AS thing FROM generate_series(1, 100) AS i) AS things",
+        )
+        .expect("SQL select failed");
+        assert!(matches);
+    }
+
+    #[pg_test]
+    fn test_hash_extended_seeded() {
+        let differs = Spi::get_one::<bool>(
+            "SELECT hashedthing_hash_extended(hashed_thing('thing'), 42) <> hashedthing_hash_extended(hashed_thing('thing'), 0)",
+        )
+        .expect("SQL select failed");
+        assert!(differs);
+
+        let stable = Spi::get_one::<bool>(
+            "SELECT hashedthing_hash_extended(hashed_thing('thing'), 42) = hashedthing_hash_extended(hashed_thing('thing'), 42)",
+        )
+        .expect("SQL select failed");
+        assert!(stable);
+    }
+
+    #[cfg(not(feature = "pg10"))]
+    #[pg_test]
+    fn test_hash_partitioning() {
+        Spi::run(
+            "CREATE TABLE hashed_things (thing HashedThing) PARTITION BY HASH (thing);
+             CREATE TABLE hashed_things_0 PARTITION OF hashed_things FOR VALUES WITH (MODULUS 2, REMAINDER 0);
+             CREATE TABLE hashed_things_1 PARTITION OF hashed_things FOR VALUES WITH (MODULUS 2, REMAINDER 1);
+             INSERT INTO hashed_things SELECT hashed_thing('thing ' || i) FROM generate_series(1, 100) AS i;",
+        );
+
+        let first =
+            Spi::get_one::<i64>("SELECT count(*) FROM hashed_things_0").expect("SQL select failed");
+        let second =
+            Spi::get_one::<i64>("SELECT count(*) FROM hashed_things_1").expect("SQL select failed");
+        assert_eq!(first + second, 100);
+        assert!(first > 0 && second > 0, "every thing went in one partition");
+
+        // rows are found again by pruning down to the partition their hash picks
+        let found = Spi::get_one::<i64>(
+            "SELECT count(*) FROM hashed_things WHERE thing = hashed_thing('thing 7')",
+        )
+        .expect("SQL select failed");
+        assert_eq!(found, 1);
+    }
+}
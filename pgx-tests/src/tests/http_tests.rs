@@ -0,0 +1,138 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::bgworkers::*;
+    use pgx::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    /// A server on a local port answering one request with `response`, whose thread returns the
+    /// request it received
+    fn serve(response: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            // the tests' requests are small enough to arrive before the server answers
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            std::thread::sleep(Duration::from_millis(50));
+            stream.set_nonblocking(true).unwrap();
+            while let Ok(n) = stream.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_http_get() {
+        let (url, server) =
+            serve("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello");
+        let client = HttpClient::new().set_header("X-Test", "yes");
+        let response = client.get(&format!("{}/path?q=1", url)).unwrap();
+        assert_eq!(response.status, 200);
+        assert!(response.is_success());
+        assert_eq!(response.header("content-type"), Some("text/plain"));
+        assert_eq!(response.text(), "hello");
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /path?q=1 HTTP/1.1\r\n"));
+        assert!(request.contains(&format!("Host: {}\r\n", &url["http://".len()..])));
+        assert!(request.contains("User-Agent: pgx\r\n"));
+        assert!(request.contains("X-Test: yes\r\n"));
+    }
+
+    #[pg_test]
+    fn test_http_post() {
+        let (url, server) = serve("HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n");
+        let response = HttpClient::new()
+            .post(&url, "application/json", br#"{"a":1}"#)
+            .unwrap();
+        assert_eq!(response.status, 201);
+        assert!(response.body.is_empty());
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST / HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Type: application/json\r\n"));
+        assert!(request.contains("Content-Length: 7\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"a\":1}"));
+    }
+
+    #[pg_test]
+    fn test_http_chunked() {
+        let (url, server) = serve(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5;x=y\r\npedia\r\n0\r\n\r\n",
+        );
+        let response = HttpClient::new().get(&url).unwrap();
+        assert_eq!(response.text(), "Wikipedia");
+        server.join().unwrap();
+    }
+
+    #[pg_test]
+    fn test_http_status() {
+        let (url, server) = serve("HTTP/1.1 404 Not Found\r\n\r\nmissing");
+        let response = HttpClient::new().get(&url).unwrap();
+        assert_eq!(response.status, 404);
+        assert!(!response.is_success());
+        // without a length, the body ends with the connection
+        assert_eq!(response.text(), "missing");
+        server.join().unwrap();
+    }
+
+    #[pg_test]
+    fn test_http_max_response_size() {
+        let (url, server) = serve("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+        let result = HttpClient::new().set_max_response_size(16).get(&url);
+        assert!(matches!(result, Err(HttpError::InvalidResponse(_))));
+        server.join().unwrap();
+    }
+
+    #[pg_test]
+    fn test_http_timeout() {
+        // accepts, through the listen backlog, but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let client = HttpClient::new().set_timeout(Duration::from_millis(200));
+        assert!(matches!(client.get(&url), Err(HttpError::TimedOut)));
+    }
+
+    #[pg_test]
+    fn test_http_invalid_url() {
+        let client = HttpClient::new();
+        assert!(matches!(
+            client.get("not a url"),
+            Err(HttpError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            client.get("ftp://localhost/file"),
+            Err(HttpError::InvalidUrl(_))
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    /// Trim the `name` of the row being inserted or updated, and count its `edits`
+    #[pg_extern]
+    unsafe fn htup_test_tidy(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+        if !called_as_trigger(fcinfo) {
+            panic!("not called by trigger manager");
+        }
+        let trigdata = &*((*fcinfo).context as *mut pg_sys::TriggerData);
+        let row = PgHeapTuple::from_trigger_data(trigdata);
+        let name = row
+            .get_by_name::<String>("name")
+            .map(|name| name.trim().to_string());
+        let edits = row.get_by_name::<i32>("edits").unwrap_or(0) + 1;
+
+        row.with_updates(&[("name", name.into_datum()), ("edits", edits.into_datum())])
+            .into_pg() as pg_sys::Datum
+    }
+
+    /// Set the row's `note` to its `edits`, given as an `int8`, which is converted to `text`
+    #[pg_extern]
+    unsafe fn htup_test_note(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+        let trigdata = &*((*fcinfo).context as *mut pg_sys::TriggerData);
+        let row = PgHeapTuple::from_trigger_data(trigdata);
+        let edits = row.get_by_name::<i32>("edits").unwrap_or(0) as i64;
+        row.with_update("note", edits).into_pg() as pg_sys::Datum
+    }
+
+    /// Update a column that isn't there
+    #[pg_extern]
+    unsafe fn htup_test_missing(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+        let trigdata = &*((*fcinfo).context as *mut pg_sys::TriggerData);
+        let row = PgHeapTuple::from_trigger_data(trigdata);
+        row.with_update("nope", 1).into_pg() as pg_sys::Datum
+    }
+
+    /// Set the row's `note` to the system columns of the row it updates
+    #[pg_extern]
+    unsafe fn htup_test_system_columns(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+        let trigdata = &*((*fcinfo).context as *mut pg_sys::TriggerData);
+        let row = PgHeapTuple::from_trigger_data(trigdata);
+        let note = PgHeapTuple::old_from_trigger_data(trigdata).map(|old| {
+            let (block, offset) =
+                item_pointer_get_both(old.ctid().expect("the old row has no ctid"));
+            // the row is locked for the update before the trigger's fired, or isn't yet
+            let xmax = old.xmax();
+            assert!(xmax == 0 || xmax == pg_sys::GetCurrentTransactionIdIfAny());
+            format!(
+                "({},{}) {} {}",
+                block,
+                offset,
+                old.xmin(),
+                old.table_oid().expect("the old row has no tableoid")
+            )
+        });
+        row.with_update("note", note).into_pg() as pg_sys::Datum
+    }
+
+    fn create_table(trigger: &str) {
+        Spi::run("CREATE TABLE tests.htup_test (id int, name text, edits int, note text)");
+        Spi::run(&format!(
+            "CREATE TRIGGER htup_test BEFORE INSERT OR UPDATE ON tests.htup_test
+                 FOR EACH ROW EXECUTE PROCEDURE tests.{}()",
+            trigger
+        ));
+    }
+
+    #[pg_test]
+    fn test_trigger_with_updates() {
+        create_table("htup_test_tidy");
+        Spi::run("INSERT INTO tests.htup_test (id, name) VALUES (1, '  alice '), (2, NULL)");
+        assert_eq!(
+            Spi::get_one::<String>("SELECT name FROM tests.htup_test WHERE id = 1").as_deref(),
+            Some("alice")
+        );
+        assert_eq!(
+            Spi::get_one::<i32>("SELECT edits FROM tests.htup_test WHERE id = 1"),
+            Some(1)
+        );
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT name IS NULL FROM tests.htup_test WHERE id = 2"),
+            Some(true)
+        );
+
+        // an UPDATE's trigger changes the new row, and leaves the columns it doesn't name alone
+        Spi::run("UPDATE tests.htup_test SET name = ' bob', note = 'kept' WHERE id = 1");
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT format('%s %s %s', name, edits, note) FROM tests.htup_test WHERE id = 1"
+            )
+            .as_deref(),
+            Some("bob 2 kept")
+        );
+    }
+
+    #[pg_test]
+    fn test_trigger_with_update_converts() {
+        create_table("htup_test_note");
+        Spi::run("INSERT INTO tests.htup_test (id, edits) VALUES (1, 42)");
+        assert_eq!(
+            Spi::get_one::<String>("SELECT note FROM tests.htup_test WHERE id = 1").as_deref(),
+            Some("42")
+        );
+    }
+
+    #[pg_test(error = "tuple has no attribute named `nope`")]
+    fn test_trigger_with_update_unknown_attribute() {
+        create_table("htup_test_missing");
+        Spi::run("INSERT INTO tests.htup_test (id) VALUES (1)");
+    }
+
+    #[pg_test]
+    fn test_trigger_system_columns() {
+        create_table("htup_test_system_columns");
+        Spi::run("INSERT INTO tests.htup_test (id) VALUES (1)");
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT note IS NULL FROM tests.htup_test WHERE id = 1"),
+            Some(true)
+        );
+
+        let before = Spi::get_one::<String>(
+            "SELECT format('%s %s %s', ctid, xmin, tableoid::oid) FROM tests.htup_test",
+        );
+        Spi::run("UPDATE tests.htup_test SET id = 2");
+        assert_eq!(
+            Spi::get_one::<String>("SELECT note FROM tests.htup_test WHERE id = 2"),
+            before
+        );
+    }
+
+    #[pg_test]
+    fn test_form_and_get() {
+        Spi::run("CREATE TYPE tests.htup_test_pair AS (label text, value int)");
+        let tupdesc = unsafe {
+            PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(
+                regtypein("tests.htup_test_pair"),
+                -1,
+            ))
+        };
+
+        let tuple = unsafe { PgHeapTuple::form(tupdesc.as_ptr(), &["x".into_datum(), None]) };
+        assert_eq!(tuple.len(), 2);
+        assert_eq!(tuple.get::<String>(1).as_deref(), Some("x"));
+        assert_eq!(tuple.get_by_name::<i32>("value"), None);
+
+        let tuple = tuple.with_update("value", 7);
+        assert_eq!(tuple.get_by_name::<String>("label").as_deref(), Some("x"));
+        assert_eq!(tuple.get_by_name::<i32>("value"), Some(7));
+
+        // it wasn't read from a table
+        assert!(tuple.ctid().is_none());
+        assert_eq!(tuple.table_oid(), None);
+    }
+
+    #[pg_test(error = "attribute 3 is out of bounds for a tuple with 2 attributes")]
+    fn test_get_out_of_bounds() {
+        Spi::run("CREATE TYPE tests.htup_test_pair AS (label text, value int)");
+        let tupdesc = unsafe {
+            PgTupleDesc::from_pg_is_copy(pg_sys::lookup_rowtype_tupdesc_copy(
+                regtypein("tests.htup_test_pair"),
+                -1,
+            ))
+        };
+        let tuple = unsafe { PgHeapTuple::form(tupdesc.as_ptr(), &[None, None]) };
+        tuple.get::<i32>(3);
+    }
+}
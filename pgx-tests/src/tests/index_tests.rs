@@ -0,0 +1,83 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE index_test_documents (
+    id serial8 NOT NULL,
+    body text
+);
+"#,
+    name = "create_index_test_documents",
+);
+
+#[pg_extern(immutable)]
+fn index_test_normalized(body: &str) -> String {
+    body.trim().to_lowercase()
+}
+
+pg_index!(
+    name = "index_test_documents_normalized",
+    on = "index_test_documents",
+    unique,
+    keys = [("index_test_normalized(body)", "text_pattern_ops")],
+    where = "body IS NOT NULL",
+    requires = ["create_index_test_documents", index_test_normalized],
+);
+
+pg_index!(
+    name = "index_test_documents_id",
+    on = "index_test_documents",
+    using = hash,
+    keys = ["id"],
+    requires = ["create_index_test_documents"],
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_pg_index_expression() {
+        let indexdef = Spi::get_one::<String>(
+            "SELECT indexdef FROM pg_indexes WHERE indexname = 'index_test_documents_normalized'",
+        )
+        .unwrap();
+        assert!(indexdef.starts_with("CREATE UNIQUE INDEX index_test_documents_normalized"));
+        assert!(indexdef.contains("index_test_normalized(body) text_pattern_ops)"));
+        assert!(indexdef.ends_with("WHERE (body IS NOT NULL)"));
+    }
+
+    #[pg_test(
+        error = "duplicate key value violates unique constraint \"index_test_documents_normalized\""
+    )]
+    fn test_pg_index_unique() {
+        Spi::run("INSERT INTO index_test_documents (body) VALUES ('Hello '), (NULL), (NULL)");
+        Spi::run("INSERT INTO index_test_documents (body) VALUES (' hello')");
+    }
+
+    #[pg_test]
+    fn test_pg_index_method() {
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT am.amname::text FROM pg_class c JOIN pg_am am ON am.oid = c.relam \
+                 WHERE c.relname = 'index_test_documents_id'"
+            ),
+            Some("hash".to_string())
+        );
+    }
+}
@@ -12,18 +12,19 @@ mod tests {
     #[allow(unused_imports)]
     use crate as pgx_tests;
     use pgx::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[pg_test]
     fn internal_insert() {
         let mut val = Internal::default();
         assert_eq!(val.initialized(), false);
 
-        let inner = unsafe { val.insert::<i32>(5) };
+        let inner = val.insert::<i32>(5);
 
         assert_eq!(*inner, 5);
         assert_eq!(val.initialized(), true);
 
-        let inner = unsafe { val.insert::<i32>(6) };
+        let inner = val.insert::<i32>(6);
 
         assert_eq!(*inner, 6);
         assert_eq!(val.initialized(), true);
@@ -61,4 +62,102 @@ mod tests {
         assert_eq!(*inner, 5);
         assert_eq!(val.initialized(), true);
     }
+
+    #[pg_test]
+    fn internal_get_checks_type() {
+        let mut val = Internal::new(5i32);
+        assert_eq!(val.get::<i32>(), Some(&5));
+
+        *val.get_mut::<i32>().unwrap() = 6;
+        assert_eq!(val.get::<i32>(), Some(&6));
+        assert_eq!(Internal::default().get::<i32>(), None);
+    }
+
+    #[pg_test(error = "`Internal` holds a `i32`, not a `alloc::string::String`")]
+    fn internal_get_wrong_type() {
+        let val = Internal::new(5i32);
+        val.get::<String>();
+    }
+
+    #[pg_test(error = "`Internal` holds a `i64`, not a `i32`")]
+    fn internal_get_or_insert_wrong_type() {
+        let mut val = Internal::new(5i64);
+        unsafe { val.get_or_insert_default::<i32>() };
+    }
+
+    #[pg_test]
+    fn internal_get_through_datum() {
+        let datum = Internal::new(String::from("state")).into_datum().unwrap();
+        let val = unsafe { Internal::from_datum(datum, false, pg_sys::INTERNALOID) }.unwrap();
+        assert_eq!(val.get::<String>().map(String::as_str), Some("state"));
+    }
+
+    #[pg_test]
+    fn internal_take() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut context = PgMemoryContexts::new("internal_take");
+        let mut val = Internal::new_in(&mut context, Counted);
+        let taken = val.take::<Counted>();
+        assert!(taken.is_some());
+        assert_eq!(val.initialized(), false);
+        assert_eq!(val.take::<Counted>().is_none(), true);
+
+        drop(taken);
+        drop(context);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 1);
+    }
+
+    #[pg_test(
+        error = "`Internal` doesn't hold a value it allocated, which could be checked to be a `i32`"
+    )]
+    fn internal_get_after_take() {
+        let datum = Internal::new(5i32).unwrap();
+        let mut first = Internal::from(datum);
+        let second = Internal::from(datum);
+        assert_eq!(first.take::<i32>(), Some(5));
+        second.get::<i32>();
+    }
+
+    #[pg_test]
+    fn internal_dropped_with_its_context() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut context = PgMemoryContexts::new("internal_dropped_with_its_context");
+        Internal::new_in(&mut context, Counted);
+        Internal::default().insert_in(&mut context, Counted);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+
+        context.reset();
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+        drop(context);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+    }
+
+    #[pg_test]
+    fn internal_drop_panic_is_a_warning() {
+        struct Panics;
+        impl Drop for Panics {
+            fn drop(&mut self) {
+                panic!("couldn't close the file");
+            }
+        }
+
+        let mut context = PgMemoryContexts::new("internal_drop_panic_is_a_warning");
+        Internal::new_in(&mut context, Panics);
+        context.reset();
+        assert_eq!(Spi::get_one::<i32>("SELECT 1"), Some(1));
+    }
 }
@@ -0,0 +1,80 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+// pgx_tests_version(), pgx_tests_build_info(), and pgx_tests_internal_settings()
+pg_introspection_functions!();
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_version() {
+        let version = Spi::get_one::<String>("SELECT pgx_tests_version()");
+        assert_eq!(version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[pg_test]
+    fn test_build_info() {
+        let (pgx_version, profile, pg_version) = Spi::get_three::<String, String, i32>(
+            "SELECT pgx_version, profile, pg_version FROM pgx_tests_build_info()",
+        );
+        assert_eq!(pgx_version.as_deref(), Some(pgx::PGX_VERSION));
+        let expected_profile = if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        };
+        assert_eq!(profile.as_deref(), Some(expected_profile));
+        assert_eq!(pg_version, Some(pg_sys::PG_VERSION_NUM as i32));
+
+        let rows = Spi::get_one::<i64>("SELECT count(*) FROM pgx_tests_build_info()");
+        assert_eq!(rows, Some(1));
+    }
+
+    #[pg_test]
+    fn test_internal_settings() {
+        static GUC: GucSetting<i32> = GucSetting::new(7);
+        GucRegistry::define_int_guc(
+            "test.introspection",
+            "test introspection guc",
+            "test introspection guc",
+            &GUC,
+            0,
+            100,
+            GucContext::Suset,
+        );
+        Spi::run("SET test.introspection = 12");
+
+        let (setting, vartype, context) = Spi::get_three::<String, String, String>(
+            "SELECT setting, vartype, context FROM pgx_tests_internal_settings()
+              WHERE name = 'test.introspection'",
+        );
+        assert_eq!(setting.as_deref(), Some("12"));
+        assert_eq!(vartype.as_deref(), Some("integer"));
+        assert_eq!(context.as_deref(), Some("superuser"));
+
+        // it agrees with pg_settings
+        let disagreements = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pgx_tests_internal_settings() i
+               JOIN pg_settings s USING (name)
+              WHERE (i.setting, i.vartype, i.context, i.short_desc)
+                    IS DISTINCT FROM (s.setting, s.vartype, s.context, s.short_desc)",
+        );
+        assert_eq!(disagreements, Some(0));
+    }
+}
@@ -0,0 +1,119 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern]
+fn jsonpath_test_first_match(path: JsonPath, values: Vec<JsonB>) -> Option<JsonB> {
+    values
+        .into_iter()
+        .find(|value| path.exists(JsonB(value.0.clone())))
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use serde_json::json;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_jsonpath_exists() {
+        let path = JsonPath::compile("$.a ? (@ > 1)");
+        assert!(path.exists(JsonB(json!({"a": 2}))));
+        assert!(!path.exists(JsonB(json!({"a": 1}))));
+        assert!(!path.exists(JsonB(json!({"b": 2}))));
+    }
+
+    #[pg_test]
+    fn test_jsonpath_matches() {
+        let path = JsonPath::compile("$.a > 1");
+        assert_eq!(path.matches(JsonB(json!({"a": 2}))), Some(true));
+        assert_eq!(path.matches(JsonB(json!({"a": 0}))), Some(false));
+        assert_eq!(path.matches(JsonB(json!({"a": "x"}))), None);
+    }
+
+    #[pg_test]
+    fn test_jsonpath_query() {
+        let path = JsonPath::compile("$.items[*] ? (@.price > $min).name")
+            .set_vars(JsonB(json!({"min": 10})));
+        let order = json!({"items": [
+            {"name": "pen", "price": 5},
+            {"name": "lamp", "price": 20},
+            {"name": "desk", "price": 200}
+        ]});
+        assert_eq!(
+            path.query_array(JsonB(order.clone())).0,
+            json!(["lamp", "desk"])
+        );
+        assert_eq!(
+            path.query_first(JsonB(order.clone())).map(|first| first.0),
+            Some(json!("lamp"))
+        );
+        let names = path
+            .query(JsonB(order))
+            .into_iter()
+            .map(|name| name.0)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![json!("lamp"), json!("desk")]);
+        assert!(path.query_first(JsonB(json!({"items": []}))).is_none());
+    }
+
+    #[pg_test]
+    fn test_jsonpath_reused() {
+        let path = JsonPath::compile("$.n");
+        let total: i64 = (0..100)
+            .map(|n| {
+                path.query_first(JsonB(json!({ "n": n })))
+                    .unwrap()
+                    .0
+                    .as_i64()
+                    .unwrap()
+            })
+            .sum();
+        assert_eq!(total, 4950);
+    }
+
+    #[pg_test(error = "JSON object does not contain key \"a\"")]
+    fn test_jsonpath_strict() {
+        JsonPath::compile("strict $.a").exists(JsonB(json!({"b": 1})));
+    }
+
+    #[pg_test]
+    fn test_jsonpath_silent() {
+        let path = JsonPath::compile("strict $.a").set_silent(true);
+        assert!(!path.exists(JsonB(json!({"b": 1}))));
+    }
+
+    #[cfg(not(feature = "pg12"))]
+    #[pg_test]
+    fn test_jsonpath_tz() {
+        Spi::run("SET LOCAL timezone TO 'UTC'");
+        let path = JsonPath::compile(r#"$.t.datetime() < "2022-04-01 12:00:00+00".datetime()"#)
+            .set_tz(true);
+        assert_eq!(
+            path.matches(JsonB(json!({"t": "2022-04-01 00:00:00"}))),
+            Some(true)
+        );
+    }
+
+    #[pg_test]
+    fn test_jsonpath_argument() {
+        let result = Spi::get_one::<JsonB>(
+            r#"SELECT jsonpath_test_first_match('$.a ? (@ > 1)', ARRAY['{"a": 1}', '{"a": 2}']::jsonb[])"#,
+        )
+        .expect("no match");
+        assert_eq!(result.0, json!({"a": 2}));
+    }
+}
@@ -0,0 +1,125 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+pub(crate) const REQUESTS: usize = 0;
+pub(crate) const CONNECTIONS: usize = 1;
+pub(crate) const REQUEST_SECONDS: usize = 2;
+pub(crate) const RENDERED: usize = 3;
+
+/// Put in shared memory by the `_PG_init()` of `bgworker_tests`, as `pgx_tests` is in the test
+/// Postgres' `shared_preload_libraries`.  Each test counts its own metric, as they run at once
+pub(crate) static TEST_METRICS: PgMetrics<4> = PgMetrics::new(
+    "pgx_tests",
+    [
+        PgMetric::counter("requests_total", "Requests served"),
+        PgMetric::gauge("connections", "Open connections"),
+        PgMetric::histogram("request_seconds", "How long requests took", &[0.1, 1.0]),
+        PgMetric::counter(
+            "rendered_total",
+            "Times the metrics were rendered,\nin \\ text",
+        ),
+    ],
+);
+
+pg_metrics_functions!(
+    TEST_METRICS,
+    pgx_tests_metrics,
+    pgx_tests_metrics_prometheus
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::{CONNECTIONS, RENDERED, REQUESTS, REQUEST_SECONDS, TEST_METRICS};
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_metrics_counter() {
+        let before = TEST_METRICS.get(REQUESTS);
+        TEST_METRICS.inc(REQUESTS);
+        TEST_METRICS.add(REQUESTS, 2);
+        assert_eq!(TEST_METRICS.get(REQUESTS), before + 3.0);
+    }
+
+    #[pg_test]
+    fn test_metrics_gauge() {
+        TEST_METRICS.set(CONNECTIONS, 4.0);
+        TEST_METRICS.add_gauge(CONNECTIONS, -1.5);
+        assert_eq!(TEST_METRICS.get(CONNECTIONS), 2.5);
+        assert_eq!(
+            Spi::get_one::<f64>("SELECT value FROM pgx_tests_metrics() WHERE name = 'connections'"),
+            Some(2.5)
+        );
+    }
+
+    #[pg_test]
+    fn test_metrics_histogram() {
+        for seconds in [0.05, 0.5, 0.5, 5.0] {
+            TEST_METRICS.observe(REQUEST_SECONDS, seconds);
+        }
+        let samples = TEST_METRICS
+            .samples()
+            .into_iter()
+            .filter(|sample| sample.name.starts_with("request_seconds"))
+            .map(|sample| (sample.name, sample.le, sample.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            samples,
+            vec![
+                ("request_seconds_bucket".to_string(), Some(0.1), 1.0),
+                ("request_seconds_bucket".to_string(), Some(1.0), 3.0),
+                (
+                    "request_seconds_bucket".to_string(),
+                    Some(f64::INFINITY),
+                    4.0
+                ),
+                ("request_seconds_sum".to_string(), None, 6.05),
+                ("request_seconds_count".to_string(), None, 4.0),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_metrics_prometheus() {
+        TEST_METRICS.inc(RENDERED);
+        let text = Spi::get_one::<String>("SELECT pgx_tests_metrics_prometheus()").unwrap();
+        let rendered = text
+            .lines()
+            .skip_while(|line| {
+                !line.ends_with(
+                    "pgx_tests_rendered_total Times the metrics were rendered,\\nin \\\\ text",
+                )
+            })
+            .take(3)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rendered,
+            vec![
+                "# HELP pgx_tests_rendered_total Times the metrics were rendered,\\nin \\\\ text",
+                "# TYPE pgx_tests_rendered_total counter",
+                "pgx_tests_rendered_total 1",
+            ]
+        );
+        assert!(text.contains("# TYPE pgx_tests_request_seconds histogram\n"));
+        assert!(text.contains("pgx_tests_request_seconds_bucket{le=\"+Inf\"} "));
+    }
+
+    #[pg_test(error = "`pgx_tests` metric `requests_total` is not a gauge")]
+    fn test_metrics_wrong_kind() {
+        TEST_METRICS.set(REQUESTS, 1.0);
+    }
+}
@@ -7,37 +7,111 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
+mod activity_tests;
 mod aggregate_tests;
+mod analyze_tests;
+mod any_tests;
 mod anyarray_tests;
 mod array_tests;
+mod atomics_tests;
+mod attstats_tests;
+mod audit_tests;
+mod backend_tests;
+mod bgworker_tests;
+mod buffile_tests;
 mod bytea_tests;
+mod cache_callback_tests;
+mod cancel_tests;
+mod capabilities_tests;
+mod catalog_table_tests;
+mod catalog_tests;
 mod cfg_tests;
+mod coercion_tests;
+mod collation_tests;
+mod composite_tests;
+mod compute_pool_tests;
 mod datetime_tests;
 mod default_arg_value_tests;
+mod dependency_tests;
 mod derive_pgtype_lifetimes;
+mod domain_tests;
+mod dump_tests;
+mod dynahash_tests;
+mod encoding_tests;
 mod enum_type_tests;
+mod exit_callback_tests;
+mod expanded_tests;
+mod explain_tests;
+mod extension_api_tests;
+mod fault_tests;
 mod fcinfo_tests;
+mod files_tests;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+mod generated_column_tests;
 mod guc_tests;
+mod harness_tests;
+mod hash_tests;
 mod hooks_tests;
+mod htup_tests;
+#[cfg(unix)]
+mod http_tests;
+mod index_tests;
 mod inet_tests;
 mod internal_tests;
+mod introspection_tests;
 mod json_tests;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+mod jsonpath_tests;
 mod lifetime_tests;
 mod log_tests;
 mod memcxt_tests;
+mod metrics_tests;
 mod name_tests;
 mod numeric_tests;
+mod opclass_tests;
+mod partition_tests;
 mod pg_extern_tests;
 mod pg_try_tests;
 mod pgbox_tests;
+mod pgx_types_tests;
+mod portable_tests;
+mod policy_tests;
 mod postgres_type_tests;
+mod privileges_tests;
+mod query_id_tests;
+mod quote_tests;
+mod raw_tests;
+mod reg_tests;
+mod registry_tests;
+mod rel_tests;
+mod reloptions_tests;
+mod replication_tests;
+mod roles_tests;
+mod rollback_callback_tests;
+mod roundtrip_tests;
+mod scheduler_tests;
 mod schema_tests;
+mod seclabel_tests;
+mod secure_spi_tests;
+mod selectivity_tests;
+mod sequence_tests;
+mod shmem_tests;
+mod sortsupport_tests;
 mod spi_tests;
 mod srf_tests;
+mod stringinfo_tests;
 mod struct_type_tests;
+mod support_tests;
+mod temp_table_tests;
+mod text_builder_tests;
+mod tsearch_tests;
+mod tupconvert_tests;
+mod tupdesc_tests;
+mod type_io_tests;
 mod uuid_tests;
 mod variadic_tests;
 mod xact_callback_tests;
 mod xid64_tests;
+mod xml_tests;
 
 pgx::pg_magic_func!();
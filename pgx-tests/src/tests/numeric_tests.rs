@@ -31,6 +31,25 @@ mod tests {
         std::u64::MAX.into()
     }
 
+    #[pg_extern]
+    fn return_a_decimal_numeric() -> Numeric {
+        Numeric::from_decimal(-12345, 2)
+    }
+
+    #[pg_extern]
+    fn numeric_roundtrip(value: Numeric) -> Numeric {
+        value
+    }
+
+    #[pg_extern]
+    fn numeric_display(value: Numeric) -> String {
+        value.to_string()
+    }
+
+    const VALUES: &str = "ARRAY[0, 0.00, -1, 1.5, -0.05, 10000, 123456789.000001, 1e30, 1e-30, \
+        0.0000001, 99999999999999999999999999999999999999, 1e40, 1.2345678901234567890123456789012345678901, \
+        'NaN']::numeric[]";
+
     #[pg_test]
     fn test_return_an_i32_numeric() {
         let result = Spi::get_one::<bool>("SELECT 32::numeric = tests.return_an_i32_numeric();")
@@ -55,6 +74,49 @@ mod tests {
         assert!(result);
     }
 
+    #[pg_test]
+    fn test_return_a_decimal_numeric() {
+        let result = Spi::get_one::<String>("SELECT tests.return_a_decimal_numeric()::text");
+        assert_eq!(result.as_deref(), Some("-123.45"));
+    }
+
+    #[pg_test]
+    fn test_numeric_as_decimal() {
+        let decimal = Spi::get_one::<Numeric>("SELECT 0.0050::numeric").unwrap();
+        assert_eq!(decimal.as_decimal(), Some((50, 4)));
+        let large = Spi::get_one::<Numeric>("SELECT 1e40::numeric").unwrap();
+        assert_eq!(large.as_decimal(), None);
+        assert_eq!(large.to_string(), format!("1{}", "0".repeat(40)));
+        let nan = Spi::get_one::<Numeric>("SELECT 'NaN'::numeric").unwrap();
+        assert_eq!(nan.as_decimal(), None);
+        assert_eq!(nan.to_string(), "NaN");
+
+        assert_eq!(Numeric::from("-0.05").as_decimal(), Some((-5, 2)));
+        assert_eq!(Numeric::from("1e5").as_decimal(), None);
+        assert_eq!(Numeric::from("+5").as_decimal(), None);
+        assert_eq!(Numeric::from("5.").as_decimal(), None);
+    }
+
+    #[pg_test]
+    fn test_numeric_from_text() {
+        // the text that isn't written as a decimal goes through `numeric_in()`
+        for (text, expected) in [("1.50", "1.50"), ("1e5", "100000"), (" +5 ", "5")] {
+            let datum = Numeric(text.to_string()).into_datum();
+            let numeric = unsafe { Numeric::from_datum(datum.unwrap(), false, pg_sys::NUMERICOID) };
+            assert_eq!(numeric.map(|n| n.0).as_deref(), Some(expected));
+        }
+    }
+
+    #[pg_test]
+    fn test_numeric_roundtrip() {
+        let result = Spi::get_one::<bool>(&format!(
+            "SELECT bool_and(tests.numeric_roundtrip(v)::text = v::text \
+                AND tests.numeric_display(v) = v::text) FROM unnest({}) v",
+            VALUES
+        ));
+        assert_eq!(result, Some(true));
+    }
+
     #[pg_test]
     fn test_deserialize_numeric() {
         use serde_json::json;
@@ -0,0 +1,172 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    PostgresType,
+    PostgresEq,
+    PostgresOrd,
+    PostgresHash,
+)]
+pub struct OpclassThing(String);
+
+impl OpclassThing {
+    pub fn samples() -> Vec<Self> {
+        ["banana", "apple", "", "cherry", "apple pie", "Zebra"]
+            .iter()
+            .map(|name| OpclassThing(name.to_string()))
+            .collect()
+    }
+}
+
+/// Money in cents, whose operators are also members of the built-in `integer_ops` families, and
+/// whose operator classes aren't the default ones
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    PostgresType,
+    PostgresEq,
+    PostgresOrd,
+    PostgresHash,
+)]
+#[pgx(
+    default_opclass = false,
+    btree_family = "integer_ops",
+    hash_family = "integer_ops"
+)]
+pub struct Cents(i64);
+
+/// An `int4` whose `Ord` is backwards, so `int4`'s operator class doesn't agree with it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backwards(i32);
+
+impl PartialOrd for Backwards {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Backwards {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+impl FromDatum for Backwards {
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: pg_sys::Oid) -> Option<Self> {
+        i32::from_datum(datum, is_null, typoid).map(Backwards)
+    }
+}
+
+impl IntoDatum for Backwards {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.0.into_datum()
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::INT4OID
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::{Backwards, OpclassThing};
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    pgx_tests::opclass_test!(
+        test_opclass_thing_btree,
+        OpclassThing,
+        btree,
+        values = OpclassThing::samples()
+    );
+    pgx_tests::opclass_test!(
+        test_opclass_thing_hash,
+        OpclassThing,
+        hash,
+        values = OpclassThing::samples()
+    );
+    pgx_tests::opclass_test!(
+        test_int4_btree,
+        i32,
+        btree,
+        values = vec![3, -1, 0, i32::MAX]
+    );
+    pgx_tests::opclass_test!(test_int4_hash, i32, hash, values = vec![3, -1, 0, i32::MAX]);
+
+    #[pg_test]
+    fn test_opclass_not_default() {
+        let defaults = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_opclass WHERE opcintype = 'Cents'::regtype AND opcdefault",
+        );
+        assert_eq!(defaults, Some(0));
+        Spi::run("CREATE TABLE cents_check (value Cents)");
+        Spi::run("CREATE INDEX ON cents_check (value Cents_btree_ops)");
+        Spi::run("CREATE INDEX ON cents_check USING hash (value Cents_hash_ops)");
+    }
+
+    /// How many of `Cents`' operators (`amop`) or support functions (`amproc`) are in the
+    /// `integer_ops` family of `am`
+    fn integer_ops_members(am: &str, prefix: &str) -> Option<i64> {
+        Spi::get_one::<i64>(&format!(
+            "SELECT count(*) FROM pg_{prefix}
+               JOIN pg_opfamily f ON f.oid = {prefix}family
+               JOIN pg_am am ON am.oid = f.opfmethod
+              WHERE f.opfname = 'integer_ops' AND am.amname = '{am}'
+                AND {prefix}lefttype = 'Cents'::regtype AND {prefix}righttype = 'Cents'::regtype",
+            prefix = prefix,
+            am = am,
+        ))
+    }
+
+    #[pg_test]
+    fn test_operator_family_members() {
+        assert_eq!(integer_ops_members("btree", "amop"), Some(5));
+        assert_eq!(integer_ops_members("btree", "amproc"), Some(2));
+        assert_eq!(integer_ops_members("hash", "amop"), Some(1));
+        let hash_functions = if pg_sys::PG_VERSION_NUM >= 110000 {
+            2
+        } else {
+            1
+        };
+        assert_eq!(integer_ops_members("hash", "amproc"), Some(hash_functions));
+    }
+
+    #[pg_test(
+        error = "`SELECT value FROM pgx_opclass_check ORDER BY value` returned [Backwards(1), Backwards(1), Backwards(2), Backwards(2)], rather than [Backwards(2), Backwards(2), Backwards(1), Backwards(1)]"
+    )]
+    fn test_opclass_disagrees_with_ord() {
+        pgx_tests::check_btree_opclass(vec![Backwards(1), Backwards(2)]);
+    }
+}
@@ -0,0 +1,205 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE partition_test_range (id int, region text) PARTITION BY RANGE (id);
+CREATE TABLE partition_test_range_low PARTITION OF partition_test_range FOR VALUES FROM (MINVALUE) TO (100);
+CREATE TABLE partition_test_range_high PARTITION OF partition_test_range FOR VALUES FROM (100) TO (200)
+    PARTITION BY LIST (region);
+CREATE TABLE partition_test_range_high_east PARTITION OF partition_test_range_high FOR VALUES IN ('east');
+CREATE TABLE partition_test_range_high_west PARTITION OF partition_test_range_high FOR VALUES IN ('west', NULL);
+
+CREATE TABLE partition_test_list (code text, dropped int, region text) PARTITION BY LIST (region);
+ALTER TABLE partition_test_list DROP COLUMN dropped;
+CREATE TABLE partition_test_list_north (region text, code text);
+ALTER TABLE partition_test_list ATTACH PARTITION partition_test_list_north FOR VALUES IN ('north');
+
+CREATE TABLE partition_test_expr (id int, region text) PARTITION BY LIST (lower(region));
+CREATE TABLE partition_test_expr_east PARTITION OF partition_test_expr FOR VALUES IN ('east');
+CREATE TABLE partition_test_expr_west PARTITION OF partition_test_expr FOR VALUES IN ('west');
+"#,
+    name = "create_partition_test_tables",
+);
+
+#[pg_extern]
+fn partition_test_route(relation: PgRelation, id: i32, region: Option<&str>) -> Option<String> {
+    let router = PartitionRouter::new(&relation, pg_sys::AccessShareLock as pg_sys::LOCKMODE);
+    router
+        .find_partition(vec![id.into_datum(), region.into_datum()])
+        .map(|oid| unsafe { PgRelation::open(oid) }.name().to_string())
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn open(name: &str) -> PgRelation {
+        PgRelation::open_with_name_and_share_lock(name).expect("no such relation")
+    }
+
+    fn partition_name(oid: Option<pg_sys::Oid>) -> Option<String> {
+        oid.map(|oid| unsafe { PgRelation::open(oid) }.name().to_string())
+    }
+
+    fn router(name: &str) -> PartitionRouter {
+        PartitionRouter::new(&open(name), pg_sys::AccessShareLock as pg_sys::LOCKMODE)
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_route_to_leaf_partitions() {
+        let router = router("partition_test_range");
+        let route = |id: i32, region: Option<&str>| {
+            partition_name(router.find_partition(vec![id.into_datum(), region.into_datum()]))
+        };
+        assert_eq!(
+            route(-5, Some("east")).as_deref(),
+            Some("partition_test_range_low")
+        );
+        assert_eq!(
+            route(150, Some("east")).as_deref(),
+            Some("partition_test_range_high_east")
+        );
+        assert_eq!(
+            route(150, Some("west")).as_deref(),
+            Some("partition_test_range_high_west")
+        );
+        assert_eq!(
+            route(150, None).as_deref(),
+            Some("partition_test_range_high_west")
+        );
+        assert_eq!(route(150, Some("south")), None);
+        assert_eq!(route(500, Some("east")), None);
+        assert_eq!(router.leaf_partitions().len(), 3);
+        assert!(router.default_partitions().is_empty());
+    }
+
+    #[cfg(not(feature = "pg10"))]
+    #[pg_test]
+    fn test_route_null_range_key() {
+        let router = router("partition_test_range");
+        assert_eq!(router.find_partition(vec![None, "east".into_datum()]), None);
+    }
+
+    #[cfg(feature = "pg10")]
+    #[pg_test(error = "range partition key of row contains null")]
+    fn test_route_null_range_key() {
+        router("partition_test_range").find_partition(vec![None, "east".into_datum()]);
+    }
+
+    #[pg_test]
+    fn test_route_by_expression() {
+        let router = router("partition_test_expr");
+        let route = |region: &str| {
+            partition_name(router.find_partition(vec![1.into_datum(), region.into_datum()]))
+        };
+        assert_eq!(route("EAST").as_deref(), Some("partition_test_expr_east"));
+        assert_eq!(route("West").as_deref(), Some("partition_test_expr_west"));
+        assert_eq!(route("north"), None);
+    }
+
+    #[cfg(not(feature = "pg10"))]
+    #[pg_test]
+    fn test_route_like_insert() {
+        Spi::run(
+            "CREATE TABLE partition_test_hash (id int, name text) PARTITION BY HASH (name, id);
+             CREATE TABLE partition_test_hash_0 PARTITION OF partition_test_hash
+                 FOR VALUES WITH (MODULUS 2, REMAINDER 0);
+             CREATE TABLE partition_test_hash_1 PARTITION OF partition_test_hash
+                 FOR VALUES WITH (MODULUS 4, REMAINDER 1);
+             CREATE TABLE partition_test_hash_3 PARTITION OF partition_test_hash
+                 FOR VALUES WITH (MODULUS 4, REMAINDER 3);",
+        );
+        let router = router("partition_test_hash");
+        for id in 0..50 {
+            let name = if id % 3 == 0 {
+                None
+            } else {
+                Some(format!("name {}", id))
+            };
+            let inserted = Spi::get_one_with_args::<String>(
+                "WITH i AS (
+                     INSERT INTO partition_test_hash VALUES ($1, $2) RETURNING tableoid::regclass
+                 )
+                 SELECT tableoid::text FROM i",
+                vec![
+                    (PgBuiltInOids::INT4OID.oid(), id.into_datum()),
+                    (PgBuiltInOids::TEXTOID.oid(), name.clone().into_datum()),
+                ],
+            );
+            let routed =
+                partition_name(router.find_partition(vec![id.into_datum(), name.into_datum()]));
+            assert_eq!(routed, inserted, "row {}", id);
+        }
+    }
+
+    #[pg_test]
+    fn test_route_with_dropped_columns() {
+        // the partition's columns are in a different order than the partitioned table's, which
+        // also has a dropped column
+        let router = router("partition_test_list");
+        let partition = router.find_partition(vec!["a".into_datum(), None, "north".into_datum()]);
+        assert_eq!(
+            partition_name(partition).as_deref(),
+            Some("partition_test_list_north")
+        );
+        assert_eq!(
+            router.find_partition(vec!["a".into_datum(), None, "south".into_datum()]),
+            None
+        );
+    }
+
+    #[cfg(not(feature = "pg10"))]
+    #[pg_test]
+    fn test_route_to_default_partition() {
+        Spi::run(
+            "CREATE TABLE partition_test_range_other PARTITION OF partition_test_range DEFAULT",
+        );
+        let router = router("partition_test_range");
+        let other = open("partition_test_range_other").oid();
+        assert_eq!(router.default_partitions(), vec![other]);
+        assert_eq!(router.leaf_partitions().last(), Some(&other));
+        assert_eq!(
+            router.find_partition(vec![500.into_datum(), "east".into_datum()]),
+            Some(other)
+        );
+        assert_eq!(
+            router.find_partition(vec![None, "east".into_datum()]),
+            Some(other)
+        );
+        // rows a partitioned partition's bound accepts aren't routed to the root's default, even
+        // if no partition of it accepts them
+        assert_eq!(
+            router.find_partition(vec![150.into_datum(), "south".into_datum()]),
+            None
+        );
+    }
+
+    #[pg_test]
+    fn test_route_from_sql() {
+        let partition = Spi::get_one::<String>(
+            "SELECT partition_test_route('partition_test_range', 150, 'east')",
+        );
+        assert_eq!(partition.as_deref(), Some("partition_test_range_high_east"));
+    }
+
+    #[pg_test(error = "\"partition_test_range_low\" is not a partitioned table")]
+    fn test_router_for_unpartitioned_table() {
+        router("partition_test_range_low");
+    }
+}
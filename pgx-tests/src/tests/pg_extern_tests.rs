@@ -8,12 +8,54 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use pgx::*;
+use serde::{Deserialize, Serialize};
 
 #[pg_extern(immutable)]
 fn returns_tuple_with_attributes() -> (name!(arg, String), name!(arg2, String)) {
     ("hi".to_string(), "bye".to_string())
 }
 
+#[pg_extern(immutable, instances(i32, i64, f64), name = "sum_array")]
+fn sum_array<T: std::iter::Sum>(values: Vec<T>) -> T {
+    values.into_iter().sum()
+}
+
+#[derive(Serialize, Deserialize, PostgresType)]
+pub struct ExternPoint {
+    x: f64,
+    y: f64,
+}
+
+#[pg_extern]
+impl ExternPoint {
+    #[pg_extern(immutable)]
+    fn new(x: f64, y: f64) -> Self {
+        ExternPoint { x, y }
+    }
+
+    #[pg_extern(immutable)]
+    fn distance(&self, other: Self) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+trait Describe {
+    fn describe(&self) -> String;
+}
+
+#[pg_extern]
+impl Describe for ExternPoint {
+    #[pg_extern(immutable)]
+    fn describe(&self) -> String {
+        format!("({}, {})", self.x, self.y)
+    }
+}
+
+#[pg_extern(immutable, parallel_safe, inline = "a * 2 + b")]
+fn extern_inline(a: i32, b: i32) -> i32 {
+    a * 2 + b
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -51,4 +93,103 @@ mod tests {
             .expect("failed to get SPI result");
         assert!(result)
     }
+
+    #[pg_extern]
+    fn greet_with_optional_title(name: &str, title: Option<&str>) -> String {
+        match title {
+            Some(title) => format!("Hello, {} {}", title, name),
+            None => format!("Hello, {}", name),
+        }
+    }
+
+    #[pg_extern]
+    fn repeat_with_optional_separator(
+        value: String,
+        times: i32,
+        separator: Option<String>,
+    ) -> impl std::iter::Iterator<Item = String> {
+        let separator = separator.unwrap_or_default();
+        (0..times).map(move |_| format!("{}{}", value, separator))
+    }
+
+    #[pg_test]
+    fn test_mixed_optional_args_are_not_strict() {
+        let result = Spi::get_one::<bool>(
+            "SELECT proisstrict FROM pg_proc WHERE proname = 'greet_with_optional_title'",
+        )
+        .expect("failed to get SPI result");
+        assert!(!result)
+    }
+
+    #[pg_test]
+    fn test_mixed_optional_args_with_null_optional() {
+        let result = Spi::get_one::<String>("SELECT tests.greet_with_optional_title('Ada', NULL)");
+        assert_eq!(result, Some("Hello, Ada".to_string()))
+    }
+
+    #[pg_test]
+    fn test_mixed_optional_args_with_both() {
+        let result = Spi::get_one::<String>("SELECT tests.greet_with_optional_title('Ada', 'Dr.')");
+        assert_eq!(result, Some("Hello, Dr. Ada".to_string()))
+    }
+
+    #[pg_test]
+    fn test_mixed_optional_args_with_null_required() {
+        let result = Spi::get_one::<String>("SELECT tests.greet_with_optional_title(NULL, 'Dr.')");
+        assert_eq!(result, None)
+    }
+
+    #[pg_test]
+    fn test_mixed_optional_args_srf_with_null_required() {
+        let count = Spi::get_one::<i64>(
+            "SELECT count(*) FROM tests.repeat_with_optional_separator('a', NULL, '-')",
+        );
+        assert_eq!(count, Some(0));
+
+        let count = Spi::get_one::<i64>(
+            "SELECT count(*) FROM tests.repeat_with_optional_separator('a', 3, NULL)",
+        );
+        assert_eq!(count, Some(3))
+    }
+    #[pg_test]
+    fn test_generic_instances() {
+        let result = Spi::get_one::<String>(
+            "SELECT concat_ws(' ', sum_array(ARRAY[1, 2]::int[]), sum_array(ARRAY[3, 4]::bigint[]), \
+             sum_array(ARRAY[0.5, 0.25]::float8[]))",
+        );
+        assert_eq!(result, Some("3 7 0.75".to_string()));
+        let count = Spi::get_one::<i64>(
+            "SELECT count(*) FROM pg_proc WHERE proname = 'sum_array' AND provolatile = 'i'",
+        );
+        assert_eq!(count, Some(3))
+    }
+
+    #[pg_test]
+    fn test_impl_functions() {
+        let distance = Spi::get_one::<f64>(
+            "SELECT extern_point_distance(extern_point_new(0, 0), extern_point_new(3, 4))",
+        );
+        assert_eq!(distance, Some(5.0));
+        let description =
+            Spi::get_one::<String>("SELECT extern_point_describe(extern_point_new(1, 2))");
+        assert_eq!(description, Some("(1, 2)".to_string()))
+    }
+
+    #[pg_test]
+    fn test_inline() {
+        assert_eq!(super::extern_inline(3, 1), 7);
+        let result = Spi::get_one::<i32>("SELECT extern_inline(3, 1)");
+        assert_eq!(result, Some(7));
+        let language = Spi::get_one::<String>(
+            "SELECT lanname::text FROM pg_proc JOIN pg_language ON pg_language.oid = prolang \
+             WHERE pg_proc.oid = 'extern_inline'::regproc",
+        );
+        assert_eq!(language, Some("sql".to_string()));
+        // the planner replaced the call with the expression itself
+        let explain = Spi::get_one::<Json>(
+            "EXPLAIN (VERBOSE, FORMAT JSON) SELECT extern_inline(x, 1) FROM generate_series(1, 3) x",
+        )
+        .expect("EXPLAIN returned null");
+        assert_eq!(explain.0[0]["Plan"]["Output"][0], "((x * 2) + 1)");
+    }
 }
@@ -8,6 +8,8 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use pgx::*;
+use std::ffi::CStr;
+use std::sync::atomic::AtomicBool;
 
 // if our Postgres ERROR and Rust panic!() handling is incorrect, this little bit of useless code
 // will crash postgres.  If things are correct it'll simply raise an ERROR saying "panic in walker".
@@ -30,19 +32,81 @@ extern "C" fn walker() -> bool {
     panic!("panic in walker");
 }
 
+/// Whether panics capture a backtrace, which `test_panic_with_backtrace` only turns on while it
+/// needs them, as `set_panic_backtraces()` can't be undone
+static BACKTRACES: AtomicBool = AtomicBool::new(false);
+
+#[pg_extern]
+fn panic_with_backtrace() {
+    panic!("panic with a backtrace");
+}
+
+/// The message of the ERROR that running `sql` raises, and its `DETAIL` that only goes to the
+/// server log, caught in a subtransaction as PL/pgSQL does for a `BEGIN ... EXCEPTION` block
+fn caught_error(sql: &str) -> (String, Option<String>) {
+    unsafe fn text(ptr: *const std::os::raw::c_char) -> Option<String> {
+        (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+
+    unsafe {
+        let context = pg_sys::CurrentMemoryContext;
+        let owner = pg_sys::CurrentResourceOwner;
+        pg_sys::BeginInternalSubTransaction(std::ptr::null_mut());
+        let result = std::panic::catch_unwind(|| Spi::run(sql));
+        pg_sys::CurrentMemoryContext = context;
+        let error = match result {
+            Ok(()) => panic!("`{}` didn't raise an ERROR", sql),
+            Err(e) if e.downcast_ref::<pg_sys::JumpContext>().is_some() => {
+                let edata = pg_sys::CopyErrorData();
+                pg_sys::FlushErrorState();
+                let error = (
+                    text((*edata).message).unwrap_or_default(),
+                    text((*edata).detail_log),
+                );
+                pg_sys::FreeErrorData(edata);
+                error
+            }
+            Err(e) => std::panic::resume_unwind(e),
+        };
+        pg_sys::RollbackAndReleaseCurrentSubTransaction();
+        pg_sys::CurrentMemoryContext = context;
+        pg_sys::CurrentResourceOwner = owner;
+        error
+    }
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
     #[allow(unused_imports)]
     use crate as pgx_tests;
 
+    use super::{caught_error, BACKTRACES};
     use pgx::*;
+    use std::sync::atomic::Ordering;
 
     #[pg_test(error = "panic in walker")]
     fn test_panic_in_extern_c_fn() {
         Spi::get_one::<()>("SELECT crash()");
     }
 
+    #[pg_test]
+    fn test_panic_with_backtrace() {
+        set_panic_backtraces(|| BACKTRACES.load(Ordering::SeqCst));
+        BACKTRACES.store(true, Ordering::SeqCst);
+        let (message, logged_detail) = caught_error("SELECT panic_with_backtrace()");
+        BACKTRACES.store(false, Ordering::SeqCst);
+
+        // the backtrace only goes to the server log, leaving the message as it was
+        assert_eq!(message, "panic with a backtrace");
+        let backtrace = logged_detail.expect("the ERROR didn't log a backtrace");
+        assert!(
+            backtrace.contains("panic_with_backtrace"),
+            "the backtrace doesn't include the function that panicked:\n{}",
+            backtrace
+        );
+    }
+
     #[pg_test]
     fn test_pg_try_unwrap_no_error() {
         let result = pg_try(|| 42).unwrap();
@@ -33,6 +33,31 @@ mod tests {
         assert_eq!(*ptr, 5);
     }
 
+    #[pg_test]
+    fn pgbox_alloc_in() {
+        let mut ptr = PgBox::<i32>::alloc_in(&mut PgMemoryContexts::TopMemoryContext);
+        *ptr = 5;
+
+        assert_eq!(*ptr, 5);
+        assert_eq!(
+            PgMemoryContexts::Of(ptr.as_ptr() as void_ptr).value(),
+            unsafe { pg_sys::TopMemoryContext }
+        );
+    }
+
+    #[pg_test]
+    fn pgbox_alloc0_in() {
+        let mut context = PgMemoryContexts::new("pgbox_alloc0_in");
+        let ptr = PgBox::<i64>::alloc0_in(&mut context);
+
+        assert_eq!(*ptr, 0);
+        assert_eq!(
+            PgMemoryContexts::Of(ptr.as_ptr() as void_ptr).value(),
+            context.value()
+        );
+        assert_ne!(context.value(), unsafe { pg_sys::CurrentMemoryContext });
+    }
+
     #[pg_test]
     fn pgbox_new() {
         let ptr: PgBox<i32, AllocatedByRust> = PgBox::new(5);
@@ -0,0 +1,69 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+/// A type a client could share, through `pgx-types`, as `pgx` reexports its types
+#[derive(PostgresType, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Appointment {
+    day: MaybeInfinite<Date>,
+    starts: Timestamp,
+    id: Uuid,
+}
+
+#[pg_extern]
+fn appointment_day(appointment: Appointment) -> MaybeInfinite<Date> {
+    appointment.day
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::Appointment;
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_same_types() {
+        // the types pgx converts to and from `Datum`s are pgx-types'
+        let date: pgx_types::Date = Spi::get_one::<Date>("SELECT '2022-06-01'::date").unwrap();
+        assert_eq!(date.pg_epoch_days(), 8187);
+        let uuid: pgx_types::Uuid =
+            Spi::get_one::<Uuid>("SELECT 'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'::uuid").unwrap();
+        assert_eq!(uuid.to_string(), "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11");
+    }
+
+    #[pg_test]
+    fn test_shared_type_round_trip() {
+        let appointment = Spi::get_one::<Appointment>(
+            r#"SELECT '{"day": "infinity", "starts": "2022-06-01T12:30:00.250-00", "id": "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11"}'::Appointment"#,
+        )
+        .unwrap();
+        assert_eq!(appointment.day, MaybeInfinite::Infinity);
+        assert_eq!(appointment.starts.millisecond(), 250);
+
+        let json = serde_json::to_string(&appointment).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Appointment>(&json).unwrap(),
+            appointment
+        );
+        assert_eq!(
+            Spi::get_one::<bool>(&format!(
+                "SELECT appointment_day('{}') = 'infinity'::date",
+                json
+            )),
+            Some(true)
+        );
+    }
+}
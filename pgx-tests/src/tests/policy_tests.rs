@@ -0,0 +1,54 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE policy_test_documents (
+    owner text NOT NULL,
+    body text
+);
+ALTER TABLE policy_test_documents ENABLE ROW LEVEL SECURITY;
+"#,
+    name = "create_policy_test_documents",
+);
+
+#[pg_extern]
+fn policy_test_owner() -> &'static str {
+    "alice"
+}
+
+pg_policy!(
+    name = "own_documents",
+    on = "policy_test_documents",
+    using = "owner = policy_test_owner()",
+    requires = ["create_policy_test_documents", policy_test_owner],
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_pg_policy() {
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT qual FROM pg_policies WHERE policyname = 'own_documents' AND tablename = 'policy_test_documents'"
+            ),
+            Some("(owner = policy_test_owner())".to_string())
+        );
+    }
+}
@@ -0,0 +1,44 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[test]
+    fn test_from_version_num() {
+        assert_eq!(
+            ServerVersion::from_version_num(140002),
+            ServerVersion {
+                major: 14,
+                minor: 2
+            }
+        );
+        assert_eq!(ServerVersion::from_version_num(100021).to_string(), "10.21");
+    }
+
+    #[pg_test]
+    fn test_running_server_version() {
+        let running = ServerVersion::running();
+        let compiled = ServerVersion::compiled();
+        assert_eq!(running.major, compiled.major);
+        assert_eq!(
+            running.to_string(),
+            Spi::get_one::<String>("SELECT current_setting('server_version_num')::int / 10000 || '.' || current_setting('server_version_num')::int % 10000")
+                .expect("server_version_num was null")
+        );
+    }
+}
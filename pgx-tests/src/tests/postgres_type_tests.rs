@@ -68,6 +68,12 @@ pub struct JsonType {
     c: i64,
 }
 
+#[derive(Serialize, Deserialize, PostgresType)]
+#[pgx(storage = "external", alignment = "double")]
+pub struct ExternalType {
+    body: String,
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -107,4 +113,22 @@ mod tests {
         assert_eq!(result.b, 2.0);
         assert_eq!(result.c, 3);
     }
+
+    #[pg_test]
+    fn test_external_type_storage() {
+        let storage = Spi::get_one::<String>(
+            "SELECT typstorage::text || typalign::text FROM pg_type WHERE typname = 'externaltype'",
+        )
+        .expect("SPI returned NULL");
+        assert_eq!(storage, "ed");
+    }
+
+    #[pg_test]
+    fn test_detoast_datum_prefix() {
+        let text = rust_str_to_text_p("hello world");
+        let prefix = unsafe { detoast_datum_prefix(text.as_ptr() as pg_sys::Datum, 5) };
+        assert_eq!(prefix, b"hello");
+        let all = unsafe { detoast_datum_prefix(text.as_ptr() as pg_sys::Datum, 100) };
+        assert_eq!(all, b"hello world");
+    }
 }
@@ -0,0 +1,152 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE privileges_test_table (id int, secret text);
+CREATE SEQUENCE privileges_test_sequence;
+GRANT SELECT ON privileges_test_table TO pg_monitor;
+GRANT UPDATE (id) ON privileges_test_table TO pg_monitor;
+"#,
+    name = "create_privileges_test_table",
+);
+
+#[pg_extern]
+fn privileges_test_can_update(relation: PgRelation) -> bool {
+    has_privileges(AclObject::Relation(relation.oid()), AclMode::UPDATE)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn pg_monitor() -> pg_sys::Oid {
+        Spi::get_one::<pg_sys::Oid>("SELECT 'pg_monitor'::regrole::oid").expect("no pg_monitor")
+    }
+
+    fn table() -> pg_sys::Oid {
+        regclassin("privileges_test_table")
+    }
+
+    fn regclassin(name: &str) -> pg_sys::Oid {
+        Spi::get_one::<pg_sys::Oid>(&format!("SELECT '{}'::regclass::oid", name))
+            .expect("no such relation")
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_owner_has_privileges() {
+        assert!(has_privileges(
+            AclObject::Relation(table()),
+            AclMode::SELECT | AclMode::INSERT | AclMode::UPDATE | AclMode::DELETE
+        ));
+        let result =
+            Spi::get_one::<bool>("SELECT privileges_test_can_update('privileges_test_table')");
+        assert_eq!(result, Some(true));
+    }
+
+    #[pg_test]
+    fn test_role_has_privileges() {
+        let role = pg_monitor();
+        assert!(role_has_privileges(
+            role,
+            AclObject::Relation(table()),
+            AclMode::SELECT
+        ));
+        assert!(!role_has_privileges(
+            role,
+            AclObject::Relation(table()),
+            AclMode::INSERT
+        ));
+        // every one of the privileges is needed
+        assert!(!role_has_privileges(
+            role,
+            AclObject::Relation(table()),
+            AclMode::SELECT | AclMode::INSERT
+        ));
+        assert!(role_has_privileges(
+            role,
+            AclObject::Column(table(), 1),
+            AclMode::UPDATE
+        ));
+        assert!(!role_has_privileges(
+            role,
+            AclObject::Column(table(), 2),
+            AclMode::UPDATE
+        ));
+    }
+
+    #[pg_test]
+    fn test_public_privileges() {
+        let role = pg_monitor();
+        let public = Spi::get_one::<pg_sys::Oid>("SELECT 'public'::regnamespace::oid")
+            .expect("no public schema");
+        assert!(role_has_privileges(
+            role,
+            AclObject::Schema(public),
+            AclMode::USAGE
+        ));
+        let database = unsafe { pg_sys::MyDatabaseId };
+        assert!(role_has_privileges(
+            role,
+            AclObject::Database(database),
+            AclMode::CONNECT
+        ));
+        let function =
+            Spi::get_one::<pg_sys::Oid>("SELECT 'now'::regproc::oid").expect("no now() function");
+        assert!(role_has_privileges(
+            role,
+            AclObject::Function(function),
+            AclMode::EXECUTE
+        ));
+        assert!(role_has_privileges(
+            role,
+            AclObject::Type(pg_sys::INT4OID),
+            AclMode::USAGE
+        ));
+    }
+
+    #[pg_test]
+    fn test_check_privileges() {
+        Spi::run("SET LOCAL ROLE pg_monitor");
+        check_privileges(AclObject::Relation(table()), AclMode::SELECT);
+        check_privileges(AclObject::Column(table(), 1), AclMode::UPDATE);
+    }
+
+    #[pg_test(error = "permission denied for table privileges_test_table")]
+    fn test_check_privileges_denied() {
+        let table = table();
+        Spi::run("SET LOCAL ROLE pg_monitor");
+        check_privileges(AclObject::Relation(table), AclMode::DELETE);
+    }
+
+    #[pg_test(error = "permission denied for sequence privileges_test_sequence")]
+    fn test_check_privileges_denied_for_sequence() {
+        let sequence = regclassin("privileges_test_sequence");
+        Spi::run("SET LOCAL ROLE pg_monitor");
+        check_privileges(AclObject::Relation(sequence), AclMode::USAGE);
+    }
+
+    #[pg_test(
+        error = "permission denied for column \"secret\" of relation \"privileges_test_table\""
+    )]
+    fn test_check_column_privileges_denied() {
+        let table = table();
+        Spi::run("SET LOCAL ROLE pg_monitor");
+        check_privileges(AclObject::Column(table, 2), AclMode::UPDATE);
+    }
+}
@@ -0,0 +1,115 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_normalize_query() {
+        assert_eq!(
+            normalize_query("SELECT * FROM orders WHERE id = 42 AND status = 'open'"),
+            "SELECT * FROM orders WHERE id = $1 AND status = $2"
+        );
+        assert_eq!(
+            normalize_query(
+                "SELECT -1, a - 2, true, NULL FROM t WHERE b IS NOT NULL AND c = -1.5e3 AND d=-7"
+            ),
+            "SELECT $1, a - $2, $3, $4 FROM t WHERE b IS NOT NULL AND c = $5 AND d=$6"
+        );
+        assert_eq!(
+            normalize_query("INSERT INTO t VALUES ($1, 'x', $2)"),
+            "INSERT INTO t VALUES ($1, $3, $2)"
+        );
+        assert_eq!(
+            normalize_query("SELECT a, 10 FROM t GROUP BY 1, 2 ORDER BY 2 DESC, 1 LIMIT 5"),
+            "SELECT a, $1 FROM t GROUP BY 1, 2 ORDER BY 2 DESC, 1 LIMIT $2"
+        );
+        assert_eq!(
+            normalize_query(
+                "UPDATE \"T 1\" SET a = $$it's$$, b = E'a\\'b' /* x = 1 */ WHERE c = x'1F' -- 2\n"
+            ),
+            "UPDATE \"T 1\" SET a = $1, b = $2 /* x = 1 */ WHERE c = $3 -- 2\n"
+        );
+        assert_eq!(
+            normalize_query("SELECT interval '1 day', 'a'::text"),
+            "SELECT interval $1, $2::text"
+        );
+    }
+
+    #[pg_test]
+    fn test_normalize_query_utility() {
+        assert_eq!(
+            normalize_query("CREATE TABLE t (a int DEFAULT 1)"),
+            "CREATE TABLE t (a int DEFAULT 1)"
+        );
+        assert_eq!(
+            normalize_query("SET work_mem = '64MB'; (SELECT 2)"),
+            "SET work_mem = '64MB'; (SELECT $1)"
+        );
+    }
+
+    #[pg_test]
+    fn test_query_fingerprint() {
+        let fingerprint =
+            query_fingerprint("SELECT * FROM orders WHERE id = 42 AND status = 'open'");
+        assert_eq!(
+            query_fingerprint("select *\n  from ORDERS -- recent\n where id = -7 and status = $1;"),
+            fingerprint
+        );
+        assert_ne!(
+            query_fingerprint("SELECT * FROM orders WHERE id = 42 OR status = 'open'"),
+            fingerprint
+        );
+        assert_ne!(
+            query_fingerprint("SELECT * FROM \"ORDERS\" WHERE id = 42 AND status = 'open'"),
+            fingerprint
+        );
+    }
+
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    #[pg_test]
+    fn test_query_id() {
+        Spi::run("SET compute_query_id = on");
+        let id = query_id("SELECT * FROM pg_class WHERE relname = 'a'");
+        assert!(id.is_some());
+        assert_eq!(
+            query_id("select * from pg_catalog.pg_class where relname = 'b'"),
+            id
+        );
+        assert_ne!(query_id("SELECT * FROM pg_class WHERE relkind = 'r'"), id);
+
+        Spi::run("SET compute_query_id = off");
+        assert_eq!(query_id("SELECT * FROM pg_class WHERE relname = 'a'"), None);
+    }
+
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    #[pg_test]
+    fn test_current_query_id() {
+        assert_eq!(
+            current_query_id(),
+            Spi::get_one::<i64>(
+                "SELECT query_id FROM pg_stat_activity WHERE pid = pg_backend_pid()"
+            )
+        );
+    }
+
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    #[pg_test(error = "query_id() takes a single statement")]
+    fn test_query_id_statements() {
+        query_id("SELECT 1; SELECT 2");
+    }
+}
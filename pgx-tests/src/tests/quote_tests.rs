@@ -0,0 +1,120 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("users"), "users");
+        assert_eq!(quote_identifier("Users"), "\"Users\"");
+        assert_eq!(quote_identifier("select"), "\"select\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[pg_test]
+    fn test_quote_qualified_identifier() {
+        assert_eq!(
+            quote_qualified_identifier(Some("public"), "My Table"),
+            "public.\"My Table\""
+        );
+        assert_eq!(quote_qualified_identifier(None, "users"), "users");
+    }
+
+    #[pg_test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("it's"), "'it''s'");
+        assert_eq!(quote_literal("C:\\temp"), "E'C:\\\\temp'");
+    }
+
+    #[pg_test]
+    fn test_format_sql() {
+        Spi::run("CREATE TABLE tests.\"Quote Test\" (\"Name\" text); INSERT INTO tests.\"Quote Test\" VALUES ('x''; DROP TABLE tests.\"Quote Test\"; --')");
+        let name = "x'; DROP TABLE tests.\"Quote Test\"; --";
+        let query = format_sql!(
+            "SELECT count(*) FROM {} WHERE {} = {}",
+            qualified("tests", "Quote Test"),
+            ident("Name"),
+            literal(name),
+        );
+        assert_eq!(Spi::get_one::<i64>(&query), Some(1));
+        assert_eq!(
+            format_sql!("SELECT {}::int", literal(42)),
+            "SELECT '42'::int"
+        );
+    }
+
+    #[pg_test]
+    fn test_truncate_identifier() {
+        assert_eq!(MAX_IDENTIFIER_LEN, 63);
+        assert_eq!(truncate_identifier("users"), "users");
+        assert_eq!(truncate_identifier(&"a".repeat(70)), "a".repeat(63));
+        // without splitting the two bytes of the 32nd character
+        assert_eq!(truncate_identifier(&"é".repeat(40)), "é".repeat(31));
+    }
+
+    #[pg_test]
+    fn test_downcase_truncate_identifier() {
+        assert_eq!(downcase_truncate_identifier("MyTable"), "mytable");
+        // only ASCII letters are downcased in UTF-8
+        assert_eq!(downcase_truncate_identifier("ÉTÉ"), "ÉtÉ");
+        assert_eq!(
+            downcase_truncate_identifier(&"A".repeat(70)),
+            "a".repeat(63)
+        );
+    }
+
+    #[pg_test]
+    fn test_identifier_names_match_catalog() {
+        let unquoted = format!("Tenant_Events_{}", "X".repeat(60));
+        let quoted = format!("Tenant Events {}", "X".repeat(60));
+        Spi::run(&format!("CREATE TABLE tests.{} ()", unquoted));
+        Spi::run(&format_sql!("CREATE TABLE tests.{} ()", ident(quoted)));
+
+        let exists = |name: String| {
+            Spi::get_one::<bool>(&format_sql!(
+                "SELECT EXISTS (SELECT FROM pg_class WHERE relname = {})",
+                literal(name),
+            ))
+        };
+        assert_eq!(exists(downcase_truncate_identifier(&unquoted)), Some(true));
+        assert_eq!(exists(truncate_identifier(&quoted)), Some(true));
+    }
+
+    #[pg_test]
+    fn test_parse_qualified_identifier() {
+        assert_eq!(parse_qualified_identifier("Events"), vec!["events"]);
+        assert_eq!(
+            parse_qualified_identifier(" app . \"My \"\"Events\"\"\" "),
+            vec!["app", "My \"Events\""]
+        );
+        assert_eq!(
+            parse_qualified_identifier("db.\"A.B\".c"),
+            vec!["db", "A.B", "c"]
+        );
+    }
+
+    #[pg_test(error = "invalid name syntax")]
+    fn test_parse_qualified_identifier_missing_name() {
+        parse_qualified_identifier("app..events");
+    }
+
+    #[pg_test(error = "invalid name syntax")]
+    fn test_parse_qualified_identifier_unclosed_quote() {
+        parse_qualified_identifier("\"app.events");
+    }
+}
@@ -0,0 +1,113 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern]
+fn raw_doubled(values: Array<f64>) -> RawArrayBuf<f64> {
+    let values = values.as_raw().unwrap_or_else(|e| error!("{}", e));
+    let mut doubled = RawArrayBuf::<f64>::new(values.len()).unwrap();
+    // through the pointers, as a native library would
+    unsafe {
+        let (input, output) = (values.as_ptr(), doubled.as_mut_ptr());
+        for i in 0..values.len() {
+            *output.add(i) = *input.add(i) * 2.0;
+        }
+    }
+    doubled
+}
+
+#[pg_extern]
+fn raw_dims(values: Array<i32>) -> Vec<i32> {
+    let values = values.as_raw().unwrap_or_else(|e| error!("{}", e));
+    values.dims().to_vec()
+}
+
+#[pg_extern]
+fn raw_elements(values: Array<i32>) -> Vec<i32> {
+    let values = values.as_raw().unwrap_or_else(|e| error!("{}", e));
+    assert_eq!(values.layout().size(), values.len() * 4);
+    values.as_slice().to_vec()
+}
+
+#[pg_extern]
+fn raw_reversed(value: &[u8]) -> RawVarlenaBuf {
+    let mut reversed = RawVarlenaBuf::new(value.len()).unwrap();
+    reversed.as_mut_slice().copy_from_slice(value);
+    reversed.as_mut_slice().reverse();
+    reversed
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_raw_array() {
+        assert_eq!(
+            Spi::get_one::<Vec<f64>>("SELECT raw_doubled(ARRAY[1.5, -2, 0]::float8[])"),
+            Some(vec![3.0, -4.0, 0.0])
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<f64>>("SELECT raw_doubled('{}'::float8[])"),
+            Some(vec![])
+        );
+    }
+
+    #[pg_test]
+    fn test_raw_array_dims() {
+        assert_eq!(
+            Spi::get_one::<Vec<i32>>("SELECT raw_dims(ARRAY[[1, 2, 3], [4, 5, 6]])"),
+            Some(vec![2, 3])
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<i32>>("SELECT raw_elements(ARRAY[[1, 2, 3], [4, 5, 6]])"),
+            Some(vec![1, 2, 3, 4, 5, 6])
+        );
+    }
+
+    #[pg_test(error = "the array has NULL elements")]
+    fn test_raw_array_nulls() {
+        Spi::get_one::<Vec<f64>>("SELECT raw_doubled(ARRAY[1, NULL]::float8[])");
+    }
+
+    #[pg_test]
+    fn test_raw_array_element_type() {
+        let datum = vec![1i32, 2].into_datum().unwrap();
+        let array =
+            unsafe { Array::<i64>::from_datum(datum, false, pg_sys::INT4ARRAYOID) }.unwrap();
+        assert_eq!(
+            array.as_raw().err(),
+            Some(RawLayoutError::WrongElementType {
+                expected: pg_sys::INT8OID,
+                found: pg_sys::INT4OID
+            })
+        );
+    }
+
+    #[pg_test]
+    fn test_raw_varlena() {
+        assert_eq!(
+            Spi::get_one::<Vec<u8>>("SELECT raw_reversed('\\x010203'::bytea)"),
+            Some(vec![3, 2, 1])
+        );
+
+        let datum = "pinned".into_datum().unwrap();
+        let pinned = unsafe { PinnedVarlena::from_datum(datum) };
+        assert_eq!(pinned.len(), 6);
+        assert_eq!(pinned.as_bytes(), b"pinned");
+    }
+}
@@ -0,0 +1,57 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[pg_extern]
+    fn reg_names(relation: RegClass, function: RegProc, typ: RegType) -> String {
+        format!("{} {} {}", relation.name(), function.name(), typ.name())
+    }
+
+    #[pg_extern]
+    fn regclass_of(name: &str) -> Option<RegClass> {
+        RegClass::from_name(name)
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_reg_arguments() {
+        let names = Spi::get_one::<String>(
+            "SELECT tests.reg_names('pg_class'::regclass, 'now'::regproc, 'int4'::regtype)",
+        );
+        assert_eq!(names.as_deref(), Some("pg_class now integer"));
+    }
+
+    #[pg_test]
+    fn test_reg_from_name() {
+        Spi::run("CREATE TABLE tests.\"Reg Test\" (id int)");
+        let relation = RegClass::from_name("tests.\"Reg Test\"").expect("table not found");
+        assert_eq!(relation.name(), "tests.\"Reg Test\"");
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT tests.regclass_of('tests.\"Reg Test\"') = 'tests.\"Reg Test\"'::regclass"
+            ),
+            Some(true)
+        );
+        assert_eq!(RegClass::from_name("tests.no_such_table"), None);
+        assert_eq!(
+            RegType::from_name("pg_catalog.text").map(|typ| typ.oid()),
+            Some(pg_sys::TEXTOID)
+        );
+        assert!(RegProc::from_name("pg_catalog.no_such_function").is_none());
+    }
+}
@@ -0,0 +1,115 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[derive(PostgresEnum, PartialEq, Debug)]
+pub enum RegistryTestColor {
+    Red,
+    Green,
+}
+
+extension_sql!(
+    r#"
+CREATE DOMAIN registry_test_positive AS integer CHECK (VALUE > 0);
+"#,
+    name = "create_registry_test_domain",
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::RegistryTestColor;
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_lookup_builtin() {
+        let converter = lookup_datum_converter(pg_sys::INT4OID).expect("no converter for int4");
+        assert_eq!(converter.rust_type(), "i32");
+        let value =
+            unsafe { converter.from_datum(42.into_datum().unwrap(), false, pg_sys::INT4OID) }
+                .expect("value was NULL");
+        assert_eq!(value.downcast_ref::<i32>(), Some(&42));
+        assert!(unsafe { converter.from_datum(0, true, pg_sys::INT4OID) }.is_none());
+    }
+
+    #[pg_test]
+    fn test_lookup_builtin_array() {
+        let datum = Spi::get_one::<pg_sys::Datum>("SELECT ARRAY['a', NULL]::text[]")
+            .expect("array was NULL");
+        let converter =
+            lookup_datum_converter(pg_sys::TEXTARRAYOID).expect("no converter for text[]");
+        let value = unsafe { converter.from_datum(datum, false, pg_sys::TEXTARRAYOID) }
+            .expect("value was NULL");
+        assert_eq!(
+            value.downcast_ref::<Vec<Option<String>>>(),
+            Some(&vec![Some("a".to_string()), None])
+        );
+    }
+
+    #[pg_test]
+    fn test_lookup_domain() {
+        let converter = lookup_datum_converter(regtypein("registry_test_positive"))
+            .expect("no converter for domain");
+        assert_eq!(converter.type_id(), std::any::TypeId::of::<i32>());
+    }
+
+    #[pg_test]
+    fn test_lookup_unregistered() {
+        assert!(lookup_datum_converter(pg_sys::POINTOID).is_none());
+    }
+
+    #[pg_test]
+    fn test_register_datum_type() {
+        register_datum_type::<RegistryTestColor>();
+        let typoid = RegistryTestColor::type_oid();
+        let converter = lookup_datum_converter(typoid).expect("no converter for enum");
+        let datum = converter
+            .into_datum(Box::new(RegistryTestColor::Green))
+            .expect("datum was NULL");
+        let value = unsafe { converter.from_datum(datum, false, typoid) }.expect("value was NULL");
+        assert_eq!(
+            value.downcast_ref::<RegistryTestColor>(),
+            Some(&RegistryTestColor::Green)
+        );
+    }
+
+    #[pg_test]
+    fn test_register_datum_type_for() {
+        register_datum_type_for::<i64>(pg_sys::INT4OID);
+        let converter = lookup_datum_converter(pg_sys::INT4OID).expect("no converter for int4");
+        assert_eq!(converter.type_id(), std::any::TypeId::of::<i64>());
+        register_datum_type_for::<i32>(pg_sys::INT4OID);
+    }
+
+    #[pg_test]
+    fn test_register_datum_type_for_builtin_array() {
+        // the built-in array types are only looked up later, and mustn't replace this
+        register_datum_type_for::<Vec<Option<i64>>>(pg_sys::INT4ARRAYOID);
+        let converter =
+            lookup_datum_converter(pg_sys::INT4ARRAYOID).expect("no converter for int4[]");
+        assert_eq!(
+            converter.type_id(),
+            std::any::TypeId::of::<Vec<Option<i64>>>()
+        );
+        register_datum_type_for::<Vec<Option<i32>>>(pg_sys::INT4ARRAYOID);
+    }
+
+    #[pg_test(error = "expected a i32")]
+    fn test_into_datum_wrong_type() {
+        let converter = lookup_datum_converter(pg_sys::INT4OID).expect("no converter for int4");
+        converter.into_datum(Box::new("not an integer"));
+    }
+}
@@ -0,0 +1,305 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE rel_test_table (id int NOT NULL, code text NOT NULL) WITH (fillfactor = 80);
+CREATE UNIQUE INDEX rel_test_table_code ON rel_test_table (code);
+CREATE INDEX rel_test_table_id ON rel_test_table (id);
+ALTER TABLE rel_test_table REPLICA IDENTITY USING INDEX rel_test_table_code;
+CREATE UNLOGGED TABLE rel_test_unlogged (id int);
+CREATE TABLE rel_test_constraints (
+    id int DEFAULT 42 CONSTRAINT rel_test_positive CHECK (id > 0),
+    label text DEFAULT 'none'::text,
+    CONSTRAINT rel_test_label_length CHECK (length(label) < 10) NO INHERIT
+);
+ALTER TABLE rel_test_constraints ADD CONSTRAINT rel_test_big CHECK (id < 1000) NOT VALID;
+CREATE TABLE rel_test_partitioned (id int) PARTITION BY RANGE (id);
+CREATE TABLE rel_test_partition_1 PARTITION OF rel_test_partitioned FOR VALUES FROM (1) TO (10);
+CREATE TABLE rel_test_partition_2 PARTITION OF rel_test_partitioned FOR VALUES FROM (10) TO (20);
+CREATE SCHEMA "Rel Tests";
+CREATE TABLE "Rel Tests"."Mixed Case" (id int);
+"#,
+    name = "create_rel_test_tables",
+);
+
+#[pg_extern]
+fn rel_test_partition_bounds(relation: PgRelation) -> Vec<String> {
+    relation
+        .partitions(pg_sys::AccessShareLock as pg_sys::LOCKMODE)
+        .iter()
+        .map(|partition| {
+            format!(
+                "{} {}",
+                partition.name(),
+                partition.partition_bound().unwrap()
+            )
+        })
+        .collect()
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn open(name: &str) -> PgRelation {
+        PgRelation::open_with_name_and_share_lock(name).expect("no such relation")
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_reloptions() {
+        assert_eq!(
+            open("rel_test_table").reloptions(),
+            vec![("fillfactor".to_string(), Some("80".to_string()))]
+        );
+        assert!(open("rel_test_unlogged").reloptions().is_empty());
+    }
+
+    #[pg_test]
+    fn test_persistence() {
+        assert_eq!(
+            open("rel_test_table").persistence(),
+            RelPersistence::Permanent
+        );
+        assert_eq!(
+            open("rel_test_unlogged").persistence(),
+            RelPersistence::Unlogged
+        );
+        Spi::run("CREATE TEMPORARY TABLE rel_test_temp (id int)");
+        assert_eq!(
+            open("rel_test_temp").persistence(),
+            RelPersistence::Temporary
+        );
+    }
+
+    #[pg_test]
+    fn test_tablespace() {
+        let table = open("rel_test_table");
+        assert_eq!(table.tablespace_oid(), None);
+        assert_eq!(table.tablespace_name(), None);
+        // the shared catalogs are in `pg_global`
+        let pg_database = open("pg_database");
+        assert!(pg_database.tablespace_oid().is_some());
+        assert_eq!(pg_database.tablespace_name().as_deref(), Some("pg_global"));
+    }
+
+    #[pg_test]
+    fn test_partitions() {
+        let partitioned = open("rel_test_partitioned");
+        assert!(!partitioned.is_partition());
+        assert_eq!(partitioned.partition_bound(), None);
+        let mut names = partitioned
+            .partitions(pg_sys::AccessShareLock as pg_sys::LOCKMODE)
+            .iter()
+            .map(|partition| partition.name().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["rel_test_partition_1", "rel_test_partition_2"]);
+
+        let partition = open("rel_test_partition_1");
+        assert!(partition.is_partition());
+        assert_eq!(
+            partition.partition_bound().as_deref(),
+            Some("FOR VALUES FROM (1) TO (10)")
+        );
+        assert!(open("rel_test_table")
+            .partitions(pg_sys::AccessShareLock as pg_sys::LOCKMODE)
+            .is_empty());
+    }
+
+    #[pg_test]
+    fn test_partitions_from_sql() {
+        let bounds =
+            Spi::get_one::<Vec<String>>("SELECT rel_test_partition_bounds('rel_test_partitioned')")
+                .expect("no bounds");
+        assert_eq!(bounds.len(), 2);
+        assert!(bounds.contains(&"rel_test_partition_2 FOR VALUES FROM (10) TO (20)".to_string()));
+    }
+
+    #[pg_test]
+    fn test_indexes() {
+        let mut names = open("rel_test_table")
+            .indexes(pg_sys::AccessShareLock as pg_sys::LOCKMODE)
+            .map(|index| index.name().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["rel_test_table_code", "rel_test_table_id"]);
+    }
+
+    #[pg_test]
+    fn test_check_constraints() {
+        let constraints = open("rel_test_constraints").check_constraints();
+        let summary = constraints
+            .iter()
+            .map(|check| {
+                (
+                    check.name.as_str(),
+                    check.expr.to_sql(),
+                    check.is_valid,
+                    check.no_inherit,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("rel_test_big", "(id < 1000)".to_string(), false, false),
+                (
+                    "rel_test_label_length",
+                    "(length(label) < 10)".to_string(),
+                    true,
+                    true
+                ),
+                ("rel_test_positive", "(id > 0)".to_string(), true, false),
+            ]
+        );
+        assert!(open("rel_test_table").check_constraints().is_empty());
+    }
+
+    #[pg_test]
+    fn test_stored_expr_node() {
+        let constraints = open("rel_test_constraints").check_constraints();
+        let check = constraints
+            .iter()
+            .find(|check| check.name == "rel_test_positive")
+            .unwrap();
+        assert!(check.expr.node_tree().starts_with("{OPEXPR"));
+        let node = check.expr.to_node();
+        assert!(unsafe { is_a(node.as_ptr(), pg_sys::NodeTag_T_OpExpr) });
+    }
+
+    #[pg_test]
+    fn test_column_defaults() {
+        let relation = open("rel_test_constraints");
+        assert_eq!(
+            relation
+                .column_default(1)
+                .map(|default| default.to_sql())
+                .as_deref(),
+            Some("42")
+        );
+        assert_eq!(
+            relation
+                .column_default(2)
+                .map(|default| default.to_sql())
+                .as_deref(),
+            Some("'none'::text")
+        );
+        assert_eq!(open("rel_test_table").column_default(1), None);
+    }
+
+    #[cfg(not(any(feature = "pg10", feature = "pg11")))]
+    #[pg_test]
+    fn test_generated_columns() {
+        Spi::run(
+            "CREATE TABLE rel_test_generated (a int, doubled int GENERATED ALWAYS AS (a * 2) STORED)",
+        );
+        let relation = open("rel_test_generated");
+        assert_eq!(relation.column_default(2), None);
+        assert_eq!(
+            relation
+                .generated_column_expr(2)
+                .map(|expr| expr.to_sql())
+                .as_deref(),
+            Some("(a * 2)")
+        );
+        assert_eq!(relation.generated_column_expr(1), None);
+    }
+
+    #[pg_test]
+    fn test_replica_identity() {
+        let index = open("rel_test_table_code").oid();
+        assert_eq!(
+            open("rel_test_table").replica_identity(),
+            ReplicaIdentity::Index(index)
+        );
+        assert_eq!(
+            open("rel_test_unlogged").replica_identity(),
+            ReplicaIdentity::Default
+        );
+        Spi::run("ALTER TABLE rel_test_unlogged REPLICA IDENTITY FULL");
+        assert_eq!(
+            open("rel_test_unlogged").replica_identity(),
+            ReplicaIdentity::Full
+        );
+    }
+
+    #[pg_test]
+    fn test_open_with_name_and_lock() {
+        let relation = PgRelation::open_with_name_and_lock(
+            "\"Rel Tests\".\"Mixed Case\"",
+            LockMode::ShareRowExclusive,
+            false,
+        )
+        .unwrap();
+        assert_eq!(relation.name(), "Mixed Case");
+        assert_eq!(relation.namespace(), "Rel Tests");
+
+        let held = || {
+            Spi::get_one::<String>(
+                "SELECT mode FROM pg_locks \
+                 WHERE relation = '\"Rel Tests\".\"Mixed Case\"'::regclass \
+                   AND pid = pg_backend_pid() AND mode <> 'AccessShareLock'",
+            )
+        };
+        assert_eq!(held().as_deref(), Some("ShareRowExclusiveLock"));
+        drop(relation);
+        // the lock is kept until the transaction ends
+        assert_eq!(held().as_deref(), Some("ShareRowExclusiveLock"));
+
+        let relation = PgRelation::open_with_schema_and_lock(
+            Some("Rel Tests"),
+            "Mixed Case",
+            LockMode::AccessShare,
+            false,
+        )
+        .unwrap();
+        assert_eq!(relation.name(), "Mixed Case");
+        assert_eq!(
+            PgRelation::open_with_name_and_lock("rel_test_table", LockMode::NoLock, false)
+                .unwrap()
+                .name(),
+            "rel_test_table"
+        );
+    }
+
+    #[pg_test]
+    fn test_open_with_name_and_lock_missing_ok() {
+        assert!(
+            PgRelation::open_with_name_and_lock("no_such_table", LockMode::AccessShare, true)
+                .is_none()
+        );
+        assert!(PgRelation::open_with_schema_and_lock(
+            Some("no_such_schema"),
+            "rel_test_table",
+            LockMode::AccessShare,
+            true,
+        )
+        .is_none());
+    }
+
+    #[pg_test(error = "relation \"tests.no_such_table\" does not exist")]
+    fn test_open_with_name_and_lock_missing() {
+        PgRelation::open_with_name_and_lock("tests.no_such_table", LockMode::AccessShare, false);
+    }
+
+    #[pg_test(error = "improper relation name (too many dotted names): a.b.c.d")]
+    fn test_open_with_name_and_lock_too_many_names() {
+        PgRelation::open_with_name_and_lock("a.b.c.d", LockMode::AccessShare, true);
+    }
+}
@@ -0,0 +1,104 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE reloptions_test_table (id int) WITH (fillfactor = 70);
+"#,
+    name = "create_reloptions_test_table",
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[repr(C)]
+    struct TestOptions {
+        vl_len_: i32,
+        knob: i32,
+        ratio: f64,
+        enabled: bool,
+        label: i32,
+    }
+
+    const TEST_FIELDS: &[RelOptionField] = &[
+        RelOptionField::new("knob", RelOptionType::Int, 4),
+        RelOptionField::new("ratio", RelOptionType::Real, 8),
+        RelOptionField::new("enabled", RelOptionType::Bool, 16),
+        RelOptionField::new("label", RelOptionType::String, 20),
+    ];
+
+    static TEST_KIND: once_cell::sync::Lazy<RelOptionKind> = once_cell::sync::Lazy::new(|| {
+        let kind = RelOptionKind::allocate();
+        kind.define_int_option("knob", "a test knob", 1, 0, 10);
+        kind.define_real_option("ratio", "a test ratio", 0.5, 0.0, 1.0);
+        kind.define_bool_option("enabled", "a test switch", true);
+        kind.define_string_option("label", "a test label", None);
+        kind
+    });
+
+    fn parse(reloptions: Vec<&str>) -> *const TestOptions {
+        let reloptions = reloptions
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .into_datum()
+            .unwrap();
+        let options =
+            unsafe { parse_reloptions::<TestOptions>(reloptions, true, *TEST_KIND, TEST_FIELDS) };
+        options as *const TestOptions
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_parse_reloptions() {
+        let options = unsafe { parse(vec!["knob=7", "label=fast"]).as_ref() }.expect("no options");
+        assert_eq!(options.knob, 7);
+        assert_eq!(options.ratio, 0.5);
+        assert!(options.enabled);
+        assert_eq!(
+            unsafe { reloption_string(options, options.label) },
+            Some("fast")
+        );
+    }
+
+    #[pg_test]
+    fn test_parse_reloptions_defaults() {
+        let options = unsafe { parse(vec!["enabled=off"]).as_ref() }.expect("no options");
+        assert_eq!(options.knob, 1);
+        assert!(!options.enabled);
+        assert_eq!(unsafe { reloption_string(options, options.label) }, None);
+    }
+
+    #[pg_test(error = "unrecognized parameter \"dial\"")]
+    fn test_parse_reloptions_validates() {
+        parse(vec!["dial=3"]);
+    }
+
+    #[pg_test(error = "value 11 out of bounds for option \"knob\"")]
+    fn test_parse_reloptions_bounds() {
+        parse(vec!["knob=11"]);
+    }
+
+    #[pg_test]
+    fn test_rd_options() {
+        let table = PgRelation::open_with_name_and_share_lock("reloptions_test_table")
+            .expect("no such relation");
+        let options = unsafe { table.rd_options::<pg_sys::StdRdOptions>() }.expect("no options");
+        assert_eq!(options.fillfactor, 70);
+    }
+}
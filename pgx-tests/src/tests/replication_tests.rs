@@ -0,0 +1,193 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    fn relation_message() -> Vec<u8> {
+        let mut message = vec![b'R'];
+        message.extend_from_slice(&16384u32.to_be_bytes());
+        message.extend_from_slice(b"public\0test_table\0d");
+        message.extend_from_slice(&2u16.to_be_bytes());
+        message.push(1);
+        message.extend_from_slice(b"id\0");
+        message.extend_from_slice(&pg_sys::INT4OID.to_be_bytes());
+        message.extend_from_slice(&(-1i32).to_be_bytes());
+        message.push(0);
+        message.extend_from_slice(b"title\0");
+        message.extend_from_slice(&pg_sys::TEXTOID.to_be_bytes());
+        message.extend_from_slice(&(-1i32).to_be_bytes());
+        message
+    }
+
+    #[pg_test]
+    fn test_decode_pgoutput_insert() {
+        let mut decoder = PgOutputDecoder::default();
+        assert_eq!(decoder.decode(1, &relation_message()), None);
+
+        let mut message = vec![b'I'];
+        message.extend_from_slice(&16384u32.to_be_bytes());
+        message.push(b'N');
+        message.extend_from_slice(&2u16.to_be_bytes());
+        message.push(b't');
+        message.extend_from_slice(&2u32.to_be_bytes());
+        message.extend_from_slice(b"42");
+        message.push(b'n');
+
+        match decoder.decode(2, &message) {
+            Some(LogicalChange::Insert { lsn, relation, new }) => {
+                assert_eq!(lsn, 2);
+                assert_eq!(relation.name, "test_table");
+                assert_eq!(relation.columns.len(), 2);
+                assert!(relation.columns[0].is_key);
+                assert_eq!(relation.columns[1].name, "title");
+                assert_eq!(
+                    new,
+                    vec![LogicalValue::Text("42".to_string()), LogicalValue::Null]
+                );
+            }
+            other => panic!("expected an insert, got {:?}", other),
+        }
+    }
+
+    #[pg_test]
+    fn test_decode_pgoutput_begin_commit() {
+        let mut decoder = PgOutputDecoder::default();
+
+        let mut begin = vec![b'B'];
+        begin.extend_from_slice(&100u64.to_be_bytes());
+        begin.extend_from_slice(&12345u64.to_be_bytes());
+        begin.extend_from_slice(&742u32.to_be_bytes());
+        assert_eq!(
+            decoder.decode(1, &begin),
+            Some(LogicalChange::Begin {
+                final_lsn: 100,
+                commit_time: 12345,
+                xid: 742
+            })
+        );
+
+        let mut commit = vec![b'C', 0];
+        commit.extend_from_slice(&100u64.to_be_bytes());
+        commit.extend_from_slice(&108u64.to_be_bytes());
+        commit.extend_from_slice(&12345u64.to_be_bytes());
+        assert_eq!(
+            decoder.decode(100, &commit),
+            Some(LogicalChange::Commit {
+                commit_lsn: 100,
+                end_lsn: 108,
+                commit_time: 12345
+            })
+        );
+    }
+
+    #[pg_test(error = "pgoutput sent a change for undescribed relation 16384")]
+    fn test_decode_pgoutput_unknown_relation() {
+        let mut message = vec![b'D'];
+        message.extend_from_slice(&16384u32.to_be_bytes());
+        message.push(b'K');
+        message.extend_from_slice(&0u16.to_be_bytes());
+        PgOutputDecoder::default().decode(1, &message);
+    }
+
+    #[pg_test]
+    fn test_logical_slot_lifecycle() {
+        let mut slot = PgLogicalSlot::create("pgx_tests_slot", "pgx_tests_publication");
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT EXISTS(SELECT 1 FROM pg_replication_slots WHERE slot_name = 'pgx_tests_slot' AND plugin = 'pgoutput')"
+            ),
+            Some(true)
+        );
+
+        assert!(slot.peek_changes(None).is_empty());
+
+        slot.drop_slot();
+        assert_eq!(
+            Spi::get_one::<bool>(
+                "SELECT EXISTS(SELECT 1 FROM pg_replication_slots WHERE slot_name = 'pgx_tests_slot')"
+            ),
+            Some(false)
+        );
+    }
+
+    #[pg_test]
+    fn test_replication_origin_lifecycle() {
+        let origin = PgReplicationOrigin::create("pgx_tests_origin");
+        assert_eq!(
+            PgReplicationOrigin::by_name("pgx_tests_origin"),
+            Some(origin)
+        );
+        assert_eq!(
+            Spi::get_one::<i32>(
+                "SELECT roident::integer FROM pg_replication_origin WHERE roname = 'pgx_tests_origin'"
+            ),
+            Some(origin.id() as i32)
+        );
+
+        PgReplicationOrigin::drop_by_name("pgx_tests_origin", true);
+        assert_eq!(PgReplicationOrigin::by_name("pgx_tests_origin"), None);
+    }
+
+    #[pg_test]
+    fn test_replication_origin_advance() {
+        let origin = PgReplicationOrigin::create("pgx_tests_origin");
+        let local_commit = pg_sys::InvalidXLogRecPtr as pg_sys::XLogRecPtr;
+        origin.advance(0x2000, local_commit, true, true);
+        assert_eq!(origin.progress(false), 0x2000);
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT pg_replication_origin_progress('pgx_tests_origin', false)::text"
+            ),
+            Some("0/2000".to_string())
+        );
+
+        // older progress is ignored, unless going backward is asked for
+        origin.advance(0x1000, local_commit, false, true);
+        assert_eq!(origin.progress(false), 0x2000);
+        origin.advance(0x1000, local_commit, true, true);
+        assert_eq!(origin.progress(false), 0x1000);
+
+        PgReplicationOrigin::drop_by_name("pgx_tests_origin", true);
+    }
+
+    #[pg_test]
+    fn test_replication_origin_session() {
+        let origin = PgReplicationOrigin::create("pgx_tests_origin");
+        origin.advance(
+            0x1000,
+            pg_sys::InvalidXLogRecPtr as pg_sys::XLogRecPtr,
+            true,
+            true,
+        );
+
+        let session = PgReplicationOriginSession::setup(origin);
+        assert_eq!(session.origin(), origin);
+        assert_eq!(session.progress(false), 0x1000);
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT pg_replication_origin_session_is_setup()"),
+            Some(true)
+        );
+
+        drop(session);
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT pg_replication_origin_session_is_setup()"),
+            Some(false)
+        );
+        PgReplicationOrigin::drop_by_name("pgx_tests_origin", true);
+    }
+}
@@ -0,0 +1,87 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn current_user() -> String {
+        Spi::get_one::<String>("SELECT current_user::text").expect("no current_user")
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_current_and_session_user() {
+        assert_eq!(current_user_id(), session_user_id());
+        assert_eq!(role_name(current_user_id()), Some(current_user()));
+        assert!(is_superuser(current_user_id()));
+    }
+
+    #[pg_test]
+    fn test_role_lookup() {
+        let monitor = role_oid("pg_monitor").expect("no pg_monitor");
+        assert_eq!(role_name(monitor).as_deref(), Some("pg_monitor"));
+        assert!(!is_superuser(monitor));
+        assert_eq!(role_oid("roles_test_no_such_role"), None);
+        assert_eq!(role_name(pg_sys::InvalidOid), None);
+    }
+
+    #[pg_test]
+    fn test_role_membership() {
+        let monitor = role_oid("pg_monitor").expect("no pg_monitor");
+        let read_all_stats = role_oid("pg_read_all_stats").expect("no pg_read_all_stats");
+        assert!(is_member_of_role(monitor, read_all_stats));
+        assert!(has_privileges_of_role(monitor, read_all_stats));
+        assert!(!is_member_of_role(read_all_stats, monitor));
+        assert!(is_member_of_role(monitor, monitor));
+        // a superuser is a member of every role
+        assert!(is_member_of_role(current_user_id(), monitor));
+    }
+
+    #[pg_test]
+    fn test_role_switch() {
+        let me = current_user_id();
+        let monitor = role_oid("pg_monitor").expect("no pg_monitor");
+        {
+            let switch = RoleSwitch::new(monitor);
+            assert_eq!(switch.previous_user_id(), me);
+            assert_eq!(current_user_id(), monitor);
+            assert_eq!(current_user(), "pg_monitor");
+            // the session user doesn't change
+            assert_eq!(session_user_id(), me);
+        }
+        assert_eq!(current_user_id(), me);
+
+        let name = with_role(monitor, current_user);
+        assert_eq!(name, "pg_monitor");
+        assert_eq!(current_user_id(), me);
+    }
+
+    #[pg_test]
+    fn test_role_switch_restored_after_panic() {
+        let me = current_user_id();
+        let monitor = role_oid("pg_monitor").expect("no pg_monitor");
+        let result = std::panic::catch_unwind(|| with_role(monitor, || panic!("as pg_monitor")));
+        assert!(result.is_err());
+        assert_eq!(current_user_id(), me);
+    }
+
+    #[pg_test(error = "cannot create temporary table within security-restricted operation")]
+    fn test_restricted_role_switch() {
+        let monitor = role_oid("pg_monitor").expect("no pg_monitor");
+        let _switch = RoleSwitch::restricted(monitor);
+        Spi::run("CREATE TEMPORARY TABLE roles_test_temp (id int)");
+    }
+}
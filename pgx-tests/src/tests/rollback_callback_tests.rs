@@ -0,0 +1,136 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+use std::cell::RefCell;
+
+thread_local! {
+    /// What's been done, and not since undone, in the current test
+    static DONE: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+/// Record `what` as done, with a rollback callback to undo it
+fn record(what: &str) -> RollbackCallbackReceipt {
+    DONE.with(|done| done.borrow_mut().push(what.to_string()));
+    let what = what.to_string();
+    register_rollback_callback(move || {
+        DONE.with(|done| {
+            let undone = done.borrow_mut().pop();
+            assert_eq!(undone.as_deref(), Some(what.as_str()));
+        })
+    })
+}
+
+fn done() -> Vec<String> {
+    DONE.with(|done| done.borrow().clone())
+}
+
+fn reset() {
+    DONE.with(|done| done.borrow_mut().clear());
+}
+
+/// Run `f` in a subtransaction, which is rolled back if `roll_back`, and released otherwise, as
+/// PL/pgSQL does for a `BEGIN ... EXCEPTION` block
+fn in_subtransaction(roll_back: bool, f: impl FnOnce()) {
+    unsafe {
+        let context = pg_sys::CurrentMemoryContext;
+        let owner = pg_sys::CurrentResourceOwner;
+        pg_sys::BeginInternalSubTransaction(std::ptr::null_mut());
+        f();
+        if roll_back {
+            pg_sys::RollbackAndReleaseCurrentSubTransaction();
+        } else {
+            pg_sys::ReleaseCurrentSubTransaction();
+        }
+        pg_sys::CurrentMemoryContext = context;
+        pg_sys::CurrentResourceOwner = owner;
+    }
+}
+
+#[pg_extern]
+fn rollback_callback_tests_record(what: &str) {
+    record(what);
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::{done, in_subtransaction, record, reset};
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_rollback_callbacks_called_on_rollback() {
+        reset();
+        record("outside");
+        in_subtransaction(true, || {
+            record("first");
+            record("second");
+            assert_eq!(done(), vec!["outside", "first", "second"]);
+        });
+        assert_eq!(done(), vec!["outside"]);
+    }
+
+    #[pg_test]
+    fn test_rollback_callbacks_not_called_on_release() {
+        reset();
+        in_subtransaction(false, || {
+            record("released");
+        });
+        assert_eq!(done(), vec!["released"]);
+    }
+
+    #[pg_test]
+    fn test_rollback_callbacks_passed_to_parent() {
+        reset();
+        in_subtransaction(true, || {
+            record("parent");
+            in_subtransaction(false, || {
+                record("released child");
+            });
+            in_subtransaction(true, || {
+                record("rolled back child");
+            });
+            assert_eq!(done(), vec!["parent", "released child"]);
+        });
+        assert!(done().is_empty());
+    }
+
+    #[pg_test]
+    fn test_rollback_callback_unregistered() {
+        reset();
+        in_subtransaction(true, || {
+            record("kept").unregister_callback();
+        });
+        assert_eq!(done(), vec!["kept"]);
+    }
+
+    #[pg_test]
+    fn test_rollback_callbacks_in_plpgsql_exception_block() {
+        reset();
+        Spi::run(
+            "DO $$
+             BEGIN
+                 PERFORM rollback_callback_tests_record('kept');
+                 BEGIN
+                     PERFORM rollback_callback_tests_record('rolled back');
+                     RAISE EXCEPTION 'roll back';
+                 EXCEPTION WHEN raise_exception THEN
+                     NULL;
+                 END;
+             END
+             $$",
+        );
+        assert_eq!(done(), vec!["kept"]);
+    }
+}
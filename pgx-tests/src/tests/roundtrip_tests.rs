@@ -0,0 +1,68 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+/// An `int4` whose `FromDatum` halves it, so it never comes back the same, unless it's zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct Halved(i32);
+
+impl FromDatum for Halved {
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: pg_sys::Oid) -> Option<Self> {
+        i32::from_datum(datum, is_null, typoid).map(|value| Halved(value / 2))
+    }
+}
+
+impl IntoDatum for Halved {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.0.into_datum()
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::INT4OID
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use super::Halved;
+    use pgx::*;
+    use proptest::strategy::Strategy;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    pgx_tests::datum_roundtrip_test!(test_i32_roundtrip, i32);
+    pgx_tests::datum_roundtrip_test!(test_i64_roundtrip, i64);
+    pgx_tests::datum_roundtrip_test!(test_bool_roundtrip, bool, cases = 8);
+    pgx_tests::datum_roundtrip_test!(test_string_roundtrip, String, strategy = "[^\\x00]{0,32}");
+
+    #[pg_test]
+    fn test_roundtrip_value() {
+        assert_eq!(pgx_tests::roundtrip_value(42i64), Ok(()));
+        assert_eq!(
+            pgx_tests::roundtrip_value(vec![Some("a, b".to_string()), None]),
+            Ok(())
+        );
+        assert_eq!(
+            pgx_tests::roundtrip_value(Halved(3)),
+            Err("Halved(3) came back from `from_datum(into_datum())` as Halved(1)".to_string())
+        );
+    }
+
+    #[pg_test(error = "Halved(1) came back from `from_datum(into_datum())` as Halved(0)")]
+    fn test_roundtrip_shrinks_failures() {
+        // only positive values, so that the one they shrink to is always 1, not -1
+        pgx_tests::check_datum_roundtrip_with::<Halved, _>((1..i32::MAX).prop_map(Halved), 16);
+    }
+}
@@ -0,0 +1,95 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+job_scheduler_sql!();
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::bgworkers::JobSchedule;
+    use pgx::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_cron_next_after() {
+        let schedule = "*/15 9-17 * * mon-fri".parse::<JobSchedule>().unwrap();
+        // a Friday evening
+        assert_eq!(
+            schedule.next_after(datetime!(2022-04-01 17:50 UTC)),
+            Some(datetime!(2022-04-04 09:00 UTC))
+        );
+        assert_eq!(
+            schedule.next_after(datetime!(2022-04-04 09:00 UTC)),
+            Some(datetime!(2022-04-04 09:15 UTC))
+        );
+
+        let schedule = "@monthly".parse::<JobSchedule>().unwrap();
+        assert_eq!(
+            schedule.next_after(datetime!(2022-12-15 12:00 UTC)),
+            Some(datetime!(2023-01-01 00:00 UTC))
+        );
+
+        // either the 13th or a Friday
+        let schedule = "0 0 13 * 5".parse::<JobSchedule>().unwrap();
+        assert_eq!(
+            schedule.next_after(datetime!(2022-04-09 00:00 UTC)),
+            Some(datetime!(2022-04-13 00:00 UTC))
+        );
+
+        // a stepped field still restricts its days: either the 1st, 11th, 21st, 31st, or a Friday
+        let schedule = "0 0 */10 * fri".parse::<JobSchedule>().unwrap();
+        assert_eq!(
+            schedule.next_after(datetime!(2022-04-09 00:00 UTC)),
+            Some(datetime!(2022-04-11 00:00 UTC))
+        );
+
+        // either the 13th or a Sunday, Wednesday, or Saturday
+        let schedule = "0 0 13 * */3".parse::<JobSchedule>().unwrap();
+        assert_eq!(
+            schedule.next_after(datetime!(2022-04-09 00:00 UTC)),
+            Some(datetime!(2022-04-10 00:00 UTC))
+        );
+
+        let schedule = "0 0 30 feb *".parse::<JobSchedule>().unwrap();
+        assert_eq!(schedule.next_after(datetime!(2022-01-01 00:00 UTC)), None);
+    }
+
+    #[pg_test]
+    fn test_schedule_sql_job() {
+        Spi::run("SELECT schedule_job('test_job', '@every 1h', 'SELECT 1')");
+        Spi::run("SELECT schedule_job('test_job', '0 3 * * *', 'SELECT 2')");
+        assert_eq!(
+            Spi::get_two::<String, String>(
+                "SELECT schedule, command FROM pgx_scheduled_jobs WHERE name = 'test_job'"
+            ),
+            (Some("0 3 * * *".to_string()), Some("SELECT 2".to_string()))
+        );
+
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT unschedule_job('test_job')"),
+            Some(true)
+        );
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT unschedule_job('test_job')"),
+            Some(false)
+        );
+    }
+
+    #[pg_test(error = "cron value 25 is outside of 0-23")]
+    fn test_schedule_sql_job_invalid() {
+        Spi::run("SELECT schedule_job('test_job', '0 25 * * *', 'SELECT 1')");
+    }
+}
@@ -7,6 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use pgx::*;
+use serde::{Deserialize, Serialize};
 
 #[pgx::pg_schema]
 mod test_schema {
@@ -20,6 +21,16 @@ mod test_schema {
     #[pg_extern(sql = false)]
     fn func_elided_from_schema() {}
 
+    #[pg_extern(sql = false)]
+    pub(crate) fn helper_elided_from_schema(x: i32) -> i32 {
+        x * 2
+    }
+
+    #[pg_extern(untrusted)]
+    fn func_marked_untrusted() -> bool {
+        true
+    }
+
     #[pg_extern(sql = generate_function)]
     fn func_generated_with_custom_sql() {}
 
@@ -80,6 +91,35 @@ fn type_in_diff_schema() -> test_schema::TestType {
     test_schema::TestType(1)
 }
 
+#[derive(Debug, PostgresType, Serialize, Deserialize)]
+#[pgx(sql_name = "renamed_type", schema = "test_schema")]
+pub struct RustNamedType(pub u64);
+
+#[derive(Debug, PostgresEnum, PartialEq)]
+#[pgx(sql_name = "renamed_enum", schema = "test_schema")]
+pub enum RustNamedEnum {
+    One,
+    Two,
+}
+
+#[derive(Debug, PostgresDomain, PartialEq)]
+#[pgx(sql_name = "renamed_domain")]
+#[check("VALUE > 0")]
+pub struct RustNamedDomain(i32);
+
+#[pg_extern(sql_name = "renamed_function", schema = "test_schema")]
+fn rust_named_function(
+    value: RustNamedType,
+    count: RustNamedDomain,
+    label: RustNamedEnum,
+) -> RustNamedEnum {
+    if value.0 + count.0 as u64 > 2 {
+        RustNamedEnum::Two
+    } else {
+        label
+    }
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -103,6 +143,47 @@ mod tests {
         Spi::run("SELECT type_in_diff_schema();");
     }
 
+    #[pg_test]
+    fn test_sql_names() {
+        use crate::tests::schema_tests::RustNamedEnum;
+
+        let value = Spi::get_one_with_args::<RustNamedEnum>(
+            "SELECT test_schema.renamed_function('1'::test_schema.renamed_type, 3, $1)",
+            vec![(
+                PgOid::from(RustNamedEnum::type_oid()),
+                RustNamedEnum::One.into_datum(),
+            )],
+        );
+        assert_eq!(value, Some(RustNamedEnum::Two));
+
+        let value = Spi::get_one::<RustNamedEnum>(
+            "SELECT test_schema.renamed_function('0'::test_schema.renamed_type, 1, 'One')",
+        );
+        assert_eq!(value, Some(RustNamedEnum::One));
+
+        let rust_names: bool = Spi::get_one(
+            "SELECT exists(SELECT 1 FROM pg_proc WHERE proname = 'rust_named_function')
+                 OR exists(SELECT 1 FROM pg_type WHERE typname IN ('rustnamedtype', 'rustnamedenum', 'rustnameddomain'))",
+        )
+        .expect("expected result");
+        assert_eq!(rust_names, false);
+    }
+
+    #[pg_test(
+        error = "value for domain renamed_domain violates check constraint \"renamed_domain_check\""
+    )]
+    fn test_sql_name_of_domain() {
+        Spi::run("SELECT test_schema.renamed_function('0'::test_schema.renamed_type, 0, 'One')");
+    }
+
+    #[pg_test]
+    fn test_untrusted_is_created() {
+        assert_eq!(
+            Spi::get_one::<bool>("SELECT test_schema.func_marked_untrusted()"),
+            Some(true)
+        );
+    }
+
     #[pg_test]
     fn elided_extern_is_elided() {
         // Validate that a function we know exists, exists
@@ -120,6 +201,19 @@ mod tests {
         assert_eq!(result, false);
     }
 
+    #[pg_test]
+    fn elided_extern_is_callable_from_rust() {
+        let result: bool = Spi::get_one(
+            "SELECT exists(SELECT 1 FROM pg_proc WHERE proname = 'helper_elided_from_schema');",
+        )
+        .expect("expected result");
+        assert_eq!(result, false);
+        assert_eq!(
+            crate::tests::schema_tests::test_schema::helper_elided_from_schema(21),
+            42
+        );
+    }
+
     #[pg_test]
     fn elided_type_is_elided() {
         // Validate that a type we know exists, exists
@@ -0,0 +1,64 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    struct TestClassification;
+
+    impl SecurityLabelProvider for TestClassification {
+        const NAME: &'static str = "pgx_tests";
+
+        fn check_relabel(
+            _object: &pg_sys::ObjectAddress,
+            label: Option<&str>,
+        ) -> Result<(), String> {
+            match label {
+                None | Some("public") | Some("secret") => Ok(()),
+                Some(label) => Err(format!("`{}` is not a valid classification", label)),
+            }
+        }
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_security_label() {
+        register_security_label_provider::<TestClassification>();
+        Spi::run("CREATE TABLE seclabel_test (id int)");
+        Spi::run("SECURITY LABEL FOR pgx_tests ON TABLE seclabel_test IS 'secret'");
+
+        let object = pg_sys::ObjectAddress {
+            classId: pg_sys::RelationRelationId,
+            objectId: Spi::get_one::<pg_sys::Oid>("SELECT 'seclabel_test'::regclass::oid")
+                .expect("table oid was null"),
+            objectSubId: 0,
+        };
+        assert_eq!(
+            security_label(&object, "pgx_tests"),
+            Some("secret".to_string())
+        );
+
+        Spi::run("SECURITY LABEL FOR pgx_tests ON TABLE seclabel_test IS NULL");
+        assert_eq!(security_label(&object, "pgx_tests"), None);
+    }
+
+    #[pg_test(error = "`top secret` is not a valid classification")]
+    fn test_invalid_security_label() {
+        register_security_label_provider::<TestClassification>();
+        Spi::run("CREATE TABLE seclabel_test (id int)");
+        Spi::run("SECURITY LABEL FOR pgx_tests ON TABLE seclabel_test IS 'top secret'");
+    }
+}
@@ -0,0 +1,110 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::time::Duration;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_secure_spi_search_path() {
+        Spi::run("SET search_path = public");
+        let secure = Spi::secure().set_search_path(&["tests", "Odd Schema"]);
+        assert_eq!(
+            secure.search_path(),
+            r#"pg_catalog, tests, "Odd Schema", pg_temp"#
+        );
+        let search_path = secure.run(|| Spi::get_one::<String>("SHOW search_path"));
+        assert_eq!(
+            search_path.as_deref(),
+            Some(r#"pg_catalog, tests, "Odd Schema", pg_temp"#)
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SHOW search_path").as_deref(),
+            Some("public")
+        );
+    }
+
+    #[pg_test]
+    fn test_secure_spi_temp_tables_cant_shadow() {
+        Spi::run("CREATE TEMPORARY TABLE pg_class (shadowed bool)");
+        Spi::run("INSERT INTO pg_class VALUES (true)");
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT count(*) FROM pg_class"),
+            Some(1)
+        );
+        let count = Spi::secure().run(|| Spi::get_one::<i64>("SELECT count(*) FROM pg_class"));
+        assert!(count.unwrap() > 1);
+    }
+
+    #[pg_test]
+    fn test_secure_spi_row_security() {
+        Spi::run("SET row_security = off");
+        let row_security = Spi::secure().run(|| Spi::get_one::<String>("SHOW row_security"));
+        assert_eq!(row_security.as_deref(), Some("on"));
+        assert_eq!(
+            Spi::get_one::<String>("SHOW row_security").as_deref(),
+            Some("off")
+        );
+    }
+
+    #[pg_test]
+    fn test_secure_spi_role() {
+        Spi::run("CREATE ROLE pgx_tests_secure_spi");
+        let role = role_oid("pgx_tests_secure_spi").unwrap();
+        let user = Spi::secure()
+            .set_role(role)
+            .run(|| Spi::get_one::<String>("SELECT current_user::text"));
+        assert_eq!(user.as_deref(), Some("pgx_tests_secure_spi"));
+        assert_ne!(current_user_id(), role);
+    }
+
+    #[pg_test(error = "canceling statement due to statement timeout")]
+    fn test_secure_spi_statement_timeout() {
+        Spi::secure()
+            .set_statement_timeout(Duration::from_millis(50))
+            .run(|| Spi::run("SELECT pg_sleep(5)"));
+    }
+
+    #[pg_test]
+    fn test_secure_spi_statement_timeout_not_reached() {
+        let slept = Spi::secure()
+            .set_statement_timeout(Duration::from_secs(60))
+            .run(|| Spi::get_one::<i32>("SELECT 1 FROM pg_sleep(0.01)"));
+        assert_eq!(slept, Some(1));
+    }
+
+    #[pg_test]
+    fn test_secure_spi_statement_timeout_resumes_outer_timer() {
+        // as postgres.c would have, if the test's statement had been run with a timeout
+        Spi::run("SET LOCAL statement_timeout = '1h'");
+        let finish = unsafe {
+            pg_sys::enable_timeout_after(pg_sys::TimeoutId_STATEMENT_TIMEOUT, 3_600_000);
+            pg_sys::get_timeout_finish_time(pg_sys::TimeoutId_STATEMENT_TIMEOUT)
+        };
+
+        let during = Spi::secure()
+            .set_statement_timeout(Duration::from_secs(60))
+            .run(|| unsafe {
+                pg_sys::get_timeout_finish_time(pg_sys::TimeoutId_STATEMENT_TIMEOUT)
+            });
+        let after = unsafe { pg_sys::get_timeout_finish_time(pg_sys::TimeoutId_STATEMENT_TIMEOUT) };
+        unsafe { pg_sys::disable_timeout(pg_sys::TimeoutId_STATEMENT_TIMEOUT, false) };
+
+        assert!(during < finish);
+        assert_eq!(after, finish);
+    }
+}
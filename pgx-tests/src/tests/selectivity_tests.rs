@@ -0,0 +1,130 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+/// How common the constant is, or 1 in 1000 when it isn't one of the most common values
+#[pg_extern(stable, parallel_safe)]
+fn selectivity_test_restrict(
+    root: Internal,
+    operator: pg_sys::Oid,
+    args: Internal,
+    var_relid: i32,
+) -> f64 {
+    let estimate = unsafe { RestrictSelectivity::new(root, operator, args, var_relid) };
+    let clause = match estimate.examine() {
+        Some(clause) => clause,
+        None => return 0.5,
+    };
+    match (
+        clause.constant::<i32>(),
+        clause.variable.most_common_values::<i32>(),
+    ) {
+        (Some(Some(value)), Some(mcvs)) => mcvs
+            .into_iter()
+            .find(|(mcv, _)| *mcv == value)
+            .map(|(_, frequency)| frequency)
+            .unwrap_or(0.001),
+        _ => 0.5,
+    }
+}
+
+#[pg_operator(immutable, parallel_safe)]
+#[opname(=%=)]
+#[restrict(selectivity_test_restrict)]
+fn selectivity_test_eq(left: i32, right: i32) -> bool {
+    left == right
+}
+
+/// Each value on the side with more distinct values matches one on the other side
+#[pg_extern(stable, parallel_safe)]
+fn selectivity_test_join(
+    root: Internal,
+    operator: pg_sys::Oid,
+    args: Internal,
+    join_type: i16,
+    sjinfo: Internal,
+) -> f64 {
+    let estimate = unsafe { JoinSelectivity::new(root, operator, args, join_type, sjinfo) };
+    let (mut left, mut right, _) = estimate.examine();
+    match (left.num_distinct(), right.num_distinct()) {
+        (Some(left), Some(right)) => clamp_probability(1.0 / left.max(right)),
+        _ => 0.5,
+    }
+}
+
+#[pg_operator(immutable, parallel_safe)]
+#[opname(=%%=)]
+#[join(selectivity_test_join)]
+fn selectivity_test_join_eq(left: i32, right: i32) -> bool {
+    left == right
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    /// 1000 rows, 900 of which are `1`, and the rest distinct
+    fn create_table() {
+        Spi::run(
+            "CREATE TABLE selectivity_test AS
+                SELECT CASE WHEN i <= 900 THEN 1 ELSE i END AS value FROM generate_series(1, 1000) AS i;
+             ANALYZE selectivity_test;",
+        );
+    }
+
+    fn estimated_rows(query: &str) -> f64 {
+        let explain = Spi::get_one::<Json>(&format!("EXPLAIN (FORMAT JSON) {}", query))
+            .expect("EXPLAIN returned null");
+        explain.0[0]["Plan"]["Plan Rows"]
+            .as_f64()
+            .expect("no Plan Rows")
+    }
+
+    #[pg_test]
+    fn test_restrict_selectivity_most_common_value() {
+        create_table();
+        let rows = estimated_rows("SELECT * FROM selectivity_test WHERE value =%= 1");
+        assert_eq!(rows, 900.0);
+    }
+
+    #[pg_test]
+    fn test_restrict_selectivity_uncommon_value() {
+        create_table();
+        let rows = estimated_rows("SELECT * FROM selectivity_test WHERE value =%= 950");
+        assert_eq!(rows, 1.0);
+    }
+
+    #[pg_test]
+    fn test_restrict_selectivity_not_a_constant() {
+        create_table();
+        let rows = estimated_rows("SELECT * FROM selectivity_test WHERE value =%= value");
+        assert_eq!(rows, 500.0);
+    }
+
+    #[pg_test]
+    fn test_join_selectivity() {
+        create_table();
+        Spi::run(
+            "CREATE TABLE selectivity_test_other AS SELECT i AS value FROM generate_series(1, 10) AS i;
+             ANALYZE selectivity_test_other;",
+        );
+        // 1000 * 10 pairs, with 101 distinct values on the larger side
+        let rows = estimated_rows(
+            "SELECT * FROM selectivity_test JOIN selectivity_test_other
+                ON selectivity_test.value =%%= selectivity_test_other.value",
+        );
+        assert_eq!(rows, (1000.0_f64 * 10.0 / 101.0).round());
+    }
+}
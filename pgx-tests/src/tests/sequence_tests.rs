@@ -0,0 +1,93 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_sequence_values() {
+        let sequence = PgSequenceBuilder::new("Sequence Test")
+            .set_schema("tests")
+            .set_start(10)
+            .set_increment(5)
+            .create();
+        assert_eq!(sequence.nextval(), 10);
+        assert_eq!(sequence.nextval(), 15);
+        assert_eq!(sequence.currval(), 15);
+
+        sequence.setval(100, false);
+        assert_eq!(sequence.nextval(), 100);
+        sequence.setval(100, true);
+        assert_eq!(sequence.nextval(), 105);
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT currval('tests.\"Sequence Test\"')"),
+            Some(105)
+        );
+    }
+
+    #[pg_test]
+    fn test_sequence_parameters() {
+        PgSequenceBuilder::new("parameters_seq")
+            .set_schema("tests")
+            .set_data_type(RegType(pg_sys::INT4OID))
+            .set_min_value(-10)
+            .set_max_value(10)
+            .set_increment(-1)
+            .set_cache(4)
+            .set_cycle(true)
+            .create();
+        let sequence = PgSequence::from_name("tests.parameters_seq").expect("no sequence");
+        assert_eq!(
+            sequence.parameters(),
+            PgSequenceParameters {
+                data_type: RegType(pg_sys::INT4OID),
+                start: 10,
+                increment: -1,
+                min_value: -10,
+                max_value: 10,
+                cache: 4,
+                cycle: true,
+            }
+        );
+    }
+
+    #[pg_test]
+    fn test_sequence_bigint_values() {
+        let sequence = PgSequenceBuilder::new("bigint_seq")
+            .set_schema("tests")
+            .set_start(1 << 40)
+            .set_max_value(i64::MAX)
+            .create();
+        assert_eq!(sequence.nextval(), 1 << 40);
+        assert_eq!(sequence.parameters().max_value, i64::MAX);
+    }
+
+    #[pg_test(error = "relation \"existing_seq\" already exists")]
+    fn test_sequence_already_exists() {
+        PgSequenceBuilder::new("existing_seq")
+            .set_schema("tests")
+            .create();
+        PgSequenceBuilder::new("existing_seq")
+            .set_schema("tests")
+            .create();
+    }
+
+    #[pg_test(error = "\"pg_class\" is not a sequence")]
+    fn test_open_non_sequence() {
+        PgSequence::from_name("pg_class");
+    }
+}
@@ -0,0 +1,44 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    fn addin_shmem_init_lock_held() -> bool {
+        unsafe {
+            let lock: *mut pg_sys::LWLock = &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+            pg_sys::LWLockHeldByMe(lock)
+        }
+    }
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_with_addin_shmem_init_lock() {
+        assert!(!addin_shmem_init_lock_held());
+        let held = with_addin_shmem_init_lock(addin_shmem_init_lock_held);
+        assert!(held);
+        assert!(!addin_shmem_init_lock_held());
+    }
+
+    #[pg_test]
+    fn test_with_addin_shmem_init_lock_released_after_panic() {
+        let result = std::panic::catch_unwind(|| {
+            with_addin_shmem_init_lock(|| panic!("while holding AddinShmemInitLock"))
+        });
+        assert!(result.is_err());
+        assert!(!addin_shmem_init_lock_held());
+    }
+}
@@ -0,0 +1,138 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    PostgresType,
+    PostgresEq,
+    PostgresOrd,
+)]
+pub struct SortedThing(String);
+
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    PostgresType,
+    PostgresEq,
+    PostgresOrd,
+)]
+#[abbreviated_keys]
+pub struct AbbreviatedThing(String);
+
+impl AbbreviatedKeys for AbbreviatedThing {
+    fn abbreviated_key(&self) -> pg_sys::Datum {
+        let mut prefix = [0u8; std::mem::size_of::<pg_sys::Datum>()];
+        let len = self.0.len().min(prefix.len());
+        prefix[..len].copy_from_slice(&self.0.as_bytes()[..len]);
+        pg_sys::Datum::from_be_bytes(prefix)
+    }
+}
+
+#[pg_extern]
+fn sorted_thing(value: String) -> SortedThing {
+    SortedThing(value)
+}
+
+#[pg_extern]
+fn sorted_thing_text(thing: SortedThing) -> String {
+    thing.0
+}
+
+#[pg_extern]
+fn abbreviated_thing(value: String) -> AbbreviatedThing {
+    AbbreviatedThing(value)
+}
+
+#[pg_extern]
+fn abbreviated_thing_text(thing: AbbreviatedThing) -> String {
+    thing.0
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+
+    /// 1000 values in no particular order, which share more than a `Datum`'s worth of prefix in
+    /// groups of ten, so abbreviated keys only sometimes tell them apart
+    fn expected() -> Vec<String> {
+        let mut values = (0..1000)
+            .map(|i| format!("value-{:04}", i * 7919 % 1000))
+            .collect::<Vec<_>>();
+        values.sort();
+        values
+    }
+
+    #[pg_test]
+    fn test_sortsupport_registered() {
+        for family in &["sortedthing_btree_ops", "abbreviatedthing_btree_ops"] {
+            let count = Spi::get_one::<i64>(&format!(
+                "SELECT count(*) FROM pg_amproc
+                 JOIN pg_opfamily ON pg_opfamily.oid = amprocfamily
+                 WHERE opfname = '{}' AND amprocnum = 2",
+                family
+            ))
+            .expect("SQL select failed");
+            assert_eq!(count, 1, "no sortsupport function in {}", family);
+        }
+    }
+
+    #[pg_test]
+    fn test_sortsupport_order_by() {
+        let sorted = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(sorted_thing_text(thing) ORDER BY thing) FROM (
+                SELECT sorted_thing('value-' || lpad((i * 7919 % 1000)::text, 4, '0')) AS thing
+                FROM generate_series(0, 999) AS i
+            ) AS things",
+        )
+        .expect("SQL select failed");
+        assert_eq!(sorted, expected());
+    }
+
+    #[pg_test]
+    fn test_sortsupport_abbreviated_order_by() {
+        let sorted = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(abbreviated_thing_text(thing) ORDER BY thing) FROM (
+                SELECT abbreviated_thing('value-' || lpad((i * 7919 % 1000)::text, 4, '0')) AS thing
+                FROM generate_series(0, 999) AS i
+            ) AS things",
+        )
+        .expect("SQL select failed");
+        assert_eq!(sorted, expected());
+    }
+
+    #[pg_test]
+    fn test_sortsupport_abbreviated_order_by_desc() {
+        let sorted = Spi::get_one::<Vec<String>>(
+            "SELECT array_agg(abbreviated_thing_text(thing) ORDER BY thing DESC) FROM (
+                SELECT abbreviated_thing('value-' || lpad((i * 7919 % 1000)::text, 4, '0')) AS thing
+                FROM generate_series(0, 999) AS i
+            ) AS things",
+        )
+        .expect("SQL select failed");
+        let mut expected = expected();
+        expected.reverse();
+        assert_eq!(sorted, expected);
+    }
+}
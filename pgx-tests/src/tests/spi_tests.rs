@@ -115,6 +115,49 @@ mod tests {
         });
     }
 
+    #[pg_test]
+    fn test_spi_get_tuple() {
+        let (i, s, b) = Spi::get::<(i64, String, bool)>("SELECT 42, 'test', NULL::bool");
+        assert_eq!(i, Some(42));
+        assert_eq!(s.as_deref(), Some("test"));
+        assert_eq!(b, None);
+
+        let (s, _) = Spi::get::<(&str, i32)>("SELECT 'borrowed', 1");
+        assert_eq!(s, Some("borrowed"));
+
+        let none = Spi::get::<(i32, i32)>("SELECT 1, 2 WHERE false");
+        assert_eq!(none, (None, None));
+
+        let (first, .., last) =
+            Spi::get::<(i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32)>(
+                "SELECT 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12",
+            );
+        assert_eq!((first, last), (Some(1), Some(12)));
+    }
+
+    #[pg_test]
+    fn test_spi_iter() {
+        let rows =
+            Spi::iter::<(i32, String)>("SELECT i, repeat('x', i) FROM generate_series(1, 3) AS i")
+                .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                (Some(1), Some("x".to_string())),
+                (Some(2), Some("xx".to_string())),
+                (Some(3), Some("xxx".to_string())),
+            ]
+        );
+
+        let total = Spi::iter_with_args::<(i64,)>(
+            "SELECT i FROM generate_series(1, $1) AS i",
+            spi_args![10i64],
+        )
+        .map(|(i,)| i.unwrap())
+        .sum::<i64>();
+        assert_eq!(total, 55);
+    }
+
     #[pg_test]
     fn test_spi_get_two_with_failure() {
         Spi::execute(|client| {
@@ -168,4 +211,328 @@ mod tests {
         );
         assert_eq!(result, Some(1));
     }
+
+    #[pg_test]
+    fn test_explain_plan() {
+        let plan = Spi::explain_plan("SELECT * FROM generate_series(1, 10)");
+        assert_eq!(plan.plan.node_type, "Function Scan");
+        assert!(plan.plan.total_cost.is_some());
+        assert!(plan.execution_time.is_none());
+    }
+
+    #[pg_test]
+    fn test_explain_plan_with_args() {
+        Spi::run("CREATE TABLE tests.explain_test (id int)");
+        let plan = Spi::explain_plan_with_args(
+            "SELECT * FROM tests.explain_test a JOIN tests.explain_test b USING (id) WHERE a.id = $1",
+            vec![(PgBuiltInOids::INT4OID.oid(), 42.into_datum())],
+        );
+        let scans = plan
+            .nodes()
+            .into_iter()
+            .filter(|node| node.relation_name.as_deref() == Some("explain_test"))
+            .count();
+        assert_eq!(scans, 2);
+    }
+
+    #[pg_test]
+    fn test_run_with_args() {
+        Spi::run("CREATE TABLE tests.run_with_args (name text, manager bigint, tags text[])");
+        Spi::run_with_args(
+            "INSERT INTO tests.run_with_args VALUES ($1, $2, $3)",
+            spi_args!["ed", None::<i64>, vec!["dba", "oncall"]],
+        );
+        let (manager_is_null, tags) = Spi::get_two::<bool, String>(
+            "SELECT manager IS NULL, array_to_string(tags, ',') FROM tests.run_with_args WHERE name = 'ed'",
+        );
+        assert_eq!(manager_is_null, Some(true));
+        assert_eq!(tags.as_deref(), Some("dba,oncall"));
+    }
+
+    #[pg_test]
+    fn test_get_one_with_inferred_args() {
+        let result = Spi::get_one_with_args::<i64>(
+            "SELECT $1 + array_length($2, 1) + coalesce($3, 0)",
+            spi_args![40i64, vec![1.5f64, 2.5], None::<i64>],
+        );
+        assert_eq!(result, Some(42));
+        assert_eq!(
+            spi_arg(None::<pgx::Uuid>).0.value(),
+            PgBuiltInOids::UUIDOID.value()
+        );
+    }
+
+    #[pg_test]
+    fn test_try_get_one() {
+        Spi::execute(|client| {
+            let table = client
+                .select("SELECT 42::bigint AS answer", None, None)
+                .first();
+            let error = table.try_get_one::<String>().unwrap_err();
+            assert_eq!(error.datum_type, pg_sys::INT8OID);
+            assert_eq!(error.expected_type, pg_sys::TEXTOID);
+            assert_eq!(
+                error.to_string(),
+                "column \"answer\" is bigint, expected text for alloc::string::String"
+            );
+            assert_eq!(table.try_get_one::<i64>(), Ok(Some(42)));
+        });
+    }
+
+    #[pg_test]
+    fn test_try_value() {
+        Spi::execute(|client| {
+            let tuple = client
+                .select("SELECT 'x'::varchar AS v, NULL::int AS n", None, None)
+                .first()
+                .get_heap_tuple()
+                .expect("no tuple");
+            let v = tuple.by_name("v").unwrap();
+            assert_eq!(v.try_value::<String>(), Ok(Some("x".to_string())));
+            assert!(v.try_value::<i32>().is_err());
+            let n = tuple.by_name("n").unwrap();
+            assert_eq!(n.try_value::<i32>(), Ok(None));
+            assert!(n.try_value::<&str>().is_err());
+        });
+    }
+
+    #[pg_test]
+    fn test_update_counts_rows() {
+        Spi::run("CREATE TABLE tests.update_counts (id int, name text)");
+        assert_eq!(
+            Spi::update(
+                "INSERT INTO tests.update_counts SELECT g, 'x' FROM generate_series(1, 5) g"
+            ),
+            5
+        );
+        assert_eq!(
+            Spi::update_with_args(
+                "UPDATE tests.update_counts SET name = $1 WHERE id > $2",
+                spi_args!["y", 3],
+            ),
+            2
+        );
+        assert_eq!(
+            Spi::update("DELETE FROM tests.update_counts WHERE id = 0"),
+            0
+        );
+        assert_eq!(Spi::update("CREATE INDEX ON tests.update_counts (id)"), 0);
+    }
+
+    #[pg_test]
+    fn test_insert_returning() {
+        Spi::run("CREATE TABLE tests.insert_returning (id serial, name text)");
+        let ids = Spi::insert_returning_with_args::<i32>(
+            "INSERT INTO tests.insert_returning (name) SELECT unnest($1) RETURNING id",
+            spi_args![vec!["ed", "edd", "eddy"]],
+        );
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3)]);
+
+        let names = Spi::insert_returning::<String>(
+            "UPDATE tests.insert_returning SET name = NULLIF(name, 'edd') RETURNING name",
+        );
+        assert_eq!(names.len(), 3);
+        assert!(names.contains(&None));
+
+        let none = Spi::insert_returning::<i32>(
+            "DELETE FROM tests.insert_returning WHERE id > 10 RETURNING id",
+        );
+        assert!(none.is_empty());
+    }
+
+    #[pg_test(error = "column \"id\" is integer, expected text for alloc::string::String")]
+    fn test_insert_returning_wrong_type() {
+        Spi::run("CREATE TABLE tests.insert_returning_wrong_type (id int)");
+        Spi::insert_returning::<String>(
+            "INSERT INTO tests.insert_returning_wrong_type VALUES (1) RETURNING id",
+        );
+    }
+
+    #[pg_extern(stable)]
+    fn spi_mode_when_stable() -> String {
+        format!("{:?}", Spi::mode())
+    }
+
+    #[pg_extern]
+    fn spi_mode_when_volatile() -> String {
+        format!("{:?}", Spi::mode())
+    }
+
+    #[pg_extern(stable)]
+    fn stable_insert() {
+        Spi::run("INSERT INTO tests.stable_insert VALUES (1)");
+    }
+
+    #[pg_test]
+    fn test_spi_mode_follows_volatility() {
+        assert_eq!(Spi::mode(), SpiMode::ReadWrite);
+        assert_eq!(
+            Spi::get_one::<String>("SELECT tests.spi_mode_when_stable()"),
+            Some("ReadOnly".to_string())
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SELECT tests.spi_mode_when_volatile()"),
+            Some("ReadWrite".to_string())
+        );
+
+        Spi::with_mode(SpiMode::ReadOnly, || {
+            assert_eq!(
+                Spi::get_one::<String>("SELECT tests.spi_mode_when_volatile()"),
+                Some("ReadWrite".to_string())
+            );
+            assert_eq!(Spi::mode(), SpiMode::ReadOnly);
+        });
+        assert_eq!(Spi::mode(), SpiMode::ReadWrite);
+    }
+
+    #[pg_test(error = "INSERT is not allowed in a non-volatile function")]
+    fn test_stable_function_cannot_write() {
+        Spi::run("CREATE TABLE tests.stable_insert (id int)");
+        Spi::run("SELECT tests.stable_insert()");
+    }
+
+    #[pg_test(error = "CREATE TABLE is not allowed in a non-volatile function")]
+    fn test_read_only_client_cannot_write() {
+        Spi::execute(|client| {
+            client.set_mode(SpiMode::ReadOnly).update(
+                "CREATE TABLE tests.read_only_client (id int)",
+                None,
+                None,
+            );
+        });
+    }
+
+    #[pg_test]
+    fn test_read_only_sees_snapshot_of_calling_query() {
+        Spi::run("CREATE TABLE tests.read_only_snapshot (id int)");
+        Spi::run("INSERT INTO tests.read_only_snapshot VALUES (1)");
+
+        let count = "SELECT count(*) FROM tests.read_only_snapshot";
+        assert_eq!(
+            Spi::with_mode(SpiMode::ReadOnly, || Spi::get_one::<i64>(count)),
+            Some(0)
+        );
+        assert_eq!(Spi::get_one::<i64>(count), Some(1));
+    }
+
+    #[pg_extern]
+    fn spi_connection_depth_when_nested() -> i64 {
+        Spi::connect(|_| Ok(Some(Spi::connection_depth() as i64))).unwrap()
+    }
+
+    #[pg_extern]
+    fn spi_nested_greeting(times: i32) -> String {
+        Spi::get_one_with_args("SELECT repeat('hi', $1)", spi_args![times]).unwrap()
+    }
+
+    #[pg_test]
+    fn test_nested_connections() {
+        assert_eq!(Spi::connection_depth(), 0);
+        Spi::execute(|_| {
+            assert_eq!(Spi::connection_depth(), 1);
+            Spi::execute(|_| assert_eq!(Spi::connection_depth(), 2));
+            assert_eq!(Spi::connection_depth(), 1);
+        });
+        assert_eq!(Spi::connection_depth(), 0);
+
+        assert_eq!(
+            Spi::get_one::<i64>("SELECT tests.spi_connection_depth_when_nested()"),
+            Some(2)
+        );
+        assert_eq!(Spi::connection_depth(), 0);
+    }
+
+    #[pg_test]
+    fn test_results_outlive_nested_connections() {
+        Spi::execute(|client| {
+            let greetings = client
+                .select(
+                    "SELECT tests.spi_nested_greeting(g) FROM generate_series(1, 3) g",
+                    None,
+                    None,
+                )
+                .map(|row| row[1].value::<String>())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                greetings,
+                vec![
+                    Some("hi".to_string()),
+                    Some("hihi".to_string()),
+                    Some("hihihi".to_string())
+                ]
+            );
+
+            // and the connection can run more statements after the nested ones finished
+            let table = client.select("SELECT 42", None, None).first();
+            assert_eq!(table.get_one::<i32>(), Some(42));
+        });
+    }
+
+    #[pg_test(error = "this SpiClient's SPI connection has finished")]
+    fn test_client_after_its_connection_finished() {
+        let mut escaped = None;
+        Spi::connect(|client| {
+            escaped = Some(client);
+            Ok(Some(()))
+        });
+        escaped.unwrap().select("SELECT 1", None, None);
+    }
+
+    #[pg_test(error = "syntax error at or near \"NOT\"")]
+    fn test_error_in_nested_connection() {
+        Spi::execute(|_| {
+            Spi::execute(|client| {
+                client.select("NOT A QUERY", None, None);
+            });
+        });
+    }
+
+    #[pg_extern]
+    fn spi_open_series(upto: i32) -> RefCursor {
+        Spi::connect(|client| {
+            let cursor = client.open_cursor("SELECT generate_series(1, $1)", Some(spi_args![upto]));
+            Ok(Some(cursor.into_refcursor()))
+        })
+        .unwrap()
+    }
+
+    #[pg_test]
+    fn test_cursor_fetch() {
+        Spi::execute(|client| {
+            let mut cursor = client.open_cursor("SELECT generate_series(1, 5)", None);
+            let first = cursor
+                .fetch(3)
+                .map(|row| row.by_ordinal(1).unwrap().value::<i32>())
+                .collect::<Vec<_>>();
+            assert_eq!(first, vec![Some(1), Some(2), Some(3)]);
+            assert_eq!(cursor.fetch(10).len(), 2);
+            assert!(cursor.fetch(10).is_empty());
+        });
+    }
+
+    #[pg_test]
+    fn test_cursor_closed_when_dropped() {
+        Spi::execute(|client| {
+            let name = client.open_cursor("SELECT 1", None).name().to_string();
+            assert!(client.find_cursor(&name).is_none());
+        });
+    }
+
+    #[pg_test]
+    fn test_return_refcursor() {
+        let cursor = Spi::get_one::<RefCursor>("SELECT tests.spi_open_series(4)").unwrap();
+        let fetch = format!("FETCH 1 FROM {}", quote_identifier(&cursor));
+        assert_eq!(Spi::get_one::<i32>(&fetch), Some(1));
+
+        let sum = Spi::connect(|client| {
+            let mut cursor = client.find_cursor(&cursor).expect("cursor was closed");
+            Ok(Some(
+                cursor
+                    .fetch(10)
+                    .map(|row| row.by_ordinal(1).unwrap().value::<i32>().unwrap())
+                    .sum::<i32>(),
+            ))
+        });
+        assert_eq!(sum, Some(2 + 3 + 4));
+    }
 }
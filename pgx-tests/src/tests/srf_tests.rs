@@ -67,6 +67,30 @@ fn return_none_setof_iterator() -> Option<impl std::iter::Iterator<Item = i32>>
     }
 }
 
+/// A row for each of `rows`, with a column for each of `columns`, whose types are the query's, or
+/// `text` if it doesn't give them
+#[pg_extern]
+fn example_runtime_columns(
+    columns: Vec<String>,
+    rows: i32,
+    context: FcInfoContext,
+) -> impl std::iter::Iterator<Item = PgComposite> {
+    let tupdesc = context.result_tupdesc().unwrap_or_else(|| {
+        let columns = columns
+            .iter()
+            .map(|column| (column.as_str(), pg_sys::TEXTOID))
+            .collect::<Vec<_>>();
+        PgTupleDesc::from_columns(&columns)
+    });
+    (1..=rows).map(move |row| {
+        let values = tupdesc
+            .iter()
+            .map(|att| format!("{}{}", att.name(), row).into_datum_as(att.atttypid))
+            .collect();
+        PgComposite::from_tupdesc(&tupdesc, values)
+    })
+}
+
 #[cfg(any(test, feature = "pg_test"))]
 #[pgx::pg_schema]
 mod tests {
@@ -165,4 +189,39 @@ mod tests {
 
         assert_eq!(cnt, Some(0))
     }
+
+    #[pg_test]
+    fn test_runtime_columns_from_query() {
+        let query =
+            "SELECT * FROM example_runtime_columns(ARRAY['a', 'b'], 2) AS t(a text, b varchar)";
+        let rows = Spi::connect(|client| {
+            let rows = client
+                .select(query, None, None)
+                .map(|row| {
+                    (
+                        row.by_ordinal(1).unwrap().value::<String>(),
+                        row.by_ordinal(2).unwrap().value::<String>(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            Ok(Some(rows))
+        })
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (Some("a1".to_string()), Some("b1".to_string())),
+                (Some("a2".to_string()), Some("b2".to_string()))
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_runtime_columns_from_function() {
+        let row =
+            Spi::get_one::<PgComposite>("SELECT example_runtime_columns(ARRAY['x', 'y', 'z'], 1)")
+                .unwrap();
+        assert_eq!(row.len(), 3);
+        assert_eq!(row.get_by_name::<String>("z").as_deref(), Some("z1"));
+    }
 }
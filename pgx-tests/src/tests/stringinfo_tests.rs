@@ -0,0 +1,127 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+    use std::io::Read;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_with_capacity() {
+        let mut buf = StringInfo::with_capacity(4096);
+        assert_eq!(buf.capacity(), 4096);
+        assert!(buf.is_empty());
+        buf.push_str("pgx");
+        assert_eq!(buf.as_bytes(), b"pgx");
+    }
+
+    #[pg_test]
+    fn test_pooled_is_reused() {
+        let ptr = {
+            let mut buf = StringInfo::pooled();
+            buf.push_str("reused");
+            buf.as_ptr()
+        };
+        let buf = StringInfo::pooled();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_ptr(), ptr);
+
+        // another one, while the first is borrowed
+        let other = StringInfo::pooled();
+        assert_ne!(other.as_ptr(), ptr);
+    }
+
+    #[pg_test]
+    fn test_pooled_into_cstr() {
+        let mut buf = StringInfo::pooled();
+        buf.push_str("copied");
+        let cstr: &std::ffi::CStr = buf.into();
+        assert_eq!(cstr.to_str(), Ok("copied"));
+    }
+
+    #[pg_test]
+    fn test_binary_round_trip() {
+        let mut buf = StringInfo::new();
+        buf.push_i8(-8);
+        buf.push_i16(-16);
+        buf.push_i32(-32);
+        buf.push_i64(-64);
+        buf.push_u32(u32::MAX);
+        buf.push_f32(1.5);
+        buf.push_f64(-2.25);
+        buf.push_counted_bytes(b"pgx");
+
+        assert_eq!(buf.read_i8(), -8);
+        assert_eq!(buf.read_i16(), -16);
+        assert_eq!(buf.read_i32(), -32);
+        assert_eq!(buf.read_i64(), -64);
+        assert_eq!(buf.read_u32(), u32::MAX);
+        assert_eq!(buf.read_f32(), 1.5);
+        assert_eq!(buf.read_f64(), -2.25);
+        assert_eq!(buf.read_counted_bytes(), Some(&b"pgx"[..]));
+        assert_eq!(buf.remaining(), 0);
+        buf.read_end();
+    }
+
+    #[pg_test]
+    fn test_network_byte_order() {
+        let mut buf = StringInfo::new();
+        buf.push_i32(1);
+        buf.push_i16(0x0102);
+        assert_eq!(buf.as_bytes(), &[0, 0, 0, 1, 1, 2]);
+    }
+
+    #[pg_test]
+    fn test_matches_int8send() {
+        let sent = Spi::get_one::<Vec<u8>>("SELECT int8send(1234567890123)").unwrap();
+        let mut buf = StringInfo::from(sent);
+        assert_eq!(buf.read_i64(), 1234567890123);
+    }
+
+    #[pg_test]
+    fn test_null_counted_bytes() {
+        let mut buf = StringInfo::new();
+        buf.push_i32(-1);
+        assert_eq!(buf.read_counted_bytes(), None);
+    }
+
+    #[pg_test]
+    fn test_read_trait() {
+        let mut buf = StringInfo::from("hello world");
+        let mut hello = [0u8; 5];
+        buf.read_exact(&mut hello).unwrap();
+        assert_eq!(&hello, b"hello");
+        assert_eq!(buf.cursor(), 5);
+
+        let mut rest = String::new();
+        buf.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, " world");
+    }
+
+    #[pg_test(error = "insufficient data left in message")]
+    fn test_read_past_end() {
+        let mut buf = StringInfo::new();
+        buf.push_i16(1);
+        buf.read_i32();
+    }
+
+    #[pg_test(error = "invalid message format")]
+    fn test_read_end_with_data_left() {
+        let mut buf = StringInfo::new();
+        buf.push_i16(1);
+        buf.read_end();
+    }
+}
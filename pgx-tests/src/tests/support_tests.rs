@@ -0,0 +1,75 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+#[pg_extern(immutable, parallel_safe, rows = 7)]
+fn support_test_week() -> impl Iterator<Item = i32> {
+    1..=7
+}
+
+/// Knows `support_test_repeat()` returns as many rows as it's asked to
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+#[pg_extern(immutable, parallel_safe)]
+fn support_test_repeat_support(request: Internal) -> Internal {
+    let request = unsafe { SupportRequest::new(request) };
+    match request.rows() {
+        Some(rows) => match rows.constant_arg::<i32>(1) {
+            Some(Some(times)) => rows.set_rows(times as f64),
+            _ => SupportRequest::unhandled(),
+        },
+        None => SupportRequest::unhandled(),
+    }
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+#[pg_extern(immutable, parallel_safe, support = support_test_repeat_support)]
+fn support_test_repeat(value: String, times: i32) -> impl Iterator<Item = String> {
+    std::iter::repeat(value).take(times as usize)
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+
+    fn estimated_rows(query: &str) -> f64 {
+        let explain = Spi::get_one::<Json>(&format!("EXPLAIN (FORMAT JSON) {}", query))
+            .expect("EXPLAIN returned null");
+        explain.0[0]["Plan"]["Plan Rows"]
+            .as_f64()
+            .expect("no Plan Rows")
+    }
+
+    #[pg_test]
+    fn test_rows() {
+        let rows = Spi::get_one::<f32>(
+            "SELECT prorows FROM pg_proc WHERE oid = 'support_test_week'::regproc",
+        );
+        assert_eq!(rows, Some(7.0));
+        assert_eq!(estimated_rows("SELECT * FROM support_test_week()"), 7.0);
+    }
+
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    #[pg_test]
+    fn test_support_rows() {
+        let rows = estimated_rows("SELECT * FROM support_test_repeat('a', 25)");
+        assert_eq!(rows, 25.0);
+    }
+
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    #[pg_test]
+    fn test_support_unhandled() {
+        // not a constant, so the planner falls back to the function's `ROWS`
+        let rows =
+            estimated_rows("SELECT * FROM generate_series(1, 2) AS i, support_test_repeat('a', i)");
+        assert_eq!(rows, 2.0 * 1000.0);
+    }
+}
@@ -0,0 +1,99 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+extension_sql!(
+    r#"
+CREATE TABLE temp_table_test_source (id int NOT NULL, label text DEFAULT 'none' CHECK (label <> ''));
+"#,
+    name = "create_temp_table_test_source",
+);
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_temp_table_columns() {
+        let table = PgTempTableBuilder::new("Staged Rows")
+            .add_column("id", RegType(pg_sys::INT8OID))
+            .add_column("Payload", RegType(pg_sys::JSONBOID))
+            .create();
+        Spi::run(r#"INSERT INTO "Staged Rows" VALUES (1, '{}')"#);
+        assert_eq!(
+            Spi::get_one::<i64>(r#"SELECT count(*) FROM pg_temp."Staged Rows""#),
+            Some(1)
+        );
+
+        let relation = unsafe { PgRelation::open(table.oid()) };
+        assert!(relation.is_temp());
+        assert!(!relation.is_other_temp());
+        assert_eq!(relation.persistence(), RelPersistence::Temporary);
+        assert_eq!(relation.tuple_desc().len(), 2);
+        relation.check_not_other_temp();
+        assert!(is_temp_namespace(relation.namespace_oid()));
+        assert!(!is_other_temp_namespace(relation.namespace_oid()));
+    }
+
+    #[pg_test]
+    fn test_temp_table_like() {
+        let table = PgTempTableBuilder::new("temp_table_test_source")
+            .set_like(RegClass::from_name("temp_table_test_source").unwrap())
+            .add_column("loaded_at", RegType(pg_sys::TIMESTAMPTZOID))
+            .set_on_commit(OnCommit::DeleteRows)
+            .create();
+        // the temporary table is found first
+        assert_eq!(RegClass::from_name("temp_table_test_source"), Some(table));
+        Spi::run("INSERT INTO temp_table_test_source (id) VALUES (1)");
+        assert_eq!(
+            Spi::get_one::<String>("SELECT label FROM temp_table_test_source"),
+            Some("none".to_string())
+        );
+        assert_eq!(
+            unsafe { PgRelation::open(table.oid()) }.tuple_desc().len(),
+            3
+        );
+    }
+
+    #[pg_test]
+    fn test_temp_table_if_not_exists() {
+        let table = PgTempTableBuilder::new("staged")
+            .add_column("id", RegType(pg_sys::INT4OID))
+            .set_on_commit(OnCommit::Drop)
+            .create();
+        let again = PgTempTableBuilder::new("staged")
+            .add_column("id", RegType(pg_sys::INT4OID))
+            .set_if_not_exists(true)
+            .create();
+        assert_eq!(table, again);
+    }
+
+    #[pg_test(error = "relation \"staged\" already exists")]
+    fn test_temp_table_exists() {
+        PgTempTableBuilder::new("staged").create();
+        PgTempTableBuilder::new("staged").create();
+    }
+
+    #[pg_test]
+    fn test_temp_table_permanent() {
+        let relation = PgRelation::open_with_name_and_share_lock("temp_table_test_source").unwrap();
+        assert!(!relation.is_temp());
+        assert!(!relation.is_other_temp());
+        assert!(!is_temp_namespace(relation.namespace_oid()));
+        assert!(!is_other_temp_namespace(relation.namespace_oid()));
+    }
+}
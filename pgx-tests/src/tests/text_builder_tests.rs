@@ -0,0 +1,52 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+    use pgx::*;
+    use std::fmt::Write;
+
+    #[pg_extern]
+    fn text_builder_numbers(upto: i32) -> TextBuilder {
+        let mut text = TextBuilder::with_capacity(0);
+        for i in 1..=upto {
+            write!(text, "{},", i).unwrap();
+        }
+        text
+    }
+
+    #[pg_test]
+    fn test_text_builder() {
+        let text = Spi::get_one::<String>("SELECT tests.text_builder_numbers(3)");
+        assert_eq!(text.as_deref(), Some("1,2,3,"));
+        let text = Spi::get_one::<String>("SELECT tests.text_builder_numbers(0)");
+        assert_eq!(text.as_deref(), Some(""));
+    }
+
+    #[pg_test]
+    fn test_text_builder_grows() {
+        let expected = (1..=100_000).map(|i| format!("{},", i)).collect::<String>();
+        let text = Spi::get_one::<String>("SELECT tests.text_builder_numbers(100000)");
+        assert_eq!(text, Some(expected));
+    }
+
+    #[pg_test]
+    fn test_text_builder_as_str() {
+        let mut text = TextBuilder::new();
+        assert!(text.is_empty());
+        text.push_str("hello");
+        write!(text, ", {}", "world").unwrap();
+        assert_eq!(text.as_str(), "hello, world");
+        assert_eq!(text.len(), 12);
+        assert_eq!(text.to_string(), "hello, world");
+    }
+}
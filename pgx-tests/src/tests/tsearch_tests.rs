@@ -0,0 +1,121 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use pgx::*;
+
+/// Lowercases every token, except for the `stopword` option, which it drops
+#[derive(PostgresTextSearchDictionary)]
+struct LowercaseDictionary {
+    stopword: Option<String>,
+}
+
+impl TextSearchDictionary for LowercaseDictionary {
+    fn init(options: Vec<(String, String)>) -> Self {
+        LowercaseDictionary {
+            stopword: options
+                .into_iter()
+                .find(|(name, _)| name == "stopword")
+                .map(|(_, value)| value),
+        }
+    }
+
+    fn lexize(&self, token: &str) -> Option<Vec<String>> {
+        let lexeme = token.to_lowercase();
+        if Some(&lexeme) == self.stopword.as_ref() {
+            Some(vec![])
+        } else {
+            Some(vec![lexeme])
+        }
+    }
+}
+
+/// Splits on commas, into "word"s and "comma"s
+#[derive(PostgresTextSearchParser)]
+struct CommaParser {
+    position: usize,
+}
+
+impl TextSearchParser for CommaParser {
+    const TOKEN_TYPES: &'static [(&'static str, &'static str)] =
+        &[("word", "Word"), ("comma", "Comma")];
+
+    fn start(_text: &str) -> Self {
+        CommaParser { position: 0 }
+    }
+
+    fn next_token(&mut self, text: &str) -> Option<(i32, std::ops::Range<usize>)> {
+        let start = self.position;
+        if start == text.len() {
+            return None;
+        }
+        if text[start..].starts_with(',') {
+            self.position += 1;
+            return Some((2, start..self.position));
+        }
+        self.position = text[start..]
+            .find(',')
+            .map_or(text.len(), |len| start + len);
+        Some((1, start..self.position))
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_text_search_dictionary() {
+        Spi::run(
+            "CREATE TEXT SEARCH DICTIONARY test_lowercase (TEMPLATE = lowercasedictionary, stopword = 'the')",
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<String>>("SELECT ts_lexize('test_lowercase', 'HeLLo')"),
+            Some(vec!["hello".to_string()])
+        );
+        assert_eq!(
+            Spi::get_one::<Vec<String>>("SELECT ts_lexize('test_lowercase', 'The')"),
+            Some(vec![])
+        );
+    }
+
+    #[pg_test]
+    fn test_text_search_parser() {
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT string_agg(tokid || ':' || token, ' ') FROM ts_parse('commaparser', 'a b,c')"
+            ),
+            Some("1:a b 2:, 1:c".to_string())
+        );
+        assert_eq!(
+            Spi::get_one::<String>(
+                "SELECT string_agg(alias, ',') FROM ts_token_type('commaparser')"
+            ),
+            Some("word,comma".to_string())
+        );
+    }
+
+    #[pg_test]
+    fn test_text_search_configuration() {
+        Spi::run("CREATE TEXT SEARCH DICTIONARY test_lowercase (TEMPLATE = lowercasedictionary)");
+        Spi::run("CREATE TEXT SEARCH CONFIGURATION test_comma (PARSER = commaparser)");
+        Spi::run(
+            "ALTER TEXT SEARCH CONFIGURATION test_comma ADD MAPPING FOR word WITH test_lowercase",
+        );
+        assert_eq!(
+            Spi::get_one::<String>("SELECT to_tsvector('test_comma', 'Hello World,Again')::text"),
+            Some("'again':2 'hello world':1".to_string())
+        );
+    }
+}
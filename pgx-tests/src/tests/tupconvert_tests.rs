@@ -0,0 +1,115 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    fn open(name: &str) -> PgRelation {
+        PgRelation::open_with_name_and_share_lock(name).expect("no such relation")
+    }
+
+    #[pg_test]
+    fn test_partition_to_parent() {
+        Spi::run("CREATE TABLE tests.tupconvert_parent (a int, b text) PARTITION BY LIST (a)");
+        Spi::run("CREATE TABLE tests.tupconvert_child (b text, a int)");
+        Spi::run(
+            "ALTER TABLE tests.tupconvert_parent
+                 ATTACH PARTITION tests.tupconvert_child FOR VALUES IN (1)",
+        );
+        let child = open("tests.tupconvert_child");
+        let parent = open("tests.tupconvert_parent");
+        let child_desc = child.tuple_desc();
+        let parent_desc = parent.tuple_desc();
+
+        let row =
+            unsafe { PgHeapTuple::form(child_desc.as_ptr(), &["x".into_datum(), 1.into_datum()]) };
+        let map = PgTupleConversionMap::by_name(&child_desc, &parent_desc);
+        assert!(map.needs_conversion());
+
+        let converted = map.convert(&row);
+        assert_eq!(converted.tupdesc(), parent_desc.as_ptr());
+        assert_eq!(converted.get::<i32>(1), Some(1));
+        assert_eq!(converted.get::<String>(2).as_deref(), Some("x"));
+    }
+
+    #[pg_test]
+    fn test_dropped_attribute() {
+        Spi::run("CREATE TABLE tests.tupconvert_old (a int, gone int, b text)");
+        Spi::run("ALTER TABLE tests.tupconvert_old DROP COLUMN gone");
+        Spi::run("CREATE TABLE tests.tupconvert_new (a int, b text, c int)");
+        Spi::run("ALTER TABLE tests.tupconvert_new DROP COLUMN c");
+        let old = open("tests.tupconvert_old");
+        let new = open("tests.tupconvert_new");
+        let old_desc = old.tuple_desc();
+        let new_desc = new.tuple_desc();
+
+        let row = unsafe {
+            PgHeapTuple::form(old_desc.as_ptr(), &[2.into_datum(), None, "y".into_datum()])
+        };
+        let converted = PgTupleConversionMap::by_name(&old_desc, &new_desc).convert(&row);
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted.get_by_name::<i32>("a"), Some(2));
+        assert_eq!(converted.get::<String>(2).as_deref(), Some("y"));
+        assert_eq!(converted.get::<i32>(3), None);
+    }
+
+    #[pg_test]
+    fn test_no_conversion_needed() {
+        Spi::run("CREATE TABLE tests.tupconvert_one (a int, b text)");
+        Spi::run("CREATE TABLE tests.tupconvert_two (a int, b text)");
+        let one = open("tests.tupconvert_one");
+        let two = open("tests.tupconvert_two");
+        let one_desc = one.tuple_desc();
+        let two_desc = two.tuple_desc();
+
+        let map = PgTupleConversionMap::by_name(&one_desc, &two_desc);
+        assert!(!map.needs_conversion());
+
+        let row = unsafe { PgHeapTuple::form(one_desc.as_ptr(), &[3.into_datum(), None]) };
+        let converted = map.convert(&row);
+        assert_ne!(converted.as_ptr(), row.as_ptr());
+        assert_eq!(converted.get_by_name::<i32>("a"), Some(3));
+        assert_eq!(converted.get_by_name::<String>("b"), None);
+    }
+
+    #[pg_test(error = "could not convert row type")]
+    fn test_mismatched_type() {
+        Spi::run("CREATE TABLE tests.tupconvert_ints (a int, b int)");
+        Spi::run("CREATE TABLE tests.tupconvert_texts (a int, b text)");
+        let ints = open("tests.tupconvert_ints");
+        let texts = open("tests.tupconvert_texts");
+        let ints_desc = ints.tuple_desc();
+        let texts_desc = texts.tuple_desc();
+        PgTupleConversionMap::by_name(&ints_desc, &texts_desc);
+    }
+
+    #[pg_test(error = "tuple has 1 attributes, but tuples with 2 are being converted")]
+    fn test_convert_wrong_tuple() {
+        Spi::run("CREATE TABLE tests.tupconvert_from (a int, b int)");
+        Spi::run("CREATE TABLE tests.tupconvert_to (b int, a int)");
+        Spi::run("CREATE TABLE tests.tupconvert_other (a int)");
+        let from = open("tests.tupconvert_from");
+        let to = open("tests.tupconvert_to");
+        let other = open("tests.tupconvert_other");
+        let from_desc = from.tuple_desc();
+        let to_desc = to.tuple_desc();
+        let other_desc = other.tuple_desc();
+
+        let row = unsafe { PgHeapTuple::form(other_desc.as_ptr(), &[1.into_datum()]) };
+        PgTupleConversionMap::by_name(&from_desc, &to_desc).convert(&row);
+    }
+}
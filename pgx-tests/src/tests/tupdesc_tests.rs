@@ -0,0 +1,111 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_deform() {
+        Spi::run("CREATE TABLE tests.deform_test (a int, b text, c int, d bigint)");
+        let relation = PgRelation::open_with_name_and_share_lock("tests.deform_test").unwrap();
+        let tupdesc = relation.tuple_desc();
+
+        let mut values = vec![
+            1i32.into_datum().unwrap(),
+            "two".into_datum().unwrap(),
+            0,
+            4i64.into_datum().unwrap(),
+        ];
+        let mut nulls = vec![false, false, true, false];
+        let tuple = unsafe {
+            PgBox::from_pg(pg_sys::heap_form_tuple(
+                tupdesc.as_ptr(),
+                values.as_mut_ptr(),
+                nulls.as_mut_ptr(),
+            ))
+        };
+
+        let (values, nulls) = tupdesc.deform(&tuple);
+        assert_eq!(nulls, vec![false, false, true, false]);
+        unsafe {
+            assert_eq!(
+                i32::from_datum(values[0], nulls[0], pg_sys::INT4OID),
+                Some(1)
+            );
+            assert_eq!(
+                <&str>::from_datum(values[1], nulls[1], pg_sys::TEXTOID),
+                Some("two")
+            );
+            assert_eq!(
+                i64::from_datum(values[3], nulls[3], pg_sys::INT8OID),
+                Some(4)
+            );
+        }
+
+        // the buffers are resized to fit
+        let mut values = vec![0; 10];
+        let mut nulls = vec![];
+        tupdesc.deform_into(&tuple, &mut values, &mut nulls);
+        assert_eq!(values.len(), 4);
+        assert_eq!(nulls, vec![false, false, true, false]);
+    }
+
+    #[pg_test]
+    fn test_from_composite_header() {
+        Spi::run("CREATE TABLE tests.composite_header_test (a int, b text)");
+        let relation =
+            PgRelation::open_with_name_and_share_lock("tests.composite_header_test").unwrap();
+        let tupdesc = relation.tuple_desc();
+
+        let mut values = vec![7i32.into_datum().unwrap(), "seven".into_datum().unwrap()];
+        let mut nulls = vec![false, false];
+        let composite = unsafe {
+            let tuple =
+                pg_sys::heap_form_tuple(tupdesc.as_ptr(), values.as_mut_ptr(), nulls.as_mut_ptr());
+            PgTupleDesc::from_composite_header((*tuple).t_data)
+        };
+        assert_eq!(composite.len(), 2);
+        assert_eq!(composite.get_attr::<i32>(0), Some(7));
+        assert_eq!(composite.get_attr::<&str>(1), Some("seven"));
+        assert_eq!(composite.deform_composite().1, vec![false, false]);
+    }
+
+    #[pg_extern]
+    fn composite_attr_count(row: Any, fcinfo: pg_sys::FunctionCallInfo) -> i32 {
+        let tupdesc = unsafe { PgTupleDesc::from_composite_cached(fcinfo, row.datum()) };
+        let first = tupdesc.get_attr::<i32>(0).unwrap_or_default();
+        first * 10 + tupdesc.len() as i32
+    }
+
+    #[pg_test]
+    fn test_from_composite_cached() {
+        Spi::run("CREATE TYPE tests.cached_pair AS (a int, b text)");
+        Spi::run("CREATE TYPE tests.cached_triple AS (a int, b text, c int)");
+        let result = Spi::get_one::<String>(
+            "SELECT string_agg(tests.composite_attr_count(ROW(x, 'y')::tests.cached_pair)::text, ',') \
+             FROM generate_series(1, 3) x",
+        );
+        assert_eq!(result, Some("12,22,32".to_string()));
+
+        // the same call site can see a different type
+        let result = Spi::get_one::<String>(
+            "SELECT string_agg(tests.composite_attr_count(CASE WHEN x = 2 THEN ROW(x, 'y', 0)::tests.cached_triple::record \
+             ELSE ROW(x, 'y')::tests.cached_pair::record END)::text, ',') FROM generate_series(1, 3) x",
+        );
+        assert_eq!(result, Some("12,23,32".to_string()));
+    }
+}
@@ -0,0 +1,113 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_datum_to_text() {
+        unsafe {
+            assert_eq!(
+                datum_to_text(42i32.into_datum().unwrap(), pg_sys::INT4OID),
+                "42"
+            );
+            assert_eq!(
+                datum_to_text(true.into_datum().unwrap(), pg_sys::BOOLOID),
+                "t"
+            );
+            assert_eq!(
+                datum_to_text("a b".into_datum().unwrap(), pg_sys::TEXTOID),
+                "a b"
+            );
+            assert_eq!(
+                datum_to_text(
+                    vec![Some("a b"), None].into_datum().unwrap(),
+                    pg_sys::TEXTARRAYOID
+                ),
+                "{\"a b\",NULL}"
+            );
+        }
+    }
+
+    #[pg_test]
+    fn test_text_to_datum() {
+        let datum = text_to_datum("42", pg_sys::INT4OID, -1);
+        assert_eq!(
+            unsafe { i32::from_datum(datum, false, pg_sys::INT4OID) },
+            Some(42)
+        );
+
+        // numeric(5, 2)
+        let typmod = ((5 << 16) | 2) + pg_sys::VARHDRSZ as i32;
+        let datum = text_to_datum("3.14159", pg_sys::NUMERICOID, typmod);
+        assert_eq!(unsafe { datum_to_text(datum, pg_sys::NUMERICOID) }, "3.14");
+    }
+
+    #[pg_test(error = "invalid input syntax for type boolean: \"maybe\"")]
+    fn test_text_to_datum_invalid() {
+        text_to_datum("maybe", pg_sys::BOOLOID, -1);
+    }
+
+    #[pg_test]
+    fn test_type_io_info_is_the_catalogs() {
+        Spi::run("CREATE DOMAIN tests.positive AS int CHECK (VALUE > 0)");
+        let domain = Spi::get_one::<pg_sys::Oid>("SELECT 'tests.positive'::regtype::oid").unwrap();
+
+        for typoid in [pg_sys::INT4OID, pg_sys::TEXTOID, domain] {
+            let mut output_function = pg_sys::InvalidOid;
+            let mut is_varlena = false;
+            let mut input_function = pg_sys::InvalidOid;
+            let mut typioparam = pg_sys::InvalidOid;
+            unsafe {
+                pg_sys::getTypeOutputInfo(typoid, &mut output_function, &mut is_varlena);
+                pg_sys::getTypeInputInfo(typoid, &mut input_function, &mut typioparam);
+            }
+
+            // twice, to have the second come from the cache
+            for _ in 0..2 {
+                assert_eq!(
+                    type_output_info(typoid),
+                    TypeOutputInfo {
+                        output_function,
+                        is_varlena
+                    }
+                );
+                assert_eq!(
+                    type_input_info(typoid),
+                    TypeInputInfo {
+                        input_function,
+                        typioparam
+                    }
+                );
+            }
+        }
+    }
+
+    #[pg_test]
+    fn test_type_io_after_type_changes() {
+        Spi::run("CREATE TYPE tests.mood AS ENUM ('sad', 'happy')");
+        let mood = Spi::get_one::<pg_sys::Oid>("SELECT 'tests.mood'::regtype::oid").unwrap();
+        let happy = text_to_datum("happy", mood, -1);
+        assert_eq!(unsafe { datum_to_text(happy, mood) }, "happy");
+
+        // which invalidates the type's syscache entry, and so the cached functions
+        Spi::run("ALTER TYPE tests.mood RENAME TO feeling");
+        assert_eq!(unsafe { datum_to_text(happy, mood) }, "happy");
+        let sad = text_to_datum("sad", mood, -1);
+        assert_eq!(unsafe { datum_to_text(sad, mood) }, "sad");
+    }
+}
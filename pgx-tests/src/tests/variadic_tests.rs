@@ -15,6 +15,15 @@ mod test {
     fn func_with_variadic_array_args(_field: &str, values: VariadicArray<&str>) -> String {
         values.get(0).unwrap().unwrap().to_string()
     }
+
+    #[pg_extern]
+    fn func_with_variadic_array_slice(skip: i32, values: VariadicArray<i32>) -> i32 {
+        values
+            .slice(skip as usize..)
+            .iter()
+            .map(|v| v.unwrap_or(0))
+            .sum()
+    }
 }
 
 #[cfg(any(test, feature = "pg_test"))]
@@ -33,4 +42,12 @@ mod tests {
         .expect("didn't get SPI result");
         assert_eq!(result, "a");
     }
+
+    #[pg_test]
+    fn test_func_with_variadic_array_slice() {
+        let result =
+            Spi::get_one::<i32>("SELECT test.func_with_variadic_array_slice(2, 1, 2, 3, 4);")
+                .expect("didn't get SPI result");
+        assert_eq!(result, 7);
+    }
 }
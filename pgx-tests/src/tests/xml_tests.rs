@@ -0,0 +1,71 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use pgx::*;
+
+#[pg_extern]
+fn xml_test_wrap(xml: Xml, tag: &str) -> Xml {
+    Xml(format!("<{}>{}</{}>", tag, xml, tag))
+}
+
+#[pg_extern]
+fn xml_test_len(xml: Xml) -> i32 {
+    xml.len() as i32
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pgx::pg_schema]
+mod tests {
+    #[allow(unused_imports)]
+    use crate as pgx_tests;
+
+    use pgx::*;
+
+    #[test]
+    fn make_idea_happy() {}
+
+    #[pg_test]
+    fn test_xml_from_datum() {
+        let xml = Spi::get_one::<Xml>("SELECT '<a>b</a>'::xml").expect("xml was NULL");
+        assert_eq!(xml, Xml("<a>b</a>".to_string()));
+        assert_eq!(&*xml, "<a>b</a>");
+    }
+
+    #[pg_test]
+    fn test_xml_arguments() {
+        let result = Spi::get_one::<String>("SELECT xml_test_wrap('<a>b</a>', 'c')::text");
+        assert_eq!(result.as_deref(), Some("<c><a>b</a></c>"));
+        let result = Spi::get_one::<i32>("SELECT xml_test_len('<a/>')");
+        assert_eq!(result, Some(4));
+    }
+
+    #[pg_test]
+    fn test_xml_into_datum() {
+        let result = Spi::get_one_with_args::<bool>(
+            "SELECT xpath_exists('/a/b', $1)",
+            vec![(
+                PgBuiltInOids::XMLOID.oid(),
+                Xml("<a><b/></a>".to_string()).into_datum(),
+            )],
+        );
+        assert_eq!(result, Some(true));
+    }
+
+    #[pg_test]
+    fn test_xml_array() {
+        let xmls = Spi::get_one::<Vec<Xml>>("SELECT ARRAY['<a/>', '<b/>']::xml[]")
+            .expect("array was NULL");
+        assert_eq!(xmls, vec![Xml("<a/>".into()), Xml("<b/>".into())]);
+    }
+
+    #[pg_test(error = "invalid XML content")]
+    fn test_xml_malformed() {
+        Xml("<a>".to_string()).into_datum();
+    }
+}
@@ -0,0 +1,80 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Helpers for `#[pg_test]`s of background workers and of what's set up in `_PG_init()`, for an
+//! extension the test Postgres preloads, with `shared_preload_libraries` in the
+//! `postgresql_conf_options()` of its `pg_test` module.
+
+use pgx::*;
+use std::time::{Duration, Instant};
+
+/// The pid of a running background worker of type `bgw_type`, which is its name unless it was
+/// given another with `BackgroundWorkerBuilder::set_type()`
+///
+/// On Postgres 10, every background worker's type is `background worker`.
+pub fn background_worker_pid(bgw_type: &str) -> Option<i32> {
+    // `pg_stat_activity` is otherwise as of the transaction's first look at it
+    unsafe { pg_sys::pgstat_clear_snapshot() };
+    Spi::get_one_with_args::<i32>(
+        "SELECT pid FROM pg_stat_activity WHERE backend_type = $1 ORDER BY backend_start LIMIT 1",
+        vec![(PgBuiltInOids::TEXTOID.oid(), bgw_type.into_datum())],
+    )
+}
+
+/// Waits up to `timeout` for a background worker of type `bgw_type` to start, for its pid
+pub fn wait_for_background_worker(bgw_type: &str, timeout: Duration) -> Option<i32> {
+    let mut pid = None;
+    wait_until(timeout, || {
+        pid = background_worker_pid(bgw_type);
+        pid.is_some()
+    });
+    pid
+}
+
+/// Sends `signal`, such as `libc::SIGHUP`, to the background worker of type `bgw_type`, returning
+/// if it was running to be sent it
+#[cfg(unix)]
+pub fn signal_background_worker(bgw_type: &str, signal: i32) -> bool {
+    match background_worker_pid(bgw_type) {
+        Some(pid) => unsafe { libc::kill(pid, signal) == 0 },
+        None => false,
+    }
+}
+
+/// Checks `condition` until it's true, for up to `timeout`, returning if it became true
+///
+/// For waiting on what happens in other processes, such as a background worker updating shared
+/// memory:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::{sync::atomic::{AtomicI32, Ordering}, time::Duration};
+///
+/// static PROCESSED: PgAtomic<AtomicI32> = PgAtomic::new();
+///
+/// # fn test_worker_processes() {
+/// assert!(pgx_tests::signal_background_worker("my_worker", libc::SIGHUP));
+/// assert!(pgx_tests::wait_until(Duration::from_secs(10), || {
+///     PROCESSED.get().load(Ordering::SeqCst) > 0
+/// }));
+/// # }
+/// ```
+pub fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let started = Instant::now();
+    loop {
+        if condition() {
+            return true;
+        }
+        if started.elapsed() >= timeout {
+            return false;
+        }
+        check_for_interrupts!();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,104 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Conversions between [`Date`], [`Time`], [`Timestamp`], [`TimestampWithTimeZone`], and
+//! `chrono`'s types, with the `chrono` feature.
+//!
+//! Converting from `chrono` fails with a `time::error::ComponentRange` for values outside of the
+//! range of the `time` types these wrap, such as dates before the year -9999
+use crate::{Date, Time, Timestamp, TimestampWithTimeZone};
+use chrono::{Datelike, TimeZone, Timelike};
+use std::convert::TryFrom;
+
+impl From<Date> for chrono::NaiveDate {
+    fn from(date: Date) -> Self {
+        chrono::NaiveDate::from_ymd_opt(date.year(), date.month() as u32, date.day() as u32)
+            .expect("date is out of range for chrono::NaiveDate")
+    }
+}
+
+impl TryFrom<chrono::NaiveDate> for Date {
+    type Error = time::error::ComponentRange;
+
+    fn try_from(date: chrono::NaiveDate) -> Result<Self, Self::Error> {
+        let month = time::Month::try_from(date.month() as u8)?;
+        Ok(Date::new(time::Date::from_calendar_date(
+            date.year(),
+            month,
+            date.day() as u8,
+        )?))
+    }
+}
+
+impl From<Time> for chrono::NaiveTime {
+    fn from(time: Time) -> Self {
+        chrono::NaiveTime::from_hms_nano_opt(
+            time.hour() as u32,
+            time.minute() as u32,
+            time.second() as u32,
+            time.nanosecond(),
+        )
+        .expect("time is out of range for chrono::NaiveTime")
+    }
+}
+
+/// Fails for a leap second
+impl TryFrom<chrono::NaiveTime> for Time {
+    type Error = time::error::ComponentRange;
+
+    fn try_from(time: chrono::NaiveTime) -> Result<Self, Self::Error> {
+        Ok(Time::new(time::Time::from_hms_nano(
+            time.hour() as u8,
+            time.minute() as u8,
+            time.second() as u8,
+            time.nanosecond(),
+        )?))
+    }
+}
+
+impl From<Timestamp> for chrono::NaiveDateTime {
+    fn from(timestamp: Timestamp) -> Self {
+        chrono::NaiveDateTime::new(
+            Date::new(timestamp.date()).into(),
+            Time::new(timestamp.time()).into(),
+        )
+    }
+}
+
+/// Fails for a leap second
+impl TryFrom<chrono::NaiveDateTime> for Timestamp {
+    type Error = time::error::ComponentRange;
+
+    fn try_from(timestamp: chrono::NaiveDateTime) -> Result<Self, Self::Error> {
+        let date = Date::try_from(timestamp.date())?;
+        let time = Time::try_from(timestamp.time())?;
+        Ok(Timestamp::new(time::PrimitiveDateTime::new(*date, *time)))
+    }
+}
+
+impl From<TimestampWithTimeZone> for chrono::DateTime<chrono::Utc> {
+    fn from(timestamp: TimestampWithTimeZone) -> Self {
+        let timestamp = time::OffsetDateTime::from(timestamp);
+        chrono::Utc
+            .timestamp_opt(timestamp.unix_timestamp(), timestamp.nanosecond())
+            .single()
+            .expect("timestamp is out of range for chrono::DateTime")
+    }
+}
+
+/// Converted to UTC
+impl<Tz: TimeZone> TryFrom<chrono::DateTime<Tz>> for TimestampWithTimeZone {
+    type Error = time::error::ComponentRange;
+
+    fn try_from(timestamp: chrono::DateTime<Tz>) -> Result<Self, Self::Error> {
+        let nanos = timestamp.timestamp() as i128 * 1_000_000_000
+            + timestamp.timestamp_subsec_nanos() as i128;
+        Ok(time::OffsetDateTime::from_unix_timestamp_nanos(nanos)?.into())
+    }
+}
@@ -0,0 +1,93 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::deserialize_from_text;
+use std::ops::{Deref, DerefMut};
+use time::format_description::FormatItem;
+
+/// The Julian day of the Postgres epoch, 2000-01-01
+pub(crate) const POSTGRES_EPOCH_JDATE: i32 = 2_451_545;
+
+/// A `date`, which can't be `-infinity` or `infinity`.  Use a
+/// [`MaybeInfinite<Date>`](crate::MaybeInfinite) for dates that may be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date(time::Date);
+
+impl Date {
+    pub fn new(date: time::Date) -> Self {
+        Date(date)
+    }
+
+    /// The date `days` after 2000-01-01, as a `date` `Datum` holds it
+    ///
+    /// ## Panics
+    ///
+    /// If the date is outside of the years `time` supports
+    pub fn from_pg_epoch_days(days: i32) -> Self {
+        Date(
+            days.checked_add(POSTGRES_EPOCH_JDATE)
+                .and_then(|julian_day| time::Date::from_julian_day(julian_day).ok())
+                .expect("date out of range"),
+        )
+    }
+
+    /// The days since 2000-01-01, as a `date` `Datum` holds them
+    pub fn pg_epoch_days(&self) -> i32 {
+        self.to_julian_day() - POSTGRES_EPOCH_JDATE
+    }
+}
+
+impl From<time::Date> for Date {
+    fn from(date: time::Date) -> Self {
+        Date(date)
+    }
+}
+
+impl From<Date> for time::Date {
+    fn from(date: Date) -> Self {
+        date.0
+    }
+}
+
+impl Deref for Date {
+    type Target = time::Date;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Date {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl serde::Serialize for Date {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(
+            &self.format(&DATE_FORMAT).map_err(|e| {
+                serde::ser::Error::custom(format!("Date formatting problem: {:?}", e))
+            })?,
+        )
+    }
+}
+
+deserialize_from_text!(Date, "a date", |text| time::Date::parse(text, &DATE_FORMAT)
+    .ok()
+    .map(Date));
+
+static DATE_FORMAT: &[FormatItem<'static>] =
+    time::macros::format_description!("[year]-[month]-[day]");
@@ -0,0 +1,142 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{Date, Interval, Timestamp, TimestampWithTimeZone};
+use serde::de::IntoDeserializer;
+use std::ops::{Add, Sub};
+
+/// A `date`, `timestamp`, or `timestamp with time zone` that may be `-infinity` or `infinity`,
+/// which [`Date`], [`Timestamp`], and [`TimestampWithTimeZone`] can't represent
+///
+/// As in Postgres, `-infinity` sorts before every finite value and `infinity` after, adding or
+/// subtracting an [`Interval`] leaves them infinite, and they serialize as the strings
+/// `"-infinity"` and `"infinity"`.
+///
+/// ```rust
+/// use pgx_types::{Date, MaybeInfinite};
+///
+/// let dates: Vec<MaybeInfinite<Date>> =
+///     serde_json::from_str(r#"["infinity", "2022-04-01", "-infinity"]"#).unwrap();
+/// assert_eq!(dates.iter().max(), Some(&MaybeInfinite::Infinity));
+/// assert_eq!(dates[1].finite().map(|date| date.day()), Some(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MaybeInfinite<T> {
+    NegativeInfinity,
+    Finite(T),
+    Infinity,
+}
+
+impl<T> MaybeInfinite<T> {
+    /// The value, if it's neither `-infinity` nor `infinity`
+    pub fn finite(self) -> Option<T> {
+        match self {
+            MaybeInfinite::Finite(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Is this neither `-infinity` nor `infinity`?
+    pub fn is_finite(&self) -> bool {
+        matches!(self, MaybeInfinite::Finite(_))
+    }
+
+    /// Is this `-infinity` or `infinity`?
+    pub fn is_infinite(&self) -> bool {
+        !self.is_finite()
+    }
+
+    /// Apply `f` to the value, if it's finite
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> MaybeInfinite<U> {
+        match self {
+            MaybeInfinite::NegativeInfinity => MaybeInfinite::NegativeInfinity,
+            MaybeInfinite::Finite(value) => MaybeInfinite::Finite(f(value)),
+            MaybeInfinite::Infinity => MaybeInfinite::Infinity,
+        }
+    }
+}
+
+impl<T: serde::Serialize> serde::Serialize for MaybeInfinite<T> {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            MaybeInfinite::NegativeInfinity => serializer.serialize_str("-infinity"),
+            MaybeInfinite::Finite(value) => value.serialize(serializer),
+            MaybeInfinite::Infinity => serializer.serialize_str("infinity"),
+        }
+    }
+}
+
+/// From the strings `"-infinity"` and `"infinity"`, or the text a finite value deserializes from
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for MaybeInfinite<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        match text.as_str() {
+            "-infinity" => Ok(MaybeInfinite::NegativeInfinity),
+            "infinity" => Ok(MaybeInfinite::Infinity),
+            _ => T::deserialize(text.into_deserializer()).map(MaybeInfinite::Finite),
+        }
+    }
+}
+
+impl<T> From<T> for MaybeInfinite<T> {
+    fn from(value: T) -> Self {
+        MaybeInfinite::Finite(value)
+    }
+}
+
+macro_rules! maybe_infinite {
+    ($ty:ty, $raw:ty, $from_raw:expr) => {
+        /// From the raw value of the type, such as a `Datum`'s, where the type's minimum is
+        /// `-infinity` and its maximum is `infinity`
+        impl From<$raw> for MaybeInfinite<$ty> {
+            fn from(raw: $raw) -> Self {
+                match raw {
+                    <$raw>::MIN => MaybeInfinite::NegativeInfinity,
+                    <$raw>::MAX => MaybeInfinite::Infinity,
+                    raw => MaybeInfinite::Finite($from_raw(raw)),
+                }
+            }
+        }
+    };
+}
+
+macro_rules! maybe_infinite_arithmetic {
+    ($ty:ty) => {
+        impl Add<Interval> for MaybeInfinite<$ty> {
+            type Output = MaybeInfinite<$ty>;
+
+            fn add(self, rhs: Interval) -> Self::Output {
+                self.map(|value| value + rhs)
+            }
+        }
+
+        impl Sub<Interval> for MaybeInfinite<$ty> {
+            type Output = MaybeInfinite<$ty>;
+
+            fn sub(self, rhs: Interval) -> Self::Output {
+                self.map(|value| value - rhs)
+            }
+        }
+    };
+}
+
+maybe_infinite!(Date, i32, Date::from_pg_epoch_days);
+maybe_infinite!(Timestamp, i64, Timestamp::from);
+maybe_infinite!(TimestampWithTimeZone, i64, TimestampWithTimeZone::from);
+maybe_infinite_arithmetic!(Timestamp);
+maybe_infinite_arithmetic!(TimestampWithTimeZone);
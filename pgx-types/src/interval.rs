@@ -0,0 +1,117 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use std::ops::{Add, Neg, Sub};
+
+/// An `interval`, which keeps its months, days, and microseconds apart, as the length of a month
+/// or a day depends on when it's added to, and for a day, the time zone
+///
+/// It's added to and subtracted from a [`Timestamp`](crate::Timestamp) or
+/// [`TimestampWithTimeZone`](crate::TimestampWithTimeZone) with `+` and `-`, as Postgres would
+#[derive(Debug, Clone, Copy)]
+pub struct Interval {
+    months: i32,
+    days: i32,
+    micros: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Self {
+        Interval {
+            months,
+            days,
+            micros,
+        }
+    }
+
+    pub fn from_months(months: i32) -> Self {
+        Interval::new(months, 0, 0)
+    }
+
+    pub fn from_days(days: i32) -> Self {
+        Interval::new(0, days, 0)
+    }
+
+    pub fn from_micros(micros: i64) -> Self {
+        Interval::new(0, 0, micros)
+    }
+
+    pub fn months(&self) -> i32 {
+        self.months
+    }
+
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    pub fn micros(&self) -> i64 {
+        self.micros
+    }
+}
+
+/// As `interval + interval`, field by field
+///
+/// ## Panics
+///
+/// If a field overflows, where Postgres raises "interval out of range"
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Interval::new(
+            self.months
+                .checked_add(rhs.months)
+                .expect("interval out of range"),
+            self.days
+                .checked_add(rhs.days)
+                .expect("interval out of range"),
+            self.micros
+                .checked_add(rhs.micros)
+                .expect("interval out of range"),
+        )
+    }
+}
+
+/// As `interval - interval`, field by field
+///
+/// ## Panics
+///
+/// If a field overflows, where Postgres raises "interval out of range"
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Interval::new(
+            self.months
+                .checked_sub(rhs.months)
+                .expect("interval out of range"),
+            self.days
+                .checked_sub(rhs.days)
+                .expect("interval out of range"),
+            self.micros
+                .checked_sub(rhs.micros)
+                .expect("interval out of range"),
+        )
+    }
+}
+
+/// ## Panics
+///
+/// If a field is its type's minimum, where Postgres raises "interval out of range"
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Self::Output {
+        Interval::new(
+            self.months.checked_neg().expect("interval out of range"),
+            self.days.checked_neg().expect("interval out of range"),
+            self.micros.checked_neg().expect("interval out of range"),
+        )
+    }
+}
@@ -0,0 +1,89 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! `pgx`'s [`Date`], [`Time`], [`Timestamp`], [`TimestampWithTimeZone`], [`Interval`], [`Uuid`],
+//! and [`MaybeInfinite`] types, which don't need a Postgres server, so that a client tool can
+//! share type definitions with a `pgx` extension, such as the structs of a
+//! `#[derive(PostgresType)]`.
+//!
+//! `pgx` reexports them, and converts them to and from `Datum`s.  They serialize with `serde` as
+//! the same text inside and outside of the server.
+//!
+//! Adding an [`Interval`] to a [`TimestampWithTimeZone`] depends on the session's time zone, so
+//! outside of a server it only works for intervals without months or days.  The rest of the
+//! arithmetic is the same everywhere
+#[cfg(feature = "chrono")]
+mod chrono;
+mod date;
+mod infinite;
+mod interval;
+mod server;
+mod time;
+mod time_stamp;
+mod time_stamp_with_timezone;
+mod uuid;
+
+pub use crate::date::*;
+pub use crate::infinite::*;
+pub use crate::interval::*;
+pub use crate::server::*;
+pub use crate::time::*;
+pub use crate::time_stamp::*;
+pub use crate::time_stamp_with_timezone::*;
+pub use crate::uuid::*;
+
+pub(crate) const USECS_PER_SEC: i64 = 1_000_000;
+pub(crate) const USECS_PER_DAY: i64 = 86_400 * USECS_PER_SEC;
+
+/// Deserialize a type from the text `$parse` parses
+macro_rules! deserialize_from_text {
+    ($ty:ty, $expecting:literal, $parse:expr) => {
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct TextVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for TextVisitor {
+                    type Value = $ty;
+
+                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        formatter.write_str($expecting)
+                    }
+
+                    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                        let parse: fn(&str) -> Option<$ty> = $parse;
+                        parse(v)
+                            .ok_or_else(|| E::custom(format!("invalid {}: \"{}\"", $expecting, v)))
+                    }
+                }
+
+                deserializer.deserialize_str(TextVisitor)
+            }
+        }
+    };
+}
+pub(crate) use deserialize_from_text;
+
+/// Parse `text` that may end in a fraction of a second, such as `"12:00:00.25"`, into what
+/// `parse` makes of the text before the `.` and the fraction in nanoseconds
+pub(crate) fn parse_with_fraction<T>(
+    text: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Option<(T, u32)> {
+    match text.split_once('.') {
+        None => Some((parse(text)?, 0)),
+        Some((whole, digits)) => {
+            if digits.is_empty() || digits.len() > 9 || !digits.bytes().all(|b| b.is_ascii_digit())
+            {
+                return None;
+            }
+            let nanos = digits.parse::<u32>().ok()? * 10u32.pow(9 - digits.len() as u32);
+            Some((parse(whole)?, nanos))
+        }
+    }
+}
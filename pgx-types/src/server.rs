@@ -0,0 +1,40 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{Interval, TimestampWithTimeZone};
+use once_cell::sync::OnceCell;
+
+/// The arithmetic that depends on the session's `timezone`, which only a Postgres server can do.
+/// `pgx` sets its implementation with [`set_server()`] when an extension is loaded
+pub trait Server: Sync {
+    /// As `timestamptz + interval`
+    fn timestamptz_pl_interval(
+        &self,
+        timestamp: TimestampWithTimeZone,
+        interval: Interval,
+    ) -> TimestampWithTimeZone;
+
+    /// As `timestamptz - interval`
+    fn timestamptz_mi_interval(
+        &self,
+        timestamp: TimestampWithTimeZone,
+        interval: Interval,
+    ) -> TimestampWithTimeZone;
+}
+
+static SERVER: OnceCell<&'static dyn Server> = OnceCell::new();
+
+/// Do the arithmetic that needs a server with `server`.  Only the first call has an effect
+pub fn set_server(server: &'static dyn Server) {
+    let _ = SERVER.set(server);
+}
+
+pub(crate) fn server() -> Option<&'static dyn Server> {
+    SERVER.get().copied()
+}
@@ -0,0 +1,92 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{deserialize_from_text, parse_with_fraction};
+use std::ops::{Deref, DerefMut};
+use time::format_description::FormatItem;
+
+#[derive(Debug)]
+pub struct Time(time::Time);
+
+impl Time {
+    pub fn new(time: time::Time) -> Self {
+        Time(time)
+    }
+}
+
+impl From<time::Time> for Time {
+    fn from(time: time::Time) -> Self {
+        Time(time)
+    }
+}
+
+impl From<Time> for time::Time {
+    fn from(time: Time) -> Self {
+        time.0
+    }
+}
+
+impl Deref for Time {
+    type Target = time::Time;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Time {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl serde::Serialize for Time {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.millisecond() > 0 {
+            serializer.serialize_str(
+                &self
+                    .format(
+                        &time::format_description::parse(&format!(
+                            "[hour]:[minute]:[second].{:03}",
+                            self.millisecond()
+                        ))
+                        .map_err(|e| {
+                            serde::ser::Error::custom(format!(
+                                "Time invalid format problem: {:?}",
+                                e
+                            ))
+                        })?,
+                    )
+                    .map_err(|e| {
+                        serde::ser::Error::custom(format!("Time formatting problem: {:?}", e))
+                    })?,
+            )
+        } else {
+            serializer.serialize_str(&self.format(&DEFAULT_TIME_FORMAT).map_err(|e| {
+                serde::ser::Error::custom(format!("Time formatting problem: {:?}", e))
+            })?)
+        }
+    }
+}
+
+deserialize_from_text!(Time, "a time", |text| {
+    let (time, nanos) = parse_with_fraction(text, |whole| {
+        time::Time::parse(whole, &DEFAULT_TIME_FORMAT).ok()
+    })?;
+    time.replace_nanosecond(nanos).ok().map(Time)
+});
+
+static DEFAULT_TIME_FORMAT: &[FormatItem<'static>] =
+    time::macros::format_description!("[hour]:[minute]:[second]");
@@ -0,0 +1,251 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{deserialize_from_text, parse_with_fraction, Interval, USECS_PER_DAY};
+use std::convert::TryFrom;
+use std::ops::{Add, Deref, DerefMut, Sub};
+use time::{format_description::FormatItem, PrimitiveDateTime};
+
+/// The Postgres epoch, 2000-01-01 00:00:00, which `timestamp` `Datum`s count microseconds from
+pub(crate) const POSTGRES_EPOCH: PrimitiveDateTime = time::macros::datetime!(2000-01-01 0:00);
+
+/// A `timestamp`, which can't be `-infinity` or `infinity`.  Use a
+/// [`MaybeInfinite<Timestamp>`](crate::MaybeInfinite) for timestamps that may be
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(time::PrimitiveDateTime);
+
+/// From the microseconds since 2000-01-01 00:00:00 that a `timestamp` `Datum` holds
+///
+/// ## Panics
+///
+/// If the timestamp is infinite, or outside of the years `time` supports
+impl From<i64> for Timestamp {
+    fn from(micros: i64) -> Self {
+        if micros == i64::MIN || micros == i64::MAX {
+            panic!(
+                "timestamp is infinite, which can only be converted to a `MaybeInfinite<Timestamp>`"
+            )
+        }
+        Timestamp(from_pg_epoch_micros(micros))
+    }
+}
+
+impl Timestamp {
+    pub fn new(timestamp: time::PrimitiveDateTime) -> Self {
+        Timestamp(timestamp)
+    }
+}
+
+/// As `timestamp + interval`
+impl Add<Interval> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, rhs: Interval) -> Self::Output {
+        Timestamp(add_interval(self.0, rhs))
+    }
+}
+
+/// As `timestamp - interval`
+impl Sub<Interval> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, rhs: Interval) -> Self::Output {
+        Timestamp(add_interval(self.0, -rhs))
+    }
+}
+
+/// As `timestamp - timestamp`
+impl Sub for Timestamp {
+    type Output = Interval;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        difference(self.0, rhs.0)
+    }
+}
+
+impl From<time::PrimitiveDateTime> for Timestamp {
+    fn from(timestamp: time::PrimitiveDateTime) -> Self {
+        Timestamp(timestamp)
+    }
+}
+
+impl From<Timestamp> for time::PrimitiveDateTime {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl Deref for Timestamp {
+    type Target = time::PrimitiveDateTime;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl DerefMut for Timestamp {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl serde::Serialize for Timestamp {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.millisecond() > 0 {
+            serializer.serialize_str(
+                &self
+                    .format(
+                        &time::format_description::parse(&format!(
+                            "[year]-[month]-[day]T[hour]:[minute]:[second].{:03}-00",
+                            self.millisecond()
+                        ))
+                        .map_err(|e| {
+                            serde::ser::Error::custom(format!(
+                                "Timestamp invalid format problem: {:?}",
+                                e
+                            ))
+                        })?,
+                    )
+                    .map_err(|e| {
+                        serde::ser::Error::custom(format!("Timestamp formatting problem: {:?}", e))
+                    })?,
+            )
+        } else {
+            serializer.serialize_str(&self.format(&DEFAULT_TIMESTAMP_FORMAT).map_err(|e| {
+                serde::ser::Error::custom(format!("Timestamp formatting problem: {:?}", e))
+            })?)
+        }
+    }
+}
+
+deserialize_from_text!(Timestamp, "a timestamp", |text| parse_timestamp(text)
+    .map(Timestamp));
+
+static DEFAULT_TIMESTAMP_FORMAT: &[FormatItem<'static>] =
+    time::macros::format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]-00");
+
+static TIMESTAMP_FORMAT_WITHOUT_OFFSET: &[FormatItem<'static>] =
+    time::macros::format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
+
+/// The date and time of the text a [`Timestamp`] or
+/// [`TimestampWithTimeZone`](crate::TimestampWithTimeZone) serializes as
+pub(crate) fn parse_timestamp(text: &str) -> Option<PrimitiveDateTime> {
+    let (timestamp, nanos) = parse_with_fraction(text.strip_suffix("-00")?, |whole| {
+        PrimitiveDateTime::parse(whole, &TIMESTAMP_FORMAT_WITHOUT_OFFSET).ok()
+    })?;
+    timestamp.replace_nanosecond(nanos).ok()
+}
+
+pub(crate) fn from_pg_epoch_micros(micros: i64) -> PrimitiveDateTime {
+    POSTGRES_EPOCH
+        .checked_add(time::Duration::microseconds(micros))
+        .expect("timestamp out of range")
+}
+
+/// `timestamp` plus the months of `interval`, keeping the day of the month unless the month is
+/// shorter, then its days, then its microseconds, as Postgres adds them
+pub(crate) fn add_interval(timestamp: PrimitiveDateTime, interval: Interval) -> PrimitiveDateTime {
+    let mut timestamp = timestamp;
+    if interval.months() != 0 {
+        let months = timestamp.year() as i64 * 12
+            + (timestamp.month() as i64 - 1)
+            + interval.months() as i64;
+        let year = i32::try_from(months.div_euclid(12)).expect("timestamp out of range");
+        let month = time::Month::try_from(months.rem_euclid(12) as u8 + 1)
+            .expect("the month is from 1 to 12");
+        let day = timestamp
+            .day()
+            .min(time::util::days_in_year_month(year, month));
+        let date =
+            time::Date::from_calendar_date(year, month, day).expect("timestamp out of range");
+        timestamp = timestamp.replace_date(date);
+    }
+    timestamp
+        .checked_add(time::Duration::days(interval.days() as i64))
+        .and_then(|timestamp| {
+            timestamp.checked_add(time::Duration::microseconds(interval.micros()))
+        })
+        .expect("timestamp out of range")
+}
+
+/// `left - right`, with whole days of it as days, as Postgres subtracts timestamps
+pub(crate) fn difference(left: PrimitiveDateTime, right: PrimitiveDateTime) -> Interval {
+    let micros = i64::try_from((left - right).whole_microseconds()).expect("interval out of range");
+    Interval::new(0, (micros / USECS_PER_DAY) as i32, micros % USECS_PER_DAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamp;
+    use crate::Interval;
+    use time::macros::datetime;
+
+    #[test]
+    fn add_months_keeps_the_day_unless_the_month_is_shorter() {
+        let timestamp = Timestamp::new(datetime!(2022-01-31 12:00));
+        assert_eq!(
+            *(timestamp + Interval::from_months(1)),
+            datetime!(2022-02-28 12:00)
+        );
+        assert_eq!(
+            *(timestamp - Interval::from_months(13)),
+            datetime!(2020-12-31 12:00)
+        );
+        assert_eq!(
+            *(timestamp + Interval::new(1, 1, 1_000_000)),
+            datetime!(2022-03-01 12:00:01)
+        );
+    }
+
+    #[test]
+    fn difference_has_whole_days() {
+        let later = Timestamp::new(datetime!(2022-03-02 06:00));
+        let earlier = Timestamp::new(datetime!(2022-02-28 12:00));
+        let interval = later - earlier;
+        assert_eq!(
+            (interval.months(), interval.days(), interval.micros()),
+            (0, 1, 18 * 3_600_000_000)
+        );
+        let interval = earlier - later;
+        assert_eq!(
+            (interval.months(), interval.days(), interval.micros()),
+            (0, -1, -18 * 3_600_000_000)
+        );
+    }
+
+    #[test]
+    fn from_datum_micros() {
+        assert_eq!(*Timestamp::from(0), datetime!(2000-01-01 0:00));
+        assert_eq!(*Timestamp::from(-1), datetime!(1999-12-31 23:59:59.999999));
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        for timestamp in [
+            datetime!(2022-06-01 12:30),
+            datetime!(2022-06-01 12:30:00.005),
+            datetime!(2022-06-01 12:30:00.25),
+        ] {
+            let timestamp = Timestamp::new(timestamp);
+            let json = serde_json::to_string(&timestamp).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Timestamp>(&json).unwrap(),
+                timestamp,
+                "{}",
+                json
+            );
+        }
+        assert!(serde_json::from_str::<Timestamp>(r#""2022-06-01 12:30""#).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::time_stamp::{add_interval, difference, from_pg_epoch_micros, parse_timestamp};
+use crate::{deserialize_from_text, server, Interval};
+use std::ops::{Add, Deref, DerefMut, Sub};
+use time::{format_description::FormatItem, UtcOffset};
+
+/// A `timestamp with time zone`, which can't be `-infinity` or `infinity`.  Use a
+/// [`MaybeInfinite<TimestampWithTimeZone>`](crate::MaybeInfinite) for timestamps that may be
+#[derive(Debug, Copy, Clone)]
+pub struct TimestampWithTimeZone(time::OffsetDateTime);
+
+/// From the microseconds since 2000-01-01 00:00:00 UTC that a `timestamp with time zone` `Datum`
+/// holds
+///
+/// ## Panics
+///
+/// If the timestamp is infinite, or outside of the years `time` supports
+impl From<i64> for TimestampWithTimeZone {
+    fn from(micros: i64) -> Self {
+        if micros == i64::MIN || micros == i64::MAX {
+            panic!("timestamp with time zone is infinite, which can only be converted to a `MaybeInfinite<TimestampWithTimeZone>`")
+        }
+        TimestampWithTimeZone(from_pg_epoch_micros(micros).assume_utc())
+    }
+}
+
+impl TimestampWithTimeZone {
+    /// This shifts the provided `time` back to UTC
+    pub fn new(time: time::PrimitiveDateTime, at_tz_offset: time::UtcOffset) -> Self {
+        TimestampWithTimeZone(
+            time.assume_utc()
+                .to_offset(
+                    UtcOffset::from_whole_seconds(-at_tz_offset.whole_seconds())
+                        .expect("Unexpected error in `UtcOffset::from_whole_seconds` during `TimestampWithTimeZone::new`")
+                ),
+        )
+    }
+
+    /// The timestamp `micros` microseconds after the Unix epoch, 1970-01-01 00:00:00 UTC
+    pub fn from_unix_epoch_micros(micros: i64) -> Self {
+        time::OffsetDateTime::from_unix_timestamp_nanos(micros as i128 * 1_000)
+            .expect("timestamp out of range")
+            .into()
+    }
+
+    /// Microseconds since the Unix epoch, 1970-01-01 00:00:00 UTC
+    pub fn unix_epoch_micros(&self) -> i64 {
+        (time::OffsetDateTime::from(*self).unix_timestamp_nanos() / 1_000) as i64
+    }
+
+    /// The date and time in UTC
+    fn utc(&self) -> time::PrimitiveDateTime {
+        time::PrimitiveDateTime::new(self.date(), self.time())
+    }
+}
+
+/// As `timestamptz + interval`, which adds months and days in the session's time zone.  Outside
+/// of a Postgres server, that's only an interval without months or days
+impl Add<Interval> for TimestampWithTimeZone {
+    type Output = TimestampWithTimeZone;
+
+    fn add(self, rhs: Interval) -> Self::Output {
+        match server() {
+            Some(server) => server.timestamptz_pl_interval(self, rhs),
+            None => TimestampWithTimeZone(add_interval(self.utc(), in_utc(rhs)).assume_utc()),
+        }
+    }
+}
+
+/// As `timestamptz - interval`, which subtracts months and days in the session's time zone.
+/// Outside of a Postgres server, that's only an interval without months or days
+impl Sub<Interval> for TimestampWithTimeZone {
+    type Output = TimestampWithTimeZone;
+
+    fn sub(self, rhs: Interval) -> Self::Output {
+        match server() {
+            Some(server) => server.timestamptz_mi_interval(self, rhs),
+            None => TimestampWithTimeZone(add_interval(self.utc(), -in_utc(rhs)).assume_utc()),
+        }
+    }
+}
+
+/// As `timestamptz - timestamptz`
+impl Sub for TimestampWithTimeZone {
+    type Output = Interval;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        difference(self.utc(), rhs.utc())
+    }
+}
+
+/// `interval`, if it can be added without knowing the session's time zone
+fn in_utc(interval: Interval) -> Interval {
+    if interval.months() != 0 || interval.days() != 0 {
+        panic!("adding months or days to a timestamp with time zone needs the session's time zone, from a Postgres server")
+    }
+    interval
+}
+
+/// Converted to UTC
+impl From<time::OffsetDateTime> for TimestampWithTimeZone {
+    fn from(timestamp: time::OffsetDateTime) -> Self {
+        TimestampWithTimeZone(timestamp.to_offset(UtcOffset::UTC))
+    }
+}
+
+/// In UTC
+impl From<TimestampWithTimeZone> for time::OffsetDateTime {
+    fn from(timestamp: TimestampWithTimeZone) -> Self {
+        // the date and time are UTC's, whatever the offset
+        timestamp.0.replace_offset(UtcOffset::UTC)
+    }
+}
+
+impl Deref for TimestampWithTimeZone {
+    type Target = time::OffsetDateTime;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl DerefMut for TimestampWithTimeZone {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl serde::Serialize for TimestampWithTimeZone {
+    fn serialize<S>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.millisecond() > 0 {
+            serializer.serialize_str(
+                &self
+                    .format(
+                        &time::format_description::parse(&format!(
+                            "[year]-[month]-[day]T[hour]:[minute]:[second].{:03}-00",
+                            self.millisecond()
+                        ))
+                        .map_err(|e| {
+                            serde::ser::Error::custom(format!(
+                                "TimeStampWithTimeZone invalid format problem: {:?}",
+                                e
+                            ))
+                        })?,
+                    )
+                    .map_err(|e| {
+                        serde::ser::Error::custom(format!(
+                            "TimeStampWithTimeZone formatting problem: {:?}",
+                            e
+                        ))
+                    })?,
+            )
+        } else {
+            serializer.serialize_str(
+                &self
+                    .format(&DEFAULT_TIMESTAMP_WITH_TIMEZONE_FORMAT)
+                    .map_err(|e| {
+                        serde::ser::Error::custom(format!(
+                            "TimeStampWithTimeZone formatting problem: {:?}",
+                            e
+                        ))
+                    })?,
+            )
+        }
+    }
+}
+
+deserialize_from_text!(
+    TimestampWithTimeZone,
+    "a timestamp with time zone",
+    |text| parse_timestamp(text).map(|timestamp| TimestampWithTimeZone(timestamp.assume_utc()))
+);
+
+static DEFAULT_TIMESTAMP_WITH_TIMEZONE_FORMAT: &[FormatItem<'static>] =
+    time::macros::format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]-00");
+
+#[cfg(test)]
+mod tests {
+    use super::TimestampWithTimeZone;
+    use crate::Interval;
+    use time::macros::datetime;
+
+    #[test]
+    fn unix_epoch() {
+        let timestamp = TimestampWithTimeZone::from_unix_epoch_micros(1_500_000);
+        assert_eq!(
+            time::OffsetDateTime::from(timestamp),
+            datetime!(1970-01-01 0:00:01.5 UTC)
+        );
+        assert_eq!(timestamp.unix_epoch_micros(), 1_500_000);
+        assert_eq!(
+            TimestampWithTimeZone::from(0).unix_epoch_micros(),
+            946_684_800_000_000
+        );
+    }
+
+    #[test]
+    fn add_micros_without_a_server() {
+        let timestamp = TimestampWithTimeZone::from(datetime!(2022-03-12 12:00 -7));
+        let later = timestamp + Interval::from_micros(3_600_000_000);
+        assert_eq!(later.hour(), 20);
+        assert_eq!((later - timestamp).micros(), 3_600_000_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "needs the session's time zone")]
+    fn add_days_without_a_server() {
+        let _ =
+            TimestampWithTimeZone::from(datetime!(2022-03-12 12:00 UTC)) + Interval::from_days(1);
+    }
+}
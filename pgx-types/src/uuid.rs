@@ -0,0 +1,165 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::deserialize_from_text;
+use core::fmt::Write;
+use std::ops::{Deref, DerefMut};
+
+pub(crate) const UUID_BYTES_LEN: usize = 16;
+pub type UuidBytes = [u8; UUID_BYTES_LEN];
+
+/// A Universally Unique Identifier (UUID).
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, Debug)]
+#[repr(transparent)]
+pub struct Uuid(UuidBytes);
+
+enum UuidFormatCase {
+    Lowercase,
+    Uppercase,
+}
+
+impl Uuid {
+    pub fn from_bytes(b: UuidBytes) -> Self {
+        Uuid(b)
+    }
+
+    pub const fn as_bytes(&self) -> &UuidBytes {
+        &self.0
+    }
+
+    pub fn from_slice(b: &[u8]) -> Result<Uuid, String> {
+        let len = b.len();
+
+        if len != UUID_BYTES_LEN {
+            Err(format!(
+                "Expected UUID to be {} bytes, got {}",
+                UUID_BYTES_LEN, len
+            ))?;
+        }
+
+        let mut bytes = [0; UUID_BYTES_LEN];
+        bytes.copy_from_slice(b);
+        Ok(Uuid::from_bytes(bytes))
+    }
+
+    /// The UUID of 32 hexadecimal digits, which may have a hyphen after any group of four, as
+    /// Postgres accepts them
+    fn parse(text: &str) -> Option<Uuid> {
+        if text.starts_with('-') || text.ends_with('-') {
+            return None;
+        }
+
+        let mut bytes = [0u8; UUID_BYTES_LEN];
+        let mut count = 0;
+        let mut since_hyphen = 0;
+        for c in text.chars() {
+            if c == '-' {
+                if since_hyphen == 0 || since_hyphen % 4 != 0 {
+                    return None;
+                }
+                since_hyphen = 0;
+                continue;
+            }
+            let nibble = c.to_digit(16)? as u8;
+            if count == UUID_BYTES_LEN * 2 {
+                return None;
+            }
+            bytes[count / 2] |= if count % 2 == 0 { nibble << 4 } else { nibble };
+            count += 1;
+            since_hyphen += 1;
+        }
+        if count == UUID_BYTES_LEN * 2 {
+            Some(Uuid(bytes))
+        } else {
+            None
+        }
+    }
+
+    fn format(&self, f: &mut std::fmt::Formatter<'_>, case: UuidFormatCase) -> std::fmt::Result {
+        let hyphenated = f.sign_minus();
+        for (i, b) in self.0.iter().enumerate() {
+            if hyphenated && (i == 4 || i == 6 || i == 8 || i == 10) {
+                f.write_char('-')?;
+            }
+            match case {
+                UuidFormatCase::Lowercase => write!(f, "{:02x}", b)?,
+                UuidFormatCase::Uppercase => write!(f, "{:02X}", b)?,
+            };
+        }
+        Ok(())
+    }
+}
+
+impl Deref for Uuid {
+    type Target = UuidBytes;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Uuid {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl std::fmt::Display for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:-x}", self)
+    }
+}
+
+impl std::fmt::LowerHex for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        self.format(f, UuidFormatCase::Lowercase)
+    }
+}
+
+impl std::fmt::UpperHex for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        self.format(f, UuidFormatCase::Uppercase)
+    }
+}
+
+/// As the hyphenated, lowercase text Postgres outputs
+impl serde::Serialize for Uuid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+deserialize_from_text!(Uuid, "a uuid", Uuid::parse);
+
+#[cfg(test)]
+mod tests {
+    use super::Uuid;
+
+    #[test]
+    fn serde_round_trip() {
+        let json = r#""a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11""#;
+        let uuid = serde_json::from_str::<Uuid>(json).unwrap();
+        assert_eq!(uuid.as_bytes()[0], 0xa0);
+        assert_eq!(serde_json::to_string(&uuid).unwrap(), json);
+        assert_eq!(
+            serde_json::from_str::<Uuid>(r#""A0EEBC999C0B4EF8BB6D6BB9BD380A11""#).unwrap(),
+            uuid
+        );
+        for invalid in [
+            r#""a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a1""#,
+            r#""a0eebc99--9c0b-4ef8-bb6d-6bb9bd380a11""#,
+            r#""a0eeb-c99-9c0b-4ef8-bb6d-6bb9bd380a11""#,
+        ] {
+            assert!(
+                serde_json::from_str::<Uuid>(invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+}
@@ -24,6 +24,7 @@ use syn::{GenericArgument, ItemFn, PathArguments, ReturnType, Type, TypeParamBou
 pub mod operator_common;
 pub mod pg_config;
 mod pgx_pg_sys_stub;
+pub mod sanitizer;
 pub mod sql_entity_graph;
 pub use pgx_pg_sys_stub::PgxPgSysStub;
 
@@ -38,7 +39,7 @@ pub mod __reexports {
     }
 }
 
-pub const SUPPORTED_MAJOR_VERSIONS: &[u16] = &[10, 11, 12, 13, 14];
+pub const SUPPORTED_MAJOR_VERSIONS: &[u16] = &[10, 11, 12, 13, 14, 15];
 pub static BASE_POSTGRES_PORT_NO: u16 = 28800;
 pub static BASE_POSTGRES_TESTING_PORT_NO: u16 = 32200;
 
@@ -70,6 +71,98 @@ pub fn get_target_dir() -> eyre::Result<PathBuf> {
     }
 }
 
+/// The target the extension is built for, when `PGX_BUILD_FLAGS` or `CARGO_BUILD_TARGET` name one
+/// other than the host
+pub fn build_target() -> Option<String> {
+    let flags = std::env::var("PGX_BUILD_FLAGS").unwrap_or_default();
+    target_from_build_flags(&flags)
+        .map(String::from)
+        .or_else(|| std::env::var("CARGO_BUILD_TARGET").ok())
+}
+
+fn target_from_build_flags(flags: &str) -> Option<&str> {
+    let mut flags = flags.split_ascii_whitespace();
+    while let Some(flag) = flags.next() {
+        if flag == "--target" {
+            return flags.next();
+        } else if let Some(target) = flag.strip_prefix("--target=") {
+            return Some(target);
+        }
+    }
+    None
+}
+
+/// The `RUSTFLAGS` to build an extension for a musl libc with, such as Alpine's Postgres.  Rust
+/// links musl statically by default, which can't produce a shared library, and wouldn't share a
+/// libc with the Postgres that loads it if it could, so it has to be linked dynamically instead.
+/// `None` when the extension isn't for musl, or `RUSTFLAGS` already says how to link it
+pub fn musl_rustflags() -> Option<String> {
+    let is_musl = match build_target() {
+        Some(target) => target.ends_with("musl"),
+        None => cfg!(target_env = "musl"),
+    };
+    let rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
+    musl_rustflags_for(is_musl, &rustflags)
+}
+
+fn musl_rustflags_for(is_musl: bool, rustflags: &str) -> Option<String> {
+    if !is_musl || rustflags.contains("crt-static") {
+        return None;
+    }
+    Some(
+        format!("{} -C target-feature=-crt-static", rustflags)
+            .trim()
+            .to_string(),
+    )
+}
+
+/// The environment variable the build sets to the commit the extension was built from, for
+/// `pg_introspection_functions!()`'s `<ext>_build_info()`
+pub const BUILD_GIT_SHA_ENV: &str = "PGX_BUILD_GIT_SHA";
+
+/// The environment variable the build sets to the version of `rustc` which built the extension,
+/// for `pg_introspection_functions!()`'s `<ext>_build_info()`
+pub const BUILD_RUSTC_VERSION_ENV: &str = "PGX_BUILD_RUSTC_VERSION";
+
+/// The environment to build the extension in `dir` with, so its `<ext>_build_info()` says which
+/// commit it was built from, if `dir` is in a git checkout, and which `rustc` built it.  Variables
+/// already set, as by a packaging script building from a tarball, are left as they are
+pub fn build_info_env(dir: &std::path::Path) -> Vec<(&'static str, String)> {
+    fn first_line(command: &mut Command) -> Option<String> {
+        let output = command.stderr(Stdio::null()).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let output = String::from_utf8(output.stdout).ok()?;
+        output
+            .lines()
+            .next()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+    }
+
+    let mut env = Vec::new();
+    if std::env::var_os(BUILD_GIT_SHA_ENV).is_none() {
+        let dir = if dir.as_os_str().is_empty() {
+            std::path::Path::new(".")
+        } else {
+            dir
+        };
+        if let Some(sha) =
+            first_line(Command::new("git").arg("-C").arg(dir).args(["rev-parse", "HEAD"]))
+        {
+            env.push((BUILD_GIT_SHA_ENV, sha));
+        }
+    }
+    if std::env::var_os(BUILD_RUSTC_VERSION_ENV).is_none() {
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        if let Some(version) = first_line(Command::new(rustc).arg("--version")) {
+            env.push((BUILD_RUSTC_VERSION_ENV, version));
+        }
+    }
+    env
+}
+
 pub fn prefix_path<P: Into<PathBuf>>(dir: P) -> String {
     let mut path = std::env::split_paths(&std::env::var_os("PATH").expect("failed to get $PATH"))
         .collect::<Vec<_>>();
@@ -91,7 +184,7 @@ pub fn createdb(
         return Ok(false);
     }
 
-    println!("{} database {}", "     Creating".bold().green(), dbname);
+    eprintln!("{} database {}", "     Creating".bold().green(), dbname);
     let mut command = Command::new(pg_config.createdb_path()?);
     command
         .env_remove("PGDATABASE")
@@ -180,6 +273,7 @@ pub enum ExternArgs {
     Volatile,
     Raw,
     NoGuard,
+    Untrusted,
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
@@ -187,6 +281,9 @@ pub enum ExternArgs {
     Schema(String),
     Name(String),
     Cost(String),
+    Rows(String),
+    Support(String),
+    Inline(String),
     Requires(Vec<PositioningRef>),
 }
 
@@ -203,9 +300,15 @@ impl core::fmt::Display for ExternArgs {
             ExternArgs::ParallelRestricted => write!(f, "PARALLEL RESTRICTED"),
             ExternArgs::Error(_) => Ok(()),
             ExternArgs::NoGuard => Ok(()),
+            ExternArgs::Untrusted => Ok(()),
             ExternArgs::Schema(_) => Ok(()),
             ExternArgs::Name(_) => Ok(()),
             ExternArgs::Cost(cost) => write!(f, "COST {}", cost),
+            ExternArgs::Rows(rows) => write!(f, "ROWS {}", rows),
+            // written with the support function's schema, by `PgExternEntity`
+            ExternArgs::Support(_) => Ok(()),
+            // replaces the `LANGUAGE c` body, by `PgExternEntity`
+            ExternArgs::Inline(_) => Ok(()),
             ExternArgs::Requires(_) => Ok(()),
         }
     }
@@ -220,6 +323,7 @@ impl ToTokens for ExternArgs {
             ExternArgs::Volatile => tokens.append(format_ident!("Volatile")),
             ExternArgs::Raw => tokens.append(format_ident!("Raw")),
             ExternArgs::NoGuard => tokens.append(format_ident!("NoGuard")),
+            ExternArgs::Untrusted => tokens.append(format_ident!("Untrusted")),
             ExternArgs::ParallelSafe => tokens.append(format_ident!("ParallelSafe")),
             ExternArgs::ParallelUnsafe => tokens.append(format_ident!("ParallelUnsafe")),
             ExternArgs::ParallelRestricted => tokens.append(format_ident!("ParallelRestricted")),
@@ -255,6 +359,30 @@ impl ToTokens for ExternArgs {
                     .to_token_stream(),
                 );
             }
+            ExternArgs::Rows(_s) => {
+                tokens.append_all(
+                    quote! {
+                        Rows(String::from("#_s"))
+                    }
+                    .to_token_stream(),
+                );
+            }
+            ExternArgs::Support(_s) => {
+                tokens.append_all(
+                    quote! {
+                        Support(String::from("#_s"))
+                    }
+                    .to_token_stream(),
+                );
+            }
+            ExternArgs::Inline(_s) => {
+                tokens.append_all(
+                    quote! {
+                        Inline(String::from("#_s"))
+                    }
+                    .to_token_stream(),
+                );
+            }
             ExternArgs::Requires(items) => {
                 tokens.append_all(
                     quote! {
@@ -299,6 +427,7 @@ pub fn parse_extern_attributes(attr: TokenStream) -> HashSet<ExternArgs> {
                     "volatile" => args.insert(ExternArgs::Volatile),
                     "raw" => args.insert(ExternArgs::Raw),
                     "no_guard" => args.insert(ExternArgs::NoGuard),
+                    "untrusted" => args.insert(ExternArgs::Untrusted),
                     "parallel_safe" => args.insert(ExternArgs::ParallelSafe),
                     "parallel_unsafe" => args.insert(ExternArgs::ParallelUnsafe),
                     "parallel_restricted" => args.insert(ExternArgs::ParallelRestricted),
@@ -560,9 +689,25 @@ pub fn anonymonize_lifetimes(value: &mut syn::Type) {
     }
 }
 
+/// The `#[cfg(..)]` attributes of an item, for the items a macro generates alongside it.
+///
+/// An attribute macro sees the `#[cfg(..)]` attributes which come after it on the item rather than
+/// the item being removed, so the functions it generates, like the `__pgx_internals` function of
+/// its SQL entity, must be given them too, or they'd be left referring to an item which isn't
+/// compiled.
+pub fn cfg_attrs(attrs: &[syn::Attribute]) -> Vec<syn::Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .cloned()
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{parse_extern_attributes, ExternArgs};
+    use crate::{
+        cfg_attrs, musl_rustflags_for, parse_extern_attributes, target_from_build_flags, ExternArgs,
+    };
     use std::str::FromStr;
 
     #[test]
@@ -575,4 +720,49 @@ mod tests {
             "syntax error at or near \"THIS\"".to_string()
         )));
     }
+
+    #[test]
+    fn build_flags_target() {
+        assert_eq!(
+            target_from_build_flags("--locked --target x86_64-unknown-linux-musl"),
+            Some("x86_64-unknown-linux-musl")
+        );
+        assert_eq!(
+            target_from_build_flags("--target=aarch64-unknown-linux-musl -j4"),
+            Some("aarch64-unknown-linux-musl")
+        );
+        assert_eq!(target_from_build_flags("--locked"), None);
+    }
+
+    #[test]
+    fn musl_links_libc_dynamically() {
+        assert_eq!(
+            musl_rustflags_for(true, ""),
+            Some("-C target-feature=-crt-static".to_string())
+        );
+        assert_eq!(
+            musl_rustflags_for(true, "-C opt-level=2"),
+            Some("-C opt-level=2 -C target-feature=-crt-static".to_string())
+        );
+        assert_eq!(
+            musl_rustflags_for(true, "-C target-feature=+crt-static"),
+            None
+        );
+        assert_eq!(musl_rustflags_for(false, ""), None);
+    }
+
+    #[test]
+    fn cfg_attrs_of_item() {
+        let func: syn::ItemFn = syn::parse_quote! {
+            /// Only with `fancy`
+            #[cfg(feature = "fancy")]
+            #[allow(dead_code)]
+            #[cfg(not(windows))]
+            fn fancy() {}
+        };
+        let cfgs = cfg_attrs(&func.attrs);
+        assert_eq!(cfgs.len(), 2);
+        assert_eq!(cfgs[0], func.attrs[1]);
+        assert_eq!(cfgs[1], func.attrs[3]);
+    }
 }
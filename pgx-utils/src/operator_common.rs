@@ -138,6 +138,26 @@ pub fn cmp(type_name: &Ident) -> proc_macro2::TokenStream {
     }
 }
 
+pub fn sortsupport(type_name: &Ident, abbreviated_keys: bool) -> proc_macro2::TokenStream {
+    let pg_name = Ident::new(
+        &format!("{}_sortsupport", type_name).to_lowercase(),
+        type_name.span(),
+    );
+    let prepare = if abbreviated_keys {
+        quote! { pgx::sortsupport::prepare_abbreviated_sort_support::<#type_name> }
+    } else {
+        quote! { pgx::sortsupport::prepare_sort_support::<#type_name> }
+    };
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #pg_name(ssup: pgx::Internal) {
+            let ssup = ssup.unwrap().expect("SortSupport is NULL") as pgx::pg_sys::SortSupport;
+            unsafe { #prepare(ssup) }
+        }
+    }
+}
+
 pub fn hash(type_name: &Ident) -> proc_macro2::TokenStream {
     let pg_name = Ident::new(
         &format!("{}_hash", type_name).to_lowercase(),
@@ -151,3 +171,17 @@ pub fn hash(type_name: &Ident) -> proc_macro2::TokenStream {
         }
     }
 }
+
+pub fn hash_extended(type_name: &Ident) -> proc_macro2::TokenStream {
+    let pg_name = Ident::new(
+        &format!("{}_hash_extended", type_name).to_lowercase(),
+        type_name.span(),
+    );
+    quote! {
+        #[allow(non_snake_case)]
+        #[pg_extern(immutable, parallel_safe)]
+        fn #pg_name(value: #type_name, seed: i64) -> i64 {
+            pgx::misc::pgx_seahash_extended(&value, seed as u64) as i64
+        }
+    }
+}
@@ -14,10 +14,12 @@ use std::{
     io::ErrorKind,
     path::{Path, PathBuf},
     process::Command,
-    str::FromStr,
 };
 use url::Url;
 
+/// Where `cargo pgx init` downloads the Postgres sources from, unless given a mirror of it
+pub const POSTGRES_SOURCE_URL: &str = "https://ftp.postgresql.org/pub/source";
+
 #[derive(Clone)]
 pub struct PgVersion {
     major_version: u16,
@@ -25,6 +27,64 @@ pub struct PgVersion {
     url: Url,
 }
 
+impl PgVersion {
+    /// Postgres v`major_version`.`minor_version`, whose source tarball is downloaded from
+    /// `source_url`, which is laid out like [`POSTGRES_SOURCE_URL`]
+    pub fn new(major_version: u16, minor_version: u16, source_url: &str) -> eyre::Result<Self> {
+        let url = format!(
+            "{}/v{}.{}/{}",
+            source_url.trim_end_matches('/'),
+            major_version,
+            minor_version,
+            source_tarball_name(major_version, minor_version)
+        );
+        Ok(PgVersion {
+            major_version,
+            minor_version,
+            url: Url::parse(&url).wrap_err_with(|| format!("invalid url `{}`", url))?,
+        })
+    }
+}
+
+/// The name of the source tarball of Postgres v`major_version`.`minor_version`
+pub fn source_tarball_name(major_version: u16, minor_version: u16) -> String {
+    format!("postgresql-{}.{}.tar.bz2", major_version, minor_version)
+}
+
+/// The major and minor version of the Postgres source tarball called `name`, if it's one
+pub fn parse_source_tarball_name(name: &str) -> Option<(u16, u16)> {
+    let version = name.strip_prefix("postgresql-")?.strip_suffix(".tar.bz2")?;
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Where postgresql.org publishes the SHA-256 checksum of Postgres v`major_version`.`minor_version`'s
+/// source tarball, which is checked however the tarball was downloaded
+pub fn source_checksum_url(major_version: u16, minor_version: u16) -> String {
+    format!(
+        "{}/v{}.{}/{}.sha256",
+        POSTGRES_SOURCE_URL,
+        major_version,
+        minor_version,
+        source_tarball_name(major_version, minor_version)
+    )
+}
+
+/// The major and minor versions of the release directories, like `v14.2`, that the HTML `index`
+/// of a directory laid out like [`POSTGRES_SOURCE_URL`] lists
+pub fn parse_source_index(index: &str) -> Vec<(u16, u16)> {
+    let mut versions = index
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+        .filter_map(|name| {
+            let (major, minor) = name.strip_prefix('v')?.split_once('.')?;
+            Some((major.parse().ok()?, minor.parse().ok()?))
+        })
+        .collect::<Vec<_>>();
+    versions.sort_unstable();
+    versions.dedup();
+    versions
+}
+
 impl Display for PgVersion {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}.{}", self.major_version, self.minor_version)
@@ -35,6 +95,9 @@ impl Display for PgVersion {
 pub struct PgConfig {
     version: Option<PgVersion>,
     pg_config: Option<PathBuf>,
+    /// The ports from the project's `pgx.toml`, in place of pgx' defaults
+    port: Option<u16>,
+    test_port: Option<u16>,
 }
 
 impl Display for PgConfig {
@@ -58,6 +121,8 @@ impl Default for PgConfig {
         PgConfig {
             version: None,
             pg_config: None,
+            port: None,
+            test_port: None,
         }
     }
 }
@@ -67,6 +132,18 @@ impl PgConfig {
         PgConfig {
             version: None,
             pg_config: Some(pg_config),
+            port: None,
+            test_port: None,
+        }
+    }
+
+    /// A version of Postgres for `cargo pgx init` to download, compile, and install
+    pub fn from_version(version: PgVersion) -> Self {
+        PgConfig {
+            version: Some(version),
+            pg_config: None,
+            port: None,
+            test_port: None,
         }
     }
 
@@ -86,6 +163,14 @@ impl PgConfig {
         self.pg_config.clone()
     }
 
+    /// Make this the `pg_config` `Pgx::from_config()` returns, for this process and every one it
+    /// starts afterwards, such as the build script of `pgx-pg-sys` or a `#[pg_test]` binary
+    pub fn export(&self) {
+        if let Some(pg_config) = &self.pg_config {
+            std::env::set_var("PGX_PG_CONFIG_PATH", pg_config);
+        }
+    }
+
     pub fn parent_path(&self) -> PathBuf {
         self.path().unwrap().parent().unwrap().to_path_buf()
     }
@@ -93,46 +178,14 @@ impl PgConfig {
     pub fn major_version(&self) -> eyre::Result<u16> {
         match &self.version {
             Some(version) => Ok(version.major_version),
-            None => {
-                let version_string = self.run("--version")?;
-                let version_parts = version_string.split_whitespace().collect::<Vec<&str>>();
-                let version = match version_parts.get(1) {
-                    Some(v) => v,
-                    None => {
-                        return Err(eyre!("invalid version string: {}", version_string));
-                    }
-                };
-                let version = match f64::from_str(version) {
-                    Ok(f) => f,
-                    Err(e) => {
-                        return Err(eyre!("invalid major version number `{}`: {:?}", version, e));
-                    }
-                };
-                Ok(version.floor() as u16)
-            }
+            None => Ok(parse_version(&self.run("--version")?)?.0),
         }
     }
 
     pub fn minor_version(&self) -> eyre::Result<u16> {
         match &self.version {
             Some(version) => Ok(version.minor_version),
-            None => {
-                let version_string = self.run("--version")?;
-                let version_parts = version_string.split_whitespace().collect::<Vec<&str>>();
-                let version = match version_parts.get(1) {
-                    Some(v) => v.split('.').next().unwrap(),
-                    None => {
-                        return Err(eyre!("invalid version string: {}", version_string));
-                    }
-                };
-                let version = match u16::from_str(version) {
-                    Ok(u) => u,
-                    Err(e) => {
-                        return Err(eyre!("invalid minor version number `{}`: {:?}", version, e));
-                    }
-                };
-                Ok(version)
-            }
+            None => Ok(parse_version(&self.run("--version")?)?.1),
         }
     }
 
@@ -151,11 +204,17 @@ impl PgConfig {
     }
 
     pub fn port(&self) -> eyre::Result<u16> {
-        Ok(BASE_POSTGRES_PORT_NO + self.major_version()?)
+        match self.port {
+            Some(port) => Ok(port),
+            None => Ok(BASE_POSTGRES_PORT_NO + self.major_version()?),
+        }
     }
 
     pub fn test_port(&self) -> eyre::Result<u16> {
-        Ok(BASE_POSTGRES_TESTING_PORT_NO + self.major_version()?)
+        match self.test_port {
+            Some(port) => Ok(port),
+            None => Ok(BASE_POSTGRES_TESTING_PORT_NO + self.major_version()?),
+        }
     }
 
     pub fn host(&self) -> &'static str {
@@ -167,32 +226,45 @@ impl PgConfig {
     }
 
     pub fn postmaster_path(&self) -> eyre::Result<PathBuf> {
-        let mut path = self.bin_dir()?;
-        path.push("postmaster");
-        Ok(path)
+        // Windows has no `postmaster` link to `postgres`
+        if cfg!(windows) {
+            self.bin_path("postgres")
+        } else {
+            self.bin_path("postmaster")
+        }
     }
 
     pub fn initdb_path(&self) -> eyre::Result<PathBuf> {
-        let mut path = self.bin_dir()?;
-        path.push("initdb");
-        Ok(path)
+        self.bin_path("initdb")
     }
 
     pub fn createdb_path(&self) -> eyre::Result<PathBuf> {
-        let mut path = self.bin_dir()?;
-        path.push("createdb");
-        Ok(path)
+        self.bin_path("createdb")
     }
 
     pub fn dropdb_path(&self) -> eyre::Result<PathBuf> {
-        let mut path = self.bin_dir()?;
-        path.push("dropdb");
-        Ok(path)
+        self.bin_path("dropdb")
     }
 
     pub fn psql_path(&self) -> eyre::Result<PathBuf> {
+        self.bin_path("psql")
+    }
+
+    pub fn pg_ctl_path(&self) -> eyre::Result<PathBuf> {
+        self.bin_path("pg_ctl")
+    }
+
+    pub fn pg_dump_path(&self) -> eyre::Result<PathBuf> {
+        self.bin_path("pg_dump")
+    }
+
+    pub fn pg_restore_path(&self) -> eyre::Result<PathBuf> {
+        self.bin_path("pg_restore")
+    }
+
+    fn bin_path(&self, program: &str) -> eyre::Result<PathBuf> {
         let mut path = self.bin_dir()?;
-        path.push("psql");
+        path.push(format!("{}{}", program, std::env::consts::EXE_SUFFIX));
         Ok(path)
     }
 
@@ -245,6 +317,30 @@ impl PgConfig {
     }
 }
 
+/// Pull the major and minor version numbers out of `pg_config --version` output.
+///
+/// Pre-release builds and forks (EnterpriseDB, Greenplum, ...) don't all report a plain
+/// `PostgreSQL 14.2`, so the first word that starts with a digit is taken as the version, everything
+/// after its leading numbers is ignored, and a missing minor version (as in `15beta1`) is zero.
+fn parse_version(version_string: &str) -> eyre::Result<(u16, u16)> {
+    fn leading_number(s: &str) -> Option<u16> {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        s[..end].parse::<u16>().ok()
+    }
+
+    let version = version_string
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .ok_or_else(|| eyre!("invalid version string: {}", version_string))?;
+    let major = leading_number(version)
+        .ok_or_else(|| eyre!("invalid major version number in `{}`", version_string))?;
+    let minor = match version.split_once('.') {
+        Some((_, rest)) => leading_number(rest).unwrap_or_default(),
+        None => 0,
+    };
+    Ok((major, minor))
+}
+
 pub struct Pgx {
     pg_configs: Vec<PgConfig>,
 }
@@ -255,7 +351,110 @@ use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ConfigToml {
+    #[serde(default)]
     configs: HashMap<String, PathBuf>,
+    /// Extra `./configure` arguments, by version label, for the Postgres versions `cargo pgx init` compiles
+    #[serde(default, rename = "configure-flags")]
+    configure_flags: HashMap<String, Vec<String>>,
+}
+
+/// The name of a project's own pgx configuration, found in the current directory or the nearest
+/// one above it, which is meant to be committed so everyone working on the project has the same
+/// Postgres setup
+pub const PROJECT_CONFIG_FILE: &str = "pgx.toml";
+
+/// A project's `pgx.toml`, which overrides `~/.pgx/config.toml` for everything run inside it:
+///
+/// ```toml
+/// # only these versions, for `all`
+/// versions = ["pg13", "pg14"]
+///
+/// # relative paths are from the directory pgx.toml is in
+/// [configs]
+/// pg14 = "/usr/lib/postgresql/14/bin/pg_config"
+///
+/// [ports]
+/// pg14 = 5414
+///
+/// [test-ports]
+/// pg14 = 5514
+///
+/// # the same as their `cargo pgx test` flags
+/// [test]
+/// features = ["my_feature"]
+/// keep-on-failure = true
+/// reuse-server = true
+/// no-schema = false
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// The versions that `all` means, when not every version `cargo pgx init` set up
+    pub versions: Option<Vec<String>>,
+    /// `pg_config`s by version label, in place of those in `~/.pgx/config.toml`
+    #[serde(default)]
+    pub configs: HashMap<String, PathBuf>,
+    #[serde(default)]
+    pub ports: HashMap<String, u16>,
+    #[serde(default)]
+    pub test_ports: HashMap<String, u16>,
+    #[serde(default)]
+    pub test: ProjectTestOptions,
+}
+
+/// The `[test]` table of a `pgx.toml`, defaults for `cargo pgx test`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectTestOptions {
+    /// Features to enable, in addition to those given with `--features`
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub keep_on_failure: bool,
+    #[serde(default)]
+    pub reuse_server: bool,
+    #[serde(default)]
+    pub no_schema: bool,
+}
+
+impl ProjectConfig {
+    /// The `pgx.toml` of the project in the current directory, or an empty one if it hasn't any
+    pub fn load() -> eyre::Result<Self> {
+        let mut dir = std::env::current_dir()?;
+        loop {
+            let path = dir.join(PROJECT_CONFIG_FILE);
+            if path.is_file() {
+                let contents = std::fs::read_to_string(&path)?;
+                return ProjectConfig::parse(&contents, &dir)
+                    .wrap_err_with(|| format!("Could not read `{}`", path.display()));
+            }
+            if !dir.pop() {
+                return Ok(ProjectConfig::default());
+            }
+        }
+    }
+
+    fn parse(contents: &str, dir: &Path) -> eyre::Result<Self> {
+        let mut config = toml::from_str::<ProjectConfig>(contents)?;
+        for path in config.configs.values_mut() {
+            if path.is_relative() {
+                *path = dir.join(&path);
+            }
+        }
+        Ok(config)
+    }
+
+    fn includes(&self, label: &str) -> bool {
+        match &self.versions {
+            Some(versions) => versions.iter().any(|version| version == label),
+            None => true,
+        }
+    }
+
+    fn apply_ports(&self, label: &str, pg_config: &mut PgConfig) {
+        pg_config.port = self.ports.get(label).copied();
+        pg_config.test_port = self.test_ports.get(label).copied();
+    }
 }
 
 pub enum PgConfigSelector<'a> {
@@ -282,51 +481,77 @@ impl Pgx {
         let pgx = Self {
             pg_configs: rss::PostgreSQLVersionRss::new(supported_major_versions)?
                 .into_iter()
-                .map(|version| PgConfig {
-                    version: Some(version),
-                    pg_config: None,
-                })
+                .map(PgConfig::from_version)
                 .collect(),
         };
         Ok(pgx)
     }
 
     pub fn from_config() -> eyre::Result<Self> {
+        let project = ProjectConfig::load()?;
         match std::env::var("PGX_PG_CONFIG_PATH") {
             Ok(pg_config) => {
                 // we have an environment variable that tells us the pg_config to use
+                let mut pg_config = PgConfig::new(pg_config.into());
+                if !project.ports.is_empty() || !project.test_ports.is_empty() {
+                    project.apply_ports(&pg_config.label()?, &mut pg_config);
+                }
                 let mut pgx = Pgx::new();
-                pgx.push(PgConfig::new(pg_config.into()));
+                pgx.push(pg_config);
                 Ok(pgx)
             }
             Err(_) => {
-                // we'll get what we need from cargo-pgx' config.toml file
+                // we'll get what we need from cargo-pgx' config.toml file, and the project's
                 let path = Pgx::config_toml()?;
-                if !path.exists() {
+                let mut configs = if path.exists() {
+                    match toml::from_str::<ConfigToml>(&std::fs::read_to_string(&path)?) {
+                        Ok(configs) => configs.configs,
+                        Err(e) => {
+                            return Err(e)
+                                .wrap_err_with(|| format!("Could not read `{}`", path.display()))
+                        }
+                    }
+                } else if !project.configs.is_empty() {
+                    HashMap::new()
+                } else {
                     return Err(eyre!(
                         "{} not found.  Have you run `{}` yet?",
                         path.display(),
                         "cargo pgx init".bold().yellow()
                     ));
-                }
-
-                match toml::from_str::<ConfigToml>(&std::fs::read_to_string(&path)?) {
-                    Ok(configs) => {
-                        let mut pgx = Pgx::new();
+                };
+                configs.extend(project.configs.clone());
 
-                        for (_, v) in configs.configs {
-                            pgx.push(PgConfig::new(v));
-                        }
-                        Ok(pgx)
-                    }
-                    Err(e) => {
-                        Err(e).wrap_err_with(|| format!("Could not read `{}`", path.display()))
+                let mut pgx = Pgx::new();
+                for (label, path) in configs {
+                    if !project.includes(&label) {
+                        continue;
                     }
+                    let mut pg_config = PgConfig::new(path);
+                    project.apply_ports(&label, &mut pg_config);
+                    pgx.push(pg_config);
                 }
+                Ok(pgx)
             }
         }
     }
 
+    /// The `[configure-flags]` table from cargo-pgx' config.toml, mapping version labels (like `pg13`)
+    /// to the extra arguments `cargo pgx init` passes to `./configure` when compiling that version.
+    ///
+    /// Returns an empty map if config.toml doesn't exist yet.
+    pub fn configure_flags() -> eyre::Result<HashMap<String, Vec<String>>> {
+        let path = Pgx::config_toml()?;
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        match toml::from_str::<ConfigToml>(&std::fs::read_to_string(&path)?) {
+            Ok(config) => Ok(config.configure_flags),
+            Err(e) => Err(e).wrap_err_with(|| format!("Could not read `{}`", path.display())),
+        }
+    }
+
     pub fn push(&mut self, pg_config: PgConfig) {
         self.pg_configs.push(pg_config);
     }
@@ -403,6 +628,19 @@ impl Pgx {
         Ok(stub_dir)
     }
 
+    /// Where `pgx-pg-sys` keeps the bindings it has generated, so they're shared by every crate
+    /// built against the same Postgres
+    pub fn bindings_cache_dir() -> Result<PathBuf, std::io::Error> {
+        match std::env::var_os("PGX_PG_SYS_BINDINGS_CACHE") {
+            Some(dir) => Ok(dir.into()),
+            None => {
+                let mut cache_dir = Self::home()?;
+                cache_dir.push("bindings-cache");
+                Ok(cache_dir)
+            }
+        }
+    }
+
     pub fn config_toml() -> Result<PathBuf, std::io::Error> {
         let mut path = Pgx::home()?;
         path.push("config.toml");
@@ -411,12 +649,11 @@ impl Pgx {
 }
 
 mod rss {
-    use crate::pg_config::PgVersion;
+    use crate::pg_config::{PgVersion, POSTGRES_SOURCE_URL};
     use eyre::WrapErr;
     use owo_colors::OwoColorize;
     use rttp_client::{types::Proxy, HttpClient};
     use serde_derive::Deserialize;
-    use url::Url;
 
     pub(super) struct PostgreSQLVersionRss;
 
@@ -452,15 +689,7 @@ mod rss {
                 let minor = minor.unwrap().parse::<u16>().unwrap_or_default();
 
                 if supported_major_versions.contains(&major) {
-                    versions.push(PgVersion {
-                        major_version: major,
-                        minor_version: minor,
-                        url: Url::parse(
-                            &format!("https://ftp.postgresql.org/pub/source/v{major}.{minor}/postgresql-{major}.{minor}.tar.bz2",
-                                     major = major, minor = minor)
-                        )
-                            .expect("invalid url")
-                    })
+                    versions.push(PgVersion::new(major, minor, POSTGRES_SOURCE_URL)?)
                 }
             }
 
@@ -493,3 +722,131 @@ mod rss {
         title: String,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_source_index, parse_source_tarball_name, parse_version, source_checksum_url,
+        PgConfig, PgVersion, ProjectConfig,
+    };
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn parse_release_versions() {
+        assert_eq!(parse_version("PostgreSQL 14.2").unwrap(), (14, 2));
+        assert_eq!(parse_version("PostgreSQL 10.19\n").unwrap(), (10, 19));
+        assert_eq!(parse_version("PostgreSQL 9.6.24").unwrap(), (9, 6));
+    }
+
+    #[test]
+    fn parse_prerelease_versions() {
+        assert_eq!(parse_version("PostgreSQL 15beta1").unwrap(), (15, 0));
+        assert_eq!(parse_version("PostgreSQL 15devel").unwrap(), (15, 0));
+        assert_eq!(parse_version("PostgreSQL 15rc2").unwrap(), (15, 0));
+    }
+
+    #[test]
+    fn parse_fork_versions() {
+        assert_eq!(
+            parse_version("PostgreSQL 14.2 (EnterpriseDB Advanced Server 14.2.1)").unwrap(),
+            (14, 2)
+        );
+        assert_eq!(
+            parse_version("PostgreSQL 12.12 (Greenplum Database 7.0.0-beta.0 build dev)").unwrap(),
+            (12, 12)
+        );
+    }
+
+    #[test]
+    fn parse_garbage() {
+        assert!(parse_version("").is_err());
+        assert!(parse_version("pg_config: command not found").is_err());
+    }
+
+    #[test]
+    fn source_tarballs() {
+        assert_eq!(
+            parse_source_tarball_name("postgresql-14.2.tar.bz2"),
+            Some((14, 2))
+        );
+        assert_eq!(
+            parse_source_tarball_name("postgresql-10.19.tar.bz2"),
+            Some((10, 19))
+        );
+        assert_eq!(
+            parse_source_tarball_name("postgresql-14.2.tar.bz2.sha256"),
+            None
+        );
+        assert_eq!(
+            parse_source_tarball_name("postgresql-15beta1.tar.bz2"),
+            None
+        );
+        assert_eq!(parse_source_tarball_name("pgx-14.2.tar.bz2"), None);
+
+        let version = PgVersion::new(14, 2, "https://mirror.example.com/postgres/").unwrap();
+        assert_eq!(
+            version.url.as_str(),
+            "https://mirror.example.com/postgres/v14.2/postgresql-14.2.tar.bz2"
+        );
+    }
+
+    #[test]
+    fn source_index() {
+        let index = r#"<html><body><h1>Index of /pub/source/</h1>
+            <a href="v9.6.24/">v9.6.24</a>
+            <a href="v10.19/">v10.19</a>
+            <a href="v14.2/">v14.2</a>
+            <a href="v14.10/">v14.10</a>
+            <a href="v15beta1/">v15beta1</a>
+            </body></html>"#;
+        assert_eq!(parse_source_index(index), vec![(10, 19), (14, 2), (14, 10)]);
+        assert_eq!(
+            source_checksum_url(14, 2),
+            "https://ftp.postgresql.org/pub/source/v14.2/postgresql-14.2.tar.bz2.sha256"
+        );
+    }
+
+    #[test]
+    fn project_config() {
+        let config = ProjectConfig::parse(
+            r#"
+            versions = ["pg14"]
+
+            [configs]
+            pg13 = "/usr/lib/postgresql/13/bin/pg_config"
+            pg14 = "postgres/bin/pg_config"
+
+            [test-ports]
+            pg14 = 5514
+
+            [test]
+            features = ["my_feature"]
+            reuse-server = true
+            "#,
+            Path::new("/work/my_extension"),
+        )
+        .unwrap();
+        assert_eq!(
+            config.configs["pg13"],
+            PathBuf::from("/usr/lib/postgresql/13/bin/pg_config")
+        );
+        assert_eq!(
+            config.configs["pg14"],
+            PathBuf::from("/work/my_extension/postgres/bin/pg_config")
+        );
+        assert!(config.includes("pg14"));
+        assert!(!config.includes("pg13"));
+        assert_eq!(config.test.features, vec!["my_feature".to_string()]);
+        assert!(config.test.reuse_server);
+        assert!(!config.test.keep_on_failure);
+
+        let mut pg_config = PgConfig::new(config.configs["pg14"].clone());
+        config.apply_ports("pg14", &mut pg_config);
+        assert_eq!(pg_config.test_port().unwrap(), 5514);
+
+        assert!(ProjectConfig::parse("prots = {}", Path::new("/")).is_err());
+        assert!(ProjectConfig::parse("", Path::new("/"))
+            .unwrap()
+            .includes("pg10"));
+    }
+}
@@ -0,0 +1,289 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Building an extension and its tests with a sanitizer, for `cargo pgx test --sanitizer`, and
+//! starting the test Postgres so that it can load the instrumented extension.
+//!
+//! `cargo pgx test` puts the sanitizer's name in `PGX_SANITIZER`, which the `cargo pgx install`
+//! run by the test framework finds there too.  Only the extension's own crates are instrumented,
+//! so the build is for the host's `--target`, which keeps `RUSTFLAGS` away from build scripts and
+//! proc macros.  Postgres isn't instrumented, so the sanitizer's runtime is preloaded into the
+//! postmaster, and every backend forked from it.
+use eyre::{eyre, WrapErr};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+/// The environment variable holding the name of the sanitizer the build is for
+pub const SANITIZER_ENV: &str = "PGX_SANITIZER";
+
+/// The environment variable holding the path of the sanitizer's runtime library, instead of the
+/// one the C compiler has
+pub const SANITIZER_RUNTIME_ENV: &str = "PGX_SANITIZER_RUNTIME";
+
+/// Leaks LeakSanitizer would otherwise report for every Postgres process as it exits
+const ADDRESS_SUPPRESSIONS: &str = "\
+# memory context blocks, which Postgres frees a context at a time, if at all, rather than an
+# allocation at a time
+leak:AllocSetAlloc
+leak:AllocSetRealloc
+leak:AllocSetContextCreateInternal
+leak:AllocSetContextCreateExtended
+leak:MemoryContextCreate
+leak:GenerationAlloc
+leak:GenerationContextCreate
+leak:SlabAlloc
+leak:SlabContextCreate
+# settings and other state malloc()ed once and kept for the life of the process
+leak:guc_malloc
+leak:guc_strdup
+leak:guc_realloc
+leak:save_ps_display_args
+leak:init_ps_display
+leak:set_config_option
+leak:ProcessConfigFile
+leak:internal_load_library
+leak:load_libraries
+leak:PostmasterMain
+# the dynamic loader's own bookkeeping for the libraries it has loaded
+leak:_dl_
+";
+
+/// The sanitizers `cargo pgx test` can build an extension with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sanitizer {
+    /// AddressSanitizer, which finds out-of-bounds accesses, uses after free, and double frees,
+    /// and, through LeakSanitizer, leaks
+    Address,
+}
+
+impl Sanitizer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sanitizer::Address => "address",
+        }
+    }
+
+    /// The sanitizer in `PGX_SANITIZER`, if any
+    pub fn from_env() -> eyre::Result<Option<Self>> {
+        match std::env::var(SANITIZER_ENV) {
+            Ok(name) if !name.trim().is_empty() => name
+                .parse()
+                .map(Some)
+                .map_err(|e: String| eyre!("{}: {}", SANITIZER_ENV, e)),
+            _ => Ok(None),
+        }
+    }
+
+    fn rustflag(&self) -> String {
+        format!("-Zsanitizer={}", self.name())
+    }
+
+    /// `RUSTFLAGS` for building the extension, or its tests, with the sanitizer
+    pub fn rustflags(&self) -> String {
+        with_rustflag(
+            &std::env::var("RUSTFLAGS").unwrap_or_default(),
+            &self.rustflag(),
+            true,
+        )
+    }
+
+    /// `RUSTFLAGS` for building what mustn't be instrumented, such as the shared library
+    /// `cargo pgx schema` loads into itself to generate the extension's SQL
+    pub fn uninstrumented_rustflags(&self) -> String {
+        with_rustflag(
+            &std::env::var("RUSTFLAGS").unwrap_or_default(),
+            &self.rustflag(),
+            false,
+        )
+    }
+
+    /// The target triple of the host, which the instrumented build is for, from a toolchain that
+    /// can build with a sanitizer, which only nightly toolchains can
+    pub fn host_target(&self) -> eyre::Result<String> {
+        let output = Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+            .arg("-vV")
+            .output()
+            .wrap_err("couldn't run `rustc -vV`")?;
+        let version = String::from_utf8_lossy(&output.stdout);
+        let field = |name: &str| {
+            version
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .map(|value| value.trim().to_string())
+        };
+
+        let release = field("release:").unwrap_or_default();
+        if !release.contains("nightly")
+            && !release.contains("dev")
+            && std::env::var_os("RUSTC_BOOTSTRAP").is_none()
+        {
+            return Err(eyre!(
+                "the {} sanitizer needs a nightly toolchain, not rustc {}.  Try `cargo +nightly pgx test --sanitizer={}`",
+                self.name(),
+                release,
+                self.name()
+            ));
+        }
+        field("host:").ok_or_else(|| eyre!("`rustc -vV` didn't say what the host target is"))
+    }
+
+    /// The sanitizer's shared runtime library, which is preloaded into Postgres, from
+    /// `PGX_SANITIZER_RUNTIME`, or else the C compiler in `CC`
+    pub fn runtime_library(&self) -> eyre::Result<PathBuf> {
+        if let Some(runtime) = std::env::var_os(SANITIZER_RUNTIME_ENV) {
+            return Ok(PathBuf::from(runtime));
+        }
+
+        let library = match self {
+            Sanitizer::Address => "libasan.so",
+        };
+        let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let output = Command::new(&cc)
+            .arg(format!("-print-file-name={}", library))
+            .output()
+            .wrap_err_with(|| format!("couldn't ask `{}` where {} is", cc, library))?;
+        let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+        if path.is_absolute() && path.exists() {
+            Ok(path)
+        } else {
+            Err(eyre!(
+                "`{}` doesn't have {}.  Set {} to the {} sanitizer's shared runtime library",
+                cc,
+                library,
+                SANITIZER_RUNTIME_ENV,
+                self.name()
+            ))
+        }
+    }
+
+    /// The suppressions of the reports the sanitizer would otherwise make of how Postgres
+    /// allocates, in the sanitizer's format
+    pub fn suppressions(&self) -> &'static str {
+        match self {
+            Sanitizer::Address => ADDRESS_SUPPRESSIONS,
+        }
+    }
+
+    /// The environment for the postmaster, with the sanitizer's runtime preloaded, and
+    /// options that leave the reports in the server's log, reading suppressions from
+    /// `suppressions_file`.  Options already in the environment come last, so they win.
+    ///
+    /// A memory error aborts the backend, so Postgres restarts as after a crash and the test
+    /// fails, while leaks, which a backend that exits cleanly reports, are only logged.
+    pub fn server_env(&self, suppressions_file: &Path) -> eyre::Result<Vec<(String, String)>> {
+        let preload_var = if cfg!(target_os = "macos") {
+            "DYLD_INSERT_LIBRARIES"
+        } else {
+            "LD_PRELOAD"
+        };
+        let runtime = self.runtime_library()?;
+
+        let with_existing = |var: &str, value: String, separator: &str| match std::env::var(var) {
+            Ok(existing) if !existing.is_empty() => {
+                format!("{}{}{}", value, separator, existing)
+            }
+            _ => value,
+        };
+
+        match self {
+            Sanitizer::Address => Ok(vec![
+                (
+                    preload_var.to_string(),
+                    with_existing(preload_var, runtime.display().to_string(), ":"),
+                ),
+                (
+                    "ASAN_OPTIONS".to_string(),
+                    with_existing(
+                        "ASAN_OPTIONS",
+                        "abort_on_error=1:detect_leaks=1:exitcode=0:detect_stack_use_after_return=0"
+                            .to_string(),
+                        ":",
+                    ),
+                ),
+                (
+                    "LSAN_OPTIONS".to_string(),
+                    with_existing(
+                        "LSAN_OPTIONS",
+                        format!(
+                            "suppressions={}:print_suppressions=0",
+                            suppressions_file.display()
+                        ),
+                        ":",
+                    ),
+                ),
+            ]),
+        }
+    }
+}
+
+impl FromStr for Sanitizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "address" | "asan" => Ok(Sanitizer::Address),
+            other => Err(format!(
+                "unknown sanitizer `{}`, the only one supported is `address`",
+                other
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Sanitizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn with_rustflag(rustflags: &str, flag: &str, include: bool) -> String {
+    let mut flags = rustflags
+        .split_ascii_whitespace()
+        .filter(|existing| *existing != flag)
+        .collect::<Vec<_>>();
+    if include {
+        flags.push(flag);
+    }
+    flags.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{with_rustflag, Sanitizer};
+
+    #[test]
+    fn parse() {
+        assert_eq!("address".parse(), Ok(Sanitizer::Address));
+        assert_eq!("asan".parse(), Ok(Sanitizer::Address));
+        assert!("memory".parse::<Sanitizer>().is_err());
+        assert_eq!(Sanitizer::Address.to_string(), "address");
+    }
+
+    #[test]
+    fn rustflags() {
+        let flag = "-Zsanitizer=address";
+        assert_eq!(with_rustflag("", flag, true), flag);
+        assert_eq!(
+            with_rustflag("-C target-cpu=native", flag, true),
+            "-C target-cpu=native -Zsanitizer=address"
+        );
+        // adding it again, as the nested `cargo pgx install` does, doesn't repeat it
+        assert_eq!(
+            with_rustflag("-C debuginfo=2 -Zsanitizer=address", flag, true),
+            "-C debuginfo=2 -Zsanitizer=address"
+        );
+        assert_eq!(
+            with_rustflag("-C debuginfo=2 -Zsanitizer=address", flag, false),
+            "-C debuginfo=2"
+        );
+    }
+}
@@ -148,8 +148,9 @@ pub struct PgAggregateEntity {
 impl Ord for PgAggregateEntity {
     fn cmp(&self, other: &Self) -> Ordering {
         self.file
-            .cmp(other.full_path)
-            .then_with(|| self.file.cmp(other.full_path))
+            .cmp(other.file)
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.full_path.cmp(other.full_path))
     }
 }
 
@@ -354,6 +355,7 @@ impl ToSql for PgAggregateEntity {
                         .find(|neighbor| match &context.graph[*neighbor] {
                             SqlGraphEntity::Type(ty) => ty.id_matches(&arg.agg_ty.ty_id),
                             SqlGraphEntity::Enum(en) => en.id_matches(&arg.agg_ty.ty_id),
+                            SqlGraphEntity::Domain(domain) => domain.id_matches(&arg.agg_ty.ty_id),
                             SqlGraphEntity::BuiltinType(defined) => {
                                 defined == &arg.agg_ty.full_path
                             }
@@ -393,6 +395,7 @@ impl ToSql for PgAggregateEntity {
                         .find(|neighbor| match &context.graph[*neighbor] {
                             SqlGraphEntity::Type(ty) => ty.id_matches(&arg.ty_id),
                             SqlGraphEntity::Enum(en) => en.id_matches(&arg.ty_id),
+                            SqlGraphEntity::Domain(domain) => domain.id_matches(&arg.ty_id),
                             SqlGraphEntity::BuiltinType(defined) => defined == &arg.full_path,
                             _ => false,
                         })
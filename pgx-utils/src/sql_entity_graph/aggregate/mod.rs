@@ -676,12 +676,19 @@ impl ToTokens for PgAggregate {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         let entity_fn = self.entity_tokens();
         let impl_item = &self.item_impl;
-        let pg_externs = self.pg_externs.iter();
+        let cfg_attrs = crate::cfg_attrs(&impl_item.attrs);
+        let pg_externs = self.pg_externs.iter().map(|pg_extern| {
+            quote! {
+                #(#cfg_attrs)*
+                #pg_extern
+            }
+        });
         let inv = quote! {
             #impl_item
 
             #(#pg_externs)*
 
+            #(#cfg_attrs)*
             #entity_fn
         };
         tokens.append_all(inv);
@@ -0,0 +1,137 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    pgx_sql::PgxSql, positioning_ref::PositioningRef, to_sql::ToSql, SqlGraphEntity,
+    SqlGraphIdentifier,
+};
+
+use eyre::{eyre, WrapErr};
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+/// The signature of a function that renders a [`CustomEntity`] to SQL, in place of its `sql`
+///
+/// The entity's [`NodeIndex`](petgraph::stable_graph::NodeIndex) is `context.customs[entity]`,
+/// which can be given to [`PgxSql::schema_prefix_for`] and the like.
+pub type CustomToSqlFn =
+    fn(
+        &CustomEntity,
+        &PgxSql,
+    ) -> std::result::Result<String, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+/// The output of a [`Custom`](crate::sql_entity_graph::Custom) from `quote::ToTokens::to_tokens`.
+///
+/// A SQL entity of a kind pgx doesn't know about itself, like a `CREATE SERVER`, declared by a
+/// proc-macro outside of pgx.  It's placed after its module's schema, after everything it
+/// `requires`, and otherwise in source order, like the rest of the entities.
+#[derive(Debug, Clone)]
+pub struct CustomEntity {
+    /// A short, stable name for the kind of entity, like `server`
+    pub kind: &'static str,
+    pub name: &'static str,
+    /// The schema it's created in, if it's given one rather than its module's
+    pub schema: Option<&'static str>,
+    pub module_path: &'static str,
+    pub full_path: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub sql: &'static str,
+    /// Renders the entity instead of `sql`, if it's set
+    pub to_sql: Option<CustomToSqlFn>,
+    pub requires: Vec<PositioningRef>,
+}
+
+impl PartialEq for CustomEntity {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.full_path == other.full_path
+    }
+}
+
+impl Eq for CustomEntity {}
+
+impl Hash for CustomEntity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.full_path.hash(state);
+    }
+}
+
+impl Ord for CustomEntity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.file
+            .cmp(other.file)
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.kind.cmp(other.kind))
+            .then_with(|| self.full_path.cmp(other.full_path))
+    }
+}
+
+impl PartialOrd for CustomEntity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<CustomEntity> for SqlGraphEntity {
+    fn from(entity: CustomEntity) -> Self {
+        SqlGraphEntity::Custom(entity)
+    }
+}
+
+impl SqlGraphIdentifier for CustomEntity {
+    fn dot_identifier(&self) -> String {
+        format!("{} {}", self.kind, self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl CustomEntity {
+    /// The entity's SQL, from its `to_sql` if it has one, without the comments `to_sql()` adds
+    pub fn content(&self, context: &PgxSql) -> eyre::Result<String> {
+        match self.to_sql {
+            Some(to_sql) => to_sql(self, context)
+                .map_err(|e| eyre!(e))
+                .wrap_err_with(|| format!("Failed to render the {} `{}`", self.kind, self.name)),
+            None => Ok(self.sql.to_string()),
+        }
+    }
+}
+
+impl ToSql for CustomEntity {
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = self.full_path))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let content = self.content(context)?;
+        let sql = format!(
+            "\n\
+                -- {file}:{line}\n\
+                -- {kind} {full_path}\n\
+                {content}\n\
+            ",
+            file = self.file,
+            line = self.line,
+            kind = self.kind,
+            full_path = self.full_path,
+            content = content,
+        );
+        tracing::trace!(%sql);
+        Ok(sql)
+    }
+}
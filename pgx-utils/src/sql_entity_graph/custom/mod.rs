@@ -0,0 +1,191 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+pub mod entity;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{Attribute, Ident, LitStr, Path};
+
+use crate::sql_entity_graph::PositioningRef;
+
+/// The kinds of the entities pgx declares itself, which a [`Custom`] can't have.
+const BUILTIN_KINDS: &[&str] = &[
+    "extension_root",
+    "required_extension",
+    "schema",
+    "custom_sql",
+    "function",
+    "type",
+    "builtin_type",
+    "enum",
+    "domain",
+    "ord",
+    "hash",
+    "aggregate",
+];
+
+/// A SQL entity of a kind pgx doesn't know about, for proc-macro crates outside of pgx to declare.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a
+/// `pgx::utils::sql_entity_graph::CustomEntity`, which `cargo pgx schema` discovers with the rest of
+/// the extension's entities.  It's placed in the generated SQL after the schema of the module it's
+/// declared in, after everything it `requires`, and otherwise in source order.  Other entities can
+/// `requires = ["name"]` it, the same as an `extension_sql!()`.
+///
+/// A derive which creates a foreign server from a struct might output:
+///
+/// ```rust
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::{Custom, PositioningRef};
+///
+/// # fn main() -> Result<(), syn::Error> {
+/// let name: syn::Ident = syn::parse_quote! { warehouse };
+/// let sql = format!("CREATE SERVER {} FOREIGN DATA WRAPPER postgres_fdw;", name);
+/// let custom = Custom::new("server", name, quote! { #sql })?
+///     .set_requires(vec![PositioningRef::Name("postgres_fdw".to_string())]);
+/// let sql_graph_entity_tokens = custom.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// For SQL that depends on the rest of the graph, such as its schema's name, give it a
+/// [`CustomToSqlFn`](crate::sql_entity_graph::CustomToSqlFn) with [`Custom::set_to_sql`].
+#[derive(Debug, Clone)]
+pub struct Custom {
+    kind: String,
+    name: Ident,
+    /// An expression of the `&'static str` of SQL, like a literal or an `include_str!()`
+    sql: TokenStream2,
+    schema: Option<LitStr>,
+    to_sql: Option<Path>,
+    requires: Vec<PositioningRef>,
+    cfg_attrs: Vec<Attribute>,
+}
+
+impl Custom {
+    /// A `kind` entity called `name`, which is created by `sql`
+    ///
+    /// The `kind` is a short, stable name like `server`, of lowercase letters, digits, and `_`,
+    /// which mustn't be one of the kinds pgx declares itself.  The `name` is unique among the
+    /// entities of its kind in the extension.
+    pub fn new(kind: &str, name: Ident, sql: TokenStream2) -> Result<Self, syn::Error> {
+        let valid = kind.starts_with(|c: char| c.is_ascii_lowercase())
+            && kind
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid || BUILTIN_KINDS.contains(&kind) {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("`{}` can't be the kind of a custom SQL entity", kind),
+            ));
+        }
+        Ok(Self {
+            kind: kind.to_string(),
+            name,
+            sql,
+            schema: None,
+            to_sql: None,
+            requires: Vec::new(),
+            cfg_attrs: Vec::new(),
+        })
+    }
+
+    /// Create it in `schema` rather than the schema of the module it's declared in
+    pub fn set_schema(mut self, schema: LitStr) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Render it with the [`CustomToSqlFn`](crate::sql_entity_graph::CustomToSqlFn) at `path`
+    /// rather than its `sql`
+    pub fn set_to_sql(mut self, path: Path) -> Self {
+        self.to_sql = Some(path);
+        self
+    }
+
+    /// The entities it must be created after
+    pub fn set_requires(mut self, requires: Vec<PositioningRef>) -> Self {
+        self.requires = requires;
+        self
+    }
+
+    /// The `#[cfg]`s of the item it's declared for, so it's left out of the SQL with the item
+    pub fn set_cfg_attrs(mut self, cfg_attrs: Vec<Attribute>) -> Self {
+        self.cfg_attrs = cfg_attrs;
+        self
+    }
+}
+
+impl ToTokens for Custom {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let kind = &self.kind;
+        let name = self.name.to_string();
+        let sql = &self.sql;
+        let schema = match &self.schema {
+            Some(schema) => quote! { Some(#schema) },
+            None => quote! { None },
+        };
+        let to_sql = match &self.to_sql {
+            Some(path) => quote! { Some(#path) },
+            None => quote! { None },
+        };
+        let requires_iter = self.requires.iter();
+        let cfg_attrs = &self.cfg_attrs;
+        let sql_graph_entity_fn_name = Ident::new(
+            &format!("__pgx_internals_custom_{}_{}", kind, name),
+            Span::call_site(),
+        );
+        let inv = quote! {
+            #(#cfg_attrs)*
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn #sql_graph_entity_fn_name() -> ::pgx::utils::sql_entity_graph::SqlGraphEntity {
+                extern crate alloc;
+                use alloc::vec::Vec;
+                use alloc::vec;
+                let submission = ::pgx::utils::sql_entity_graph::CustomEntity {
+                    kind: #kind,
+                    name: #name,
+                    schema: #schema,
+                    module_path: module_path!(),
+                    full_path: concat!(module_path!(), "::", #name),
+                    file: file!(),
+                    line: line!(),
+                    sql: #sql,
+                    to_sql: #to_sql,
+                    requires: vec![#(#requires_iter),*],
+                };
+                ::pgx::utils::sql_entity_graph::SqlGraphEntity::Custom(submission)
+            }
+        };
+        tokens.append_all(inv);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Custom;
+    use quote::{quote, ToTokens};
+    use syn::parse_quote;
+
+    #[test]
+    fn custom_entity_fn() {
+        let custom = Custom::new("publication", parse_quote! { changes }, quote! { "" }).unwrap();
+        let tokens = custom.to_token_stream().to_string();
+        assert!(tokens.contains("__pgx_internals_custom_publication_changes"));
+        assert!(tokens.contains("kind : \"publication\""));
+    }
+
+    #[test]
+    fn custom_kind_must_be_new() {
+        assert!(Custom::new("function", parse_quote! { f }, quote! { "" }).is_err());
+        assert!(Custom::new("Server", parse_quote! { s }, quote! { "" }).is_err());
+        assert!(Custom::new("", parse_quote! { s }, quote! { "" }).is_err());
+    }
+}
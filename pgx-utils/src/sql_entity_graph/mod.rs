@@ -8,41 +8,71 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 pub(crate) mod aggregate;
 pub(crate) mod control_file;
+pub(crate) mod custom;
 pub(crate) mod extension_sql;
 pub(crate) mod mapping;
+pub(crate) mod pg_catalog_table;
 pub(crate) mod pg_extern;
+pub(crate) mod pg_generated_column;
+pub(crate) mod pg_index;
+pub(crate) mod pg_policy;
 pub(crate) mod pgx_attribute;
+pub(crate) mod operator_family;
 pub(crate) mod pgx_sql;
 pub(crate) mod positioning_ref;
+pub(crate) mod postgres_domain;
 pub(crate) mod postgres_enum;
 pub(crate) mod postgres_hash;
 pub(crate) mod postgres_ord;
 pub(crate) mod postgres_type;
+pub(crate) mod required_extension;
 pub(crate) mod schema;
+pub(crate) mod sql_name;
 pub(crate) mod to_sql;
+pub(crate) mod trusted;
 
 pub use aggregate::{
     entity::{AggregateTypeEntity, MaybeVariadicAggregateTypeEntity, PgAggregateEntity},
     AggregateType, AggregateTypeList, FinalizeModify, ParallelOption, PgAggregate,
 };
 pub use control_file::ControlFile;
+pub use custom::{
+    entity::{CustomEntity, CustomToSqlFn},
+    Custom,
+};
 pub use extension_sql::{
     entity::{ExtensionSqlEntity, SqlDeclaredEntity},
     ExtensionSql, ExtensionSqlFile, SqlDeclared,
 };
 pub use mapping::{RustSourceOnlySqlMapping, RustSqlMapping};
+pub use pg_catalog_table::{
+    entity::{CatalogTableColumn, CatalogTableDefinition, CATALOG_TABLE_KIND},
+    CatalogTableField, PgCatalogTable,
+};
+pub use operator_family::OperatorFamilies;
 pub use pg_extern::{
     entity::{PgExternArgumentEntity, PgExternEntity, PgExternReturnEntity, PgOperatorEntity},
-    NameMacro, PgExtern, PgExternArgument, PgOperator,
+    expand_generic_pg_extern, expand_impl_pg_extern, NameMacro, PgExtern, PgExternArgument,
+    PgOperator,
 };
+pub use pg_generated_column::PgGeneratedColumn;
+pub use pg_index::{IndexKey, PgIndex};
+pub use pg_policy::{PgPolicy, PolicyCommand};
 pub use pgx_sql::PgxSql;
 pub use positioning_ref::PositioningRef;
+pub use postgres_domain::{entity::PostgresDomainEntity, PostgresDomain};
 pub use postgres_enum::{entity::PostgresEnumEntity, PostgresEnum};
 pub use postgres_hash::{entity::PostgresHashEntity, PostgresHash};
 pub use postgres_ord::{entity::PostgresOrdEntity, PostgresOrd};
-pub use postgres_type::{entity::PostgresTypeEntity, PostgresType};
+pub use postgres_type::{entity::PostgresTypeEntity, storage::TypeStorage, PostgresType};
+pub use required_extension::{
+    entity::{required_extensions, RequiredExtensionEntity},
+    RequiredExtension,
+};
 pub use schema::{entity::SchemaEntity, Schema};
+pub use sql_name::SqlName;
 pub use to_sql::{entity::ToSqlConfigEntity, ToSql, ToSqlConfig};
+pub use trusted::{TrustedAuditFinding, TrustedAuditProblem};
 
 pub use crate::ExternArgs;
 
@@ -70,15 +100,18 @@ pub trait SqlGraphIdentifier {
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SqlGraphEntity {
     ExtensionRoot(ControlFile),
+    RequiredExtension(RequiredExtensionEntity),
     Schema(SchemaEntity),
     CustomSql(ExtensionSqlEntity),
     Function(PgExternEntity),
     Type(PostgresTypeEntity),
     BuiltinType(String),
     Enum(PostgresEnumEntity),
+    Domain(PostgresDomainEntity),
     Ord(PostgresOrdEntity),
     Hash(PostgresHashEntity),
     Aggregate(PgAggregateEntity),
+    Custom(CustomEntity),
 }
 
 impl SqlGraphEntity {
@@ -97,6 +130,26 @@ impl SqlGraphEntity {
             rust_identifier = self.rust_identifier(),
         )
     }
+
+    /// A short, stable name for the kind of entity, like `function` or `type`, or the kind a
+    /// [`CustomEntity`] is declared with.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SqlGraphEntity::ExtensionRoot(_) => "extension_root",
+            SqlGraphEntity::RequiredExtension(_) => "required_extension",
+            SqlGraphEntity::Schema(_) => "schema",
+            SqlGraphEntity::CustomSql(_) => "custom_sql",
+            SqlGraphEntity::Function(_) => "function",
+            SqlGraphEntity::Type(_) => "type",
+            SqlGraphEntity::BuiltinType(_) => "builtin_type",
+            SqlGraphEntity::Enum(_) => "enum",
+            SqlGraphEntity::Domain(_) => "domain",
+            SqlGraphEntity::Ord(_) => "ord",
+            SqlGraphEntity::Hash(_) => "hash",
+            SqlGraphEntity::Aggregate(_) => "aggregate",
+            SqlGraphEntity::Custom(item) => item.kind,
+        }
+    }
 }
 
 impl SqlGraphIdentifier for SqlGraphEntity {
@@ -108,10 +161,13 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Type(item) => item.dot_identifier(),
             SqlGraphEntity::BuiltinType(item) => format!("preexisting type {}", item),
             SqlGraphEntity::Enum(item) => item.dot_identifier(),
+            SqlGraphEntity::Domain(item) => item.dot_identifier(),
             SqlGraphEntity::Ord(item) => item.dot_identifier(),
             SqlGraphEntity::Hash(item) => item.dot_identifier(),
             SqlGraphEntity::Aggregate(item) => item.dot_identifier(),
+            SqlGraphEntity::Custom(item) => item.dot_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.dot_identifier(),
+            SqlGraphEntity::RequiredExtension(item) => item.dot_identifier(),
         }
     }
 
@@ -123,10 +179,13 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Type(item) => item.rust_identifier(),
             SqlGraphEntity::BuiltinType(item) => item.to_string(),
             SqlGraphEntity::Enum(item) => item.rust_identifier(),
+            SqlGraphEntity::Domain(item) => item.rust_identifier(),
             SqlGraphEntity::Ord(item) => item.rust_identifier(),
             SqlGraphEntity::Hash(item) => item.rust_identifier(),
             SqlGraphEntity::Aggregate(item) => item.rust_identifier(),
+            SqlGraphEntity::Custom(item) => item.rust_identifier(),
             SqlGraphEntity::ExtensionRoot(item) => item.rust_identifier(),
+            SqlGraphEntity::RequiredExtension(item) => item.rust_identifier(),
         }
     }
 
@@ -138,10 +197,13 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Type(item) => item.file(),
             SqlGraphEntity::BuiltinType(_item) => None,
             SqlGraphEntity::Enum(item) => item.file(),
+            SqlGraphEntity::Domain(item) => item.file(),
             SqlGraphEntity::Ord(item) => item.file(),
             SqlGraphEntity::Hash(item) => item.file(),
             SqlGraphEntity::Aggregate(item) => item.file(),
+            SqlGraphEntity::Custom(item) => item.file(),
             SqlGraphEntity::ExtensionRoot(item) => item.file(),
+            SqlGraphEntity::RequiredExtension(item) => item.file(),
         }
     }
 
@@ -153,10 +215,13 @@ impl SqlGraphIdentifier for SqlGraphEntity {
             SqlGraphEntity::Type(item) => item.line(),
             SqlGraphEntity::BuiltinType(_item) => None,
             SqlGraphEntity::Enum(item) => item.line(),
+            SqlGraphEntity::Domain(item) => item.line(),
             SqlGraphEntity::Ord(item) => item.line(),
             SqlGraphEntity::Hash(item) => item.line(),
             SqlGraphEntity::Aggregate(item) => item.line(),
+            SqlGraphEntity::Custom(item) => item.line(),
             SqlGraphEntity::ExtensionRoot(item) => item.line(),
+            SqlGraphEntity::RequiredExtension(item) => item.line(),
         }
     }
 }
@@ -208,6 +273,10 @@ impl ToSql for SqlGraphEntity {
                 .to_sql_config
                 .to_sql(self, context)
                 .unwrap_or_else(|| item.to_sql(context)),
+            SqlGraphEntity::Domain(item) => item
+                .to_sql_config
+                .to_sql(self, context)
+                .unwrap_or_else(|| item.to_sql(context)),
             SqlGraphEntity::Ord(item) => item
                 .to_sql_config
                 .to_sql(self, context)
@@ -220,7 +289,9 @@ impl ToSql for SqlGraphEntity {
                 .to_sql_config
                 .to_sql(self, context)
                 .unwrap_or_else(|| item.to_sql(context)),
+            SqlGraphEntity::Custom(item) => item.to_sql(context),
             SqlGraphEntity::ExtensionRoot(item) => item.to_sql(context),
+            SqlGraphEntity::RequiredExtension(item) => item.to_sql(context),
         }
     }
 }
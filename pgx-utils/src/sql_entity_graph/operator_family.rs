@@ -0,0 +1,158 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::pgx_attribute::{ArgValue, PgxArg, PgxAttribute};
+use syn::{spanned::Spanned, Attribute, Lit, LitStr};
+
+/// A parsed `#[pgx(default_opclass = false, btree_family = "..", hash_family = "..")]` of a
+/// `#[derive(PostgresOrd)]` or `#[derive(PostgresHash)]`, which sets whether the operator class it
+/// creates is the `DEFAULT` one for the type, and which existing operator families its operators
+/// and support functions are added to, with `ALTER OPERATOR FAMILY .. ADD`.
+///
+/// `btree_family` and `hash_family` can be given more than once.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OperatorFamilies {
+    /// Is the type's operator class its `DEFAULT` one?  It is unless `default_opclass = false`
+    pub default_opclass: bool,
+    /// The btree families a `#[derive(PostgresOrd)]` joins, such as `integer_ops`
+    pub btree: Vec<LitStr>,
+    /// The hash families a `#[derive(PostgresHash)]` joins
+    pub hash: Vec<LitStr>,
+}
+
+impl Default for OperatorFamilies {
+    fn default() -> Self {
+        Self {
+            default_opclass: true,
+            btree: Vec::new(),
+            hash: Vec::new(),
+        }
+    }
+}
+
+impl OperatorFamilies {
+    /// Used to parse the operator families from a set of item attributes
+    pub fn from_attributes(attrs: &[Attribute]) -> Result<Self, syn::Error> {
+        let mut families = OperatorFamilies::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+            let attr = attr.parse_args::<PgxAttribute>()?;
+            for arg in attr.args {
+                let nv = match arg {
+                    PgxArg::NameValue(nv) => nv,
+                    _ => continue,
+                };
+                if nv.path.is_ident("default_opclass") {
+                    match nv.value {
+                        ArgValue::Lit(Lit::Bool(value)) => families.default_opclass = value.value,
+                        _ => {
+                            return Err(syn::Error::new(
+                                nv.path.span(),
+                                "expected `#[pgx(default_opclass = false)]`",
+                            ))
+                        }
+                    }
+                    continue;
+                }
+                let (slot, key) = if nv.path.is_ident("btree_family") {
+                    (&mut families.btree, "btree_family")
+                } else if nv.path.is_ident("hash_family") {
+                    (&mut families.hash, "hash_family")
+                } else {
+                    continue;
+                };
+                match nv.value {
+                    ArgValue::Lit(Lit::Str(value)) if !value.value().is_empty() => slot.push(value),
+                    _ => {
+                        return Err(syn::Error::new(
+                            nv.path.span(),
+                            format!("expected `#[pgx({} = \"family\")]`", key),
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(families)
+    }
+
+    /// The `&'static [&'static str]` of the btree families, for an entity
+    pub fn btree_tokens(&self) -> proc_macro2::TokenStream {
+        slice_tokens(&self.btree)
+    }
+
+    /// The `&'static [&'static str]` of the hash families, for an entity
+    pub fn hash_tokens(&self) -> proc_macro2::TokenStream {
+        slice_tokens(&self.hash)
+    }
+}
+
+fn slice_tokens(families: &[LitStr]) -> proc_macro2::TokenStream {
+    quote::quote! { &[#(#families),*] }
+}
+
+/// The `CREATE OPERATOR CLASS` keyword which makes an operator class its type's default, if it is
+pub(crate) fn default_keyword(default_opclass: bool) -> &'static str {
+    if default_opclass {
+        " DEFAULT"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OperatorFamilies;
+    use syn::{parse_quote, DeriveInput};
+
+    #[test]
+    fn families_and_default() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(PostgresOrd, PostgresHash)]
+            #[pgx(default_opclass = false, btree_family = "integer_ops")]
+            #[pgx(btree_family = "my_ops", hash_family = "integer_ops")]
+            struct Small(i16);
+        };
+        let families = OperatorFamilies::from_attributes(&input.attrs).unwrap();
+        assert!(!families.default_opclass);
+        assert_eq!(
+            families
+                .btree
+                .iter()
+                .map(|family| family.value())
+                .collect::<Vec<_>>(),
+            vec!["integer_ops", "my_ops"]
+        );
+        assert_eq!(families.hash.len(), 1);
+        assert_eq!(families.hash[0].value(), "integer_ops");
+    }
+
+    #[test]
+    fn defaults_to_a_default_opclass_of_its_own() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(PostgresOrd)]
+            #[pgx(sql_name = "small")]
+            struct Small(i16);
+        };
+        let families = OperatorFamilies::from_attributes(&input.attrs).unwrap();
+        assert_eq!(families, OperatorFamilies::default());
+        assert!(families.default_opclass);
+    }
+
+    #[test]
+    fn family_must_be_a_string() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(PostgresHash)]
+            #[pgx(hash_family = integer_ops)]
+            struct Small(i16);
+        };
+        let err = OperatorFamilies::from_attributes(&input.attrs).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected `#[pgx(hash_family = \"family\")]`"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+/// The kind of the [`CustomEntity`](crate::sql_entity_graph::CustomEntity) of a
+/// `#[derive(PgCatalogTable)]`
+pub const CATALOG_TABLE_KIND: &str = "catalog_table";
+
+/// A column of a [`CatalogTableDefinition`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogTableColumn {
+    pub name: &'static str,
+    pub sql_type: String,
+    pub nullable: bool,
+    /// The SQL expression of its `DEFAULT`, if it has one
+    pub default: Option<&'static str>,
+}
+
+impl CatalogTableColumn {
+    /// The column's definition, as it's written in `CREATE TABLE` and `ALTER TABLE ... ADD COLUMN`
+    pub fn sql(&self) -> String {
+        let mut sql = format!("{} {}", quote_name(self.name), self.sql_type);
+        if !self.nullable {
+            sql.push_str(" NOT NULL");
+        }
+        if let Some(default) = self.default {
+            sql.push_str(&format!(" DEFAULT {}", default));
+        }
+        sql
+    }
+}
+
+/// The shape of a `#[derive(PgCatalogTable)]` table, which its entity renders once the SQL types
+/// of its columns are known
+#[derive(Debug, Clone)]
+pub struct CatalogTableDefinition {
+    pub name: &'static str,
+    pub columns: Vec<CatalogTableColumn>,
+    pub primary_key: Vec<&'static str>,
+    /// The `CREATE INDEX` statements of its indexes, with `{table}` in place of the table
+    pub indexes: Vec<&'static str>,
+    /// The `(columns) VALUES (values)` of the rows it's created with
+    pub rows: Vec<&'static str>,
+    /// The filter of `pg_extension_config_dump()`, if `pg_dump` dumps its rows, which is `""`
+    /// for all of them
+    pub dump: Option<&'static str>,
+}
+
+impl CatalogTableDefinition {
+    /// The table's `CREATE TABLE`, followed by the statements creating its indexes and rows, where
+    /// `prefix` is the schema it's in, with a trailing `.`
+    pub fn sql(&self, prefix: &str) -> String {
+        let table = format!("{}{}", prefix, quote_name(self.name));
+
+        let mut elements = self
+            .columns
+            .iter()
+            .map(CatalogTableColumn::sql)
+            .collect::<Vec<_>>();
+        if !self.primary_key.is_empty() {
+            let keys = self
+                .primary_key
+                .iter()
+                .map(|key| quote_name(key))
+                .collect::<Vec<_>>();
+            elements.push(format!("PRIMARY KEY ({})", keys.join(", ")));
+        }
+
+        let mut statements = vec![format!(
+            "CREATE TABLE {} (\n\t{}\n);",
+            table,
+            elements.join(",\n\t")
+        )];
+        for index in &self.indexes {
+            statements.push(index.replace("{table}", &table));
+        }
+        for row in &self.rows {
+            statements.push(format!(
+                "INSERT INTO {} {} ON CONFLICT DO NOTHING;",
+                table, row
+            ));
+        }
+        if let Some(filter) = self.dump {
+            statements.push(format!(
+                "SELECT pg_catalog.pg_extension_config_dump('{}', '{}');",
+                table.replace('\'', "''"),
+                filter.replace('\'', "''")
+            ));
+        }
+        statements.join("\n")
+    }
+}
+
+fn quote_name(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The statements which bring the table `previous` created, the SQL generated for an earlier
+/// version of the extension, up to `current`, its [`CatalogTableDefinition::sql`] now, or `None`
+/// if `previous` didn't create the table.
+///
+/// Columns, indexes, and rows which have been declared since are added.  A column that's been
+/// changed or removed, a changed primary key, or a new `NOT NULL` column without a `DEFAULT`, which
+/// the table's existing rows would have no value for, is left as it is, with a comment, as it
+/// needs a migration of its own.
+pub(crate) fn catalog_table_upgrade(current: &str, previous: &str) -> Option<Vec<String>> {
+    let header_end = current.find(" (\n")?;
+    let create = &current[..header_end + 3];
+    let table = create.strip_prefix("CREATE TABLE ")?.strip_suffix(" (\n")?;
+    let current_elements = table_elements(current, create)?;
+    let previous_elements = table_elements(previous, create)?;
+
+    let mut statements = vec![];
+    for element in &current_elements {
+        let name = match column_name(element) {
+            Some(name) => name,
+            None => {
+                if !previous_elements.contains(element) {
+                    statements.push(format!(
+                        "-- {} now has `{}`, which needs a migration of its own",
+                        table, element
+                    ));
+                }
+                continue;
+            }
+        };
+        match previous_elements
+            .iter()
+            .find(|previous| column_name(previous) == Some(name))
+        {
+            Some(previous) if previous == element => (),
+            Some(previous) => statements.push(format!(
+                "-- the column {} of {} was `{}`, and is now `{}`, which needs a migration of its own",
+                name, table, previous, element
+            )),
+            // the rows the table already has would have to be given a value
+            None if is_not_null_without_default(&element[name.len()..]) => {
+                statements.push(format!(
+                    "-- the column {} of {} is new, and `{}` without a DEFAULT, which needs a migration of its own",
+                    name, table, element
+                ))
+            }
+            None => statements.push(format!("ALTER TABLE {} ADD COLUMN {};", table, element)),
+        }
+    }
+    for previous in &previous_elements {
+        match column_name(previous) {
+            Some(name)
+                if !current_elements
+                    .iter()
+                    .any(|element| column_name(element) == Some(name)) =>
+            {
+                statements.push(format!(
+                    "-- the column {} of {} is no longer declared, and is left as it is",
+                    name, table
+                ))
+            }
+            Some(_) => (),
+            None if !current_elements.contains(previous) => statements.push(format!(
+                "-- {} no longer has `{}`, which is left as it is",
+                table, previous
+            )),
+            None => (),
+        }
+    }
+
+    // the rest are each a statement of their own, which is run again if `previous` didn't have it
+    let create_end = current.find("\n);")? + 3;
+    for statement in current[create_end..].split(";\n") {
+        let statement = statement.trim().trim_end_matches(';');
+        if statement.is_empty() {
+            continue;
+        }
+        let statement = format!("{};", statement);
+        if !previous.contains(&statement) {
+            statements.push(statement);
+        }
+    }
+    Some(statements)
+}
+
+/// The column definitions and constraints of the `CREATE TABLE` that starts with `create` in `sql`
+fn table_elements(sql: &str, create: &str) -> Option<Vec<String>> {
+    let start = sql.find(create)? + create.len();
+    let end = start + sql[start..].find("\n);")?;
+    Some(
+        sql[start..end]
+            .lines()
+            .map(|line| line.trim().trim_end_matches(',').to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+    )
+}
+
+/// Is the rest of a column's definition, after its name, `NOT NULL` without a `DEFAULT`?
+fn is_not_null_without_default(definition: &str) -> bool {
+    definition.contains(" NOT NULL") && !definition.contains(" DEFAULT ")
+}
+
+/// The quoted name of the column `element` defines, or `None` if it's a constraint
+fn column_name(element: &str) -> Option<&str> {
+    if !element.starts_with('"') {
+        return None;
+    }
+    let mut chars = element.char_indices().skip(1).peekable();
+    while let Some((position, c)) = chars.next() {
+        if c == '"' {
+            if matches!(chars.peek(), Some((_, '"'))) {
+                chars.next();
+            } else {
+                return Some(&element[..=position]);
+            }
+        }
+    }
+    None
+}
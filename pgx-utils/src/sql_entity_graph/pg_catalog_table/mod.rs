@@ -0,0 +1,633 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+pub mod entity;
+
+use crate::sql_entity_graph::{
+    pgx_attribute::{ArgValue, PgxArg, PgxAttribute},
+    Custom, PgIndex, PositioningRef, SqlName,
+};
+use entity::CATALOG_TABLE_KIND;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    DeriveInput, Ident, Lit, LitStr, Token, Type,
+};
+
+/// A parsed `#[derive(PgCatalogTable)]` item, a table the extension creates for its own metadata.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a
+/// `pgx::utils::sql_entity_graph::CustomEntity` of the kind `catalog_table`, which renders the
+/// table's `CREATE TABLE`, with its indexes and the rows it's created with.
+///
+/// The struct accepts `#[pgx(sql_name = "name", schema = "schema")]`, as [`SqlName`] describes,
+/// `#[pgx(dump)]`, or `#[pgx(dump = "WHERE ...")]` for some of its rows, to have `pg_dump` dump its
+/// rows, `#[index(...)]` with the arguments of a `pg_index!()` other than `on`, `#[row(column =
+/// value, ...)]`, and `#[requires(...)]`.  Fields accept `#[pgx(primary_key)]`,
+/// `#[pgx(name = "column")]`, `#[pgx(sql_type = "type")]`, and `#[pgx(default = "expression")]`.
+/// Fields of an `Option<T>` are nullable.
+///
+/// ```rust
+/// use syn::{parse_quote, DeriveInput};
+/// use quote::ToTokens;
+/// use pgx_utils::sql_entity_graph::PgCatalogTable;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let input: DeriveInput = parse_quote! {
+///     #[derive(PgCatalogTable)]
+///     #[pgx(sql_name = "job_queues", dump)]
+///     #[index(name = "job_queues_priority", keys = ["priority"])]
+///     #[row(name = "default", priority = 0)]
+///     struct JobQueue {
+///         #[pgx(primary_key)]
+///         name: String,
+///         priority: i32,
+///         description: Option<String>,
+///     }
+/// };
+/// let parsed = PgCatalogTable::from_derive_input(input)?;
+/// let sql_graph_entity_tokens = parsed.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgCatalogTable {
+    name: Ident,
+    sql_name: SqlName,
+    fields: Vec<CatalogTableField>,
+    indexes: Vec<PgIndex>,
+    rows: Vec<Vec<(String, String)>>,
+    dump: Option<LitStr>,
+    requires: Vec<PositioningRef>,
+}
+
+/// A field of a [`PgCatalogTable`], and the column it's stored in
+#[derive(Debug, Clone)]
+pub struct CatalogTableField {
+    pub ident: Ident,
+    pub column: String,
+    /// The field's type, or `T` of an `Option<T>`
+    pub ty: Type,
+    pub nullable: bool,
+    pub primary_key: bool,
+    pub sql_type: Option<LitStr>,
+    pub default: Option<LitStr>,
+}
+
+impl PgCatalogTable {
+    pub fn from_derive_input(derive_input: DeriveInput) -> Result<Self, syn::Error> {
+        let sql_name = SqlName::from_attributes(derive_input.attrs.as_slice())?;
+        if !derive_input.generics.params.is_empty() {
+            return Err(syn::Error::new(
+                derive_input.generics.span(),
+                "#[derive(PgCatalogTable)] can't be applied to a generic struct",
+            ));
+        }
+        let fields =
+            match derive_input.data {
+                syn::Data::Struct(syn::DataStruct {
+                    fields: syn::Fields::Named(fields),
+                    ..
+                }) => fields.named,
+                _ => return Err(syn::Error::new(
+                    derive_input.ident.span(),
+                    "#[derive(PgCatalogTable)] can only be applied to structs with named fields",
+                )),
+            };
+        let fields = fields
+            .into_iter()
+            .map(CatalogTableField::from_field)
+            .collect::<Result<Vec<_>, _>>()?;
+        if fields.is_empty() {
+            return Err(syn::Error::new(
+                derive_input.ident.span(),
+                "a catalog table needs at least one field",
+            ));
+        }
+        for (position, field) in fields.iter().enumerate() {
+            if fields[..position]
+                .iter()
+                .any(|other| other.column == field.column)
+            {
+                return Err(syn::Error::new(
+                    field.ident.span(),
+                    format!(
+                        "the column `{}` is already used by another field",
+                        field.column
+                    ),
+                ));
+            }
+        }
+
+        let mut dump = None;
+        let mut indexes = vec![];
+        let mut rows = vec![];
+        let mut requires = vec![];
+        for attr in &derive_input.attrs {
+            if attr.path.is_ident("pgx") {
+                for arg in attr.parse_args::<PgxAttribute>()?.args {
+                    match arg {
+                        PgxArg::Path(path) if path.is_ident("dump") => {
+                            dump = Some(LitStr::new("", path.span()))
+                        }
+                        PgxArg::NameValue(nv) if nv.path.is_ident("dump") => match nv.value {
+                            ArgValue::Lit(Lit::Str(filter)) => dump = Some(filter),
+                            _ => {
+                                return Err(syn::Error::new(
+                                    nv.path.span(),
+                                    "expected `#[pgx(dump = \"WHERE ...\")]`",
+                                ))
+                            }
+                        },
+                        // parsed by `SqlName`
+                        PgxArg::NameValue(nv)
+                            if nv.path.is_ident("sql_name") || nv.path.is_ident("schema") => {}
+                        _ => {
+                            return Err(syn::Error::new(
+                                attr.span(),
+                                "catalog tables only accept `#[pgx(sql_name = \"name\", schema = \"schema\", dump)]`",
+                            ))
+                        }
+                    }
+                }
+            } else if attr.path.is_ident("index") {
+                let args = attr.parse_args::<TokenStream2>()?;
+                let index = syn::parse2::<PgIndex>(quote! { on = "{table}", #args })?;
+                if !index.requires.is_empty() {
+                    return Err(syn::Error::new(
+                        attr.span(),
+                        "an index is created with its table, so give the table its `#[requires(...)]`",
+                    ));
+                }
+                indexes.push(index);
+            } else if attr.path.is_ident("row") {
+                let values =
+                    attr.parse_args_with(Punctuated::<RowValue, Token![,]>::parse_terminated)?;
+                rows.push(Self::row(&fields, attr.span(), values)?);
+            } else if attr.path.is_ident("requires") {
+                requires.extend(
+                    attr.parse_args_with(
+                        Punctuated::<PositioningRef, Token![,]>::parse_terminated,
+                    )?,
+                );
+            }
+        }
+
+        Ok(Self {
+            name: derive_input.ident,
+            sql_name,
+            fields,
+            indexes,
+            rows,
+            dump,
+            requires,
+        })
+    }
+
+    /// The columns and values of a `#[row(...)]`, in the order of the fields, which must give a
+    /// value to every `NOT NULL` column without a default
+    fn row(
+        fields: &[CatalogTableField],
+        span: Span,
+        values: Punctuated<RowValue, Token![,]>,
+    ) -> Result<Vec<(String, String)>, syn::Error> {
+        for value in &values {
+            if !fields.iter().any(|field| field.column == value.column) {
+                return Err(syn::Error::new(
+                    value.span,
+                    format!("there's no column `{}`", value.column),
+                ));
+            }
+        }
+        let mut row = vec![];
+        for field in fields {
+            match values.iter().find(|value| value.column == field.column) {
+                Some(value) => row.push((field.column.clone(), value.sql.clone())),
+                None if field.nullable || field.default.is_some() => (),
+                None => {
+                    return Err(syn::Error::new(
+                        span,
+                        format!("the row needs a value for the column `{}`", field.column),
+                    ))
+                }
+            }
+        }
+        Ok(row)
+    }
+
+    /// The table's SQL name and schema
+    pub fn sql_name(&self) -> &SqlName {
+        &self.sql_name
+    }
+
+    pub fn fields(&self) -> &[CatalogTableField] {
+        &self.fields
+    }
+
+    /// The `(columns) VALUES (values)` of each of its `#[row(...)]`s
+    pub fn rows_sql(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let (columns, values): (Vec<_>, Vec<_>) = row
+                    .iter()
+                    .map(|(column, value)| (quote_name(column), value.clone()))
+                    .unzip();
+                format!("({}) VALUES ({})", columns.join(", "), values.join(", "))
+            })
+            .collect()
+    }
+}
+
+fn quote_name(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+impl CatalogTableField {
+    fn from_field(field: syn::Field) -> Result<Self, syn::Error> {
+        let ident = field.ident.clone().expect("named fields have names");
+        let mut column = ident.unraw().to_string();
+        let mut primary_key = false;
+        let mut sql_type = None;
+        let mut default = None;
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+            for arg in attr.parse_args::<PgxAttribute>()?.args {
+                match arg {
+                    PgxArg::Path(path) if path.is_ident("primary_key") => primary_key = true,
+                    PgxArg::NameValue(nv) => match (&nv.value, nv.path.get_ident()) {
+                        (ArgValue::Lit(Lit::Str(value)), Some(name)) if name == "name" => {
+                            column = value.value()
+                        }
+                        (ArgValue::Lit(Lit::Str(value)), Some(name)) if name == "sql_type" => {
+                            sql_type = Some(value.clone())
+                        }
+                        (ArgValue::Lit(Lit::Str(value)), Some(name)) if name == "default" => {
+                            default = Some(value.clone())
+                        }
+                        _ => {
+                            return Err(syn::Error::new(
+                                nv.path.span(),
+                                "expected `#[pgx(name = \"column\", sql_type = \"type\", default = \"expression\")]`",
+                            ))
+                        }
+                    },
+                    _ => {
+                        return Err(syn::Error::new(
+                            attr.span(),
+                            "catalog table fields only accept `#[pgx(primary_key, name = \"column\", sql_type = \"type\", default = \"expression\")]`",
+                        ))
+                    }
+                }
+            }
+        }
+
+        let (ty, nullable) = match option_inner(&field.ty) {
+            Some(inner) => (inner.clone(), true),
+            None => (field.ty.clone(), false),
+        };
+        if primary_key && nullable {
+            return Err(syn::Error::new(
+                field.ty.span(),
+                "a primary key can't be an `Option`, as it can't be NULL",
+            ));
+        }
+        Ok(Self {
+            ident,
+            column,
+            ty,
+            nullable,
+            primary_key,
+            sql_type,
+            default,
+        })
+    }
+}
+
+/// The `T` of an `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+            match args.args.first()? {
+                syn::GenericArgument::Type(inner) => Some(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A `column = value` of a `#[row(...)]`, where the value is a literal, which may be negative, or
+/// `None`, for `NULL`
+struct RowValue {
+    column: String,
+    span: Span,
+    sql: String,
+}
+
+impl Parse for RowValue {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let ident = input.call(Ident::parse_any)?;
+        let _eq: Token![=] = input.parse()?;
+        let negative = input.parse::<Option<Token![-]>>()?.is_some();
+        let sql = if !negative && !input.peek(Lit) && input.peek(Ident::peek_any) {
+            let none = input.call(Ident::parse_any)?;
+            if none != "None" {
+                return Err(syn::Error::new(
+                    none.span(),
+                    "expected a literal, or `None` for NULL",
+                ));
+            }
+            "NULL".to_string()
+        } else {
+            match input.parse::<Lit>()? {
+                Lit::Int(value) => format!(
+                    "{}{}",
+                    if negative { "-" } else { "" },
+                    value.base10_digits()
+                ),
+                Lit::Float(value) => {
+                    format!(
+                        "{}{}",
+                        if negative { "-" } else { "" },
+                        value.base10_digits()
+                    )
+                }
+                Lit::Str(value) if !negative => format!("'{}'", value.value().replace('\'', "''")),
+                Lit::Bool(value) if !negative => value.value.to_string(),
+                other => {
+                    return Err(syn::Error::new(
+                        other.span(),
+                        "expected a string, number, or bool literal",
+                    ))
+                }
+            }
+        };
+        Ok(Self {
+            column: ident.unraw().to_string(),
+            span: ident.span(),
+            sql,
+        })
+    }
+}
+
+impl ToTokens for PgCatalogTable {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let name = &self.name;
+        let table_name = self.sql_name.name(name);
+        let to_sql_fn = Ident::new(
+            &format!("__pgx_catalog_table_sql_{}", name),
+            Span::call_site(),
+        );
+
+        let columns = self.fields.iter().map(|field| {
+            let column = &field.column;
+            let ty = &field.ty;
+            let nullable = field.nullable;
+            let sql_type = match &field.sql_type {
+                Some(sql_type) => quote! { String::from(#sql_type) },
+                None => quote! {
+                    context
+                        .rust_to_sql(
+                            core::any::TypeId::of::<#ty>(),
+                            stringify!(#ty),
+                            core::any::type_name::<#ty>(),
+                        )
+                        .ok_or_else(|| format!(
+                            "the SQL type of `{}` isn't known, so give the column `{}` a `#[pgx(sql_type = \"...\")]`",
+                            stringify!(#ty),
+                            #column,
+                        ))?
+                },
+            };
+            let default = match &field.default {
+                Some(default) => quote! { Some(#default) },
+                None => quote! { None },
+            };
+            quote! {
+                ::pgx::utils::sql_entity_graph::CatalogTableColumn {
+                    name: #column,
+                    sql_type: #sql_type,
+                    nullable: #nullable,
+                    default: #default,
+                }
+            }
+        });
+        let primary_key = self
+            .fields
+            .iter()
+            .filter(|field| field.primary_key)
+            .map(|field| &field.column);
+        let indexes = self.indexes.iter().map(PgIndex::sql);
+        let rows = self.rows_sql();
+        let dump = match &self.dump {
+            Some(filter) => quote! { Some(#filter) },
+            None => quote! { None },
+        };
+
+        tokens.append_all(quote! {
+            #[doc(hidden)]
+            pub fn #to_sql_fn(
+                entity: &::pgx::utils::sql_entity_graph::CustomEntity,
+                context: &::pgx::utils::sql_entity_graph::PgxSql,
+            ) -> std::result::Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+                let definition = ::pgx::utils::sql_entity_graph::CatalogTableDefinition {
+                    name: #table_name,
+                    columns: vec![#(#columns),*],
+                    primary_key: vec![#(#primary_key),*],
+                    indexes: vec![#(#indexes),*],
+                    rows: vec![#(#rows),*],
+                    dump: #dump,
+                };
+                let prefix = context.schema_prefix_for(&context.customs[entity]);
+                Ok(definition.sql(&prefix))
+            }
+        });
+
+        let mut custom = Custom::new(CATALOG_TABLE_KIND, name.clone(), quote! { "" })
+            .expect("catalog tables are a custom kind")
+            .set_to_sql(to_sql_fn.into())
+            .set_requires(self.requires.clone());
+        if let Some(schema) = &self.sql_name.schema {
+            custom = custom.set_schema(schema.clone());
+        }
+        custom.to_tokens(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{entity::catalog_table_upgrade, PgCatalogTable};
+    use crate::sql_entity_graph::{CatalogTableColumn, CatalogTableDefinition};
+    use quote::ToTokens;
+    use syn::{parse_quote, DeriveInput};
+
+    fn job_queues() -> DeriveInput {
+        parse_quote! {
+            #[derive(PgCatalogTable)]
+            #[pgx(sql_name = "job_queues", schema = "jobs", dump)]
+            #[index(name = "job_queues_priority", keys = ["priority"], where = "priority > 0")]
+            #[row(name = "default", priority = -1, paused = false)]
+            #[row(name = "it's", priority = 2, description = None)]
+            #[requires("create_jobs_schema")]
+            struct JobQueue {
+                #[pgx(primary_key)]
+                name: String,
+                priority: i32,
+                #[pgx(default = "false")]
+                paused: bool,
+                #[pgx(sql_type = "varchar(200)")]
+                description: Option<String>,
+            }
+        }
+    }
+
+    #[test]
+    fn catalog_table_attributes() {
+        let table = PgCatalogTable::from_derive_input(job_queues()).unwrap();
+        assert_eq!(table.sql_name().schema.as_ref().unwrap().value(), "jobs");
+        let fields = table.fields();
+        assert_eq!(fields.len(), 4);
+        assert!(fields[0].primary_key);
+        assert!(fields[3].nullable);
+        assert_eq!(fields[3].ty, parse_quote! { String });
+        assert_eq!(
+            table.rows_sql(),
+            vec![
+                "(\"name\", \"priority\", \"paused\") VALUES ('default', -1, false)",
+                "(\"name\", \"priority\", \"description\") VALUES ('it''s', 2, NULL)",
+            ]
+        );
+        assert_eq!(
+            table.indexes[0].sql(),
+            "CREATE INDEX \"job_queues_priority\" ON {table} (priority) WHERE priority > 0;"
+        );
+
+        let tokens = table.to_token_stream().to_string();
+        assert!(tokens.contains("__pgx_catalog_table_sql_JobQueue"));
+        assert!(tokens.contains("kind : \"catalog_table\""));
+        assert!(tokens.contains("schema : Some (\"jobs\")"));
+    }
+
+    #[test]
+    fn catalog_table_rows_are_checked() {
+        let missing_column: DeriveInput = parse_quote! {
+            #[derive(PgCatalogTable)]
+            #[row(name = "default", colour = "red")]
+            struct JobQueue { name: String }
+        };
+        assert!(PgCatalogTable::from_derive_input(missing_column).is_err());
+
+        let missing_value: DeriveInput = parse_quote! {
+            #[derive(PgCatalogTable)]
+            #[row(description = "nameless")]
+            struct JobQueue { name: String, description: Option<String> }
+        };
+        assert!(PgCatalogTable::from_derive_input(missing_value).is_err());
+
+        let nullable_key: DeriveInput = parse_quote! {
+            #[derive(PgCatalogTable)]
+            struct JobQueue { #[pgx(primary_key)] name: Option<String> }
+        };
+        assert!(PgCatalogTable::from_derive_input(nullable_key).is_err());
+    }
+
+    fn definition(columns: Vec<CatalogTableColumn>, rows: Vec<&'static str>) -> String {
+        CatalogTableDefinition {
+            name: "job_queues",
+            columns,
+            primary_key: vec!["name"],
+            indexes: vec!["CREATE INDEX \"job_queues_priority\" ON {table} (priority);"],
+            rows,
+            dump: Some(""),
+        }
+        .sql("jobs.")
+    }
+
+    fn column(name: &'static str, sql_type: &str, nullable: bool) -> CatalogTableColumn {
+        CatalogTableColumn {
+            name,
+            sql_type: sql_type.to_string(),
+            nullable,
+            default: None,
+        }
+    }
+
+    #[test]
+    fn catalog_table_sql() {
+        let sql = definition(
+            vec![
+                column("name", "TEXT", false),
+                column("priority", "INT", true),
+            ],
+            vec!["(\"name\") VALUES ('default')"],
+        );
+        assert_eq!(
+            sql,
+            "CREATE TABLE jobs.\"job_queues\" (\n\
+             \t\"name\" TEXT NOT NULL,\n\
+             \t\"priority\" INT,\n\
+             \tPRIMARY KEY (\"name\")\n\
+             );\n\
+             CREATE INDEX \"job_queues_priority\" ON jobs.\"job_queues\" (priority);\n\
+             INSERT INTO jobs.\"job_queues\" (\"name\") VALUES ('default') ON CONFLICT DO NOTHING;\n\
+             SELECT pg_catalog.pg_extension_config_dump('jobs.\"job_queues\"', '');"
+        );
+    }
+
+    #[test]
+    fn catalog_table_upgrades() {
+        let previous = format!(
+            "CREATE SCHEMA jobs;\n{}\n",
+            definition(
+                vec![column("name", "TEXT", false), column("owner", "TEXT", true)],
+                vec!["(\"name\") VALUES ('default')"],
+            )
+        );
+        let current = definition(
+            vec![
+                column("name", "TEXT", false),
+                CatalogTableColumn {
+                    default: Some("0"),
+                    ..column("priority", "INT", false)
+                },
+                column("queued_by", "TEXT", false),
+            ],
+            vec![
+                "(\"name\") VALUES ('default')",
+                "(\"name\") VALUES ('urgent')",
+            ],
+        );
+        assert_eq!(
+            catalog_table_upgrade(&current, &previous).unwrap(),
+            vec![
+                "ALTER TABLE jobs.\"job_queues\" ADD COLUMN \"priority\" INT NOT NULL DEFAULT 0;",
+                "-- the column \"queued_by\" of jobs.\"job_queues\" is new, and `\"queued_by\" TEXT NOT NULL` without a DEFAULT, which needs a migration of its own",
+                "-- the column \"owner\" of jobs.\"job_queues\" is no longer declared, and is left as it is",
+                "INSERT INTO jobs.\"job_queues\" (\"name\") VALUES ('urgent') ON CONFLICT DO NOTHING;",
+            ]
+        );
+        assert_eq!(
+            catalog_table_upgrade(&current, &current).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(catalog_table_upgrade(&current, "CREATE SCHEMA jobs;"), None);
+    }
+}
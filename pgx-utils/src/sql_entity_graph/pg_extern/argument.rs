@@ -86,7 +86,7 @@ impl PgExternArgument {
             _ => None,
         };
 
-        // We special case ignore `*mut pg_sys::FunctionCallInfoData`
+        // We special case ignore `*mut pg_sys::FunctionCallInfoData`, and `pgx::FcInfoContext`, which wraps it
         match true_ty {
             syn::Type::Reference(ref mut ty_ref) => {
                 if let Some(ref mut lifetime) = &mut ty_ref.lifetime {
@@ -106,8 +106,10 @@ impl PgExternArgument {
                         _ => (),
                     }
                 }
+                let is_fcinfo_context = matches!(segments.segments.last(), Some(segment) if segment.ident == "FcInfoContext");
                 if (saw_pg_sys && saw_functioncallinfobasedata)
                     || (saw_functioncallinfobasedata && segments.segments.len() == 1)
+                    || is_fcinfo_context
                 {
                     return Ok(None);
                 } else {
@@ -23,6 +23,7 @@ pub enum Attribute {
     Volatile,
     Raw,
     NoGuard,
+    Untrusted,
     ParallelSafe,
     ParallelUnsafe,
     ParallelRestricted,
@@ -30,6 +31,9 @@ pub enum Attribute {
     Schema(syn::LitStr),
     Name(syn::LitStr),
     Cost(syn::Expr),
+    Rows(syn::Expr),
+    Support(syn::Ident),
+    Inline(syn::LitStr),
     Requires(Punctuated<PositioningRef, Token![,]>),
     Sql(ToSqlConfig),
 }
@@ -43,6 +47,7 @@ impl Attribute {
             Attribute::Volatile => quote! { ::pgx::utils::ExternArgs::Volatile },
             Attribute::Raw => quote! { ::pgx::utils::ExternArgs::Raw },
             Attribute::NoGuard => quote! { ::pgx::utils::ExternArgs::NoGuard },
+            Attribute::Untrusted => quote! { ::pgx::utils::ExternArgs::Untrusted },
             Attribute::ParallelSafe => {
                 quote! { ::pgx::utils::ExternArgs::ParallelSafe }
             }
@@ -64,6 +69,15 @@ impl Attribute {
             Attribute::Cost(s) => {
                 quote! { ::pgx::utils::ExternArgs::Cost(format!("{}", #s)) }
             }
+            Attribute::Rows(s) => {
+                quote! { ::pgx::utils::ExternArgs::Rows(format!("{}", #s)) }
+            }
+            Attribute::Support(s) => {
+                quote! { ::pgx::utils::ExternArgs::Support(String::from(stringify!(#s))) }
+            }
+            Attribute::Inline(s) => {
+                quote! { ::pgx::utils::ExternArgs::Inline(String::from(#s)) }
+            }
             Attribute::Requires(items) => {
                 let items_iter = items
                     .iter()
@@ -88,6 +102,7 @@ impl ToTokens for Attribute {
             Attribute::Volatile => quote! { volatile },
             Attribute::Raw => quote! { raw },
             Attribute::NoGuard => quote! { no_guard },
+            Attribute::Untrusted => quote! { untrusted },
             Attribute::ParallelSafe => {
                 quote! { parallel_safe }
             }
@@ -109,6 +124,15 @@ impl ToTokens for Attribute {
             Attribute::Cost(s) => {
                 quote! { cost = #s }
             }
+            Attribute::Rows(s) => {
+                quote! { rows = #s }
+            }
+            Attribute::Support(s) => {
+                quote! { support = #s }
+            }
+            Attribute::Inline(s) => {
+                quote! { inline = #s }
+            }
             Attribute::Requires(items) => {
                 let items_iter = items
                     .iter()
@@ -135,6 +159,7 @@ impl Parse for Attribute {
             "volatile" => Self::Volatile,
             "raw" => Self::Raw,
             "no_guard" => Self::NoGuard,
+            "untrusted" => Self::Untrusted,
             "parallel_safe" => Self::ParallelSafe,
             "parallel_unsafe" => Self::ParallelUnsafe,
             "parallel_restricted" => Self::ParallelRestricted,
@@ -148,7 +173,7 @@ impl Parse for Attribute {
                 let literal: syn::LitStr = input.parse()?;
                 Attribute::Schema(literal)
             }
-            "name" => {
+            "name" | "sql_name" => {
                 let _eq: Token![=] = input.parse()?;
                 let literal: syn::LitStr = input.parse()?;
                 Self::Name(literal)
@@ -158,6 +183,21 @@ impl Parse for Attribute {
                 let literal: syn::Expr = input.parse()?;
                 Self::Cost(literal)
             }
+            "rows" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::Expr = input.parse()?;
+                Self::Rows(literal)
+            }
+            "support" => {
+                let _eq: Token![=] = input.parse()?;
+                let ident: syn::Ident = input.parse()?;
+                Self::Support(ident)
+            }
+            "inline" => {
+                let _eq: Token![=] = input.parse()?;
+                let literal: syn::LitStr = input.parse()?;
+                Self::Inline(literal)
+            }
             "requires" => {
                 let _eq: syn::token::Eq = input.parse()?;
                 let content;
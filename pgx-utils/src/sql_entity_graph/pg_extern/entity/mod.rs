@@ -49,7 +49,8 @@ impl Ord for PgExternEntity {
     fn cmp(&self, other: &Self) -> Ordering {
         self.file
             .cmp(other.file)
-            .then_with(|| self.file.cmp(other.file))
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.full_path.cmp(other.full_path))
     }
 }
 
@@ -105,22 +106,65 @@ impl ToSql for PgExternEntity {
             extern_attrs.push(ExternArgs::Strict);
         }
 
+        let support = self.extern_attrs.iter().find_map(|attr| match attr {
+            ExternArgs::Support(name) => Some(name),
+            _ => None,
+        });
+        let support = match support {
+            Some(name) => {
+                let (support_fn, graph_index) = context
+                    .graph
+                    .neighbors_undirected(self_index)
+                    .find_map(|neighbor| match &context.graph[neighbor] {
+                        SqlGraphEntity::Function(func)
+                            if func.name == name && func.module_path == self.module_path =>
+                        {
+                            Some((func, neighbor))
+                        }
+                        _ => None,
+                    })
+                    .ok_or_else(|| eyre!("Could not find support function `{}` in graph.", name))?;
+                format!(
+                    "SUPPORT {schema}\"{name}\"\n",
+                    schema = support_fn
+                        .schema
+                        .map(|schema| format!("{}.", schema))
+                        .unwrap_or_else(|| context.schema_prefix_for(&graph_index)),
+                    name = name,
+                )
+            }
+            None => String::new(),
+        };
+
+        // a function with an SQL equivalent is created in SQL, which the planner can inline
+        let body = match self.extern_attrs.iter().find_map(|attr| match attr {
+            ExternArgs::Inline(expression) => Some(expression),
+            _ => None,
+        }) {
+            Some(expression) => format!("LANGUAGE sql\nAS $$ SELECT {} $$;", expression),
+            None => format!(
+                "LANGUAGE c /* Rust */\nAS 'MODULE_PATHNAME', '{}_wrapper';",
+                self.unaliased_name
+            ),
+        };
+
         let fn_sql = format!("\
                                 CREATE OR REPLACE FUNCTION {schema}\"{name}\"({arguments}) {returns}\n\
                                 {extern_attrs}\
+                                {support}\
                                 {search_path}\
-                                LANGUAGE c /* Rust */\n\
-                                AS 'MODULE_PATHNAME', '{unaliased_name}_wrapper';\
+                                {body}\
                             ",
                              schema = self.schema.map(|schema| format!("{}.", schema)).unwrap_or_else(|| context.schema_prefix_for(&self_index)),
                              name = self.name,
-                             unaliased_name = self.unaliased_name,
+                             body = body,
                              arguments = if !self.fn_args.is_empty() {
                                  let mut args = Vec::new();
                                  for (idx, arg) in self.fn_args.iter().enumerate() {
                                      let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
                                          SqlGraphEntity::Type(ty) => ty.id_matches(&arg.ty_id),
                                          SqlGraphEntity::Enum(en) => en.id_matches(&arg.ty_id),
+                                         SqlGraphEntity::Domain(domain) => domain.id_matches(&arg.ty_id),
                                          SqlGraphEntity::BuiltinType(defined) => defined == &arg.full_path,
                                          _ => false,
                                      }).ok_or_else(|| eyre!("Could not find arg type in graph. Got: {:?}", arg))?;
@@ -152,6 +196,7 @@ impl ToSql for PgExternEntity {
                                      let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
                                          SqlGraphEntity::Type(ty) => ty.id_matches(&id),
                                          SqlGraphEntity::Enum(en) => en.id_matches(&id),
+                                         SqlGraphEntity::Domain(domain) => domain.id_matches(&id),
                                          SqlGraphEntity::BuiltinType(defined) => &*defined == full_path,
                                          _ => false,
                                      }).ok_or_else(|| eyre!("Could not find return type in graph."))?;
@@ -176,6 +221,7 @@ impl ToSql for PgExternEntity {
                                      let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
                                          SqlGraphEntity::Type(ty) => ty.id_matches(&id),
                                          SqlGraphEntity::Enum(en) => en.id_matches(&id),
+                                         SqlGraphEntity::Domain(domain) => domain.id_matches(&id),
                                          SqlGraphEntity::BuiltinType(defined) => defined == full_path,
                                          _ => false,
                                      }).ok_or_else(|| eyre!("Could not find return type in graph."))?;
@@ -202,6 +248,7 @@ impl ToSql for PgExternEntity {
                                          let graph_index = context.graph.neighbors_undirected(self_index).find(|neighbor| match &context.graph[*neighbor] {
                                              SqlGraphEntity::Type(ty) => ty.id_matches(&id),
                                              SqlGraphEntity::Enum(en) => en.id_matches(&id),
+                                             SqlGraphEntity::Domain(domain) => domain.id_matches(&id),
                                              SqlGraphEntity::BuiltinType(defined) => defined == ty_name,
                                              _ => false,
                                          });
@@ -232,6 +279,7 @@ impl ToSql for PgExternEntity {
                                  },
                                  PgExternReturnEntity::Trigger => String::from("RETURNS trigger"),
                              },
+                             support = support,
                              search_path = if let Some(search_path) = &self.search_path {
                                  let retval = format!("SET search_path TO {}", search_path.join(", "));
                                  retval + "\n"
@@ -316,6 +364,8 @@ impl ToSql for PgExternEntity {
                 .neighbors_undirected(self_index)
                 .find(|neighbor| match &context.graph[*neighbor] {
                     SqlGraphEntity::Type(ty) => ty.id_matches(&left_arg.ty_id),
+                    SqlGraphEntity::Domain(domain) => domain.id_matches(&left_arg.ty_id),
+                    SqlGraphEntity::BuiltinType(full_path) => *full_path == left_arg.full_path,
                     _ => false,
                 })
                 .ok_or_else(|| eyre!("Could not find left arg function in graph."))?;
@@ -328,6 +378,8 @@ impl ToSql for PgExternEntity {
                 .neighbors_undirected(self_index)
                 .find(|neighbor| match &context.graph[*neighbor] {
                     SqlGraphEntity::Type(ty) => ty.id_matches(&right_arg.ty_id),
+                    SqlGraphEntity::Domain(domain) => domain.id_matches(&right_arg.ty_id),
+                    SqlGraphEntity::BuiltinType(full_path) => *full_path == right_arg.full_path,
                     _ => false,
                 })
                 .ok_or_else(|| eyre!("Could not find right arg function in graph."))?;
@@ -335,14 +387,17 @@ impl ToSql for PgExternEntity {
             let operator_sql = format!("\n\n\
                                         -- {file}:{line}\n\
                                         -- {module_path}::{unaliased_name}\n\
-                                        CREATE OPERATOR {opname} (\n\
-                                            \tPROCEDURE=\"{name}\",\n\
+                                        CREATE OPERATOR {schema}{opname} (\n\
+                                            \tPROCEDURE={schema_prefix}\"{name}\",\n\
                                             \tLEFTARG={schema_prefix_left}{left_arg}, /* {left_name} */\n\
                                             \tRIGHTARG={schema_prefix_right}{right_arg}{maybe_comma} /* {right_name} */\n\
                                             {optionals}\
                                         );\
                                         ",
+                                        // like its function, the operator goes in the schema it's given, if any
+                                        schema = self.schema.map(|schema| format!("{}.", schema)).unwrap_or_default(),
                                         opname = op.opname.unwrap(),
+                                        schema_prefix = context.schema_prefix_for(&self_index),
                                         file = self.file,
                                         line = self.line,
                                         name = self.name,
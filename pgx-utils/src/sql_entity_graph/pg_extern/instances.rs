@@ -0,0 +1,518 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::{Ident, Spacing, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::Parser, punctuated::Punctuated, spanned::Spanned, FnArg, GenericArgument, GenericParam,
+    ImplItem, ItemFn, ItemImpl, Pat, PathArguments, ReturnType, Signature, Token, Type,
+    WherePredicate,
+};
+
+/// The attributes of a function which only mean something to the `#[pg_extern]` on it
+const PG_EXTERN_HELPERS: &[&str] = &[
+    "opname",
+    "commutator",
+    "negator",
+    "restrict",
+    "join",
+    "hashes",
+    "merges",
+    "search_path",
+];
+
+/// An identifier in a signature, and what it's replaced with, by itself and before a `::`
+type Substitution = (Ident, TokenStream2, TokenStream2);
+
+/// Expand a `#[pg_extern(instances(..))]` generic function into a `#[pg_extern]` function for
+/// each of its `instances`, which calls it with those types.
+///
+/// An instance of a function with one type parameter is a type, and of a function with more, a
+/// tuple of a type for each parameter, in order.  An instance is called the generic function's
+/// name and its types', like `sum_array_i64` for `sum_array::<i64>`, and gets the rest of the
+/// generic function's `#[pg_extern]` and other attributes.
+///
+/// ```rust
+/// use pgx_utils::sql_entity_graph::expand_generic_pg_extern;
+/// use quote::quote;
+///
+/// # fn main() -> Result<(), syn::Error> {
+/// let instances = expand_generic_pg_extern(
+///     quote! { immutable, instances(i32, f64) },
+///     syn::parse_quote! {
+///         fn double<T: std::ops::Add<Output = T> + Copy>(value: T) -> T {
+///             value + value
+///         }
+///     },
+/// )?;
+/// assert!(instances.to_string().contains("fn double_f64 (value : f64) -> f64"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn expand_generic_pg_extern(
+    attr: TokenStream2,
+    func: ItemFn,
+) -> Result<TokenStream2, syn::Error> {
+    let (instances, attr) = take_instances(attr)?;
+    if let Some(param) = func.sig.generics.const_params().next() {
+        return Err(syn::Error::new(
+            param.span(),
+            "#[pg_extern] can't create instances of a function with const parameters",
+        ));
+    }
+    let type_params = func
+        .sig
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    let instances = match instances {
+        Some(instances) => instances,
+        None => return Err(syn::Error::new(
+            func.sig.generics.span(),
+            "a generic #[pg_extern] function needs the types to create it for, as `instances(..)`",
+        )),
+    };
+
+    let mut generic = func.clone();
+    generic.attrs.retain(|attr| !is_pg_extern_helper(attr));
+    let mut tokens = generic.to_token_stream();
+
+    let fn_ident = &func.sig.ident;
+    for instance in instances {
+        let types = if type_params.len() == 1 {
+            vec![instance]
+        } else {
+            match instance {
+                Type::Tuple(tuple) if tuple.elems.len() == type_params.len() => {
+                    tuple.elems.into_iter().collect()
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        other.span(),
+                        format!(
+                            "expected a tuple of a type for each of {}",
+                            type_params
+                                .iter()
+                                .map(|param| format!("`{}`", param))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    ))
+                }
+            }
+        };
+        let suffixes = types
+            .iter()
+            .map(|ty| {
+                type_suffix(ty).ok_or_else(|| {
+                    syn::Error::new(
+                        ty.span(),
+                        "#[pg_extern] can't name an instance for this type",
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let substitutions = type_params
+            .iter()
+            .zip(types.iter())
+            .map(|(param, ty)| (param.clone(), ty.to_token_stream(), quote! { <#ty> }))
+            .collect::<Vec<_>>();
+
+        let mut sig = func.sig.clone();
+        sig.ident = format_ident!("{}_{}", fn_ident, suffixes.join("_"));
+        sig.generics.params = sig
+            .generics
+            .params
+            .into_iter()
+            .filter(|param| matches!(param, GenericParam::Lifetime(_)))
+            .collect();
+        if let Some(where_clause) = &mut sig.generics.where_clause {
+            where_clause.predicates = where_clause
+                .predicates
+                .clone()
+                .into_iter()
+                .filter(|predicate| matches!(predicate, WherePredicate::Lifetime(_)))
+                .collect();
+        }
+        let args = substitute_signature(&mut sig, &substitutions)?;
+
+        let attrs = &func.attrs;
+        let vis = &func.vis;
+        tokens.extend(quote! {
+            #[::pgx::pg_extern(#attr)]
+            #(#attrs)*
+            #vis #sig {
+                #fn_ident::<#(#types),*>(#(#args),*)
+            }
+        });
+    }
+    Ok(tokens)
+}
+
+/// Expand a `#[pg_extern]` inherent or trait `impl` into the `impl` and a `#[pg_extern]`
+/// function for each of its associated functions which is itself marked `#[pg_extern]`.
+///
+/// The function is called the type's name and the associated function's, like `point_distance`
+/// for `Point::distance`, and gets the associated function's `#[pg_extern]` and other attributes.
+/// A `self` receiver is its first argument, `this`.
+///
+/// ```rust
+/// use pgx_utils::sql_entity_graph::expand_impl_pg_extern;
+/// use quote::quote;
+///
+/// # fn main() -> Result<(), syn::Error> {
+/// let expanded = expand_impl_pg_extern(
+///     quote! {},
+///     syn::parse_quote! {
+///         impl Point {
+///             #[pg_extern(immutable)]
+///             fn distance(&self, other: Self) -> f64 {
+///                 ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+///             }
+///         }
+///     },
+/// )?;
+/// assert!(expanded
+///     .to_string()
+///     .contains("fn point_distance (this : Point , other : Point) -> f64"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn expand_impl_pg_extern(
+    attr: TokenStream2,
+    mut item: ItemImpl,
+) -> Result<TokenStream2, syn::Error> {
+    if !attr.is_empty() {
+        return Err(syn::Error::new(
+            attr.span(),
+            "#[pg_extern] on an `impl` takes no arguments, they're given to #[pg_extern] on its functions",
+        ));
+    }
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            item.generics.span(),
+            "#[pg_extern] can't create functions for a generic `impl`",
+        ));
+    }
+    let self_ty = item.self_ty.clone();
+    let qualified = match &item.trait_ {
+        Some((_, path, _)) => quote! { <#self_ty as #path> },
+        None => quote! { <#self_ty> },
+    };
+    let self_name = type_suffix(&self_ty).ok_or_else(|| {
+        syn::Error::new(
+            self_ty.span(),
+            "#[pg_extern] can't name functions for this type",
+        )
+    })?;
+    let substitutions = vec![(
+        Ident::new("Self", Span::call_site()),
+        self_ty.to_token_stream(),
+        qualified.clone(),
+    )];
+
+    let mut externs = TokenStream2::new();
+    for impl_item in &mut item.items {
+        let method = match impl_item {
+            ImplItem::Method(method) => method,
+            _ => continue,
+        };
+        let marker = match method.attrs.iter().position(is_pg_extern_marker) {
+            Some(position) => method.attrs.remove(position),
+            None => continue,
+        };
+        let attrs = method.attrs.clone();
+        method.attrs.retain(|attr| !is_pg_extern_helper(attr));
+
+        let mut sig = method.sig.clone();
+        sig.ident = format_ident!("{}_{}", self_name, method.sig.ident);
+        let mut receiver = None;
+        let mut mut_receiver = false;
+        if let Some(FnArg::Receiver(this)) = sig.inputs.first() {
+            mut_receiver = this.reference.is_some() && this.mutability.is_some();
+            receiver = Some(match (&this.reference, &this.mutability) {
+                (Some(_), Some(_)) => quote! { &mut this },
+                (Some(_), None) => quote! { &this },
+                (None, _) => quote! { this },
+            });
+            sig.inputs = std::iter::once(syn::parse_quote! { this: #self_ty })
+                .chain(sig.inputs.clone().into_iter().skip(1))
+                .collect();
+        }
+        let args = substitute_signature(&mut sig, &substitutions)?;
+        if mut_receiver {
+            // it's borrowed mutably, as `self` was
+            sig.inputs[0] = syn::parse_quote! { mut this: #self_ty };
+        }
+        let args = match receiver {
+            Some(pass) => std::iter::once(pass)
+                .chain(args.into_iter().skip(1).map(|arg| arg.to_token_stream()))
+                .collect::<Vec<_>>(),
+            None => args.iter().map(|arg| arg.to_token_stream()).collect(),
+        };
+
+        let marker_args = &marker.tokens;
+        let vis = &method.vis;
+        let method_ident = &method.sig.ident;
+        externs.extend(quote! {
+            #[::pgx::pg_extern #marker_args]
+            #(#attrs)*
+            #vis #sig {
+                #qualified::#method_ident(#(#args),*)
+            }
+        });
+    }
+    Ok(quote! {
+        #item
+        #externs
+    })
+}
+
+/// Split the `instances(..)` off of a `#[pg_extern]`'s arguments
+fn take_instances(
+    attr: TokenStream2,
+) -> Result<(Option<Punctuated<Type, Token![,]>>, TokenStream2), syn::Error> {
+    let mut instances = None;
+    let mut rest = Vec::new();
+    let mut arg = Vec::new();
+    for token in attr
+        .into_iter()
+        .chain(std::iter::once(TokenTree::Punct(proc_macro2::Punct::new(
+            ',',
+            Spacing::Alone,
+        ))))
+    {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == ',' => {
+                match arg.as_slice() {
+                    [TokenTree::Ident(ident), TokenTree::Group(group)] if ident == "instances" => {
+                        let parser = Punctuated::<Type, Token![,]>::parse_terminated;
+                        instances = Some(parser.parse2(group.stream())?);
+                    }
+                    [] => (),
+                    _ => rest.push(arg.iter().cloned().collect::<TokenStream2>()),
+                }
+                arg.clear();
+            }
+            token => arg.push(token),
+        }
+    }
+    Ok((instances, quote! { #(#rest),* }))
+}
+
+/// Substitute the types in a signature, and return the names of its arguments
+fn substitute_signature(
+    sig: &mut Signature,
+    substitutions: &[Substitution],
+) -> Result<Vec<Ident>, syn::Error> {
+    let mut args = Vec::new();
+    for input in sig.inputs.iter_mut() {
+        let pat_type = match input {
+            FnArg::Typed(pat_type) => pat_type,
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new(
+                    receiver.span(),
+                    "#[pg_extern] can't take `self` here",
+                ))
+            }
+        };
+        match &mut *pat_type.pat {
+            Pat::Ident(pat_ident) if pat_ident.ident != "self" => {
+                pat_ident.mutability = None;
+                args.push(pat_ident.ident.clone());
+            }
+            pat => {
+                return Err(syn::Error::new(
+                    pat.span(),
+                    "#[pg_extern] needs a name for this argument",
+                ))
+            }
+        }
+        *pat_type.ty = syn::parse2(substitute(pat_type.ty.to_token_stream(), substitutions))?;
+    }
+    if let ReturnType::Type(_, ty) = &mut sig.output {
+        **ty = syn::parse2(substitute(ty.to_token_stream(), substitutions))?;
+    }
+    Ok(args)
+}
+
+/// Replace the identifiers in `tokens`, including inside macros like `name!()` and `default!()`
+fn substitute(tokens: TokenStream2, substitutions: &[Substitution]) -> TokenStream2 {
+    let mut substituted = TokenStream2::new();
+    let mut tokens = tokens.into_iter().peekable();
+    let mut after_quote = false;
+    while let Some(token) = tokens.next() {
+        let next_after_quote = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '\'');
+        match token {
+            TokenTree::Group(group) => {
+                let mut replaced = proc_macro2::Group::new(
+                    group.delimiter(),
+                    substitute(group.stream(), substitutions),
+                );
+                replaced.set_span(group.span());
+                substituted.extend(std::iter::once(TokenTree::Group(replaced)));
+            }
+            TokenTree::Ident(ident) if !after_quote => {
+                match substitutions.iter().find(|(param, _, _)| *param == ident) {
+                    Some((_, ty, qualified)) => {
+                        let before_path = matches!(
+                            tokens.peek(),
+                            Some(TokenTree::Punct(punct)) if punct.as_char() == ':'
+                        );
+                        substituted.extend(if before_path {
+                            qualified.clone()
+                        } else {
+                            ty.clone()
+                        });
+                    }
+                    None => substituted.extend(std::iter::once(TokenTree::Ident(ident))),
+                }
+            }
+            token => substituted.extend(std::iter::once(token)),
+        }
+        after_quote = next_after_quote;
+    }
+    substituted
+}
+
+/// A name for a type in an identifier, like `i32` or `vec_string` for `Vec<String>`
+fn type_suffix(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            let mut parts = vec![snake_case(&segment.ident.to_string())];
+            if let PathArguments::AngleBracketed(arguments) = &segment.arguments {
+                for argument in &arguments.args {
+                    if let GenericArgument::Type(ty) = argument {
+                        parts.push(type_suffix(ty)?);
+                    }
+                }
+            }
+            Some(parts.join("_"))
+        }
+        Type::Reference(reference) => type_suffix(&reference.elem),
+        Type::Slice(slice) => Some(format!("{}_slice", type_suffix(&slice.elem)?)),
+        Type::Array(array) => Some(format!("{}_array", type_suffix(&array.elem)?)),
+        Type::Tuple(tuple) => Some(
+            tuple
+                .elems
+                .iter()
+                .map(type_suffix)
+                .collect::<Option<Vec<_>>>()?
+                .join("_"),
+        ),
+        Type::Paren(paren) => type_suffix(&paren.elem),
+        Type::Group(group) => type_suffix(&group.elem),
+        _ => None,
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() && previous_lower {
+            snake.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+fn is_pg_extern_marker(attr: &syn::Attribute) -> bool {
+    attr.path
+        .segments
+        .last()
+        .map(|segment| segment.ident == "pg_extern" || segment.ident == "pg_operator")
+        .unwrap_or_default()
+}
+
+fn is_pg_extern_helper(attr: &syn::Attribute) -> bool {
+    attr.path
+        .segments
+        .last()
+        .map(|segment| PG_EXTERN_HELPERS.contains(&segment.ident.to_string().as_str()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_generic_pg_extern, expand_impl_pg_extern};
+    use quote::quote;
+    use syn::parse_quote;
+
+    #[test]
+    fn generic_instances() {
+        let expanded = expand_generic_pg_extern(
+            quote! { immutable, instances((i32, Option<i64>), (f64, String)), name = "pick" },
+            parse_quote! {
+                #[opname(<)]
+                fn pick<'a, A: Copy, B>(mut a: A, b: default!(B, "NULL"), label: &'a str) -> Vec<(A, B)>
+                where
+                    A: Clone,
+                {
+                    todo!()
+                }
+            },
+        )
+        .unwrap()
+        .to_string();
+        assert!(expanded.contains(
+            "# [:: pgx :: pg_extern (immutable , name = \"pick\")] # [opname (<)] \
+             fn pick_i32_option_i64 < 'a > (a : i32 , b : default ! (Option < i64 > , \"NULL\") \
+             , label : & 'a str) -> Vec < (i32 , Option < i64 >) > { \
+             pick :: < i32 , Option < i64 > > (a , b , label) }"
+        ));
+        assert!(expanded.contains("fn pick_f64_string"));
+        // the generic function itself is left as it is, without the operator
+        assert!(expanded.starts_with("fn pick < 'a , A : Copy , B >"));
+    }
+
+    #[test]
+    fn generic_needs_instances() {
+        let err = expand_generic_pg_extern(quote! { immutable }, parse_quote! { fn f<T>(t: T) {} })
+            .unwrap_err();
+        assert!(err.to_string().contains("instances(..)"));
+        let err = expand_generic_pg_extern(
+            quote! { instances(i32) },
+            parse_quote! { fn f<A, B>(a: A, b: B) {} },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a tuple of a type for each of `A`, `B`"
+        );
+    }
+
+    #[test]
+    fn trait_impl_functions() {
+        let expanded = expand_impl_pg_extern(
+            quote! {},
+            parse_quote! {
+                impl Shape for UnitCircle {
+                    #[pg_extern]
+                    fn scaled(self, by: f64) -> Self::Scaled { todo!() }
+                    fn helper() {}
+                }
+            },
+        )
+        .unwrap()
+        .to_string();
+        assert!(expanded.starts_with(
+            "impl Shape for UnitCircle { fn scaled (self , by : f64) -> Self :: Scaled"
+        ));
+        assert!(expanded.contains(
+            "# [:: pgx :: pg_extern] fn unit_circle_scaled (this : UnitCircle , by : f64) \
+             -> < UnitCircle as Shape > :: Scaled { \
+             < UnitCircle as Shape > :: scaled (this , by) }"
+        ));
+        assert!(!expanded.contains("unit_circle_helper"));
+    }
+}
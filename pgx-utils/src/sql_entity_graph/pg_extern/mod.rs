@@ -9,11 +9,14 @@ Use of this source code is governed by the MIT license that can be found in the
 mod argument;
 mod attribute;
 pub mod entity;
+mod instances;
 mod operator;
 mod returning;
 mod search_path;
+mod volatility;
 
 pub use argument::PgExternArgument;
+pub use instances::{expand_generic_pg_extern, expand_impl_pg_extern};
 pub use operator::PgOperator;
 pub use returning::NameMacro;
 
@@ -22,10 +25,11 @@ use attribute::Attribute;
 use operator::{PgxOperatorAttributeWithIdent, PgxOperatorOpName};
 use returning::Returning;
 use search_path::SearchPathList;
+use volatility::volatile_calls;
 
 use eyre::WrapErr;
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
-use quote::{quote, ToTokens, TokenStreamExt};
+use quote::{format_ident, quote, quote_spanned, ToTokens, TokenStreamExt};
 use std::convert::TryFrom;
 use syn::{
     parse::{Parse, ParseStream, Parser},
@@ -83,6 +87,58 @@ impl PgExtern {
         self.attrs.as_slice()
     }
 
+    /// Whether the function is `VOLATILE`, as it is unless it's declared `immutable` or `stable`
+    pub fn is_volatile(&self) -> bool {
+        !self
+            .attrs
+            .iter()
+            .any(|a| matches!(a, Attribute::Immutable | Attribute::Stable))
+    }
+
+    /// A deprecation warning at each call an `immutable` function makes that gives a different
+    /// result as the database or the session changes, such as one that runs SQL through SPI, since
+    /// Postgres may call it only once, as it plans a query, and use the result from then on.  An
+    /// `#[allow(deprecated)]` on the function silences them
+    fn volatility_warnings(&self) -> TokenStream2 {
+        if !self.attrs.iter().any(|a| matches!(a, Attribute::Immutable)) {
+            return TokenStream2::new();
+        }
+        let ident = &self.func.sig.ident;
+        let cfg_attrs = crate::cfg_attrs(&self.func.attrs);
+        let allow_attrs = self
+            .func
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("allow"))
+            .collect::<Vec<_>>();
+        let mut tokens = TokenStream2::new();
+        for (index, call) in volatile_calls(&self.func).into_iter().enumerate() {
+            let warning = format_ident!(
+                "__pgx_volatility_warning_{}_{}",
+                ident,
+                index,
+                span = call.span
+            );
+            let note = format!(
+                "`{}` is declared `immutable`, but `{}` {}, so it isn't.  Postgres may call an \
+                 immutable function once, as it plans a query, and use that result from then on, \
+                 so declare it `stable` or `volatile`",
+                ident, call.callee, call.reason
+            );
+            tokens.append_all(quote_spanned! {call.span=>
+                #(#cfg_attrs)*
+                #[allow(non_upper_case_globals)]
+                #[deprecated(note = #note)]
+                #[doc(hidden)]
+                const #warning: () = ();
+                #(#cfg_attrs)*
+                #(#allow_attrs)*
+                const _: () = #warning;
+            });
+        }
+        tokens
+    }
+
     fn overridden(&self) -> Option<syn::LitStr> {
         let mut span = None;
         let mut retval = None;
@@ -173,9 +229,10 @@ impl PgExtern {
             .attrs
             .iter()
             .find(|f| {
+                // by its last segment, as it may be a path like `pgx::search_path`
                 f.path
                     .segments
-                    .first()
+                    .last()
                     .map(|f| f.ident == Ident::new("search_path", Span::call_site()))
                     .unwrap_or_default()
             })
@@ -217,6 +274,35 @@ impl PgExtern {
 
         let func = syn::parse2::<syn::ItemFn>(item)?;
 
+        // Postgres only estimates how many rows a function returns when it returns a set
+        let returns_set = || {
+            matches!(
+                Returning::try_from(&func.sig.output),
+                Ok(Returning::SetOf(_) | Returning::Iterated(_))
+            )
+        };
+        if attrs.iter().any(|attr| matches!(attr, Attribute::Rows(_))) && !returns_set() {
+            return Err(syn::Error::new(
+                func.sig.ident.span(),
+                "`rows` is only for functions that return a set",
+            ));
+        }
+        // an SQL function is only inlined as an expression when it returns a single value
+        let returns_value = matches!(
+            Returning::try_from(&func.sig.output),
+            Ok(Returning::Type(_))
+        );
+        if attrs
+            .iter()
+            .any(|attr| matches!(attr, Attribute::Inline(_)))
+            && !returns_value
+        {
+            return Err(syn::Error::new(
+                func.sig.ident.span(),
+                "`inline` is only for functions that return a single value",
+            ));
+        }
+
         if let Some(ref mut to_sql_config) = to_sql_config {
             if let Some(ref mut content) = to_sql_config.content {
                 let value = content.value();
@@ -269,9 +355,12 @@ impl ToTokens for PgExtern {
             }
         };
 
+        let cfg_attrs = crate::cfg_attrs(&self.func.attrs);
+
         let sql_graph_entity_fn_name =
             syn::Ident::new(&format!("__pgx_internals_fn_{}", ident), Span::call_site());
         let inv = quote! {
+            #(#cfg_attrs)*
             #[no_mangle]
             #[doc(hidden)]
             pub extern "C" fn  #sql_graph_entity_fn_name() -> ::pgx::utils::sql_entity_graph::SqlGraphEntity {
@@ -298,6 +387,7 @@ impl ToTokens for PgExtern {
             }
         };
         tokens.append_all(inv);
+        tokens.append_all(self.volatility_warnings());
     }
 }
 
@@ -327,3 +417,94 @@ impl Parse for PgExtern {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PgExtern;
+    use quote::{quote, ToTokens};
+
+    #[test]
+    fn rows_and_support() {
+        let parsed = PgExtern::new(
+            quote! { rows = 10, support = numbers_support },
+            quote! {
+                fn numbers(n: i32) -> impl Iterator<Item = i32> { 0..n }
+            },
+        )
+        .unwrap();
+        let tokens = parsed.to_token_stream().to_string();
+        assert!(tokens.contains("ExternArgs :: Rows"));
+        assert!(tokens
+            .contains("ExternArgs :: Support (String :: from (stringify ! (numbers_support)))"));
+    }
+
+    #[test]
+    fn rows_needs_a_set() {
+        let err = PgExtern::new(
+            quote! { rows = 10 },
+            quote! {
+                fn number() -> i32 { 1 }
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`rows` is only for functions that return a set"
+        );
+    }
+
+    #[test]
+    fn inline() {
+        let parsed = PgExtern::new(
+            quote! { immutable, inline = "a + b" },
+            quote! {
+                fn add(a: i32, b: i32) -> i32 { a + b }
+            },
+        )
+        .unwrap();
+        let tokens = parsed.to_token_stream().to_string();
+        assert!(tokens.contains(r#"ExternArgs :: Inline (String :: from ("a + b"))"#));
+
+        let err = PgExtern::new(
+            quote! { inline = "generate_series(1, n)" },
+            quote! {
+                fn numbers(n: i32) -> impl Iterator<Item = i32> { 0..n }
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`inline` is only for functions that return a single value"
+        );
+    }
+
+    #[test]
+    fn volatility_warnings() {
+        let parsed = PgExtern::new(
+            quote! { immutable },
+            quote! {
+                fn title(id: i64) -> Option<String> {
+                    Spi::get_one_with_args("SELECT title FROM t WHERE id = $1", vec![])
+                }
+            },
+        )
+        .unwrap();
+        let tokens = parsed.to_token_stream().to_string();
+        assert!(tokens.contains("const __pgx_volatility_warning_title_0 : () = () ;"));
+        assert!(tokens.contains(
+            "`title` is declared `immutable`, but `Spi::get_one_with_args` runs SQL through SPI"
+        ));
+
+        let parsed = PgExtern::new(
+            quote! { stable },
+            quote! {
+                fn title(id: i64) -> Option<String> {
+                    Spi::get_one_with_args("SELECT title FROM t WHERE id = $1", vec![])
+                }
+            },
+        )
+        .unwrap();
+        let tokens = parsed.to_token_stream().to_string();
+        assert!(!tokens.contains("__pgx_volatility_warning"));
+    }
+}
@@ -0,0 +1,157 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use proc_macro2::Span;
+use syn::{spanned::Spanned, visit::Visit};
+
+/// A call in the body of an `immutable` function that gives a different result as the database,
+/// the session, or the time changes, which an `immutable` function mustn't
+#[derive(Debug, Clone)]
+pub struct VolatileCall {
+    pub span: Span,
+    /// What's called, as it's written, such as `Spi::get_one`
+    pub callee: String,
+    /// Why it isn't immutable, such as "runs SQL through SPI"
+    pub reason: &'static str,
+}
+
+/// The calls `func`'s body makes that an `immutable` function mustn't, each only the first time
+/// it's made.  These are only those apparent from the body itself, not those of the functions it
+/// calls
+pub fn volatile_calls(func: &syn::ItemFn) -> Vec<VolatileCall> {
+    let mut visitor = VolatileCalls::default();
+    visitor.visit_block(&func.block);
+    visitor.calls
+}
+
+#[derive(Default)]
+struct VolatileCalls {
+    calls: Vec<VolatileCall>,
+}
+
+impl VolatileCalls {
+    fn push(&mut self, span: Span, callee: String, reason: &'static str) {
+        if !self.calls.iter().any(|call| call.callee == callee) {
+            self.calls.push(VolatileCall {
+                span,
+                callee,
+                reason,
+            });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for VolatileCalls {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        let segments = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        if let Some(reason) = path_reason(&segments) {
+            self.push(path.span(), segments.join("::"), reason);
+        }
+        syn::visit::visit_path(self, path);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if call.method == "nextval" || call.method == "currval" {
+            self.push(
+                call.method.span(),
+                format!(".{}()", call.method),
+                "uses a sequence",
+            );
+        }
+        syn::visit::visit_expr_method_call(self, call);
+    }
+}
+
+fn path_reason(segments: &[String]) -> Option<&'static str> {
+    const SPI: &str = "runs SQL through SPI";
+    const CLOCK: &str = "reads the clock";
+    const RANDOM: &str = "is random";
+
+    let last = segments.last()?.as_str();
+    let qualifier = match segments.len() {
+        0 | 1 => None,
+        len => Some(segments[len - 2].as_str()),
+    };
+    if segments
+        .iter()
+        .any(|segment| segment == "Spi" || segment == "SpiClient")
+        || last.starts_with("SPI_")
+    {
+        return Some(SPI);
+    }
+    let reason = match (qualifier, last) {
+        (
+            _,
+            "GetCurrentTimestamp"
+            | "GetCurrentTransactionStartTimestamp"
+            | "GetCurrentStatementStartTimestamp"
+            | "GetSQLCurrentTimestamp"
+            | "GetSQLLocalTimestamp",
+        ) => CLOCK,
+        (Some("SystemTime" | "Instant" | "Utc" | "Local"), "now") => CLOCK,
+        (_, "pg_prng_uint64" | "pg_prng_double" | "thread_rng") => RANDOM,
+        (Some("rand"), "random") | (Some("pg_sys"), "random") => RANDOM,
+        (_, "nextval_oid" | "currval_oid") => "uses a sequence",
+        (_, "current_user_id" | "session_user_id" | "GetUserId" | "GetSessionUserId") => {
+            "depends on the current role"
+        }
+        (_, "GetConfigOption" | "GetConfigOptionByName") => "reads a setting",
+        (Some("env"), "var" | "var_os" | "vars") => "reads the environment",
+        (Some("fs"), _) => "reads files",
+        _ => return None,
+    };
+    Some(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::volatile_calls;
+    use syn::parse_quote;
+
+    #[test]
+    fn finds_volatile_calls() {
+        let func = parse_quote! {
+            fn lookup(id: i64) -> Option<String> {
+                let started = std::time::SystemTime::now();
+                let title = Spi::get_one_with_args("SELECT title FROM t WHERE id = $1", vec![]);
+                let again = Spi::get_one_with_args("SELECT title FROM t WHERE id = $1", vec![]);
+                let next = PgSequence::from_name("s").unwrap().nextval();
+                unsafe { pg_sys::SPI_connect() };
+                title
+            }
+        };
+        let calls = volatile_calls(&func)
+            .into_iter()
+            .map(|call| (call.callee, call.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            calls,
+            vec![
+                ("std::time::SystemTime::now".to_string(), "reads the clock"),
+                ("Spi::get_one_with_args".to_string(), "runs SQL through SPI"),
+                (".nextval()".to_string(), "uses a sequence"),
+                ("pg_sys::SPI_connect".to_string(), "runs SQL through SPI"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_immutable_code() {
+        let func = parse_quote! {
+            fn add(a: i32, b: i32, now: i32, random: i32) -> i32 {
+                let var = a.checked_add(b).unwrap_or(i32::MAX);
+                var + now + random + std::cmp::max(a, b)
+            }
+        };
+        assert!(volatile_calls(&func).is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    extension_sql::ExtensionSqlAttribute, positioning_ref::PositioningRef, ExtensionSql,
+};
+
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::ToTokens;
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    LitStr, Token,
+};
+
+/// A parsed `pg_generated_column!()` item.
+///
+/// It should be used with [`syn::parse::Parse`] functions.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a `pgx::utils::sql_entity_graph::ExtensionSqlEntity`
+/// containing the `ALTER TABLE ... ADD COLUMN` statement.
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::PgGeneratedColumn;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let parsed: Macro = parse_quote! {
+///     pg_generated_column!(
+///         name = "tokens",
+///         on = "documents",
+///         type = "tsvector",
+///         expression = "document_tokens(body)",
+///         requires = ["create_documents", document_tokens],
+///     )
+/// };
+/// let inner_tokens = parsed.tokens;
+/// let inner: PgGeneratedColumn = parse_quote! {
+///     #inner_tokens
+/// };
+/// assert_eq!(
+///     inner.sql(),
+///     "ALTER TABLE documents ADD COLUMN \"tokens\" tsvector GENERATED ALWAYS AS (document_tokens(body)) STORED;"
+/// );
+/// let sql_graph_entity_tokens = inner.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgGeneratedColumn {
+    pub name: LitStr,
+    pub table: LitStr,
+    pub sql_type: LitStr,
+    pub expression: LitStr,
+    pub requires: Punctuated<PositioningRef, Token![,]>,
+}
+
+impl PgGeneratedColumn {
+    /// The `ALTER TABLE ... ADD COLUMN` statement
+    pub fn sql(&self) -> String {
+        format!(
+            "ALTER TABLE {} ADD COLUMN \"{}\" {} GENERATED ALWAYS AS ({}) STORED;",
+            self.table.value(),
+            self.name.value().replace('"', "\"\""),
+            self.sql_type.value(),
+            self.expression.value()
+        )
+    }
+
+    /// The name of the generated `extension_sql!()` entity, which other entities can `require`
+    pub fn entity_name(&self) -> String {
+        format!(
+            "generated_column_{}_on_{}",
+            self.name.value(),
+            self.table.value()
+        )
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+    }
+}
+
+impl Parse for PgGeneratedColumn {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let mut name = None;
+        let mut table = None;
+        let mut sql_type = None;
+        let mut expression = None;
+        let mut requires = Punctuated::new();
+
+        while !input.is_empty() {
+            // `type` is a keyword
+            let ident = input.call(Ident::parse_any)?;
+            match ident.to_string().as_str() {
+                "name" => {
+                    let _eq: Token![=] = input.parse()?;
+                    name = Some(input.parse()?);
+                }
+                "on" => {
+                    let _eq: Token![=] = input.parse()?;
+                    table = Some(input.parse()?);
+                }
+                "type" => {
+                    let _eq: Token![=] = input.parse()?;
+                    sql_type = Some(input.parse()?);
+                }
+                "expression" => {
+                    let _eq: Token![=] = input.parse()?;
+                    expression = Some(input.parse()?);
+                }
+                "requires" => {
+                    let _eq: Token![=] = input.parse()?;
+                    let content;
+                    let _bracket = syn::bracketed!(content in input);
+                    requires = content.parse_terminated(PositioningRef::parse)?;
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("Unknown pg_generated_column attribute: {}", other),
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                let _comma: Token![,] = input.parse()?;
+            }
+        }
+
+        let expected = |attribute: &str| {
+            syn::Error::new(input.span(), format!("expected `{}` to be set", attribute))
+        };
+        Ok(Self {
+            name: name.ok_or_else(|| expected("name"))?,
+            table: table.ok_or_else(|| expected("on"))?,
+            sql_type: sql_type.ok_or_else(|| expected("type"))?,
+            expression: expression.ok_or_else(|| expected("expression"))?,
+            requires,
+        })
+    }
+}
+
+impl ToTokens for PgGeneratedColumn {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let mut attrs = Punctuated::new();
+        attrs.push(ExtensionSqlAttribute::Requires(self.requires.clone()));
+        ExtensionSql {
+            sql: LitStr::new(&self.sql(), Span::call_site()),
+            name: LitStr::new(&self.entity_name(), Span::call_site()),
+            attrs,
+        }
+        .to_tokens(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PgGeneratedColumn;
+    use syn::parse_quote;
+
+    #[test]
+    fn generated_column() {
+        let column: PgGeneratedColumn = parse_quote! {
+            name = "Total",
+            on = "app.orders",
+            type = "numeric",
+            expression = "order_total(items)",
+            requires = ["create_orders", order_total],
+        };
+        assert_eq!(
+            column.sql(),
+            "ALTER TABLE app.orders ADD COLUMN \"Total\" numeric GENERATED ALWAYS AS (order_total(items)) STORED;"
+        );
+        assert_eq!(column.entity_name(), "generated_column_Total_on_app_orders");
+        assert_eq!(column.requires.len(), 2);
+    }
+
+    #[test]
+    fn generated_column_requires_an_expression() {
+        let column = syn::parse2::<PgGeneratedColumn>(quote::quote! {
+            name = "total", on = "orders", type = "numeric"
+        });
+        assert!(column.is_err());
+    }
+}
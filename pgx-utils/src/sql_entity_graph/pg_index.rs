@@ -0,0 +1,298 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    extension_sql::ExtensionSqlAttribute, positioning_ref::PositioningRef, ExtensionSql,
+};
+
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::ToTokens;
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    LitStr, Token,
+};
+
+/// A parsed `pg_index!()` item.
+///
+/// It should be used with [`syn::parse::Parse`] functions.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a `pgx::utils::sql_entity_graph::ExtensionSqlEntity`
+/// containing the `CREATE INDEX` statement.
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::PgIndex;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let parsed: Macro = parse_quote! {
+///     pg_index!(
+///         name = "documents_tokens",
+///         on = "documents",
+///         using = gin,
+///         keys = [("document_tokens(body)", "document_tokens_ops")],
+///         requires = ["create_documents", document_tokens, "document_tokens_ops"],
+///     )
+/// };
+/// let inner_tokens = parsed.tokens;
+/// let inner: PgIndex = parse_quote! {
+///     #inner_tokens
+/// };
+/// assert_eq!(
+///     inner.sql(),
+///     "CREATE INDEX \"documents_tokens\" ON documents USING gin ((document_tokens(body)) document_tokens_ops);"
+/// );
+/// let sql_graph_entity_tokens = inner.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgIndex {
+    pub name: LitStr,
+    pub table: LitStr,
+    pub method: Option<Ident>,
+    pub unique: bool,
+    pub keys: Vec<IndexKey>,
+    pub include: Vec<LitStr>,
+    pub predicate: Option<LitStr>,
+    pub requires: Punctuated<PositioningRef, Token![,]>,
+}
+
+/// One of the keys of a [`PgIndex`], a column or an expression, with the operator class to index
+/// it with, if it isn't its type's default for the index's method
+#[derive(Debug, Clone)]
+pub struct IndexKey {
+    pub key: LitStr,
+    pub opclass: Option<LitStr>,
+}
+
+impl IndexKey {
+    /// The key as it's written in `CREATE INDEX`, where an expression is parenthesized
+    pub fn sql(&self) -> String {
+        let key = self.key.value();
+        let is_column = matches!(key.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+        let mut sql = if is_column { key } else { format!("({})", key) };
+        if let Some(opclass) = &self.opclass {
+            sql.push(' ');
+            sql.push_str(&opclass.value());
+        }
+        sql
+    }
+}
+
+impl Parse for IndexKey {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        if input.peek(syn::token::Paren) {
+            let content;
+            let _paren = syn::parenthesized!(content in input);
+            let key = content.parse()?;
+            let _comma: Token![,] = content.parse()?;
+            let opclass = Some(content.parse()?);
+            Ok(Self { key, opclass })
+        } else {
+            Ok(Self {
+                key: input.parse()?,
+                opclass: None,
+            })
+        }
+    }
+}
+
+impl PgIndex {
+    /// The `CREATE INDEX` statement
+    pub fn sql(&self) -> String {
+        let mut sql = format!(
+            "CREATE {}INDEX \"{}\" ON {}",
+            if self.unique { "UNIQUE " } else { "" },
+            self.name.value().replace('"', "\"\""),
+            self.table.value()
+        );
+        if let Some(method) = &self.method {
+            sql.push_str(&format!(" USING {}", method));
+        }
+        let keys = self.keys.iter().map(IndexKey::sql).collect::<Vec<_>>();
+        sql.push_str(&format!(" ({})", keys.join(", ")));
+        if !self.include.is_empty() {
+            let include = self
+                .include
+                .iter()
+                .map(|column| column.value())
+                .collect::<Vec<_>>();
+            sql.push_str(&format!(" INCLUDE ({})", include.join(", ")));
+        }
+        if let Some(predicate) = &self.predicate {
+            sql.push_str(&format!(" WHERE {}", predicate.value()));
+        }
+        sql.push(';');
+        sql
+    }
+
+    /// The name of the generated `extension_sql!()` entity, which other entities can `require`
+    pub fn entity_name(&self) -> String {
+        format!("index_{}", self.name.value())
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+}
+
+impl Parse for PgIndex {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let mut name = None;
+        let mut table = None;
+        let mut method = None;
+        let mut unique = false;
+        let mut keys = vec![];
+        let mut include = vec![];
+        let mut predicate = None;
+        let mut requires = Punctuated::new();
+
+        while !input.is_empty() {
+            // `where` is a keyword
+            let ident = input.call(Ident::parse_any)?;
+            match ident.to_string().as_str() {
+                "name" => {
+                    let _eq: Token![=] = input.parse()?;
+                    name = Some(input.parse()?);
+                }
+                "on" => {
+                    let _eq: Token![=] = input.parse()?;
+                    table = Some(input.parse()?);
+                }
+                "using" => {
+                    let _eq: Token![=] = input.parse()?;
+                    method = Some(input.parse()?);
+                }
+                "unique" => unique = true,
+                "keys" => {
+                    let _eq: Token![=] = input.parse()?;
+                    let content;
+                    let _bracket = syn::bracketed!(content in input);
+                    keys = content
+                        .parse_terminated::<IndexKey, Token![,]>(IndexKey::parse)?
+                        .into_iter()
+                        .collect();
+                }
+                "include" => {
+                    let _eq: Token![=] = input.parse()?;
+                    let content;
+                    let _bracket = syn::bracketed!(content in input);
+                    include = content
+                        .parse_terminated::<LitStr, Token![,]>(|input| input.parse())?
+                        .into_iter()
+                        .collect();
+                }
+                "where" => {
+                    let _eq: Token![=] = input.parse()?;
+                    predicate = Some(input.parse()?);
+                }
+                "requires" => {
+                    let _eq: Token![=] = input.parse()?;
+                    let content;
+                    let _bracket = syn::bracketed!(content in input);
+                    requires = content.parse_terminated(PositioningRef::parse)?;
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("Unknown pg_index attribute: {}", other),
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                let _comma: Token![,] = input.parse()?;
+            }
+        }
+
+        let name: LitStr =
+            name.ok_or_else(|| syn::Error::new(input.span(), "expected `name` to be set"))?;
+        let table =
+            table.ok_or_else(|| syn::Error::new(input.span(), "expected `on` to be set"))?;
+        if keys.is_empty() {
+            return Err(syn::Error::new(
+                name.span(),
+                "an index needs at least one of `keys`",
+            ));
+        }
+
+        Ok(Self {
+            name,
+            table,
+            method,
+            unique,
+            keys,
+            include,
+            predicate,
+            requires,
+        })
+    }
+}
+
+impl ToTokens for PgIndex {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let mut attrs = Punctuated::new();
+        attrs.push(ExtensionSqlAttribute::Requires(self.requires.clone()));
+        ExtensionSql {
+            sql: LitStr::new(&self.sql(), Span::call_site()),
+            name: LitStr::new(&self.entity_name(), Span::call_site()),
+            attrs,
+        }
+        .to_tokens(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PgIndex;
+    use syn::parse_quote;
+
+    #[test]
+    fn index_all_options() {
+        let index: PgIndex = parse_quote! {
+            name = "accounts_email",
+            on = "app.accounts",
+            using = btree,
+            unique,
+            keys = ["tenant_id", ("lower(email)", "text_pattern_ops")],
+            include = ["id"],
+            where = "deleted_at IS NULL",
+            requires = ["create_accounts"],
+        };
+        assert_eq!(
+            index.sql(),
+            "CREATE UNIQUE INDEX \"accounts_email\" ON app.accounts USING btree \
+             (tenant_id, (lower(email)) text_pattern_ops) INCLUDE (id) WHERE deleted_at IS NULL;"
+        );
+        assert_eq!(index.entity_name(), "index_accounts_email");
+        assert_eq!(index.requires.len(), 1);
+    }
+
+    #[test]
+    fn index_defaults() {
+        let index: PgIndex = parse_quote! {
+            name = "accounts_created", on = "accounts", keys = ["created_at"]
+        };
+        assert_eq!(
+            index.sql(),
+            "CREATE INDEX \"accounts_created\" ON accounts (created_at);"
+        );
+    }
+
+    #[test]
+    fn index_requires_keys() {
+        let index = syn::parse2::<PgIndex>(quote::quote! {
+            name = "nothing", on = "accounts"
+        });
+        assert!(index.is_err());
+    }
+}
@@ -0,0 +1,291 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    extension_sql::ExtensionSqlAttribute, positioning_ref::PositioningRef, ExtensionSql,
+};
+
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::ToTokens;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    LitStr, Token,
+};
+
+/// A parsed `pg_policy!()` item.
+///
+/// It should be used with [`syn::parse::Parse`] functions.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a `pgx::utils::sql_entity_graph::ExtensionSqlEntity`
+/// containing the `CREATE POLICY` statement.
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::PgPolicy;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let parsed: Macro = parse_quote! {
+///     pg_policy!(
+///         name = "own_documents",
+///         on = "documents",
+///         command = select,
+///         using = "owner = current_user",
+///         requires = ["create_documents"],
+///     )
+/// };
+/// let inner_tokens = parsed.tokens;
+/// let inner: PgPolicy = parse_quote! {
+///     #inner_tokens
+/// };
+/// assert_eq!(
+///     inner.sql(),
+///     "CREATE POLICY \"own_documents\" ON documents FOR SELECT USING (owner = current_user);"
+/// );
+/// let sql_graph_entity_tokens = inner.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PgPolicy {
+    pub name: LitStr,
+    pub table: LitStr,
+    pub command: PolicyCommand,
+    pub restrictive: bool,
+    pub roles: Vec<LitStr>,
+    pub using: Option<LitStr>,
+    pub with_check: Option<LitStr>,
+    pub requires: Punctuated<PositioningRef, Token![,]>,
+}
+
+/// The command a policy applies to, from `CREATE POLICY ... FOR command`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyCommand {
+    All,
+    Select,
+    Insert,
+    Update,
+    Delete,
+}
+
+impl PgPolicy {
+    /// The `CREATE POLICY` statement
+    pub fn sql(&self) -> String {
+        let mut sql = format!(
+            "CREATE POLICY \"{}\" ON {}",
+            self.name.value().replace('"', "\"\""),
+            self.table.value()
+        );
+        if self.restrictive {
+            sql.push_str(" AS RESTRICTIVE");
+        }
+        match self.command {
+            PolicyCommand::All => (),
+            PolicyCommand::Select => sql.push_str(" FOR SELECT"),
+            PolicyCommand::Insert => sql.push_str(" FOR INSERT"),
+            PolicyCommand::Update => sql.push_str(" FOR UPDATE"),
+            PolicyCommand::Delete => sql.push_str(" FOR DELETE"),
+        }
+        if !self.roles.is_empty() {
+            let roles = self
+                .roles
+                .iter()
+                .map(|role| role.value())
+                .collect::<Vec<_>>();
+            sql.push_str(&format!(" TO {}", roles.join(", ")));
+        }
+        if let Some(using) = &self.using {
+            sql.push_str(&format!(" USING ({})", using.value()));
+        }
+        if let Some(with_check) = &self.with_check {
+            sql.push_str(&format!(" WITH CHECK ({})", with_check.value()));
+        }
+        sql.push(';');
+        sql
+    }
+
+    /// The name of the generated `extension_sql!()` entity, which other entities can `require`
+    pub fn entity_name(&self) -> String {
+        format!("policy_{}_on_{}", self.name.value(), self.table.value())
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+}
+
+impl Parse for PgPolicy {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let mut name = None;
+        let mut table = None;
+        let mut command = PolicyCommand::All;
+        let mut restrictive = false;
+        let mut roles = vec![];
+        let mut using = None;
+        let mut with_check = None;
+        let mut requires = Punctuated::new();
+
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            match ident.to_string().as_str() {
+                "name" => {
+                    let _eq: Token![=] = input.parse()?;
+                    name = Some(input.parse()?);
+                }
+                "on" => {
+                    let _eq: Token![=] = input.parse()?;
+                    table = Some(input.parse()?);
+                }
+                "command" => {
+                    let _eq: Token![=] = input.parse()?;
+                    let found: Ident = input.parse()?;
+                    command = match found.to_string().as_str() {
+                        "all" => PolicyCommand::All,
+                        "select" => PolicyCommand::Select,
+                        "insert" => PolicyCommand::Insert,
+                        "update" => PolicyCommand::Update,
+                        "delete" => PolicyCommand::Delete,
+                        _ => {
+                            return Err(syn::Error::new(
+                                found.span(),
+                                "`command` must be one of `all`, `select`, `insert`, `update`, or `delete`",
+                            ))
+                        }
+                    };
+                }
+                "restrictive" => restrictive = true,
+                "to" => {
+                    let _eq: Token![=] = input.parse()?;
+                    let content;
+                    let _bracket = syn::bracketed!(content in input);
+                    roles = content
+                        .parse_terminated::<LitStr, Token![,]>(|input| input.parse())?
+                        .into_iter()
+                        .collect();
+                }
+                "using" => {
+                    let _eq: Token![=] = input.parse()?;
+                    using = Some(input.parse()?);
+                }
+                "with_check" => {
+                    let _eq: Token![=] = input.parse()?;
+                    with_check = Some(input.parse()?);
+                }
+                "requires" => {
+                    let _eq: Token![=] = input.parse()?;
+                    let content;
+                    let _bracket = syn::bracketed!(content in input);
+                    requires = content.parse_terminated(PositioningRef::parse)?;
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("Unknown pg_policy attribute: {}", other),
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                let _comma: Token![,] = input.parse()?;
+            }
+        }
+
+        let name: LitStr =
+            name.ok_or_else(|| syn::Error::new(input.span(), "expected `name` to be set"))?;
+        let table =
+            table.ok_or_else(|| syn::Error::new(input.span(), "expected `on` to be set"))?;
+        if using.is_none() && with_check.is_none() {
+            return Err(syn::Error::new(
+                name.span(),
+                "a policy needs a `using` or `with_check` expression",
+            ));
+        }
+        if command == PolicyCommand::Insert && using.is_some() {
+            return Err(syn::Error::new(
+                name.span(),
+                "an `insert` policy can only have a `with_check` expression",
+            ));
+        }
+        if matches!(command, PolicyCommand::Select | PolicyCommand::Delete) && with_check.is_some()
+        {
+            return Err(syn::Error::new(
+                name.span(),
+                "`select` and `delete` policies can only have a `using` expression",
+            ));
+        }
+
+        Ok(Self {
+            name,
+            table,
+            command,
+            restrictive,
+            roles,
+            using,
+            with_check,
+            requires,
+        })
+    }
+}
+
+impl ToTokens for PgPolicy {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let mut attrs = Punctuated::new();
+        attrs.push(ExtensionSqlAttribute::Requires(self.requires.clone()));
+        ExtensionSql {
+            sql: LitStr::new(&self.sql(), Span::call_site()),
+            name: LitStr::new(&self.entity_name(), Span::call_site()),
+            attrs,
+        }
+        .to_tokens(tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PgPolicy;
+    use syn::parse_quote;
+
+    #[test]
+    fn policy_all_options() {
+        let policy: PgPolicy = parse_quote! {
+            name = "tenant_isolation",
+            on = "app.accounts",
+            command = update,
+            restrictive,
+            to = ["app_user", "PUBLIC"],
+            using = "tenant_id = current_tenant()",
+            with_check = "tenant_id = current_tenant()",
+            requires = ["create_accounts", current_tenant],
+        };
+        assert_eq!(
+            policy.sql(),
+            "CREATE POLICY \"tenant_isolation\" ON app.accounts AS RESTRICTIVE FOR UPDATE TO app_user, PUBLIC \
+             USING (tenant_id = current_tenant()) WITH CHECK (tenant_id = current_tenant());"
+        );
+        assert_eq!(
+            policy.entity_name(),
+            "policy_tenant_isolation_on_app_accounts"
+        );
+        assert_eq!(policy.requires.len(), 2);
+    }
+
+    #[test]
+    fn policy_requires_an_expression() {
+        let policy = syn::parse2::<PgPolicy>(quote::quote! {
+            name = "nothing", on = "accounts"
+        });
+        assert!(policy.is_err());
+    }
+
+    #[test]
+    fn policy_insert_rejects_using() {
+        let policy = syn::parse2::<PgPolicy>(quote::quote! {
+            name = "inserts", on = "accounts", command = insert, using = "true"
+        });
+        assert!(policy.is_err());
+    }
+}
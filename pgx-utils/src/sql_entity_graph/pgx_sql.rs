@@ -7,7 +7,12 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use eyre::{eyre, WrapErr};
-use std::{any::TypeId, collections::HashMap, fmt::Debug, path::Path};
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    path::Path,
+};
 
 use owo_colors::{OwoColorize, XtermColors};
 use petgraph::{dot::Dot, graph::NodeIndex, stable_graph::StableGraph};
@@ -16,19 +21,24 @@ use tracing::instrument;
 use crate::sql_entity_graph::{
     aggregate::entity::PgAggregateEntity,
     control_file::ControlFile,
+    custom::entity::CustomEntity,
     extension_sql::{
         entity::{ExtensionSqlEntity, SqlDeclaredEntity},
         SqlDeclared,
     },
     mapping::{RustSourceOnlySqlMapping, RustSqlMapping},
+    pg_catalog_table::entity::{catalog_table_upgrade, CATALOG_TABLE_KIND},
     pg_extern::entity::{PgExternEntity, PgExternReturnEntity},
     positioning_ref::PositioningRef,
-    postgres_enum::entity::PostgresEnumEntity,
+    postgres_domain::entity::PostgresDomainEntity,
+    postgres_enum::entity::{labels_in_sql, PostgresEnumEntity},
     postgres_hash::entity::PostgresHashEntity,
     postgres_ord::entity::PostgresOrdEntity,
     postgres_type::entity::PostgresTypeEntity,
+    required_extension::entity::RequiredExtensionEntity,
     schema::entity::SchemaEntity,
     to_sql::ToSql,
+    trusted::{superuser_only_constructs, TrustedAuditFinding, TrustedAuditProblem},
     SqlGraphEntity, SqlGraphIdentifier,
 };
 
@@ -61,9 +71,12 @@ pub struct PgxSql {
     pub types: HashMap<PostgresTypeEntity, NodeIndex>,
     pub builtin_types: HashMap<String, NodeIndex>,
     pub enums: HashMap<PostgresEnumEntity, NodeIndex>,
+    pub domains: HashMap<PostgresDomainEntity, NodeIndex>,
     pub ords: HashMap<PostgresOrdEntity, NodeIndex>,
     pub hashes: HashMap<PostgresHashEntity, NodeIndex>,
     pub aggregates: HashMap<PgAggregateEntity, NodeIndex>,
+    pub customs: HashMap<CustomEntity, NodeIndex>,
+    pub required_extensions: HashMap<RequiredExtensionEntity, NodeIndex>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
@@ -91,9 +104,12 @@ impl PgxSql {
         let mut externs: Vec<PgExternEntity> = Vec::default();
         let mut types: Vec<PostgresTypeEntity> = Vec::default();
         let mut enums: Vec<PostgresEnumEntity> = Vec::default();
+        let mut domains: Vec<PostgresDomainEntity> = Vec::default();
         let mut ords: Vec<PostgresOrdEntity> = Vec::default();
         let mut hashes: Vec<PostgresHashEntity> = Vec::default();
         let mut aggregates: Vec<PgAggregateEntity> = Vec::default();
+        let mut customs: Vec<CustomEntity> = Vec::default();
+        let mut required_extensions: Vec<RequiredExtensionEntity> = Vec::default();
         for entity in entities {
             match entity {
                 SqlGraphEntity::ExtensionRoot(input_control) => {
@@ -115,6 +131,9 @@ impl PgxSql {
                 SqlGraphEntity::Enum(input_enum) => {
                     enums.push(input_enum);
                 }
+                SqlGraphEntity::Domain(input_domain) => {
+                    domains.push(input_domain);
+                }
                 SqlGraphEntity::Ord(input_ord) => {
                     ords.push(input_ord);
                 }
@@ -124,6 +143,12 @@ impl PgxSql {
                 SqlGraphEntity::Aggregate(input_hash) => {
                     aggregates.push(input_hash);
                 }
+                SqlGraphEntity::Custom(input_custom) => {
+                    customs.push(input_custom);
+                }
+                SqlGraphEntity::RequiredExtension(input_required_extension) => {
+                    required_extensions.push(input_required_extension);
+                }
             }
         }
 
@@ -143,6 +168,7 @@ impl PgxSql {
         let mapped_schemas = initialize_schemas(&mut graph, bootstrap, finalize, schemas)?;
         let mapped_enums = initialize_enums(&mut graph, root, bootstrap, finalize, enums)?;
         let mapped_types = initialize_types(&mut graph, root, bootstrap, finalize, types)?;
+        let mapped_domains = initialize_domains(&mut graph, root, bootstrap, finalize, domains)?;
         let (mapped_externs, mut mapped_builtin_types) = initialize_externs(
             &mut graph,
             root,
@@ -151,6 +177,7 @@ impl PgxSql {
             externs,
             &mapped_types,
             &mapped_enums,
+            &mapped_domains,
         )?;
         let mapped_ords = initialize_ords(&mut graph, root, bootstrap, finalize, ords)?;
         let mapped_hashes = initialize_hashes(&mut graph, root, bootstrap, finalize, hashes)?;
@@ -163,7 +190,11 @@ impl PgxSql {
             &mut mapped_builtin_types,
             &mapped_enums,
             &mapped_types,
+            &mapped_domains,
         )?;
+        let mapped_customs = initialize_customs(&mut graph, root, bootstrap, finalize, customs)?;
+        let mapped_required_extensions =
+            initialize_required_extensions(&mut graph, root, required_extensions)?;
 
         // Now we can circle back and build up the edge sets.
         connect_schemas(&mut graph, &mapped_schemas, root);
@@ -174,17 +205,27 @@ impl PgxSql {
             &mapped_types,
             &mapped_enums,
             &mapped_externs,
+            &mapped_customs,
         )?;
         connect_enums(&mut graph, &mapped_enums, &mapped_schemas);
         connect_types(&mut graph, &mapped_types, &mapped_schemas);
+        connect_domains(
+            &mut graph,
+            &mapped_domains,
+            &mapped_schemas,
+            &mapped_types,
+            &mapped_enums,
+        );
         connect_externs(
             &mut graph,
             &mapped_externs,
             &mapped_schemas,
             &mapped_types,
             &mapped_enums,
+            &mapped_domains,
             &mapped_builtin_types,
             &mapped_extension_sqls,
+            &mapped_customs,
         )?;
         connect_ords(
             &mut graph,
@@ -208,9 +249,20 @@ impl PgxSql {
             &mapped_schemas,
             &mapped_types,
             &mapped_enums,
+            &mapped_domains,
             &mapped_builtin_types,
             &mapped_externs,
         );
+        connect_customs(
+            &mut graph,
+            &mapped_customs,
+            &mapped_schemas,
+            &mapped_types,
+            &mapped_enums,
+            &mapped_externs,
+            &mapped_extension_sqls,
+        )?;
+        connect_required_extensions(&mut graph, &mapped_required_extensions, root);
 
         let mut this = Self {
             type_mappings: type_mappings.map(|x| (x.id.clone(), x)).collect(),
@@ -222,9 +274,12 @@ impl PgxSql {
             types: mapped_types,
             builtin_types: mapped_builtin_types,
             enums: mapped_enums,
+            domains: mapped_domains,
             ords: mapped_ords,
             hashes: mapped_hashes,
             aggregates: mapped_aggregates,
+            customs: mapped_customs,
+            required_extensions: mapped_required_extensions,
             graph: graph,
             graph_root: root,
             graph_bootstrap: bootstrap,
@@ -341,6 +396,10 @@ impl PgxSql {
                         "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#C9A7C8\", weight = 5, shape = \"oval\"",
                         node.dot_identifier()
                     ),
+                    SqlGraphEntity::Domain(_item) => format!(
+                        "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#D8BFD8\", weight = 5, shape = \"oval\"",
+                        node.dot_identifier()
+                    ),
                     SqlGraphEntity::Ord(_item) => format!(
                         "label = \"{}\", penwidth = 0, style = \"filled\", fillcolor = \"#FFCFD3\", weight = 5, shape = \"diamond\"",
                         node.dot_identifier()
@@ -357,10 +416,18 @@ impl PgxSql {
                         "label = \"{}\", weight = 3, shape = \"signature\"",
                         node.dot_identifier()
                     ),
+                    SqlGraphEntity::Custom(_item) => format!(
+                        "label = \"{}\", weight = 3, shape = \"component\"",
+                        node.dot_identifier()
+                    ),
                     SqlGraphEntity::ExtensionRoot(_item) => format!(
                         "label = \"{}\", shape = \"cylinder\"",
                         node.dot_identifier()
                     ),
+                    SqlGraphEntity::RequiredExtension(_item) => format!(
+                        "label = \"{}\", shape = \"cylinder\", style = \"dashed\"",
+                        node.dot_identifier()
+                    ),
                 }
             },
         );
@@ -375,7 +442,83 @@ impl PgxSql {
         Ok(())
     }
 
+    /// Write the entity graph as JSON: every node, every edge between them, and the order the
+    /// nodes are emitted in by [`PgxSql::to_sql`].  Intended for tooling and for debugging
+    /// unexpected ordering in the generated SQL.
+    #[instrument(level = "error", skip(self))]
+    pub fn to_json(&self, file: impl AsRef<Path> + Debug) -> eyre::Result<()> {
+        use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+        use std::{
+            fs::{create_dir_all, File},
+            path::Path,
+        };
+
+        let nodes = self
+            .graph
+            .node_indices()
+            .map(|index| {
+                let node = &self.graph[index];
+                serde_json::json!({
+                    "id": index.index(),
+                    "kind": node.kind(),
+                    "identifier": node.dot_identifier(),
+                    "rust_identifier": node.rust_identifier(),
+                    "file": node.file(),
+                    "line": node.line(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let edges = self
+            .graph
+            .edge_references()
+            .map(|edge| {
+                serde_json::json!({
+                    "from": edge.source().index(),
+                    "to": edge.target().index(),
+                    "relationship": match edge.weight() {
+                        SqlGraphRelationship::RequiredBy => "required_by",
+                        SqlGraphRelationship::RequiredByArg => "required_by_arg",
+                        SqlGraphRelationship::RequiredByReturn => "required_by_return",
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        let order = self
+            .toposort()?
+            .into_iter()
+            .map(|index| index.index())
+            .collect::<Vec<_>>();
+
+        let path = Path::new(file.as_ref());
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let out = File::create(path)?;
+        serde_json::to_writer_pretty(
+            out,
+            &serde_json::json!({
+                "nodes": nodes,
+                "edges": edges,
+                "order": order,
+            }),
+        )?;
+        Ok(())
+    }
+
     pub fn schema_alias_of(&self, item_index: &NodeIndex) -> Option<String> {
+        // one given by `schema = ".."` overrides that of the item's module
+        let explicit = match &self.graph[*item_index] {
+            SqlGraphEntity::Function(item) => item.schema,
+            SqlGraphEntity::Type(item) => item.schema,
+            SqlGraphEntity::Enum(item) => item.schema,
+            SqlGraphEntity::Domain(item) => item.schema,
+            SqlGraphEntity::Custom(item) => item.schema,
+            _ => None,
+        };
+        if let Some(schema) = explicit {
+            return Some(String::from(schema));
+        }
+
         self.graph
             .neighbors_undirected(*item_index)
             .flat_map(|neighbor_index| match &self.graph[neighbor_index] {
@@ -398,15 +541,101 @@ impl PgxSql {
             .unwrap_or_else(|| "".to_string())
     }
 
+    /// The schema-qualified SQL name of the extension's type, enum, or domain with the [`TypeId`]
+    /// `id`, for an item at `item_index` which requires it, such as the operator class of a
+    /// `#[derive(PostgresOrd)]`
+    pub fn qualified_type_name_for(&self, item_index: &NodeIndex, id: TypeId) -> Option<String> {
+        let type_index =
+            self.graph
+                .neighbors_undirected(*item_index)
+                .find(|neighbor| match &self.graph[*neighbor] {
+                    SqlGraphEntity::Type(ty) => ty.id_matches(&id),
+                    SqlGraphEntity::Enum(en) => en.id_matches(&id),
+                    SqlGraphEntity::Domain(domain) => domain.id_matches(&id),
+                    _ => false,
+                })?;
+        let name = self.type_id_to_sql_type(id)?;
+        Some(self.schema_prefix_for(&type_index) + &name)
+    }
+
+    /// The order the entities are emitted in.
+    ///
+    /// Every entity comes after everything it requires.  Of the entities whose requirements have
+    /// all been emitted, the next is the one which comes first in the source: by file, then line,
+    /// then kind (as [`SqlGraphEntity::kind`] names it), then Rust identifier, with the entities
+    /// which aren't from the source, like the builtin types, before all the others.
+    ///
+    /// So the order depends only on the entities and what they require, and not on the order
+    /// they were found in or the graph was built in, and the SQL generated for the same source
+    /// is the same from one build to the next.
+    #[instrument(level = "error", skip(self))]
+    pub fn toposort(&self) -> eyre::Result<Vec<NodeIndex>> {
+        use petgraph::Direction;
+        use std::collections::BTreeSet;
+
+        let key = |index: NodeIndex| {
+            let node = &self.graph[index];
+            (
+                node.file(),
+                node.line(),
+                node.kind(),
+                node.rust_identifier(),
+                index,
+            )
+        };
+
+        let mut remaining = self
+            .graph
+            .node_indices()
+            .map(|index| {
+                let requirements = self
+                    .graph
+                    .neighbors_directed(index, Direction::Incoming)
+                    .collect::<HashSet<_>>();
+                (index, requirements.len())
+            })
+            .collect::<HashMap<_, _>>();
+        let mut ready = remaining
+            .iter()
+            .filter(|(_, requirements)| **requirements == 0)
+            .map(|(index, _)| key(*index))
+            .collect::<BTreeSet<_>>();
+
+        let mut order = Vec::with_capacity(remaining.len());
+        while let Some(next) = ready.iter().next().cloned() {
+            ready.remove(&next);
+            let index = next.4;
+            remaining.remove(&index);
+            order.push(index);
+
+            let dependents = self
+                .graph
+                .neighbors_directed(index, Direction::Outgoing)
+                .collect::<HashSet<_>>();
+            for dependent in dependents {
+                let requirements = remaining
+                    .get_mut(&dependent)
+                    .expect("a SQL entity was emitted before one it requires");
+                *requirements -= 1;
+                if *requirements == 0 {
+                    ready.insert(key(dependent));
+                }
+            }
+        }
+
+        match remaining.keys().min_by_key(|index| key(**index)) {
+            None => Ok(order),
+            Some(index) => Err(eyre!(
+                "Failed to toposort SQL entities, node with cycle: {:?}",
+                self.graph[*index]
+            )),
+        }
+    }
+
     #[instrument(level = "error", skip(self))]
     pub fn to_sql(&self) -> eyre::Result<String> {
         let mut full_sql = String::new();
-        for step_id in petgraph::algo::toposort(&self.graph, None).map_err(|e| {
-            eyre!(
-                "Failed to toposort SQL entities, node with cycle: {:?}",
-                self.graph[e.node_id()]
-            )
-        })? {
+        for step_id in self.toposort()? {
             let step = &self.graph[step_id];
 
             let sql = step.to_sql(self)?;
@@ -419,6 +648,152 @@ impl PgxSql {
         Ok(full_sql)
     }
 
+    /// The SQL for an extension upgrade script which brings the enums created by `previous`, the
+    /// SQL generated for an earlier version of the extension, up to date: `ALTER TYPE ... ADD
+    /// VALUE` for the labels added to them since, and `CREATE TYPE` for enums which are new.
+    ///
+    /// Enums with `#[pgx(sql = ..)]` are left to whoever wrote their SQL.
+    #[instrument(level = "error", skip_all)]
+    pub fn enum_upgrade_sql(&self, previous: &str) -> eyre::Result<String> {
+        let mut enums = self
+            .enums
+            .iter()
+            .filter(|(item, _)| {
+                item.to_sql_config.enabled
+                    && item.to_sql_config.callback.is_none()
+                    && item.to_sql_config.content.is_none()
+            })
+            .collect::<Vec<_>>();
+        enums.sort_by_key(|(item, _)| item.full_path);
+
+        let mut upgrade_sql = String::new();
+        for (item, index) in enums {
+            let qualified_name = format!("{}{}", self.schema_prefix_for(index), item.name);
+            match labels_in_sql(previous, &qualified_name) {
+                Some(previous_labels) => {
+                    let statements = item.add_value_sql(&qualified_name, &previous_labels)?;
+                    if !statements.is_empty() {
+                        upgrade_sql.push_str(&format!(
+                            "\n-- {}\n{}\n",
+                            item.full_path,
+                            statements.join("\n")
+                        ));
+                    }
+                }
+                None => {
+                    upgrade_sql.push_str(&item.to_sql(self)?);
+                    upgrade_sql.push('\n');
+                }
+            }
+        }
+        Ok(upgrade_sql)
+    }
+
+    /// The SQL which brings the `#[derive(PgCatalogTable)]` tables created by `previous`, the SQL
+    /// generated for an earlier version of the extension, up to date, to be included in the
+    /// extension's upgrade script: the tables which have been declared since, and the columns,
+    /// indexes, and rows which have been added to the others.  Columns which have been changed or
+    /// removed, and new `NOT NULL` columns without a `DEFAULT`, are commented on, and left as they
+    /// are, for a migration of their own.
+    #[instrument(level = "error", skip(self, previous))]
+    pub fn catalog_table_upgrade_sql(&self, previous: &str) -> eyre::Result<String> {
+        let mut tables = self
+            .customs
+            .keys()
+            .filter(|item| item.kind == CATALOG_TABLE_KIND)
+            .collect::<Vec<_>>();
+        tables.sort_by_key(|item| item.full_path);
+
+        let mut upgrade_sql = String::new();
+        for item in tables {
+            let current = item.content(self)?;
+            match catalog_table_upgrade(&current, previous) {
+                Some(statements) => {
+                    if !statements.is_empty() {
+                        upgrade_sql.push_str(&format!(
+                            "\n-- {}\n{}\n",
+                            item.full_path,
+                            statements.join("\n")
+                        ));
+                    }
+                }
+                None => {
+                    upgrade_sql.push_str(&item.to_sql(self)?);
+                    upgrade_sql.push('\n');
+                }
+            }
+        }
+        Ok(upgrade_sql)
+    }
+
+    /// Audit the entities for whether the extension may be marked `trusted = true` in its control
+    /// file, so a non-superuser with `CREATE` on a database may install it: the problems found, in
+    /// the order the entities are emitted in, of which there are none if it may.
+    ///
+    /// A trusted extension's script is run as the bootstrap superuser, so it mustn't create objects
+    /// in `pg_catalog`, or run SQL which only a superuser should, and none of its functions may be
+    /// marked `#[pg_extern(untrusted)]`.  Entities with `#[pgx(sql = false)]` aren't audited.
+    #[instrument(level = "error", skip(self))]
+    pub fn trusted_audit(&self) -> eyre::Result<Vec<TrustedAuditFinding>> {
+        let mut findings = Vec::new();
+        for index in self.toposort()? {
+            let entity = &self.graph[index];
+            let finding = |problem| TrustedAuditFinding {
+                entity: entity.rust_identifier(),
+                location: entity
+                    .file()
+                    .zip(entity.line())
+                    .map(|(file, line)| format!("{}:{}", file, line)),
+                problem,
+            };
+
+            let (in_pg_catalog, enabled) = match entity {
+                SqlGraphEntity::ExtensionRoot(control) => {
+                    (control.schema.as_deref() == Some("pg_catalog"), true)
+                }
+                SqlGraphEntity::Schema(item) => (item.name == "pg_catalog", true),
+                SqlGraphEntity::Function(item) => (
+                    item.schema == Some("pg_catalog"),
+                    item.to_sql_config.enabled,
+                ),
+                SqlGraphEntity::Type(item) => (
+                    item.schema == Some("pg_catalog"),
+                    item.to_sql_config.enabled,
+                ),
+                SqlGraphEntity::Enum(item) => (
+                    item.schema == Some("pg_catalog"),
+                    item.to_sql_config.enabled,
+                ),
+                SqlGraphEntity::Domain(item) => (
+                    item.schema == Some("pg_catalog"),
+                    item.to_sql_config.enabled,
+                ),
+                SqlGraphEntity::Ord(item) => (false, item.to_sql_config.enabled),
+                SqlGraphEntity::Hash(item) => (false, item.to_sql_config.enabled),
+                SqlGraphEntity::Aggregate(item) => (false, item.to_sql_config.enabled),
+                SqlGraphEntity::Custom(item) => (item.schema == Some("pg_catalog"), true),
+                SqlGraphEntity::CustomSql(_)
+                | SqlGraphEntity::BuiltinType(_)
+                | SqlGraphEntity::RequiredExtension(_) => (false, true),
+            };
+            if !enabled {
+                continue;
+            }
+            if in_pg_catalog {
+                findings.push(finding(TrustedAuditProblem::InPgCatalog));
+            }
+            if let SqlGraphEntity::Function(item) = entity {
+                if item.extern_attrs.contains(&crate::ExternArgs::Untrusted) {
+                    findings.push(finding(TrustedAuditProblem::Untrusted));
+                }
+            }
+            for construct in superuser_only_constructs(&entity.to_sql(self)?) {
+                findings.push(finding(TrustedAuditProblem::SuperuserOnly(construct)));
+            }
+        }
+        Ok(findings)
+    }
+
     #[instrument(level = "error", skip(self))]
     pub fn register_types(&mut self) {
         for (item, _index) in self.enums.clone() {
@@ -432,6 +807,17 @@ impl PgxSql {
                 );
             }
         }
+        for (item, _index) in self.domains.clone() {
+            for mapping in &item.mappings {
+                assert_eq!(
+                    self.type_mappings
+                        .insert(mapping.id.clone(), mapping.clone()),
+                    None,
+                    "Cannot map `{}` twice.",
+                    item.full_path,
+                );
+            }
+        }
         for (item, _index) in self.types.clone() {
             for mapping in &item.mappings {
                 assert_eq!(
@@ -580,6 +966,7 @@ pub fn find_positioning_ref_target<'a>(
     externs: &'a HashMap<PgExternEntity, NodeIndex>,
     schemas: &'a HashMap<SchemaEntity, NodeIndex>,
     extension_sqls: &'a HashMap<ExtensionSqlEntity, NodeIndex>,
+    customs: &'a HashMap<CustomEntity, NodeIndex>,
 ) -> Option<&'a NodeIndex> {
     match positioning_ref {
         PositioningRef::FullPath(path) => {
@@ -611,6 +998,11 @@ pub fn find_positioning_ref_target<'a>(
                     return Some(&other_index);
                 }
             }
+            for (other, other_index) in customs {
+                if *last_segment == other.name && other.module_path.ends_with(&module_path) {
+                    return Some(other_index);
+                }
+            }
         }
         PositioningRef::Name(name) => {
             for (other, other_index) in extension_sqls {
@@ -618,6 +1010,11 @@ pub fn find_positioning_ref_target<'a>(
                     return Some(&other_index);
                 }
             }
+            for (other, other_index) in customs {
+                if other.name == *name {
+                    return Some(other_index);
+                }
+            }
         }
     };
     None
@@ -631,6 +1028,7 @@ fn connect_extension_sqls(
     types: &HashMap<PostgresTypeEntity, NodeIndex>,
     enums: &HashMap<PostgresEnumEntity, NodeIndex>,
     externs: &HashMap<PgExternEntity, NodeIndex>,
+    customs: &HashMap<CustomEntity, NodeIndex>,
 ) -> eyre::Result<()> {
     for (item, &index) in extension_sqls {
         make_schema_connection(
@@ -639,6 +1037,7 @@ fn connect_extension_sqls(
             index,
             &item.rust_identifier(),
             item.module_path,
+            None,
             schemas,
         );
 
@@ -650,6 +1049,7 @@ fn connect_extension_sqls(
                 externs,
                 schemas,
                 extension_sqls,
+                customs,
             ) {
                 tracing::debug!(from = %item.rust_identifier(), to = ?graph[*target].rust_identifier(), "Adding ExtensionSQL after positioning ref target");
                 graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
@@ -737,6 +1137,7 @@ fn connect_enums(
             index,
             &item.rust_identifier(),
             item.module_path,
+            item.schema,
             schemas,
         );
     }
@@ -773,11 +1174,62 @@ fn connect_types(
             index,
             &item.rust_identifier(),
             item.module_path,
+            item.schema,
             schemas,
         );
     }
 }
 
+#[tracing::instrument(level = "error", skip_all)]
+fn initialize_domains(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    domains: Vec<PostgresDomainEntity>,
+) -> eyre::Result<HashMap<PostgresDomainEntity, NodeIndex>> {
+    let mut mapped_domains = HashMap::default();
+    for item in domains {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+        mapped_domains.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_domains)
+}
+
+#[tracing::instrument(level = "error", skip_all)]
+fn connect_domains(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    domains: &HashMap<PostgresDomainEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+    types: &HashMap<PostgresTypeEntity, NodeIndex>,
+    enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+) {
+    for (item, &index) in domains {
+        make_schema_connection(
+            graph,
+            "Domain",
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            item.schema,
+            schemas,
+        );
+
+        // a domain over one of the extension's own types must come after it
+        make_type_or_enum_connection(
+            graph,
+            "Domain",
+            index,
+            &item.rust_identifier(),
+            &item.base_id,
+            types,
+            enums,
+        );
+    }
+}
+
 #[tracing::instrument(level = "error", skip_all)]
 fn initialize_externs(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
@@ -787,6 +1239,7 @@ fn initialize_externs(
     externs: Vec<PgExternEntity>,
     mapped_types: &HashMap<PostgresTypeEntity, NodeIndex>,
     mapped_enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+    mapped_domains: &HashMap<PostgresDomainEntity, NodeIndex>,
 ) -> eyre::Result<(
     HashMap<PgExternEntity, NodeIndex>,
     HashMap<String, NodeIndex>,
@@ -813,6 +1266,12 @@ fn initialize_externs(
                     break;
                 }
             }
+            for (ty_item, &_ty_index) in mapped_domains {
+                if ty_item.id_matches(&arg.ty_id) {
+                    found = true;
+                    break;
+                }
+            }
             if !found {
                 mapped_builtin_types
                     .entry(arg.full_path.to_string())
@@ -839,6 +1298,12 @@ fn initialize_externs(
                         break;
                     }
                 }
+                for (ty_item, &_ty_index) in mapped_domains {
+                    if ty_item.id_matches(id) {
+                        found = true;
+                        break;
+                    }
+                }
                 if !found {
                     mapped_builtin_types
                         .entry(full_path.to_string())
@@ -862,6 +1327,12 @@ fn initialize_externs(
                             break;
                         }
                     }
+                    for (ty_item, &_ty_index) in mapped_domains {
+                        if ty_item.id_matches(&iterated_return.0) {
+                            found = true;
+                            break;
+                        }
+                    }
                     if !found {
                         mapped_builtin_types
                             .entry(iterated_return.1.to_string())
@@ -885,8 +1356,10 @@ fn connect_externs(
     schemas: &HashMap<SchemaEntity, NodeIndex>,
     types: &HashMap<PostgresTypeEntity, NodeIndex>,
     enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+    domains: &HashMap<PostgresDomainEntity, NodeIndex>,
     builtin_types: &HashMap<String, NodeIndex>,
     extension_sqls: &HashMap<ExtensionSqlEntity, NodeIndex>,
+    customs: &HashMap<CustomEntity, NodeIndex>,
 ) -> eyre::Result<()> {
     for (item, &index) in externs {
         make_schema_connection(
@@ -895,6 +1368,7 @@ fn connect_externs(
             index,
             &item.rust_identifier(),
             item.module_path,
+            item.schema,
             schemas,
         );
 
@@ -909,6 +1383,7 @@ fn connect_externs(
                             externs,
                             schemas,
                             extension_sqls,
+                            customs,
                         ) {
                             tracing::debug!(from = %item.rust_identifier(), to = %graph[*target].rust_identifier(), "Adding Extern after positioning ref target");
                             graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
@@ -917,10 +1392,37 @@ fn connect_externs(
                         }
                     }
                 }
+                // a planner support function written in Rust must exist before the function
+                // naming it
+                crate::ExternArgs::Support(support) => {
+                    for (support_item, &support_index) in externs {
+                        if support_item.module_path == item.module_path
+                            && support_item.name == *support
+                        {
+                            tracing::debug!(from = %item.rust_identifier(), to = %support_item.rust_identifier(), "Adding Extern after support function Extern edge");
+                            graph.add_edge(support_index, index, SqlGraphRelationship::RequiredBy);
+                        }
+                    }
+                }
                 _ => (),
             }
         }
 
+        if let Some(operator) = &item.operator {
+            // selectivity estimators written in Rust must exist before the operator naming them
+            let estimators = operator.restrict.iter().chain(operator.join.iter());
+            for estimator in estimators {
+                for (estimator_item, &estimator_index) in externs {
+                    if estimator_item.module_path == item.module_path
+                        && estimator_item.name == *estimator
+                    {
+                        tracing::debug!(from = %item.rust_identifier(), to = %estimator_item.rust_identifier(), "Adding Extern after selectivity estimator Extern edge");
+                        graph.add_edge(estimator_index, index, SqlGraphRelationship::RequiredBy);
+                    }
+                }
+            }
+        }
+
         for arg in &item.fn_args {
             let mut found = false;
             for (ty_item, &ty_index) in types {
@@ -941,6 +1443,16 @@ fn connect_externs(
                     }
                 }
             }
+            if !found {
+                for (domain_item, &domain_index) in domains {
+                    if domain_item.id_matches(&arg.ty_id) {
+                        tracing::debug!(from = %item.rust_identifier(), to = %domain_item.rust_identifier(), "Adding Extern after Domain (due to argument) edge");
+                        graph.add_edge(domain_index, index, SqlGraphRelationship::RequiredByArg);
+                        found = true;
+                        break;
+                    }
+                }
+            }
             if !found {
                 let builtin_index = builtin_types
                     .get(arg.full_path)
@@ -987,6 +1499,16 @@ fn connect_externs(
                         }
                     }
                 }
+                if !found {
+                    for (ty_item, &ty_index) in domains {
+                        if ty_item.id_matches(id) {
+                            tracing::debug!(from = %item.rust_identifier(), to = %ty_item.rust_identifier(), "Adding Extern after Domain (due to return) edge");
+                            graph.add_edge(ty_index, index, SqlGraphRelationship::RequiredByReturn);
+                            found = true;
+                            break;
+                        }
+                    }
+                }
                 if !found {
                     let builtin_index = builtin_types
                         .get(&full_path.to_string())
@@ -1039,6 +1561,20 @@ fn connect_externs(
                             }
                         }
                     }
+                    if !found {
+                        for (ty_item, &ty_index) in domains {
+                            if ty_item.id_matches(&iterated_return.0) {
+                                tracing::debug!(from = %item.rust_identifier(), to = %ty_item.rust_identifier(), "Adding Extern after Domain (due to return) edge");
+                                graph.add_edge(
+                                    ty_index,
+                                    index,
+                                    SqlGraphRelationship::RequiredByReturn,
+                                );
+                                found = true;
+                                break;
+                            }
+                        }
+                    }
                     if !found {
                         let builtin_index = builtin_types
                             .get(&iterated_return.1.to_string())
@@ -1117,6 +1653,7 @@ fn connect_ords(
             index,
             &item.rust_identifier(),
             item.module_path,
+            None,
             schemas,
         );
 
@@ -1135,12 +1672,14 @@ fn connect_ords(
                 item.module_path == extern_item.module_path && extern_item.name == fn_name
             };
             let cmp_fn_matches = fn_matches(item.cmp_fn_name());
+            let sortsupport_fn_matches = fn_matches(item.sortsupport_fn_name());
             let lt_fn_matches = fn_matches(item.lt_fn_name());
             let lte_fn_matches = fn_matches(item.le_fn_name());
             let eq_fn_matches = fn_matches(item.eq_fn_name());
             let gt_fn_matches = fn_matches(item.gt_fn_name());
             let gte_fn_matches = fn_matches(item.ge_fn_name());
             if cmp_fn_matches
+                || sortsupport_fn_matches
                 || lt_fn_matches
                 || lte_fn_matches
                 || eq_fn_matches
@@ -1188,6 +1727,7 @@ fn connect_hashes(
             index,
             &item.rust_identifier(),
             item.module_path,
+            None,
             schemas,
         );
 
@@ -1202,14 +1742,15 @@ fn connect_hashes(
         );
 
         for (extern_item, &extern_index) in externs {
-            let hash_fn_name = item.fn_name();
-            let hash_fn_matches =
-                item.module_path == extern_item.module_path && extern_item.name == hash_fn_name;
+            let fn_matches = |fn_name| {
+                item.module_path == extern_item.module_path && extern_item.name == fn_name
+            };
+            let hash_fn_matches = fn_matches(item.fn_name());
+            let extended_fn_matches = fn_matches(item.extended_fn_name());
 
-            if hash_fn_matches {
+            if hash_fn_matches || extended_fn_matches {
                 tracing::debug!(from = ?item.full_path, to = extern_item.full_path, "Adding Hash after Extern edge");
                 graph.add_edge(extern_index, index, SqlGraphRelationship::RequiredBy);
-                break;
             }
         }
     }
@@ -1224,6 +1765,7 @@ fn initialize_aggregates(
     mapped_builtin_types: &mut HashMap<String, NodeIndex>,
     mapped_enums: &HashMap<PostgresEnumEntity, NodeIndex>,
     mapped_types: &HashMap<PostgresTypeEntity, NodeIndex>,
+    mapped_domains: &HashMap<PostgresDomainEntity, NodeIndex>,
 ) -> eyre::Result<HashMap<PgAggregateEntity, NodeIndex>> {
     let mut mapped_aggregates = HashMap::default();
     for item in aggregates {
@@ -1244,6 +1786,12 @@ fn initialize_aggregates(
                     break;
                 }
             }
+            for (ty_item, &_ty_index) in mapped_domains {
+                if ty_item.id_matches(&arg.agg_ty.ty_id) {
+                    found = true;
+                    break;
+                }
+            }
             if !found {
                 mapped_builtin_types
                     .entry(arg.agg_ty.full_path.to_string())
@@ -1267,6 +1815,7 @@ fn connect_aggregates(
     schemas: &HashMap<SchemaEntity, NodeIndex>,
     types: &HashMap<PostgresTypeEntity, NodeIndex>,
     enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+    domains: &HashMap<PostgresDomainEntity, NodeIndex>,
     builtin_types: &HashMap<String, NodeIndex>,
     externs: &HashMap<PgExternEntity, NodeIndex>,
 ) {
@@ -1277,6 +1826,7 @@ fn connect_aggregates(
             index,
             &item.rust_identifier(),
             item.module_path,
+            None,
             schemas,
         );
 
@@ -1291,7 +1841,7 @@ fn connect_aggregates(
         );
 
         for arg in &item.args {
-            let found = make_type_or_enum_connection(
+            let mut found = make_type_or_enum_connection(
                 graph,
                 "Aggregate",
                 index,
@@ -1300,6 +1850,16 @@ fn connect_aggregates(
                 types,
                 enums,
             );
+            if !found {
+                found = make_domain_connection(
+                    graph,
+                    "Aggregate",
+                    index,
+                    &item.rust_identifier(),
+                    &arg.agg_ty.ty_id,
+                    domains,
+                );
+            }
             if !found {
                 let builtin_index = builtin_types.get(arg.agg_ty.full_path).expect(&format!(
                     "Could not fetch Builtin Type {}.",
@@ -1311,7 +1871,7 @@ fn connect_aggregates(
         }
 
         for arg in item.direct_args.as_ref().unwrap_or(&vec![]) {
-            let found = make_type_or_enum_connection(
+            let mut found = make_type_or_enum_connection(
                 graph,
                 "Aggregate",
                 index,
@@ -1320,6 +1880,16 @@ fn connect_aggregates(
                 types,
                 enums,
             );
+            if !found {
+                found = make_domain_connection(
+                    graph,
+                    "Aggregate",
+                    index,
+                    &item.rust_identifier(),
+                    &arg.ty_id,
+                    domains,
+                );
+            }
             if !found {
                 let builtin_index = builtin_types
                     .get(arg.full_path)
@@ -1330,7 +1900,7 @@ fn connect_aggregates(
         }
 
         if let Some(arg) = &item.mstype {
-            let found = make_type_or_enum_connection(
+            let mut found = make_type_or_enum_connection(
                 graph,
                 "Aggregate",
                 index,
@@ -1339,6 +1909,16 @@ fn connect_aggregates(
                 types,
                 enums,
             );
+            if !found {
+                found = make_domain_connection(
+                    graph,
+                    "Aggregate",
+                    index,
+                    &item.rust_identifier(),
+                    &arg.ty_id,
+                    domains,
+                );
+            }
             if !found {
                 let builtin_index = builtin_types
                     .get(arg.full_path)
@@ -1459,17 +2039,123 @@ fn connect_aggregates(
     }
 }
 
+#[tracing::instrument(level = "error", skip_all)]
+fn initialize_customs(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    bootstrap: Option<NodeIndex>,
+    finalize: Option<NodeIndex>,
+    customs: Vec<CustomEntity>,
+) -> eyre::Result<HashMap<CustomEntity, NodeIndex>> {
+    let mut mapped_customs = HashMap::default();
+    for item in customs {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+        mapped_customs.insert(item, index);
+        build_base_edges(graph, index, root, bootstrap, finalize);
+    }
+    Ok(mapped_customs)
+}
+
+#[tracing::instrument(level = "error", skip_all)]
+fn initialize_required_extensions(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    root: NodeIndex,
+    required_extensions: Vec<RequiredExtensionEntity>,
+) -> eyre::Result<HashMap<RequiredExtensionEntity, NodeIndex>> {
+    let mut mapped_required_extensions = HashMap::default();
+    for item in required_extensions {
+        let entity: SqlGraphEntity = item.clone().into();
+        let index = graph.add_node(entity);
+        mapped_required_extensions.insert(item, index);
+        graph.add_edge(root, index, SqlGraphRelationship::RequiredBy);
+    }
+    Ok(mapped_required_extensions)
+}
+
+/// The required extensions are checked before anything else is created, so a missing one is
+/// reported as such, rather than by whatever first uses it
+#[tracing::instrument(level = "error", skip_all)]
+fn connect_required_extensions(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    required_extensions: &HashMap<RequiredExtensionEntity, NodeIndex>,
+    root: NodeIndex,
+) {
+    let firsts = graph
+        .neighbors_directed(root, petgraph::Direction::Outgoing)
+        .filter(|index| !matches!(graph[*index], SqlGraphEntity::RequiredExtension(_)))
+        .collect::<Vec<_>>();
+    for &index in required_extensions.values() {
+        for &first in &firsts {
+            graph.add_edge(index, first, SqlGraphRelationship::RequiredBy);
+        }
+    }
+}
+
+#[tracing::instrument(level = "error", skip_all)]
+fn connect_customs(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    customs: &HashMap<CustomEntity, NodeIndex>,
+    schemas: &HashMap<SchemaEntity, NodeIndex>,
+    types: &HashMap<PostgresTypeEntity, NodeIndex>,
+    enums: &HashMap<PostgresEnumEntity, NodeIndex>,
+    externs: &HashMap<PgExternEntity, NodeIndex>,
+    extension_sqls: &HashMap<ExtensionSqlEntity, NodeIndex>,
+) -> eyre::Result<()> {
+    for (item, &index) in customs {
+        make_schema_connection(
+            graph,
+            item.kind,
+            index,
+            &item.rust_identifier(),
+            item.module_path,
+            item.schema,
+            schemas,
+        );
+
+        for requires in &item.requires {
+            if let Some(target) = find_positioning_ref_target(
+                requires,
+                types,
+                enums,
+                externs,
+                schemas,
+                extension_sqls,
+                customs,
+            ) {
+                tracing::debug!(from = %item.rust_identifier(), to = ?graph[*target].rust_identifier(), "Adding custom entity after positioning ref target");
+                graph.add_edge(*target, index, SqlGraphRelationship::RequiredBy);
+            } else {
+                return Err(eyre!(
+                    "Could not find `requires` target of {} `{}` ({}:{}): {}",
+                    item.kind,
+                    item.rust_identifier(),
+                    item.file,
+                    item.line,
+                    requires,
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 fn make_schema_connection(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
     kind: &str,
     index: NodeIndex,
     rust_identifier: &str,
     module_path: &str,
+    schema: Option<&str>,
     schemas: &HashMap<SchemaEntity, NodeIndex>,
 ) -> bool {
     let mut found = false;
     for (schema_item, &schema_index) in schemas {
-        if module_path == schema_item.module_path {
+        let matches = match schema {
+            Some(schema) => schema == schema_item.name,
+            None => module_path == schema_item.module_path,
+        };
+        if matches {
             tracing::debug!(from = ?rust_identifier, to = schema_item.module_path, "Adding {kind} after Schema edge.", kind = kind);
             graph.add_edge(schema_index, index, SqlGraphRelationship::RequiredBy);
             found = true;
@@ -1499,6 +2185,26 @@ fn make_extern_connection(
     found
 }
 
+fn make_domain_connection(
+    graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
+    kind: &str,
+    index: NodeIndex,
+    rust_identifier: &str,
+    ty_id: &TypeId,
+    domains: &HashMap<PostgresDomainEntity, NodeIndex>,
+) -> bool {
+    let mut found = false;
+    for (domain_item, &domain_index) in domains {
+        if domain_item.id_matches(ty_id) {
+            tracing::debug!(from = ?rust_identifier, to = domain_item.full_path, "Adding {kind} after Domain edge.", kind = kind);
+            graph.add_edge(domain_index, index, SqlGraphRelationship::RequiredBy);
+            found = true;
+            break;
+        }
+    }
+    found
+}
+
 fn make_type_or_enum_connection(
     graph: &mut StableGraph<SqlGraphEntity, SqlGraphRelationship>,
     kind: &str,
@@ -1528,3 +2234,199 @@ fn make_type_or_enum_connection(
 
     found
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PgxSql;
+    use crate::sql_entity_graph::{
+        required_extensions, ControlFile, CustomEntity, ExtensionSqlEntity, PositioningRef,
+        RequiredExtensionEntity, SchemaEntity, SqlGraphEntity, TrustedAuditProblem,
+    };
+
+    fn schema(name: &'static str, file: &'static str, line: u32) -> SqlGraphEntity {
+        SqlGraphEntity::Schema(SchemaEntity {
+            module_path: name,
+            name,
+            file,
+            line,
+        })
+    }
+
+    fn build(entities: Vec<SqlGraphEntity>) -> PgxSql {
+        let control = SqlGraphEntity::ExtensionRoot(ControlFile {
+            comment: "a test".to_string(),
+            default_version: "1.0".to_string(),
+            module_pathname: "$libdir/test".to_string(),
+            relocatable: false,
+            superuser: false,
+            schema: None,
+        });
+        PgxSql::build(
+            std::iter::empty(),
+            std::iter::empty(),
+            std::iter::once(control).chain(entities),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn toposort_is_in_source_order() {
+        let entities = vec![
+            schema("zebra", "src/a.rs", 20),
+            schema("yak", "src/b.rs", 1),
+            schema("xerus", "src/a.rs", 3),
+        ];
+        let mut reversed = entities.clone();
+        reversed.reverse();
+
+        let sql = build(entities).to_sql().unwrap();
+        assert_eq!(sql, build(reversed).to_sql().unwrap());
+
+        let positions = ["xerus", "zebra", "yak"]
+            .iter()
+            .map(|name| sql.find(&format!("CREATE SCHEMA IF NOT EXISTS {};", name)))
+            .collect::<Option<Vec<_>>>()
+            .unwrap();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    fn custom(
+        name: &'static str,
+        line: u32,
+        sql: &'static str,
+        requires: Vec<PositioningRef>,
+    ) -> SqlGraphEntity {
+        SqlGraphEntity::Custom(CustomEntity {
+            kind: "server",
+            name,
+            schema: None,
+            module_path: "shop",
+            full_path: name,
+            file: "src/lib.rs",
+            line,
+            sql,
+            to_sql: None,
+            requires,
+        })
+    }
+
+    #[test]
+    fn custom_entities_are_ordered_by_requires() {
+        let mut sales = custom(
+            "sales",
+            1,
+            "CREATE USER MAPPING FOR CURRENT_USER SERVER warehouse;",
+            vec![PositioningRef::Name("warehouse".to_string())],
+        );
+        if let SqlGraphEntity::Custom(sales) = &mut sales {
+            sales.to_sql = Some(|entity, context| {
+                let prefix = context.schema_prefix_for(&context.customs[entity]);
+                Ok(format!(
+                    "CREATE FOREIGN TABLE {}sales () SERVER warehouse;",
+                    prefix
+                ))
+            });
+        }
+        let warehouse = custom(
+            "warehouse",
+            2,
+            "CREATE SERVER warehouse FOREIGN DATA WRAPPER postgres_fdw;",
+            vec![],
+        );
+        let pgx_sql = build(vec![schema("shop", "src/lib.rs", 0), sales, warehouse]);
+        let sql = pgx_sql.to_sql().unwrap();
+
+        let positions = [
+            "CREATE SCHEMA IF NOT EXISTS shop;",
+            "-- server warehouse\nCREATE SERVER warehouse",
+            "CREATE FOREIGN TABLE shop.sales",
+        ]
+        .iter()
+        .map(|sql_fragment| sql.find(sql_fragment))
+        .collect::<Option<Vec<_>>>()
+        .unwrap();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let unknown = custom(
+            "sales",
+            1,
+            "",
+            vec![PositioningRef::Name("nope".to_string())],
+        );
+        let control = SqlGraphEntity::ExtensionRoot(pgx_sql.control.clone());
+        assert!(PgxSql::build(
+            std::iter::empty(),
+            std::iter::empty(),
+            vec![control, unknown].into_iter(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn required_extensions_are_checked_first() {
+        let required = |name, version, line| {
+            SqlGraphEntity::RequiredExtension(RequiredExtensionEntity {
+                name,
+                version,
+                module_path: "test",
+                file: "src/z.rs",
+                line,
+            })
+        };
+        let sql = build(vec![
+            schema("early", "src/a.rs", 1),
+            required("hstore", Some(">=1.4, <2"), 2),
+            required("plpgsql", None, 1),
+        ])
+        .to_sql()
+        .unwrap();
+
+        assert_eq!(required_extensions(&sql), vec!["plpgsql", "hstore"]);
+        assert!(
+            sql.find("extname = 'hstore'").unwrap()
+                < sql.find("CREATE SCHEMA IF NOT EXISTS early;").unwrap()
+        );
+        assert!(sql.contains(
+            "IF NOT (installed_numbers >= ARRAY[1, 4] AND installed_numbers < ARRAY[2]) THEN"
+        ));
+        assert_eq!(sql.matches("installed_numbers :=").count(), 1);
+    }
+
+    #[test]
+    fn trusted_audit() {
+        let sql = SqlGraphEntity::CustomSql(ExtensionSqlEntity {
+            module_path: "test",
+            full_path: "test::setup",
+            sql: "CREATE ROLE test_admin;\n-- SECURITY DEFINER is only mentioned",
+            file: "src/lib.rs",
+            line: 30,
+            name: "setup",
+            bootstrap: false,
+            finalize: false,
+            requires: vec![],
+            creates: vec![],
+        });
+        let findings = build(vec![schema("pg_catalog", "src/lib.rs", 10), sql])
+            .trusted_audit()
+            .unwrap();
+        let problems = findings
+            .iter()
+            .map(|finding| (finding.problem.clone(), finding.location.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                (TrustedAuditProblem::InPgCatalog, Some("src/lib.rs:10")),
+                (
+                    TrustedAuditProblem::SuperuserOnly("CREATE ROLE"),
+                    Some("src/lib.rs:30")
+                ),
+            ]
+        );
+
+        assert!(build(vec![schema("public", "src/lib.rs", 1)])
+            .trusted_audit()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    mapping::RustSqlMapping,
+    pgx_sql::PgxSql,
+    to_sql::{entity::ToSqlConfigEntity, ToSql},
+    SqlGraphEntity, SqlGraphIdentifier,
+};
+
+use eyre::eyre;
+use std::{
+    any::TypeId,
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+/// The output of a [`PostgresDomain`](crate::sql_entity_graph::postgres_domain::PostgresDomain) from `quote::ToTokens::to_tokens`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostgresDomainEntity {
+    pub name: &'static str,
+    /// The schema it's created in, if it's given one rather than its module's
+    pub schema: Option<&'static str>,
+    pub file: &'static str,
+    pub line: u32,
+    pub full_path: &'static str,
+    pub module_path: &'static str,
+    pub mappings: std::collections::HashSet<RustSqlMapping>,
+    pub base_id: TypeId,
+    pub base_source: &'static str,
+    pub base_full_path: &'static str,
+    pub checks: Vec<&'static str>,
+    pub to_sql_config: ToSqlConfigEntity,
+}
+
+impl Hash for PostgresDomainEntity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.full_path.hash(state);
+    }
+}
+
+impl Ord for PostgresDomainEntity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.file
+            .cmp(other.file)
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.full_path.cmp(other.full_path))
+    }
+}
+
+impl PartialOrd for PostgresDomainEntity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PostgresDomainEntity {
+    pub fn id_matches(&self, candidate: &core::any::TypeId) -> bool {
+        self.mappings.iter().any(|tester| *candidate == tester.id)
+    }
+}
+
+impl Into<SqlGraphEntity> for PostgresDomainEntity {
+    fn into(self) -> SqlGraphEntity {
+        SqlGraphEntity::Domain(self)
+    }
+}
+
+impl SqlGraphIdentifier for PostgresDomainEntity {
+    fn dot_identifier(&self) -> String {
+        format!("domain {}", self.full_path)
+    }
+    fn rust_identifier(&self) -> String {
+        self.full_path.to_string()
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl ToSql for PostgresDomainEntity {
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let self_index = context.domains[self];
+        let base = context
+            .rust_to_sql(self.base_id, self.base_source, self.base_full_path)
+            .ok_or_else(|| {
+                eyre!(
+                    "Failed to map base type `{}` to SQL type while building domain `{}`.",
+                    self.base_full_path,
+                    self.full_path
+                )
+            })?;
+        let sql = format!(
+            "\n\
+                    -- {file}:{line}\n\
+                    -- {full_path}\n\
+                    CREATE DOMAIN {schema}{name} AS {base}{checks};\
+                ",
+            schema = context.schema_prefix_for(&self_index),
+            full_path = self.full_path,
+            file = self.file,
+            line = self.line,
+            name = self.name,
+            base = base,
+            checks = self
+                .checks
+                .iter()
+                .map(|check| format!("\n\tCHECK ({})", check))
+                .collect::<String>(),
+        );
+        tracing::trace!(%sql);
+        Ok(sql)
+    }
+}
@@ -0,0 +1,292 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+pub mod entity;
+
+use crate::sql_entity_graph::{PostgresEnum, SqlName, ToSqlConfig};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{
+    parse::{Parse, ParseStream},
+    Attribute, DeriveInput, Ident, ItemStruct, LitStr, Type,
+};
+
+/// A parsed `#[derive(PostgresDomain)]` item.
+///
+/// It should be used with [`syn::parse::Parse`] functions.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a `pgx::datum::sql_entity_graph::PostgresDomainEntity`.
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::PostgresDomain;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let parsed: PostgresDomain = parse_quote! {
+///     #[derive(PostgresDomain)]
+///     #[check("VALUE > 0")]
+///     struct Positive(i32);
+/// };
+/// let sql_graph_entity_tokens = parsed.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// `#[pgx(sql_name = "name", schema = "schema")]` gives the domain another name in SQL, or
+/// creates it in another schema, as [`SqlName`] describes.
+#[derive(Debug, Clone)]
+pub struct PostgresDomain {
+    name: Ident,
+    base: Type,
+    checks: Vec<LitStr>,
+    to_sql_config: ToSqlConfig,
+    sql_name: SqlName,
+}
+
+impl PostgresDomain {
+    pub fn new(
+        name: Ident,
+        base: Type,
+        checks: Vec<LitStr>,
+        to_sql_config: ToSqlConfig,
+        sql_name: SqlName,
+    ) -> Self {
+        Self {
+            name,
+            base,
+            checks,
+            to_sql_config,
+            sql_name,
+        }
+    }
+
+    pub fn from_derive_input(derive_input: DeriveInput) -> Result<Self, syn::Error> {
+        let to_sql_config =
+            ToSqlConfig::from_attributes(derive_input.attrs.as_slice())?.unwrap_or_default();
+        let checks = Self::checks_from_attributes(derive_input.attrs.as_slice())?;
+        let sql_name = SqlName::from_attributes(derive_input.attrs.as_slice())?;
+        let fields = match derive_input.data {
+            syn::Data::Struct(data_struct) => data_struct.fields,
+            syn::Data::Union(_) | syn::Data::Enum(_) => {
+                return Err(syn::Error::new(
+                    derive_input.ident.span(),
+                    "expected a tuple struct",
+                ))
+            }
+        };
+        let base = Self::base_from_fields(&derive_input.ident, fields)?;
+        Ok(Self::new(
+            derive_input.ident,
+            base,
+            checks,
+            to_sql_config,
+            sql_name,
+        ))
+    }
+
+    /// A `text` domain for a `#[derive(PostgresTextEnum)]` enum, whose `CHECK` is that its values
+    /// are one of the enum's labels, and the labels, with the variant each is for
+    ///
+    /// The labels are the variants' names, or their `#[pgx(name = "label")]`, as for a
+    /// [`PostgresEnum`].
+    pub fn from_text_enum(
+        derive_input: DeriveInput,
+    ) -> Result<(Self, Vec<(Ident, String)>), syn::Error> {
+        if let syn::Data::Enum(data_enum) = &derive_input.data {
+            if let Some(variant) = data_enum
+                .variants
+                .iter()
+                .find(|variant| !matches!(variant.fields, syn::Fields::Unit))
+            {
+                return Err(syn::Error::new(
+                    variant.ident.span(),
+                    "#[derive(PostgresTextEnum)] variants can't have fields",
+                ));
+            }
+        }
+        let name = derive_input.ident.clone();
+        let to_sql_config =
+            ToSqlConfig::from_attributes(derive_input.attrs.as_slice())?.unwrap_or_default();
+        let sql_name = SqlName::from_attributes(derive_input.attrs.as_slice())?;
+        let labels = PostgresEnum::from_derive_input(derive_input)?.labels()?;
+        let check = format!(
+            "VALUE IN ({})",
+            labels
+                .iter()
+                .map(|(_, label)| format!("'{}'", label.replace('\'', "''")))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let domain = Self::new(
+            name,
+            syn::parse_quote! { String },
+            vec![LitStr::new(&check, Span::call_site())],
+            to_sql_config,
+            sql_name,
+        );
+        Ok((domain, labels))
+    }
+
+    /// The domain's SQL name and schema
+    pub fn sql_name(&self) -> &SqlName {
+        &self.sql_name
+    }
+
+    /// The domain's base type, the struct's only field
+    fn base_from_fields(name: &Ident, fields: syn::Fields) -> Result<Type, syn::Error> {
+        match fields {
+            syn::Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                Ok(unnamed.unnamed.into_iter().next().unwrap().ty)
+            }
+            _ => Err(syn::Error::new(
+                name.span(),
+                "expected a tuple struct with a single field, the domain's base type",
+            )),
+        }
+    }
+
+    /// The expressions of every `#[check("...")]` attribute
+    fn checks_from_attributes(attrs: &[Attribute]) -> Result<Vec<LitStr>, syn::Error> {
+        attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("check"))
+            .map(|attr| attr.parse_args::<LitStr>())
+            .collect()
+    }
+}
+
+impl Parse for PostgresDomain {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let parsed: ItemStruct = input.parse()?;
+        let to_sql_config =
+            ToSqlConfig::from_attributes(parsed.attrs.as_slice())?.unwrap_or_default();
+        let checks = Self::checks_from_attributes(parsed.attrs.as_slice())?;
+        let sql_name = SqlName::from_attributes(parsed.attrs.as_slice())?;
+        let base = Self::base_from_fields(&parsed.ident, parsed.fields)?;
+        Ok(Self::new(
+            parsed.ident,
+            base,
+            checks,
+            to_sql_config,
+            sql_name,
+        ))
+    }
+}
+
+impl ToTokens for PostgresDomain {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let name = self.name.clone();
+        let base = &self.base;
+        let checks = self.checks.iter();
+        let sql_graph_entity_fn_name = syn::Ident::new(
+            &format!("__pgx_internals_domain_{}", name),
+            Span::call_site(),
+        );
+
+        let to_sql_config = &self.to_sql_config;
+        let sql_name = self.sql_name.name(&name);
+        let schema = self.sql_name.schema_tokens();
+
+        let inv = quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn  #sql_graph_entity_fn_name() -> ::pgx::utils::sql_entity_graph::SqlGraphEntity {
+                extern crate alloc;
+                use alloc::vec::Vec;
+                use alloc::vec;
+                let mut mappings = Default::default();
+                <#name as ::pgx::datum::WithTypeIds>::register_with_refs(&mut mappings, #sql_name.to_string());
+                ::pgx::datum::WithSizedTypeIds::<#name>::register_sized_with_refs(&mut mappings, #sql_name.to_string());
+                ::pgx::datum::WithArrayTypeIds::<#name>::register_array_with_refs(&mut mappings, #sql_name.to_string());
+                ::pgx::datum::WithVarlenaTypeIds::<#name>::register_varlena_with_refs(&mut mappings, #sql_name.to_string());
+
+                let submission = ::pgx::utils::sql_entity_graph::PostgresDomainEntity {
+                    name: #sql_name,
+                    schema: #schema,
+                    file: file!(),
+                    line: line!(),
+                    module_path: module_path!(),
+                    full_path: core::any::type_name::<#name>(),
+                    mappings,
+                    base_id: core::any::TypeId::of::<#base>(),
+                    base_source: stringify!(#base),
+                    base_full_path: core::any::type_name::<#base>(),
+                    checks: vec![ #( #checks ),* ],
+                    to_sql_config: #to_sql_config,
+                };
+                ::pgx::utils::sql_entity_graph::SqlGraphEntity::Domain(submission)
+            }
+        };
+        tokens.append_all(inv);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PostgresDomain;
+    use quote::ToTokens;
+    use syn::parse_quote;
+
+    #[test]
+    fn domain_checks() {
+        let domain: PostgresDomain = parse_quote! {
+            #[check("VALUE > 0")]
+            #[check("VALUE < 100")]
+            struct Percent(i32);
+        };
+        assert_eq!(domain.name, "Percent");
+        assert_eq!(domain.base.to_token_stream().to_string(), "i32");
+        assert_eq!(
+            domain
+                .checks
+                .iter()
+                .map(|check| check.value())
+                .collect::<Vec<_>>(),
+            vec!["VALUE > 0", "VALUE < 100"]
+        );
+    }
+
+    #[test]
+    fn text_enum_domain() {
+        let (domain, labels) = PostgresDomain::from_text_enum(parse_quote! {
+            enum Mode {
+                Fast,
+                #[pgx(name = "don't hurry")]
+                Careful,
+            }
+        })
+        .unwrap();
+        assert_eq!(domain.base.to_token_stream().to_string(), "String");
+        assert_eq!(
+            domain.checks[0].value(),
+            "VALUE IN ('Fast', 'don''t hurry')"
+        );
+        assert_eq!(labels[1].0, "Careful");
+        let err = PostgresDomain::from_text_enum(parse_quote! {
+            enum Mode { Fast(u8) }
+        })
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "#[derive(PostgresTextEnum)] variants can't have fields"
+        );
+    }
+
+    #[test]
+    fn domain_requires_a_single_field() {
+        let domain = syn::parse2::<PostgresDomain>(quote::quote! {
+            struct Pair(i32, i32);
+        });
+        assert!(domain.is_err());
+        let domain = syn::parse2::<PostgresDomain>(quote::quote! {
+            struct Named { value: i32 }
+        });
+        assert!(domain.is_err());
+    }
+}
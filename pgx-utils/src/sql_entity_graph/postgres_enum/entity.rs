@@ -13,6 +13,7 @@ use crate::sql_entity_graph::{
     SqlGraphEntity, SqlGraphIdentifier,
 };
 
+use eyre::eyre;
 use std::{
     cmp::Ordering,
     hash::{Hash, Hasher},
@@ -22,6 +23,8 @@ use std::{
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PostgresEnumEntity {
     pub name: &'static str,
+    /// The schema it's created in, if it's given one rather than its module's
+    pub schema: Option<&'static str>,
     pub file: &'static str,
     pub line: u32,
     pub full_path: &'static str,
@@ -41,7 +44,8 @@ impl Ord for PostgresEnumEntity {
     fn cmp(&self, other: &Self) -> Ordering {
         self.file
             .cmp(other.file)
-            .then_with(|| self.file.cmp(other.file))
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.full_path.cmp(other.full_path))
     }
 }
 
@@ -55,6 +59,96 @@ impl PostgresEnumEntity {
     pub fn id_matches(&self, candidate: &core::any::TypeId) -> bool {
         self.mappings.iter().any(|tester| *candidate == tester.id)
     }
+
+    /// The `ALTER TYPE ... ADD VALUE` statements which bring the enum, as created with
+    /// `previous_labels`, up to date, each adding a label where it belongs in the sort order.
+    ///
+    /// Postgres can't remove enum labels, or reorder them, so it's an error if the enum no longer
+    /// has one of `previous_labels`, or has them in a different order.
+    pub fn add_value_sql(
+        &self,
+        qualified_name: &str,
+        previous_labels: &[String],
+    ) -> eyre::Result<Vec<String>> {
+        if let Some(removed) = previous_labels
+            .iter()
+            .find(|label| !self.variants.contains(&label.as_str()))
+        {
+            return Err(eyre!(
+                "Enum `{}` no longer has the label `{}`, and Postgres can't remove it.",
+                self.full_path,
+                removed
+            ));
+        }
+        let kept = self
+            .variants
+            .iter()
+            .filter(|label| previous_labels.iter().any(|previous| previous == *label))
+            .collect::<Vec<_>>();
+        if kept
+            .iter()
+            .zip(previous_labels)
+            .any(|(kept, previous)| *kept != previous)
+        {
+            return Err(eyre!(
+                "Enum `{}` has reordered its labels, and Postgres can't reorder them.",
+                self.full_path
+            ));
+        }
+
+        let mut statements = Vec::new();
+        for (position, label) in self.variants.iter().enumerate() {
+            if previous_labels.iter().any(|previous| previous == label) {
+                continue;
+            }
+            let next_existing = self.variants[position + 1..]
+                .iter()
+                .find(|next| previous_labels.iter().any(|previous| previous == *next));
+            statements.push(format!(
+                "ALTER TYPE {name} ADD VALUE IF NOT EXISTS {label}{position};",
+                name = qualified_name,
+                label = quote_label(label),
+                position = match next_existing {
+                    Some(next) => format!(" BEFORE {}", quote_label(next)),
+                    None => String::default(),
+                },
+            ));
+        }
+        Ok(statements)
+    }
+}
+
+pub(crate) fn quote_label(label: &str) -> String {
+    format!("'{}'", label.replace('\'', "''"))
+}
+
+/// The labels of the enum `qualified_name` in the `CREATE TYPE ... AS ENUM` statement generated
+/// for it in `sql`, if there is one
+pub(crate) fn labels_in_sql(sql: &str, qualified_name: &str) -> Option<Vec<String>> {
+    let create = format!("CREATE TYPE {} AS ENUM (", qualified_name);
+    let start = sql.find(&create)? + create.len();
+    let mut labels = Vec::new();
+    let mut chars = sql[start..].chars().peekable();
+    loop {
+        match chars.next()? {
+            '\'' => {
+                let mut label = String::new();
+                loop {
+                    match chars.next()? {
+                        '\'' if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            label.push('\'');
+                        }
+                        '\'' => break,
+                        other => label.push(other),
+                    }
+                }
+                labels.push(label);
+            }
+            ')' => return Some(labels),
+            _ => (),
+        }
+    }
 }
 
 impl Into<SqlGraphEntity> for PostgresEnumEntity {
@@ -100,7 +194,7 @@ impl ToSql for PostgresEnumEntity {
             variants = self
                 .variants
                 .iter()
-                .map(|variant| format!("\t'{}'", variant))
+                .map(|variant| format!("\t{}", quote_label(variant)))
                 .collect::<Vec<_>>()
                 .join(",\n")
                 + "\n",
@@ -8,12 +8,16 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 pub mod entity;
 
-use crate::sql_entity_graph::ToSqlConfig;
+use crate::sql_entity_graph::{
+    pgx_attribute::{ArgValue, PgxArg, PgxAttribute},
+    SqlName, ToSqlConfig,
+};
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{quote, ToTokens, TokenStreamExt};
 use syn::{
     parse::{Parse, ParseStream},
-    DeriveInput, Generics, ItemEnum,
+    spanned::Spanned,
+    DeriveInput, Generics, ItemEnum, Lit,
 };
 use syn::{punctuated::Punctuated, Ident, Token};
 
@@ -23,6 +27,11 @@ use syn::{punctuated::Punctuated, Ident, Token};
 ///
 /// Using [`quote::ToTokens`] will output the declaration for a `pgx::datum::sql_entity_graph::PostgresEnumEntity`.
 ///
+/// Variants accept `#[pgx(name = "label")]`, to use a label other than the variant's name, and
+/// `#[pgx(order = 1)]`, to place it elsewhere in the enum's sort order than its position, which is
+/// otherwise used.  The enum itself accepts `#[pgx(sql_name = "name", schema = "schema")]`, as
+/// [`SqlName`] describes.
+///
 /// ```rust
 /// use syn::{Macro, parse::Parse, parse_quote, parse};
 /// use quote::{quote, ToTokens};
@@ -33,6 +42,8 @@ use syn::{punctuated::Punctuated, Ident, Token};
 ///     #[derive(PostgresEnum)]
 ///     enum Demo {
 ///         Example,
+///         #[pgx(name = "another example")]
+///         AnotherExample,
 ///     }
 /// };
 /// let sql_graph_entity_tokens = parsed.to_token_stream();
@@ -45,6 +56,7 @@ pub struct PostgresEnum {
     generics: Generics,
     variants: Punctuated<syn::Variant, Token![,]>,
     to_sql_config: ToSqlConfig,
+    sql_name: SqlName,
 }
 
 impl PostgresEnum {
@@ -53,30 +65,103 @@ impl PostgresEnum {
         generics: Generics,
         variants: Punctuated<syn::Variant, Token![,]>,
         to_sql_config: ToSqlConfig,
+        sql_name: SqlName,
     ) -> Self {
         Self {
             name,
             generics,
             variants,
             to_sql_config,
+            sql_name,
         }
     }
 
     pub fn from_derive_input(derive_input: DeriveInput) -> Result<Self, syn::Error> {
         let to_sql_config =
             ToSqlConfig::from_attributes(derive_input.attrs.as_slice())?.unwrap_or_default();
+        let sql_name = SqlName::from_attributes(derive_input.attrs.as_slice())?;
         let data_enum = match derive_input.data {
             syn::Data::Enum(data_enum) => data_enum,
             syn::Data::Union(_) | syn::Data::Struct(_) => {
                 return Err(syn::Error::new(derive_input.ident.span(), "expected enum"))
             }
         };
-        Ok(Self::new(
+        let parsed = Self::new(
             derive_input.ident,
             derive_input.generics,
             data_enum.variants,
             to_sql_config,
-        ))
+            sql_name,
+        );
+        parsed.labels()?;
+        Ok(parsed)
+    }
+
+    /// The enum's SQL name and schema
+    pub fn sql_name(&self) -> &SqlName {
+        &self.sql_name
+    }
+
+    /// The enum's labels, with the variant each is for, in the order Postgres sorts them
+    pub fn labels(&self) -> Result<Vec<(Ident, String)>, syn::Error> {
+        let mut labels = Vec::with_capacity(self.variants.len());
+        for (position, variant) in self.variants.iter().enumerate() {
+            let mut label = variant.ident.to_string();
+            let mut order = position as i64;
+            let mut explicit = false;
+            for attr in variant
+                .attrs
+                .iter()
+                .filter(|attr| attr.path.is_ident("pgx"))
+            {
+                let attr = attr.parse_args::<PgxAttribute>()?;
+                for arg in attr.args {
+                    match arg {
+                        PgxArg::NameValue(nv) if nv.path.is_ident("name") => match nv.value {
+                            ArgValue::Lit(Lit::Str(name)) => label = name.value(),
+                            _ => {
+                                return Err(syn::Error::new(
+                                    nv.path.span(),
+                                    "expected `#[pgx(name = \"label\")]`",
+                                ))
+                            }
+                        },
+                        PgxArg::NameValue(nv) if nv.path.is_ident("order") => match nv.value {
+                            ArgValue::Lit(Lit::Int(value)) => {
+                                order = value.base10_parse()?;
+                                explicit = true;
+                            }
+                            _ => {
+                                return Err(syn::Error::new(
+                                    nv.path.span(),
+                                    "expected `#[pgx(order = 1)]`",
+                                ))
+                            }
+                        },
+                        _ => {
+                            return Err(syn::Error::new(
+                                variant.span(),
+                                "enum variants only accept `#[pgx(name = \"label\", order = 1)]`",
+                            ))
+                        }
+                    }
+                }
+            }
+            if labels.iter().any(|(_, _, _, existing)| *existing == label) {
+                return Err(syn::Error::new(
+                    variant.span(),
+                    format!("the label `{}` is already used by another variant", label),
+                ));
+            }
+            labels.push((order, explicit, variant.ident.clone(), label));
+        }
+        // a variant given a position comes before the one declared there, and the sort is stable,
+        // so variants otherwise keep the order they're declared in
+        labels.sort_by_key(|(order, explicit, _, _)| (*order, !*explicit));
+        Ok(labels
+            .into_iter()
+            .map(|(_, _, ident, label)| (ident, label))
+            .collect())
     }
 }
 
@@ -85,12 +170,16 @@ impl Parse for PostgresEnum {
         let parsed: ItemEnum = input.parse()?;
         let to_sql_config =
             ToSqlConfig::from_attributes(parsed.attrs.as_slice())?.unwrap_or_default();
-        Ok(Self::new(
+        let sql_name = SqlName::from_attributes(parsed.attrs.as_slice())?;
+        let parsed = Self::new(
             parsed.ident,
             parsed.generics,
             parsed.variants,
             to_sql_config,
-        ))
+            sql_name,
+        );
+        parsed.labels()?;
+        Ok(parsed)
     }
 }
 
@@ -104,11 +193,18 @@ impl ToTokens for PostgresEnum {
         }
         let (_impl_generics, ty_generics, _where_clauses) = static_generics.split_for_impl();
 
-        let variants = self.variants.iter();
+        // checked when parsed
+        let labels = self
+            .labels()
+            .expect("invalid enum labels")
+            .into_iter()
+            .map(|(_, label)| label);
         let sql_graph_entity_fn_name =
             syn::Ident::new(&format!("__pgx_internals_enum_{}", name), Span::call_site());
 
         let to_sql_config = &self.to_sql_config;
+        let sql_name = self.sql_name.name(&name);
+        let schema = self.sql_name.schema_tokens();
 
         let inv = quote! {
             #[no_mangle]
@@ -118,19 +214,20 @@ impl ToTokens for PostgresEnum {
                 use alloc::vec::Vec;
                 use alloc::vec;
                 let mut mappings = Default::default();
-                <#name #ty_generics as ::pgx::datum::WithTypeIds>::register_with_refs(&mut mappings, stringify!(#name).to_string());
-                ::pgx::datum::WithSizedTypeIds::<#name #ty_generics>::register_sized_with_refs(&mut mappings, stringify!(#name).to_string());
-                ::pgx::datum::WithArrayTypeIds::<#name #ty_generics>::register_array_with_refs(&mut mappings, stringify!(#name).to_string());
-                ::pgx::datum::WithVarlenaTypeIds::<#name #ty_generics>::register_varlena_with_refs(&mut mappings, stringify!(#name).to_string());
+                <#name #ty_generics as ::pgx::datum::WithTypeIds>::register_with_refs(&mut mappings, #sql_name.to_string());
+                ::pgx::datum::WithSizedTypeIds::<#name #ty_generics>::register_sized_with_refs(&mut mappings, #sql_name.to_string());
+                ::pgx::datum::WithArrayTypeIds::<#name #ty_generics>::register_array_with_refs(&mut mappings, #sql_name.to_string());
+                ::pgx::datum::WithVarlenaTypeIds::<#name #ty_generics>::register_varlena_with_refs(&mut mappings, #sql_name.to_string());
 
                 let submission = ::pgx::utils::sql_entity_graph::PostgresEnumEntity {
-                    name: stringify!(#name),
+                    name: #sql_name,
+                    schema: #schema,
                     file: file!(),
                     line: line!(),
                     module_path: module_path!(),
                     full_path: core::any::type_name::<#name #ty_generics>(),
                     mappings,
-                    variants: vec![ #(  stringify!(#variants)  ),* ],
+                    variants: vec![ #( #labels ),* ],
                     to_sql_config: #to_sql_config,
                 };
                 ::pgx::utils::sql_entity_graph::SqlGraphEntity::Enum(submission)
@@ -139,3 +236,158 @@ impl ToTokens for PostgresEnum {
         tokens.append_all(inv);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        entity::{labels_in_sql, PostgresEnumEntity},
+        PostgresEnum,
+    };
+    use syn::parse_quote;
+
+    fn entity(variants: Vec<&'static str>) -> PostgresEnumEntity {
+        PostgresEnumEntity {
+            name: "Status",
+            schema: None,
+            file: file!(),
+            line: line!(),
+            full_path: "demo::Status",
+            module_path: "demo",
+            mappings: Default::default(),
+            variants,
+            to_sql_config: Default::default(),
+        }
+    }
+
+    fn labels(previous: &[&str]) -> Vec<String> {
+        previous.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[test]
+    fn enum_labels() {
+        let parsed: PostgresEnum = parse_quote! {
+            enum Status {
+                #[pgx(name = "in progress")]
+                InProgress,
+                Done,
+                #[pgx(order = 0)]
+                Todo,
+            }
+        };
+        let labels = parsed
+            .labels()
+            .unwrap()
+            .into_iter()
+            .map(|(ident, label)| (ident.to_string(), label))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec![
+                ("Todo".to_string(), "Todo".to_string()),
+                ("InProgress".to_string(), "in progress".to_string()),
+                ("Done".to_string(), "Done".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_labels_are_unique() {
+        let parsed = syn::parse2::<PostgresEnum>(quote::quote! {
+            enum Status {
+                #[pgx(name = "Done")]
+                Finished,
+                Done,
+            }
+        });
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn enum_labels_in_sql() {
+        let sql = "\n-- src/lib.rs:1\n-- demo::Status\nCREATE TYPE demo.Status AS ENUM (\n\t'Todo',\n\t'it''s done'\n);\n";
+        assert_eq!(
+            labels_in_sql(sql, "demo.Status"),
+            Some(labels(&["Todo", "it's done"]))
+        );
+        assert_eq!(labels_in_sql(sql, "Status"), None);
+    }
+
+    #[test]
+    fn enum_labels_in_sql_of_several_enums() {
+        let sql = "CREATE TYPE demo.Colour AS ENUM (\n\t'red (ish)',\n\t'blue'\n);\n\
+                   CREATE TYPE demo.Status AS ENUM (\n\t'Todo'\n);\n\
+                   CREATE TYPE demo.Empty AS ENUM (\n);\n";
+        assert_eq!(
+            labels_in_sql(sql, "demo.Colour"),
+            Some(labels(&["red (ish)", "blue"]))
+        );
+        assert_eq!(labels_in_sql(sql, "demo.Status"), Some(labels(&["Todo"])));
+        assert_eq!(labels_in_sql(sql, "demo.Empty"), Some(vec![]));
+
+        // cut off before the end of the statement
+        assert_eq!(
+            labels_in_sql("CREATE TYPE demo.Status AS ENUM (\n\t'Todo'", "demo.Status"),
+            None
+        );
+        assert_eq!(
+            labels_in_sql("CREATE TYPE demo.Status AS ENUM (\n\t'To", "demo.Status"),
+            None
+        );
+    }
+
+    #[test]
+    fn enum_add_value_sql() {
+        let statements = entity(vec!["Todo", "Blocked", "Done", "Archived"])
+            .add_value_sql("demo.Status", &labels(&["Todo", "Done"]))
+            .unwrap();
+        assert_eq!(
+            statements,
+            vec![
+                "ALTER TYPE demo.Status ADD VALUE IF NOT EXISTS 'Blocked' BEFORE 'Done';",
+                "ALTER TYPE demo.Status ADD VALUE IF NOT EXISTS 'Archived';",
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_add_value_sql_placement() {
+        // labels added next to each other are each placed before the next one which exists
+        let statements = entity(vec!["New", "Todo", "Blocked", "Stalled", "Done"])
+            .add_value_sql("Status", &labels(&["Todo", "Done"]))
+            .unwrap();
+        assert_eq!(
+            statements,
+            vec![
+                "ALTER TYPE Status ADD VALUE IF NOT EXISTS 'New' BEFORE 'Todo';",
+                "ALTER TYPE Status ADD VALUE IF NOT EXISTS 'Blocked' BEFORE 'Done';",
+                "ALTER TYPE Status ADD VALUE IF NOT EXISTS 'Stalled' BEFORE 'Done';",
+            ]
+        );
+
+        let status = entity(vec!["it's", "Todo"]);
+        assert_eq!(
+            status.add_value_sql("Status", &[]).unwrap(),
+            vec![
+                "ALTER TYPE Status ADD VALUE IF NOT EXISTS 'it''s';",
+                "ALTER TYPE Status ADD VALUE IF NOT EXISTS 'Todo';",
+            ]
+        );
+        assert_eq!(
+            status
+                .add_value_sql("Status", &labels(&["it's", "Todo"]))
+                .unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn enum_add_value_sql_rejects_changes() {
+        let status = entity(vec!["Done", "Todo"]);
+        assert!(status
+            .add_value_sql("Status", &labels(&["Todo", "Done"]))
+            .is_err());
+        assert!(status
+            .add_value_sql("Status", &labels(&["Todo", "Blocked"]))
+            .is_err());
+    }
+}
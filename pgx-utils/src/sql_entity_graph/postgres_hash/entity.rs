@@ -7,6 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use crate::sql_entity_graph::{
+    operator_family::default_keyword,
     pgx_sql::PgxSql,
     to_sql::{entity::ToSqlConfigEntity, ToSql},
     SqlGraphEntity, SqlGraphIdentifier,
@@ -23,19 +24,31 @@ pub struct PostgresHashEntity {
     pub module_path: &'static str,
     pub id: core::any::TypeId,
     pub to_sql_config: ToSqlConfigEntity,
+    /// Is the operator class the type's `DEFAULT` hash one?
+    pub default_opclass: bool,
+    /// The existing hash operator families the type's operator and support functions are also
+    /// added to
+    pub families: &'static [&'static str],
+    /// Does Postgres take an extended hash function?  It does from Postgres 11 on
+    pub extended: bool,
 }
 
 impl PostgresHashEntity {
     pub(crate) fn fn_name(&self) -> String {
         format!("{}_hash", self.name.to_lowercase())
     }
+
+    pub(crate) fn extended_fn_name(&self) -> String {
+        format!("{}_hash_extended", self.name.to_lowercase())
+    }
 }
 
 impl Ord for PostgresHashEntity {
     fn cmp(&self, other: &Self) -> Ordering {
         self.file
             .cmp(other.file)
-            .then_with(|| self.file.cmp(other.file))
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.full_path.cmp(other.full_path))
     }
 }
 
@@ -69,21 +82,54 @@ impl SqlGraphIdentifier for PostgresHashEntity {
 }
 
 impl ToSql for PostgresHashEntity {
-    #[tracing::instrument(level = "debug", err, skip(self, _context), fields(identifier = %self.rust_identifier()))]
-    fn to_sql(&self, _context: &PgxSql) -> eyre::Result<String> {
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let type_name = context
+            .qualified_type_name_for(&context.hashes[self], self.id)
+            .unwrap_or_else(|| self.name.to_string());
+        let extended_fn = if self.extended {
+            format!(
+                ",\n\tFUNCTION    2   {extended_fn_name}({type_name}, bigint)",
+                type_name = type_name,
+                extended_fn_name = self.extended_fn_name(),
+            )
+        } else {
+            String::new()
+        };
+        let families = self
+            .families
+            .iter()
+            .map(|family| {
+                format!(
+                    "\n\
+                    ALTER OPERATOR FAMILY {family} USING hash ADD\n\
+                        \tOPERATOR    1   =  ({type_name}, {type_name}),\n\
+                        \tFUNCTION    1   {fn_name}({type_name}){extended_fn};\
+                    ",
+                    family = family,
+                    type_name = type_name,
+                    fn_name = self.fn_name(),
+                    extended_fn = extended_fn,
+                )
+            })
+            .collect::<String>();
         let sql = format!("\n\
                             -- {file}:{line}\n\
                             -- {full_path}\n\
                             CREATE OPERATOR FAMILY {name}_hash_ops USING hash;\n\
-                            CREATE OPERATOR CLASS {name}_hash_ops DEFAULT FOR TYPE {name} USING hash FAMILY {name}_hash_ops AS\n\
-                                \tOPERATOR    1   =  ({name}, {name}),\n\
-                                \tFUNCTION    1   {fn_name}({name});\
-                            ",
+                            CREATE OPERATOR CLASS {name}_hash_ops{default} FOR TYPE {type_name} USING hash FAMILY {name}_hash_ops AS\n\
+                                \tOPERATOR    1   =  ({type_name}, {type_name}),\n\
+                                \tFUNCTION    1   {fn_name}({type_name}){extended_fn};\
+                            {families}",
                           name = self.name,
+                          default = default_keyword(self.default_opclass),
+                          type_name = type_name,
                           full_path = self.full_path,
                           file = self.file,
                           line = self.line,
                           fn_name = self.fn_name(),
+                          extended_fn = extended_fn,
+                          families = families,
         );
         tracing::trace!(%sql);
         Ok(sql)
@@ -15,7 +15,7 @@ use syn::{
     DeriveInput, Ident,
 };
 
-use crate::sql_entity_graph::ToSqlConfig;
+use crate::sql_entity_graph::{OperatorFamilies, ToSqlConfig};
 
 /// A parsed `#[derive(PostgresHash)]` item.
 ///
@@ -64,20 +64,23 @@ use crate::sql_entity_graph::ToSqlConfig;
 pub struct PostgresHash {
     pub name: Ident,
     pub to_sql_config: ToSqlConfig,
+    pub families: OperatorFamilies,
 }
 
 impl PostgresHash {
-    pub fn new(name: Ident, to_sql_config: ToSqlConfig) -> Self {
+    pub fn new(name: Ident, to_sql_config: ToSqlConfig, families: OperatorFamilies) -> Self {
         Self {
             name,
             to_sql_config,
+            families,
         }
     }
 
     pub fn from_derive_input(derive_input: DeriveInput) -> Result<Self, syn::Error> {
         let to_sql_config =
             ToSqlConfig::from_attributes(derive_input.attrs.as_slice())?.unwrap_or_default();
-        Ok(Self::new(derive_input.ident, to_sql_config))
+        let families = OperatorFamilies::from_attributes(derive_input.attrs.as_slice())?;
+        Ok(Self::new(derive_input.ident, to_sql_config, families))
     }
 }
 
@@ -92,7 +95,8 @@ impl Parse for PostgresHash {
             _ => return Err(syn::Error::new(input.span(), "expected enum or struct")),
         };
         let to_sql_config = ToSqlConfig::from_attributes(attrs)?.unwrap_or_default();
-        Ok(Self::new(ident, to_sql_config))
+        let families = OperatorFamilies::from_attributes(attrs)?;
+        Ok(Self::new(ident, to_sql_config, families))
     }
 }
 
@@ -104,6 +108,8 @@ impl ToTokens for PostgresHash {
             Span::call_site(),
         );
         let to_sql_config = &self.to_sql_config;
+        let default_opclass = self.families.default_opclass;
+        let families = self.families.hash_tokens();
         let inv = quote! {
             #[no_mangle]
             #[doc(hidden)]
@@ -120,6 +126,9 @@ impl ToTokens for PostgresHash {
                     module_path: module_path!(),
                     id: TypeId::of::<#name>(),
                     to_sql_config: #to_sql_config,
+                    default_opclass: #default_opclass,
+                    families: #families,
+                    extended: ::pgx::pg_sys::PG_VERSION_NUM >= 110000,
                 };
                 ::pgx::utils::sql_entity_graph::SqlGraphEntity::Hash(submission)
             }
@@ -7,6 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use crate::sql_entity_graph::{
+    operator_family::default_keyword,
     pgx_sql::PgxSql,
     to_sql::{entity::ToSqlConfigEntity, ToSql},
     SqlGraphEntity, SqlGraphIdentifier,
@@ -23,6 +24,11 @@ pub struct PostgresOrdEntity {
     pub module_path: &'static str,
     pub id: core::any::TypeId,
     pub to_sql_config: ToSqlConfigEntity,
+    /// Is the operator class the type's `DEFAULT` btree one?
+    pub default_opclass: bool,
+    /// The existing btree operator families the type's operators and support functions are also
+    /// added to, such as `integer_ops`
+    pub families: &'static [&'static str],
 }
 
 impl PostgresOrdEntity {
@@ -30,6 +36,10 @@ impl PostgresOrdEntity {
         format!("{}_cmp", self.name.to_lowercase())
     }
 
+    pub(crate) fn sortsupport_fn_name(&self) -> String {
+        format!("{}_sortsupport", self.name.to_lowercase())
+    }
+
     pub(crate) fn lt_fn_name(&self) -> String {
         format!("{}_lt", self.name.to_lowercase())
     }
@@ -49,13 +59,39 @@ impl PostgresOrdEntity {
     pub(crate) fn ge_fn_name(&self) -> String {
         format!("{}_ge", self.name.to_lowercase())
     }
+
+    /// The `ALTER OPERATOR FAMILY .. ADD` adding the type's operators and support functions to
+    /// each of [`families`](Self::families)
+    fn families_sql(&self, type_name: &str) -> String {
+        self.families
+            .iter()
+            .map(|family| {
+                format!("\n\
+                        ALTER OPERATOR FAMILY {family} USING btree ADD\n\
+                            \tOPERATOR 1 < ({type_name}, {type_name}),\n\
+                            \tOPERATOR 2 <= ({type_name}, {type_name}),\n\
+                            \tOPERATOR 3 = ({type_name}, {type_name}),\n\
+                            \tOPERATOR 4 >= ({type_name}, {type_name}),\n\
+                            \tOPERATOR 5 > ({type_name}, {type_name}),\n\
+                            \tFUNCTION 1 ({type_name}, {type_name}) {cmp_fn_name}({type_name}, {type_name}),\n\
+                            \tFUNCTION 2 ({type_name}, {type_name}) {sortsupport_fn_name}(internal);\
+                        ",
+                        family = family,
+                        type_name = type_name,
+                        cmp_fn_name = self.cmp_fn_name(),
+                        sortsupport_fn_name = self.sortsupport_fn_name(),
+                )
+            })
+            .collect()
+    }
 }
 
 impl Ord for PostgresOrdEntity {
     fn cmp(&self, other: &Self) -> Ordering {
         self.file
             .cmp(other.file)
-            .then_with(|| self.file.cmp(other.file))
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.full_path.cmp(other.full_path))
     }
 }
 
@@ -89,25 +125,33 @@ impl SqlGraphIdentifier for PostgresOrdEntity {
 }
 
 impl ToSql for PostgresOrdEntity {
-    #[tracing::instrument(level = "debug", err, skip(self, _context), fields(identifier = %self.rust_identifier()))]
-    fn to_sql(&self, _context: &PgxSql) -> eyre::Result<String> {
+    #[tracing::instrument(level = "debug", err, skip(self, context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, context: &PgxSql) -> eyre::Result<String> {
+        let type_name = context
+            .qualified_type_name_for(&context.ords[self], self.id)
+            .unwrap_or_else(|| self.name.to_string());
         let sql = format!("\n\
                             -- {file}:{line}\n\
                             -- {full_path}\n\
                             CREATE OPERATOR FAMILY {name}_btree_ops USING btree;\n\
-                            CREATE OPERATOR CLASS {name}_btree_ops DEFAULT FOR TYPE {name} USING btree FAMILY {name}_btree_ops AS\n\
+                            CREATE OPERATOR CLASS {name}_btree_ops{default} FOR TYPE {type_name} USING btree FAMILY {name}_btree_ops AS\n\
                                   \tOPERATOR 1 <,\n\
                                   \tOPERATOR 2 <=,\n\
                                   \tOPERATOR 3 =,\n\
                                   \tOPERATOR 4 >=,\n\
                                   \tOPERATOR 5 >,\n\
-                                  \tFUNCTION 1 {cmp_fn_name}({name}, {name});\
-                            ",
+                                  \tFUNCTION 1 {cmp_fn_name}({type_name}, {type_name}),\n\
+                                  \tFUNCTION 2 {sortsupport_fn_name}(internal);\
+                            {families}",
                           name = self.name,
+                          default = default_keyword(self.default_opclass),
+                          type_name = type_name,
                           full_path = self.full_path,
                           file = self.file,
                           line = self.line,
                           cmp_fn_name = self.cmp_fn_name(),
+                          sortsupport_fn_name = self.sortsupport_fn_name(),
+                          families = self.families_sql(&type_name),
         );
         tracing::trace!(%sql);
         Ok(sql)
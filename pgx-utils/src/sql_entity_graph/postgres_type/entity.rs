@@ -23,6 +23,8 @@ use std::{
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PostgresTypeEntity {
     pub name: &'static str,
+    /// The schema it's created in, if it's given one rather than its module's
+    pub schema: Option<&'static str>,
     pub file: &'static str,
     pub line: u32,
     pub full_path: &'static str,
@@ -33,6 +35,10 @@ pub struct PostgresTypeEntity {
     pub out_fn: &'static str,
     pub out_fn_module_path: String,
     pub to_sql_config: ToSqlConfigEntity,
+    /// The `STORAGE` of its values, if it's given one rather than `extended`
+    pub storage: Option<&'static str>,
+    /// The `ALIGNMENT` of its values, if it's given one rather than `int4`
+    pub alignment: Option<&'static str>,
 }
 
 impl Hash for PostgresTypeEntity {
@@ -45,7 +51,8 @@ impl Ord for PostgresTypeEntity {
     fn cmp(&self, other: &Self) -> Ordering {
         self.file
             .cmp(other.file)
-            .then_with(|| self.file.cmp(other.file))
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.full_path.cmp(other.full_path))
     }
 }
 
@@ -189,7 +196,8 @@ impl ToSql for PostgresTypeEntity {
                                     \tINTERNALLENGTH = variable,\n\
                                     \tINPUT = {schema_prefix_in_fn}{in_fn}, /* {in_fn_path} */\n\
                                     \tOUTPUT = {schema_prefix_out_fn}{out_fn}, /* {out_fn_path} */\n\
-                                    \tSTORAGE = extended\n\
+                                    {alignment}\
+                                    \tSTORAGE = {storage}\n\
                                 );\
                             ",
                                         full_path = item.full_path,
@@ -203,6 +211,8 @@ impl ToSql for PostgresTypeEntity {
                                         schema_prefix_out_fn = context.schema_prefix_for(&out_fn_graph_index),
                                         out_fn = item.out_fn,
                                         out_fn_path = out_fn_path,
+                                        alignment = item.alignment.map(|alignment| format!("\tALIGNMENT = {},\n", alignment)).unwrap_or_default(),
+                                        storage = item.storage.unwrap_or("extended"),
         );
         tracing::trace!(sql = %materialized_type);
 
@@ -7,6 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 pub mod entity;
+pub mod storage;
 
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
 use quote::{quote, ToTokens, TokenStreamExt};
@@ -19,7 +20,8 @@ use syn::{
     DeriveInput, Generics, ItemStruct,
 };
 
-use crate::sql_entity_graph::ToSqlConfig;
+use crate::sql_entity_graph::{SqlName, ToSqlConfig};
+use storage::TypeStorage;
 
 /// A parsed `#[derive(PostgresType)]` item.
 ///
@@ -43,6 +45,10 @@ use crate::sql_entity_graph::ToSqlConfig;
 /// # Ok(())
 /// # }
 /// ```
+///
+/// `#[pgx(sql_name = "name", schema = "schema")]` gives the type another name in SQL, or creates
+/// it in another schema, as [`SqlName`] describes, and `#[pgx(storage = "main", alignment =
+/// "double")]` sets how its values are stored, as [`TypeStorage`] describes.
 #[derive(Debug, Clone)]
 pub struct PostgresType {
     name: Ident,
@@ -50,6 +56,8 @@ pub struct PostgresType {
     in_fn: Ident,
     out_fn: Ident,
     to_sql_config: ToSqlConfig,
+    sql_name: SqlName,
+    storage: TypeStorage,
 }
 
 impl PostgresType {
@@ -59,6 +67,8 @@ impl PostgresType {
         in_fn: Ident,
         out_fn: Ident,
         to_sql_config: ToSqlConfig,
+        sql_name: SqlName,
+        storage: TypeStorage,
     ) -> Self {
         Self {
             generics,
@@ -66,6 +76,8 @@ impl PostgresType {
             in_fn,
             out_fn,
             to_sql_config,
+            sql_name,
+            storage,
         }
     }
 
@@ -81,6 +93,8 @@ impl PostgresType {
         };
         let to_sql_config =
             ToSqlConfig::from_attributes(derive_input.attrs.as_slice())?.unwrap_or_default();
+        let sql_name = SqlName::from_attributes(derive_input.attrs.as_slice())?;
+        let storage = TypeStorage::from_attributes(derive_input.attrs.as_slice())?;
         let funcname_in = Ident::new(
             &format!("{}_in", derive_input.ident).to_lowercase(),
             derive_input.ident.span(),
@@ -95,6 +109,8 @@ impl PostgresType {
             funcname_in,
             funcname_out,
             to_sql_config,
+            sql_name,
+            storage,
         ))
     }
 
@@ -118,6 +134,8 @@ impl Parse for PostgresType {
         let parsed: ItemStruct = input.parse()?;
         let to_sql_config =
             ToSqlConfig::from_attributes(parsed.attrs.as_slice())?.unwrap_or_default();
+        let sql_name = SqlName::from_attributes(parsed.attrs.as_slice())?;
+        let storage = TypeStorage::from_attributes(parsed.attrs.as_slice())?;
         let funcname_in = Ident::new(
             &format!("{}_in", parsed.ident).to_lowercase(),
             parsed.ident.span(),
@@ -132,6 +150,8 @@ impl Parse for PostgresType {
             funcname_in,
             funcname_out,
             to_sql_config,
+            sql_name,
+            storage,
         ))
     }
 }
@@ -154,6 +174,10 @@ impl ToTokens for PostgresType {
         );
 
         let to_sql_config = &self.to_sql_config;
+        let sql_name = self.sql_name.name(name);
+        let schema = self.sql_name.schema_tokens();
+        let storage = self.storage.storage_tokens();
+        let alignment = self.storage.alignment_tokens();
 
         let inv = quote! {
             #[no_mangle]
@@ -167,22 +191,23 @@ impl ToTokens for PostgresType {
                 let mut mappings = Default::default();
                 <#name #ty_generics as pgx::datum::WithTypeIds>::register_with_refs(
                     &mut mappings,
-                    stringify!(#name).to_string()
+                    #sql_name.to_string()
                 );
                 pgx::datum::WithSizedTypeIds::<#name #ty_generics>::register_sized_with_refs(
                     &mut mappings,
-                    stringify!(#name).to_string()
+                    #sql_name.to_string()
                 );
                 pgx::datum::WithArrayTypeIds::<#name #ty_generics>::register_array_with_refs(
                     &mut mappings,
-                    stringify!(#name).to_string()
+                    #sql_name.to_string()
                 );
                 pgx::datum::WithVarlenaTypeIds::<#name #ty_generics>::register_varlena_with_refs(
                     &mut mappings,
-                    stringify!(#name).to_string()
+                    #sql_name.to_string()
                 );
                 let submission = ::pgx::utils::sql_entity_graph::PostgresTypeEntity {
-                    name: stringify!(#name),
+                    name: #sql_name,
+                    schema: #schema,
                     file: file!(),
                     line: line!(),
                     module_path: module_path!(),
@@ -203,6 +228,8 @@ impl ToTokens for PostgresType {
                         path_items.join("::")
                     },
                     to_sql_config: #to_sql_config,
+                    storage: #storage,
+                    alignment: #alignment,
                 };
                 ::pgx::utils::sql_entity_graph::SqlGraphEntity::Type(submission)
             }
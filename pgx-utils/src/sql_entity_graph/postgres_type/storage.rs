@@ -0,0 +1,122 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::pgx_attribute::{ArgValue, PgxArg, PgxAttribute};
+use syn::{spanned::Spanned, Attribute, Lit};
+
+/// The `STORAGE` strategies of a `CREATE TYPE`
+const STORAGES: &[&str] = &["plain", "main", "external", "extended"];
+
+/// The `ALIGNMENT`s of a `CREATE TYPE`
+const ALIGNMENTS: &[&str] = &["char", "int2", "int4", "double"];
+
+/// A parsed `#[pgx(storage = "..", alignment = "..")]` of a `#[derive(PostgresType)]`, which sets
+/// how Postgres stores its values, in the `STORAGE` and `ALIGNMENT` of its `CREATE TYPE`.
+///
+/// Postgres has no compression method for a type, only for a column, which
+/// `ALTER TABLE .. ALTER COLUMN .. SET COMPRESSION` sets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TypeStorage {
+    /// `plain`, `main`, `external`, or `extended`, which is the default
+    pub storage: Option<String>,
+    /// `char`, `int2`, `int4`, which is the default, or `double`
+    pub alignment: Option<String>,
+}
+
+impl TypeStorage {
+    /// Used to parse the storage from a set of item attributes
+    pub fn from_attributes(attrs: &[Attribute]) -> Result<Self, syn::Error> {
+        let mut storage = TypeStorage::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+            let attr = attr.parse_args::<PgxAttribute>()?;
+            for arg in attr.args {
+                let nv = match arg {
+                    PgxArg::NameValue(nv) => nv,
+                    _ => continue,
+                };
+                let (slot, allowed) = if nv.path.is_ident("storage") {
+                    (&mut storage.storage, STORAGES)
+                } else if nv.path.is_ident("alignment") {
+                    (&mut storage.alignment, ALIGNMENTS)
+                } else {
+                    continue;
+                };
+                match &nv.value {
+                    ArgValue::Lit(Lit::Str(value))
+                        if allowed.contains(&value.value().to_lowercase().as_str()) =>
+                    {
+                        *slot = Some(value.value().to_lowercase())
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            nv.path.span(),
+                            format!(
+                                "expected one of {}",
+                                allowed
+                                    .iter()
+                                    .map(|allowed| format!("\"{}\"", allowed))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            ),
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(storage)
+    }
+
+    /// The `Option<&'static str>` of the storage, for an entity
+    pub fn storage_tokens(&self) -> proc_macro2::TokenStream {
+        option_tokens(&self.storage)
+    }
+
+    /// The `Option<&'static str>` of the alignment, for an entity
+    pub fn alignment_tokens(&self) -> proc_macro2::TokenStream {
+        option_tokens(&self.alignment)
+    }
+}
+
+fn option_tokens(value: &Option<String>) -> proc_macro2::TokenStream {
+    match value {
+        Some(value) => quote::quote! { Some(#value) },
+        None => quote::quote! { None },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypeStorage;
+    use syn::{parse_quote, DeriveInput};
+
+    #[test]
+    fn storage_and_alignment() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(PostgresType)]
+            #[pgx(storage = "EXTERNAL", alignment = "double")]
+            struct Document { body: String }
+        };
+        let storage = TypeStorage::from_attributes(&input.attrs).unwrap();
+        assert_eq!(storage.storage.as_deref(), Some("external"));
+        assert_eq!(storage.alignment.as_deref(), Some("double"));
+    }
+
+    #[test]
+    fn storage_must_be_a_strategy() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(PostgresType)]
+            #[pgx(storage = "compressed")]
+            struct Document { body: String }
+        };
+        let err = TypeStorage::from_attributes(&input.attrs).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected one of \"plain\", \"main\", \"external\", \"extended\""
+        );
+    }
+}
@@ -0,0 +1,129 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::{
+    pgx_sql::PgxSql, required_extension::parse_version_requirement, to_sql::ToSql, SqlGraphEntity,
+    SqlGraphIdentifier,
+};
+
+use eyre::eyre;
+
+/// What starts the SQL of each [`RequiredExtensionEntity`], followed by the extension's name, so
+/// `cargo pgx install` can find them in the schema to put in the control file's `requires`
+const REQUIRES_EXTENSION_COMMENT: &str = "-- requires extension: ";
+
+/// The output of a [`RequiredExtension`](crate::sql_entity_graph::RequiredExtension) from
+/// `quote::ToTokens::to_tokens`.
+///
+/// It's emitted before everything but the extension root, as a check that the extension is
+/// installed, in a version which meets the requirement, which fails `CREATE EXTENSION` if not.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RequiredExtensionEntity {
+    pub name: &'static str,
+    /// A version requirement like `>=1.4, <2`
+    pub version: Option<&'static str>,
+    pub module_path: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+}
+
+impl From<RequiredExtensionEntity> for SqlGraphEntity {
+    fn from(entity: RequiredExtensionEntity) -> Self {
+        SqlGraphEntity::RequiredExtension(entity)
+    }
+}
+
+impl SqlGraphIdentifier for RequiredExtensionEntity {
+    fn dot_identifier(&self) -> String {
+        format!("requires {}", self.name)
+    }
+    fn rust_identifier(&self) -> String {
+        format!("requires_extension!({:?})", self.name)
+    }
+
+    fn file(&self) -> Option<&'static str> {
+        Some(self.file)
+    }
+
+    fn line(&self) -> Option<u32> {
+        Some(self.line)
+    }
+}
+
+impl ToSql for RequiredExtensionEntity {
+    #[tracing::instrument(level = "debug", err, skip(self, _context), fields(identifier = %self.rust_identifier()))]
+    fn to_sql(&self, _context: &PgxSql) -> eyre::Result<String> {
+        let version_check = match self.version {
+            Some(version) => {
+                let comparisons = parse_version_requirement(version).map_err(|e| eyre!(e))?;
+                let condition = comparisons
+                    .iter()
+                    .map(|(operator, numbers)| {
+                        format!(
+                            "installed_numbers {} ARRAY[{}]",
+                            operator,
+                            numbers
+                                .iter()
+                                .map(|number| number.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" AND ");
+                format!(
+                    "\n\
+                    \tinstalled_numbers := pg_catalog.string_to_array(pg_catalog.substring(installed, '^[0-9]+(?:\\.[0-9]+)*'), '.')::pg_catalog.int4[];\n\
+                    \tIF NOT ({condition}) THEN\n\
+                    \t\tRAISE EXCEPTION 'this extension requires version {version} of the \"{name}\" extension, but version % is installed', installed\n\
+                    \t\t\tUSING HINT = 'Update it with ALTER EXTENSION ... UPDATE';\n\
+                    \tEND IF;\
+                    ",
+                    condition = condition,
+                    version = version,
+                    name = self.name,
+                )
+            }
+            None => String::new(),
+        };
+        let sql = format!(
+            "\n\
+            -- {file}:{line}\n\
+            {comment}{name}{version}\n\
+            DO $$\n\
+            DECLARE\n\
+            \tinstalled pg_catalog.text := (SELECT extversion FROM pg_catalog.pg_extension WHERE extname = '{name}');\n\
+            \tinstalled_numbers pg_catalog.int4[];\n\
+            BEGIN\n\
+            \tIF installed IS NULL THEN\n\
+            \t\tRAISE EXCEPTION 'this extension requires the \"{name}\" extension, which is not installed'\n\
+            \t\t\tUSING HINT = 'Create it first, or use CREATE EXTENSION ... CASCADE';\n\
+            \tEND IF;{version_check}\n\
+            END\n\
+            $$;\
+            ",
+            file = self.file,
+            line = self.line,
+            comment = REQUIRES_EXTENSION_COMMENT,
+            name = self.name,
+            version = self.version.map(|v| format!(" {}", v)).unwrap_or_default(),
+            version_check = version_check,
+        );
+        tracing::trace!(%sql);
+        Ok(sql)
+    }
+}
+
+/// The names of the extensions the SQL of an extension's `requires_extension!()`s checks for, in
+/// the order they're checked
+pub fn required_extensions(sql: &str) -> Vec<&str> {
+    sql.lines()
+        .filter_map(|line| line.strip_prefix(REQUIRES_EXTENSION_COMMENT))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .collect()
+}
@@ -0,0 +1,172 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+pub mod entity;
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::{
+    parse::{Parse, ParseStream},
+    Ident, LitStr, Token,
+};
+
+/// A parsed `requires_extension!()` item.
+///
+/// It should be used with [`syn::parse::Parse`] functions.
+///
+/// Using [`quote::ToTokens`] will output the declaration for a
+/// `pgx::utils::sql_entity_graph::RequiredExtensionEntity`.
+///
+/// ```rust
+/// use syn::{Macro, parse::Parse, parse_quote, parse};
+/// use quote::{quote, ToTokens};
+/// use pgx_utils::sql_entity_graph::RequiredExtension;
+///
+/// # fn main() -> eyre::Result<()> {
+/// let parsed: Macro = parse_quote! {
+///     requires_extension!("hstore", ">=1.4")
+/// };
+/// let inner_tokens = parsed.tokens;
+/// let inner: RequiredExtension = parse_quote! {
+///     #inner_tokens
+/// };
+/// let sql_graph_entity_tokens = inner.to_token_stream();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequiredExtension {
+    pub name: LitStr,
+    pub version: Option<LitStr>,
+}
+
+impl Parse for RequiredExtension {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let name: LitStr = input.parse()?;
+        let valid = !name.value().is_empty()
+            && name
+                .value()
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(syn::Error::new(
+                name.span(),
+                "an extension's name is letters, digits, `_`, and `-`",
+            ));
+        }
+        let version = if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let version: LitStr = input.parse()?;
+            parse_version_requirement(&version.value())
+                .map_err(|message| syn::Error::new(version.span(), message))?;
+            let _trailing_comma: Option<Token![,]> = input.parse()?;
+            Some(version)
+        } else {
+            None
+        };
+        Ok(Self { name, version })
+    }
+}
+
+impl ToTokens for RequiredExtension {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let name = &self.name;
+        let version = match &self.version {
+            Some(version) => quote! { Some(#version) },
+            None => quote! { None },
+        };
+        let sql_graph_entity_fn_name = Ident::new(
+            &format!(
+                "__pgx_internals_required_extension_{}",
+                name.value().replace('-', "_")
+            ),
+            Span::call_site(),
+        );
+        let inv = quote! {
+            #[no_mangle]
+            #[doc(hidden)]
+            pub extern "C" fn #sql_graph_entity_fn_name() -> ::pgx::utils::sql_entity_graph::SqlGraphEntity {
+                let submission = ::pgx::utils::sql_entity_graph::RequiredExtensionEntity {
+                    name: #name,
+                    version: #version,
+                    module_path: module_path!(),
+                    file: file!(),
+                    line: line!(),
+                };
+                ::pgx::utils::sql_entity_graph::SqlGraphEntity::RequiredExtension(submission)
+            }
+        };
+        tokens.append_all(inv);
+    }
+}
+
+/// The comparisons of a version requirement like `>=1.4, <2`, each an operator and the numbers of
+/// a version.  A version without an operator has to be matched exactly.
+pub(crate) fn parse_version_requirement(
+    requirement: &str,
+) -> Result<Vec<(&'static str, Vec<u32>)>, String> {
+    let mut comparisons = Vec::new();
+    for comparison in requirement.split(',') {
+        let comparison = comparison.trim();
+        let operator = [">=", "<=", ">", "<", "="]
+            .iter()
+            .find(|operator| comparison.starts_with(**operator))
+            .copied();
+        let version = comparison[operator.map(str::len).unwrap_or(0)..].trim();
+        let numbers = version
+            .split('.')
+            .map(|number| number.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                format!(
+                    "`{}` isn't a version requirement like `>=1.4`, or `>=1.4, <2`",
+                    requirement
+                )
+            })?;
+        comparisons.push((operator.unwrap_or("="), numbers));
+    }
+    Ok(comparisons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_version_requirement, RequiredExtension};
+    use quote::ToTokens;
+    use syn::parse_quote;
+
+    #[test]
+    fn version_requirements() {
+        assert_eq!(
+            parse_version_requirement(">=1.4").unwrap(),
+            vec![(">=", vec![1, 4])]
+        );
+        assert_eq!(
+            parse_version_requirement(">= 1.4, <2").unwrap(),
+            vec![(">=", vec![1, 4]), ("<", vec![2])]
+        );
+        assert_eq!(
+            parse_version_requirement("1.0").unwrap(),
+            vec![("=", vec![1, 0])]
+        );
+        assert!(parse_version_requirement("").is_err());
+        assert!(parse_version_requirement("~1.4").is_err());
+        assert!(parse_version_requirement(">=1.4beta").is_err());
+    }
+
+    #[test]
+    fn required_extension_fn() {
+        let required: RequiredExtension = parse_quote! { "uuid-ossp", ">=1.1" };
+        let tokens = required.to_token_stream().to_string();
+        assert!(tokens.contains("__pgx_internals_required_extension_uuid_ossp"));
+        assert!(tokens.contains("version : Some (\">=1.1\")"));
+
+        let required: RequiredExtension = parse_quote! { "hstore" };
+        assert!(required.version.is_none());
+        assert!(syn::parse_str::<RequiredExtension>(r#""hstore; DROP", "1""#).is_err());
+        assert!(syn::parse_str::<RequiredExtension>(r#""hstore", "newest""#).is_err());
+    }
+}
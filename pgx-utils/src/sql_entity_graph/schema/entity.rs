@@ -23,7 +23,8 @@ impl Ord for SchemaEntity {
     fn cmp(&self, other: &Self) -> Ordering {
         self.file
             .cmp(other.file)
-            .then_with(|| self.file.cmp(other.file))
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.name.cmp(other.name))
     }
 }
 
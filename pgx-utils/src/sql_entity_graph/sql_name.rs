@@ -0,0 +1,121 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use crate::sql_entity_graph::pgx_attribute::{ArgValue, PgxArg, PgxAttribute};
+use syn::{spanned::Spanned, Attribute, Ident, Lit, LitStr};
+
+/// A parsed `#[pgx(sql_name = "..", schema = "..")]` of a `#[derive(PostgresType)]`,
+/// `#[derive(PostgresEnum)]`, or `#[derive(PostgresDomain)]`, which gives the type another name in
+/// SQL than its Rust name, or creates it in another schema than the one of its module.
+///
+/// The other arguments of the `#[pgx]` attribute, like `sql`, are left to the rest of the derive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SqlName {
+    pub name: Option<LitStr>,
+    pub schema: Option<LitStr>,
+}
+
+impl SqlName {
+    /// Used to parse the SQL name from a set of item attributes
+    pub fn from_attributes(attrs: &[Attribute]) -> Result<Self, syn::Error> {
+        let mut sql_name = SqlName::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("pgx")) {
+            let attr = attr.parse_args::<PgxAttribute>()?;
+            for arg in attr.args {
+                let nv = match arg {
+                    PgxArg::NameValue(nv) => nv,
+                    _ => continue,
+                };
+                let slot = if nv.path.is_ident("sql_name") {
+                    &mut sql_name.name
+                } else if nv.path.is_ident("schema") {
+                    &mut sql_name.schema
+                } else {
+                    continue;
+                };
+                match nv.value {
+                    ArgValue::Lit(Lit::Str(value)) if !value.value().is_empty() => {
+                        *slot = Some(value)
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            nv.path.span(),
+                            "expected `#[pgx(sql_name = \"name\", schema = \"schema\")]`",
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(sql_name)
+    }
+
+    /// The type's name in SQL, which is `ident` unless it's given another
+    pub fn name(&self, ident: &Ident) -> String {
+        self.name
+            .as_ref()
+            .map(|name| name.value())
+            .unwrap_or_else(|| ident.to_string())
+    }
+
+    /// The type's name in SQL, qualified by its schema if it's given one, to look it up with
+    /// `regtypein()`
+    pub fn qualified_name(&self, ident: &Ident) -> String {
+        match &self.schema {
+            Some(schema) => format!("{}.{}", schema.value(), self.name(ident)),
+            None => self.name(ident),
+        }
+    }
+
+    /// The `Option<&'static str>` of the schema, for an entity
+    pub fn schema_tokens(&self) -> proc_macro2::TokenStream {
+        match &self.schema {
+            Some(schema) => quote::quote! { Some(#schema) },
+            None => quote::quote! { None },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqlName;
+    use syn::{parse_quote, DeriveInput};
+
+    #[test]
+    fn sql_name_and_schema() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(PostgresEnum)]
+            #[pgx(sql_name = "order_status", schema = "shop")]
+            enum OrderStatus { Open, Closed }
+        };
+        let sql_name = SqlName::from_attributes(&input.attrs).unwrap();
+        assert_eq!(sql_name.name(&input.ident), "order_status");
+        assert_eq!(sql_name.qualified_name(&input.ident), "shop.order_status");
+    }
+
+    #[test]
+    fn defaults_to_the_rust_name() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(PostgresType)]
+            #[pgx(sql = false)]
+            struct Dog { treats: i64 }
+        };
+        let sql_name = SqlName::from_attributes(&input.attrs).unwrap();
+        assert_eq!(sql_name, SqlName::default());
+        assert_eq!(sql_name.qualified_name(&input.ident), "Dog");
+    }
+
+    #[test]
+    fn sql_name_must_be_a_string() {
+        let input: DeriveInput = parse_quote! {
+            #[derive(PostgresDomain)]
+            #[pgx(sql_name = positive)]
+            struct Positive(i32);
+        };
+        assert!(SqlName::from_attributes(&input.attrs).is_err());
+    }
+}
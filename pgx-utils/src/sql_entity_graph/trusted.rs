@@ -0,0 +1,125 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use regex::Regex;
+
+/// Something about an entity which keeps the extension from being marked `trusted = true` in its
+/// control file, found by [`PgxSql::trusted_audit()`][crate::sql_entity_graph::PgxSql::trusted_audit].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedAuditFinding {
+    /// The entity's Rust identifier, like `fn my_extension::helper`
+    pub entity: String,
+    /// Where the entity is declared, like `src/lib.rs:10`, if it's known
+    pub location: Option<String>,
+    pub problem: TrustedAuditProblem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustedAuditProblem {
+    /// It's created in `pg_catalog`, which only a superuser may create objects in
+    InPgCatalog,
+    /// Its SQL has a statement or clause, such as `CREATE LANGUAGE`, which only a superuser may
+    /// run, or which would let whoever installs the extension act as one
+    SuperuserOnly(&'static str),
+    /// It's a function marked `#[pg_extern(untrusted)]`
+    Untrusted,
+}
+
+impl std::fmt::Display for TrustedAuditFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{}: ", location)?;
+        }
+        match &self.problem {
+            TrustedAuditProblem::InPgCatalog => {
+                write!(f, "`{}` is created in `pg_catalog`", self.entity)
+            }
+            TrustedAuditProblem::SuperuserOnly(construct) => {
+                write!(f, "`{}` uses `{}`", self.entity, construct)
+            }
+            TrustedAuditProblem::Untrusted => {
+                write!(f, "`{}` is marked `untrusted`", self.entity)
+            }
+        }
+    }
+}
+
+/// The statements and clauses a trusted extension's SQL may not have, and how they're named
+const SUPERUSER_ONLY: &[(&str, &str)] = &[
+    (
+        r"\bCREATE\s+(OR\s+REPLACE\s+)?(TRUSTED\s+)?(PROCEDURAL\s+)?LANGUAGE\b",
+        "CREATE LANGUAGE",
+    ),
+    (r"\bCREATE\s+EVENT\s+TRIGGER\b", "CREATE EVENT TRIGGER"),
+    (r"\bALTER\s+SYSTEM\b", "ALTER SYSTEM"),
+    (r"\b(CREATE|ALTER)\s+(ROLE|USER)\b", "CREATE ROLE"),
+    (r"\bCREATE\s+TABLESPACE\b", "CREATE TABLESPACE"),
+    (
+        r"\bCREATE\s+FOREIGN\s+DATA\s+WRAPPER\b",
+        "CREATE FOREIGN DATA WRAPPER",
+    ),
+    (r"\bCREATE\s+SUBSCRIPTION\b", "CREATE SUBSCRIPTION"),
+    (r"\bCOPY\b[^;]*\b(FROM|TO)\s+PROGRAM\b", "COPY ... PROGRAM"),
+    (r"\bLOAD\s+'", "LOAD"),
+    (r"\bSECURITY\s+DEFINER\b", "SECURITY DEFINER"),
+    (
+        r"\bSET\s+SESSION\s+AUTHORIZATION\b",
+        "SET SESSION AUTHORIZATION",
+    ),
+];
+
+/// The superuser-only statements and clauses in `sql`, ignoring its `--` comments
+pub(crate) fn superuser_only_constructs(sql: &str) -> Vec<&'static str> {
+    let sql = sql
+        .lines()
+        .map(|line| match line.find("--") {
+            Some(comment) => &line[..comment],
+            None => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    SUPERUSER_ONLY
+        .iter()
+        .filter(|(pattern, _)| {
+            Regex::new(&format!("(?i){}", pattern))
+                .expect("invalid superuser-only regex")
+                .is_match(&sql)
+        })
+        .map(|(_, construct)| *construct)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::superuser_only_constructs;
+
+    #[test]
+    fn finds_superuser_only_constructs() {
+        assert_eq!(
+            superuser_only_constructs(
+                "CREATE OR REPLACE FUNCTION f() RETURNS void\nSECURITY DEFINER\nLANGUAGE sql AS '';\n\
+                 copy t from\n  program 'ls';"
+            ),
+            vec!["COPY ... PROGRAM", "SECURITY DEFINER"]
+        );
+        assert_eq!(
+            superuser_only_constructs("create trusted language plfoo handler foo;"),
+            vec!["CREATE LANGUAGE"]
+        );
+    }
+
+    #[test]
+    fn ignores_comments_and_ordinary_sql() {
+        assert!(superuser_only_constructs(
+            "-- src/lib.rs:1\n-- ALTER SYSTEM isn't run\n\
+             CREATE TABLE users (role text); -- CREATE ROLE neither\n\
+             CREATE FUNCTION f() RETURNS void LANGUAGE c AS 'MODULE_PATHNAME', 'f_wrapper';"
+        )
+        .is_empty());
+    }
+}
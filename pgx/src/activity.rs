@@ -0,0 +1,115 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Reporting what a backend is doing, in `pg_stat_activity` and its process title, so that it's
+//! visible to whoever is monitoring the cluster
+use crate::misc::cstring;
+use crate::pg_sys;
+
+/// The `state` column of `pg_stat_activity`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PgBackendState {
+    /// `idle`
+    Idle,
+    /// `active`
+    Running,
+    /// `idle in transaction`
+    IdleInTransaction,
+    /// `idle in transaction (aborted)`
+    IdleInTransactionAborted,
+}
+
+impl From<PgBackendState> for pg_sys::BackendState {
+    fn from(state: PgBackendState) -> Self {
+        match state {
+            PgBackendState::Idle => pg_sys::BackendState_STATE_IDLE,
+            PgBackendState::Running => pg_sys::BackendState_STATE_RUNNING,
+            PgBackendState::IdleInTransaction => pg_sys::BackendState_STATE_IDLEINTRANSACTION,
+            PgBackendState::IdleInTransactionAborted => {
+                pg_sys::BackendState_STATE_IDLEINTRANSACTION_ABORTED
+            }
+        }
+    }
+}
+
+/// Set this backend's `state` and `query` in `pg_stat_activity`, as a background worker would
+/// for each unit of work.  `activity` is truncated to `track_activity_query_size`.
+///
+/// This does nothing if `track_activities` is off
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// fn process_queue() {
+///     report_activity(PgBackendState::Running, "processing queue");
+///     // ...
+///     report_activity(PgBackendState::Idle, "");
+/// }
+/// ```
+pub fn report_activity(state: PgBackendState, activity: &str) {
+    let activity = cstring(activity, "activity");
+    unsafe { pg_sys::pgstat_report_activity(state.into(), activity.as_ptr()) }
+}
+
+/// Report `activity` as this backend's `query` in `pg_stat_activity` while `f` runs, then put the
+/// query that's actually running back, so that a long-running function can show its progress
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn rebuild_everything() {
+///     for step in 1..=3 {
+///         with_activity(&format!("rebuild_everything(): step {} of 3", step), || {
+///             // ...
+///         });
+///     }
+/// }
+/// ```
+pub fn with_activity<R, F: FnOnce() -> R>(activity: &str, f: F) -> R {
+    struct RestoreActivity;
+
+    impl Drop for RestoreActivity {
+        fn drop(&mut self) {
+            unsafe {
+                let query = std::ptr::addr_of!(pg_sys::debug_query_string).read();
+                if !query.is_null() {
+                    pg_sys::pgstat_report_activity(pg_sys::BackendState_STATE_RUNNING, query);
+                }
+            }
+        }
+    }
+
+    report_activity(PgBackendState::Running, activity);
+    let _restore = RestoreActivity;
+    f()
+}
+
+/// Set the `application_name` this backend shows in `pg_stat_activity`, without changing the
+/// setting itself, as background workers do to tell themselves apart
+pub fn report_application_name(name: &str) {
+    let name = cstring(name, "application_name");
+    unsafe { pg_sys::pgstat_report_appname(name.as_ptr()) }
+}
+
+/// Set the activity shown in this backend's process title, as `ps` and `top` show it, if
+/// `update_process_title` is on
+pub fn set_process_title(activity: &str) {
+    let activity = cstring(activity, "activity");
+
+    #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+    unsafe {
+        pg_sys::set_ps_display(activity.as_ptr(), false)
+    }
+
+    #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+    unsafe {
+        pg_sys::set_ps_display(activity.as_ptr())
+    }
+}
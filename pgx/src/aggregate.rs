@@ -269,6 +269,7 @@ CREATE AGGREGATE DemoSum (
 */
 
 use crate::{
+    datum::Internal,
     error,
     memcxt::PgMemoryContexts,
     pg_sys::{AggCheckCallContext, CurrentMemoryContext, FunctionCallInfo, MemoryContext},
@@ -298,6 +299,11 @@ where
     /// this should be set to `PgVarlena<Self>`.
     ///
     /// Other types are supported as well, this can be useful if multiple aggregates share a state.
+    ///
+    /// Other than [`Internal`], every state is converted to and from its SQL representation on
+    /// each call.  An [`Internal`] state is only a pointer, so it's passed along as-is, which
+    /// makes it the best choice for anything large, like a collection.  Use
+    /// [`Aggregate::state_or_insert_with()`] to allocate it in the aggregate context.
     type State;
 
     /// The type of the argument(s).
@@ -433,6 +439,87 @@ where
         }
     }
 
+    /// The aggregate context, which lasts for as long as the current group's state does.  The
+    /// transition function itself runs in a context which Postgres resets after every row.
+    ///
+    /// Raises an `ERROR` when not called as part of an aggregate.
+    #[inline(always)]
+    fn aggregate_context(fcinfo: FunctionCallInfo) -> PgMemoryContexts {
+        let aggregate_memory_context =
+            unsafe { Self::memory_context(fcinfo) }.unwrap_or_else(|| {
+                error!("Cannot access Aggregate memory contexts when not an aggregate.")
+            });
+        PgMemoryContexts::For(aggregate_memory_context)
+    }
+
+    /// Get the value of an [`Internal`] state, first setting it to the result of `f`, which is
+    /// allocated in the [`Aggregate::aggregate_context()`] no matter which memory context is
+    /// current.
+    ///
+    /// The value is dropped when Postgres is done with the group: at the end of the query, when
+    /// the context is reset for a new set of groups, or, since Postgres 13, between the batches
+    /// of a hash aggregate which has spilled to disk.  Spilled rows are aggregated into a new
+    /// state, so a state never has to be written out.  Memory the value allocates itself isn't
+    /// counted against `work_mem` when deciding whether to spill, however.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    /// use std::collections::HashSet;
+    ///
+    /// pub struct DistinctCount;
+    ///
+    /// #[pg_aggregate]
+    /// impl Aggregate for DistinctCount {
+    ///     type Args = i64;
+    ///     type State = Internal;
+    ///     type Finalize = i64;
+    ///
+    ///     fn state(
+    ///         mut current: Self::State,
+    ///         arg: Self::Args,
+    ///         fcinfo: pg_sys::FunctionCallInfo,
+    ///     ) -> Self::State {
+    ///         let seen = unsafe { Self::state_or_insert_default::<HashSet<i64>>(&mut current, fcinfo) };
+    ///         seen.insert(arg);
+    ///         current
+    ///     }
+    ///
+    ///     fn finalize(
+    ///         mut current: Self::State,
+    ///         _direct_args: Self::OrderedSetArgs,
+    ///         fcinfo: pg_sys::FunctionCallInfo,
+    ///     ) -> Self::Finalize {
+    ///         let seen = unsafe { Self::state_or_insert_default::<HashSet<i64>>(&mut current, fcinfo) };
+    ///         seen.len() as i64
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// ## Safety
+    ///
+    /// An initialized `current` must already point to a `T`, as with [`Internal::get_or_insert_with()`].
+    #[inline(always)]
+    unsafe fn state_or_insert_with<T: 'static, F: FnOnce() -> T>(
+        current: &mut Internal,
+        fcinfo: FunctionCallInfo,
+        f: F,
+    ) -> &mut T {
+        current.get_or_insert_with_in(&mut Self::aggregate_context(fcinfo), f)
+    }
+
+    /// [`Aggregate::state_or_insert_with()`], starting from `T::default()`
+    ///
+    /// ## Safety
+    ///
+    /// An initialized `current` must already point to a `T`, as with [`Internal::get_or_insert_with()`].
+    #[inline(always)]
+    unsafe fn state_or_insert_default<T: Default + 'static>(
+        current: &mut Internal,
+        fcinfo: FunctionCallInfo,
+    ) -> &mut T {
+        Self::state_or_insert_with(current, fcinfo, T::default)
+    }
+
     #[inline(always)]
     fn in_memory_context<
         R,
@@ -441,10 +528,6 @@ where
         fcinfo: FunctionCallInfo,
         f: F,
     ) -> R {
-        let aggregate_memory_context =
-            unsafe { Self::memory_context(fcinfo) }.unwrap_or_else(|| {
-                error!("Cannot access Aggregate memory contexts when not an aggregate.")
-            });
-        PgMemoryContexts::For(aggregate_memory_context).switch_to(f)
+        Self::aggregate_context(fcinfo).switch_to(f)
     }
 }
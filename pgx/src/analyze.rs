@@ -0,0 +1,272 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Supplying the rows `ANALYZE` samples from relations Postgres can't read itself, such as
+//! foreign tables, so they get statistics like any other table
+use crate::{elog, pg_sys, PgLogLevel, PgMemoryContexts, PgRelation};
+use std::os::raw::c_int;
+
+/// A relation which hands its own rows to `ANALYZE`, which picks a random sample of them to
+/// gather statistics from.
+///
+/// This is how an FDW's `AnalyzeForeignTable` callback works, and [`analyze_foreign_table()`]
+/// is one, for `T`:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// struct Numbers;
+///
+/// impl AcquireSampleRows for Numbers {
+///     fn total_pages(_relation: &PgRelation) -> pg_sys::BlockNumber {
+///         10
+///     }
+///
+///     fn acquire_sample_rows(_relation: &PgRelation, sample: &mut PgAnalyzeSample) {
+///         for i in 1..=1000i32 {
+///             sample.add_row(&[i as pg_sys::Datum], &[false]);
+///         }
+///     }
+/// }
+///
+/// unsafe fn init_fdw_routine(routine: &mut pg_sys::FdwRoutine) {
+///     routine.AnalyzeForeignTable = Some(analyze_foreign_table::<Numbers>);
+/// }
+/// ```
+pub trait AcquireSampleRows {
+    /// About how many pages the relation takes up, for scaling per-page estimates
+    fn total_pages(relation: &PgRelation) -> pg_sys::BlockNumber;
+
+    /// Add every row of the relation to `sample`, which keeps a random selection of them, like
+    /// Postgres does when it reads a table
+    fn acquire_sample_rows(relation: &PgRelation, sample: &mut PgAnalyzeSample);
+}
+
+/// An FDW `AnalyzeForeignTable` callback, which has `ANALYZE` sample the foreign table through
+/// `T`
+///
+/// # Safety
+///
+/// Only Postgres should call this, with the arguments it gives `AnalyzeForeignTable`
+pub unsafe extern "C" fn analyze_foreign_table<T: AcquireSampleRows>(
+    relation: pg_sys::Relation,
+    func: *mut pg_sys::AcquireSampleRowsFunc,
+    totalpages: *mut pg_sys::BlockNumber,
+) -> bool {
+    pg_sys::guard(|| {
+        let relation = PgRelation::from_pg(relation);
+        *func = Some(acquire_sample_rows::<T>);
+        *totalpages = T::total_pages(&relation);
+        true
+    })
+}
+
+/// An `AcquireSampleRowsFunc`, which samples the relation's rows through `T`
+///
+/// # Safety
+///
+/// Only Postgres should call this, with the arguments it gives an `AcquireSampleRowsFunc`
+pub unsafe extern "C" fn acquire_sample_rows<T: AcquireSampleRows>(
+    relation: pg_sys::Relation,
+    elevel: c_int,
+    rows: *mut pg_sys::HeapTuple,
+    targrows: c_int,
+    totalrows: *mut f64,
+    totaldeadrows: *mut f64,
+) -> c_int {
+    pg_sys::guard(|| {
+        let relation = PgRelation::from_pg(relation);
+        let mut sample = PgAnalyzeSample::new(&relation, rows, targrows);
+        T::acquire_sample_rows(&relation, &mut sample);
+
+        // `ANALYZE VERBOSE` asks for `INFO`, otherwise it's `DEBUG2`
+        let level = if elevel == pg_sys::INFO as c_int {
+            PgLogLevel::INFO
+        } else {
+            PgLogLevel::DEBUG2
+        };
+        elog(
+            level,
+            &format!(
+                "\"{}\": table contains {:.0} rows, {} rows in sample",
+                relation.name(),
+                sample.total_rows(),
+                sample.len()
+            ),
+        );
+
+        *totalrows = sample.total_rows();
+        *totaldeadrows = sample.dead_rows();
+        sample.len() as c_int
+    })
+}
+
+/// The rows `ANALYZE` will gather statistics from: a random selection of those added to it, of
+/// up to [`PgAnalyzeSample::target_rows()`], chosen the same way Postgres chooses them from a
+/// table
+pub struct PgAnalyzeSample {
+    tupdesc: pg_sys::TupleDesc,
+    rows: *mut pg_sys::HeapTuple,
+    target_rows: usize,
+    len: usize,
+    total_rows: f64,
+    dead_rows: f64,
+    rows_to_skip: f64,
+    reservoir: pg_sys::ReservoirStateData,
+    memory_context: PgMemoryContexts,
+}
+
+impl PgAnalyzeSample {
+    /// A sample of `relation`'s rows, kept in `rows`, which has room for `target_rows` of them.
+    /// They're allocated in the `CurrentMemoryContext`, where `ANALYZE` expects them.
+    ///
+    /// # Safety
+    ///
+    /// `rows` must have room for `target_rows` rows, and outlive the sample
+    pub unsafe fn new(
+        relation: &PgRelation,
+        rows: *mut pg_sys::HeapTuple,
+        target_rows: c_int,
+    ) -> Self {
+        let target_rows = target_rows.max(0);
+        let mut reservoir = pg_sys::ReservoirStateData::default();
+        pg_sys::reservoir_init_selection_state(&mut reservoir, target_rows);
+        PgAnalyzeSample {
+            tupdesc: relation.rd_att,
+            rows,
+            target_rows: target_rows as usize,
+            len: 0,
+            total_rows: 0.0,
+            dead_rows: 0.0,
+            rows_to_skip: -1.0,
+            reservoir,
+            memory_context: PgMemoryContexts::For(PgMemoryContexts::CurrentMemoryContext.value()),
+        }
+    }
+
+    /// Add a live row, as a value, and whether it's `NULL`, for each of the relation's
+    /// attributes, in order
+    pub fn add_row(&mut self, values: &[pg_sys::Datum], nulls: &[bool]) {
+        let natts = unsafe { (*self.tupdesc).natts } as usize;
+        assert_eq!(
+            values.len(),
+            natts,
+            "wrong number of values for the relation"
+        );
+        assert_eq!(nulls.len(), natts, "wrong number of nulls for the relation");
+
+        if let Some(slot) = self.next_slot() {
+            let tupdesc = self.tupdesc;
+            let values = values.as_ptr() as *mut pg_sys::Datum;
+            let nulls = nulls.as_ptr() as *mut bool;
+            // `heap_form_tuple()` only reads them
+            let tuple = self
+                .memory_context
+                .switch_to(|_| unsafe { pg_sys::heap_form_tuple(tupdesc, values, nulls) });
+            unsafe { self.keep(slot, tuple) }
+        }
+    }
+
+    /// Add a live row, which is copied if it's chosen for the sample
+    ///
+    /// # Safety
+    ///
+    /// `tuple` must be a valid tuple, matching the relation's tuple descriptor
+    pub unsafe fn add_tuple(&mut self, tuple: pg_sys::HeapTuple) {
+        if let Some(slot) = self.next_slot() {
+            let tuple = self
+                .memory_context
+                .switch_to(|_| pg_sys::heap_copytuple(tuple));
+            self.keep(slot, tuple)
+        }
+    }
+
+    /// Count rows which are still there, but deleted, and never part of the sample
+    pub fn add_dead_rows(&mut self, count: f64) {
+        self.dead_rows += count;
+    }
+
+    /// How many rows are in the sample
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the sample empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The most rows the sample holds
+    pub fn target_rows(&self) -> usize {
+        self.target_rows
+    }
+
+    /// How many live rows have been added
+    pub fn total_rows(&self) -> f64 {
+        self.total_rows
+    }
+
+    /// How many dead rows have been counted
+    pub fn dead_rows(&self) -> f64 {
+        self.dead_rows
+    }
+
+    /// The rows in the sample so far
+    pub fn rows(&self) -> &[pg_sys::HeapTuple] {
+        if self.len == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.rows, self.len) }
+        }
+    }
+
+    /// Where the next row goes, if it's to be part of the sample, by Vitter's reservoir
+    /// sampling, as in `acquire_sample_rows()`
+    fn next_slot(&mut self) -> Option<usize> {
+        let slot = if self.len < self.target_rows {
+            Some(self.len)
+        } else if self.target_rows == 0 {
+            None
+        } else {
+            if self.rows_to_skip < 0.0 {
+                self.rows_to_skip = unsafe {
+                    pg_sys::reservoir_get_next_S(
+                        &mut self.reservoir,
+                        self.total_rows,
+                        self.target_rows as c_int,
+                    )
+                };
+            }
+            let slot = if self.rows_to_skip <= 0.0 {
+                // a `pg_prng_state` on pg15, rather than an array
+                #[cfg(not(feature = "pg15"))]
+                let fract =
+                    unsafe { pg_sys::sampler_random_fract(self.reservoir.randstate.as_mut_ptr()) };
+                #[cfg(feature = "pg15")]
+                let fract = unsafe { pg_sys::sampler_random_fract(&mut self.reservoir.randstate) };
+                Some(((self.target_rows as f64 * fract) as usize).min(self.target_rows - 1))
+            } else {
+                None
+            };
+            self.rows_to_skip -= 1.0;
+            slot
+        };
+        self.total_rows += 1.0;
+        slot
+    }
+
+    unsafe fn keep(&mut self, slot: usize, tuple: pg_sys::HeapTuple) {
+        if slot < self.len {
+            pg_sys::heap_freetuple(*self.rows.add(slot));
+        } else {
+            self.len += 1;
+        }
+        *self.rows.add(slot) = tuple;
+    }
+}
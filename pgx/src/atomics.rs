@@ -7,6 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 pub struct PgAtomic<T> {
     inner: OnceCell<*mut T>,
@@ -43,3 +44,121 @@ where
 
 unsafe impl<T> Send for PgAtomic<T> where T: atomic_traits::Atomic + Default {}
 unsafe impl<T> Sync for PgAtomic<T> where T: atomic_traits::Atomic + Default {}
+
+macro_rules! pg_atomic_type {
+    ($name:ident, $pg_type:ident, $atomic:ty, $int:ty) => {
+        /// An atomic integer with the same layout as Postgres'
+        #[doc = concat!("`pg_sys::", stringify!($pg_type), "`,")]
+        /// and the same memory ordering guarantees as its `pg_atomic_*()` functions: reads and
+        /// writes have no barrier semantics, while every other operation is a full barrier.
+        ///
+        /// Being atomic, it can be updated through the shared (`&`) access given by
+        /// `PgLwLock::share()`, so a shared memory struct of statistics counters allows any
+        /// number of backends to count at once, without them waiting on each other for an
+        /// exclusive lock:
+        ///
+        /// ```rust,no_run
+        /// use pgx::*;
+        ///
+        /// #[derive(Default)]
+        /// pub struct Stats {
+        ///     hits: PgAtomicU64,
+        ///     misses: PgAtomicU64,
+        /// }
+        /// unsafe impl PGXSharedMemory for Stats {}
+        ///
+        /// static STATS: PgLwLock<Stats> = PgLwLock::new();
+        ///
+        /// fn count_hit() {
+        ///     STATS.share().hits.fetch_add(1);
+        /// }
+        /// ```
+        #[repr(transparent)]
+        #[derive(Default)]
+        pub struct $name($atomic);
+
+        // so a `*mut pg_sys::$pg_type` can be reinterpreted as one of ours
+        const _: () =
+            assert!(std::mem::size_of::<$name>() == std::mem::size_of::<crate::pg_sys::$pg_type>());
+
+        impl $name {
+            pub const fn new(value: $int) -> Self {
+                Self(<$atomic>::new(value))
+            }
+
+            /// Borrow one of Postgres' own atomics, such as a field of a shared memory struct
+            ///
+            /// ## Safety
+            ///
+            /// `ptr` must be valid, suitably aligned, and outlive `'a`
+            pub unsafe fn from_ptr<'a>(ptr: *mut crate::pg_sys::$pg_type) -> &'a Self {
+                &*(ptr as *const Self)
+            }
+
+            /// The current value, without any barrier semantics
+            pub fn read(&self) -> $int {
+                self.0.load(Ordering::Relaxed)
+            }
+
+            /// Set the value, without any barrier semantics
+            pub fn write(&self, value: $int) {
+                self.0.store(value, Ordering::Relaxed)
+            }
+
+            /// Set the value, returning the previous one
+            pub fn exchange(&self, value: $int) -> $int {
+                self.0.swap(value, Ordering::SeqCst)
+            }
+
+            /// Set the value to `new` if it's currently `expected`.  Returns the previous value,
+            /// as `Ok` if it was replaced, and `Err` if it wasn't
+            pub fn compare_exchange(&self, expected: $int, new: $int) -> Result<$int, $int> {
+                self.0
+                    .compare_exchange(expected, new, Ordering::SeqCst, Ordering::SeqCst)
+            }
+
+            /// Add to the value, wrapping around on overflow, and return the previous value
+            pub fn fetch_add(&self, value: $int) -> $int {
+                self.0.fetch_add(value, Ordering::SeqCst)
+            }
+
+            /// Subtract from the value, wrapping around on overflow, and return the previous value
+            pub fn fetch_sub(&self, value: $int) -> $int {
+                self.0.fetch_sub(value, Ordering::SeqCst)
+            }
+
+            /// Bitwise "and" the value with `value`, and return the previous value
+            pub fn fetch_and(&self, value: $int) -> $int {
+                self.0.fetch_and(value, Ordering::SeqCst)
+            }
+
+            /// Bitwise "or" the value with `value`, and return the previous value
+            pub fn fetch_or(&self, value: $int) -> $int {
+                self.0.fetch_or(value, Ordering::SeqCst)
+            }
+
+            /// Add to the value, wrapping around on overflow, and return the new value
+            pub fn add_fetch(&self, value: $int) -> $int {
+                self.fetch_add(value).wrapping_add(value)
+            }
+
+            /// Subtract from the value, wrapping around on overflow, and return the new value
+            pub fn sub_fetch(&self, value: $int) -> $int {
+                self.fetch_sub(value).wrapping_sub(value)
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_tuple(stringify!($name))
+                    .field(&self.read())
+                    .finish()
+            }
+        }
+
+        unsafe impl crate::PGXSharedMemory for $name {}
+    };
+}
+
+pg_atomic_type!(PgAtomicU32, pg_atomic_uint32, AtomicU32, u32);
+pg_atomic_type!(PgAtomicU64, pg_atomic_uint64, AtomicU64, u64);
@@ -0,0 +1,251 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The statistics `ANALYZE` gathers about each column, from `pg_statistic`, for cost estimation,
+//! support functions, and planner hooks
+use crate::{pg_sys, FromDatum, IntoDatum};
+use std::marker::PhantomData;
+
+/// A column's row of `pg_statistic`: its histogram, most common values, number of distinct
+/// values, and so on.
+///
+/// These are the statistics as `ANALYZE` last left them, read the way the planner reads them,
+/// so there's no check the user has been granted access to the column, as there is through the
+/// `pg_stats` view.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn most_common_value(relid: pg_sys::Oid, attnum: i16) -> Option<i32> {
+///     let stats = PgAttributeStats::for_column(relid, attnum, false)?;
+///     let mcvs = stats.most_common_values::<i32>()?;
+///     mcvs.first().map(|(value, _)| *value)
+/// }
+/// ```
+pub struct PgAttributeStats<'a> {
+    tuple: pg_sys::HeapTuple,
+    release: bool,
+    _marker: PhantomData<&'a pg_sys::HeapTupleData>,
+}
+
+impl PgAttributeStats<'static> {
+    /// The statistics for column `attnum` of relation `relid`, if it has been analyzed.  With
+    /// `inherited`, they're the ones for the whole inheritance tree or partitioned table, rather
+    /// than just the parent
+    pub fn for_column(relid: pg_sys::Oid, attnum: i16, inherited: bool) -> Option<Self> {
+        let tuple = unsafe {
+            pg_sys::SearchSysCache(
+                pg_sys::SysCacheIdentifier_STATRELATTINH as i32,
+                relid.into_datum().unwrap(),
+                attnum.into_datum().unwrap(),
+                inherited.into_datum().unwrap(),
+                0,
+            )
+        };
+        if tuple.is_null() {
+            None
+        } else {
+            Some(PgAttributeStats {
+                tuple,
+                release: true,
+                _marker: PhantomData,
+            })
+        }
+    }
+}
+
+impl<'a> PgAttributeStats<'a> {
+    /// Statistics from a `pg_statistic` tuple someone else is responsible for, such as the one
+    /// in a `VariableStatData`
+    ///
+    /// # Safety
+    ///
+    /// `tuple` must be a `pg_statistic` tuple which stays valid for `'a`
+    pub unsafe fn from_tuple(tuple: pg_sys::HeapTuple) -> Option<Self> {
+        if tuple.is_null() {
+            None
+        } else {
+            Some(PgAttributeStats {
+                tuple,
+                release: false,
+                _marker: PhantomData,
+            })
+        }
+    }
+
+    pub fn as_ptr(&self) -> pg_sys::HeapTuple {
+        self.tuple
+    }
+
+    fn form(&self) -> &pg_sys::FormData_pg_statistic {
+        unsafe { &*(pg_sys::pgx_GETSTRUCT(self.tuple) as *const pg_sys::FormData_pg_statistic) }
+    }
+
+    /// The relation whose column these are the statistics for
+    pub fn relid(&self) -> pg_sys::Oid {
+        self.form().starelid
+    }
+
+    /// The column these are the statistics for
+    pub fn attnum(&self) -> i16 {
+        self.form().staattnum
+    }
+
+    /// Are these the statistics for the relation's whole inheritance tree?
+    pub fn inherited(&self) -> bool {
+        self.form().stainherit
+    }
+
+    /// The fraction of values which are `NULL`
+    pub fn null_fraction(&self) -> f64 {
+        self.form().stanullfrac as f64
+    }
+
+    /// The average size of the non-`NULL` values, in bytes
+    pub fn average_width(&self) -> i32 {
+        self.form().stawidth
+    }
+
+    /// `stadistinct`, as `ANALYZE` stored it: how many distinct non-`NULL` values there are when
+    /// it's positive, minus what fraction of the rows they are when it's negative, because it
+    /// will grow with the table, and zero when it's unknown
+    pub fn n_distinct(&self) -> f64 {
+        self.form().stadistinct as f64
+    }
+
+    /// How many distinct non-`NULL` values there are in `rows` rows, or `None` when it's unknown
+    pub fn distinct_values(&self, rows: f64) -> Option<f64> {
+        match self.n_distinct() {
+            n_distinct if n_distinct > 0.0 => Some(n_distinct),
+            n_distinct if n_distinct < 0.0 => Some((-n_distinct * rows).round().max(1.0)),
+            _ => None,
+        }
+    }
+
+    /// The most common values, and the fraction of all rows each is found in, if they're `T`s
+    pub fn most_common_values<T: FromDatum + IntoDatum>(&self) -> Option<Vec<(T, f64)>> {
+        let slot = self.stats_slot(
+            pg_sys::STATISTIC_KIND_MCV as i32,
+            pg_sys::InvalidOid,
+            (pg_sys::ATTSTATSSLOT_VALUES | pg_sys::ATTSTATSSLOT_NUMBERS) as i32,
+        )?;
+        let values = slot.values_as::<T>()?;
+        Some(
+            values
+                .into_iter()
+                .zip(slot.numbers().iter().map(|&number| number as f64))
+                .collect(),
+        )
+    }
+
+    /// The bounds of a histogram of the values, not counting the most common ones, dividing
+    /// them into buckets with equal numbers of rows, if they're `T`s
+    pub fn histogram<T: FromDatum + IntoDatum>(&self) -> Option<Vec<T>> {
+        let slot = self.stats_slot(
+            pg_sys::STATISTIC_KIND_HISTOGRAM as i32,
+            pg_sys::InvalidOid,
+            pg_sys::ATTSTATSSLOT_VALUES as i32,
+        )?;
+        slot.values_as::<T>()
+    }
+
+    /// How closely the table's physical order follows the values' order, from -1, when it's
+    /// the reverse, to 1, when it's the same
+    pub fn correlation(&self) -> Option<f64> {
+        let slot = self.stats_slot(
+            pg_sys::STATISTIC_KIND_CORRELATION as i32,
+            pg_sys::InvalidOid,
+            pg_sys::ATTSTATSSLOT_NUMBERS as i32,
+        )?;
+        slot.numbers()
+            .first()
+            .map(|&correlation| correlation as f64)
+    }
+
+    /// Any kind of statistics, with `get_attstatsslot()`.  `operator` is the one they must have
+    /// been gathered for, if not `InvalidOid`, and `flags` says whether to fetch
+    /// `ATTSTATSSLOT_VALUES`, `ATTSTATSSLOT_NUMBERS`, or both
+    pub fn stats_slot(
+        &self,
+        kind: i32,
+        operator: pg_sys::Oid,
+        flags: i32,
+    ) -> Option<PgAttStatsSlot> {
+        let mut slot = PgAttStatsSlot {
+            slot: Default::default(),
+        };
+        let found =
+            unsafe { pg_sys::get_attstatsslot(&mut slot.slot, self.tuple, kind, operator, flags) };
+        if found {
+            Some(slot)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for PgAttributeStats<'_> {
+    fn drop(&mut self) {
+        if self.release {
+            unsafe { pg_sys::ReleaseSysCache(self.tuple) }
+        }
+    }
+}
+
+/// One kind of statistics about a column, freed on drop
+pub struct PgAttStatsSlot {
+    slot: pg_sys::AttStatsSlot,
+}
+
+impl PgAttStatsSlot {
+    pub fn as_ptr(&mut self) -> *mut pg_sys::AttStatsSlot {
+        &mut self.slot
+    }
+
+    /// The type of [`PgAttStatsSlot::values()`]
+    pub fn value_type(&self) -> pg_sys::Oid {
+        self.slot.valuetype
+    }
+
+    pub fn values(&self) -> &[pg_sys::Datum] {
+        if self.slot.values.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.slot.values, self.slot.nvalues as usize) }
+        }
+    }
+
+    pub fn numbers(&self) -> &[f32] {
+        if self.slot.numbers.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.slot.numbers, self.slot.nnumbers as usize) }
+        }
+    }
+
+    /// The values, if they're `T`s
+    pub fn values_as<T: FromDatum + IntoDatum>(&self) -> Option<Vec<T>> {
+        if self.value_type() != T::type_oid() {
+            return None;
+        }
+        Some(
+            self.values()
+                .iter()
+                .filter_map(|&datum| unsafe { T::from_datum(datum, false, self.value_type()) })
+                .collect(),
+        )
+    }
+}
+
+impl Drop for PgAttStatsSlot {
+    fn drop(&mut self) {
+        unsafe { pg_sys::free_attstatsslot(&mut self.slot) }
+    }
+}
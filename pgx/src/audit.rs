@@ -0,0 +1,641 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Audit logging of the statements backends run, in the style of `pgaudit`, built on [`PgHooks`]
+//!
+//! A [`PgAuditLog`] defines settings, under a prefix of the extension's choosing, for which
+//! statements are recorded and where to:
+//!
+//! - `<prefix>.log`: the classes of statements to record, as a comma-separated list of `read`,
+//!   `write`, `ddl`, `role`, `misc`, `all`, and `none`, where a class preceded by `-` is left out,
+//!   as in `all, -misc`.  Unknown classes are ignored.  The default is `none`
+//! - `<prefix>.log_catalog`: whether to record statements which only use relations in
+//!   `pg_catalog` and `information_schema`, like `psql`'s `\d`.  The default is `on`
+//! - `<prefix>.log_relations`: if set, a comma-separated list of relations, by name or
+//!   schema-qualified name, and only statements which read or write one of them are recorded
+//! - `<prefix>.sink`: where statements are recorded.  `log` (the default) writes them to the
+//!   server log, `file` appends them to `<prefix>.file`, and `table` inserts them into
+//!   `<prefix>.table`
+//!
+//! They can only be changed by superusers, so users can't hide what they do.  Statements are
+//! recorded once they've finished, so a statement which fails isn't.  Neither is one whose
+//! transaction aborts, with the `table` sink, as the row recording it is rolled back with it.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! static AUDIT: PgAuditLog = PgAuditLog::new("myext_audit");
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     // statements are then recorded, once `myext_audit.log` says which
+//!     AUDIT.register();
+//! }
+//! ```
+//!
+//! An extension with [`PgHooks`] of its own calls [`PgAuditLog::define_gucs()`] instead, and
+//! calls the same hooks of a [`PgAuditHooks`] from its own.
+use crate::{
+    pg_sys, register_hook, GucContext, GucEnum, GucRegistry, GucSetting, HookResult, PgBox,
+    PgHooks, PgList, PgMemoryContexts, Spi,
+};
+use std::ffi::CStr;
+use std::io::Write;
+use std::os::raw::c_char;
+
+/// The classes of statements a [`PgAuditLog`] records, as its `.log` setting names them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgAuditClass {
+    /// `SELECT`, and `COPY` from a relation
+    Read,
+    /// `INSERT`, `UPDATE`, `DELETE`, and `TRUNCATE`, and `COPY` into a relation
+    Write,
+    /// Creating, altering, and dropping objects, other than roles
+    Ddl,
+    /// `GRANT`, `REVOKE`, and creating, altering, and dropping roles
+    Role,
+    /// Everything else, like `SET`, `VACUUM`, and `BEGIN`
+    Misc,
+}
+
+impl PgAuditClass {
+    const ALL: [PgAuditClass; 5] = [
+        PgAuditClass::Read,
+        PgAuditClass::Write,
+        PgAuditClass::Ddl,
+        PgAuditClass::Role,
+        PgAuditClass::Misc,
+    ];
+
+    /// The class's name, as the `.log` setting names it
+    pub fn name(&self) -> &'static str {
+        match self {
+            PgAuditClass::Read => "read",
+            PgAuditClass::Write => "write",
+            PgAuditClass::Ddl => "ddl",
+            PgAuditClass::Role => "role",
+            PgAuditClass::Misc => "misc",
+        }
+    }
+
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+}
+
+/// Where a [`PgAuditLog`] records statements, as its `.sink` setting names them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgAuditSink {
+    /// The server log, at the `LOG` level, as `AUDIT: ` then [`PgAuditEvent::to_csv()`]
+    Log,
+    /// Appended to the file named by the `.file` setting, relative to the data directory, as the
+    /// time then [`PgAuditEvent::to_csv()`]
+    File,
+    /// Inserted into the table named by the `.table` setting, which must have `text` columns
+    /// `class`, `command`, `username`, `session_username`, `database`, and `statement`, and a
+    /// `text[]` column `relations`.  The row is part of the transaction
+    Table,
+}
+
+impl GucEnum<PgAuditSink> for PgAuditSink {
+    fn from_ordinal(ordinal: i32) -> PgAuditSink {
+        match ordinal {
+            0 => PgAuditSink::Log,
+            1 => PgAuditSink::File,
+            2 => PgAuditSink::Table,
+            _ => panic!("Unrecognized ordinal "),
+        }
+    }
+
+    fn to_ordinal(&self) -> i32 {
+        *self as i32
+    }
+
+    unsafe fn config_matrix(&self) -> *const pg_sys::config_enum_entry {
+        let names = ["log", "file", "table"];
+        let entries = PgMemoryContexts::TopMemoryContext
+            .palloc0_slice::<pg_sys::config_enum_entry>(names.len() + 1);
+        for (ordinal, (entry, name)) in entries.iter_mut().zip(names).enumerate() {
+            entry.name = PgMemoryContexts::TopMemoryContext.pstrdup(name);
+            entry.val = ordinal as i32;
+            entry.hidden = false;
+        }
+        entries.as_ptr()
+    }
+}
+
+/// A relation a statement read or wrote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgAuditRelation {
+    pub oid: pg_sys::Oid,
+    pub schema: String,
+    pub name: String,
+}
+
+impl std::fmt::Display for PgAuditRelation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.schema, self.name)
+    }
+}
+
+/// A statement a [`PgAuditLog`] records
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgAuditEvent {
+    pub class: PgAuditClass,
+    /// The statement's command tag, like `SELECT` or `CREATE TABLE`
+    pub command: String,
+    /// The text of the statement alone, even when it was sent along with others
+    pub statement: String,
+    /// The relations the statement read or wrote, each once, which is none for utility
+    /// statements
+    pub relations: Vec<PgAuditRelation>,
+    /// The user whose privileges the statement ran with, `current_user`
+    pub user: String,
+    /// The user who connected, `session_user`
+    pub session_user: String,
+    pub database: String,
+}
+
+impl PgAuditEvent {
+    /// The event as a line of CSV, without its line ending, of its class, command, user, session
+    /// user, database, relations (separated by spaces), and statement
+    pub fn to_csv(&self) -> String {
+        let relations = self
+            .relations
+            .iter()
+            .map(|relation| relation.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        [
+            self.class.name(),
+            &self.command,
+            &self.user,
+            &self.session_user,
+            &self.database,
+            &relations,
+            &self.statement,
+        ]
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+/// Records the statements backends run, as its settings say to.  See the
+/// [module documentation](crate::audit)
+pub struct PgAuditLog {
+    prefix: &'static str,
+    log: GucSetting<Option<&'static str>>,
+    log_catalog: GucSetting<bool>,
+    log_relations: GucSetting<Option<&'static str>>,
+    sink: GucSetting<PgAuditSink>,
+    file: GucSetting<Option<&'static str>>,
+    table: GucSetting<Option<&'static str>>,
+}
+
+/// Set while a [`PgAuditLog`] records a statement, so the `table` sink's `INSERT` isn't recorded
+static mut RECORDING: bool = false;
+
+impl PgAuditLog {
+    /// An audit log whose settings are named `<prefix>.log` and so on
+    pub const fn new(prefix: &'static str) -> Self {
+        PgAuditLog {
+            prefix,
+            log: GucSetting::new(Some("none")),
+            log_catalog: GucSetting::new(true),
+            log_relations: GucSetting::new(None),
+            sink: GucSetting::new(PgAuditSink::Log),
+            file: GucSetting::new(None),
+            table: GucSetting::new(None),
+        }
+    }
+
+    /// The prefix given to [`PgAuditLog::new()`]
+    pub fn get_prefix(&self) -> &'static str {
+        self.prefix
+    }
+
+    /// Define the settings, and register a [`PgAuditHooks`] to record statements.  Call it from
+    /// `_PG_init()`
+    pub fn register(&'static self) {
+        self.define_gucs();
+        unsafe { register_hook(Box::leak(Box::new(PgAuditHooks::new(self)))) }
+    }
+
+    /// Define the settings, for an extension which calls a [`PgAuditHooks`] from its own
+    /// [`PgHooks`]
+    pub fn define_gucs(&'static self) {
+        let name = |setting: &str| format!("{}.{}", self.prefix, setting);
+        GucRegistry::define_string_guc(
+            &name("log"),
+            "Classes of statements to record in the audit log",
+            "A comma-separated list of read, write, ddl, role, misc, all, and none, where a class preceded by - is left out.",
+            &self.log,
+            GucContext::Suset,
+        );
+        GucRegistry::define_bool_guc(
+            &name("log_catalog"),
+            "Record statements which only use system catalogs in the audit log",
+            "",
+            &self.log_catalog,
+            GucContext::Suset,
+        );
+        GucRegistry::define_string_guc(
+            &name("log_relations"),
+            "Record only statements which use these relations in the audit log",
+            "A comma-separated list of relation names, which may be schema-qualified.",
+            &self.log_relations,
+            GucContext::Suset,
+        );
+        GucRegistry::define_enum_guc(
+            &name("sink"),
+            "Where the audit log is recorded",
+            "log writes it to the server log, file to the file named by the file setting, and table to the table named by the table setting.",
+            &self.sink,
+            GucContext::Suset,
+        );
+        GucRegistry::define_string_guc(
+            &name("file"),
+            "The file the audit log is appended to, relative to the data directory",
+            "",
+            &self.file,
+            GucContext::Suset,
+        );
+        GucRegistry::define_string_guc(
+            &name("table"),
+            "The table the audit log is inserted into",
+            "",
+            &self.table,
+            GucContext::Suset,
+        );
+    }
+
+    /// The classes of statements the `.log` setting says to record
+    pub fn classes(&self) -> Vec<PgAuditClass> {
+        let bits = self.class_bits();
+        PgAuditClass::ALL
+            .into_iter()
+            .filter(|class| bits & class.bit() != 0)
+            .collect()
+    }
+
+    /// Should statements of `class` be recorded?  They aren't while another is being recorded
+    pub fn is_enabled(&self, class: PgAuditClass) -> bool {
+        unsafe { !RECORDING && self.class_bits() & class.bit() != 0 }
+    }
+
+    /// Record `event` in the sink, if the settings say to.  [`PgAuditHooks`] calls it for each
+    /// statement, and extensions can call it for events of their own
+    pub fn record(&self, event: &PgAuditEvent) {
+        if !self.is_enabled(event.class) || !self.includes_relations(&event.relations) {
+            return;
+        }
+
+        let _recording = Recording::start();
+        match self.sink.get() {
+            PgAuditSink::Log => {
+                crate::log!("AUDIT: {}", event.to_csv());
+            }
+            PgAuditSink::File => {
+                let file = self.required_setting(&self.file, "file");
+                let now = unsafe {
+                    CStr::from_ptr(pg_sys::timestamptz_to_str(pg_sys::GetCurrentTimestamp()))
+                };
+                let line = format!("{},{}\n", csv_field(&now.to_string_lossy()), event.to_csv());
+                if let Err(e) = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&file)
+                    .and_then(|mut f| f.write_all(line.as_bytes()))
+                {
+                    crate::error!("could not write audit log file \"{}\": {}", file, e);
+                }
+            }
+            PgAuditSink::Table => {
+                let table = self.required_setting(&self.table, "table");
+                let relations = event
+                    .relations
+                    .iter()
+                    .map(|relation| relation.to_string())
+                    .collect::<Vec<_>>();
+                Spi::run_with_args(
+                    &format!(
+                        "INSERT INTO {} (class, command, username, session_username, database, relations, statement) \
+                         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                        table
+                    ),
+                    crate::spi_args![
+                        event.class.name(),
+                        event.command.as_str(),
+                        event.user.as_str(),
+                        event.session_user.as_str(),
+                        event.database.as_str(),
+                        relations,
+                        event.statement.as_str()
+                    ],
+                );
+            }
+        }
+    }
+
+    fn class_bits(&self) -> u8 {
+        let setting = self.log.get().unwrap_or_default();
+        let mut bits = 0;
+        for word in setting.split(',').map(|word| word.trim().to_lowercase()) {
+            let (exclude, word) = match word.strip_prefix('-') {
+                Some(word) => (true, word.trim()),
+                None => (false, word.as_str()),
+            };
+            let word_bits = match word {
+                "all" => PgAuditClass::ALL
+                    .iter()
+                    .fold(0, |bits, class| bits | class.bit()),
+                "none" => {
+                    bits = 0;
+                    continue;
+                }
+                _ => match PgAuditClass::ALL.iter().find(|class| class.name() == word) {
+                    Some(class) => class.bit(),
+                    None => continue,
+                },
+            };
+            if exclude {
+                bits &= !word_bits;
+            } else {
+                bits |= word_bits;
+            }
+        }
+        bits
+    }
+
+    /// Do the `.log_catalog` and `.log_relations` settings include a statement using `relations`?
+    fn includes_relations(&self, relations: &[PgAuditRelation]) -> bool {
+        let catalog_only = !relations.is_empty()
+            && relations
+                .iter()
+                .all(|relation| ["pg_catalog", "information_schema"].contains(&&*relation.schema));
+        if catalog_only && !self.log_catalog.get() {
+            return false;
+        }
+        match self.log_relations.get() {
+            Some(names) if !names.trim().is_empty() => names.split(',').any(|name| {
+                let name = name.trim();
+                relations
+                    .iter()
+                    .any(|relation| relation.name == name || relation.to_string() == name)
+            }),
+            _ => true,
+        }
+    }
+
+    fn required_setting(&self, setting: &GucSetting<Option<&'static str>>, name: &str) -> String {
+        match setting.get() {
+            Some(value) if !value.is_empty() => value,
+            _ => crate::error!(
+                "{}.{} must be set when {}.sink is '{}'",
+                self.prefix,
+                name,
+                self.prefix,
+                name
+            ),
+        }
+    }
+}
+
+/// Sets [`RECORDING`] until dropped, including by an `ERROR` while recording
+struct Recording;
+
+impl Recording {
+    fn start() -> Self {
+        unsafe { RECORDING = true };
+        Recording
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        unsafe { RECORDING = false };
+    }
+}
+
+/// The [`PgHooks`] which record statements in a [`PgAuditLog`], as they finish
+pub struct PgAuditHooks {
+    audit: &'static PgAuditLog,
+}
+
+impl PgAuditHooks {
+    pub fn new(audit: &'static PgAuditLog) -> Self {
+        PgAuditHooks { audit }
+    }
+}
+
+impl PgHooks for PgAuditHooks {
+    fn executor_end(
+        &mut self,
+        query_desc: PgBox<pg_sys::QueryDesc>,
+        prev_hook: fn(query_desc: PgBox<pg_sys::QueryDesc>) -> HookResult<()>,
+    ) -> HookResult<()> {
+        let class = match query_desc.operation {
+            pg_sys::CmdType_CMD_SELECT => PgAuditClass::Read,
+            pg_sys::CmdType_CMD_UTILITY
+            | pg_sys::CmdType_CMD_NOTHING
+            | pg_sys::CmdType_CMD_UNKNOWN => PgAuditClass::Misc,
+            _ => PgAuditClass::Write,
+        };
+        // the plan is still there after `ExecutorEnd()`, but the statement is recorded once it's
+        // finished with, as it can fail
+        let event = if self.audit.is_enabled(class) && !query_desc.plannedstmt.is_null() {
+            Some(unsafe { executor_event(class, &query_desc) })
+        } else {
+            None
+        };
+        let result = prev_hook(query_desc);
+        if let Some(event) = event {
+            self.audit.record(&event);
+        }
+        result
+    }
+
+    fn process_utility_hook(
+        &mut self,
+        pstmt: PgBox<pg_sys::PlannedStmt>,
+        query_string: &std::ffi::CStr,
+        read_only_tree: Option<bool>,
+        context: pg_sys::ProcessUtilityContext,
+        params: PgBox<pg_sys::ParamListInfoData>,
+        query_env: PgBox<pg_sys::QueryEnvironment>,
+        dest: PgBox<pg_sys::DestReceiver>,
+        completion_tag: *mut pg_sys::QueryCompletion,
+        prev_hook: fn(
+            pstmt: PgBox<pg_sys::PlannedStmt>,
+            query_string: &std::ffi::CStr,
+            read_only_tree: Option<bool>,
+            context: pg_sys::ProcessUtilityContext,
+            params: PgBox<pg_sys::ParamListInfoData>,
+            query_env: PgBox<pg_sys::QueryEnvironment>,
+            dest: PgBox<pg_sys::DestReceiver>,
+            completion_tag: *mut pg_sys::QueryCompletion,
+        ) -> HookResult<()>,
+    ) -> HookResult<()> {
+        let class = unsafe { utility_class(pstmt.utilityStmt) };
+        let event = if self.audit.is_enabled(class) {
+            Some(unsafe {
+                PgAuditEvent {
+                    class,
+                    command: command_tag(pstmt.utilityStmt),
+                    statement: statement_text(
+                        query_string.as_ptr(),
+                        pstmt.stmt_location,
+                        pstmt.stmt_len,
+                    ),
+                    relations: Vec::new(),
+                    ..current_identity()
+                }
+            })
+        } else {
+            None
+        };
+        let result = prev_hook(
+            pstmt,
+            query_string,
+            read_only_tree,
+            context,
+            params,
+            query_env,
+            dest,
+            completion_tag,
+        );
+        if let Some(event) = event {
+            self.audit.record(&event);
+        }
+        result
+    }
+}
+
+unsafe fn executor_event(
+    class: PgAuditClass,
+    query_desc: &PgBox<pg_sys::QueryDesc>,
+) -> PgAuditEvent {
+    let pstmt = query_desc.plannedstmt;
+    let mut relations: Vec<PgAuditRelation> = Vec::new();
+    let rtable = PgList::<pg_sys::RangeTblEntry>::from_pg((*pstmt).rtable);
+    for rte in rtable.iter_ptr() {
+        if (*rte).rtekind != pg_sys::RTEKind_RTE_RELATION
+            || relations
+                .iter()
+                .any(|relation| relation.oid == (*rte).relid)
+        {
+            continue;
+        }
+        if let Some(relation) = audit_relation((*rte).relid) {
+            relations.push(relation);
+        }
+    }
+
+    PgAuditEvent {
+        class,
+        command: command_tag(pstmt as *mut pg_sys::Node),
+        statement: statement_text(
+            query_desc.sourceText,
+            (*pstmt).stmt_location,
+            (*pstmt).stmt_len,
+        ),
+        relations,
+        ..current_identity()
+    }
+}
+
+unsafe fn utility_class(node: *mut pg_sys::Node) -> PgAuditClass {
+    match (*node).type_ {
+        pg_sys::NodeTag_T_GrantStmt
+        | pg_sys::NodeTag_T_GrantRoleStmt
+        | pg_sys::NodeTag_T_AlterDefaultPrivilegesStmt
+        | pg_sys::NodeTag_T_CreateRoleStmt
+        | pg_sys::NodeTag_T_AlterRoleStmt
+        | pg_sys::NodeTag_T_AlterRoleSetStmt
+        | pg_sys::NodeTag_T_DropRoleStmt
+        | pg_sys::NodeTag_T_ReassignOwnedStmt => PgAuditClass::Role,
+        _ => match pg_sys::GetCommandLogLevel(node) {
+            pg_sys::LogStmtLevel_LOGSTMT_DDL => PgAuditClass::Ddl,
+            pg_sys::LogStmtLevel_LOGSTMT_MOD => PgAuditClass::Write,
+            _ => PgAuditClass::Misc,
+        },
+    }
+}
+
+/// An event of no class, with only who ran it and where filled in
+unsafe fn current_identity() -> PgAuditEvent {
+    PgAuditEvent {
+        class: PgAuditClass::Misc,
+        command: String::new(),
+        statement: String::new(),
+        relations: Vec::new(),
+        user: c_string(pg_sys::GetUserNameFromId(pg_sys::GetUserId(), false)),
+        session_user: c_string(pg_sys::GetUserNameFromId(pg_sys::GetSessionUserId(), false)),
+        database: c_string(pg_sys::get_database_name(pg_sys::MyDatabaseId)),
+    }
+}
+
+unsafe fn audit_relation(oid: pg_sys::Oid) -> Option<PgAuditRelation> {
+    let name = pg_sys::get_rel_name(oid);
+    if name.is_null() {
+        return None;
+    }
+    Some(PgAuditRelation {
+        oid,
+        schema: c_string(pg_sys::get_namespace_name(pg_sys::get_rel_namespace(oid))),
+        name: c_string(name),
+    })
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+unsafe fn command_tag(node: *mut pg_sys::Node) -> String {
+    c_string(pg_sys::CreateCommandTag(node))
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+unsafe fn command_tag(node: *mut pg_sys::Node) -> String {
+    c_string(pg_sys::GetCommandTagName(pg_sys::CreateCommandTag(node)))
+}
+
+/// The statement at `location` in `source`, which may hold others, as `PlannedStmt` gives it
+unsafe fn statement_text(source: *const c_char, location: i32, len: i32) -> String {
+    if source.is_null() {
+        return String::new();
+    }
+    let source = CStr::from_ptr(source).to_bytes();
+    let start = (location.max(0) as usize).min(source.len());
+    // a length of zero is the rest of the string
+    let end = if location >= 0 && len > 0 {
+        (start + len as usize).min(source.len())
+    } else {
+        source.len()
+    };
+    String::from_utf8_lossy(&source[start..end])
+        .trim()
+        .to_string()
+}
+
+unsafe fn c_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
+
+/// `field`, quoted if it has to be
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,144 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Checking that code which calls into Postgres runs in a backend, on the thread Postgres runs
+//! it on, so that calling it from a thread the extension spawned, or from a process that isn't
+//! Postgres at all, such as a client sharing the extension's crate or a plain `cargo test`, fails
+//! with a clear message instead of crashing, and ending the backend's process deliberately
+use crate::pg_sys;
+use once_cell::sync::OnceCell;
+use std::thread::ThreadId;
+
+/// The thread Postgres loaded the extension on, which backends forked from the postmaster
+/// inherit, as the thread that forks is the one the child runs on
+static BACKEND_THREAD: OnceCell<ThreadId> = OnceCell::new();
+
+/// Remember the current thread as the backend's, from [`initialize()`](crate::initialize)
+pub(crate) fn register_backend_thread() {
+    let _ = BACKEND_THREAD.set(std::thread::current().id());
+}
+
+/// Why code that calls into Postgres can't run here
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendError {
+    /// Postgres hasn't loaded the extension into this process, so it isn't a backend
+    NotABackend,
+    /// This isn't the thread the backend runs on, such as one the extension spawned
+    NotBackendThread { thread: Option<String> },
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::NotABackend => write!(
+                f,
+                "Postgres functions can only be called by an extension Postgres has loaded"
+            ),
+            BackendError::NotBackendThread { thread } => write!(
+                f,
+                "Postgres functions can only be called from the backend's thread, not {}",
+                thread
+                    .as_deref()
+                    .map(|name| format!("thread \"{}\"", name))
+                    .unwrap_or_else(|| "an unnamed thread".to_string())
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+/// Is this process a Postgres backend, or other Postgres process, that has loaded the extension?
+pub fn is_backend() -> bool {
+    BACKEND_THREAD.get().is_some()
+}
+
+/// Is this the thread of a Postgres backend, the only one that can call into Postgres?
+pub fn is_backend_thread() -> bool {
+    BACKEND_THREAD.get() == Some(&std::thread::current().id())
+}
+
+/// Whether code that calls into Postgres can run here, and if not, why
+pub fn check_backend() -> Result<(), BackendError> {
+    match BACKEND_THREAD.get() {
+        None => Err(BackendError::NotABackend),
+        Some(thread) if *thread != std::thread::current().id() => {
+            Err(BackendError::NotBackendThread {
+                thread: std::thread::current().name().map(str::to_string),
+            })
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Panic with a [`BackendError`] if code that calls into Postgres can't run here, which on a
+/// thread the extension spawned only ends that thread, rather than crashing the backend.  The
+/// `#[requires_backend]` attribute puts this at the start of a function.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[requires_backend]
+/// fn current_database_name() -> String {
+///     unsafe { std::ffi::CStr::from_ptr(pg_sys::get_database_name(pg_sys::MyDatabaseId)) }
+///         .to_string_lossy()
+///         .into_owned()
+/// }
+///
+/// std::thread::spawn(|| current_database_name()).join().expect_err("not the backend's thread");
+/// ```
+#[track_caller]
+pub fn assert_backend() {
+    if let Err(e) = check_backend() {
+        panic!("{}", e);
+    }
+}
+
+/// [`assert_backend()`] in builds with debug assertions, for checks too frequent for release
+/// builds, like those of allocations
+#[macro_export]
+macro_rules! debug_assert_backend {
+    () => {
+        if cfg!(debug_assertions) {
+            $crate::assert_backend();
+        }
+    };
+}
+
+/// End the backend's process, or the background worker's, with exit `code`, after the callbacks
+/// registered with `before_shmem_exit()` and `on_proc_exit()` have run, which release the
+/// process' locks, abort its transaction, and detach it from shared memory.
+///
+/// A background worker that exits with code 0 isn't restarted, and one that exits with code 1 is,
+/// after its restart time.  The postmaster treats any other code as a crash, ending every other
+/// backend, so `code` must be 0 or 1.  Like an `ERROR`, the process exits without dropping the
+/// Rust values on the stack.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn one_shot_worker(_arg: pg_sys::Datum) {
+///     // ...do the work once...
+///
+///     // and don't have the postmaster start the worker again
+///     proc_exit(0);
+/// }
+/// ```
+pub fn proc_exit(code: i32) -> ! {
+    assert_backend();
+    assert!(
+        code == 0 || code == 1,
+        "exit code {} would make the postmaster restart every backend",
+        code
+    );
+    unsafe { pg_sys::proc_exit(code) };
+    unreachable!("proc_exit returned")
+}
@@ -0,0 +1,106 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::bgworkers::BackgroundWorker;
+
+/// A background worker's configuration, read from the GUCs it registered in `_PG_init()`
+pub trait BackgroundWorkerConfig: Sized {
+    /// Read the configuration from the current values of its GUCs
+    fn load() -> Self;
+}
+
+/// A background worker's configuration, read again whenever the worker receives a SIGHUP, such
+/// as from `pg_reload_conf()`.
+///
+/// The worker must attach the SIGHUP handler with [`BackgroundWorker::attach_signal_handlers()`],
+/// which reloads `postgresql.conf`, and then call [`ReloadableConfig::check_for_reload()`] each
+/// time around its main loop:
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+/// use std::time::Duration;
+///
+/// static NAPTIME: GucSetting<i32> = GucSetting::new(10);
+///
+/// struct Config {
+///     naptime: Duration,
+/// }
+///
+/// impl BackgroundWorkerConfig for Config {
+///     fn load() -> Self {
+///         Config {
+///             naptime: Duration::from_secs(NAPTIME.get() as u64),
+///         }
+///     }
+/// }
+///
+/// #[pg_guard]
+/// pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///
+///     let mut config = ReloadableConfig::<Config>::new()
+///         .on_reload(|config| log!("naptime is now {:?}", config.naptime));
+///     while BackgroundWorker::wait_latch(Some(config.get().naptime)) {
+///         config.check_for_reload();
+///         // do the work
+///     }
+/// }
+/// ```
+pub struct ReloadableConfig<C: BackgroundWorkerConfig> {
+    current: C,
+    on_reload: Option<OnReload<C>>,
+}
+
+type OnReload<C> = Box<dyn FnMut(&C)>;
+
+impl<C: BackgroundWorkerConfig> ReloadableConfig<C> {
+    pub fn new() -> Self {
+        ReloadableConfig {
+            current: C::load(),
+            on_reload: None,
+        }
+    }
+
+    /// Call `on_reload` with the new configuration every time it's reloaded
+    pub fn on_reload<F: FnMut(&C) + 'static>(mut self, on_reload: F) -> Self {
+        self.on_reload = Some(Box::new(on_reload));
+        self
+    }
+
+    /// The configuration as of the last reload
+    pub fn get(&self) -> &C {
+        &self.current
+    }
+
+    /// Reload the configuration if a SIGHUP was received since the last time, returning whether
+    /// it was.  This consumes [`BackgroundWorker::sighup_received()`]
+    pub fn check_for_reload(&mut self) -> bool {
+        if BackgroundWorker::sighup_received() {
+            self.reload();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Read the configuration again, whether or not a SIGHUP was received
+    pub fn reload(&mut self) {
+        self.current = C::load();
+        if let Some(on_reload) = self.on_reload.as_mut() {
+            on_reload(&self.current);
+        }
+    }
+}
+
+impl<C: BackgroundWorkerConfig> Default for ReloadableConfig<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
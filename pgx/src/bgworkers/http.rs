@@ -0,0 +1,592 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A blocking HTTP client whose waits are on the backend's latch, so they end when the worker is
+//! told to shut down, rather than holding it up
+use super::{shutdown, GOT_SIGTERM};
+use crate::pg_sys;
+use native_tls::TlsConnector;
+use std::cell::Cell;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Why an [`HttpClient`] request failed
+#[derive(Debug)]
+pub enum HttpError {
+    /// The URL couldn't be parsed, or isn't `http` or `https`
+    InvalidUrl(String),
+    /// Resolving, connecting, sending, or receiving failed
+    Io(io::Error),
+    /// The TLS handshake failed
+    Tls(String),
+    /// The response wasn't HTTP, or was larger than [`HttpClient::set_max_response_size()`]
+    InvalidResponse(String),
+    /// The request took longer than [`HttpClient::set_timeout()`]
+    TimedOut,
+    /// The worker received a SIGTERM, or the postmaster died, so it should exit
+    Shutdown,
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::InvalidUrl(message) => write!(f, "invalid URL: {}", message),
+            HttpError::Io(e) => write!(f, "{}", e),
+            HttpError::Tls(message) => write!(f, "TLS handshake failed: {}", message),
+            HttpError::InvalidResponse(message) => write!(f, "invalid response: {}", message),
+            HttpError::TimedOut => write!(f, "the request timed out"),
+            HttpError::Shutdown => write!(f, "the request was interrupted by shutdown"),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl From<io::Error> for HttpError {
+    fn from(e: io::Error) -> Self {
+        HttpError::Io(e)
+    }
+}
+
+/// The response to an [`HttpClient`] request, whatever its status
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// The value of the first header named `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Was the request successful, with a `2xx` status?
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The body as text, with anything that isn't UTF-8 replaced
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// A blocking HTTP/1.1 client for background workers, and functions, that talk to web services.
+///
+/// A client blocking in a socket would hold up the worker's shutdown, so its sockets don't
+/// block, and it waits on them with the backend's latch instead, just like
+/// [`BackgroundWorker::wait_latch()`](crate::bgworkers::BackgroundWorker::wait_latch) does.  A
+/// request then fails with [`HttpError::Shutdown`] as soon as the worker receives a SIGTERM, if it
+/// attached a handler for it, or the postmaster dies, and the worker goes on to exit as it would
+/// have.  The SIGTERM isn't consumed, so the worker's loop still sees it.  In a backend running a
+/// query, a query cancel interrupts the request with the query's `ERROR`.
+///
+/// Each request has its own connection, which is closed once it's answered.  Redirects aren't
+/// followed, and resolving the host's name is the one wait which can't be interrupted.  `https`
+/// URLs use the platform's TLS library, and its trusted certificates.
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+/// use std::time::Duration;
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn webhook_worker_main(_arg: pg_sys::Datum) {
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///     let client = HttpClient::new().set_timeout(Duration::from_secs(10));
+///
+///     while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) {
+///         match client.post("https://example.com/hook", "application/json", b"{}") {
+///             Ok(response) if response.is_success() => {}
+///             Ok(response) => log!("the webhook answered {}", response.status),
+///             Err(HttpError::Shutdown) => break,
+///             Err(e) => log!("the webhook failed: {}", e),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    timeout: Duration,
+    max_response_size: usize,
+    headers: Vec<(String, String)>,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What ended a wait on a socket, other than the socket being ready
+#[derive(Debug, Clone, Copy)]
+enum Interruption {
+    TimedOut,
+    Shutdown,
+}
+
+impl HttpClient {
+    /// A client whose requests time out after 30 seconds, and whose responses may be up to 16MB
+    pub fn new() -> Self {
+        HttpClient {
+            timeout: Duration::from_secs(30),
+            max_response_size: 16 * 1024 * 1024,
+            headers: vec![("User-Agent".to_string(), "pgx".to_string())],
+        }
+    }
+
+    /// How long a request may take in all, from connecting to the whole response being received
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The largest response, with its headers, in bytes
+    pub fn set_max_response_size(mut self, bytes: usize) -> Self {
+        self.max_response_size = bytes;
+        self
+    }
+
+    /// Send a header with every request, replacing one of the same name, like `User-Agent`
+    pub fn set_header(mut self, name: &str, value: &str) -> Self {
+        self.headers
+            .retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// `GET` `url`
+    pub fn get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+        self.request("GET", url, &[], None)
+    }
+
+    /// `POST` `body` to `url`, as `content_type`
+    pub fn post(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> Result<HttpResponse, HttpError> {
+        self.request("POST", url, &[("Content-Type", content_type)], Some(body))
+    }
+
+    /// Send a `method` request to `url`, with `headers` besides the client's, and `body`.  It must
+    /// be called from the backend's thread, whose latch it waits on
+    pub fn request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Option<&[u8]>,
+    ) -> Result<HttpResponse, HttpError> {
+        crate::debug_assert_backend!();
+        let url = Url::parse(url).map_err(|e| HttpError::InvalidUrl(format!("{}: {}", url, e)))?;
+        let https = match url.scheme() {
+            "http" => false,
+            "https" => true,
+            scheme => {
+                return Err(HttpError::InvalidUrl(format!(
+                    "unsupported scheme \"{}\"",
+                    scheme
+                )))
+            }
+        };
+        let host = url
+            .host_str()
+            .ok_or_else(|| HttpError::InvalidUrl(format!("{}: no host", url)))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+
+        let deadline = Instant::now() + self.timeout;
+        let interrupted = Rc::new(Cell::new(None));
+        let request = self.encode_request(method, &url, headers, body);
+        let result = connect(&url, deadline, &interrupted).and_then(|stream| {
+            let mut stream = LatchStream {
+                stream,
+                deadline,
+                interrupted: interrupted.clone(),
+            };
+            if https {
+                let connector = TlsConnector::new().map_err(|e| HttpError::Tls(e.to_string()))?;
+                let mut stream = connector
+                    .connect(&host, stream)
+                    .map_err(|e| HttpError::Tls(e.to_string()))?;
+                self.exchange(&mut stream, method, &request)
+            } else {
+                self.exchange(&mut stream, method, &request)
+            }
+        });
+
+        // what ended a wait explains the error it caused, even through the TLS library
+        match (result, interrupted.get()) {
+            (Err(_), Some(Interruption::TimedOut)) => Err(HttpError::TimedOut),
+            (Err(_), Some(Interruption::Shutdown)) => Err(HttpError::Shutdown),
+            (result, _) => result,
+        }
+    }
+
+    fn encode_request(
+        &self,
+        method: &str,
+        url: &Url,
+        headers: &[(&str, &str)],
+        body: Option<&[u8]>,
+    ) -> Vec<u8> {
+        let mut target = url.path().to_string();
+        if let Some(query) = url.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+        let mut host = url.host_str().unwrap_or_default().to_string();
+        if let Some(port) = url.port() {
+            host = format!("{}:{}", host, port);
+        }
+
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n",
+            method, target, host
+        );
+        let client_headers = self
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                !headers
+                    .iter()
+                    .any(|(header, _)| header.eq_ignore_ascii_case(name))
+            })
+            .map(|(name, value)| (name.as_str(), value.as_str()));
+        for (name, value) in client_headers.chain(headers.iter().copied()) {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if let Some(body) = body {
+            request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        request.push_str("\r\n");
+
+        let mut request = request.into_bytes();
+        request.extend_from_slice(body.unwrap_or_default());
+        request
+    }
+
+    fn exchange(
+        &self,
+        stream: &mut impl ReadWrite,
+        method: &str,
+        request: &[u8],
+    ) -> Result<HttpResponse, HttpError> {
+        stream.write_all(request)?;
+        stream.flush()?;
+
+        let mut received = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            if let Some(response) = parse_response(&received, method, false)? {
+                return Ok(response);
+            }
+            let n = match stream.read(&mut buf) {
+                Ok(n) => n,
+                // servers often close TLS connections without saying so first
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => 0,
+                Err(e) => return Err(e.into()),
+            };
+            if n == 0 {
+                return parse_response(&received, method, true)?.ok_or_else(|| {
+                    HttpError::InvalidResponse("the connection closed mid-response".to_string())
+                });
+            }
+            received.extend_from_slice(&buf[..n]);
+            if received.len() > self.max_response_size {
+                return Err(HttpError::InvalidResponse(format!(
+                    "the response is larger than {} bytes",
+                    self.max_response_size
+                )));
+            }
+        }
+    }
+}
+
+trait ReadWrite: Read + Write {}
+impl<T: Read + Write> ReadWrite for T {}
+
+/// A non-blocking socket, which waits on the backend's latch until it's ready, so to the TLS
+/// library it's a blocking one
+#[derive(Debug)]
+struct LatchStream {
+    stream: TcpStream,
+    deadline: Instant,
+    interrupted: Rc<Cell<Option<Interruption>>>,
+}
+
+impl Read for LatchStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.stream.read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => wait_for_socket(
+                    &self.stream,
+                    pg_sys::WL_SOCKET_READABLE,
+                    self.deadline,
+                    &self.interrupted,
+                )?,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Write for LatchStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        loop {
+            match self.stream.write(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => wait_for_socket(
+                    &self.stream,
+                    pg_sys::WL_SOCKET_WRITEABLE,
+                    self.deadline,
+                    &self.interrupted,
+                )?,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                result => return result,
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Connect to the first of `url`'s addresses that accepts, without blocking
+fn connect(
+    url: &Url,
+    deadline: Instant,
+    interrupted: &Cell<Option<Interruption>>,
+) -> Result<TcpStream, HttpError> {
+    let addrs = url.socket_addrs(|| None)?;
+    let mut last_error =
+        io::Error::new(io::ErrorKind::NotFound, format!("{} has no addresses", url));
+    for addr in addrs {
+        match connect_addr(&addr, deadline, interrupted) {
+            Ok(stream) => return Ok(stream),
+            Err(e) if interrupted.get().is_some() => return Err(e.into()),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error.into())
+}
+
+fn connect_addr(
+    addr: &SocketAddr,
+    deadline: Instant,
+    interrupted: &Cell<Option<Interruption>>,
+) -> io::Result<TcpStream> {
+    unsafe {
+        let (family, sockaddr, len) = sockaddr(addr);
+        let fd = libc::socket(family, libc::SOCK_STREAM, 0);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // closes the socket from here on
+        let stream = TcpStream::from_raw_fd(fd);
+        libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        stream.set_nonblocking(true)?;
+
+        if libc::connect(fd, &sockaddr as *const _ as *const libc::sockaddr, len) < 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::EINPROGRESS) {
+                return Err(e);
+            }
+            wait_for_socket(&stream, pg_sys::WL_SOCKET_CONNECTED, deadline, interrupted)?;
+            if let Some(e) = stream.take_error()? {
+                return Err(e);
+            }
+        }
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+unsafe fn sockaddr(addr: &SocketAddr) -> (libc::c_int, libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = std::mem::zeroed();
+    match addr {
+        SocketAddr::V4(addr) => {
+            let sin = &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in);
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+            (
+                libc::AF_INET,
+                storage,
+                std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+            )
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6);
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_addr.s6_addr = addr.ip().octets();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_scope_id = addr.scope_id();
+            (
+                libc::AF_INET6,
+                storage,
+                std::mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t,
+            )
+        }
+    }
+}
+
+/// Wait on the backend's latch until `stream` has `event`, or shutdown is requested, or
+/// `deadline` passes, recording which in `interrupted`
+#[allow(clippy::io_other_error)]
+fn wait_for_socket(
+    stream: &TcpStream,
+    event: u32,
+    deadline: Instant,
+    interrupted: &Cell<Option<Interruption>>,
+) -> io::Result<()> {
+    let interrupt = |why: Interruption| {
+        interrupted.set(Some(why));
+        Err(io::Error::new(io::ErrorKind::Other, format!("{:?}", why)))
+    };
+    loop {
+        if GOT_SIGTERM.load(Ordering::SeqCst) || shutdown::shutdown_requested() {
+            return interrupt(Interruption::Shutdown);
+        }
+        let remaining = match deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if !remaining.is_zero() => remaining,
+            _ => return interrupt(Interruption::TimedOut),
+        };
+
+        let events = unsafe {
+            pg_sys::WaitLatchOrSocket(
+                pg_sys::MyLatch,
+                (pg_sys::WL_LATCH_SET | event | pg_sys::WL_TIMEOUT | pg_sys::WL_POSTMASTER_DEATH)
+                    as i32,
+                stream.as_raw_fd(),
+                remaining.as_millis().clamp(1, i32::MAX as u128) as _,
+                pg_sys::PG_WAIT_EXTENSION,
+            )
+        } as u32;
+        if events & pg_sys::WL_POSTMASTER_DEATH != 0 {
+            shutdown::request_shutdown();
+            return interrupt(Interruption::Shutdown);
+        }
+        if events & pg_sys::WL_LATCH_SET != 0 {
+            unsafe { pg_sys::ResetLatch(pg_sys::MyLatch) };
+            crate::check_for_interrupts!();
+        }
+        if events & event != 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// The response in `received`, or `None` if more is to come.  Once the connection is `closed`,
+/// a response without a length ends there
+#[allow(clippy::unnecessary_map_or)]
+fn parse_response(
+    received: &[u8],
+    method: &str,
+    closed: bool,
+) -> Result<Option<HttpResponse>, HttpError> {
+    let invalid = |message: &str| HttpError::InvalidResponse(message.to_string());
+    let head_len = match received.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(position) => position + 4,
+        None => return Ok(None),
+    };
+    let head = std::str::from_utf8(&received[..head_len - 4])
+        .map_err(|_| invalid("the headers aren't UTF-8"))?;
+    let mut lines = head.split("\r\n");
+
+    let status_line = lines.next().unwrap_or_default();
+    let mut parts = status_line.splitn(3, ' ');
+    if !parts.next().unwrap_or_default().starts_with("HTTP/") {
+        return Err(invalid("the response isn't HTTP"));
+    }
+    let status = parts
+        .next()
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| invalid("the status is missing"))?;
+
+    let mut headers = Vec::new();
+    for line in lines {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid("a header has no value"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    let data = &received[head_len..];
+    let body = if method.eq_ignore_ascii_case("HEAD") || status == 204 || status == 304 {
+        Some(Vec::new())
+    } else if header("Transfer-Encoding").map_or(false, |encoding| {
+        encoding.to_ascii_lowercase().contains("chunked")
+    }) {
+        decode_chunked(data)?
+    } else if let Some(length) = header("Content-Length") {
+        let length = length
+            .parse::<usize>()
+            .map_err(|_| invalid("the Content-Length isn't a number"))?;
+        (data.len() >= length).then(|| data[..length].to_vec())
+    } else {
+        closed.then(|| data.to_vec())
+    };
+
+    Ok(body.map(|body| HttpResponse {
+        status,
+        headers,
+        body,
+    }))
+}
+
+/// The body of a chunked response, or `None` if more is to come
+fn decode_chunked(mut data: &[u8]) -> Result<Option<Vec<u8>>, HttpError> {
+    let mut body = Vec::new();
+    loop {
+        let line_len = match data.windows(2).position(|window| window == b"\r\n") {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+        let size = std::str::from_utf8(&data[..line_len])
+            .ok()
+            .and_then(|line| usize::from_str_radix(line.split(';').next()?.trim(), 16).ok())
+            .ok_or_else(|| {
+                HttpError::InvalidResponse("a chunk's size isn't a number".to_string())
+            })?;
+        data = &data[line_len + 2..];
+        if size == 0 {
+            // the trailers, which are ignored, end with an empty line
+            let trailers_end = data.windows(2).position(|window| window == b"\r\n");
+            return Ok(trailers_end.map(|_| body));
+        }
+        if data.len() < size + 2 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&data[..size]);
+        data = &data[size + 2..];
+    }
+}
@@ -10,17 +10,48 @@ Use of this source code is governed by the MIT license that can be found in the
 //! Safely create Postgres Background Workers, including with full SPI support
 //!
 //! See: [https://www.postgresql.org/docs/12/bgworker.html](https://www.postgresql.org/docs/12/bgworker.html)
-use crate::pg_sys;
+mod config;
+#[cfg(all(feature = "http", unix))]
+mod http;
+mod pool;
+#[cfg(feature = "async")]
+mod runtime;
+#[cfg(feature = "scheduler")]
+mod scheduler;
+mod shutdown;
+mod throttle;
+
+pub use config::*;
+#[cfg(all(feature = "http", unix))]
+pub use http::*;
+pub use pool::*;
+#[cfg(feature = "scheduler")]
+pub use scheduler::*;
+pub use shutdown::*;
+pub use throttle::*;
+
+use crate::{pg_sys, PgMemoryContexts};
+use std::any::Any;
 use std::convert::TryInto;
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::os::raw::c_char;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::panic::{catch_unwind, RefUnwindSafe, UnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::time::Duration;
 
 pub static mut PREV_SHMEM_STARTUP_HOOK: Option<unsafe extern "C" fn()> = None;
 static GOT_SIGHUP: AtomicBool = AtomicBool::new(false);
 static GOT_SIGTERM: AtomicBool = AtomicBool::new(false);
+static GOT_SIGINT: AtomicBool = AtomicBool::new(false);
+static GOT_SIGUSR1: AtomicBool = AtomicBool::new(false);
+/// SIGHUPs not yet returned by [`BackgroundWorker::wait_latch_or_signal()`], which leaves
+/// `GOT_SIGHUP` for [`BackgroundWorker::sighup_received()`] and `ReloadableConfig`
+static SIGHUP_EVENT: AtomicBool = AtomicBool::new(false);
+/// one bit per signal number, for those attached with [`BackgroundWorker::attach_signal()`]
+static PENDING_SIGNALS: AtomicU64 = AtomicU64::new(0);
+/// Postgres' own SIGUSR1 handler, which latches and `ProcSignal`s still need
+static PREV_SIGUSR1_HANDLER: AtomicUsize = AtomicUsize::new(0);
 
 bitflags! {
     struct BGWflags: i32 {
@@ -34,6 +65,8 @@ bitflags! {
     pub struct SignalWakeFlags: i32 {
         const SIGHUP = 0x1;
         const SIGTERM = 0x2;
+        const SIGINT = 0x4;
+        const SIGUSR1 = 0x8;
     }
 }
 
@@ -53,12 +86,30 @@ bitflags! {
 }
 
 /// The various points in which a BackgroundWorker can be started by Postgres
+#[derive(Clone, Copy)]
 pub enum BgWorkerStartTime {
     PostmasterStart = pg_sys::BgWorkerStartTime_BgWorkerStart_PostmasterStart as isize,
     ConsistentState = pg_sys::BgWorkerStartTime_BgWorkerStart_ConsistentState as isize,
     RecoveryFinished = pg_sys::BgWorkerStartTime_BgWorkerStart_RecoveryFinished as isize,
 }
 
+/// What woke a Background Worker up, according to [`BackgroundWorker::wait_latch_or_signal()`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BackgroundWorkerEvent {
+    Sighup,
+    /// The worker should shut down
+    Sigterm,
+    Sigint,
+    Sigusr1,
+    /// One of the signals attached with [`BackgroundWorker::attach_signal()`]
+    Signal(i32),
+    /// Something else set the worker's latch
+    Latch,
+    Timeout,
+    /// The postmaster died, so the worker should exit without cleaning up
+    PostmasterDeath,
+}
+
 /// Static interface into a running Background Worker
 ///
 /// It also provides a few helper functions as wrappers around the global `pgx::pg_sys::MyBgworkerEntry`
@@ -69,7 +120,13 @@ impl BackgroundWorker {
     pub fn get_name() -> &'static str {
         #[cfg(feature = "pg10")]
         const LEN: usize = 64;
-        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+        #[cfg(any(
+            feature = "pg11",
+            feature = "pg12",
+            feature = "pg13",
+            feature = "pg14",
+            feature = "pg15"
+        ))]
         const LEN: usize = 96;
 
         unsafe {
@@ -100,26 +157,107 @@ impl BackgroundWorker {
         GOT_SIGHUP.swap(false, Ordering::SeqCst)
     }
 
-    /// Have we received a SIGTERM?
+    /// Have we received a SIGTERM?  If so, the [`WorkerShutdownHandle`] is tripped too
     pub fn sigterm_received() -> bool {
         // toggle the bool to false, returning whatever it was
-        GOT_SIGTERM.swap(false, Ordering::SeqCst)
+        let received = GOT_SIGTERM.swap(false, Ordering::SeqCst);
+        if received {
+            shutdown::request_shutdown();
+        }
+        received
+    }
+
+    /// Have we received a SIGINT?
+    pub fn sigint_received() -> bool {
+        GOT_SIGINT.swap(false, Ordering::SeqCst)
+    }
+
+    /// Have we received a SIGUSR1?
+    ///
+    /// Before pg14 Postgres wakes processes up with SIGUSR1s as well as setting their latches, so
+    /// a SIGUSR1 that arrives while the worker's latch is already set isn't counted
+    pub fn sigusr1_received() -> bool {
+        GOT_SIGUSR1.swap(false, Ordering::SeqCst)
+    }
+
+    /// Wait on the background worker's latch for up to `timeout`, or forever if it's `None`,
+    /// returning why it woke up.
+    ///
+    /// Signals received before the call are returned right away, one per call, SIGTERM first.  Like
+    /// [`BackgroundWorker::sigterm_received()`] and the rest, returning a signal consumes it, except
+    /// for a SIGHUP, which is still there for [`BackgroundWorker::sighup_received()`] and
+    /// [`ReloadableConfig::check_for_reload()`]:
+    ///
+    /// ```rust,no_run
+    /// use pgx::bgworkers::*;
+    /// use pgx::*;
+    /// use std::time::Duration;
+    ///
+    /// #[pg_guard]
+    /// pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
+    ///     BackgroundWorker::attach_signal_handlers(
+    ///         SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM | SignalWakeFlags::SIGUSR1,
+    ///     );
+    ///
+    ///     loop {
+    ///         match BackgroundWorker::wait_latch_or_signal(Some(Duration::from_secs(10))) {
+    ///             BackgroundWorkerEvent::Sigterm | BackgroundWorkerEvent::PostmasterDeath => break,
+    ///             BackgroundWorkerEvent::Sigusr1 => { /* process new work right away */ }
+    ///             BackgroundWorkerEvent::Timeout => { /* periodic work */ }
+    ///             _ => {}
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn wait_latch_or_signal(timeout: Option<Duration>) -> BackgroundWorkerEvent {
+        if let Some(event) = take_signal() {
+            return event;
+        }
+
+        let events = match timeout {
+            Some(t) => wait_latch(
+                t.as_millis().try_into().unwrap(),
+                WLflags::WL_LATCH_SET | WLflags::WL_TIMEOUT | WLflags::WL_POSTMASTER_DEATH,
+            ),
+            None => wait_latch(0, WLflags::WL_LATCH_SET | WLflags::WL_POSTMASTER_DEATH),
+        };
+        let events = WLflags::from_bits_truncate(events);
+
+        if events.contains(WLflags::WL_POSTMASTER_DEATH) {
+            shutdown::request_shutdown();
+            BackgroundWorkerEvent::PostmasterDeath
+        } else if let Some(event) = take_signal() {
+            event
+        } else if events.contains(WLflags::WL_LATCH_SET) {
+            BackgroundWorkerEvent::Latch
+        } else {
+            BackgroundWorkerEvent::Timeout
+        }
     }
 
     /// Wait for the specified amount of time on the background worker's latch
     ///
     /// Returns true if we're still supposed to be alive and haven't received a SIGTERM
     pub fn wait_latch(timeout: Option<Duration>) -> bool {
-        match timeout {
+        let events = match timeout {
             Some(t) => wait_latch(
                 t.as_millis().try_into().unwrap(),
                 WLflags::WL_LATCH_SET | WLflags::WL_TIMEOUT | WLflags::WL_POSTMASTER_DEATH,
             ),
             None => wait_latch(0, WLflags::WL_LATCH_SET | WLflags::WL_POSTMASTER_DEATH),
         };
+        if WLflags::from_bits_truncate(events).contains(WLflags::WL_POSTMASTER_DEATH) {
+            shutdown::request_shutdown();
+        }
         !BackgroundWorker::sigterm_received()
     }
 
+    /// A handle that threads spawned by this worker can use to find out that it's exiting.  See
+    /// [`WorkerShutdownHandle`]
+    pub fn shutdown_handle() -> WorkerShutdownHandle {
+        WorkerShutdownHandle::get()
+    }
+
     /// Is this `BackgroundWorker` allowed to continue?
     pub fn worker_continue() -> bool {
         pg_sys::WL_POSTMASTER_DEATH as i32 != 0
@@ -138,7 +276,13 @@ impl BackgroundWorker {
             #[cfg(feature = "pg10")]
             pg_sys::BackgroundWorkerInitializeConnection(db as *mut c_char, user as *mut c_char);
 
-            #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+            #[cfg(any(
+                feature = "pg11",
+                feature = "pg12",
+                feature = "pg13",
+                feature = "pg14",
+                feature = "pg15"
+            ))]
             pg_sys::BackgroundWorkerInitializeConnection(db, user, 0);
         };
     }
@@ -159,30 +303,148 @@ impl BackgroundWorker {
             if wake.contains(SignalWakeFlags::SIGTERM) {
                 pg_sys::pqsignal(pg_sys::SIGTERM as i32, Some(worker_spi_sigterm));
             }
+            if wake.contains(SignalWakeFlags::SIGINT) {
+                pg_sys::pqsignal(libc::SIGINT, Some(worker_sigint));
+            }
+            if wake.contains(SignalWakeFlags::SIGUSR1) {
+                if let Some(prev) = pg_sys::pqsignal(libc::SIGUSR1, Some(worker_sigusr1)) {
+                    let ours = worker_sigusr1 as unsafe extern "C" fn(i32);
+                    if prev as *const () != ours as *const () {
+                        PREV_SIGUSR1_HANDLER.store(prev as usize, Ordering::SeqCst);
+                    }
+                }
+            }
             pg_sys::BackgroundWorkerUnblockSignals();
         }
     }
 
+    /// Deliver `signal`, which isn't one of the [`SignalWakeFlags`], as a
+    /// [`BackgroundWorkerEvent::Signal`] from [`BackgroundWorker::wait_latch_or_signal()`].
+    ///
+    /// The handler only notes the signal and sets the latch, so the worker handles it in its main
+    /// loop, where it's safe to do anything, rather than in the signal handler.  It replaces
+    /// whatever handler the signal had, so don't use it for signals Postgres handles itself
+    pub fn attach_signal(signal: i32) {
+        assert!(
+            (1..64).contains(&signal),
+            "signal {} can't be attached",
+            signal
+        );
+        unsafe {
+            pg_sys::pqsignal(signal, Some(worker_signal));
+        }
+    }
+
     /// Once connected to SPI via `connect_worker_to_spi()`, begin a transaction to
-    /// use the `pgx::Spi` interface.
-    pub fn transaction<F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe>(
-        transaction_body: F,
-    ) {
+    /// use the `pgx::Spi` interface, returning whatever `transaction_body` returns.
+    ///
+    /// If `transaction_body` raises an error (or panics), the transaction is aborted before the
+    /// error is rethrown, so code that catches it doesn't carry on in a broken transaction.
+    /// Use [`BackgroundWorker::try_transaction()`] to keep the worker running instead.
+    pub fn transaction<R, F: FnOnce() -> R + UnwindSafe + RefUnwindSafe>(transaction_body: F) -> R {
         unsafe {
-            pg_sys::SetCurrentStatementStartTimestamp();
-            pg_sys::StartTransactionCommand();
-            pg_sys::PushActiveSnapshot(pg_sys::GetTransactionSnapshot());
+            start_transaction();
         }
-        pg_sys::guard(|| transaction_body());
+        let result = pg_sys::pg_try(|| transaction_body())
+            .unwrap_or_rethrow(|| unsafe { pg_sys::AbortCurrentTransaction() });
         unsafe {
             pg_sys::PopActiveSnapshot();
             pg_sys::CommitTransactionCommand();
         }
+        result
     }
+
+    /// Like [`BackgroundWorker::transaction()`], but an error (or panic) raised by
+    /// `transaction_body` is logged, the transaction is aborted, and the error's message is
+    /// returned, leaving the worker ready for its next transaction.
+    ///
+    /// ```rust,no_run
+    /// use pgx::bgworkers::*;
+    /// use pgx::*;
+    /// use std::time::Duration;
+    ///
+    /// #[pg_guard]
+    /// #[no_mangle]
+    /// pub extern "C" fn worker_main(_arg: pg_sys::Datum) {
+    ///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    ///     BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+    ///
+    ///     while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) {
+    ///         // a failed refresh is logged, and tried again next time around
+    ///         let _ = BackgroundWorker::try_transaction(|| Spi::run("REFRESH MATERIALIZED VIEW stats"));
+    ///     }
+    /// }
+    /// ```
+    pub fn try_transaction<R, F: FnOnce() -> R + UnwindSafe + RefUnwindSafe>(
+        transaction_body: F,
+    ) -> Result<R, String> {
+        unsafe {
+            start_transaction();
+        }
+        match catch_unwind(transaction_body) {
+            Ok(result) => {
+                unsafe {
+                    pg_sys::PopActiveSnapshot();
+                    pg_sys::CommitTransactionCommand();
+                }
+                Ok(result)
+            }
+            Err(e) => Err(unsafe { abort_transaction(e) }),
+        }
+    }
+}
+
+unsafe fn start_transaction() {
+    pg_sys::SetCurrentStatementStartTimestamp();
+    pg_sys::StartTransactionCommand();
+    pg_sys::PushActiveSnapshot(pg_sys::GetTransactionSnapshot());
+}
+
+/// Report and clear the error that ended a transaction, then abort it, the same way Postgres'
+/// own background processes recover from an ERROR
+unsafe fn abort_transaction(error: Box<dyn Any + Send>) -> String {
+    pg_sys::InterruptHoldoffCount += 1;
+
+    let message = if error.downcast_ref::<pg_sys::JumpContext>().is_some() {
+        // the ERROR is still on Postgres' error stack, where `CurrentMemoryContext` is probably
+        // the ErrorContext, which it can't be copied into
+        PgMemoryContexts::TopMemoryContext.set_as_current();
+        let edata = pg_sys::CopyErrorData();
+        let message = if (*edata).message.is_null() {
+            "unknown error".to_string()
+        } else {
+            CStr::from_ptr((*edata).message)
+                .to_string_lossy()
+                .into_owned()
+        };
+        pg_sys::FreeErrorData(edata);
+        pg_sys::EmitErrorReport();
+        message
+    } else {
+        let message = if let Some(panic) = error.downcast_ref::<pg_sys::PgxPanic>() {
+            panic.message.to_string()
+        } else if let Some(message) = error.downcast_ref::<String>() {
+            message.clone()
+        } else if let Some(message) = error.downcast_ref::<&str>() {
+            message.to_string()
+        } else {
+            "unknown panic".to_string()
+        };
+        crate::warning!("background worker transaction failed: {}", message);
+        message
+    };
+
+    pg_sys::AbortCurrentTransaction();
+    pg_sys::FlushErrorState();
+    PgMemoryContexts::TopMemoryContext.set_as_current();
+
+    pg_sys::InterruptHoldoffCount -= 1;
+    message
 }
 
 unsafe extern "C" fn worker_spi_sighup(_signal_args: i32) {
     GOT_SIGHUP.store(true, Ordering::SeqCst);
+    SIGHUP_EVENT.store(true, Ordering::SeqCst);
     pg_sys::ProcessConfigFile(pg_sys::GucContext_PGC_SIGHUP);
     pg_sys::SetLatch(pg_sys::MyLatch);
 }
@@ -192,6 +454,60 @@ unsafe extern "C" fn worker_spi_sigterm(_signal_args: i32) {
     pg_sys::SetLatch(pg_sys::MyLatch);
 }
 
+unsafe extern "C" fn worker_sigint(_signal_args: i32) {
+    GOT_SIGINT.store(true, Ordering::SeqCst);
+    pg_sys::SetLatch(pg_sys::MyLatch);
+}
+
+unsafe extern "C" fn worker_sigusr1(signal_args: i32) {
+    // before pg14, setting another process' latch also sends it a SIGUSR1, once the latch is set,
+    // so one that finds the latch already set is Postgres waking the worker, not a SIGUSR1 for it
+    #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+    let latch_wakeup = std::ptr::read_volatile(&(*pg_sys::MyLatch).is_set) != 0;
+    #[cfg(any(feature = "pg14", feature = "pg15"))]
+    let latch_wakeup = false;
+    if !latch_wakeup {
+        GOT_SIGUSR1.store(true, Ordering::SeqCst);
+    }
+
+    // SIG_DFL and SIG_IGN aren't functions we can call
+    let prev = PREV_SIGUSR1_HANDLER.load(Ordering::SeqCst);
+    if prev > 1 {
+        let prev = std::mem::transmute::<usize, unsafe extern "C" fn(i32)>(prev);
+        prev(signal_args);
+    }
+    pg_sys::SetLatch(pg_sys::MyLatch);
+}
+
+unsafe extern "C" fn worker_signal(signal_args: i32) {
+    PENDING_SIGNALS.fetch_or(1 << signal_args, Ordering::SeqCst);
+    pg_sys::SetLatch(pg_sys::MyLatch);
+}
+
+/// Consume the most important signal received, if any
+fn take_signal() -> Option<BackgroundWorkerEvent> {
+    if BackgroundWorker::sigterm_received() {
+        return Some(BackgroundWorkerEvent::Sigterm);
+    }
+    if BackgroundWorker::sigint_received() {
+        return Some(BackgroundWorkerEvent::Sigint);
+    }
+    if SIGHUP_EVENT.swap(false, Ordering::SeqCst) {
+        return Some(BackgroundWorkerEvent::Sighup);
+    }
+    if BackgroundWorker::sigusr1_received() {
+        return Some(BackgroundWorkerEvent::Sigusr1);
+    }
+
+    let pending = PENDING_SIGNALS.load(Ordering::SeqCst);
+    if pending != 0 {
+        let signal = pending.trailing_zeros();
+        PENDING_SIGNALS.fetch_and(!(1 << signal), Ordering::SeqCst);
+        return Some(BackgroundWorkerEvent::Signal(signal as i32));
+    }
+    None
+}
+
 /// A builder-style interface for creating a new Background Worker
 ///
 /// This must be used from within your extension's `_PG_init()` function,
@@ -379,8 +695,44 @@ impl BackgroundWorkerBuilder {
     /// Once properly configured, call `load()` to get the BackgroundWorker registered and
     /// started at the proper time by Postgres.
     pub fn load(self: Self) {
+        let mut bgw = self.to_pg_bgw();
+
+        unsafe {
+            pg_sys::RegisterBackgroundWorker(&mut bgw);
+            if self.bgw_flags.contains(BGWflags::BGWORKER_SHMEM_ACCESS)
+                && self.shared_memory_startup_fn.is_some()
+            {
+                PREV_SHMEM_STARTUP_HOOK = pg_sys::shmem_startup_hook;
+                pg_sys::shmem_startup_hook = self.shared_memory_startup_fn;
+            }
+        };
+    }
+
+    /// Start the BackgroundWorker now, from a running backend or another BackgroundWorker,
+    /// rather than registering it from `_PG_init()`.
+    ///
+    /// Returns `None` if no background worker slots are free (see `max_worker_processes`).
+    /// Use `set_notify_pid()` for the latch of the starting process to be set when the worker
+    /// starts and exits.
+    pub fn load_dynamic(self) -> Option<DynamicBackgroundWorker> {
+        let mut bgw = self.to_pg_bgw();
+
+        // the handle has to outlive whatever memory context we happen to be in
+        let (registered, handle) = PgMemoryContexts::TopMemoryContext.switch_to(move |_| unsafe {
+            let mut handle = std::ptr::null_mut();
+            let registered = pg_sys::RegisterDynamicBackgroundWorker(&mut bgw, &mut handle);
+            (registered, handle)
+        });
+        if registered {
+            Some(DynamicBackgroundWorker { handle })
+        } else {
+            None
+        }
+    }
+
+    fn to_pg_bgw(&self) -> pg_sys::BackgroundWorker {
         #[cfg(feature = "pg10")]
-        let mut bgw = pg_sys::BackgroundWorker {
+        let bgw = pg_sys::BackgroundWorker {
             bgw_name: RpgffiChar::from(&self.bgw_name[..]).0,
             bgw_flags: self.bgw_flags.bits(),
             bgw_start_time: self.bgw_start_time as u32,
@@ -395,8 +747,14 @@ impl BackgroundWorkerBuilder {
             bgw_notify_pid: self.bgw_notify_pid,
         };
 
-        #[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
-        let mut bgw = pg_sys::BackgroundWorker {
+        #[cfg(any(
+            feature = "pg11",
+            feature = "pg12",
+            feature = "pg13",
+            feature = "pg14",
+            feature = "pg15"
+        ))]
+        let bgw = pg_sys::BackgroundWorker {
             bgw_name: RpgffiChar::from(&self.bgw_name[..]).0,
             bgw_type: RpgffiChar::from(&self.bgw_type[..]).0,
             bgw_flags: self.bgw_flags.bits(),
@@ -412,15 +770,50 @@ impl BackgroundWorkerBuilder {
             bgw_notify_pid: self.bgw_notify_pid,
         };
 
-        unsafe {
-            pg_sys::RegisterBackgroundWorker(&mut bgw);
-            if self.bgw_flags.contains(BGWflags::BGWORKER_SHMEM_ACCESS)
-                && self.shared_memory_startup_fn.is_some()
-            {
-                PREV_SHMEM_STARTUP_HOOK = pg_sys::shmem_startup_hook;
-                pg_sys::shmem_startup_hook = self.shared_memory_startup_fn;
-            }
-        };
+        bgw
+    }
+}
+
+/// The state of a [`DynamicBackgroundWorker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundWorkerStatus {
+    /// The worker is running, as the contained process id
+    Started(pg_sys::pid_t),
+    /// The postmaster hasn't started the worker yet
+    NotYetStarted,
+    /// The worker has exited
+    Stopped,
+    /// The postmaster has died, and the worker's state is unknown
+    PostmasterDied,
+}
+
+/// A BackgroundWorker started with [`BackgroundWorkerBuilder::load_dynamic()`]
+pub struct DynamicBackgroundWorker {
+    handle: *mut pg_sys::BackgroundWorkerHandle,
+}
+
+impl DynamicBackgroundWorker {
+    /// What is the worker doing?
+    pub fn status(&self) -> BackgroundWorkerStatus {
+        let mut pid = 0;
+        match unsafe { pg_sys::GetBackgroundWorkerPid(self.handle, &mut pid) } {
+            pg_sys::BgwHandleStatus_BGWH_STARTED => BackgroundWorkerStatus::Started(pid),
+            pg_sys::BgwHandleStatus_BGWH_NOT_YET_STARTED => BackgroundWorkerStatus::NotYetStarted,
+            pg_sys::BgwHandleStatus_BGWH_STOPPED => BackgroundWorkerStatus::Stopped,
+            _ => BackgroundWorkerStatus::PostmasterDied,
+        }
+    }
+
+    /// Ask the postmaster to send the worker a SIGTERM, or not to start it if it hasn't yet
+    pub fn terminate(&self) {
+        unsafe { pg_sys::TerminateBackgroundWorker(self.handle) }
+    }
+}
+
+impl Drop for DynamicBackgroundWorker {
+    fn drop(&mut self) {
+        // dropping the handle leaves the worker running
+        unsafe { pg_sys::pfree(self.handle as *mut std::os::raw::c_void) }
     }
 }
 
@@ -442,7 +835,13 @@ fn wait_latch(timeout: i64, wakeup_flags: WLflags) -> i32 {
 #[cfg(feature = "pg10")]
 type RpgffiChar = RpgffiChar64;
 
-#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[cfg(any(
+    feature = "pg11",
+    feature = "pg12",
+    feature = "pg13",
+    feature = "pg14",
+    feature = "pg15"
+))]
 type RpgffiChar = RpgffiChar96;
 
 struct RpgffiChar64([c_char; 64]);
@@ -0,0 +1,564 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Supervised pools of Background Workers
+//!
+//! A [`PgWorkerPool`] runs a leader BackgroundWorker which starts each of the pool's workers,
+//! restarts them according to their [`RestartPolicy`] with an exponential backoff, and terminates
+//! any that stop reporting heartbeats.
+use crate::bgworkers::{
+    BackgroundWorker, BackgroundWorkerBuilder, BackgroundWorkerStatus, BgWorkerStartTime,
+    DynamicBackgroundWorker, SignalWakeFlags,
+};
+use crate::{
+    pg_sys, with_addin_shmem_init_lock, PgSharedMemoryInitialization, TimestampWithTimeZone,
+};
+use once_cell::sync::OnceCell;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicI32, AtomicI64, AtomicPtr, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// The most workers a single [`PgWorkerPool`] can supervise
+pub const MAX_POOL_WORKERS: usize = 32;
+
+/// How often the leader checks on its workers, when not woken by one of them starting or exiting
+const SUPERVISOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the leader of a [`PgWorkerPool`] does when one of its workers exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Leave the worker stopped
+    Never,
+    /// Restart the worker if it exited with an error, was terminated for missing heartbeats, or
+    /// never called [`PgWorkerPool::join()`]
+    OnFailure,
+    /// Restart the worker however it exited
+    Always,
+}
+
+/// One worker of a [`PgWorkerPool`]
+///
+/// By default it restarts on failure and has no SPI access.
+pub struct PgPoolWorker {
+    name: String,
+    function: String,
+    restart_policy: RestartPolicy,
+    spi: bool,
+}
+
+impl PgPoolWorker {
+    /// A worker which runs the "main" `function`, which has the same requirements as
+    /// [`BackgroundWorkerBuilder::set_function()`]
+    pub fn new(name: &str, function: &str) -> PgPoolWorker {
+        PgPoolWorker {
+            name: name.to_string(),
+            function: function.to_string(),
+            restart_policy: RestartPolicy::OnFailure,
+            spi: false,
+        }
+    }
+
+    /// What should happen when this worker exits?
+    pub fn set_restart_policy(mut self, input: RestartPolicy) -> Self {
+        self.restart_policy = input;
+        self
+    }
+
+    /// Does this worker intend to use SPI?
+    ///
+    /// If set, the postmaster won't start the worker until recovery has finished
+    pub fn enable_spi_access(mut self) -> Self {
+        self.spi = true;
+        self
+    }
+}
+
+struct PoolConfig {
+    library: String,
+    workers: Vec<PgPoolWorker>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    heartbeat_timeout: Option<Duration>,
+}
+
+/// The state of each worker, which the leader and the workers share
+#[derive(Default)]
+struct PoolShared {
+    /// Bumped each time a leader starts, so workers started by a previous leader can tell
+    generation: AtomicU32,
+    slots: [WorkerSlot; MAX_POOL_WORKERS],
+}
+
+#[derive(Default)]
+struct WorkerSlot {
+    pid: AtomicI32,
+    heartbeat: AtomicI64,
+    exit_code: AtomicI32,
+    restarts: AtomicU32,
+}
+
+/// A pool of supervised Background Workers
+///
+/// The pool lives in a `static`, is placed in shared memory with `pg_shmem_init!()`, and is
+/// configured with a [`PgWorkerPoolBuilder`], all from `_PG_init()`.  Its leader's "main"
+/// function only needs to call [`PgWorkerPool::supervise()`], and each worker calls
+/// [`PgWorkerPool::join()`] when it starts and [`PgWorkerPool::heartbeat()`] as it works.
+///
+/// > Like any BackgroundWorker, the extension **must** be loaded via `postgresql.conf`'s
+/// > `shared_preload_libraries` configuration setting.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+/// use std::time::Duration;
+///
+/// static POOL: PgWorkerPool = PgWorkerPool::new("example_pool");
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(POOL);
+///     PgWorkerPoolBuilder::new(&POOL)
+///         .set_library("example")
+///         .set_leader_function("example_pool_leader")
+///         .add_worker(PgPoolWorker::new("indexer", "indexer_main").enable_spi_access())
+///         .set_backoff(Duration::from_secs(1), Duration::from_secs(60))
+///         .set_heartbeat_timeout(Some(Duration::from_secs(30)))
+///         .load();
+/// }
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn example_pool_leader(_arg: pg_sys::Datum) {
+///     POOL.supervise();
+/// }
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn indexer_main(_arg: pg_sys::Datum) {
+///     POOL.join();
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///     BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+///
+///     while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) && POOL.heartbeat() {
+///         // do work here
+///     }
+/// }
+/// ```
+pub struct PgWorkerPool {
+    name: &'static str,
+    config: OnceCell<PoolConfig>,
+    shared: AtomicPtr<PoolShared>,
+}
+
+/// The state of one of a [`PgWorkerPool`]'s workers, from [`PgWorkerPool::status()`]
+#[derive(Debug, Clone)]
+pub struct PgPoolWorkerStatus {
+    pub name: String,
+    /// The worker's process id, if it's running and has joined the pool
+    pub pid: Option<pg_sys::pid_t>,
+    pub last_heartbeat: Option<TimestampWithTimeZone>,
+    /// How many times the leader has restarted the worker
+    pub restarts: u32,
+}
+
+impl PgWorkerPool {
+    /// Create an empty pool, to be held in a `static`
+    pub const fn new(name: &'static str) -> Self {
+        PgWorkerPool {
+            name,
+            config: OnceCell::new(),
+            shared: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// What is the name of this pool?
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Run the pool's leader.  This must be all its "main" function does, and only returns once
+    /// the leader receives a SIGTERM, after terminating the pool's workers
+    pub fn supervise(&'static self) {
+        BackgroundWorker::attach_signal_handlers(
+            SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM,
+        );
+
+        let config = self.config();
+        let generation = self.shared().generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut workers = config
+            .workers
+            .iter()
+            .map(|_| SupervisedWorker {
+                handle: None,
+                started_at: Instant::now(),
+                next_start: Instant::now(),
+                failures: 0,
+                terminated: false,
+                finished: false,
+            })
+            .collect::<Vec<_>>();
+
+        loop {
+            for (index, worker) in workers.iter_mut().enumerate() {
+                self.supervise_worker(index, worker, generation);
+            }
+
+            if !BackgroundWorker::wait_latch(Some(SUPERVISOR_INTERVAL)) {
+                break;
+            }
+        }
+
+        for worker in &workers {
+            if let Some(handle) = &worker.handle {
+                handle.terminate();
+            }
+        }
+    }
+
+    /// Must be called first thing from a pool worker's "main" function, so the leader can tell
+    /// how the worker exits
+    pub fn join(&'static self) {
+        let index = self.worker_index();
+        let slot = &self.shared().slots[index];
+        slot.pid
+            .store(unsafe { pg_sys::MyProcPid }, Ordering::SeqCst);
+        slot.heartbeat
+            .store(unsafe { pg_sys::GetCurrentTimestamp() }, Ordering::SeqCst);
+        unsafe {
+            pg_sys::before_shmem_exit(
+                Some(pool_worker_exit),
+                self as *const PgWorkerPool as pg_sys::Datum,
+            );
+        }
+    }
+
+    /// Report that this pool worker is still healthy.  Should be called more often than the
+    /// pool's heartbeat timeout.
+    ///
+    /// Returns false if the leader which started this worker has since exited, in which case the
+    /// worker should exit too, as the new leader will start its own
+    pub fn heartbeat(&'static self) -> bool {
+        let slot = &self.shared().slots[self.worker_index()];
+        slot.heartbeat
+            .store(unsafe { pg_sys::GetCurrentTimestamp() }, Ordering::SeqCst);
+
+        let generation = BackgroundWorker::get_extra().parse::<u32>().ok();
+        generation == Some(self.shared().generation.load(Ordering::SeqCst))
+    }
+
+    /// The state of each of the pool's workers, in the order they were added
+    pub fn status(&self) -> Vec<PgPoolWorkerStatus> {
+        let shared = self.shared();
+        self.config()
+            .workers
+            .iter()
+            .zip(shared.slots.iter())
+            .map(|(worker, slot)| {
+                let pid = slot.pid.load(Ordering::SeqCst);
+                let heartbeat = slot.heartbeat.load(Ordering::SeqCst);
+                PgPoolWorkerStatus {
+                    name: worker.name.clone(),
+                    pid: if pid == 0 { None } else { Some(pid) },
+                    last_heartbeat: if heartbeat == 0 {
+                        None
+                    } else {
+                        Some(TimestampWithTimeZone::from(heartbeat))
+                    },
+                    restarts: slot.restarts.load(Ordering::SeqCst),
+                }
+            })
+            .collect()
+    }
+
+    fn supervise_worker(&self, index: usize, worker: &mut SupervisedWorker, generation: u32) {
+        let config = self.config();
+        let spec = &config.workers[index];
+        let slot = &self.shared().slots[index];
+
+        let status = match &worker.handle {
+            Some(handle) => handle.status(),
+            None => {
+                if !worker.finished && Instant::now() >= worker.next_start {
+                    self.start_worker(index, worker, generation);
+                }
+                return;
+            }
+        };
+
+        match status {
+            BackgroundWorkerStatus::NotYetStarted => (),
+            BackgroundWorkerStatus::Started(pid) => {
+                if let Some(timeout) = config.heartbeat_timeout {
+                    if worker.terminated {
+                        return;
+                    }
+
+                    let heartbeat = slot.heartbeat.load(Ordering::SeqCst);
+                    let stale = if heartbeat == 0 {
+                        worker.started_at.elapsed() > timeout
+                    } else {
+                        let now = unsafe { pg_sys::GetCurrentTimestamp() };
+                        now - heartbeat > timeout.as_micros() as i64
+                    };
+                    if stale {
+                        crate::warning!(
+                            "terminating worker \"{}\" of pool \"{}\" (pid {}), which missed its heartbeat",
+                            spec.name,
+                            self.name,
+                            pid
+                        );
+                        worker.terminated = true;
+                        if let Some(handle) = &worker.handle {
+                            handle.terminate();
+                        }
+                    }
+                }
+            }
+            BackgroundWorkerStatus::Stopped | BackgroundWorkerStatus::PostmasterDied => {
+                worker.handle = None;
+                slot.pid.store(0, Ordering::SeqCst);
+
+                let failed = worker.terminated || slot.exit_code.load(Ordering::SeqCst) != 0;
+                worker.terminated = false;
+                let restart = match spec.restart_policy {
+                    RestartPolicy::Never => false,
+                    RestartPolicy::OnFailure => failed,
+                    RestartPolicy::Always => true,
+                };
+                if !restart {
+                    worker.finished = true;
+                    return;
+                }
+
+                if !failed || worker.started_at.elapsed() >= config.max_backoff {
+                    // it ran long enough that this isn't a crash loop
+                    worker.failures = 0;
+                }
+                if failed {
+                    worker.failures = worker.failures.saturating_add(1);
+                }
+                worker.next_start = Instant::now() + config.backoff(worker.failures);
+                slot.restarts.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    fn start_worker(&self, index: usize, worker: &mut SupervisedWorker, generation: u32) {
+        let config = self.config();
+        let spec = &config.workers[index];
+        let slot = &self.shared().slots[index];
+
+        // until the worker says otherwise, assume it failed
+        slot.exit_code.store(-1, Ordering::SeqCst);
+        slot.heartbeat.store(0, Ordering::SeqCst);
+        slot.pid.store(0, Ordering::SeqCst);
+
+        let mut builder = BackgroundWorkerBuilder::new(&format!("{} {}", self.name, spec.name))
+            .set_type(self.name)
+            .set_library(&config.library)
+            .set_function(&spec.function)
+            .set_argument(Some(index as pg_sys::Datum))
+            .set_extra(&generation.to_string())
+            .set_notify_pid(unsafe { pg_sys::MyProcPid })
+            .enable_shmem_access(None);
+        if spec.spi {
+            builder = builder.enable_spi_access();
+        }
+
+        worker.started_at = Instant::now();
+        match builder.load_dynamic() {
+            Some(handle) => worker.handle = Some(handle),
+            None => {
+                crate::warning!(
+                    "could not start worker \"{}\" of pool \"{}\"; consider increasing max_worker_processes",
+                    spec.name,
+                    self.name
+                );
+                worker.next_start = Instant::now() + config.max_backoff;
+            }
+        }
+    }
+
+    fn worker_index(&self) -> usize {
+        let entry =
+            unsafe { pg_sys::MyBgworkerEntry.as_ref() }.expect("not running in a BackgroundWorker");
+        let index = entry.bgw_main_arg;
+        if index >= self.config().workers.len() {
+            panic!("not running in a worker of pool \"{}\"", self.name);
+        }
+        index
+    }
+
+    fn config(&self) -> &PoolConfig {
+        self.config.get().unwrap_or_else(|| {
+            panic!(
+                "worker pool \"{}\" was not loaded with a PgWorkerPoolBuilder",
+                self.name
+            )
+        })
+    }
+
+    fn shared(&self) -> &'static PoolShared {
+        unsafe { self.shared.load(Ordering::SeqCst).as_ref() }.unwrap_or_else(|| {
+            panic!(
+                "worker pool \"{}\" was not initialized with pg_shmem_init!()",
+                self.name
+            )
+        })
+    }
+}
+
+impl PgSharedMemoryInitialization for PgWorkerPool {
+    fn pg_init(&'static self) {
+        unsafe {
+            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<PoolShared>());
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let shm_name = std::ffi::CString::new(format!("pgx worker pool {}", self.name))
+                .expect("CString::new() failed");
+
+            let shared = with_addin_shmem_init_lock(|| {
+                let mut found = false;
+                let shared = pg_sys::ShmemInitStruct(
+                    shm_name.into_raw(),
+                    std::mem::size_of::<PoolShared>(),
+                    &mut found,
+                ) as *mut PoolShared;
+                if !found {
+                    std::ptr::write(shared, PoolShared::default());
+                }
+                shared
+            });
+
+            self.shared.store(shared, Ordering::SeqCst);
+        }
+    }
+}
+
+/// A builder-style interface for configuring a [`PgWorkerPool`]
+///
+/// This must be used from within your extension's `_PG_init()` function, finishing with the
+/// `.load()` function.  By default the library and the pool leader's function are both named
+/// after the pool, failed workers are first restarted after one second, backing off to a minute,
+/// and heartbeats aren't checked.
+pub struct PgWorkerPoolBuilder {
+    pool: &'static PgWorkerPool,
+    leader_function: String,
+    config: PoolConfig,
+}
+
+impl PgWorkerPoolBuilder {
+    pub fn new(pool: &'static PgWorkerPool) -> PgWorkerPoolBuilder {
+        PgWorkerPoolBuilder {
+            pool,
+            leader_function: pool.name.to_string(),
+            config: PoolConfig {
+                library: pool.name.to_string(),
+                workers: vec![],
+                initial_backoff: Duration::from_secs(1),
+                max_backoff: Duration::from_secs(60),
+                heartbeat_timeout: None,
+            },
+        }
+    }
+
+    /// What is the library name that contains the leader's and workers' "main" functions?
+    pub fn set_library(mut self, input: &str) -> Self {
+        self.config.library = input.to_string();
+        self
+    }
+
+    /// The leader's "main" function, which must call [`PgWorkerPool::supervise()`]
+    pub fn set_leader_function(mut self, input: &str) -> Self {
+        self.leader_function = input.to_string();
+        self
+    }
+
+    /// Add a worker to the pool.  The leader starts them in the order they're added
+    pub fn add_worker(mut self, worker: PgPoolWorker) -> Self {
+        self.config.workers.push(worker);
+        self
+    }
+
+    /// How long to wait before restarting a worker that failed.  The wait doubles with each
+    /// consecutive failure, up to `max`
+    pub fn set_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.config.initial_backoff = initial;
+        self.config.max_backoff = max;
+        self
+    }
+
+    /// Terminate (and possibly restart) workers which go longer than this without calling
+    /// [`PgWorkerPool::heartbeat()`], or `None` to not check
+    pub fn set_heartbeat_timeout(mut self, input: Option<Duration>) -> Self {
+        self.config.heartbeat_timeout = input;
+        self
+    }
+
+    /// Once properly configured, call `load()` to register the pool's leader with Postgres.
+    pub fn load(self) {
+        if self.config.workers.len() > MAX_POOL_WORKERS {
+            panic!(
+                "worker pool \"{}\" has more than {} workers",
+                self.pool.name, MAX_POOL_WORKERS
+            );
+        }
+
+        // the leader restarts itself if it crashes, and then its workers
+        BackgroundWorkerBuilder::new(&format!("{} leader", self.pool.name))
+            .set_type(self.pool.name)
+            .set_library(&self.config.library)
+            .set_function(&self.leader_function)
+            .enable_shmem_access(None)
+            .set_start_time(BgWorkerStartTime::ConsistentState)
+            .set_restart_time(Some(
+                self.config.initial_backoff.max(Duration::from_secs(1)),
+            ))
+            .load();
+
+        if self.pool.config.set(self.config).is_err() {
+            panic!("worker pool \"{}\" was loaded twice", self.pool.name);
+        }
+    }
+}
+
+impl PoolConfig {
+    fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.min(31));
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+/// The leader's view of one of its workers
+struct SupervisedWorker {
+    handle: Option<DynamicBackgroundWorker>,
+    started_at: Instant,
+    next_start: Instant,
+    failures: u32,
+    terminated: bool,
+    finished: bool,
+}
+
+unsafe extern "C" fn pool_worker_exit(code: c_int, arg: pg_sys::Datum) {
+    let pool = &*(arg as *const PgWorkerPool);
+    let shared = pool.shared.load(Ordering::SeqCst).as_ref();
+    if let (Some(shared), Some(entry)) = (shared, pg_sys::MyBgworkerEntry.as_ref()) {
+        if let Some(slot) = shared.slots.get(entry.bgw_main_arg) {
+            slot.exit_code.store(code, Ordering::SeqCst);
+            slot.pid.store(0, Ordering::SeqCst);
+        }
+    }
+}
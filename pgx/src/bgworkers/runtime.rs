@@ -0,0 +1,134 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Async Background Workers, hosting a tokio runtime
+//!
+//! Requires `pgx`'s `async` feature.
+use crate::bgworkers::{shutdown, BackgroundWorker, GOT_SIGTERM};
+use crate::pg_sys;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// How often async waits check the worker's latch.  Postgres sets the latch from signal handlers,
+/// where waking the runtime directly isn't safe
+const LATCH_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+impl BackgroundWorker {
+    /// Run `future` to completion on a single threaded tokio runtime, with its time driver
+    /// enabled.
+    ///
+    /// Returns `None` if the worker receives a SIGTERM first, in which case the future is dropped
+    /// where it last yielded, so a worker blocked on the network still exits promptly.  The
+    /// future, and any tasks it spawns, are polled on the worker's own thread, so they can use
+    /// Postgres (including [`BackgroundWorker::transaction()`] and SPI), but anything passed to
+    /// `tokio::task::spawn_blocking()` runs on another thread and must not.
+    ///
+    /// ```rust,no_run
+    /// use pgx::bgworkers::*;
+    /// use pgx::*;
+    /// use std::time::Duration;
+    ///
+    /// #[pg_guard]
+    /// #[no_mangle]
+    /// pub extern "C" fn async_worker_main(_arg: pg_sys::Datum) {
+    ///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+    ///     BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+    ///
+    ///     BackgroundWorker::run_async(async {
+    ///         while BackgroundWorker::wait_latch_async(Some(Duration::from_secs(10))).await {
+    ///             // await network requests here, then save the results
+    ///             BackgroundWorker::transaction(|| Spi::run("SELECT 1"));
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn run_async<F: Future>(future: F) -> Option<F::Output> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("failed to start the async runtime");
+
+        runtime.block_on(UntilTerminated {
+            future: Box::pin(future),
+            terminated: Box::pin(sigterm()),
+        })
+    }
+
+    /// The async version of [`BackgroundWorker::wait_latch()`], for use within
+    /// [`BackgroundWorker::run_async()`]
+    ///
+    /// Waits for the specified amount of time, or until the worker's latch is set, such as by a
+    /// SIGHUP or SIGTERM.  Returns true if we're still supposed to be alive: we haven't received
+    /// a SIGTERM, and the postmaster hasn't died
+    pub async fn wait_latch_async(timeout: Option<Duration>) -> bool {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            // polls, without blocking, so the runtime can get on with other tasks meanwhile
+            let events = unsafe {
+                pg_sys::WaitLatch(
+                    pg_sys::MyLatch,
+                    (pg_sys::WL_LATCH_SET | pg_sys::WL_TIMEOUT | pg_sys::WL_POSTMASTER_DEATH)
+                        as i32,
+                    0,
+                    pg_sys::PG_WAIT_EXTENSION,
+                )
+            } as u32;
+            if events & pg_sys::WL_POSTMASTER_DEATH != 0 {
+                shutdown::request_shutdown();
+                return false;
+            }
+            if events & pg_sys::WL_LATCH_SET != 0 {
+                unsafe { pg_sys::ResetLatch(pg_sys::MyLatch) };
+                break;
+            }
+
+            let wait = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) if !remaining.is_zero() => remaining.min(LATCH_POLL_INTERVAL),
+                    _ => break,
+                },
+                None => LATCH_POLL_INTERVAL,
+            };
+            tokio::time::sleep(wait).await;
+        }
+        crate::check_for_interrupts!();
+
+        !BackgroundWorker::sigterm_received()
+    }
+}
+
+/// Resolves once the worker has received a SIGTERM, leaving the flag for
+/// [`BackgroundWorker::sigterm_received()`]
+async fn sigterm() {
+    while !GOT_SIGTERM.load(Ordering::SeqCst) {
+        tokio::time::sleep(LATCH_POLL_INTERVAL).await;
+    }
+}
+
+struct UntilTerminated<F: Future> {
+    future: Pin<Box<F>>,
+    terminated: Pin<Box<dyn Future<Output = ()>>>,
+}
+
+impl<F: Future> Future for UntilTerminated<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        match self.terminated.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,249 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+use std::str::FromStr;
+use std::time::Duration;
+use time::{Date, Month, OffsetDateTime, Time, UtcOffset};
+
+/// When a scheduled job runs
+///
+/// Schedules parse from a standard five field cron expression (`minute hour day-of-month month
+/// day-of-week`, supporting `*`, ranges, lists, steps, and month and day names), one of the
+/// `@hourly`, `@daily`, `@weekly`, `@monthly`, or `@yearly` shorthands, or an interval such as
+/// `@every 30s` (with a unit of `s`, `m`, `h`, or `d`).
+///
+/// ```rust
+/// use pgx::bgworkers::JobSchedule;
+/// use std::time::Duration;
+///
+/// assert_eq!(
+///     "@every 5m".parse::<JobSchedule>(),
+///     Ok(JobSchedule::Every(Duration::from_secs(300)))
+/// );
+/// assert!("*/15 9-17 * * mon-fri".parse::<JobSchedule>().is_ok());
+/// assert!("61 * * * *".parse::<JobSchedule>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobSchedule {
+    /// Repeatedly, with this long between the start of each run
+    Every(Duration),
+    /// At each minute matching a cron expression, in UTC
+    Cron(CronSchedule),
+}
+
+impl JobSchedule {
+    /// When the job should next run, after `after`, or `None` if it never will (such as on
+    /// February 30th)
+    pub fn next_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        match self {
+            JobSchedule::Every(interval) => Some(after + *interval),
+            JobSchedule::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+impl FromStr for JobSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(interval) = s.strip_prefix("@every") {
+            return parse_interval(interval.trim()).map(JobSchedule::Every);
+        }
+
+        let expression = match s {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        expression.parse().map(JobSchedule::Cron)
+    }
+}
+
+/// A parsed cron expression.  See [`JobSchedule`] for the syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl CronSchedule {
+    /// The first minute matching the expression, after `after`
+    pub fn next_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        let after = after.to_offset(UtcOffset::UTC);
+        let mut next = after.replace_time(Time::from_hms(after.hour(), after.minute(), 0).ok()?)
+            + time::Duration::MINUTE;
+
+        // a matching day-of-month and month, if any, comes around within a few years
+        while next.year() <= after.year() + 5 {
+            if !has(self.months, next.month() as u8) {
+                let (year, month) = match next.month() {
+                    Month::December => (next.year() + 1, Month::January),
+                    month => (next.year(), month.next()),
+                };
+                next = Date::from_calendar_date(year, month, 1)
+                    .ok()?
+                    .with_time(Time::MIDNIGHT)
+                    .assume_utc();
+            } else if !self.day_matches(next.date()) {
+                next = next
+                    .date()
+                    .next_day()?
+                    .with_time(Time::MIDNIGHT)
+                    .assume_utc();
+            } else if !has(self.hours, next.hour()) {
+                next = next.replace_time(Time::from_hms(next.hour(), 0, 0).ok()?)
+                    + time::Duration::HOUR;
+            } else if !has(self.minutes, next.minute()) {
+                next += time::Duration::MINUTE;
+            } else {
+                return Some(next);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, date: Date) -> bool {
+        let day_of_month = has(self.days_of_month, date.day());
+        let day_of_week = has(self.days_of_week, date.weekday().number_days_from_sunday());
+
+        // like cron, when both are restricted either one matching is enough
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(format!(
+                "cron expression `{}` should have 5 fields, not {}",
+                s,
+                fields.len()
+            ));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, DAY_NAMES, 0)?;
+        if has(days_of_week, 7) {
+            // both 0 and 7 are Sunday
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(CronSchedule {
+            minutes: parse_field(fields[0], 0, 59, &[], 0)?,
+            hours: parse_field(fields[1], 0, 23, &[], 0)?,
+            days_of_month: parse_field(fields[2], 1, 31, &[], 0)?,
+            months: parse_field(fields[3], 1, 12, MONTH_NAMES, 1)?,
+            days_of_week,
+            // only a bare `*` leaves the day unrestricted: `*/10` is the 1st, 11th, 21st and 31st
+            any_day_of_month: fields[2] == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+}
+
+fn has(mask: u64, value: u8) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// The set of values one field of a cron expression matches, as a bitmask.  `names` are
+/// alternatives for the values from `first_name` on
+fn parse_field(
+    field: &str,
+    min: u8,
+    max: u8,
+    names: &[&str],
+    first_name: u8,
+) -> Result<u64, String> {
+    let value = |value: &str| -> Result<u8, String> {
+        let lowercase = value.to_ascii_lowercase();
+        let value = match names.iter().position(|name| *name == lowercase) {
+            Some(position) => position as u8 + first_name,
+            None => value
+                .parse::<u8>()
+                .map_err(|_| format!("`{}` is not a valid cron value", value))?,
+        };
+        if value < min || value > max {
+            return Err(format!(
+                "cron value {} is outside of {}-{}",
+                value, min, max
+            ));
+        }
+        Ok(value)
+    };
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<usize>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("`{}` is not a valid cron step", step)),
+            },
+            None => (part, None),
+        };
+
+        let (low, high) = if range == "*" {
+            (min, max)
+        } else if let Some((low, high)) = range.split_once('-') {
+            (value(low)?, value(high)?)
+        } else {
+            let start = value(range)?;
+            // `5/10` means from 5 on, every 10
+            (start, if step.is_some() { max } else { start })
+        };
+        if low > high {
+            return Err(format!("cron range `{}` is backwards", range));
+        }
+
+        for value in (low..=high).step_by(step.unwrap_or(1)) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_interval(interval: &str) -> Result<Duration, String> {
+    let unit_at = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(interval.len());
+    let (count, unit) = interval.split_at(unit_at);
+    let count = count
+        .parse::<u64>()
+        .map_err(|_| format!("`{}` is not a valid interval", interval))?;
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("`{}` is not a valid interval unit", unit)),
+    };
+    if count == 0 {
+        return Err("intervals must be longer than zero".to_string());
+    }
+    Ok(Duration::from_secs(count * seconds))
+}
@@ -0,0 +1,636 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Run Rust functions and SQL commands on a schedule, from Background Workers
+//!
+//! A [`PgJobScheduler`] is a BackgroundWorker which starts each job in its own short-lived
+//! BackgroundWorker when it's due, so a job that errors only ends its own run.  Jobs are either
+//! Rust functions added with [`PgJobSchedulerBuilder::add_job()`], or SQL commands kept in the
+//! `pgx_scheduled_jobs` table, which [`job_scheduler_sql!()`](crate::job_scheduler_sql) adds
+//! to the extension along with the `schedule_job(name, schedule, command)` and
+//! `unschedule_job(name)` functions to manage them.
+//!
+//! Requires `pgx`'s `scheduler` feature.
+mod cron;
+
+pub use cron::*;
+
+use crate::bgworkers::{
+    BackgroundWorker, BackgroundWorkerBuilder, BackgroundWorkerStatus, DynamicBackgroundWorker,
+    SignalWakeFlags,
+};
+use crate::{pg_sys, IntoDatum, PgBuiltInOids, PgSqlErrorCode, Spi};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// How often the scheduler looks for changes to `pgx_scheduled_jobs`
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The longest a job's name can be, so it fits in its BackgroundWorker's `extra` data
+pub const MAX_JOB_NAME_LEN: usize = 120;
+
+/// Adds the `pgx_scheduled_jobs` table and the `schedule_job()` and `unschedule_job()` functions
+/// a [`PgJobScheduler`] uses for SQL jobs to the extension.
+///
+/// ```sql
+/// SELECT schedule_job('nightly_vacuum', '0 3 * * *', 'VACUUM ANALYZE');
+/// SELECT unschedule_job('nightly_vacuum');
+/// ```
+///
+/// Jobs can also be disabled with `UPDATE pgx_scheduled_jobs SET enabled = false`, and the
+/// `last_run` and `last_status` columns show how each job last went.
+#[macro_export]
+macro_rules! job_scheduler_sql {
+    () => {
+        $crate::extension_sql!(
+            r#"
+CREATE TABLE pgx_scheduled_jobs (
+    name text PRIMARY KEY CHECK (octet_length(name) <= 120),
+    schedule text NOT NULL,
+    command text NOT NULL,
+    enabled bool NOT NULL DEFAULT true,
+    last_run timestamptz,
+    last_status text
+);
+"#,
+            name = "pgx_scheduled_jobs",
+        );
+
+        /// Schedule `command` to run at `schedule`, replacing any job of the same `name`
+        #[$crate::pg_extern]
+        #[$crate::search_path(@extschema@)]
+        fn schedule_job(name: &str, schedule: &str, command: &str) {
+            $crate::bgworkers::schedule_sql_job(name, schedule, command)
+        }
+
+        /// Stop running the job `name`, returning false if there was no such job
+        #[$crate::pg_extern]
+        #[$crate::search_path(@extschema@)]
+        fn unschedule_job(name: &str) -> bool {
+            $crate::bgworkers::unschedule_sql_job(name)
+        }
+    };
+}
+
+#[doc(hidden)]
+pub fn schedule_sql_job(name: &str, schedule: &str, command: &str) {
+    if name.len() > MAX_JOB_NAME_LEN {
+        crate::ereport(
+            crate::PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_NAME_TOO_LONG,
+            &format!("job names are limited to {} bytes", MAX_JOB_NAME_LEN),
+            file!(),
+            line!(),
+            column!(),
+        );
+    }
+    if let Err(e) = schedule.parse::<JobSchedule>() {
+        crate::ereport(
+            crate::PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            &e,
+            file!(),
+            line!(),
+            column!(),
+        );
+    }
+
+    Spi::connect(|mut client| {
+        client.update(
+            "INSERT INTO pgx_scheduled_jobs (name, schedule, command) VALUES ($1, $2, $3) \
+             ON CONFLICT (name) DO UPDATE \
+             SET schedule = excluded.schedule, command = excluded.command, enabled = true",
+            None,
+            Some(vec![
+                (PgBuiltInOids::TEXTOID.oid(), name.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), schedule.into_datum()),
+                (PgBuiltInOids::TEXTOID.oid(), command.into_datum()),
+            ]),
+        );
+        Ok(Some(()))
+    });
+}
+
+#[doc(hidden)]
+pub fn unschedule_sql_job(name: &str) -> bool {
+    Spi::get_one_with_args::<bool>(
+        "WITH deleted AS (DELETE FROM pgx_scheduled_jobs WHERE name = $1 RETURNING 1) \
+         SELECT count(*) > 0 FROM deleted",
+        vec![(PgBuiltInOids::TEXTOID.oid(), name.into_datum())],
+    )
+    .unwrap_or(false)
+}
+
+struct RustJob {
+    name: String,
+    schedule: JobSchedule,
+    job: fn(),
+}
+
+struct SchedulerConfig {
+    library: String,
+    function: String,
+    job_function: String,
+    database: Option<String>,
+    username: Option<String>,
+    extension: String,
+    jobs: Vec<RustJob>,
+}
+
+/// A Background Worker which runs jobs on a schedule
+///
+/// The scheduler lives in a `static` and is configured with a [`PgJobSchedulerBuilder`] from
+/// `_PG_init()`.  It needs two "main" functions: the scheduler's, which calls
+/// [`PgJobScheduler::run()`], and one for the workers running each job, which calls
+/// [`PgJobScheduler::run_job()`].
+///
+/// Schedules are checked against the wall clock, and a job that's still running when it's next
+/// due skips that run.
+///
+/// > Like any BackgroundWorker, the extension **must** be loaded via `postgresql.conf`'s
+/// > `shared_preload_libraries` configuration setting.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+///
+/// static SCHEDULER: PgJobScheduler = PgJobScheduler::new("example_scheduler");
+///
+/// job_scheduler_sql!();
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     PgJobSchedulerBuilder::new(&SCHEDULER)
+///         .set_library("example")
+///         .set_function("example_scheduler_main")
+///         .set_job_function("example_scheduler_job")
+///         .set_database("postgres")
+///         .add_job("cleanup", "@hourly".parse().unwrap(), || {
+///             Spi::run("DELETE FROM sessions WHERE expires < now()");
+///         })
+///         .load();
+/// }
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn example_scheduler_main(_arg: pg_sys::Datum) {
+///     SCHEDULER.run();
+/// }
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn example_scheduler_job(_arg: pg_sys::Datum) {
+///     SCHEDULER.run_job();
+/// }
+/// ```
+pub struct PgJobScheduler {
+    name: &'static str,
+    config: OnceCell<SchedulerConfig>,
+}
+
+/// The scheduler's view of one job
+struct ScheduledJob {
+    /// The schedule as written in `pgx_scheduled_jobs`, to notice when it changes
+    source: Option<String>,
+    schedule: Option<JobSchedule>,
+    enabled: bool,
+    next_run: Option<OffsetDateTime>,
+    running: Option<DynamicBackgroundWorker>,
+}
+
+impl PgJobScheduler {
+    /// Create an empty scheduler, to be held in a `static`
+    pub const fn new(name: &'static str) -> Self {
+        PgJobScheduler {
+            name,
+            config: OnceCell::new(),
+        }
+    }
+
+    /// What is the name of this scheduler?
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Run the scheduler.  This must be all its "main" function does, and only returns once it
+    /// receives a SIGTERM.  Jobs which are running at the time are left to finish
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn run(&'static self) {
+        BackgroundWorker::attach_signal_handlers(
+            SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM,
+        );
+        let config = self.config();
+        BackgroundWorker::connect_worker_to_spi(
+            config.database.as_deref(),
+            config.username.as_deref(),
+        );
+
+        let now = OffsetDateTime::now_utc();
+        let mut jobs = config
+            .jobs
+            .iter()
+            .map(|job| {
+                (
+                    format!("r:{}", job.name),
+                    ScheduledJob {
+                        source: None,
+                        schedule: Some(job.schedule.clone()),
+                        enabled: true,
+                        next_run: job.schedule.next_after(now),
+                        running: None,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut last_refresh = None;
+        loop {
+            let now = OffsetDateTime::now_utc();
+            if last_refresh.map_or(true, |last| now - last >= REFRESH_INTERVAL) {
+                BackgroundWorker::transaction(AssertUnwindSafe(|| {
+                    self.refresh_sql_jobs(&mut jobs, now)
+                }));
+                last_refresh = Some(now);
+            }
+
+            for (key, job) in jobs.iter_mut() {
+                self.check_job(key, job, now);
+            }
+
+            let until_due = jobs
+                .values()
+                .filter(|job| job.enabled && job.running.is_none())
+                .filter_map(|job| job.next_run)
+                .map(|next_run| (next_run - now).try_into().unwrap_or(Duration::ZERO))
+                .min()
+                .unwrap_or(REFRESH_INTERVAL);
+            if !BackgroundWorker::wait_latch(Some(until_due.min(REFRESH_INTERVAL))) {
+                break;
+            }
+        }
+    }
+
+    /// Run the job this BackgroundWorker was started for.  This must be all the job workers'
+    /// "main" function does.
+    ///
+    /// Rust jobs run inside a transaction, so they can use [`Spi`]
+    pub fn run_job(&'static self) {
+        BackgroundWorker::attach_signal_handlers(
+            SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM,
+        );
+        let config = self.config();
+        BackgroundWorker::connect_worker_to_spi(
+            config.database.as_deref(),
+            config.username.as_deref(),
+        );
+
+        match BackgroundWorker::get_extra().split_once(':') {
+            Some(("r", name)) => {
+                let job = config
+                    .jobs
+                    .iter()
+                    .find(|job| job.name == name)
+                    .unwrap_or_else(|| {
+                        panic!("scheduler \"{}\" has no job \"{}\"", self.name, name)
+                    });
+                BackgroundWorker::transaction(job.job);
+            }
+            Some(("s", name)) => {
+                let name = name.to_string();
+                BackgroundWorker::transaction(|| {
+                    self.set_sql_job_status(&name, "running", true);
+                });
+                BackgroundWorker::transaction(|| {
+                    if let Some(command) = self.sql_job_command(&name) {
+                        Spi::run(&command);
+                    }
+                });
+                BackgroundWorker::transaction(|| {
+                    self.set_sql_job_status(&name, "succeeded", false);
+                });
+            }
+            _ => panic!("not running in a job of scheduler \"{}\"", self.name),
+        }
+    }
+
+    fn check_job(&self, key: &str, job: &mut ScheduledJob, now: OffsetDateTime) {
+        if let Some(handle) = &job.running {
+            if let BackgroundWorkerStatus::Stopped | BackgroundWorkerStatus::PostmasterDied =
+                handle.status()
+            {
+                job.running = None;
+                if let Some(name) = key.strip_prefix("s:") {
+                    let name = name.to_string();
+                    // the job didn't get as far as recording that it succeeded
+                    BackgroundWorker::transaction(move || {
+                        self.mark_sql_job_failed(&name);
+                    });
+                }
+            }
+        }
+
+        let due = match job.next_run {
+            Some(next_run) => job.enabled && next_run <= now,
+            None => false,
+        };
+        if !due {
+            return;
+        }
+
+        job.next_run = job
+            .schedule
+            .as_ref()
+            .and_then(|schedule| schedule.next_after(now));
+        if job.running.is_some() {
+            crate::log!(
+                "scheduler \"{}\" skipped job \"{}\", which is still running",
+                self.name,
+                &key[2..]
+            );
+            return;
+        }
+
+        let config = self.config();
+        let builder = BackgroundWorkerBuilder::new(&truncated(
+            &format!("{} job {}", self.name, &key[2..]),
+            pg_sys::BGW_MAXLEN as usize - 1,
+        ))
+        .set_type(self.name)
+        .set_library(&config.library)
+        .set_function(&config.job_function)
+        .set_extra(key)
+        .set_notify_pid(unsafe { pg_sys::MyProcPid })
+        .enable_spi_access();
+        job.running = builder.load_dynamic();
+        if job.running.is_none() {
+            crate::warning!(
+                "scheduler \"{}\" could not start job \"{}\"; consider increasing max_worker_processes",
+                self.name,
+                &key[2..]
+            );
+        }
+    }
+
+    fn refresh_sql_jobs(&self, jobs: &mut HashMap<String, ScheduledJob>, now: OffsetDateTime) {
+        let table = match self.jobs_table() {
+            Some(table) => table,
+            None => return,
+        };
+
+        let mut seen = Vec::new();
+        Spi::connect(|client| {
+            let query = format!("SELECT name, schedule, enabled FROM {}", table);
+            for row in client.select(&query, None, None) {
+                let name = row.by_ordinal(1)?.value::<String>().expect("name was null");
+                let source = row
+                    .by_ordinal(2)?
+                    .value::<String>()
+                    .expect("schedule was null");
+                let enabled = row
+                    .by_ordinal(3)?
+                    .value::<bool>()
+                    .expect("enabled was null");
+
+                let key = format!("s:{}", name);
+                let job = jobs.entry(key.clone()).or_insert_with(|| ScheduledJob {
+                    source: None,
+                    schedule: None,
+                    enabled,
+                    next_run: None,
+                    running: None,
+                });
+                if job.source.as_ref() != Some(&source) {
+                    job.schedule = match source.parse::<JobSchedule>() {
+                        Ok(schedule) => Some(schedule),
+                        Err(e) => {
+                            crate::warning!(
+                                "scheduler \"{}\" is ignoring job \"{}\": {}",
+                                self.name,
+                                name,
+                                e
+                            );
+                            None
+                        }
+                    };
+                    job.next_run = job
+                        .schedule
+                        .as_ref()
+                        .and_then(|schedule| schedule.next_after(now));
+                    job.source = Some(source);
+                }
+                job.enabled = enabled;
+                seen.push(key);
+            }
+            Ok(Some(()))
+        });
+
+        // forget unscheduled jobs, once they're done running
+        jobs.retain(|key, job| {
+            !key.starts_with("s:") || seen.contains(key) || job.running.is_some()
+        });
+        for (key, job) in jobs.iter_mut() {
+            if key.starts_with("s:") && !seen.contains(key) {
+                job.enabled = false;
+            }
+        }
+    }
+
+    /// The schema qualified `pgx_scheduled_jobs` table, if the extension is installed
+    fn jobs_table(&self) -> Option<String> {
+        Spi::get_one_with_args::<String>(
+            "SELECT quote_ident(n.nspname) || '.pgx_scheduled_jobs' \
+             FROM pg_extension e JOIN pg_namespace n ON n.oid = e.extnamespace \
+             WHERE e.extname = $1",
+            vec![(
+                PgBuiltInOids::TEXTOID.oid(),
+                self.config().extension.as_str().into_datum(),
+            )],
+        )
+    }
+
+    fn sql_job_command(&self, name: &str) -> Option<String> {
+        let table = self.jobs_table()?;
+        Spi::get_one_with_args::<String>(
+            &format!("SELECT command FROM {} WHERE name = $1", table),
+            vec![(PgBuiltInOids::TEXTOID.oid(), name.into_datum())],
+        )
+    }
+
+    fn set_sql_job_status(&self, name: &str, status: &str, started: bool) {
+        if let Some(table) = self.jobs_table() {
+            let query = if started {
+                format!(
+                    "UPDATE {} SET last_status = $2, last_run = now() WHERE name = $1",
+                    table
+                )
+            } else {
+                format!("UPDATE {} SET last_status = $2 WHERE name = $1", table)
+            };
+            Spi::connect(|mut client| {
+                client.update(
+                    &query,
+                    None,
+                    Some(vec![
+                        (PgBuiltInOids::TEXTOID.oid(), name.into_datum()),
+                        (PgBuiltInOids::TEXTOID.oid(), status.into_datum()),
+                    ]),
+                );
+                Ok(Some(()))
+            });
+        }
+    }
+
+    fn mark_sql_job_failed(&self, name: &str) {
+        if let Some(table) = self.jobs_table() {
+            Spi::connect(|mut client| {
+                client.update(
+                    &format!(
+                        "UPDATE {} SET last_status = 'failed' WHERE name = $1 AND last_status = 'running'",
+                        table
+                    ),
+                    None,
+                    Some(vec![(PgBuiltInOids::TEXTOID.oid(), name.into_datum())]),
+                );
+                Ok(Some(()))
+            });
+        }
+    }
+
+    fn config(&self) -> &SchedulerConfig {
+        self.config.get().unwrap_or_else(|| {
+            panic!(
+                "scheduler \"{}\" was not loaded with a PgJobSchedulerBuilder",
+                self.name
+            )
+        })
+    }
+}
+
+/// A builder-style interface for configuring a [`PgJobScheduler`]
+///
+/// This must be used from within your extension's `_PG_init()` function, finishing with the
+/// `.load()` function.  By default the library, the extension, and the scheduler's function are
+/// named after the scheduler, the job function is named `<scheduler>_job`, and jobs run in the
+/// `postgres` database.
+pub struct PgJobSchedulerBuilder {
+    scheduler: &'static PgJobScheduler,
+    config: SchedulerConfig,
+}
+
+impl PgJobSchedulerBuilder {
+    pub fn new(scheduler: &'static PgJobScheduler) -> PgJobSchedulerBuilder {
+        PgJobSchedulerBuilder {
+            scheduler,
+            config: SchedulerConfig {
+                library: scheduler.name.to_string(),
+                function: scheduler.name.to_string(),
+                job_function: format!("{}_job", scheduler.name),
+                database: Some("postgres".to_string()),
+                username: None,
+                extension: scheduler.name.to_string(),
+                jobs: vec![],
+            },
+        }
+    }
+
+    /// What is the library name that contains the scheduler's and its jobs' "main" functions?
+    ///
+    /// This also sets the extension name, if it hasn't been set
+    pub fn set_library(mut self, input: &str) -> Self {
+        if self.config.extension == self.config.library {
+            self.config.extension = input.to_string();
+        }
+        self.config.library = input.to_string();
+        self
+    }
+
+    /// The scheduler's "main" function, which must call [`PgJobScheduler::run()`]
+    pub fn set_function(mut self, input: &str) -> Self {
+        self.config.function = input.to_string();
+        self
+    }
+
+    /// The job workers' "main" function, which must call [`PgJobScheduler::run_job()`]
+    pub fn set_job_function(mut self, input: &str) -> Self {
+        self.config.job_function = input.to_string();
+        self
+    }
+
+    /// The database the scheduler reads `pgx_scheduled_jobs` from, and runs jobs in
+    pub fn set_database(mut self, input: &str) -> Self {
+        self.config.database = Some(input.to_string());
+        self
+    }
+
+    /// The user jobs run as, instead of the bootstrap superuser
+    pub fn set_username(mut self, input: &str) -> Self {
+        self.config.username = Some(input.to_string());
+        self
+    }
+
+    /// The extension whose schema has the `pgx_scheduled_jobs` table
+    pub fn set_extension(mut self, input: &str) -> Self {
+        self.config.extension = input.to_string();
+        self
+    }
+
+    /// Run `job` at `schedule`
+    pub fn add_job(mut self, name: &str, schedule: JobSchedule, job: fn()) -> Self {
+        self.config.jobs.push(RustJob {
+            name: name.to_string(),
+            schedule,
+            job,
+        });
+        self
+    }
+
+    /// Once properly configured, call `load()` to register the scheduler with Postgres.
+    pub fn load(self) {
+        for (i, job) in self.config.jobs.iter().enumerate() {
+            if job.name.len() > MAX_JOB_NAME_LEN {
+                panic!(
+                    "job name \"{}\" is longer than {} bytes",
+                    job.name, MAX_JOB_NAME_LEN
+                );
+            }
+            if self.config.jobs[..i]
+                .iter()
+                .any(|other| other.name == job.name)
+            {
+                panic!("scheduler has more than one job named \"{}\"", job.name);
+            }
+        }
+
+        BackgroundWorkerBuilder::new(&format!("{} scheduler", self.scheduler.name))
+            .set_type(self.scheduler.name)
+            .set_library(&self.config.library)
+            .set_function(&self.config.function)
+            .enable_spi_access()
+            .set_restart_time(Some(REFRESH_INTERVAL))
+            .load();
+
+        if self.scheduler.config.set(self.config).is_err() {
+            panic!("scheduler \"{}\" was loaded twice", self.scheduler.name);
+        }
+    }
+}
+
+/// `s` cut down to at most `len` bytes, on a character boundary
+fn truncated(s: &str, len: usize) -> String {
+    let mut end = s.len().min(len);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
+}
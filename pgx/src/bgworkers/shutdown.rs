@@ -0,0 +1,147 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::pg_sys;
+use once_cell::sync::OnceCell;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often [`WorkerShutdownHandle::wait_timeout()`] checks whether the postmaster is still alive
+const POSTMASTER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static SHUTDOWN: OnceCell<Arc<ShutdownState>> = OnceCell::new();
+
+struct ShutdownState {
+    requested: Mutex<bool>,
+    condvar: Condvar,
+    postmaster_pid: libc::pid_t,
+}
+
+/// Tells threads a background worker spawned that the worker is exiting, because it received a
+/// SIGTERM or the postmaster died, so they can finish up rather than be orphaned.
+///
+/// Postgres isn't thread-safe, so the threads must never call into Postgres (or pgx), and the
+/// handle doesn't either: it's `Send` and `Sync`, and only uses the standard library.  The worker
+/// itself trips the handle when [`BackgroundWorker::sigterm_received()`],
+/// [`BackgroundWorker::wait_latch()`], or [`BackgroundWorker::wait_latch_or_signal()`] sees that
+/// it should exit, and threads also notice the postmaster dying on their own, even while the
+/// worker is busy.
+///
+/// The worker should join its threads before returning, since they're killed when it exits:
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+/// use std::time::Duration;
+///
+/// #[pg_guard]
+/// pub extern "C" fn background_worker_main(_arg: pg_sys::Datum) {
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///
+///     let shutdown = BackgroundWorker::shutdown_handle();
+///     let poller = std::thread::spawn(move || {
+///         while !shutdown.wait_timeout(Duration::from_secs(1)) {
+///             // talk to some external service, without touching Postgres
+///         }
+///     });
+///
+///     while BackgroundWorker::wait_latch(Some(Duration::from_secs(10))) {}
+///     poller.join().expect("poller thread panicked");
+/// }
+/// ```
+///
+/// [`BackgroundWorker::sigterm_received()`]: crate::bgworkers::BackgroundWorker::sigterm_received
+/// [`BackgroundWorker::wait_latch()`]: crate::bgworkers::BackgroundWorker::wait_latch
+/// [`BackgroundWorker::wait_latch_or_signal()`]: crate::bgworkers::BackgroundWorker::wait_latch_or_signal
+#[derive(Clone)]
+pub struct WorkerShutdownHandle {
+    state: Arc<ShutdownState>,
+}
+
+impl WorkerShutdownHandle {
+    /// The worker's handle, which must first be asked for from the worker's own thread
+    pub(crate) fn get() -> Self {
+        let state = SHUTDOWN.get_or_init(|| {
+            Arc::new(ShutdownState {
+                requested: Mutex::new(false),
+                condvar: Condvar::new(),
+                postmaster_pid: unsafe { std::ptr::addr_of!(pg_sys::PostmasterPid).read() },
+            })
+        });
+        WorkerShutdownHandle {
+            state: state.clone(),
+        }
+    }
+
+    /// Should threads exit, because the worker is exiting or the postmaster died?
+    pub fn is_shutdown(&self) -> bool {
+        *self.state.requested.lock().unwrap() || self.postmaster_died()
+    }
+
+    /// Sleep for up to `timeout`, waking as soon as the worker is shutting down, and return
+    /// [`WorkerShutdownHandle::is_shutdown()`]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut requested = self.state.requested.lock().unwrap();
+        loop {
+            if *requested || self.postmaster_died() {
+                return true;
+            }
+            let remaining = match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => remaining,
+                _ => return false,
+            };
+            requested = self
+                .state
+                .condvar
+                .wait_timeout(requested, remaining.min(POSTMASTER_POLL_INTERVAL))
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Sleep until the worker is shutting down
+    pub fn wait(&self) {
+        while !self.wait_timeout(POSTMASTER_POLL_INTERVAL) {}
+    }
+
+    /// Tell threads to exit, such as when the worker stops for a reason of its own.  This can't be
+    /// undone
+    pub fn shutdown(&self) {
+        *self.state.requested.lock().unwrap() = true;
+        self.state.condvar.notify_all();
+    }
+
+    /// Once the postmaster dies, the worker is reparented, so this is safe to check from any thread
+    fn postmaster_died(&self) -> bool {
+        self.state.postmaster_pid != 0 && unsafe { libc::getppid() } != self.state.postmaster_pid
+    }
+}
+
+/// Has the handle been tripped?  Unlike [`BackgroundWorker::sigterm_received()`], this doesn't
+/// consume anything the worker's own loop looks for
+///
+/// [`BackgroundWorker::sigterm_received()`]: crate::bgworkers::BackgroundWorker::sigterm_received
+#[allow(clippy::unnecessary_map_or)]
+#[cfg(all(feature = "http", unix))]
+pub(crate) fn shutdown_requested() -> bool {
+    SHUTDOWN
+        .get()
+        .map_or(false, |state| *state.requested.lock().unwrap())
+}
+
+/// Trip the handle, if any thread asked for it
+pub(crate) fn request_shutdown() {
+    if let Some(state) = SHUTDOWN.get() {
+        WorkerShutdownHandle {
+            state: state.clone(),
+        }
+        .shutdown();
+    }
+}
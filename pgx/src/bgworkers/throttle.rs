@@ -0,0 +1,166 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use super::{shutdown, wait_latch, WLflags, GOT_SIGTERM};
+use crate::{pg_sys, GucContext, GucRegistry, GucSetting};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Cost-based delays for a worker's maintenance, the way `VACUUM` delays itself, so a worker
+/// rebuilding or compacting relations doesn't starve the queries running alongside it.
+///
+/// The worker charges the throttle for the work it does, such as the pages it reads and dirties,
+/// and once the cost adds up to `<name>.cost_limit`, the throttle sleeps for `<name>.cost_delay`
+/// milliseconds, or longer if the worker went far past the limit, up to four times the delay, as
+/// `VACUUM` does.  A delay of `0` turns the throttle off.
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+///
+/// static THROTTLE: CostThrottle = CostThrottle::new("myext_compaction", 200, 2.0);
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     THROTTLE.define_gucs();
+/// }
+///
+/// #[pg_guard]
+/// pub extern "C" fn compaction_worker_main(_arg: pg_sys::Datum) {
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///     BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+///
+///     loop {
+///         // compact a batch, reading 8 pages and dirtying 2 of them
+///         if !THROTTLE.charge_pages(0, 8, 2) {
+///             break;
+///         }
+///     }
+/// }
+/// ```
+pub struct CostThrottle {
+    name: &'static str,
+    cost_limit: GucSetting<i32>,
+    cost_delay: GucSetting<f64>,
+    balance: AtomicI64,
+}
+
+impl CostThrottle {
+    /// A throttle whose settings are `<name>.cost_limit` and `<name>.cost_delay`, in milliseconds,
+    /// which are `cost_limit` and `cost_delay` until they're set
+    pub const fn new(name: &'static str, cost_limit: i32, cost_delay: f64) -> Self {
+        CostThrottle {
+            name,
+            cost_limit: GucSetting::new(cost_limit),
+            cost_delay: GucSetting::new(cost_delay),
+            balance: AtomicI64::new(0),
+        }
+    }
+
+    /// The name given to [`CostThrottle::new()`]
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Define the `<name>.cost_limit` and `<name>.cost_delay` settings, which are set in
+    /// `postgresql.conf`, and take effect once workers reload it after a SIGHUP.  Call it from
+    /// `_PG_init()`, or the throttle always uses the values given to [`CostThrottle::new()`]
+    pub fn define_gucs(&'static self) {
+        GucRegistry::define_int_guc(
+            &format!("{}.cost_limit", self.name),
+            "The cost of the work after which the worker sleeps",
+            "The work is charged with the same costs as vacuum_cost_page_hit, vacuum_cost_page_miss, and vacuum_cost_page_dirty.",
+            &self.cost_limit,
+            1,
+            10000,
+            GucContext::Sighup,
+        );
+        GucRegistry::define_float_guc(
+            &format!("{}.cost_delay", self.name),
+            "How many milliseconds the worker sleeps for when the cost limit is exceeded",
+            "0 turns off the cost-based delay.",
+            &self.cost_delay,
+            0.0,
+            100.0,
+            GucContext::Sighup,
+        );
+    }
+
+    pub fn cost_limit(&self) -> i32 {
+        self.cost_limit.get()
+    }
+
+    /// How long the throttle sleeps for once the cost limit is reached, or `None` if it's off
+    pub fn cost_delay(&self) -> Option<Duration> {
+        let delay = self.cost_delay.get();
+        (delay > 0.0).then(|| Duration::from_secs_f64(delay / 1000.0))
+    }
+
+    /// The cost charged since the throttle last slept
+    pub fn balance(&self) -> i64 {
+        self.balance.load(Ordering::Relaxed)
+    }
+
+    /// Forget the cost charged so far, such as after the worker has been idle
+    pub fn reset(&self) {
+        self.balance.store(0, Ordering::Relaxed);
+    }
+
+    /// Charge `cost` for work that's been done, and sleep if the cost limit is reached.  Returns
+    /// `false` if the worker was told to exit, with a SIGTERM, or the postmaster died, while it
+    /// slept, like [`BackgroundWorker::wait_latch()`](crate::bgworkers::BackgroundWorker::wait_latch),
+    /// but without consuming the SIGTERM
+    pub fn charge(&self, cost: i64) -> bool {
+        let delay = match self.cost_delay() {
+            Some(delay) => delay,
+            None => return true,
+        };
+        let balance = self.balance.fetch_add(cost, Ordering::Relaxed) + cost;
+        let limit = self.cost_limit().max(1) as i64;
+        if balance < limit {
+            return true;
+        }
+
+        let delay = delay.mul_f64(balance as f64 / limit as f64).min(delay * 4);
+        self.reset();
+        self.sleep(delay)
+    }
+
+    /// Charge for the pages the work used, with Postgres' `vacuum_cost_page_hit`,
+    /// `vacuum_cost_page_miss`, and `vacuum_cost_page_dirty` costs for a page found in shared
+    /// buffers, one read from disk, and one dirtied.  See [`CostThrottle::charge()`]
+    pub fn charge_pages(&self, hits: u32, misses: u32, dirtied: u32) -> bool {
+        let cost = unsafe {
+            hits as i64 * pg_sys::VacuumCostPageHit as i64
+                + misses as i64 * pg_sys::VacuumCostPageMiss as i64
+                + dirtied as i64 * pg_sys::VacuumCostPageDirty as i64
+        };
+        self.charge(cost)
+    }
+
+    /// Sleep for all of `delay`, even if the latch is set for something else, unless the worker
+    /// should exit
+    fn sleep(&self, delay: Duration) -> bool {
+        let deadline = Instant::now() + delay;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            if remaining.is_zero() || GOT_SIGTERM.load(Ordering::SeqCst) {
+                break;
+            }
+            let events = wait_latch(
+                remaining.as_millis().max(1) as i64,
+                WLflags::WL_LATCH_SET | WLflags::WL_TIMEOUT | WLflags::WL_POSTMASTER_DEATH,
+            );
+            if WLflags::from_bits_truncate(events).contains(WLflags::WL_POSTMASTER_DEATH) {
+                shutdown::request_shutdown();
+                return false;
+            }
+        }
+        !GOT_SIGTERM.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,138 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Temporary files managed by Postgres, for spilling state that doesn't fit in memory
+//!
+//! Needs the `storage` feature, as it calls into `storage/buffile.h` directly.
+use crate::{pg_sys, PgMemoryContexts};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+
+/// The size of each of the segment files a `BufFile` is made of, from `storage/file/buffile.c`
+const MAX_PHYSICAL_FILESIZE: u64 = 0x4000_0000;
+
+/// A temporary file, created by Postgres in one of the `temp_tablespaces` and counted against
+/// `temp_file_limit`, that's read and written through a buffer, like `BufFileCreateTemp()` does.
+///
+/// The file is deleted when it's dropped, or at the end of the transaction if that's first, so it
+/// must not outlive the transaction it was created in.
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::io::{Read, Seek, SeekFrom, Write};
+///
+/// let mut file = PgTempFile::new();
+/// file.write_all(b"more than work_mem").unwrap();
+/// file.seek(SeekFrom::Start(0)).unwrap();
+///
+/// let mut contents = String::new();
+/// file.read_to_string(&mut contents).unwrap();
+/// ```
+pub struct PgTempFile {
+    file: *mut pg_sys::BufFile,
+    // `BufFileSize()` is only for shared files, and doesn't count what's still in the buffer
+    len: u64,
+}
+
+impl PgTempFile {
+    pub fn new() -> Self {
+        // allocated for the whole transaction, as the file lives as long as that
+        let file = PgMemoryContexts::CurTransactionContext
+            .switch_to(|_| unsafe { pg_sys::BufFileCreateTemp(false) });
+        PgTempFile { file, len: 0 }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::BufFile {
+        self.file
+    }
+
+    /// The size of the file, in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn position(&self) -> u64 {
+        let mut fileno = 0;
+        let mut offset = 0;
+        unsafe { pg_sys::BufFileTell(self.file, &mut fileno, &mut offset) };
+        fileno as u64 * MAX_PHYSICAL_FILESIZE + offset as u64
+    }
+}
+
+impl Default for PgTempFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Read for PgTempFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Ok(unsafe { pg_sys::BufFileRead(self.file, buf.as_mut_ptr() as _, buf.len()) })
+    }
+}
+
+impl Write for PgTempFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let written = buf_file_write(self.file, buf)?;
+        self.len = self.len.max(self.position());
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        // the buffer is written out as needed, and the file is never read by anything else
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+fn buf_file_write(file: *mut pg_sys::BufFile, buf: &[u8]) -> Result<usize, Error> {
+    match unsafe { pg_sys::BufFileWrite(file, buf.as_ptr() as _, buf.len()) } {
+        written if written == buf.len() => Ok(written),
+        _ => Err(Error::new(
+            ErrorKind::Other,
+            "could not write to temporary file",
+        )),
+    }
+}
+
+// failing to write raises an ERROR
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+fn buf_file_write(file: *mut pg_sys::BufFile, buf: &[u8]) -> Result<usize, Error> {
+    unsafe { pg_sys::BufFileWrite(file, buf.as_ptr() as _, buf.len()) };
+    Ok(buf.len())
+}
+
+impl Seek for PgTempFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, pg_sys::SEEK_SET),
+            SeekFrom::Current(offset) => (offset, pg_sys::SEEK_CUR),
+            SeekFrom::End(offset) => (self.len as i64 + offset, pg_sys::SEEK_SET),
+        };
+
+        let result =
+            unsafe { pg_sys::BufFileSeek(self.file, 0, offset as pg_sys::off_t, whence as i32) };
+        if result != 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek position in temporary file",
+            ));
+        }
+        Ok(self.position())
+    }
+}
+
+impl Drop for PgTempFile {
+    fn drop(&mut self) {
+        unsafe { pg_sys::BufFileClose(self.file) }
+    }
+}
@@ -7,7 +7,9 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-//! Provides safe wrappers around Postgres' "Transaction" and "Sub Transaction" hook system
+//! Provides safe wrappers around Postgres' "Transaction" and "Sub Transaction" hook system, with
+//! closures to undo Rust state when a savepoint rolls back, its relcache and syscache
+//! invalidation callbacks, and the callbacks it calls as a process exits
 
 use crate::pg_sys;
 use std::cell::RefCell;
@@ -376,3 +378,489 @@ where
 
     SubXactCallbackReceipt(wrapped_func)
 }
+
+/// Registering a rollback callback returns a `RollbackCallbackReceipt` that can be used to
+/// unregister the callback if it becomes unnecessary before the transaction ends
+pub struct RollbackCallbackReceipt(Rc<RefCell<Option<RollbackCallbackWrapper>>>);
+
+impl RollbackCallbackReceipt {
+    /// Consumes this `RollbackCallbackReceipt` and unregisters the registered callback it
+    /// represents
+    pub fn unregister_callback(self) {
+        self.0.replace(None);
+    }
+}
+
+struct RollbackCallbackWrapper(
+    Box<dyn FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static>,
+);
+
+/// Shorthand for the closures waiting on a rollback, each with the subtransaction whose rollback
+/// it's waiting on, in the order they were registered
+type RollbackCallbacks = Vec<(
+    pg_sys::SubTransactionId,
+    Rc<RefCell<Option<RollbackCallbackWrapper>>>,
+)>;
+
+/// Register a closure to be called if the current subtransaction rolls back, to undo what the
+/// extension has done in Rust alongside what the subtransaction did in SQL, such as adding to a
+/// per-backend cache or counter.
+///
+/// The current subtransaction is the one of the innermost savepoint, or of the `BEGIN ...
+/// EXCEPTION` block of a PL/pgSQL function, or the transaction itself outside of either.  When
+/// it's released, the closure is passed on to the subtransaction around it, so the closure is
+/// called if any subtransaction its changes became part of rolls back, up to and including the
+/// transaction.  Once the transaction commits the closure is dropped without being called, as it
+/// is at `PREPARE TRANSACTION`.
+///
+/// The closures of a subtransaction are called in the reverse of the order they were registered
+/// in, so each undoes its change to the state the one registered before it left.
+///
+/// ## Examples
+///
+/// Keep a per-backend count of the rows a function has inserted, which `ROLLBACK TO SAVEPOINT`
+/// takes back along with the rows:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::cell::Cell;
+///
+/// thread_local! {
+///     static INSERTED: Cell<i64> = Cell::new(0);
+/// }
+///
+/// fn insert_row() {
+///     Spi::run("INSERT INTO t VALUES (1)");
+///     INSERTED.with(|inserted| inserted.set(inserted.get() + 1));
+///     register_rollback_callback(|| INSERTED.with(|inserted| inserted.set(inserted.get() - 1)));
+/// }
+/// ```
+///
+/// ## Panics
+///
+/// If it's called outside of a transaction.
+///
+/// ## Safety
+///
+/// The closures are called while Postgres is rolling back, so they mustn't use SPI or the
+/// catalogs.  A Rust `panic!()` in one is reported as a `WARNING`, and the rest are still called.
+pub fn register_rollback_callback<F>(f: F) -> RollbackCallbackReceipt
+where
+    F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    thread_local! {
+        // the closures of the current transaction.  It starts as None, until our callbacks are
+        // registered with Postgres, which they then are for the life of the backend
+        static ROLLBACK_HOOKS: RefCell<Option<RollbackCallbacks>> = const { RefCell::new(None) };
+    }
+
+    // the subtransaction id of the transaction itself, Postgres' `TopSubTransactionId`, which
+    // every other one of the transaction is larger than
+    const TOP_SUB_TRANSACTION_ID: pg_sys::SubTransactionId = 1;
+
+    // removes the closures of `subid` and of the subtransactions within it, which, as they
+    // started after it, have larger ids, and calls them, newest first
+    fn roll_back(subid: pg_sys::SubTransactionId) {
+        let hooks = ROLLBACK_HOOKS.with(|hooks| {
+            let mut hooks = hooks.borrow_mut();
+            let hooks = hooks.as_mut().expect("ROLLBACK_HOOKS was None");
+            let (rolled_back, kept) = hooks.drain(..).partition(|(id, _)| *id >= subid);
+            *hooks = kept;
+            rolled_back
+        });
+
+        for (_, hook) in hooks.into_iter().rev() {
+            // effectively 'take' the hook from the internal RefCell
+            if let Some(hook) = hook.replace(None) {
+                if let Err(e) = std::panic::catch_unwind(move || (hook.0)()) {
+                    crate::warning!("a rollback callback panicked: {}", panic_message(e));
+                }
+            }
+        }
+    }
+
+    unsafe extern "C" fn subxact_callback(
+        event: pg_sys::SubXactEvent,
+        my_subid: pg_sys::SubTransactionId,
+        parent_subid: pg_sys::SubTransactionId,
+        _arg: *mut ::std::os::raw::c_void,
+    ) {
+        match PgSubXactCallbackEvent::translate_pg_event(event) {
+            PgSubXactCallbackEvent::AbortSub => roll_back(my_subid),
+            PgSubXactCallbackEvent::CommitSub => ROLLBACK_HOOKS.with(|hooks| {
+                let mut hooks = hooks.borrow_mut();
+                for (id, _) in hooks.as_mut().expect("ROLLBACK_HOOKS was None").iter_mut() {
+                    if *id >= my_subid {
+                        *id = parent_subid;
+                    }
+                }
+            }),
+            _ => {}
+        }
+    }
+
+    unsafe extern "C" fn xact_callback(
+        event: pg_sys::XactEvent,
+        _arg: *mut ::std::os::raw::c_void,
+    ) {
+        match PgXactCallbackEvent::translate_pg_event(event) {
+            PgXactCallbackEvent::Abort => roll_back(TOP_SUB_TRANSACTION_ID),
+            PgXactCallbackEvent::Commit | PgXactCallbackEvent::Prepare => {
+                ROLLBACK_HOOKS.with(|hooks| {
+                    hooks
+                        .borrow_mut()
+                        .as_mut()
+                        .expect("ROLLBACK_HOOKS was None")
+                        .clear()
+                })
+            }
+            _ => {}
+        }
+    }
+
+    let subid = unsafe {
+        if !pg_sys::IsTransactionState() {
+            panic!("register_rollback_callback() called outside of a transaction");
+        }
+        pg_sys::GetCurrentSubTransactionId()
+    };
+
+    let wrapped_func = Rc::new(RefCell::new(Some(RollbackCallbackWrapper(Box::new(f)))));
+    ROLLBACK_HOOKS.with(|hooks| {
+        hooks
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                unsafe {
+                    pg_sys::RegisterSubXactCallback(Some(subxact_callback), std::ptr::null_mut());
+                    pg_sys::RegisterXactCallback(Some(xact_callback), std::ptr::null_mut());
+                }
+                Vec::new()
+            })
+            .push((subid, Rc::clone(&wrapped_func)));
+    });
+
+    RollbackCallbackReceipt(wrapped_func)
+}
+
+/// The message of a panic caught from a callback
+fn panic_message(e: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(panic) = e.downcast_ref::<pg_sys::PgxPanic>() {
+        panic.message.to_string()
+    } else if let Some(message) = e.downcast_ref::<String>() {
+        message.clone()
+    } else if let Some(message) = e.downcast_ref::<&str>() {
+        message.to_string()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// When, as a backend or background worker exits, a closure registered with
+/// [`register_exit_callback()`] is called.
+///
+/// A process exits through `proc_exit()`, whether it's done, or it's been told to stop, or it
+/// raised a `FATAL`, and Postgres first calls its `before_shmem_exit()` callbacks, then detaches
+/// it from shared memory, and then calls its `on_proc_exit()` callbacks.  Each kind is called in
+/// the reverse of the order they were registered in.
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum PgExitCallbackEvent {
+    /// Called with `before_shmem_exit()`, while the process is still attached to shared memory,
+    /// so it can give back what it holds there, such as a slot in a shared array.  Postgres' own
+    /// callbacks, which abort the transaction the process was in, if any, and release its locks,
+    /// are registered as it starts, so they're called after these, and these mustn't use SPI or
+    /// the catalogs
+    BeforeShmemExit,
+
+    /// Called with `on_proc_exit()`, once the process has detached from shared memory, just
+    /// before it exits, so only what's the process' own can be used, such as to flush a file or
+    /// close a socket
+    ProcExit,
+}
+
+impl PgExitCallbackEvent {
+    fn into_arg(self) -> pg_sys::Datum {
+        match self {
+            PgExitCallbackEvent::BeforeShmemExit => 0,
+            PgExitCallbackEvent::ProcExit => 1,
+        }
+    }
+
+    fn from_arg(arg: pg_sys::Datum) -> Self {
+        match arg {
+            0 => PgExitCallbackEvent::BeforeShmemExit,
+            1 => PgExitCallbackEvent::ProcExit,
+            unknown => panic!("Unrecognized exit callback: {}", unknown),
+        }
+    }
+}
+
+/// Registering an exit callback returns an `ExitCallbackReceipt` that can be used to unregister
+/// the callback if it becomes unnecessary before the process exits
+pub struct ExitCallbackReceipt(Rc<RefCell<Option<ExitCallbackWrapper>>>);
+
+impl ExitCallbackReceipt {
+    /// Consumes this `ExitCallbackReceipt` and unregisters the registered callback it represents
+    pub fn unregister_callback(self) {
+        self.0.replace(None);
+    }
+}
+
+struct ExitCallbackWrapper(
+    Box<dyn FnOnce(i32) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static>,
+);
+
+type ExitCallbackMap = HashMap<PgExitCallbackEvent, Vec<Rc<RefCell<Option<ExitCallbackWrapper>>>>>;
+
+/// Register a closure to be called with the process' exit code when this backend or background
+/// worker exits, before or after it detaches from shared memory, according to `which_event`.
+///
+/// The closures for each event are called in the reverse of the order they were registered in,
+/// as Postgres calls its own, and all of them together, at the point the first was registered.
+/// A closure registered in one process isn't called when another exits, including a process
+/// forked from it, so register it from the backend or worker itself, not from the `_PG_init()` of
+/// a library in `shared_preload_libraries`, which runs in the postmaster.  Postgres can only
+/// register 20 callbacks of each kind for a process, but these all share one.
+///
+/// ## Examples
+///
+/// Flush what a worker has buffered when it's told to stop:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::io::Write;
+///
+/// let mut log = std::io::BufWriter::new(std::fs::File::create("/tmp/worker.log").unwrap());
+/// writeln!(log, "started").unwrap();
+/// register_exit_callback(PgExitCallbackEvent::ProcExit, move |code| {
+///     writeln!(log, "exiting with {}", code).ok();
+///     log.flush().ok();
+/// });
+/// ```
+///
+/// ## Safety
+///
+/// A Rust `panic!()` in the closure is reported as a `WARNING`, and the process goes on exiting.
+/// A Postgres `ereport(ERROR)` becomes a `FATAL` while the process is exiting, and the process
+/// exits without calling the rest of the closures for the event.
+pub fn register_exit_callback<F>(which_event: PgExitCallbackEvent, f: F) -> ExitCallbackReceipt
+where
+    F: FnOnce(i32) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    thread_local! {
+        // the callbacks of the process whose pid they're with.  A process forked from the one
+        // that registered them inherits them, but not their registration with Postgres, and
+        // they're the other process' anyway
+        static EXIT_HOOKS: RefCell<Option<(i32, ExitCallbackMap)>> = const { RefCell::new(None) };
+    }
+
+    unsafe extern "C" fn callback(code: ::std::os::raw::c_int, arg: pg_sys::Datum) {
+        let which_event = PgExitCallbackEvent::from_arg(arg);
+        let hooks = EXIT_HOOKS.with(|hooks| {
+            hooks
+                .borrow_mut()
+                .as_mut()
+                .and_then(|(_, hooks)| hooks.remove(&which_event))
+        });
+
+        if let Some(hooks) = hooks {
+            for hook in hooks.into_iter().rev() {
+                // effectively 'take' the hook from the internal RefCell
+                if let Some(hook) = hook.replace(None) {
+                    if let Err(e) = std::panic::catch_unwind(move || (hook.0)(code)) {
+                        crate::warning!(
+                            "a {:?} callback panicked: {}",
+                            which_event,
+                            panic_message(e)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let wrapped_func = Rc::new(RefCell::new(Some(ExitCallbackWrapper(Box::new(f)))));
+    EXIT_HOOKS.with(|hooks| {
+        let mut hooks = hooks.borrow_mut();
+        let pid = unsafe { pg_sys::MyProcPid };
+        if !matches!(*hooks, Some((registered_by, _)) if registered_by == pid) {
+            hooks.replace((pid, HashMap::new()));
+        }
+        let (_, hooks) = hooks.as_mut().expect("EXIT_HOOKS was None"); // this should never happen
+
+        // our one callback for the event is registered along with its first closure
+        let entry = hooks.entry(which_event).or_insert_with(|| {
+            unsafe {
+                match which_event {
+                    PgExitCallbackEvent::BeforeShmemExit => {
+                        pg_sys::before_shmem_exit(Some(callback), which_event.into_arg())
+                    }
+                    PgExitCallbackEvent::ProcExit => {
+                        pg_sys::on_proc_exit(Some(callback), which_event.into_arg())
+                    }
+                }
+            }
+            Vec::new()
+        });
+        entry.push(Rc::clone(&wrapped_func));
+    });
+
+    ExitCallbackReceipt(wrapped_func)
+}
+
+/// Register a closure to be called whenever this backend processes a relcache invalidation, with
+/// the oid of the invalidated relation, or `None` when the entire relcache was invalidated.
+///
+/// Such callbacks are how an extension learns that data it caches about a relation has gone
+/// stale, whichever backend changed it.  Postgres offers no way to unregister them, so the closure
+/// stays registered for the life of the backend.  Register it from `_PG_init()` so every backend
+/// has it.
+///
+/// ## Examples
+///
+/// Keep a per-backend cache of relation names correct across `ALTER TABLE ... RENAME`:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::cell::RefCell;
+/// use std::collections::HashMap;
+///
+/// thread_local! {
+///     static RELATION_NAMES: RefCell<HashMap<pg_sys::Oid, String>> = RefCell::new(HashMap::new());
+/// }
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     register_relcache_callback(|relid| {
+///         RELATION_NAMES.with(|names| match relid {
+///             Some(relid) => {
+///                 names.borrow_mut().remove(&relid);
+///             }
+///             None => names.borrow_mut().clear(),
+///         })
+///     });
+/// }
+/// ```
+///
+/// ## Safety
+///
+/// Postgres raises an ERROR once more than a handful of relcache callbacks (10, as of Postgres
+/// 14) have been registered across all loaded extensions, so don't register one per cache entry
+pub fn register_relcache_callback<F>(f: F)
+where
+    F: Fn(Option<pg_sys::Oid>) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    unsafe extern "C" fn callback<F>(arg: pg_sys::Datum, relid: pg_sys::Oid)
+    where
+        F: Fn(Option<pg_sys::Oid>) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+    {
+        let f = &*(arg as *const F);
+        let relid = if relid == pg_sys::InvalidOid {
+            None
+        } else {
+            Some(relid)
+        };
+        crate::guard::guard(|| f(relid));
+    }
+
+    // the closure lives for as long as the backend, because Postgres can't forget about it
+    let f: &'static F = Box::leak(Box::new(f));
+    unsafe {
+        pg_sys::CacheRegisterRelcacheCallback(Some(callback::<F>), f as *const F as pg_sys::Datum);
+    }
+}
+
+/// Register a closure to be called whenever this backend processes an invalidation of the
+/// syscache `cache_id`, with the hash value of the invalidated entry's keys, or `None` when the
+/// entire cache was invalidated.
+///
+/// Compare the hash value against the [`syscache_hash_value()`] of the keys whose entries you
+/// depend on.  As with [`register_relcache_callback()`], the closure stays registered for the life
+/// of the backend, so register it from `_PG_init()`.
+///
+/// ## Examples
+///
+/// Keep a per-backend cache of type names, keyed by type oid, correct across `ALTER TYPE`:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::cell::RefCell;
+/// use std::collections::HashMap;
+///
+/// thread_local! {
+///     // each type's name, and the `pg_sys::SysCacheIdentifier_TYPEOID` hash value of its oid
+///     static TYPE_NAMES: RefCell<HashMap<pg_sys::Oid, (String, u32)>> = RefCell::new(HashMap::new());
+/// }
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     register_syscache_callback(pg_sys::SysCacheIdentifier_TYPEOID, |hashvalue| {
+///         TYPE_NAMES.with(|names| match hashvalue {
+///             Some(hashvalue) => names.borrow_mut().retain(|_, (_, hash)| *hash != hashvalue),
+///             None => names.borrow_mut().clear(),
+///         })
+///     });
+/// }
+///
+/// fn remember_type_name(typoid: pg_sys::Oid, name: String) {
+///     let hash = syscache_hash_value(pg_sys::SysCacheIdentifier_TYPEOID, &[typoid.into_datum().unwrap()]);
+///     TYPE_NAMES.with(|names| names.borrow_mut().insert(typoid, (name, hash)));
+/// }
+/// ```
+///
+/// ## Safety
+///
+/// Postgres raises an ERROR once too many syscache callbacks (64, as of Postgres 14) have been
+/// registered across all loaded extensions
+pub fn register_syscache_callback<F>(cache_id: pg_sys::SysCacheIdentifier, f: F)
+where
+    F: Fn(Option<u32>) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+{
+    unsafe extern "C" fn callback<F>(
+        arg: pg_sys::Datum,
+        _cache_id: ::std::os::raw::c_int,
+        hashvalue: u32,
+    ) where
+        F: Fn(Option<u32>) + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+    {
+        let f = &*(arg as *const F);
+        let hashvalue = if hashvalue == 0 {
+            None
+        } else {
+            Some(hashvalue)
+        };
+        crate::guard::guard(|| f(hashvalue));
+    }
+
+    // the closure lives for as long as the backend, because Postgres can't forget about it
+    let f: &'static F = Box::leak(Box::new(f));
+    unsafe {
+        pg_sys::CacheRegisterSyscacheCallback(
+            cache_id as ::std::os::raw::c_int,
+            Some(callback::<F>),
+            f as *const F as pg_sys::Datum,
+        );
+    }
+}
+
+/// The hash value the syscache `cache_id` gives the entry for `keys`, as passed to
+/// [`register_syscache_callback()`] closures when that entry is invalidated.
+///
+/// The syscaches are keyed by between one and four values, such as a single oid for
+/// `pg_sys::SysCacheIdentifier_TYPEOID`.  This must be called from within a transaction.
+pub fn syscache_hash_value(cache_id: pg_sys::SysCacheIdentifier, keys: &[pg_sys::Datum]) -> u32 {
+    assert!(
+        (1..=4).contains(&keys.len()),
+        "syscaches have between one and four keys, not {}",
+        keys.len()
+    );
+    let key = |i: usize| keys.get(i).copied().unwrap_or(0);
+    unsafe {
+        pg_sys::GetSysCacheHashValue(
+            cache_id as ::std::os::raw::c_int,
+            key(0),
+            key(1),
+            key(2),
+            key(3),
+        )
+    }
+}
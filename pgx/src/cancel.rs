@@ -0,0 +1,227 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Cancelling long-running Rust code, including what runs on threads of its own, when Postgres
+//! cancels the query it's part of.
+//!
+//! Postgres cancels a query with a signal, whose handler only sets a flag that the backend checks
+//! with `CHECK_FOR_INTERRUPTS()`, from which the query's `ERROR` is raised.  Only the backend's
+//! thread can do that, and a thread computing a result for it can't see the flag.  A
+//! [`CancellationToken`] carries the cancellation to such threads, which poll it or wait on it,
+//! or register callbacks on it:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use std::time::Duration;
+//!
+//! #[pg_extern]
+//! fn count_primes(below: i64) -> i64 {
+//!     // a `statement_timeout` or `pg_cancel_backend()` stops the computation, then raises
+//!     // the query's `ERROR`
+//!     run_cancellable(|token| {
+//!         (2..below)
+//!             .take_while(|_| !token.is_cancelled())
+//!             .filter(|n| (2..*n).take_while(|d| d * d <= *n).all(|d| n % d != 0))
+//!             .count() as i64
+//!     })
+//! }
+//! ```
+use crate::pg_sys;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often [`run_cancellable()`] checks for a query cancel while its closure runs
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+type CancelCallback = Box<dyn FnOnce() + Send>;
+
+struct CancelState {
+    cancelled: Mutex<bool>,
+    condvar: Condvar,
+    callbacks: Mutex<Vec<CancelCallback>>,
+}
+
+/// Tells code, on any thread, that what it's doing should stop, because the query it's part of
+/// was cancelled, or for a reason of its own.
+///
+/// A token is `Send` and `Sync`, and cheap to clone, and all its clones are cancelled together.
+/// Only [`CancellationToken::poll_interrupts()`] and [`run_cancellable()`] look at Postgres, and
+/// only on the backend's thread; the rest, like [`crate::bgworkers::WorkerShutdownHandle`], only
+/// uses the standard library, so threads can use it without calling into Postgres, which isn't
+/// thread-safe.
+#[derive(Clone)]
+pub struct CancellationToken {
+    state: Arc<CancelState>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancellationToken {
+    /// A token that hasn't been cancelled
+    pub fn new() -> Self {
+        CancellationToken {
+            state: Arc::new(CancelState {
+                cancelled: Mutex::new(false),
+                condvar: Condvar::new(),
+                callbacks: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Has the token been cancelled?
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.cancelled.lock().unwrap()
+    }
+
+    /// `Err(Cancelled)` once the token is cancelled, for code to return early with `?`
+    pub fn error_if_cancelled(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sleep for up to `timeout`, waking as soon as the token is cancelled, and return
+    /// [`CancellationToken::is_cancelled()`]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut cancelled = self.state.cancelled.lock().unwrap();
+        loop {
+            if *cancelled {
+                return true;
+            }
+            let remaining = match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => remaining,
+                _ => return false,
+            };
+            cancelled = self
+                .state
+                .condvar
+                .wait_timeout(cancelled, remaining)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Run `callback` when the token is cancelled, on the thread that cancels it, or now if it
+    /// already has been.  It shouldn't call into Postgres, as that thread may not be the backend's
+    pub fn on_cancel(&self, callback: impl FnOnce() + Send + 'static) {
+        {
+            let cancelled = self.state.cancelled.lock().unwrap();
+            if !*cancelled {
+                self.state
+                    .callbacks
+                    .lock()
+                    .unwrap()
+                    .push(Box::new(callback));
+                return;
+            }
+        }
+        callback()
+    }
+
+    /// Cancel the token, waking its waiters and running its callbacks.  This can't be undone, and
+    /// cancelling it again does nothing
+    pub fn cancel(&self) {
+        {
+            let mut cancelled = self.state.cancelled.lock().unwrap();
+            if *cancelled {
+                return;
+            }
+            *cancelled = true;
+            self.state.condvar.notify_all();
+        }
+        let callbacks = std::mem::take(&mut *self.state.callbacks.lock().unwrap());
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    /// Cancel the token if Postgres is going to cancel the query, because of a `statement_timeout`,
+    /// `pg_cancel_backend()`, or the backend being terminated, and return
+    /// [`CancellationToken::is_cancelled()`].  This doesn't raise the query's `ERROR`, which the
+    /// next `check_for_interrupts!()` does.  It must be called from the backend's thread
+    pub fn poll_interrupts(&self) -> bool {
+        crate::debug_assert_backend!();
+        if query_cancel_pending() {
+            self.cancel();
+        }
+        self.is_cancelled()
+    }
+}
+
+/// What [`CancellationToken::error_if_cancelled()`] returns once the token is cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Is a query cancel or the backend's termination pending, which the next `check_for_interrupts!()`
+/// will act on, rather than hold off?
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+pub fn query_cancel_pending() -> bool {
+    unsafe {
+        let pending = std::ptr::addr_of!(pg_sys::QueryCancelPending).read_volatile()
+            || std::ptr::addr_of!(pg_sys::ProcDiePending).read_volatile();
+        pending && pg_sys::InterruptHoldoffCount == 0 && pg_sys::CritSectionCount == 0
+    }
+}
+
+/// Is a query cancel or the backend's termination pending, which the next `check_for_interrupts!()`
+/// will act on, rather than hold off?
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+pub fn query_cancel_pending() -> bool {
+    unsafe {
+        let pending = std::ptr::addr_of!(pg_sys::QueryCancelPending).read_volatile() != 0
+            || std::ptr::addr_of!(pg_sys::ProcDiePending).read_volatile() != 0;
+        pending && pg_sys::InterruptHoldoffCount == 0 && pg_sys::CritSectionCount == 0
+    }
+}
+
+/// Run `f` on a thread of its own with a [`CancellationToken`], which is cancelled when Postgres
+/// cancels the query, while the backend's thread waits for it.  Once `f` returns, a cancelled
+/// query's `ERROR` is raised, and otherwise what it returned is, or its panic is raised again.
+///
+/// `f` can't call into Postgres, and has to return soon after the token is cancelled, as the
+/// query can't be cancelled until it does.  It can spawn threads of its own, giving them clones of
+/// the token.
+pub fn run_cancellable<T, F>(f: F) -> T
+where
+    T: Send,
+    F: FnOnce(&CancellationToken) -> T + Send,
+{
+    crate::assert_backend();
+    let token = CancellationToken::new();
+    let result = std::thread::scope(|scope| {
+        let worker = scope.spawn(|| f(&token));
+        while !worker.is_finished() {
+            if token.poll_interrupts() {
+                break;
+            }
+            std::thread::sleep(INTERRUPT_POLL_INTERVAL);
+        }
+        worker.join()
+    });
+    crate::check_for_interrupts!();
+    match result {
+        Ok(value) => value,
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
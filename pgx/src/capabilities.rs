@@ -0,0 +1,174 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Probing, at runtime, for the optional capabilities of the server an extension is loaded into,
+//! like another extension, the functions it provides, or JIT compilation, so one build of an
+//! extension can enable what it can and gracefully disable the rest.
+//!
+//! The catalog probes, [`extension_version()`] and [`has_function()`], are cached until the end of
+//! the transaction, or until a function is created, replaced, or dropped, which creating,
+//! updating, or dropping an extension almost always does.  So they're cheap enough to call for
+//! every row:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn resemblance(a: &str, b: &str) -> f32 {
+//!     // pg_trgm's, when it's been created
+//!     if has_function("public.similarity(text, text)") {
+//!         Spi::get_one_with_args(
+//!             "SELECT public.similarity($1, $2)",
+//!             vec![
+//!                 (PgBuiltInOids::TEXTOID.oid(), a.into_datum()),
+//!                 (PgBuiltInOids::TEXTOID.oid(), b.into_datum()),
+//!             ],
+//!         )
+//!         .unwrap_or(0.0)
+//!     } else if a == b {
+//!         1.0
+//!     } else {
+//!         0.0
+//!     }
+//! }
+//! ```
+use crate::{
+    pg_sys, quote_literal, register_syscache_callback, register_xact_callback, IntoDatum,
+    PgBuiltInOids, PgXactCallbackEvent, Spi,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+/// What's been found in the catalogs this transaction
+#[derive(Default)]
+struct Probed {
+    extension_versions: HashMap<String, Option<String>>,
+    functions: HashMap<String, bool>,
+}
+
+thread_local! {
+    static PROBED: RefCell<Option<Probed>> = const { RefCell::new(None) };
+}
+
+static mut INVALIDATION_REGISTERED: bool = false;
+
+/// The cached result of the probe for `key` in the `probes` of its kind, or what `run` finds,
+/// which is cached until the transaction ends or `pg_proc` changes
+fn probe<T: Clone>(
+    probes: fn(&mut Probed) -> &mut HashMap<String, T>,
+    key: &str,
+    run: impl FnOnce() -> T,
+) -> T {
+    let cached = PROBED.with(|cache| {
+        cache
+            .borrow_mut()
+            .as_mut()
+            .and_then(|probed| probes(probed).get(key).cloned())
+    });
+    if let Some(found) = cached {
+        return found;
+    }
+
+    let found = run();
+    let is_new_cache = PROBED.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let is_new_cache = cache.is_none();
+        let probed = cache.get_or_insert_with(Probed::default);
+        probes(probed).insert(key.to_string(), found.clone());
+        is_new_cache
+    });
+    if is_new_cache {
+        // forget it all when the transaction ends, as nothing tells us when `pg_extension` changes
+        register_xact_callback(PgXactCallbackEvent::Commit, forget_probes);
+        register_xact_callback(PgXactCallbackEvent::Abort, forget_probes);
+    }
+    unsafe {
+        if !INVALIDATION_REGISTERED {
+            INVALIDATION_REGISTERED = true;
+            register_syscache_callback(pg_sys::SysCacheIdentifier_PROCOID, |_| forget_probes());
+        }
+    }
+    found
+}
+
+fn forget_probes() {
+    PROBED.with(|cache| cache.borrow_mut().take());
+}
+
+/// The version of the extension `name` created in this database, or `None` if it hasn't been
+pub fn extension_version(name: &str) -> Option<String> {
+    probe(
+        |probed| &mut probed.extension_versions,
+        name,
+        || {
+            Spi::get_one_with_args::<String>(
+                "SELECT extversion::text FROM pg_catalog.pg_extension WHERE extname = $1",
+                vec![(PgBuiltInOids::TEXTOID.oid(), name.into_datum())],
+            )
+        },
+    )
+}
+
+/// Is the extension `name` created in this database?
+pub fn has_extension(name: &str) -> bool {
+    extension_version(name).is_some()
+}
+
+/// Is there a function with the `signature`, like `public.similarity(text, text)`?
+///
+/// It's looked up as `regprocedure` does, so an unqualified name or argument type is found with
+/// the current `search_path`, and the signature must be one `regprocedure` can parse.
+pub fn has_function(signature: &str) -> bool {
+    probe(
+        |probed| &mut probed.functions,
+        signature,
+        || {
+            Spi::get_one::<bool>(&format!(
+                "SELECT pg_catalog.to_regprocedure({}) IS NOT NULL",
+                quote_literal(signature)
+            ))
+            .unwrap_or(false)
+        },
+    )
+}
+
+/// Will queries be JIT compiled, when they cost enough?  It's only true when the `jit` setting
+/// is on and the JIT provider, `jit_provider`, could be loaded, which Postgres remembers after
+/// the first try.  Postgres 10 has no JIT.
+pub fn jit_available() -> bool {
+    #[cfg(feature = "pg10")]
+    {
+        false
+    }
+
+    #[cfg(not(feature = "pg10"))]
+    unsafe {
+        crate::direct_function_call::<bool>(pg_sys::pg_jit_available, vec![]).unwrap_or(false)
+    }
+}
+
+/// Is the server's shared memory in huge pages?  It's `None` when the `huge_pages` setting is
+/// `try`, as Postgres doesn't say whether it managed to get them.
+#[allow(clippy::manual_c_str_literals)]
+pub fn huge_pages() -> Option<bool> {
+    let setting = unsafe {
+        let value = pg_sys::GetConfigOption(
+            "huge_pages\0".as_ptr() as *const std::os::raw::c_char,
+            false,
+            false,
+        );
+        CStr::from_ptr(value).to_string_lossy().into_owned()
+    };
+    match setting.as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,122 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Tables an extension keeps its own metadata in, declared as Rust structs
+//!
+//! A `#[derive(PgCatalogTable)]` struct is a row of a table the extension's install script
+//! creates, along with the table's indexes and the rows it starts out with.  The table's columns
+//! are the struct's fields, so they can't drift apart, and as fields, indexes, and rows are added
+//! in later versions, `cargo pgx schema --upgrade-from` writes the SQL that adds them to the
+//! table for the extension's upgrade script.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[derive(PgCatalogTable)]
+//! #[pgx(sql_name = "job_queues", schema = "jobs", dump)]
+//! #[index(name = "job_queues_priority", keys = ["priority"])]
+//! #[row(name = "default", priority = 0)]
+//! struct JobQueue {
+//!     #[pgx(primary_key)]
+//!     name: String,
+//!     priority: i32,
+//!     #[pgx(default = "false")]
+//!     paused: bool,
+//!     description: Option<String>,
+//! }
+//!
+//! #[pg_extern]
+//! fn busiest_queue() -> Option<String> {
+//!     JobQueue::select_where("NOT paused ORDER BY priority DESC", vec![])
+//!         .into_iter()
+//!         .next()
+//!         .map(|queue| queue.name)
+//! }
+//! ```
+use crate::{pg_sys, quote_identifier, quote_qualified_identifier};
+use crate::{PgMemoryContexts, PgOid, Spi, SpiHeapTupleData};
+
+/// A row of a table the extension creates, from `#[derive(PgCatalogTable)]`, with a field for
+/// each of its columns
+pub trait PgCatalogTable: Sized {
+    /// The schema it's given with `#[pgx(schema = "...")]`, if it is, or else it's found on the
+    /// `search_path`
+    const SCHEMA: Option<&'static str>;
+    const NAME: &'static str;
+    /// The names of its columns, in the order of the struct's fields
+    const COLUMNS: &'static [&'static str];
+
+    /// The row `row` is of the table's columns, in order
+    #[doc(hidden)]
+    fn from_row(row: &SpiHeapTupleData) -> Self;
+
+    /// The values of the fields, in order, as the arguments of an `INSERT`
+    #[doc(hidden)]
+    fn into_args(self) -> Vec<(PgOid, Option<pg_sys::Datum>)>;
+
+    /// The table's name, qualified with its schema if it's given one, and quoted
+    fn qualified_name() -> String {
+        quote_qualified_identifier(Self::SCHEMA, Self::NAME)
+    }
+
+    /// Every row of the table, in no particular order
+    fn select_all() -> Vec<Self> {
+        Self::select_where("true", vec![])
+    }
+
+    /// The rows `condition` is true for, which is SQL, such as `priority > $1`, given `args`,
+    /// which may be followed by an `ORDER BY` or `LIMIT`
+    fn select_where(condition: &str, args: Vec<(PgOid, Option<pg_sys::Datum>)>) -> Vec<Self> {
+        let query = format!(
+            "SELECT {} FROM {} WHERE {}",
+            quoted_columns(Self::COLUMNS),
+            Self::qualified_name(),
+            condition
+        );
+        let args = if args.is_empty() { None } else { Some(args) };
+
+        // the rows are made in the memory context we were called in, before SPI frees the one it
+        // reads them into
+        let mut outer_memory_context =
+            PgMemoryContexts::For(PgMemoryContexts::CurrentMemoryContext.value());
+        let mut rows = Vec::new();
+        Spi::connect(|client| {
+            for row in client.select(&query, None, args) {
+                rows.push(outer_memory_context.switch_to(|_| Self::from_row(&row)));
+            }
+            Ok(None::<()>)
+        });
+        rows
+    }
+
+    /// Insert it as a row of the table, raising an ERROR if it conflicts with one that's already
+    /// there
+    fn insert(self) {
+        let values = (1..=Self::COLUMNS.len())
+            .map(|parameter| format!("${}", parameter))
+            .collect::<Vec<_>>();
+        Spi::run_with_args(
+            &format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                Self::qualified_name(),
+                quoted_columns(Self::COLUMNS),
+                values.join(", ")
+            ),
+            self.into_args(),
+        );
+    }
+}
+
+fn quoted_columns(columns: &[&str]) -> String {
+    columns
+        .iter()
+        .map(|column| quote_identifier(column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
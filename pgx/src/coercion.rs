@@ -0,0 +1,167 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Finding, and applying, the coercions between types that Postgres' parser would, from casts in
+//! `pg_cast`, binary compatible types, and I/O conversion
+use crate::{pg_sys, FromDatum, IntoDatum};
+
+/// Where a coercion is being made, which decides which casts may be applied, from the most to the
+/// least restrictive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgCoercionContext {
+    /// Without being asked for, such as for a function's argument
+    Implicit,
+    /// When assigning to a column, as by `INSERT` and `UPDATE`
+    Assignment,
+    /// When asked for, with `CAST()` or `::`
+    Explicit,
+}
+
+impl From<PgCoercionContext> for pg_sys::CoercionContext {
+    fn from(context: PgCoercionContext) -> Self {
+        match context {
+            PgCoercionContext::Implicit => pg_sys::CoercionContext_COERCION_IMPLICIT,
+            PgCoercionContext::Assignment => pg_sys::CoercionContext_COERCION_ASSIGNMENT,
+            PgCoercionContext::Explicit => pg_sys::CoercionContext_COERCION_EXPLICIT,
+        }
+    }
+}
+
+/// How a value is coerced from one type to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgCoercionPathway {
+    /// By calling the cast's function
+    Function(pg_sys::Oid),
+    /// As it is, because the types are binary compatible
+    RelabelType,
+    /// One element at a time, because both types are arrays
+    ArrayCoerce,
+    /// Through the source type's output function, and the target type's input function
+    CoerceViaIO,
+}
+
+/// How a value of type `source` can be coerced to `target`, in `context`, if it can be.  This is
+/// `find_coercion_pathway()`, so it doesn't know about coercions the parser makes specially, such
+/// as to a domain's base type, or from a row type, which [`can_coerce_type()`] does
+pub fn find_coercion_pathway(
+    source: pg_sys::Oid,
+    target: pg_sys::Oid,
+    context: PgCoercionContext,
+) -> Option<PgCoercionPathway> {
+    let mut funcid = pg_sys::InvalidOid;
+    let pathway =
+        unsafe { pg_sys::find_coercion_pathway(target, source, context.into(), &mut funcid) };
+    match pathway {
+        pg_sys::CoercionPathType_COERCION_PATH_FUNC => Some(PgCoercionPathway::Function(funcid)),
+        pg_sys::CoercionPathType_COERCION_PATH_RELABELTYPE => Some(PgCoercionPathway::RelabelType),
+        pg_sys::CoercionPathType_COERCION_PATH_ARRAYCOERCE => Some(PgCoercionPathway::ArrayCoerce),
+        pg_sys::CoercionPathType_COERCION_PATH_COERCEVIAIO => Some(PgCoercionPathway::CoerceViaIO),
+        _ => None,
+    }
+}
+
+/// Can a value of type `source` be coerced to `target`, in `context`, the way the parser would?
+pub fn can_coerce_type(
+    source: pg_sys::Oid,
+    target: pg_sys::Oid,
+    context: PgCoercionContext,
+) -> bool {
+    // Postgres 10 and 11 declare the arrays `Oid *`, but only read them
+    unsafe {
+        pg_sys::can_coerce_type(
+            1,
+            &source as *const pg_sys::Oid as *mut _,
+            &target as *const pg_sys::Oid as *mut _,
+            context.into(),
+        )
+    }
+}
+
+/// Are values of type `source` also values of type `target`, without any conversion?
+pub fn is_binary_coercible(source: pg_sys::Oid, target: pg_sys::Oid) -> bool {
+    unsafe { pg_sys::IsBinaryCoercible(source, target) }
+}
+
+/// Coerce `value`, of type `source`, to `target`, in `context`, exactly as the parser would if it
+/// were a constant in a query, including applying `target_typmod`, which can be `-1`, and
+/// checking a domain's constraints.  The result is `(value, is_null)`, allocated in the
+/// `CurrentMemoryContext`, or `None` if there's no such coercion.
+///
+/// This is how a function taking `anyelement` can turn its argument into the type it needs, as
+/// [`AnyElement::coerce_to()`](crate::AnyElement::coerce_to) does:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn add_one(value: AnyElement) -> Option<i64> {
+///     let value = value.coerce_to::<i64>(PgCoercionContext::Implicit)?;
+///     Some(value + 1)
+/// }
+/// ```
+pub fn coerce_datum(
+    value: pg_sys::Datum,
+    is_null: bool,
+    source: pg_sys::Oid,
+    target: pg_sys::Oid,
+    target_typmod: i32,
+    context: PgCoercionContext,
+) -> Option<(pg_sys::Datum, bool)> {
+    unsafe {
+        let mut typlen = 0;
+        let mut typbyval = false;
+        pg_sys::get_typlenbyval(source, &mut typlen, &mut typbyval);
+        let constant = pg_sys::makeConst(
+            source,
+            -1,
+            pg_sys::get_typcollation(source),
+            typlen as i32,
+            value,
+            is_null,
+            typbyval,
+        );
+
+        let expr = pg_sys::coerce_to_target_type(
+            std::ptr::null_mut(),
+            constant as *mut pg_sys::Node,
+            source,
+            target,
+            target_typmod,
+            context.into(),
+            pg_sys::CoercionForm_COERCE_IMPLICIT_CAST,
+            -1,
+        );
+        if expr.is_null() {
+            return None;
+        }
+        pg_sys::assign_expr_collations(std::ptr::null_mut(), expr);
+
+        // evaluated in the `CurrentMemoryContext`, rather than the expression context's, so the
+        // result outlives it
+        let state = pg_sys::ExecInitExpr(expr as *mut pg_sys::Expr, std::ptr::null_mut());
+        let econtext = pg_sys::CreateStandaloneExprContext();
+        let evalfunc = (*state).evalfunc.expect("ExprState has no evalfunc");
+        let mut is_null = false;
+        let result = evalfunc(state, econtext, &mut is_null);
+        pg_sys::FreeExprContext(econtext, true);
+        Some((result, is_null))
+    }
+}
+
+/// Coerce `value`, of type `source`, to a `T`, in `context`, as with [`coerce_datum()`].  The
+/// inner `Option` is `None` when the value is `NULL`
+pub fn coerce_to<T: FromDatum + IntoDatum>(
+    value: pg_sys::Datum,
+    is_null: bool,
+    source: pg_sys::Oid,
+    context: PgCoercionContext,
+) -> Option<Option<T>> {
+    let (datum, is_null) = coerce_datum(value, is_null, source, T::type_oid(), -1, context)?;
+    Some(unsafe { T::from_datum(datum, is_null, T::type_oid()) })
+}
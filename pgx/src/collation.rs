@@ -0,0 +1,103 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Comparing strings the way Postgres does, according to a collation rather than byte order
+use crate::{pg_get_collation, pg_sys};
+use std::cmp::Ordering;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// A collation, such as the one a function was called with, for ordering text as Postgres would.
+///
+/// Types that contain text should compare it with [`PgCollation::compare()`], so that indexes
+/// and `ORDER BY` respect the column's collation:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn compare_names(a: &str, b: &str, fcinfo: pg_sys::FunctionCallInfo) -> i32 {
+///     let collation = unsafe { PgCollation::from_fcinfo(fcinfo) }.unwrap_or_default();
+///     collation.compare(a, b) as i32
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PgCollation(pub pg_sys::Oid);
+
+impl Default for PgCollation {
+    /// The database's default collation
+    fn default() -> Self {
+        PgCollation(pg_sys::DEFAULT_COLLATION_OID)
+    }
+}
+
+impl PgCollation {
+    /// The `"C"` collation, which orders by bytes
+    pub fn c() -> Self {
+        PgCollation(pg_sys::C_COLLATION_OID)
+    }
+
+    /// The collation the function was called with, or `None` if none of its arguments are
+    /// collatable
+    ///
+    /// # Safety
+    ///
+    /// The same as [`pg_get_collation()`]
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Option<Self> {
+        match pg_get_collation(fcinfo) {
+            pg_sys::InvalidOid => None,
+            oid => Some(PgCollation(oid)),
+        }
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.0
+    }
+
+    /// The collation's name, unqualified, or `None` if it doesn't exist
+    pub fn name(&self) -> Option<String> {
+        unsafe {
+            let name = pg_sys::get_collation_name(self.0);
+            if name.is_null() {
+                None
+            } else {
+                let result = CStr::from_ptr(name).to_string_lossy().into_owned();
+                pg_sys::pfree(name as *mut std::os::raw::c_void);
+                Some(result)
+            }
+        }
+    }
+
+    /// Does this collation order by bytes, like `"C"` and `"POSIX"` do?
+    pub fn is_c(&self) -> bool {
+        unsafe { pg_sys::lc_collate_is_c(self.0) }
+    }
+
+    /// Can strings that aren't byte-for-byte equal compare as equal under this collation?  Only
+    /// ICU collations can be nondeterministic
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    pub fn is_deterministic(&self) -> bool {
+        unsafe { pg_sys::get_collation_isdeterministic(self.0) }
+    }
+
+    /// Compare `a` and `b` under this collation, just like comparing `text` values does
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        // Postgres 10 and 11 declare the strings `char *`, but only read them
+        let result = unsafe {
+            pg_sys::varstr_cmp(
+                a.as_ptr() as *mut c_char,
+                a.len() as i32,
+                b.as_ptr() as *mut c_char,
+                b.len() as i32,
+                self.0,
+            )
+        };
+        result.cmp(&0)
+    }
+}
@@ -0,0 +1,153 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Running CPU-bound, pure Rust work, like vector math, on several cores, from a function
+//! Postgres calls.
+//!
+//! Postgres isn't thread-safe, so the threads must never call into Postgres, or into pgx.  Rather
+//! than trusting them not to, their threads forbid it with [`forbid_postgres_calls()`], so that
+//! calling a Postgres function from one panics, even in release builds, and the panic is raised as
+//! the query's `ERROR` on the backend's thread, rather than corrupting the backend.  Copy what the
+//! work needs out of Postgres' memory first, such as into a `Vec`.
+//!
+//! The work is cancelled along with its query, through a [`CancellationToken`] that the threads
+//! check, as [`run_cancellable()`] does.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn squared_distance(a: Vec<f32>, b: Vec<f32>) -> f32 {
+//!     let pairs = a.into_iter().zip(b).collect::<Vec<_>>();
+//!     compute_pool::run(|pool| {
+//!         // a cancelled query raises its `ERROR` instead of this being returned
+//!         let squares = pool.map(&pairs, |(x, y)| (x - y) * (x - y)).unwrap_or_default();
+//!         squares.iter().sum()
+//!     })
+//! }
+//! ```
+use crate::{forbid_postgres_calls, run_cancellable, CancellationToken, Cancelled};
+use std::thread::{Scope, ScopedJoinHandle};
+
+/// The threads of a [`run()`], for spawning threads of the pool and sharing work out among them
+pub struct ComputeScope<'scope, 'env: 'scope> {
+    scope: &'scope Scope<'scope, 'env>,
+    token: CancellationToken,
+    threads: usize,
+}
+
+impl<'scope, 'env> ComputeScope<'scope, 'env> {
+    /// The token cancelled when the query is
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Has the query been cancelled?
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// How many threads [`ComputeScope::map()`] shares its work among
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Run `f` on a thread of the pool, which can't call into Postgres, with the token.  The
+    /// thread is joined by the time [`run()`] returns, if it isn't before
+    pub fn spawn<T, F>(&self, f: F) -> ScopedJoinHandle<'scope, T>
+    where
+        T: Send + 'scope,
+        F: FnOnce(&CancellationToken) -> T + Send + 'scope,
+    {
+        let token = self.token.clone();
+        self.scope.spawn(move || {
+            forbid_postgres_calls();
+            f(&token)
+        })
+    }
+
+    /// `f` of each of the `items`, in their order, computed on [`ComputeScope::threads()`]
+    /// threads, each given a run of the items.  It's `Err(Cancelled)` if the query was cancelled
+    /// before every item was done, and a panic of `f` is raised again
+    #[allow(clippy::manual_div_ceil)]
+    pub fn map<I, O, F>(&self, items: &[I], f: F) -> Result<Vec<O>, Cancelled>
+    where
+        I: Sync,
+        O: Send,
+        F: Fn(&I) -> O + Sync,
+    {
+        let chunk_size = ((items.len() + self.threads - 1) / self.threads).max(1);
+        let f = &f;
+        let chunks = std::thread::scope(|scope| {
+            let handles = items
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let token = self.token.clone();
+                    scope.spawn(move || {
+                        forbid_postgres_calls();
+                        let mut results = Vec::with_capacity(chunk.len());
+                        for item in chunk {
+                            token.error_if_cancelled()?;
+                            results.push(f(item));
+                        }
+                        Ok(results)
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| match handle.join() {
+                    Ok(results) => results,
+                    Err(panic) => std::panic::resume_unwind(panic),
+                })
+                .collect::<Vec<Result<Vec<O>, Cancelled>>>()
+        });
+
+        let mut results = Vec::with_capacity(items.len());
+        for chunk in chunks {
+            results.extend(chunk?);
+        }
+        Ok(results)
+    }
+}
+
+/// Run `f` on a thread of its own, which can't call into Postgres, with a [`ComputeScope`] for
+/// spreading its work over as many threads as there are cores, while the backend's thread waits
+/// for them all to finish, cancelling them if Postgres cancels the query.  A cancelled query's
+/// `ERROR` is raised once they have, and otherwise what `f` returned is, or a panic of any of them
+/// is raised again.  It must be called from the backend's thread
+pub fn run<'env, T, F>(f: F) -> T
+where
+    T: Send,
+    F: for<'scope> FnOnce(&ComputeScope<'scope, 'env>) -> T + Send,
+{
+    let threads = std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1);
+    run_with_threads(threads, f)
+}
+
+/// [`run()`], sharing [`ComputeScope::map()`]'s work among `threads` threads
+pub fn run_with_threads<'env, T, F>(threads: usize, f: F) -> T
+where
+    T: Send,
+    F: for<'scope> FnOnce(&ComputeScope<'scope, 'env>) -> T + Send,
+{
+    assert!(threads > 0, "a compute pool needs at least one thread");
+    run_cancellable(|token| {
+        forbid_postgres_calls();
+        std::thread::scope(|scope| {
+            f(&ComputeScope {
+                scope,
+                token: token.clone(),
+                threads,
+            })
+        })
+    })
+}
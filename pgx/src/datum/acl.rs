@@ -0,0 +1,135 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{direct_function_call, pg_sys, FromDatum, IntoDatum};
+use std::ffi::{CStr, CString};
+
+bitflags! {
+    /// The privileges of an [`AclItem`]
+    pub struct AclMode: u32 {
+        const INSERT = pg_sys::ACL_INSERT;
+        const SELECT = pg_sys::ACL_SELECT;
+        const UPDATE = pg_sys::ACL_UPDATE;
+        const DELETE = pg_sys::ACL_DELETE;
+        const TRUNCATE = pg_sys::ACL_TRUNCATE;
+        const REFERENCES = pg_sys::ACL_REFERENCES;
+        const TRIGGER = pg_sys::ACL_TRIGGER;
+        const EXECUTE = pg_sys::ACL_EXECUTE;
+        const USAGE = pg_sys::ACL_USAGE;
+        const CREATE = pg_sys::ACL_CREATE;
+        const CREATE_TEMP = pg_sys::ACL_CREATE_TEMP;
+        const CONNECT = pg_sys::ACL_CONNECT;
+    }
+}
+
+/// The layout of Postgres' `AclItem`, which isn't in the bindings
+#[repr(C)]
+struct RawAclItem {
+    ai_grantee: pg_sys::Oid,
+    ai_grantor: pg_sys::Oid,
+    /// the privileges in the low 16 bits, and their grant options in the high 16
+    ai_privs: u32,
+}
+
+/// An `aclitem`, one entry of an access control list such as `pg_class.relacl`, which is a
+/// `Vec<AclItem>`
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// // CREATE FUNCTION public_privileges(regclass) ... AS 'SELECT relacl FROM pg_class WHERE oid = $1'
+/// fn public_privileges(relacl: Vec<AclItem>) -> AclMode {
+///     relacl
+///         .iter()
+///         .filter(|item| item.is_public())
+///         .fold(AclMode::empty(), |privileges, item| privileges | item.privileges)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AclItem {
+    /// The role the privileges are granted to, or `pg_sys::InvalidOid` for `PUBLIC`
+    pub grantee: pg_sys::Oid,
+    /// The role that granted them
+    pub grantor: pg_sys::Oid,
+    pub privileges: AclMode,
+    /// The privileges that the grantee can grant to others
+    pub grant_options: AclMode,
+}
+
+impl AclItem {
+    /// Parse an `aclitem` in its text form, such as `"alice=arw/postgres"`
+    ///
+    /// ## Panics
+    ///
+    /// Raises a Postgres ERROR if `item` isn't a valid `aclitem`, or names a role that doesn't exist
+    pub fn parse(item: &str) -> Self {
+        let cstr = CString::new(item).expect("aclitem contains a null byte");
+        unsafe { direct_function_call(pg_sys::aclitemin, vec![cstr.as_c_str().into_datum()]) }
+            .expect("aclitemin returned NULL")
+    }
+
+    /// Are the privileges granted to `PUBLIC`?
+    pub fn is_public(&self) -> bool {
+        self.grantee == pg_sys::InvalidOid
+    }
+
+    /// This item in its text form, such as `"alice=arw/postgres"`
+    pub fn to_text(&self) -> String {
+        unsafe {
+            direct_function_call::<&CStr>(pg_sys::aclitemout, vec![self.into_datum()])
+                .expect("aclitemout returned NULL")
+                .to_str()
+                .expect("aclitem is not valid UTF8")
+                .to_string()
+        }
+    }
+}
+
+impl FromDatum for AclItem {
+    const NEEDS_TYPID: bool = false;
+
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("aclitem datum is declared non-null but Datum is zero");
+        } else {
+            let raw = &*(datum as *const RawAclItem);
+            Some(AclItem {
+                grantee: raw.ai_grantee,
+                grantor: raw.ai_grantor,
+                privileges: AclMode::from_bits_truncate(raw.ai_privs & 0xFFFF),
+                grant_options: AclMode::from_bits_truncate(raw.ai_privs >> 16),
+            })
+        }
+    }
+}
+
+impl IntoDatum for AclItem {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        unsafe {
+            let raw = pg_sys::palloc(std::mem::size_of::<RawAclItem>()) as *mut RawAclItem;
+            raw.write(RawAclItem {
+                ai_grantee: self.grantee,
+                ai_grantor: self.grantor,
+                ai_privs: self.privileges.bits() | (self.grant_options.bits() << 16),
+            });
+            Some(raw as pg_sys::Datum)
+        }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::ACLITEMOID
+    }
+}
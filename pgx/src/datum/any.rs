@@ -0,0 +1,81 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{pg_sys, FromDatum, IntoDatum, RegType};
+
+/// An argument of the SQL pseudo-type `"any"`, which accepts a value of any type.
+///
+/// Unlike with `anyelement`, every `"any"` argument can have a different type, and Postgres
+/// doesn't resolve them, so the function has to look at [`Any::oid()`] at runtime.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn describe(value: Any) -> String {
+///     match value.oid() {
+///         pg_sys::INT4OID => format!("the integer {}", value.value::<i32>().unwrap()),
+///         pg_sys::TEXTOID => format!("the text {}", value.value::<&str>().unwrap()),
+///         _ => format!("a value of type {}", value.type_name()),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Any {
+    datum: pg_sys::Datum,
+    typoid: pg_sys::Oid,
+}
+
+impl Any {
+    pub fn datum(&self) -> pg_sys::Datum {
+        self.datum
+    }
+
+    /// The type of the value, as the function was called with it
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.typoid
+    }
+
+    /// The name of the value's type, as SQL would show it
+    pub fn type_name(&self) -> String {
+        RegType(self.typoid).name()
+    }
+
+    /// The value as a `T`, which should match [`Any::oid()`]
+    #[inline]
+    pub fn value<T: FromDatum>(&self) -> Option<T> {
+        unsafe { T::from_datum(self.datum(), false, self.oid()) }
+    }
+}
+
+impl FromDatum for Any {
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: pg_sys::Oid) -> Option<Any> {
+        if is_null {
+            None
+        } else {
+            Some(Any { datum, typoid })
+        }
+    }
+}
+
+impl IntoDatum for Any {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.datum)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::ANYOID
+    }
+
+    fn is_compatible_with(_: pg_sys::Oid) -> bool {
+        true
+    }
+}
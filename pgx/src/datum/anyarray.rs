@@ -54,4 +54,8 @@ impl IntoDatum for AnyArray {
     fn type_oid() -> u32 {
         pg_sys::ANYARRAYOID
     }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        unsafe { pg_sys::get_element_type(other) != pg_sys::InvalidOid }
+    }
 }
@@ -7,7 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-use crate::{pg_sys, FromDatum, IntoDatum};
+use crate::{coerce_to, pg_sys, FromDatum, IntoDatum, PgCoercionContext};
 
 #[derive(Debug, Clone, Copy)]
 pub struct AnyElement {
@@ -28,6 +28,12 @@ impl AnyElement {
     pub fn into<T: FromDatum>(&self) -> Option<T> {
         unsafe { T::from_datum(self.datum(), false, self.oid()) }
     }
+
+    /// The value as a `T`, coerced the way the parser would coerce it, in `context`, or `None`
+    /// when there's no such coercion
+    pub fn coerce_to<T: FromDatum + IntoDatum>(&self, context: PgCoercionContext) -> Option<T> {
+        coerce_to(self.datum(), false, self.oid(), context).flatten()
+    }
 }
 
 impl FromDatum for AnyElement {
@@ -54,4 +60,8 @@ impl IntoDatum for AnyElement {
     fn type_oid() -> u32 {
         pg_sys::ANYELEMENTOID
     }
+
+    fn is_compatible_with(_: pg_sys::Oid) -> bool {
+        true
+    }
 }
@@ -10,6 +10,7 @@ Use of this source code is governed by the MIT license that can be found in the
 use crate::{pg_sys, void_mut_ptr, FromDatum, IntoDatum, PgMemoryContexts};
 use serde::Serializer;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 
 pub type VariadicArray<'a, T> = Array<'a, T>;
 
@@ -92,6 +93,12 @@ impl<'a, T: FromDatum> Array<'a, T> {
         }
     }
 
+    /// The detoasted `ArrayType` the elements were deconstructed from, which is null for an
+    /// [`Array`](crate::datum::Array) made with `::over()`
+    pub(crate) fn array_type_ptr(&self) -> *mut pg_sys::ArrayType {
+        self.array_type
+    }
+
     pub fn into_array_type(self) -> *const pg_sys::ArrayType {
         if self.array_type.is_null() {
             panic!("attempt to dereference a NULL array");
@@ -137,6 +144,95 @@ impl<'a, T: FromDatum> Array<'a, T> {
         }
     }
 
+    /// Return an Iterator of the contained Datums (converted to Rust types), skipping SQL NULL
+    /// values.
+    ///
+    /// Unlike `.iter().flatten()`, NULLs are found with the array's null bitmap, and an array
+    /// without one isn't checked for them at all
+    pub fn iter_non_null(&self) -> ArrayNonNullIterator<'_, T> {
+        ArrayNonNullIterator {
+            array: self,
+            nulls: self.null_bitmap(),
+            curr: 0,
+        }
+    }
+
+    /// Return an Iterator of whether each element is non-NULL, along with its value, which is
+    /// `T::default()` for SQL NULL values.
+    ///
+    /// This is for code that handles NULLs as a default value, such as accumulating into a sum,
+    /// without matching on an `Option<T>` for every element
+    pub fn iter_with_validity(&self) -> ArrayValidityIterator<'_, T>
+    where
+        T: Default,
+    {
+        ArrayValidityIterator {
+            array: self,
+            nulls: self.null_bitmap(),
+            curr: 0,
+        }
+    }
+
+    /// Where to find the NULL elements, for the iterators that don't make an `Option<T>` for each
+    #[allow(clippy::manual_div_ceil)]
+    fn null_bitmap(&self) -> NullBitmap<'_> {
+        if self.array_type.is_null() {
+            // an array made with `::over()` only has its "is_null" indicators
+            return if self.nulls.is_null() {
+                NullBitmap::NoNulls
+            } else {
+                NullBitmap::Flags(self.null_slice)
+            };
+        }
+
+        unsafe {
+            let array = &*self.array_type;
+            if array.dataoffset == 0 {
+                NullBitmap::NoNulls
+            } else {
+                // as `ARR_NULLBITMAP()`, the bitmap follows the dimensions and lower bounds
+                let bitmap = (self.array_type as *const u8).add(
+                    std::mem::size_of::<pg_sys::ArrayType>()
+                        + 2 * std::mem::size_of::<i32>() * array.ndim as usize,
+                );
+                NullBitmap::Bits(std::slice::from_raw_parts(bitmap, (self.nelems + 7) / 8))
+            }
+        }
+    }
+
+    /// A view of the elements in `range`, without copying them, which can be iterated over, sliced
+    /// again, or passed onward as an array `Datum` with [`IntoDatum`].
+    ///
+    /// Indexes are zero-based, over the elements in storage order, so a slice of a multidimensional
+    /// array is one-dimensional.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// #[pg_extern]
+    /// fn moving_sums(values: Array<i32>, width: i32) -> Vec<i64> {
+    ///     let width = width as usize;
+    ///     (0..=values.len().saturating_sub(width))
+    ///         .map(|start| {
+    ///             let window = values.slice(start..start + width);
+    ///             window.iter().map(|v| v.unwrap_or(0) as i64).sum()
+    ///         })
+    ///         .collect()
+    /// }
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// If `range` is out of bounds, as slicing a Rust slice would
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> ArraySlice<'_, T> {
+        let (start, end) = slice_bounds(range, self.nelems);
+        ArraySlice {
+            array: self,
+            start,
+            end,
+        }
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.nelems
@@ -158,6 +254,154 @@ impl<'a, T: FromDatum> Array<'a, T> {
     }
 }
 
+/// A view of some of the elements of an [`Array`], from [`Array::slice()`]
+pub struct ArraySlice<'a, T: FromDatum> {
+    array: &'a Array<'a, T>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a, T: FromDatum> ArraySlice<'a, T> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The element at index `i` of this slice, like [`Array::get()`]
+    #[allow(clippy::option_option)]
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<Option<T>> {
+        if i >= self.len() {
+            None
+        } else {
+            self.array.get(self.start + i)
+        }
+    }
+
+    /// Return an Iterator of Option<T> over the Datums in this slice.
+    pub fn iter(&self) -> impl Iterator<Item = Option<T>> + '_ {
+        (self.start..self.end).map(move |i| self.array.get(i).unwrap())
+    }
+
+    /// A view of the elements in `range` of this slice, like [`Array::slice()`]
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> ArraySlice<'a, T> {
+        let (start, end) = slice_bounds(range, self.len());
+        ArraySlice {
+            array: self.array,
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+
+    /// Does this slice cover all of an array that came from a `Datum`, which already has the
+    /// one-dimensional shape, numbered from 1, of the array the slice makes, so it can be passed
+    /// on as that `Datum`?
+    fn is_whole_array(&self) -> bool {
+        if self.array.ptr.is_null()
+            || self.array.array_type.is_null()
+            || self.start != 0
+            || self.end != self.array.nelems
+        {
+            return false;
+        }
+
+        unsafe {
+            let array = &*self.array.array_type;
+            match array.ndim {
+                0 => true,
+                1 => {
+                    // as `ARR_LBOUND()`, the lower bounds follow the dimensions
+                    let lbound = (self.array.array_type as *const u8)
+                        .add(std::mem::size_of::<pg_sys::ArrayType>() + std::mem::size_of::<i32>())
+                        as *const i32;
+                    lbound.read_unaligned() == 1
+                }
+                _ => false,
+            }
+        }
+    }
+}
+
+/// The sliced elements are copied into a new one-dimensional array, numbered from 1, as Postgres
+/// arrays can't point into one another, directly from the deconstructed array without an
+/// intermediate `Vec`.  Slicing every element of an array that already has that shape passes on
+/// the array's own `Datum` instead, while a whole multidimensional array is still flattened
+impl<'a, T: FromDatum + IntoDatum> IntoDatum for ArraySlice<'a, T> {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        if self.is_whole_array() {
+            return Some(self.array.ptr as pg_sys::Datum);
+        }
+
+        let elemtype = if self.array.typoid == pg_sys::InvalidOid {
+            T::type_oid()
+        } else {
+            self.array.typoid
+        };
+
+        unsafe {
+            if self.is_empty() {
+                return Some(pg_sys::construct_empty_array(elemtype) as pg_sys::Datum);
+            }
+
+            let mut typlen = 0;
+            let mut typbyval = false;
+            let mut typalign = 0;
+            pg_sys::get_typlenbyvalalign(elemtype, &mut typlen, &mut typbyval, &mut typalign);
+
+            let nulls = if self.array.nulls.is_null() {
+                std::ptr::null_mut()
+            } else {
+                self.array.nulls.add(self.start)
+            };
+            let mut dims = [self.len() as i32];
+            let mut lbs = [1];
+            Some(pg_sys::construct_md_array(
+                self.array.elements.add(self.start),
+                nulls,
+                1,
+                dims.as_mut_ptr(),
+                lbs.as_mut_ptr(),
+                elemtype,
+                typlen as i32,
+                typbyval,
+                typalign,
+            ) as pg_sys::Datum)
+        }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        unsafe { pg_sys::get_array_type(T::type_oid()) }
+    }
+}
+
+/// The `start..end` bounds of `range` over `len` elements
+fn slice_bounds<R: RangeBounds<usize>>(range: R, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(start) => *start,
+        Bound::Excluded(start) => start + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => end + 1,
+        Bound::Excluded(end) => *end,
+        Bound::Unbounded => len,
+    };
+    if start > end {
+        panic!("slice index starts at {} but ends at {}", start, end);
+    } else if end > len {
+        panic!(
+            "range end index {} out of range for array of length {}",
+            end, len
+        );
+    }
+    (start, end)
+}
+
 pub struct ArrayTypedIterator<'a, T: 'a + FromDatum> {
     array: &'a Array<'a, T>,
     curr: usize,
@@ -182,6 +426,86 @@ impl<'a, T: FromDatum> Iterator for ArrayTypedIterator<'a, T> {
     }
 }
 
+enum NullBitmap<'a> {
+    NoNulls,
+    Bits(&'a [u8]),
+    Flags(&'a [bool]),
+}
+
+impl<'a> NullBitmap<'a> {
+    #[inline]
+    fn is_null(&self, i: usize) -> bool {
+        match self {
+            NullBitmap::NoNulls => false,
+            // a set bit is a non-NULL element
+            NullBitmap::Bits(bits) => bits[i / 8] & (1 << (i % 8)) == 0,
+            NullBitmap::Flags(flags) => flags[i],
+        }
+    }
+}
+
+pub struct ArrayNonNullIterator<'a, T: 'a + FromDatum> {
+    array: &'a Array<'a, T>,
+    nulls: NullBitmap<'a>,
+    curr: usize,
+}
+
+impl<'a, T: FromDatum> Iterator for ArrayNonNullIterator<'a, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.curr < self.array.nelems {
+            let i = self.curr;
+            self.curr += 1;
+            if !self.nulls.is_null(i) {
+                return unsafe {
+                    T::from_datum(self.array.elem_slice[i], false, self.array.typoid)
+                };
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.array.nelems - self.curr))
+    }
+}
+
+pub struct ArrayValidityIterator<'a, T: 'a + FromDatum + Default> {
+    array: &'a Array<'a, T>,
+    nulls: NullBitmap<'a>,
+    curr: usize,
+}
+
+impl<'a, T: FromDatum + Default> Iterator for ArrayValidityIterator<'a, T> {
+    type Item = (bool, T);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.curr >= self.array.nelems {
+            return None;
+        }
+
+        let i = self.curr;
+        self.curr += 1;
+        if self.nulls.is_null(i) {
+            Some((false, T::default()))
+        } else {
+            let value =
+                unsafe { T::from_datum(self.array.elem_slice[i], false, self.array.typoid) };
+            Some((true, value.unwrap_or_default()))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.array.nelems - self.curr;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: FromDatum + Default> ExactSizeIterator for ArrayValidityIterator<'a, T> {}
+
 pub struct ArrayIterator<'a, T: 'a + FromDatum> {
     array: &'a Array<'a, T>,
     curr: usize,
@@ -277,7 +601,7 @@ impl<'a, T: FromDatum> Drop for Array<'a, T> {
 
 impl<'a, T: FromDatum> FromDatum for Array<'a, T> {
     #[inline]
-    unsafe fn from_datum(datum: usize, is_null: bool, typoid: u32) -> Option<Array<'a, T>> {
+    unsafe fn from_datum(datum: usize, is_null: bool, _typoid: u32) -> Option<Array<'a, T>> {
         if is_null {
             None
         } else if datum == 0 {
@@ -316,12 +640,13 @@ impl<'a, T: FromDatum> FromDatum for Array<'a, T> {
                 &mut nelems,
             );
 
+            // elements are converted with the array's element type, not the type of the array
             Some(Array::from_pg(
                 ptr,
                 array,
                 elements,
                 nulls,
-                typoid,
+                array_ref.elemtype,
                 nelems as usize,
             ))
         }
@@ -379,9 +704,10 @@ where
     T: IntoDatum,
 {
     fn into_datum(self) -> Option<pg_sys::Datum> {
+        let elem_oid = array_element_type_oid(self.iter());
         let mut state = unsafe {
             pg_sys::initArrayResult(
-                T::type_oid(),
+                elem_oid,
                 PgMemoryContexts::CurrentMemoryContext.value(),
                 false,
             )
@@ -395,7 +721,7 @@ where
                     state,
                     datum.unwrap_or(0usize),
                     isnull,
-                    T::type_oid(),
+                    elem_oid,
                     PgMemoryContexts::CurrentMemoryContext.value(),
                 );
             }
@@ -414,6 +740,10 @@ where
     fn type_oid() -> u32 {
         unsafe { pg_sys::get_array_type(T::type_oid()) }
     }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        is_array_of::<T>(other)
+    }
 }
 
 impl<'a, T> IntoDatum for &'a [T]
@@ -421,9 +751,10 @@ where
     T: IntoDatum + Copy,
 {
     fn into_datum(self) -> Option<pg_sys::Datum> {
+        let elem_oid = array_element_type_oid(self.iter());
         let mut state = unsafe {
             pg_sys::initArrayResult(
-                T::type_oid(),
+                elem_oid,
                 PgMemoryContexts::CurrentMemoryContext.value(),
                 false,
             )
@@ -437,7 +768,7 @@ where
                     state,
                     datum.unwrap_or(0usize),
                     isnull,
-                    T::type_oid(),
+                    elem_oid,
                     PgMemoryContexts::CurrentMemoryContext.value(),
                 );
             }
@@ -456,4 +787,30 @@ where
     fn type_oid() -> u32 {
         unsafe { pg_sys::get_array_type(T::type_oid()) }
     }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        is_array_of::<T>(other)
+    }
+}
+
+/// The element type of an array of `elements`, which is the composite type of its composite
+/// elements, as those only know theirs at runtime, and otherwise `T::type_oid()`
+fn array_element_type_oid<'a, T: IntoDatum + 'a>(elements: impl Iterator<Item = &'a T>) -> u32 {
+    let mut elem_oid = None;
+    for oid in elements.filter_map(|element| element.composite_type_oid()) {
+        match elem_oid {
+            None => elem_oid = Some(oid),
+            Some(elem_oid) if elem_oid != oid => {
+                panic!("array elements must all be of the same composite type")
+            }
+            Some(_) => {}
+        }
+    }
+    elem_oid.unwrap_or_else(T::type_oid)
+}
+
+/// Is `oid` an array type whose elements `T` can be converted from?
+fn is_array_of<T: IntoDatum>(oid: pg_sys::Oid) -> bool {
+    let elemtype = unsafe { pg_sys::get_element_type(oid) };
+    elemtype != pg_sys::InvalidOid && T::is_compatible_with(elemtype)
 }
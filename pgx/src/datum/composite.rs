@@ -0,0 +1,348 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{pg_sys, FromDatum, IntoDatum, PgTupleDesc, TypeConversionError};
+
+/// A value of a composite (row) type, such as a table's row type or one made with
+/// `CREATE TYPE ... AS (...)`, which maps to SQL's `record`.
+///
+/// Its attributes are read with [`PgComposite::get()`], which works for any `FromDatum` type,
+/// including another `PgComposite` or a `Vec<PgComposite>`, so nested row types and arrays of
+/// them can be read without deconstructing them by hand.  A `Vec<PgComposite>` becomes an array
+/// of the composite type of its elements.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// // CREATE TYPE member AS (name text, age int);
+/// // CREATE TYPE team AS (name text, members member[]);
+/// #[pg_extern]
+/// fn oldest_member(team: PgComposite) -> Option<String> {
+///     let members = team.get_by_name::<Vec<PgComposite>>("members")?;
+///     members
+///         .iter()
+///         .max_by_key(|member| member.get::<i32>(1))
+///         .and_then(|member| member.get::<String>(0))
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PgComposite {
+    header: pg_sys::HeapTupleHeader,
+}
+
+impl PgComposite {
+    /// Make a value of the composite type `typoid` from the `Datum` of each of its attributes, in
+    /// order, where `None` is `NULL`.  An attribute that's itself a composite, or an array of
+    /// them, can be given as the `.into_datum()` of a `PgComposite` or `Vec<PgComposite>`.
+    ///
+    /// The value is allocated in the `CurrentMemoryContext`.
+    ///
+    /// ## Panics
+    ///
+    /// If `typoid` isn't a composite type, or `values` doesn't have a value for every attribute
+    pub fn from_values(typoid: pg_sys::Oid, values: Vec<Option<pg_sys::Datum>>) -> Self {
+        let tupdesc = unsafe { PgTupleDesc::from_pg(pg_sys::lookup_rowtype_tupdesc(typoid, -1)) };
+        if values.len() != tupdesc.len() {
+            panic!(
+                "composite type {} has {} attributes, but {} values were given",
+                typoid,
+                tupdesc.len(),
+                values.len()
+            );
+        }
+
+        PgComposite::form(&tupdesc, &values)
+    }
+
+    /// Make a row described by `tupdesc` from the `Datum` of each of its attributes, in order,
+    /// where `None` is `NULL`, such as one of many rows of the same anonymous row type, whose
+    /// attributes aren't known until the function is called:
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// // SELECT * FROM pivot(ARRAY['a', 'b']) AS t(a int, b int)
+    /// #[pg_extern]
+    /// fn pivot(keys: Vec<String>, context: FcInfoContext) -> impl Iterator<Item = PgComposite> {
+    ///     let tupdesc = context.result_tupdesc().unwrap_or_else(|| {
+    ///         let columns = keys.iter().map(|key| (key.as_str(), pg_sys::INT4OID)).collect::<Vec<_>>();
+    ///         PgTupleDesc::from_columns(&columns)
+    ///     });
+    ///     (0..3).map(move |row| {
+    ///         let values = (0..tupdesc.len()).map(|col| (row * col as i32).into_datum()).collect();
+    ///         PgComposite::from_tupdesc(&tupdesc, values)
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// The value is allocated in the `CurrentMemoryContext`.
+    ///
+    /// ## Panics
+    ///
+    /// If `values` doesn't have a value for every attribute
+    pub fn from_tupdesc(tupdesc: &PgTupleDesc, values: Vec<Option<pg_sys::Datum>>) -> Self {
+        if values.len() != tupdesc.len() {
+            panic!(
+                "row type has {} attributes, but {} values were given",
+                tupdesc.len(),
+                values.len()
+            );
+        }
+        // an anonymous row type has to be registered for its values to say which it is
+        if tupdesc.tdtypeid == pg_sys::RECORDOID && tupdesc.tdtypmod < 0 {
+            unsafe { pg_sys::BlessTupleDesc(tupdesc.as_ptr()) };
+        }
+
+        PgComposite::form(tupdesc, &values)
+    }
+
+    fn form(tupdesc: &PgTupleDesc, values: &[Option<pg_sys::Datum>]) -> Self {
+        let mut nulls = values.iter().map(|v| v.is_none()).collect::<Vec<_>>();
+        let mut datums = values.iter().map(|v| v.unwrap_or(0)).collect::<Vec<_>>();
+        unsafe {
+            let tuple =
+                pg_sys::heap_form_tuple(tupdesc.as_ptr(), datums.as_mut_ptr(), nulls.as_mut_ptr());
+            PgComposite {
+                header: pg_sys::HeapTupleHeaderGetDatum((*tuple).t_data) as pg_sys::HeapTupleHeader,
+            }
+        }
+    }
+
+    /// The composite type of this value, or `pg_sys::RECORDOID` for an anonymous row
+    pub fn oid(&self) -> pg_sys::Oid {
+        unsafe { crate::heap_tuple_header_get_type_id(self.header) }
+    }
+
+    /// The typmod of this value, which identifies the row type of an anonymous row
+    pub fn typmod(&self) -> i32 {
+        unsafe { crate::heap_tuple_header_get_typmod(self.header) }
+    }
+
+    /// The `PgTupleDesc` of this value's type, backed by this value, so its attributes can also be
+    /// read with [`PgTupleDesc::get_attr()`]
+    pub fn tupdesc(&self) -> PgTupleDesc<'static> {
+        unsafe { PgTupleDesc::from_composite_header(self.header) }
+    }
+
+    /// How many attributes does this value have?
+    pub fn len(&self) -> usize {
+        self.tupdesc().len()
+    }
+
+    /// Does this value have any attributes?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get an attribute, which is `None` if it's `NULL`.  The `attno` argument is zero-based
+    ///
+    /// ## Panics
+    ///
+    /// If `attno` is past the last attribute
+    pub fn get<T: FromDatum>(&self, attno: usize) -> Option<T> {
+        let tupdesc = self.tupdesc();
+        if attno >= tupdesc.len() {
+            panic!(
+                "attribute {} is out of bounds for a composite with {} attributes",
+                attno,
+                tupdesc.len()
+            );
+        }
+        tupdesc.get_attr(attno)
+    }
+
+    /// Like `get()`, but returns a [`TypeConversionError`] naming the attribute if it isn't of a
+    /// type `T` can be converted from
+    ///
+    /// ## Panics
+    ///
+    /// If `attno` is past the last attribute
+    pub fn try_get<T: FromDatum + IntoDatum>(
+        &self,
+        attno: usize,
+    ) -> Result<Option<T>, TypeConversionError> {
+        let tupdesc = self.tupdesc();
+        if attno >= tupdesc.len() {
+            panic!(
+                "attribute {} is out of bounds for a composite with {} attributes",
+                attno,
+                tupdesc.len()
+            );
+        }
+        tupdesc.try_get_attr(attno)
+    }
+
+    /// Get the attribute called `name`, which is `None` if it's `NULL`
+    ///
+    /// ## Panics
+    ///
+    /// If this value's type has no attribute called `name`
+    pub fn get_by_name<T: FromDatum>(&self, name: &str) -> Option<T> {
+        self.get(self.attno(name))
+    }
+
+    /// Like `get_by_name()`, but returns a [`TypeConversionError`] naming the attribute if it isn't
+    /// of a type `T` can be converted from
+    ///
+    /// ## Panics
+    ///
+    /// If this value's type has no attribute called `name`
+    pub fn try_get_by_name<T: FromDatum + IntoDatum>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>, TypeConversionError> {
+        self.try_get(self.attno(name))
+    }
+
+    /// The zero-based number of the attribute called `name`
+    fn attno(&self, name: &str) -> usize {
+        self.tupdesc()
+            .iter()
+            .position(|att| !att.is_dropped() && att.name() == name)
+            .unwrap_or_else(|| panic!("composite type has no attribute named `{}`", name))
+    }
+}
+
+/// Builds a [`PgComposite`] from Rust values, one attribute at a time, without going through SPI,
+/// such as to return a row type from a function, or to put rows into an array.
+///
+/// Each value is converted to its attribute's type with [`IntoDatum::into_datum_as()`], so an
+/// `i64` can fill in a `numeric`, and an attribute with no value is `NULL`.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// // CREATE TYPE member AS (name text, age int);
+/// // CREATE TYPE team AS (name text, members member[]);
+/// #[pg_extern]
+/// fn make_team(name: &str, member_names: Vec<String>) -> PgComposite {
+///     let members = member_names
+///         .into_iter()
+///         .map(|member| {
+///             PgCompositeBuilder::new(regtypein("member"))
+///                 .set_by_name("name", member)
+///                 .build()
+///         })
+///         .collect::<Vec<_>>();
+///
+///     PgCompositeBuilder::new(regtypein("team"))
+///         .set_attr(0, name)
+///         .set_attr(1, members)
+///         .build()
+/// }
+/// ```
+pub struct PgCompositeBuilder<'a> {
+    tupdesc: PgTupleDesc<'a>,
+    values: Vec<Option<pg_sys::Datum>>,
+}
+
+impl PgCompositeBuilder<'static> {
+    /// Build a value of the composite type `typoid`
+    ///
+    /// ## Panics
+    ///
+    /// If `typoid` isn't a composite type
+    pub fn new(typoid: pg_sys::Oid) -> Self {
+        PgCompositeBuilder::from_tupdesc(unsafe {
+            PgTupleDesc::from_pg(pg_sys::lookup_rowtype_tupdesc(typoid, -1))
+        })
+    }
+}
+
+impl<'a> PgCompositeBuilder<'a> {
+    /// Build a row described by `tupdesc`, such as the result type of a function that returns
+    /// `record`, from `pg_sys::get_call_result_type()`
+    pub fn from_tupdesc(tupdesc: PgTupleDesc<'a>) -> Self {
+        // an anonymous row type has to be registered for its values to say which it is
+        if tupdesc.tdtypeid == pg_sys::RECORDOID && tupdesc.tdtypmod < 0 {
+            unsafe { pg_sys::BlessTupleDesc(tupdesc.as_ptr()) };
+        }
+        let values = vec![None; tupdesc.len()];
+        PgCompositeBuilder { tupdesc, values }
+    }
+
+    /// Set the attribute `attno`, which is zero-based, to `value`, where `None` is `NULL`
+    ///
+    /// ## Panics
+    ///
+    /// If `attno` is past the last attribute, or is dropped, or if `value` can't be converted to
+    /// the attribute's type
+    pub fn set_attr<T: IntoDatum>(mut self, attno: usize, value: T) -> Self {
+        let att = self.tupdesc.get(attno).unwrap_or_else(|| {
+            panic!(
+                "attribute {} is out of bounds for a composite with {} attributes",
+                attno,
+                self.tupdesc.len()
+            )
+        });
+        if att.is_dropped() {
+            panic!("attribute {} of the composite type is dropped", attno);
+        }
+        self.values[attno] = value.into_datum_as(att.atttypid);
+        self
+    }
+
+    /// Set the attribute called `name` to `value`, where `None` is `NULL`
+    ///
+    /// ## Panics
+    ///
+    /// If there's no attribute called `name`, or if `value` can't be converted to its type
+    pub fn set_by_name<T: IntoDatum>(self, name: &str, value: T) -> Self {
+        let attno = self
+            .tupdesc
+            .iter()
+            .position(|att| !att.is_dropped() && att.name() == name)
+            .unwrap_or_else(|| panic!("composite type has no attribute named `{}`", name));
+        self.set_attr(attno, value)
+    }
+
+    /// Make the value, allocated in the `CurrentMemoryContext`
+    pub fn build(self) -> PgComposite {
+        PgComposite::form(&self.tupdesc, &self.values)
+    }
+}
+
+impl FromDatum for PgComposite {
+    const NEEDS_TYPID: bool = false;
+
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("composite was flagged not null but datum is zero");
+        } else {
+            // copied, as an element of an array or an attribute of another composite points into
+            // a detoasted copy of that, which may be freed before this is used
+            Some(PgComposite {
+                header: pg_sys::pg_detoast_datum_copy(datum as *mut pg_sys::varlena)
+                    as pg_sys::HeapTupleHeader,
+            })
+        }
+    }
+}
+
+impl IntoDatum for PgComposite {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.header as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::RECORDOID
+    }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        unsafe { pg_sys::type_is_rowtype(other) }
+    }
+
+    fn composite_type_oid(&self) -> Option<pg_sys::Oid> {
+        Some(self.oid())
+    }
+}
@@ -10,11 +10,60 @@ Use of this source code is governed by the MIT license that can be found in the
 //! for converting a pg_sys::Datum and a corresponding "is_null" bool into a typed Option
 
 use crate::{
-    pg_sys, text_to_rust_str_unchecked, varlena_to_byte_slice, AllocatedByPostgres, PgBox,
-    PgMemoryContexts,
+    pg_sys, text_to_rust_str_unchecked, varlena_to_byte_slice, AllocatedByPostgres, IntoDatum,
+    PgBox, PgMemoryContexts, RegType,
 };
 use std::ffi::CStr;
 
+/// The error from [`FromDatum::try_from_datum()`] when a `Datum` isn't of a type the Rust type can
+/// be converted from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeConversionError {
+    /// The type of the `Datum`
+    pub datum_type: pg_sys::Oid,
+    /// The type the Rust type is converted from, its `IntoDatum::type_oid()`
+    pub expected_type: pg_sys::Oid,
+    /// The name of the Rust type
+    pub rust_type: &'static str,
+    /// The column or attribute the `Datum` came from, when that's known
+    pub column: Option<String>,
+}
+
+impl TypeConversionError {
+    pub fn new<T>(datum_type: pg_sys::Oid, expected_type: pg_sys::Oid) -> Self {
+        TypeConversionError {
+            datum_type,
+            expected_type,
+            rust_type: std::any::type_name::<T>(),
+            column: None,
+        }
+    }
+
+    /// Name the column the `Datum` came from, for the error's message
+    pub fn set_column(mut self, column: &str) -> Self {
+        self.column = Some(column.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for TypeConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.column {
+            Some(column) => write!(f, "column \"{}\" is ", column)?,
+            None => write!(f, "value is ")?,
+        }
+        write!(
+            f,
+            "{}, expected {} for {}",
+            RegType(self.datum_type).name(),
+            RegType(self.expected_type).name(),
+            self.rust_type
+        )
+    }
+}
+
+impl std::error::Error for TypeConversionError {}
+
 /// Convert a `(pg_sys::Datum, is_null:bool, type_oid:pg_sys::Oid)` tuple into a Rust type
 ///
 /// Default implementations are provided for the common Rust types.
@@ -62,6 +111,36 @@ pub trait FromDatum {
     {
         memory_context.switch_to(|_| FromDatum::from_datum(datum, is_null, typoid))
     }
+
+    /// Like `from_datum()`, but first checks that `typoid` is a type this can be converted from, as
+    /// decided by [`IntoDatum::is_compatible_with()`], returning a [`TypeConversionError`] naming
+    /// both types if it isn't, rather than misreading the `Datum`.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let datum = 42i64.into_datum().unwrap();
+    /// let error = unsafe { String::try_from_datum(datum, false, pg_sys::INT8OID) }.unwrap_err();
+    /// assert_eq!(error.to_string(), "value is bigint, expected text for alloc::string::String");
+    /// ```
+    ///
+    /// ## Safety
+    ///
+    /// Same caveats as `From::from_datum(...)`
+    unsafe fn try_from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Result<Option<Self>, TypeConversionError>
+    where
+        Self: Sized + IntoDatum,
+    {
+        if Self::is_compatible_with(typoid) {
+            Ok(FromDatum::from_datum(datum, is_null, typoid))
+        } else {
+            Err(TypeConversionError::new::<Self>(typoid, Self::type_oid()))
+        }
+    }
 }
 
 /// for pg_sys::Datum
@@ -276,6 +355,19 @@ impl<'a> FromDatum for &'a std::ffi::CStr {
     }
 }
 
+/// for cstring, copied into Rust's memory
+impl FromDatum for std::ffi::CString {
+    const NEEDS_TYPID: bool = false;
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<std::ffi::CString> {
+        <&CStr>::from_datum(datum, is_null, typoid).map(|cstr| cstr.to_owned())
+    }
+}
+
 impl<'a> FromDatum for &'a crate::cstr_core::CStr {
     const NEEDS_TYPID: bool = false;
     #[inline]
@@ -0,0 +1,56 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::datum::date::{DATEVAL_NOBEGIN, DATEVAL_NOEND};
+use crate::datum::time_stamp::{DT_NOBEGIN, DT_NOEND};
+use crate::{pg_sys, Date, FromDatum, IntoDatum, MaybeInfinite, Timestamp, TimestampWithTimeZone};
+
+macro_rules! maybe_infinite {
+    ($ty:ty, $raw:ty, $nobegin:expr, $noend:expr) => {
+        impl FromDatum for MaybeInfinite<$ty> {
+            const NEEDS_TYPID: bool = <$ty as FromDatum>::NEEDS_TYPID;
+
+            #[inline]
+            unsafe fn from_datum(
+                datum: pg_sys::Datum,
+                is_null: bool,
+                typoid: pg_sys::Oid,
+            ) -> Option<Self> {
+                if is_null {
+                    None
+                } else if datum as $raw == $nobegin {
+                    Some(MaybeInfinite::NegativeInfinity)
+                } else if datum as $raw == $noend {
+                    Some(MaybeInfinite::Infinity)
+                } else {
+                    <$ty>::from_datum(datum, false, typoid).map(MaybeInfinite::Finite)
+                }
+            }
+        }
+
+        impl IntoDatum for MaybeInfinite<$ty> {
+            #[inline]
+            fn into_datum(self) -> Option<pg_sys::Datum> {
+                match self {
+                    MaybeInfinite::NegativeInfinity => Some($nobegin as pg_sys::Datum),
+                    MaybeInfinite::Finite(value) => value.into_datum(),
+                    MaybeInfinite::Infinity => Some($noend as pg_sys::Datum),
+                }
+            }
+
+            fn type_oid() -> pg_sys::Oid {
+                <$ty>::type_oid()
+            }
+        }
+    };
+}
+
+maybe_infinite!(Date, i32, DATEVAL_NOBEGIN, DATEVAL_NOEND);
+maybe_infinite!(Timestamp, i64, DT_NOBEGIN, DT_NOEND);
+maybe_infinite!(TimestampWithTimeZone, i64, DT_NOBEGIN, DT_NOEND);
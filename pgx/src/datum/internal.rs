@@ -8,6 +8,10 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use crate::{pg_sys, FromDatum, IntoDatum, PgMemoryContexts};
+use std::any::{type_name, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem::ManuallyDrop;
 
 /// Represents Postgres' `internal` data type, which is documented as:
 ///
@@ -23,22 +27,117 @@ use crate::{pg_sys, FromDatum, IntoDatum, PgMemoryContexts};
 /// [Internal] is a wrapper around an `Option<pg_sys::Datum>`, which when retreived via
 /// `::get/get_mut()` is cast to a pointer of `T`, returning the respective reference.
 ///
+/// The backend remembers the Rust type of each value an [`Internal`] allocates, by its address,
+/// until the value is dropped or taken, so [`Internal::get()`], [`Internal::get_mut()`], and
+/// [`Internal::take()`] can check a value is really a `T`, even after it's passed through Postgres as
+/// a datum, like an aggregate's state or a set-returning function's.  They panic when it isn't.
+///
+/// A value an [`Internal`] allocates is dropped when the memory context it was allocated in is
+/// reset or deleted, even when that's because the transaction aborted, so it can own resources
+/// like file handles or mmaps.  For an aggregate's state, that's its aggregate context.
+///
 /// ## Safety
 ///
-/// We make no guarantees about what the internal [pg_sys::Datum] actually points to in memory, so
-/// it is your responsibility to ensure that what you're casting it to is really what it is.
+/// We make no guarantees about what an internal [pg_sys::Datum] from Postgres itself, like a
+/// `PlannerInfo *`, actually points to in memory, so it is your responsibility to ensure that what
+/// you're casting it to with [`Internal::get_unchecked()`] is really what it is.
 #[derive(Default)]
 pub struct Internal(Option<pg_sys::Datum>);
 
+/// The Rust type of a value allocated for an [`Internal`]
+#[derive(Debug, Clone, Copy)]
+struct InternalType {
+    id: TypeId,
+    name: &'static str,
+}
+
+impl InternalType {
+    fn of<T: 'static>() -> Self {
+        Self {
+            id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+        }
+    }
+}
+
+thread_local! {
+    /// The types of the values allocated for [`Internal`]s which haven't been dropped or taken, by
+    /// their address
+    static INTERNAL_TYPES: RefCell<HashMap<usize, InternalType>> = RefCell::new(HashMap::new());
+}
+
+/// A value allocated for an [`Internal`], at the same address as the value itself, which is never
+/// zero-sized so that no two share an address
+#[repr(C)]
+struct Tracked<T> {
+    value: ManuallyDrop<T>,
+    type_id: TypeId,
+}
+
+impl<T> Drop for Tracked<T> {
+    fn drop(&mut self) {
+        let forgotten = INTERNAL_TYPES
+            .try_with(|types| types.borrow_mut().remove(&(self as *mut Self as usize)))
+            .ok()
+            .flatten();
+        // it's no longer remembered if it was taken
+        if matches!(forgotten, Some(ty) if ty.id == self.type_id) {
+            unsafe { ManuallyDrop::drop(&mut self.value) }
+        }
+    }
+}
+
+/// Allocate `value` in `memory_context`, remembering its type
+fn track<T: 'static>(memory_context: &mut PgMemoryContexts, value: T) -> pg_sys::Datum {
+    let ptr = memory_context.leak_and_drop_on_delete(Tracked {
+        value: ManuallyDrop::new(value),
+        type_id: TypeId::of::<T>(),
+    });
+    INTERNAL_TYPES.with(|types| {
+        types
+            .borrow_mut()
+            .insert(ptr as usize, InternalType::of::<T>())
+    });
+    ptr as pg_sys::Datum
+}
+
+/// The type of the value at `datum`, if an [`Internal`] allocated it
+fn tracked_type(datum: pg_sys::Datum) -> Option<InternalType> {
+    INTERNAL_TYPES.with(|types| types.borrow().get(&datum).copied())
+}
+
+/// Panic unless the value at `datum` is a `T` an [`Internal`] allocated
+fn check_type<T: 'static>(datum: pg_sys::Datum) {
+    match tracked_type(datum) {
+        Some(ty) if ty.id == TypeId::of::<T>() => (),
+        Some(ty) => panic!(
+            "`Internal` holds a `{}`, not a `{}`",
+            ty.name,
+            type_name::<T>()
+        ),
+        None => panic!(
+            "`Internal` doesn't hold a value it allocated, which could be checked to be a `{}`",
+            type_name::<T>()
+        ),
+    }
+}
+
 impl Internal {
     /// Construct a new Internal from any type.  
     ///
     /// The value will be dropped when the [PgMemoryContexts::CurrentMemoryContext] is deleted.
     #[inline(always)]
-    pub fn new<T>(t: T) -> Self {
-        Self(Some(
-            PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(t) as pg_sys::Datum,
-        ))
+    pub fn new<T: 'static>(t: T) -> Self {
+        Self::new_in(&mut PgMemoryContexts::CurrentMemoryContext, t)
+    }
+
+    /// Construct a new Internal from any type, in `memory_context`.
+    ///
+    /// The value will be dropped when `memory_context` is reset or deleted.  An aggregate's state
+    /// belongs in its aggregate context, see [`Aggregate::aggregate_context`](crate::Aggregate::aggregate_context).
+    #[inline(always)]
+    pub fn new_in<T: 'static>(memory_context: &mut PgMemoryContexts, t: T) -> Self {
+        Self(Some(track(memory_context, t)))
     }
 
     /// Returns true if the internal value is initialized. If false, this is a null pointer.
@@ -47,6 +146,21 @@ impl Internal {
         self.0.is_some()
     }
 
+    /// Return a reference to the `T` this [`Internal`] holds, as `Some(&T)`, unless the backing
+    /// datum is null, then `None`.
+    ///
+    /// ## Panics
+    ///
+    /// If it holds something other than a `T` allocated by an [`Internal`], including the
+    /// structures Postgres itself passes as `internal`, which need [`Internal::get_unchecked()`].
+    #[inline(always)]
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.0.map(|datum| {
+            check_type::<T>(datum);
+            unsafe { &*(datum as *const T) }
+        })
+    }
+
     /// Return a reference to the memory pointed to by this [`Internal`], as `Some(&T)`, unless the
     /// backing datum is null, then `None`.
     ///
@@ -55,26 +169,47 @@ impl Internal {
     /// We cannot guarantee that the contained datum points to memory that is really `T`.  This is
     /// your responsibility.
     #[inline(always)]
-    pub unsafe fn get<T>(&self) -> Option<&T> {
+    pub unsafe fn get_unchecked<T>(&self) -> Option<&T> {
         self.0.and_then(|datum| (datum as *const T).as_ref())
     }
 
     /// Initializes the internal with `value`, then returns a mutable reference to it.
     ///
-    /// If the Internal is already initialized with a value, the old value is dropped.
+    /// If the Internal is already initialized with a value, the old value is dropped when its
+    /// memory context is.
     ///
     /// See also [`Internal::get_or_insert`], which doesn’t update the value if already initialized.
+    #[inline(always)]
+    pub fn insert<T: 'static>(&mut self, value: T) -> &mut T {
+        self.insert_in(&mut PgMemoryContexts::CurrentMemoryContext, value)
+    }
+
+    /// Like [`Internal::insert`], but `value` is allocated in `memory_context`, and dropped when it's
+    /// reset or deleted.
+    #[inline(always)]
+    pub fn insert_in<T: 'static>(
+        &mut self,
+        memory_context: &mut PgMemoryContexts,
+        value: T,
+    ) -> &mut T {
+        let ptr = self.0.insert(track(memory_context, value));
+        unsafe { &mut *(*ptr as *mut T) }
+    }
+
+    /// Return a mutable reference to the `T` this [`Internal`] holds, as `Some(&mut T)`, unless the
+    /// backing datum is null, then `None`.
     ///
-    /// ## Safety
+    /// ## Panics
     ///
-    /// We cannot guarantee that the contained datum points to memory that is really `T`.  This is
-    /// your responsibility.
+    /// If it holds something other than a `T` allocated by an [`Internal`], including the
+    /// structures Postgres itself passes as `internal`, which need
+    /// [`Internal::get_mut_unchecked()`].
     #[inline(always)]
-    pub unsafe fn insert<T>(&mut self, value: T) -> &mut T {
-        let datum =
-            PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(value) as pg_sys::Datum;
-        let ptr = self.0.insert(datum);
-        &mut *(*ptr as *mut T)
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.0.map(|datum| {
+            check_type::<T>(datum);
+            unsafe { &mut *(datum as *mut T) }
+        })
     }
 
     /// Return a reference to the memory pointed to by this [`Internal`], as `Some(&mut T)`, unless the
@@ -85,10 +220,27 @@ impl Internal {
     /// We cannot guarantee that the contained datum points to memory that is really `T`.  This is
     /// your responsibility.
     #[inline(always)]
-    pub unsafe fn get_mut<T>(&self) -> Option<&mut T> {
+    pub unsafe fn get_mut_unchecked<T>(&self) -> Option<&mut T> {
         self.0.and_then(|datum| (datum as *mut T).as_mut())
     }
 
+    /// Move the `T` this [`Internal`] holds out of it, as `Some(T)`, leaving it uninitialized, unless
+    /// the backing datum is null, then `None`.
+    ///
+    /// Other copies of the datum, like the one Postgres passed, no longer hold a value, which makes
+    /// [`Internal::get()`] of them panic.
+    ///
+    /// ## Panics
+    ///
+    /// If it holds something other than a `T` allocated by an [`Internal`].
+    pub fn take<T: 'static>(&mut self) -> Option<T> {
+        self.0.take().map(|datum| {
+            check_type::<T>(datum);
+            INTERNAL_TYPES.with(|types| types.borrow_mut().remove(&datum));
+            unsafe { ManuallyDrop::take(&mut (*(datum as *mut Tracked<T>)).value) }
+        })
+    }
+
     /// Initializes the internal with `value` if it is not initialized, then returns a mutable reference to
     /// the contained value.
     ///
@@ -96,10 +248,11 @@ impl Internal {
     ///
     /// ## Safety
     ///
-    /// We cannot guarantee that the contained datum points to memory that is really `T`.  This is
-    /// your responsibility.
+    /// We cannot guarantee that the contained datum points to memory that is really `T`, when it's
+    /// one Postgres allocated rather than an [`Internal`].  This is your responsibility.  Otherwise,
+    /// it panics if it isn't a `T`.
     #[inline(always)]
-    pub unsafe fn get_or_insert<T>(&mut self, value: T) -> &mut T {
+    pub unsafe fn get_or_insert<T: 'static>(&mut self, value: T) -> &mut T {
         self.get_or_insert_with(|| value)
     }
 
@@ -110,12 +263,13 @@ impl Internal {
     ///
     /// ## Safety
     ///
-    /// We cannot guarantee that the contained datum points to memory that is really `T`.  This is
-    /// your responsibility.
+    /// We cannot guarantee that the contained datum points to memory that is really `T`, when it's
+    /// one Postgres allocated rather than an [`Internal`].  This is your responsibility.  Otherwise,
+    /// it panics if it isn't a `T`.
     #[inline(always)]
     pub unsafe fn get_or_insert_default<T>(&mut self) -> &mut T
     where
-        T: Default,
+        T: Default + 'static,
     {
         self.get_or_insert_with(|| T::default())
     }
@@ -125,20 +279,42 @@ impl Internal {
     ///
     /// ## Safety
     ///
-    /// We cannot guarantee that the contained datum points to memory that is really `T`.  This is
-    /// your responsibility.
+    /// We cannot guarantee that the contained datum points to memory that is really `T`, when it's
+    /// one Postgres allocated rather than an [`Internal`].  This is your responsibility.  Otherwise,
+    /// it panics if it isn't a `T`.
     #[inline(always)]
     pub unsafe fn get_or_insert_with<F, T>(&mut self, f: F) -> &mut T
     where
         F: FnOnce() -> T,
+        T: 'static,
+    {
+        self.get_or_insert_with_in(&mut PgMemoryContexts::CurrentMemoryContext, f)
+    }
+
+    /// Like [`Internal::get_or_insert_with`], but the value from `f` is allocated in
+    /// `memory_context`, and dropped when it's reset or deleted.
+    ///
+    /// ## Safety
+    ///
+    /// We cannot guarantee that the contained datum points to memory that is really `T`, when it's
+    /// one Postgres allocated rather than an [`Internal`].  This is your responsibility.  Otherwise,
+    /// it panics if it isn't a `T`.
+    #[inline(always)]
+    pub unsafe fn get_or_insert_with_in<F, T>(
+        &mut self,
+        memory_context: &mut PgMemoryContexts,
+        f: F,
+    ) -> &mut T
+    where
+        F: FnOnce() -> T,
+        T: 'static,
     {
-        let ptr = self.0.get_or_insert_with(|| {
-            let result = f();
-            let datum = PgMemoryContexts::CurrentMemoryContext.leak_and_drop_on_delete(result)
-                as pg_sys::Datum;
-            datum
-        });
-        &mut *(*ptr as *mut T)
+        match self.0 {
+            Some(datum) if tracked_type(datum).is_some() => check_type::<T>(datum),
+            Some(_) => (),
+            None => self.0 = Some(track(memory_context, f())),
+        }
+        &mut *(self.0.unwrap() as *mut T)
     }
 
     /// Returns the contained `Option<pg_sys::Datum>`
@@ -0,0 +1,41 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{pg_sys, FromDatum, Interval, IntoDatum, PgBox};
+
+impl FromDatum for Interval {
+    const NEEDS_TYPID: bool = false;
+
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("interval was flagged not null but datum is zero");
+        } else {
+            let interval = &*(datum as *const pg_sys::Interval);
+            Some(Interval::new(interval.month, interval.day, interval.time))
+        }
+    }
+}
+
+impl IntoDatum for Interval {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let mut interval = PgBox::<pg_sys::Interval>::alloc();
+        interval.month = self.months();
+        interval.day = self.days();
+        interval.time = self.micros();
+        Some(interval.into_pg() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::INTERVALOID
+    }
+}
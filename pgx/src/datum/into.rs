@@ -32,6 +32,70 @@ pub trait IntoDatum {
     fn array_type_oid() -> pg_sys::Oid {
         unsafe { pg_sys::get_array_type(Self::type_oid()) }
     }
+
+    /// Can a `Datum` of type `other` be converted into this type by `FromDatum`?  By default that's
+    /// when it's this type, or binary compatible with it, such as `varchar` for `text`, the base
+    /// type of a domain, or a `reg*` type for `oid`.  Types that can be converted from several
+    /// types, such as pseudo-types, override it
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        other == Self::type_oid() || crate::is_binary_coercible(other, Self::type_oid())
+    }
+
+    /// The composite type of this value, for types like [`PgComposite`](crate::PgComposite) whose
+    /// [`IntoDatum::type_oid()`] is only `record` and that only know their actual type at runtime
+    fn composite_type_oid(&self) -> Option<pg_sys::Oid> {
+        None
+    }
+
+    /// Convert into a `Datum` of the type `target`, rather than of this type, such as for filling
+    /// in a tuple whose `TupleDesc` came from elsewhere, as foreign data wrappers and
+    /// `DestReceiver`s do.
+    ///
+    /// When `target` isn't this type, or binary compatible with it, the value is coerced the way
+    /// `INSERT` would coerce it into a column of that type, such as `i64` to `numeric` or `String`
+    /// to `name`, including checking a domain's constraints.  See [`coerce_datum()`](crate::coerce_datum).
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let numeric = 42i64.into_datum_as(pg_sys::NUMERICOID);
+    /// let name = String::from("pgx").into_datum_as(pg_sys::NAMEOID);
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// If there's no assignment coercion from this type to `target`
+    fn into_datum_as(self, target: pg_sys::Oid) -> Option<pg_sys::Datum>
+    where
+        Self: Sized,
+    {
+        let source = self.composite_type_oid().unwrap_or_else(Self::type_oid);
+        let datum = self.into_datum();
+        if source == target || crate::is_binary_coercible(source, target) {
+            return datum;
+        }
+
+        let (value, is_null) = crate::coerce_datum(
+            datum.unwrap_or(0),
+            datum.is_none(),
+            source,
+            target,
+            -1,
+            crate::PgCoercionContext::Assignment,
+        )
+        .unwrap_or_else(|| {
+            panic!(
+                "cannot convert a value of type {} to type {}",
+                crate::RegType(source).name(),
+                crate::RegType(target).name()
+            )
+        });
+        if is_null {
+            None
+        } else {
+            Some(value)
+        }
+    }
 }
 
 /// for supporting NULL as the None value of an Option<T>
@@ -49,6 +113,14 @@ where
     fn type_oid() -> u32 {
         T::type_oid()
     }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        T::is_compatible_with(other)
+    }
+
+    fn composite_type_oid(&self) -> Option<pg_sys::Oid> {
+        self.as_ref().and_then(|t| t.composite_type_oid())
+    }
 }
 
 /// for bool
@@ -175,6 +247,10 @@ impl<'a> IntoDatum for &'a str {
     fn type_oid() -> u32 {
         pg_sys::TEXTOID
     }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        is_text_type(other)
+    }
 }
 
 impl IntoDatum for String {
@@ -186,6 +262,10 @@ impl IntoDatum for String {
     fn type_oid() -> u32 {
         pg_sys::TEXTOID
     }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        is_text_type(other)
+    }
 }
 
 impl IntoDatum for &String {
@@ -197,6 +277,10 @@ impl IntoDatum for &String {
     fn type_oid() -> u32 {
         pg_sys::TEXTOID
     }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        is_text_type(other)
+    }
 }
 
 impl IntoDatum for char {
@@ -226,6 +310,18 @@ impl<'a> IntoDatum for &'a std::ffi::CStr {
     }
 }
 
+/// for cstring, copied into the `CurrentMemoryContext`
+impl IntoDatum for std::ffi::CString {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(unsafe { pg_sys::pstrdup(self.as_ptr()) } as pg_sys::Datum)
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::CSTRINGOID
+    }
+}
+
 impl<'a> IntoDatum for &'a crate::cstr_core::CStr {
     #[inline]
     fn into_datum(self) -> Option<pg_sys::Datum> {
@@ -302,4 +398,17 @@ impl IntoDatum for pg_sys::Datum {
     fn type_oid() -> pg_sys::Oid {
         pg_sys::INT8OID
     }
+
+    /// a `pg_sys::Datum` is any type
+    fn is_compatible_with(_: pg_sys::Oid) -> bool {
+        true
+    }
+}
+
+/// `&str` and `String` also read `bpchar`, whose cast to `text` trims trailing spaces, and so isn't
+/// binary compatible with it
+fn is_text_type(oid: pg_sys::Oid) -> bool {
+    oid == pg_sys::TEXTOID
+        || oid == pg_sys::BPCHAROID
+        || crate::is_binary_coercible(oid, pg_sys::TEXTOID)
 }
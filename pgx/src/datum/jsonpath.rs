@@ -0,0 +1,202 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{
+    direct_function_call, direct_function_call_as_datum, pg_sys, FromDatum, IntoDatum, JsonB,
+};
+use std::ffi::CString;
+
+type JsonPathFunction = unsafe fn(pg_sys::FunctionCallInfo) -> pg_sys::Datum;
+
+/// The `jsonb_path_*_tz()` variant of a function, which Postgres 12 doesn't have, so it can't be
+/// asked for with [`JsonPath::set_tz()`]
+#[cfg(feature = "pg12")]
+macro_rules! tz_variant {
+    ($func:ident, $func_tz:ident) => {
+        pg_sys::$func
+    };
+}
+#[cfg(not(feature = "pg12"))]
+macro_rules! tz_variant {
+    ($func:ident, $func_tz:ident) => {
+        pg_sys::$func_tz
+    };
+}
+
+/// A compiled `jsonpath`, which can be applied to any number of `jsonb` values without being
+/// parsed again, as `jsonb_path_exists()`, `jsonb_path_match()`, `jsonb_path_query_array()`, and
+/// `jsonb_path_query_first()` would apply it.
+///
+/// The `jsonb` values it's applied to are anything that converts to a `jsonb` datum, such as a
+/// [`JsonB`].
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use serde_json::json;
+///
+/// let path = JsonPath::compile("$.items[*] ? (@.price > $min)").set_vars(JsonB(json!({"min": 10})));
+/// let expensive = path.query_array(JsonB(json!({"items": [{"price": 5}, {"price": 20}]})));
+/// assert_eq!(expensive.0, json!([{"price": 20}]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct JsonPath {
+    path: pg_sys::Datum,
+    vars: Option<pg_sys::Datum>,
+    silent: bool,
+    tz: bool,
+}
+
+impl JsonPath {
+    /// Parse `path`, allocating it in the `CurrentMemoryContext`, so to apply it across calls it
+    /// should be compiled in a longer-lived one
+    ///
+    /// ## Panics
+    ///
+    /// Raises a Postgres ERROR if `path` isn't a valid `jsonpath`
+    pub fn compile(path: &str) -> Self {
+        let cstring = CString::new(path).expect("jsonpath contains a null byte");
+        let datum = unsafe {
+            direct_function_call_as_datum(
+                pg_sys::jsonpath_in,
+                vec![Some(cstring.as_ptr() as pg_sys::Datum)],
+            )
+        }
+        .expect("jsonpath_in returned NULL");
+        JsonPath {
+            path: datum,
+            vars: None,
+            silent: false,
+            tz: false,
+        }
+    }
+
+    /// The values of the path's `$name` variables, as the keys of a `jsonb` object
+    pub fn set_vars(mut self, vars: JsonB) -> Self {
+        self.vars = vars.into_datum();
+        self
+    }
+
+    /// Suppress the errors Postgres would raise for missing object keys, indexes out of bounds,
+    /// and the like, as the `silent` argument of the `jsonb_path_*()` functions does
+    pub fn set_silent(mut self, silent: bool) -> Self {
+        self.silent = silent;
+        self
+    }
+
+    /// Allow comparing dates and times with and without time zones, by the session's
+    /// `timezone`, as the `jsonb_path_*_tz()` functions do.  Postgres 13 and later only
+    #[cfg(not(feature = "pg12"))]
+    pub fn set_tz(mut self, tz: bool) -> Self {
+        self.tz = tz;
+        self
+    }
+
+    /// Does the path return any item for `jsonb`?
+    pub fn exists<J: IntoDatum>(&self, jsonb: J) -> bool {
+        self.call(
+            pg_sys::jsonb_path_exists,
+            tz_variant!(jsonb_path_exists, jsonb_path_exists_tz),
+            jsonb,
+        )
+        .unwrap_or(false)
+    }
+
+    /// The result of the path's predicate for `jsonb`, or `None` if it's unknown
+    pub fn matches<J: IntoDatum>(&self, jsonb: J) -> Option<bool> {
+        self.call(
+            pg_sys::jsonb_path_match,
+            tz_variant!(jsonb_path_match, jsonb_path_match_tz),
+            jsonb,
+        )
+    }
+
+    /// All of the items the path returns for `jsonb`, as a `jsonb` array
+    pub fn query_array<J: IntoDatum>(&self, jsonb: J) -> JsonB {
+        self.call(
+            pg_sys::jsonb_path_query_array,
+            tz_variant!(jsonb_path_query_array, jsonb_path_query_array_tz),
+            jsonb,
+        )
+        .expect("jsonb_path_query_array returned NULL")
+    }
+
+    /// The first item the path returns for `jsonb`, if any
+    pub fn query_first<J: IntoDatum>(&self, jsonb: J) -> Option<JsonB> {
+        self.call(
+            pg_sys::jsonb_path_query_first,
+            tz_variant!(jsonb_path_query_first, jsonb_path_query_first_tz),
+            jsonb,
+        )
+    }
+
+    /// All of the items the path returns for `jsonb`
+    pub fn query<J: IntoDatum>(&self, jsonb: J) -> Vec<JsonB> {
+        match self.query_array(jsonb).0 {
+            serde_json::Value::Array(items) => items.into_iter().map(JsonB).collect(),
+            _ => unreachable!("jsonb_path_query_array didn't return an array"),
+        }
+    }
+
+    fn call<J: IntoDatum, R: FromDatum>(
+        &self,
+        func: JsonPathFunction,
+        func_tz: JsonPathFunction,
+        jsonb: J,
+    ) -> Option<R> {
+        if J::type_oid() != pg_sys::JSONBOID {
+            panic!("a jsonpath can only be applied to jsonb");
+        }
+        let vars = self
+            .vars
+            .or_else(|| JsonB(serde_json::json!({})).into_datum());
+        unsafe {
+            direct_function_call(
+                if self.tz { func_tz } else { func },
+                vec![
+                    jsonb.into_datum(),
+                    Some(self.path),
+                    vars,
+                    self.silent.into_datum(),
+                ],
+            )
+        }
+    }
+}
+
+impl FromDatum for JsonPath {
+    const NEEDS_TYPID: bool = false;
+
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("a jsonpath Datum was flagged as non-null but the datum is zero");
+        } else {
+            Some(JsonPath {
+                path: pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena) as pg_sys::Datum,
+                vars: None,
+                silent: false,
+                tz: false,
+            })
+        }
+    }
+}
+
+/// Only the path itself; its vars and flags are dropped
+impl IntoDatum for JsonPath {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.path)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::JSONPATHOID
+    }
+}
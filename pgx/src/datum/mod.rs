@@ -9,32 +9,49 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Handing for easily converting Postgres Datum types into their corresponding Rust types
 //! and converting Rust types into their corresponding Postgres types
+mod acl;
+mod any;
 mod anyarray;
 mod anyelement;
 mod array;
+mod composite;
 mod date;
 mod from;
 mod geo;
+mod infinite;
 mod inet;
 mod internal;
+mod interval;
 mod into;
 mod item_pointer_data;
 mod json;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+mod jsonpath;
+mod name;
 mod numeric;
+mod options;
+mod raw;
+mod refcursor;
+mod reg;
+mod registry;
+mod text_builder;
 mod time;
 mod time_stamp;
 mod time_stamp_with_timezone;
 mod time_with_timezone;
+mod time_zone;
 mod tuples;
+mod type_io;
 mod uuid;
 mod varlena;
+mod xml;
 
-pub use self::time::*;
-pub use self::uuid::*;
+pub use acl::*;
+pub use any::*;
 pub use anyarray::*;
 pub use anyelement::*;
 pub use array::*;
-pub use date::*;
+pub use composite::*;
 pub use from::*;
 pub use geo::*;
 pub use inet::*;
@@ -42,21 +59,41 @@ pub use internal::*;
 pub use into::*;
 pub use item_pointer_data::*;
 pub use json::*;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+pub use jsonpath::*;
+pub use name::*;
 pub use numeric::*;
+pub use options::*;
+pub use raw::*;
+pub use refcursor::*;
+pub use reg::*;
+pub use registry::*;
+pub use text_builder::*;
 use once_cell::sync::Lazy;
 use std::any::TypeId;
 pub use time_stamp::*;
 pub use time_stamp_with_timezone::*;
 pub use time_with_timezone::*;
+pub use time_zone::*;
 pub use tuples::*;
+pub use type_io::*;
 pub use varlena::*;
+pub use xml::*;
+
+pub use pgx_types::{
+    Date, Interval, MaybeInfinite, Time, Timestamp, TimestampWithTimeZone, Uuid, UuidBytes,
+};
 
 use crate::PgBox;
 use pgx_utils::sql_entity_graph::RustSqlMapping;
 
 /// A tagging trait to indicate a user type is also meant to be used by Postgres
 /// Implemented automatically by `#[derive(PostgresType)]`
-pub trait PostgresType {}
+pub trait PostgresType {
+    /// The schema-qualified name the type is looked up by, if `#[pgx(sql_name = "..", schema =
+    /// "..")]` gives it another than its Rust name
+    const SQL_NAME: Option<&'static str> = None;
+}
 
 /// A type which can have it's [`core::any::TypeId`]s registered for Rust to SQL mapping.
 ///
@@ -0,0 +1,82 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{direct_function_call_as_datum, name_data_to_str, pg_sys, FromDatum, IntoDatum};
+use std::fmt;
+use std::ops::Deref;
+
+/// A `name`, the type of the identifiers in the catalogs, such as `pg_class.relname`, which is
+/// truncated to `NAMEDATALEN - 1` bytes when it's converted into a `Datum`.
+///
+/// A `Vec<Name>` is a `name[]`; `text[]` and `oid[]` are a `Vec<String>` and a `Vec<u32>`
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Name(pub String);
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Name(name)
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Name(name.to_string())
+    }
+}
+
+impl From<Name> for String {
+    fn from(name: Name) -> Self {
+        name.0
+    }
+}
+
+impl FromDatum for Name {
+    const NEEDS_TYPID: bool = false;
+
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Name> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("name datum is declared non-null but Datum is zero");
+        } else {
+            let name = &*(datum as *const pg_sys::NameData);
+            Some(Name(name_data_to_str(name).to_string()))
+        }
+    }
+}
+
+impl IntoDatum for Name {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let cstr = std::ffi::CString::new(self.0).expect("failed to convert name into CString");
+        unsafe { direct_function_call_as_datum(pg_sys::namein, vec![cstr.as_c_str().into_datum()]) }
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::NAMEOID
+    }
+}
@@ -8,7 +8,8 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use crate::{
-    direct_function_call, direct_function_call_as_datum, pg_sys, void_mut_ptr, FromDatum, IntoDatum,
+    detoast_datum, direct_function_call, direct_function_call_as_datum, pg_sys, set_varsize,
+    vardata_4b, varsize, void_mut_ptr, FromDatum, IntoDatum,
 };
 use pgx_pg_sys::pg_try;
 use serde::de::{Error, Visitor};
@@ -16,9 +17,209 @@ use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Number;
 use std::fmt;
 
-#[derive(Serialize)]
+/// A `numeric`, as the text Postgres writes it.
+///
+/// Values that are exact decimals, `mantissa * 10^-scale`, with no more than 38 significant
+/// digits, are converted to and from Postgres' `NumericData` directly, rather than through
+/// `numeric_out()` and `numeric_in()`.  The rest, such as `NaN`, go through those.
+#[derive(Debug, Clone, Serialize)]
 pub struct Numeric(pub String);
 
+// The layout of a `NumericData`, which is private to Postgres' `numeric.c`
+const DEC_DIGITS: i32 = 4;
+const NUMERIC_SIGN_MASK: u16 = 0xC000;
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_SHORT: u16 = 0x8000;
+const NUMERIC_SPECIAL: u16 = 0xC000;
+const NUMERIC_DSCALE_MASK: u16 = 0x3FFF;
+const NUMERIC_SHORT_SIGN_MASK: u16 = 0x2000;
+const NUMERIC_SHORT_DSCALE_MASK: u16 = 0x1F80;
+const NUMERIC_SHORT_DSCALE_SHIFT: u16 = 7;
+const NUMERIC_SHORT_DSCALE_MAX: u16 = NUMERIC_SHORT_DSCALE_MASK >> NUMERIC_SHORT_DSCALE_SHIFT;
+const NUMERIC_SHORT_WEIGHT_SIGN_MASK: u16 = 0x0040;
+const NUMERIC_SHORT_WEIGHT_MASK: u16 = 0x003F;
+const NUMERIC_SHORT_WEIGHT_MAX: i32 = NUMERIC_SHORT_WEIGHT_MASK as i32;
+const NUMERIC_SHORT_WEIGHT_MIN: i32 = -(NUMERIC_SHORT_WEIGHT_MASK as i32) - 1;
+
+impl Numeric {
+    /// The exact decimal `mantissa * 10^-scale`, such as `Numeric::from_decimal(12345, 2)` for
+    /// `123.45`.  The `scale` is the number of digits after the decimal point Postgres shows,
+    /// which may be at most 1000
+    pub fn from_decimal(mantissa: i128, scale: u16) -> Self {
+        let digits = mantissa.unsigned_abs().to_string();
+        let sign = if mantissa < 0 { "-" } else { "" };
+        let scale = scale as usize;
+        Numeric(if scale == 0 {
+            format!("{}{}", sign, digits)
+        } else if digits.len() > scale {
+            let (whole, fraction) = digits.split_at(digits.len() - scale);
+            format!("{}{}.{}", sign, whole, fraction)
+        } else {
+            format!("{}0.{:0>width$}", sign, digits, width = scale)
+        })
+    }
+
+    /// The value as `(mantissa, scale)`, if it's written as an exact decimal, like `-123.45`,
+    /// with no more than 38 significant digits, which is the case for every finite value from
+    /// Postgres that fits
+    pub fn as_decimal(&self) -> Option<(i128, u16)> {
+        let (negative, unsigned) = match self.0.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, self.0.as_str()),
+        };
+        let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if whole.is_empty()
+            || (unsigned.contains('.') && fraction.is_empty())
+            || !whole
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+
+        let mut mantissa: i128 = 0;
+        for digit in whole.bytes().chain(fraction.bytes()) {
+            let digit = (digit - b'0') as i128;
+            mantissa = mantissa.checked_mul(10)?;
+            mantissa = if negative {
+                mantissa.checked_sub(digit)?
+            } else {
+                mantissa.checked_add(digit)?
+            };
+        }
+        Some((mantissa, fraction.len().try_into().ok()?))
+    }
+
+    /// Decode a `NumericData` into a decimal, unless it's `NaN` or an infinity, or too large
+    unsafe fn decode(numeric: *const pg_sys::varlena) -> Option<(i128, u16)> {
+        let size = varsize(numeric);
+        let data = vardata_4b(numeric) as *const u16;
+        let header = data.read_unaligned();
+        let (negative, scale, weight, digits) = match header & NUMERIC_SIGN_MASK {
+            NUMERIC_SPECIAL => return None,
+            NUMERIC_SHORT => {
+                let negative = header & NUMERIC_SHORT_SIGN_MASK != 0;
+                let scale = (header & NUMERIC_SHORT_DSCALE_MASK) >> NUMERIC_SHORT_DSCALE_SHIFT;
+                let mut weight = (header & NUMERIC_SHORT_WEIGHT_MASK) as i32;
+                if header & NUMERIC_SHORT_WEIGHT_SIGN_MASK != 0 {
+                    weight += NUMERIC_SHORT_WEIGHT_MIN;
+                }
+                (negative, scale, weight, data.add(1))
+            }
+            sign => {
+                let scale = header & NUMERIC_DSCALE_MASK;
+                let weight = (data.add(1) as *const i16).read_unaligned() as i32;
+                (sign == NUMERIC_NEG, scale, weight, data.add(2))
+            }
+        };
+        let ndigits = (size - (digits as usize - numeric as usize)) / 2;
+
+        // each digit, in base `NBASE`, is a number of `10^exponent`s of the mantissa
+        let mut mantissa: i128 = 0;
+        for i in 0..ndigits {
+            let digit = (digits.add(i) as *const i16).read_unaligned() as i128;
+            let exponent = DEC_DIGITS * (weight - i as i32) + scale as i32;
+            let value = if exponent >= 0 {
+                digit.checked_mul(10i128.checked_pow(exponent as u32)?)?
+            } else {
+                // digits past the scale should only ever be zeros
+                let divisor = 10i128.checked_pow(-exponent as u32)?;
+                if digit % divisor != 0 {
+                    return None;
+                }
+                digit / divisor
+            };
+            mantissa = if negative {
+                mantissa.checked_sub(value)?
+            } else {
+                mantissa.checked_add(value)?
+            };
+        }
+        Some((mantissa, scale))
+    }
+
+    /// Encode a decimal as a `NumericData`, as Postgres' `make_result()` would
+    #[allow(clippy::manual_is_multiple_of)]
+    fn encode(mantissa: i128, scale: u16) -> pg_sys::Datum {
+        // the decimal digits, most significant first, padded with zeros on both sides to line
+        // up with the `NBASE` digits either side of the decimal point
+        let mut decimal = mantissa.unsigned_abs().to_string().into_bytes();
+        let before_point = decimal.len() as i32 - scale as i32;
+        let leading = (DEC_DIGITS - before_point.rem_euclid(DEC_DIGITS)) % DEC_DIGITS;
+        let mut weight = (before_point + leading) / DEC_DIGITS - 1;
+        let mut padded = vec![b'0'; leading as usize];
+        padded.append(&mut decimal);
+        while padded.len() % DEC_DIGITS as usize != 0 {
+            padded.push(b'0');
+        }
+
+        let mut digits = padded
+            .chunks(DEC_DIGITS as usize)
+            .map(|chunk| chunk.iter().fold(0i16, |n, c| n * 10 + (c - b'0') as i16))
+            .collect::<Vec<_>>();
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        let zeros = digits.iter().take_while(|digit| **digit == 0).count();
+        digits.drain(..zeros);
+        weight -= zeros as i32;
+        let negative = mantissa < 0;
+        if digits.is_empty() {
+            weight = 0;
+        }
+
+        let short = scale <= NUMERIC_SHORT_DSCALE_MAX
+            && (NUMERIC_SHORT_WEIGHT_MIN..=NUMERIC_SHORT_WEIGHT_MAX).contains(&weight);
+        let header_size = pg_sys::VARHDRSZ + if short { 2 } else { 4 };
+        let size = header_size + digits.len() * 2;
+        unsafe {
+            let numeric = pg_sys::palloc0(size) as *mut pg_sys::varlena;
+            set_varsize(numeric, size as i32);
+            let data = vardata_4b(numeric) as *mut u16;
+            if short {
+                let mut header = NUMERIC_SHORT
+                    | (scale << NUMERIC_SHORT_DSCALE_SHIFT)
+                    | (weight as u16 & NUMERIC_SHORT_WEIGHT_MASK);
+                if negative {
+                    header |= NUMERIC_SHORT_SIGN_MASK;
+                }
+                if weight < 0 {
+                    header |= NUMERIC_SHORT_WEIGHT_SIGN_MASK;
+                }
+                data.write_unaligned(header);
+            } else {
+                let sign = if negative { NUMERIC_NEG } else { 0 };
+                data.write_unaligned(sign | (scale & NUMERIC_DSCALE_MASK));
+                (data.add(1) as *mut i16).write_unaligned(weight as i16);
+            }
+            let first_digit = (numeric as *mut u8).add(header_size) as *mut i16;
+            for (i, digit) in digits.into_iter().enumerate() {
+                first_digit.add(i).write_unaligned(digit);
+            }
+            numeric as pg_sys::Datum
+        }
+    }
+}
+
+impl fmt::Display for Numeric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for Numeric {
+    fn from(text: String) -> Self {
+        Numeric(text)
+    }
+}
+
+impl From<&str> for Numeric {
+    fn from(text: &str) -> Self {
+        Numeric(text.to_string())
+    }
+}
+
 impl<'de> Deserialize<'de> for Numeric {
     fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error>
     where
@@ -74,13 +275,13 @@ impl<'de> Deserialize<'de> for Numeric {
                 unsafe {
                     pg_try(|| {
                         // this might throw, but that's okay
-                        let datum = Numeric(v.clone()).into_datum().unwrap();
+                        let datum = Numeric::from(v.as_str()).into_datum().unwrap();
 
                         // and don't leak the NumericData datum Postgres created
                         pg_sys::pfree(datum as void_mut_ptr);
 
                         // we have it as a valid String
-                        Ok(Numeric(v.clone()))
+                        Ok(Numeric::from(v.clone()))
                     })
                     .unwrap_or(Err(Error::custom(format!("invalid Numeric value: {}", v))))
                 }
@@ -93,61 +294,61 @@ impl<'de> Deserialize<'de> for Numeric {
 
 impl Into<Numeric> for i8 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from_decimal(self as i128, 0)
     }
 }
 
 impl Into<Numeric> for i16 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from_decimal(self as i128, 0)
     }
 }
 
 impl Into<Numeric> for i32 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from_decimal(self as i128, 0)
     }
 }
 
 impl Into<Numeric> for i64 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from_decimal(self as i128, 0)
     }
 }
 
 impl Into<Numeric> for u8 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from_decimal(self as i128, 0)
     }
 }
 
 impl Into<Numeric> for u16 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from_decimal(self as i128, 0)
     }
 }
 
 impl Into<Numeric> for u32 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from_decimal(self as i128, 0)
     }
 }
 
 impl Into<Numeric> for u64 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from_decimal(self as i128, 0)
     }
 }
 
 impl Into<Numeric> for f32 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from(format!("{}", self))
     }
 }
 
 impl Into<Numeric> for f64 {
     fn into(self) -> Numeric {
-        Numeric(format!("{}", self))
+        Numeric::from(format!("{}", self))
     }
 }
 
@@ -159,16 +360,28 @@ impl FromDatum for Numeric {
         if is_null {
             None
         } else {
+            let numeric = detoast_datum(datum);
+            if let Some((mantissa, scale)) = Numeric::decode(numeric) {
+                return Some(Numeric::from_decimal(mantissa, scale));
+            }
+
             let cstr =
                 direct_function_call::<&std::ffi::CStr>(pg_sys::numeric_out, vec![Some(datum)])
                     .expect("numeric_out returned null");
-            Some(Numeric(cstr.to_str().unwrap().into()))
+            Some(Numeric::from(cstr.to_str().unwrap()))
         }
     }
 }
 
 impl IntoDatum for Numeric {
     fn into_datum(self) -> Option<pg_sys::Datum> {
+        match self.as_decimal() {
+            Some((mantissa, scale)) if scale as u32 <= pg_sys::NUMERIC_MAX_DISPLAY_SCALE => {
+                return Some(Numeric::encode(mantissa, scale))
+            }
+            // so Postgres parses, or complains about, the rest
+            _ => {}
+        }
         let cstring =
             std::ffi::CString::new(self.0).expect("failed to convert numeric string into CString");
         let cstr = cstring.as_c_str();
@@ -0,0 +1,43 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+/// Split `key=value` options, as the catalogs keep them in `text[]` columns like
+/// `pg_class.reloptions`, `pg_attribute.attoptions`, and `pg_foreign_table.ftoptions`, into keys
+/// and values, as `untransformRelOptions()` would.  An option without a `=` has no value
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let reloptions = Spi::get_one::<Vec<String>>(
+///     "SELECT reloptions FROM pg_class WHERE oid = 'my_table'::regclass",
+/// )
+/// .unwrap_or_default();
+/// let fillfactor = parse_options(&reloptions)
+///     .into_iter()
+///     .find(|(key, _)| key == "fillfactor")
+///     .and_then(|(_, value)| value);
+/// ```
+pub fn parse_options<S: AsRef<str>>(options: &[S]) -> Vec<(String, Option<String>)> {
+    options
+        .iter()
+        .map(|option| match option.as_ref().split_once('=') {
+            Some((key, value)) => (key.to_string(), Some(value.to_string())),
+            None => (option.as_ref().to_string(), None),
+        })
+        .collect()
+}
+
+/// Join keys and values into `key=value` options, the reverse of [`parse_options()`], for
+/// storing in a `text[]` like `pg_class.reloptions`
+pub fn format_options<K: AsRef<str>, V: AsRef<str>>(options: &[(K, V)]) -> Vec<String> {
+    options
+        .iter()
+        .map(|(key, value)| format!("{}={}", key.as_ref(), value.as_ref()))
+        .collect()
+}
@@ -0,0 +1,424 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Handing the data of arrays and varlenas to native libraries, like BLAS or CUDA, as raw
+//! pointers and layouts, without copying it, and having them write their results straight into a
+//! new array or varlena that's returned as a `Datum`.
+//!
+//! The pointers are only valid for as long as what they were borrowed from, which for an
+//! [`Array`] is the detoasted copy it holds, so the borrow checker keeps that copy from being freed
+//! while they're in use.  As they're raw pointers, though, a library that holds onto them after the
+//! call returns can't be checked, and mustn't.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use std::os::raw::c_int;
+//!
+//! extern "C" {
+//!     // from a BLAS library
+//!     fn cblas_saxpy(n: c_int, alpha: f32, x: *const f32, incx: c_int, y: *mut f32, incy: c_int);
+//! }
+//!
+//! #[pg_extern]
+//! fn scaled_sum(alpha: f32, x: Array<f32>, y: Array<f32>) -> RawArrayBuf<f32> {
+//!     let x = x.as_raw().unwrap_or_else(|e| error!("x: {}", e));
+//!     let y = y.as_raw().unwrap_or_else(|e| error!("y: {}", e));
+//!     assert_eq!(x.len(), y.len(), "the arrays must be the same length");
+//!
+//!     let mut result = RawArrayBuf::<f32>::new(y.len()).unwrap();
+//!     result.as_mut_slice().copy_from_slice(y.as_slice());
+//!     unsafe { cblas_saxpy(x.len() as c_int, alpha, x.as_ptr(), 1, result.as_mut_ptr(), 1) };
+//!     result
+//! }
+//! ```
+use crate::{pg_sys, set_varsize, varsize_any_exhdr, void_mut_ptr, Array, FromDatum, IntoDatum};
+use std::alloc::Layout;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// `MaxAllocSize`, the most `palloc()` allocates at once
+const MAX_ALLOC_SIZE: usize = 0x3fffffff;
+
+/// Why an array's elements can't be used as a contiguous `[T]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawLayoutError {
+    /// The array was made with `Array::over()`, so its elements are only `Datum`s
+    NotAnArrayType,
+    /// The array has `NULL` elements, which aren't stored, so the others aren't evenly spaced
+    HasNulls,
+    /// The array's elements are of another type than the one asked for
+    WrongElementType {
+        expected: pg_sys::Oid,
+        found: pg_sys::Oid,
+    },
+    /// The element type isn't stored as the Rust type is laid out in memory, like a variable
+    /// length type, or one of another size or alignment
+    IncompatibleLayout { typlen: i16, size: usize },
+    /// The array or varlena would be larger than Postgres can allocate
+    TooLarge { len: usize },
+}
+
+impl std::fmt::Display for RawLayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RawLayoutError::NotAnArrayType => {
+                write!(f, "the array's elements aren't in an ArrayType")
+            }
+            RawLayoutError::HasNulls => write!(f, "the array has NULL elements"),
+            RawLayoutError::WrongElementType { expected, found } => write!(
+                f,
+                "the array's elements are of type {}, not type {}",
+                found, expected
+            ),
+            RawLayoutError::IncompatibleLayout { typlen, size } => write!(
+                f,
+                "elements of length {} can't be read as a Rust type of size {}",
+                typlen, size
+            ),
+            RawLayoutError::TooLarge { len } => {
+                write!(f, "{} elements are more than Postgres can allocate", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RawLayoutError {}
+
+/// The elements of an array, contiguous in memory, as a native library wants them
+pub struct RawArray<'a, T> {
+    data: *const T,
+    len: usize,
+    dims: &'a [i32],
+}
+
+impl<'a, T> RawArray<'a, T> {
+    /// The first element, valid for [`RawArray::len()`] elements while this is borrowed.  An empty
+    /// array's pointer is dangling, as with an empty slice
+    pub fn as_ptr(&self) -> *const T {
+        self.data
+    }
+
+    /// How many elements the array has, in all its dimensions
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The array's dimensions.  Its elements are in row-major order, so the last dimension's
+    /// elements are adjacent
+    pub fn dims(&self) -> &[i32] {
+        self.dims
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.data, self.len) }
+    }
+
+    /// The size and alignment of the elements together, for libraries that ask for them
+    pub fn layout(&self) -> Layout {
+        Layout::array::<T>(self.len).expect("the array's layout overflowed")
+    }
+}
+
+impl<'a, T: FromDatum + IntoDatum + Copy> Array<'a, T> {
+    /// The array's elements, without copying them, for passing to native libraries.  Only arrays
+    /// without `NULL`s, of fixed-length types stored as `T` is laid out, such as `real[]` as
+    /// `f32`, can be.  The pointers of the [`RawArray`] point into the detoasted array this
+    /// [`Array`] holds, which it can't be dropped before
+    pub fn as_raw(&self) -> Result<RawArray<'_, T>, RawLayoutError> {
+        let array = self.array_type_ptr();
+        if array.is_null() {
+            return Err(RawLayoutError::NotAnArrayType);
+        }
+        unsafe {
+            let header = &*array;
+            if header.dataoffset != 0 {
+                return Err(RawLayoutError::HasNulls);
+            }
+            check_element_layout::<T>(header.elemtype)?;
+
+            // as `ARR_DIMS()`, the dimensions follow the header
+            let ndim = header.ndim as usize;
+            let dims = std::slice::from_raw_parts(
+                (array as *const u8).add(std::mem::size_of::<pg_sys::ArrayType>()) as *const i32,
+                ndim,
+            );
+            let len = if ndim == 0 {
+                0
+            } else {
+                dims.iter().map(|dim| *dim as usize).product()
+            };
+            let data = if len == 0 {
+                NonNull::dangling().as_ptr()
+            } else {
+                (array as *const u8).add(array_overhead(ndim)) as *const T
+            };
+            Ok(RawArray { data, len, dims })
+        }
+    }
+}
+
+/// A new one-dimensional array of `len` elements, allocated in `CurrentMemoryContext` and zeroed,
+/// for a native library to write its results into, which is returned as a `Datum` without being
+/// copied.  Like [`RawArray`], only fixed-length types stored as `T` is laid out, such as `f32` for
+/// `real[]`, can be.
+pub struct RawArrayBuf<T> {
+    array: *mut pg_sys::ArrayType,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: IntoDatum + Copy> RawArrayBuf<T> {
+    pub fn new(len: usize) -> Result<Self, RawLayoutError> {
+        check_element_layout::<T>(T::type_oid())?;
+        let ndim = if len == 0 { 0 } else { 1 };
+        let size = len
+            .checked_mul(std::mem::size_of::<T>())
+            .and_then(|size| size.checked_add(array_overhead(ndim)))
+            .filter(|size| *size <= MAX_ALLOC_SIZE && len <= i32::MAX as usize)
+            .ok_or(RawLayoutError::TooLarge { len })?;
+
+        unsafe {
+            let array = pg_sys::palloc0(size) as *mut pg_sys::ArrayType;
+            set_varsize(array as *mut pg_sys::varlena, size as i32);
+            (*array).ndim = ndim as i32;
+            (*array).dataoffset = 0;
+            (*array).elemtype = T::type_oid();
+            if ndim == 1 {
+                // as `ARR_DIMS()` and `ARR_LBOUND()`, the dimensions follow the header, and then the
+                // lower bounds
+                let dims =
+                    (array as *mut u8).add(std::mem::size_of::<pg_sys::ArrayType>()) as *mut i32;
+                *dims = len as i32;
+                *dims.add(1) = 1;
+            }
+            Ok(RawArrayBuf {
+                array,
+                len,
+                _marker: PhantomData,
+            })
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The first element, valid for writing [`RawArrayBuf::len()`] elements while this is
+    /// borrowed.  An empty array's pointer is dangling
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        if self.len == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            unsafe { (self.array as *mut u8).add(array_overhead(1)) as *mut T }
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        let data = if self.len == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            unsafe { (self.array as *const u8).add(array_overhead(1)) as *const T }
+        };
+        unsafe { std::slice::from_raw_parts(data, self.len) }
+    }
+
+    /// The size and alignment of the elements together, for libraries that ask for them
+    pub fn layout(&self) -> Layout {
+        Layout::array::<T>(self.len).expect("the array's layout overflowed")
+    }
+}
+
+impl<T> Drop for RawArrayBuf<T> {
+    fn drop(&mut self) {
+        if !self.array.is_null() {
+            unsafe { pg_sys::pfree(self.array as void_mut_ptr) }
+        }
+    }
+}
+
+impl<T: IntoDatum + Copy> IntoDatum for RawArrayBuf<T> {
+    fn into_datum(mut self) -> Option<pg_sys::Datum> {
+        let array = std::mem::replace(&mut self.array, std::ptr::null_mut());
+        Some(array as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        T::array_type_oid()
+    }
+}
+
+/// The data of a varlena `Datum`, such as a `bytea` or a custom type's, detoasted, for passing to
+/// native libraries.  A detoasted copy is freed when this is dropped, so the pointers into it are
+/// only valid while this is borrowed.
+///
+/// The data follows the varlena's 4 byte header, so it's only 4 byte aligned, which is too little
+/// for reading `f64`s or `i64`s in place.
+pub struct PinnedVarlena<'a> {
+    varlena: *mut pg_sys::varlena,
+    copied: bool,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> PinnedVarlena<'a> {
+    /// ## Safety
+    ///
+    /// The `datum` must be a non-null varlena, which outlives `'a`
+    pub unsafe fn from_datum(datum: pg_sys::Datum) -> Self {
+        let varlena = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena);
+        PinnedVarlena {
+            varlena,
+            copied: varlena as pg_sys::Datum != datum,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The first byte of the data, valid for [`PinnedVarlena::len()`] bytes while this is borrowed
+    pub fn as_ptr(&self) -> *const u8 {
+        unsafe { (self.varlena as *const u8).add(pg_sys::VARHDRSZ) }
+    }
+
+    /// The length of the data, without the header
+    pub fn len(&self) -> usize {
+        unsafe { varsize_any_exhdr(self.varlena) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.as_ptr(), self.len()) }
+    }
+}
+
+impl<'a> Drop for PinnedVarlena<'a> {
+    fn drop(&mut self) {
+        if self.copied {
+            unsafe { pg_sys::pfree(self.varlena as void_mut_ptr) }
+        }
+    }
+}
+
+/// A new varlena with `len` bytes of data, allocated in `CurrentMemoryContext` and zeroed, for a
+/// native library to write its results into, which is returned as a `bytea` `Datum` without
+/// being copied, or as another varlena type's with [`RawVarlenaBuf::into_varlena()`].  Like
+/// [`PinnedVarlena`]'s, its data is only 4 byte aligned.
+pub struct RawVarlenaBuf {
+    varlena: *mut pg_sys::varlena,
+    len: usize,
+}
+
+impl RawVarlenaBuf {
+    pub fn new(len: usize) -> Result<Self, RawLayoutError> {
+        let size = len
+            .checked_add(pg_sys::VARHDRSZ)
+            .filter(|size| *size <= MAX_ALLOC_SIZE)
+            .ok_or(RawLayoutError::TooLarge { len })?;
+        unsafe {
+            let varlena = pg_sys::palloc0(size) as *mut pg_sys::varlena;
+            set_varsize(varlena, size as i32);
+            Ok(RawVarlenaBuf { varlena, len })
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The first byte of the data, valid for writing [`RawVarlenaBuf::len()`] bytes while this is
+    /// borrowed
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        unsafe { (self.varlena as *mut u8).add(pg_sys::VARHDRSZ) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    /// The varlena, which is no longer freed when this is dropped, for returning as the `Datum` of
+    /// a varlena type other than `bytea`
+    pub fn into_varlena(mut self) -> *mut pg_sys::varlena {
+        std::mem::replace(&mut self.varlena, std::ptr::null_mut())
+    }
+}
+
+impl Drop for RawVarlenaBuf {
+    fn drop(&mut self) {
+        if !self.varlena.is_null() {
+            unsafe { pg_sys::pfree(self.varlena as void_mut_ptr) }
+        }
+    }
+}
+
+impl IntoDatum for RawVarlenaBuf {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.into_varlena() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::BYTEAOID
+    }
+}
+
+/// Are elements of type `elemtype` stored as a `T` is laid out, so that an array of them can be
+/// read as a `[T]`?
+#[allow(clippy::manual_is_multiple_of)]
+fn check_element_layout<T: IntoDatum>(elemtype: pg_sys::Oid) -> Result<(), RawLayoutError> {
+    if elemtype != T::type_oid() {
+        return Err(RawLayoutError::WrongElementType {
+            expected: T::type_oid(),
+            found: elemtype,
+        });
+    }
+
+    let mut typlen = 0;
+    let mut typbyval = false;
+    let mut typalign = 0;
+    unsafe { pg_sys::get_typlenbyvalalign(elemtype, &mut typlen, &mut typbyval, &mut typalign) };
+    let align = match typalign as u8 {
+        b'c' => 1,
+        b's' => 2,
+        b'i' => 4,
+        _ => 8,
+    };
+
+    // elements are each aligned to `typalign`, so they're only adjacent if they're a multiple of it
+    let size = std::mem::size_of::<T>();
+    if typlen <= 0
+        || typlen as usize != size
+        || size % align != 0
+        || std::mem::align_of::<T>() > align
+    {
+        return Err(RawLayoutError::IncompatibleLayout { typlen, size });
+    }
+    Ok(())
+}
+
+/// Where the elements of an array without `NULL`s start, as `ARR_OVERHEAD_NONULLS()`
+fn array_overhead(ndim: usize) -> usize {
+    let size = std::mem::size_of::<pg_sys::ArrayType>() + 2 * std::mem::size_of::<i32>() * ndim;
+    let align = pg_sys::MAXIMUM_ALIGNOF as usize;
+    (size + align - 1) & !(align - 1)
+}
@@ -0,0 +1,80 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{pg_sys, FromDatum, IntoDatum};
+use std::fmt;
+use std::ops::Deref;
+
+/// A `refcursor`, the name of an open cursor, such as one a function opened with
+/// [`SpiClient::open_cursor()`](crate::SpiClient::open_cursor) for its caller to `FETCH` from.
+///
+/// It's only a name, as in SQL, so the cursor it names may already be closed.
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct RefCursor(pub String);
+
+impl Deref for RefCursor {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for RefCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for RefCursor {
+    fn from(name: String) -> Self {
+        RefCursor(name)
+    }
+}
+
+impl From<&str> for RefCursor {
+    fn from(name: &str) -> Self {
+        RefCursor(name.to_string())
+    }
+}
+
+impl From<RefCursor> for String {
+    fn from(cursor: RefCursor) -> Self {
+        cursor.0
+    }
+}
+
+impl FromDatum for RefCursor {
+    const NEEDS_TYPID: bool = false;
+
+    #[inline]
+    unsafe fn from_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<RefCursor> {
+        // a refcursor is stored as the text of its name
+        String::from_datum(datum, is_null, typoid).map(RefCursor)
+    }
+}
+
+impl IntoDatum for RefCursor {
+    #[inline]
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        self.0.into_datum()
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::REFCURSOROID
+    }
+
+    fn is_compatible_with(other: pg_sys::Oid) -> bool {
+        other == pg_sys::REFCURSOROID || String::is_compatible_with(other)
+    }
+}
@@ -0,0 +1,117 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{direct_function_call, pg_sys, FromDatum, IntoDatum};
+use std::ffi::CStr;
+
+macro_rules! reg_type {
+    ($(#[$attr:meta])* $name:ident, $sql:literal, $type_oid:ident, $to_reg:ident, $out:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub pg_sys::Oid);
+
+        impl $name {
+            #[doc = concat!("Look up the oid of `name` like SQL's `to_", $sql, "()`, returning `None` if it doesn't exist.")]
+            ///
+            /// `name` may be schema-qualified, and is otherwise looked up in the `search_path`.  Like
+            /// in SQL, it's folded to lowercase unless it's double-quoted
+            pub fn from_name(name: &str) -> Option<Self> {
+                unsafe {
+                    direct_function_call::<pg_sys::Oid>(pg_sys::$to_reg, vec![name.into_datum()])
+                        .map($name)
+                }
+            }
+
+            pub fn oid(&self) -> pg_sys::Oid {
+                self.0
+            }
+
+            #[doc = concat!("The name SQL would show for this `", $sql, "`, schema-qualified if it isn't in the `search_path`,")]
+            /// or the oid itself if it doesn't exist
+            pub fn name(&self) -> String {
+                unsafe {
+                    direct_function_call::<&CStr>(pg_sys::$out, vec![Some(self.0 as pg_sys::Datum)])
+                        .expect(concat!(stringify!($out), "() returned NULL"))
+                        .to_string_lossy()
+                        .into_owned()
+                }
+            }
+        }
+
+        impl From<pg_sys::Oid> for $name {
+            fn from(oid: pg_sys::Oid) -> Self {
+                $name(oid)
+            }
+        }
+
+        impl From<$name> for pg_sys::Oid {
+            fn from(reg: $name) -> Self {
+                reg.0
+            }
+        }
+
+        impl FromDatum for $name {
+            const NEEDS_TYPID: bool = false;
+
+            unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+                if is_null {
+                    None
+                } else {
+                    Some($name(datum as pg_sys::Oid))
+                }
+            }
+        }
+
+        impl IntoDatum for $name {
+            fn into_datum(self) -> Option<pg_sys::Datum> {
+                Some(self.0 as pg_sys::Datum)
+            }
+
+            fn type_oid() -> pg_sys::Oid {
+                pg_sys::$type_oid
+            }
+        }
+    };
+}
+
+reg_type!(
+    /// A relation, such as a table, index, or view, as SQL's `regclass`
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// #[pg_extern]
+    /// fn column_count(relation: RegClass) -> i32 {
+    ///     unsafe { PgRelation::open(relation.oid()) }.tuple_desc().len() as i32
+    /// }
+    /// ```
+    RegClass,
+    "regclass",
+    REGCLASSOID,
+    to_regclass,
+    regclassout
+);
+
+reg_type!(
+    /// A function, by its name alone, as SQL's `regproc`
+    RegProc,
+    "regproc",
+    REGPROCOID,
+    to_regproc,
+    regprocout
+);
+
+reg_type!(
+    /// A type, as SQL's `regtype`
+    RegType,
+    "regtype",
+    REGTYPEOID,
+    to_regtype,
+    regtypeout
+);
@@ -0,0 +1,225 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A registry of which Rust type converts `Datum`s of each Postgres type, for code that only
+//! learns the types of the `Datum`s it handles at runtime
+use crate::{
+    pg_sys, AclItem, Date, FromDatum, Inet, IntoDatum, Json, JsonB, MaybeInfinite, Name, Numeric,
+    Time, TimeWithTimeZone, Timestamp, TimestampWithTimeZone, Uuid, Xml,
+};
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// How to convert `Datum`s of a Postgres type to and from the Rust type registered for it, with
+/// the Rust values boxed as `dyn Any`, from [`lookup_datum_converter()`]
+#[derive(Clone, Copy)]
+pub struct DatumConverter {
+    rust_type: &'static str,
+    type_id: TypeId,
+    from_datum: unsafe fn(pg_sys::Datum, bool, pg_sys::Oid) -> Option<Box<dyn Any>>,
+    into_datum: fn(Box<dyn Any>) -> Option<pg_sys::Datum>,
+}
+
+impl DatumConverter {
+    /// The converter for `T`
+    pub fn of<T: FromDatum + IntoDatum + 'static>() -> Self {
+        DatumConverter {
+            rust_type: std::any::type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+            from_datum: |datum, is_null, typoid| unsafe {
+                T::from_datum(datum, is_null, typoid).map(|value| Box::new(value) as Box<dyn Any>)
+            },
+            into_datum: |value| match value.downcast::<T>() {
+                Ok(value) => value.into_datum(),
+                Err(_) => panic!("expected a {}", std::any::type_name::<T>()),
+            },
+        }
+    }
+
+    /// The name of the Rust type
+    pub fn rust_type(&self) -> &'static str {
+        self.rust_type
+    }
+
+    /// The `TypeId` of the Rust type, which a `Box<dyn Any>` from
+    /// [`DatumConverter::from_datum()`] can be downcast to
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Convert the `Datum` into the Rust type, boxed.  `None` is `NULL`
+    ///
+    /// ## Safety
+    ///
+    /// Same caveats as `FromDatum::from_datum(...)`
+    pub unsafe fn from_datum(
+        &self,
+        datum: pg_sys::Datum,
+        is_null: bool,
+        typoid: pg_sys::Oid,
+    ) -> Option<Box<dyn Any>> {
+        (self.from_datum)(datum, is_null, typoid)
+    }
+
+    /// Convert a boxed value of the Rust type into a `Datum`
+    ///
+    /// ## Panics
+    ///
+    /// If `value` isn't of the Rust type
+    pub fn into_datum(&self, value: Box<dyn Any>) -> Option<pg_sys::Datum> {
+        (self.into_datum)(value)
+    }
+}
+
+impl std::fmt::Debug for DatumConverter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatumConverter")
+            .field("rust_type", &self.rust_type)
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    converters: HashMap<pg_sys::Oid, DatumConverter>,
+    /// built-in array types, by their element type, whose oid is looked up when a converter is
+    /// next looked up, as not every version's bindings have all of their oids and the catalogs
+    /// can't be read when the registry is first used, which may be in `_PG_init()`
+    builtin_arrays: Vec<(pg_sys::Oid, DatumConverter)>,
+    /// types whose oid is looked up when a converter is next looked up, as they may not exist yet
+    /// when they're registered
+    pending: Vec<(fn() -> pg_sys::Oid, DatumConverter)>,
+}
+
+impl Registry {
+    fn with_builtins() -> Self {
+        let mut registry = Registry::default();
+        macro_rules! builtin {
+            ($rust:ty, $($oid:ident),+) => {
+                $(registry.converters.insert(pg_sys::$oid, DatumConverter::of::<$rust>());)+
+            };
+            ($rust:ty, $($oid:ident),+ ; arrays) => {
+                builtin!($rust, $($oid),+);
+                $(registry
+                    .builtin_arrays
+                    .push((pg_sys::$oid, DatumConverter::of::<Vec<Option<$rust>>>()));)+
+            };
+        }
+
+        builtin!(bool, BOOLOID; arrays);
+        builtin!(i8, CHAROID);
+        builtin!(i16, INT2OID; arrays);
+        builtin!(i32, INT4OID; arrays);
+        builtin!(i64, INT8OID; arrays);
+        builtin!(u32, OIDOID; arrays);
+        builtin!(f32, FLOAT4OID; arrays);
+        builtin!(f64, FLOAT8OID; arrays);
+        builtin!(String, TEXTOID, VARCHAROID, BPCHAROID; arrays);
+        builtin!(Vec<u8>, BYTEAOID);
+        builtin!(Numeric, NUMERICOID);
+        builtin!(MaybeInfinite<Date>, DATEOID);
+        builtin!(Time, TIMEOID);
+        builtin!(TimeWithTimeZone, TIMETZOID);
+        builtin!(MaybeInfinite<Timestamp>, TIMESTAMPOID);
+        builtin!(MaybeInfinite<TimestampWithTimeZone>, TIMESTAMPTZOID);
+        builtin!(Json, JSONOID);
+        builtin!(JsonB, JSONBOID);
+        builtin!(Uuid, UUIDOID);
+        builtin!(Inet, INETOID);
+        builtin!(Xml, XMLOID; arrays);
+        builtin!(Name, NAMEOID; arrays);
+        builtin!(AclItem, ACLITEMOID; arrays);
+        registry
+    }
+
+    /// Look up the oids of the built-in array types and the registered types.  Each one stays
+    /// pending until its lookup succeeds, so one that raises an ERROR, like a type that hasn't
+    /// been created yet, is tried again next time
+    fn resolve_pending(&mut self) {
+        while let Some(&(element_oid, converter)) = self.builtin_arrays.first() {
+            let array_oid = unsafe { pg_sys::get_array_type(element_oid) };
+            // a converter registered for the array type before it was looked up wins
+            if array_oid != pg_sys::InvalidOid {
+                self.converters.entry(array_oid).or_insert(converter);
+            }
+            self.builtin_arrays.remove(0);
+        }
+
+        while let Some(&(type_oid, converter)) = self.pending.first() {
+            let typoid = type_oid();
+            self.converters.insert(typoid, converter);
+            self.pending.remove(0);
+        }
+    }
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::with_builtins());
+}
+
+/// Register `T` as the Rust type for its [`IntoDatum::type_oid()`], such as a type from
+/// `#[derive(PostgresType)]` or `#[derive(PostgresEnum)]`, replacing any registered for it
+/// before.  Built-in types like `integer`, `text[]`, and `jsonb` are already registered.
+///
+/// The type's oid is looked up by the next [`lookup_datum_converter()`], which has to be inside a
+/// transaction, so this can be called from `_PG_init()`, before the type even exists.
+///
+/// Registrations belong to the backend they're made in
+pub fn register_datum_type<T: FromDatum + IntoDatum + 'static>() {
+    REGISTRY.with(|registry| {
+        registry
+            .borrow_mut()
+            .pending
+            .push((T::type_oid, DatumConverter::of::<T>()))
+    });
+}
+
+/// Register `T` as the Rust type for the type `typoid`, replacing any registered for it before,
+/// for a type that isn't `T`'s [`IntoDatum::type_oid()`] but that it converts, such as `varchar`
+/// for `String`
+pub fn register_datum_type_for<T: FromDatum + IntoDatum + 'static>(typoid: pg_sys::Oid) {
+    REGISTRY.with(|registry| {
+        registry
+            .borrow_mut()
+            .converters
+            .insert(typoid, DatumConverter::of::<T>())
+    });
+}
+
+/// The converter registered for the type `typoid`, or for the base type if it's a domain.
+/// `date` and the timestamp types convert to [`MaybeInfinite`]s, as they may be infinite.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn describe(value: AnyElement) -> String {
+///     let converter = match lookup_datum_converter(value.oid()) {
+///         Some(converter) => converter,
+///         None => return format!("a value of type {}", RegType(value.oid()).name()),
+///     };
+///     let boxed = unsafe { converter.from_datum(value.datum(), false, value.oid()) }.unwrap();
+///     if let Some(i) = boxed.downcast_ref::<i32>() {
+///         format!("the integer {}", i)
+///     } else {
+///         format!("a {}", converter.rust_type())
+///     }
+/// }
+/// ```
+pub fn lookup_datum_converter(typoid: pg_sys::Oid) -> Option<DatumConverter> {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.resolve_pending();
+        registry.converters.get(&typoid).copied().or_else(|| {
+            let base = unsafe { pg_sys::getBaseType(typoid) };
+            registry.converters.get(&base).copied()
+        })
+    })
+}
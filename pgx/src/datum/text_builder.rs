@@ -0,0 +1,139 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{pg_sys, set_varsize, void_mut_ptr, IntoDatum};
+use std::fmt;
+
+/// A `text` value which is written in place, in a varlena allocated in `CurrentMemoryContext`
+/// and grown with `repalloc()` as it's written to, rather than built as a Rust `String` which is
+/// then copied into one.
+///
+/// It's written with [`write!()`], through its [`fmt::Write`] implementation, and returned from a
+/// function as its `text`:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::fmt::Write;
+///
+/// #[pg_extern]
+/// fn numbers(upto: i32) -> TextBuilder {
+///     let mut text = TextBuilder::new();
+///     for i in 1..=upto {
+///         writeln!(text, "{}", i).unwrap();
+///     }
+///     text
+/// }
+/// ```
+pub struct TextBuilder {
+    varlena: *mut pg_sys::varlena,
+    len: usize,
+    capacity: usize,
+}
+
+impl TextBuilder {
+    pub fn new() -> Self {
+        TextBuilder::with_capacity(64)
+    }
+
+    /// A `TextBuilder` with room for `capacity` bytes before it has to grow
+    pub fn with_capacity(capacity: usize) -> Self {
+        let varlena =
+            unsafe { pg_sys::palloc(pg_sys::VARHDRSZ + capacity) as *mut pg_sys::varlena };
+        TextBuilder {
+            varlena,
+            len: 0,
+            capacity,
+        }
+    }
+
+    /// The number of bytes written
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        let needed = self.len + s.len();
+        if needed > self.capacity {
+            let capacity = needed.max(self.capacity * 2);
+            self.varlena = unsafe {
+                pg_sys::repalloc(self.varlena as void_mut_ptr, pg_sys::VARHDRSZ + capacity)
+                    as *mut pg_sys::varlena
+            };
+            self.capacity = capacity;
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(s.as_ptr(), self.data().add(self.len), s.len());
+        }
+        self.len = needed;
+    }
+
+    /// What's been written so far
+    pub fn as_str(&self) -> &str {
+        unsafe {
+            let bytes = std::slice::from_raw_parts(self.data(), self.len);
+            // only `&str`s are ever written
+            std::str::from_utf8_unchecked(bytes)
+        }
+    }
+
+    /// The finished `text`, which is freed with its memory context
+    pub fn into_pg(self) -> *mut pg_sys::text {
+        let varlena = self.varlena;
+        unsafe {
+            set_varsize(varlena, (pg_sys::VARHDRSZ + self.len) as i32);
+        }
+        std::mem::forget(self);
+        varlena as *mut pg_sys::text
+    }
+
+    fn data(&self) -> *mut u8 {
+        unsafe { (self.varlena as *mut u8).add(pg_sys::VARHDRSZ) }
+    }
+}
+
+impl Default for TextBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Write for TextBuilder {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl fmt::Display for TextBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl IntoDatum for TextBuilder {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        Some(self.into_pg() as pg_sys::Datum)
+    }
+
+    fn type_oid() -> pg_sys::Oid {
+        pg_sys::TEXTOID
+    }
+}
+
+impl Drop for TextBuilder {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::pfree(self.varlena as void_mut_ptr);
+        }
+    }
+}
@@ -8,32 +8,23 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use crate::datum::time::USECS_PER_SEC;
-use crate::{direct_function_call_as_datum, pg_sys, FromDatum, IntoDatum, TimestampWithTimeZone};
-use std::ops::{Deref, DerefMut};
-use time::{format_description::FormatItem, PrimitiveDateTime};
+use crate::{
+    direct_function_call, direct_function_call_as_datum, pg_sys, FromDatum, IntoDatum, Timestamp,
+    TimestampWithTimeZone,
+};
 
-#[derive(Debug, Copy, Clone)]
-pub struct Timestamp(time::PrimitiveDateTime);
-
-impl From<pg_sys::Timestamp> for Timestamp {
-    fn from(item: pg_sys::Timestamp) -> Self {
-        unsafe { Timestamp::from_datum(item as usize, false, pg_sys::TIMESTAMPOID).unwrap() }
-    }
-}
+/// The `Datum` of the timestamp `-infinity`
+pub(crate) const DT_NOBEGIN: i64 = i64::MIN;
+/// The `Datum` of the timestamp `infinity`
+pub(crate) const DT_NOEND: i64 = i64::MAX;
 
 impl FromDatum for Timestamp {
     #[inline]
-    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, typoid: u32) -> Option<Timestamp> {
-        let ts: Option<TimestampWithTimeZone> =
-            TimestampWithTimeZone::from_datum(datum, is_null, typoid);
-        match ts {
-            None => None,
-            Some(ts) => {
-                let date = ts.date();
-                let time = ts.time();
-
-                Some(Timestamp(PrimitiveDateTime::new(date, time)))
-            }
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _typoid: u32) -> Option<Timestamp> {
+        if is_null {
+            None
+        } else {
+            Some(Timestamp::from(datum as i64))
         }
     }
 }
@@ -66,59 +57,37 @@ impl IntoDatum for Timestamp {
         pg_sys::TIMESTAMPOID
     }
 }
-impl Timestamp {
-    pub fn new(timestamp: time::PrimitiveDateTime) -> Self {
-        Timestamp(timestamp)
-    }
-}
 
-impl Deref for Timestamp {
-    type Target = time::PrimitiveDateTime;
+/// The functions on a [`Timestamp`] that need the Postgres server's time zone database
+pub trait TimestampFunctions {
+    /// The moment when it's this date and time in the time zone `zone`, as
+    /// `timestamp AT TIME ZONE zone` would give it.  `zone` is anything Postgres accepts there,
+    /// such as `"America/Denver"` or `"MST"`
+    fn at_time_zone(&self, zone: &str) -> TimestampWithTimeZone;
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
+    /// This timestamp truncated to the precision `field`, such as `"hour"` or `"month"`, as
+    /// `date_trunc(field, timestamp)` would
+    fn trunc(&self, field: &str) -> Self;
 }
-impl DerefMut for Timestamp {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+
+impl TimestampFunctions for Timestamp {
+    fn at_time_zone(&self, zone: &str) -> TimestampWithTimeZone {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamp_zone,
+                vec![zone.into_datum(), self.into_datum()],
+            )
+        }
+        .expect("timestamp_zone returned NULL")
     }
-}
 
-impl serde::Serialize for Timestamp {
-    fn serialize<S>(
-        &self,
-        serializer: S,
-    ) -> std::result::Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
-    where
-        S: serde::Serializer,
-    {
-        if self.millisecond() > 0 {
-            serializer.serialize_str(
-                &self
-                    .format(
-                        &time::format_description::parse(&format!(
-                            "[year]-[month]-[day]T[hour]:[minute]:[second].{}-00",
-                            self.millisecond()
-                        ))
-                        .map_err(|e| {
-                            serde::ser::Error::custom(format!(
-                                "Timestamp invalid format problem: {:?}",
-                                e
-                            ))
-                        })?,
-                    )
-                    .map_err(|e| {
-                        serde::ser::Error::custom(format!("Timestamp formatting problem: {:?}", e))
-                    })?,
+    fn trunc(&self, field: &str) -> Self {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamp_trunc,
+                vec![field.into_datum(), self.into_datum()],
             )
-        } else {
-            serializer.serialize_str(&self.format(&DEFAULT_TIMESTAMP_FORMAT).map_err(|e| {
-                serde::ser::Error::custom(format!("Timestamp formatting problem: {:?}", e))
-            })?)
         }
+        .expect("timestamp_trunc returned NULL")
     }
 }
-
-static DEFAULT_TIMESTAMP_FORMAT: &[FormatItem<'static>] =
-    time::macros::format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]-00");
@@ -8,22 +8,10 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 use crate::datum::time::USECS_PER_SEC;
-use crate::{direct_function_call_as_datum, pg_sys, FromDatum, IntoDatum};
-use std::{
-    convert::TryFrom,
-    ops::{Deref, DerefMut},
+use crate::{
+    direct_function_call, direct_function_call_as_datum, pg_sys, FromDatum, Interval, IntoDatum,
+    PgTimeZone, Timestamp, TimestampFields, TimestampWithTimeZone,
 };
-use time::{format_description::FormatItem, UtcOffset};
-
-#[derive(Debug, Copy, Clone)]
-pub struct TimestampWithTimeZone(time::OffsetDateTime);
-
-impl From<pg_sys::TimestampTz> for TimestampWithTimeZone {
-    fn from(item: pg_sys::TimestampTz) -> Self {
-        unsafe { TimestampWithTimeZone::from_datum(item as usize, false, pg_sys::TIMESTAMPTZOID).unwrap() }
-    }
-}
-
 
 impl FromDatum for TimestampWithTimeZone {
     #[inline]
@@ -35,54 +23,7 @@ impl FromDatum for TimestampWithTimeZone {
         if is_null {
             None
         } else {
-            let mut tm = pg_sys::pg_tm {
-                tm_sec: 0,
-                tm_min: 0,
-                tm_hour: 0,
-                tm_mday: 0,
-                tm_mon: 0,
-                tm_year: 0,
-                tm_wday: 0,
-                tm_yday: 0,
-                tm_isdst: 0,
-                tm_gmtoff: 0,
-                tm_zone: std::ptr::null_mut(),
-            };
-            let mut tz = 0i32;
-            let mut fsec = 0 as pg_sys::fsec_t;
-            let mut tzn = std::ptr::null::<std::os::raw::c_char>();
-            pg_sys::timestamp2tm(
-                datum as i64,
-                &mut tz,
-                &mut tm,
-                &mut fsec,
-                &mut tzn,
-                std::ptr::null_mut(),
-            );
-            let date = time::Date::from_calendar_date(
-                tm.tm_year,
-                time::Month::try_from(tm.tm_mon as u8)
-                    .expect("Got month outside of range in TimestampWithTimeZone::from_datum"),
-                tm.tm_mday as u8,
-            )
-            .expect("failed to create date from TimestampWithTimeZone");
-
-            let time = time::Time::from_hms_micro(
-                tm.tm_hour as u8,
-                tm.tm_min as u8,
-                tm.tm_sec as u8,
-                fsec as u32,
-            )
-            .expect("failed to create time from TimestampWithTimeZonez");
-
-            Some(TimestampWithTimeZone(
-                time::PrimitiveDateTime::new(date, time)
-                    .assume_utc()
-                    .to_offset(
-                        UtcOffset::from_whole_seconds(tz)
-                            .expect("Unexpected error in `UtcOffset::from_whole_seconds` during `TimestampWithTimeZone::from_datum`")
-                    ),
-            ))
+            Some(TimestampWithTimeZone::from(datum as i64))
         }
     }
 }
@@ -118,76 +59,180 @@ impl IntoDatum for TimestampWithTimeZone {
     }
 }
 
-impl TimestampWithTimeZone {
-    /// This shifts the provided `time` back to UTC
-    pub fn new(time: time::PrimitiveDateTime, at_tz_offset: time::UtcOffset) -> Self {
-        TimestampWithTimeZone(
-            time.assume_utc()
-                .to_offset(
-                    UtcOffset::from_whole_seconds(-at_tz_offset.whole_seconds())
-                        .expect("Unexpected error in `UtcOffset::from_whole_seconds` during `TimestampWithTimeZone::new`")
-                ),
-        )
-    }
+/// The functions on a [`TimestampWithTimeZone`] that need the Postgres server's time zone
+/// database
+pub trait TimestampWithTimeZoneFunctions: Sized {
+    /// The date and time of this timestamp in the time zone `tz`, by the server's time zone
+    /// database, as Postgres' `timestamp2tm()` would give them
+    fn fields_in(&self, tz: &PgTimeZone) -> TimestampFields;
+
+    /// The timestamp with the date and time `fields` in the time zone `tz`, with the offset from
+    /// UTC in effect in `tz` then, as Postgres' `tm2timestamp()` would make it
+    ///
+    /// ## Panics
+    ///
+    /// If the date and time are out of range
+    fn from_fields_in(fields: &TimestampFields, tz: &PgTimeZone) -> Self;
+
+    /// This timestamp's date and time in the time zone `zone`, as
+    /// `timestamptz AT TIME ZONE zone` would give them.  `zone` is anything Postgres accepts
+    /// there, such as `"America/Denver"` or `"MST"`
+    fn at_time_zone(&self, zone: &str) -> Timestamp;
+
+    /// This timestamp truncated to the precision `field`, such as `"hour"` or `"month"`, in the
+    /// session's time zone, as `date_trunc(field, timestamptz)` would
+    fn trunc(&self, field: &str) -> Self;
+
+    /// This timestamp truncated to the precision `field` in the time zone `zone`, as
+    /// `date_trunc(field, timestamptz, zone)` would
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    fn trunc_in_zone(&self, field: &str, zone: &str) -> Self;
 }
 
-impl Deref for TimestampWithTimeZone {
-    type Target = time::OffsetDateTime;
+impl TimestampWithTimeZoneFunctions for TimestampWithTimeZone {
+    fn fields_in(&self, tz: &PgTimeZone) -> TimestampFields {
+        let mut tm = empty_tm();
+        let mut offset = 0i32;
+        let mut fsec = 0 as pg_sys::fsec_t;
+        let mut tzn = std::ptr::null::<std::os::raw::c_char>();
+        let result = unsafe {
+            pg_sys::timestamp2tm(
+                raw(self),
+                &mut offset,
+                &mut tm,
+                &mut fsec,
+                &mut tzn,
+                tz.as_ptr(),
+            )
+        };
+        if result != 0 {
+            panic!("timestamp out of range");
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+        TimestampFields {
+            year: tm.tm_year,
+            month: tm.tm_mon as u8,
+            day: tm.tm_mday as u8,
+            hour: tm.tm_hour as u8,
+            minute: tm.tm_min as u8,
+            second: tm.tm_sec as u8,
+            microsecond: fsec as u32,
+            // Postgres counts seconds west of UTC
+            utc_offset: -offset,
+        }
     }
+
+    fn from_fields_in(fields: &TimestampFields, tz: &PgTimeZone) -> Self {
+        let mut tm = empty_tm();
+        tm.tm_year = fields.year;
+        tm.tm_mon = fields.month as i32;
+        tm.tm_mday = fields.day as i32;
+        tm.tm_hour = fields.hour as i32;
+        tm.tm_min = fields.minute as i32;
+        tm.tm_sec = fields.second as i32;
+
+        let mut timestamp = 0 as pg_sys::TimestampTz;
+        unsafe {
+            let mut offset = pg_sys::DetermineTimeZoneOffset(&mut tm, tz.as_ptr());
+            if pg_sys::tm2timestamp(
+                &mut tm,
+                fields.microsecond as pg_sys::fsec_t,
+                &mut offset,
+                &mut timestamp,
+            ) != 0
+            {
+                panic!("timestamp out of range");
+            }
+        }
+        TimestampWithTimeZone::from(timestamp)
+    }
+
+    fn at_time_zone(&self, zone: &str) -> Timestamp {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamptz_zone,
+                vec![zone.into_datum(), self.into_datum()],
+            )
+        }
+        .expect("timestamptz_zone returned NULL")
+    }
+
+    fn trunc(&self, field: &str) -> Self {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamptz_trunc,
+                vec![field.into_datum(), self.into_datum()],
+            )
+        }
+        .expect("timestamptz_trunc returned NULL")
+    }
+
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    fn trunc_in_zone(&self, field: &str, zone: &str) -> Self {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamptz_trunc_zone,
+                vec![field.into_datum(), self.into_datum(), zone.into_datum()],
+            )
+        }
+        .expect("timestamptz_trunc_zone returned NULL")
+    }
+}
+
+fn raw(timestamp: &TimestampWithTimeZone) -> pg_sys::TimestampTz {
+    timestamp.into_datum().expect("timestamptz was NULL") as pg_sys::TimestampTz
 }
-impl DerefMut for TimestampWithTimeZone {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+
+fn empty_tm() -> pg_sys::pg_tm {
+    pg_sys::pg_tm {
+        tm_sec: 0,
+        tm_min: 0,
+        tm_hour: 0,
+        tm_mday: 0,
+        tm_mon: 0,
+        tm_year: 0,
+        tm_wday: 0,
+        tm_yday: 0,
+        tm_isdst: 0,
+        tm_gmtoff: 0,
+        tm_zone: std::ptr::null_mut(),
     }
 }
 
-impl serde::Serialize for TimestampWithTimeZone {
-    fn serialize<S>(
+/// `pgx`'s [`pgx_types::Server`], which does the arithmetic through Postgres' functions
+struct PgServer;
+
+impl pgx_types::Server for PgServer {
+    fn timestamptz_pl_interval(
         &self,
-        serializer: S,
-    ) -> std::result::Result<<S as serde::Serializer>::Ok, <S as serde::Serializer>::Error>
-    where
-        S: serde::Serializer,
-    {
-        if self.millisecond() > 0 {
-            serializer.serialize_str(
-                &self
-                    .format(
-                        &time::format_description::parse(&format!(
-                            "[year]-[month]-[day]T[hour]:[minute]:[second].{}-00",
-                            self.millisecond()
-                        ))
-                        .map_err(|e| {
-                            serde::ser::Error::custom(format!(
-                                "TimeStampWithTimeZone invalid format problem: {:?}",
-                                e
-                            ))
-                        })?,
-                    )
-                    .map_err(|e| {
-                        serde::ser::Error::custom(format!(
-                            "TimeStampWithTimeZone formatting problem: {:?}",
-                            e
-                        ))
-                    })?,
+        timestamp: TimestampWithTimeZone,
+        interval: Interval,
+    ) -> TimestampWithTimeZone {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamptz_pl_interval,
+                vec![timestamp.into_datum(), interval.into_datum()],
             )
-        } else {
-            serializer.serialize_str(
-                &self
-                    .format(&DEFAULT_TIMESTAMP_WITH_TIMEZONE_FORMAT)
-                    .map_err(|e| {
-                        serde::ser::Error::custom(format!(
-                            "TimeStampWithTimeZone formatting problem: {:?}",
-                            e
-                        ))
-                    })?,
+        }
+        .expect("timestamptz_pl_interval returned NULL")
+    }
+
+    fn timestamptz_mi_interval(
+        &self,
+        timestamp: TimestampWithTimeZone,
+        interval: Interval,
+    ) -> TimestampWithTimeZone {
+        unsafe {
+            direct_function_call(
+                pg_sys::timestamptz_mi_interval,
+                vec![timestamp.into_datum(), interval.into_datum()],
             )
         }
+        .expect("timestamptz_mi_interval returned NULL")
     }
 }
 
-static DEFAULT_TIMESTAMP_WITH_TIMEZONE_FORMAT: &[FormatItem<'static>] =
-    time::macros::format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]-00");
+/// Make `TimestampWithTimeZone ± Interval` add in the session's time zone, as Postgres does
+pub(crate) fn register_server() {
+    pgx_types::set_server(&PgServer);
+}
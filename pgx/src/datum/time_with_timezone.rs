@@ -7,8 +7,7 @@ All rights reserved.
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
 
-use crate::datum::time::Time;
-use crate::{pg_sys, FromDatum, IntoDatum, PgBox};
+use crate::{pg_sys, FromDatum, IntoDatum, PgBox, Time};
 use std::ops::{Deref, DerefMut};
 use time::format_description::FormatItem;
 
@@ -24,7 +23,7 @@ impl FromDatum for TimeWithTimeZone {
 
             let mut time = Time::from_datum(timetz.time as pg_sys::Datum, false, typoid)
                 .expect("failed to convert TimeWithTimeZone");
-            time.0 += time::Duration::seconds(timetz.zone as i64);
+            *time += time::Duration::seconds(timetz.zone as i64);
 
             Some(TimeWithTimeZone(time))
         }
@@ -53,7 +52,7 @@ impl TimeWithTimeZone {
     /// This shifts the provided `time` back to UTC using the specified `utc_offset`
     pub fn new(mut time: time::Time, at_tz_offset: time::UtcOffset) -> Self {
         time -= time::Duration::seconds(at_tz_offset.whole_seconds() as i64);
-        TimeWithTimeZone(Time(time))
+        TimeWithTimeZone(Time::new(time))
     }
 }
 
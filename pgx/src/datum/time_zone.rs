@@ -0,0 +1,70 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::pg_sys;
+use std::ffi::{CStr, CString};
+
+/// A time zone from the server's time zone database, for
+/// [`TimestampWithTimeZone::fields_in()`](crate::TimestampWithTimeZoneFunctions::fields_in) and
+/// [`TimestampWithTimeZone::from_fields_in()`](crate::TimestampWithTimeZoneFunctions::from_fields_in)
+#[derive(Debug, Clone, Copy)]
+pub struct PgTimeZone {
+    // Postgres never frees the time zones it loads
+    tz: *mut pg_sys::pg_tz,
+}
+
+impl PgTimeZone {
+    /// The session's `timezone`
+    pub fn session() -> Self {
+        PgTimeZone {
+            tz: unsafe { pg_sys::session_timezone },
+        }
+    }
+
+    /// The time zone called `name`, as in `pg_timezone_names`, such as `"America/Denver"` or
+    /// `"UTC"`, or `None` if there's no such time zone
+    pub fn lookup(name: &str) -> Option<Self> {
+        let name = CString::new(name).ok()?;
+        let tz = unsafe { pg_sys::pg_tzset(name.as_ptr()) };
+        if tz.is_null() {
+            None
+        } else {
+            Some(PgTimeZone { tz })
+        }
+    }
+
+    /// The name of this time zone
+    pub fn name(&self) -> &str {
+        unsafe { CStr::from_ptr(pg_sys::pg_get_timezone_name(self.tz)) }
+            .to_str()
+            .expect("time zone name is not valid UTF8")
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::pg_tz {
+        self.tz
+    }
+}
+
+/// The date and time of a `timestamp with time zone` in a time zone, as Postgres' `pg_tm` would
+/// have them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampFields {
+    pub year: i32,
+    /// 1 to 12
+    pub month: u8,
+    /// 1 to 31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub microsecond: u32,
+    /// Seconds east of UTC, which is ignored by
+    /// [`TimestampWithTimeZone::from_fields_in()`](crate::TimestampWithTimeZoneFunctions::from_fields_in)
+    pub utc_offset: i32,
+}
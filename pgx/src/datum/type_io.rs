@@ -0,0 +1,165 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Converting `Datum`s to and from their text form with their types' input and output functions.
+//!
+//! Which functions those are is cached per backend, by type oid, as looking them up in the
+//! catalogs for every value can cost more than converting it, and is forgotten whenever the
+//! type's `pg_type` row is invalidated
+use crate::{pg_sys, register_syscache_callback, syscache_hash_value};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+
+/// A type's output function, and whether its values are varlenas, from `getTypeOutputInfo()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeOutputInfo {
+    pub output_function: pg_sys::Oid,
+    pub is_varlena: bool,
+}
+
+/// A type's input function, and the oid the function is given as the type to parse, from
+/// `getTypeInputInfo()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeInputInfo {
+    pub input_function: pg_sys::Oid,
+    pub typioparam: pg_sys::Oid,
+}
+
+/// What's been looked up about a type
+#[derive(Default)]
+struct TypeIo {
+    /// the `TYPEOID` syscache hash value of the type's oid, to tell which invalidations are of it
+    hash_value: u32,
+    output: Option<TypeOutputInfo>,
+    input: Option<TypeInputInfo>,
+}
+
+thread_local! {
+    static TYPE_IO: RefCell<HashMap<pg_sys::Oid, TypeIo>> = RefCell::new(HashMap::new());
+    static INVALIDATION_REGISTERED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The output function of the type `typoid`, and whether its values are varlenas
+pub fn type_output_info(typoid: pg_sys::Oid) -> TypeOutputInfo {
+    cached(
+        typoid,
+        |io| io.output,
+        |io, output| io.output = Some(output),
+        || {
+            let mut output_function = pg_sys::InvalidOid;
+            let mut is_varlena = false;
+            unsafe { pg_sys::getTypeOutputInfo(typoid, &mut output_function, &mut is_varlena) };
+            TypeOutputInfo {
+                output_function,
+                is_varlena,
+            }
+        },
+    )
+}
+
+/// The input function of the type `typoid`, and the oid it's given as the type to parse
+pub fn type_input_info(typoid: pg_sys::Oid) -> TypeInputInfo {
+    cached(
+        typoid,
+        |io| io.input,
+        |io, input| io.input = Some(input),
+        || {
+            let mut input_function = pg_sys::InvalidOid;
+            let mut typioparam = pg_sys::InvalidOid;
+            unsafe { pg_sys::getTypeInputInfo(typoid, &mut input_function, &mut typioparam) };
+            TypeInputInfo {
+                input_function,
+                typioparam,
+            }
+        },
+    )
+}
+
+/// The text form of `datum`, a value of the type `typoid`, from the type's output function, as
+/// `SELECT value::text` would have it
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let datum = vec![1, 2, 3].into_datum().unwrap();
+/// assert_eq!(unsafe { datum_to_text(datum, pg_sys::INT4ARRAYOID) }, "{1,2,3}");
+/// ```
+///
+/// ## Safety
+///
+/// `datum` must be a value of the type `typoid`, and not `NULL`
+pub unsafe fn datum_to_text(datum: pg_sys::Datum, typoid: pg_sys::Oid) -> String {
+    let output = type_output_info(typoid);
+    let text = pg_sys::OidOutputFunctionCall(output.output_function, datum);
+    let owned = CStr::from_ptr(text).to_string_lossy().into_owned();
+    pg_sys::pfree(text as *mut std::os::raw::c_void);
+    owned
+}
+
+/// Parse `text` into a value of the type `typoid`, with the type's input function, as
+/// `SELECT 'text'::type` would.  `typmod` is the type's modifier, such as the precision and
+/// scale of a `numeric(5, 2)`, or -1 for none.
+///
+/// The value is allocated in the `CurrentMemoryContext`, and Postgres raises an `ERROR` if
+/// `text` isn't a valid value of the type
+pub fn text_to_datum(text: &str, typoid: pg_sys::Oid, typmod: i32) -> pg_sys::Datum {
+    let input = type_input_info(typoid);
+    let text = CString::new(text).expect("text contained a null byte");
+    unsafe {
+        pg_sys::OidInputFunctionCall(
+            input.input_function,
+            text.as_ptr() as *mut std::os::raw::c_char,
+            input.typioparam,
+            typmod,
+        )
+    }
+}
+
+/// Look up what `get` finds about `typoid` with `lookup`, unless it's cached.  Postgres isn't
+/// called while the cache is borrowed, as it may process invalidations, which change the cache
+fn cached<T: Copy>(
+    typoid: pg_sys::Oid,
+    get: fn(&TypeIo) -> Option<T>,
+    set: fn(&mut TypeIo, T),
+    lookup: impl FnOnce() -> T,
+) -> T {
+    if let Some(found) = TYPE_IO.with(|cache| cache.borrow().get(&typoid).and_then(get)) {
+        return found;
+    }
+
+    register_invalidation();
+    let found = lookup();
+    let hash_value = syscache_hash_value(
+        pg_sys::SysCacheIdentifier_TYPEOID,
+        &[typoid as pg_sys::Datum],
+    );
+    TYPE_IO.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let io = cache.entry(typoid).or_default();
+        io.hash_value = hash_value;
+        set(io, found);
+    });
+    found
+}
+
+fn register_invalidation() {
+    if INVALIDATION_REGISTERED.with(|registered| registered.replace(true)) {
+        return;
+    }
+
+    register_syscache_callback(pg_sys::SysCacheIdentifier_TYPEOID, |hash_value| {
+        TYPE_IO.with(|cache| match hash_value {
+            Some(hash_value) => cache
+                .borrow_mut()
+                .retain(|_, io| io.hash_value != hash_value),
+            None => cache.borrow_mut().clear(),
+        })
+    });
+}
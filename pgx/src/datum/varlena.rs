@@ -341,7 +341,10 @@ where
     }
 
     fn type_oid() -> u32 {
-        crate::rust_regtypein::<T>()
+        match T::SQL_NAME {
+            Some(sql_name) => crate::regtypein(sql_name),
+            None => crate::rust_regtypein::<T>(),
+        }
     }
 }
 
@@ -0,0 +1,92 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+use crate::{
+    direct_function_call_as_datum, pg_sys, vardata_any, varsize_any_exhdr, void_mut_ptr, FromDatum,
+    IntoDatum,
+};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::ops::Deref;
+
+/// An `xml` value, as its text.
+///
+/// Converting one into a `Datum` parses it, as `xml_in()` would, so a malformed document raises
+/// an ERROR rather than being stored
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+pub struct Xml(pub String);
+
+impl Deref for Xml {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for Xml {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Xml {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl FromDatum for Xml {
+    const NEEDS_TYPID: bool = false;
+
+    #[inline]
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _typoid: u32) -> Option<Xml> {
+        if is_null {
+            None
+        } else if datum == 0 {
+            panic!("xml datum is declared non-null but Datum is zero");
+        } else {
+            // an xml value is stored as its text
+            let varlena = datum as *mut pg_sys::varlena;
+            let detoasted = pg_sys::pg_detoast_datum_packed(varlena);
+            let len = varsize_any_exhdr(detoasted);
+            let data = vardata_any(detoasted);
+
+            let result = std::str::from_utf8(std::slice::from_raw_parts(data as *mut u8, len))
+                .expect("xml is not valid UTF8")
+                .to_owned();
+
+            if detoasted != varlena {
+                pg_sys::pfree(detoasted as void_mut_ptr);
+            }
+
+            Some(Xml(result))
+        }
+    }
+}
+
+impl IntoDatum for Xml {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let cstr = std::ffi::CString::new(self.0).expect("failed to convert xml into CString");
+        unsafe { direct_function_call_as_datum(pg_sys::xml_in, vec![cstr.as_c_str().into_datum()]) }
+    }
+
+    fn type_oid() -> u32 {
+        pg_sys::XMLOID
+    }
+}
+
+impl From<String> for Xml {
+    fn from(xml: String) -> Self {
+        Xml(xml)
+    }
+}
@@ -0,0 +1,146 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Recording dependencies between database objects in `pg_depend`, such as making an object an
+//! extension creates at runtime a member of the extension, so `DROP EXTENSION` drops it too
+use crate::pg_sys;
+use std::ffi::CString;
+
+/// How an object depends on the object it references, which decides what dropping the referenced
+/// object does to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgDependencyType {
+    /// Dropping the referenced object needs `CASCADE`, which drops this one too
+    Normal,
+    /// Dropping the referenced object drops this one, even without `CASCADE`
+    Auto,
+    /// This object is part of the implementation of the referenced object, so it can't be dropped
+    /// by itself, and dropping the referenced object drops it
+    Internal,
+    /// This object is a member of the referenced extension, so it can't be dropped by itself,
+    /// `DROP EXTENSION` drops it, and `pg_dump` leaves it out, expecting the extension's script
+    /// to create it
+    Extension,
+    /// This object isn't a member of the referenced extension, so `pg_dump` dumps it, but
+    /// `DROP EXTENSION` drops it
+    AutoExtension,
+}
+
+impl From<PgDependencyType> for pg_sys::DependencyType {
+    fn from(dependency: PgDependencyType) -> Self {
+        match dependency {
+            PgDependencyType::Normal => pg_sys::DependencyType_DEPENDENCY_NORMAL,
+            PgDependencyType::Auto => pg_sys::DependencyType_DEPENDENCY_AUTO,
+            PgDependencyType::Internal => pg_sys::DependencyType_DEPENDENCY_INTERNAL,
+            PgDependencyType::Extension => pg_sys::DependencyType_DEPENDENCY_EXTENSION,
+            PgDependencyType::AutoExtension => pg_sys::DependencyType_DEPENDENCY_AUTO_EXTENSION,
+        }
+    }
+}
+
+/// The address of a database object, which is a row of the catalog `class_id`, such as
+/// `pg_sys::RelationRelationId` for a relation
+pub fn object_address(class_id: pg_sys::Oid, object_id: pg_sys::Oid) -> pg_sys::ObjectAddress {
+    pg_sys::ObjectAddress {
+        classId: class_id,
+        objectId: object_id,
+        objectSubId: 0,
+    }
+}
+
+/// The address of the relation `relid`
+pub fn relation_address(relid: pg_sys::Oid) -> pg_sys::ObjectAddress {
+    object_address(pg_sys::RelationRelationId, relid)
+}
+
+/// The address of the function `funcid`
+pub fn function_address(funcid: pg_sys::Oid) -> pg_sys::ObjectAddress {
+    object_address(pg_sys::ProcedureRelationId, funcid)
+}
+
+/// The address of the type `typoid`
+pub fn type_address(typoid: pg_sys::Oid) -> pg_sys::ObjectAddress {
+    object_address(pg_sys::TypeRelationId, typoid)
+}
+
+/// The address of the extension called `name`, or `None` if it isn't installed
+pub fn extension_address(name: &str) -> Option<pg_sys::ObjectAddress> {
+    let name = CString::new(name).expect("extension name contains a null byte");
+    match unsafe { pg_sys::get_extension_oid(name.as_ptr(), true) } {
+        pg_sys::InvalidOid => None,
+        oid => Some(object_address(pg_sys::ExtensionRelationId, oid)),
+    }
+}
+
+/// Record that `depender` depends on `referenced`.  Like any other catalog change, it's only
+/// seen by later commands after `pg_sys::CommandCounterIncrement()`
+pub fn record_dependency(
+    depender: &pg_sys::ObjectAddress,
+    referenced: &pg_sys::ObjectAddress,
+    dependency: PgDependencyType,
+) {
+    unsafe { pg_sys::recordDependencyOn(depender, referenced, dependency.into()) }
+}
+
+/// Make `object`, which an extension created outside of its script, such as a table made by one
+/// of its functions, a member of the extension called `extension`, so that `DROP EXTENSION`
+/// drops it instead of leaving it behind.
+///
+/// While `CREATE EXTENSION` or `ALTER EXTENSION ... UPDATE` runs the extension's script, the
+/// objects it creates are already made members.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn create_queue(name: &str) {
+///     Spi::run(&format!("CREATE TABLE {} (id bigserial, payload jsonb)", quote_identifier(name)));
+///     let relation = PgRelation::open_with_name_and_share_lock(name).unwrap();
+///     add_to_extension(&relation_address(relation.oid()), "my_queues");
+/// }
+/// ```
+///
+/// ## Panics
+///
+/// If there's no extension called `extension`, or if `object` is already a member of another
+/// extension
+pub fn add_to_extension(object: &pg_sys::ObjectAddress, extension: &str) {
+    let extension_address = extension_address(extension)
+        .unwrap_or_else(|| panic!("extension \"{}\" does not exist", extension));
+    if let Some(current) = extension_of_object(object) {
+        if current != extension_address.objectId {
+            panic!(
+                "object is already a member of an extension other than \"{}\"",
+                extension
+            );
+        }
+        return;
+    }
+    record_dependency(object, &extension_address, PgDependencyType::Extension);
+}
+
+/// The oid of the extension `object` is a member of, if any
+pub fn extension_of_object(object: &pg_sys::ObjectAddress) -> Option<pg_sys::Oid> {
+    match unsafe { pg_sys::getExtensionOfObject(object.classId, object.objectId) } {
+        pg_sys::InvalidOid => None,
+        oid => Some(oid),
+    }
+}
+
+/// Is an extension's script being run by `CREATE EXTENSION` or `ALTER EXTENSION ... UPDATE`,
+/// and if so, what is the oid of the extension?
+pub fn creating_extension() -> Option<pg_sys::Oid> {
+    unsafe {
+        if pg_sys::creating_extension {
+            Some(pg_sys::CurrentExtensionObject)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Helper functions for working with Postgres `DOMAIN` types, such as those declared with
+//! `#[derive(PostgresDomain)]`
+use crate::pg_sys;
+
+/// Check `value`, of the domain's base type, against every constraint of `domain`, raising the
+/// same `ERROR` Postgres would if it violates one of them, such as a `CHECK` or `NOT NULL`
+pub fn domain_check(value: pg_sys::Datum, is_null: bool, domain: pg_sys::Oid) {
+    unsafe {
+        pg_sys::domain_check(
+            value,
+            is_null,
+            domain,
+            std::ptr::null_mut(),
+            pg_sys::CurrentMemoryContext,
+        )
+    }
+}
+
+/// The type a domain is over, looking through domains of domains, or `typoid` itself if it isn't
+/// a domain
+pub fn domain_base_type(typoid: pg_sys::Oid) -> pg_sys::Oid {
+    unsafe { pg_sys::getBaseType(typoid) }
+}
@@ -0,0 +1,243 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Backend-local hash tables built on Postgres' own `dynahash`, allocated in a memory context
+use crate::{pg_sys, PgMemoryContexts};
+use std::ffi::CString;
+use std::marker::PhantomData;
+
+/// How `dynahash` lays out each entry: the key must come first
+#[repr(C)]
+pub(crate) struct Entry<K, V> {
+    pub(crate) key: K,
+    pub(crate) value: V,
+}
+
+impl<K, V> Entry<K, V> {
+    pub(crate) fn size() -> usize {
+        std::mem::size_of::<Self>()
+    }
+}
+
+/// Look up `key` in `htab`, returning its entry (if any) and whether it was already there
+pub(crate) unsafe fn search<K, V>(
+    htab: *mut pg_sys::HTAB,
+    key: &K,
+    action: pg_sys::HASHACTION,
+) -> (*mut Entry<K, V>, bool) {
+    let mut found = false;
+    let entry = pg_sys::hash_search(
+        htab,
+        key as *const K as *const std::os::raw::c_void,
+        action,
+        &mut found,
+    );
+    (entry as *mut Entry<K, V>, found)
+}
+
+/// Set `key`'s value in its new or existing `entry`, returning the value it replaced
+pub(crate) unsafe fn set_value<K, V>(entry: *mut Entry<K, V>, found: bool, value: V) -> Option<V> {
+    // `dynahash` has already copied the key into a new entry
+    let slot = std::ptr::addr_of_mut!((*entry).value);
+    if found {
+        Some(std::mem::replace(&mut *slot, value))
+    } else {
+        slot.write(value);
+        None
+    }
+}
+
+/// A hash table of `Copy` keys and values, made with `hash_create()` like the ones Postgres uses
+/// for its own per-backend caches.
+///
+/// The table is allocated in a memory context of its own, under the one it's created in, so it
+/// goes away when that context is reset or deleted, along with everything else from the query or
+/// transaction it belongs to.  It must not be used after that, which is why it's best kept in
+/// the same place as other data from the context, like an executor node's state.  Tables that
+/// should last for the life of the backend can be created in the `TopMemoryContext`.
+///
+/// Keys are hashed and compared by their bytes, so they shouldn't have padding, which might not
+/// be the same between two otherwise equal keys.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn distinct_count(values: Vec<i64>) -> i64 {
+///     let mut seen = PgHashTable::<i64, ()>::new("distinct_count", values.len());
+///     for value in values {
+///         seen.insert(value, ());
+///     }
+///     seen.len() as i64
+/// }
+/// ```
+pub struct PgHashTable<K, V> {
+    htab: *mut pg_sys::HTAB,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K, V> PgHashTable<K, V>
+where
+    K: Copy + 'static,
+    V: Copy + 'static,
+{
+    /// Create an empty table in the `CurrentMemoryContext`, sized for `nelem` entries.  It grows
+    /// past that as needed.  `name` shows up in its memory context's name and in error messages
+    pub fn new(name: &str, nelem: usize) -> Self {
+        Self::new_in(&PgMemoryContexts::CurrentMemoryContext, name, nelem)
+    }
+
+    /// Create an empty table in `memory_context`, sized for `nelem` entries
+    pub fn new_in(memory_context: &PgMemoryContexts, name: &str, nelem: usize) -> Self {
+        let name = CString::new(name).expect("CString::new() failed");
+        let mut info = pg_sys::HASHCTL {
+            keysize: std::mem::size_of::<K>(),
+            entrysize: Entry::<K, V>::size(),
+            hcxt: memory_context.value(),
+            ..Default::default()
+        };
+
+        let htab = unsafe {
+            pg_sys::hash_create(
+                name.as_ptr(),
+                nelem.max(1) as _,
+                // only pg14 declares it `*const`
+                std::ptr::addr_of_mut!(info),
+                (pg_sys::HASH_ELEM | pg_sys::HASH_BLOBS | pg_sys::HASH_CONTEXT) as i32,
+            )
+        };
+        PgHashTable {
+            htab,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::HTAB {
+        self.htab
+    }
+
+    /// A copy of the value for `key`, if any
+    pub fn get(&self, key: &K) -> Option<V> {
+        let (entry, _) = unsafe { search::<K, V>(self.htab, key, pg_sys::HASHACTION_HASH_FIND) };
+        unsafe { entry.as_ref() }.map(|entry| entry.value)
+    }
+
+    /// The value for `key`, which can be changed in place
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let (entry, _) = unsafe { search::<K, V>(self.htab, key, pg_sys::HASHACTION_HASH_FIND) };
+        unsafe { entry.as_mut() }.map(|entry| &mut entry.value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Set the value for `key`, returning the value it replaced
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        unsafe {
+            let (entry, found) = search(self.htab, &key, pg_sys::HASHACTION_HASH_ENTER);
+            set_value(entry, found, value)
+        }
+    }
+
+    /// Remove `key` from the table, returning its value
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (entry, _) = unsafe { search::<K, V>(self.htab, key, pg_sys::HASHACTION_HASH_REMOVE) };
+        // the entry is on the free list now, but it's still there to read
+        unsafe { entry.as_ref() }.map(|entry| entry.value)
+    }
+
+    /// Remove every entry for which `keep` returns false
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut keep: F) {
+        let removed = self
+            .iter()
+            .filter(|(key, value)| !keep(key, value))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        for key in removed {
+            self.remove(&key);
+        }
+    }
+
+    /// Remove every entry
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false)
+    }
+
+    /// How many entries the table holds
+    pub fn len(&self) -> usize {
+        unsafe { pg_sys::hash_get_num_entries(self.htab) as usize }
+    }
+
+    /// Does the table hold no entries?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every entry, in no particular order
+    pub fn iter(&self) -> PgHashTableIter<'_, K, V> {
+        unsafe { PgHashTableIter::new(self.htab) }
+    }
+
+    /// Free the table now, rather than when its memory context goes away
+    pub fn destroy(self) {
+        unsafe {
+            pg_sys::hash_destroy(self.htab);
+        }
+    }
+}
+
+/// Copies of a [`PgHashTable`]'s or [`PgShmemHash`](crate::PgShmemHash)'s entries
+pub struct PgHashTableIter<'a, K, V> {
+    status: pg_sys::HASH_SEQ_STATUS,
+    done: bool,
+    _marker: PhantomData<&'a (K, V)>,
+}
+
+impl<K, V> PgHashTableIter<'_, K, V> {
+    /// Scan `htab`, which must not be modified, or freed, until the iterator is dropped
+    pub(crate) unsafe fn new(htab: *mut pg_sys::HTAB) -> Self {
+        let mut status = std::mem::zeroed::<pg_sys::HASH_SEQ_STATUS>();
+        pg_sys::hash_seq_init(&mut status, htab);
+        PgHashTableIter {
+            status,
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Copy, V: Copy> Iterator for PgHashTableIter<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let entry = unsafe { pg_sys::hash_seq_search(&mut self.status) } as *mut Entry<K, V>;
+        match unsafe { entry.as_ref() } {
+            Some(entry) => Some((entry.key, entry.value)),
+            None => {
+                // `hash_seq_search()` ends the scan itself once it runs out of entries
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl<K, V> Drop for PgHashTableIter<'_, K, V> {
+    fn drop(&mut self) {
+        if !self.done {
+            unsafe {
+                pg_sys::hash_seq_term(&mut self.status);
+            }
+        }
+    }
+}
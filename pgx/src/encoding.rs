@@ -0,0 +1,191 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Character encodings, and converting text between them with Postgres' conversions
+//!
+//! Text in a database is in the database's encoding, which needn't be UTF-8, so bytes from outside
+//! of Postgres, like a CSV file in `LATIN1` or a Shift JIS payload, must be converted to it before
+//! they're made into a text datum, and Rust `String`s must be converted to and from UTF-8.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn import_latin1(bytes: &[u8]) -> String {
+//!     // an ERROR is raised if the bytes aren't `LATIN1`, or a character isn't in UTF-8
+//!     PgEncoding::LATIN1.decode(bytes)
+//! }
+//! ```
+use crate::{pg_sys, rust_byte_slice_to_bytea, PgBox};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+/// A character encoding Postgres knows, such as a database's or a client's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PgEncoding(i32);
+
+impl PgEncoding {
+    pub const SQL_ASCII: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_SQL_ASCII as i32);
+    pub const UTF8: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_UTF8 as i32);
+    pub const LATIN1: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_LATIN1 as i32);
+    pub const LATIN9: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_LATIN9 as i32);
+    pub const WIN1252: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_WIN1252 as i32);
+    pub const EUC_JP: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_EUC_JP as i32);
+    pub const SJIS: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_SJIS as i32);
+    pub const GB18030: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_GB18030 as i32);
+    pub const BIG5: PgEncoding = PgEncoding(pg_sys::pg_enc_PG_BIG5 as i32);
+
+    /// The current database's encoding, which its text is in
+    pub fn database() -> Self {
+        PgEncoding(unsafe { pg_sys::GetDatabaseEncoding() })
+    }
+
+    /// The client's encoding, as set with `client_encoding`, which Postgres converts the text it
+    /// sends and receives to and from
+    pub fn client() -> Self {
+        PgEncoding(unsafe { pg_sys::pg_get_client_encoding() })
+    }
+
+    /// The encoding called `name`, ignoring case and punctuation, including aliases like
+    /// `"shift_jis"` and `"latin1"`, or `None` if Postgres doesn't know it
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = CString::new(name).ok()?;
+        match unsafe { pg_sys::pg_char_to_encoding(name.as_ptr()) } {
+            id if id < 0 => None,
+            id => Some(PgEncoding(id)),
+        }
+    }
+
+    /// The encoding with Postgres' number `id`, like a `pg_database.encoding`, or `None` if
+    /// there isn't one
+    #[allow(clippy::unnecessary_lazy_evaluations)]
+    pub fn from_id(id: i32) -> Option<Self> {
+        (0..pg_sys::pg_enc__PG_LAST_ENCODING_ as i32)
+            .contains(&id)
+            .then(|| PgEncoding(id))
+    }
+
+    /// Postgres' number for the encoding
+    pub fn id(&self) -> i32 {
+        self.0
+    }
+
+    /// The name Postgres gives the encoding, like `"UTF8"` or `"SJIS"`
+    pub fn name(&self) -> &'static str {
+        unsafe { CStr::from_ptr(pg_sys::pg_encoding_to_char(self.0)) }
+            .to_str()
+            .expect("encoding name isn't valid UTF8")
+    }
+
+    /// Can a database be in this encoding?  Some, like `SJIS`, are only for clients
+    pub fn is_server_encoding(&self) -> bool {
+        unsafe { pg_sys::pg_valid_server_encoding_id(self.0) != 0 }
+    }
+
+    /// The most bytes one character takes in the encoding
+    pub fn max_char_len(&self) -> usize {
+        unsafe { pg_sys::pg_encoding_max_length(self.0) as usize }
+    }
+
+    /// Are `bytes` valid in the encoding?  Any bytes but `0` are, in `SQL_ASCII`
+    pub fn is_valid(&self, bytes: &[u8]) -> bool {
+        unsafe { verify(*self, bytes, true) }
+    }
+
+    /// `bytes`, which are in this encoding, in UTF-8.
+    ///
+    /// ## Panics
+    ///
+    /// Raises a Postgres ERROR if `bytes` aren't valid in this encoding, or one of their
+    /// characters can't be in UTF-8
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let utf8 = convert_encoding(bytes, *self, PgEncoding::UTF8);
+        // the conversion verified it
+        unsafe { String::from_utf8_unchecked(utf8) }
+    }
+
+    /// `s` in this encoding, with the same ERRORs as [`PgEncoding::decode()`]
+    pub fn encode(&self, s: &str) -> Vec<u8> {
+        convert_encoding(s.as_bytes(), PgEncoding::UTF8, *self)
+    }
+
+    /// `bytes`, which are in this encoding, in the database's encoding, with the same ERRORs as
+    /// [`PgEncoding::decode()`]
+    pub fn to_database(&self, bytes: &[u8]) -> Vec<u8> {
+        convert_encoding(bytes, *self, PgEncoding::database())
+    }
+
+    /// `bytes`, which are in the database's encoding, in this encoding, with the same ERRORs as
+    /// [`PgEncoding::decode()`]
+    pub fn from_database(&self, bytes: &[u8]) -> Vec<u8> {
+        convert_encoding(bytes, PgEncoding::database(), *self)
+    }
+
+    /// A `text` of `bytes`, which are in this encoding, converted to the database's, with the
+    /// same ERRORs as [`PgEncoding::decode()`].  Use it in place of
+    /// [`rust_str_to_text_p()`](crate::rust_str_to_text_p) for text that isn't UTF-8, or for a
+    /// database that isn't
+    pub fn to_text_p(&self, bytes: &[u8]) -> PgBox<pg_sys::text> {
+        // a text is laid out as a bytea is
+        rust_byte_slice_to_bytea(&self.to_database(bytes))
+    }
+}
+
+impl std::fmt::Display for PgEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// `bytes`, which are in the `from` encoding, in the `to` encoding, using Postgres' conversion
+/// between them, like `convert()` in SQL.  Converting from `SQL_ASCII`, or to it, only checks that
+/// `bytes` are valid in the other encoding.
+///
+/// ## Panics
+///
+/// Raises a Postgres ERROR if `bytes` aren't valid in the `from` encoding, one of their characters
+/// can't be in the `to` encoding, or there's no conversion between them
+pub fn convert_encoding(bytes: &[u8], from: PgEncoding, to: PgEncoding) -> Vec<u8> {
+    unsafe {
+        // Postgres doesn't check bytes it doesn't have to convert
+        if from == to || to == PgEncoding::SQL_ASCII {
+            verify(from, bytes, false);
+            return bytes.to_vec();
+        } else if from == PgEncoding::SQL_ASCII {
+            verify(to, bytes, false);
+            return bytes.to_vec();
+        }
+
+        let converted = pg_sys::pg_do_encoding_conversion(
+            bytes.as_ptr() as *mut u8,
+            bytes.len() as i32,
+            from.0,
+            to.0,
+        );
+        if std::ptr::eq(converted as *const u8, bytes.as_ptr()) {
+            bytes.to_vec()
+        } else {
+            let result = CStr::from_ptr(converted as *const c_char)
+                .to_bytes()
+                .to_vec();
+            pg_sys::pfree(converted as *mut std::ffi::c_void);
+            result
+        }
+    }
+}
+
+/// Are `bytes` valid in `encoding`?  If they aren't, it raises an ERROR, unless `no_error`
+unsafe fn verify(encoding: PgEncoding, bytes: &[u8], no_error: bool) -> bool {
+    pg_sys::pg_verify_mbstr(
+        encoding.0,
+        bytes.as_ptr() as *const c_char,
+        bytes.len() as i32,
+        no_error,
+    )
+}
@@ -0,0 +1,349 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Postgres' "expanded" representation of varlena values, which can be read and changed in place
+//! rather than being flattened and copied every time they're passed around
+//!
+//! A function that receives an expanded value gets a pointer to it instead of its flat, on-disk
+//! form.  PL/pgSQL keeps the arrays and records in its variables expanded, and an aggregate
+//! whose transition function returns a read-write expanded value gets it back on the next call,
+//! to change in place, instead of a copy.
+use crate::varlena::{set_varsize, varatt_is_1b_e, vartag_external, vartag_is_expanded};
+use crate::{pg_guard, pg_sys, void_mut_ptr, FromDatum, PgMemoryContexts};
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// Is `datum` a pointer to an expanded object, either read-write or read-only?
+///
+/// # Safety
+///
+/// `datum` must be a varlena
+pub unsafe fn datum_is_expanded(datum: pg_sys::Datum) -> bool {
+    let ptr = datum as *const pg_sys::varlena;
+    varatt_is_1b_e(ptr) && vartag_is_expanded(vartag_external(ptr) as pg_sys::vartag_external)
+}
+
+/// Is `datum` a read-write pointer to an expanded object, which the function it's passed to may
+/// change in place?
+///
+/// # Safety
+///
+/// `datum` must be a varlena
+pub unsafe fn datum_is_expanded_rw(datum: pg_sys::Datum) -> bool {
+    let ptr = datum as *const pg_sys::varlena;
+    varatt_is_1b_e(ptr)
+        && vartag_external(ptr) as pg_sys::vartag_external
+            == pg_sys::vartag_external_VARTAG_EXPANDED_RW
+}
+
+/// An array argument, read from Postgres' expanded form of it without flattening it first.
+///
+/// Arrays that are already expanded, like those passed from PL/pgSQL variables, are used as is,
+/// and flat arrays are expanded, which costs about the same as [`Array`](crate::Array)
+/// deconstructing them
+pub struct ExpandedArray<'a, T: FromDatum> {
+    eah: *mut pg_sys::ExpandedArrayHeader,
+    was_expanded: bool,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T: FromDatum> ExpandedArray<'a, T> {
+    /// How many elements does the array have, across all its dimensions?
+    pub fn len(&self) -> usize {
+        unsafe { (*self.eah).nelems as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The type of the array's elements
+    pub fn element_type(&self) -> pg_sys::Oid {
+        unsafe { (*self.eah).element_type }
+    }
+
+    /// The length of each of the array's dimensions
+    pub fn dims(&self) -> &[i32] {
+        unsafe {
+            let eah = &*self.eah;
+            if eah.ndims == 0 {
+                &[]
+            } else {
+                std::slice::from_raw_parts(eah.dims, eah.ndims as usize)
+            }
+        }
+    }
+
+    /// Was the array already expanded when it was passed in?
+    pub fn was_expanded(&self) -> bool {
+        self.was_expanded
+    }
+
+    /// The element at `i`, counting from zero across all the dimensions, which is `Some(None)`
+    /// if the element is NULL and `None` if it's past the end of the array
+    #[allow(clippy::option_option)]
+    pub fn get(&self, i: usize) -> Option<Option<T>> {
+        if i >= self.len() {
+            return None;
+        }
+        unsafe {
+            let eah = &*self.eah;
+            let is_null = !eah.dnulls.is_null() && *eah.dnulls.add(i);
+            Some(T::from_datum(
+                *eah.dvalues.add(i),
+                is_null,
+                eah.element_type,
+            ))
+        }
+    }
+
+    /// Iterate over the elements, which are `None` when NULL
+    pub fn iter(&self) -> impl Iterator<Item = Option<T>> + '_ {
+        (0..self.len()).map(move |i| self.get(i).unwrap())
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::ExpandedArrayHeader {
+        self.eah
+    }
+}
+
+impl<'a, T: FromDatum> FromDatum for ExpandedArray<'a, T> {
+    unsafe fn from_datum(datum: pg_sys::Datum, is_null: bool, _: pg_sys::Oid) -> Option<Self> {
+        if is_null {
+            return None;
+        } else if datum == 0 {
+            panic!("array was flagged not null but datum is zero");
+        }
+
+        let was_expanded = datum_is_expanded(datum);
+        let eah = if was_expanded {
+            // read-only ones too, which we won't change
+            pg_sys::DatumGetEOHP(datum) as *mut pg_sys::ExpandedArrayHeader
+        } else {
+            pg_sys::DatumGetExpandedArray(datum)
+        };
+        assert_eq!(
+            (*eah).ea_magic as u32,
+            pg_sys::EA_MAGIC,
+            "expanded object is not an array"
+        );
+        pg_sys::deconstruct_expanded_array(eah);
+
+        Some(ExpandedArray {
+            eah,
+            was_expanded,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// A Rust type that's the expanded form of a varlena type, for use with [`PgExpanded`].
+///
+/// The flat form is what's stored on disk and passed around when the value isn't expanded, and
+/// is whatever the type's input and output functions, and `#[derive(PostgresType)]`, use
+pub trait PgExpandedObject: Sized + 'static {
+    /// How many bytes the flat form takes, not counting the varlena header
+    fn flat_size(&self) -> usize;
+
+    /// Write the flat form into `data`, which is [`PgExpandedObject::flat_size()`] bytes long
+    fn flatten_into(&self, data: &mut [u8]);
+
+    /// Read the flat form, without its varlena header
+    fn expand(data: &[u8]) -> Self;
+}
+
+/// What every [`PgExpanded`] object looks like, after the header Postgres knows about
+#[repr(C)]
+struct ExpandedObject {
+    hdr: pg_sys::ExpandedObjectHeader,
+    type_id: TypeId,
+    value: void_mut_ptr,
+    flat_size: unsafe fn(void_mut_ptr) -> usize,
+    flatten_into: unsafe fn(void_mut_ptr, &mut [u8]),
+}
+
+static EXPANDED_OBJECT_METHODS: pg_sys::ExpandedObjectMethods = pg_sys::ExpandedObjectMethods {
+    get_flat_size: Some(get_flat_size),
+    flatten_into: Some(flatten_into),
+};
+
+/// A value of a [`PgExpandedObject`] type, in a memory context of its own, which Postgres can pass
+/// between functions by reference, and flattens only when it has to store it.
+///
+/// Dereferences to the value, so it can be changed in place.  As an aggregate's transition
+/// state, the same object comes back on every call, rather than a copy of it:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(Default)]
+/// struct Counts(Vec<i64>);
+///
+/// impl PgExpandedObject for Counts {
+///     fn flat_size(&self) -> usize {
+///         self.0.len() * 8
+///     }
+///
+///     fn flatten_into(&self, data: &mut [u8]) {
+///         for (count, bytes) in self.0.iter().zip(data.chunks_mut(8)) {
+///             bytes.copy_from_slice(&count.to_ne_bytes());
+///         }
+///     }
+///
+///     fn expand(data: &[u8]) -> Self {
+///         Counts(data.chunks(8).map(|bytes| i64::from_ne_bytes(bytes.try_into().unwrap())).collect())
+///     }
+/// }
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn counts_transition(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+///     let mut counts = match pg_getarg_datum(fcinfo, 0) {
+///         Some(state) => unsafe { PgExpanded::<Counts>::from_datum(state) },
+///         None => {
+///             // the state lives as long as the aggregate's memory context
+///             let mut aggcontext = std::ptr::null_mut();
+///             unsafe { pg_sys::AggCheckCallContext(fcinfo, &mut aggcontext) };
+///             PgExpanded::new_in(&PgMemoryContexts::For(aggcontext), Counts::default())
+///         }
+///     };
+///     counts.0.push(pg_getarg::<i64>(fcinfo, 1).unwrap_or_default());
+///     counts.into_datum()
+/// }
+/// ```
+pub struct PgExpanded<T: PgExpandedObject> {
+    object: *mut ExpandedObject,
+    _marker: PhantomData<T>,
+}
+
+impl<T: PgExpandedObject> PgExpanded<T> {
+    /// Expand `value` into a new memory context under the `CurrentMemoryContext`
+    pub fn new(value: T) -> Self {
+        Self::new_in(&PgMemoryContexts::CurrentMemoryContext, value)
+    }
+
+    /// Expand `value` into a new memory context under `parent`.  The value is dropped when that
+    /// context is deleted, such as when Postgres is done with the object
+    #[allow(clippy::manual_c_str_literals)]
+    pub fn new_in(parent: &PgMemoryContexts, value: T) -> Self {
+        unsafe {
+            let mut context = PgMemoryContexts::For(pg_sys::AllocSetContextCreateExtended(
+                parent.value(),
+                "expanded object\0".as_ptr() as *const std::os::raw::c_char,
+                pg_sys::ALLOCSET_DEFAULT_MINSIZE as usize,
+                pg_sys::ALLOCSET_DEFAULT_INITSIZE as usize,
+                pg_sys::ALLOCSET_DEFAULT_MAXSIZE as usize,
+            ));
+            let object = context.palloc0_struct::<ExpandedObject>();
+            pg_sys::EOH_init_header(
+                &mut (*object).hdr,
+                &EXPANDED_OBJECT_METHODS,
+                context.value(),
+            );
+            (*object).type_id = TypeId::of::<T>();
+            (*object).value = context.leak_and_drop_on_delete(value) as void_mut_ptr;
+            (*object).flat_size = flat_size_of::<T>;
+            (*object).flatten_into = flatten_value_into::<T>;
+
+            PgExpanded {
+                object,
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    /// The value `datum` points to.
+    ///
+    /// A read-write pointer to a `PgExpanded<T>` is used as is, so changes to it are seen by
+    /// whoever passed it.  Anything else, like a read-only pointer or a flat value, is expanded
+    /// into a new object in the `CurrentMemoryContext`.
+    ///
+    /// # Safety
+    ///
+    /// `datum` must be a non-NULL value of the varlena type `T` is the expanded form of
+    pub unsafe fn from_datum(datum: pg_sys::Datum) -> Self {
+        if datum_is_expanded_rw(datum) {
+            let object = pg_sys::DatumGetEOHP(datum) as *mut ExpandedObject;
+            if std::ptr::eq((*object).hdr.eoh_methods, &EXPANDED_OBJECT_METHODS)
+                && (*object).type_id == TypeId::of::<T>()
+            {
+                return PgExpanded {
+                    object,
+                    _marker: PhantomData,
+                };
+            }
+        }
+
+        // this flattens expanded values of any kind
+        let flat = pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena);
+        let data = crate::varlena::varlena_to_byte_slice(flat);
+        Self::new(T::expand(data))
+    }
+
+    /// A read-write pointer to the object, for returning it.  Postgres takes care of the object
+    /// from then on, and deletes it once it has no more use for it
+    pub fn into_datum(self) -> pg_sys::Datum {
+        unsafe { (*self.object).hdr.eoh_rw_ptr.as_ptr() as pg_sys::Datum }
+    }
+
+    /// A read-only pointer to the object, for passing it to functions that shouldn't change it
+    pub fn as_read_only_datum(&self) -> pg_sys::Datum {
+        unsafe { (*self.object).hdr.eoh_ro_ptr.as_ptr() as pg_sys::Datum }
+    }
+
+    /// The memory context the object lives in
+    pub fn memory_context(&self) -> PgMemoryContexts {
+        PgMemoryContexts::For(unsafe { (*self.object).hdr.eoh_context })
+    }
+}
+
+impl<T: PgExpandedObject> Deref for PgExpanded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*((*self.object).value as *const T) }
+    }
+}
+
+impl<T: PgExpandedObject> DerefMut for PgExpanded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *((*self.object).value as *mut T) }
+    }
+}
+
+unsafe fn flat_size_of<T: PgExpandedObject>(value: void_mut_ptr) -> usize {
+    (*(value as *const T)).flat_size()
+}
+
+unsafe fn flatten_value_into<T: PgExpandedObject>(value: void_mut_ptr, data: &mut [u8]) {
+    (*(value as *const T)).flatten_into(data)
+}
+
+#[pg_guard]
+unsafe extern "C" fn get_flat_size(eohptr: *mut pg_sys::ExpandedObjectHeader) -> pg_sys::Size {
+    let object = eohptr as *mut ExpandedObject;
+    ((*object).flat_size)((*object).value) + pg_sys::VARHDRSZ
+}
+
+#[pg_guard]
+unsafe extern "C" fn flatten_into(
+    eohptr: *mut pg_sys::ExpandedObjectHeader,
+    result: void_mut_ptr,
+    allocated_size: pg_sys::Size,
+) {
+    let object = eohptr as *mut ExpandedObject;
+    std::ptr::write_bytes(result as *mut u8, 0, allocated_size);
+    set_varsize(result as *mut pg_sys::varlena, allocated_size as i32);
+    let data = std::slice::from_raw_parts_mut(
+        (result as *mut u8).add(pg_sys::VARHDRSZ),
+        allocated_size - pg_sys::VARHDRSZ,
+    );
+    ((*object).flatten_into)((*object).value, data)
+}
@@ -0,0 +1,491 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Adding to the output of `EXPLAIN`, from custom scans, foreign data wrappers, and functions
+//!
+//! Custom scan providers and foreign data wrappers are handed Postgres' `ExplainState` by their
+//! explain callbacks, which [`PgExplainState`] wraps.  Functions aren't, but once
+//! [`register_explain_hooks()`] has been called from `_PG_init()`, they can use
+//! [`add_explain_property()`] to add to the output of the `EXPLAIN ANALYZE` running them.
+use crate::misc::cstring;
+use crate::{pg_guard, pg_sys};
+use std::cell::RefCell;
+
+/// The output format requested by `EXPLAIN (FORMAT ...)`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExplainFormat {
+    Text,
+    Xml,
+    Json,
+    Yaml,
+}
+
+/// The value of a property added to `EXPLAIN` output
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExplainProperty {
+    Text(String),
+    /// An integer, whose `unit` is only shown in the text format
+    Integer {
+        value: i64,
+        unit: Option<&'static str>,
+    },
+    /// A float, shown with `ndigits` digits after the decimal point and whose `unit` is only
+    /// shown in the text format
+    Float {
+        value: f64,
+        unit: Option<&'static str>,
+        ndigits: i32,
+    },
+    Bool(bool),
+}
+
+/// A safe wrapper around the `ExplainState` of an `EXPLAIN` in progress, such as the one given to
+/// a custom scan's `ExplainCustomScan` or a foreign data wrapper's `ExplainForeignScan` callback
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_guard]
+/// unsafe extern "C" fn explain_foreign_scan(
+///     _node: *mut pg_sys::ForeignScanState,
+///     es: *mut pg_sys::ExplainState,
+/// ) {
+///     let mut es = PgExplainState::from_pg(es);
+///     es.add_text("Remote Server", "example.com");
+///     if es.analyze() {
+///         es.add_integer("Remote Requests", None, 3);
+///     }
+/// }
+/// ```
+pub struct PgExplainState {
+    es: *mut pg_sys::ExplainState,
+}
+
+impl PgExplainState {
+    /// Wrap the `ExplainState` Postgres handed us.  It must not be null, and must be for an
+    /// `EXPLAIN` that is still running
+    pub unsafe fn from_pg(es: *mut pg_sys::ExplainState) -> Self {
+        assert!(!es.is_null(), "ExplainState is null");
+        PgExplainState { es }
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::ExplainState {
+        self.es
+    }
+
+    /// Is this an `EXPLAIN ANALYZE`, which runs the statement?
+    pub fn analyze(&self) -> bool {
+        unsafe { (*self.es).analyze }
+    }
+
+    /// Was `VERBOSE` requested?
+    pub fn verbose(&self) -> bool {
+        unsafe { (*self.es).verbose }
+    }
+
+    /// Are estimated costs being shown?
+    pub fn costs(&self) -> bool {
+        unsafe { (*self.es).costs }
+    }
+
+    /// Was `BUFFERS` requested?
+    pub fn buffers(&self) -> bool {
+        unsafe { (*self.es).buffers }
+    }
+
+    /// Are actual times being shown?
+    pub fn timing(&self) -> bool {
+        unsafe { (*self.es).timing }
+    }
+
+    /// Is the summary, with planning and execution times, being shown?
+    pub fn summary(&self) -> bool {
+        unsafe { (*self.es).summary }
+    }
+
+    pub fn format(&self) -> ExplainFormat {
+        match unsafe { (*self.es).format } {
+            pg_sys::ExplainFormat_EXPLAIN_FORMAT_XML => ExplainFormat::Xml,
+            pg_sys::ExplainFormat_EXPLAIN_FORMAT_JSON => ExplainFormat::Json,
+            pg_sys::ExplainFormat_EXPLAIN_FORMAT_YAML => ExplainFormat::Yaml,
+            _ => ExplainFormat::Text,
+        }
+    }
+
+    /// Add a property whose value is text
+    pub fn add_text(&mut self, label: &str, value: &str) {
+        let label = cstring(label, "EXPLAIN label");
+        let value = cstring(value, "EXPLAIN value");
+        unsafe { pg_sys::ExplainPropertyText(label.as_ptr(), value.as_ptr(), self.es) }
+    }
+
+    /// Add a property whose value is an integer, optionally followed by its `unit` in the text
+    /// format
+    #[cfg(not(feature = "pg10"))]
+    pub fn add_integer(&mut self, label: &str, unit: Option<&str>, value: i64) {
+        let label = cstring(label, "EXPLAIN label");
+        let unit = unit.map(|unit| cstring(unit, "EXPLAIN unit"));
+        unsafe {
+            pg_sys::ExplainPropertyInteger(
+                label.as_ptr(),
+                unit.as_ref().map_or(std::ptr::null(), |unit| unit.as_ptr()),
+                value,
+                self.es,
+            )
+        }
+    }
+
+    /// Add a property whose value is an integer, optionally followed by its `unit` in the text
+    /// format
+    #[cfg(feature = "pg10")]
+    pub fn add_integer(&mut self, label: &str, unit: Option<&str>, value: i64) {
+        // Postgres 10 doesn't know about units, so does what later versions do by hand
+        match unit {
+            Some(unit) if self.format() == ExplainFormat::Text => {
+                self.add_text(label, &format!("{} {}", value, unit))
+            }
+            _ => {
+                let label = cstring(label, "EXPLAIN label");
+                unsafe {
+                    pg_sys::ExplainPropertyLong(
+                        label.as_ptr(),
+                        value as std::os::raw::c_long,
+                        self.es,
+                    )
+                }
+            }
+        }
+    }
+
+    /// Add a property whose value is a float, shown with `ndigits` digits after the decimal point,
+    /// optionally followed by its `unit` in the text format
+    #[cfg(not(feature = "pg10"))]
+    pub fn add_float(&mut self, label: &str, unit: Option<&str>, value: f64, ndigits: i32) {
+        let label = cstring(label, "EXPLAIN label");
+        let unit = unit.map(|unit| cstring(unit, "EXPLAIN unit"));
+        unsafe {
+            pg_sys::ExplainPropertyFloat(
+                label.as_ptr(),
+                unit.as_ref().map_or(std::ptr::null(), |unit| unit.as_ptr()),
+                value,
+                ndigits,
+                self.es,
+            )
+        }
+    }
+
+    /// Add a property whose value is a float, shown with `ndigits` digits after the decimal point,
+    /// optionally followed by its `unit` in the text format
+    #[cfg(feature = "pg10")]
+    pub fn add_float(&mut self, label: &str, unit: Option<&str>, value: f64, ndigits: i32) {
+        match unit {
+            Some(unit) if self.format() == ExplainFormat::Text => self.add_text(
+                label,
+                &format!("{:.*} {}", ndigits.max(0) as usize, value, unit),
+            ),
+            _ => {
+                let label = cstring(label, "EXPLAIN label");
+                unsafe { pg_sys::ExplainPropertyFloat(label.as_ptr(), value, ndigits, self.es) }
+            }
+        }
+    }
+
+    /// Add a property whose value is a boolean
+    pub fn add_bool(&mut self, label: &str, value: bool) {
+        let label = cstring(label, "EXPLAIN label");
+        unsafe { pg_sys::ExplainPropertyBool(label.as_ptr(), value, self.es) }
+    }
+
+    /// Add a property of any kind
+    pub fn add_property(&mut self, label: &str, property: &ExplainProperty) {
+        match property {
+            ExplainProperty::Text(value) => self.add_text(label, value),
+            ExplainProperty::Integer { value, unit } => self.add_integer(label, *unit, *value),
+            ExplainProperty::Float {
+                value,
+                unit,
+                ndigits,
+            } => self.add_float(label, *unit, *value, *ndigits),
+            ExplainProperty::Bool(value) => self.add_bool(label, *value),
+        }
+    }
+
+    /// Add the properties added by `f` as a group, such as a JSON object or array.
+    ///
+    /// `objtype` names the group in the XML format, `label` names it in the formats with labeled
+    /// properties, and `labeled` says whether the group's own members have labels (an object) or
+    /// not (an array).  These are the arguments of Postgres' `ExplainOpenGroup()`, which
+    /// Postgres 10 doesn't export
+    #[cfg(not(feature = "pg10"))]
+    pub fn add_group<R, F: FnOnce(&mut Self) -> R>(
+        &mut self,
+        objtype: &str,
+        label: Option<&str>,
+        labeled: bool,
+        f: F,
+    ) -> R {
+        let objtype = cstring(objtype, "EXPLAIN group type");
+        let label = label.map(|label| cstring(label, "EXPLAIN label"));
+        let label = label
+            .as_ref()
+            .map_or(std::ptr::null(), |label| label.as_ptr());
+        unsafe { pg_sys::ExplainOpenGroup(objtype.as_ptr(), label, labeled, self.es) };
+        let result = f(self);
+        unsafe { pg_sys::ExplainCloseGroup(objtype.as_ptr(), label, labeled, self.es) };
+        result
+    }
+}
+
+/// The `EXPLAIN ANALYZE` running in this backend, if any, and what functions have added to it
+struct Explaining {
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    properties: Vec<(String, ExplainProperty)>,
+}
+
+thread_local! {
+    static EXPLAINING: RefCell<Option<Explaining>> = const { RefCell::new(None) };
+}
+
+static mut HOOKS_REGISTERED: bool = false;
+static mut PREV_EXPLAIN_ONE_QUERY_HOOK: pg_sys::ExplainOneQuery_hook_type = None;
+static mut PREV_EXECUTOR_END_HOOK: pg_sys::ExecutorEnd_hook_type = None;
+
+/// Install the `ExplainOneQuery` and `ExecutorEnd` hooks that [`add_explain_property()`] needs,
+/// chaining to any installed before them.  Call this from `_PG_init()`; calling it again does
+/// nothing
+pub unsafe fn register_explain_hooks() {
+    if HOOKS_REGISTERED {
+        return;
+    }
+    HOOKS_REGISTERED = true;
+    PREV_EXPLAIN_ONE_QUERY_HOOK = pg_sys::ExplainOneQuery_hook;
+    pg_sys::ExplainOneQuery_hook = Some(explain_one_query);
+    PREV_EXECUTOR_END_HOOK = pg_sys::ExecutorEnd_hook;
+    pg_sys::ExecutorEnd_hook = Some(explain_executor_end);
+}
+
+/// Is an `EXPLAIN ANALYZE` running in this backend, such that [`add_explain_property()`] would
+/// show up in its output?  Useful to skip measuring something only shown there
+pub fn explain_analyze_in_progress() -> bool {
+    EXPLAINING.with(|explaining| explaining.borrow().is_some())
+}
+
+/// From a function, add a property to the output of the `EXPLAIN ANALYZE` running it, replacing
+/// any added earlier with the same label.  A function called for every row can so keep a running
+/// total.
+///
+/// The properties are shown with the statement's summary, once it has finished running.  They're
+/// ignored if no `EXPLAIN ANALYZE` is running, or if [`register_explain_hooks()`] wasn't called.
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use std::cell::Cell;
+///
+/// thread_local! {
+///     static LOOKUPS: Cell<i64> = Cell::new(0);
+/// }
+///
+/// #[pg_extern]
+/// fn lookup(key: &str) -> Option<String> {
+///     let lookups = LOOKUPS.with(|lookups| {
+///         lookups.set(lookups.get() + 1);
+///         lookups.get()
+///     });
+///     add_explain_property(
+///         "Lookups",
+///         ExplainProperty::Integer { value: lookups, unit: None },
+///     );
+///     Some(key.to_uppercase())
+/// }
+/// ```
+pub fn add_explain_property(label: &str, property: ExplainProperty) {
+    EXPLAINING.with(|explaining| {
+        if let Some(explaining) = explaining.borrow_mut().as_mut() {
+            match explaining
+                .properties
+                .iter_mut()
+                .find(|(existing, _)| existing == label)
+            {
+                Some((_, existing)) => *existing = property,
+                None => explaining.properties.push((label.to_string(), property)),
+            }
+        }
+    })
+}
+
+#[cfg(any(
+    feature = "pg11",
+    feature = "pg12",
+    feature = "pg13",
+    feature = "pg14",
+    feature = "pg15"
+))]
+#[pg_guard]
+unsafe extern "C" fn explain_one_query(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    explaining(es, query_string, || match PREV_EXPLAIN_ONE_QUERY_HOOK {
+        Some(prev) => prev(
+            query,
+            cursor_options,
+            into,
+            es,
+            query_string,
+            params,
+            query_env,
+        ),
+        None => plan_and_explain(
+            query,
+            cursor_options,
+            into,
+            es,
+            query_string,
+            params,
+            query_env,
+        ),
+    })
+}
+
+#[cfg(feature = "pg10")]
+#[pg_guard]
+unsafe extern "C" fn explain_one_query(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+) {
+    explaining(es, query_string, || match PREV_EXPLAIN_ONE_QUERY_HOOK {
+        Some(prev) => prev(query, cursor_options, into, es, query_string, params),
+        None => plan_and_explain(
+            query,
+            cursor_options,
+            into,
+            es,
+            query_string,
+            params,
+            std::ptr::null_mut(),
+        ),
+    })
+}
+
+/// Run `explain` with the `EXPLAIN` of `query_string` as the one functions add properties to,
+/// restoring any outer one afterwards, even if it raises an ERROR
+unsafe fn explaining<F: FnOnce() + std::panic::UnwindSafe + std::panic::RefUnwindSafe>(
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    explain: F,
+) {
+    let current = if (*es).analyze {
+        Some(Explaining {
+            es,
+            query_string,
+            properties: Vec::new(),
+        })
+    } else {
+        None
+    };
+    let outer = EXPLAINING.with(|explaining| explaining.replace(current));
+    pg_sys::pg_try(explain)
+        .finally_or_rethrow(|| EXPLAINING.with(|explaining| *explaining.borrow_mut() = outer))
+}
+
+/// What Postgres' `ExplainOneQuery()` does without a hook
+unsafe fn plan_and_explain(
+    query: *mut pg_sys::Query,
+    cursor_options: i32,
+    into: *mut pg_sys::IntoClause,
+    es: *mut pg_sys::ExplainState,
+    query_string: *const std::os::raw::c_char,
+    params: pg_sys::ParamListInfo,
+    query_env: *mut pg_sys::QueryEnvironment,
+) {
+    let start = std::time::Instant::now();
+
+    #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+    {
+        let plan = pg_sys::pg_plan_query(query, cursor_options, params);
+        let planduration = instr_time(start.elapsed());
+        pg_sys::ExplainOnePlan(
+            plan,
+            into,
+            es,
+            query_string,
+            params,
+            query_env,
+            &planduration,
+        );
+    }
+
+    #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+    {
+        let buffers_start = pg_sys::pgBufferUsage;
+        let plan = pg_sys::pg_plan_query(query, query_string, cursor_options, params);
+        let planduration = instr_time(start.elapsed());
+        let mut bufusage = pg_sys::BufferUsage::default();
+        pg_sys::BufferUsageAccumDiff(
+            &mut bufusage,
+            std::ptr::addr_of!(pg_sys::pgBufferUsage),
+            &buffers_start,
+        );
+        pg_sys::ExplainOnePlan(
+            plan,
+            into,
+            es,
+            query_string,
+            params,
+            query_env,
+            &planduration,
+            if (*es).buffers {
+                &bufusage
+            } else {
+                std::ptr::null()
+            },
+        );
+    }
+}
+
+/// `ExplainOnePlan()` ends the statement it explains before closing its output, so that's when
+/// the properties functions added go in
+#[pg_guard]
+unsafe extern "C" fn explain_executor_end(query_desc: *mut pg_sys::QueryDesc) {
+    // statements run by functions have their own query strings
+    let explained = EXPLAINING.with(|explaining| match explaining.borrow_mut().as_mut() {
+        Some(explaining) if (*query_desc).sourceText == explaining.query_string => {
+            Some((explaining.es, std::mem::take(&mut explaining.properties)))
+        }
+        _ => None,
+    });
+    if let Some((es, properties)) = explained {
+        let mut es = PgExplainState::from_pg(es);
+        for (label, property) in properties {
+            es.add_property(&label, &property);
+        }
+    }
+
+    match PREV_EXECUTOR_END_HOOK {
+        Some(prev) => prev(query_desc),
+        None => pg_sys::standard_ExecutorEnd(query_desc),
+    }
+}
+
+fn instr_time(duration: std::time::Duration) -> pg_sys::instr_time {
+    pg_sys::instr_time {
+        tv_sec: duration.as_secs() as _,
+        tv_nsec: duration.subsec_nanos() as _,
+    }
+}
@@ -0,0 +1,271 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Rust APIs that one extension exports for other extensions loaded in the same backend to call,
+//! such as a raster extension calling into the geometry extension it builds on
+use crate::pg_sys;
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+/// Marks what an exported API's rendezvous variable points to, so one set by a library that
+/// isn't exporting a pgx API isn't mistaken for one
+const API_MAGIC: u32 = 0x5047_5841;
+
+/// An API that an extension exports, a struct whose fields are the functions other extensions
+/// can call, which should be declared with [`pg_extension_api!`] in a crate both extensions
+/// depend on.
+///
+/// ## Safety
+///
+/// The struct must be `#[repr(C)]`, and a new version of it may only add fields to its end if
+/// `MINOR_VERSION` goes up, and must increment `MAJOR_VERSION` for any other change, since an
+/// extension built against one version calls into an extension built against another
+pub unsafe trait ExtensionApi: Copy + Sync + 'static {
+    /// The name the API is exported under, unique to it across all extensions
+    const NAME: &'static str;
+    /// Extensions only use an API of the same major version as the one they were built against
+    const MAJOR_VERSION: u16;
+    /// Extensions use an API of the same or a later minor version than the one they were built
+    /// against
+    const MINOR_VERSION: u16;
+}
+
+/// An [`ExtensionApi`] along with the version it was built as, which an extension exports with
+/// [`export_extension_api()`]
+#[repr(C)]
+pub struct ExportedApi<T: ExtensionApi> {
+    header: ApiHeader,
+    api: T,
+}
+
+#[repr(C)]
+struct ApiHeader {
+    magic: u32,
+    major_version: u16,
+    minor_version: u16,
+    size: usize,
+}
+
+impl<T: ExtensionApi> ExportedApi<T> {
+    pub const fn new(api: T) -> Self {
+        ExportedApi {
+            header: ApiHeader {
+                magic: API_MAGIC,
+                major_version: T::MAJOR_VERSION,
+                minor_version: T::MINOR_VERSION,
+                size: std::mem::size_of::<T>(),
+            },
+            api,
+        }
+    }
+}
+
+/// Why an [`ExtensionApi`] couldn't be imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionApiError {
+    /// No library loaded in this backend exports the API
+    NotLoaded { name: &'static str },
+    /// Something other than a pgx API is exported under the API's name
+    NotAnApi { name: &'static str },
+    /// The API is exported as a version the importing extension can't use
+    IncompatibleVersion {
+        name: &'static str,
+        /// The major and minor version exported
+        exported: (u16, u16),
+        /// The major and minor version the importing extension was built against
+        required: (u16, u16),
+    },
+}
+
+impl std::fmt::Display for ExtensionApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtensionApiError::NotLoaded { name } => {
+                write!(f, "extension API \"{}\" is not loaded", name)
+            }
+            ExtensionApiError::NotAnApi { name } => {
+                write!(f, "\"{}\" is not an extension API", name)
+            }
+            ExtensionApiError::IncompatibleVersion {
+                name,
+                exported,
+                required,
+            } => write!(
+                f,
+                "extension API \"{}\" is version {}.{}, but version {}.{} is required",
+                name, exported.0, exported.1, required.0, required.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExtensionApiError {}
+
+fn rendezvous_variable<T: ExtensionApi>() -> *mut *mut c_void {
+    let name = CString::new(format!("pgx_api.{}", T::NAME))
+        .expect("extension API name contains a null byte");
+    unsafe { pg_sys::find_rendezvous_variable(name.as_ptr()) }
+}
+
+/// Export `api` for other extensions to import with [`import_extension_api()`], which should be
+/// done in `_PG_init()`.
+///
+/// ## Panics
+///
+/// If another library already exports an API with the same name
+pub fn export_extension_api<T: ExtensionApi>(api: &'static ExportedApi<T>) {
+    let variable = rendezvous_variable::<T>();
+    let api = api as *const ExportedApi<T> as *mut c_void;
+    unsafe {
+        if !(*variable).is_null() && *variable != api {
+            panic!(
+                "extension API \"{}\" is already exported by another library",
+                T::NAME
+            );
+        }
+        *variable = api;
+    }
+}
+
+/// The API another extension in this backend exports as `T`, if it's a version this extension
+/// can use.  The exporting extension's library must already be loaded, such as by being in
+/// `shared_preload_libraries` or by one of its functions having been called, and
+/// [`load_extension_api()`] loads it first.
+pub fn import_extension_api<T: ExtensionApi>() -> Result<&'static T, ExtensionApiError> {
+    let exported = unsafe { *rendezvous_variable::<T>() };
+    if exported.is_null() {
+        return Err(ExtensionApiError::NotLoaded { name: T::NAME });
+    }
+
+    let header = unsafe { &*(exported as *const ApiHeader) };
+    if header.magic != API_MAGIC {
+        return Err(ExtensionApiError::NotAnApi { name: T::NAME });
+    }
+    if header.major_version != T::MAJOR_VERSION
+        || header.minor_version < T::MINOR_VERSION
+        || header.size < std::mem::size_of::<T>()
+    {
+        return Err(ExtensionApiError::IncompatibleVersion {
+            name: T::NAME,
+            exported: (header.major_version, header.minor_version),
+            required: (T::MAJOR_VERSION, T::MINOR_VERSION),
+        });
+    }
+    Ok(unsafe { &(*(exported as *const ExportedApi<T>)).api })
+}
+
+/// Like [`import_extension_api()`], but loads the exporting extension's `library` first, such
+/// as `"$libdir/postgis_raster"`, if it isn't already.
+///
+/// ## Panics
+///
+/// Raises a Postgres ERROR if `library` can't be loaded
+pub fn load_extension_api<T: ExtensionApi>(library: &str) -> Result<&'static T, ExtensionApiError> {
+    let library = CString::new(library).expect("library name contains a null byte");
+    unsafe { pg_sys::load_file(library.as_ptr(), false) };
+    import_extension_api()
+}
+
+/// Declare an [`ExtensionApi`], the struct of functions one extension exports to others, with the
+/// name it's exported under and its version.  This belongs in a crate that both the exporting
+/// and importing extensions depend on.
+///
+/// The functions are called across libraries that may have been built by different versions of
+/// Rust, so they should be `extern "C"` and take and return `#[repr(C)]` types, and the exporting
+/// extension should `#[pg_guard]` them.
+///
+/// The struct gets `import()`, which raises an ERROR if the API can't be imported, and
+/// `try_import()`, which returns why.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// pg_extension_api! {
+///     name = "geometry", major = 1, minor = 0;
+///
+///     /// what the geometry extension offers other extensions
+///     pub struct GeometryApi {
+///         pub area: extern "C" fn(pg_sys::Datum) -> f64,
+///     }
+/// }
+///
+/// // in the geometry extension
+/// #[pg_guard]
+/// extern "C" fn area(geometry: pg_sys::Datum) -> f64 {
+///     // ...
+/// #   0.0
+/// }
+///
+/// static GEOMETRY_API: ExportedApi<GeometryApi> = ExportedApi::new(GeometryApi { area });
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     export_extension_api(&GEOMETRY_API);
+/// }
+///
+/// // in the raster extension
+/// fn tile_area(tile: pg_sys::Datum) -> f64 {
+///     (GeometryApi::import().area)(tile)
+/// }
+/// ```
+#[macro_export]
+macro_rules! pg_extension_api {
+    (
+        name = $api_name:literal, major = $major:literal, minor = $minor:literal;
+
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_attr:meta])*
+                $field_vis:vis $field:ident: $field_ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        $vis struct $name {
+            $(
+                $(#[$field_attr])*
+                $field_vis $field: $field_ty
+            ),*
+        }
+
+        unsafe impl $crate::ExtensionApi for $name {
+            const NAME: &'static str = $api_name;
+            const MAJOR_VERSION: u16 = $major;
+            const MINOR_VERSION: u16 = $minor;
+        }
+
+        impl $name {
+            /// The exported API, if a version this extension can use is loaded
+            #[allow(dead_code)]
+            $vis fn try_import() -> Result<&'static Self, $crate::ExtensionApiError> {
+                $crate::import_extension_api::<Self>()
+            }
+
+            /// The exported API, raising an ERROR if a version this extension can use isn't
+            /// loaded
+            #[allow(dead_code)]
+            $vis fn import() -> &'static Self {
+                Self::try_import().unwrap_or_else(|e| {
+                    $crate::ereport(
+                        $crate::PgLogLevel::ERROR,
+                        $crate::PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+                        &e.to_string(),
+                        file!(),
+                        line!(),
+                        column!(),
+                    );
+                    unreachable!()
+                })
+            }
+        }
+    };
+}
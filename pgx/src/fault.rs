@@ -0,0 +1,146 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Fault injection points, places in an extension's code where a test can make it raise an
+//! `ERROR`, panic, or stall, so its error paths and races can be tested
+//!
+//! A [`fault_point!()`](crate::fault_point) is only compiled into the extension when it's built
+//! for `cargo test` or with its `pg_test` feature, as `cargo pgx test` does, and is nothing
+//! otherwise.  The faults are set by the `pgx.fault_points` setting, a comma-separated list of
+//! `name=action`, where the action is `error`, `panic`, or `sleep:<milliseconds>`:
+//!
+//! ```sql
+//! -- for this transaction
+//! SELECT set_config('pgx.fault_points', 'flush=error, commit=sleep:500', true);
+//! -- for the sessions which connect to the database after this
+//! ALTER DATABASE my_database SET pgx.fault_points = 'flush=error';
+//! ```
+use crate::{check_for_interrupts, ereport, pg_sys, PgLogLevel, PgSqlErrorCode};
+use std::ffi::CStr;
+use std::time::{Duration, Instant};
+
+/// The setting which lists the faults to inject
+pub const FAULT_POINTS_SETTING: &str = "pgx.fault_points";
+
+/// What a [`fault_point!()`](crate::fault_point) does when it's hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultAction {
+    /// Raise an `ERROR`, like a failed call into Postgres
+    Error,
+    /// Panic, like a bug in the extension's Rust
+    Panic,
+    /// Wait, still accepting query cancels, to widen a race with another session
+    Sleep(Duration),
+}
+
+impl std::str::FromStr for FaultAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "error" => Ok(FaultAction::Error),
+            "panic" => Ok(FaultAction::Panic),
+            action => action
+                .strip_prefix("sleep:")
+                .and_then(|ms| ms.trim().parse().ok())
+                .map(|ms| FaultAction::Sleep(Duration::from_millis(ms)))
+                .ok_or_else(|| {
+                    format!(
+                        "`{}` isn't a fault action, which are `error`, `panic`, and `sleep:<milliseconds>`",
+                        action
+                    )
+                }),
+        }
+    }
+}
+
+/// The action `pgx.fault_points` sets for the fault point called `name`, if it sets one
+pub fn injected_fault(name: &str) -> Result<Option<FaultAction>, String> {
+    let setting = std::ffi::CString::new(FAULT_POINTS_SETTING).unwrap();
+    let value = unsafe { pg_sys::GetConfigOption(setting.as_ptr(), true, false) };
+    if value.is_null() {
+        return Ok(None);
+    }
+    let value = unsafe { CStr::from_ptr(value) }.to_string_lossy();
+    for fault in value.split(',').filter(|fault| !fault.trim().is_empty()) {
+        let (point, action) = fault
+            .split_once('=')
+            .ok_or_else(|| format!("`{}` isn't a `name=action` fault", fault.trim()))?;
+        if point.trim() == name {
+            return action.parse().map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Inject the fault `pgx.fault_points` sets for the fault point called `name`, if it sets one,
+/// which [`fault_point!()`](crate::fault_point) calls
+pub fn hit_fault_point(name: &str) {
+    let action = match injected_fault(name) {
+        Ok(action) => action,
+        Err(e) => {
+            ereport(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                &format!("invalid {}: {}", FAULT_POINTS_SETTING, e),
+                file!(),
+                line!(),
+                column!(),
+            );
+            return;
+        }
+    };
+    match action {
+        Some(FaultAction::Error) => ereport(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+            &format!("fault injected at `{}`", name),
+            file!(),
+            line!(),
+            column!(),
+        ),
+        Some(FaultAction::Panic) => panic!("fault injected at `{}`", name),
+        Some(FaultAction::Sleep(duration)) => {
+            let start = Instant::now();
+            while start.elapsed() < duration {
+                check_for_interrupts!();
+                std::thread::sleep(std::cmp::min(
+                    duration - start.elapsed(),
+                    Duration::from_millis(10),
+                ));
+            }
+        }
+        None => {}
+    }
+}
+
+/// Mark a place in the extension's code where a test can inject a fault, by the point's `name` in
+/// the `pgx.fault_points` setting
+///
+/// It's only compiled when the extension is built for `cargo test` or with its `pg_test` feature,
+/// so it costs nothing in the extension's releases.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn archive_orders() {
+///     Spi::run("INSERT INTO archive SELECT * FROM orders");
+///     // a test can check the copies are rolled back if the rest fails
+///     fault_point!("archive_orders_copied");
+///     Spi::run("DELETE FROM orders");
+/// }
+/// ```
+#[macro_export]
+macro_rules! fault_point {
+    ($name:literal) => {{
+        #[cfg(any(test, feature = "pg_test"))]
+        $crate::hit_fault_point($name);
+    }};
+}
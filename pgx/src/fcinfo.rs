@@ -130,7 +130,7 @@ mod pg_10_11 {
     }
 }
 
-#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
 mod pg_12_13_14 {
     use crate::{pg_sys, FromDatum};
 
@@ -191,7 +191,7 @@ mod pg_12_13_14 {
 #[cfg(any(feature = "pg10", feature = "pg11"))]
 pub use pg_10_11::*;
 
-#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
 pub use pg_12_13_14::*;
 use std::ops::DerefMut;
 
@@ -212,6 +212,31 @@ pub unsafe fn get_getarg_type(fcinfo: pg_sys::FunctionCallInfo, num: usize) -> p
     pg_sys::get_fn_expr_argtype(fcinfo.as_ref().unwrap().flinfo, num as std::os::raw::c_int)
 }
 
+/// The number of arguments the function was called with, such as to loop over `VARIADIC "any"`
+/// arguments with [`pg_getarg()`] and [`get_getarg_type()`]
+///
+/// # Safety
+///
+/// The provided `fcinfo` must be null or valid, otherwise this function results in undefined
+/// behavior due to reading through a dangling pointer.
+#[inline]
+pub unsafe fn pg_nargs(fcinfo: pg_sys::FunctionCallInfo) -> usize {
+    fcinfo.as_ref().unwrap().nargs as usize
+}
+
+/// The collation the function was called with, like `PG_GET_COLLATION()`, such as that of the
+/// column being sorted.  It's `pg_sys::InvalidOid` if none of the arguments are collatable.  See
+/// [`PgCollation::from_fcinfo()`](crate::PgCollation::from_fcinfo)
+///
+/// # Safety
+///
+/// The provided `fcinfo` must be null or valid, otherwise this function results in undefined
+/// behavior due to reading through a dangling pointer.
+#[inline]
+pub unsafe fn pg_get_collation(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Oid {
+    fcinfo.as_ref().unwrap().fncollation
+}
+
 /// this is intended for Postgres functions that take an actual `cstring` argument, not for getting
 /// a varlena argument type as a CStr.
 #[inline]
@@ -247,6 +272,70 @@ pub unsafe fn pg_func_extra<ReturnType, DefaultValue: FnOnce() -> ReturnType>(
     PgBox::from_pg(flinfo.fn_extra as *mut ReturnType)
 }
 
+/// The fn_extra slot is shared with anything else that uses it, like the set-returning-function
+/// machinery, so [`fn_extra_cache()`] marks its own
+const FN_EXTRA_CACHE_MAGIC: u64 = 0x7067_785f_6361_6368;
+
+#[repr(C)]
+struct FnExtraCache {
+    magic: u64,
+    value: Box<dyn std::any::Any>,
+}
+
+/// Cache a value in the function's `.flinfo.fn_extra`, creating it with `init` on the first call,
+/// so that expensive setup, like compiling a regex or parsing a configuration, happens once per
+/// call site -- each use of the function in a query -- rather than once per row.  The value is
+/// allocated in `.flinfo.fn_mcxt`, as is any memory `init` pallocs, and is dropped with it.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn is_reserved(word: &str, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+///     let reserved = unsafe {
+///         fn_extra_cache(fcinfo, || {
+///             Spi::get_one::<String>("SELECT current_setting('myext.reserved_words')")
+///                 .unwrap_or_default()
+///                 .split(',')
+///                 .map(|word| word.trim().to_lowercase())
+///                 .collect::<Vec<_>>()
+///         })
+///     };
+///     reserved.contains(&word.to_lowercase())
+/// }
+/// ```
+///
+/// Panics if the function already cached a value of a different type, or if something else uses
+/// `fn_extra`, as set-returning functions and [`pg_func_extra()`] do.
+///
+/// # Safety
+///
+/// `fcinfo` must be valid, and the returned reference must not outlive the function call
+pub unsafe fn fn_extra_cache<'a, T: 'static, F: FnOnce() -> T>(
+    fcinfo: pg_sys::FunctionCallInfo,
+    init: F,
+) -> &'a mut T {
+    let flinfo = fcinfo.as_ref().unwrap().flinfo;
+    if (*flinfo).fn_extra.is_null() {
+        let mut memcxt = PgMemoryContexts::For((*flinfo).fn_mcxt);
+        let init = std::panic::AssertUnwindSafe(init);
+        let value: Box<dyn std::any::Any> = Box::new(memcxt.switch_to(move |_| init()));
+        (*flinfo).fn_extra = memcxt.leak_and_drop_on_delete(FnExtraCache {
+            magic: FN_EXTRA_CACHE_MAGIC,
+            value,
+        }) as void_mut_ptr;
+    }
+
+    let cache = &mut *((*flinfo).fn_extra as *mut FnExtraCache);
+    if cache.magic != FN_EXTRA_CACHE_MAGIC {
+        panic!("fn_extra is already in use, such as by a set-returning function");
+    }
+    cache
+        .value
+        .downcast_mut::<T>()
+        .expect("fn_extra holds a cached value of a different type")
+}
+
 /// This mimics the functionality of Postgres' `DirectFunctionCall` macros, allowing you to call
 /// internal Postgres functions using its "V1" calling convention.  Unlike the Postgres' C macros,
 /// the function is allowed to return a NULL datum.
@@ -411,7 +500,7 @@ fn make_function_call_info(
     fcinfo_boxed
 }
 
-#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
 fn make_function_call_info(
     nargs: usize,
     arg_array: [usize; 100],
@@ -487,3 +576,142 @@ pub unsafe fn srf_return_done(
     let mut rsi = PgBox::from_pg(fcinfo.resultinfo as *mut pg_sys::ReturnSetInfo);
     rsi.isDone = pg_sys::ExprDoneCond_ExprEndResult;
 }
+
+/// How a function was called, according to [`FcInfoContext::call_context()`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CallContext {
+    /// As a plain function, such as from a query's target list
+    Function,
+    /// As the support function of an aggregate
+    Aggregate,
+    /// As the support function of an aggregate used as a window function
+    WindowAggregate,
+    /// As a window function
+    WindowFunction,
+    /// As a trigger
+    Trigger,
+    /// As an event trigger
+    EventTrigger,
+}
+
+/// A safe view of the [`pg_sys::FunctionCallInfo`] a function was called with.
+///
+/// A `#[pg_extern]` function receives one by taking a `FcInfoContext` as one of its arguments,
+/// which isn't part of the function's SQL signature.  It's useful for polymorphic functions, which need
+/// the actual types of their arguments, and for functions that cache something expensive between
+/// calls from the same place in a query:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn is_money(value: Any, mut context: FcInfoContext) -> bool {
+///     // looked up once per query, rather than once per row
+///     let money = *context.cache(|| RegType::from_name("money"));
+///     money.map(|money| money.oid()) == Some(value.oid())
+/// }
+/// ```
+pub struct FcInfoContext {
+    fcinfo: pg_sys::FunctionCallInfo,
+}
+
+impl FcInfoContext {
+    /// # Safety
+    ///
+    /// `fcinfo` must be the valid [`pg_sys::FunctionCallInfo`] of the function being called
+    pub unsafe fn from_fcinfo(fcinfo: pg_sys::FunctionCallInfo) -> Self {
+        FcInfoContext { fcinfo }
+    }
+
+    pub fn as_ptr(&self) -> pg_sys::FunctionCallInfo {
+        self.fcinfo
+    }
+
+    /// The number of arguments the function was called with
+    pub fn nargs(&self) -> usize {
+        unsafe { pg_nargs(self.fcinfo) }
+    }
+
+    /// The actual type of argument `num`, which for polymorphic arguments like `anyelement` is the
+    /// type it was resolved to.  It's `pg_sys::InvalidOid` if it can't be determined, such as when
+    /// the function is called through `DirectFunctionCall`
+    pub fn arg_type(&self, num: usize) -> pg_sys::Oid {
+        unsafe { get_getarg_type(self.fcinfo, num) }
+    }
+
+    /// Is argument `num` a constant, or otherwise the same for every row of the query?
+    pub fn arg_is_stable(&self, num: usize) -> bool {
+        unsafe { pg_sys::get_fn_expr_arg_stable(self.flinfo(), num as std::os::raw::c_int) }
+    }
+
+    /// The actual return type of the function, which for polymorphic functions is the type it
+    /// was resolved to
+    pub fn return_type(&self) -> pg_sys::Oid {
+        unsafe { pg_sys::get_fn_expr_rettype(self.flinfo()) }
+    }
+
+    /// The row type the function's result is expected to have, if it returns a composite type,
+    /// including a `record` whose columns the query gives, as in
+    /// `SELECT * FROM f() AS t(a int, b text)`.  It's `None` for a `record` whose columns are left
+    /// to the function, which can make its own with
+    /// [`PgTupleDesc::from_columns()`](crate::PgTupleDesc::from_columns).
+    ///
+    /// The TupleDesc is a copy, allocated in the `CurrentMemoryContext`
+    pub fn result_tupdesc(&self) -> Option<crate::PgTupleDesc<'static>> {
+        unsafe {
+            let mut tupdesc = std::ptr::null_mut();
+            let class =
+                pg_sys::get_call_result_type(self.fcinfo, std::ptr::null_mut(), &mut tupdesc);
+            if class == pg_sys::TypeFuncClass_TYPEFUNC_COMPOSITE && !tupdesc.is_null() {
+                // it may be the executor's own, for a `record` with a column definition list
+                Some(crate::PgTupleDesc::from_pg_copy(tupdesc))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Were the `VARIADIC` arguments passed as an explicit array, like `f(VARIADIC ARRAY[..])`?
+    pub fn is_variadic(&self) -> bool {
+        unsafe { pg_sys::get_fn_expr_variadic(self.flinfo()) }
+    }
+
+    /// The collation the function was called with.  See [`pg_get_collation()`]
+    pub fn collation(&self) -> Option<crate::PgCollation> {
+        unsafe { crate::PgCollation::from_fcinfo(self.fcinfo) }
+    }
+
+    /// Whether the function was called as part of an aggregate, a trigger, or a window function
+    pub fn call_context(&self) -> CallContext {
+        unsafe {
+            match pg_sys::AggCheckCallContext(self.fcinfo, std::ptr::null_mut()) as u32 {
+                pg_sys::AGG_CONTEXT_AGGREGATE => return CallContext::Aggregate,
+                pg_sys::AGG_CONTEXT_WINDOW => return CallContext::WindowAggregate,
+                _ => {}
+            }
+
+            let context = self.fcinfo.as_ref().unwrap().context;
+            if context.is_null() {
+                CallContext::Function
+            } else if crate::is_a(context, pg_sys::NodeTag_T_TriggerData) {
+                CallContext::Trigger
+            } else if crate::is_a(context, pg_sys::NodeTag_T_EventTriggerData) {
+                CallContext::EventTrigger
+            } else if crate::is_a(context, pg_sys::NodeTag_T_WindowObjectData) {
+                CallContext::WindowFunction
+            } else {
+                CallContext::Function
+            }
+        }
+    }
+
+    /// The value cached for this call site -- this use of the function in a query -- creating it
+    /// with `init` on the first call.  See [`fn_extra_cache()`]
+    pub fn cache<T: 'static, F: FnOnce() -> T>(&mut self, init: F) -> &mut T {
+        unsafe { fn_extra_cache(self.fcinfo, init) }
+    }
+
+    fn flinfo(&self) -> *mut pg_sys::FmgrInfo {
+        unsafe { self.fcinfo.as_ref() }.unwrap().flinfo
+    }
+}
@@ -0,0 +1,275 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Reading and writing an extension's files, such as ones it imports or exports, in a directory
+//! of its own on the server, with the same permission checks as `COPY` to and from a file
+//!
+//! A [`PgFileArea`] is a directory named after the extension in the data directory, unless its
+//! `<name>.directory` setting says otherwise, and paths are relative to it.  A path which would
+//! be outside of it, with `..`, an absolute path, or a symbolic link, raises an `ERROR`, so users
+//! can only reach the files the extension stages there.
+//!
+//! Reading requires the current user to be a superuser or have the privileges of the
+//! `pg_read_server_files` role, and writing or removing files requires `pg_write_server_files`,
+//! as `COPY` does.  Those roles are new in Postgres 11, so before that only superusers may.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! static EXPORTS: PgFileArea = PgFileArea::new("myext_exports");
+//!
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     EXPORTS.define_guc();
+//! }
+//!
+//! #[pg_extern]
+//! fn export_report(name: &str, report: &str) {
+//!     EXPORTS
+//!         .write(&format!("{}.csv", name), report.as_bytes())
+//!         .unwrap_or_else(|e| error!("could not export \"{}\": {}", name, e));
+//! }
+//! ```
+use crate::{
+    ereport, has_privileges_of_role, is_superuser, pg_sys, role_oid, GucContext, GucRegistry,
+    GucSetting, PgLogLevel, PgSqlErrorCode,
+};
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// The files an extension can read and write, in a directory of its own.  See the
+/// [module documentation](crate::files)
+pub struct PgFileArea {
+    name: &'static str,
+    directory: GucSetting<Option<&'static str>>,
+}
+
+impl PgFileArea {
+    /// The files in the data directory's `name` directory, whose setting is `<name>.directory`
+    pub const fn new(name: &'static str) -> Self {
+        PgFileArea {
+            name,
+            directory: GucSetting::new(None),
+        }
+    }
+
+    /// The name given to [`PgFileArea::new()`]
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Define the `<name>.directory` setting, which superusers can set to keep the files
+    /// elsewhere.  Call it from `_PG_init()`, or the files are always in the data directory
+    pub fn define_guc(&'static self) {
+        GucRegistry::define_string_guc(
+            &format!("{}.directory", self.name),
+            "The directory of the extension's files",
+            "A path relative to the data directory, or an absolute path.  By default, a directory in the data directory named after the extension.",
+            &self.directory,
+            GucContext::Suset,
+        );
+    }
+
+    /// The directory the files are in, which is only created once a file is written
+    pub fn directory(&self) -> PathBuf {
+        let data_directory = unsafe { CStr::from_ptr(pg_sys::DataDir) }
+            .to_string_lossy()
+            .into_owned();
+        match self.directory.get() {
+            Some(directory) if !directory.is_empty() => Path::new(&data_directory).join(directory),
+            _ => Path::new(&data_directory).join(self.name),
+        }
+    }
+
+    /// The full path of `path`, which is relative to the directory.
+    ///
+    /// ## Panics
+    ///
+    /// Raises a Postgres ERROR if `path` is absolute, or would be outside of the directory
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn path(&self, path: &str) -> PathBuf {
+        let relative = Path::new(path);
+        let is_relative = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if path.is_empty() || !is_relative {
+            self.path_error(path);
+        }
+
+        let directory = self.directory();
+        let full_path = directory.join(relative);
+        // a symbolic link can't lead out of the directory either
+        if let Ok(directory) = directory.canonicalize() {
+            let existing = full_path
+                .ancestors()
+                .find_map(|path| path.canonicalize().ok());
+            if existing.map_or(false, |existing| !existing.starts_with(&directory)) {
+                self.path_error(path);
+            }
+        }
+        full_path
+    }
+
+    /// Can the current user read the files, by being a superuser or having the privileges of
+    /// `pg_read_server_files`?
+    pub fn can_read(&self) -> bool {
+        has_server_files_role("pg_read_server_files")
+    }
+
+    /// Can the current user write and remove the files, by being a superuser or having the
+    /// privileges of `pg_write_server_files`?
+    pub fn can_write(&self) -> bool {
+        has_server_files_role("pg_write_server_files")
+    }
+
+    /// Open the file at `path` for reading.
+    ///
+    /// ## Panics
+    ///
+    /// Raises a Postgres ERROR if the current user [can't read](PgFileArea::can_read()) the files,
+    /// or `path` is outside of the directory
+    pub fn open(&self, path: &str) -> io::Result<File> {
+        self.check_read();
+        File::open(self.path(path))
+    }
+
+    /// The contents of the file at `path`, with the same checks as [`PgFileArea::open()`]
+    pub fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.check_read();
+        std::fs::read(self.path(path))
+    }
+
+    /// The contents of the file at `path`, which must be UTF-8, with the same checks as
+    /// [`PgFileArea::open()`]
+    pub fn read_to_string(&self, path: &str) -> io::Result<String> {
+        self.check_read();
+        std::fs::read_to_string(self.path(path))
+    }
+
+    /// The names of the files and directories in the directory, in order.  It's empty if nothing
+    /// has been written to it yet
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        self.check_read();
+        let entries = match std::fs::read_dir(self.directory()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            names.push(entry?.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Does the file at `path` exist?  It has the same checks as [`PgFileArea::open()`]
+    pub fn exists(&self, path: &str) -> bool {
+        self.check_read();
+        self.path(path).exists()
+    }
+
+    /// Create the file at `path` for writing, replacing it if it exists, and creating the
+    /// directories it's in.  Only its owner, the server, can read the file.
+    ///
+    /// ## Panics
+    ///
+    /// Raises a Postgres ERROR if the current user [can't write](PgFileArea::can_write()) the
+    /// files, or `path` is outside of the directory
+    pub fn create(&self, path: &str) -> io::Result<File> {
+        self.check_write();
+        self.open_for_writing(
+            path,
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )
+    }
+
+    /// Replace the file at `path` with `contents`, with the same checks as
+    /// [`PgFileArea::create()`]
+    pub fn write(&self, path: &str, contents: &[u8]) -> io::Result<()> {
+        self.create(path)?.write_all(contents)
+    }
+
+    /// Add `contents` to the end of the file at `path`, creating it if it doesn't exist, with the
+    /// same checks as [`PgFileArea::create()`]
+    pub fn append(&self, path: &str, contents: &[u8]) -> io::Result<()> {
+        self.check_write();
+        self.open_for_writing(path, OpenOptions::new().append(true).create(true))?
+            .write_all(contents)
+    }
+
+    /// Remove the file at `path`, with the same checks as [`PgFileArea::create()`]
+    pub fn remove(&self, path: &str) -> io::Result<()> {
+        self.check_write();
+        std::fs::remove_file(self.path(path))
+    }
+
+    fn open_for_writing(&self, path: &str, options: &mut OpenOptions) -> io::Result<File> {
+        let full_path = self.path(path);
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(options, 0o600);
+        options.open(full_path)
+    }
+
+    fn check_read(&self) {
+        if !self.can_read() {
+            self.permission_error("pg_read_server_files", "read");
+        }
+    }
+
+    fn check_write(&self) {
+        if !self.can_write() {
+            self.permission_error("pg_write_server_files", "write");
+        }
+    }
+
+    fn permission_error(&self, role: &str, action: &str) -> ! {
+        raise_error(
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            &format!(
+                "must be superuser or have privileges of the {} role to {} the files of \"{}\"",
+                role, action, self.name
+            ),
+        )
+    }
+
+    fn path_error(&self, path: &str) -> ! {
+        raise_error(
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            &format!(
+                "path \"{}\" is not in the directory of the files of \"{}\"",
+                path, self.name
+            ),
+        )
+    }
+}
+
+/// Is the current user a superuser, or does it have the privileges of the predefined `role`?
+#[allow(clippy::unnecessary_map_or)]
+fn has_server_files_role(role: &str) -> bool {
+    let user = unsafe { pg_sys::GetUserId() };
+    is_superuser(user) || role_oid(role).map_or(false, |role| has_privileges_of_role(user, role))
+}
+
+fn raise_error(code: PgSqlErrorCode, message: &str) -> ! {
+    ereport(
+        PgLogLevel::ERROR,
+        code,
+        message,
+        file!(),
+        line!(),
+        column!(),
+    );
+    unreachable!("ERROR ereport returned")
+}
@@ -9,9 +9,10 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Provides a safe interface into Postgres' Configuration System (GUC)
 use crate::{pg_sys, PgMemoryContexts};
-use std::cell::Cell;
-use std::ffi::CStr;
+use std::cell::{Cell, RefCell};
+use std::ffi::{CStr, CString};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GucContext {
     /// cannot be set by the user at all, but only through
     /// internal processes ("server_version" is an example).  These are GUC
@@ -50,6 +51,21 @@ pub enum GucContext {
     Userset = pg_sys::GucContext_PGC_USERSET as isize,
 }
 
+impl GucContext {
+    /// The context's name, as the `context` column of `pg_settings` shows it
+    pub fn name(self) -> &'static str {
+        match self {
+            GucContext::Internal => "internal",
+            GucContext::Postmaster => "postmaster",
+            GucContext::Sighup => "sighup",
+            GucContext::SuBackend => "superuser-backend",
+            GucContext::Backend => "backend",
+            GucContext::Suset => "superuser",
+            GucContext::Userset => "user",
+        }
+    }
+}
+
 pub trait GucEnum<T>
 where
     T: Copy,
@@ -143,8 +159,59 @@ where
     }
 }
 
+/// A setting defined through [`GucRegistry`], as `pg_introspection_functions!()`'s
+/// `<ext>_internal_settings()` lists it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinedGuc {
+    pub name: String,
+    pub short_description: String,
+    /// `bool`, `integer`, `real`, `string`, or `enum`, as the `vartype` column of `pg_settings`
+    /// shows it
+    pub vartype: &'static str,
+    pub context: GucContext,
+}
+
+impl DefinedGuc {
+    /// The setting's current value, as the `setting` column of `pg_settings` shows it, or `None`
+    /// for a string setting without one
+    pub fn setting(&self) -> Option<String> {
+        let name = CString::new(self.name.as_str()).expect("GUC name contains a null byte");
+        unsafe {
+            let value = pg_sys::GetConfigOption(name.as_ptr(), true, false);
+            if value.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(value).to_string_lossy().into_owned())
+            }
+        }
+    }
+}
+
+thread_local! {
+    // the settings defined in this process, or, for a library in `shared_preload_libraries`, in
+    // the postmaster it was forked from
+    static DEFINED_GUCS: RefCell<Vec<DefinedGuc>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Record a setting once Postgres has accepted its definition, so one it rejected isn't listed
+fn record_guc(name: &str, short_description: &str, vartype: &'static str, context: GucContext) {
+    DEFINED_GUCS.with(|defined| {
+        defined.borrow_mut().push(DefinedGuc {
+            name: name.to_string(),
+            short_description: short_description.to_string(),
+            vartype,
+            context,
+        })
+    });
+}
+
 pub struct GucRegistry {}
 impl GucRegistry {
+    /// The settings this extension has defined, in the order it defined them
+    pub fn defined() -> Vec<DefinedGuc> {
+        DEFINED_GUCS.with(|defined| defined.borrow().clone())
+    }
+
     pub fn define_bool_guc(
         name: &str,
         short_description: &str,
@@ -166,6 +233,7 @@ impl GucRegistry {
                 None,
             )
         }
+        record_guc(name, short_description, "bool", context);
     }
 
     pub fn define_int_guc(
@@ -193,6 +261,7 @@ impl GucRegistry {
                 None,
             )
         }
+        record_guc(name, short_description, "integer", context);
     }
 
     pub fn define_string_guc(
@@ -221,6 +290,7 @@ impl GucRegistry {
                 None,
             )
         }
+        record_guc(name, short_description, "string", context);
     }
 
     pub fn define_float_guc(
@@ -248,6 +318,7 @@ impl GucRegistry {
                 None,
             )
         }
+        record_guc(name, short_description, "real", context);
     }
 
     pub fn define_enum_guc<T>(
@@ -274,5 +345,6 @@ impl GucRegistry {
                 None,
             )
         }
+        record_guc(name, short_description, "enum", context);
     }
 }
@@ -123,6 +123,7 @@ pub trait PgHooks {
     }
 
     /// Hook for plugins to get control of the planner
+    #[cfg(feature = "planner")]
     fn planner(
         &mut self,
         parse: PgBox<pg_sys::Query>,
@@ -154,6 +155,7 @@ struct Hooks {
     prev_executor_end_hook: pg_sys::ExecutorEnd_hook_type,
     prev_executor_check_perms_hook: pg_sys::ExecutorCheckPerms_hook_type,
     prev_process_utility_hook: pg_sys::ProcessUtility_hook_type,
+    #[cfg(feature = "planner")]
     prev_planner_hook: pg_sys::planner_hook_type,
 }
 
@@ -184,6 +186,7 @@ pub unsafe fn register_hook(hook: &'static mut (dyn PgHooks)) {
         prev_process_utility_hook: pg_sys::ProcessUtility_hook
             .replace(pgx_process_utility)
             .or(Some(pgx_standard_process_utility_wrapper)),
+        #[cfg(feature = "planner")]
         prev_planner_hook: pg_sys::planner_hook
             .replace(pgx_planner)
             .or(Some(pgx_standard_planner_wrapper)),
@@ -364,7 +367,7 @@ unsafe extern "C" fn pgx_process_utility(
     )
     .inner
 }
-#[cfg(feature = "pg14")]
+#[cfg(any(feature = "pg14", feature = "pg15"))]
 #[pg_guard]
 unsafe extern "C" fn pgx_process_utility(
     pstmt: *mut pg_sys::PlannedStmt,
@@ -420,7 +423,10 @@ unsafe extern "C" fn pgx_process_utility(
     .inner
 }
 
-#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+#[cfg(all(
+    feature = "planner",
+    any(feature = "pg10", feature = "pg11", feature = "pg12")
+))]
 #[pg_guard]
 unsafe extern "C" fn pgx_planner(
     parse: *mut pg_sys::Query,
@@ -430,7 +436,10 @@ unsafe extern "C" fn pgx_planner(
     pgx_planner_impl(parse, std::ptr::null(), cursor_options, bound_params)
 }
 
-#[cfg(any(feature = "pg13", feature = "pg14"))]
+#[cfg(all(
+    feature = "planner",
+    any(feature = "pg13", feature = "pg14", feature = "pg15")
+))]
 #[pg_guard]
 unsafe extern "C" fn pgx_planner(
     parse: *mut pg_sys::Query,
@@ -441,6 +450,7 @@ unsafe extern "C" fn pgx_planner(
     pgx_planner_impl(parse, query_string, cursor_options, bound_params)
 }
 
+#[cfg(feature = "planner")]
 #[pg_guard]
 unsafe extern "C" fn pgx_planner_impl(
     parse: *mut pg_sys::Query,
@@ -464,7 +474,7 @@ unsafe extern "C" fn pgx_planner_impl(
                 )
             }
 
-            #[cfg(any(feature = "pg13", feature = "pg14"))]
+            #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
             {
                 (HOOKS.as_mut().unwrap().prev_planner_hook.as_ref().unwrap())(
                     parse.into_pg(),
@@ -544,7 +554,7 @@ unsafe extern "C" fn pgx_standard_process_utility_wrapper(
     )
 }
 
-#[cfg(feature = "pg14")]
+#[cfg(any(feature = "pg14", feature = "pg15"))]
 #[pg_guard]
 unsafe extern "C" fn pgx_standard_process_utility_wrapper(
     pstmt: *mut pg_sys::PlannedStmt,
@@ -568,7 +578,10 @@ unsafe extern "C" fn pgx_standard_process_utility_wrapper(
     )
 }
 
-#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+#[cfg(all(
+    feature = "planner",
+    any(feature = "pg10", feature = "pg11", feature = "pg12")
+))]
 #[pg_guard]
 unsafe extern "C" fn pgx_standard_planner_wrapper(
     parse: *mut pg_sys::Query,
@@ -578,7 +591,10 @@ unsafe extern "C" fn pgx_standard_planner_wrapper(
     pg_sys::standard_planner(parse, cursor_options, bound_params)
 }
 
-#[cfg(any(feature = "pg13", feature = "pg14"))]
+#[cfg(all(
+    feature = "planner",
+    any(feature = "pg13", feature = "pg14", feature = "pg15")
+))]
 #[pg_guard]
 unsafe extern "C" fn pgx_standard_planner_wrapper(
     parse: *mut pg_sys::Query,
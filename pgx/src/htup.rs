@@ -189,3 +189,270 @@ pub fn heap_getattr_datum_ex(
         typbyval,
     }
 }
+
+/// A `pg_sys::HeapTuple` together with the `TupleDesc` describing it, such as the row a trigger
+/// fired for, whose attributes can be read and replaced by name.
+///
+/// Replacing attributes, with [`PgHeapTuple::with_updates()`] or [`PgHeapTuple::with_update()`],
+/// makes a new tuple with `heap_modify_tuple()`, which is what a `BEFORE` trigger returns to
+/// change the row being inserted or updated:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// // CREATE TRIGGER stamp BEFORE INSERT OR UPDATE ON documents FOR EACH ROW EXECUTE PROCEDURE stamp();
+/// #[pg_extern]
+/// unsafe fn stamp(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+///     if !called_as_trigger(fcinfo) {
+///         panic!("not called by trigger manager");
+///     }
+///     let trigdata = &*((*fcinfo).context as *mut pg_sys::TriggerData);
+///     let row = PgHeapTuple::from_trigger_data(trigdata);
+///     let title = row.get_by_name::<String>("title").map(|title| title.trim().to_string());
+///
+///     row.with_updates(&[
+///         ("title", title.into_datum()),
+///         ("revision", (row.get_by_name::<i32>("revision").unwrap_or(0) + 1).into_datum()),
+///     ])
+///     .into_pg() as pg_sys::Datum
+/// }
+/// ```
+pub struct PgHeapTuple<'a> {
+    tuple: pg_sys::HeapTuple,
+    tupdesc: PgBox<pg_sys::TupleDescData>,
+    _tupdesc: std::marker::PhantomData<&'a pg_sys::TupleDescData>,
+}
+
+impl<'a> PgHeapTuple<'a> {
+    /// Wrap `tuple`, which `tupdesc` describes
+    ///
+    /// ## Safety
+    ///
+    /// `tuple` must be a valid tuple of the row type `tupdesc` describes, and both must live for
+    /// `'a`
+    pub unsafe fn from_heap_tuple(tuple: pg_sys::HeapTuple, tupdesc: pg_sys::TupleDesc) -> Self {
+        assert!(!tuple.is_null(), "HeapTuple is null");
+        assert!(!tupdesc.is_null(), "TupleDesc is null");
+        PgHeapTuple {
+            tuple,
+            tupdesc: PgBox::from_pg(tupdesc),
+            _tupdesc: std::marker::PhantomData,
+        }
+    }
+
+    /// The row a row-level trigger returns, which is the new row for an `INSERT` or `UPDATE`,
+    /// and the deleted row for a `DELETE`
+    ///
+    /// ## Safety
+    ///
+    /// `trigdata` must be the `TriggerData` of a row-level trigger
+    pub unsafe fn from_trigger_data(trigdata: &'a pg_sys::TriggerData) -> Self {
+        let tuple = if crate::trigger_fired_by_update(trigdata.tg_event) {
+            trigdata.tg_newtuple
+        } else {
+            trigdata.tg_trigtuple
+        };
+        PgHeapTuple::from_heap_tuple(tuple, (*trigdata.tg_relation).rd_att)
+    }
+
+    /// The row a row-level `UPDATE` or `DELETE` trigger was fired for, as it was before the
+    /// statement, which unlike the new row of an `UPDATE`, has the system columns of the row in the
+    /// table.  `None` for an `INSERT`.
+    ///
+    /// ## Safety
+    ///
+    /// `trigdata` must be the `TriggerData` of a row-level trigger
+    pub unsafe fn old_from_trigger_data(trigdata: &'a pg_sys::TriggerData) -> Option<Self> {
+        if crate::trigger_fired_by_insert(trigdata.tg_event) || trigdata.tg_trigtuple.is_null() {
+            None
+        } else {
+            Some(PgHeapTuple::from_heap_tuple(
+                trigdata.tg_trigtuple,
+                (*trigdata.tg_relation).rd_att,
+            ))
+        }
+    }
+
+    /// Form a tuple of the row type `tupdesc` describes from the `Datum` of each of its
+    /// attributes, in order, where `None` is `NULL`, with `heap_form_tuple()`.  The tuple is
+    /// allocated in the `CurrentMemoryContext`
+    ///
+    /// ## Safety
+    ///
+    /// `tupdesc` must be valid for `'a`, and each value must be of its attribute's type
+    pub unsafe fn form(tupdesc: pg_sys::TupleDesc, values: &[Option<pg_sys::Datum>]) -> Self {
+        let natts = tupdesc.as_ref().expect("TupleDesc is null").natts as usize;
+        if values.len() != natts {
+            panic!(
+                "tuple has {} attributes, but {} values were given",
+                natts,
+                values.len()
+            );
+        }
+
+        let mut datums = values.iter().map(|v| v.unwrap_or(0)).collect::<Vec<_>>();
+        let mut nulls = values.iter().map(|v| v.is_none()).collect::<Vec<_>>();
+        let tuple = pg_sys::heap_form_tuple(tupdesc, datums.as_mut_ptr(), nulls.as_mut_ptr());
+        PgHeapTuple::from_heap_tuple(tuple, tupdesc)
+    }
+
+    /// How many attributes does the tuple have, including dropped ones?
+    pub fn len(&self) -> usize {
+        self.tupdesc.natts as usize
+    }
+
+    /// Does the tuple have any attributes?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get an attribute, which is `None` if it's `NULL`.  As with [`heap_getattr()`], `attno` is
+    /// 1-based
+    ///
+    /// ## Panics
+    ///
+    /// If `attno` isn't the number of one of the tuple's attributes
+    pub fn get<T: FromDatum>(&self, attno: usize) -> Option<T> {
+        let att = self.attribute(attno);
+        unsafe {
+            let datum = heap_getattr_raw(self.tuple, attno, self.tupdesc.as_ptr());
+            T::from_datum(datum.unwrap_or(0), datum.is_none(), att.atttypid)
+        }
+    }
+
+    /// Get the attribute called `name`, which is `None` if it's `NULL`
+    ///
+    /// ## Panics
+    ///
+    /// If the tuple has no attribute called `name`
+    pub fn get_by_name<T: FromDatum>(&self, name: &str) -> Option<T> {
+        self.get(self.attno(name))
+    }
+
+    /// The `ctid` system column, the tuple's location in its table, unless it wasn't read from
+    /// one, like a tuple formed in memory or the new row of a `BEFORE` trigger
+    pub fn ctid(&self) -> Option<pg_sys::ItemPointerData> {
+        let ctid = unsafe { (*self.tuple).t_self };
+        if unsafe { item_pointer_is_valid(&ctid) } {
+            Some(ctid)
+        } else {
+            None
+        }
+    }
+
+    /// The `tableoid` system column, the oid of the table the tuple was read from, unless it wasn't
+    /// read from one
+    pub fn table_oid(&self) -> Option<pg_sys::Oid> {
+        let oid = unsafe { (*self.tuple).t_tableOid };
+        if oid != pg_sys::InvalidOid {
+            Some(oid)
+        } else {
+            None
+        }
+    }
+
+    /// The `xmin` system column, the id of the transaction which inserted this version of the row,
+    /// which is `FrozenTransactionId` once the row is frozen
+    ///
+    /// Only a tuple read from a table has one.
+    pub fn xmin(&self) -> pg_sys::TransactionId {
+        self.system_attribute(pg_sys::MinTransactionIdAttributeNumber) as pg_sys::TransactionId
+    }
+
+    /// The `xmax` system column, the id of the transaction which deleted or locked this version of
+    /// the row, which is `InvalidTransactionId` if none has, and a `MultiXactId` if several locked
+    /// it
+    ///
+    /// Only a tuple read from a table has one.
+    pub fn xmax(&self) -> pg_sys::TransactionId {
+        self.system_attribute(pg_sys::MaxTransactionIdAttributeNumber) as pg_sys::TransactionId
+    }
+
+    /// A system column, by its (negative) attribute number, with `heap_getsysattr()`
+    fn system_attribute(&self, attnum: i32) -> pg_sys::Datum {
+        let mut isnull = false;
+        unsafe { pg_sys::heap_getsysattr(self.tuple, attnum, self.tupdesc.as_ptr(), &mut isnull) }
+    }
+
+    /// A copy of this tuple with the attributes named in `updates` replaced by their values,
+    /// where `None` is `NULL`, made with `heap_modify_tuple()` in the `CurrentMemoryContext`.
+    ///
+    /// Each value must be a `Datum` of its attribute's type, such as from
+    /// [`IntoDatum::into_datum()`] for the Rust type of the attribute's type, or else
+    /// [`IntoDatum::into_datum_as()`] with the attribute's type, as [`PgHeapTuple::with_update()`]
+    /// does.
+    ///
+    /// ## Panics
+    ///
+    /// If the tuple has no attribute called one of the names in `updates`
+    pub fn with_updates(&self, updates: &[(&str, Option<pg_sys::Datum>)]) -> PgHeapTuple<'a> {
+        let natts = self.len();
+        let mut datums = vec![0; natts];
+        let mut nulls = vec![false; natts];
+        let mut replace = vec![false; natts];
+        for (name, value) in updates {
+            let i = self.attno(name) - 1;
+            datums[i] = value.unwrap_or(0);
+            nulls[i] = value.is_none();
+            replace[i] = true;
+        }
+
+        unsafe {
+            let tuple = pg_sys::heap_modify_tuple(
+                self.tuple,
+                self.tupdesc.as_ptr(),
+                datums.as_mut_ptr(),
+                nulls.as_mut_ptr(),
+                replace.as_mut_ptr(),
+            );
+            PgHeapTuple::from_heap_tuple(tuple, self.tupdesc.as_ptr())
+        }
+    }
+
+    /// A copy of this tuple with the attribute called `name` replaced by `value`, converted to
+    /// the attribute's type with [`IntoDatum::into_datum_as()`], where `None` is `NULL`
+    ///
+    /// ## Panics
+    ///
+    /// If the tuple has no attribute called `name`, or `value` can't be converted to its type
+    pub fn with_update<T: IntoDatum>(&self, name: &str, value: T) -> PgHeapTuple<'a> {
+        let typoid = self.attribute(self.attno(name)).atttypid;
+        self.with_updates(&[(name, value.into_datum_as(typoid))])
+    }
+
+    /// The wrapped `pg_sys::HeapTuple`
+    pub fn as_ptr(&self) -> pg_sys::HeapTuple {
+        self.tuple
+    }
+
+    /// The `TupleDesc` describing the tuple
+    pub fn tupdesc(&self) -> pg_sys::TupleDesc {
+        self.tupdesc.as_ptr()
+    }
+
+    /// Unwrap the `pg_sys::HeapTuple`, such as to return it from a trigger as a `pg_sys::Datum`
+    pub fn into_pg(self) -> pg_sys::HeapTuple {
+        self.tuple
+    }
+
+    fn attribute(&self, attno: usize) -> &pg_sys::FormData_pg_attribute {
+        if attno == 0 || attno > self.len() {
+            panic!(
+                "attribute {} is out of bounds for a tuple with {} attributes",
+                attno,
+                self.len()
+            );
+        }
+        crate::tupdesc::tupdesc_get_attr(&self.tupdesc, attno - 1)
+    }
+
+    /// The 1-based number of the attribute called `name`
+    fn attno(&self, name: &str) -> usize {
+        (1..=self.len())
+            .find(|attno| {
+                let att = self.attribute(*attno);
+                !att.is_dropped() && att.name() == name
+            })
+            .unwrap_or_else(|| panic!("tuple has no attribute named `{}`", name))
+    }
+}
@@ -33,11 +33,29 @@ extern crate bitflags;
 // expose our various derive macros
 pub use pgx_macros::*;
 
+pub mod activity;
 pub mod aggregate;
+pub mod analyze;
+pub mod backend;
 pub mod callbacks;
+pub mod catalog_table;
+pub mod cancel;
+pub mod capabilities;
+pub mod coercion;
+pub mod collation;
+pub mod compute_pool;
 pub mod datum;
+pub mod dependency;
+pub mod domain;
+pub mod dynahash;
+pub mod encoding;
 pub mod enum_helper;
+pub mod expanded;
+pub mod explain;
+pub mod extension_api;
+pub mod fault;
 pub mod fcinfo;
+pub mod files;
 pub mod guc;
 pub mod hooks;
 pub mod htup;
@@ -47,18 +65,45 @@ pub mod list;
 #[macro_use]
 pub mod log;
 pub mod atomics;
+pub mod attstats;
+pub mod audit;
 pub mod bgworkers;
+#[cfg(feature = "storage")]
+pub mod buffile;
 pub mod lwlock;
 pub mod memcxt;
+pub mod metrics;
 pub mod misc;
 pub mod namespace;
 pub mod nodes;
+pub mod partition;
 pub mod pgbox;
+pub mod portable;
+pub mod privileges;
+pub mod query_id;
+pub mod quote;
 pub mod rel;
+pub mod reloptions;
+pub mod replication;
+pub mod roles;
+pub mod seclabel;
+pub mod secure_spi;
+#[cfg(feature = "planner")]
+pub mod selectivity;
+pub mod sequence;
+pub mod shared_cache;
 pub mod shmem;
+pub mod shmem_hash;
+pub mod sortsupport;
 pub mod spi;
+pub mod statistics;
 pub mod stringinfo;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+pub mod support;
+pub mod temp_table;
 pub mod trigger_support;
+pub mod tsearch;
+pub mod tupconvert;
 pub mod tupdesc;
 pub mod varlena;
 pub mod wrappers;
@@ -67,12 +112,33 @@ pub mod xid;
 #[doc(hidden)]
 pub use once_cell;
 
+pub use activity::*;
 pub use aggregate::*;
+pub use analyze::*;
 pub use atomics::*;
+pub use attstats::*;
+pub use audit::*;
+pub use backend::*;
+#[cfg(feature = "storage")]
+pub use buffile::*;
 pub use callbacks::*;
+pub use catalog_table::*;
+pub use cancel::*;
+pub use capabilities::*;
+pub use coercion::*;
+pub use collation::*;
 pub use datum::*;
+pub use dependency::*;
+pub use domain::*;
+pub use dynahash::*;
+pub use encoding::*;
 pub use enum_helper::*;
+pub use expanded::*;
+pub use explain::*;
+pub use extension_api::*;
+pub use fault::*;
 pub use fcinfo::*;
+pub use files::*;
 pub use guc::*;
 pub use hooks::*;
 pub use htup::*;
@@ -82,14 +148,37 @@ pub use list::*;
 pub use log::*;
 pub use lwlock::*;
 pub use memcxt::*;
+pub use metrics::*;
 pub use namespace::*;
 pub use nodes::*;
+pub use partition::*;
 pub use pgbox::*;
+pub use portable::*;
+pub use privileges::*;
+pub use query_id::*;
+pub use quote::*;
 pub use rel::*;
+pub use reloptions::*;
+pub use replication::*;
+pub use roles::*;
+pub use seclabel::*;
+pub use secure_spi::*;
+#[cfg(feature = "planner")]
+pub use selectivity::*;
+pub use sequence::*;
+pub use shared_cache::*;
 pub use shmem::*;
+pub use shmem_hash::*;
+pub use sortsupport::*;
 pub use spi::*;
+pub use statistics::*;
 pub use stringinfo::*;
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+pub use support::*;
+pub use temp_table::*;
 pub use trigger_support::*;
+pub use tsearch::*;
+pub use tupconvert::*;
 pub use tupdesc::*;
 pub use varlena::*;
 pub use wrappers::*;
@@ -102,6 +191,9 @@ pub use pgx_pg_sys::PgBuiltInOids; // reexport this so it looks like it comes fr
 pub use cstr_core;
 pub use pgx_utils as utils;
 
+/// The version of `pgx` the extension was built with
+pub const PGX_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 use core::any::TypeId;
 use once_cell::sync::Lazy;
 use std::collections::HashSet;
@@ -204,6 +296,7 @@ pub static DEFAULT_TYPEID_SQL_MAPPING: Lazy<HashSet<RustSqlMapping>> = Lazy::new
 
     map_type!(m, String, "text");
     map_type!(m, &std::ffi::CStr, "cstring");
+    map_type!(m, std::ffi::CString, "cstring");
     map_type!(m, &crate::cstr_core::CStr, "cstring");
     map_type!(m, (), "void");
     map_type!(m, i8, "\"char\"");
@@ -216,6 +309,8 @@ pub static DEFAULT_TYPEID_SQL_MAPPING: Lazy<HashSet<RustSqlMapping>> = Lazy::new
     map_type!(m, f64, "double precision");
     map_type!(m, datum::JsonB, "jsonb");
     map_type!(m, datum::Json, "json");
+    #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+    map_type!(m, datum::JsonPath, "jsonpath");
     map_type!(m, pgx_pg_sys::ItemPointerData, "tid");
     map_type!(m, pgx_pg_sys::Point, "point");
     map_type!(m, pgx_pg_sys::BOX, "box");
@@ -224,15 +319,40 @@ pub static DEFAULT_TYPEID_SQL_MAPPING: Lazy<HashSet<RustSqlMapping>> = Lazy::new
     map_type!(m, TimeWithTimeZone, "time with time zone");
     map_type!(m, Timestamp, "timestamp");
     map_type!(m, TimestampWithTimeZone, "timestamp with time zone");
+    map_type!(m, datum::Interval, "interval");
+    map_type!(m, datum::MaybeInfinite<Date>, "date");
+    map_type!(m, datum::MaybeInfinite<Timestamp>, "timestamp");
+    map_type!(
+        m,
+        datum::MaybeInfinite<TimestampWithTimeZone>,
+        "timestamp with time zone"
+    );
+    #[cfg(feature = "planner")]
     map_type!(m, pgx_pg_sys::PlannerInfo, "internal");
     map_type!(m, datum::Internal, "internal");
     map_type!(m, pgbox::PgBox<pgx_pg_sys::IndexAmRoutine>, "internal");
     map_type!(m, rel::PgRelation, "regclass");
+    map_type!(m, datum::RegClass, "regclass");
+    map_type!(m, datum::RegProc, "regproc");
+    map_type!(m, datum::RegType, "regtype");
     map_type!(m, datum::Numeric, "numeric");
     map_type!(m, datum::AnyElement, "anyelement");
+    map_type!(m, datum::Any, "\"any\"");
     map_type!(m, datum::AnyArray, "anyarray");
+    map_type!(m, datum::PgComposite, "record");
     map_type!(m, datum::Inet, "inet");
     map_type!(m, datum::Uuid, "uuid");
+    map_type!(m, datum::Xml, "xml");
+    map_type!(m, datum::Name, "name");
+    map_type!(m, datum::RefCursor, "refcursor");
+    map_type!(m, datum::TextBuilder, "text");
+    map_type!(m, datum::RawArrayBuf<i16>, "smallint[]");
+    map_type!(m, datum::RawArrayBuf<i32>, "integer[]");
+    map_type!(m, datum::RawArrayBuf<i64>, "bigint[]");
+    map_type!(m, datum::RawArrayBuf<f32>, "real[]");
+    map_type!(m, datum::RawArrayBuf<f64>, "double precision[]");
+    map_type!(m, datum::RawVarlenaBuf, "bytea");
+    map_type!(m, datum::AclItem, "aclitem");
 
     m
 });
@@ -310,7 +430,7 @@ macro_rules! pg_magic_func {
                 float8byval: pgx::pg_sys::USE_FLOAT8_BYVAL as i32,
             };
 
-            #[cfg(any(feature = "pg13", feature = "pg14"))]
+            #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
             const MY_MAGIC: pgx::pg_sys::Pg_magic_struct = pgx::pg_sys::Pg_magic_struct {
                 len: size_of::<pgx::pg_sys::Pg_magic_struct>() as i32,
                 version: pgx::pg_sys::PG_VERSION_NUM as i32 / 100,
@@ -396,4 +516,71 @@ macro_rules! pg_sql_graph_magic {
 #[allow(unused)]
 pub fn initialize() {
     register_pg_guard_panic_handler();
+    backend::register_backend_thread();
+    datum::register_server();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    /// The names of everything `pg_sys::` is called on in the Rust files under `path`
+    fn pg_sys_calls(path: &Path, calls: &mut HashSet<String>) {
+        if path.is_dir() {
+            for entry in std::fs::read_dir(path).unwrap() {
+                pg_sys_calls(&entry.unwrap().path(), calls);
+            }
+        } else if path.extension() == Some("rs".as_ref()) {
+            let source = std::fs::read_to_string(path).unwrap();
+            for (start, prefix) in source.match_indices("pg_sys::") {
+                let rest = &source[start + prefix.len()..];
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                if rest[end..].trim_start().starts_with('(') {
+                    calls.insert(rest[..end].to_string());
+                }
+            }
+        }
+    }
+
+    /// `PGX_PG_SYS_ALLOWLIST` leaves out the bindings of any function pgx calls that
+    /// `include/pgx_functions.txt` doesn't list, so pgx wouldn't compile
+    #[test]
+    fn pgx_functions_lists_every_function_pgx_calls() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let pg_sys = manifest_dir.join("../pgx-pg-sys");
+        let listed = std::fs::read_to_string(pg_sys.join("include/pgx_functions.txt")).unwrap();
+        let listed = listed
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect::<HashSet<_>>();
+
+        let mut functions = HashSet::new();
+        for version in ["pg10", "pg11", "pg12", "pg13", "pg14"] {
+            let bindings =
+                std::fs::read_to_string(pg_sys.join(format!("src/{}.rs", version))).unwrap();
+            for (start, prefix) in bindings.match_indices("pub fn ") {
+                let rest = &bindings[start + prefix.len()..];
+                if let Some(end) = rest.find('(') {
+                    functions.insert(rest[..end].to_string());
+                }
+            }
+        }
+
+        let mut calls = HashSet::new();
+        pg_sys_calls(&manifest_dir.join("src"), &mut calls);
+        let mut missing = calls
+            .iter()
+            .filter(|name| functions.contains(*name) && !listed.contains(name.as_str()))
+            .collect::<Vec<_>>();
+        missing.sort();
+        assert!(
+            missing.is_empty(),
+            "pgx-pg-sys/include/pgx_functions.txt is missing {:?}",
+            missing
+        );
+    }
 }
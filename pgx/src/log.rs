@@ -531,6 +531,42 @@ pub fn ereport(
     }
 }
 
+/// Emit a Postgres `ereport` at level `FATAL`, which ends the session, or the background worker,
+/// after the callbacks registered to run as the process exits have released its locks and
+/// aborted its transaction.  A background worker that ends this way exits with code 1, so the
+/// postmaster restarts it after its restart time.
+///
+/// The process exits from inside the `ereport`, so Rust values on the stack aren't dropped.
+#[track_caller]
+pub fn ereport_fatal(code: PgSqlErrorCode, message: &str) -> ! {
+    let location = std::panic::Location::caller();
+    ereport(
+        PgLogLevel::FATAL,
+        code,
+        message,
+        location.file(),
+        location.line(),
+        location.column(),
+    );
+    unreachable!("FATAL ereport returned")
+}
+
+/// Emit a Postgres `ereport` at level `PANIC`, which aborts the process, and makes the postmaster
+/// end every other backend and reinitialize shared memory, as after a crash
+#[track_caller]
+pub fn ereport_panic(code: PgSqlErrorCode, message: &str) -> ! {
+    let location = std::panic::Location::caller();
+    ereport(
+        PgLogLevel::PANIC,
+        code,
+        message,
+        location.file(),
+        location.line(),
+        location.column(),
+    );
+    unreachable!("PANIC ereport returned")
+}
+
 /// Log to Postgres' `debug5` log level.
 ///
 /// This macro accepts arguments like the [`println`](std::println) and [`format`](std::format) macros.
@@ -814,9 +850,26 @@ macro_rules! error {
 ///         before or while processing the request.
 /// The connection to the server was lost. Attempting reset: Succeeded.
 /// ```
+///
+/// Given a [`PgSqlErrorCode`] before the message, it's reported with that error code, rather
+/// than `XX000`, `internal_error`:
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::BackgroundWorker;
+/// use pgx::*;
+///
+/// pgx::FATAL!(
+///     PgSqlErrorCode::ERRCODE_ADMIN_SHUTDOWN,
+///     "terminating worker {}",
+///     BackgroundWorker::get_name()
+/// );
+/// ```
 #[allow(non_snake_case)]
 #[macro_export]
 macro_rules! FATAL {
+    ($code:path, $fmt:literal $($arg:tt)*) => (
+        $crate::log::ereport_fatal($code, format!($fmt $($arg)*).as_str())
+    );
     ($($arg:tt)*) => (
         { $crate::log::elog($crate::log::PgLogLevel::FATAL, format!($($arg)*).as_str()); unreachable!("elog failed"); }
     )
@@ -855,9 +908,14 @@ macro_rules! FATAL {
 ///         before or while processing the request.
 /// The connection to the server was lost. Attempting reset: Failed.
 /// ```
+///
+/// Like [`FATAL!`], it takes an optional [`PgSqlErrorCode`] before the message.
 #[allow(non_snake_case)]
 #[macro_export]
 macro_rules! PANIC {
+    ($code:path, $fmt:literal $($arg:tt)*) => (
+        $crate::log::ereport_panic($code, format!($fmt $($arg)*).as_str())
+    );
     ($($arg:tt)*) => (
         { $crate::log::elog($crate::log::PgLogLevel::PANIC, format!($($arg)*).as_str()); unreachable!("elog failed"); }
     )
@@ -880,7 +938,7 @@ pub fn interrupt_pending() -> bool {
 }
 
 /// Is an interrupt pending?
-#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
 #[inline]
 pub fn interrupt_pending() -> bool {
     unsafe { crate::pg_sys::InterruptPending != 0 }
@@ -899,7 +957,7 @@ macro_rules! check_for_interrupts {
             }
         }
 
-        #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14"))]
+        #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
         #[allow(unused_unsafe)]
         unsafe {
             if $crate::pg_sys::InterruptPending != 0 {
@@ -326,6 +326,7 @@ impl PgMemoryContexts {
 
     /// Allocate memory in this context, which will be free'd whenever Postgres deletes this MemoryContext
     pub fn palloc(&mut self, len: usize) -> *mut std::os::raw::c_void {
+        crate::debug_assert_backend!();
         unsafe { pg_sys::MemoryContextAlloc(self.value(), len) }
     }
 
@@ -353,13 +354,51 @@ impl PgMemoryContexts {
     ///
     /// The allocated memory is zero'd
     pub fn palloc0(&mut self, len: usize) -> *mut std::os::raw::c_void {
+        crate::debug_assert_backend!();
         unsafe { pg_sys::MemoryContextAllocZero(self.value(), len) }
     }
 
+    /// Move `v` to the heap, and run its `Drop` when this context is reset or deleted, including
+    /// when that's because the transaction aborted, like that of an aggregate which errored
+    /// mid-flight.  That lets a value own resources Postgres doesn't know about, like file
+    /// handles or mmaps, without leaking them.
+    ///
+    /// As the context may be reset while Postgres is cleaning up after an `ERROR`, a panic or
+    /// `ERROR` in `Drop` is reported as a `WARNING` rather than raised.
     pub fn leak_and_drop_on_delete<T>(&mut self, v: T) -> *mut T {
         unsafe extern "C" fn drop_on_delete<T>(ptr: void_mut_ptr) {
             let boxed = Box::from_raw(ptr as *mut T);
-            drop(boxed);
+            if let Err(e) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(boxed))) {
+                let message = if e.downcast_ref::<pg_sys::JumpContext>().is_some() {
+                    // it was an ERROR, which is still on Postgres' error stack
+                    let previous = PgMemoryContexts::TopMemoryContext.set_as_current();
+                    let edata = pg_sys::CopyErrorData();
+                    pg_sys::FlushErrorState();
+                    previous.set_as_current();
+                    let message = if (*edata).message.is_null() {
+                        "unknown error".to_string()
+                    } else {
+                        std::ffi::CStr::from_ptr((*edata).message)
+                            .to_string_lossy()
+                            .into_owned()
+                    };
+                    pg_sys::FreeErrorData(edata);
+                    message
+                } else if let Some(panic) = e.downcast_ref::<pg_sys::PgxPanic>() {
+                    panic.message.to_string()
+                } else if let Some(message) = e.downcast_ref::<String>() {
+                    message.clone()
+                } else if let Some(message) = e.downcast_ref::<&str>() {
+                    message.to_string()
+                } else {
+                    "unknown panic".to_string()
+                };
+                crate::warning!(
+                    "dropping a `{}` with its memory context failed: {}",
+                    std::any::type_name::<T>(),
+                    message
+                );
+            }
         }
 
         let leaked_ptr = Box::leak(Box::new(v));
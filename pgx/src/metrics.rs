@@ -0,0 +1,422 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Counters, gauges, and histograms of what an extension is doing, kept in shared memory, and
+//! exposed to SQL and to Prometheus
+use crate::{pg_sys, with_addin_shmem_init_lock, PgAtomicU64, PgSharedMemoryInitialization};
+use std::ffi::CString;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// The most buckets a histogram can have, besides the `+Inf` bucket every histogram has
+pub const MAX_HISTOGRAM_BUCKETS: usize = 16;
+
+/// What a [`PgMetric`] measures, which is how Prometheus treats it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PgMetricKind {
+    /// A count that only goes up, like requests served
+    Counter,
+    /// A value that goes up and down, like open connections
+    Gauge,
+    /// Counts of observations, like request durations, at most each of the buckets' upper bounds,
+    /// which must be in increasing order
+    Histogram(&'static [f64]),
+}
+
+impl PgMetricKind {
+    /// The kind's name, as Prometheus calls it
+    pub fn name(&self) -> &'static str {
+        match self {
+            PgMetricKind::Counter => "counter",
+            PgMetricKind::Gauge => "gauge",
+            PgMetricKind::Histogram(_) => "histogram",
+        }
+    }
+}
+
+/// A metric of a [`PgMetrics`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PgMetric {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: PgMetricKind,
+}
+
+impl PgMetric {
+    /// A [`PgMetricKind::Counter`]
+    pub const fn counter(name: &'static str, help: &'static str) -> Self {
+        PgMetric {
+            name,
+            help,
+            kind: PgMetricKind::Counter,
+        }
+    }
+
+    /// A [`PgMetricKind::Gauge`]
+    pub const fn gauge(name: &'static str, help: &'static str) -> Self {
+        PgMetric {
+            name,
+            help,
+            kind: PgMetricKind::Gauge,
+        }
+    }
+
+    /// A [`PgMetricKind::Histogram`], with at most [`MAX_HISTOGRAM_BUCKETS`] `buckets`
+    pub const fn histogram(
+        name: &'static str,
+        help: &'static str,
+        buckets: &'static [f64],
+    ) -> Self {
+        PgMetric {
+            name,
+            help,
+            kind: PgMetricKind::Histogram(buckets),
+        }
+    }
+}
+
+/// One metric's values, in shared memory
+struct MetricShared {
+    /// a counter's count, a gauge's `f64` bits, or a histogram's count of observations
+    value: PgAtomicU64,
+    /// the `f64` bits of a histogram's sum of observations
+    sum: PgAtomicU64,
+    /// a histogram's count of observations in each bucket and not in the one before
+    buckets: [PgAtomicU64; MAX_HISTOGRAM_BUCKETS],
+}
+
+/// `N` metrics, counted by every backend in shared memory.
+///
+/// Counting is lock-free, so backends never wait on each other to count.  Unlike
+/// [`PgStatistics`](crate::PgStatistics), the metrics aren't saved across restarts, which
+/// Prometheus expects of counters anyway.
+///
+/// Like any other shared memory type, it must be a `static` passed to `pg_shmem_init!()` during
+/// `_PG_init()`, and the extension must be in `shared_preload_libraries`.  Use
+/// [`pg_metrics_functions!()`](crate::pg_metrics_functions) to expose the metrics to SQL.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// const REQUESTS: usize = 0;
+/// const CONNECTIONS: usize = 1;
+/// const REQUEST_SECONDS: usize = 2;
+///
+/// static METRICS: PgMetrics<3> = PgMetrics::new(
+///     "myext",
+///     [
+///         PgMetric::counter("requests_total", "Requests served"),
+///         PgMetric::gauge("connections", "Open connections"),
+///         PgMetric::histogram("request_seconds", "How long requests took", &[0.01, 0.1, 1.0]),
+///     ],
+/// );
+///
+/// pg_metrics_functions!(METRICS, myext_metrics, myext_metrics_prometheus);
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(METRICS);
+/// }
+///
+/// fn served(seconds: f64) {
+///     METRICS.inc(REQUESTS);
+///     METRICS.observe(REQUEST_SECONDS, seconds);
+/// }
+/// ```
+pub struct PgMetrics<const N: usize> {
+    name: &'static str,
+    metrics: [PgMetric; N],
+    shared: AtomicPtr<[MetricShared; N]>,
+}
+
+unsafe impl<const N: usize> Send for PgMetrics<N> {}
+unsafe impl<const N: usize> Sync for PgMetrics<N> {}
+
+/// A value of a metric, from [`PgMetrics::samples()`], named as Prometheus names them.  A
+/// histogram has a `_bucket` sample for each bucket, counting the observations at most `le`,
+/// then `_sum` and `_count` samples
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgMetricSample {
+    pub name: String,
+    pub kind: PgMetricKind,
+    /// The upper bound of a `_bucket` sample, which is infinite for the last one
+    pub le: Option<f64>,
+    pub value: f64,
+}
+
+impl<const N: usize> PgMetrics<N> {
+    /// Create metrics named `name`.  The name identifies their shared memory, so it must be
+    /// unique, and is the namespace their names are prefixed with for Prometheus, so it must be
+    /// made of ASCII letters, digits, and underscores, as each metric's name must be
+    pub const fn new(name: &'static str, metrics: [PgMetric; N]) -> Self {
+        PgMetrics {
+            name,
+            metrics,
+            shared: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// The name given to [`PgMetrics::new()`]
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The metrics, in order
+    pub fn metrics(&self) -> &[PgMetric; N] {
+        &self.metrics
+    }
+
+    /// Add one to counter number `metric`
+    pub fn inc(&self, metric: usize) {
+        self.add(metric, 1)
+    }
+
+    /// Add `value` to counter number `metric`
+    pub fn add(&self, metric: usize, value: u64) {
+        self.expect_kind(metric, "counter");
+        self.shared()[metric].value.fetch_add(value);
+    }
+
+    /// Set gauge number `metric` to `value`
+    pub fn set(&self, metric: usize, value: f64) {
+        self.expect_kind(metric, "gauge");
+        self.shared()[metric].value.write(value.to_bits());
+    }
+
+    /// Add `delta`, which may be negative, to gauge number `metric`
+    pub fn add_gauge(&self, metric: usize, delta: f64) {
+        self.expect_kind(metric, "gauge");
+        add_f64(&self.shared()[metric].value, delta);
+    }
+
+    /// The value of counter or gauge number `metric`, or the count of observations of histogram
+    /// number `metric`
+    pub fn get(&self, metric: usize) -> f64 {
+        let shared = &self.shared()[metric];
+        match self.metrics[metric].kind {
+            PgMetricKind::Gauge => f64::from_bits(shared.value.read()),
+            _ => shared.value.read() as f64,
+        }
+    }
+
+    /// Count `value` in histogram number `metric`
+    pub fn observe(&self, metric: usize, value: f64) {
+        let buckets = match self.expect_kind(metric, "histogram") {
+            PgMetricKind::Histogram(buckets) => buckets,
+            _ => unreachable!(),
+        };
+        let shared = &self.shared()[metric];
+        if let Some(bucket) = buckets.iter().position(|le| value <= *le) {
+            shared.buckets[bucket].fetch_add(1);
+        }
+        add_f64(&shared.sum, value);
+        shared.value.fetch_add(1);
+    }
+
+    /// The value of every metric.  Each is read atomically, but other backends may count between
+    /// one and the next
+    pub fn samples(&self) -> Vec<PgMetricSample> {
+        (0..N)
+            .flat_map(|metric| self.metric_samples(metric))
+            .collect()
+    }
+
+    /// Every metric, in Prometheus' text exposition format, for a scraper to fetch, such as
+    /// through a `COPY (SELECT myext_metrics_prometheus()) TO STDOUT` run by an exporter
+    pub fn prometheus_text(&self) -> String {
+        let mut text = String::new();
+        for (index, metric) in self.metrics.iter().enumerate() {
+            let name = format!("{}_{}", self.name, metric.name);
+            writeln!(text, "# HELP {} {}", name, escape_help(metric.help)).unwrap();
+            writeln!(text, "# TYPE {} {}", name, metric.kind.name()).unwrap();
+            for sample in self.metric_samples(index) {
+                write!(text, "{}_{}", self.name, sample.name).unwrap();
+                if let Some(le) = sample.le {
+                    write!(text, "{{le=\"{}\"}}", format_value(le)).unwrap();
+                }
+                writeln!(text, " {}", format_value(sample.value)).unwrap();
+            }
+        }
+        text
+    }
+
+    fn metric_samples(&self, index: usize) -> Vec<PgMetricSample> {
+        let metric = &self.metrics[index];
+        let shared = &self.shared()[index];
+        let sample = |suffix: &str, le: Option<f64>, value: f64| PgMetricSample {
+            name: format!("{}{}", metric.name, suffix),
+            kind: metric.kind,
+            le,
+            value,
+        };
+        match metric.kind {
+            PgMetricKind::Counter => vec![sample("", None, shared.value.read() as f64)],
+            PgMetricKind::Gauge => vec![sample("", None, f64::from_bits(shared.value.read()))],
+            PgMetricKind::Histogram(buckets) => {
+                // the buckets are cumulative, and the last is every observation, which another
+                // backend's `observe()` may not have counted yet when its bucket was read
+                let mut samples = Vec::with_capacity(buckets.len() + 3);
+                let mut cumulative = 0;
+                for (le, bucket) in buckets.iter().zip(&shared.buckets) {
+                    cumulative += bucket.read();
+                    samples.push(sample("_bucket", Some(*le), cumulative as f64));
+                }
+                let count = shared.value.read().max(cumulative) as f64;
+                samples.push(sample("_bucket", Some(f64::INFINITY), count));
+                samples.push(sample("_sum", None, f64::from_bits(shared.sum.read())));
+                samples.push(sample("_count", None, count));
+                samples
+            }
+        }
+    }
+
+    fn expect_kind(&self, metric: usize, kind: &str) -> PgMetricKind {
+        let found = self
+            .metrics
+            .get(metric)
+            .unwrap_or_else(|| panic!("`{}` has no metric {}", self.name, metric));
+        if found.kind.name() != kind {
+            panic!("`{}` metric `{}` is not a {}", self.name, found.name, kind);
+        }
+        found.kind
+    }
+
+    fn shared(&self) -> &[MetricShared; N] {
+        let shared = self.shared.load(Ordering::SeqCst);
+        assert!(
+            !shared.is_null(),
+            "PgMetrics `{}` is not in shared memory.  Was it passed to `pg_shmem_init!()`?",
+            self.name
+        );
+        unsafe { &*shared }
+    }
+}
+
+impl<const N: usize> PgSharedMemoryInitialization for PgMetrics<N> {
+    fn pg_init(&'static self) {
+        for metric in &self.metrics {
+            if let PgMetricKind::Histogram(buckets) = metric.kind {
+                assert!(
+                    buckets.len() <= MAX_HISTOGRAM_BUCKETS,
+                    "`{}` histogram `{}` has more than {} buckets",
+                    self.name,
+                    metric.name,
+                    MAX_HISTOGRAM_BUCKETS
+                );
+                assert!(
+                    buckets.windows(2).all(|pair| pair[0] < pair[1]),
+                    "`{}` histogram `{}`'s buckets are not in increasing order",
+                    self.name,
+                    metric.name
+                );
+            }
+        }
+        unsafe {
+            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<[MetricShared; N]>());
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.name).expect("CString::new() failed");
+
+            with_addin_shmem_init_lock(|| {
+                let mut found = false;
+                let shared = pg_sys::ShmemInitStruct(
+                    name.as_ptr(),
+                    std::mem::size_of::<[MetricShared; N]>(),
+                    &mut found,
+                ) as *mut [MetricShared; N];
+                if !found {
+                    // zero, as a count and as the bits of an `f64`
+                    std::ptr::write_bytes(shared, 0, 1);
+                }
+                self.shared.store(shared, Ordering::SeqCst);
+            });
+        }
+    }
+}
+
+/// Add `delta` to the `f64` whose bits `atomic` holds
+fn add_f64(atomic: &PgAtomicU64, delta: f64) {
+    let mut current = atomic.read();
+    loop {
+        let new = (f64::from_bits(current) + delta).to_bits();
+        match atomic.compare_exchange(current, new) {
+            Ok(_) => return,
+            Err(actual) => current = actual,
+        }
+    }
+}
+
+/// A value as Prometheus writes them
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Create SQL functions exposing a [`PgMetrics`]
+///
+/// `$view` is a set returning function with a row for each of [`PgMetrics::samples()`], as
+/// `(name text, kind text, le double precision, value double precision)`.  `$prometheus`, if
+/// given, is a function returning [`PgMetrics::prometheus_text()`].
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// static CACHE_METRICS: PgMetrics<2> = PgMetrics::new(
+///     "cache",
+///     [
+///         PgMetric::counter("hits_total", "Lookups found in the cache"),
+///         PgMetric::counter("misses_total", "Lookups not found in the cache"),
+///     ],
+/// );
+///
+/// pg_metrics_functions!(CACHE_METRICS, cache_metrics, cache_metrics_prometheus);
+/// ```
+#[macro_export]
+macro_rules! pg_metrics_functions {
+    ($metrics:ident, $view:ident) => {
+        #[$crate::pg_extern]
+        fn $view() -> impl std::iter::Iterator<
+            Item = (
+                name!(name, String),
+                name!(kind, &'static str),
+                name!(le, Option<f64>),
+                name!(value, f64),
+            ),
+        > {
+            $metrics
+                .samples()
+                .into_iter()
+                .map(|sample| (sample.name, sample.kind.name(), sample.le, sample.value))
+        }
+    };
+    ($metrics:ident, $view:ident, $prometheus:ident) => {
+        $crate::pg_metrics_functions!($metrics, $view);
+
+        #[$crate::pg_extern]
+        fn $prometheus() -> String {
+            $metrics.prometheus_text()
+        }
+    };
+}
@@ -6,12 +6,21 @@ All rights reserved.
 
 Use of this source code is governed by the MIT license that can be found in the LICENSE file.
 */
+use std::ffi::CString;
 use std::hash::{Hash, Hasher};
 
 /// wrapper around `SeaHasher` from [Seahash](https://crates.io/crates/seahash)
 ///
 /// Primarily used by `pgx`'s `#[derive(PostgresHash)]` macro.
 pub fn pgx_seahash<T: Hash>(value: &T) -> u64 {
+    pgx_seahash_extended(value, 0)
+}
+
+/// [`pgx_seahash()`], with `seed` mixed in, like Postgres' extended hash functions.  A `seed` of
+/// zero gives the same hash as [`pgx_seahash()`], which Postgres requires of them.
+///
+/// Primarily used by `pgx`'s `#[derive(PostgresHash)]` macro.
+pub fn pgx_seahash_extended<T: Hash>(value: &T, seed: u64) -> u64 {
     // taken from sources of "SeaHasher, v4.0.1" [Seahash](https://crates.io/crates/seahash)
     // assuming the underlying implementation doesn't change, we
     // also want to ensure however we seed it doesn't change either
@@ -19,7 +28,7 @@ pub fn pgx_seahash<T: Hash>(value: &T) -> u64 {
     // these hash values might be stored on disk by Postgres, so we can't afford
     // to have them changing over time
     let mut hasher = seahash::SeaHasher::with_seeds(
-        0x16f11fe89b0d677c,
+        0x16f11fe89b0d677c ^ seed,
         0xb480a793d8e6c86c,
         0x6fe2e5aaf078ebc9,
         0x14f994a4c5259381,
@@ -27,3 +36,9 @@ pub fn pgx_seahash<T: Hash>(value: &T) -> u64 {
     value.hash(&mut hasher);
     hasher.finish()
 }
+
+/// `s` as a `CString`, for passing to Postgres.  `what` names it in the panic if it contains a
+/// null byte
+pub(crate) fn cstring(s: &str, what: &str) -> CString {
+    CString::new(s).unwrap_or_else(|_| panic!("{} contains a null byte", what))
+}
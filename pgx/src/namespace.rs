@@ -14,9 +14,16 @@ use crate::pg_sys;
 
 /// A helper struct for creating a Postgres `List` of `String`s to qualify an object name
 pub struct PgQualifiedNameBuilder {
-    list: PgList<pg_sys::Value>,
+    list: PgList<QualifiedNamePart>,
 }
 
+/// The `Node` type `pg_sys::makeString()` returns.  Postgres 15 split the generic `Value` node
+/// into one node type per kind of value
+#[cfg(not(feature = "pg15"))]
+type QualifiedNamePart = pg_sys::Value;
+#[cfg(feature = "pg15")]
+type QualifiedNamePart = pg_sys::String;
+
 impl Default for PgQualifiedNameBuilder {
     fn default() -> Self {
         Self::new()
@@ -26,7 +33,7 @@ impl Default for PgQualifiedNameBuilder {
 impl PgQualifiedNameBuilder {
     pub fn new() -> PgQualifiedNameBuilder {
         PgQualifiedNameBuilder {
-            list: PgList::<pg_sys::Value>::new(),
+            list: PgList::<QualifiedNamePart>::new(),
         }
     }
 
@@ -0,0 +1,532 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Finding the partition of a partitioned table that a row belongs in
+use crate::{pg_sys, PgList, PgRelation};
+
+/// Finds which leaf partition of a partitioned table a row belongs in, for an extension that
+/// inserts rows into the partitions itself, so they land where an `INSERT` into the partitioned
+/// table would have put them.
+///
+/// A row is routed the way Postgres routes an `INSERT`: its partition key is computed for the
+/// root, the root's partition bounds are searched for the partition it belongs in, falling back
+/// to the default partition, and so on down through any partitioned partitions.  The partition
+/// keys, descriptors, and key expressions are set up once by [`PartitionRouter::new()`], so
+/// routing many rows should reuse one router.  Partitions attached or detached after it's made
+/// aren't seen, so it shouldn't outlive the lock it was made with.
+///
+/// On Postgres 10, which has no default partitions, a row with a `NULL` in a range partition
+/// key raises the same ERROR an `INSERT` would.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// fn insert_measurements(measurements: &PgRelation, rows: Vec<(i32, f64)>) {
+///     let router = PartitionRouter::new(measurements, pg_sys::RowExclusiveLock as pg_sys::LOCKMODE);
+///     for (sensor, value) in rows {
+///         let partition = router
+///             .find_partition(vec![sensor.into_datum(), value.into_datum()])
+///             .expect("no partition for row");
+///         // ... insert the row into `partition`
+///     }
+/// }
+/// ```
+pub struct PartitionRouter {
+    estate: *mut pg_sys::EState,
+    econtext: *mut pg_sys::ExprContext,
+    natts: usize,
+    /// the root, kept open for the dispatch information that points into its relcache entry
+    #[cfg(feature = "pg10")]
+    _root: PgRelation,
+    #[cfg(feature = "pg10")]
+    dispatch: *mut pg_sys::PartitionDispatch,
+    #[cfg(feature = "pg10")]
+    num_dispatch: usize,
+    /// a slot in the root's row type, which rows are routed from
+    #[cfg(feature = "pg10")]
+    slot: *mut pg_sys::TupleTableSlot,
+    /// the root and its partitioned partitions, with the root first
+    #[cfg(not(feature = "pg10"))]
+    levels: Vec<PartitionedTable>,
+    leaves: Vec<pg_sys::Oid>,
+    /// the leaves that are their parent's default partition
+    default_leaves: Vec<pg_sys::Oid>,
+}
+
+#[cfg(not(feature = "pg10"))]
+struct PartitionedTable {
+    /// kept open, as `key` and `partdesc` belong to its relcache entry
+    _relation: PgRelation,
+    key: pg_sys::PartitionKey,
+    partdesc: pg_sys::PartitionDesc,
+    /// the compiled expressions of the key's columns that are expressions, in order
+    key_exprs: Vec<*mut pg_sys::ExprState>,
+    /// a slot in the table's row type, as its key's attribute numbers are the table's, which may
+    /// be numbered differently than the root's after columns are dropped
+    slot: *mut pg_sys::TupleTableSlot,
+    /// for each of the table's attributes, the root attribute it is, or `None` if dropped
+    root_attnos: Vec<Option<usize>>,
+    /// what each partition in `partdesc` is, in its order
+    partitions: Vec<Partition>,
+}
+
+#[cfg(not(feature = "pg10"))]
+#[derive(Clone, Copy)]
+enum Partition {
+    Leaf(pg_sys::Oid),
+    /// an index into `PartitionRouter::levels`
+    Partitioned(usize),
+}
+
+/// the seed hash partitions hash their keys with, from `partitioning/partbounds.h`
+#[cfg(not(feature = "pg10"))]
+const HASH_PARTITION_SEED: u64 = 0x7A5B22367996DCFD;
+
+impl PartitionRouter {
+    /// Make a router for the partitioned table `root`, locking all of its partitions with
+    /// `lockmode`, which should be `RowExclusiveLock` to insert into them.  The router is
+    /// allocated in the `CurrentMemoryContext`.
+    ///
+    /// ## Panics
+    ///
+    /// If `root` isn't a partitioned table
+    pub fn new(root: &PgRelation, lockmode: pg_sys::LOCKMODE) -> Self {
+        if !root.is_partitioned_table() {
+            panic!("\"{}\" is not a partitioned table", root.name());
+        }
+
+        unsafe {
+            // an INSERT locks the whole tree before it looks at any partition descriptor
+            pg_sys::find_all_inheritors(root.oid(), lockmode, std::ptr::null_mut());
+        }
+
+        let estate = unsafe { pg_sys::CreateExecutorState() };
+        let econtext = unsafe { pg_sys::MakePerTupleExprContext(estate) };
+        let natts = root.tuple_desc().len();
+        Self::with_partitions(root, estate, econtext, natts)
+    }
+
+    #[cfg(feature = "pg10")]
+    fn with_partitions(
+        root: &PgRelation,
+        estate: *mut pg_sys::EState,
+        econtext: *mut pg_sys::ExprContext,
+        natts: usize,
+    ) -> Self {
+        unsafe {
+            let root = PgRelation::open(root.oid());
+            let mut num_dispatch = 0;
+            let mut leaf_oids = std::ptr::null_mut();
+            let dispatch = pg_sys::RelationGetPartitionDispatchInfo(
+                root.as_ptr(),
+                &mut num_dispatch,
+                &mut leaf_oids,
+            );
+            let leaves = PgList::<pg_sys::Oid>::from_pg(leaf_oids)
+                .iter_oid()
+                .collect();
+            let slot = make_virtual_slot(pg_sys::CreateTupleDescCopy(root.rd_att));
+            PartitionRouter {
+                estate,
+                econtext,
+                natts,
+                _root: root,
+                dispatch,
+                num_dispatch: num_dispatch as usize,
+                slot,
+                leaves,
+                default_leaves: Vec::new(),
+            }
+        }
+    }
+
+    #[cfg(not(feature = "pg10"))]
+    fn with_partitions(
+        root: &PgRelation,
+        estate: *mut pg_sys::EState,
+        econtext: *mut pg_sys::ExprContext,
+        natts: usize,
+    ) -> Self {
+        let root_names = root
+            .tuple_desc()
+            .iter()
+            .map(|att| (!att.is_dropped()).then(|| att.name().to_string()))
+            .collect::<Vec<_>>();
+
+        let mut router = PartitionRouter {
+            estate,
+            econtext,
+            natts,
+            levels: Vec::new(),
+            leaves: Vec::new(),
+            default_leaves: Vec::new(),
+        };
+        router.add_partitioned_table(unsafe { PgRelation::open(root.oid()) }, &root_names);
+        router
+    }
+
+    /// Add `relation`, and its partitioned partitions after it, to `levels`, returning its index
+    #[cfg(not(feature = "pg10"))]
+    fn add_partitioned_table(
+        &mut self,
+        relation: PgRelation,
+        root_names: &[Option<String>],
+    ) -> usize {
+        let index = self.levels.len();
+        unsafe {
+            let key = partition_key(relation.as_ptr());
+            let partdesc = partition_desc(relation.as_ptr());
+            let key_exprs = PgList::<pg_sys::ExprState>::from_pg(pg_sys::ExecPrepareExprList(
+                (*key).partexprs,
+                self.estate,
+            ))
+            .iter_ptr()
+            .collect();
+            let root_attnos = relation
+                .tuple_desc()
+                .iter()
+                .map(|att| {
+                    if att.is_dropped() {
+                        None
+                    } else {
+                        root_names
+                            .iter()
+                            .position(|name| name.as_deref() == Some(att.name()))
+                    }
+                })
+                .collect();
+            let slot = make_virtual_slot(pg_sys::CreateTupleDescCopy(relation.rd_att));
+            self.levels.push(PartitionedTable {
+                _relation: relation,
+                key,
+                partdesc,
+                key_exprs,
+                slot,
+                root_attnos,
+                partitions: Vec::new(),
+            });
+
+            let nparts = (*partdesc).nparts as usize;
+            let default_index = if nparts == 0 {
+                -1
+            } else {
+                (*(*partdesc).boundinfo).default_index
+            };
+            let mut partitions = Vec::with_capacity(nparts);
+            for i in 0..nparts {
+                let oid = *(*partdesc).oids.add(i);
+                if pg_sys::get_rel_relkind(oid)
+                    == pg_sys::RELKIND_PARTITIONED_TABLE as std::os::raw::c_char
+                {
+                    let child = self.add_partitioned_table(PgRelation::open(oid), root_names);
+                    partitions.push(Partition::Partitioned(child));
+                } else {
+                    if i as i32 == default_index {
+                        self.default_leaves.push(oid);
+                    } else {
+                        self.leaves.push(oid);
+                    }
+                    partitions.push(Partition::Leaf(oid));
+                }
+            }
+            self.levels[index].partitions = partitions;
+        }
+        index
+    }
+
+    /// The oid of the leaf partition that a row with the root table's attribute `values`, in
+    /// order and where `None` is `NULL`, belongs in, or `None` if no partition accepts it, which
+    /// an `INSERT` would raise an ERROR for.
+    ///
+    /// ## Panics
+    ///
+    /// If `values` doesn't have a value for every attribute of the root table, including any
+    /// dropped ones
+    pub fn find_partition(&self, values: Vec<Option<pg_sys::Datum>>) -> Option<pg_sys::Oid> {
+        if values.len() != self.natts {
+            panic!(
+                "partitioned table has {} attributes, but {} values were given",
+                self.natts,
+                values.len()
+            );
+        }
+
+        unsafe {
+            pg_sys::MemoryContextReset((*self.econtext).ecxt_per_tuple_memory);
+        }
+        self.route(&values)
+    }
+
+    #[cfg(feature = "pg10")]
+    fn route(&self, values: &[Option<pg_sys::Datum>]) -> Option<pg_sys::Oid> {
+        unsafe {
+            clear_slot(self.slot);
+            for (i, value) in values.iter().enumerate() {
+                *(*self.slot).tts_values.add(i) = value.unwrap_or(0);
+                *(*self.slot).tts_isnull.add(i) = value.is_none();
+            }
+            pg_sys::ExecStoreVirtualTuple(self.slot);
+
+            let mut failed_at = std::ptr::null_mut();
+            let mut failed_slot = std::ptr::null_mut();
+            let index = pg_sys::get_partition_for_tuple(
+                self.dispatch,
+                self.slot,
+                self.estate,
+                &mut failed_at,
+                &mut failed_slot,
+            );
+            (index >= 0).then(|| self.leaves[index as usize])
+        }
+    }
+
+    #[cfg(not(feature = "pg10"))]
+    fn route(&self, values: &[Option<pg_sys::Datum>]) -> Option<pg_sys::Oid> {
+        let mut table = &self.levels[0];
+        loop {
+            let index = unsafe { self.partition_index(table, values) }?;
+            match table.partitions[index] {
+                Partition::Leaf(oid) => return Some(oid),
+                Partition::Partitioned(level) => table = &self.levels[level],
+            }
+        }
+    }
+
+    /// The index in `table`'s partition descriptor of the partition the row belongs in, as
+    /// `get_partition_for_tuple()` in `executor/execPartition.c` finds it
+    #[allow(clippy::unnecessary_lazy_evaluations)]
+    #[cfg(not(feature = "pg10"))]
+    unsafe fn partition_index(
+        &self,
+        table: &PartitionedTable,
+        values: &[Option<pg_sys::Datum>],
+    ) -> Option<usize> {
+        let partdesc = &*table.partdesc;
+        if partdesc.nparts == 0 {
+            return None;
+        }
+        let boundinfo = &*partdesc.boundinfo;
+        let key = &*table.key;
+
+        clear_slot(table.slot);
+        for (i, root_attno) in table.root_attnos.iter().enumerate() {
+            let value = root_attno.and_then(|attno| values[attno]);
+            *(*table.slot).tts_values.add(i) = value.unwrap_or(0);
+            *(*table.slot).tts_isnull.add(i) = value.is_none();
+        }
+        pg_sys::ExecStoreVirtualTuple(table.slot);
+        (*self.econtext).ecxt_scantuple = table.slot;
+
+        let partnatts = key.partnatts as usize;
+        let mut key_values: Vec<pg_sys::Datum> = Vec::with_capacity(partnatts);
+        let mut key_isnull = Vec::with_capacity(partnatts);
+        let mut key_exprs = table.key_exprs.iter();
+        for i in 0..partnatts {
+            let attno = *key.partattrs.add(i);
+            let (value, is_null) = if attno == 0 {
+                let state = *key_exprs
+                    .next()
+                    .expect("partition key is missing an expression");
+                let econtext = self.econtext;
+                let evalfunc = (*state).evalfunc.expect("ExprState has no evalfunc");
+                crate::PgMemoryContexts::For((*econtext).ecxt_per_tuple_memory).switch_to(|_| {
+                    let mut is_null = false;
+                    let value = evalfunc(state, econtext, &mut is_null);
+                    (value, is_null)
+                })
+            } else {
+                let attno = attno as usize - 1;
+                (
+                    *(*table.slot).tts_values.add(attno),
+                    *(*table.slot).tts_isnull.add(attno),
+                )
+            };
+            key_values.push(value);
+            key_isnull.push(is_null);
+        }
+
+        let index = match key.strategy as u8 {
+            pg_sys::PARTITION_STRATEGY_HASH => {
+                // the greatest modulus, as `get_hash_partition_greatest_modulus()` finds it
+                let modulus = **boundinfo.datums.add(boundinfo.ndatums as usize - 1) as i32 as u64;
+                let hash = hash_partition_key(key, &key_values, &key_isnull);
+                *boundinfo.indexes.add((hash % modulus) as usize)
+            }
+            pg_sys::PARTITION_STRATEGY_LIST => {
+                if key_isnull[0] {
+                    boundinfo.null_index
+                } else {
+                    let mut is_equal = false;
+                    let offset = pg_sys::partition_list_bsearch(
+                        key.partsupfunc,
+                        key.partcollation,
+                        partdesc.boundinfo,
+                        key_values[0],
+                        &mut is_equal,
+                    );
+                    if offset >= 0 && is_equal {
+                        *boundinfo.indexes.add(offset as usize)
+                    } else {
+                        -1
+                    }
+                }
+            }
+            pg_sys::PARTITION_STRATEGY_RANGE => {
+                // a NULL in any column of a range key is only accepted by a default partition
+                if key_isnull.contains(&true) {
+                    -1
+                } else {
+                    let mut is_equal = false;
+                    let offset = pg_sys::partition_range_datum_bsearch(
+                        key.partsupfunc,
+                        key.partcollation,
+                        partdesc.boundinfo,
+                        partnatts as i32,
+                        key_values.as_mut_ptr(),
+                        &mut is_equal,
+                    );
+                    *boundinfo.indexes.add((offset + 1) as usize)
+                }
+            }
+            strategy => panic!("unrecognized partitioning strategy {}", strategy),
+        };
+
+        let index = if index < 0 {
+            boundinfo.default_index
+        } else {
+            index
+        };
+        (index >= 0).then(|| index as usize)
+    }
+
+    /// The oids of all of the leaf partitions, with the default partitions last
+    pub fn leaf_partitions(&self) -> Vec<pg_sys::Oid> {
+        self.leaves
+            .iter()
+            .chain(self.default_leaves.iter())
+            .copied()
+            .collect()
+    }
+
+    /// The oids of the leaf partitions that are the default partition of the root table or of
+    /// one of its partitioned partitions, which get the rows their siblings don't accept
+    pub fn default_partitions(&self) -> Vec<pg_sys::Oid> {
+        self.default_leaves.clone()
+    }
+}
+
+impl Drop for PartitionRouter {
+    fn drop(&mut self) {
+        unsafe {
+            #[cfg(feature = "pg10")]
+            {
+                // the first is the root, which has no slot of its own
+                for i in 1..self.num_dispatch {
+                    let dispatch = *self.dispatch.add(i);
+                    pg_sys::relation_close((*dispatch).reldesc, pg_sys::NoLock as pg_sys::LOCKMODE);
+                    pg_sys::ExecDropSingleTupleTableSlot((*dispatch).tupslot);
+                }
+                pg_sys::ExecDropSingleTupleTableSlot(self.slot);
+            }
+            #[cfg(not(feature = "pg10"))]
+            for table in &self.levels {
+                pg_sys::ExecDropSingleTupleTableSlot(table.slot);
+            }
+            pg_sys::FreeExecutorState(self.estate);
+        }
+    }
+}
+
+/// The hash of a row's hash partition key, as `compute_partition_hash_value()` in
+/// `partitioning/partbounds.c` computes it
+#[cfg(not(feature = "pg10"))]
+unsafe fn hash_partition_key(
+    key: &pg_sys::PartitionKeyData,
+    values: &[pg_sys::Datum],
+    isnull: &[bool],
+) -> u64 {
+    let mut row_hash = 0u64;
+    for (i, (&value, &is_null)) in values.iter().zip(isnull).enumerate() {
+        if is_null {
+            continue;
+        }
+        let hash = pg_sys::FunctionCall2Coll(
+            key.partsupfunc.add(i),
+            hash_collation(key, i),
+            value,
+            HASH_PARTITION_SEED as pg_sys::Datum,
+        ) as u64;
+        // `hash_combine64()`
+        row_hash ^= hash
+            .wrapping_add(0x49a0f4dd15e5a975)
+            .wrapping_add(row_hash << 54)
+            .wrapping_add(row_hash >> 7);
+    }
+    row_hash
+}
+
+/// Postgres 11 hashes partition keys without their collation
+#[cfg(feature = "pg11")]
+unsafe fn hash_collation(_key: &pg_sys::PartitionKeyData, _i: usize) -> pg_sys::Oid {
+    pg_sys::InvalidOid
+}
+
+#[cfg(not(any(feature = "pg10", feature = "pg11")))]
+unsafe fn hash_collation(key: &pg_sys::PartitionKeyData, i: usize) -> pg_sys::Oid {
+    *key.partcollation.add(i)
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12"))]
+unsafe fn partition_key(relation: pg_sys::Relation) -> pg_sys::PartitionKey {
+    (*relation).rd_partkey
+}
+
+#[cfg(not(any(feature = "pg10", feature = "pg11", feature = "pg12")))]
+unsafe fn partition_key(relation: pg_sys::Relation) -> pg_sys::PartitionKey {
+    pg_sys::RelationGetPartitionKey(relation)
+}
+
+#[cfg(any(feature = "pg11", feature = "pg12"))]
+unsafe fn partition_desc(relation: pg_sys::Relation) -> pg_sys::PartitionDesc {
+    (*relation).rd_partdesc
+}
+
+#[cfg(feature = "pg13")]
+unsafe fn partition_desc(relation: pg_sys::Relation) -> pg_sys::PartitionDesc {
+    pg_sys::RelationGetPartitionDesc(relation)
+}
+
+/// Partitions being detached concurrently are left out, as an `INSERT` leaves them out
+#[cfg(not(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13")))]
+unsafe fn partition_desc(relation: pg_sys::Relation) -> pg_sys::PartitionDesc {
+    pg_sys::RelationGetPartitionDesc(relation, true)
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn make_virtual_slot(tupdesc: pg_sys::TupleDesc) -> *mut pg_sys::TupleTableSlot {
+    pg_sys::MakeSingleTupleTableSlot(tupdesc)
+}
+
+#[cfg(not(any(feature = "pg10", feature = "pg11")))]
+unsafe fn make_virtual_slot(tupdesc: pg_sys::TupleDesc) -> *mut pg_sys::TupleTableSlot {
+    pg_sys::MakeSingleTupleTableSlot(tupdesc, &pg_sys::TTSOpsVirtual)
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn clear_slot(slot: *mut pg_sys::TupleTableSlot) {
+    pg_sys::ExecClearTuple(slot);
+}
+
+/// `ExecClearTuple()` is an inline function in these versions
+#[cfg(not(any(feature = "pg10", feature = "pg11")))]
+unsafe fn clear_slot(slot: *mut pg_sys::TupleTableSlot) {
+    ((*(*slot).tts_ops)
+        .clear
+        .expect("slot has no clear function"))(slot);
+}
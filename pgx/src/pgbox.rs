@@ -222,6 +222,7 @@ impl<T, AllocatedBy: WhoAllocated<T>> PgBox<T, AllocatedBy> {
     /// ```
     #[inline]
     pub fn alloc() -> PgBox<T, AllocatedByRust> {
+        crate::debug_assert_backend!();
         PgBox::<T, AllocatedByRust> {
             ptr: Some(unsafe {
                 NonNull::new_unchecked(pg_sys::palloc(std::mem::size_of::<T>()) as *mut T)
@@ -244,6 +245,7 @@ impl<T, AllocatedBy: WhoAllocated<T>> PgBox<T, AllocatedBy> {
     /// ```
     #[inline]
     pub fn alloc0() -> PgBox<T, AllocatedByRust> {
+        crate::debug_assert_backend!();
         PgBox::<T, AllocatedByRust> {
             ptr: Some(unsafe {
                 NonNull::new_unchecked(pg_sys::palloc0(std::mem::size_of::<T>()) as *mut T)
@@ -252,31 +254,70 @@ impl<T, AllocatedBy: WhoAllocated<T>> PgBox<T, AllocatedBy> {
         }
     }
 
-    /// Allocate enough memory for the type'd struct, within the specified Postgres MemoryContext.
+    /// Allocate enough memory for the type'd struct, within `memory_context`, such as the
+    /// `TopMemoryContext`, a child context, or a function's `fn_mcxt`, without switching to it.
     /// The allocated memory is uninitalized.
     ///
     /// When this object is dropped the backing memory will be pfree'd,
     /// unless it is instead turned `into_pg()`, at which point it will be freeded
-    /// when its owning MemoryContext is deleted by Postgres (likely transaction end).
+    /// when `memory_context` is reset or deleted.
     ///
     /// ## Examples
     /// ```rust,no_run
     /// use pgx::{PgBox, pg_sys, PgMemoryContexts};
-    /// let ctid = PgBox::<pg_sys::ItemPointerData>::alloc_in_context(PgMemoryContexts::TopTransactionContext);
+    /// let mut cache = PgMemoryContexts::new("my cache");
+    /// let ctid = PgBox::<pg_sys::ItemPointerData>::alloc_in(&mut cache);
     /// ```
     #[inline]
-    pub fn alloc_in_context(memory_context: PgMemoryContexts) -> PgBox<T, AllocatedByRust> {
+    pub fn alloc_in(memory_context: &mut PgMemoryContexts) -> PgBox<T, AllocatedByRust> {
+        crate::debug_assert_backend!();
         PgBox::<T, AllocatedByRust> {
-            ptr: Some(unsafe {
-                NonNull::new_unchecked(pg_sys::MemoryContextAlloc(
-                    memory_context.value(),
-                    std::mem::size_of::<T>(),
-                ) as *mut T)
-            }),
+            ptr: Some(unsafe { NonNull::new_unchecked(memory_context.palloc_struct::<T>()) }),
+            __marker: PhantomData,
+        }
+    }
+
+    /// Allocate enough memory for the type'd struct, within `memory_context`, like
+    /// [`PgBox::alloc_in()`].  The allocated memory is zero-filled.
+    ///
+    /// When this object is dropped the backing memory will be pfree'd,
+    /// unless it is instead turned `into_pg()`, at which point it will be freeded
+    /// when `memory_context` is reset or deleted.
+    ///
+    /// ## Examples
+    /// ```rust,no_run
+    /// use pgx::{PgBox, pg_sys, PgMemoryContexts};
+    /// let ctid = PgBox::<pg_sys::ItemPointerData>::alloc0_in(&mut PgMemoryContexts::TopMemoryContext);
+    /// ```
+    #[inline]
+    pub fn alloc0_in(memory_context: &mut PgMemoryContexts) -> PgBox<T, AllocatedByRust> {
+        crate::debug_assert_backend!();
+        PgBox::<T, AllocatedByRust> {
+            ptr: Some(unsafe { NonNull::new_unchecked(memory_context.palloc0_struct::<T>()) }),
             __marker: PhantomData,
         }
     }
 
+    /// Allocate enough memory for the type'd struct, within the specified Postgres MemoryContext.
+    /// The allocated memory is uninitalized.
+    ///
+    /// When this object is dropped the backing memory will be pfree'd,
+    /// unless it is instead turned `into_pg()`, at which point it will be freeded
+    /// when its owning MemoryContext is deleted by Postgres (likely transaction end).
+    ///
+    /// A [`PgMemoryContexts::Owned`] is deleted when it's dropped at the end of this call, so use
+    /// [`PgBox::alloc_in()`] for those.
+    ///
+    /// ## Examples
+    /// ```rust,no_run
+    /// use pgx::{PgBox, pg_sys, PgMemoryContexts};
+    /// let ctid = PgBox::<pg_sys::ItemPointerData>::alloc_in_context(PgMemoryContexts::TopTransactionContext);
+    /// ```
+    #[inline]
+    pub fn alloc_in_context(mut memory_context: PgMemoryContexts) -> PgBox<T, AllocatedByRust> {
+        Self::alloc_in(&mut memory_context)
+    }
+
     /// Allocate enough memory for the type'd struct, within the specified Postgres MemoryContext.
     /// The allocated memory is zero-filled.
     ///
@@ -284,22 +325,17 @@ impl<T, AllocatedBy: WhoAllocated<T>> PgBox<T, AllocatedBy> {
     /// unless it is instead turned `into_pg()`, at which point it will be freeded
     /// when its owning MemoryContext is deleted by Postgres (likely transaction end).
     ///
+    /// A [`PgMemoryContexts::Owned`] is deleted when it's dropped at the end of this call, so use
+    /// [`PgBox::alloc0_in()`] for those.
+    ///
     /// ## Examples
     /// ```rust,no_run
     /// use pgx::{PgBox, pg_sys, PgMemoryContexts};
     /// let ctid = PgBox::<pg_sys::ItemPointerData>::alloc0_in_context(PgMemoryContexts::TopTransactionContext);
     /// ```
     #[inline]
-    pub fn alloc0_in_context(memory_context: PgMemoryContexts) -> PgBox<T, AllocatedByRust> {
-        PgBox::<T, AllocatedByRust> {
-            ptr: Some(unsafe {
-                NonNull::new_unchecked(pg_sys::MemoryContextAllocZero(
-                    memory_context.value(),
-                    std::mem::size_of::<T>(),
-                ) as *mut T)
-            }),
-            __marker: PhantomData,
-        }
+    pub fn alloc0_in_context(mut memory_context: PgMemoryContexts) -> PgBox<T, AllocatedByRust> {
+        Self::alloc0_in(&mut memory_context)
     }
 
     /// Allocate a Postgres `pg_sys::Node` subtype, using `palloc` in the `CurrentMemoryContext`.
@@ -0,0 +1,85 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Detecting, at runtime, which Postgres an extension has been loaded into.
+//!
+//! One shared library can't serve several Postgres *major* versions:  the `PG_MODULE_MAGIC` block
+//! (`pg_module_magic!()`) records the major version the extension was compiled against, and
+//! Postgres refuses to load a library built for any other.  Struct layouts, node tags, and
+//! function signatures change between major versions too, so there's no stable subset to dispatch
+//! over.  Extensions still need one build per major version, selected with the `pgXX` features.
+//!
+//! Within a major version Postgres keeps its ABI stable, and one library works across all of
+//! its minor releases.  The exception is a library compiled against a *newer* minor release
+//! than the server it's loaded into, which may use functions or variables the older server
+//! doesn't have yet.  [`check_server_version()`] warns about that, and [`ServerVersion`] lets
+//! an extension adjust to the exact release it's running on.
+use crate::pg_sys;
+use std::ffi::CStr;
+
+/// A Postgres release, as `major.minor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServerVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ServerVersion {
+    /// The release of the running server, from its `server_version_num` setting
+    #[allow(clippy::manual_c_str_literals)]
+    pub fn running() -> Self {
+        let version_num = unsafe {
+            let value = pg_sys::GetConfigOption(
+                "server_version_num\0".as_ptr() as *const std::os::raw::c_char,
+                false,
+                false,
+            );
+            CStr::from_ptr(value)
+                .to_str()
+                .expect("`server_version_num` is not valid UTF8")
+                .parse::<u32>()
+                .expect("`server_version_num` is not a number")
+        };
+        Self::from_version_num(version_num)
+    }
+
+    /// The release of the Postgres headers this extension was compiled against
+    pub fn compiled() -> Self {
+        Self::from_version_num(pg_sys::PG_VERSION_NUM)
+    }
+
+    /// Split a `PG_VERSION_NUM`-style number, such as `140002`, into its parts
+    pub fn from_version_num(version_num: u32) -> Self {
+        ServerVersion {
+            major: (version_num / 10000) as u16,
+            minor: (version_num % 10000) as u16,
+        }
+    }
+}
+
+impl std::fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Warn if the running server is an older minor release than the one this extension was compiled
+/// against.  Meant to be called from `_PG_init()`
+pub fn check_server_version() {
+    let running = ServerVersion::running();
+    let compiled = ServerVersion::compiled();
+    if running < compiled {
+        warning!(
+            "this extension was compiled against Postgres {}, but the server is running {}.  Upgrade the server to at least {}",
+            compiled,
+            running,
+            compiled
+        );
+    }
+}
@@ -0,0 +1,139 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Checking whether a role has privileges on a database object, so a function can refuse to
+//! operate on an object the calling user can't use before it starts
+use crate::{ereport, pg_sys, AclMode, PgLogLevel, PgSqlErrorCode};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// A database object that privileges can be granted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AclObject {
+    /// A table, view, sequence, or other relation, by its `pg_class` oid
+    Relation(pg_sys::Oid),
+    /// A column of a relation, by the relation's oid and the column's attribute number
+    Column(pg_sys::Oid, pg_sys::AttrNumber),
+    Schema(pg_sys::Oid),
+    Function(pg_sys::Oid),
+    Database(pg_sys::Oid),
+    Type(pg_sys::Oid),
+}
+
+impl AclObject {
+    /// Like Postgres' `aclcheck_error()`, what a "permission denied" error calls this object
+    fn describe(&self) -> String {
+        unsafe {
+            match *self {
+                AclObject::Relation(relid) => {
+                    let kind = match pg_sys::get_rel_relkind(relid) as u8 {
+                        b'S' => "sequence",
+                        b'v' => "view",
+                        b'm' => "materialized view",
+                        b'f' => "foreign table",
+                        _ => "table",
+                    };
+                    format!("{} {}", kind, owned_name(pg_sys::get_rel_name(relid)))
+                }
+                AclObject::Column(relid, attnum) => format!(
+                    "column \"{}\" of relation \"{}\"",
+                    owned_name(attname(relid, attnum)),
+                    owned_name(pg_sys::get_rel_name(relid))
+                ),
+                AclObject::Schema(nspid) => {
+                    format!("schema {}", owned_name(pg_sys::get_namespace_name(nspid)))
+                }
+                AclObject::Function(funcid) => {
+                    format!("function {}", owned_name(pg_sys::get_func_name(funcid)))
+                }
+                AclObject::Database(dbid) => {
+                    format!("database {}", owned_name(pg_sys::get_database_name(dbid)))
+                }
+                AclObject::Type(typoid) => {
+                    format!("type {}", owned_name(pg_sys::format_type_be(typoid)))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "pg10")]
+unsafe fn attname(relid: pg_sys::Oid, attnum: pg_sys::AttrNumber) -> *mut c_char {
+    pg_sys::get_attname(relid, attnum)
+}
+
+#[cfg(not(feature = "pg10"))]
+unsafe fn attname(relid: pg_sys::Oid, attnum: pg_sys::AttrNumber) -> *mut c_char {
+    pg_sys::get_attname(relid, attnum, true)
+}
+
+unsafe fn owned_name(name: *mut c_char) -> String {
+    if name.is_null() {
+        "(unknown)".to_string()
+    } else {
+        CStr::from_ptr(name).to_string_lossy().into_owned()
+    }
+}
+
+/// Does the current user have all of `privileges` on `object`, either directly, through a role
+/// they're a member of, or through `PUBLIC`?  The owner of an object has every privilege on it,
+/// and a superuser has every privilege on everything.
+///
+/// ## Panics
+///
+/// Raises a Postgres ERROR if `object` doesn't exist, or if `privileges` includes one that
+/// doesn't apply to that kind of object, such as `AclMode::EXECUTE` on a table
+pub fn has_privileges(object: AclObject, privileges: AclMode) -> bool {
+    role_has_privileges(unsafe { pg_sys::GetUserId() }, object, privileges)
+}
+
+/// Like [`has_privileges()`], but for the role `roleid` instead of the current user
+pub fn role_has_privileges(roleid: pg_sys::Oid, object: AclObject, privileges: AclMode) -> bool {
+    let mode = privileges.bits();
+    let result = unsafe {
+        match object {
+            AclObject::Relation(relid) => pg_sys::pg_class_aclcheck(relid, roleid, mode),
+            AclObject::Column(relid, attnum) => {
+                pg_sys::pg_attribute_aclcheck(relid, attnum, roleid, mode)
+            }
+            AclObject::Schema(nspid) => pg_sys::pg_namespace_aclcheck(nspid, roleid, mode),
+            AclObject::Function(funcid) => pg_sys::pg_proc_aclcheck(funcid, roleid, mode),
+            AclObject::Database(dbid) => pg_sys::pg_database_aclcheck(dbid, roleid, mode),
+            AclObject::Type(typoid) => pg_sys::pg_type_aclcheck(typoid, roleid, mode),
+        }
+    };
+    result == pg_sys::AclResult_ACLCHECK_OK
+}
+
+/// Raise the same "permission denied" ERROR Postgres would if the current user doesn't have all
+/// of `privileges` on `object`, so a function can check up front instead of failing partway
+/// through with a less obvious error
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn archive_rows(source: PgRelation, archive: PgRelation) {
+///     check_privileges(AclObject::Relation(source.oid()), AclMode::SELECT | AclMode::DELETE);
+///     check_privileges(AclObject::Relation(archive.oid()), AclMode::INSERT);
+///     // ...
+/// }
+/// ```
+pub fn check_privileges(object: AclObject, privileges: AclMode) {
+    if !has_privileges(object, privileges) {
+        ereport(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            &format!("permission denied for {}", object.describe()),
+            file!(),
+            line!(),
+            column!(),
+        );
+    }
+}
@@ -0,0 +1,559 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Grouping queries by their shape, as `pg_stat_statements` does, for monitoring extensions
+//!
+//! [`normalize_query()`] masks a query's constants with parameters, the way `pg_stat_statements`
+//! shows its queries, and [`query_fingerprint()`] hashes that shape, ignoring whitespace, comments,
+//! and case, so queries that only differ in their constants can be counted together:
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! assert_eq!(
+//!     normalize_query("SELECT * FROM orders WHERE id = 42 AND status = 'open'"),
+//!     "SELECT * FROM orders WHERE id = $1 AND status = $2"
+//! );
+//! assert_eq!(
+//!     query_fingerprint("select *  from orders where id = 7 and status = 'shipped'"),
+//!     query_fingerprint("SELECT * FROM orders WHERE id = 42 AND status = 'open'"),
+//! );
+//! ```
+//!
+//! Both work on the query's text alone, so they don't need its tables to exist, and work the same
+//! on every version of Postgres, but they can't tell apart names that resolve to different objects.
+//! On Postgres 14 and later, [`query_id()`] is the `queryid` Postgres itself computes from the
+//! analyzed query, which `pg_stat_statements` and `pg_stat_activity` report.
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+use crate::{pg_sys, PgList};
+
+/// `query`, with its constants replaced by `$1`, `$2`, and so on, numbered after any parameters
+/// it already has, the way `pg_stat_statements` shows a query.
+///
+/// As in `pg_stat_statements`, only the constants of `SELECT`, `INSERT`, `UPDATE`, `DELETE`, and
+/// the like are replaced, including `true`, `false`, `NULL`, and negative numbers, and a constant
+/// that's part of the query's syntax, like `ORDER BY 1`, isn't.  A utility statement, such as
+/// `CREATE TABLE`, is left as it is.
+pub fn normalize_query(query: &str) -> String {
+    let tokens = tokenize(query);
+    let masked = masked_constants(query, &tokens);
+
+    let mut param = tokens
+        .iter()
+        .filter_map(|token| match token.kind {
+            TokenKind::Param(n) => Some(n),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+
+    let mut normalized = String::with_capacity(query.len());
+    let mut copied = 0;
+    for (start, end) in masked {
+        param += 1;
+        normalized.push_str(&query[copied..start]);
+        normalized.push_str(&format!("${}", param));
+        copied = end;
+    }
+    normalized.push_str(&query[copied..]);
+    normalized
+}
+
+/// A hash of the shape of `query`, which is the same for queries that only differ in their
+/// constants, as they're masked by [`normalize_query()`], parameters, whitespace, comments, and
+/// the case of unquoted names and keywords.  It's stable across Postgres versions and platforms,
+/// but isn't the `queryid` Postgres computes.  See [`query_id()`] for that
+pub fn query_fingerprint(query: &str) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let tokens = tokenize(query);
+    let masked = masked_constants(query, &tokens);
+    let is_masked = |token: &Token| {
+        masked
+            .iter()
+            .any(|&(start, end)| token.start >= start && token.end <= end)
+    };
+
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    let mut previous_masked = false;
+    for token in tokens
+        .iter()
+        .filter(|token| token.kind != TokenKind::Semicolon)
+    {
+        if is_masked(token) || matches!(token.kind, TokenKind::Param(_)) {
+            // a negative constant is two tokens, and counts once
+            if !previous_masked {
+                feed(b"? ");
+            }
+            previous_masked = true;
+            continue;
+        }
+        previous_masked = false;
+        let text = &query[token.start..token.end];
+        match token.kind {
+            TokenKind::Word => feed(text.to_lowercase().as_bytes()),
+            _ => feed(text.as_bytes()),
+        }
+        feed(b" ");
+    }
+    hash
+}
+
+/// The `queryid` Postgres computes for `query`, which must be a single statement, as
+/// `pg_stat_statements` and `pg_stat_activity` report it, or `None` if query ids aren't being
+/// computed, because `compute_query_id` is `off`, or `auto` without an extension like
+/// `pg_stat_statements` asking for them.
+///
+/// The query is parsed and analyzed, so it's the same for queries whose names resolve to the same
+/// objects, even if they're spelled differently.
+///
+/// ## Panics
+///
+/// Raises a Postgres ERROR if `query` isn't a single statement, or can't be analyzed, such as if
+/// one of its tables doesn't exist, or it has a parameter
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+pub fn query_id(query: &str) -> Option<i64> {
+    let query_string = std::ffi::CString::new(query).expect("query contains a null byte");
+    unsafe {
+        let statements =
+            PgList::<pg_sys::RawStmt>::from_pg(pg_sys::pg_parse_query(query_string.as_ptr()));
+        if statements.len() != 1 {
+            crate::ereport(
+                crate::PgLogLevel::ERROR,
+                crate::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR,
+                "query_id() takes a single statement",
+                file!(),
+                line!(),
+                column!(),
+            );
+        }
+
+        #[cfg(feature = "pg14")]
+        let queries = pg_sys::pg_analyze_and_rewrite(
+            statements.head().unwrap(),
+            query_string.as_ptr(),
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+        );
+        #[cfg(feature = "pg15")]
+        let queries = pg_sys::pg_analyze_and_rewrite_fixedparams(
+            statements.head().unwrap(),
+            query_string.as_ptr(),
+            std::ptr::null(),
+            0,
+            std::ptr::null_mut(),
+        );
+
+        // rewriting keeps the query id
+        let queries = PgList::<pg_sys::Query>::from_pg(queries);
+        match queries.head().map(|query| (*query).queryId) {
+            Some(0) | None => None,
+            Some(query_id) => Some(query_id as i64),
+        }
+    }
+}
+
+/// The `queryid` of the top-level statement this backend is running, as `pg_stat_activity`
+/// reports it, or `None` if query ids aren't being computed.  See [`query_id()`]
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+pub fn current_query_id() -> Option<i64> {
+    match unsafe { pg_sys::pgstat_get_my_query_id() } {
+        0 => None,
+        query_id => Some(query_id as i64),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    /// A keyword or an unquoted name
+    Word,
+    QuotedName,
+    /// A string or a number
+    Constant,
+    Param(u32),
+    Operator,
+    Semicolon,
+    /// Any other punctuation, like `(` or `::`
+    Punctuation,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+/// The statements `pg_stat_statements` masks the constants of, rather than keeping their text
+const JUMBLED_STATEMENTS: &[&str] = &[
+    "select", "insert", "update", "delete", "with", "values", "table", "merge",
+];
+
+/// Keywords after which a `-` negates the number that follows it, rather than subtracting it
+const NEGATING_KEYWORDS: &[&str] = &[
+    "all",
+    "and",
+    "any",
+    "array",
+    "as",
+    "between",
+    "by",
+    "case",
+    "distinct",
+    "else",
+    "having",
+    "in",
+    "is",
+    "like",
+    "limit",
+    "not",
+    "offset",
+    "on",
+    "or",
+    "returning",
+    "select",
+    "set",
+    "some",
+    "then",
+    "values",
+    "when",
+    "where",
+];
+
+/// The spans of `query` that hold the constants [`normalize_query()`] masks
+fn masked_constants(query: &str, tokens: &[Token]) -> Vec<(usize, usize)> {
+    let word = |token: &Token| {
+        (token.kind == TokenKind::Word).then(|| query[token.start..token.end].to_lowercase())
+    };
+
+    let mut masked = vec![];
+    let mut jumbled = None;
+    // are we in an `ORDER BY` or `GROUP BY` list, where integers are column positions?
+    let mut in_by_list = false;
+    for (i, token) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| &tokens[i]);
+        let previous_word = previous.and_then(word);
+        let this_word = word(token);
+
+        if token.kind == TokenKind::Semicolon {
+            jumbled = None;
+            continue;
+        }
+        if jumbled.is_none() && token.kind != TokenKind::Punctuation {
+            jumbled = Some(
+                matches!(&this_word, Some(word) if JUMBLED_STATEMENTS.contains(&word.as_str())),
+            );
+        }
+        if jumbled != Some(true) {
+            continue;
+        }
+
+        let text = &query[token.start..token.end];
+        let is_integer =
+            token.kind == TokenKind::Constant && text.bytes().all(|b| b.is_ascii_digit());
+        let before_by = i.checked_sub(2).and_then(|i| word(&tokens[i]));
+        if previous_word.as_deref() == Some("by")
+            && matches!(before_by.as_deref(), Some("order" | "group"))
+        {
+            in_by_list = true;
+        } else if in_by_list
+            && !is_integer
+            && text != ","
+            && !matches!(
+                this_word.as_deref(),
+                Some("asc" | "desc" | "nulls" | "first" | "last")
+            )
+        {
+            in_by_list = false;
+        }
+
+        match token.kind {
+            TokenKind::Constant if in_by_list && is_integer => {}
+            TokenKind::Constant => {
+                // a negative number is one constant, from its `-`
+                let negated = previous.filter(|previous| {
+                    previous.kind == TokenKind::Operator
+                        && &query[previous.start..previous.end] == "-"
+                        && text.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+                        && negates(query, tokens, i - 1)
+                });
+                match negated {
+                    Some(minus) => masked.push((minus.start, token.end)),
+                    None => masked.push((token.start, token.end)),
+                }
+            }
+            TokenKind::Word => match this_word.as_deref() {
+                Some("true" | "false") => masked.push((token.start, token.end)),
+                Some("null") if !matches!(previous_word.as_deref(), Some("is" | "not")) => {
+                    masked.push((token.start, token.end))
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    masked
+}
+
+/// Is the `-` at `tokens[i]` a negation, rather than a subtraction?  It is when there's nothing it
+/// could subtract from before it
+fn negates(query: &str, tokens: &[Token], i: usize) -> bool {
+    let previous = match i.checked_sub(1) {
+        Some(previous) => &tokens[previous],
+        None => return true,
+    };
+    let text = &query[previous.start..previous.end];
+    match previous.kind {
+        TokenKind::Operator | TokenKind::Semicolon => true,
+        TokenKind::Punctuation => !matches!(text, ")" | "]"),
+        TokenKind::Word => NEGATING_KEYWORDS.contains(&text.to_lowercase().as_str()),
+        TokenKind::QuotedName | TokenKind::Constant | TokenKind::Param(_) => false,
+    }
+}
+
+fn is_name_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b >= 0x80
+}
+
+fn is_name_char(b: u8) -> bool {
+    is_name_start(b) || b.is_ascii_digit() || b == b'$'
+}
+
+fn is_operator_char(b: u8) -> bool {
+    b"+-*/<>=~!@#%^&|`?".contains(&b)
+}
+
+/// Split `query` into tokens the way Postgres' lexer does, closely enough to find its constants,
+/// dropping whitespace and comments
+#[allow(clippy::unnecessary_map_or)]
+fn tokenize(query: &str) -> Vec<Token> {
+    let bytes = query.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let b = bytes[i];
+        let kind = if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if bytes[i..].starts_with(b"--") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+            continue;
+        } else if bytes[i..].starts_with(b"/*") {
+            i = skip_block_comment(bytes, i);
+            continue;
+        } else if b == b'\'' {
+            i = skip_string(bytes, i, false);
+            TokenKind::Constant
+        } else if matches!(b, b'e' | b'E') && bytes.get(i + 1) == Some(&b'\'') {
+            i = skip_string(bytes, i + 1, true);
+            TokenKind::Constant
+        } else if matches!(b, b'b' | b'B' | b'x' | b'X' | b'n' | b'N')
+            && bytes.get(i + 1) == Some(&b'\'')
+        {
+            i = skip_string(bytes, i + 1, false);
+            TokenKind::Constant
+        } else if matches!(b, b'u' | b'U') && bytes[i + 1..].starts_with(b"&'") {
+            i = skip_string(bytes, i + 2, false);
+            TokenKind::Constant
+        } else if matches!(b, b'u' | b'U') && bytes[i + 1..].starts_with(b"&\"") {
+            i = skip_quoted_name(bytes, i + 2);
+            TokenKind::QuotedName
+        } else if b == b'"' {
+            i = skip_quoted_name(bytes, i);
+            TokenKind::QuotedName
+        } else if is_name_start(b) {
+            while i < bytes.len() && is_name_char(bytes[i]) {
+                i += 1;
+            }
+            TokenKind::Word
+        } else if b.is_ascii_digit()
+            || (b == b'.' && bytes.get(i + 1).map_or(false, u8::is_ascii_digit))
+        {
+            i = skip_number(bytes, i);
+            TokenKind::Constant
+        } else if b == b'$' && bytes.get(i + 1).map_or(false, u8::is_ascii_digit) {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            TokenKind::Param(query[start + 1..i].parse().unwrap_or(u32::MAX))
+        } else if let Some(end) = skip_dollar_quoted(bytes, i) {
+            i = end;
+            TokenKind::Constant
+        } else if is_operator_char(b) {
+            i = skip_operator(bytes, i);
+            TokenKind::Operator
+        } else if b == b';' {
+            i += 1;
+            TokenKind::Semicolon
+        } else {
+            i += if bytes[i..].starts_with(b"::") { 2 } else { 1 };
+            TokenKind::Punctuation
+        };
+
+        // strings separated only by whitespace with a newline in it are one string
+        if kind == TokenKind::Constant && bytes[i - 1] == b'\'' {
+            let escapes = matches!(b, b'e' | b'E');
+            while let Some(next) = string_continuation(bytes, i) {
+                i = skip_string(bytes, next, escapes);
+            }
+        }
+        tokens.push(Token {
+            kind,
+            start,
+            end: i,
+        });
+    }
+    tokens
+}
+
+/// The end of the `'`-quoted string at `i`, where `''` is a quote, as is `\'` if `escapes`
+fn skip_string(bytes: &[u8], mut i: usize, escapes: bool) -> usize {
+    i += 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if escapes => i += 2,
+            b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+            b'\'' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Where the string that continues the one ending at `i` starts, if there is one
+#[allow(clippy::unnecessary_lazy_evaluations)]
+fn string_continuation(bytes: &[u8], i: usize) -> Option<usize> {
+    let mut j = i;
+    let mut newline = false;
+    while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+        newline |= bytes[j] == b'\n' || bytes[j] == b'\r';
+        j += 1;
+    }
+    (newline && bytes.get(j) == Some(&b'\'')).then(|| j)
+}
+
+fn skip_quoted_name(bytes: &[u8], mut i: usize) -> usize {
+    i += 1;
+    while i < bytes.len() {
+        if bytes[i] == b'"' {
+            if bytes.get(i + 1) == Some(&b'"') {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Block comments nest, unlike in C
+fn skip_block_comment(bytes: &[u8], mut i: usize) -> usize {
+    let mut depth = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+#[allow(clippy::unnecessary_map_or)]
+fn skip_number(bytes: &[u8], mut i: usize) -> usize {
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    i = digits(i);
+    // but not the `..` of a slice, like `a[1..2]`, which Postgres doesn't have, or `1.x`
+    if bytes.get(i) == Some(&b'.') && bytes.get(i + 1) != Some(&b'.') {
+        i = digits(i + 1);
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let exponent = match bytes.get(i + 1) {
+            Some(b'+' | b'-') => i + 2,
+            _ => i + 1,
+        };
+        if bytes.get(exponent).map_or(false, u8::is_ascii_digit) {
+            i = digits(exponent);
+        }
+    }
+    i
+}
+
+/// The end of the `$tag$`-quoted string at `i`, if there is one
+fn skip_dollar_quoted(bytes: &[u8], i: usize) -> Option<usize> {
+    if bytes[i] != b'$' {
+        return None;
+    }
+    let mut j = i + 1;
+    if j < bytes.len() && is_name_start(bytes[j]) {
+        while j < bytes.len() && is_name_char(bytes[j]) && bytes[j] != b'$' {
+            j += 1;
+        }
+    }
+    if bytes.get(j) != Some(&b'$') {
+        return None;
+    }
+    let tag = &bytes[i..=j];
+    let body = j + 1;
+    Some(
+        bytes[body..]
+            .windows(tag.len())
+            .position(|window| window == tag)
+            .map_or(bytes.len(), |end| body + end + tag.len()),
+    )
+}
+
+/// An operator ends before a comment starts, and doesn't end in `+` or `-` unless it has one of
+/// the characters only an operator of its own would, so that `=-1` is `=` and `-1`
+fn skip_operator(bytes: &[u8], start: usize) -> usize {
+    let mut end = start;
+    while end < bytes.len()
+        && is_operator_char(bytes[end])
+        && !bytes[end..].starts_with(b"--")
+        && !bytes[end..].starts_with(b"/*")
+    {
+        end += 1;
+    }
+    if end == start {
+        // a `--` or `/*` would have been a comment
+        return start + 1;
+    }
+    let operator = &bytes[start..end];
+    if !operator.iter().any(|b| b"~!@#^&|`?%".contains(b)) {
+        while end - start > 1 && matches!(bytes[end - 1], b'+' | b'-') {
+            end -= 1;
+        }
+    }
+    end
+}
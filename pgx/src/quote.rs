@@ -0,0 +1,233 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Quoting identifiers and literals for SQL built at runtime, such as for SPI
+//!
+//! Table and column names, and values, that come from users must be quoted before they're put
+//! into a query, or they could change what the query does.  Prefer passing values as SPI
+//! arguments, but identifiers can't be, so use [`quote_identifier()`] or the [`format_sql!`]
+//! macro for those.
+//!
+//! Names built at runtime, such as a table per tenant, are also limited to
+//! [`MAX_IDENTIFIER_LEN`] bytes, and unquoted ones are downcased, so use
+//! [`truncate_identifier()`] and [`downcase_truncate_identifier()`] to know the name Postgres
+//! gives the object, so it can be found again in the catalogs.
+use crate::misc::cstring;
+use crate::{pg_sys, PgEncoding};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// The longest an identifier can be, in bytes of the database's encoding, which is
+/// `NAMEDATALEN - 1`.  Longer ones are truncated
+pub const MAX_IDENTIFIER_LEN: usize = pg_sys::NAMEDATALEN as usize - 1;
+
+/// Quote `ident` as an identifier, if it needs to be, just like SQL's `quote_ident()`.
+///
+/// Identifiers are quoted if they aren't all lowercase, or are keywords.
+pub fn quote_identifier(ident: &str) -> String {
+    let ident = cstring(ident, "SQL identifier");
+    unsafe {
+        let quoted = pg_sys::quote_identifier(ident.as_ptr());
+        let result = CStr::from_ptr(quoted).to_string_lossy().into_owned();
+        // Postgres hands back the identifier itself when it doesn't need quoting
+        if quoted != ident.as_ptr() {
+            pg_sys::pfree(quoted as *mut std::os::raw::c_void);
+        }
+        result
+    }
+}
+
+/// Quote `ident` as an identifier qualified by `qualifier`, such as a schema, quoting each part
+/// if it needs to be
+pub fn quote_qualified_identifier(qualifier: Option<&str>, ident: &str) -> String {
+    let qualifier = qualifier.map(|qualifier| cstring(qualifier, "SQL identifier"));
+    let ident = cstring(ident, "SQL identifier");
+    unsafe {
+        let quoted = pg_sys::quote_qualified_identifier(
+            qualifier
+                .as_ref()
+                .map_or(std::ptr::null(), |qualifier| qualifier.as_ptr()),
+            ident.as_ptr(),
+        );
+        let result = CStr::from_ptr(quoted).to_string_lossy().into_owned();
+        pg_sys::pfree(quoted as *mut std::os::raw::c_void);
+        result
+    }
+}
+
+/// Quote `value` as a string literal, just like SQL's `quote_literal()`.  Values with
+/// backslashes are quoted as escape strings, such as `E'C:\\temp'`
+pub fn quote_literal(value: &str) -> String {
+    let value = cstring(value, "SQL literal");
+    unsafe {
+        let quoted = pg_sys::quote_literal_cstr(value.as_ptr());
+        let result = CStr::from_ptr(quoted).to_string_lossy().into_owned();
+        pg_sys::pfree(quoted as *mut std::os::raw::c_void);
+        result
+    }
+}
+
+/// `ident` as Postgres truncates identifiers, to at most [`MAX_IDENTIFIER_LEN`] bytes in the
+/// database's encoding, without splitting a character.  A quoted identifier names the object
+/// that's truncated to.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let table = format!("events_for_{}", "a_tenant_with_a_very_long_name_that_goes_on_and_on");
+/// Spi::run(&format_sql!("CREATE TABLE {} ()", ident(table)));
+/// let created = Spi::get_one::<bool>(&format_sql!(
+///     "SELECT EXISTS (SELECT FROM pg_class WHERE relname = {})",
+///     literal(truncate_identifier(&table)),
+/// ));
+/// ```
+///
+/// ## Panics
+///
+/// Raises a Postgres ERROR if `ident` can't be in the database's encoding, or, in a `SQL_ASCII`
+/// database, if it's truncated partway through a character, which a `String` can't hold
+pub fn truncate_identifier(ident: &str) -> String {
+    let encoding = PgEncoding::database();
+    let bytes = encoding.encode(ident);
+    if bytes.len() <= MAX_IDENTIFIER_LEN {
+        return ident.to_string();
+    }
+    let len = unsafe {
+        pg_sys::pg_mbcliplen(
+            bytes.as_ptr() as *const c_char,
+            bytes.len() as i32,
+            MAX_IDENTIFIER_LEN as i32,
+        )
+    };
+    encoding.decode(&bytes[..len as usize])
+}
+
+/// `ident` as Postgres reads it unquoted, downcased and then [truncated](truncate_identifier()).
+/// Only ASCII letters are downcased, unless the database's encoding has a byte per character,
+/// in which case other letters are as well, by its `LC_CTYPE`, as Postgres does.  It has the same
+/// ERRORs as [`truncate_identifier()`]
+pub fn downcase_truncate_identifier(ident: &str) -> String {
+    let encoding = PgEncoding::database();
+    let single_byte = encoding.max_char_len() == 1;
+    let downcased = encoding
+        .encode(ident)
+        .into_iter()
+        .map(|byte| match byte {
+            b'A'..=b'Z' => byte.to_ascii_lowercase(),
+            0x80..=0xff if single_byte && unsafe { libc::isupper(byte as i32) } != 0 => unsafe {
+                libc::tolower(byte as i32) as u8
+            },
+            _ => byte,
+        })
+        .collect::<Vec<_>>();
+    truncate_identifier(&encoding.decode(&downcased))
+}
+
+/// The names in `name`, a possibly qualified name as SQL writes it, like `"My Schema".events`,
+/// each as Postgres reads it: quoted names as they're quoted and unquoted names
+/// [downcased](downcase_truncate_identifier()), and both truncated.
+///
+/// ## Panics
+///
+/// Raises a Postgres ERROR, "invalid name syntax", if `name` doesn't parse, such as if it's empty,
+/// a quote isn't closed, or a name between dots is missing
+pub fn parse_qualified_identifier(name: &str) -> Vec<String> {
+    let invalid = || -> ! {
+        crate::ereport(
+            crate::PgLogLevel::ERROR,
+            crate::PgSqlErrorCode::ERRCODE_INVALID_NAME,
+            "invalid name syntax",
+            file!(),
+            line!(),
+            column!(),
+        );
+        unreachable!("ERROR ereport returned")
+    };
+
+    let mut names = Vec::new();
+    let mut chars = name.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next_if_eq(&'"').is_some() {
+            let mut quoted = String::new();
+            loop {
+                match chars.next() {
+                    // a doubled quote is a quote
+                    Some('"') if chars.next_if_eq(&'"').is_some() => quoted.push('"'),
+                    Some('"') => break,
+                    Some(c) => quoted.push(c),
+                    None => invalid(),
+                }
+            }
+            if quoted.is_empty() {
+                invalid();
+            }
+            names.push(truncate_identifier(&quoted));
+        } else {
+            let mut unquoted = String::new();
+            while let Some(c) = chars.next_if(|&c| c != '.' && !c.is_whitespace()) {
+                unquoted.push(c);
+            }
+            if unquoted.is_empty() {
+                invalid();
+            }
+            names.push(downcase_truncate_identifier(&unquoted));
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            Some('.') => continue,
+            None => return names,
+            Some(_) => invalid(),
+        }
+    }
+}
+
+/// Like `format!()`, but every argument is quoted for SQL according to how it's wrapped:
+///
+/// - `ident(name)` with [`quote_identifier()`]
+/// - `qualified(schema, name)` with [`quote_qualified_identifier()`]
+/// - `literal(value)` with [`quote_literal()`], of anything that implements `Display`
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// fn count_matching(schema: &str, table: &str, column: &str, value: &str) -> Option<i64> {
+///     Spi::get_one(&format_sql!(
+///         "SELECT count(*) FROM {} WHERE {} = {}",
+///         qualified(schema, table),
+///         ident(column),
+///         literal(value),
+///     ))
+/// }
+/// ```
+#[macro_export]
+macro_rules! format_sql {
+    ($fmt:literal $(, $quote:ident ( $($arg:expr),+ ))* $(,)?) => {
+        format!($fmt $(, $crate::format_sql!(@quote $quote $($arg),+))*)
+    };
+    (@quote ident $ident:expr) => {
+        $crate::quote_identifier(::std::convert::AsRef::<str>::as_ref(&$ident))
+    };
+    (@quote qualified $qualifier:expr, $ident:expr) => {
+        $crate::quote_qualified_identifier(
+            Some(::std::convert::AsRef::<str>::as_ref(&$qualifier)),
+            ::std::convert::AsRef::<str>::as_ref(&$ident),
+        )
+    };
+    (@quote literal $value:expr) => {
+        $crate::quote_literal(&::std::string::ToString::to_string(&$value))
+    };
+    (@quote $quote:ident $($arg:expr),+) => {
+        compile_error!(concat!(
+            "format_sql!() arguments must be `ident(..)`, `qualified(.., ..)`, or `literal(..)`, not `",
+            stringify!($quote),
+            "(..)`"
+        ))
+    };
+}
@@ -9,12 +9,129 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Provides a safe wrapper around Postgres' `pg_sys::RelationData` struct
 use crate::{
-    direct_function_call, name_data_to_str, pg_sys, FromDatum, IntoDatum, PgBox, PgList,
-    PgTupleDesc,
+    direct_function_call, name_data_to_str, parse_options, pg_sys, FromDatum, IntoDatum, PgBox,
+    PgList, PgTupleDesc,
 };
+use std::ffi::{CStr, CString};
 use std::ops::Deref;
 use std::os::raw::c_char;
 
+/// Whether a relation survives a crash or the end of its session, from `pg_class.relpersistence`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelPersistence {
+    /// An ordinary relation, whose changes are WAL-logged
+    Permanent,
+    /// An `UNLOGGED` relation, which isn't WAL-logged and is emptied after a crash
+    Unlogged,
+    /// A `TEMPORARY` relation, which is dropped at the end of its session
+    Temporary,
+}
+
+/// Which columns of an updated or deleted row are WAL-logged for logical replication to identify
+/// the row by, from `pg_class.relreplident`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicaIdentity {
+    /// The primary key's columns, if there is one
+    Default,
+    Nothing,
+    /// Every column
+    Full,
+    /// The columns of this unique index
+    Index(pg_sys::Oid),
+}
+
+/// The lock modes a relation can be opened with, from the weakest to the strongest, as
+/// `LOCK TABLE` names them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LockMode {
+    /// No lock, for a relation the caller already holds a lock on
+    NoLock,
+    /// Taken by `SELECT`, and only conflicts with `AccessExclusive`
+    AccessShare,
+    /// Taken by `SELECT FOR UPDATE` and `SELECT FOR SHARE`
+    RowShare,
+    /// Taken by `INSERT`, `UPDATE`, and `DELETE`
+    RowExclusive,
+    /// Taken by `VACUUM`, `ANALYZE`, and `CREATE INDEX CONCURRENTLY`
+    ShareUpdateExclusive,
+    /// Taken by `CREATE INDEX`, and blocks changes to the relation's rows
+    Share,
+    /// Taken by `CREATE TRIGGER`, and blocks changes, like `Share`, but only one can be held
+    ShareRowExclusive,
+    /// Only allows other transactions to read the relation, with `AccessShare`
+    Exclusive,
+    /// Taken by `DROP TABLE`, `TRUNCATE`, and most `ALTER TABLE`s, and conflicts with every lock
+    AccessExclusive,
+}
+
+impl From<LockMode> for pg_sys::LOCKMODE {
+    fn from(mode: LockMode) -> Self {
+        (match mode {
+            LockMode::NoLock => pg_sys::NoLock,
+            LockMode::AccessShare => pg_sys::AccessShareLock,
+            LockMode::RowShare => pg_sys::RowShareLock,
+            LockMode::RowExclusive => pg_sys::RowExclusiveLock,
+            LockMode::ShareUpdateExclusive => pg_sys::ShareUpdateExclusiveLock,
+            LockMode::Share => pg_sys::ShareLock,
+            LockMode::ShareRowExclusive => pg_sys::ShareRowExclusiveLock,
+            LockMode::Exclusive => pg_sys::ExclusiveLock,
+            LockMode::AccessExclusive => pg_sys::AccessExclusiveLock,
+        }) as pg_sys::LOCKMODE
+    }
+}
+
+/// An expression a relation keeps in the catalogs, such as a `CHECK` constraint or a column's
+/// default, in the `nodeToString()` form Postgres stores it in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredExpr {
+    relid: pg_sys::Oid,
+    node_tree: String,
+}
+
+impl StoredExpr {
+    /// The expression as it's stored, a `pg_node_tree`
+    pub fn node_tree(&self) -> &str {
+        &self.node_tree
+    }
+
+    /// The expression's node tree, allocated in the `CurrentMemoryContext`
+    pub fn to_node(&self) -> PgBox<pg_sys::Node> {
+        let node_tree =
+            CString::new(self.node_tree.as_str()).expect("node tree contains a null byte");
+        unsafe {
+            PgBox::from_pg(
+                pg_sys::stringToNode(node_tree.as_ptr() as *mut c_char) as *mut pg_sys::Node
+            )
+        }
+    }
+
+    /// The expression as SQL, as `pg_get_expr()` would deparse it
+    pub fn to_sql(&self) -> String {
+        unsafe {
+            direct_function_call::<String>(
+                pg_sys::pg_get_expr,
+                vec![
+                    self.node_tree.as_str().into_datum(),
+                    self.relid.into_datum(),
+                ],
+            )
+        }
+        .expect("pg_get_expr returned NULL")
+    }
+}
+
+/// A `CHECK` constraint of a relation, from [`PgRelation::check_constraints()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckConstraint {
+    pub name: String,
+    pub expr: StoredExpr,
+    /// Is the constraint known to hold for every row, which isn't so if it was added `NOT VALID`
+    /// and hasn't been validated since?
+    pub is_valid: bool,
+    /// Is the constraint only on this relation, and not on the tables that inherit from it?
+    pub no_inherit: bool,
+}
+
 pub struct PgRelation {
     boxed: PgBox<pg_sys::RelationData>,
     need_close: bool,
@@ -147,6 +264,129 @@ impl PgRelation {
         }
     }
 
+    /// Open the relation `relname`, which may be schema-qualified and quoted as it would be in
+    /// SQL, like `"My Schema".events`, taking `lockmode` on it before it's opened, and keeping it
+    /// until this instance is dropped.  An unqualified name is looked up in the `search_path`.
+    ///
+    /// If there's no such relation, it's `None` if `missing_ok`, and otherwise raises the same
+    /// ERROR Postgres would, so a function can probe for an optional table:
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// #[pg_extern]
+    /// fn archived_rows() -> i64 {
+    ///     match PgRelation::open_with_name_and_lock("archive.events", LockMode::AccessShare, true) {
+    ///         Some(archive) => archive.reltuples().unwrap_or_default() as i64,
+    ///         None => 0,
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// Raises a Postgres ERROR if `relname` isn't a valid name, as with
+    /// [`parse_qualified_identifier()`](crate::parse_qualified_identifier)
+    pub fn open_with_name_and_lock(
+        relname: &str,
+        lockmode: LockMode,
+        missing_ok: bool,
+    ) -> Option<Self> {
+        let mut names = crate::parse_qualified_identifier(relname);
+        if names.len() > 3 {
+            crate::ereport(
+                crate::PgLogLevel::ERROR,
+                crate::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR,
+                &format!(
+                    "improper relation name (too many dotted names): {}",
+                    relname
+                ),
+                file!(),
+                line!(),
+                column!(),
+            );
+            unreachable!("ERROR ereport returned")
+        }
+        let relname = names.pop().unwrap();
+        let schema = names.pop();
+        let catalog = names.pop();
+        unsafe { Self::open_range_var(catalog, schema, &relname, lockmode, missing_ok) }
+    }
+
+    /// Like [`PgRelation::open_with_name_and_lock()`], but with the schema and the relation's
+    /// names as they're stored in the catalogs, rather than quoted
+    pub fn open_with_schema_and_lock(
+        schema: Option<&str>,
+        relname: &str,
+        lockmode: LockMode,
+        missing_ok: bool,
+    ) -> Option<Self> {
+        unsafe {
+            Self::open_range_var(
+                None,
+                schema.map(str::to_string),
+                relname,
+                lockmode,
+                missing_ok,
+            )
+        }
+    }
+
+    unsafe fn open_range_var(
+        catalog: Option<String>,
+        schema: Option<String>,
+        relname: &str,
+        lockmode: LockMode,
+        missing_ok: bool,
+    ) -> Option<Self> {
+        let pstrdup = |name: &str| {
+            let name = CString::new(name).expect("relation name contains a null byte");
+            pg_sys::pstrdup(name.as_ptr())
+        };
+        let range_var = pg_sys::makeRangeVar(
+            schema.as_deref().map_or(std::ptr::null_mut(), pstrdup),
+            pstrdup(relname),
+            -1,
+        );
+        (*range_var).catalogname = catalog.as_deref().map_or(std::ptr::null_mut(), pstrdup);
+
+        let lockmode = pg_sys::LOCKMODE::from(lockmode);
+        #[cfg(feature = "pg10")]
+        let oid = pg_sys::RangeVarGetRelidExtended(
+            range_var,
+            lockmode,
+            missing_ok,
+            false,
+            None,
+            std::ptr::null_mut(),
+        );
+        #[cfg(not(feature = "pg10"))]
+        let oid = pg_sys::RangeVarGetRelidExtended(
+            range_var,
+            lockmode,
+            if missing_ok {
+                pg_sys::RVROption_RVR_MISSING_OK
+            } else {
+                0
+            },
+            None,
+            std::ptr::null_mut(),
+        );
+        if oid == pg_sys::InvalidOid {
+            return None;
+        }
+
+        // the lock is already held, and released when it's closed
+        Some(PgRelation {
+            boxed: PgBox::from_pg(pg_sys::relation_open(
+                oid,
+                pg_sys::NoLock as pg_sys::LOCKMODE,
+            )),
+            need_close: true,
+            lockmode: Some(lockmode),
+        })
+    }
+
     /// RelationGetRelationName
     ///            Returns the rel's name.
     ///
@@ -193,9 +433,18 @@ impl PgRelation {
     }
 
     /// Return an iterator of indices, as `PgRelation`s, attached to this relation
+    #[deprecated(note = "use `PgRelation::indexes()`")]
     pub fn indicies(
         &self,
         lockmode: pg_sys::LOCKMODE,
+    ) -> impl std::iter::Iterator<Item = PgRelation> {
+        self.indexes(lockmode)
+    }
+
+    /// Return an iterator of the indexes on this relation, as `PgRelation`s opened with `lockmode`
+    pub fn indexes(
+        &self,
+        lockmode: pg_sys::LOCKMODE,
     ) -> impl std::iter::Iterator<Item = PgRelation> {
         // SAFETY: we know self.boxed is a valid pointer as we created it
         let list = unsafe {
@@ -293,6 +542,242 @@ impl PgRelation {
         rd_rel.relkind == pg_sys::RELKIND_TOASTVALUE as c_char
     }
 
+    /// The relation's storage parameters, such as `fillfactor`, as a key and its value, in the
+    /// order they were set
+    pub fn reloptions(&self) -> Vec<(String, Option<String>)> {
+        self.pg_class_attribute(pg_sys::Anum_pg_class_reloptions, |datum| unsafe {
+            Vec::<String>::from_datum(datum.unwrap_or(0), datum.is_none(), pg_sys::TEXTARRAYOID)
+        })
+        .map(|reloptions| parse_options(&reloptions))
+        .unwrap_or_default()
+    }
+
+    /// The relation's storage parameters as its access method's `amoptions` function parsed them,
+    /// such as a custom index's options from [`parse_reloptions()`](crate::parse_reloptions), or
+    /// `None` if it has none
+    ///
+    /// ## Safety
+    ///
+    /// This method is unsafe because it blindly assumes the options are a `T`, which is a
+    /// `pg_sys::StdRdOptions` for a table.
+    pub unsafe fn rd_options<T>(&self) -> Option<&T> {
+        (self.boxed.rd_options as *const T).as_ref()
+    }
+
+    /// Is the relation permanent, unlogged, or temporary?
+    pub fn persistence(&self) -> RelPersistence {
+        match self.rd_rel().relpersistence as u8 {
+            pg_sys::RELPERSISTENCE_UNLOGGED => RelPersistence::Unlogged,
+            pg_sys::RELPERSISTENCE_TEMP => RelPersistence::Temporary,
+            _ => RelPersistence::Permanent,
+        }
+    }
+
+    /// Is the relation a temporary table, or one of its indexes, of this session or another?
+    pub fn is_temp(&self) -> bool {
+        self.persistence() == RelPersistence::Temporary
+    }
+
+    /// Is the relation another session's temporary table, or one of its indexes?  Its rows are
+    /// in the other session's local buffers, so they can't be read from this one
+    pub fn is_other_temp(&self) -> bool {
+        self.is_temp() && !self.boxed.rd_islocaltemp
+    }
+
+    /// Raise an ERROR, as Postgres would, if the relation is another session's temporary table,
+    /// before scanning a relation that came from the catalogs, rather than the user
+    ///
+    /// ## Panics
+    ///
+    /// Raises a Postgres ERROR if [`PgRelation::is_other_temp()`]
+    pub fn check_not_other_temp(&self) {
+        if self.is_other_temp() {
+            crate::ereport(
+                crate::PgLogLevel::ERROR,
+                crate::PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                "cannot access temporary tables of other sessions",
+                file!(),
+                line!(),
+                column!(),
+            );
+        }
+    }
+
+    /// The oid of the tablespace the relation is stored in, or `None` if it's in its database's
+    /// default tablespace
+    pub fn tablespace_oid(&self) -> Option<pg_sys::Oid> {
+        match self.rd_rel().reltablespace {
+            pg_sys::InvalidOid => None,
+            spcoid => Some(spcoid),
+        }
+    }
+
+    /// The name of the tablespace the relation is stored in, or `None` if it's in its database's
+    /// default tablespace
+    pub fn tablespace_name(&self) -> Option<String> {
+        let name = unsafe { pg_sys::get_tablespace_name(self.tablespace_oid()?) };
+        if name.is_null() {
+            None
+        } else {
+            Some(
+                unsafe { CStr::from_ptr(name) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
+    }
+
+    /// Is the relation a partition of a partitioned table?
+    pub fn is_partition(&self) -> bool {
+        self.rd_rel().relispartition
+    }
+
+    /// If the relation is a partition, its bound, as it would be written after
+    /// `ATTACH PARTITION`, such as `FOR VALUES FROM (1) TO (10)`
+    pub fn partition_bound(&self) -> Option<String> {
+        if !self.is_partition() {
+            return None;
+        }
+        self.pg_class_attribute(pg_sys::Anum_pg_class_relpartbound, |datum| unsafe {
+            direct_function_call::<String>(
+                pg_sys::pg_get_expr,
+                vec![datum, self.oid().into_datum()],
+            )
+        })
+    }
+
+    /// The partitions of a partitioned table, or the tables that inherit from any other table,
+    /// opened with `lockmode`.  Only the direct children are returned, so a partition that's
+    /// itself partitioned has its own partitions
+    pub fn partitions(&self, lockmode: pg_sys::LOCKMODE) -> Vec<PgRelation> {
+        let list = unsafe {
+            PgList::<pg_sys::Oid>::from_pg(pg_sys::find_inheritance_children(self.oid(), lockmode))
+        };
+        list.iter_oid()
+            .map(|oid| PgRelation::with_lock(oid, lockmode))
+            .collect()
+    }
+
+    /// The columns an updated or deleted row is identified by for logical replication
+    pub fn replica_identity(&self) -> ReplicaIdentity {
+        match self.rd_rel().relreplident as u8 {
+            pg_sys::REPLICA_IDENTITY_NOTHING => ReplicaIdentity::Nothing,
+            pg_sys::REPLICA_IDENTITY_FULL => ReplicaIdentity::Full,
+            pg_sys::REPLICA_IDENTITY_INDEX => ReplicaIdentity::Index(unsafe {
+                pg_sys::RelationGetReplicaIndex(self.boxed.as_ptr())
+            }),
+            _ => ReplicaIdentity::Default,
+        }
+    }
+
+    fn rd_rel(&self) -> &pg_sys::FormData_pg_class {
+        unsafe { self.boxed.rd_rel.as_ref() }.expect("rd_rel is NULL")
+    }
+
+    /// Call `f` with an attribute of the relation's `pg_class` row that isn't in its `rd_rel`,
+    /// which is `None` if it's `NULL`
+    fn pg_class_attribute<R, F: FnOnce(Option<pg_sys::Datum>) -> R>(&self, attno: u32, f: F) -> R {
+        unsafe {
+            let tuple = pg_sys::SearchSysCache(
+                pg_sys::SysCacheIdentifier_RELOID as i32,
+                self.oid() as pg_sys::Datum,
+                0,
+                0,
+                0,
+            );
+            if tuple.is_null() {
+                panic!("cache lookup failed for relation {}", self.oid());
+            }
+            let mut is_null = false;
+            let datum = pg_sys::SysCacheGetAttr(
+                pg_sys::SysCacheIdentifier_RELOID as i32,
+                tuple,
+                attno as pg_sys::AttrNumber,
+                &mut is_null,
+            );
+            let result = f(if is_null { None } else { Some(datum) });
+            pg_sys::ReleaseSysCache(tuple);
+            result
+        }
+    }
+
+    /// The relation's `CHECK` constraints, in order by name
+    pub fn check_constraints(&self) -> Vec<CheckConstraint> {
+        let constr = match self.tuple_constr() {
+            Some(constr) => constr,
+            None => return Vec::new(),
+        };
+        (0..constr.num_check as usize)
+            .map(|i| {
+                let check = unsafe { &*constr.check.add(i) };
+                CheckConstraint {
+                    name: unsafe { CStr::from_ptr(check.ccname) }
+                        .to_string_lossy()
+                        .into_owned(),
+                    expr: self.stored_expr(check.ccbin),
+                    is_valid: check.ccvalid,
+                    no_inherit: check.ccnoinherit,
+                }
+            })
+            .collect()
+    }
+
+    /// The default of the column `attnum`, which is one-based, if it has one and isn't a
+    /// generated column
+    pub fn column_default(&self, attnum: pg_sys::AttrNumber) -> Option<StoredExpr> {
+        if self.is_generated_column(attnum) {
+            None
+        } else {
+            self.attr_default(attnum)
+        }
+    }
+
+    /// The expression the column `attnum`, which is one-based, is generated by, if it's a
+    /// generated column.  Postgres 12 and later only
+    #[cfg(not(any(feature = "pg10", feature = "pg11")))]
+    pub fn generated_column_expr(&self, attnum: pg_sys::AttrNumber) -> Option<StoredExpr> {
+        if self.is_generated_column(attnum) {
+            self.attr_default(attnum)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(any(feature = "pg10", feature = "pg11"))]
+    fn is_generated_column(&self, _attnum: pg_sys::AttrNumber) -> bool {
+        false
+    }
+
+    #[cfg(not(any(feature = "pg10", feature = "pg11")))]
+    fn is_generated_column(&self, attnum: pg_sys::AttrNumber) -> bool {
+        match self.tuple_desc().get(attnum as usize - 1) {
+            Some(att) => att.attgenerated != 0,
+            None => false,
+        }
+    }
+
+    fn attr_default(&self, attnum: pg_sys::AttrNumber) -> Option<StoredExpr> {
+        let constr = self.tuple_constr()?;
+        (0..constr.num_defval as usize)
+            .map(|i| unsafe { &*constr.defval.add(i) })
+            .find(|default| default.adnum == attnum)
+            .map(|default| self.stored_expr(default.adbin))
+    }
+
+    fn tuple_constr(&self) -> Option<&pg_sys::TupleConstr> {
+        unsafe { (*self.boxed.rd_att).constr.as_ref() }
+    }
+
+    fn stored_expr(&self, node_tree: *const c_char) -> StoredExpr {
+        StoredExpr {
+            relid: self.oid(),
+            node_tree: unsafe { CStr::from_ptr(node_tree) }
+                .to_str()
+                .expect("node tree is not valid UTF8")
+                .to_string(),
+        }
+    }
+
     /// ensures that the returned `PgRelation` is closed by Rust when it is dropped
     pub fn to_owned(mut self) -> Self {
         self.need_close = true;
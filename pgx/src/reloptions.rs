@@ -0,0 +1,326 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Custom relation options, the `WITH (..)` storage parameters an index or table access method
+//! accepts, such as a `CREATE INDEX .. USING my_am .. WITH (pages_per_range = 64)`
+//!
+//! The options are defined for a [`RelOptionKind`] in the extension's `_PG_init()`, and its
+//! access method's `amoptions` function parses a relation's `reloptions` into a `#[repr(C)]`
+//! struct with [`parse_reloptions()`].  Postgres keeps that struct in the relation's relcache
+//! entry, where [`PgRelation::rd_options()`](crate::PgRelation::rd_options) reads it later.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! /// Laid out like a `bytea`, with the `vl_len_` first
+//! #[repr(C)]
+//! struct MyIndexOptions {
+//!     vl_len_: i32,
+//!     pages_per_range: i32,
+//!     /// where `mode`'s string is, after the struct
+//!     mode: i32,
+//! }
+//!
+//! static mut MY_INDEX_KIND: Option<RelOptionKind> = None;
+//!
+//! #[allow(non_snake_case)]
+//! #[pg_guard]
+//! pub extern "C" fn _PG_init() {
+//!     let kind = RelOptionKind::allocate();
+//!     kind.define_int_option("pages_per_range", "pages summarized per range", 128, 1, 1024);
+//!     kind.define_string_option("mode", "how ranges are summarized", Some("minmax"));
+//!     unsafe { MY_INDEX_KIND = Some(kind) };
+//! }
+//!
+//! #[pg_guard]
+//! unsafe extern "C" fn my_amoptions(reloptions: pg_sys::Datum, validate: bool) -> *mut pg_sys::bytea {
+//!     parse_reloptions::<MyIndexOptions>(
+//!         reloptions,
+//!         validate,
+//!         MY_INDEX_KIND.unwrap(),
+//!         &[
+//!             RelOptionField::new("pages_per_range", RelOptionType::Int, 4),
+//!             RelOptionField::new("mode", RelOptionType::String, 8),
+//!         ],
+//!     )
+//! }
+//!
+//! fn pages_per_range(index: &PgRelation) -> i32 {
+//!     unsafe { index.rd_options::<MyIndexOptions>() }
+//!         .map(|options| options.pages_per_range)
+//!         .unwrap_or(128)
+//! }
+//! ```
+use crate::{pg_sys, PgMemoryContexts};
+use std::ffi::{CStr, CString};
+
+/// The kinds of relation an option applies to, in Postgres' `relopt_kind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelOptionKind(pg_sys::relopt_kind);
+
+impl RelOptionKind {
+    pub const HEAP: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_HEAP);
+    pub const TOAST: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_TOAST);
+    pub const BTREE: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_BTREE);
+    pub const HASH: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_HASH);
+    pub const GIN: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_GIN);
+    pub const GIST: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_GIST);
+    pub const SPGIST: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_SPGIST);
+    pub const BRIN: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_BRIN);
+    pub const VIEW: RelOptionKind = RelOptionKind(pg_sys::relopt_kind_RELOPT_KIND_VIEW);
+
+    /// A new kind, for the relations of the extension's own access method, as
+    /// `add_reloption_kind()` allocates
+    ///
+    /// It, and its options, are defined in the extension's `_PG_init()`, so every backend that
+    /// could parse the options has them.
+    pub fn allocate() -> Self {
+        RelOptionKind(unsafe { pg_sys::add_reloption_kind() })
+    }
+
+    /// The kind Postgres knows it by
+    pub fn as_raw(&self) -> pg_sys::relopt_kind {
+        self.0
+    }
+
+    /// Define a `bool` option called `name`
+    pub fn define_bool_option(&self, name: &str, description: &str, default_value: bool) {
+        unsafe {
+            pg_sys::add_bool_reloption(
+                self.0,
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(description),
+                default_value,
+                #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+                RELOPTION_LOCKMODE,
+            )
+        }
+    }
+
+    /// Define an `int` option called `name`, which is between `min_value` and `max_value`
+    pub fn define_int_option(
+        &self,
+        name: &str,
+        description: &str,
+        default_value: i32,
+        min_value: i32,
+        max_value: i32,
+    ) {
+        unsafe {
+            pg_sys::add_int_reloption(
+                self.0,
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(description),
+                default_value,
+                min_value,
+                max_value,
+                #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+                RELOPTION_LOCKMODE,
+            )
+        }
+    }
+
+    /// Define a `float8` option called `name`, which is between `min_value` and `max_value`
+    pub fn define_real_option(
+        &self,
+        name: &str,
+        description: &str,
+        default_value: f64,
+        min_value: f64,
+        max_value: f64,
+    ) {
+        unsafe {
+            pg_sys::add_real_reloption(
+                self.0,
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(description),
+                default_value,
+                min_value,
+                max_value,
+                #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+                RELOPTION_LOCKMODE,
+            )
+        }
+    }
+
+    /// Define a string option called `name`, which is `NULL` by default if it has no
+    /// `default_value`
+    pub fn define_string_option(&self, name: &str, description: &str, default_value: Option<&str>) {
+        unsafe {
+            let default_value = match default_value {
+                Some(s) => PgMemoryContexts::TopMemoryContext.pstrdup(s),
+                None => std::ptr::null_mut(),
+            };
+            pg_sys::add_string_reloption(
+                self.0,
+                PgMemoryContexts::TopMemoryContext.pstrdup(name),
+                PgMemoryContexts::TopMemoryContext.pstrdup(description),
+                default_value,
+                None,
+                #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+                RELOPTION_LOCKMODE,
+            )
+        }
+    }
+}
+
+/// The lock an `ALTER .. SET (..)` of a custom option takes, which is the strongest, as Postgres
+/// can't know what the extension does with it
+#[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+const RELOPTION_LOCKMODE: pg_sys::LOCKMODE = pg_sys::AccessExclusiveLock as pg_sys::LOCKMODE;
+
+/// The type of an option, and of its field in the struct [`parse_reloptions()`] fills
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelOptionType {
+    /// A `bool` field
+    Bool,
+    /// An `i32` field
+    Int,
+    /// An `f64` field
+    Real,
+    /// An `i32` field, of where the string is from the start of the struct, or `0` if it's
+    /// `NULL`, which [`reloption_string()`] reads
+    String,
+}
+
+impl RelOptionType {
+    fn as_raw(&self) -> pg_sys::relopt_type {
+        match self {
+            RelOptionType::Bool => pg_sys::relopt_type_RELOPT_TYPE_BOOL,
+            RelOptionType::Int => pg_sys::relopt_type_RELOPT_TYPE_INT,
+            RelOptionType::Real => pg_sys::relopt_type_RELOPT_TYPE_REAL,
+            RelOptionType::String => pg_sys::relopt_type_RELOPT_TYPE_STRING,
+        }
+    }
+}
+
+/// Which field of the struct [`parse_reloptions()`] fills an option is put in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelOptionField {
+    pub name: &'static str,
+    pub option_type: RelOptionType,
+    /// The field's offset in the struct, in bytes
+    pub offset: usize,
+}
+
+impl RelOptionField {
+    pub const fn new(name: &'static str, option_type: RelOptionType, offset: usize) -> Self {
+        RelOptionField {
+            name,
+            option_type,
+            offset,
+        }
+    }
+}
+
+/// Parse a relation's `reloptions`, a `text[]` of `name=value`s, into a `T`, for an access
+/// method's `amoptions` function to return
+///
+/// Each of the `kind`'s options in `fields` is put in its field, with its default if it isn't
+/// set.  When `validate` is true, an option which isn't one of the `kind`'s, or a value that
+/// isn't valid, raises an `ERROR`.  The `T` is allocated in `CurrentMemoryContext`, with its
+/// string options after it, and it's a null pointer if there are no options at all.
+///
+/// ## Safety
+///
+/// This function is unsafe because it blindly assumes `T` is a `#[repr(C)]` struct which starts
+/// with an `i32` varlena header, and has a field of the right type at each of the `fields`'
+/// offsets.
+pub unsafe fn parse_reloptions<T>(
+    reloptions: pg_sys::Datum,
+    validate: bool,
+    kind: RelOptionKind,
+    fields: &[RelOptionField],
+) -> *mut pg_sys::bytea {
+    let names = fields
+        .iter()
+        .map(|field| CString::new(field.name).expect("option name contains a null byte"))
+        .collect::<Vec<_>>();
+    let elems = fields
+        .iter()
+        .zip(names.iter())
+        .map(|(field, name)| pg_sys::relopt_parse_elt {
+            optname: name.as_ptr(),
+            opttype: field.option_type.as_raw(),
+            offset: field.offset as i32,
+        })
+        .collect::<Vec<_>>();
+
+    build_reloptions(
+        reloptions,
+        validate,
+        kind.0,
+        std::mem::size_of::<T>(),
+        &elems,
+    ) as *mut pg_sys::bytea
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+unsafe fn build_reloptions(
+    reloptions: pg_sys::Datum,
+    validate: bool,
+    kind: pg_sys::relopt_kind,
+    size: usize,
+    elems: &[pg_sys::relopt_parse_elt],
+) -> *mut std::os::raw::c_void {
+    let mut numoptions = 0;
+    let options = pg_sys::parseRelOptions(reloptions, validate, kind, &mut numoptions);
+    if numoptions == 0 {
+        return std::ptr::null_mut();
+    }
+    let rdopts = pg_sys::allocateReloptStruct(size, options, numoptions);
+    pg_sys::fillRelOptions(
+        rdopts,
+        size,
+        options,
+        numoptions,
+        validate,
+        elems.as_ptr(),
+        elems.len() as i32,
+    );
+    pg_sys::pfree(options as *mut std::os::raw::c_void);
+    rdopts
+}
+
+#[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+unsafe fn build_reloptions(
+    reloptions: pg_sys::Datum,
+    validate: bool,
+    kind: pg_sys::relopt_kind,
+    size: usize,
+    elems: &[pg_sys::relopt_parse_elt],
+) -> *mut std::os::raw::c_void {
+    pg_sys::build_reloptions(
+        reloptions,
+        validate,
+        kind,
+        size,
+        elems.as_ptr(),
+        elems.len() as i32,
+    )
+}
+
+/// The string option a [`parse_reloptions()`]-filled `options` has at `offset`, from its
+/// [`RelOptionType::String`] field, or `None` if it's `NULL`
+///
+/// ## Safety
+///
+/// This function is unsafe because it blindly assumes `offset` is from a `String` field of
+/// `options`, which was filled by [`parse_reloptions()`].
+pub unsafe fn reloption_string<T>(options: &T, offset: i32) -> Option<&str> {
+    if offset == 0 {
+        return None;
+    }
+    let string = (options as *const T as *const std::os::raw::c_char).offset(offset as isize);
+    Some(
+        CStr::from_ptr(string)
+            .to_str()
+            .expect("option is not valid UTF8"),
+    )
+}
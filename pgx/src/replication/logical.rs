@@ -0,0 +1,415 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Streaming changes out of a logical replication slot from inside the server, without a
+//! walsender connection
+//!
+//! A [`PgLogicalSlot`] decodes through the built-in `pgoutput` plugin, the same one used by
+//! `CREATE SUBSCRIPTION`, so the tables it streams are chosen with a `CREATE PUBLICATION`.
+//! Changes are read with `pg_logical_slot_get_binary_changes()` and are only removed from the
+//! slot when the reading transaction commits.  If whatever the consumer does with them happens
+//! in that same transaction, each change is applied exactly once.
+use crate::bgworkers::BackgroundWorker;
+use crate::{pg_sys, IntoDatum, PgBuiltInOids, Spi};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A logical replication slot, decoded with `pgoutput` for a publication
+///
+/// ## Example
+///
+/// A background worker that logs every change to the tables in the `cdc` publication:
+///
+/// ```rust,no_run
+/// use pgx::bgworkers::*;
+/// use pgx::*;
+/// use std::time::Duration;
+///
+/// #[pg_guard]
+/// #[no_mangle]
+/// pub extern "C" fn cdc_worker_main(_arg: pg_sys::Datum) {
+///     BackgroundWorker::attach_signal_handlers(SignalWakeFlags::SIGHUP | SignalWakeFlags::SIGTERM);
+///     BackgroundWorker::connect_worker_to_spi(Some("postgres"), None);
+///
+///     let mut slot = PgLogicalSlot::open("cdc_slot", "cdc");
+///     slot.stream(Duration::from_secs(1), |change| log!("{:?}", change));
+/// }
+/// ```
+pub struct PgLogicalSlot {
+    name: String,
+    publication: String,
+    decoder: PgOutputDecoder,
+}
+
+impl PgLogicalSlot {
+    /// Create the slot `name` and return it, streaming the tables in `publication`.  Must be
+    /// called inside a transaction that hasn't written anything
+    pub fn create(name: &str, publication: &str) -> Self {
+        Spi::get_one_with_args::<String>(
+            "SELECT slot_name::text FROM pg_create_logical_replication_slot($1, 'pgoutput')",
+            vec![(PgBuiltInOids::TEXTOID.oid(), name.into_datum())],
+        );
+        PgLogicalSlot::open(name, publication)
+    }
+
+    /// Use the existing slot `name`, streaming the tables in `publication`
+    pub fn open(name: &str, publication: &str) -> Self {
+        PgLogicalSlot {
+            name: name.to_string(),
+            publication: publication.to_string(),
+            decoder: PgOutputDecoder::default(),
+        }
+    }
+
+    /// Drop the slot.  Must be called inside a transaction
+    pub fn drop_slot(self) {
+        Spi::get_one_with_args::<String>(
+            "SELECT pg_drop_replication_slot($1)::text",
+            vec![(PgBuiltInOids::TEXTOID.oid(), self.name.into_datum())],
+        );
+    }
+
+    /// The slot's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Read, and consume, up to `limit` pending changes (or all of them if `None`).  Must be
+    /// called inside a transaction, and the changes are only consumed if it commits
+    pub fn get_changes(&mut self, limit: Option<i32>) -> Vec<LogicalChange> {
+        self.read_changes("pg_logical_slot_get_binary_changes", limit)
+    }
+
+    /// Like [`PgLogicalSlot::get_changes()`], but leaves the changes in the slot
+    pub fn peek_changes(&mut self, limit: Option<i32>) -> Vec<LogicalChange> {
+        self.read_changes("pg_logical_slot_peek_binary_changes", limit)
+    }
+
+    /// The main loop for a background worker:  every `interval`, and until SIGTERM, consume the
+    /// pending changes in a new transaction and hand each one to `on_change`
+    ///
+    /// The worker must already be connected with [`BackgroundWorker::connect_worker_to_spi()`].
+    pub fn stream<F: FnMut(&LogicalChange)>(&mut self, interval: Duration, mut on_change: F) {
+        while BackgroundWorker::wait_latch(Some(interval)) {
+            let mut this = std::panic::AssertUnwindSafe(&mut *self);
+            let mut on_change = std::panic::AssertUnwindSafe(&mut on_change);
+            BackgroundWorker::transaction(move || {
+                for change in this.get_changes(None) {
+                    (on_change)(&change);
+                }
+            });
+        }
+    }
+
+    fn read_changes(&mut self, function: &str, limit: Option<i32>) -> Vec<LogicalChange> {
+        let query = format!(
+            "SELECT (lsn - '0/0')::int8, data FROM {}($1, NULL, $2, 'proto_version', '1', 'publication_names', quote_ident($3))",
+            function
+        );
+        let args = vec![
+            (
+                PgBuiltInOids::TEXTOID.oid(),
+                self.name.as_str().into_datum(),
+            ),
+            (PgBuiltInOids::INT4OID.oid(), limit.into_datum()),
+            (
+                PgBuiltInOids::TEXTOID.oid(),
+                self.publication.as_str().into_datum(),
+            ),
+        ];
+
+        let decoder = &mut self.decoder;
+        let mut changes = Vec::new();
+        Spi::connect(|client| {
+            for row in client.select(&query, None, Some(args)) {
+                let lsn = row.by_ordinal(1)?.value::<i64>().expect("lsn was null");
+                let data = row
+                    .by_ordinal(2)?
+                    .value::<Vec<u8>>()
+                    .expect("data was null");
+                if let Some(change) = decoder.decode(lsn as pg_sys::XLogRecPtr, &data) {
+                    changes.push(change);
+                }
+            }
+            Ok(Some(()))
+        });
+        changes
+    }
+}
+
+/// One change decoded from a [`PgLogicalSlot`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicalChange {
+    /// A transaction started.  Everything until the matching `Commit` belongs to it
+    Begin {
+        /// The LSN of the transaction's commit record
+        final_lsn: pg_sys::XLogRecPtr,
+        /// When the transaction committed, in microseconds since 2000-01-01
+        commit_time: pg_sys::TimestampTz,
+        xid: pg_sys::TransactionId,
+    },
+    /// The current transaction committed
+    Commit {
+        commit_lsn: pg_sys::XLogRecPtr,
+        end_lsn: pg_sys::XLogRecPtr,
+        commit_time: pg_sys::TimestampTz,
+    },
+    /// The current transaction was itself replicated from the replication origin `name`
+    Origin {
+        origin_lsn: pg_sys::XLogRecPtr,
+        name: String,
+    },
+    /// A row was inserted into `relation`
+    Insert {
+        lsn: pg_sys::XLogRecPtr,
+        relation: LogicalRelation,
+        new: Vec<LogicalValue>,
+    },
+    /// A row of `relation` was updated.  `old` holds the replica identity columns of the old
+    /// row if they changed, or all of its columns with `REPLICA IDENTITY FULL`
+    Update {
+        lsn: pg_sys::XLogRecPtr,
+        relation: LogicalRelation,
+        old: Option<Vec<LogicalValue>>,
+        new: Vec<LogicalValue>,
+    },
+    /// A row was deleted from `relation`.  `old` holds its replica identity columns
+    Delete {
+        lsn: pg_sys::XLogRecPtr,
+        relation: LogicalRelation,
+        old: Vec<LogicalValue>,
+    },
+    /// `relations` were truncated
+    Truncate {
+        lsn: pg_sys::XLogRecPtr,
+        relations: Vec<LogicalRelation>,
+        cascade: bool,
+        restart_identity: bool,
+    },
+}
+
+/// A table whose changes are being streamed, along with its columns at the time of the change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogicalRelation {
+    pub oid: pg_sys::Oid,
+    pub namespace: String,
+    pub name: String,
+    /// The table's `relreplident`:  `d`efault, `n`othing, `f`ull, or `i`ndex
+    pub replica_identity: u8,
+    pub columns: Vec<LogicalColumn>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogicalColumn {
+    pub name: String,
+    pub type_oid: pg_sys::Oid,
+    pub typmod: i32,
+    /// Is the column part of the replica identity?
+    pub is_key: bool,
+}
+
+/// A column value from a changed row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogicalValue {
+    Null,
+    /// A TOASTed value that the change didn't touch, and so wasn't sent
+    UnchangedToast,
+    /// The value in its type's text output format
+    Text(String),
+}
+
+/// Decodes `pgoutput`'s protocol version 1 messages, as documented in the "Logical Replication
+/// Message Formats" chapter of the Postgres docs
+#[derive(Debug, Default)]
+pub struct PgOutputDecoder {
+    relations: HashMap<pg_sys::Oid, LogicalRelation>,
+}
+
+impl PgOutputDecoder {
+    /// Decode the message written at `lsn`.  Relation and type messages only describe the
+    /// messages that follow them, and produce `None`
+    pub fn decode(&mut self, lsn: pg_sys::XLogRecPtr, message: &[u8]) -> Option<LogicalChange> {
+        let mut reader = MessageReader { buf: message };
+        let change = match reader.u8() {
+            b'B' => LogicalChange::Begin {
+                final_lsn: reader.u64(),
+                commit_time: reader.u64() as pg_sys::TimestampTz,
+                xid: reader.u32(),
+            },
+            b'C' => {
+                let _flags = reader.u8();
+                LogicalChange::Commit {
+                    commit_lsn: reader.u64(),
+                    end_lsn: reader.u64(),
+                    commit_time: reader.u64() as pg_sys::TimestampTz,
+                }
+            }
+            b'O' => LogicalChange::Origin {
+                origin_lsn: reader.u64(),
+                name: reader.string(),
+            },
+            b'R' => {
+                let oid = reader.u32();
+                let namespace = reader.string();
+                let name = reader.string();
+                let replica_identity = reader.u8();
+                let columns = (0..reader.u16())
+                    .map(|_| {
+                        let is_key = reader.u8() & 1 != 0;
+                        LogicalColumn {
+                            is_key,
+                            name: reader.string(),
+                            type_oid: reader.u32(),
+                            typmod: reader.u32() as i32,
+                        }
+                    })
+                    .collect();
+                self.relations.insert(
+                    oid,
+                    LogicalRelation {
+                        oid,
+                        namespace,
+                        name,
+                        replica_identity,
+                        columns,
+                    },
+                );
+                return None;
+            }
+            b'Y' => return None,
+            b'I' => {
+                let relation = self.relation(reader.u32());
+                reader.expect(b'N');
+                LogicalChange::Insert {
+                    lsn,
+                    relation,
+                    new: reader.tuple(),
+                }
+            }
+            b'U' => {
+                let relation = self.relation(reader.u32());
+                let old = match reader.u8() {
+                    b'K' | b'O' => {
+                        let old = reader.tuple();
+                        reader.expect(b'N');
+                        Some(old)
+                    }
+                    b'N' => None,
+                    other => panic!("unexpected pgoutput update tuple kind `{}`", other as char),
+                };
+                LogicalChange::Update {
+                    lsn,
+                    relation,
+                    old,
+                    new: reader.tuple(),
+                }
+            }
+            b'D' => {
+                let relation = self.relation(reader.u32());
+                match reader.u8() {
+                    b'K' | b'O' => {}
+                    other => panic!("unexpected pgoutput delete tuple kind `{}`", other as char),
+                }
+                LogicalChange::Delete {
+                    lsn,
+                    relation,
+                    old: reader.tuple(),
+                }
+            }
+            b'T' => {
+                let nrelations = reader.u32();
+                let options = reader.u8();
+                LogicalChange::Truncate {
+                    lsn,
+                    relations: (0..nrelations)
+                        .map(|_| self.relation(reader.u32()))
+                        .collect(),
+                    cascade: options & 1 != 0,
+                    restart_identity: options & 2 != 0,
+                }
+            }
+            other => panic!("unrecognized pgoutput message type `{}`", other as char),
+        };
+        Some(change)
+    }
+
+    fn relation(&self, oid: pg_sys::Oid) -> LogicalRelation {
+        self.relations
+            .get(&oid)
+            .cloned()
+            .unwrap_or_else(|| panic!("pgoutput sent a change for undescribed relation {}", oid))
+    }
+}
+
+struct MessageReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> MessageReader<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        if self.buf.len() < len {
+            panic!("truncated pgoutput message");
+        }
+        let (taken, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        taken
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.take(1)[0]
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_be_bytes(self.take(2).try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_be_bytes(self.take(4).try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_be_bytes(self.take(8).try_into().unwrap())
+    }
+
+    fn expect(&mut self, byte: u8) {
+        let found = self.u8();
+        if found != byte {
+            panic!(
+                "expected pgoutput `{}`, found `{}`",
+                byte as char, found as char
+            );
+        }
+    }
+
+    /// a null-terminated string
+    fn string(&mut self) -> String {
+        let len = self
+            .buf
+            .iter()
+            .position(|b| *b == 0)
+            .expect("unterminated string in pgoutput message");
+        let string = String::from_utf8_lossy(self.take(len)).into_owned();
+        self.take(1);
+        string
+    }
+
+    fn tuple(&mut self) -> Vec<LogicalValue> {
+        (0..self.u16())
+            .map(|_| match self.u8() {
+                b'n' => LogicalValue::Null,
+                b'u' => LogicalValue::UnchangedToast,
+                b't' => {
+                    let len = self.u32() as usize;
+                    LogicalValue::Text(String::from_utf8_lossy(self.take(len)).into_owned())
+                }
+                other => panic!("unexpected pgoutput column kind `{}`", other as char),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,20 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Consuming logical replication, and tracking its progress with replication origins
+//!
+//! The replication origin wrappers need the `replication` feature, as they call into
+//! `replication/origin.h` directly.
+mod logical;
+#[cfg(feature = "replication")]
+mod origin;
+
+pub use logical::*;
+#[cfg(feature = "replication")]
+pub use origin::*;
@@ -0,0 +1,153 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A replication origin records how far a replication consumer has applied changes from some
+//! remote source.  When a [`PgReplicationOriginSession`] is active and
+//! [`PgReplicationOriginSession::begin_apply()`] has been told the remote LSN of the transaction
+//! being applied, the origin's progress is advanced as part of the local commit record -- so
+//! after a crash the consumer restarts from exactly the last transaction it committed.
+use crate::pg_sys;
+use std::ffi::CString;
+
+/// A replication origin, identified by its `RepOriginId`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PgReplicationOrigin {
+    id: pg_sys::RepOriginId,
+}
+
+impl PgReplicationOrigin {
+    /// Create a new replication origin named `name`.  Must be called inside a transaction, and
+    /// raises an ERROR if an origin by that name already exists
+    pub fn create(name: &str) -> Self {
+        let name = CString::new(name).expect("replication origin name contains a null byte");
+        let id = unsafe { pg_sys::replorigin_create(name.as_ptr() as *mut _) };
+        PgReplicationOrigin { id }
+    }
+
+    /// Look up an existing replication origin by name, returning `None` if there isn't one.
+    /// Must be called inside a transaction
+    pub fn by_name(name: &str) -> Option<Self> {
+        let name = CString::new(name).expect("replication origin name contains a null byte");
+        let id = unsafe { pg_sys::replorigin_by_name(name.as_ptr() as *mut _, true) };
+        if id == INVALID_REP_ORIGIN_ID {
+            None
+        } else {
+            Some(PgReplicationOrigin { id })
+        }
+    }
+
+    /// Drop the replication origin named `name`.  If `nowait` is false, waits for any session
+    /// using the origin to release it, otherwise raises an ERROR.  Must be called inside a
+    /// transaction
+    pub fn drop_by_name(name: &str, nowait: bool) {
+        let name = CString::new(name).expect("replication origin name contains a null byte");
+        unsafe {
+            #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+            {
+                let id = pg_sys::replorigin_by_name(name.as_ptr() as *mut _, false);
+                pg_sys::replorigin_drop(id, nowait);
+            }
+
+            #[cfg(any(feature = "pg14", feature = "pg15"))]
+            pg_sys::replorigin_drop_by_name(name.as_ptr(), false, nowait);
+        }
+    }
+
+    /// The origin's `RepOriginId`
+    pub fn id(&self) -> pg_sys::RepOriginId {
+        self.id
+    }
+
+    /// Set the origin's progress to `remote_commit`, the remote LSN that has been applied, which
+    /// locally committed at `local_commit`.  Unless `go_backward` is true, progress that is
+    /// older than what's already recorded is ignored.  If `wal_log` is true, the new progress
+    /// survives a crash
+    pub fn advance(
+        &self,
+        remote_commit: pg_sys::XLogRecPtr,
+        local_commit: pg_sys::XLogRecPtr,
+        go_backward: bool,
+        wal_log: bool,
+    ) {
+        unsafe {
+            // keep the origin from being dropped out from underneath us, the same as
+            // `pg_replication_origin_advance()`
+            pg_sys::LockRelationOid(
+                pg_sys::ReplicationOriginRelationId,
+                pg_sys::RowExclusiveLock as pg_sys::LOCKMODE,
+            );
+            pg_sys::replorigin_advance(self.id, remote_commit, local_commit, go_backward, wal_log);
+        }
+    }
+
+    /// The remote LSN up to which changes have been applied.  If `flush` is true, only progress
+    /// whose local commit has been flushed to disk is reported
+    pub fn progress(&self, flush: bool) -> pg_sys::XLogRecPtr {
+        unsafe { pg_sys::replorigin_get_progress(self.id, flush) }
+    }
+}
+
+/// Marks this backend as replaying changes from a [`PgReplicationOrigin`], until dropped
+///
+/// Transactions committed while the session is active are tagged with the origin, which logical
+/// decoding output plugins can use to filter them out.  An origin can only be used by one session
+/// at a time, and a backend can only have one session.
+pub struct PgReplicationOriginSession {
+    origin: PgReplicationOrigin,
+}
+
+impl PgReplicationOriginSession {
+    /// Start replaying from `origin`.  Raises an ERROR if another backend is already using it
+    pub fn setup(origin: PgReplicationOrigin) -> Self {
+        unsafe {
+            pg_sys::replorigin_session_setup(origin.id);
+            pg_sys::replorigin_session_origin = origin.id;
+        }
+        PgReplicationOriginSession { origin }
+    }
+
+    /// The origin this session is replaying from
+    pub fn origin(&self) -> PgReplicationOrigin {
+        self.origin
+    }
+
+    /// Note that the current transaction applies the remote transaction which committed at
+    /// `remote_lsn` and `remote_commit_time`.  The origin's progress is advanced when, and only
+    /// if, the current transaction commits
+    pub fn begin_apply(
+        &self,
+        remote_lsn: pg_sys::XLogRecPtr,
+        remote_commit_time: pg_sys::TimestampTz,
+    ) {
+        unsafe {
+            pg_sys::replorigin_session_origin_lsn = remote_lsn;
+            pg_sys::replorigin_session_origin_timestamp = remote_commit_time;
+        }
+    }
+
+    /// The remote LSN up to which this session's origin has been applied.  If `flush` is true,
+    /// only progress whose local commit has been flushed to disk is reported
+    pub fn progress(&self, flush: bool) -> pg_sys::XLogRecPtr {
+        unsafe { pg_sys::replorigin_session_get_progress(flush) }
+    }
+}
+
+impl Drop for PgReplicationOriginSession {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::replorigin_session_reset();
+            pg_sys::replorigin_session_origin = INVALID_REP_ORIGIN_ID;
+            pg_sys::replorigin_session_origin_lsn = pg_sys::InvalidXLogRecPtr as pg_sys::XLogRecPtr;
+            pg_sys::replorigin_session_origin_timestamp = 0;
+        }
+    }
+}
+
+/// this comes from `replication/origin.h`
+const INVALID_REP_ORIGIN_ID: pg_sys::RepOriginId = 0;
@@ -0,0 +1,133 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! The current and session users, role membership, and running code as another role
+use crate::pg_sys;
+use std::ffi::{CStr, CString};
+
+/// The role privileges are checked as, which is what SQL's `current_user` returns.  It's the
+/// owner of a `SECURITY DEFINER` function while it runs, or the role of a `SET ROLE`
+pub fn current_user_id() -> pg_sys::Oid {
+    unsafe { pg_sys::GetUserId() }
+}
+
+/// The role that connected, what SQL's `session_user` returns, unless a superuser changed it with
+/// `SET SESSION AUTHORIZATION`
+pub fn session_user_id() -> pg_sys::Oid {
+    unsafe { pg_sys::GetSessionUserId() }
+}
+
+/// The name of the role `roleid`, or `None` if there's no such role
+pub fn role_name(roleid: pg_sys::Oid) -> Option<String> {
+    let name = unsafe { pg_sys::GetUserNameFromId(roleid, true) };
+    if name.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned())
+    }
+}
+
+/// The oid of the role called `name`, or `None` if there's no such role
+pub fn role_oid(name: &str) -> Option<pg_sys::Oid> {
+    let name = CString::new(name).expect("role name contains a null byte");
+    match unsafe { pg_sys::get_role_oid(name.as_ptr(), true) } {
+        pg_sys::InvalidOid => None,
+        roleid => Some(roleid),
+    }
+}
+
+/// Is `roleid` a superuser?
+pub fn is_superuser(roleid: pg_sys::Oid) -> bool {
+    unsafe { pg_sys::superuser_arg(roleid) }
+}
+
+/// Is `member` a member of `role`, directly or through other roles, whether or not it inherits
+/// `role`'s privileges?  A role is a member of itself, and a superuser is a member of every role
+pub fn is_member_of_role(member: pg_sys::Oid, role: pg_sys::Oid) -> bool {
+    unsafe { pg_sys::is_member_of_role(member, role) }
+}
+
+/// Does `member` have `role`'s privileges, by being `role`, a superuser, or a member of `role`
+/// that inherits them?
+pub fn has_privileges_of_role(member: pg_sys::Oid, role: pg_sys::Oid) -> bool {
+    unsafe { pg_sys::has_privs_of_role(member, role) }
+}
+
+/// Runs code as another role until it's dropped, like a `SECURITY DEFINER` function does, so
+/// privileges are checked as that role.  Dropping it switches back to the role and security
+/// context from before, even if the code in between raised an ERROR.
+///
+/// It doesn't check that the current user may become the role, so an extension serving many
+/// tenants should check that with [`is_member_of_role()`] first, and it must be dropped in the
+/// transaction it was made in.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn tenant_row_count(tenant: &str) -> i64 {
+///     let tenant = role_oid(tenant).expect("no such tenant");
+///     if !is_member_of_role(session_user_id(), tenant) {
+///         error!("not a member of the tenant's role");
+///     }
+///     let _as_tenant = RoleSwitch::new(tenant);
+///     Spi::get_one("SELECT count(*) FROM tenant_rows").unwrap()
+/// }
+/// ```
+pub struct RoleSwitch {
+    previous_user_id: pg_sys::Oid,
+    previous_sec_context: i32,
+}
+
+impl RoleSwitch {
+    /// Switch to `roleid`
+    pub fn new(roleid: pg_sys::Oid) -> Self {
+        Self::with_sec_context(roleid, pg_sys::SECURITY_LOCAL_USERID_CHANGE as i32)
+    }
+
+    /// Switch to `roleid` for a security-restricted operation, as maintenance commands like
+    /// `ANALYZE` run the table owner's index functions, which can't change settings or create
+    /// temporary tables that would outlive the switch
+    pub fn restricted(roleid: pg_sys::Oid) -> Self {
+        Self::with_sec_context(
+            roleid,
+            (pg_sys::SECURITY_LOCAL_USERID_CHANGE | pg_sys::SECURITY_RESTRICTED_OPERATION) as i32,
+        )
+    }
+
+    fn with_sec_context(roleid: pg_sys::Oid, flags: i32) -> Self {
+        let mut previous_user_id = pg_sys::InvalidOid;
+        let mut previous_sec_context = 0;
+        unsafe {
+            pg_sys::GetUserIdAndSecContext(&mut previous_user_id, &mut previous_sec_context);
+            pg_sys::SetUserIdAndSecContext(roleid, previous_sec_context | flags);
+        }
+        RoleSwitch {
+            previous_user_id,
+            previous_sec_context,
+        }
+    }
+
+    /// The role that was the current user before the switch
+    pub fn previous_user_id(&self) -> pg_sys::Oid {
+        self.previous_user_id
+    }
+}
+
+impl Drop for RoleSwitch {
+    fn drop(&mut self) {
+        unsafe { pg_sys::SetUserIdAndSecContext(self.previous_user_id, self.previous_sec_context) }
+    }
+}
+
+/// Run `f` as the role `roleid`, with a [`RoleSwitch`]
+pub fn with_role<R, F: FnOnce() -> R>(roleid: pg_sys::Oid, f: F) -> R {
+    let _switch = RoleSwitch::new(roleid);
+    f()
+}
@@ -0,0 +1,100 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Security label providers, for `SECURITY LABEL FOR provider ON object IS 'label'`
+use crate::{ereport, pg_sys, PgLogLevel, PgSqlErrorCode};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+/// A provider of security labels, for mandatory access control or data classification
+///
+/// Postgres stores the labels, and the provider decides which labels are valid for an object.
+/// Register the provider with [`register_security_label_provider()`] from `_PG_init()`.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// struct Classification;
+///
+/// impl SecurityLabelProvider for Classification {
+///     const NAME: &'static str = "classification";
+///
+///     fn check_relabel(_object: &pg_sys::ObjectAddress, label: Option<&str>) -> Result<(), String> {
+///         match label {
+///             None | Some("public") | Some("confidential") => Ok(()),
+///             Some(label) => Err(format!("`{}` is not a classification", label)),
+///         }
+///     }
+/// }
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     register_security_label_provider::<Classification>();
+/// }
+/// ```
+pub trait SecurityLabelProvider {
+    /// The name used in `SECURITY LABEL FOR name ...`
+    const NAME: &'static str;
+
+    /// Decide if `object` can be given `label`, or have its label removed if `label` is `None`.
+    /// An `Err` rejects the label with an `ERROR`, using the returned message
+    fn check_relabel(object: &pg_sys::ObjectAddress, label: Option<&str>) -> Result<(), String>;
+}
+
+/// Register `P` as a security label provider.  Must be called from `_PG_init()`
+pub fn register_security_label_provider<P: SecurityLabelProvider>() {
+    let name = CString::new(P::NAME).expect("security label provider name contains a null byte");
+    // Postgres makes its own copy of the name
+    unsafe { pg_sys::register_label_provider(name.as_ptr(), Some(relabel_hook::<P>)) }
+}
+
+/// The label `provider` has given `object`, if any
+pub fn security_label(object: &pg_sys::ObjectAddress, provider: &str) -> Option<String> {
+    let provider =
+        CString::new(provider).expect("security label provider name contains a null byte");
+    let label = unsafe { pg_sys::GetSecurityLabel(object, provider.as_ptr()) };
+    if label.is_null() {
+        None
+    } else {
+        let owned = unsafe { CStr::from_ptr(label) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { pg_sys::pfree(label as *mut std::os::raw::c_void) };
+        Some(owned)
+    }
+}
+
+unsafe extern "C" fn relabel_hook<P: SecurityLabelProvider>(
+    object: *const pg_sys::ObjectAddress,
+    seclabel: *const c_char,
+) {
+    pg_sys::guard(|| {
+        let object = object.as_ref().expect("security label object is NULL");
+        let label = if seclabel.is_null() {
+            None
+        } else {
+            Some(
+                CStr::from_ptr(seclabel)
+                    .to_str()
+                    .expect("security label is not valid UTF8"),
+            )
+        };
+
+        if let Err(message) = P::check_relabel(object, label) {
+            ereport(
+                PgLogLevel::ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_NAME,
+                &message,
+                file!(),
+                line!(),
+                column!(),
+            );
+        }
+    })
+}
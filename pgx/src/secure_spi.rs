@@ -0,0 +1,210 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Running SPI statements from `SECURITY DEFINER` functions without trusting the caller's settings
+//!
+//! A `SECURITY DEFINER` function runs with its owner's privileges, but with the caller's
+//! `search_path`, so a caller who can create objects in a schema on it, including their own
+//! temporary schema, can shadow a table, function, or operator the function's SQL uses with one
+//! of their own, which then runs as the owner.  [`Spi::secure()`] runs SPI statements with a
+//! `search_path` of only `pg_catalog`, the schemas it's given, and `pg_temp` last, where it can't
+//! shadow anything, along with `row_security` on, and, optionally, as another role and with a
+//! statement timeout.  Everything is put back as it was once the statements are done, even if
+//! they raised an ERROR.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//! use std::time::Duration;
+//!
+//! // made `SECURITY DEFINER` by an `ALTER FUNCTION` in an `extension_sql!()`
+//! #[pg_extern]
+//! fn tenant_invoices() -> i64 {
+//!     Spi::secure()
+//!         .set_search_path(&["billing"])
+//!         // as the caller, so their row level security policies apply
+//!         .set_role(session_user_id())
+//!         .set_statement_timeout(Duration::from_secs(5))
+//!         .run(|| Spi::get_one("SELECT count(*) FROM invoices"))
+//!         .unwrap()
+//! }
+//! ```
+use crate::{pg_sys, quote_identifier, RoleSwitch, Spi};
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::time::Duration;
+
+/// Runs SPI statements with a minimal `search_path`, from [`Spi::secure()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecureSpi {
+    search_path: Vec<String>,
+    role: Option<pg_sys::Oid>,
+    statement_timeout: Option<Duration>,
+}
+
+impl Spi {
+    /// Run SPI statements with a `search_path` of only `pg_catalog` and `pg_temp`, which
+    /// [`SecureSpi::set_search_path()`] adds the extension's own schemas to, for `SECURITY DEFINER`
+    /// functions, which mustn't trust the caller's
+    pub fn secure() -> SecureSpi {
+        SecureSpi {
+            search_path: vec![],
+            role: None,
+            statement_timeout: None,
+        }
+    }
+}
+
+impl SecureSpi {
+    /// Search `schemas`, in order, after `pg_catalog` and before `pg_temp`.  They're used as is,
+    /// rather than parsed as SQL, so they're the schemas' exact names
+    pub fn set_search_path(mut self, schemas: &[&str]) -> Self {
+        self.search_path = schemas.iter().map(|schema| schema.to_string()).collect();
+        self
+    }
+
+    /// Run the statements as `role`, like a `SET LOCAL ROLE` would, but as a `SECURITY DEFINER`
+    /// function may, with a [`RoleSwitch`]
+    pub fn set_role(mut self, role: pg_sys::Oid) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    /// Cancel the statements if they're still running after `timeout`, with the same ERROR as a
+    /// `statement_timeout`.  The timeout replaces the `statement_timeout` of the statement that
+    /// called the function while they run, unless that has less time left, and that resumes with
+    /// the time it had left afterwards
+    pub fn set_statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// The `search_path` the statements are run with
+    pub fn search_path(&self) -> String {
+        let mut schemas = vec!["pg_catalog".to_string()];
+        schemas.extend(
+            self.search_path
+                .iter()
+                .map(|schema| quote_identifier(schema)),
+        );
+        schemas.push("pg_temp".to_string());
+        schemas.join(", ")
+    }
+
+    /// Run `f`, and the SPI statements in it, such as those of [`Spi::get_one()`]
+    pub fn run<R, F: FnOnce() -> R>(self, f: F) -> R {
+        // put back in the reverse order
+        let _role = self.role.map(RoleSwitch::new);
+        let _settings =
+            GucNestLevel::enter(&[("search_path", &self.search_path()), ("row_security", "on")]);
+        let _timeout = self.statement_timeout.and_then(StatementTimeout::enable);
+        f()
+    }
+}
+
+/// Settings changed until it's dropped, as the `SET` clause of a function does
+struct GucNestLevel(c_int);
+
+impl GucNestLevel {
+    fn enter(settings: &[(&str, &str)]) -> Self {
+        let nest_level = GucNestLevel(unsafe { pg_sys::NewGUCNestLevel() });
+        let context = if unsafe { pg_sys::superuser() } {
+            pg_sys::GucContext_PGC_SUSET
+        } else {
+            pg_sys::GucContext_PGC_USERSET
+        };
+        for (name, value) in settings {
+            let name = CString::new(*name).expect("setting name contains a null byte");
+            let value = CString::new(*value).expect("setting value contains a null byte");
+            unsafe {
+                pg_sys::set_config_option(
+                    name.as_ptr(),
+                    value.as_ptr(),
+                    context,
+                    pg_sys::GucSource_PGC_S_SESSION,
+                    pg_sys::GucAction_GUC_ACTION_SAVE,
+                    true,
+                    0,
+                    false,
+                );
+            }
+        }
+        nest_level
+    }
+}
+
+impl Drop for GucNestLevel {
+    fn drop(&mut self) {
+        unsafe { pg_sys::AtEOXact_GUC(true, self.0) }
+    }
+}
+
+/// The `STATEMENT_TIMEOUT` timer, armed until it's dropped, with when the timer of the statement
+/// we're in was due to fire, if it has one
+struct StatementTimeout(Option<pg_sys::TimestampTz>);
+
+impl StatementTimeout {
+    /// Arm the timer, unless the `statement_timeout` of the statement we're in fires sooner
+    fn enable(timeout: Duration) -> Option<Self> {
+        let timeout_ms = timeout.as_millis().clamp(1, c_int::MAX as u128) as c_int;
+        let statement_finish = statement_timer_finish();
+        if let Some(finish) = statement_finish {
+            let now = unsafe { pg_sys::GetCurrentTimestamp() };
+            if finish - now <= timeout_ms as i64 * 1000 {
+                return None;
+            }
+        }
+        unsafe { pg_sys::enable_timeout_after(pg_sys::TimeoutId_STATEMENT_TIMEOUT, timeout_ms) };
+        Some(StatementTimeout(statement_finish))
+    }
+}
+
+impl Drop for StatementTimeout {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::disable_timeout(pg_sys::TimeoutId_STATEMENT_TIMEOUT, false);
+            if let Some(finish) = self.0 {
+                // at the same time as before, which fires right away if that's already passed
+                pg_sys::enable_timeout_at(pg_sys::TimeoutId_STATEMENT_TIMEOUT, finish);
+            }
+        }
+    }
+}
+
+/// When the `STATEMENT_TIMEOUT` timer is due to fire, if it's armed
+#[cfg(any(feature = "pg14", feature = "pg15"))]
+fn statement_timer_finish() -> Option<pg_sys::TimestampTz> {
+    unsafe {
+        if pg_sys::get_timeout_active(pg_sys::TimeoutId_STATEMENT_TIMEOUT) {
+            Some(pg_sys::get_timeout_finish_time(
+                pg_sys::TimeoutId_STATEMENT_TIMEOUT,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// When the `STATEMENT_TIMEOUT` timer is due to fire, if it's armed.  Before Postgres 14 there's
+/// no asking, but postgres.c arms it when a statement starts if `statement_timeout` is set, and a
+/// finish time that's passed is one from a timer that was since disabled, or never armed at all,
+/// as in background workers
+#[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12", feature = "pg13"))]
+fn statement_timer_finish() -> Option<pg_sys::TimestampTz> {
+    unsafe {
+        if pg_sys::StatementTimeout <= 0 {
+            return None;
+        }
+        let finish = pg_sys::get_timeout_finish_time(pg_sys::TimeoutId_STATEMENT_TIMEOUT);
+        if finish > pg_sys::GetCurrentTimestamp() {
+            Some(finish)
+        } else {
+            None
+        }
+    }
+}
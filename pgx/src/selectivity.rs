@@ -0,0 +1,349 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Selectivity estimators for operators, written in Rust, with access to the planner's statistics
+//!
+//! Needs the `planner` feature, as it calls into `utils/selfuncs.h` directly.
+use crate::{is_a, pg_sys, FromDatum, Internal, IntoDatum, PgAttStatsSlot, PgAttributeStats};
+
+/// Clamp an estimate to between 0 and 1, like Postgres' `CLAMP_PROBABILITY()`
+pub fn clamp_probability(selectivity: f64) -> f64 {
+    selectivity.clamp(0.0, 1.0)
+}
+
+/// The arguments Postgres gives an operator's restriction selectivity estimator, which guesses
+/// what fraction of a table's rows a clause like `column OP constant` matches.
+///
+/// The estimator is a `#[pg_extern]` with exactly this signature, named by the operator's
+/// `#[restrict]` attribute:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern(stable, parallel_safe)]
+/// fn my_eq_restrict(
+///     root: Internal,
+///     operator: pg_sys::Oid,
+///     args: Internal,
+///     var_relid: i32,
+/// ) -> f64 {
+///     let estimate = unsafe { RestrictSelectivity::new(root, operator, args, var_relid) };
+///     let clause = match estimate.examine() {
+///         Some(clause) => clause,
+///         None => return pg_sys::DEFAULT_EQ_SEL,
+///     };
+///     match (clause.constant::<i32>(), clause.variable.most_common_values::<i32>()) {
+///         (Some(Some(value)), Some(mcvs)) => mcvs
+///             .into_iter()
+///             .find(|(mcv, _)| *mcv == value)
+///             .map(|(_, frequency)| frequency)
+///             .unwrap_or(pg_sys::DEFAULT_EQ_SEL),
+///         _ => pg_sys::DEFAULT_EQ_SEL,
+///     }
+/// }
+///
+/// #[pg_operator(immutable, parallel_safe)]
+/// #[opname(=~=)]
+/// #[restrict(my_eq_restrict)]
+/// fn my_eq(left: i32, right: i32) -> bool {
+///     left == right
+/// }
+/// ```
+pub struct RestrictSelectivity {
+    root: *mut pg_sys::PlannerInfo,
+    operator: pg_sys::Oid,
+    args: *mut pg_sys::List,
+    var_relid: i32,
+}
+
+impl RestrictSelectivity {
+    /// # Safety
+    ///
+    /// The arguments must be the ones Postgres passed to the estimator
+    pub unsafe fn new(
+        root: Internal,
+        operator: pg_sys::Oid,
+        args: Internal,
+        var_relid: i32,
+    ) -> Self {
+        RestrictSelectivity {
+            root: root.unwrap().expect("PlannerInfo is NULL") as *mut pg_sys::PlannerInfo,
+            operator,
+            args: args.unwrap().expect("args are NULL") as *mut pg_sys::List,
+            var_relid,
+        }
+    }
+
+    /// The operator whose clause is being estimated
+    pub fn operator(&self) -> pg_sys::Oid {
+        self.operator
+    }
+
+    pub fn root(&self) -> *mut pg_sys::PlannerInfo {
+        self.root
+    }
+
+    /// The operator's arguments, as expressions
+    pub fn args(&self) -> *mut pg_sys::List {
+        self.args
+    }
+
+    /// The relation being estimated for, or zero for whichever one the clause refers to
+    pub fn var_relid(&self) -> i32 {
+        self.var_relid
+    }
+
+    /// Split the clause into the column (or expression) on one side and whatever's on the
+    /// other, which is usually a constant.  `None` when neither side is a column of a single
+    /// relation, such as when both are
+    pub fn examine(&self) -> Option<RestrictClause> {
+        let mut variable = PgVariableStats::empty();
+        let mut other = std::ptr::null_mut();
+        let mut var_on_left = false;
+        let found = unsafe {
+            pg_sys::get_restriction_variable(
+                self.root,
+                self.args,
+                self.var_relid,
+                &mut variable.data,
+                &mut other,
+                &mut var_on_left,
+            )
+        };
+        if found {
+            Some(RestrictClause {
+                variable,
+                other,
+                var_on_left,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// A clause being estimated by a [`RestrictSelectivity`], as `variable OP other`, or the other
+/// way around
+pub struct RestrictClause {
+    pub variable: PgVariableStats,
+    /// The other side of the clause, simplified as far as the planner could
+    pub other: *mut pg_sys::Node,
+    /// Is the variable the operator's left argument?
+    pub var_on_left: bool,
+}
+
+impl RestrictClause {
+    /// The other side of the clause, as `(value, is_null, type)`, if it's a constant
+    pub fn constant_datum(&self) -> Option<(pg_sys::Datum, bool, pg_sys::Oid)> {
+        unsafe {
+            if is_a(self.other, pg_sys::NodeTag_T_Const) {
+                let constant = &*(self.other as *mut pg_sys::Const);
+                Some((
+                    constant.constvalue,
+                    constant.constisnull,
+                    constant.consttype,
+                ))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// The other side of the clause, if it's a constant of type `T`.  The inner `Option` is
+    /// `None` when the constant is `NULL`
+    pub fn constant<T: FromDatum + IntoDatum>(&self) -> Option<Option<T>> {
+        let (datum, is_null, typoid) = self.constant_datum()?;
+        if typoid != T::type_oid() {
+            return None;
+        }
+        Some(unsafe { T::from_datum(datum, is_null, typoid) })
+    }
+}
+
+/// The arguments Postgres gives an operator's join selectivity estimator, which guesses what
+/// fraction of the pairs of rows from two relations a clause like `a.column OP b.column` matches.
+///
+/// The estimator is a `#[pg_extern]` with exactly this signature, named by the operator's
+/// `#[join]` attribute:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern(stable, parallel_safe)]
+/// fn my_eq_join(
+///     root: Internal,
+///     operator: pg_sys::Oid,
+///     args: Internal,
+///     join_type: i16,
+///     sjinfo: Internal,
+/// ) -> f64 {
+///     let estimate = unsafe { JoinSelectivity::new(root, operator, args, join_type, sjinfo) };
+///     let (mut left, mut right, _) = estimate.examine();
+///     // every value on the larger side matches one on the smaller
+///     match (left.num_distinct(), right.num_distinct()) {
+///         (Some(left), Some(right)) => clamp_probability(1.0 / left.max(right)),
+///         _ => pg_sys::DEFAULT_EQ_SEL,
+///     }
+/// }
+/// ```
+pub struct JoinSelectivity {
+    root: *mut pg_sys::PlannerInfo,
+    operator: pg_sys::Oid,
+    args: *mut pg_sys::List,
+    join_type: pg_sys::JoinType,
+    sjinfo: *mut pg_sys::SpecialJoinInfo,
+}
+
+impl JoinSelectivity {
+    /// # Safety
+    ///
+    /// The arguments must be the ones Postgres passed to the estimator
+    pub unsafe fn new(
+        root: Internal,
+        operator: pg_sys::Oid,
+        args: Internal,
+        join_type: i16,
+        sjinfo: Internal,
+    ) -> Self {
+        JoinSelectivity {
+            root: root.unwrap().expect("PlannerInfo is NULL") as *mut pg_sys::PlannerInfo,
+            operator,
+            args: args.unwrap().expect("args are NULL") as *mut pg_sys::List,
+            join_type: join_type as pg_sys::JoinType,
+            sjinfo: sjinfo.unwrap().unwrap_or(0) as *mut pg_sys::SpecialJoinInfo,
+        }
+    }
+
+    /// The operator whose clause is being estimated
+    pub fn operator(&self) -> pg_sys::Oid {
+        self.operator
+    }
+
+    pub fn root(&self) -> *mut pg_sys::PlannerInfo {
+        self.root
+    }
+
+    /// The operator's arguments, as expressions
+    pub fn args(&self) -> *mut pg_sys::List {
+        self.args
+    }
+
+    pub fn join_type(&self) -> pg_sys::JoinType {
+        self.join_type
+    }
+
+    pub fn sjinfo(&self) -> *mut pg_sys::SpecialJoinInfo {
+        self.sjinfo
+    }
+
+    /// The statistics for the operator's left and right arguments, and whether they're the other
+    /// way around from the join's own left and right sides
+    pub fn examine(&self) -> (PgVariableStats, PgVariableStats, bool) {
+        let mut left = PgVariableStats::empty();
+        let mut right = PgVariableStats::empty();
+        let mut reversed = false;
+        unsafe {
+            pg_sys::get_join_variables(
+                self.root,
+                self.args,
+                self.sjinfo,
+                &mut left.data,
+                &mut right.data,
+                &mut reversed,
+            );
+        }
+        (left, right, reversed)
+    }
+}
+
+/// What the planner knows about a column, or an expression with statistics of its own, from
+/// `pg_statistic`.  Everything is `None` when there are no statistics, such as before the table's
+/// first `ANALYZE`
+pub struct PgVariableStats {
+    data: pg_sys::VariableStatData,
+}
+
+impl PgVariableStats {
+    fn empty() -> Self {
+        PgVariableStats {
+            data: Default::default(),
+        }
+    }
+
+    pub fn as_ptr(&mut self) -> *mut pg_sys::VariableStatData {
+        &mut self.data
+    }
+
+    /// The expression's type
+    pub fn type_oid(&self) -> pg_sys::Oid {
+        self.data.vartype
+    }
+
+    /// Are there statistics to go on?
+    pub fn has_stats(&self) -> bool {
+        !self.data.statsTuple.is_null()
+    }
+
+    /// Is every value known to be unique, such as from a unique index?
+    pub fn is_unique(&self) -> bool {
+        self.data.isunique
+    }
+
+    /// The statistics themselves, from `pg_statistic`
+    pub fn stats(&self) -> Option<PgAttributeStats<'_>> {
+        unsafe { PgAttributeStats::from_tuple(self.data.statsTuple) }
+    }
+
+    /// The fraction of values which are `NULL`
+    pub fn null_fraction(&self) -> Option<f64> {
+        Some(self.stats()?.null_fraction())
+    }
+
+    /// How many distinct values there are, unless the planner would only be guessing
+    pub fn num_distinct(&mut self) -> Option<f64> {
+        let mut is_default = false;
+        let num_distinct =
+            unsafe { pg_sys::get_variable_numdistinct(&mut self.data, &mut is_default) };
+        if is_default {
+            None
+        } else {
+            Some(num_distinct)
+        }
+    }
+
+    /// The most common values, and the fraction of all rows each is found in, if they're `T`s
+    pub fn most_common_values<T: FromDatum + IntoDatum>(&self) -> Option<Vec<(T, f64)>> {
+        self.stats()?.most_common_values()
+    }
+
+    /// The bounds of a histogram of the values, not counting the most common ones, if they're
+    /// `T`s
+    pub fn histogram<T: FromDatum + IntoDatum>(&self) -> Option<Vec<T>> {
+        self.stats()?.histogram()
+    }
+
+    /// Any kind of statistics, as for [`PgAttributeStats::stats_slot()`]
+    pub fn stats_slot(
+        &self,
+        kind: i32,
+        operator: pg_sys::Oid,
+        flags: i32,
+    ) -> Option<PgAttStatsSlot> {
+        self.stats()?.stats_slot(kind, operator, flags)
+    }
+}
+
+impl Drop for PgVariableStats {
+    fn drop(&mut self) {
+        // `ReleaseVariableStats()`
+        if let (false, Some(freefunc)) = (self.data.statsTuple.is_null(), self.data.freefunc) {
+            unsafe { freefunc(self.data.statsTuple) }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Creating and using sequences from Rust, without building SQL strings
+use crate::pg_sys::pgx_GETSTRUCT;
+use crate::{direct_function_call, pg_sys, IntoDatum, PgBox, PgMemoryContexts, RegClass, RegType};
+
+/// A sequence, used through the same functions that implement SQL's `nextval()`, `currval()`, and
+/// `setval()`, and so with the same permission checks
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern]
+/// fn next_ticket() -> i64 {
+///     let sequence = PgSequence::from_name("tickets_seq").unwrap_or_else(|| {
+///         PgSequenceBuilder::new("tickets_seq")
+///             .set_start(1000)
+///             .create()
+///     });
+///     sequence.nextval()
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PgSequence(RegClass);
+
+/// The options a sequence was created with, from the `pg_sequence` catalog
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PgSequenceParameters {
+    pub data_type: RegType,
+    pub start: i64,
+    pub increment: i64,
+    pub min_value: i64,
+    pub max_value: i64,
+    pub cache: i64,
+    pub cycle: bool,
+}
+
+impl PgSequence {
+    /// Use `sequence`, which must be a sequence
+    pub fn open(sequence: RegClass) -> Self {
+        let relkind = unsafe { pg_sys::get_rel_relkind(sequence.oid()) } as u8;
+        if relkind != pg_sys::RELKIND_SEQUENCE {
+            panic!("\"{}\" is not a sequence", sequence.name());
+        }
+        PgSequence(sequence)
+    }
+
+    /// Use the sequence named `name`, which may be schema-qualified, if it exists.  See
+    /// [`RegClass::from_name()`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        RegClass::from_name(name).map(PgSequence::open)
+    }
+
+    pub fn regclass(&self) -> RegClass {
+        self.0
+    }
+
+    pub fn oid(&self) -> pg_sys::Oid {
+        self.0.oid()
+    }
+
+    /// Advance the sequence and return its new value
+    pub fn nextval(&self) -> i64 {
+        unsafe { direct_function_call::<i64>(pg_sys::nextval_oid, vec![self.oid().into_datum()]) }
+            .expect("nextval() returned NULL")
+    }
+
+    /// The value `nextval()` most recently returned for this sequence in this session.  Raises an
+    /// ERROR if it hasn't been called yet
+    pub fn currval(&self) -> i64 {
+        unsafe { direct_function_call::<i64>(pg_sys::currval_oid, vec![self.oid().into_datum()]) }
+            .expect("currval() returned NULL")
+    }
+
+    /// Set the sequence's current value.  If `is_called` is false, the next `nextval()` returns
+    /// `value` itself rather than advancing past it
+    pub fn setval(&self, value: i64, is_called: bool) {
+        unsafe {
+            direct_function_call::<i64>(
+                pg_sys::setval3_oid,
+                vec![
+                    self.oid().into_datum(),
+                    value.into_datum(),
+                    is_called.into_datum(),
+                ],
+            )
+        };
+    }
+
+    /// The options the sequence was created, or last altered, with
+    pub fn parameters(&self) -> PgSequenceParameters {
+        unsafe {
+            let tuple = pg_sys::SearchSysCache(
+                pg_sys::SysCacheIdentifier_SEQRELID as i32,
+                self.oid() as pg_sys::Datum,
+                0,
+                0,
+                0,
+            );
+            if tuple.is_null() {
+                panic!("cache lookup failed for sequence {}", self.oid());
+            }
+            let form = &*(pgx_GETSTRUCT(tuple) as pg_sys::Form_pg_sequence);
+            let parameters = PgSequenceParameters {
+                data_type: RegType(form.seqtypid),
+                start: form.seqstart,
+                increment: form.seqincrement,
+                min_value: form.seqmin,
+                max_value: form.seqmax,
+                cache: form.seqcache,
+                cycle: form.seqcycle,
+            };
+            pg_sys::ReleaseSysCache(tuple);
+            parameters
+        }
+    }
+}
+
+/// Creates a sequence, for extensions that hand out their own ids.  Options that aren't set get
+/// Postgres' defaults
+pub struct PgSequenceBuilder {
+    schema: Option<String>,
+    name: String,
+    data_type: Option<RegType>,
+    start: Option<i64>,
+    increment: Option<i64>,
+    min_value: Option<i64>,
+    max_value: Option<i64>,
+    cache: Option<i64>,
+    cycle: bool,
+}
+
+impl PgSequenceBuilder {
+    /// Create a sequence named `name`, which is used as is, rather than parsed as SQL
+    pub fn new(name: &str) -> Self {
+        PgSequenceBuilder {
+            schema: None,
+            name: name.to_string(),
+            data_type: None,
+            start: None,
+            increment: None,
+            min_value: None,
+            max_value: None,
+            cache: None,
+            cycle: false,
+        }
+    }
+
+    /// Create the sequence in `schema`, rather than the first schema of the `search_path`
+    pub fn set_schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    /// `smallint`, `integer`, or the default, `bigint`
+    pub fn set_data_type(mut self, data_type: RegType) -> Self {
+        self.data_type = Some(data_type);
+        self
+    }
+
+    pub fn set_start(mut self, start: i64) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn set_increment(mut self, increment: i64) -> Self {
+        self.increment = Some(increment);
+        self
+    }
+
+    pub fn set_min_value(mut self, min_value: i64) -> Self {
+        self.min_value = Some(min_value);
+        self
+    }
+
+    pub fn set_max_value(mut self, max_value: i64) -> Self {
+        self.max_value = Some(max_value);
+        self
+    }
+
+    /// How many values each session allocates at once
+    pub fn set_cache(mut self, cache: i64) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Wrap around at the maximum (or minimum, when counting down), rather than raising an ERROR
+    pub fn set_cycle(mut self, cycle: bool) -> Self {
+        self.cycle = cycle;
+        self
+    }
+
+    /// Create the sequence, raising an ERROR if it already exists, the same way `CREATE
+    /// SEQUENCE` does, including checking for `CREATE` on the schema
+    pub fn create(self) -> PgSequence {
+        let pstrdup = |s: &str| PgMemoryContexts::CurrentMemoryContext.pstrdup(s);
+        let mut options = std::ptr::null_mut();
+        let mut add_option = |name: &str, arg: *mut pg_sys::Node| unsafe {
+            let option = pg_sys::makeDefElem(pstrdup(name), arg, -1);
+            options = pg_sys::lappend(options, option as *mut std::os::raw::c_void);
+        };
+
+        if let Some(data_type) = self.data_type {
+            add_option("as", unsafe {
+                pg_sys::makeTypeNameFromOid(data_type.oid(), -1) as *mut pg_sys::Node
+            });
+        }
+        let values = [
+            ("increment", self.increment),
+            ("minvalue", self.min_value),
+            ("maxvalue", self.max_value),
+            ("start", self.start),
+            ("cache", self.cache),
+        ];
+        for (name, value) in values.iter() {
+            if let Some(value) = value {
+                // as the grammar does for numbers that don't fit in an `int`, which
+                // `defGetInt64()` reads back with `int8in()`
+                let value = unsafe { pg_sys::makeFloat(pstrdup(&value.to_string())) };
+                add_option(name, value as *mut pg_sys::Node);
+            }
+        }
+        if self.cycle {
+            add_option("cycle", unsafe {
+                pg_sys::makeInteger(1) as *mut pg_sys::Node
+            });
+        }
+
+        let mut stmt = PgBox::<pg_sys::CreateSeqStmt>::alloc_node(pg_sys::NodeTag_T_CreateSeqStmt);
+        stmt.sequence = unsafe {
+            pg_sys::makeRangeVar(
+                self.schema.as_deref().map_or(std::ptr::null_mut(), pstrdup),
+                pstrdup(&self.name),
+                -1,
+            )
+        };
+        stmt.options = options;
+        stmt.ownerId = pg_sys::InvalidOid;
+
+        unsafe {
+            let pstate = pg_sys::make_parsestate(std::ptr::null_mut());
+            let address = pg_sys::DefineSequence(pstate, stmt.into_pg());
+            pg_sys::free_parsestate(pstate);
+            // so the new sequence is visible
+            pg_sys::CommandCounterIncrement();
+            PgSequence(RegClass(address.objectId))
+        }
+    }
+}
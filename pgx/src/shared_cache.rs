@@ -0,0 +1,344 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A fixed-size cache in Postgres Shared Memory, shared by every backend
+use crate::{pg_sys, register_relcache_callback, register_syscache_callback};
+use crate::{with_addin_shmem_init_lock, PgSharedMemoryInitialization};
+use hash32::{Hash, Hasher};
+use heapless::FnvIndexMap;
+use std::ffi::CString;
+use std::ops::{Deref, DerefMut};
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// How many partitions, each with its own `LWLock`, a [`PgSharedCache`] is split into
+pub const SHARED_CACHE_PARTITIONS: usize = 16;
+
+type Partition<K, V, const N: usize> = FnvIndexMap<K, V, N>;
+
+/// A cache of `Copy` values, living in shared memory so every backend sees the same entries.
+///
+/// The cache is split into [`SHARED_CACHE_PARTITIONS`] partitions by the hash of each key, and
+/// each partition has its own `LWLock`, so backends working with different keys rarely wait on
+/// each other.  Every partition holds up to `N` entries (which must be a power of two), and
+/// inserting into a full partition evicts one of its other entries.
+///
+/// Like any other shared memory type, it must be a `static` passed to `pg_shmem_init!()` during
+/// `_PG_init()`, and the extension must be in `shared_preload_libraries`.
+///
+/// Data derived from the catalogs goes stale when the catalogs change, which
+/// [`PgSharedCache::invalidate_on_relcache()`] and [`PgSharedCache::invalidate_on_syscache()`]
+/// take care of.  Every backend runs those callbacks, so an entry put back by a backend that
+/// hadn't yet seen an invalidation is removed once it does.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// // the number of live tuples of each relation we've looked at, up to 16 * 64 of them
+/// static RELATION_SIZES: PgSharedCache<pg_sys::Oid, f32, 64> = PgSharedCache::new("relation_sizes");
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(RELATION_SIZES);
+///     RELATION_SIZES.invalidate_on_relcache(|relid, _, invalidated| match invalidated {
+///         Some(invalidated) => *relid == invalidated,
+///         None => true,
+///     });
+/// }
+///
+/// fn relation_size(relid: pg_sys::Oid) -> f32 {
+///     RELATION_SIZES.get_or_insert_with(relid, || unsafe {
+///         let relation = PgRelation::open(relid);
+///         relation.reltuples().unwrap_or_default()
+///     })
+/// }
+/// ```
+pub struct PgSharedCache<K, V, const N: usize> {
+    name: &'static str,
+    partitions: AtomicPtr<Partition<K, V, N>>,
+    locks: AtomicPtr<pg_sys::LWLockPadded>,
+}
+
+unsafe impl<K, V, const N: usize> Send for PgSharedCache<K, V, N> {}
+unsafe impl<K, V, const N: usize> Sync for PgSharedCache<K, V, N> {}
+
+impl<K, V, const N: usize> PgSharedCache<K, V, N>
+where
+    K: Eq + Hash + Copy + 'static,
+    V: Copy + 'static,
+{
+    /// Create an empty cache, as a `static`.  `name` identifies its shared memory and names its
+    /// `LWLock` tranche, so it must be unique and at most 47 bytes long
+    pub const fn new(name: &'static str) -> Self {
+        PgSharedCache {
+            name,
+            partitions: AtomicPtr::new(std::ptr::null_mut()),
+            locks: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// The name given to [`PgSharedCache::new()`]
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The most entries the cache can hold
+    pub fn capacity(&self) -> usize {
+        SHARED_CACHE_PARTITIONS * N
+    }
+
+    /// A copy of the value cached for `key`, if any
+    pub fn get(&self, key: &K) -> Option<V> {
+        let partition = self.partition_of(key);
+        let entries = self.share(partition);
+        entries.get(key).copied()
+    }
+
+    /// The value cached for `key`, or the one `f` computes, which is then cached.
+    ///
+    /// `f` runs without any of the cache's locks held, so it can do catalog lookups and SPI, but
+    /// two backends might both compute a missing value
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&self, key: K, f: F) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let value = f();
+        self.insert(key, value);
+        value
+    }
+
+    /// Cache `value` for `key`, returning the value it replaced.  If `key`'s partition is full,
+    /// another of its entries is evicted first
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let partition = self.partition_of(&key);
+        let mut entries = self.exclusive(partition);
+        match entries.insert(key, value) {
+            Ok(replaced) => replaced,
+            Err((key, value)) => {
+                // pick a victim based on the new key, rather than always the same slot
+                let victim = *entries
+                    .keys()
+                    .nth(hash(&key) as usize % entries.len())
+                    .expect("a full partition has no entries");
+                entries.swap_remove(&victim);
+                assert!(
+                    entries.insert(key, value).is_ok(),
+                    "no room in the partition after evicting an entry"
+                );
+                None
+            }
+        }
+    }
+
+    /// Remove `key` from the cache, returning its value
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let partition = self.partition_of(key);
+        let mut entries = self.exclusive(partition);
+        entries.swap_remove(key)
+    }
+
+    /// Remove every entry for which `keep` returns false
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&self, mut keep: F) {
+        for partition in 0..SHARED_CACHE_PARTITIONS {
+            let mut entries = self.exclusive(partition);
+            let removed = entries
+                .iter()
+                .filter(|(key, value)| !keep(key, value))
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>();
+            for key in removed {
+                entries.swap_remove(&key);
+            }
+        }
+    }
+
+    /// Remove every entry
+    pub fn clear(&self) {
+        for partition in 0..SHARED_CACHE_PARTITIONS {
+            let mut entries = self.exclusive(partition);
+            entries.clear();
+        }
+    }
+
+    /// How many entries the cache holds
+    pub fn len(&self) -> usize {
+        (0..SHARED_CACHE_PARTITIONS)
+            .map(|partition| self.share(partition).len())
+            .sum()
+    }
+
+    /// Does the cache hold no entries?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whenever a relcache invalidation is processed, remove the entries for which `invalidated`
+    /// returns true, given the invalidated relation's oid, or `None` if the entire relcache was
+    /// invalidated.  See [`register_relcache_callback()`](crate::register_relcache_callback)
+    ///
+    /// Call this from `_PG_init()`, after `pg_shmem_init!()`
+    pub fn invalidate_on_relcache<F>(&'static self, invalidated: F)
+    where
+        F: Fn(&K, &V, Option<pg_sys::Oid>) -> bool + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        register_relcache_callback(move |relid| {
+            self.retain(|key, value| !invalidated(key, value, relid))
+        });
+    }
+
+    /// Whenever an invalidation of the syscache `cache_id` is processed, remove the entries for
+    /// which `invalidated` returns true, given the hash value of the invalidated syscache entry's
+    /// keys, or `None` if the entire syscache was invalidated.  See
+    /// [`register_syscache_callback()`](crate::register_syscache_callback)
+    ///
+    /// Call this from `_PG_init()`, after `pg_shmem_init!()`
+    pub fn invalidate_on_syscache<F>(
+        &'static self,
+        cache_id: pg_sys::SysCacheIdentifier,
+        invalidated: F,
+    ) where
+        F: Fn(&K, &V, Option<u32>) -> bool + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        register_syscache_callback(cache_id, move |hashvalue| {
+            self.retain(|key, value| !invalidated(key, value, hashvalue))
+        });
+    }
+
+    fn partition_of(&self, key: &K) -> usize {
+        // the partition's own map hashes with the low bits, so use the high ones
+        (hash(key) >> 28) as usize % SHARED_CACHE_PARTITIONS
+    }
+
+    fn share(&self, partition: usize) -> PartitionShareGuard<'_, K, V, N> {
+        let lock = self.lock(partition, pg_sys::LWLockMode_LW_SHARED);
+        PartitionShareGuard {
+            data: unsafe { &*self.entries(partition) },
+            _lock: lock,
+        }
+    }
+
+    fn exclusive(&self, partition: usize) -> PartitionExclusiveGuard<'_, K, V, N> {
+        let lock = self.lock(partition, pg_sys::LWLockMode_LW_EXCLUSIVE);
+        PartitionExclusiveGuard {
+            data: unsafe { &mut *self.entries(partition) },
+            _lock: lock,
+        }
+    }
+
+    fn lock(&self, partition: usize, mode: pg_sys::LWLockMode) -> PartitionLock {
+        let locks = self.locks.load(Ordering::SeqCst);
+        assert!(
+            !locks.is_null(),
+            "PgSharedCache `{}` is not in shared memory.  Was it passed to `pg_shmem_init!()`?",
+            self.name
+        );
+        unsafe {
+            let lock = &mut (*locks.add(partition)).lock as *mut pg_sys::LWLock;
+            pg_sys::LWLockAcquire(lock, mode);
+            PartitionLock(lock)
+        }
+    }
+
+    fn entries(&self, partition: usize) -> *mut Partition<K, V, N> {
+        unsafe { self.partitions.load(Ordering::SeqCst).add(partition) }
+    }
+}
+
+impl<K, V, const N: usize> PgSharedMemoryInitialization for PgSharedCache<K, V, N>
+where
+    K: Eq + Hash + Copy + 'static,
+    V: Copy + 'static,
+{
+    fn pg_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.name).expect("CString::new() failed");
+            pg_sys::RequestAddinShmemSpace(
+                std::mem::size_of::<Partition<K, V, N>>() * SHARED_CACHE_PARTITIONS,
+            );
+            pg_sys::RequestNamedLWLockTranche(name.as_ptr(), SHARED_CACHE_PARTITIONS as i32);
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.name).expect("CString::new() failed");
+
+            let (partitions, locks) = with_addin_shmem_init_lock(|| {
+                let mut found = false;
+                let partitions = pg_sys::ShmemInitStruct(
+                    name.as_ptr(),
+                    std::mem::size_of::<Partition<K, V, N>>() * SHARED_CACHE_PARTITIONS,
+                    &mut found,
+                ) as *mut Partition<K, V, N>;
+                if !found {
+                    for partition in 0..SHARED_CACHE_PARTITIONS {
+                        std::ptr::write(partitions.add(partition), FnvIndexMap::new());
+                    }
+                }
+                (partitions, pg_sys::GetNamedLWLockTranche(name.as_ptr()))
+            });
+
+            self.partitions.store(partitions, Ordering::SeqCst);
+            self.locks.store(locks, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Releases a partition's `LWLock` on drop
+struct PartitionLock(*mut pg_sys::LWLock);
+
+impl Drop for PartitionLock {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::LWLockRelease(self.0);
+        }
+    }
+}
+
+/// A partition, with its `LWLock` held in shared mode
+struct PartitionShareGuard<'a, K, V, const N: usize> {
+    data: &'a Partition<K, V, N>,
+    _lock: PartitionLock,
+}
+
+impl<K, V, const N: usize> Deref for PartitionShareGuard<'_, K, V, N> {
+    type Target = Partition<K, V, N>;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+/// A partition, with its `LWLock` held in exclusive mode
+struct PartitionExclusiveGuard<'a, K, V, const N: usize> {
+    data: &'a mut Partition<K, V, N>,
+    _lock: PartitionLock,
+}
+
+impl<K, V, const N: usize> Deref for PartitionExclusiveGuard<'_, K, V, N> {
+    type Target = Partition<K, V, N>;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<K, V, const N: usize> DerefMut for PartitionExclusiveGuard<'_, K, V, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.data
+    }
+}
+
+fn hash<K: Hash>(key: &K) -> u32 {
+    let mut hasher = hash32::FnvHasher::default();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
@@ -28,6 +28,11 @@ pub unsafe trait PGXSharedMemory {}
 /// > Extensions that use shared memory **must** be loaded via `postgresql.conf`'s
 /// `shared_preload_libraries` configuration setting.  
 ///
+/// Starting with Postgres 15, shared memory may only be requested from within the
+/// `shmem_request_hook`.  `pg_shmem_init!()` takes care of that itself, making the request right
+/// away on older versions and from that hook on newer ones, so the same `_PG_init()` works
+/// across every supported version.
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -48,7 +53,7 @@ pub unsafe trait PGXSharedMemory {}
 #[macro_export]
 macro_rules! pg_shmem_init {
     ($thing:expr) => {
-        $thing.pg_init();
+        $crate::__pgx_private_shmem_request!($thing);
 
         unsafe {
             static mut PREV_SHMEM_STARTUP_HOOK: Option<unsafe extern "C" fn()> = None;
@@ -67,10 +72,48 @@ macro_rules! pg_shmem_init {
         }
     };
 }
+
+/// Reserves the shared memory for `$thing` on behalf of `pg_shmem_init!()`.  Prior to Postgres 15
+/// that happens directly in `_PG_init()`
+#[cfg(not(feature = "pg15"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pgx_private_shmem_request {
+    ($thing:expr) => {
+        $thing.pg_init();
+    };
+}
+
+/// Reserves the shared memory for `$thing` on behalf of `pg_shmem_init!()`.  Postgres 15 only
+/// allows that from the `shmem_request_hook`
+#[cfg(feature = "pg15")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pgx_private_shmem_request {
+    ($thing:expr) => {
+        unsafe {
+            static mut PREV_SHMEM_REQUEST_HOOK: Option<unsafe extern "C" fn()> = None;
+            PREV_SHMEM_REQUEST_HOOK = pg_sys::shmem_request_hook;
+            pg_sys::shmem_request_hook = Some(__pgx_private_shmem_request_hook);
+
+            #[pg_guard]
+            extern "C" fn __pgx_private_shmem_request_hook() {
+                unsafe {
+                    if let Some(i) = PREV_SHMEM_REQUEST_HOOK {
+                        i();
+                    }
+                }
+                $thing.pg_init();
+            }
+        }
+    };
+}
+
 /// A trait that types can implement to provide their own Postgres Shared Memory initialization process
 pub trait PgSharedMemoryInitialization {
     /// Automatically called when the an extension is loaded.  If using the `pg_shmem_init!()` macro
-    /// in `_PG_init()`, this is called automatically
+    /// in `_PG_init()`, this is called automatically (from the `shmem_request_hook` on Postgres 15
+    /// and later)
     fn pg_init(&'static self);
 
     /// Automatically called by the `pg_shmem_init!()` macro, when Postgres is initializing its
@@ -104,11 +147,35 @@ where
     }
 }
 
+/// Run `f` holding Postgres' `AddinShmemInitLock`, as
+/// [`PgSharedMemoryInitialization::shmem_init()`] implementations must while they find or create
+/// their shared memory, such as with `pg_sys::ShmemInitStruct()`.  The lock is released when `f`
+/// returns or panics
+pub fn with_addin_shmem_init_lock<R, F: FnOnce() -> R>(f: F) -> R {
+    /// Releases the lock on drop
+    struct AddinShmemInitLock(*mut pg_sys::LWLock);
+
+    impl Drop for AddinShmemInitLock {
+        fn drop(&mut self) {
+            unsafe { pg_sys::LWLockRelease(self.0) }
+        }
+    }
+
+    let _lock = unsafe {
+        // `AddinShmemInitLock` is `&MainLWLockArray[21].lock`, from `storage/lwlocknames.h`
+        let lock: *mut pg_sys::LWLock = &mut (*pg_sys::MainLWLockArray.add(21)).lock;
+        pg_sys::LWLockAcquire(lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
+        AddinShmemInitLock(lock)
+    };
+    f()
+}
+
 /// This struct contains methods to drive creation of types in shared memory
 pub struct PgSharedMem {}
 
 impl PgSharedMem {
-    /// Must be run from PG_init, use for types which are guarded by a LWLock
+    /// Must be run from PG_init (or the `shmem_request_hook` on Postgres 15 and later), use for
+    /// types which are guarded by a LWLock
     pub fn pg_init_locked<T: Default + PGXSharedMemory>(lock: &PgLwLock<T>) {
         unsafe {
             let lock = std::ffi::CString::new(lock.get_name()).expect("CString::new failed");
@@ -117,7 +184,7 @@ impl PgSharedMem {
         }
     }
 
-    /// Must be run from _PG_init for atomics
+    /// Must be run from _PG_init (or the `shmem_request_hook` on Postgres 15 and later) for atomics
     pub fn pg_init_atomic<T: atomic_traits::Atomic + Default>(_atomic: &PgAtomic<T>) {
         unsafe {
             pg_sys::RequestAddinShmemSpace(std::mem::size_of::<T>());
@@ -127,11 +194,8 @@ impl PgSharedMem {
     /// Must be run from the shared memory init hook, use for types which are guarded by a `LWLock`
     pub fn shmem_init_locked<T: Default + PGXSharedMemory>(lock: &PgLwLock<T>) {
         let mut found = false;
-        unsafe {
+        with_addin_shmem_init_lock(|| unsafe {
             let shm_name = std::ffi::CString::new(lock.get_name()).expect("CString::new failed");
-            let addin_shmem_init_lock: *mut pg_sys::LWLock =
-                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
-            pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
 
             let fv_shmem =
                 pg_sys::ShmemInitStruct(shm_name.into_raw(), std::mem::size_of::<T>(), &mut found)
@@ -140,21 +204,16 @@ impl PgSharedMem {
             std::ptr::write(fv_shmem, <T>::default());
 
             lock.attach(fv_shmem);
-            pg_sys::LWLockRelease(addin_shmem_init_lock);
-        }
+        })
     }
 
     /// Must be run from the shared memory init hook, use for rust atomics behind `PgAtomic`
     pub fn shmem_init_atomic<T: atomic_traits::Atomic + Default>(atomic: &PgAtomic<T>) {
-        unsafe {
+        with_addin_shmem_init_lock(|| unsafe {
             let shm_name =
                 std::ffi::CString::new(Uuid::new_v4().to_string()).expect("CString::new() failed");
 
-            let addin_shmem_init_lock: *mut pg_sys::LWLock =
-                &mut (*pg_sys::MainLWLockArray.add(21)).lock;
-
             let mut found = false;
-            pg_sys::LWLockAcquire(addin_shmem_init_lock, pg_sys::LWLockMode_LW_EXCLUSIVE);
             let fv_shmem =
                 pg_sys::ShmemInitStruct(shm_name.into_raw(), std::mem::size_of::<T>(), &mut found)
                     as *mut T;
@@ -162,8 +221,7 @@ impl PgSharedMem {
             atomic.attach(fv_shmem);
             let atomic = T::default();
             std::ptr::copy(&atomic, fv_shmem, 1);
-            pg_sys::LWLockRelease(addin_shmem_init_lock);
-        }
+        })
     }
 }
 
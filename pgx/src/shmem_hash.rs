@@ -0,0 +1,317 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! A fixed-size hash table in Postgres Shared Memory, built on Postgres' own `dynahash`
+use crate::dynahash::{search, set_value, Entry};
+use crate::{pg_sys, with_addin_shmem_init_lock, PgHashTableIter, PgSharedMemoryInitialization};
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// A hash table of `Copy` keys and values in shared memory, made with `ShmemInitHash()` just like
+/// the ones Postgres itself keeps there, and shared by every backend.
+///
+/// It holds up to `max_entries` entries, and it's guarded by a single `LWLock` of its own:
+/// [`PgShmemHash::share()`] gives read access to the table and [`PgShmemHash::exclusive()`] gives
+/// write access, for as long as the guard they return is alive.  Holding the lock across several
+/// operations makes them atomic, like a look up followed by an insert.
+///
+/// Keys are hashed and compared by their bytes, so they shouldn't have padding, which might not
+/// be the same between two otherwise equal keys.
+///
+/// Like any other shared memory type, it must be a `static` passed to `pg_shmem_init!()` during
+/// `_PG_init()`, and the extension must be in `shared_preload_libraries`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// // how many times each user has called `count_call()`
+/// static CALLS: PgShmemHash<pg_sys::Oid, i64> = PgShmemHash::new("calls_per_user", 1024);
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(CALLS);
+/// }
+///
+/// #[pg_extern]
+/// fn count_call() -> i64 {
+///     let user = unsafe { pg_sys::GetUserId() };
+///     let mut calls = CALLS.exclusive();
+///     let count = calls.get(&user).unwrap_or(0) + 1;
+///     calls.insert(user, count).expect("too many users");
+///     count
+/// }
+/// ```
+pub struct PgShmemHash<K, V> {
+    name: &'static str,
+    max_entries: usize,
+    htab: AtomicPtr<pg_sys::HTAB>,
+    lock: AtomicPtr<pg_sys::LWLock>,
+    _marker: PhantomData<(K, V)>,
+}
+
+unsafe impl<K, V> Send for PgShmemHash<K, V> {}
+unsafe impl<K, V> Sync for PgShmemHash<K, V> {}
+
+impl<K, V> PgShmemHash<K, V>
+where
+    K: Copy + 'static,
+    V: Copy + 'static,
+{
+    /// Create an empty table, as a `static`.  `name` identifies its shared memory and names its
+    /// `LWLock` tranche, so it must be unique and at most 47 bytes long
+    pub const fn new(name: &'static str, max_entries: usize) -> Self {
+        PgShmemHash {
+            name,
+            max_entries,
+            htab: AtomicPtr::new(std::ptr::null_mut()),
+            lock: AtomicPtr::new(std::ptr::null_mut()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// The name given to [`PgShmemHash::new()`]
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The most entries the table can hold
+    pub fn capacity(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Lock the table for reading, until the returned guard is dropped
+    pub fn share(&self) -> PgShmemHashShareGuard<K, V> {
+        PgShmemHashShareGuard {
+            table: self.locked(pg_sys::LWLockMode_LW_SHARED),
+        }
+    }
+
+    /// Lock the table for reading and writing, until the returned guard is dropped
+    pub fn exclusive(&self) -> PgShmemHashExclusiveGuard<K, V> {
+        PgShmemHashExclusiveGuard {
+            table: self.locked(pg_sys::LWLockMode_LW_EXCLUSIVE),
+        }
+    }
+
+    /// The table's `HTAB`, for going through `dynahash` directly.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold the table's lock, such as through one of its guards, for as long as
+    /// it uses the table, and must only write to it while holding the lock exclusively
+    pub unsafe fn as_ptr(&self) -> *mut pg_sys::HTAB {
+        self.htab()
+    }
+
+    fn htab(&self) -> *mut pg_sys::HTAB {
+        let htab = self.htab.load(Ordering::SeqCst);
+        assert!(
+            !htab.is_null(),
+            "PgShmemHash `{}` is not in shared memory.  Was it passed to `pg_shmem_init!()`?",
+            self.name
+        );
+        htab
+    }
+
+    fn locked(&self, mode: pg_sys::LWLockMode) -> LockedTable<K, V> {
+        let htab = self.htab();
+        let lock = self.lock.load(Ordering::SeqCst);
+        unsafe {
+            pg_sys::LWLockAcquire(lock, mode);
+        }
+        LockedTable {
+            htab,
+            lock,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> PgSharedMemoryInitialization for PgShmemHash<K, V>
+where
+    K: Copy + 'static,
+    V: Copy + 'static,
+{
+    fn pg_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.name).expect("CString::new() failed");
+            pg_sys::RequestAddinShmemSpace(pg_sys::hash_estimate_size(
+                self.max_entries as _,
+                Entry::<K, V>::size(),
+            ));
+            pg_sys::RequestNamedLWLockTranche(name.as_ptr(), 1);
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.name).expect("CString::new() failed");
+
+            let mut info = pg_sys::HASHCTL {
+                keysize: std::mem::size_of::<K>(),
+                entrysize: Entry::<K, V>::size(),
+                ..Default::default()
+            };
+
+            let (htab, lock) = with_addin_shmem_init_lock(|| {
+                let htab = pg_sys::ShmemInitHash(
+                    name.as_ptr(),
+                    self.max_entries as _,
+                    self.max_entries as _,
+                    &mut info,
+                    (pg_sys::HASH_ELEM | pg_sys::HASH_BLOBS) as i32,
+                );
+                let lock = &mut (*pg_sys::GetNamedLWLockTranche(name.as_ptr())).lock;
+                (htab, lock as *mut pg_sys::LWLock)
+            });
+
+            self.htab.store(htab, Ordering::SeqCst);
+            self.lock.store(lock, Ordering::SeqCst);
+        }
+    }
+}
+
+/// The table while its lock is held, which is released on drop
+struct LockedTable<K, V> {
+    htab: *mut pg_sys::HTAB,
+    lock: *mut pg_sys::LWLock,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K: Copy, V: Copy> LockedTable<K, V> {
+    fn search(&self, key: &K, action: pg_sys::HASHACTION) -> (*mut Entry<K, V>, bool) {
+        unsafe { search(self.htab, key, action) }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        let (entry, _) = self.search(key, pg_sys::HASHACTION_HASH_FIND);
+        unsafe { entry.as_ref() }.map(|entry| entry.value)
+    }
+
+    fn len(&self) -> usize {
+        unsafe { pg_sys::hash_get_num_entries(self.htab) as usize }
+    }
+
+    fn iter(&self) -> PgHashTableIter<'_, K, V> {
+        unsafe { PgHashTableIter::new(self.htab) }
+    }
+}
+
+impl<K, V> Drop for LockedTable<K, V> {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::LWLockRelease(self.lock);
+        }
+    }
+}
+
+/// Read access to a [`PgShmemHash`], while holding its lock in shared mode
+pub struct PgShmemHashShareGuard<K, V> {
+    table: LockedTable<K, V>,
+}
+
+impl<K: Copy, V: Copy> PgShmemHashShareGuard<K, V> {
+    /// A copy of the value for `key`, if any
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.table.get(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// How many entries the table holds
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Does the table hold no entries?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every entry, in no particular order
+    pub fn iter(&self) -> PgHashTableIter<'_, K, V> {
+        self.table.iter()
+    }
+}
+
+/// Read and write access to a [`PgShmemHash`], while holding its lock in exclusive mode
+pub struct PgShmemHashExclusiveGuard<K, V> {
+    table: LockedTable<K, V>,
+}
+
+impl<K: Copy, V: Copy> PgShmemHashExclusiveGuard<K, V> {
+    /// A copy of the value for `key`, if any
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.table.get(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The value for `key`, which can be changed in place
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let (entry, _) = self.table.search(key, pg_sys::HASHACTION_HASH_FIND);
+        unsafe { entry.as_mut() }.map(|entry| &mut entry.value)
+    }
+
+    /// Set the value for `key`, returning the value it replaced.  If the table is full, the key
+    /// and value are handed back instead
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        let (entry, found) = self.table.search(&key, pg_sys::HASHACTION_HASH_ENTER_NULL);
+        if entry.is_null() {
+            return Err((key, value));
+        }
+        Ok(unsafe { set_value(entry, found, value) })
+    }
+
+    /// Remove `key` from the table, returning its value
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (entry, _) = self.table.search(key, pg_sys::HASHACTION_HASH_REMOVE);
+        // the entry is on the free list now, but it's still there to read
+        unsafe { entry.as_ref() }.map(|entry| entry.value)
+    }
+
+    /// Remove every entry for which `keep` returns false
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut keep: F) {
+        let removed = self
+            .iter()
+            .filter(|(key, value)| !keep(key, value))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        for key in removed {
+            self.remove(&key);
+        }
+    }
+
+    /// Remove every entry
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false)
+    }
+
+    /// How many entries the table holds
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Does the table hold no entries?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every entry, in no particular order
+    pub fn iter(&self) -> PgHashTableIter<'_, K, V> {
+        self.table.iter()
+    }
+}
@@ -0,0 +1,140 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Sort support for `#[derive(PostgresOrd)]` types, so Postgres can compare them directly while
+//! sorting, rather than calling their SQL comparison function for every pair of values
+use crate::{pg_sys, FromDatum, PgMemoryContexts};
+use std::os::raw::c_int;
+
+/// A type which Postgres can sort by an "abbreviated key": a `Datum` that stands in for the whole
+/// value, and compares as an unsigned integer, so most comparisons don't need to convert the
+/// values at all.
+///
+/// Keys must agree with the type's `Ord`: whenever `a < b`, `a.abbreviated_key()` must be less
+/// than or equal to `b.abbreviated_key()`.  Values with equal keys are compared in full, so the
+/// more values a key tells apart, the faster the sort.  Postgres only abbreviates the first
+/// column it sorts by.
+///
+/// Add `#[abbreviated_keys]` next to `#[derive(PostgresOrd)]` to use it:
+///
+/// ```rust,no_run
+/// use pgx::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(
+///     PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, PostgresType, PostgresEq, PostgresOrd,
+/// )]
+/// #[abbreviated_keys]
+/// pub struct Name {
+///     name: String,
+/// }
+///
+/// impl AbbreviatedKeys for Name {
+///     fn abbreviated_key(&self) -> pg_sys::Datum {
+///         // `String`s sort by their bytes, so the first few, most significant first, will do
+///         let mut prefix = [0u8; std::mem::size_of::<pg_sys::Datum>()];
+///         let len = self.name.len().min(prefix.len());
+///         prefix[..len].copy_from_slice(&self.name.as_bytes()[..len]);
+///         pg_sys::Datum::from_be_bytes(prefix)
+///     }
+/// }
+/// ```
+pub trait AbbreviatedKeys: Ord {
+    fn abbreviated_key(&self) -> pg_sys::Datum;
+}
+
+/// Set up `ssup` to compare `T`s with their `Ord`.  This is what the `sortsupport` function from
+/// `#[derive(PostgresOrd)]` does.
+///
+/// # Safety
+///
+/// `ssup` must be the `SortSupport` Postgres gave the `sortsupport` function, for a column of `T`
+#[allow(clippy::manual_c_str_literals)]
+pub unsafe fn prepare_sort_support<T: FromDatum + Ord>(ssup: pg_sys::SortSupport) {
+    let ssup = &mut *ssup;
+
+    // what comparisons allocate is freed after each one, rather than when the sort is done
+    let scratch = pg_sys::AllocSetContextCreateExtended(
+        ssup.ssup_cxt,
+        "sort support\0".as_ptr() as *const std::os::raw::c_char,
+        pg_sys::ALLOCSET_SMALL_MINSIZE as usize,
+        pg_sys::ALLOCSET_SMALL_INITSIZE as usize,
+        pg_sys::ALLOCSET_SMALL_MAXSIZE as usize,
+    );
+    ssup.ssup_extra = scratch as *mut std::os::raw::c_void;
+    ssup.comparator = Some(compare::<T>);
+}
+
+/// Like [`prepare_sort_support()`], but also has Postgres sort by `T`'s abbreviated keys, when
+/// it's able to.  This is what `#[abbreviated_keys]` asks `#[derive(PostgresOrd)]` for.
+///
+/// # Safety
+///
+/// `ssup` must be the `SortSupport` Postgres gave the `sortsupport` function, for a column of `T`
+pub unsafe fn prepare_abbreviated_sort_support<T: FromDatum + AbbreviatedKeys>(
+    ssup: pg_sys::SortSupport,
+) {
+    prepare_sort_support::<T>(ssup);
+
+    let ssup = &mut *ssup;
+    if ssup.abbreviate {
+        ssup.abbrev_full_comparator = ssup.comparator;
+        ssup.comparator = Some(compare_abbreviated);
+        ssup.abbrev_converter = Some(abbreviate::<T>);
+        ssup.abbrev_abort = Some(never_abort);
+    }
+}
+
+unsafe extern "C" fn compare<T: FromDatum + Ord>(
+    x: pg_sys::Datum,
+    y: pg_sys::Datum,
+    ssup: pg_sys::SortSupport,
+) -> c_int {
+    in_scratch_context(ssup, move || {
+        let x = T::from_datum(x, false, pg_sys::InvalidOid).expect("sorted datum is NULL");
+        let y = T::from_datum(y, false, pg_sys::InvalidOid).expect("sorted datum is NULL");
+        x.cmp(&y) as c_int
+    })
+}
+
+unsafe extern "C" fn abbreviate<T: FromDatum + AbbreviatedKeys>(
+    original: pg_sys::Datum,
+    ssup: pg_sys::SortSupport,
+) -> pg_sys::Datum {
+    in_scratch_context(ssup, move || {
+        T::from_datum(original, false, pg_sys::InvalidOid)
+            .expect("sorted datum is NULL")
+            .abbreviated_key()
+    })
+}
+
+unsafe extern "C" fn compare_abbreviated(
+    x: pg_sys::Datum,
+    y: pg_sys::Datum,
+    _ssup: pg_sys::SortSupport,
+) -> c_int {
+    x.cmp(&y) as c_int
+}
+
+/// There's no cheap way to tell how well keys are telling values apart, so they're always used
+unsafe extern "C" fn never_abort(_memtupcount: c_int, _ssup: pg_sys::SortSupport) -> bool {
+    false
+}
+
+unsafe fn in_scratch_context<R, F>(ssup: pg_sys::SortSupport, f: F) -> R
+where
+    F: FnOnce() -> R + std::panic::UnwindSafe + std::panic::RefUnwindSafe,
+{
+    let scratch = (*ssup).ssup_extra as pg_sys::MemoryContext;
+    pg_sys::guard(|| {
+        let result = PgMemoryContexts::For(scratch).switch_to(|_| f());
+        pg_sys::MemoryContextReset(scratch);
+        result
+    })
+}
@@ -9,9 +9,13 @@ Use of this source code is governed by the MIT license that can be found in the
 
 //! Safe access to Postgres' *Server Programming Interface* (SPI).
 
-use crate::{pg_sys, FromDatum, IntoDatum, Json, PgMemoryContexts, PgOid};
+use crate::{
+    pg_sys, FromDatum, IntoDatum, Json, PgMemoryContexts, PgOid, RefCursor, TypeConversionError,
+};
 use enum_primitive_derive::*;
 use num_traits::FromPrimitive;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::ops::{Index, IndexMut};
@@ -60,6 +64,89 @@ pub enum SpiError {
 
 pub struct Spi;
 
+/// An argument for the `_with_args` SPI functions, whose type is inferred from `value`'s Rust type.
+///
+/// `None::<T>` is a NULL of `T`'s type, and a `Vec<T>` is an array of `T`
+pub fn spi_arg<T: IntoDatum>(value: T) -> (PgOid, Option<pg_sys::Datum>) {
+    (PgOid::from(T::type_oid()), value.into_datum())
+}
+
+/// Build the arguments for the `_with_args` SPI functions from Rust values, with [`spi_arg()`]
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// Spi::run_with_args(
+///     "INSERT INTO users (name, manager, tags) VALUES ($1, $2, $3)",
+///     spi_args!["ed", None::<i64>, vec!["dba", "oncall"]],
+/// );
+/// ```
+#[macro_export]
+macro_rules! spi_args {
+    ($($arg:expr),* $(,)?) => {
+        vec![$($crate::spi_arg($arg)),*]
+    };
+}
+
+/// Whether SPI statements may modify the database, which also decides which snapshot they see.
+///
+/// `#[pg_extern]` functions run SPI with the mode their volatility calls for, as Postgres' own
+/// procedural languages do: `immutable` and `stable` functions run it read-only, and the rest
+/// read-write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiMode {
+    /// Statements may modify the database.  Each takes a new snapshot, after incrementing the
+    /// command counter, so it sees what the statements before it did, including in the calling
+    /// query, which an `immutable` or `stable` function must not
+    ReadWrite,
+    /// Statements may only read, and see the snapshot of the query calling the function, as
+    /// `immutable` and `stable` functions must.  Postgres raises an `ERROR` for any statement that
+    /// would modify the database, like "INSERT is not allowed in a non-volatile function"
+    ReadOnly,
+}
+
+impl SpiMode {
+    fn read_only(self) -> bool {
+        self == SpiMode::ReadOnly
+    }
+}
+
+thread_local! {
+    static SPI_MODE: Cell<SpiMode> = const { Cell::new(SpiMode::ReadWrite) };
+
+    /// The [`SpiMode`] of each connection [`Spi::connect()`] has open, nested in one another,
+    /// innermost last
+    static SPI_CONNECTIONS: RefCell<Vec<SpiMode>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Makes a [`SpiMode`] the default of the SPI statements run until it's dropped, when the mode
+/// before it is the default again.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// let _read_only = SpiModeScope::enter(SpiMode::ReadOnly);
+/// assert_eq!(Spi::mode(), SpiMode::ReadOnly);
+/// ```
+pub struct SpiModeScope {
+    previous: SpiMode,
+}
+
+impl SpiModeScope {
+    pub fn enter(mode: SpiMode) -> Self {
+        SpiModeScope {
+            previous: SPI_MODE.with(|current| current.replace(mode)),
+        }
+    }
+}
+
+impl Drop for SpiModeScope {
+    fn drop(&mut self) {
+        SPI_MODE.with(|current| current.set(self.previous));
+    }
+}
+
+/// Runs statements in the innermost SPI connection open, which must be the one it was made for
 pub struct SpiClient;
 
 #[derive(Debug)]
@@ -92,19 +179,14 @@ impl Spi {
         })
     }
 
+    /// Same as `Spi::get::<(A, B)>()`
     pub fn get_two<A: FromDatum + IntoDatum, B: FromDatum + IntoDatum>(
         query: &str,
     ) -> (Option<A>, Option<B>) {
-        Spi::connect(|client| {
-            let (a, b) = client
-                .select(query, Some(1), None)
-                .first()
-                .get_two::<A, B>();
-            Ok(Some((a, b)))
-        })
-        .unwrap()
+        Spi::get::<(A, B)>(query)
     }
 
+    /// Same as `Spi::get::<(A, B, C)>()`
     pub fn get_three<
         A: FromDatum + IntoDatum,
         B: FromDatum + IntoDatum,
@@ -112,14 +194,7 @@ impl Spi {
     >(
         query: &str,
     ) -> (Option<A>, Option<B>, Option<C>) {
-        Spi::connect(|client| {
-            let (a, b, c) = client
-                .select(query, Some(1), None)
-                .first()
-                .get_three::<A, B, C>();
-            Ok(Some((a, b, c)))
-        })
-        .unwrap()
+        Spi::get::<(A, B, C)>(query)
     }
 
     pub fn get_one_with_args<A: FromDatum + IntoDatum>(
@@ -129,20 +204,15 @@ impl Spi {
         Spi::connect(|client| Ok(client.select(query, Some(1), Some(args)).first().get_one()))
     }
 
+    /// Same as `Spi::get_with_args::<(A, B)>()`
     pub fn get_two_with_args<A: FromDatum + IntoDatum, B: FromDatum + IntoDatum>(
         query: &str,
         args: Vec<(PgOid, Option<pg_sys::Datum>)>,
     ) -> (Option<A>, Option<B>) {
-        Spi::connect(|client| {
-            let (a, b) = client
-                .select(query, Some(1), Some(args))
-                .first()
-                .get_two::<A, B>();
-            Ok(Some((a, b)))
-        })
-        .unwrap()
+        Spi::get_with_args::<(A, B)>(query, args)
     }
 
+    /// Same as `Spi::get_with_args::<(A, B, C)>()`
     pub fn get_three_with_args<
         A: FromDatum + IntoDatum,
         B: FromDatum + IntoDatum,
@@ -151,14 +221,80 @@ impl Spi {
         query: &str,
         args: Vec<(PgOid, Option<pg_sys::Datum>)>,
     ) -> (Option<A>, Option<B>, Option<C>) {
+        Spi::get_with_args::<(A, B, C)>(query, args)
+    }
+
+    /// The first row of a query's results, as a tuple of the values of its columns, which are
+    /// each `None` when they're `NULL`, or when there are no rows.  A tuple has up to 12 columns
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let (name, age, admin) =
+    ///     Spi::get::<(String, i32, bool)>("SELECT name, age, admin FROM users WHERE id = 1");
+    /// ```
+    pub fn get<T: SpiTuple>(query: &str) -> T::Row {
+        Spi::first_row::<T>(query, None)
+    }
+
+    /// [`Spi::get()`] for a query that has parameters, such as `$1`
+    pub fn get_with_args<T: SpiTuple>(
+        query: &str,
+        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+    ) -> T::Row {
+        Spi::first_row::<T>(query, Some(args))
+    }
+
+    fn first_row<T: SpiTuple>(
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> T::Row {
+        Spi::rows::<T>(query, Some(1), args)
+            .pop()
+            .unwrap_or_else(T::nulls)
+    }
+
+    /// Every row of a query's results, as a tuple of the values of its columns, as for
+    /// [`Spi::get()`].  The rows are all read before the query's connection to SPI is finished
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// for (name, age) in Spi::iter::<(String, i32)>("SELECT name, age FROM users") {
+    ///     info!("{:?} is {:?}", name, age);
+    /// }
+    /// ```
+    pub fn iter<T: SpiTuple>(query: &str) -> impl Iterator<Item = T::Row> {
+        Spi::rows::<T>(query, None, None).into_iter()
+    }
+
+    /// [`Spi::iter()`] for a query that has parameters, such as `$1`
+    pub fn iter_with_args<T: SpiTuple>(
+        query: &str,
+        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+    ) -> impl Iterator<Item = T::Row> {
+        Spi::rows::<T>(query, None, Some(args)).into_iter()
+    }
+
+    fn rows<T: SpiTuple>(
+        query: &str,
+        limit: Option<i64>,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> Vec<T::Row> {
+        // the rows' values are copied into the memory context we were called in, before SPI
+        // frees the one it reads them into
+        let outer_memory_context = PgMemoryContexts::CurrentMemoryContext.value();
+        let mut rows = Vec::new();
         Spi::connect(|client| {
-            let (a, b, c) = client
-                .select(query, Some(1), Some(args))
-                .first()
-                .get_three::<A, B, C>();
-            Ok(Some((a, b, c)))
-        })
-        .unwrap()
+            let mut table = client.select(query, limit, args);
+            rows.reserve(table.len());
+            for row in 0..table.len() {
+                table.current = row as isize;
+                rows.push(unsafe { T::from_row(&table, Some(outer_memory_context)) });
+            }
+            Ok(None::<()>)
+        });
+        rows
     }
 
     /// just run an arbitrary SQL statement.
@@ -172,11 +308,127 @@ impl Spi {
         })
     }
 
+    /// just run an arbitrary SQL statement that has parameters, such as `$1`.  See [`spi_args!`]
+    /// for building `args` from Rust values
+    ///
+    /// ## Safety
+    ///
+    /// The statement runs in read/write mode
+    pub fn run_with_args(query: &str, args: Vec<(PgOid, Option<pg_sys::Datum>)>) {
+        Spi::execute(|mut client| {
+            client.update(query, None, Some(args));
+        })
+    }
+
+    /// run a statement that modifies the database, such as an `INSERT`, `UPDATE`, or `DELETE`,
+    /// returning how many rows it processed
+    ///
+    /// ## Safety
+    ///
+    /// The statement runs in read/write mode
+    pub fn update(query: &str) -> u64 {
+        Spi::processed(query, None)
+    }
+
+    /// [`Spi::update()`] for a statement that has parameters, such as `$1`.  See [`spi_args!`]
+    /// for building `args` from Rust values
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let promoted = Spi::update_with_args(
+    ///     "UPDATE users SET manager = $1 WHERE manager = $2",
+    ///     spi_args![7i64, 3i64],
+    /// );
+    /// ```
+    pub fn update_with_args(query: &str, args: Vec<(PgOid, Option<pg_sys::Datum>)>) -> u64 {
+        Spi::processed(query, Some(args))
+    }
+
+    fn processed(query: &str, args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>) -> u64 {
+        Spi::connect(|mut client| Ok(Some(client.update(query, None, args).len() as i64)))
+            .expect("SPI didn't say how many rows were processed") as u64
+    }
+
+    /// run a statement that modifies the database and has a `RETURNING` clause, such as an
+    /// `INSERT ... RETURNING id`, returning the first column of every row it returned.  Panics
+    /// if that column isn't of a type `T` can be converted from.
+    ///
+    /// ## Safety
+    ///
+    /// The statement runs in read/write mode
+    pub fn insert_returning<T: FromDatum + IntoDatum>(query: &str) -> Vec<Option<T>> {
+        Spi::returning(query, None)
+    }
+
+    /// [`Spi::insert_returning()`] for a statement that has parameters, such as `$1`
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let ids = Spi::insert_returning_with_args::<i64>(
+    ///     "INSERT INTO users (name) SELECT unnest($1) RETURNING id",
+    ///     spi_args![vec!["ed", "edd", "eddy"]],
+    /// );
+    /// assert_eq!(ids.len(), 3);
+    /// ```
+    pub fn insert_returning_with_args<T: FromDatum + IntoDatum>(
+        query: &str,
+        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+    ) -> Vec<Option<T>> {
+        Spi::returning(query, Some(args))
+    }
+
+    fn returning<T: FromDatum + IntoDatum>(
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> Vec<Option<T>> {
+        Spi::connect(|mut client| {
+            let mut table = client.update(query, None, args);
+            let mut rows = Vec::with_capacity(table.len());
+            for row in 0..table.len() {
+                table.current = row as isize;
+                rows.push(
+                    table
+                        .try_get_datum::<T>(1)
+                        .unwrap_or_else(|e| panic!("{}", e)),
+                );
+            }
+            Ok(Some(rows))
+        })
+        .unwrap_or_default()
+    }
+
     /// explain a query, returning its result in json form
     pub fn explain(query: &str) -> Json {
+        Spi::explain_query(query, None)
+    }
+
+    /// explain a query that has parameters, such as `$1`, returning its result in json form.
+    ///
+    /// The plan is the one Postgres would choose for the given argument values
+    pub fn explain_with_args(query: &str, args: Vec<(PgOid, Option<pg_sys::Datum>)>) -> Json {
+        Spi::explain_query(query, Some(args))
+    }
+
+    /// explain a query, returning the plan of its first statement as a [`QueryPlan`]
+    pub fn explain_plan(query: &str) -> QueryPlan {
+        QueryPlan::from_explain(&Spi::explain(query))
+    }
+
+    /// explain a query that has parameters, such as `$1`, returning the plan of its first
+    /// statement as a [`QueryPlan`]
+    pub fn explain_plan_with_args(
+        query: &str,
+        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+    ) -> QueryPlan {
+        QueryPlan::from_explain(&Spi::explain_with_args(query, args))
+    }
+
+    fn explain_query(query: &str, args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>) -> Json {
         Spi::connect(|mut client| {
             let table = client
-                .update(&format!("EXPLAIN (format json) {}", query), None, None)
+                .update(&format!("EXPLAIN (format json) {}", query), None, args)
                 .first();
             Ok(Some(
                 table
@@ -187,6 +439,32 @@ impl Spi {
         .unwrap()
     }
 
+    /// The [`SpiMode`] SPI statements run in, unless their [`SpiClient`] says otherwise
+    pub fn mode() -> SpiMode {
+        SPI_MODE.with(|current| current.get())
+    }
+
+    /// Run `f` with `mode` as the default for the SPI statements in it, including those of the
+    /// `Spi` functions like [`Spi::get_one()`]
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// let count = Spi::with_mode(SpiMode::ReadOnly, || {
+    ///     Spi::get_one::<i64>("SELECT count(*) FROM users")
+    /// });
+    /// ```
+    pub fn with_mode<R, F: FnOnce() -> R>(mode: SpiMode, f: F) -> R {
+        let _scope = SpiModeScope::enter(mode);
+        f()
+    }
+
+    /// How many SPI connections [`Spi::connect()`] has open, nested in one another, such as when
+    /// a function run by a statement from one connects again
+    pub fn connection_depth() -> usize {
+        SPI_CONNECTIONS.with(|connections| connections.borrow().len())
+    }
+
     /// execute SPI commands via the provided `SpiClient`
     pub fn execute<F: FnOnce(SpiClient) + std::panic::UnwindSafe>(f: F) {
         Spi::connect(|client| {
@@ -207,21 +485,45 @@ impl Spi {
             PgMemoryContexts::For(PgMemoryContexts::CurrentMemoryContext.value());
 
         /// a struct to manage our SPI connection lifetime
-        struct SpiConnection;
+        struct SpiConnection {
+            depth: usize,
+        }
         impl SpiConnection {
             /// Connect to Postgres' SPI system
             fn connect() -> Self {
                 // connect to SPI
                 Spi::check_status(unsafe { pg_sys::SPI_connect() });
-                SpiConnection
+                let depth = SPI_CONNECTIONS.with(|connections| {
+                    let mut connections = connections.borrow_mut();
+                    connections.push(Spi::mode());
+                    connections.len()
+                });
+                SpiConnection { depth }
             }
         }
 
         impl Drop for SpiConnection {
-            /// when SpiConnection is dropped, we make sure to disconnect from SPI
+            /// when SpiConnection is dropped, we make sure to disconnect from SPI.  Connections
+            /// are always finished innermost first, as Postgres requires, since each is dropped
+            /// before the closure of the one it's nested in returns
             fn drop(&mut self) {
+                let depth = SPI_CONNECTIONS.with(|connections| {
+                    let mut connections = connections.borrow_mut();
+                    let depth = connections.len();
+                    connections.truncate(self.depth - 1);
+                    depth
+                });
+                debug_assert_eq!(depth, self.depth, "SPI connections finished out of order");
+
                 // disconnect from SPI
-                Spi::check_status(unsafe { pg_sys::SPI_finish() });
+                let status = unsafe { pg_sys::SPI_finish() };
+
+                // while unwinding from a panic or elog(ERROR), Postgres cleans up whatever the
+                // connection left behind when it aborts the transaction, and panicking again
+                // would abort the backend's process
+                if !std::thread::panicking() {
+                    Spi::check_status(status);
+                }
             }
         }
 
@@ -229,9 +531,8 @@ impl Spi {
         let _connection = SpiConnection::connect();
 
         // run the provided closure within the memory context that SPI_connect()
-        // just put us un.  We'll disconnect from SPI when the closure is finished.
-        // If there's a panic or elog(ERROR), we don't care about also disconnecting from
-        // SPI b/c Postgres will do that for us automatically
+        // just put us un.  We'll disconnect from SPI when the closure is finished,
+        // including if it panics or raises an elog(ERROR)
         match f(SpiClient) {
             // copy the result to the outer memory context we saved above
             Ok(result) => {
@@ -284,35 +585,59 @@ impl Spi {
 }
 
 impl SpiClient {
+    /// Run this client's statements in `mode`, rather than the default of [`Spi::mode()`]
+    pub fn set_mode(self, mode: SpiMode) -> Self {
+        self.check_connection();
+        SPI_CONNECTIONS.with(|connections| *connections.borrow_mut().last_mut().unwrap() = mode);
+        self
+    }
+
+    /// The [`SpiMode`] this client runs its statements in
+    pub fn mode(&self) -> SpiMode {
+        self.check_connection();
+        SPI_CONNECTIONS.with(|connections| *connections.borrow().last().unwrap())
+    }
+
+    /// Panic unless an SPI connection is open, as one that has finished has freed the results of
+    /// its statements.  Within a nested connection, this client runs statements in that one, so
+    /// they're freed when it finishes, rather than when the client's own does
+    fn check_connection(&self) {
+        if Spi::connection_depth() == 0 {
+            panic!("this SpiClient's SPI connection has finished");
+        }
+    }
+
     /// perform a SELECT statement
+    ///
+    /// Postgres docs say:
+    ///
+    ///    It is generally unwise to mix read-only and read-write commands within a single function
+    ///    using SPI; that could result in very confusing behavior, since the read-only queries
+    ///    would not see the results of any database updates done by the read-write queries.
+    ///
+    /// So rather than always reading, it runs in the client's [`SpiMode`], as
+    /// [`SpiClient::update()`] does
     pub fn select(
         &self,
         query: &str,
         limit: Option<i64>,
         args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
     ) -> SpiTupleTable {
-        // Postgres docs say:
-        //
-        //    It is generally unwise to mix read-only and read-write commands within a single function
-        //    using SPI; that could result in very confusing behavior, since the read-only queries
-        //    would not see the results of any database updates done by the read-write queries.
-        //
-        // As such, we don't actually set read-only to true here
-
-        // TODO:  can we detect if the command counter (or something?) has incremented and if yes
-        //        then we set read_only=false, else we can set it to true?
-        //        Is this even a good idea?
-        SpiClient::execute(query, false, limit, args)
+        self.check_connection();
+        SpiClient::execute(query, self.mode().read_only(), limit, args)
     }
 
-    /// perform any query (including utility statements) that modify the database in some way
+    /// perform any query (including utility statements) that modify the database in some way.
+    ///
+    /// In [`SpiMode::ReadOnly`], Postgres raises an `ERROR` if it does
     pub fn update(
         &mut self,
         query: &str,
         limit: Option<i64>,
         args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
     ) -> SpiTupleTable {
-        SpiClient::execute(query, false, limit, args)
+        self.check_connection();
+        SpiClient::execute(query, self.mode().read_only(), limit, args)
     }
 
     fn execute(
@@ -329,27 +654,7 @@ impl SpiClient {
         let status_code = match args {
             Some(args) => {
                 let nargs = args.len();
-                let mut argtypes = vec![];
-                let mut datums = vec![];
-                let mut nulls = vec![];
-
-                for (argtype, datum) in args {
-                    argtypes.push(argtype.value());
-
-                    match datum {
-                        Some(datum) => {
-                            // ' ' here means that the datum is not null
-                            datums.push(datum);
-                            nulls.push(' ' as std::os::raw::c_char);
-                        }
-
-                        None => {
-                            // 'n' here means that the datum is null
-                            datums.push(0);
-                            nulls.push('n' as std::os::raw::c_char);
-                        }
-                    }
-                }
+                let (mut argtypes, mut datums, nulls) = SpiClient::split_args(args);
 
                 unsafe {
                     pg_sys::SPI_execute_with_args(
@@ -366,8 +671,158 @@ impl SpiClient {
             None => unsafe { pg_sys::SPI_execute(src.as_ptr(), read_only, limit.unwrap_or(0)) },
         };
 
+        SpiTupleTable::from_spi(Spi::check_status(status_code))
+    }
+
+    /// The types, values, and nulls SPI takes arguments as
+    fn split_args(
+        args: Vec<(PgOid, Option<pg_sys::Datum>)>,
+    ) -> (
+        Vec<pg_sys::Oid>,
+        Vec<pg_sys::Datum>,
+        Vec<std::os::raw::c_char>,
+    ) {
+        let mut argtypes = vec![];
+        let mut datums = vec![];
+        let mut nulls = vec![];
+
+        for (argtype, datum) in args {
+            argtypes.push(argtype.value());
+
+            match datum {
+                Some(datum) => {
+                    // ' ' here means that the datum is not null
+                    datums.push(datum);
+                    nulls.push(' ' as std::os::raw::c_char);
+                }
+
+                None => {
+                    // 'n' here means that the datum is null
+                    datums.push(0);
+                    nulls.push('n' as std::os::raw::c_char);
+                }
+            }
+        }
+
+        (argtypes, datums, nulls)
+    }
+
+    /// Open a cursor over the rows of `query`, which are fetched a few at a time with
+    /// [`SpiCursor::fetch()`], rather than all at once, as [`SpiClient::select()`] does.
+    ///
+    /// The cursor is closed when the `SpiCursor` is dropped, unless it's turned into the
+    /// [`RefCursor`] that names it, with [`SpiCursor::into_refcursor()`], which a function can
+    /// return for its caller to `FETCH` from.  It then stays open until the end of the
+    /// transaction, or until it's closed with `CLOSE`.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// // BEGIN; SELECT all_the_things(); FETCH 100 FROM "<unnamed portal 1>"; ...
+    /// #[pg_extern]
+    /// fn all_the_things() -> RefCursor {
+    ///     Spi::connect(|client| {
+    ///         let cursor = client.open_cursor("SELECT * FROM things", None);
+    ///         Ok(Some(cursor.into_refcursor()))
+    ///     })
+    ///     .unwrap()
+    /// }
+    /// ```
+    pub fn open_cursor(
+        &self,
+        query: &str,
+        args: Option<Vec<(PgOid, Option<pg_sys::Datum>)>>,
+    ) -> SpiCursor<'_> {
+        self.check_connection();
+        let src = std::ffi::CString::new(query).expect("query contained a null byte");
+        let args = args.unwrap_or_default();
+        let nargs = args.len();
+        let (mut argtypes, mut datums, nulls) = SpiClient::split_args(args);
+
+        let portal = unsafe {
+            pg_sys::SPI_cursor_open_with_args(
+                std::ptr::null(),
+                src.as_ptr(),
+                nargs as i32,
+                argtypes.as_mut_ptr(),
+                datums.as_mut_ptr(),
+                nulls.as_ptr(),
+                self.mode().read_only(),
+                0,
+            )
+        };
+        SpiCursor {
+            portal,
+            client: self,
+        }
+    }
+
+    /// The cursor called `name`, such as one opened by `DECLARE`, or returned as a [`RefCursor`]
+    /// by another function, if it's open
+    pub fn find_cursor(&self, name: &str) -> Option<SpiCursor<'_>> {
+        self.check_connection();
+        let name = std::ffi::CString::new(name).expect("cursor name contained a null byte");
+        let portal = unsafe { pg_sys::SPI_cursor_find(name.as_ptr()) };
+        if portal.is_null() {
+            None
+        } else {
+            Some(SpiCursor {
+                portal,
+                client: self,
+            })
+        }
+    }
+}
+
+/// An open cursor, from [`SpiClient::open_cursor()`] or [`SpiClient::find_cursor()`], which is
+/// closed when it's dropped
+pub struct SpiCursor<'client> {
+    portal: pg_sys::Portal,
+    client: &'client SpiClient,
+}
+
+impl SpiCursor<'_> {
+    /// Fetch the next `count` rows, or fewer if it runs out.  The `SpiTupleTable` is empty once
+    /// they've all been fetched
+    pub fn fetch(&mut self, count: i64) -> SpiTupleTable {
+        self.client.check_connection();
+        unsafe {
+            pg_sys::SPI_tuptable = std::ptr::null_mut();
+            pg_sys::SPI_cursor_fetch(self.portal, true, count as std::os::raw::c_long);
+        }
+        SpiTupleTable::from_spi(SpiOk::Fetch)
+    }
+
+    /// The name of the cursor, which SQL can `FETCH` from
+    pub fn name(&self) -> &str {
+        unsafe { std::ffi::CStr::from_ptr((*self.portal).name) }
+            .to_str()
+            .expect("cursor name is not valid UTF8")
+    }
+
+    /// The [`RefCursor`] that names the cursor, leaving it open for SQL to `FETCH` from after the
+    /// function returns
+    pub fn into_refcursor(self) -> RefCursor {
+        let cursor = RefCursor::from(self.name());
+        std::mem::forget(self);
+        cursor
+    }
+}
+
+impl Drop for SpiCursor<'_> {
+    fn drop(&mut self) {
+        // while unwinding from an elog(ERROR), the transaction's abort closes it
+        if !std::thread::panicking() {
+            unsafe { pg_sys::SPI_cursor_close(self.portal) }
+        }
+    }
+}
+
+impl SpiTupleTable {
+    /// The rows of the last SPI statement, or fetch
+    fn from_spi(status_code: SpiOk) -> Self {
         SpiTupleTable {
-            status_code: Spi::check_status(status_code),
+            status_code,
             table: unsafe { pg_sys::SPI_tuptable },
             size: unsafe { pg_sys::SPI_processed as usize },
             tupdesc: if unsafe { pg_sys::SPI_tuptable }.is_null() {
@@ -378,9 +833,7 @@ impl SpiClient {
             current: -1,
         }
     }
-}
 
-impl SpiTupleTable {
     /// `SpiTupleTable`s are positioned before the start, for iteration purposes.
     ///
     /// This method moves the position to the first row.  If there are no rows, this
@@ -404,18 +857,26 @@ impl SpiTupleTable {
     }
 
     pub fn get_two<A: FromDatum, B: FromDatum>(&self) -> (Option<A>, Option<B>) {
-        let a = self.get_datum::<A>(1);
-        let b = self.get_datum::<B>(2);
-        (a, b)
+        self.get::<(A, B)>()
     }
 
     pub fn get_three<A: FromDatum, B: FromDatum, C: FromDatum>(
         &self,
     ) -> (Option<A>, Option<B>, Option<C>) {
-        let a = self.get_datum::<A>(1);
-        let b = self.get_datum::<B>(2);
-        let c = self.get_datum::<C>(3);
-        (a, b, c)
+        self.get::<(A, B, C)>()
+    }
+
+    /// The current row, as a tuple of the values of its columns, as for [`Spi::get()`]
+    pub fn get<T: SpiTuple>(&self) -> T::Row {
+        unsafe { T::from_row(self, None) }
+    }
+
+    /// Like `get_one()`, but returns a [`TypeConversionError`] naming the column if it isn't of a
+    /// type `A` can be converted from
+    pub fn try_get_one<A: FromDatum + IntoDatum>(
+        &self,
+    ) -> std::result::Result<Option<A>, TypeConversionError> {
+        self.try_get_datum(1)
     }
 
     pub fn get_heap_tuple(&self) -> Option<SpiHeapTupleData> {
@@ -439,6 +900,13 @@ impl SpiTupleTable {
     }
 
     pub fn get_datum<T: FromDatum>(&self, ordinal: i32) -> Option<T> {
+        let (datum, is_null, typoid) = self.get_raw_datum(ordinal)?;
+        unsafe { T::from_datum(datum, is_null, typoid) }
+    }
+
+    /// The current row's value of a column, as `(value, is_null, type)`, or `None` if there's no
+    /// such column, or row
+    fn get_raw_datum(&self, ordinal: i32) -> Option<(pg_sys::Datum, bool, pg_sys::Oid)> {
         if self.current < 0 {
             panic!("SpiTupleTable positioned before start")
         }
@@ -458,15 +926,110 @@ impl SpiTupleTable {
                         let datum =
                             pg_sys::SPI_getbinval(heap_tuple, tupdesc, ordinal, &mut is_null);
 
-                        T::from_datum(datum, is_null, pg_sys::SPI_gettypeid(tupdesc, ordinal))
+                        Some((datum, is_null, pg_sys::SPI_gettypeid(tupdesc, ordinal)))
                     }
                 },
                 None => panic!("TupDesc is NULL"),
             }
         }
     }
+
+    /// Like `get_datum()`, but returns a [`TypeConversionError`] naming the column if it isn't of
+    /// a type `T` can be converted from
+    pub fn try_get_datum<T: FromDatum + IntoDatum>(
+        &self,
+        ordinal: i32,
+    ) -> std::result::Result<Option<T>, TypeConversionError> {
+        if let Some(tupdesc) = self.tupdesc {
+            unsafe {
+                if ordinal >= 1 && ordinal <= (*tupdesc).natts {
+                    let typoid = pg_sys::SPI_gettypeid(tupdesc, ordinal);
+                    if !T::is_compatible_with(typoid) {
+                        let column = std::ffi::CStr::from_ptr(pg_sys::SPI_fname(tupdesc, ordinal));
+                        return Err(TypeConversionError::new::<T>(typoid, T::type_oid())
+                            .set_column(&column.to_string_lossy()));
+                    }
+                }
+            }
+        }
+        Ok(self.get_datum(ordinal))
+    }
 }
 
+/// A tuple of the types of the columns of a row of an SPI result, such as `(String, i32)`, for
+/// [`Spi::get()`] and [`Spi::iter()`], which give each row as a tuple of `Option`s, such as
+/// `(Option<String>, Option<i32>)`, with `None` for `NULL`.  Implemented for tuples of up to 12
+/// `FromDatum` types
+pub trait SpiTuple {
+    type Row;
+
+    /// The row of `table` it's positioned on, with each value copied into `memory_context`, if
+    /// there is one
+    ///
+    /// ## Safety
+    ///
+    /// `memory_context` must be a valid memory context
+    #[doc(hidden)]
+    unsafe fn from_row(
+        table: &SpiTupleTable,
+        memory_context: Option<pg_sys::MemoryContext>,
+    ) -> Self::Row;
+
+    /// A row of `NULL`s, for when there isn't one
+    #[doc(hidden)]
+    fn nulls() -> Self::Row;
+}
+
+/// A column of a row of an SPI result, copied into `memory_context`, if there is one
+unsafe fn spi_column<T: FromDatum>(
+    table: &SpiTupleTable,
+    ordinal: i32,
+    memory_context: Option<pg_sys::MemoryContext>,
+) -> Option<T> {
+    let (datum, is_null, typoid) = table.get_raw_datum(ordinal)?;
+    match memory_context {
+        Some(memory_context) => T::from_datum_in_memory_context(
+            PgMemoryContexts::For(memory_context),
+            datum,
+            is_null,
+            typoid,
+        ),
+        None => T::from_datum(datum, is_null, typoid),
+    }
+}
+
+macro_rules! spi_tuple {
+    ($($column:ident $ordinal:literal),+) => {
+        impl<$($column: FromDatum),+> SpiTuple for ($($column,)+) {
+            type Row = ($(Option<$column>,)+);
+
+            unsafe fn from_row(
+                table: &SpiTupleTable,
+                memory_context: Option<pg_sys::MemoryContext>,
+            ) -> Self::Row {
+                ($(spi_column::<$column>(table, $ordinal, memory_context),)+)
+            }
+
+            fn nulls() -> Self::Row {
+                ($(None::<$column>,)+)
+            }
+        }
+    };
+}
+
+spi_tuple!(A 1);
+spi_tuple!(A 1, B 2);
+spi_tuple!(A 1, B 2, C 3);
+spi_tuple!(A 1, B 2, C 3, D 4);
+spi_tuple!(A 1, B 2, C 3, D 4, E 5);
+spi_tuple!(A 1, B 2, C 3, D 4, E 5, F 6);
+spi_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7);
+spi_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8);
+spi_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8, I 9);
+spi_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8, I 9, J 10);
+spi_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8, I 9, J 10, K 11);
+spi_tuple!(A 1, B 2, C 3, D 4, E 5, F 6, G 7, H 8, I 9, J 10, K 11, L 12);
+
 impl SpiHeapTupleData {
     /// Create a new `SpiHeapTupleData` from its constituent parts
     pub unsafe fn new(tupdesc: pg_sys::TupleDesc, htup: *mut pg_sys::HeapTupleData) -> Self {
@@ -624,6 +1187,14 @@ impl SpiHeapTupleDataEntry {
             None => None,
         }
     }
+
+    /// Like `value()`, but returns a [`TypeConversionError`] if this isn't of a type `T` can be
+    /// converted from
+    pub fn try_value<T: FromDatum + IntoDatum>(
+        &self,
+    ) -> std::result::Result<Option<T>, TypeConversionError> {
+        unsafe { T::try_from_datum(self.datum.unwrap_or(0), self.datum.is_none(), self.type_oid) }
+    }
 }
 
 /// Provide ordinal indexing into a `SpiHeapTupleData`.
@@ -696,3 +1267,103 @@ impl Iterator for SpiTupleTable {
     // Removed this function as it comes with an iterator
     //fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
 }
+
+/// The plan of one statement, as explained by `EXPLAIN (FORMAT JSON)`.  See [`Spi::explain_plan()`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryPlan {
+    #[serde(rename = "Plan")]
+    pub plan: PlanNode,
+
+    /// In milliseconds, if the `EXPLAIN` included a summary
+    #[serde(rename = "Planning Time", default)]
+    pub planning_time: Option<f64>,
+
+    /// In milliseconds, if the `EXPLAIN` ran the statement and included a summary
+    #[serde(rename = "Execution Time", default)]
+    pub execution_time: Option<f64>,
+}
+
+impl QueryPlan {
+    /// The plan of the first statement in the result of an `EXPLAIN (FORMAT JSON)`
+    pub fn from_explain(explain: &Json) -> Self {
+        serde_json::from_value(explain.0[0].clone()).expect("failed to parse json EXPLAIN result")
+    }
+
+    /// Every node of the plan, starting with its root and going depth-first
+    pub fn nodes(&self) -> Vec<&PlanNode> {
+        self.plan.nodes()
+    }
+}
+
+/// A node of a [`QueryPlan`].  The most common properties have fields of their own, and the rest,
+/// which depend on the node's type and the `EXPLAIN` options, are in `other`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanNode {
+    /// Such as "Seq Scan", "Index Scan", or "Hash Join"
+    #[serde(rename = "Node Type")]
+    pub node_type: String,
+
+    /// How this node relates to its parent, such as "Outer" or "Inner"
+    #[serde(rename = "Parent Relationship", default)]
+    pub parent_relationship: Option<String>,
+
+    #[serde(rename = "Relation Name", default)]
+    pub relation_name: Option<String>,
+
+    /// Only shown with `VERBOSE`
+    #[serde(rename = "Schema", default)]
+    pub schema: Option<String>,
+
+    #[serde(rename = "Alias", default)]
+    pub alias: Option<String>,
+
+    #[serde(rename = "Index Name", default)]
+    pub index_name: Option<String>,
+
+    #[serde(rename = "Join Type", default)]
+    pub join_type: Option<String>,
+
+    #[serde(rename = "Startup Cost", default)]
+    pub startup_cost: Option<f64>,
+
+    #[serde(rename = "Total Cost", default)]
+    pub total_cost: Option<f64>,
+
+    /// The estimated number of rows
+    #[serde(rename = "Plan Rows", default)]
+    pub plan_rows: Option<f64>,
+
+    /// The estimated average width of rows, in bytes
+    #[serde(rename = "Plan Width", default)]
+    pub plan_width: Option<i64>,
+
+    #[serde(rename = "Filter", default)]
+    pub filter: Option<String>,
+
+    #[serde(rename = "Index Cond", default)]
+    pub index_cond: Option<String>,
+
+    /// The actual number of rows, per loop, if the `EXPLAIN` ran the statement
+    #[serde(rename = "Actual Rows", default)]
+    pub actual_rows: Option<f64>,
+
+    #[serde(rename = "Actual Loops", default)]
+    pub actual_loops: Option<f64>,
+
+    #[serde(rename = "Plans", default)]
+    pub plans: Vec<PlanNode>,
+
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PlanNode {
+    /// This node and all of its descendants, depth-first
+    pub fn nodes(&self) -> Vec<&PlanNode> {
+        let mut nodes = vec![self];
+        for child in &self.plans {
+            nodes.extend(child.nodes());
+        }
+        nodes
+    }
+}
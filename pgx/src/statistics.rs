@@ -0,0 +1,529 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Cumulative statistics, kept in shared memory and saved across restarts, in the style of
+//! `pg_stat_statements`
+use crate::{
+    pg_sys, with_addin_shmem_init_lock, PgAtomicU64, PgLogLevel, PgSharedMemoryInitialization,
+    PgSqlErrorCode,
+};
+use heapless::FnvIndexMap;
+use std::ffi::CString;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// Databases and objects are identified by their oid
+type StatisticsKey = (pg_sys::Oid, pg_sys::Oid);
+
+/// Identifies the file format, "PGXS"
+const STATISTICS_FILE_MAGIC: u32 = 0x50475853;
+
+/// The statistics themselves, in shared memory
+struct StatisticsShared<const C: usize, const N: usize> {
+    /// the slot in `counters` of each database and object with statistics
+    slots: FnvIndexMap<StatisticsKey, usize, N>,
+    counters: [[PgAtomicU64; C]; N],
+}
+
+/// `C` cumulative counters for each of up to `N` databases or objects, in shared memory.
+///
+/// Postgres 15 doesn't allow extensions to add their own kinds of statistics to its cumulative
+/// statistics system, so, like `pg_stat_statements`, the counters live in shared memory.  They
+/// are saved to the `pg_stat/` directory when the server shuts down cleanly, and loaded again
+/// when it starts, but are lost on a crash.
+///
+/// Counters are kept for the current database as a whole, with [`PgStatistics::add_database()`],
+/// or for any object in it, like a table or function, with [`PgStatistics::add()`].  Counting
+/// only takes a shared lock, so backends don't wait on each other.  Once `N` (which must be a
+/// power of two) databases and objects have counters, others aren't counted until the
+/// statistics are reset.
+///
+/// Like any other shared memory type, it must be a `static` passed to `pg_shmem_init!()` during
+/// `_PG_init()`, and the extension must be in `shared_preload_libraries`.  Use
+/// [`pg_statistics_functions!()`](crate::pg_statistics_functions) to expose the statistics to SQL.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// const CALLS: usize = 0;
+/// const ROWS: usize = 1;
+///
+/// static FUNCTION_STATS: PgStatistics<2, 1024> = PgStatistics::new("function_stats", ["calls", "rows"]);
+///
+/// pg_statistics_functions!(FUNCTION_STATS, function_stats, function_stats_reset);
+///
+/// #[pg_guard]
+/// pub extern "C" fn _PG_init() {
+///     pg_shmem_init!(FUNCTION_STATS);
+/// }
+///
+/// fn count_call(funcoid: pg_sys::Oid, rows: u64) {
+///     FUNCTION_STATS.add(funcoid, CALLS, 1);
+///     FUNCTION_STATS.add(funcoid, ROWS, rows);
+/// }
+/// ```
+pub struct PgStatistics<const C: usize, const N: usize> {
+    name: &'static str,
+    counter_names: [&'static str; C],
+    shared: AtomicPtr<StatisticsShared<C, N>>,
+    lock: AtomicPtr<pg_sys::LWLock>,
+}
+
+unsafe impl<const C: usize, const N: usize> Send for PgStatistics<C, N> {}
+unsafe impl<const C: usize, const N: usize> Sync for PgStatistics<C, N> {}
+
+/// The counters of one database or object, from [`PgStatistics::entries()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PgStatisticsEntry<const C: usize> {
+    pub database: pg_sys::Oid,
+    /// `pg_sys::InvalidOid` for the counters of the database as a whole
+    pub object: pg_sys::Oid,
+    pub counters: [u64; C],
+}
+
+impl<const C: usize, const N: usize> PgStatistics<C, N> {
+    /// Create statistics named `name`, with a counter for each of `counter_names`.  The name
+    /// identifies the statistics' shared memory and their file in `pg_stat/`, so it must be
+    /// unique, at most 47 bytes long, and safe to use as a file name
+    pub const fn new(name: &'static str, counter_names: [&'static str; C]) -> Self {
+        PgStatistics {
+            name,
+            counter_names,
+            shared: AtomicPtr::new(std::ptr::null_mut()),
+            lock: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+
+    /// The name given to [`PgStatistics::new()`]
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The names of the counters, in order
+    pub fn counter_names(&self) -> &[&'static str; C] {
+        &self.counter_names
+    }
+
+    /// Add `value` to counter number `counter` of `object` in the current database
+    pub fn add(&self, object: pg_sys::Oid, counter: usize, value: u64) {
+        assert!(counter < C, "`{}` has no counter {}", self.name, counter);
+        let key = (unsafe { pg_sys::MyDatabaseId }, object);
+
+        let lock = self.lock(pg_sys::LWLockMode_LW_SHARED);
+        let shared = self.shared();
+        match shared.slots.get(&key) {
+            Some(slot) => {
+                shared.counters[*slot][counter].fetch_add(value);
+            }
+            None => {
+                // trade our shared lock for an exclusive one to give the object a slot
+                drop(lock);
+                if self.allocate_slot(key).is_some() {
+                    self.add(object, counter, value);
+                }
+            }
+        }
+    }
+
+    /// Add `value` to counter number `counter` of the current database as a whole
+    pub fn add_database(&self, counter: usize, value: u64) {
+        self.add(pg_sys::InvalidOid, counter, value)
+    }
+
+    /// The counters of `object` in `database`, if it has any.  Use `pg_sys::InvalidOid` as the
+    /// `object` for the counters of the database as a whole
+    pub fn get(&self, database: pg_sys::Oid, object: pg_sys::Oid) -> Option<[u64; C]> {
+        let _lock = self.lock(pg_sys::LWLockMode_LW_SHARED);
+        let shared = self.shared();
+        shared
+            .slots
+            .get(&(database, object))
+            .map(|slot| read_counters(&shared.counters[*slot]))
+    }
+
+    /// The counters of every database and object with statistics
+    pub fn entries(&self) -> Vec<PgStatisticsEntry<C>> {
+        let _lock = self.lock(pg_sys::LWLockMode_LW_SHARED);
+        let shared = self.shared();
+        shared
+            .slots
+            .iter()
+            .map(|((database, object), slot)| PgStatisticsEntry {
+                database: *database,
+                object: *object,
+                counters: read_counters(&shared.counters[*slot]),
+            })
+            .collect()
+    }
+
+    /// Discard every counter
+    pub fn reset(&self) {
+        let _lock = self.lock(pg_sys::LWLockMode_LW_EXCLUSIVE);
+        let shared = self.shared_mut();
+        for slot in shared.slots.values() {
+            for counter in &shared.counters[*slot] {
+                counter.write(0);
+            }
+        }
+        shared.slots.clear();
+    }
+
+    /// Find or make a slot for `key`, or `None` if they're all taken
+    fn allocate_slot(&self, key: StatisticsKey) -> Option<usize> {
+        let _lock = self.lock(pg_sys::LWLockMode_LW_EXCLUSIVE);
+        let shared = self.shared_mut();
+        if let Some(slot) = shared.slots.get(&key) {
+            return Some(*slot);
+        }
+        // slots are only ever freed all at once, by `reset()`, so the next one is always free
+        let slot = shared.slots.len();
+        shared.slots.insert(key, slot).ok()?;
+        Some(slot)
+    }
+
+    fn shared(&self) -> &StatisticsShared<C, N> {
+        unsafe { &*self.shared_ptr() }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    fn shared_mut(&self) -> &mut StatisticsShared<C, N> {
+        unsafe { &mut *self.shared_ptr() }
+    }
+
+    fn shared_ptr(&self) -> *mut StatisticsShared<C, N> {
+        let shared = self.shared.load(Ordering::SeqCst);
+        assert!(
+            !shared.is_null(),
+            "PgStatistics `{}` is not in shared memory.  Was it passed to `pg_shmem_init!()`?",
+            self.name
+        );
+        shared
+    }
+
+    fn lock(&self, mode: pg_sys::LWLockMode) -> StatisticsLock {
+        let lock = self.lock.load(Ordering::SeqCst);
+        assert!(
+            !lock.is_null(),
+            "PgStatistics `{}` is not in shared memory.  Was it passed to `pg_shmem_init!()`?",
+            self.name
+        );
+        unsafe {
+            pg_sys::LWLockAcquire(lock, mode);
+        }
+        StatisticsLock(lock)
+    }
+
+    fn file_name(&self) -> String {
+        format!("pg_stat/{}.stat", self.name)
+    }
+
+    /// Load the statistics saved by the last clean shutdown, then remove the file so a crash
+    /// doesn't bring them back
+    fn load(&self) {
+        let file_name = self.file_name();
+        let bytes = match std::fs::read(&file_name) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                crate::log!("could not read statistics file \"{}\": {}", file_name, e);
+                return;
+            }
+        };
+
+        match decode_statistics::<C>(&bytes) {
+            Some(entries) => {
+                for (key, counters) in entries {
+                    if let Some(slot) = self.allocate_slot(key) {
+                        for (counter, value) in self.shared().counters[slot].iter().zip(counters) {
+                            counter.write(value);
+                        }
+                    }
+                }
+            }
+            None => {
+                crate::log!("ignoring invalid statistics file \"{}\"", file_name);
+            }
+        }
+        let _ = std::fs::remove_file(&file_name);
+    }
+
+    /// Save the statistics, for the next time the server starts
+    fn save(&self) {
+        let bytes = encode_statistics(&self.entries());
+        let file_name = self.file_name();
+        let temp_name = format!("{}.tmp", file_name);
+        if let Err(e) =
+            std::fs::write(&temp_name, &bytes).and_then(|_| std::fs::rename(&temp_name, &file_name))
+        {
+            crate::log!("could not write statistics file \"{}\": {}", file_name, e);
+            let _ = std::fs::remove_file(&temp_name);
+        }
+    }
+}
+
+impl<const C: usize, const N: usize> PgSharedMemoryInitialization for PgStatistics<C, N> {
+    fn pg_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.name).expect("CString::new() failed");
+            pg_sys::RequestAddinShmemSpace(std::mem::size_of::<StatisticsShared<C, N>>());
+            pg_sys::RequestNamedLWLockTranche(name.as_ptr(), 1);
+        }
+    }
+
+    fn shmem_init(&'static self) {
+        unsafe {
+            let name = CString::new(self.name).expect("CString::new() failed");
+
+            let mut found = false;
+            with_addin_shmem_init_lock(|| {
+                let shared = pg_sys::ShmemInitStruct(
+                    name.as_ptr(),
+                    std::mem::size_of::<StatisticsShared<C, N>>(),
+                    &mut found,
+                ) as *mut StatisticsShared<C, N>;
+                if !found {
+                    // the counters start at zero, which is what a zeroed `PgAtomicU64` holds
+                    std::ptr::write_bytes(shared, 0, 1);
+                    std::ptr::write(std::ptr::addr_of_mut!((*shared).slots), FnvIndexMap::new());
+                }
+                self.shared.store(shared, Ordering::SeqCst);
+                self.lock.store(
+                    &mut (*pg_sys::GetNamedLWLockTranche(name.as_ptr())).lock,
+                    Ordering::SeqCst,
+                );
+            });
+
+            // only the postmaster (or a standalone backend) loads and saves the statistics
+            if !found && !pg_sys::IsUnderPostmaster {
+                self.load();
+                pg_sys::on_shmem_exit(
+                    Some(save_statistics::<C, N>),
+                    self as *const Self as pg_sys::Datum,
+                );
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn save_statistics<const C: usize, const N: usize>(
+    code: std::os::raw::c_int,
+    arg: pg_sys::Datum,
+) {
+    // like pg_stat_statements, don't save statistics after a crash
+    if code == 0 {
+        let statistics = &*(arg as *const PgStatistics<C, N>);
+        crate::guard::guard(|| statistics.save());
+    }
+}
+
+/// The contents of a statistics file: the magic number, `C`, the number of entries, and then
+/// each entry's database, object, and counters, in native-endian 32-bit words
+fn encode_statistics<const C: usize>(entries: &[PgStatisticsEntry<C>]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(12 + entries.len() * (8 + C * 8));
+    let mut push = |word: u32| bytes.extend_from_slice(&word.to_ne_bytes());
+    push(STATISTICS_FILE_MAGIC);
+    push(C as u32);
+    push(entries.len() as u32);
+    for entry in entries {
+        push(entry.database);
+        push(entry.object);
+        for value in entry.counters {
+            push(value as u32);
+            push((value >> 32) as u32);
+        }
+    }
+    bytes
+}
+
+/// The entries of a statistics file from [`encode_statistics()`], or `None` if it isn't one, or
+/// is for a different number of counters, or is truncated or has anything after its entries
+#[allow(clippy::manual_is_multiple_of)]
+fn decode_statistics<const C: usize>(bytes: &[u8]) -> Option<Vec<(StatisticsKey, [u64; C])>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    let mut words = bytes.chunks_exact(4).map(|word| {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(word);
+        u32::from_ne_bytes(buf)
+    });
+    if words.next()? != STATISTICS_FILE_MAGIC || words.next()? as usize != C {
+        return None;
+    }
+
+    let count = words.next()? as usize;
+    if bytes.len() != 12 + count * (8 + C * 8) {
+        return None;
+    }
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        let key = (words.next()?, words.next()?);
+        let mut counters = [0; C];
+        for counter in &mut counters {
+            let low = words.next()? as u64;
+            let high = words.next()? as u64;
+            *counter = high << 32 | low;
+        }
+        entries.push((key, counters));
+    }
+    Some(entries)
+}
+
+fn read_counters<const C: usize>(counters: &[PgAtomicU64; C]) -> [u64; C] {
+    let mut values = [0; C];
+    for (value, counter) in values.iter_mut().zip(counters) {
+        *value = counter.read();
+    }
+    values
+}
+
+/// Releases the statistics' `LWLock` on drop
+struct StatisticsLock(*mut pg_sys::LWLock);
+
+impl Drop for StatisticsLock {
+    fn drop(&mut self) {
+        unsafe {
+            pg_sys::LWLockRelease(self.0);
+        }
+    }
+}
+
+/// Create SQL functions exposing a [`PgStatistics`]
+///
+/// `$view` is a set returning function with a row for each counter of each database and object,
+/// as `(dbid oid, objid oid, counter text, value bigint)`, and `$reset` is a function which
+/// discards the statistics, and which only superusers may call.
+///
+/// A view which pivots the counters into columns is then one `extension_sql!()` away:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// static CACHE_STATS: PgStatistics<2, 64> = PgStatistics::new("cache_stats", ["hits", "misses"]);
+///
+/// pg_statistics_functions!(CACHE_STATS, cache_stats_counters, cache_stats_reset);
+///
+/// extension_sql!(
+///     r#"
+/// CREATE VIEW cache_stats AS
+///     SELECT dbid,
+///            sum(value) FILTER (WHERE counter = 'hits') AS hits,
+///            sum(value) FILTER (WHERE counter = 'misses') AS misses
+///       FROM cache_stats_counters()
+///      GROUP BY dbid;
+/// "#,
+///     name = "cache_stats_view",
+///     requires = [cache_stats_counters],
+/// );
+/// ```
+#[macro_export]
+macro_rules! pg_statistics_functions {
+    ($statistics:ident, $view:ident, $reset:ident) => {
+        #[$crate::pg_extern]
+        fn $view() -> impl std::iter::Iterator<
+            Item = (
+                $crate::name!(dbid, $crate::pg_sys::Oid),
+                $crate::name!(objid, $crate::pg_sys::Oid),
+                $crate::name!(counter, &'static str),
+                $crate::name!(value, i64),
+            ),
+        > {
+            let counter_names = *$statistics.counter_names();
+            $statistics.entries().into_iter().flat_map(move |entry| {
+                std::iter::IntoIterator::into_iter(counter_names)
+                    .zip(entry.counters)
+                    .map(move |(counter, value)| {
+                        (entry.database, entry.object, counter, value as i64)
+                    })
+            })
+        }
+
+        #[$crate::pg_extern]
+        fn $reset() {
+            $crate::reset_statistics_as_superuser(&$statistics)
+        }
+    };
+}
+
+#[doc(hidden)]
+pub fn reset_statistics_as_superuser<const C: usize, const N: usize>(
+    statistics: &PgStatistics<C, N>,
+) {
+    if !unsafe { pg_sys::superuser() } {
+        crate::ereport(
+            PgLogLevel::ERROR,
+            PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+            &format!("must be superuser to reset `{}`", statistics.get_name()),
+            file!(),
+            line!(),
+            column!(),
+        );
+    }
+    statistics.reset();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_statistics, encode_statistics, PgStatisticsEntry};
+
+    fn entries() -> Vec<PgStatisticsEntry<2>> {
+        vec![
+            PgStatisticsEntry {
+                database: 1,
+                object: 0,
+                counters: [0, u64::MAX],
+            },
+            PgStatisticsEntry {
+                database: 1,
+                object: 16384,
+                counters: [42, 1 << 32],
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let decoded = decode_statistics::<2>(&encode_statistics(&entries())).unwrap();
+        let expected = entries()
+            .into_iter()
+            .map(|entry| ((entry.database, entry.object), entry.counters))
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, expected);
+
+        let empty = encode_statistics::<2>(&[]);
+        assert_eq!(decode_statistics::<2>(&empty), Some(vec![]));
+    }
+
+    #[test]
+    fn corrupted() {
+        let bytes = encode_statistics(&entries());
+        // truncated, in the middle of a word and at the end of one
+        assert_eq!(decode_statistics::<2>(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(decode_statistics::<2>(&bytes[..bytes.len() - 4]), None);
+        assert_eq!(decode_statistics::<2>(&bytes[..8]), None);
+        assert_eq!(decode_statistics::<2>(&[]), None);
+
+        // trailing garbage
+        let mut longer = bytes.clone();
+        longer.extend_from_slice(&[0; 4]);
+        assert_eq!(decode_statistics::<2>(&longer), None);
+
+        // the wrong magic number, or number of counters
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] ^= 0xff;
+        assert_eq!(decode_statistics::<2>(&wrong_magic), None);
+        assert_eq!(decode_statistics::<3>(&bytes), None);
+
+        // an entry count that doesn't match the file's size
+        let mut wrong_count = bytes;
+        wrong_count[8..12].copy_from_slice(&u32::MAX.to_ne_bytes());
+        assert_eq!(decode_statistics::<2>(&wrong_count), None);
+    }
+}
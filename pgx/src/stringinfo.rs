@@ -10,8 +10,10 @@ Use of this source code is governed by the MIT license that can be found in the
 //! A safe wrapper around Postgres `StringInfo` structure
 #![allow(dead_code, non_snake_case)]
 
-use crate::{pg_sys, void_mut_ptr};
+use crate::{pg_sys, void_mut_ptr, PgMemoryContexts};
+use std::cell::RefCell;
 use std::io::Error;
+use std::ops::{Deref, DerefMut};
 
 /// StringInfoData holds information about an extensible string that is allocated by Postgres'
 /// memory system, but generally follows Rust's drop semantics
@@ -65,6 +67,15 @@ impl std::io::Write for StringInfo {
     }
 }
 
+impl std::io::Read for StringInfo {
+    /// Read from the cursor, like the `pq_getmsg*()` functions do
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = buf.len().min(self.remaining());
+        buf[..len].copy_from_slice(self.read_bytes(len));
+        Ok(len)
+    }
+}
+
 impl ToString for StringInfo {
     fn to_string(&self) -> String {
         unsafe { std::str::from_utf8_unchecked(self.as_bytes()).to_owned() }
@@ -91,9 +102,38 @@ impl StringInfo {
     /// Unless `.into_pg()` or `.into_char_ptr()` are called, memory management of
     /// this `StringInfo` follow Rust's drop semantics.
     pub fn with_capacity(len: usize) -> Self {
-        let mut si = StringInfo::default();
-        si.enlarge(len);
-        si
+        // like `makeStringInfo()`, but without its default-sized buffer
+        unsafe {
+            let sid = pg_sys::palloc0(std::mem::size_of::<pg_sys::StringInfoData>())
+                as pg_sys::StringInfo;
+            (*sid).data = pg_sys::palloc(len + 1) as *mut std::os::raw::c_char;
+            (*sid).maxlen = (len + 1) as i32;
+            pg_sys::resetStringInfo(sid);
+            StringInfo {
+                sid,
+                needs_pfree: true,
+            }
+        }
+    }
+
+    /// Borrow an empty `StringInfo` from those kept for reuse, or a new one if they're all in
+    /// use, which goes back to be reused when it's dropped.
+    ///
+    /// This saves allocating a buffer, and growing it, for every value written by something which
+    /// is called over and over, such as a type's output or send function, which copies the
+    /// pooled buffer into its result, like `#[derive(PostgresType)]`'s output functions do.
+    /// Pooled buffers are in `TopMemoryContext`
+    pub fn pooled() -> PooledStringInfo {
+        let sid = STRINGINFO_POOL.with(|pool| pool.borrow_mut().pop());
+        let sid = sid.unwrap_or_else(|| {
+            PgMemoryContexts::TopMemoryContext.switch_to(|_| unsafe { pg_sys::makeStringInfo() })
+        });
+        PooledStringInfo {
+            buffer: StringInfo {
+                sid,
+                needs_pfree: false,
+            },
+        }
     }
 
     /// Construct a `StringInfo` from a Postgres-allocated `pg_sys::StringInfo`.
@@ -168,6 +208,13 @@ impl StringInfo {
         unsafe { pg_sys::resetStringInfo(self.sid) }
     }
 
+    /// How many bytes it can hold before it has to grow
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        // safe:  self.sid will never be null, and `maxlen` counts the trailing null byte
+        unsafe { (*self.sid).maxlen as usize - 1 }
+    }
+
     /// Ensure that this `StringInfo` is at least `needed` bytes long
     #[inline]
     pub fn enlarge(&mut self, needed: usize) {
@@ -215,6 +262,20 @@ impl StringInfo {
         self.sid
     }
 
+    /// A copy of the contents, and a trailing null byte, as a `"char *"` allocated in
+    /// `CurrentMemoryContext`, leaving this `StringInfo` to be reused
+    pub fn copy_to_char_ptr(&self) -> *mut std::os::raw::c_char {
+        let len = self.len();
+        unsafe {
+            let ptr = pg_sys::palloc(len + 1) as *mut std::os::raw::c_char;
+            if len > 0 {
+                std::ptr::copy_nonoverlapping(self.as_ptr(), ptr, len);
+            }
+            *ptr.add(len) = 0;
+            ptr
+        }
+    }
+
     /// Convert this `StringInfo` into a `"char *"` that is wholly owned and now managed by Postgres
     #[inline]
     pub fn into_char_ptr(mut self) -> *const std::os::raw::c_char {
@@ -228,6 +289,108 @@ impl StringInfo {
     }
 }
 
+/// Generates the `push_*()`/`read_*()` pair for a number, in network byte order
+macro_rules! binary_number {
+    ($push:ident, $read:ident, $ty:ty, $pq_send:literal, $pq_get:literal) => {
+        #[doc = concat!("Push a `", stringify!($ty), "` onto the end in network byte order, like `", $pq_send, "()`")]
+        #[inline]
+        pub fn $push(&mut self, value: $ty) {
+            self.push_bytes(&value.to_be_bytes())
+        }
+
+        #[doc = concat!("Read a `", stringify!($ty), "` in network byte order from the cursor, like `", $pq_get, "()`.")]
+        ///
+        /// Raises an ERROR if there isn't enough data left
+        #[inline]
+        pub fn $read(&mut self) -> $ty {
+            const SIZE: usize = std::mem::size_of::<$ty>();
+            let mut bytes = [0u8; SIZE];
+            bytes.copy_from_slice(self.read_bytes(SIZE));
+            <$ty>::from_be_bytes(bytes)
+        }
+    };
+}
+
+/// Binary protocol support, for types' `send`/`recv` functions and `COPY BINARY`
+impl StringInfo {
+    binary_number!(push_i8, read_i8, i8, "pq_sendint8", "pq_getmsgint");
+    binary_number!(push_i16, read_i16, i16, "pq_sendint16", "pq_getmsgint");
+    binary_number!(push_i32, read_i32, i32, "pq_sendint32", "pq_getmsgint");
+    binary_number!(push_i64, read_i64, i64, "pq_sendint64", "pq_getmsgint64");
+    binary_number!(push_u8, read_u8, u8, "pq_sendbyte", "pq_getmsgbyte");
+    binary_number!(push_u16, read_u16, u16, "pq_sendint16", "pq_getmsgint");
+    binary_number!(push_u32, read_u32, u32, "pq_sendint32", "pq_getmsgint");
+    binary_number!(push_u64, read_u64, u64, "pq_sendint64", "pq_getmsgint64");
+    binary_number!(push_f32, read_f32, f32, "pq_sendfloat4", "pq_getmsgfloat4");
+    binary_number!(push_f64, read_f64, f64, "pq_sendfloat8", "pq_getmsgfloat8");
+
+    /// Push `bytes` onto the end, preceded by their length as an `i32`, which is how the binary
+    /// format of `bytea`, `text`, and the fields of `COPY BINARY` are represented
+    #[inline]
+    pub fn push_counted_bytes(&mut self, bytes: &[u8]) {
+        self.push_i32(bytes.len() as i32);
+        self.push_bytes(bytes)
+    }
+
+    /// Read bytes preceded by their length as an `i32`, as [`StringInfo::push_counted_bytes()`]
+    /// writes them.  A length of -1, as `COPY BINARY` uses for `NULL`, is `None`
+    #[inline]
+    pub fn read_counted_bytes(&mut self) -> Option<&[u8]> {
+        match self.read_i32() {
+            -1 => None,
+            len if len < 0 => StringInfo::invalid_message("invalid length"),
+            len => Some(self.read_bytes(len as usize)),
+        }
+    }
+
+    /// The position that the `read_*()` functions read from next, which starts at zero
+    #[inline]
+    pub fn cursor(&self) -> usize {
+        // safe:  self.sid will never be null
+        unsafe { (*self.sid).cursor as usize }
+    }
+
+    /// The number of bytes left to read after the cursor
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.len().saturating_sub(self.cursor())
+    }
+
+    /// Read `len` bytes from the cursor, like `pq_getmsgbytes()`.
+    ///
+    /// Raises an ERROR if there aren't that many left
+    pub fn read_bytes(&mut self, len: usize) -> &[u8] {
+        if len > self.remaining() {
+            StringInfo::invalid_message("insufficient data left in message");
+        }
+
+        let start = self.cursor();
+        // safe:  self.sid will never be null
+        unsafe { (*self.sid).cursor += len as i32 };
+        &self.as_bytes()[start..start + len]
+    }
+
+    /// Raise an ERROR if there's anything left to read, like `pq_getmsgend()`, so that a `recv`
+    /// function rejects input with trailing garbage
+    pub fn read_end(&self) {
+        if self.remaining() != 0 {
+            StringInfo::invalid_message("invalid message format");
+        }
+    }
+
+    fn invalid_message(message: &str) -> ! {
+        crate::ereport(
+            crate::PgLogLevel::ERROR,
+            crate::PgSqlErrorCode::ERRCODE_PROTOCOL_VIOLATION,
+            message,
+            file!(),
+            line!(),
+            column!(),
+        );
+        unreachable!()
+    }
+}
+
 impl Default for StringInfo {
     fn default() -> Self {
         Self::new()
@@ -279,3 +442,70 @@ impl Drop for StringInfo {
         }
     }
 }
+
+/// The most buffers [`StringInfo::pooled()`] keeps for reuse
+const STRINGINFO_POOL_SIZE: usize = 8;
+
+/// The largest buffer [`StringInfo::pooled()`] keeps for reuse, so one very large value doesn't
+/// keep its memory for the life of the backend
+const STRINGINFO_POOL_MAX_CAPACITY: usize = 1024 * 1024;
+
+thread_local! {
+    // as pointers, so they're not freed as the backend exits
+    static STRINGINFO_POOL: RefCell<Vec<pg_sys::StringInfo>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A [`StringInfo`] from [`StringInfo::pooled()`], which is reset and given back to be reused
+/// when it's dropped
+pub struct PooledStringInfo {
+    buffer: StringInfo,
+}
+
+impl Deref for PooledStringInfo {
+    type Target = StringInfo;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl DerefMut for PooledStringInfo {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buffer
+    }
+}
+
+impl From<PooledStringInfo> for &'static std::ffi::CStr {
+    fn from(buffer: PooledStringInfo) -> Self {
+        unsafe { std::ffi::CStr::from_ptr(buffer.copy_to_char_ptr()) }
+    }
+}
+
+impl From<PooledStringInfo> for &'static crate::cstr_core::CStr {
+    fn from(buffer: PooledStringInfo) -> Self {
+        unsafe { crate::cstr_core::CStr::from_ptr(buffer.copy_to_char_ptr()) }
+    }
+}
+
+impl Drop for PooledStringInfo {
+    fn drop(&mut self) {
+        let sid = self.buffer.sid;
+        let kept = self.buffer.capacity() <= STRINGINFO_POOL_MAX_CAPACITY
+            && STRINGINFO_POOL.with(|pool| {
+                let mut pool = pool.borrow_mut();
+                if pool.len() < STRINGINFO_POOL_SIZE {
+                    unsafe { pg_sys::resetStringInfo(sid) };
+                    pool.push(sid);
+                    true
+                } else {
+                    false
+                }
+            });
+        if !kept {
+            unsafe {
+                pg_sys::pfree((*sid).data as void_mut_ptr);
+                pg_sys::pfree(sid as void_mut_ptr);
+            }
+        }
+    }
+}
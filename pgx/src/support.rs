@@ -0,0 +1,124 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Planner support functions, which tell the planner more about a function than its `COST` and
+//! `ROWS` can, such as how many rows it returns given the arguments it was called with
+use crate::{is_a, pg_sys, FromDatum, Internal, IntoDatum, PgList};
+
+/// The request Postgres gives a function's planner support function.
+///
+/// The support function is a `#[pg_extern]` taking and returning `Internal`, named by the
+/// function's `support` attribute.  It answers the requests it knows how to, and returns
+/// [`SupportRequest::unhandled()`] for the rest:
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[pg_extern(immutable, parallel_safe)]
+/// fn repeat_support(request: Internal) -> Internal {
+///     let request = unsafe { SupportRequest::new(request) };
+///     match request.rows() {
+///         Some(rows) => match rows.constant_arg::<i32>(1) {
+///             Some(Some(times)) => rows.set_rows(times as f64),
+///             _ => SupportRequest::unhandled(),
+///         },
+///         None => SupportRequest::unhandled(),
+///     }
+/// }
+///
+/// #[pg_extern(immutable, parallel_safe, support = repeat_support)]
+/// fn repeat(value: String, times: i32) -> impl Iterator<Item = String> {
+///     std::iter::repeat(value).take(times as usize)
+/// }
+/// ```
+pub struct SupportRequest {
+    node: *mut pg_sys::Node,
+}
+
+impl SupportRequest {
+    /// # Safety
+    ///
+    /// The argument must be the one Postgres passed to the support function
+    pub unsafe fn new(request: Internal) -> Self {
+        SupportRequest {
+            node: request.unwrap().expect("support request is NULL") as *mut pg_sys::Node,
+        }
+    }
+
+    /// Tell Postgres the request wasn't handled, so it falls back to what it would have done
+    /// without a support function
+    pub fn unhandled() -> Internal {
+        // Postgres wants a NULL pointer, not an SQL NULL
+        Internal::from(Some(0))
+    }
+
+    pub fn as_ptr(&self) -> *mut pg_sys::Node {
+        self.node
+    }
+
+    /// The request for how many rows the function returns, if that's what this is
+    pub fn rows(&self) -> Option<RowsRequest> {
+        if unsafe { is_a(self.node, pg_sys::NodeTag_T_SupportRequestRows) } {
+            Some(RowsRequest {
+                request: self.node as *mut pg_sys::SupportRequestRows,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// A request to estimate how many rows a set-returning function returns, made while planning a
+/// query that calls it
+pub struct RowsRequest {
+    request: *mut pg_sys::SupportRequestRows,
+}
+
+impl RowsRequest {
+    pub fn as_ptr(&self) -> *mut pg_sys::SupportRequestRows {
+        self.request
+    }
+
+    /// The function's `n`th argument (counting from zero), if it's a constant of type `T`, or can
+    /// be simplified to one.  The inner `Option` is `None` when the constant is `NULL`
+    pub fn constant_arg<T: FromDatum + IntoDatum>(&self, n: usize) -> Option<Option<T>> {
+        unsafe {
+            let call = (*self.request).node;
+            let args = if is_a(call, pg_sys::NodeTag_T_FuncExpr) {
+                (*(call as *mut pg_sys::FuncExpr)).args
+            } else if is_a(call, pg_sys::NodeTag_T_OpExpr) {
+                (*(call as *mut pg_sys::OpExpr)).args
+            } else {
+                return None;
+            };
+            let arg = PgList::<pg_sys::Node>::from_pg(args).get_ptr(n)?;
+            let arg = pg_sys::estimate_expression_value((*self.request).root, arg);
+            if !is_a(arg, pg_sys::NodeTag_T_Const) {
+                return None;
+            }
+            let constant = &*(arg as *mut pg_sys::Const);
+            if constant.consttype != T::type_oid() {
+                return None;
+            }
+            Some(T::from_datum(
+                constant.constvalue,
+                constant.constisnull,
+                constant.consttype,
+            ))
+        }
+    }
+
+    /// Answer the request with how many rows the function will return
+    pub fn set_rows(self, rows: f64) -> Internal {
+        unsafe {
+            (*self.request).rows = rows;
+        }
+        Internal::from(Some(self.request as pg_sys::Datum))
+    }
+}
@@ -0,0 +1,144 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Creating temporary tables from Rust, such as staging tables for data being loaded
+//!
+//! A temporary table is only visible to the session that created it, in its own `pg_temp_N`
+//! schema, and is dropped when the session ends, or sooner with [`OnCommit::Drop`].  Other sessions
+//! can see the table in the catalogs, but not its rows, which are in the buffers of the session
+//! that owns it, so see [`PgRelation::check_not_other_temp()`](crate::PgRelation::check_not_other_temp)
+//! before reading a relation that could be another session's temporary table.
+//!
+//! ```rust,no_run
+//! use pgx::*;
+//!
+//! #[pg_extern]
+//! fn stage_orders() -> i64 {
+//!     PgTempTableBuilder::new("staged_orders")
+//!         .set_like(RegClass::from_name("orders").unwrap())
+//!         .set_on_commit(OnCommit::Drop)
+//!         .create();
+//!     Spi::run("INSERT INTO staged_orders SELECT * FROM orders WHERE NOT shipped");
+//!     Spi::get_one("SELECT count(*) FROM staged_orders").unwrap()
+//! }
+//! ```
+use crate::{pg_sys, quote_identifier, RegClass, RegType, Spi};
+
+/// What becomes of a temporary table's rows at the end of each transaction, from its
+/// `ON COMMIT` clause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnCommit {
+    /// Keep the rows until they're deleted, or the session ends, which is the default
+    PreserveRows,
+    /// Empty the table, as `TRUNCATE` would
+    DeleteRows,
+    /// Drop the table
+    Drop,
+}
+
+impl OnCommit {
+    /// The SQL of the `ON COMMIT` clause
+    pub fn sql(&self) -> &'static str {
+        match self {
+            OnCommit::PreserveRows => "ON COMMIT PRESERVE ROWS",
+            OnCommit::DeleteRows => "ON COMMIT DELETE ROWS",
+            OnCommit::Drop => "ON COMMIT DROP",
+        }
+    }
+}
+
+/// Creates a temporary table for this session
+pub struct PgTempTableBuilder {
+    name: String,
+    like: Option<RegClass>,
+    columns: Vec<(String, RegType)>,
+    on_commit: OnCommit,
+    if_not_exists: bool,
+}
+
+impl PgTempTableBuilder {
+    /// Create a temporary table named `name`, which is used as is, rather than parsed as SQL
+    pub fn new(name: &str) -> Self {
+        PgTempTableBuilder {
+            name: name.to_string(),
+            like: None,
+            columns: vec![],
+            on_commit: OnCommit::PreserveRows,
+            if_not_exists: false,
+        }
+    }
+
+    /// Copy the columns of `relation`, with their defaults, `NOT NULL`s, and `CHECK`s, before the
+    /// columns of [`PgTempTableBuilder::add_column()`]
+    pub fn set_like(mut self, relation: RegClass) -> Self {
+        self.like = Some(relation);
+        self
+    }
+
+    /// Add a column named `name`, which is used as is, of type `sql_type`
+    pub fn add_column(mut self, name: &str, sql_type: RegType) -> Self {
+        self.columns.push((name.to_string(), sql_type));
+        self
+    }
+
+    pub fn set_on_commit(mut self, on_commit: OnCommit) -> Self {
+        self.on_commit = on_commit;
+        self
+    }
+
+    /// Use the session's temporary table if it already exists, rather than raising an ERROR
+    pub fn set_if_not_exists(mut self, if_not_exists: bool) -> Self {
+        self.if_not_exists = if_not_exists;
+        self
+    }
+
+    /// Create the table in the session's temporary schema, so it's found first, even in place of a
+    /// permanent table of the same name
+    pub fn create(self) -> RegClass {
+        let name = quote_identifier(&self.name);
+
+        let mut elements = vec![];
+        if let Some(like) = self.like {
+            // the output of `regclass` is already quoted
+            elements.push(format!(
+                "LIKE {} INCLUDING DEFAULTS INCLUDING CONSTRAINTS",
+                like.name()
+            ));
+        }
+        for (column, sql_type) in &self.columns {
+            elements.push(format!("{} {}", quote_identifier(column), sql_type.name()));
+        }
+        Spi::run(&format!(
+            "CREATE TEMPORARY TABLE {}{} ({}) {}",
+            if self.if_not_exists {
+                "IF NOT EXISTS "
+            } else {
+                ""
+            },
+            name,
+            elements.join(", "),
+            self.on_commit.sql()
+        ));
+
+        RegClass::from_name(&format!("pg_temp.{}", name))
+            .expect("created temporary table not found")
+    }
+}
+
+/// Is `namespace` this session's temporary schema, or the schema of its temporary tables' TOAST
+/// tables?
+pub fn is_temp_namespace(namespace: pg_sys::Oid) -> bool {
+    unsafe { pg_sys::isTempOrTempToastNamespace(namespace) }
+}
+
+/// Is `namespace` another session's temporary schema, or the schema of its temporary tables'
+/// TOAST tables?
+pub fn is_other_temp_namespace(namespace: pg_sys::Oid) -> bool {
+    unsafe { pg_sys::isOtherTempNamespace(namespace) }
+}
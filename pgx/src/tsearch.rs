@@ -0,0 +1,213 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Full text search dictionary templates and parsers, written in Rust
+//!
+//! Implement [`TextSearchDictionary`] on a type and `#[derive(PostgresTextSearchDictionary)]` to
+//! create a text search template named after the type, or [`TextSearchParser`] and
+//! `#[derive(PostgresTextSearchParser)]` to create a text search parser.  Dictionaries and
+//! configurations using them are then created with plain SQL:
+//!
+//! ```sql
+//! CREATE TEXT SEARCH DICTIONARY my_dictionary (TEMPLATE = mystemmer, language = 'en');
+//! ```
+use crate::pg_sys::AsPgCStr;
+use crate::{pg_sys, Internal, PgList};
+use std::ops::Range;
+
+/// A text search dictionary template, which turns tokens into normalized lexemes
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(PostgresTextSearchDictionary)]
+/// struct Lowercase;
+///
+/// impl TextSearchDictionary for Lowercase {
+///     fn init(_options: Vec<(String, String)>) -> Self {
+///         Lowercase
+///     }
+///
+///     fn lexize(&self, token: &str) -> Option<Vec<String>> {
+///         Some(vec![token.to_lowercase()])
+///     }
+/// }
+/// ```
+pub trait TextSearchDictionary: Sized {
+    /// Set up a dictionary from the `(name, value)` options given to its
+    /// `CREATE TEXT SEARCH DICTIONARY` statement.  The dictionary is cached for the rest of the
+    /// session
+    fn init(options: Vec<(String, String)>) -> Self;
+
+    /// The lexemes for `token`.  `None` means the dictionary doesn't recognize the token, and it's
+    /// passed on to the next dictionary in the configuration, while an empty `Vec` means the token
+    /// is a stop word and is dropped
+    fn lexize(&self, token: &str) -> Option<Vec<String>>;
+}
+
+/// A text search parser, which splits a document into typed tokens
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// #[derive(PostgresTextSearchParser)]
+/// struct Whitespace {
+///     position: usize,
+/// }
+///
+/// impl TextSearchParser for Whitespace {
+///     const TOKEN_TYPES: &'static [(&'static str, &'static str)] = &[("word", "Word")];
+///
+///     fn start(_text: &str) -> Self {
+///         Whitespace { position: 0 }
+///     }
+///
+///     fn next_token(&mut self, text: &str) -> Option<(i32, std::ops::Range<usize>)> {
+///         let rest = &text[self.position..];
+///         let start = self.position + (rest.len() - rest.trim_start().len());
+///         if start == text.len() {
+///             return None;
+///         }
+///         let end = text[start..]
+///             .find(char::is_whitespace)
+///             .map_or(text.len(), |len| start + len);
+///         self.position = end;
+///         Some((1, start..end))
+///     }
+/// }
+/// ```
+pub trait TextSearchParser: Sized {
+    /// The `(alias, description)` of each type of token the parser produces.  A token's type is
+    /// its 1-based position in this list, and the aliases are what `ALTER TEXT SEARCH
+    /// CONFIGURATION ... ADD MAPPING FOR` refers to
+    const TOKEN_TYPES: &'static [(&'static str, &'static str)];
+
+    /// Start parsing `text`
+    fn start(text: &str) -> Self;
+
+    /// The type and byte range within `text` of the next token, or `None` once there are no more.
+    /// Every part of `text` should be covered by some token, even if only by a token type mapped to
+    /// no dictionaries, so that headlines can be generated from them
+    fn next_token(&mut self, text: &str) -> Option<(i32, Range<usize>)>;
+}
+
+#[doc(hidden)]
+pub fn tsearch_dictionary_init<D: TextSearchDictionary + 'static>(options: Internal) -> Internal {
+    let options = unsafe {
+        PgList::<pg_sys::DefElem>::from_pg(options.unwrap().unwrap_or(0) as *mut pg_sys::List)
+    };
+    let options = options
+        .iter_ptr()
+        .map(|defel| unsafe {
+            (
+                std::ffi::CStr::from_ptr((*defel).defname)
+                    .to_string_lossy()
+                    .into_owned(),
+                std::ffi::CStr::from_ptr(pg_sys::defGetString(defel))
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        })
+        .collect();
+
+    // `init` is called in the memory context of the dictionary's cache entry, so this lives for
+    // as long as Postgres keeps the dictionary around
+    Internal::new(D::init(options))
+}
+
+#[doc(hidden)]
+pub fn tsearch_dictionary_lexize<D: TextSearchDictionary + 'static>(
+    dictionary: Internal,
+    token: Internal,
+    length: i32,
+) -> Internal {
+    let dictionary = dictionary
+        .get::<D>()
+        .expect("text search dictionary is NULL");
+    let token = unsafe { tsearch_str(token, length) };
+
+    match dictionary.lexize(token) {
+        // a NULL pointer, rather than an SQL NULL, passes the token along
+        None => Internal::from(Some(0)),
+        Some(lexemes) => {
+            let array = unsafe {
+                pg_sys::palloc0((lexemes.len() + 1) * std::mem::size_of::<pg_sys::TSLexeme>())
+                    as *mut pg_sys::TSLexeme
+            };
+            for (i, lexeme) in lexemes.iter().enumerate() {
+                unsafe { (*array.add(i)).lexeme = lexeme.as_pg_cstr() };
+            }
+            Internal::from(Some(array as pg_sys::Datum))
+        }
+    }
+}
+
+struct ParserState<P> {
+    text: &'static str,
+    parser: P,
+}
+
+#[doc(hidden)]
+pub fn tsearch_parser_start<P: TextSearchParser + 'static>(
+    text: Internal,
+    length: i32,
+) -> Internal {
+    // the text outlives the parse, which ends with `end`
+    let text = unsafe { tsearch_str(text, length) };
+    Internal::new(ParserState {
+        text,
+        parser: P::start(text),
+    })
+}
+
+#[doc(hidden)]
+pub fn tsearch_parser_gettoken<P: TextSearchParser + 'static>(
+    mut state: Internal,
+    token: Internal,
+    length: Internal,
+) -> i32 {
+    let state = state
+        .get_mut::<ParserState<P>>()
+        .expect("text search parser state is NULL");
+    match state.parser.next_token(state.text) {
+        None => 0,
+        Some((token_type, range)) => {
+            let start = state.text[range.clone()].as_ptr();
+            unsafe {
+                *(token.unwrap().expect("token is NULL") as *mut *const u8) = start;
+                *(length.unwrap().expect("length is NULL") as *mut i32) = range.len() as i32;
+            }
+            token_type
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn tsearch_parser_lextypes<P: TextSearchParser + 'static>() -> Internal {
+    let array = unsafe {
+        pg_sys::palloc0((P::TOKEN_TYPES.len() + 1) * std::mem::size_of::<pg_sys::LexDescr>())
+            as *mut pg_sys::LexDescr
+    };
+    for (i, (alias, description)) in P::TOKEN_TYPES.iter().enumerate() {
+        unsafe {
+            let descr = &mut *array.add(i);
+            descr.lexid = i as i32 + 1;
+            descr.alias = alias.as_pg_cstr();
+            descr.descr = description.as_pg_cstr();
+        }
+    }
+    Internal::from(Some(array as pg_sys::Datum))
+}
+
+/// The `length` bytes of text at `ptr`, which Postgres doesn't null-terminate
+unsafe fn tsearch_str(ptr: Internal, length: i32) -> &'static str {
+    let ptr = ptr.unwrap().expect("text search text is NULL") as *const u8;
+    let bytes = std::slice::from_raw_parts(ptr, length as usize);
+    std::str::from_utf8(bytes).expect("text search text is not valid UTF8")
+}
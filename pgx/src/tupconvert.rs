@@ -0,0 +1,132 @@
+/*
+Portions Copyright 2019-2021 ZomboDB, LLC.
+Portions Copyright 2021-2022 Technology Concepts & Design, Inc. <support@tcdi.com>
+
+All rights reserved.
+
+Use of this source code is governed by the MIT license that can be found in the LICENSE file.
+*/
+
+//! Converting tuples from one row type to another whose attributes have the same names and
+//! types, but not necessarily in the same order or with the same dropped attributes, such as a
+//! partition's rows to its partitioned table's, or a table's rows from before an `ALTER TABLE`
+use crate::{pg_sys, PgHeapTuple, PgTupleDesc};
+
+/// How to convert tuples of one row type, the input's, to another, the output's, by matching
+/// their attributes by name, with Postgres' `convert_tuples_by_name()`.
+///
+/// Each of the output's attributes must be one of the input's, of the same type, or else Postgres
+/// raises an `ERROR` reporting which one doesn't match, while the input's other attributes are
+/// left out.  Where their attributes already line up, no conversion is needed.
+///
+/// ```rust,no_run
+/// use pgx::*;
+///
+/// fn to_parent_row<'a>(
+///     row: &PgHeapTuple,
+///     partition: &'a PgTupleDesc,
+///     parent: &'a PgTupleDesc,
+/// ) -> PgHeapTuple<'a> {
+///     PgTupleConversionMap::by_name(partition, parent).convert(row)
+/// }
+/// ```
+pub struct PgTupleConversionMap<'a> {
+    /// `NULL` if no conversion is needed
+    map: *mut pg_sys::TupleConversionMap,
+    indesc: &'a PgTupleDesc<'a>,
+    outdesc: &'a PgTupleDesc<'a>,
+}
+
+impl<'a> PgTupleConversionMap<'a> {
+    /// The conversion of tuples described by `indesc` to tuples described by `outdesc`, which is
+    /// allocated in the `CurrentMemoryContext`
+    pub fn by_name(indesc: &'a PgTupleDesc<'a>, outdesc: &'a PgTupleDesc<'a>) -> Self {
+        #[cfg(any(feature = "pg10", feature = "pg11", feature = "pg12"))]
+        let map = unsafe {
+            pg_sys::convert_tuples_by_name(
+                indesc.as_ptr(),
+                outdesc.as_ptr(),
+                b"could not convert row type\0".as_ptr() as *const std::os::raw::c_char,
+            )
+        };
+
+        #[cfg(any(feature = "pg13", feature = "pg14", feature = "pg15"))]
+        let map = unsafe { pg_sys::convert_tuples_by_name(indesc.as_ptr(), outdesc.as_ptr()) };
+
+        PgTupleConversionMap {
+            map,
+            indesc,
+            outdesc,
+        }
+    }
+
+    /// Do tuples need to be converted, or are the row types the same?
+    pub fn needs_conversion(&self) -> bool {
+        !self.map.is_null()
+    }
+
+    /// The descriptor of the tuples being converted
+    pub fn indesc(&self) -> &'a PgTupleDesc<'a> {
+        self.indesc
+    }
+
+    /// The descriptor of the converted tuples
+    pub fn outdesc(&self) -> &'a PgTupleDesc<'a> {
+        self.outdesc
+    }
+
+    /// Convert `tuple`, which must be described by this map's input descriptor, into a new tuple
+    /// allocated in the `CurrentMemoryContext`, which is a copy of `tuple` if no conversion is
+    /// needed
+    ///
+    /// ## Panics
+    ///
+    /// If `tuple` doesn't have as many attributes as the input descriptor does
+    pub fn convert(&self, tuple: &PgHeapTuple) -> PgHeapTuple<'a> {
+        if tuple.len() != self.indesc.len() {
+            panic!(
+                "tuple has {} attributes, but tuples with {} are being converted",
+                tuple.len(),
+                self.indesc.len()
+            );
+        }
+        unsafe {
+            let converted = if self.map.is_null() {
+                pg_sys::heap_copytuple(tuple.as_ptr())
+            } else {
+                self.convert_raw(tuple.as_ptr())
+            };
+            PgHeapTuple::from_heap_tuple(converted, self.outdesc.as_ptr())
+        }
+    }
+
+    /// Convert `tuple`, as [`PgTupleConversionMap::convert()`] does, returning `tuple` itself if no
+    /// conversion is needed
+    ///
+    /// ## Safety
+    ///
+    /// `tuple` must be a valid tuple described by this map's input descriptor
+    pub unsafe fn convert_raw(&self, tuple: pg_sys::HeapTuple) -> pg_sys::HeapTuple {
+        if self.map.is_null() {
+            return tuple;
+        }
+
+        #[cfg(any(feature = "pg10", feature = "pg11"))]
+        {
+            pg_sys::do_convert_tuple(tuple, self.map)
+        }
+
+        #[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+        {
+            pg_sys::execute_attr_map_tuple(tuple, self.map)
+        }
+    }
+}
+
+impl<'a> Drop for PgTupleConversionMap<'a> {
+    fn drop(&mut self) {
+        if !self.map.is_null() {
+            unsafe { pg_sys::free_conversion_map(self.map) }
+        }
+    }
+}
@@ -8,7 +8,10 @@ Use of this source code is governed by the MIT license that can be found in the
 */
 
 //! Provides a safe wrapper around Postgres' `pg_sys::TupleDescData` struct
-use crate::{pg_sys, void_mut_ptr, AllocatedByRust, FromDatum, PgBox, PgRelation};
+use crate::{
+    pg_sys, void_mut_ptr, AllocatedByRust, FromDatum, IntoDatum, PgBox, PgMemoryContexts,
+    PgRelation, TypeConversionError,
+};
 
 use std::ops::Deref;
 
@@ -125,6 +128,37 @@ impl<'a> PgTupleDesc<'a> {
         }
     }
 
+    /// Build an anonymous row type with an attribute for each `(name, type)` in `columns`, such
+    /// as for a function returning `SETOF record` whose columns depend on its arguments.  See
+    /// [`PgComposite::from_tupdesc()`](crate::PgComposite::from_tupdesc) for making its rows.
+    ///
+    /// The TupleDesc is allocated in the `CurrentMemoryContext`, and `pfree()`'d when this
+    /// instance is dropped
+    ///
+    /// ```rust,no_run
+    /// use pgx::{pg_sys, PgTupleDesc};
+    /// let tupdesc = PgTupleDesc::from_columns(&[("id", pg_sys::INT8OID), ("name", pg_sys::TEXTOID)]);
+    /// assert_eq!(tupdesc.get(1).unwrap().name(), "name");
+    /// ```
+    pub fn from_columns(columns: &[(&str, pg_sys::Oid)]) -> PgTupleDesc<'static> {
+        unsafe {
+            let tupdesc = create_template_tupdesc(columns.len());
+            for (i, (name, typoid)) in columns.iter().enumerate() {
+                let name = std::ffi::CString::new(*name).expect("column name contains a NUL");
+                pg_sys::TupleDescInitEntry(
+                    tupdesc,
+                    (i + 1) as pg_sys::AttrNumber,
+                    name.as_ptr(),
+                    *typoid,
+                    -1,
+                    0,
+                );
+            }
+
+            PgTupleDesc::from_pg_is_copy(tupdesc)
+        }
+    }
+
     /// wrap the `pg_sys::TupleDesc` contained by the specified `PgRelation`
     pub fn from_relation(parent: &PgRelation) -> PgTupleDesc {
         PgTupleDesc {
@@ -147,24 +181,86 @@ impl<'a> PgTupleDesc<'a> {
     pub unsafe fn from_composite(composite: pg_sys::Datum) -> Self {
         let htup_header =
             pg_sys::pg_detoast_datum(composite as *mut pg_sys::varlena) as pg_sys::HeapTupleHeader;
+        Self::from_composite_header(htup_header)
+    }
+
+    /// Like `::from_composite()`, but for a composite value that's already been detoasted, such
+    /// as by an earlier `pg_detoast_datum()`, so it isn't checked again
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe as it cannot guarantee that `htup_header` is a valid, detoasted,
+    /// composite value
+    pub unsafe fn from_composite_header(htup_header: pg_sys::HeapTupleHeader) -> Self {
         let tup_type = crate::heap_tuple_header_get_type_id(htup_header);
         let tup_typmod = crate::heap_tuple_header_get_typmod(htup_header);
         let tupdesc = pg_sys::lookup_rowtype_tupdesc(tup_type, tup_typmod);
 
-        let mut data = PgBox::<pg_sys::HeapTupleData>::alloc();
-
-        data.t_len = crate::heap_tuple_header_get_datum_length(htup_header) as u32;
-        data.t_data = htup_header;
-
         PgTupleDesc {
             tupdesc: PgBox::from_pg(tupdesc),
             parent: None,
-            data: Some(data),
+            data: Some(composite_tuple_data(htup_header)),
             need_release: true,
             need_pfree: false,
         }
     }
 
+    /// Like `::from_composite()`, but the composite type's TupleDesc is cached in the function's
+    /// `.flinfo.fn_extra`, so a function called for many rows only looks it up again when the
+    /// type changes, as it can for `record` arguments.  See [`fn_extra_cache()`](crate::fn_extra_cache),
+    /// which this uses, so the function can't also cache something else there.
+    ///
+    /// ```rust,no_run
+    /// use pgx::*;
+    ///
+    /// #[pg_guard]
+    /// #[no_mangle]
+    /// pub extern "C" fn first_column_is_null(fcinfo: pg_sys::FunctionCallInfo) -> pg_sys::Datum {
+    ///     let row = pg_getarg_datum_raw(fcinfo, 0);
+    ///     let tupdesc = unsafe { PgTupleDesc::from_composite_cached(fcinfo, row) };
+    ///     let (_, nulls) = tupdesc.deform_composite();
+    ///     nulls.first().copied().unwrap_or(true).into_datum().unwrap()
+    /// }
+    /// ```
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe as it cannot guarantee that the provided `pg_sys::Datum` actually
+    /// points to a composite type, or that `fcinfo` is valid.  The returned `PgTupleDesc` must not
+    /// outlive the function call
+    pub unsafe fn from_composite_cached(
+        fcinfo: pg_sys::FunctionCallInfo,
+        composite: pg_sys::Datum,
+    ) -> Self {
+        let htup_header =
+            pg_sys::pg_detoast_datum(composite as *mut pg_sys::varlena) as pg_sys::HeapTupleHeader;
+        let tup_type = crate::heap_tuple_header_get_type_id(htup_header);
+        let tup_typmod = crate::heap_tuple_header_get_typmod(htup_header);
+
+        let cache = crate::fn_extra_cache(fcinfo, || RowTypeCache {
+            typid: tup_type,
+            typmod: tup_typmod,
+            tupdesc: pg_sys::lookup_rowtype_tupdesc_copy(tup_type, tup_typmod),
+        });
+        if cache.typid != tup_type || cache.typmod != tup_typmod {
+            let fn_mcxt = (*(*fcinfo).flinfo).fn_mcxt;
+            pg_sys::FreeTupleDesc(cache.tupdesc);
+            cache.tupdesc = PgMemoryContexts::For(fn_mcxt)
+                .switch_to(|_| pg_sys::lookup_rowtype_tupdesc_copy(tup_type, tup_typmod));
+            cache.typid = tup_type;
+            cache.typmod = tup_typmod;
+        }
+
+        PgTupleDesc {
+            tupdesc: PgBox::from_pg(cache.tupdesc),
+            parent: None,
+            data: Some(composite_tuple_data(htup_header)),
+            // it belongs to the cache
+            need_release: false,
+            need_pfree: false,
+        }
+    }
+
     /// From which relation was this TupleDesc created, if any?
     pub fn parent(&self) -> Option<&PgRelation> {
         self.parent
@@ -214,6 +310,67 @@ impl<'a> PgTupleDesc<'a> {
         )
     }
 
+    /// Like `get_attr()`, but returns a [`TypeConversionError`] naming the attribute if it isn't of
+    /// a type `T` can be converted from
+    pub fn try_get_attr<T: FromDatum + IntoDatum>(
+        &self,
+        attno: usize,
+    ) -> Result<Option<T>, TypeConversionError> {
+        let att = self.get(attno).expect("no attribute");
+        let typoid = att.type_oid().value();
+        if T::is_compatible_with(typoid) {
+            Ok(self.get_attr(attno))
+        } else {
+            Err(TypeConversionError::new::<T>(typoid, T::type_oid()).set_column(att.name()))
+        }
+    }
+
+    /// Split `tuple`, which must match this TupleDesc, into the `Datum` and null flag of every
+    /// attribute, in one pass over the tuple.
+    ///
+    /// Getting each attribute with [`heap_getattr()`](crate::heap_getattr) has to walk the tuple
+    /// up to that attribute every time, once there's a null or variable-length attribute before it,
+    /// which adds up for wide rows.  By-reference `Datum`s point into the tuple itself
+    pub fn deform(&self, tuple: &pg_sys::HeapTupleData) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        let mut values = Vec::new();
+        let mut nulls = Vec::new();
+        self.deform_into(tuple, &mut values, &mut nulls);
+        (values, nulls)
+    }
+
+    /// Like [`PgTupleDesc::deform()`], reusing `values` and `nulls`, such as when deforming every
+    /// tuple of a scan
+    pub fn deform_into(
+        &self,
+        tuple: &pg_sys::HeapTupleData,
+        values: &mut Vec<pg_sys::Datum>,
+        nulls: &mut Vec<bool>,
+    ) {
+        values.clear();
+        values.resize(self.len(), 0);
+        nulls.clear();
+        nulls.resize(self.len(), true);
+        unsafe {
+            pg_sys::heap_deform_tuple(
+                tuple as *const _ as *mut _,
+                self.tupdesc.as_ptr(),
+                values.as_mut_ptr(),
+                nulls.as_mut_ptr(),
+            );
+        }
+    }
+
+    /// [`PgTupleDesc::deform()`] the backing composite data.
+    ///
+    /// This is only possible for `PgTupleDesc` created with `from_composite()`
+    pub fn deform_composite(&self) -> (Vec<pg_sys::Datum>, Vec<bool>) {
+        self.deform(
+            self.data
+                .as_ref()
+                .expect("no composite data associated with this PgTupleDesc"),
+        )
+    }
+
     /// Iterate over our attributes
     pub fn iter(&self) -> TupleDescIterator {
         TupleDescIterator {
@@ -241,6 +398,35 @@ impl<'a> Drop for PgTupleDesc<'a> {
     }
 }
 
+/// The TupleDesc [`PgTupleDesc::from_composite_cached()`] keeps in `fn_extra`, a copy allocated
+/// in `fn_mcxt`
+struct RowTypeCache {
+    typid: pg_sys::Oid,
+    typmod: i32,
+    tupdesc: pg_sys::TupleDesc,
+}
+
+/// A `HeapTupleData` pointing at the composite value `htup_header`
+unsafe fn composite_tuple_data(
+    htup_header: pg_sys::HeapTupleHeader,
+) -> PgBox<pg_sys::HeapTupleData, AllocatedByRust> {
+    let mut data = PgBox::<pg_sys::HeapTupleData>::alloc();
+
+    data.t_len = crate::heap_tuple_header_get_datum_length(htup_header) as u32;
+    data.t_data = htup_header;
+    data
+}
+
+#[cfg(any(feature = "pg10", feature = "pg11"))]
+unsafe fn create_template_tupdesc(natts: usize) -> pg_sys::TupleDesc {
+    pg_sys::CreateTemplateTupleDesc(natts as std::os::raw::c_int, false)
+}
+
+#[cfg(any(feature = "pg12", feature = "pg13", feature = "pg14", feature = "pg15"))]
+unsafe fn create_template_tupdesc(natts: usize) -> pg_sys::TupleDesc {
+    pg_sys::CreateTemplateTupleDesc(natts as std::os::raw::c_int)
+}
+
 pub unsafe fn release_tupdesc(ptr: pg_sys::TupleDesc) {
     if (*ptr).tdrefcount >= 0 {
         pg_sys::DecrTupleDescRefCount(ptr)
@@ -250,7 +436,7 @@ pub unsafe fn release_tupdesc(ptr: pg_sys::TupleDesc) {
 /// `attno` is 0-based
 #[cfg(feature = "pg10")]
 #[inline]
-fn tupdesc_get_attr(
+pub(crate) fn tupdesc_get_attr(
     tupdesc: &PgBox<pg_sys::TupleDescData>,
     attno: usize,
 ) -> &pg_sys::FormData_pg_attribute {
@@ -259,9 +445,15 @@ fn tupdesc_get_attr(
 }
 
 /// `attno` is 0-based
-#[cfg(any(feature = "pg11", feature = "pg12", feature = "pg13", feature = "pg14"))]
+#[cfg(any(
+    feature = "pg11",
+    feature = "pg12",
+    feature = "pg13",
+    feature = "pg14",
+    feature = "pg15"
+))]
 #[inline]
-fn tupdesc_get_attr(
+pub(crate) fn tupdesc_get_attr(
     tupdesc: &PgBox<pg_sys::TupleDescData>,
     attno: usize,
 ) -> &pg_sys::FormData_pg_attribute {
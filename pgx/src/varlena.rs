@@ -316,6 +316,56 @@ pub unsafe fn varlena_to_byte_slice<'a>(varlena: *const pg_sys::varlena) -> &'a
     std::slice::from_raw_parts(data as *const u8, len)
 }
 
+/// Detoast a varlena `Datum`, decompressing it and fetching it from its TOAST table if it has to.
+///
+/// This is the `PG_DETOAST_DATUM()` macro.  The returned `varlena *` is the `datum` itself if it
+/// wasn't toasted, and is otherwise allocated in `CurrentMemoryContext`.
+///
+/// ## Safety
+///
+/// This function is unsafe because it blindly assumes the `datum` is a non-null varlena.
+#[inline]
+pub unsafe fn detoast_datum(datum: pg_sys::Datum) -> *mut pg_sys::varlena {
+    pg_sys::pg_detoast_datum(datum as *mut pg_sys::varlena)
+}
+
+/// Detoast only `count` bytes of a varlena `Datum`'s data, starting at byte `first`, or the rest
+/// of it when `count` is negative.
+///
+/// This is the `PG_DETOAST_DATUM_SLICE()` macro.  A value stored with `STORAGE = external`, and so
+/// not compressed, only has the chunks of its TOAST table which hold the slice fetched, which makes
+/// looking at the start of a large custom type's values, like a header, cheap.  A compressed value
+/// is decompressed only as far as it has to be.
+///
+/// The returned `varlena *` is allocated in `CurrentMemoryContext`.
+///
+/// ## Safety
+///
+/// This function is unsafe because it blindly assumes the `datum` is a non-null varlena.
+#[inline]
+pub unsafe fn detoast_datum_slice(
+    datum: pg_sys::Datum,
+    first: i32,
+    count: i32,
+) -> *mut pg_sys::varlena {
+    pg_sys::pg_detoast_datum_slice(datum as *mut pg_sys::varlena, first, count)
+}
+
+/// The first `len` bytes of a varlena `Datum`'s data, or all of it if it's shorter, detoasting no
+/// more of it than that with [`detoast_datum_slice()`].
+///
+/// ## Safety
+///
+/// This function is unsafe because it blindly assumes the `datum` is a non-null varlena.
+///
+/// Note also that the returned `&[u8]` is backed by memory allocated in `CurrentMemoryContext`, and
+/// becomes invalid the moment that is reset or deleted
+#[inline]
+pub unsafe fn detoast_datum_prefix<'a>(datum: pg_sys::Datum, len: usize) -> &'a [u8] {
+    let len = std::cmp::min(len, i32::MAX as usize) as i32;
+    varlena_to_byte_slice(detoast_datum_slice(datum, 0, len))
+}
+
 /// Convert a Rust `&str` into a Postgres `text *`.
 ///
 /// This allocates the returned Postgres `text *` in `CurrentMemoryContext`.
@@ -11,6 +11,7 @@
 DIR=`pwd`
 set -x
 
+cd $DIR/pgx-types && cargo publish && sleep 30
 cd $DIR/pgx-utils && cargo publish && sleep 30
 cd $DIR/pgx-macros && cargo publish && sleep 30
 cd $DIR/pgx-pg-sys && cargo publish --no-verify && sleep 30
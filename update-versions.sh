@@ -29,6 +29,7 @@ CARGO_TOMLS_TO_BUMP=(
     ./pgx-tests/Cargo.toml
     ./cargo-pgx/Cargo.toml
     ./pgx-pg-sys/Cargo.toml
+    ./pgx-types/Cargo.toml
 )
 
 CARGO_TOMLS_TO_SED=(
@@ -40,6 +41,7 @@ CARGO_TOMLS_TO_SED=(
     ./pgx-tests/Cargo.toml
     ./cargo-pgx/Cargo.toml
     ./pgx-pg-sys/Cargo.toml
+    ./pgx-types/Cargo.toml
     ./pgx-examples/*/Cargo.toml
     ./Cargo.toml
 )
@@ -50,6 +52,7 @@ DEPENDENCIES_TO_UPDATE=(
     "pgx-macros"
     "pgx-pgx-sys"
     "pgx-utils"
+    "pgx-types"
     "cargo-pgx"
 )
 